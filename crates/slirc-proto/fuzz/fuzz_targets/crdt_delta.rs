@@ -0,0 +1,48 @@
+//! Fuzz target for serialized CRDT deltas
+//!
+//! Deltas arrive from peers and from the on-disk CRDT journal as CBOR. This
+//! fuzzer decodes arbitrary bytes as user and channel deltas and merges
+//! whatever decodes into a fresh replica, checking that decoding and
+//! merging never panic and that merging the same delta twice is a no-op.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use slirc_proto::sync::{
+    ChannelCrdt, ChannelDelta, DeltaCrdt, HybridTimestamp, ServerId, UserCrdt, UserDelta,
+};
+
+fn origin() -> HybridTimestamp {
+    HybridTimestamp::new(0, 0, &ServerId::new("000"))
+}
+
+fuzz_target!(|data: &[u8]| {
+    // Journal entries are small; skip inputs that only slow the fuzzer down
+    if data.len() > 4096 {
+        return;
+    }
+
+    if let Ok(delta) = ciborium::from_reader::<UserDelta, _>(data) {
+        let mut user = UserCrdt::new(
+            delta.uid.clone(),
+            "nick".to_string(),
+            "user".to_string(),
+            "Real Name".to_string(),
+            "host.example".to_string(),
+            "cloak.example".to_string(),
+            origin(),
+        );
+        user.merge_delta(&delta);
+        let once = user.clone();
+        user.merge_delta(&delta);
+        assert_eq!(user, once, "merging a user delta twice changed the replica");
+    }
+
+    if let Ok(delta) = ciborium::from_reader::<ChannelDelta, _>(data) {
+        let mut channel = ChannelCrdt::new(delta.name.clone(), origin());
+        channel.merge_delta(&delta);
+        let once = channel.clone();
+        channel.merge_delta(&delta);
+        assert_eq!(channel, once, "merging a channel delta twice changed the replica");
+    }
+});
@@ -9,7 +9,7 @@ WORKERS=${2:-1}    # Default to 1 worker
 echo "Running fuzz tests for ${DURATION} seconds each with ${WORKERS} worker(s)..."
 
 # Array of fuzz targets
-TARGETS=("message_parser" "ctcp_parser" "prefix_parser" "mode_parser")
+TARGETS=("message_parser" "ctcp_parser" "prefix_parser" "mode_parser" "crdt_delta")
 
 for target in "${TARGETS[@]}"; do
     echo "Fuzzing ${target}..."
@@ -74,10 +74,14 @@ pub enum Capability {
     EventPlayback,
     /// Message redaction/deletion (draft/message-redaction)
     MessageRedaction,
+    /// User and channel metadata (draft/metadata-2)
+    Metadata,
     /// Message relaying between networks (draft/relaymsg)
     DraftRelaymsg,
     /// Extended MONITOR notifications (extended-monitor)
     ExtendedMonitor,
+    /// Connection compression via COMPRESS (sid3.xyz/compress)
+    Compress,
     /// Unknown/custom capability
     Custom(String),
 }
@@ -113,8 +117,10 @@ impl AsRef<str> for Capability {
             Self::Typing => "typing",
             Self::EventPlayback => "draft/event-playback",
             Self::MessageRedaction => "draft/message-redaction",
+            Self::Metadata => "draft/metadata-2",
             Self::DraftRelaymsg => "draft/relaymsg",
             Self::ExtendedMonitor => "extended-monitor",
+            Self::Compress => "sid3.xyz/compress",
             Self::Custom(s) => s,
         }
     }
@@ -157,8 +163,10 @@ impl From<&str> for Capability {
             "typing" => Self::Typing,
             "draft/event-playback" => Self::EventPlayback,
             "draft/message-redaction" => Self::MessageRedaction,
+            "draft/metadata-2" => Self::Metadata,
             "draft/relaymsg" => Self::DraftRelaymsg,
             "extended-monitor" => Self::ExtendedMonitor,
+            "sid3.xyz/compress" => Self::Compress,
             other => Self::Custom(other.to_string()),
         }
     }
@@ -214,6 +222,7 @@ mod tests {
             Capability::MessageRedaction.as_ref(),
             "draft/message-redaction"
         );
+        assert_eq!(Capability::Metadata.as_ref(), "draft/metadata-2");
     }
 
     #[test]
@@ -236,5 +245,6 @@ mod tests {
             Capability::from("draft/message-redaction"),
             Capability::MessageRedaction
         );
+        assert_eq!(Capability::from("draft/metadata-2"), Capability::Metadata);
     }
 }
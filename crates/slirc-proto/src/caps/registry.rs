@@ -174,6 +174,12 @@ pub const CAPABILITIES: &[CapabilityDef] = &[
         value: None,
         description: "Message deletion/redaction support",
     },
+    CapabilityDef {
+        name: "draft/metadata-2",
+        version: 302,
+        value: None,
+        description: "User and channel metadata with subscriptions",
+    },
 ];
 
 /// Build a space-separated list of capabilities for CAP LS response.
@@ -258,6 +264,7 @@ mod tests {
         assert!(is_supported("typing"));
         assert!(is_supported("draft/event-playback"));
         assert!(is_supported("draft/message-redaction"));
+        assert!(is_supported("draft/metadata-2"));
     }
 
     #[test]
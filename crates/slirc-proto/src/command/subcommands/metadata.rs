@@ -1,10 +1,11 @@
 //! METADATA subcommand types.
 //!
 //! METADATA allows clients and servers to get, set, and list metadata
-//! associated with users and channels. This is an Ergo-defined extension.
+//! associated with users and channels, and to subscribe to changes.
 //!
 //! # Reference
 //! - Ergo documentation: <https://ergo.chat/manual/commands/metadata>
+//! - IRCv3 draft/metadata-2: <https://ircv3.net/specs/extensions/metadata>
 
 use std::str::FromStr;
 
@@ -21,6 +22,16 @@ pub enum MetadataSubCommand {
     SET,
     /// LIST - List all metadata for target
     LIST,
+    /// CLEAR - Remove all metadata for target
+    CLEAR,
+    /// SUB - Subscribe to notifications for keys
+    SUB,
+    /// UNSUB - Unsubscribe from keys
+    UNSUB,
+    /// SUBS - List subscribed keys
+    SUBS,
+    /// SYNC - Request subscribed metadata for target
+    SYNC,
 }
 
 impl MetadataSubCommand {
@@ -31,6 +42,11 @@ impl MetadataSubCommand {
             Self::GET => "GET",
             Self::SET => "SET",
             Self::LIST => "LIST",
+            Self::CLEAR => "CLEAR",
+            Self::SUB => "SUB",
+            Self::UNSUB => "UNSUB",
+            Self::SUBS => "SUBS",
+            Self::SYNC => "SYNC",
         }
     }
 }
@@ -43,6 +59,11 @@ impl FromStr for MetadataSubCommand {
             "GET" => Ok(Self::GET),
             "SET" => Ok(Self::SET),
             "LIST" => Ok(Self::LIST),
+            "CLEAR" => Ok(Self::CLEAR),
+            "SUB" => Ok(Self::SUB),
+            "UNSUB" => Ok(Self::UNSUB),
+            "SUBS" => Ok(Self::SUBS),
+            "SYNC" => Ok(Self::SYNC),
             _ => Err(MessageParseError::InvalidSubcommand {
                 cmd: "METADATA",
                 sub: s.to_owned(),
@@ -79,6 +100,10 @@ mod tests {
             "LIST".parse::<MetadataSubCommand>().unwrap(),
             MetadataSubCommand::LIST
         );
+        assert_eq!(
+            "sub".parse::<MetadataSubCommand>().unwrap(),
+            MetadataSubCommand::SUB
+        );
         assert!("INVALID".parse::<MetadataSubCommand>().is_err());
     }
 
@@ -81,6 +81,7 @@ pub mod sasl;
 pub mod state;
 #[cfg(feature = "sync")]
 pub mod sync;
+pub mod units;
 pub mod util;
 
 pub use self::caps::{Capability, NegotiationVersion};
@@ -145,5 +146,5 @@ pub use self::transport::{
 pub mod websocket;
 #[cfg(feature = "tokio")]
 pub use self::websocket::{
-    build_handshake_response, validate_handshake, HandshakeResult, WebSocketConfig,
+    build_handshake_response, validate_handshake, HandshakeResult, IrcSubprotocol, WebSocketConfig,
 };
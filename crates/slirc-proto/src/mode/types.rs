@@ -151,6 +151,10 @@ pub enum ChannelMode {
     Limit,
     /// 'f' - Advanced flood protection
     Flood,
+    /// 'j' - Join throttle (`joins:seconds`)
+    JoinThrottle,
+    /// 'H' - History replayed on join (`lines:minutes`)
+    HistoryReplay,
     /// 'F' - Channel forwarding (redirects joins to another channel)
     JoinForward,
     /// 'k' - Channel key
@@ -169,6 +173,8 @@ pub enum ChannelMode {
     NoExternalMessages,
     /// 'r' - Registered users only (on some servers)
     RegisteredOnly,
+    /// 'R' - Only users logged in to an account can join
+    RegisteredJoin,
     /// 's' - Secret (hidden from LIST, WHO)
     Secret,
     /// 't' - Only ops can change topic
@@ -237,6 +243,8 @@ impl ModeType for ChannelMode {
                 | Self::Limit
                 | Self::Key
                 | Self::Flood
+                | Self::JoinThrottle
+                | Self::HistoryReplay
                 | Self::JoinForward
                 | Self::Founder
                 | Self::Admin
@@ -263,6 +271,8 @@ impl ModeType for ChannelMode {
             'l' => Self::Limit,
             'k' => Self::Key,
             'f' => Self::Flood,
+            'j' => Self::JoinThrottle,
+            'H' => Self::HistoryReplay,
             'F' => Self::JoinForward,
             'i' => Self::InviteOnly,
             'm' => Self::Moderated,
@@ -270,6 +280,7 @@ impl ModeType for ChannelMode {
             'U' => Self::OpModerated,
             'n' => Self::NoExternalMessages,
             'r' => Self::RegisteredOnly,
+            'R' => Self::RegisteredJoin,
             's' => Self::Secret,
             't' => Self::ProtectedTopic,
             'c' => Self::NoColors,
@@ -310,11 +321,14 @@ impl fmt::Display for ChannelMode {
             Self::Limit => 'l',
             Self::Key => 'k',
             Self::Flood => 'f',
+            Self::JoinThrottle => 'j',
+            Self::HistoryReplay => 'H',
             Self::JoinForward => 'F',
             Self::InviteOnly => 'i',
             Self::Moderated => 'm',
             Self::NoExternalMessages => 'n',
             Self::RegisteredOnly => 'r',
+            Self::RegisteredJoin => 'R',
             Self::Secret => 's',
             Self::ProtectedTopic => 't',
             Self::NoColors => 'c',
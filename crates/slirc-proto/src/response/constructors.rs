@@ -602,6 +602,34 @@ impl Response {
         err_helpnotfound, ERR_HELPNOTFOUND, subject, "No help available on this topic"
     );
 
+    impl_err!(
+        /// `716 ERR_TARGUMODEG`
+        /// `<nick> :is in +g mode (server-side ignore)`
+        err_targumodeg, ERR_TARGUMODEG, nick, "is in +g mode (server-side ignore)"
+    );
+
+    impl_err!(
+        /// `717 RPL_TARGNOTIFY`
+        /// `<nick> :has been informed that you messaged them`
+        rpl_targnotify, RPL_TARGNOTIFY, nick, "has been informed that you messaged them"
+    );
+
+    /// `718 RPL_UMODEGMSG`
+    /// `<nick> <user@host> :is messaging you, and you have user mode +g set`
+    pub fn rpl_umodegmsg(client: &str, nick: &str, userhost: &str) -> Message {
+        Self::error_msg(
+            Response::RPL_UMODEGMSG,
+            vec![
+                client.to_string(),
+                nick.to_string(),
+                userhost.to_string(),
+                format!(
+                    "is messaging you, and you have user mode +g set. Use /ACCEPT {nick} to allow"
+                ),
+            ],
+        )
+    }
+
     // === 900-999 SASL Replies ===
 
     /// `900 RPL_LOGGEDIN`
@@ -630,6 +658,12 @@ impl Response {
         err_saslfail, ERR_SASLFAIL, "SASL authentication failed"
     );
 
+    impl_err!(
+        /// `908 RPL_SASLMECHS`
+        /// `<mechanisms> :are available SASL mechanisms`
+        rpl_saslmechs, RPL_SASLMECHS, mechanisms, "are available SASL mechanisms"
+    );
+
     // === 670 / 691 STARTTLS Replies (IRCv3) ===
 
     /// `670 RPL_STARTTLS`
@@ -66,6 +66,7 @@ impl Response {
             477 => Response::ERR_NEEDREGGEDNICK,
             478 => Response::ERR_BANLISTFULL,
             479 => Response::ERR_BADCHANNAME,
+            480 => Response::ERR_CANNOTKNOCK,
             481 => Response::ERR_NOPRIVILEGES,
             482 => Response::ERR_CHANOPRIVSNEEDED,
             483 => Response::ERR_CANTKILLSERVER,
@@ -77,12 +78,14 @@ impl Response {
             501 => Response::ERR_UMODEUNKNOWNFLAG,
             502 => Response::ERR_USERSDONTMATCH,
             511 => Response::ERR_SILELISTFULL,
+            512 => Response::ERR_TOOMANYWATCH,
             524 => Response::ERR_HELPNOTFOUND,
             573 => Response::ERR_CANNOTSENDRP,
             635 => Response::ERR_NORULES,
             712 => Response::ERR_TOOMANYKNOCK,
             713 => Response::ERR_CHANOPEN,
             714 => Response::ERR_KNOCKONCHAN,
+            716 => Response::ERR_TARGUMODEG,
             723 => Response::ERR_NOPRIVS,
             734 => Response::ERR_MONLISTFULL,
             765 => Response::ERR_TARGETINVALID,
@@ -90,6 +93,7 @@ impl Response {
             767 => Response::ERR_KEYINVALID,
             768 => Response::ERR_KEYNOTSET,
             769 => Response::ERR_KEYNOPERMISSION,
+            773 => Response::ERR_METADATATOOMANYSUBS,
             902 => Response::ERR_NICKLOCKED,
             904 => Response::ERR_SASLFAIL,
             905 => Response::ERR_SASLTOOLONG,
@@ -34,9 +34,11 @@ impl Response {
     pub fn is_error(&self) -> bool {
         let code = self.code();
         (400..600).contains(&code)
+            || code == 716
             || code == 723
             || code == 734
             || (765..=769).contains(&code)
+            || code == 773
             || code == 902
             || (904..=907).contains(&code)
     }
@@ -121,6 +123,7 @@ impl Response {
                 | Response::RPL_WHOISACCOUNT
                 | Response::RPL_WHOISBOT
                 | Response::RPL_WHOISACTUALLY
+                | Response::RPL_WHOISGATEWAY
                 | Response::RPL_WHOISHOST
                 | Response::RPL_WHOISMODES
                 | Response::RPL_WHOISCERTFP
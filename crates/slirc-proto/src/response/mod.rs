@@ -220,6 +220,8 @@ pub enum Response {
     RPL_EXCEPTLIST = 348,
     /// 349 - End of exception list
     RPL_ENDOFEXCEPTLIST = 349,
+    /// 350 - WHOIS WEBIRC gateway (InspIRCd)
+    RPL_WHOISGATEWAY = 350,
     /// 351 - Server version
     RPL_VERSION = 351,
     /// 352 - WHO reply
@@ -386,6 +388,8 @@ pub enum Response {
     ERR_BANLISTFULL = 478,
     /// 479 - Bad channel name
     ERR_BADCHANNAME = 479,
+    /// 480 - Cannot knock on channel
+    ERR_CANNOTKNOCK = 480,
     /// 481 - No privileges
     ERR_NOPRIVILEGES = 481,
     /// 482 - Channel op privileges needed
@@ -408,6 +412,8 @@ pub enum Response {
     ERR_USERSDONTMATCH = 502,
     /// 511 - Silence list full
     ERR_SILELISTFULL = 511,
+    /// 512 - WATCH list full
+    ERR_TOOMANYWATCH = 512,
     /// 524 - Help not found
     ERR_HELPNOTFOUND = 524,
     /// 525 - Invalid channel key
@@ -416,6 +422,18 @@ pub enum Response {
     ERR_CANNOTSENDRP = 573,
 
     // === Extended/Modern Numerics (600+) ===
+    /// 600 - WATCH: user logged online
+    RPL_LOGON = 600,
+    /// 601 - WATCH: user logged offline
+    RPL_LOGOFF = 601,
+    /// 602 - WATCH: stopped watching
+    RPL_WATCHOFF = 602,
+    /// 603 - WATCH statistics
+    RPL_WATCHSTAT = 603,
+    /// 604 - WATCH: user is online
+    RPL_NOWON = 604,
+    /// 605 - WATCH: user is offline
+    RPL_NOWOFF = 605,
     /// 606 - Map entry
     RPL_MAP = 606,
     /// 607 - End of map
@@ -457,6 +475,12 @@ pub enum Response {
     ERR_CHANOPEN = 713,
     /// 714 - Knock on channel
     ERR_KNOCKONCHAN = 714,
+    /// 716 - Target has caller ID (+g) set
+    ERR_TARGUMODEG = 716,
+    /// 717 - Caller ID target notified
+    RPL_TARGNOTIFY = 717,
+    /// 718 - Caller ID message notification
+    RPL_UMODEGMSG = 718,
     /// 723 - No privileges
     ERR_NOPRIVS = 723,
     /// 728 - Quiet list entry
@@ -476,6 +500,12 @@ pub enum Response {
     /// 734 - Monitor list full
     ERR_MONLISTFULL = 734,
 
+    // Challenge
+    /// 740 - CHALLENGE text to sign
+    RPL_RSACHALLENGE2 = 740,
+    /// 741 - End of CHALLENGE text
+    RPL_ENDOFRSACHALLENGE2 = 741,
+
     // Metadata
     /// 760 - WHOIS key/value
     RPL_WHOISKEYVALUE = 760,
@@ -495,6 +525,14 @@ pub enum Response {
     ERR_KEYNOTSET = 768,
     /// 769 - Key no permission
     ERR_KEYNOPERMISSION = 769,
+    /// 770 - Subscribed to metadata keys
+    RPL_METADATASUBOK = 770,
+    /// 771 - Unsubscribed from metadata keys
+    RPL_METADATAUNSUBOK = 771,
+    /// 772 - Metadata subscriptions
+    RPL_METADATASUBS = 772,
+    /// 773 - Too many metadata subscriptions
+    ERR_METADATATOOMANYSUBS = 773,
 
     // SASL (IRCv3)
     /// 900 - Logged in
@@ -528,6 +566,12 @@ impl Response {
     /// Deprecated alias for [`Response::ERR_NEEDREGGEDNICK`].
     #[deprecated(since = "1.3.0", note = "use ERR_NEEDREGGEDNICK")]
     pub const ERR_NOCHANMODES: Response = Response::ERR_NEEDREGGEDNICK;
+
+    /// 606 - WATCH list entries (shares its code with [`Response::RPL_MAP`]).
+    pub const RPL_WATCHLIST: Response = Response::RPL_MAP;
+
+    /// 607 - End of WATCH list (shares its code with [`Response::RPL_MAPEND`]).
+    pub const RPL_ENDOFWATCHLIST: Response = Response::RPL_MAPEND;
 }
 
 #[cfg(test)]
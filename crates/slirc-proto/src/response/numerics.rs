@@ -96,6 +96,7 @@ impl Response {
             347 => Response::RPL_ENDOFINVITELIST,
             348 => Response::RPL_EXCEPTLIST,
             349 => Response::RPL_ENDOFEXCEPTLIST,
+            350 => Response::RPL_WHOISGATEWAY,
             351 => Response::RPL_VERSION,
             352 => Response::RPL_WHOREPLY,
             353 => Response::RPL_NAMREPLY,
@@ -122,6 +123,12 @@ impl Response {
             394 => Response::RPL_ENDOFUSERS,
             395 => Response::RPL_NOUSERS,
             396 => Response::RPL_HOSTHIDDEN,
+            600 => Response::RPL_LOGON,
+            601 => Response::RPL_LOGOFF,
+            602 => Response::RPL_WATCHOFF,
+            603 => Response::RPL_WATCHSTAT,
+            604 => Response::RPL_NOWON,
+            605 => Response::RPL_NOWOFF,
             606 => Response::RPL_MAP,
             607 => Response::RPL_MAPEND,
             632 => Response::RPL_RULESTART,
@@ -136,14 +143,21 @@ impl Response {
             706 => Response::RPL_ENDOFHELP,
             710 => Response::RPL_KNOCK,
             711 => Response::RPL_KNOCKDLVR,
+            717 => Response::RPL_TARGNOTIFY,
+            718 => Response::RPL_UMODEGMSG,
             728 => Response::RPL_QUIETLIST,
             729 => Response::RPL_ENDOFQUIETLIST,
             730 => Response::RPL_MONONLINE,
             731 => Response::RPL_MONOFFLINE,
             732 => Response::RPL_MONLIST,
             733 => Response::RPL_ENDOFMONLIST,
+            740 => Response::RPL_RSACHALLENGE2,
+            741 => Response::RPL_ENDOFRSACHALLENGE2,
             760 => Response::RPL_WHOISKEYVALUE,
             761 => Response::RPL_KEYVALUE,
+            770 => Response::RPL_METADATASUBOK,
+            771 => Response::RPL_METADATAUNSUBOK,
+            772 => Response::RPL_METADATASUBS,
             900 => Response::RPL_LOGGEDIN,
             901 => Response::RPL_LOGGEDOUT,
             903 => Response::RPL_SASLSUCCESS,
@@ -7,14 +7,14 @@
 //!
 //! - **PLAIN**: Simple username/password authentication (RFC 4616)
 //! - **EXTERNAL**: Certificate-based authentication (client cert)
-//! - **SCRAM-SHA-256**: Challenge-response authentication (RFC 7677) - *partial support*
+//! - **SCRAM-SHA-256**: Challenge-response authentication (RFC 7677)
 //!
 //! # SCRAM-SHA-256 Support
 //!
-//! SCRAM-SHA-256 is recognized and preferred by [`choose_mechanism`], but full
-//! client-side implementation requires cryptographic dependencies (sha2, hmac, pbkdf2).
-//! The [`ScramClient`] struct provides the state machine; actual payload generation
-//! will be added in a future release with an optional `scram` feature flag.
+//! SCRAM-SHA-256 is recognized and preferred by [`choose_mechanism`]. The
+//! [`ScramClient`] builds the client messages and verifies the server
+//! signature when the `scram` feature enables the cryptographic dependencies
+//! (sha2, hmac, pbkdf2); without it only the state machine is available.
 //!
 //! # Reference
 //! - IRCv3 SASL: <https://ircv3.net/specs/extensions/sasl-3.2>
@@ -10,6 +10,9 @@
 //! Without the `scram` feature, the state machine is available but
 //! `process_server_first()` returns `ScramError::CryptoNotAvailable`.
 //!
+//! Channel binding is not supported: the client always sends the `n` GS2
+//! flag, optionally with an authorization identity.
+//!
 //! # SCRAM Protocol Flow
 //!
 //! 1. Client sends `client-first-message`: `n,,n=user,r=nonce`
@@ -22,7 +25,7 @@
 //! ```ignore
 //! use slirc_proto::sasl::ScramClient;
 //!
-//! let mut client = ScramClient::new("username", "password");
+//! let mut client = ScramClient::new("username", "password")?;
 //! let first = client.client_first_message();
 //! // Send first to server, receive server_first back
 //! # #[cfg(feature = "scram")]
@@ -64,6 +67,8 @@ pub struct ScramClient {
     username: String,
     #[cfg(feature = "scram")]
     password: String,
+    /// Optional authorization identity for the GS2 header.
+    authzid: Option<String>,
     client_nonce: String,
     /// Stored for AuthMessage computation
     client_first_message_bare: String,
@@ -109,6 +114,7 @@ impl ScramClient {
             username: username.to_string(),
             #[cfg(feature = "scram")]
             password: password.to_string(),
+            authzid: None,
             client_nonce: nonce,
             client_first_message_bare: String::new(),
             server_first_message: String::new(),
@@ -118,6 +124,13 @@ impl ScramClient {
         })
     }
 
+    /// Request authorization as `authzid` rather than as `username`.
+    #[must_use]
+    pub fn with_authzid(mut self, authzid: &str) -> Self {
+        self.authzid = Some(authzid.to_string());
+        self
+    }
+
     /// Get the current SCRAM state.
     #[must_use]
     pub fn state(&self) -> &ScramState {
@@ -132,15 +145,26 @@ impl ScramClient {
     pub fn client_first_message(&mut self) -> String {
         self.state = ScramState::ClientFirstSent;
 
-        // gs2-header: n,, (no channel binding, no authzid)
         // client-first-message-bare: n=username,r=nonce
-        let bare = format!("n={},r={}", saslprep(&self.username), self.client_nonce);
+        let bare = format!(
+            "n={},r={}",
+            escape_saslname(&saslprep(&self.username)),
+            self.client_nonce
+        );
         self.client_first_message_bare = bare.clone();
-        let full = format!("n,,{bare}");
+        let full = format!("{}{bare}", self.gs2_header());
 
         BASE64.encode(full.as_bytes())
     }
 
+    /// GS2 header: `n` (no channel binding) and the optional authzid.
+    fn gs2_header(&self) -> String {
+        match &self.authzid {
+            Some(authzid) => format!("n,a={},", escape_saslname(&saslprep(authzid))),
+            None => "n,,".to_string(),
+        }
+    }
+
     /// Process the server-first-message and generate client-final-message.
     ///
     /// # Arguments
@@ -153,14 +177,57 @@ impl ScramClient {
     ///
     /// # Errors
     ///
-    /// Returns `ScramError::CryptoNotAvailable` if the `scram` feature is not enabled.
+    /// - `ScramError::UnexpectedMessage` if the client-first-message was not sent
+    /// - `ScramError::ServerError` if the server reported an error
+    /// - `ScramError::CryptoNotAvailable` if the `scram` feature is not enabled
     pub fn process_server_first(&mut self, server_first: &str) -> Result<String, ScramError> {
+        if self.state != ScramState::ClientFirstSent {
+            return Err(ScramError::UnexpectedMessage);
+        }
+        let result = self.parse_server_first(server_first);
+        if let Err(e) = &result {
+            self.state = ScramState::Failed(e.to_string());
+        }
+        let (nonce, salt, iterations) = result?;
+
+        self.state = ScramState::ServerFirstReceived {
+            nonce: nonce.clone(),
+            salt: salt.clone(),
+            iterations,
+        };
+
+        #[cfg(feature = "scram")]
+        {
+            self.compute_client_final(&nonce, &salt, iterations)
+        }
+
+        #[cfg(not(feature = "scram"))]
+        {
+            let _ = (nonce, salt, iterations);
+            Err(ScramError::CryptoNotAvailable)
+        }
+    }
+
+    /// Parse and validate a base64-encoded server-first-message into its
+    /// nonce, salt and iteration count.
+    fn parse_server_first(
+        &mut self,
+        server_first: &str,
+    ) -> Result<(String, Vec<u8>, u32), ScramError> {
         let decoded = decode_base64(server_first).map_err(|_| ScramError::InvalidEncoding)?;
         let message = String::from_utf8(decoded).map_err(|_| ScramError::InvalidEncoding)?;
 
         // Store for AuthMessage computation
         self.server_first_message = message.clone();
 
+        if let Some(error) = message.strip_prefix("e=") {
+            return Err(ScramError::ServerError(error.to_string()));
+        }
+        // Mandatory extensions are not supported (RFC 5802 section 5.1)
+        if message.starts_with("m=") {
+            return Err(ScramError::UnsupportedExtension);
+        }
+
         // Parse server-first-message: r=nonce,s=salt,i=iterations
         let mut nonce = None;
         let mut salt = None;
@@ -180,27 +247,15 @@ impl ScramClient {
         let salt = salt.ok_or(ScramError::MissingSalt)?;
         let iterations = iterations.ok_or(ScramError::MissingIterations)?;
 
-        // Verify that server nonce starts with our client nonce
-        if !nonce.starts_with(&self.client_nonce) {
+        // The server nonce must extend our client nonce
+        if nonce.len() <= self.client_nonce.len() || !nonce.starts_with(&self.client_nonce) {
             return Err(ScramError::NonceMismatch);
         }
-
-        self.state = ScramState::ServerFirstReceived {
-            nonce: nonce.clone(),
-            salt: salt.clone(),
-            iterations,
-        };
-
-        #[cfg(feature = "scram")]
-        {
-            self.compute_client_final(&nonce, &salt, iterations)
+        if iterations == 0 {
+            return Err(ScramError::InvalidIterations);
         }
 
-        #[cfg(not(feature = "scram"))]
-        {
-            let _ = (nonce, salt, iterations);
-            Err(ScramError::CryptoNotAvailable)
-        }
+        Ok((nonce, salt, iterations))
     }
 
     /// Compute and return the client-final-message (requires `scram` feature).
@@ -220,9 +275,12 @@ impl ScramClient {
         // StoredKey = H(ClientKey)
         let stored_key = sha256(&client_key);
 
-        // client-final-message-without-proof = c=biws,r=nonce
-        // biws = base64("n,,") for no channel binding
-        let client_final_without_proof = format!("c=biws,r={nonce}");
+        // client-final-message-without-proof = c=base64(gs2-header),r=nonce
+        // ("c=biws" without an authzid)
+        let client_final_without_proof = format!(
+            "c={},r={nonce}",
+            BASE64.encode(self.gs2_header().as_bytes())
+        );
 
         // AuthMessage = client-first-message-bare + "," +
         //               server-first-message + "," +
@@ -274,32 +332,21 @@ impl ScramClient {
     /// # Errors
     ///
     /// - `ScramError::CryptoNotAvailable` if `scram` feature not enabled
+    /// - `ScramError::UnexpectedMessage` if no client-final-message was sent
+    /// - `ScramError::ServerError` if the server reported an error
     /// - `ScramError::ServerVerificationFailed` if signature doesn't match
     pub fn verify_server_final(&mut self, server_final: &str) -> Result<(), ScramError> {
         #[cfg(feature = "scram")]
         {
-            let decoded = decode_base64(server_final).map_err(|_| ScramError::InvalidEncoding)?;
-            let message = String::from_utf8(decoded).map_err(|_| ScramError::InvalidEncoding)?;
-
-            // Parse v=verifier
-            let verifier = message
-                .strip_prefix("v=")
-                .ok_or(ScramError::ServerVerificationFailed)?;
-
-            let server_sig = decode_base64(verifier).map_err(|_| ScramError::InvalidEncoding)?;
-
-            let expected = self
-                .server_signature
-                .as_ref()
-                .ok_or(ScramError::ServerVerificationFailed)?;
-
-            if server_sig == *expected {
-                self.state = ScramState::Complete;
-                Ok(())
-            } else {
-                self.state = ScramState::Failed("server verification failed".to_string());
-                Err(ScramError::ServerVerificationFailed)
+            if self.state != ScramState::ClientFinalSent {
+                return Err(ScramError::UnexpectedMessage);
             }
+            let result = self.check_server_signature(server_final);
+            self.state = match &result {
+                Ok(()) => ScramState::Complete,
+                Err(e) => ScramState::Failed(e.to_string()),
+            };
+            result
         }
 
         #[cfg(not(feature = "scram"))]
@@ -310,6 +357,37 @@ impl ScramClient {
     }
 }
 
+#[cfg(feature = "scram")]
+impl ScramClient {
+    /// Compare the server-final-message's verifier against the expected
+    /// ServerSignature.
+    fn check_server_signature(&self, server_final: &str) -> Result<(), ScramError> {
+        let decoded = decode_base64(server_final).map_err(|_| ScramError::InvalidEncoding)?;
+        let message = String::from_utf8(decoded).map_err(|_| ScramError::InvalidEncoding)?;
+
+        // server-final-message = (server-error / verifier) ["," extensions]
+        let first = message.split(',').next().unwrap_or_default();
+        if let Some(error) = first.strip_prefix("e=") {
+            return Err(ScramError::ServerError(error.to_string()));
+        }
+        let verifier = first
+            .strip_prefix("v=")
+            .ok_or(ScramError::ServerVerificationFailed)?;
+        let server_sig = decode_base64(verifier).map_err(|_| ScramError::InvalidEncoding)?;
+
+        let expected = self
+            .server_signature
+            .as_ref()
+            .ok_or(ScramError::ServerVerificationFailed)?;
+
+        if constant_time_eq(&server_sig, expected) {
+            Ok(())
+        } else {
+            Err(ScramError::ServerVerificationFailed)
+        }
+    }
+}
+
 /// Errors that can occur during SCRAM authentication.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ScramError {
@@ -327,6 +405,12 @@ pub enum ScramError {
     InvalidIterations,
     /// Server verification failed.
     ServerVerificationFailed,
+    /// The server reported an error (`e=` attribute).
+    ServerError(String),
+    /// The server requires an extension this client does not support.
+    UnsupportedExtension,
+    /// A server message arrived in the wrong state.
+    UnexpectedMessage,
     /// Cryptographic error.
     CryptoError(String),
     /// Cryptographic operations not available (requires `scram` feature).
@@ -343,6 +427,9 @@ impl std::fmt::Display for ScramError {
             Self::MissingIterations => write!(f, "missing iteration count"),
             Self::InvalidIterations => write!(f, "invalid iteration count"),
             Self::ServerVerificationFailed => write!(f, "server verification failed"),
+            Self::ServerError(msg) => write!(f, "server error: {}", msg),
+            Self::UnsupportedExtension => write!(f, "unsupported mandatory extension"),
+            Self::UnexpectedMessage => write!(f, "unexpected message for current state"),
             Self::CryptoError(msg) => write!(f, "crypto error: {}", msg),
             Self::CryptoNotAvailable => {
                 write!(f, "SCRAM crypto not available (requires scram feature)")
@@ -383,6 +470,12 @@ fn sha256(data: &[u8]) -> [u8; 32] {
     hasher.finalize().into()
 }
 
+/// Compare two byte strings without short-circuiting on the first mismatch.
+#[cfg(feature = "scram")]
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 // ============================================================================
 // Nonce generation
 // ============================================================================
@@ -427,6 +520,11 @@ fn saslprep(s: &str) -> String {
     s.to_string()
 }
 
+/// Escape a saslname: `=` becomes `=3D` and `,` becomes `=2C` (RFC 5802).
+fn escape_saslname(s: &str) -> String {
+    s.replace('=', "=3D").replace(',', "=2C")
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -480,6 +578,7 @@ mod tests {
         let mut client = ScramClient {
             username: "user".to_string(),
             password: "pencil".to_string(),
+            authzid: None,
             client_nonce: "rOprNGfwEbeRWgbNEkqO".to_string(),
             client_first_message_bare: String::new(),
             server_first_message: String::new(),
@@ -514,6 +613,94 @@ mod tests {
         assert!(matches!(client.state(), ScramState::Complete));
     }
 
+    #[test]
+    fn test_client_first_escapes_username_and_authzid() {
+        let mut client = ScramClient::new("a=b,c", "pencil")
+            .unwrap()
+            .with_authzid("org");
+        let first = client.client_first_message();
+        let decoded = String::from_utf8(BASE64.decode(&first).unwrap()).unwrap();
+
+        assert!(decoded.starts_with("n,a=org,n=a=3Db=2Cc,r="));
+    }
+
+    #[test]
+    fn test_server_first_error_and_state() {
+        let mut client = ScramClient::new("user", "pencil").unwrap();
+        let server_first = BASE64.encode(b"e=unknown-user");
+
+        // Nothing sent yet
+        assert_eq!(
+            client.process_server_first(&server_first).unwrap_err(),
+            ScramError::UnexpectedMessage
+        );
+
+        let _ = client.client_first_message();
+        assert_eq!(
+            client.process_server_first(&server_first).unwrap_err(),
+            ScramError::ServerError("unknown-user".to_string())
+        );
+        assert!(matches!(client.state(), ScramState::Failed(_)));
+    }
+
+    /// Run the RFC 7677 exchange up to the server-final-message.
+    #[cfg(feature = "scram")]
+    fn rfc7677_client(authzid: Option<&str>) -> (ScramClient, String) {
+        let mut client = ScramClient {
+            username: "user".to_string(),
+            password: "pencil".to_string(),
+            authzid: authzid.map(str::to_string),
+            client_nonce: "rOprNGfwEbeRWgbNEkqO".to_string(),
+            client_first_message_bare: String::new(),
+            server_first_message: String::new(),
+            state: ScramState::Initial,
+            server_signature: None,
+        };
+        let _ = client.client_first_message();
+        let server_first = BASE64.encode(
+            b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096",
+        );
+        let client_final = client.process_server_first(&server_first).unwrap();
+        let decoded = String::from_utf8(BASE64.decode(client_final).unwrap()).unwrap();
+        (client, decoded)
+    }
+
+    #[cfg(feature = "scram")]
+    #[test]
+    fn test_client_final_binds_gs2_header() {
+        let (_, decoded) = rfc7677_client(Some("org"));
+        let expected = format!("c={},", BASE64.encode(b"n,a=org,"));
+        assert!(decoded.starts_with(&expected));
+    }
+
+    #[cfg(feature = "scram")]
+    #[test]
+    fn test_server_final_rejections() {
+        let (mut client, _) = rfc7677_client(None);
+        let forged = BASE64.encode(b"v=AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=");
+        assert_eq!(
+            client.verify_server_final(&forged).unwrap_err(),
+            ScramError::ServerVerificationFailed
+        );
+        assert!(matches!(client.state(), ScramState::Failed(_)));
+
+        let (mut client, _) = rfc7677_client(None);
+        let error = BASE64.encode(b"e=invalid-proof");
+        assert_eq!(
+            client.verify_server_final(&error).unwrap_err(),
+            ScramError::ServerError("invalid-proof".to_string())
+        );
+
+        // Replaying a valid server-final after completion is rejected
+        let (mut client, _) = rfc7677_client(None);
+        let valid = BASE64.encode(b"v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=");
+        client.verify_server_final(&valid).unwrap();
+        assert_eq!(
+            client.verify_server_final(&valid).unwrap_err(),
+            ScramError::UnexpectedMessage
+        );
+    }
+
     #[cfg(feature = "scram")]
     #[test]
     fn test_hi_pbkdf2() {
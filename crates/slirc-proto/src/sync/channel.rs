@@ -3,19 +3,30 @@
 //! This module provides `ChannelCrdt`, a CRDT-enabled wrapper around channel
 //! state that supports distributed synchronization across linked servers.
 
-use super::clock::HybridTimestamp;
-use super::traits::{AwSet, Crdt, LwwRegister};
+use super::clock::{HybridTimestamp, ServerId};
+use super::traits::{merge_some, AwSet, Crdt, DeltaCrdt, LwwRegister};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 
 /// CRDT-enabled channel state for distributed synchronization.
 ///
 /// Uses different CRDT strategies for different fields:
 /// - **LWW (Last-Writer-Wins)**: topic, key, limit, modes
-/// - **`AWSet` (Add-Wins Set)**: members, bans, invites, excepts
+/// - **`AWSet` (Add-Wins Set)**: members, bans, invites, excepts, quiets
+///
+/// Removals are kept as timestamped state too: a cleared mode is an LWW
+/// register holding `false`, and a removed list entry leaves a tombstone,
+/// so an unban or `-m` survives a merge with a peer that has not seen it.
 ///
 /// Channel membership uses a specialized `MembershipCrdt` that tracks
 /// both presence and per-member modes (op, voice, etc.).
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+///
+/// Member modes follow the TS6 channel timestamp rule on merge: if one side
+/// has an older `created_at` (compared in whole milliseconds), its member
+/// modes win outright and the other side's are reset, so a channel
+/// recreated during a split cannot keep the ops it handed out. With equal
+/// creation times, each member's modes merge register by register with LWW.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct ChannelCrdt {
     /// Channel name (normalized to lowercase).
     pub name: String,
@@ -44,6 +55,10 @@ pub struct ChannelCrdt {
     /// Ban exceptions (+e).
     pub excepts: AwSet<ListEntryCrdt>,
 
+    /// Quiets (+q).
+    #[serde(default)]
+    pub quiets: AwSet<ListEntryCrdt>,
+
     /// Timestamp when channel was created.
     pub created_at: HybridTimestamp,
 }
@@ -59,8 +74,11 @@ pub struct TopicCrdt {
     pub set_at: i64,
 }
 
-/// A list entry (ban, invite, except) as a CRDT-compatible type.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq, Hash)]
+/// A list entry (ban, invite, except, quiet) as a CRDT-compatible type.
+///
+/// Entries are identified by their mask alone, so the same mask set on two
+/// servers is one entry and removing it removes it everywhere.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ListEntryCrdt {
     /// The ban/invite/except mask (e.g., *!*@host).
     pub mask: String,
@@ -70,10 +88,24 @@ pub struct ListEntryCrdt {
     pub set_at: i64,
 }
 
+impl PartialEq for ListEntryCrdt {
+    fn eq(&self, other: &Self) -> bool {
+        self.mask == other.mask
+    }
+}
+
+impl Eq for ListEntryCrdt {}
+
+impl std::hash::Hash for ListEntryCrdt {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.mask.hash(state);
+    }
+}
+
 /// CRDT-enabled channel modes.
 ///
 /// Each boolean mode is an independent LWW register.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct ChannelModesCrdt {
     /// +n: No external messages.
     pub no_external: LwwRegister<bool>,
@@ -129,6 +161,49 @@ impl ChannelModesCrdt {
             censor: LwwRegister::new(false, timestamp),
         }
     }
+
+    /// Get the register for a boolean mode by its mode character.
+    #[must_use]
+    pub fn flag(&self, mode: char) -> Option<&LwwRegister<bool>> {
+        Some(match mode {
+            'n' => &self.no_external,
+            't' => &self.topic_ops_only,
+            'm' => &self.moderated,
+            'i' => &self.invite_only,
+            's' => &self.secret,
+            'p' => &self.private,
+            'R' => &self.registered_only,
+            'c' => &self.no_colors,
+            'C' => &self.no_ctcp,
+            'z' => &self.ssl_only,
+            'D' => &self.delayed_join,
+            'S' => &self.strip_colors,
+            'B' => &self.anti_caps,
+            'G' => &self.censor,
+            _ => return None,
+        })
+    }
+
+    /// Get the register for a boolean mode by its mode character, mutably.
+    pub fn flag_mut(&mut self, mode: char) -> Option<&mut LwwRegister<bool>> {
+        Some(match mode {
+            'n' => &mut self.no_external,
+            't' => &mut self.topic_ops_only,
+            'm' => &mut self.moderated,
+            'i' => &mut self.invite_only,
+            's' => &mut self.secret,
+            'p' => &mut self.private,
+            'R' => &mut self.registered_only,
+            'c' => &mut self.no_colors,
+            'C' => &mut self.no_ctcp,
+            'z' => &mut self.ssl_only,
+            'D' => &mut self.delayed_join,
+            'S' => &mut self.strip_colors,
+            'B' => &mut self.anti_caps,
+            'G' => &mut self.censor,
+            _ => return None,
+        })
+    }
 }
 
 impl Crdt for ChannelModesCrdt {
@@ -173,7 +248,7 @@ impl Crdt for ChannelModesCrdt {
 ///
 /// Each member's presence and modes are tracked independently.
 /// Uses `AWSet` semantics for presence (JOIN adds, PART/KICK removes).
-#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct MembershipCrdt {
     /// Map from UID to member state.
     /// Presence is tracked via `AWSet`, modes via LWW.
@@ -183,7 +258,7 @@ pub struct MembershipCrdt {
 }
 
 /// Per-member modes (op, voice, etc.) as CRDT.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct MemberModesCrdt {
     /// Channel owner mode (+q).
     pub owner: LwwRegister<bool>,
@@ -299,6 +374,49 @@ impl MembershipCrdt {
     pub fn is_empty(&self) -> bool {
         self.presence.is_empty()
     }
+
+    /// Drop departures stamped before `stable_millis`, with the modes kept
+    /// for those members. Returns the number of tombstones dropped.
+    pub fn prune_tombstones(&mut self, stable_millis: i64) -> usize {
+        let departed: Vec<String> = self
+            .presence
+            .removed()
+            .filter(|(_, ts)| ts.millis < stable_millis)
+            .map(|(uid, _)| uid.clone())
+            .collect();
+        for uid in &departed {
+            if !self.presence.contains(uid) {
+                self.modes.remove(uid);
+            }
+        }
+        self.presence.prune_tombstones(stable_millis)
+    }
+}
+
+impl MembershipCrdt {
+    /// Merge membership from a channel with a newer creation time.
+    ///
+    /// Presence merges as usual, but `other`'s member modes are discarded:
+    /// members known only to `other` get unprivileged modes at the lowest
+    /// possible timestamp, so any mode set under our creation time wins.
+    fn merge_outranked(&mut self, other: &Self) {
+        self.presence.merge(&other.presence);
+
+        let reset_ts = HybridTimestamp::new(i64::MIN, 0, &ServerId::new("000"));
+        for (uid, other_modes) in &other.modes {
+            match self.modes.get_mut(uid) {
+                Some(self_modes) => {
+                    self_modes.join_time = self_modes.join_time.min(other_modes.join_time);
+                }
+                None => {
+                    self.modes.insert(
+                        uid.clone(),
+                        MemberModesCrdt::new(other_modes.join_time, reset_ts),
+                    );
+                }
+            }
+        }
+    }
 }
 
 impl Crdt for MembershipCrdt {
@@ -331,6 +449,29 @@ impl Crdt for MembershipCrdt {
 }
 
 impl ChannelCrdt {
+    /// Timestamps of the topic and of each mode that is set: the state a
+    /// TS6 burst carries for the channel besides its members.
+    pub fn state_timestamps(&self) -> impl Iterator<Item = HybridTimestamp> + '_ {
+        const FLAGS: [char; 14] = [
+            'n', 't', 'm', 'i', 's', 'p', 'R', 'c', 'C', 'z', 'D', 'S', 'B', 'G',
+        ];
+        let flags = FLAGS
+            .iter()
+            .filter_map(|&mode| self.modes.flag(mode))
+            .filter(|flag| *flag.value())
+            .map(LwwRegister::timestamp);
+        let topic = self.topic.value().is_some().then(|| self.topic.timestamp());
+        let key = self.key.value().is_some().then(|| self.key.timestamp());
+        let limit = self.limit.value().is_some().then(|| self.limit.timestamp());
+        let redirect = self
+            .modes
+            .redirect
+            .value()
+            .is_some()
+            .then(|| self.modes.redirect.timestamp());
+        flags.chain([topic, key, limit, redirect].into_iter().flatten())
+    }
+
     /// Create a new channel.
     #[must_use]
     pub fn new(name: String, timestamp: HybridTimestamp) -> Self {
@@ -344,6 +485,7 @@ impl ChannelCrdt {
             bans: AwSet::new(),
             invites: AwSet::new(),
             excepts: AwSet::new(),
+            quiets: AwSet::new(),
             created_at: timestamp,
         }
     }
@@ -383,6 +525,18 @@ impl ChannelCrdt {
         self.bans.add(entry, timestamp);
     }
 
+    /// Drop member and list tombstones stamped before `stable_millis`.
+    ///
+    /// See [`AwSet::prune_tombstones`] for when this is safe. Returns the
+    /// number of tombstones dropped.
+    pub fn prune_tombstones(&mut self, stable_millis: i64) -> usize {
+        self.members.prune_tombstones(stable_millis)
+            + self.bans.prune_tombstones(stable_millis)
+            + self.invites.prune_tombstones(stable_millis)
+            + self.excepts.prune_tombstones(stable_millis)
+            + self.quiets.prune_tombstones(stable_millis)
+    }
+
     /// Remove a ban.
     pub fn remove_ban(&mut self, mask: &str, timestamp: HybridTimestamp) {
         // Find and remove the ban entry with matching mask
@@ -399,6 +553,48 @@ impl ChannelCrdt {
     }
 }
 
+/// Merge `other` into `members` under the TS rule used by `ChannelCrdt`.
+///
+/// The side with the older creation time keeps its member modes and the
+/// other side's are reset; with equal creation times modes merge per LWW.
+fn merge_members(
+    members: &mut MembershipCrdt,
+    created_at: HybridTimestamp,
+    other: &MembershipCrdt,
+    other_created_at: HybridTimestamp,
+) {
+    match created_at.millis.cmp(&other_created_at.millis) {
+        Ordering::Equal => members.merge(other),
+        Ordering::Less => members.merge_outranked(other),
+        Ordering::Greater => {
+            let mut merged = other.clone();
+            merged.merge_outranked(members);
+            *members = merged;
+        }
+    }
+}
+
+/// Membership dominance under the TS rule used by `merge_members`.
+fn members_dominate(
+    members: &MembershipCrdt,
+    created_at: HybridTimestamp,
+    other: &MembershipCrdt,
+    other_created_at: HybridTimestamp,
+) -> bool {
+    match created_at.millis.cmp(&other_created_at.millis) {
+        Ordering::Equal => members.dominates(other),
+        // Our modes outrank theirs; we only need to have seen every member
+        Ordering::Less => {
+            members.presence.dominates(&other.presence)
+                && other
+                    .modes
+                    .keys()
+                    .all(|uid| members.modes.contains_key(uid))
+        }
+        Ordering::Greater => false,
+    }
+}
+
 impl Crdt for ChannelCrdt {
     fn merge(&mut self, other: &Self) {
         debug_assert_eq!(self.name, other.name);
@@ -407,10 +603,17 @@ impl Crdt for ChannelCrdt {
         self.modes.merge(&other.modes);
         self.key.merge(&other.key);
         self.limit.merge(&other.limit);
-        self.members.merge(&other.members);
+        // TS rule: the older channel's member modes win
+        merge_members(
+            &mut self.members,
+            self.created_at,
+            &other.members,
+            other.created_at,
+        );
         self.bans.merge(&other.bans);
         self.invites.merge(&other.invites);
         self.excepts.merge(&other.excepts);
+        self.quiets.merge(&other.quiets);
         // created_at: take earlier timestamp
         if other.created_at < self.created_at {
             self.created_at = other.created_at;
@@ -422,10 +625,194 @@ impl Crdt for ChannelCrdt {
             && self.modes.dominates(&other.modes)
             && self.key.dominates(&other.key)
             && self.limit.dominates(&other.limit)
-            && self.members.dominates(&other.members)
+            && members_dominate(
+                &self.members,
+                self.created_at,
+                &other.members,
+                other.created_at,
+            )
             && self.bans.dominates(&other.bans)
             && self.invites.dominates(&other.invites)
             && self.excepts.dominates(&other.excepts)
+            && self.quiets.dominates(&other.quiets)
+    }
+}
+
+impl DeltaCrdt for MembershipCrdt {
+    type Delta = Self;
+
+    fn delta_from(&self, base: &Self) -> Option<Self> {
+        let modes: HashMap<_, _> = self
+            .modes
+            .iter()
+            .filter(|(uid, modes)| base.modes.get(*uid).map_or(true, |b| !b.dominates(modes)))
+            .map(|(uid, modes)| (uid.clone(), modes.clone()))
+            .collect();
+        let presence = self.presence.delta_from(&base.presence);
+        if presence.is_none() && modes.is_empty() {
+            return None;
+        }
+        Some(Self {
+            presence: presence.unwrap_or_default(),
+            modes,
+        })
+    }
+
+    fn merge_delta(&mut self, delta: &Self) {
+        self.merge(delta);
+    }
+}
+
+/// A delta representing changes to a `ChannelCrdt`.
+///
+/// Only contains fields that have changed, for efficient network transfer.
+/// The creation time is always included because it decides, under the TS
+/// rule, whose member modes survive the merge. Channel modes are small and
+/// are sent whole when any of them changed.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ChannelDelta {
+    /// Channel name (normalized to lowercase).
+    pub name: String,
+    /// Creation time of the channel on the sending side.
+    pub created_at: HybridTimestamp,
+    /// Updated topic (if changed).
+    pub topic: Option<LwwRegister<Option<TopicCrdt>>>,
+    /// Channel modes (if any changed).
+    pub modes: Option<ChannelModesCrdt>,
+    /// Updated key (if changed).
+    pub key: Option<LwwRegister<Option<String>>>,
+    /// Updated limit (if changed).
+    pub limit: Option<LwwRegister<Option<u32>>>,
+    /// Joins, parts and member mode changes since last sync.
+    pub members: MembershipCrdt,
+    /// Bans added or removed since last sync.
+    pub bans: Option<AwSet<ListEntryCrdt>>,
+    /// Invite exceptions added or removed since last sync.
+    pub invites: Option<AwSet<ListEntryCrdt>>,
+    /// Ban exceptions added or removed since last sync.
+    pub excepts: Option<AwSet<ListEntryCrdt>>,
+    /// Quiets added or removed since last sync.
+    #[serde(default)]
+    pub quiets: Option<AwSet<ListEntryCrdt>>,
+}
+
+impl ChannelDelta {
+    /// Create an empty delta for a channel.
+    #[must_use]
+    pub fn new(name: String, created_at: HybridTimestamp) -> Self {
+        Self {
+            name,
+            created_at,
+            topic: None,
+            modes: None,
+            key: None,
+            limit: None,
+            members: MembershipCrdt::new(),
+            bans: None,
+            invites: None,
+            excepts: None,
+            quiets: None,
+        }
+    }
+
+    /// Check if the delta carries no changes besides the creation time.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::new(self.name.clone(), self.created_at)
+    }
+}
+
+impl Crdt for ChannelDelta {
+    fn merge(&mut self, other: &Self) {
+        debug_assert_eq!(self.name, other.name);
+
+        self.topic.merge(&other.topic);
+        self.modes.merge(&other.modes);
+        self.key.merge(&other.key);
+        self.limit.merge(&other.limit);
+        merge_members(
+            &mut self.members,
+            self.created_at,
+            &other.members,
+            other.created_at,
+        );
+        self.bans.merge(&other.bans);
+        self.invites.merge(&other.invites);
+        self.excepts.merge(&other.excepts);
+        self.quiets.merge(&other.quiets);
+        if other.created_at < self.created_at {
+            self.created_at = other.created_at;
+        }
+    }
+
+    fn dominates(&self, other: &Self) -> bool {
+        self.topic.dominates(&other.topic)
+            && self.modes.dominates(&other.modes)
+            && self.key.dominates(&other.key)
+            && self.limit.dominates(&other.limit)
+            && members_dominate(
+                &self.members,
+                self.created_at,
+                &other.members,
+                other.created_at,
+            )
+            && self.bans.dominates(&other.bans)
+            && self.invites.dominates(&other.invites)
+            && self.excepts.dominates(&other.excepts)
+            && self.quiets.dominates(&other.quiets)
+    }
+}
+
+impl DeltaCrdt for ChannelCrdt {
+    type Delta = ChannelDelta;
+
+    fn delta_from(&self, base: &Self) -> Option<ChannelDelta> {
+        debug_assert_eq!(self.name, base.name);
+
+        // An older creation time than the base means our member modes now
+        // outrank the base's, even where their timestamps are lower: ship
+        // them all.
+        let members = if self.created_at.millis < base.created_at.millis {
+            self.members.clone()
+        } else {
+            self.members.delta_from(&base.members).unwrap_or_default()
+        };
+        let delta = ChannelDelta {
+            name: self.name.clone(),
+            created_at: self.created_at,
+            topic: self.topic.delta_from(&base.topic),
+            modes: (!base.modes.dominates(&self.modes)).then(|| self.modes.clone()),
+            key: self.key.delta_from(&base.key),
+            limit: self.limit.delta_from(&base.limit),
+            members,
+            bans: self.bans.delta_from(&base.bans),
+            invites: self.invites.delta_from(&base.invites),
+            excepts: self.excepts.delta_from(&base.excepts),
+            quiets: self.quiets.delta_from(&base.quiets),
+        };
+        (!delta.is_empty() || delta.created_at != base.created_at).then_some(delta)
+    }
+
+    fn merge_delta(&mut self, delta: &ChannelDelta) {
+        debug_assert_eq!(self.name, delta.name);
+
+        merge_some(&mut self.topic, delta.topic.as_ref());
+        merge_some(&mut self.modes, delta.modes.as_ref());
+        merge_some(&mut self.key, delta.key.as_ref());
+        merge_some(&mut self.limit, delta.limit.as_ref());
+        merge_members(
+            &mut self.members,
+            self.created_at,
+            &delta.members,
+            delta.created_at,
+        );
+        merge_some(&mut self.bans, delta.bans.as_ref());
+        merge_some(&mut self.invites, delta.invites.as_ref());
+        merge_some(&mut self.excepts, delta.excepts.as_ref());
+        merge_some(&mut self.quiets, delta.quiets.as_ref());
+        if delta.created_at < self.created_at {
+            self.created_at = delta.created_at;
+        }
     }
 }
 
@@ -568,6 +955,30 @@ mod tests {
         assert!(chan.bans.is_empty());
     }
 
+    #[test]
+    fn test_channel_crdt_prune_tombstones() {
+        let server = ServerId::new("001");
+        let mut chan = make_channel("#test", &server, 100);
+        let ts = |millis| HybridTimestamp::new(millis, 0, &server);
+
+        chan.join("001AAA".to_string(), ts(200));
+        chan.part("001AAA", ts(300));
+        chan.join("001BBB".to_string(), ts(200));
+        chan.part("001BBB", ts(600));
+        chan.add_ban("*!*@bad.host".to_string(), "oper".to_string(), ts(200));
+        chan.remove_ban("*!*@bad.host", ts(300));
+
+        assert_eq!(chan.prune_tombstones(500), 2);
+        assert_eq!(chan.bans.removed().count(), 0);
+        assert_eq!(chan.members.presence.removed().count(), 1);
+        // The departed member's kept modes go with its tombstone
+        assert!(!chan.members.modes.contains_key("001AAA"));
+        assert!(chan.members.modes.contains_key("001BBB"));
+
+        // Nothing left before the stable point
+        assert_eq!(chan.prune_tombstones(500), 0);
+    }
+
     #[test]
     fn test_channel_crdt_mode_change() {
         let server1 = ServerId::new("001");
@@ -754,4 +1165,260 @@ mod tests {
 
         assert_eq!(topic1, topic2);
     }
+
+    #[test]
+    fn test_member_op_converges_after_concurrent_change() {
+        let server1 = ServerId::new("001");
+        let server2 = ServerId::new("002");
+        let ts0 = HybridTimestamp::new(100, 0, &server1);
+
+        let mut base = ChannelCrdt::new("#test".to_string(), ts0);
+        base.join("001AAA".to_string(), ts0);
+
+        // +o on one side of the split, -o later on the other
+        let mut side1 = base.clone();
+        side1
+            .members
+            .get_modes_mut("001AAA")
+            .unwrap()
+            .op
+            .update(true, HybridTimestamp::new(200, 0, &server1));
+        let mut side2 = base.clone();
+        side2
+            .members
+            .get_modes_mut("001AAA")
+            .unwrap()
+            .op
+            .update(false, HybridTimestamp::new(300, 0, &server2));
+
+        let mut merged1 = side1.clone();
+        merged1.merge(&side2);
+        let mut merged2 = side2.clone();
+        merged2.merge(&side1);
+
+        assert_eq!(merged1, merged2);
+        assert!(!*merged1.members.get_modes("001AAA").unwrap().op.value());
+    }
+
+    #[test]
+    fn test_older_channel_member_modes_win() {
+        let server1 = ServerId::new("001");
+        let server2 = ServerId::new("002");
+
+        // Original channel: 001AAA is opped
+        let old_ts = HybridTimestamp::new(1_000, 0, &server1);
+        let mut old = ChannelCrdt::new("#test".to_string(), old_ts);
+        old.join("001AAA".to_string(), old_ts);
+        old.members
+            .get_modes_mut("001AAA")
+            .unwrap()
+            .op
+            .update(true, HybridTimestamp::new(1_001, 0, &server1));
+
+        // Recreated during a split: 002BBB opped itself much later
+        let new_ts = HybridTimestamp::new(5_000, 0, &server2);
+        let mut new = ChannelCrdt::new("#test".to_string(), new_ts);
+        new.join("002BBB".to_string(), new_ts);
+        new.members
+            .get_modes_mut("002BBB")
+            .unwrap()
+            .op
+            .update(true, HybridTimestamp::new(5_001, 0, &server2));
+
+        let mut merged1 = old.clone();
+        merged1.merge(&new);
+        let mut merged2 = new.clone();
+        merged2.merge(&old);
+
+        assert_eq!(merged1, merged2);
+        assert_eq!(merged1.created_at, old_ts);
+        assert!(merged1.members.contains("002BBB"));
+        assert!(*merged1.members.get_modes("001AAA").unwrap().op.value());
+        assert!(!*merged1.members.get_modes("002BBB").unwrap().op.value());
+        assert!(merged1.dominates(&old));
+        assert!(merged1.dominates(&new));
+    }
+
+    #[test]
+    fn test_state_timestamps_cover_only_set_state() {
+        let server = ServerId::new("001");
+        let mut chan = make_channel("#test", &server, 100);
+        chan.set_topic(
+            "hello".to_string(),
+            "nick".to_string(),
+            HybridTimestamp::new(200, 0, &server),
+        );
+        chan.modes
+            .moderated
+            .update(false, HybridTimestamp::new(300, 0, &server));
+
+        // +n and +t from creation, and the topic; not the unset +m or key
+        let mut millis: Vec<_> = chan.state_timestamps().map(|ts| ts.millis).collect();
+        millis.sort_unstable();
+        assert_eq!(millis, [100, 100, 200]);
+    }
+
+    #[test]
+    fn test_channel_delta_carries_only_changes() {
+        let server = ServerId::new("001");
+        let mut base = make_channel("#test", &server, 100);
+        for i in 0..10 {
+            base.join(format!("001AA{i}"), HybridTimestamp::new(110, 0, &server));
+        }
+        assert!(base.delta_from(&base).is_none());
+
+        let mut chan = base.clone();
+        chan.set_topic(
+            "hello".to_string(),
+            "nick".to_string(),
+            HybridTimestamp::new(200, 0, &server),
+        );
+        chan.part("001AA3", HybridTimestamp::new(210, 0, &server));
+
+        let delta = chan.delta_from(&base).expect("channel changed");
+        assert!(delta.topic.is_some());
+        assert!(delta.modes.is_none());
+        assert!(delta.bans.is_none());
+        assert!(delta.members.is_empty());
+        assert!(delta.members.modes.is_empty());
+
+        let mut peer = base.clone();
+        peer.merge_delta(&delta);
+        assert_eq!(peer, chan);
+    }
+
+    #[test]
+    fn test_channel_delta_respects_ts_rule() {
+        let server1 = ServerId::new("001");
+        let server2 = ServerId::new("002");
+
+        // The peer's copy was recreated during a split and handed out ops
+        let mut newer = make_channel("#test", &server2, 200);
+        newer.join("002AAA".to_string(), HybridTimestamp::new(210, 0, &server2));
+        newer
+            .members
+            .get_modes_mut("002AAA")
+            .unwrap()
+            .op
+            .update(true, HybridTimestamp::new(220, 0, &server2));
+
+        // We merge the original channel, then ship what changed
+        let mut older = make_channel("#test", &server1, 100);
+        older.join("001AAA".to_string(), HybridTimestamp::new(110, 0, &server1));
+        older
+            .members
+            .get_modes_mut("001AAA")
+            .unwrap()
+            .op
+            .update(true, HybridTimestamp::new(120, 0, &server1));
+        let base = newer.clone();
+        let mut merged = newer.clone();
+        merged.merge(&older);
+
+        let delta = merged.delta_from(&base).expect("creation time changed");
+        assert_eq!(delta.created_at, older.created_at);
+
+        let mut peer = newer.clone();
+        peer.merge_delta(&delta);
+        assert_eq!(peer, merged);
+        assert!(*peer.members.get_modes("001AAA").unwrap().op.value());
+        assert!(!*peer.members.get_modes("002AAA").unwrap().op.value());
+    }
+
+    fn quiet(mask: &str, set_by: &str) -> ListEntryCrdt {
+        ListEntryCrdt {
+            mask: mask.to_string(),
+            set_by: set_by.to_string(),
+            set_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_quiet_removal_survives_merge() {
+        let server1 = ServerId::new("001");
+        let server2 = ServerId::new("002");
+
+        let mut ours = make_channel("#test", &server1, 100);
+        ours.quiets.add(
+            quiet("*!*@spam", "op"),
+            HybridTimestamp::new(110, 0, &server1),
+        );
+        let theirs = ours.clone();
+
+        // We unquiet; the peer still holds the older add
+        ours.quiets.remove(
+            &quiet("*!*@spam", "op"),
+            HybridTimestamp::new(120, 0, &server1),
+        );
+
+        let mut merged = theirs.clone();
+        merged.merge(&ours);
+        assert!(merged.quiets.is_empty());
+
+        // A later re-add on the peer wins over our removal
+        let mut readd = theirs;
+        readd.quiets.add(
+            quiet("*!*@spam", "op2"),
+            HybridTimestamp::new(130, 0, &server2),
+        );
+        ours.merge(&readd);
+        assert_eq!(ours.quiets.iter().next().unwrap().set_by, "op2");
+    }
+
+    #[test]
+    fn test_list_entries_are_keyed_by_mask() {
+        let server1 = ServerId::new("001");
+        let server2 = ServerId::new("002");
+
+        let mut a = make_channel("#test", &server1, 100);
+        a.bans.add(
+            quiet("*!*@bad", "alice"),
+            HybridTimestamp::new(110, 0, &server1),
+        );
+        let mut b = make_channel("#test", &server2, 100);
+        b.bans.add(
+            quiet("*!*@bad", "bob"),
+            HybridTimestamp::new(115, 0, &server2),
+        );
+
+        a.merge(&b);
+        b.merge(&a);
+        assert_eq!(a.bans.len(), 1);
+        assert_eq!(a, b);
+        // The later add's metadata wins on both sides
+        assert_eq!(a.bans.iter().next().unwrap().set_by, "bob");
+    }
+
+    #[test]
+    fn test_channel_delta_carries_quiets() {
+        let server = ServerId::new("001");
+        let base = make_channel("#test", &server, 100);
+        let mut chan = base.clone();
+        chan.quiets.add(
+            quiet("*!*@spam", "op"),
+            HybridTimestamp::new(110, 0, &server),
+        );
+
+        let delta = chan.delta_from(&base).expect("quiet added");
+        assert!(delta.quiets.is_some());
+        assert!(delta.bans.is_none());
+
+        let mut peer = base;
+        peer.merge_delta(&delta);
+        assert_eq!(peer, chan);
+    }
+
+    #[test]
+    fn test_flag_lookup_by_mode_char() {
+        let server = ServerId::new("001");
+        let mut chan = make_channel("#test", &server, 100);
+
+        chan.modes
+            .flag_mut('m')
+            .unwrap()
+            .update(true, HybridTimestamp::new(110, 0, &server));
+        assert!(*chan.modes.moderated.value());
+        assert!(*chan.modes.flag('n').unwrap().value());
+        assert!(chan.modes.flag('k').is_none());
+    }
 }
@@ -4,9 +4,21 @@
 //! - `ServerId`: Unique identifier for a server in the cluster.
 //! - `VectorClock`: Tracks causal dependencies across servers.
 //! - `HybridTimestamp`: Combines wall clock and logical counter for ordering.
+//! - `HybridClock`: Issues monotonic timestamps and bounds drift from peers.
+//! - `StableVersionVector`: Finds the point every server has seen, so
+//!   tombstones before it can be garbage collected.
 
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Default bound on how far a peer's timestamp may run ahead of the local
+/// wall clock (one minute).
+pub const DEFAULT_MAX_DRIFT_MS: i64 = 60_000;
+
+/// Default time a retirement marker is kept before it may be expired
+/// (seven days), long enough for every replica to have merged it.
+pub const DEFAULT_RETIREMENT_GRACE_MS: i64 = 7 * 24 * 60 * 60 * 1000;
 
 /// A unique identifier for a server in the cluster.
 ///
@@ -78,10 +90,28 @@ impl HybridTimestamp {
     }
 
     /// Create a timestamp for the current time.
+    ///
+    /// Issued by the process-wide [`HybridClock`], so successive calls are
+    /// strictly increasing even if the wall clock steps backwards.
     #[must_use]
     pub fn now(server: &ServerId) -> Self {
-        let millis = chrono::Utc::now().timestamp_millis();
-        Self::new(millis, 0, server)
+        HybridClock::global().now(server)
+    }
+
+    /// Check whether this timestamp was issued for `server`.
+    #[must_use]
+    pub fn is_from(&self, server: &ServerId) -> bool {
+        Self::new(0, 0, server).server_hash == self.server_hash
+    }
+
+    /// This timestamp's place in its server's issue order, packed into one
+    /// counter: millis in the high bits, the logical counter in the low 20.
+    ///
+    /// `None` before the Unix epoch.
+    #[must_use]
+    pub fn position(&self) -> Option<u64> {
+        let millis = u64::try_from(self.millis).ok()?;
+        Some(millis << 20 | u64::from(self.counter.min(0xF_FFFF)))
     }
 
     /// Increment the logical counter.
@@ -135,14 +165,146 @@ impl Ord for HybridTimestamp {
     }
 }
 
+/// A peer timestamp ran further ahead of the local clock than allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("clock skew of {skew_ms}ms exceeds the {max_drift_ms}ms limit")]
+pub struct ClockSkewError {
+    /// How far the remote timestamp is ahead of local wall time.
+    pub skew_ms: i64,
+    /// The configured drift bound.
+    pub max_drift_ms: i64,
+}
+
+/// A hybrid logical clock.
+///
+/// Tracks the latest timestamp issued or observed so that:
+/// - [`now`](Self::now) never goes backwards, even if wall time regresses
+///   (the logical counter advances instead);
+/// - timestamps from peers more than `max_drift_ms` ahead of local wall
+///   time are rejected by [`observe`](Self::observe) or pulled back by
+///   [`clamp`](Self::clamp), so one misconfigured clock cannot win every
+///   LWW comparison.
+#[derive(Debug)]
+pub struct HybridClock {
+    /// Latest `(millis, counter)` issued or observed.
+    last: Mutex<(i64, u32)>,
+    max_drift_ms: i64,
+}
+
+static GLOBAL_CLOCK: HybridClock = HybridClock::new(DEFAULT_MAX_DRIFT_MS);
+
+impl HybridClock {
+    /// Create a clock that accepts peer timestamps at most `max_drift_ms`
+    /// ahead of local wall time.
+    #[must_use]
+    pub const fn new(max_drift_ms: i64) -> Self {
+        Self {
+            last: Mutex::new((i64::MIN, 0)),
+            max_drift_ms,
+        }
+    }
+
+    /// The process-wide clock used by [`HybridTimestamp::now`].
+    #[must_use]
+    pub fn global() -> &'static Self {
+        &GLOBAL_CLOCK
+    }
+
+    /// The drift bound in milliseconds.
+    #[must_use]
+    pub fn max_drift_ms(&self) -> i64 {
+        self.max_drift_ms
+    }
+
+    /// How far `remote_millis` is ahead of local wall time (negative if behind).
+    #[must_use]
+    pub fn skew_ms(remote_millis: i64) -> i64 {
+        remote_millis.saturating_sub(chrono::Utc::now().timestamp_millis())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, (i64, u32)> {
+        // The guarded state is two integers; a panic elsewhere cannot leave
+        // it inconsistent.
+        self.last.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Issue a timestamp strictly after every one issued or observed so far.
+    #[must_use]
+    pub fn now(&self, server: &ServerId) -> HybridTimestamp {
+        let wall = chrono::Utc::now().timestamp_millis();
+        let mut last = self.lock();
+        *last = if wall > last.0 {
+            (wall, 0)
+        } else if last.1 == u32::MAX {
+            (last.0.saturating_add(1), 0)
+        } else {
+            (last.0, last.1 + 1)
+        };
+        HybridTimestamp::new(last.0, last.1, server)
+    }
+
+    /// Record a timestamp received from a peer.
+    ///
+    /// Subsequent local timestamps will order after it. Fails without
+    /// recording anything if it is too far ahead of local wall time.
+    pub fn observe(&self, remote: &HybridTimestamp) -> Result<(), ClockSkewError> {
+        let skew_ms = Self::skew_ms(remote.millis);
+        if skew_ms > self.max_drift_ms {
+            return Err(ClockSkewError {
+                skew_ms,
+                max_drift_ms: self.max_drift_ms,
+            });
+        }
+        let mut last = self.lock();
+        if (remote.millis, remote.counter) > *last {
+            *last = (remote.millis, remote.counter);
+        }
+        Ok(())
+    }
+
+    /// Pull a peer timestamp back within the drift bound and observe it.
+    ///
+    /// Timestamps within the bound are returned unchanged.
+    #[must_use]
+    pub fn clamp(&self, remote: HybridTimestamp) -> HybridTimestamp {
+        let limit = chrono::Utc::now()
+            .timestamp_millis()
+            .saturating_add(self.max_drift_ms);
+        let clamped = if remote.millis > limit {
+            HybridTimestamp {
+                millis: limit,
+                ..remote
+            }
+        } else {
+            remote
+        };
+        let mut last = self.lock();
+        if (clamped.millis, clamped.counter) > *last {
+            *last = (clamped.millis, clamped.counter);
+        }
+        clamped
+    }
+}
+
 /// A vector clock for tracking causal dependencies.
 ///
 /// Each entry maps a server ID to its latest known timestamp.
 /// Vector clocks enable detecting concurrent events and ensuring
 /// causal consistency.
-#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+///
+/// Servers that permanently leave the network are pruned with
+/// [`retire`](Self::retire). The retirement is recorded as a marker that
+/// merges like any other entry, so an unpruned replica cannot reintroduce
+/// the server's counter. Once every replica has seen the marker (after a
+/// grace period such as [`DEFAULT_RETIREMENT_GRACE_MS`]), the marker itself
+/// is dropped with [`expire_retirements`](Self::expire_retirements), keeping
+/// the clock bounded by the number of live servers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct VectorClock {
     entries: HashMap<String, u64>,
+    /// Retired servers and when they were retired (millis since epoch).
+    #[serde(default)]
+    retired: HashMap<String, i64>,
 }
 
 impl VectorClock {
@@ -158,20 +320,99 @@ impl VectorClock {
         self.entries.get(server.as_str()).copied().unwrap_or(0)
     }
 
+    /// Number of servers with a counter in this clock.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the clock has no counters.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
     /// Increment the counter for a server.
+    ///
+    /// Ignored for a retired server until its retirement expires.
     pub fn increment(&mut self, server: &ServerId) {
+        if self.is_retired(server) {
+            return;
+        }
         let entry = self.entries.entry(server.as_str().to_string()).or_insert(0);
         *entry = entry.saturating_add(1);
     }
 
+    /// Raise the counter for a server to at least `value`.
+    ///
+    /// Ignored for a retired server until its retirement expires.
+    pub fn advance(&mut self, server: &ServerId, value: u64) {
+        if self.is_retired(server) {
+            return;
+        }
+        let entry = self.entries.entry(server.as_str().to_string()).or_insert(0);
+        *entry = (*entry).max(value);
+    }
+
+    /// Check whether the counter for the server that issued `ts` has
+    /// reached its [`position`](HybridTimestamp::position), for clocks
+    /// [`advance`](Self::advance)d by timestamp.
+    #[must_use]
+    pub fn covers(&self, ts: &HybridTimestamp) -> bool {
+        let Some(position) = ts.position() else {
+            return false;
+        };
+        self.entries.iter().any(|(server, &counter)| {
+            counter >= position && ts.is_from(&ServerId::new(server.as_str()))
+        })
+    }
+
     /// Update to include all events from another clock.
     pub fn merge(&mut self, other: &Self) {
+        for (server, &at) in &other.retired {
+            let entry = self.retired.entry(server.clone()).or_insert(at);
+            *entry = (*entry).max(at);
+            self.entries.remove(server);
+        }
         for (server, &counter) in &other.entries {
+            if self.retired.contains_key(server) {
+                continue;
+            }
             let entry = self.entries.entry(server.clone()).or_insert(0);
             *entry = (*entry).max(counter);
         }
     }
 
+    /// Prune a server that has permanently left the network.
+    ///
+    /// `at_millis` is when the retirement was announced; it starts the
+    /// grace period for [`expire_retirements`](Self::expire_retirements).
+    pub fn retire(&mut self, server: &ServerId, at_millis: i64) {
+        self.entries.remove(server.as_str());
+        let entry = self
+            .retired
+            .entry(server.as_str().to_string())
+            .or_insert(at_millis);
+        *entry = (*entry).max(at_millis);
+    }
+
+    /// Check if a server has been retired.
+    #[must_use]
+    pub fn is_retired(&self, server: &ServerId) -> bool {
+        self.retired.contains_key(server.as_str())
+    }
+
+    /// Drop retirement markers older than `grace_ms` at `now_millis`.
+    ///
+    /// Only safe once every replica has merged the retirement; after this
+    /// the server ID may be reused. Returns the number of markers dropped.
+    pub fn expire_retirements(&mut self, now_millis: i64, grace_ms: i64) -> usize {
+        let before = self.retired.len();
+        self.retired
+            .retain(|_, &mut at| at.saturating_add(grace_ms) > now_millis);
+        before - self.retired.len()
+    }
+
     /// Check if this clock is causally before or concurrent with another.
     ///
     /// Returns:
@@ -184,9 +425,13 @@ impl VectorClock {
         let mut self_greater = false;
         let mut other_greater = false;
 
-        // Check all servers in both clocks
-        let all_servers: std::collections::HashSet<_> =
-            self.entries.keys().chain(other.entries.keys()).collect();
+        // Check all servers in both clocks, ignoring any either side retired
+        let all_servers: std::collections::HashSet<_> = self
+            .entries
+            .keys()
+            .chain(other.entries.keys())
+            .filter(|s| !self.retired.contains_key(*s) && !other.retired.contains_key(*s))
+            .collect();
 
         for server in all_servers {
             let self_val = self.entries.get(server).copied().unwrap_or(0);
@@ -214,6 +459,142 @@ impl VectorClock {
     }
 }
 
+/// Tracks which updates every server in the network has seen, to find the
+/// point before which tombstones are causally stable.
+///
+/// Each server periodically announces its own clock together with the
+/// latest clock it has heard announced by every other server. Since links
+/// deliver a server's messages in order, hearing an announcement made at
+/// `t` means every update that server stamped before `t` has arrived too.
+///
+/// The stable point is the lowest entry across every server's vector: all
+/// servers have seen every update stamped before it, so no add older than
+/// it can still arrive and a tombstone older than it can be dropped.
+///
+/// A server that splits keeps its last vector, holding the stable point
+/// back until it returns or is dropped with [`expire`](Self::expire).
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct StableVersionVector {
+    local: ServerId,
+    /// For each server, the latest announced clock (millis) it has heard
+    /// from each server, its own included.
+    vectors: HashMap<ServerId, HashMap<ServerId, i64>>,
+    /// When each server's vector was last updated (local millis).
+    heard_at: HashMap<ServerId, i64>,
+}
+
+impl StableVersionVector {
+    /// Create a tracker for the local server.
+    #[must_use]
+    pub fn new(local: ServerId) -> Self {
+        Self {
+            local,
+            vectors: HashMap::new(),
+            heard_at: HashMap::new(),
+        }
+    }
+
+    /// Record the local clock and return the vector to announce.
+    pub fn announce(&mut self, now_millis: i64) -> HashMap<ServerId, i64> {
+        let local = self.local.clone();
+        self.raise(&local, &local, now_millis);
+        self.heard_at.insert(local.clone(), now_millis);
+        self.vectors.get(&local).cloned().unwrap_or_default()
+    }
+
+    /// Record a vector announced by `server` and received at `now_millis`.
+    ///
+    /// Entries only move forward, so a stale or replayed announcement
+    /// changes nothing.
+    pub fn observe(&mut self, server: &ServerId, vector: &HashMap<ServerId, i64>, now_millis: i64) {
+        if *server == self.local {
+            return;
+        }
+        for (origin, &millis) in vector {
+            self.raise(server, origin, millis);
+        }
+        // The announcement itself is the latest update heard from `server`
+        if let Some(&own) = vector.get(server) {
+            let local = self.local.clone();
+            self.raise(&local, server, own);
+        }
+        self.heard_at.insert(server.clone(), now_millis);
+    }
+
+    fn raise(&mut self, holder: &ServerId, origin: &ServerId, millis: i64) {
+        let entry = self
+            .vectors
+            .entry(holder.clone())
+            .or_default()
+            .entry(origin.clone())
+            .or_insert(millis);
+        *entry = (*entry).max(millis);
+    }
+
+    /// Stop tracking a server that has left the network for good.
+    pub fn forget(&mut self, server: &ServerId) {
+        self.vectors.remove(server);
+        self.heard_at.remove(server);
+        for vector in self.vectors.values_mut() {
+            vector.remove(server);
+        }
+    }
+
+    /// Forget servers not heard from for `grace_ms` at `now_millis`.
+    ///
+    /// Returns the number of servers dropped.
+    pub fn expire(&mut self, now_millis: i64, grace_ms: i64) -> usize {
+        let stale: Vec<ServerId> = self
+            .heard_at
+            .iter()
+            .filter(|(server, &at)| {
+                **server != self.local && at.saturating_add(grace_ms) <= now_millis
+            })
+            .map(|(server, _)| server.clone())
+            .collect();
+        for server in &stale {
+            self.forget(server);
+        }
+        stale.len()
+    }
+
+    /// Check if an announcement from `server` has been recorded.
+    #[must_use]
+    pub fn contains(&self, server: &ServerId) -> bool {
+        self.vectors.contains_key(server)
+    }
+
+    /// Number of servers tracked, the local one included.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    /// Check if nothing has been recorded yet.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Millis before which every server has seen every update.
+    ///
+    /// `None` until the local clock has been announced, or while some
+    /// server has not yet heard from some other server.
+    #[must_use]
+    pub fn stable_millis(&self) -> Option<i64> {
+        if !self.vectors.contains_key(&self.local) {
+            return None;
+        }
+        let mut stable = i64::MAX;
+        for vector in self.vectors.values() {
+            for server in self.vectors.keys() {
+                stable = stable.min(*vector.get(server)?);
+            }
+        }
+        Some(stable)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -294,7 +675,70 @@ mod tests {
 
         assert!(ts.millis >= before);
         assert!(ts.millis <= after);
-        assert_eq!(ts.counter, 0);
+        assert!(HybridTimestamp::now(&server) > ts);
+    }
+
+    #[test]
+    fn test_hybrid_clock_monotonic_within_millisecond() {
+        let server = ServerId::new("001");
+        let clock = HybridClock::new(DEFAULT_MAX_DRIFT_MS);
+
+        let mut prev = clock.now(&server);
+        for _ in 0..1000 {
+            let next = clock.now(&server);
+            assert!(next > prev);
+            prev = next;
+        }
+    }
+
+    #[test]
+    fn test_hybrid_clock_survives_wall_clock_regression() {
+        let server = ServerId::new("001");
+        let clock = HybridClock::new(DEFAULT_MAX_DRIFT_MS);
+
+        // A peer 30s ahead is within bounds; afterwards our wall clock is
+        // effectively "behind" the clock state.
+        let ahead = chrono::Utc::now().timestamp_millis() + 30_000;
+        let remote = HybridTimestamp::new(ahead, 7, &ServerId::new("002"));
+        assert!(clock.observe(&remote).is_ok());
+
+        let ts1 = clock.now(&server);
+        let ts2 = clock.now(&server);
+        assert_eq!(ts1.millis, ahead);
+        assert_eq!(ts1.counter, 8);
+        assert!(ts1 > remote);
+        assert!(ts2 > ts1);
+    }
+
+    #[test]
+    fn test_hybrid_clock_rejects_future_timestamp() {
+        let server = ServerId::new("001");
+        let clock = HybridClock::new(1_000);
+
+        let far = chrono::Utc::now().timestamp_millis() + 3_600_000;
+        let remote = HybridTimestamp::new(far, 0, &ServerId::new("002"));
+        let err = clock.observe(&remote).unwrap_err();
+        assert!(err.skew_ms > 1_000);
+        assert_eq!(err.max_drift_ms, 1_000);
+
+        // Rejected timestamps do not drag the clock forward
+        assert!(clock.now(&server).millis < far);
+    }
+
+    #[test]
+    fn test_hybrid_clock_clamps_future_timestamp() {
+        let clock = HybridClock::new(1_000);
+        let peer = ServerId::new("002");
+
+        let far = chrono::Utc::now().timestamp_millis() + 3_600_000;
+        let clamped = clock.clamp(HybridTimestamp::new(far, 3, &peer));
+        assert!(clamped.millis < far);
+        assert!(HybridClock::skew_ms(clamped.millis) <= 1_000);
+        assert_eq!(clamped.counter, 3);
+
+        // Timestamps in the past pass through unchanged
+        let past = HybridTimestamp::new(100, 0, &peer);
+        assert_eq!(clock.clamp(past), past);
     }
 
     #[test]
@@ -403,4 +847,156 @@ mod tests {
             Some(Ordering::Equal)
         ));
     }
+
+    #[test]
+    fn test_vector_clock_retire_prunes_entry() {
+        let live = ServerId::new("001");
+        let gone = ServerId::new("002");
+
+        let mut vc = VectorClock::new();
+        vc.increment(&live);
+        vc.increment(&gone);
+        assert_eq!(vc.len(), 2);
+
+        vc.retire(&gone, 1_000);
+        assert_eq!(vc.len(), 1);
+        assert!(vc.is_retired(&gone));
+        assert_eq!(vc.get(&gone), 0);
+
+        vc.increment(&gone);
+        assert_eq!(vc.get(&gone), 0);
+    }
+
+    #[test]
+    fn test_vector_clock_retirement_survives_merge() {
+        let live = ServerId::new("001");
+        let gone = ServerId::new("002");
+
+        // A replica that has not heard of the retirement yet
+        let mut stale = VectorClock::new();
+        stale.increment(&live);
+        stale.increment(&gone);
+        stale.increment(&gone);
+
+        let mut pruned = stale.clone();
+        pruned.retire(&gone, 1_000);
+
+        // Merging the stale replica does not bring the counter back...
+        let mut a = pruned.clone();
+        a.merge(&stale);
+        assert_eq!(a.get(&gone), 0);
+        assert_eq!(a.len(), 1);
+
+        // ...and the stale replica learns the retirement
+        let mut b = stale.clone();
+        b.merge(&pruned);
+        assert!(b.is_retired(&gone));
+        assert_eq!(b.len(), 1);
+        assert!(matches!(a.partial_cmp_causal(&b), Some(Ordering::Equal)));
+
+        // A retired entry on one side does not make the clocks concurrent
+        let mut ahead = stale.clone();
+        ahead.increment(&live);
+        assert!(pruned.happened_before(&ahead));
+    }
+
+    #[test]
+    fn test_vector_clock_expire_retirements() {
+        let gone = ServerId::new("002");
+        let mut vc = VectorClock::new();
+        vc.increment(&gone);
+        vc.retire(&gone, 1_000);
+
+        assert_eq!(vc.expire_retirements(1_500, 1_000), 0);
+        assert!(vc.is_retired(&gone));
+
+        assert_eq!(vc.expire_retirements(2_000, 1_000), 1);
+        assert!(!vc.is_retired(&gone));
+        assert!(vc.is_empty());
+
+        // The SID can be reused after expiry
+        vc.increment(&gone);
+        assert_eq!(vc.get(&gone), 1);
+    }
+
+    #[test]
+    fn test_vector_clock_covers_advanced_timestamps() {
+        let (a, b) = (ServerId::new("00A"), ServerId::new("00B"));
+        let seen = HybridTimestamp::new(1_000, 3, &a);
+        let mut vc = VectorClock::new();
+        vc.advance(&a, seen.position().unwrap());
+        vc.advance(&a, 500);
+        assert_eq!(vc.get(&a), seen.position().unwrap());
+
+        assert!(vc.covers(&seen));
+        assert!(vc.covers(&HybridTimestamp::new(999, 7, &a)));
+        // Later in the same millisecond is not covered
+        assert!(!vc.covers(&HybridTimestamp::new(1_000, 4, &a)));
+        assert!(!vc.covers(&HybridTimestamp::new(1_001, 0, &a)));
+        // Only the issuing server's counter counts
+        assert!(!vc.covers(&HybridTimestamp::new(10, 0, &b)));
+    }
+
+    fn vector(entries: &[(&ServerId, i64)]) -> HashMap<ServerId, i64> {
+        entries.iter().map(|(s, m)| ((*s).clone(), *m)).collect()
+    }
+
+    #[test]
+    fn test_stable_vector_waits_for_every_server() {
+        let (a, b, c) = (
+            ServerId::new("00A"),
+            ServerId::new("00B"),
+            ServerId::new("00C"),
+        );
+        let mut svv = StableVersionVector::new(a.clone());
+        assert_eq!(svv.stable_millis(), None);
+
+        // Alone on the network everything we stamped is stable
+        svv.announce(100);
+        assert_eq!(svv.stable_millis(), Some(100));
+
+        // B has not heard from us yet
+        svv.observe(&b, &vector(&[(&b, 150)]), 150);
+        assert_eq!(svv.stable_millis(), None);
+
+        svv.announce(200);
+        svv.observe(&b, &vector(&[(&a, 200), (&b, 210)]), 210);
+        assert_eq!(svv.stable_millis(), Some(200));
+
+        // A new server holds the point back until everyone has heard it
+        svv.observe(&c, &vector(&[(&a, 200), (&b, 210), (&c, 220)]), 220);
+        assert_eq!(svv.stable_millis(), None);
+        svv.observe(&b, &vector(&[(&a, 200), (&b, 230), (&c, 220)]), 230);
+        svv.announce(240);
+        assert_eq!(svv.stable_millis(), Some(200));
+    }
+
+    #[test]
+    fn test_stable_vector_ignores_stale_announcements() {
+        let (a, b) = (ServerId::new("00A"), ServerId::new("00B"));
+        let mut svv = StableVersionVector::new(a.clone());
+        svv.announce(500);
+        svv.observe(&b, &vector(&[(&a, 500), (&b, 600)]), 600);
+        assert_eq!(svv.stable_millis(), Some(500));
+
+        svv.observe(&b, &vector(&[(&a, 100), (&b, 100)]), 700);
+        assert_eq!(svv.stable_millis(), Some(500));
+    }
+
+    #[test]
+    fn test_stable_vector_split_server_holds_point_until_expired() {
+        let (a, b) = (ServerId::new("00A"), ServerId::new("00B"));
+        let mut svv = StableVersionVector::new(a.clone());
+        svv.announce(100);
+        svv.observe(&b, &vector(&[(&a, 100), (&b, 100)]), 100);
+
+        // B goes quiet; its last vector still pins the stable point
+        svv.announce(5_000);
+        assert_eq!(svv.stable_millis(), Some(100));
+
+        assert_eq!(svv.expire(5_000, 10_000), 0);
+        assert_eq!(svv.expire(20_000, 10_000), 1);
+        assert_eq!(svv.len(), 1);
+        assert_eq!(svv.stable_millis(), Some(5_000));
+    }
 }
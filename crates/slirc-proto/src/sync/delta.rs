@@ -0,0 +1,201 @@
+//! Buffering of outgoing CRDT deltas.
+//!
+//! A `DeltaBuffer` collects the deltas produced by local changes until
+//! they are shipped to peers, joining them so that a burst of changes to
+//! the same user or channel goes out as a single delta.
+
+use super::traits::{Crdt, DeltaCrdt};
+
+/// Pending deltas for one replica, waiting to be shipped to peers.
+///
+/// Once more than `max_pending` deltas are buffered they are compacted
+/// (joined into one); `compact` can also be called on a timer. `take`
+/// always hands back a single, compacted delta.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DeltaBuffer<D> {
+    pending: Vec<D>,
+    max_pending: usize,
+}
+
+impl<D: Crdt + Clone> Default for DeltaBuffer<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: Crdt + Clone> DeltaBuffer<D> {
+    /// Default number of deltas held before they are compacted.
+    pub const DEFAULT_MAX_PENDING: usize = 64;
+
+    /// Create an empty buffer with the default compaction threshold.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_max_pending(Self::DEFAULT_MAX_PENDING)
+    }
+
+    /// Create an empty buffer that compacts once more than `max_pending`
+    /// deltas are held.
+    #[must_use]
+    pub fn with_max_pending(max_pending: usize) -> Self {
+        Self {
+            pending: Vec::new(),
+            max_pending: max_pending.max(1),
+        }
+    }
+
+    /// Buffer a delta.
+    pub fn push(&mut self, delta: D) {
+        self.pending.push(delta);
+        if self.pending.len() > self.max_pending {
+            self.compact();
+        }
+    }
+
+    /// Apply a local change to `state` and buffer the delta it produced.
+    ///
+    /// Changes that leave `state` as it was buffer nothing.
+    pub fn record<T, R>(&mut self, state: &mut T, change: impl FnOnce(&mut T) -> R) -> R
+    where
+        T: DeltaCrdt<Delta = D> + Clone,
+    {
+        let base = state.clone();
+        let result = change(state);
+        if let Some(delta) = state.delta_from(&base) {
+            self.push(delta);
+        }
+        result
+    }
+
+    /// Join all buffered deltas into one.
+    pub fn compact(&mut self) {
+        let mut pending = self.pending.drain(..);
+        if let Some(mut joined) = pending.next() {
+            for delta in pending {
+                joined.merge(&delta);
+            }
+            self.pending.push(joined);
+        }
+    }
+
+    /// Take everything buffered as a single delta, leaving the buffer empty.
+    pub fn take(&mut self) -> Option<D> {
+        self.compact();
+        self.pending.pop()
+    }
+
+    /// Get the number of buffered (not yet compacted) deltas.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Check if nothing is buffered.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::channel::{ChannelCrdt, ChannelDelta};
+    use crate::sync::clock::{HybridTimestamp, ServerId};
+    use crate::sync::user::{UserCrdt, UserDelta};
+
+    fn ts(millis: i64, server: &ServerId) -> HybridTimestamp {
+        HybridTimestamp::new(millis, 0, server)
+    }
+
+    fn make_user(server: &ServerId) -> UserCrdt {
+        UserCrdt::new(
+            "001AAA".to_string(),
+            "Nick".to_string(),
+            "user".to_string(),
+            "Real Name".to_string(),
+            "host.com".to_string(),
+            "cloak.host".to_string(),
+            ts(100, server),
+        )
+    }
+
+    #[test]
+    fn test_record_skips_no_op_changes() {
+        let server = ServerId::new("001");
+        let mut user = make_user(&server);
+        let mut buffer: DeltaBuffer<UserDelta> = DeltaBuffer::new();
+
+        // An older write loses to the current value and changes nothing
+        buffer.record(&mut user, |u| {
+            u.set_nick("Old".to_string(), ts(50, &server))
+        });
+        assert!(buffer.is_empty());
+
+        buffer.record(&mut user, |u| {
+            u.set_nick("New".to_string(), ts(200, &server))
+        });
+        assert_eq!(buffer.len(), 1);
+    }
+
+    #[test]
+    fn test_compacted_delta_matches_full_state() {
+        let server = ServerId::new("001");
+        let mut user = make_user(&server);
+        let mut peer = user.clone();
+        let mut buffer = DeltaBuffer::with_max_pending(2);
+
+        buffer.record(&mut user, |u| u.set_nick("A".to_string(), ts(200, &server)));
+        buffer.record(&mut user, |u| {
+            u.join_channel("#foo".to_string(), ts(210, &server));
+        });
+        buffer.record(&mut user, |u| {
+            u.set_away(Some("brb".to_string()), ts(220, &server));
+        });
+        // The third delta pushed the buffer past its threshold
+        assert_eq!(buffer.len(), 1);
+        buffer.record(&mut user, |u| u.set_nick("B".to_string(), ts(230, &server)));
+        buffer.record(&mut user, |u| u.part_channel("#foo", ts(240, &server)));
+
+        let delta = buffer.take().expect("changes were buffered");
+        assert!(buffer.is_empty());
+        // Superseded values are not shipped
+        assert_eq!(delta.nick.as_ref().map(|n| n.value().as_str()), Some("B"));
+        assert!(delta.user.is_none());
+        assert!(delta.modes.is_none());
+
+        peer.merge_delta(&delta);
+        assert_eq!(peer, user);
+    }
+
+    #[test]
+    fn test_channel_buffer() {
+        let server = ServerId::new("001");
+        let mut chan = ChannelCrdt::new("#test".to_string(), ts(100, &server));
+        chan.join("001AAA".to_string(), ts(110, &server));
+        let mut peer = chan.clone();
+        let mut buffer: DeltaBuffer<ChannelDelta> = DeltaBuffer::new();
+
+        buffer.record(&mut chan, |c| {
+            c.join("001AAB".to_string(), ts(200, &server));
+        });
+        buffer.record(&mut chan, |c| {
+            if let Some(modes) = c.members.get_modes_mut("001AAB") {
+                modes.op.update(true, ts(210, &server));
+            }
+        });
+        buffer.record(&mut chan, |c| {
+            c.add_ban("*!*@bad".to_string(), "op".to_string(), ts(220, &server));
+        });
+        assert_eq!(buffer.len(), 3);
+        buffer.compact();
+        assert_eq!(buffer.len(), 1);
+
+        let delta = buffer.take().expect("changes were buffered");
+        assert!(delta.topic.is_none());
+        assert!(delta.modes.is_none());
+        assert!(!delta.members.contains("001AAA"));
+
+        peer.merge_delta(&delta);
+        assert_eq!(peer, chan);
+    }
+}
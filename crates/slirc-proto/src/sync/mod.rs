@@ -37,16 +37,35 @@
 //! - **`ORSet` (Observed-Remove Set)**: For collections where concurrent add/remove
 //!   should both succeed (like channel membership).
 //! - **Vector Clock**: For causal ordering of events across servers.
+//!
+//! # Delta Sync
+//!
+//! `UserCrdt` and `ChannelCrdt` implement `DeltaCrdt`: after a burst has
+//! given a peer the full state, later changes are shipped as `UserDelta` /
+//! `ChannelDelta` values holding only the registers and set entries that
+//! changed. A `DeltaBuffer` collects and compacts them between sends.
+//!
+//! # Wire Format
+//!
+//! Every type here derives serde. With the `sync-wire` feature, the `wire`
+//! module encodes states and deltas as compact postcard bytes in a
+//! versioned envelope, for link transfer and persistence.
 
 pub mod channel;
 pub mod clock;
+pub mod delta;
 pub mod traits;
 pub mod user;
+#[cfg(feature = "sync-wire")]
+pub mod wire;
 
-pub use channel::ChannelCrdt;
-pub use clock::{HybridTimestamp, ServerId, VectorClock};
-pub use traits::{Crdt, Mergeable, StateDelta};
-pub use user::UserCrdt;
+pub use channel::{ChannelCrdt, ChannelDelta};
+pub use clock::{
+    ClockSkewError, HybridClock, HybridTimestamp, ServerId, StableVersionVector, VectorClock,
+};
+pub use delta::DeltaBuffer;
+pub use traits::{Crdt, DeltaCrdt, Mergeable, StateDelta};
+pub use user::{UserCrdt, UserDelta};
 
 #[cfg(test)]
 mod tests {
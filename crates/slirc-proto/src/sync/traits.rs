@@ -38,6 +38,52 @@ pub trait StateDelta: Crdt {
     fn apply_delta(&mut self, delta: &Self::Delta);
 }
 
+/// A delta-state CRDT.
+///
+/// Instead of shipping the whole state after every change, a replica ships
+/// the part of its state the peer has not seen yet. Deltas are themselves
+/// CRDTs, so several of them can be joined into one before sending.
+///
+/// Merging a delta only has the same effect as merging the full state when
+/// the receiver already holds the state the delta was taken against (for a
+/// peer link, everything sent in the burst and in earlier deltas).
+pub trait DeltaCrdt: Crdt {
+    /// The delta type; joining two deltas is `Crdt::merge`.
+    type Delta: Crdt + Clone + serde::Serialize + for<'de> serde::Deserialize<'de>;
+
+    /// The part of `self` that `base` has not seen, or `None` if there is none.
+    fn delta_from(&self, base: &Self) -> Option<Self::Delta>;
+
+    /// Merge a delta produced by `delta_from` on another replica.
+    fn merge_delta(&mut self, delta: &Self::Delta);
+}
+
+/// An optional CRDT merges like the CRDT, with `None` as the empty state.
+impl<T: Crdt + Clone> Crdt for Option<T> {
+    fn merge(&mut self, other: &Self) {
+        match (self.as_mut(), other) {
+            (_, None) => {}
+            (Some(this), Some(other)) => this.merge(other),
+            (None, Some(other)) => *self = Some(other.clone()),
+        }
+    }
+
+    fn dominates(&self, other: &Self) -> bool {
+        match (self, other) {
+            (_, None) => true,
+            (Some(this), Some(other)) => this.dominates(other),
+            (None, Some(_)) => false,
+        }
+    }
+}
+
+/// Merge `update` into `target` when there is one.
+pub(crate) fn merge_some<T: Crdt>(target: &mut T, update: Option<&T>) {
+    if let Some(update) = update {
+        target.merge(update);
+    }
+}
+
 /// A value with an associated timestamp for Last-Writer-Wins semantics.
 ///
 /// When merging, the value with the higher timestamp wins. Ties are broken
@@ -60,7 +106,7 @@ pub trait Mergeable: Clone {
 /// A Last-Writer-Wins register.
 ///
 /// Wraps any value with a timestamp for LWW semantics.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct LwwRegister<T> {
     value: T,
     timestamp: HybridTimestamp,
@@ -110,10 +156,25 @@ impl<T: Clone> Crdt for LwwRegister<T> {
     }
 }
 
+impl<T> DeltaCrdt for LwwRegister<T>
+where
+    T: Clone + serde::Serialize + for<'de> serde::Deserialize<'de>,
+{
+    type Delta = Self;
+
+    fn delta_from(&self, base: &Self) -> Option<Self> {
+        (self.timestamp > base.timestamp).then(|| self.clone())
+    }
+
+    fn merge_delta(&mut self, delta: &Self) {
+        self.merge(delta);
+    }
+}
+
 /// An Add-Wins Set (`AWSet`) for sets where adds take precedence.
 ///
 /// When an add and remove happen concurrently, the add wins.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct AwSet<T>
 where
     T: Clone + Eq + std::hash::Hash,
@@ -151,6 +212,7 @@ where
         // Add wins: if add timestamp >= tombstone timestamp, element is present
         let tombstone_ts = self.tombstones.get(&element).copied();
         if tombstone_ts.map_or(true, |ts| timestamp >= ts) {
+            self.elements.remove(&element);
             self.elements.insert(element, timestamp);
         }
     }
@@ -176,6 +238,29 @@ where
         self.elements.keys()
     }
 
+    /// Get the add timestamp of a present element.
+    pub fn added_at(&self, element: &T) -> Option<HybridTimestamp> {
+        self.elements.get(element).copied()
+    }
+
+    /// Iterate over removed elements with their removal timestamps.
+    pub fn removed(&self) -> impl Iterator<Item = (&T, HybridTimestamp)> {
+        self.tombstones.iter().map(|(elem, ts)| (elem, *ts))
+    }
+
+    /// Drop tombstones stamped before `stable_millis`.
+    ///
+    /// Only safe once every replica has seen every update stamped before
+    /// that point (see [`StableVersionVector`]): no add those tombstones
+    /// would have cancelled can still arrive. Returns the number dropped.
+    ///
+    /// [`StableVersionVector`]: super::clock::StableVersionVector
+    pub fn prune_tombstones(&mut self, stable_millis: i64) -> usize {
+        let before = self.tombstones.len();
+        self.tombstones.retain(|_, ts| ts.millis >= stable_millis);
+        before - self.tombstones.len()
+    }
+
     /// Get the number of elements.
     #[must_use]
     pub fn len(&self) -> usize {
@@ -202,6 +287,9 @@ where
                     // Check against our tombstones
                     let our_tomb = self.tombstones.get(elem).copied();
                     if our_tomb.map_or(true, |ts| other_ts >= ts) {
+                        // Replace the key too: equal elements may still
+                        // differ in fields outside their identity
+                        self.elements.remove(elem);
                         self.elements.insert(elem.clone(), other_ts);
                     }
                 }
@@ -227,10 +315,12 @@ where
 
     fn dominates(&self, other: &Self) -> bool {
         // We dominate if all of other's elements and tombstones are
-        // present with equal or greater timestamps
+        // present with equal or greater timestamps. An add we have since
+        // seen removed by a later tombstone also counts as present.
         for (elem, &other_ts) in &other.elements {
-            match self.elements.get(elem) {
-                Some(&self_ts) if self_ts >= other_ts => {}
+            match (self.elements.get(elem), self.tombstones.get(elem)) {
+                (Some(&self_ts), _) if self_ts >= other_ts => {}
+                (_, Some(&tomb_ts)) if tomb_ts > other_ts => {}
                 _ => return false,
             }
         }
@@ -244,6 +334,34 @@ where
     }
 }
 
+impl<T> DeltaCrdt for AwSet<T>
+where
+    T: Clone + Eq + std::hash::Hash + serde::Serialize + for<'de> serde::Deserialize<'de>,
+{
+    type Delta = Self;
+
+    /// Only the adds and tombstones `base` is missing or holds at an
+    /// older timestamp.
+    fn delta_from(&self, base: &Self) -> Option<Self> {
+        let newer = |ours: &std::collections::HashMap<T, HybridTimestamp>,
+                     theirs: &std::collections::HashMap<T, HybridTimestamp>| {
+            ours.iter()
+                .filter(|(elem, ts)| theirs.get(*elem).map_or(true, |base_ts| *ts > base_ts))
+                .map(|(elem, ts)| (elem.clone(), *ts))
+                .collect::<std::collections::HashMap<_, _>>()
+        };
+        let delta = Self {
+            elements: newer(&self.elements, &base.elements),
+            tombstones: newer(&self.tombstones, &base.tombstones),
+        };
+        (!delta.elements.is_empty() || !delta.tombstones.is_empty()).then_some(delta)
+    }
+
+    fn merge_delta(&mut self, delta: &Self) {
+        self.merge(delta);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -496,4 +614,86 @@ mod tests {
         set.add("item".to_string(), ts3);
         assert!(set.contains(&"item".to_string()));
     }
+
+    #[test]
+    fn test_awset_tombstone_dominates_older_add() {
+        let server = ServerId::new("001");
+        let ts1 = HybridTimestamp::new(100, 0, &server);
+        let ts2 = HybridTimestamp::new(200, 0, &server);
+        let ts3 = HybridTimestamp::new(300, 0, &server);
+
+        let mut set1: AwSet<String> = AwSet::new();
+        set1.add("item".to_string(), ts1);
+
+        let mut set2: AwSet<String> = AwSet::new();
+        set2.add("item".to_string(), ts2);
+        set2.remove(&"item".to_string(), ts3);
+
+        // set2 has seen a removal newer than set1's add
+        let mut merged = set1.clone();
+        merged.merge(&set2);
+        assert!(!merged.contains(&"item".to_string()));
+        assert!(merged.dominates(&set1));
+        assert!(merged.dominates(&set2));
+    }
+
+    #[test]
+    fn test_awset_prune_stable_tombstones() {
+        let server = ServerId::new("001");
+        let ts1 = HybridTimestamp::new(100, 0, &server);
+        let ts2 = HybridTimestamp::new(200, 0, &server);
+        let ts3 = HybridTimestamp::new(300, 0, &server);
+
+        let mut set: AwSet<String> = AwSet::new();
+        set.add("old".to_string(), ts1);
+        set.remove(&"old".to_string(), ts2);
+        set.add("recent".to_string(), ts1);
+        set.remove(&"recent".to_string(), ts3);
+        set.add("kept".to_string(), ts1);
+
+        assert_eq!(set.prune_tombstones(300), 1);
+        let removed: Vec<_> = set.removed().map(|(e, _)| e.as_str()).collect();
+        assert_eq!(removed, ["recent"]);
+        assert!(set.contains(&"kept".to_string()));
+
+        // Every replica has the removal, so adds after it still win
+        set.add("old".to_string(), ts3);
+        assert!(set.contains(&"old".to_string()));
+    }
+
+    #[test]
+    fn test_awset_delta_only_unseen_entries() {
+        let server = ServerId::new("001");
+        let ts1 = HybridTimestamp::new(100, 0, &server);
+        let ts2 = HybridTimestamp::new(200, 0, &server);
+
+        let mut base: AwSet<String> = AwSet::new();
+        base.add("kept".to_string(), ts1);
+        base.add("gone".to_string(), ts1);
+        assert!(base.delta_from(&base).is_none());
+
+        let mut set = base.clone();
+        set.add("new".to_string(), ts2);
+        set.remove(&"gone".to_string(), ts2);
+
+        let delta = set.delta_from(&base).expect("set changed");
+        assert_eq!(delta.len(), 1);
+        assert!(delta.contains(&"new".to_string()));
+        assert!(!delta.contains(&"kept".to_string()));
+
+        base.merge_delta(&delta);
+        assert_eq!(base, set);
+    }
+
+    #[test]
+    fn test_lww_register_delta() {
+        let server = ServerId::new("001");
+        let ts1 = HybridTimestamp::new(100, 0, &server);
+        let ts2 = HybridTimestamp::new(200, 0, &server);
+
+        let base = LwwRegister::new("old".to_string(), ts1);
+        let reg = LwwRegister::new("new".to_string(), ts2);
+        assert!(base.delta_from(&reg).is_none());
+        assert_eq!(reg.delta_from(&base), Some(reg.clone()));
+    }
 }
@@ -4,17 +4,18 @@
 //! that supports distributed synchronization across linked servers.
 
 use super::clock::HybridTimestamp;
-use super::traits::{AwSet, Crdt, LwwRegister};
-use std::collections::HashSet;
+use super::traits::{merge_some, AwSet, Crdt, DeltaCrdt, LwwRegister};
+use std::collections::{HashMap, HashSet};
 
 /// CRDT-enabled user state for distributed synchronization.
 ///
 /// Uses different CRDT strategies for different fields:
-/// - **LWW (Last-Writer-Wins)**: nick, user, realname, host, away, account
+/// - **LWW (Last-Writer-Wins)**: nick, user, realname, host, ip, away,
+///   account, certfp, each metadata key
 /// - **`AWSet` (Add-Wins Set)**: channels, caps, `silence_list`, snomasks
 ///
 /// This allows concurrent modifications to resolve deterministically.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct UserCrdt {
     /// Unique identifier (never changes after creation).
     pub uid: String,
@@ -34,12 +35,21 @@ pub struct UserCrdt {
     /// Visible (cloaked) hostname (LWW: tracks host changes).
     pub visible_host: LwwRegister<String>,
 
+    /// IP address, if known (LWW: set at registration).
+    pub ip: LwwRegister<Option<String>>,
+
     /// Account name if identified (LWW: changes on IDENTIFY/LOGOUT).
     pub account: LwwRegister<Option<String>>,
 
     /// Away message (LWW: changes on AWAY command).
     pub away: LwwRegister<Option<String>>,
 
+    /// TLS client certificate fingerprint (LWW: set at registration).
+    pub certfp: LwwRegister<Option<String>>,
+
+    /// User metadata (LWW per key; `None` marks a deleted key).
+    pub metadata: HashMap<String, LwwRegister<Option<String>>>,
+
     /// Channels the user is in (`AWSet`: JOIN adds, PART/KICK removes).
     pub channels: AwSet<String>,
 
@@ -59,7 +69,7 @@ pub struct UserCrdt {
 /// CRDT-enabled user modes.
 ///
 /// Each mode flag is an independent LWW register.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct UserModesCrdt {
     /// Invisible mode (+i).
     pub invisible: LwwRegister<bool>,
@@ -89,6 +99,8 @@ pub struct UserModesCrdt {
     pub caller_id: LwwRegister<bool>,
     /// Network Administrator (+N).
     pub net_admin: LwwRegister<bool>,
+    /// Network service (+S).
+    pub service: LwwRegister<bool>,
 }
 
 impl UserModesCrdt {
@@ -110,8 +122,50 @@ impl UserModesCrdt {
             deaf: LwwRegister::new(false, timestamp),
             caller_id: LwwRegister::new(false, timestamp),
             net_admin: LwwRegister::new(false, timestamp),
+            service: LwwRegister::new(false, timestamp),
         }
     }
+
+    /// Get the register for a boolean mode by its mode character.
+    #[must_use]
+    pub fn flag(&self, mode: char) -> Option<&LwwRegister<bool>> {
+        Some(match mode {
+            'i' => &self.invisible,
+            'w' => &self.wallops,
+            'o' => &self.oper,
+            'r' => &self.registered,
+            'Z' => &self.secure,
+            'R' => &self.registered_only,
+            'T' => &self.no_ctcp,
+            'B' => &self.bot,
+            'p' => &self.hide_channels,
+            'd' => &self.deaf,
+            'g' => &self.caller_id,
+            'N' => &self.net_admin,
+            'S' => &self.service,
+            _ => return None,
+        })
+    }
+
+    /// Get the register for a boolean mode by its mode character, mutably.
+    pub fn flag_mut(&mut self, mode: char) -> Option<&mut LwwRegister<bool>> {
+        Some(match mode {
+            'i' => &mut self.invisible,
+            'w' => &mut self.wallops,
+            'o' => &mut self.oper,
+            'r' => &mut self.registered,
+            'Z' => &mut self.secure,
+            'R' => &mut self.registered_only,
+            'T' => &mut self.no_ctcp,
+            'B' => &mut self.bot,
+            'p' => &mut self.hide_channels,
+            'd' => &mut self.deaf,
+            'g' => &mut self.caller_id,
+            'N' => &mut self.net_admin,
+            'S' => &mut self.service,
+            _ => return None,
+        })
+    }
 }
 
 impl Crdt for UserModesCrdt {
@@ -130,6 +184,7 @@ impl Crdt for UserModesCrdt {
         self.deaf.merge(&other.deaf);
         self.caller_id.merge(&other.caller_id);
         self.net_admin.merge(&other.net_admin);
+        self.service.merge(&other.service);
     }
 
     fn dominates(&self, other: &Self) -> bool {
@@ -147,6 +202,7 @@ impl Crdt for UserModesCrdt {
             && self.deaf.dominates(&other.deaf)
             && self.caller_id.dominates(&other.caller_id)
             && self.net_admin.dominates(&other.net_admin)
+            && self.service.dominates(&other.service)
     }
 }
 
@@ -169,8 +225,11 @@ impl UserCrdt {
             realname: LwwRegister::new(realname, timestamp),
             host: LwwRegister::new(host, timestamp),
             visible_host: LwwRegister::new(visible_host, timestamp),
+            ip: LwwRegister::new(None, timestamp),
             account: LwwRegister::new(None, timestamp),
             away: LwwRegister::new(None, timestamp),
+            certfp: LwwRegister::new(None, timestamp),
+            metadata: HashMap::new(),
             channels: AwSet::new(),
             caps: AwSet::new(),
             modes: UserModesCrdt::new(timestamp),
@@ -205,6 +264,79 @@ impl UserCrdt {
         self.modes.registered.update(true, timestamp);
     }
 
+    /// Log out of the current account.
+    pub fn logout(&mut self, timestamp: HybridTimestamp) {
+        self.account.update(None, timestamp);
+        self.modes.registered.update(false, timestamp);
+    }
+
+    /// Set or clear operator status and type.
+    pub fn set_oper(&mut self, oper_type: Option<String>, timestamp: HybridTimestamp) {
+        self.modes.oper.update(oper_type.is_some(), timestamp);
+        self.modes.oper_type.update(oper_type, timestamp);
+    }
+
+    /// Set or clear a boolean user mode.
+    ///
+    /// Returns `false` for characters that are not boolean user modes.
+    pub fn set_mode(&mut self, mode: char, on: bool, timestamp: HybridTimestamp) -> bool {
+        match self.modes.flag_mut(mode) {
+            Some(reg) => {
+                reg.update(on, timestamp);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Set the TLS client certificate fingerprint.
+    pub fn set_certfp(&mut self, certfp: Option<String>, timestamp: HybridTimestamp) {
+        self.certfp.update(certfp, timestamp);
+    }
+
+    /// Set (`Some`) or delete (`None`) a metadata key.
+    pub fn set_metadata(&mut self, key: String, value: Option<String>, timestamp: HybridTimestamp) {
+        match self.metadata.get_mut(&key) {
+            Some(reg) => reg.update(value, timestamp),
+            None => {
+                self.metadata
+                    .insert(key, LwwRegister::new(value, timestamp));
+            }
+        }
+    }
+
+    /// Get a metadata value, if set.
+    #[must_use]
+    pub fn get_metadata(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .get(key)
+            .and_then(|reg| reg.value().as_deref())
+    }
+
+    /// Iterate over metadata keys that are currently set.
+    pub fn metadata_iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.metadata
+            .iter()
+            .filter_map(|(k, reg)| reg.value().as_ref().map(|v| (k, v)))
+    }
+
+    /// Drop set tombstones and deleted metadata keys stamped before
+    /// `stable_millis`.
+    ///
+    /// See [`AwSet::prune_tombstones`] for when this is safe. Returns the
+    /// number of entries dropped.
+    pub fn prune_tombstones(&mut self, stable_millis: i64) -> usize {
+        let metadata_before = self.metadata.len();
+        self.metadata
+            .retain(|_, reg| reg.value().is_some() || reg.timestamp().millis >= stable_millis);
+        (metadata_before - self.metadata.len())
+            + self.channels.prune_tombstones(stable_millis)
+            + self.caps.prune_tombstones(stable_millis)
+            + self.silence_list.prune_tombstones(stable_millis)
+            + self.accept_list.prune_tombstones(stable_millis)
+            + self.modes.snomasks.prune_tombstones(stable_millis)
+    }
+
     /// Convert to a set of channels (for compatibility with existing code).
     #[must_use]
     pub fn channels_set(&self) -> HashSet<String> {
@@ -228,8 +360,18 @@ impl Crdt for UserCrdt {
         self.realname.merge(&other.realname);
         self.host.merge(&other.host);
         self.visible_host.merge(&other.visible_host);
+        self.ip.merge(&other.ip);
         self.account.merge(&other.account);
         self.away.merge(&other.away);
+        self.certfp.merge(&other.certfp);
+        for (key, other_reg) in &other.metadata {
+            match self.metadata.get_mut(key) {
+                Some(reg) => reg.merge(other_reg),
+                None => {
+                    self.metadata.insert(key.clone(), other_reg.clone());
+                }
+            }
+        }
         self.channels.merge(&other.channels);
         self.caps.merge(&other.caps);
         self.modes.merge(&other.modes);
@@ -243,8 +385,15 @@ impl Crdt for UserCrdt {
             && self.realname.dominates(&other.realname)
             && self.host.dominates(&other.host)
             && self.visible_host.dominates(&other.visible_host)
+            && self.ip.dominates(&other.ip)
             && self.account.dominates(&other.account)
             && self.away.dominates(&other.away)
+            && self.certfp.dominates(&other.certfp)
+            && other.metadata.iter().all(|(key, other_reg)| {
+                self.metadata
+                    .get(key)
+                    .is_some_and(|reg| reg.dominates(other_reg))
+            })
             && self.channels.dominates(&other.channels)
             && self.caps.dominates(&other.caps)
             && self.modes.dominates(&other.modes)
@@ -256,20 +405,186 @@ impl Crdt for UserCrdt {
 /// A delta representing changes to a `UserCrdt`.
 ///
 /// Only contains fields that have changed, for efficient network transfer.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// Set fields carry just the adds and removals the peer has not seen; user
+/// modes are small and are sent whole when any of them changed.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct UserDelta {
     /// User ID (required to identify the user).
     pub uid: String,
     /// Updated nick (if changed).
     pub nick: Option<LwwRegister<String>>,
-    /// Updated away message (if changed).
-    pub away: Option<LwwRegister<Option<String>>>,
+    /// Updated username (if changed).
+    pub user: Option<LwwRegister<String>>,
+    /// Updated real name (if changed).
+    pub realname: Option<LwwRegister<String>>,
+    /// Updated hostname (if changed).
+    pub host: Option<LwwRegister<String>>,
+    /// Updated visible hostname (if changed).
+    pub visible_host: Option<LwwRegister<String>>,
+    /// Updated IP address (if changed).
+    pub ip: Option<LwwRegister<Option<String>>>,
     /// Updated account (if changed).
     pub account: Option<LwwRegister<Option<String>>>,
-    /// Channels joined since last sync.
-    pub channels_added: Vec<(String, HybridTimestamp)>,
-    /// Channels parted since last sync.
-    pub channels_removed: Vec<(String, HybridTimestamp)>,
+    /// Updated away message (if changed).
+    pub away: Option<LwwRegister<Option<String>>>,
+    /// Updated certificate fingerprint (if changed).
+    pub certfp: Option<LwwRegister<Option<String>>>,
+    /// Metadata keys set or deleted since last sync.
+    pub metadata: HashMap<String, LwwRegister<Option<String>>>,
+    /// Channels joined or parted since last sync.
+    pub channels: Option<AwSet<String>>,
+    /// Capabilities enabled or disabled since last sync.
+    pub caps: Option<AwSet<String>>,
+    /// User modes (if any changed).
+    pub modes: Option<UserModesCrdt>,
+    /// Silence list entries added or removed since last sync.
+    pub silence_list: Option<AwSet<String>>,
+    /// Accept list entries added or removed since last sync.
+    pub accept_list: Option<AwSet<String>>,
+}
+
+impl UserDelta {
+    /// Create an empty delta for a user.
+    #[must_use]
+    pub fn new(uid: String) -> Self {
+        Self {
+            uid,
+            nick: None,
+            user: None,
+            realname: None,
+            host: None,
+            visible_host: None,
+            ip: None,
+            account: None,
+            away: None,
+            certfp: None,
+            metadata: HashMap::new(),
+            channels: None,
+            caps: None,
+            modes: None,
+            silence_list: None,
+            accept_list: None,
+        }
+    }
+
+    /// Check if the delta carries no changes.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::new(self.uid.clone())
+    }
+}
+
+impl Crdt for UserDelta {
+    fn merge(&mut self, other: &Self) {
+        debug_assert_eq!(self.uid, other.uid);
+
+        self.nick.merge(&other.nick);
+        self.user.merge(&other.user);
+        self.realname.merge(&other.realname);
+        self.host.merge(&other.host);
+        self.visible_host.merge(&other.visible_host);
+        self.ip.merge(&other.ip);
+        self.account.merge(&other.account);
+        self.away.merge(&other.away);
+        self.certfp.merge(&other.certfp);
+        for (key, other_reg) in &other.metadata {
+            match self.metadata.get_mut(key) {
+                Some(reg) => reg.merge(other_reg),
+                None => {
+                    self.metadata.insert(key.clone(), other_reg.clone());
+                }
+            }
+        }
+        self.channels.merge(&other.channels);
+        self.caps.merge(&other.caps);
+        self.modes.merge(&other.modes);
+        self.silence_list.merge(&other.silence_list);
+        self.accept_list.merge(&other.accept_list);
+    }
+
+    fn dominates(&self, other: &Self) -> bool {
+        self.nick.dominates(&other.nick)
+            && self.user.dominates(&other.user)
+            && self.realname.dominates(&other.realname)
+            && self.host.dominates(&other.host)
+            && self.visible_host.dominates(&other.visible_host)
+            && self.ip.dominates(&other.ip)
+            && self.account.dominates(&other.account)
+            && self.away.dominates(&other.away)
+            && self.certfp.dominates(&other.certfp)
+            && other.metadata.iter().all(|(key, other_reg)| {
+                self.metadata
+                    .get(key)
+                    .is_some_and(|reg| reg.dominates(other_reg))
+            })
+            && self.channels.dominates(&other.channels)
+            && self.caps.dominates(&other.caps)
+            && self.modes.dominates(&other.modes)
+            && self.silence_list.dominates(&other.silence_list)
+            && self.accept_list.dominates(&other.accept_list)
+    }
+}
+
+impl DeltaCrdt for UserCrdt {
+    type Delta = UserDelta;
+
+    fn delta_from(&self, base: &Self) -> Option<UserDelta> {
+        debug_assert_eq!(self.uid, base.uid);
+
+        let metadata: HashMap<_, _> = self
+            .metadata
+            .iter()
+            .filter(|(key, reg)| base.metadata.get(*key).map_or(true, |b| !b.dominates(reg)))
+            .map(|(key, reg)| (key.clone(), reg.clone()))
+            .collect();
+        let delta = UserDelta {
+            uid: self.uid.clone(),
+            nick: self.nick.delta_from(&base.nick),
+            user: self.user.delta_from(&base.user),
+            realname: self.realname.delta_from(&base.realname),
+            host: self.host.delta_from(&base.host),
+            visible_host: self.visible_host.delta_from(&base.visible_host),
+            ip: self.ip.delta_from(&base.ip),
+            account: self.account.delta_from(&base.account),
+            away: self.away.delta_from(&base.away),
+            certfp: self.certfp.delta_from(&base.certfp),
+            metadata,
+            channels: self.channels.delta_from(&base.channels),
+            caps: self.caps.delta_from(&base.caps),
+            modes: (!base.modes.dominates(&self.modes)).then(|| self.modes.clone()),
+            silence_list: self.silence_list.delta_from(&base.silence_list),
+            accept_list: self.accept_list.delta_from(&base.accept_list),
+        };
+
+        (!delta.is_empty()).then_some(delta)
+    }
+
+    fn merge_delta(&mut self, delta: &UserDelta) {
+        debug_assert_eq!(self.uid, delta.uid);
+
+        merge_some(&mut self.nick, delta.nick.as_ref());
+        merge_some(&mut self.user, delta.user.as_ref());
+        merge_some(&mut self.realname, delta.realname.as_ref());
+        merge_some(&mut self.host, delta.host.as_ref());
+        merge_some(&mut self.visible_host, delta.visible_host.as_ref());
+        merge_some(&mut self.ip, delta.ip.as_ref());
+        merge_some(&mut self.account, delta.account.as_ref());
+        merge_some(&mut self.away, delta.away.as_ref());
+        merge_some(&mut self.certfp, delta.certfp.as_ref());
+        for (key, other_reg) in &delta.metadata {
+            match self.metadata.get_mut(key) {
+                Some(reg) => reg.merge(other_reg),
+                None => {
+                    self.metadata.insert(key.clone(), other_reg.clone());
+                }
+            }
+        }
+        merge_some(&mut self.channels, delta.channels.as_ref());
+        merge_some(&mut self.caps, delta.caps.as_ref());
+        merge_some(&mut self.modes, delta.modes.as_ref());
+        merge_some(&mut self.silence_list, delta.silence_list.as_ref());
+        merge_some(&mut self.accept_list, delta.accept_list.as_ref());
+    }
 }
 
 #[cfg(test)]
@@ -368,6 +683,63 @@ mod tests {
         assert!(*user.modes.registered.value());
     }
 
+    #[test]
+    fn test_user_crdt_set_oper_and_deoper() {
+        let server = ServerId::new("001");
+        let mut user = make_user("001AAA", "Nick", &server, 100);
+
+        user.set_oper(
+            Some("admin".to_string()),
+            HybridTimestamp::new(200, 0, &server),
+        );
+        assert!(*user.modes.oper.value());
+        assert_eq!(user.modes.oper_type.value().as_deref(), Some("admin"));
+
+        user.set_oper(None, HybridTimestamp::new(300, 0, &server));
+        assert!(!*user.modes.oper.value());
+        assert!(user.modes.oper_type.value().is_none());
+    }
+
+    #[test]
+    fn test_user_crdt_set_mode_by_char() {
+        let server = ServerId::new("001");
+        let mut user = make_user("001AAA", "Nick", &server, 100);
+
+        assert!(user.set_mode('i', true, HybridTimestamp::new(200, 0, &server)));
+        assert!(user.set_mode('B', true, HybridTimestamp::new(200, 0, &server)));
+        assert!(!user.set_mode('x', true, HybridTimestamp::new(200, 0, &server)));
+        assert!(*user.modes.invisible.value());
+        assert!(*user.modes.bot.value());
+
+        // An older change loses
+        user.set_mode('i', false, HybridTimestamp::new(150, 0, &server));
+        assert!(*user.modes.invisible.value());
+    }
+
+    #[test]
+    fn test_user_crdt_field_changes_merge_independently() {
+        let s1 = ServerId::new("001");
+        let s2 = ServerId::new("002");
+        let base = make_user("001AAA", "Nick", &s1, 100);
+
+        // Concurrent AWAY on one side, IDENTIFY and -i on the other
+        let mut a = base.clone();
+        a.set_away(Some("lunch".to_string()), HybridTimestamp::new(300, 0, &s1));
+        let mut b = base;
+        b.identify("acct".to_string(), HybridTimestamp::new(200, 0, &s2));
+        b.set_mode('w', true, HybridTimestamp::new(250, 0, &s2));
+
+        let mut ab = a.clone();
+        ab.merge(&b);
+        let mut ba = b;
+        ba.merge(&a);
+        assert_eq!(ab, ba);
+        assert_eq!(ab.away.value().as_deref(), Some("lunch"));
+        assert_eq!(ab.account.value().as_deref(), Some("acct"));
+        assert!(*ab.modes.registered.value());
+        assert!(*ab.modes.wallops.value());
+    }
+
     #[test]
     fn test_user_crdt_join_part_channel() {
         let server = ServerId::new("001");
@@ -382,6 +754,27 @@ mod tests {
         assert!(!user.channels.contains(&"#test".to_string()));
     }
 
+    #[test]
+    fn test_user_crdt_prune_tombstones() {
+        let server = ServerId::new("001");
+        let mut user = make_user("001AAA", "alice", &server, 100);
+        let ts = |millis| HybridTimestamp::new(millis, 0, &server);
+
+        user.join_channel("#old".to_string(), ts(200));
+        user.part_channel("#old", ts(300));
+        user.join_channel("#new".to_string(), ts(200));
+        user.part_channel("#new", ts(900));
+        user.set_metadata("gone".to_string(), Some("x".to_string()), ts(200));
+        user.set_metadata("gone".to_string(), None, ts(300));
+        user.set_metadata("set".to_string(), Some("y".to_string()), ts(200));
+
+        assert_eq!(user.prune_tombstones(500), 2);
+        let removed: Vec<_> = user.channels.removed().map(|(c, _)| c.as_str()).collect();
+        assert_eq!(removed, ["#new"]);
+        assert!(!user.metadata.contains_key("gone"));
+        assert_eq!(user.get_metadata("set"), Some("y"));
+    }
+
     #[test]
     fn test_user_crdt_channels_set() {
         let server = ServerId::new("001");
@@ -511,4 +904,143 @@ mod tests {
 
         assert!(user.accept_list.contains(&"friend".to_string()));
     }
+
+    #[test]
+    fn test_user_crdt_oper_and_logout() {
+        let server = ServerId::new("001");
+        let mut user = make_user("001AAA", "Nick", &server, 100);
+
+        user.set_oper(
+            Some("admin".to_string()),
+            HybridTimestamp::new(200, 0, &server),
+        );
+        assert!(*user.modes.oper.value());
+        assert_eq!(user.modes.oper_type.value().as_deref(), Some("admin"));
+
+        user.identify("acct".to_string(), HybridTimestamp::new(300, 0, &server));
+        user.logout(HybridTimestamp::new(400, 0, &server));
+        assert!(user.account.value().is_none());
+        assert!(!*user.modes.registered.value());
+
+        user.set_oper(None, HybridTimestamp::new(500, 0, &server));
+        assert!(!*user.modes.oper.value());
+        assert!(user.modes.oper_type.value().is_none());
+    }
+
+    #[test]
+    fn test_user_crdt_certfp_merge() {
+        let server1 = ServerId::new("001");
+        let server2 = ServerId::new("002");
+        let mut user1 = make_user("001AAA", "Nick", &server1, 100);
+        let mut user2 = user1.clone();
+
+        user2.set_certfp(
+            Some("abcdef".to_string()),
+            HybridTimestamp::new(200, 0, &server2),
+        );
+        user1.merge(&user2);
+        assert_eq!(user1.certfp.value().as_deref(), Some("abcdef"));
+    }
+
+    #[test]
+    fn test_user_crdt_metadata_per_key_lww() {
+        let server1 = ServerId::new("001");
+        let server2 = ServerId::new("002");
+        let mut user1 = make_user("001AAA", "Nick", &server1, 100);
+        let mut user2 = user1.clone();
+
+        user1.set_metadata(
+            "url".to_string(),
+            Some("https://a".to_string()),
+            HybridTimestamp::new(200, 0, &server1),
+        );
+        user1.set_metadata(
+            "color".to_string(),
+            Some("red".to_string()),
+            HybridTimestamp::new(200, 0, &server1),
+        );
+        user2.set_metadata(
+            "url".to_string(),
+            Some("https://b".to_string()),
+            HybridTimestamp::new(300, 0, &server2),
+        );
+        // Deleting a key is a write too
+        user2.set_metadata(
+            "color".to_string(),
+            None,
+            HybridTimestamp::new(300, 0, &server2),
+        );
+
+        let mut merged1 = user1.clone();
+        merged1.merge(&user2);
+        let mut merged2 = user2.clone();
+        merged2.merge(&user1);
+
+        assert_eq!(merged1, merged2);
+        assert_eq!(merged1.get_metadata("url"), Some("https://b"));
+        assert_eq!(merged1.get_metadata("color"), None);
+        assert_eq!(merged1.metadata_iter().count(), 1);
+        assert!(merged1.dominates(&user1));
+        assert!(!user1.dominates(&merged1));
+    }
+
+    #[test]
+    fn test_user_delta_carries_only_changes() {
+        let server1 = ServerId::new("001");
+        let server2 = ServerId::new("002");
+        let base = make_user("001AAA", "Nick", &server1, 100);
+        assert!(base.delta_from(&base).is_none());
+
+        let mut user = base.clone();
+        user.set_nick(
+            "NewNick".to_string(),
+            HybridTimestamp::new(200, 0, &server1),
+        );
+        user.set_metadata(
+            "url".to_string(),
+            Some("https://a".to_string()),
+            HybridTimestamp::new(200, 0, &server1),
+        );
+
+        let delta = user.delta_from(&base).expect("user changed");
+        assert_eq!(delta.uid, "001AAA");
+        assert!(delta.nick.is_some());
+        assert!(delta.realname.is_none());
+        assert!(delta.channels.is_none());
+        assert!(delta.modes.is_none());
+        assert_eq!(delta.metadata.len(), 1);
+
+        // A replica that diverged concurrently ends up like a full merge
+        let mut peer = base.clone();
+        peer.set_away(
+            Some("away".to_string()),
+            HybridTimestamp::new(150, 0, &server2),
+        );
+        let mut full = peer.clone();
+        full.merge(&user);
+        peer.merge_delta(&delta);
+        assert_eq!(peer, full);
+    }
+
+    #[test]
+    fn test_user_delta_join() {
+        let server = ServerId::new("001");
+        let base = make_user("001AAA", "Nick", &server, 100);
+
+        let mut step1 = base.clone();
+        step1.join_channel("#foo".to_string(), HybridTimestamp::new(200, 0, &server));
+        let mut step2 = step1.clone();
+        step2
+            .modes
+            .invisible
+            .update(true, HybridTimestamp::new(300, 0, &server));
+
+        let mut joined = step1.delta_from(&base).expect("joined a channel");
+        joined.merge(&step2.delta_from(&step1).expect("set a mode"));
+        assert!(joined.dominates(&step1.delta_from(&base).unwrap()));
+
+        let mut peer = base.clone();
+        peer.merge_delta(&joined);
+        assert_eq!(peer, step2);
+    }
 }
@@ -0,0 +1,245 @@
+//! Compact binary encoding of CRDT state and deltas.
+//!
+//! Values are encoded with [postcard] inside a small versioned envelope:
+//!
+//! ```text
+//! <version: varint> <kind: u8> <payload: postcard bytes>
+//! ```
+//!
+//! postcard is not self-describing, so the layout of a payload is fixed by
+//! the envelope version. A change to the serialized shape of any
+//! [`WireFormat`] type bumps [`WIRE_VERSION`] and keeps decoding the older
+//! versions it replaces; a peer that receives a newer version than it
+//! knows gets [`WireError::UnsupportedVersion`] and can fall back to a
+//! format both ends understand. The kind byte catches a value decoded as
+//! the wrong type before its payload is misread.
+
+use super::{ChannelCrdt, ChannelDelta, StableVersionVector, UserCrdt, UserDelta, VectorClock};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Envelope version written by [`encode`].
+pub const WIRE_VERSION: u16 = 1;
+
+/// What an envelope holds, so a payload is never decoded as the wrong type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum WireKind {
+    /// [`UserCrdt`].
+    User = 1,
+    /// [`ChannelCrdt`].
+    Channel = 2,
+    /// [`UserDelta`].
+    UserDelta = 3,
+    /// [`ChannelDelta`].
+    ChannelDelta = 4,
+    /// [`VectorClock`].
+    VectorClock = 5,
+    /// [`StableVersionVector`].
+    StableVersionVector = 6,
+}
+
+/// A CRDT type with a binary wire encoding.
+pub trait WireFormat: Serialize + DeserializeOwned {
+    /// Kind tag written to the envelope.
+    const KIND: WireKind;
+}
+
+impl WireFormat for UserCrdt {
+    const KIND: WireKind = WireKind::User;
+}
+
+impl WireFormat for ChannelCrdt {
+    const KIND: WireKind = WireKind::Channel;
+}
+
+impl WireFormat for UserDelta {
+    const KIND: WireKind = WireKind::UserDelta;
+}
+
+impl WireFormat for ChannelDelta {
+    const KIND: WireKind = WireKind::ChannelDelta;
+}
+
+impl WireFormat for VectorClock {
+    const KIND: WireKind = WireKind::VectorClock;
+}
+
+impl WireFormat for StableVersionVector {
+    const KIND: WireKind = WireKind::StableVersionVector;
+}
+
+/// Errors from decoding (or, rarely, encoding) a wire envelope.
+#[derive(Debug, thiserror::Error)]
+pub enum WireError {
+    /// The envelope was written by a newer version of this format.
+    #[error("wire format version {0} is newer than supported version {WIRE_VERSION}")]
+    UnsupportedVersion(u16),
+    /// The envelope holds a different type than the one requested.
+    #[error("expected {expected:?}, found {found:?}")]
+    UnexpectedKind {
+        /// Kind of the type being decoded.
+        expected: WireKind,
+        /// Kind recorded in the envelope.
+        found: WireKind,
+    },
+    /// The envelope or payload could not be encoded or decoded.
+    #[error("malformed wire data: {0}")]
+    Malformed(#[from] postcard::Error),
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope<'a> {
+    version: u16,
+    kind: WireKind,
+    #[serde(borrow)]
+    payload: &'a [u8],
+}
+
+/// Encode `value` in the current wire format.
+pub fn encode<T: WireFormat>(value: &T) -> Result<Vec<u8>, WireError> {
+    let payload = postcard::to_allocvec(value)?;
+    let envelope = Envelope {
+        version: WIRE_VERSION,
+        kind: T::KIND,
+        payload: &payload,
+    };
+    Ok(postcard::to_allocvec(&envelope)?)
+}
+
+/// Decode a value encoded by [`encode`] on this or an older version.
+pub fn decode<T: WireFormat>(bytes: &[u8]) -> Result<T, WireError> {
+    let envelope: Envelope<'_> = postcard::from_bytes(bytes)?;
+    if envelope.version > WIRE_VERSION {
+        return Err(WireError::UnsupportedVersion(envelope.version));
+    }
+    if envelope.kind != T::KIND {
+        return Err(WireError::UnexpectedKind {
+            expected: T::KIND,
+            found: envelope.kind,
+        });
+    }
+    Ok(postcard::from_bytes(envelope.payload)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::channel::ListEntryCrdt;
+    use crate::sync::{DeltaCrdt, HybridTimestamp, ServerId};
+
+    fn ts(millis: i64) -> HybridTimestamp {
+        HybridTimestamp::new(millis, 0, &ServerId::new("001"))
+    }
+
+    fn user() -> UserCrdt {
+        let mut user = UserCrdt::new(
+            "001AAAAAA".to_string(),
+            "alice".to_string(),
+            "alice".to_string(),
+            "Alice".to_string(),
+            "host.example".to_string(),
+            "cloak.example".to_string(),
+            ts(1),
+        );
+        user.set_away(Some("lunch".to_string()), ts(2));
+        user.join_channel("#rust".to_string(), ts(3));
+        user.set_metadata("url".to_string(), None, ts(4));
+        user
+    }
+
+    fn channel() -> ChannelCrdt {
+        let mut channel = ChannelCrdt::new("#rust".to_string(), ts(1));
+        channel.set_topic("hello".to_string(), "alice".to_string(), ts(2));
+        channel.join("001AAAAAA".to_string(), ts(3));
+        channel.add_ban("*!*@spam".to_string(), "alice".to_string(), ts(4));
+        channel.remove_ban("*!*@spam", ts(5));
+        channel.quiets.add(
+            ListEntryCrdt {
+                mask: "*!*@noisy".to_string(),
+                set_by: "alice".to_string(),
+                set_at: 6,
+            },
+            ts(6),
+        );
+        channel
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let user = user();
+        assert_eq!(decode::<UserCrdt>(&encode(&user).unwrap()).unwrap(), user);
+
+        let channel = channel();
+        assert_eq!(
+            decode::<ChannelCrdt>(&encode(&channel).unwrap()).unwrap(),
+            channel
+        );
+
+        let mut clock = VectorClock::new();
+        clock.increment(&ServerId::new("001"));
+        clock.retire(&ServerId::new("002"), 10);
+        assert_eq!(
+            decode::<VectorClock>(&encode(&clock).unwrap()).unwrap(),
+            clock
+        );
+
+        let mut stable = StableVersionVector::new(ServerId::new("001"));
+        stable.announce(10);
+        let decoded = decode::<StableVersionVector>(&encode(&stable).unwrap()).unwrap();
+        assert_eq!(decoded.stable_millis(), stable.stable_millis());
+    }
+
+    #[test]
+    fn test_delta_round_trip() {
+        let base = channel();
+        let mut changed = base.clone();
+        changed.part("001AAAAAA", ts(7));
+        let delta = changed.delta_from(&base).unwrap();
+        assert_eq!(
+            decode::<ChannelDelta>(&encode(&delta).unwrap()).unwrap(),
+            delta
+        );
+
+        let base = user();
+        let mut changed = base.clone();
+        changed.set_nick("alice_".to_string(), ts(8));
+        let delta = changed.delta_from(&base).unwrap();
+        assert_eq!(
+            decode::<UserDelta>(&encode(&delta).unwrap()).unwrap(),
+            delta
+        );
+    }
+
+    #[test]
+    fn test_rejects_newer_version() {
+        let mut bytes = encode(&VectorClock::new()).unwrap();
+        // The version is the first varint; 2 fits in one byte.
+        bytes[0] = 2;
+        assert!(matches!(
+            decode::<VectorClock>(&bytes),
+            Err(WireError::UnsupportedVersion(2))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_wrong_kind() {
+        let bytes = encode(&VectorClock::new()).unwrap();
+        assert!(matches!(
+            decode::<UserCrdt>(&bytes),
+            Err(WireError::UnexpectedKind {
+                expected: WireKind::User,
+                found: WireKind::VectorClock,
+            })
+        ));
+    }
+
+    #[test]
+    fn test_rejects_truncated_input() {
+        let bytes = encode(&user()).unwrap();
+        assert!(matches!(
+            decode::<UserCrdt>(&bytes[..bytes.len() / 2]),
+            Err(WireError::Malformed(_))
+        ));
+    }
+}
@@ -318,39 +318,29 @@ where
     S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
 {
     loop {
-        match stream.next().await {
-            Some(Ok(WsMessage::Text(text))) => {
-                if text.len() > MAX_IRC_LINE_LEN {
-                    return Err(TransportReadError::Protocol(
-                        ProtocolError::MessageTooLong {
-                            actual: text.len(),
-                            limit: MAX_IRC_LINE_LEN,
-                        },
-                    ));
+        let text = match stream.next().await {
+            Some(Ok(WsMessage::Text(text))) => text,
+            Some(Ok(WsMessage::Binary(data))) => match String::from_utf8(data) {
+                Ok(text) => text,
+                Err(e) => {
+                    let byte_pos = e.utf8_error().valid_up_to();
+                    let details = e.utf8_error().to_string();
+                    let raw_line = e.into_bytes();
+                    let command_hint = crate::error::extract_command_hint(&raw_line);
+                    return Err(TransportReadError::Protocol(ProtocolError::InvalidUtf8 {
+                        raw_line,
+                        byte_pos,
+                        details,
+                        command_hint,
+                    }));
                 }
-
-                let trimmed = text.trim_end_matches(&['\r', '\n'][..]);
-
-                for ch in trimmed.chars() {
-                    if crate::format::is_illegal_control_char(ch) {
-                        return Err(TransportReadError::Protocol(
-                            ProtocolError::IllegalControlChar(ch),
-                        ));
-                    }
-                }
-
-                return Ok(Some(trimmed.to_string()));
-            }
+            },
             Some(Ok(WsMessage::Close(_))) | None => {
                 return Ok(None);
             }
             Some(Ok(WsMessage::Ping(_))) | Some(Ok(WsMessage::Pong(_))) => {
                 continue;
             }
-            Some(Ok(WsMessage::Binary(_))) => {
-                warn!("Ignoring binary WebSocket frame (IRC is text-only)");
-                continue;
-            }
             Some(Ok(WsMessage::Frame(_))) => {
                 continue;
             }
@@ -360,7 +350,28 @@ where
                     format!("WebSocket error: {}", e),
                 )));
             }
+        };
+
+        if text.len() > MAX_IRC_LINE_LEN {
+            return Err(TransportReadError::Protocol(
+                ProtocolError::MessageTooLong {
+                    actual: text.len(),
+                    limit: MAX_IRC_LINE_LEN,
+                },
+            ));
+        }
+
+        let trimmed = text.trim_end_matches(&['\r', '\n'][..]);
+
+        for ch in trimmed.chars() {
+            if crate::format::is_illegal_control_char(ch) {
+                return Err(TransportReadError::Protocol(
+                    ProtocolError::IllegalControlChar(ch),
+                ));
+            }
         }
+
+        return Ok(Some(trimmed.to_string()));
     }
 }
 
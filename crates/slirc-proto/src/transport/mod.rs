@@ -203,6 +203,34 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "zstd")]
+    #[tokio::test]
+    async fn test_zero_copy_zstd_switch_mid_buffer() {
+        // The plain line that turns compression on and the first compressed
+        // bytes arrive in the same read.
+        let mut peer = zero_copy::compress::Zstd::new().unwrap();
+        let mut data = b"COMPRESS ZSTD\r\n".to_vec();
+        data.extend(peer.deflate(b"NICK alice\r\n").unwrap());
+        data.extend(peer.deflate(b"USER alice 0 * :Alice\r\n").unwrap());
+        let mut transport = ZeroCopyTransport::new(MockReader::new(&data));
+
+        {
+            let msg = transport.next().await.unwrap().unwrap();
+            assert_eq!(msg.command_name(), "COMPRESS");
+        }
+        transport.enable_zstd().unwrap();
+        assert!(transport.is_compressed());
+        {
+            let msg = transport.next().await.unwrap().unwrap();
+            assert_eq!(msg.args(), &["alice"]);
+        }
+        {
+            let msg = transport.next().await.unwrap().unwrap();
+            assert_eq!(msg.command_name(), "USER");
+        }
+        assert!(transport.next().await.is_none());
+    }
+
     #[tokio::test]
     async fn test_zero_copy_lf_only() {
         // IRC also accepts LF without CR
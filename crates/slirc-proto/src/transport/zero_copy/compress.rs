@@ -0,0 +1,123 @@
+//! zstd compression for zero-copy transports.
+//!
+//! Once compression is on, each direction of the connection is a single zstd
+//! stream, flushed after every write so the peer can act on each line at once
+//! while the compression context carries across lines.
+//!
+//! Incoming bytes are decompressed lazily: compressed input waits in a
+//! backlog and is only inflated while the line buffer is short of a full
+//! line, so a small compressed payload cannot expand into an unbounded
+//! line buffer.
+
+use std::io::{self, Write};
+
+use bytes::{Buf, BytesMut};
+use zstd::stream::raw::{Decoder, InBuffer, Operation, OutBuffer};
+use zstd::stream::write::Encoder;
+
+/// Bytes decompressed per step.
+const INFLATE_CHUNK: usize = 4096;
+
+/// Compression state of a connection.
+pub struct Zstd {
+    compressor: Encoder<'static, Vec<u8>>,
+    decompressor: Decoder<'static>,
+    /// Compressed input not yet decompressed.
+    backlog: BytesMut,
+}
+
+impl Zstd {
+    /// Fresh zstd streams for both directions.
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {
+            compressor: Encoder::new(Vec::new(), zstd::DEFAULT_COMPRESSION_LEVEL)?,
+            decompressor: Decoder::new()?,
+            backlog: BytesMut::new(),
+        })
+    }
+
+    /// Queue compressed input read from the stream.
+    pub fn push(&mut self, compressed: &[u8]) {
+        self.backlog.extend_from_slice(compressed);
+    }
+
+    /// Decompress backlog into `plain` until it holds more than `limit`
+    /// bytes or the backlog runs dry. Returns whether any bytes were added.
+    pub fn inflate(&mut self, plain: &mut BytesMut, limit: usize) -> io::Result<bool> {
+        let mut added = false;
+        let mut chunk = [0u8; INFLATE_CHUNK];
+        while plain.len() <= limit {
+            let mut input = InBuffer::around(&self.backlog);
+            let mut output = OutBuffer::around(&mut chunk[..]);
+            self.decompressor.run(&mut input, &mut output)?;
+            let (read, written) = (input.pos(), output.pos());
+            self.backlog.advance(read);
+            plain.extend_from_slice(&chunk[..written]);
+            added |= written > 0;
+            // A full chunk may leave output behind in the decoder even once
+            // the backlog is empty.
+            if written < INFLATE_CHUNK && (self.backlog.is_empty() || read == 0) {
+                break;
+            }
+        }
+        Ok(added)
+    }
+
+    /// Compress `plain` and flush, returning the bytes to send.
+    pub fn deflate(&mut self, plain: &[u8]) -> io::Result<Vec<u8>> {
+        self.compressor.write_all(plain)?;
+        self.compressor.flush()?;
+        Ok(std::mem::take(self.compressor.get_mut()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair() -> (Zstd, Zstd) {
+        (Zstd::new().unwrap(), Zstd::new().unwrap())
+    }
+
+    #[test]
+    fn test_round_trip_line_by_line() {
+        let (mut tx, mut rx) = pair();
+        for line in ["NICK alice\r\n", "USER alice 0 * :Alice\r\n"] {
+            let wire = tx.deflate(line.as_bytes()).unwrap();
+            rx.push(&wire);
+            let mut plain = BytesMut::new();
+            assert!(rx.inflate(&mut plain, 8191).unwrap());
+            assert_eq!(&plain[..], line.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_inflate_stops_at_limit() {
+        let (mut tx, mut rx) = pair();
+        let burst = "PRIVMSG #chan :hello\r\n".repeat(1000);
+        rx.push(&tx.deflate(burst.as_bytes()).unwrap());
+
+        let mut plain = BytesMut::new();
+        rx.inflate(&mut plain, 512).unwrap();
+        assert!(plain.len() <= 512 + INFLATE_CHUNK);
+        assert!(plain.len() < burst.len());
+
+        // The rest comes out as the buffer drains
+        let mut total = plain.len();
+        loop {
+            plain.clear();
+            if !rx.inflate(&mut plain, 512).unwrap() {
+                break;
+            }
+            total += plain.len();
+        }
+        assert_eq!(total, burst.len());
+    }
+
+    #[test]
+    fn test_garbage_is_an_error() {
+        let (_, mut rx) = pair();
+        rx.push(b"PING :not compressed\r\n");
+        assert!(rx.inflate(&mut BytesMut::new(), 8191).is_err());
+    }
+}
@@ -28,6 +28,8 @@
 //! }
 //! ```
 
+#[cfg(feature = "zstd")]
+pub mod compress;
 pub mod helpers;
 pub mod tcp;
 pub mod trait_def;
@@ -12,6 +12,8 @@ use crate::Message;
 
 use super::super::error::TransportReadError;
 use super::super::MAX_IRC_LINE_LEN;
+#[cfg(feature = "zstd")]
+use super::compress::Zstd;
 use super::helpers::{find_crlf, validate_irc_line_length, validate_line};
 use super::trait_def::LendingStream;
 
@@ -44,6 +46,9 @@ pub struct ZeroCopyTransport<S> {
     max_line_len: usize,
     /// Whether we are currently skipping bytes until a newline because of a buffer overflow
     skipping_overflow: bool,
+    /// zstd state once the connection is compressed.
+    #[cfg(feature = "zstd")]
+    zstd: Option<Zstd>,
 }
 
 impl<S> ZeroCopyTransport<S> {
@@ -55,6 +60,8 @@ impl<S> ZeroCopyTransport<S> {
             consumed: 0,
             max_line_len: MAX_IRC_LINE_LEN,
             skipping_overflow: false,
+            #[cfg(feature = "zstd")]
+            zstd: None,
         }
     }
 
@@ -69,6 +76,8 @@ impl<S> ZeroCopyTransport<S> {
             consumed: 0,
             max_line_len: MAX_IRC_LINE_LEN,
             skipping_overflow: false,
+            #[cfg(feature = "zstd")]
+            zstd: None,
         }
     }
 
@@ -80,6 +89,8 @@ impl<S> ZeroCopyTransport<S> {
             consumed: 0,
             max_line_len: max_len,
             skipping_overflow: false,
+            #[cfg(feature = "zstd")]
+            zstd: None,
         }
     }
 
@@ -109,6 +120,48 @@ impl<S> ZeroCopyTransport<S> {
     pub fn stream_ref(&self) -> &S {
         &self.stream
     }
+
+    /// Compress everything sent and received from now on with zstd.
+    ///
+    /// Input already buffered past the last line returned by `next()` is
+    /// taken to be the start of the peer's compressed stream.
+    #[cfg(feature = "zstd")]
+    pub fn enable_zstd(&mut self) -> std::io::Result<()> {
+        let mut zstd = Zstd::new()?;
+        zstd.push(&self.buffer[self.consumed..]);
+        self.buffer.truncate(self.consumed);
+        self.zstd = Some(zstd);
+        Ok(())
+    }
+
+    /// Whether the connection is compressed.
+    pub fn is_compressed(&self) -> bool {
+        #[cfg(feature = "zstd")]
+        return self.zstd.is_some();
+        #[cfg(not(feature = "zstd"))]
+        false
+    }
+
+    /// Add bytes read from the stream to the line buffer, or to the
+    /// compressed backlog when the connection is compressed.
+    fn fill(&mut self, data: &[u8]) {
+        #[cfg(feature = "zstd")]
+        if let Some(zstd) = self.zstd.as_mut() {
+            zstd.push(data);
+            return;
+        }
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Decompress pending input into the line buffer. Returns whether any
+    /// lines may have arrived; always `false` on uncompressed connections.
+    fn inflate(&mut self) -> std::io::Result<bool> {
+        #[cfg(feature = "zstd")]
+        if let Some(zstd) = self.zstd.as_mut() {
+            return zstd.inflate(&mut self.buffer, self.max_line_len);
+        }
+        Ok(false)
+    }
 }
 
 impl<S: AsyncWrite + Unpin> ZeroCopyTransport<S> {
@@ -124,8 +177,7 @@ impl<S: AsyncWrite + Unpin> ZeroCopyTransport<S> {
     /// ```
     pub async fn write_message(&mut self, message: &Message) -> std::io::Result<()> {
         let serialized = message.to_string();
-        self.stream.write_all(serialized.as_bytes()).await?;
-        self.stream.flush().await
+        self.write_bytes(serialized.as_bytes()).await
     }
 
     /// Write a borrowed IRC message to the transport (zero-copy forwarding).
@@ -146,8 +198,7 @@ impl<S: AsyncWrite + Unpin> ZeroCopyTransport<S> {
         // Use a small stack buffer for typical messages, heap-allocate only if needed
         let mut buf = String::with_capacity(512);
         write!(&mut buf, "{}", message).expect("fmt::Write to String cannot fail");
-        self.stream.write_all(buf.as_bytes()).await?;
-        self.stream.flush().await
+        self.write_bytes(buf.as_bytes()).await
     }
 
     /// Write multiple IRC messages to the transport in a single batch.
@@ -166,7 +217,19 @@ impl<S: AsyncWrite + Unpin> ZeroCopyTransport<S> {
             write!(&mut buffer, "{}", message).expect("fmt::Write to String cannot fail");
         }
 
-        self.stream.write_all(buffer.as_bytes()).await?;
+        self.write_bytes(buffer.as_bytes()).await
+    }
+
+    /// Write serialized lines, compressing them if the connection is
+    /// compressed, and flush.
+    async fn write_bytes(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        #[cfg(feature = "zstd")]
+        if let Some(zstd) = self.zstd.as_mut() {
+            let compressed = zstd.deflate(bytes)?;
+            self.stream.write_all(&compressed).await?;
+            return self.stream.flush().await;
+        }
+        self.stream.write_all(bytes).await?;
         self.stream.flush().await
     }
 }
@@ -275,6 +338,13 @@ impl<S: AsyncRead + Unpin> ZeroCopyTransport<S> {
                 }
             }
 
+            // Decompress input that has already arrived before reading more
+            match self.inflate() {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => return Some(Err(TransportReadError::Io(e))),
+            }
+
             // Need more data - read from stream
             let mut temp = [0u8; 4096];
             match self.stream.read(&mut temp).await {
@@ -291,7 +361,7 @@ impl<S: AsyncRead + Unpin> ZeroCopyTransport<S> {
                     }
                 }
                 Ok(n) => {
-                    self.buffer.extend_from_slice(&temp[..n]);
+                    self.fill(&temp[..n]);
                 }
                 Err(e) => return Some(Err(TransportReadError::Io(e))),
             }
@@ -374,8 +444,14 @@ impl<S: AsyncRead + Unpin> LendingStream for ZeroCopyTransport<S> {
                 ))));
             }
 
-            // Need more data - try to read from stream
+            // Need more data - decompress what has arrived, then try to read
+            // from stream
             let this = self.as_mut().get_mut();
+            match this.inflate() {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => return Poll::Ready(Some(Err(TransportReadError::Io(e)))),
+            }
             let mut read_buf = [0u8; 4096];
             let mut read_buf_slice = tokio::io::ReadBuf::new(&mut read_buf);
 
@@ -395,7 +471,7 @@ impl<S: AsyncRead + Unpin> LendingStream for ZeroCopyTransport<S> {
                             ))));
                         }
                     }
-                    this.buffer.extend_from_slice(read_buf_slice.filled());
+                    this.fill(read_buf_slice.filled());
                     // Loop to check buffer again
                 }
                 Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(TransportReadError::Io(e)))),
@@ -116,6 +116,46 @@ impl ZeroCopyTransportEnum {
         }
     }
 
+    /// Send outgoing lines in binary frames (`binary.ircv3.net`).
+    ///
+    /// Has no effect on non-WebSocket transports.
+    pub fn set_binary_frames(&mut self, binary: bool) {
+        match self {
+            #[cfg(feature = "tokio")]
+            Self::WebSocket(t) => t.set_binary_frames(binary),
+            #[cfg(feature = "tokio")]
+            Self::WebSocketTls(t) => t.set_binary_frames(binary),
+            _ => {}
+        }
+    }
+
+    /// Compress the connection with zstd from now on.
+    ///
+    /// Only TCP and TLS transports can be compressed.
+    #[cfg(feature = "zstd")]
+    pub fn enable_zstd(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(t) => t.enable_zstd(),
+            Self::Tls(t) => t.enable_zstd(),
+            Self::ClientTls(t) => t.enable_zstd(),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Compression not supported on WebSocket connections",
+            )),
+        }
+    }
+
+    /// Check if this transport is compressed.
+    pub fn is_compressed(&self) -> bool {
+        match self {
+            Self::Tcp(t) => t.is_compressed(),
+            Self::Tls(t) => t.is_compressed(),
+            Self::ClientTls(t) => t.is_compressed(),
+            #[cfg(feature = "tokio")]
+            _ => false,
+        }
+    }
+
     /// Read the next message from the transport.
     pub async fn next(&mut self) -> Option<Result<MessageRef<'_>, TransportReadError>> {
         match self {
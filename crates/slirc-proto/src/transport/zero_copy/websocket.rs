@@ -19,13 +19,15 @@ use super::trait_def::LendingStream;
 /// Zero-copy transport wrapper for WebSocket streams.
 ///
 /// WebSocket uses frame-based messaging rather than byte streaming, so this
-/// wrapper extracts text payloads from frames and writes them to an internal
-/// buffer for zero-copy parsing.
+/// wrapper extracts the payloads of text and binary frames and writes them to
+/// an internal buffer for zero-copy parsing. Lines from binary frames get the
+/// same UTF-8 check as lines read from a socket.
 pub struct ZeroCopyWebSocketTransport<S> {
     stream: WebSocketStream<S>,
     buffer: BytesMut,
     consumed: usize,
     max_line_len: usize,
+    binary: bool,
 }
 
 impl<S> ZeroCopyWebSocketTransport<S> {
@@ -36,6 +38,7 @@ impl<S> ZeroCopyWebSocketTransport<S> {
             buffer: BytesMut::with_capacity(8192),
             consumed: 0,
             max_line_len: MAX_IRC_LINE_LEN,
+            binary: false,
         }
     }
 
@@ -46,6 +49,7 @@ impl<S> ZeroCopyWebSocketTransport<S> {
             buffer,
             consumed: 0,
             max_line_len: MAX_IRC_LINE_LEN,
+            binary: false,
         }
     }
 
@@ -54,6 +58,12 @@ impl<S> ZeroCopyWebSocketTransport<S> {
         self.max_line_len = len;
     }
 
+    /// Send outgoing lines in binary frames instead of text frames
+    /// (`binary.ircv3.net`).
+    pub fn set_binary_frames(&mut self, binary: bool) {
+        self.binary = binary;
+    }
+
     /// Get a reference to the inner WebSocket stream.
     pub fn stream_ref(&self) -> &WebSocketStream<S> {
         &self.stream
@@ -101,7 +111,10 @@ where
                             }
                         }
                     }
-                    Err(e) => return Some(Err(e)),
+                    Err(e) => {
+                        self.consumed = line_len;
+                        return Some(Err(e));
+                    }
                 }
             }
 
@@ -120,9 +133,10 @@ where
                 Some(Ok(WsMessage::Text(text))) => {
                     // WebSocket IRC messages may or may not have CRLF
                     // Append the text, ensuring it ends with LF for our line parser
-                    let text = text.trim_end_matches(['\r', '\n']);
-                    self.buffer.extend_from_slice(text.as_bytes());
-                    self.buffer.extend_from_slice(b"\n");
+                    push_frame(&mut self.buffer, text.as_bytes());
+                }
+                Some(Ok(WsMessage::Binary(data))) => {
+                    push_frame(&mut self.buffer, &data);
                 }
                 Some(Ok(WsMessage::Close(_))) | None => {
                     if self.buffer.is_empty() {
@@ -138,10 +152,6 @@ where
                     // Ignore control frames, continue reading
                     continue;
                 }
-                Some(Ok(WsMessage::Binary(_))) => {
-                    // IRC is text-only, skip binary frames
-                    continue;
-                }
                 Some(Err(e)) => {
                     return Some(Err(TransportReadError::Io(std::io::Error::new(
                         std::io::ErrorKind::Other,
@@ -154,15 +164,12 @@ where
 
     /// Write an IRC message to the WebSocket transport.
     ///
-    /// This sends the message as a WebSocket text frame. The CRLF
-    /// terminator is stripped since WebSocket uses frame boundaries.
+    /// This sends the message as a WebSocket text frame, or a binary frame
+    /// when binary frames are on. The CRLF terminator is stripped since
+    /// WebSocket uses frame boundaries.
     pub async fn write_message(&mut self, message: &Message) -> std::io::Result<()> {
         let text = message.to_string();
-        let text = text.trim_end_matches(&['\r', '\n'][..]);
-        self.stream
-            .send(WsMessage::Text(text.to_string()))
-            .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+        self.send_line(&text).await
     }
 
     /// Write multiple IRC messages to the WebSocket transport.
@@ -184,15 +191,36 @@ where
         use std::fmt::Write;
         let mut buf = String::with_capacity(512);
         write!(&mut buf, "{}", message).expect("fmt::Write to String cannot fail");
-        // Strip CRLF for WebSocket (uses frame boundaries)
-        let text = buf.trim_end_matches(&['\r', '\n'][..]);
+        self.send_line(&buf).await
+    }
+
+    /// Send one line as a frame, without its CRLF (WebSocket uses frame
+    /// boundaries).
+    async fn send_line(&mut self, line: &str) -> std::io::Result<()> {
+        let line = line.trim_end_matches(&['\r', '\n'][..]);
+        let frame = if self.binary {
+            WsMessage::Binary(line.as_bytes().to_vec())
+        } else {
+            WsMessage::Text(line.to_string())
+        };
         self.stream
-            .send(WsMessage::Text(text.to_string()))
+            .send(frame)
             .await
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
     }
 }
 
+/// Append a frame's payload to the line buffer, ending it with LF for the
+/// line parser whether or not the peer sent CRLF.
+fn push_frame(buffer: &mut BytesMut, payload: &[u8]) {
+    let end = payload
+        .iter()
+        .rposition(|&b| b != b'\r' && b != b'\n')
+        .map_or(0, |i| i + 1);
+    buffer.extend_from_slice(&payload[..end]);
+    buffer.extend_from_slice(b"\n");
+}
+
 impl<S> LendingStream for ZeroCopyWebSocketTransport<S>
 where
     S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
@@ -231,6 +259,7 @@ where
                 {
                     let line_slice = &self.buffer[..line_len];
                     if let Err(e) = validate_line(line_slice) {
+                        self.consumed = line_len;
                         return Poll::Ready(Some(Err(e)));
                     }
                 }
@@ -271,11 +300,12 @@ where
             let this = self.as_mut().get_mut();
             match Pin::new(&mut this.stream).poll_next(cx) {
                 Poll::Ready(Some(Ok(WsMessage::Text(text)))) => {
-                    let text = text.trim_end_matches(['\r', '\n']);
-                    this.buffer.extend_from_slice(text.as_bytes());
-                    this.buffer.extend_from_slice(b"\n");
+                    push_frame(&mut this.buffer, text.as_bytes());
                     // Loop to check buffer again
                 }
+                Poll::Ready(Some(Ok(WsMessage::Binary(data)))) => {
+                    push_frame(&mut this.buffer, &data);
+                }
                 Poll::Ready(Some(Ok(WsMessage::Close(_)))) | Poll::Ready(None) => {
                     if this.buffer.is_empty() {
                         return Poll::Ready(None);
@@ -293,9 +323,6 @@ where
                 ))) => {
                     continue;
                 }
-                Poll::Ready(Some(Ok(WsMessage::Binary(_)))) => {
-                    continue;
-                }
                 Poll::Ready(Some(Err(e))) => {
                     return Poll::Ready(Some(Err(TransportReadError::Io(std::io::Error::new(
                         std::io::ErrorKind::Other,
@@ -0,0 +1,346 @@
+//! Duration and size values as written by operators.
+//!
+//! IRC servers take durations such as `30m`, `2h` or `1d12h` in ban
+//! commands, services settings and configuration, and sizes such as `64k`
+//! or `2M` for limits. This module parses both strictly (no stray
+//! characters, no silent wrap-around) and formats them back into the same
+//! compact, space-free form, so a formatted value can be used as a single
+//! IRC parameter or ISUPPORT token and parses back to the same value.
+
+use std::time::Duration;
+use thiserror::Error;
+
+/// Largest duration accepted, in seconds.
+///
+/// Durations are commonly added to Unix timestamps held in an `i64`, so
+/// anything that would not fit is rejected as an overflow.
+pub const MAX_DURATION_SECS: u64 = i64::MAX as u64;
+
+const DURATION_UNITS: [(char, u64); 5] = [
+    ('w', 604_800),
+    ('d', 86_400),
+    ('h', 3_600),
+    ('m', 60),
+    ('s', 1),
+];
+
+const SIZE_UNITS: [(char, u64); 4] = [
+    ('T', 1 << 40),
+    ('G', 1 << 30),
+    ('M', 1 << 20),
+    ('K', 1 << 10),
+];
+
+/// Error returned when a duration or size cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum UnitError {
+    /// The input was empty.
+    #[error("empty value")]
+    Empty,
+    /// A unit appeared without a number before it, e.g. `h` or `1hm`.
+    #[error("missing number before '{0}'")]
+    MissingNumber(char),
+    /// A number was not followed by a unit where one is required, e.g. `1h30`.
+    #[error("missing unit after {0}")]
+    MissingUnit(String),
+    /// An unrecognised unit or stray character.
+    #[error("unknown unit '{0}'")]
+    UnknownUnit(char),
+    /// The value does not fit.
+    #[error("value too large")]
+    Overflow,
+}
+
+/// Parse a duration such as `30`, `45s`, `30m`, `2h`, `7d`, `1w` or
+/// `1d12h30m`.
+///
+/// A bare number is seconds. Otherwise the input is one or more
+/// `<number><unit>` groups with units `w`, `d`, `h`, `m` and `s`
+/// (case-insensitive), which are summed.
+///
+/// # Examples
+///
+/// ```
+/// use slirc_proto::units::{parse_duration, UnitError};
+/// use std::time::Duration;
+///
+/// assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+/// assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+/// assert_eq!(parse_duration("1h30"), Err(UnitError::MissingUnit("30".into())));
+/// ```
+pub fn parse_duration(s: &str) -> Result<Duration, UnitError> {
+    if s.is_empty() {
+        return Err(UnitError::Empty);
+    }
+    if s.bytes().all(|b| b.is_ascii_digit()) {
+        let secs = parse_number(s)?;
+        return checked_duration(secs);
+    }
+
+    let mut total: u64 = 0;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        let (number, tail) = rest.split_at(digits);
+        let Some(unit) = tail.chars().next() else {
+            return Err(UnitError::MissingUnit(number.to_string()));
+        };
+        if number.is_empty() {
+            return Err(if unit_seconds(unit).is_some() {
+                UnitError::MissingNumber(unit)
+            } else {
+                UnitError::UnknownUnit(unit)
+            });
+        }
+        let multiplier = unit_seconds(unit).ok_or(UnitError::UnknownUnit(unit))?;
+        total = parse_number(number)?
+            .checked_mul(multiplier)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or(UnitError::Overflow)?;
+        rest = &tail[unit.len_utf8()..];
+    }
+    checked_duration(total)
+}
+
+/// Format a duration in the form [`parse_duration`] accepts, largest unit
+/// first: `1d1h1m1s`, `2h`, `0s`.
+///
+/// Days are the largest unit, so a week formats as `7d`. Sub-second
+/// precision is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use slirc_proto::units::format_duration;
+/// use std::time::Duration;
+///
+/// assert_eq!(format_duration(Duration::from_secs(90061)), "1d1h1m1s");
+/// assert_eq!(format_duration(Duration::from_secs(7200)), "2h");
+/// ```
+pub fn format_duration(duration: Duration) -> String {
+    let mut secs = duration.as_secs();
+    if secs == 0 {
+        return "0s".to_string();
+    }
+
+    let mut out = String::new();
+    for (unit, size) in &DURATION_UNITS[1..] {
+        if secs >= *size {
+            out.push_str(&(secs / size).to_string());
+            out.push(*unit);
+            secs %= size;
+        }
+    }
+    out
+}
+
+/// Parse a byte size such as `512`, `100B`, `64k`, `64KB`, `64KiB`, `2M` or
+/// `1G`.
+///
+/// Units are binary (`k` is 1024 bytes) and case-insensitive; an optional
+/// trailing `B` or `iB` is accepted.
+///
+/// # Examples
+///
+/// ```
+/// use slirc_proto::units::parse_size;
+///
+/// assert_eq!(parse_size("512"), Ok(512));
+/// assert_eq!(parse_size("64k"), Ok(65536));
+/// assert_eq!(parse_size("2MiB"), Ok(2 * 1024 * 1024));
+/// ```
+pub fn parse_size(s: &str) -> Result<u64, UnitError> {
+    if s.is_empty() {
+        return Err(UnitError::Empty);
+    }
+
+    let digits = s.bytes().take_while(u8::is_ascii_digit).count();
+    let (number, suffix) = s.split_at(digits);
+    if number.is_empty() {
+        let c = suffix.chars().next().unwrap_or_default();
+        return Err(UnitError::MissingNumber(c));
+    }
+    let number = parse_number(number)?;
+
+    let mut chars = suffix.chars();
+    let multiplier = match chars.next() {
+        None => return Ok(number),
+        Some(c) if c.eq_ignore_ascii_case(&'b') => {
+            if let Some(extra) = chars.next() {
+                return Err(UnitError::UnknownUnit(extra));
+            }
+            return Ok(number);
+        }
+        Some(c) => size_multiplier(c).ok_or(UnitError::UnknownUnit(c))?,
+    };
+    match chars.as_str() {
+        "" | "b" | "B" | "ib" | "iB" | "Ib" | "IB" => {}
+        other => {
+            let c = other.chars().next().unwrap_or_default();
+            return Err(UnitError::UnknownUnit(c));
+        }
+    }
+    number.checked_mul(multiplier).ok_or(UnitError::Overflow)
+}
+
+/// Format a byte size in the form [`parse_size`] accepts, using the
+/// largest unit that divides it exactly: `64K`, `3M`, `1000`.
+///
+/// # Examples
+///
+/// ```
+/// use slirc_proto::units::format_size;
+///
+/// assert_eq!(format_size(65536), "64K");
+/// assert_eq!(format_size(1000), "1000");
+/// ```
+pub fn format_size(bytes: u64) -> String {
+    SIZE_UNITS
+        .iter()
+        .find(|(_, size)| bytes > 0 && bytes % size == 0)
+        .map_or_else(
+            || bytes.to_string(),
+            |(unit, size)| format!("{}{}", bytes / size, unit),
+        )
+}
+
+fn parse_number(digits: &str) -> Result<u64, UnitError> {
+    // The caller guarantees ASCII digits, so the only failure is overflow
+    digits.parse().map_err(|_| UnitError::Overflow)
+}
+
+fn checked_duration(secs: u64) -> Result<Duration, UnitError> {
+    if secs > MAX_DURATION_SECS {
+        return Err(UnitError::Overflow);
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+fn unit_seconds(unit: char) -> Option<u64> {
+    DURATION_UNITS
+        .iter()
+        .find(|(c, _)| unit.eq_ignore_ascii_case(c))
+        .map(|(_, secs)| *secs)
+}
+
+fn size_multiplier(unit: char) -> Option<u64> {
+    SIZE_UNITS
+        .iter()
+        .find(|(c, _)| unit.eq_ignore_ascii_case(c))
+        .map(|(_, size)| *size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: u64) -> Result<Duration, UnitError> {
+        Ok(Duration::from_secs(s))
+    }
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("0"), secs(0));
+        assert_eq!(parse_duration("30"), secs(30));
+        assert_eq!(parse_duration("30s"), secs(30));
+        assert_eq!(parse_duration("5m"), secs(300));
+        assert_eq!(parse_duration("2H"), secs(7200));
+        assert_eq!(parse_duration("1d"), secs(86400));
+        assert_eq!(parse_duration("1w"), secs(604800));
+        assert_eq!(parse_duration("1d2h30m"), secs(86400 + 7200 + 1800));
+        assert_eq!(parse_duration("30m1h"), secs(5400));
+    }
+
+    #[test]
+    fn test_parse_duration_is_strict() {
+        assert_eq!(parse_duration(""), Err(UnitError::Empty));
+        assert_eq!(parse_duration("h"), Err(UnitError::MissingNumber('h')));
+        assert_eq!(parse_duration("1hm"), Err(UnitError::MissingNumber('m')));
+        assert_eq!(
+            parse_duration("1h30"),
+            Err(UnitError::MissingUnit("30".to_string()))
+        );
+        assert_eq!(parse_duration("1y"), Err(UnitError::UnknownUnit('y')));
+        assert_eq!(parse_duration("-5m"), Err(UnitError::UnknownUnit('-')));
+        assert_eq!(parse_duration("1h 30m"), Err(UnitError::UnknownUnit(' ')));
+        assert_eq!(parse_duration("soon"), Err(UnitError::MissingNumber('s')));
+    }
+
+    #[test]
+    fn test_parse_duration_overflow() {
+        assert_eq!(
+            parse_duration("99999999999999999999"),
+            Err(UnitError::Overflow)
+        );
+        assert_eq!(
+            parse_duration("9223372036854775807"),
+            secs(MAX_DURATION_SECS)
+        );
+        assert_eq!(
+            parse_duration("9223372036854775808"),
+            Err(UnitError::Overflow)
+        );
+        assert_eq!(
+            parse_duration("30000000000000000w"),
+            Err(UnitError::Overflow)
+        );
+        assert_eq!(
+            parse_duration("9223372036854775807s1s"),
+            Err(UnitError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+        assert_eq!(format_duration(Duration::from_millis(1500)), "1s");
+        assert_eq!(format_duration(Duration::from_secs(300)), "5m");
+        assert_eq!(format_duration(Duration::from_secs(604800)), "7d");
+        assert_eq!(format_duration(Duration::from_secs(90061)), "1d1h1m1s");
+    }
+
+    #[test]
+    fn test_duration_round_trip() {
+        for s in [0, 1, 59, 60, 3599, 3600, 86399, 90061, MAX_DURATION_SECS] {
+            let d = Duration::from_secs(s);
+            assert_eq!(parse_duration(&format_duration(d)), Ok(d));
+        }
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("0"), Ok(0));
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("512b"), Ok(512));
+        assert_eq!(parse_size("64k"), Ok(65536));
+        assert_eq!(parse_size("64KB"), Ok(65536));
+        assert_eq!(parse_size("64KiB"), Ok(65536));
+        assert_eq!(parse_size("2M"), Ok(2 << 20));
+        assert_eq!(parse_size("1g"), Ok(1 << 30));
+        assert_eq!(parse_size("1T"), Ok(1 << 40));
+    }
+
+    #[test]
+    fn test_parse_size_is_strict() {
+        assert_eq!(parse_size(""), Err(UnitError::Empty));
+        assert_eq!(parse_size("k"), Err(UnitError::MissingNumber('k')));
+        assert_eq!(parse_size("1x"), Err(UnitError::UnknownUnit('x')));
+        assert_eq!(parse_size("1kx"), Err(UnitError::UnknownUnit('x')));
+        assert_eq!(parse_size("1bb"), Err(UnitError::UnknownUnit('b')));
+        assert_eq!(parse_size("1.5M"), Err(UnitError::UnknownUnit('.')));
+        assert_eq!(parse_size("99999999999T"), Err(UnitError::Overflow));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0");
+        assert_eq!(format_size(1000), "1000");
+        assert_eq!(format_size(1024), "1K");
+        assert_eq!(format_size(1536), "1536");
+        assert_eq!(format_size(3 << 20), "3M");
+        assert_eq!(format_size(1 << 40), "1T");
+        for n in [0, 1, 1000, 1024, 65536, 3 << 20, u64::MAX] {
+            assert_eq!(parse_size(&format_size(n)), Ok(n));
+        }
+    }
+}
@@ -62,6 +62,54 @@ impl WebSocketConfig {
     }
 }
 
+/// IRCv3 WebSocket subprotocol.
+///
+/// Reference: <https://ircv3.net/specs/extensions/websocket>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrcSubprotocol {
+    /// `text.ircv3.net`: lines travel in UTF-8 text frames.
+    Text,
+    /// `binary.ircv3.net`: lines travel in binary frames.
+    Binary,
+}
+
+impl IrcSubprotocol {
+    /// The subprotocol name used in `Sec-WebSocket-Protocol`.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            IrcSubprotocol::Text => "text.ircv3.net",
+            IrcSubprotocol::Binary => "binary.ircv3.net",
+        }
+    }
+
+    /// Parse a subprotocol name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text.ircv3.net" => Some(IrcSubprotocol::Text),
+            "binary.ircv3.net" => Some(IrcSubprotocol::Binary),
+            _ => None,
+        }
+    }
+
+    /// Pick the first IRCv3 subprotocol the client offers, in its order of
+    /// preference.
+    #[cfg(feature = "tokio")]
+    pub fn negotiate(req: &Request) -> Option<Self> {
+        req.headers()
+            .get_all("Sec-WebSocket-Protocol")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .find_map(|p| Self::from_name(p.trim()))
+    }
+}
+
+impl fmt::Display for IrcSubprotocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Result of WebSocket handshake validation.
 #[derive(Debug)]
 #[non_exhaustive]
@@ -343,6 +391,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ircv3_subprotocol_negotiation() {
+        let req = mock_request(None, Some("irc, binary.ircv3.net, text.ircv3.net"));
+        assert_eq!(
+            IrcSubprotocol::negotiate(&req),
+            Some(IrcSubprotocol::Binary)
+        );
+
+        let req = mock_request(None, Some("text.ircv3.net"));
+        assert_eq!(IrcSubprotocol::negotiate(&req), Some(IrcSubprotocol::Text));
+
+        let req = mock_request(None, Some("irc"));
+        assert_eq!(IrcSubprotocol::negotiate(&req), None);
+        assert_eq!(IrcSubprotocol::negotiate(&mock_request(None, None)), None);
+    }
+
     #[test]
     fn test_no_subprotocol_negotiation() {
         let config = WebSocketConfig {
@@ -0,0 +1,340 @@
+//! Property-based tests for CRDT merge laws.
+//!
+//! Each test builds three replicas from a shared initial state, applies a
+//! random interleaving of operations to them with randomized hybrid
+//! timestamps, and checks that merge is:
+//! 1. Commutative: `a ⊔ b == b ⊔ a`
+//! 2. Associative: `(a ⊔ b) ⊔ c == a ⊔ (b ⊔ c)`
+//! 3. Idempotent: `a ⊔ a == a`
+//!
+//! and that a merged replica dominates both of its inputs.
+//!
+//! Every operation gets a unique timestamp (the logical counter is the
+//! operation's index), matching the real system where a server never issues
+//! the same hybrid timestamp twice.
+//!
+//! Run with: `cargo test --features sync`
+
+#![cfg(feature = "sync")]
+
+use proptest::prelude::*;
+use slirc_proto::sync::channel::ListEntryCrdt;
+use slirc_proto::sync::traits::{AwSet, LwwRegister};
+use slirc_proto::sync::{ChannelCrdt, Crdt, HybridTimestamp, ServerId, UserCrdt, VectorClock};
+use std::cmp::Ordering;
+use std::fmt::Debug;
+
+// =============================================================================
+// HELPERS
+// =============================================================================
+
+const SERVERS: [&str; 3] = ["001", "002", "003"];
+
+/// The timestamp every replica's initial state is created with.
+fn origin() -> HybridTimestamp {
+    HybridTimestamp::new(0, 0, &ServerId::new("000"))
+}
+
+fn stamp(millis: i64, counter: u32, server: usize) -> HybridTimestamp {
+    HybridTimestamp::new(millis, counter, &ServerId::new(SERVERS[server]))
+}
+
+fn merged<T: Crdt + Clone>(a: &T, b: &T) -> T {
+    let mut out = a.clone();
+    out.merge(b);
+    out
+}
+
+/// Apply an interleaving of `(replica, millis, server, op)` to three copies
+/// of `initial`.
+fn replicas<T: Clone, O>(
+    initial: &T,
+    ops: &[(usize, i64, usize, O)],
+    apply: impl Fn(&mut T, &O, HybridTimestamp),
+) -> [T; 3] {
+    let mut out = [initial.clone(), initial.clone(), initial.clone()];
+    for (i, (replica, millis, server, op)) in ops.iter().enumerate() {
+        let ts = stamp(*millis, i as u32 + 1, *server);
+        apply(&mut out[*replica], op, ts);
+    }
+    out
+}
+
+fn check_laws<T: Crdt + Clone + PartialEq + Debug>(
+    [a, b, c]: &[T; 3],
+) -> Result<(), TestCaseError> {
+    prop_assert_eq!(merged(a, b), merged(b, a), "merge is not commutative");
+    prop_assert_eq!(
+        merged(&merged(a, b), c),
+        merged(a, &merged(b, c)),
+        "merge is not associative"
+    );
+    for x in [a, b, c] {
+        prop_assert_eq!(&merged(x, x), x, "merge is not idempotent");
+    }
+
+    let ab = merged(a, b);
+    prop_assert!(
+        ab.dominates(a) && ab.dominates(b),
+        "merge result does not dominate inputs"
+    );
+    Ok(())
+}
+
+/// `(replica, millis, server, op)`: which replica applies the op, and the
+/// wall clock and origin server of its timestamp.
+fn interleaving<O: Debug>(
+    op: impl Strategy<Value = O>,
+) -> impl Strategy<Value = Vec<(usize, i64, usize, O)>> {
+    prop::collection::vec((0..3usize, 0..20i64, 0..3usize, op), 0..32)
+}
+
+// =============================================================================
+// STRATEGIES - Operations per CRDT type
+// =============================================================================
+
+#[derive(Debug, Clone)]
+enum SetOp {
+    Add(u8),
+    Remove(u8),
+}
+
+/// Small element domain so adds and removes collide often.
+fn set_op() -> impl Strategy<Value = SetOp> {
+    prop_oneof![
+        (0..6u8).prop_map(SetOp::Add),
+        (0..6u8).prop_map(SetOp::Remove)
+    ]
+}
+
+fn apply_set(set: &mut AwSet<u8>, op: &SetOp, ts: HybridTimestamp) {
+    match op {
+        SetOp::Add(e) => set.add(*e, ts),
+        SetOp::Remove(e) => set.remove(e, ts),
+    }
+}
+
+#[derive(Debug, Clone)]
+enum ClockOp {
+    Increment(usize),
+    Retire(usize, i64),
+}
+
+/// Retirements are rare next to increments, as on a real network.
+fn clock_op() -> impl Strategy<Value = ClockOp> {
+    prop_oneof![
+        8 => (0..3usize).prop_map(ClockOp::Increment),
+        1 => (0..3usize, 0..5i64).prop_map(|(s, at)| ClockOp::Retire(s, at)),
+    ]
+}
+
+fn apply_clock(clock: &mut VectorClock, op: &ClockOp) {
+    match op {
+        ClockOp::Increment(s) => clock.increment(&ServerId::new(SERVERS[*s])),
+        ClockOp::Retire(s, at) => clock.retire(&ServerId::new(SERVERS[*s]), *at),
+    }
+}
+
+fn merged_clock(a: &VectorClock, b: &VectorClock) -> VectorClock {
+    let mut out = a.clone();
+    out.merge(b);
+    out
+}
+
+#[derive(Debug, Clone)]
+enum UserOp {
+    Nick(u8),
+    Away(Option<u8>),
+    Identify(u8),
+    Join(u8),
+    Part(u8),
+    Cap(SetOp),
+    Snomask(SetOp),
+    Oper(Option<u8>),
+    Logout,
+    Certfp(Option<u8>),
+    Metadata(u8, Option<u8>),
+}
+
+fn user_op() -> impl Strategy<Value = UserOp> {
+    prop_oneof![
+        any::<u8>().prop_map(UserOp::Nick),
+        any::<Option<u8>>().prop_map(UserOp::Away),
+        (0..4u8).prop_map(UserOp::Identify),
+        (0..4u8).prop_map(UserOp::Join),
+        (0..4u8).prop_map(UserOp::Part),
+        set_op().prop_map(UserOp::Cap),
+        set_op().prop_map(UserOp::Snomask),
+        any::<Option<u8>>().prop_map(UserOp::Oper),
+        Just(UserOp::Logout),
+        any::<Option<u8>>().prop_map(UserOp::Certfp),
+        (0..3u8, any::<Option<u8>>()).prop_map(|(k, v)| UserOp::Metadata(k, v)),
+    ]
+}
+
+fn apply_user(user: &mut UserCrdt, op: &UserOp, ts: HybridTimestamp) {
+    match op {
+        UserOp::Nick(n) => user.set_nick(format!("nick{n}"), ts),
+        UserOp::Away(msg) => user.set_away(msg.map(|m| format!("away {m}")), ts),
+        UserOp::Identify(a) => user.identify(format!("account{a}"), ts),
+        UserOp::Join(c) => user.join_channel(format!("#chan{c}"), ts),
+        UserOp::Part(c) => user.part_channel(&format!("#chan{c}"), ts),
+        UserOp::Cap(SetOp::Add(c)) => user.caps.add(format!("cap{c}"), ts),
+        UserOp::Cap(SetOp::Remove(c)) => user.caps.remove(&format!("cap{c}"), ts),
+        UserOp::Snomask(SetOp::Add(m)) => user.modes.snomasks.add(char::from(b'a' + m), ts),
+        UserOp::Snomask(SetOp::Remove(m)) => user.modes.snomasks.remove(&char::from(b'a' + m), ts),
+        UserOp::Oper(t) => user.set_oper(t.map(|t| format!("type{t}")), ts),
+        UserOp::Logout => user.logout(ts),
+        UserOp::Certfp(fp) => user.set_certfp(fp.map(|fp| format!("{fp:02x}")), ts),
+        UserOp::Metadata(k, v) => {
+            user.set_metadata(format!("key{k}"), v.map(|v| format!("value {v}")), ts)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum ChannelOp {
+    Topic(u8),
+    ClearTopic,
+    Key(Option<u8>),
+    Limit(Option<u32>),
+    Moderated(bool),
+    Join(u8),
+    Part(u8),
+    Op(u8, bool),
+    Ban(SetOp),
+    Quiet(SetOp),
+}
+
+fn channel_op() -> impl Strategy<Value = ChannelOp> {
+    prop_oneof![
+        any::<u8>().prop_map(ChannelOp::Topic),
+        Just(ChannelOp::ClearTopic),
+        any::<Option<u8>>().prop_map(ChannelOp::Key),
+        any::<Option<u32>>().prop_map(ChannelOp::Limit),
+        any::<bool>().prop_map(ChannelOp::Moderated),
+        (0..4u8).prop_map(ChannelOp::Join),
+        (0..4u8).prop_map(ChannelOp::Part),
+        (0..4u8, any::<bool>()).prop_map(|(u, on)| ChannelOp::Op(u, on)),
+        set_op().prop_map(ChannelOp::Ban),
+        set_op().prop_map(ChannelOp::Quiet),
+    ]
+}
+
+fn quiet(m: u8) -> ListEntryCrdt {
+    ListEntryCrdt {
+        mask: format!("*!*@host{m}"),
+        set_by: "setter".into(),
+        set_at: 0,
+    }
+}
+
+fn apply_channel(channel: &mut ChannelCrdt, op: &ChannelOp, ts: HybridTimestamp) {
+    match op {
+        ChannelOp::Topic(t) => channel.set_topic(format!("topic {t}"), "setter".into(), ts),
+        ChannelOp::ClearTopic => channel.clear_topic(ts),
+        ChannelOp::Key(k) => channel.key.update(k.map(|k| format!("key{k}")), ts),
+        ChannelOp::Limit(l) => channel.limit.update(*l, ts),
+        ChannelOp::Moderated(on) => channel.modes.moderated.update(*on, ts),
+        ChannelOp::Join(u) => channel.join(format!("001AAAAA{u}"), ts),
+        ChannelOp::Part(u) => channel.part(&format!("001AAAAA{u}"), ts),
+        ChannelOp::Op(u, on) => {
+            if let Some(modes) = channel.members.get_modes_mut(&format!("001AAAAA{u}")) {
+                modes.op.update(*on, ts);
+            }
+        }
+        ChannelOp::Ban(SetOp::Add(m)) => {
+            channel.add_ban(format!("*!*@host{m}"), "setter".into(), ts)
+        }
+        ChannelOp::Ban(SetOp::Remove(m)) => channel.remove_ban(&format!("*!*@host{m}"), ts),
+        ChannelOp::Quiet(SetOp::Add(m)) => channel.quiets.add(quiet(*m), ts),
+        ChannelOp::Quiet(SetOp::Remove(m)) => channel.quiets.remove(&quiet(*m), ts),
+    }
+}
+
+// =============================================================================
+// PROPERTIES
+// =============================================================================
+
+proptest! {
+    #[test]
+    fn lww_register_merge_laws(ops in interleaving(any::<u8>())) {
+        let initial = LwwRegister::new(0u8, origin());
+        let reps = replicas(&initial, &ops, |reg, value, ts| reg.update(*value, ts));
+        check_laws(&reps)?;
+    }
+
+    /// `VectorClock` is not a `Crdt` (it has no `dominates`), so the laws
+    /// are checked directly, with causal order standing in for dominance.
+    #[test]
+    fn vector_clock_merge_laws(ops in prop::collection::vec((0..3usize, clock_op()), 0..32)) {
+        let mut reps = [VectorClock::new(), VectorClock::new(), VectorClock::new()];
+        for (replica, op) in &ops {
+            apply_clock(&mut reps[*replica], op);
+        }
+        let [a, b, c] = &reps;
+
+        prop_assert_eq!(merged_clock(a, b), merged_clock(b, a), "merge is not commutative");
+        prop_assert_eq!(
+            merged_clock(&merged_clock(a, b), c),
+            merged_clock(a, &merged_clock(b, c)),
+            "merge is not associative"
+        );
+        for x in &reps {
+            prop_assert_eq!(&merged_clock(x, x), x, "merge is not idempotent");
+        }
+
+        let ab = merged_clock(a, b);
+        for x in [a, b] {
+            prop_assert!(
+                matches!(ab.partial_cmp_causal(x), Some(Ordering::Greater | Ordering::Equal)),
+                "merge result is causally before an input"
+            );
+        }
+    }
+
+    #[test]
+    fn awset_merge_laws(ops in interleaving(set_op())) {
+        let reps = replicas(&AwSet::new(), &ops, apply_set);
+        check_laws(&reps)?;
+    }
+
+    #[test]
+    fn user_crdt_merge_laws(ops in interleaving(user_op())) {
+        let initial = UserCrdt::new(
+            "001AAAAAA".to_string(),
+            "nick".to_string(),
+            "user".to_string(),
+            "Real Name".to_string(),
+            "host.example".to_string(),
+            "cloak.example".to_string(),
+            origin(),
+        );
+        let reps = replicas(&initial, &ops, apply_user);
+        check_laws(&reps)?;
+    }
+
+    /// Replicas created at different times exercise the TS rule for
+    /// member modes (the older channel's modes win).
+    #[test]
+    fn channel_crdt_ts_rule_merge_laws(
+        created in prop::array::uniform3(0..3i64),
+        ops in interleaving(channel_op()),
+    ) {
+        let mut reps = created.map(|millis| {
+            ChannelCrdt::new("#test".to_string(), stamp(millis, 0, 0))
+        });
+        for (i, (replica, millis, server, op)) in ops.iter().enumerate() {
+            let ts = stamp(*millis, i as u32 + 1, *server);
+            apply_channel(&mut reps[*replica], op, ts);
+        }
+        check_laws(&reps)?;
+    }
+
+    #[test]
+    fn channel_crdt_merge_laws(ops in interleaving(channel_op())) {
+        let initial = ChannelCrdt::new("#test".to_string(), origin());
+        let reps = replicas(&initial, &ops, apply_channel);
+        check_laws(&reps)?;
+    }
+}
@@ -0,0 +1,13 @@
+-- Q-Lines (reserved nicknames and channel names)
+-- Masks starting with a channel prefix (#, &, +, !) reserve channel names;
+-- all other masks reserve nicknames.
+
+CREATE TABLE qlines (
+    mask TEXT PRIMARY KEY,
+    reason TEXT,
+    set_by TEXT NOT NULL,
+    set_at INTEGER NOT NULL,
+    expires_at INTEGER
+);
+
+CREATE INDEX idx_qlines_expires ON qlines(expires_at);
@@ -0,0 +1,27 @@
+-- CRDT journal for crash recovery
+-- Replicated user and channel changes are appended to crdt_journal as they
+-- happen and periodically folded into crdt_snapshot. crdt_clock holds the
+-- CBOR-encoded vector clock (changes seen per origin server) as of the
+-- last snapshot.
+
+CREATE TABLE crdt_snapshot (
+    kind TEXT NOT NULL,            -- 'U' (user) or 'C' (channel)
+    key TEXT NOT NULL,             -- UID or lowercase channel name
+    state BLOB NOT NULL,           -- CBOR-encoded UserCrdt / ChannelCrdt
+    PRIMARY KEY (kind, key)
+);
+
+CREATE TABLE crdt_journal (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    key TEXT NOT NULL,
+    origin TEXT NOT NULL,          -- SID the change came from
+    state BLOB,                    -- NULL when the entity was removed
+    recorded_at INTEGER NOT NULL
+);
+
+CREATE TABLE crdt_clock (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    clock BLOB NOT NULL,
+    snapshot_at INTEGER NOT NULL
+);
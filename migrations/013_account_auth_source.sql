@@ -0,0 +1,6 @@
+-- Provenance of each account
+-- NULL for accounts registered locally; otherwise the name of the external
+-- auth provider ('ldap', 'oauth2') that created the account on first login.
+-- Only accounts created by a provider are linked to its logins.
+
+ALTER TABLE accounts ADD COLUMN auth_source TEXT;
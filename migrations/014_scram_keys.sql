@@ -0,0 +1,8 @@
+-- SCRAM-SHA-256 StoredKey and ServerKey (RFC 5802 section 3)
+-- The server only needs these two keys to check a client proof and sign its
+-- reply; unlike scram_hashed_password (the salted password) they cannot be
+-- replayed as a client. Accounts keep scram_hashed_password until their next
+-- password change, and the keys are derived from it on lookup meanwhile.
+
+ALTER TABLE accounts ADD COLUMN scram_stored_key BLOB;
+ALTER TABLE accounts ADD COLUMN scram_server_key BLOB;
@@ -0,0 +1,15 @@
+-- Account groups (organization accounts)
+-- Any account can act as a group: each row makes account_id a member of
+-- group_id, and a member holds whatever channel access the group holds.
+-- Groups may themselves be members of other groups; membership is resolved
+-- transitively and never forms a cycle.
+
+CREATE TABLE account_group_members (
+    group_id INTEGER NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    account_id INTEGER NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    added_by TEXT NOT NULL,
+    added_at INTEGER NOT NULL,
+    PRIMARY KEY (group_id, account_id)
+);
+
+CREATE INDEX idx_account_group_members_account ON account_group_members(account_id);
@@ -0,0 +1,16 @@
+-- HostServ virtual hosts
+-- One row per account: the vhost assigned by an operator (if any), whether
+-- the owner has it switched on, and a pending request awaiting review.
+
+CREATE TABLE account_vhosts (
+    account_id INTEGER PRIMARY KEY REFERENCES accounts(id) ON DELETE CASCADE,
+    vhost TEXT,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    set_by TEXT,
+    set_at INTEGER,
+    requested TEXT,
+    requested_at INTEGER
+);
+
+CREATE INDEX idx_account_vhosts_requested ON account_vhosts(requested_at)
+    WHERE requested IS NOT NULL;
@@ -0,0 +1,14 @@
+-- OperServ session limits
+-- Per-host overrides of the connections-per-IP limit. The mask is a single
+-- IP or a CIDR range; the most specific matching range wins.
+
+CREATE TABLE session_limits (
+    mask TEXT PRIMARY KEY,
+    max_sessions INTEGER NOT NULL,
+    reason TEXT,
+    set_by TEXT NOT NULL,
+    set_at INTEGER NOT NULL,
+    expires_at INTEGER
+);
+
+CREATE INDEX idx_session_limits_expires ON session_limits(expires_at);
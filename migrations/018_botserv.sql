@@ -0,0 +1,33 @@
+-- BotServ channel bots
+-- Bots are created by operators and assigned to registered channels by
+-- their founders. Each channel has at most one bot, with its own greet
+-- message and badwords list.
+
+CREATE TABLE bots (
+    id INTEGER PRIMARY KEY,
+    nick TEXT UNIQUE NOT NULL COLLATE NOCASE,
+    username TEXT NOT NULL,
+    hostname TEXT NOT NULL,
+    realname TEXT NOT NULL,
+    created_by TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE TABLE channel_bots (
+    channel_id INTEGER PRIMARY KEY REFERENCES channels(id) ON DELETE CASCADE,
+    bot_id INTEGER NOT NULL REFERENCES bots(id) ON DELETE CASCADE,
+    greet TEXT,
+    kickban INTEGER NOT NULL DEFAULT 0,
+    assigned_by TEXT NOT NULL,
+    assigned_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_channel_bots_bot ON channel_bots(bot_id);
+
+CREATE TABLE channel_badwords (
+    channel_id INTEGER NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    word TEXT NOT NULL COLLATE NOCASE,
+    added_by TEXT NOT NULL,
+    added_at INTEGER NOT NULL,
+    PRIMARY KEY (channel_id, word)
+);
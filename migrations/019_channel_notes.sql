@@ -0,0 +1,13 @@
+-- ChanServ channel notes
+-- Shared moderation notes kept by channel staff, readable only by the
+-- founder and access-list members.
+
+CREATE TABLE channel_notes (
+    id INTEGER PRIMARY KEY,
+    channel_id INTEGER NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    note TEXT NOT NULL,
+    author TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_channel_notes_channel ON channel_notes(channel_id);
@@ -0,0 +1,14 @@
+-- NickServ email confirmation and password resets
+-- Accounts registered while email verification is on start unverified;
+-- existing accounts count as verified. Codes are single-use, one per account
+-- and kind ('confirm' or 'reset'); issuing a new one replaces the old.
+
+ALTER TABLE accounts ADD COLUMN verified INTEGER NOT NULL DEFAULT 1;
+
+CREATE TABLE account_codes (
+    account_id INTEGER NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    code TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (account_id, kind)
+);
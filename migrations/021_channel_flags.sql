@@ -0,0 +1,17 @@
+-- ChanServ FLAGS
+-- Access entries now use Atheme-style flags, where +o and +v only grant the
+-- OP and VOICE commands. Give existing entries the flags that carry their old
+-- meaning: auto-op, AKICK and list viewing for +o, auto-voice for +v.
+-- instr() is case-sensitive, unlike LIKE.
+
+UPDATE channel_access SET flags = flags || 'O'
+WHERE instr(flags, 'o') > 0 AND instr(flags, 'O') = 0;
+
+UPDATE channel_access SET flags = flags || 'r'
+WHERE instr(flags, 'o') > 0 AND instr(flags, 'r') = 0;
+
+UPDATE channel_access SET flags = flags || 'A'
+WHERE instr(flags, 'o') > 0 AND instr(flags, 'A') = 0;
+
+UPDATE channel_access SET flags = flags || 'V'
+WHERE instr(flags, 'v') > 0 AND instr(flags, 'V') = 0;
@@ -0,0 +1,8 @@
+-- ChanServ successor and channel expiry
+-- The successor becomes founder when the founder's account is dropped.
+-- last_used_at is kept current while a channel is in use, and registrations
+-- unused for [chanserv] expire_days are dropped.
+
+ALTER TABLE channels ADD COLUMN successor_account_id INTEGER REFERENCES accounts(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_channels_last_used ON channels(last_used_at);
@@ -0,0 +1,10 @@
+-- draft/read-marker
+-- The newest message each account has read in each channel or query,
+-- shared by all of the account's clients.
+
+CREATE TABLE read_markers (
+    account_id INTEGER NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    target TEXT NOT NULL,
+    marked_at INTEGER NOT NULL,
+    PRIMARY KEY (account_id, target)
+);
@@ -0,0 +1,4 @@
+-- Modes of registered channels, saved whenever they change so a channel
+-- recreated after emptying gets them back.
+
+ALTER TABLE channels ADD COLUMN modes TEXT;
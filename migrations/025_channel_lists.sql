@@ -0,0 +1,12 @@
+-- Ban, exception, invite-exception and quiet lists of registered channels.
+-- The channel actor keeps the live copy and rewrites a channel's rows after
+-- each change; they are loaded when the actor is spawned.
+
+CREATE TABLE channel_lists (
+    channel_id INTEGER NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    mode TEXT NOT NULL,
+    mask TEXT NOT NULL,
+    set_by TEXT NOT NULL,
+    set_at INTEGER NOT NULL,
+    PRIMARY KEY (channel_id, mode, mask)
+);
@@ -0,0 +1,3 @@
+-- Expiry of timed list entries (TBAN, +b mask ~30m), in unix seconds.
+
+ALTER TABLE channel_lists ADD COLUMN expires_at INTEGER;
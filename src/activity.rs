@@ -0,0 +1,348 @@
+//! Lightweight activity counters behind the `/api/stats/top` endpoint.
+//!
+//! Hot paths bump an entry in a sharded map as they already pass by (command
+//! timer, channel member gauge, message fan-out, line dispatch, actor loop).
+//! A request only sorts those maps; it never walks users, channels or actors.
+
+use dashmap::DashMap;
+use serde::Serialize;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+/// Length of the window rates are counted over.
+pub const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Queued events at which a channel actor's mailbox is reported.
+pub const MAILBOX_OUTLIER: usize = 16;
+
+static ACTIVITY: LazyLock<Activity> = LazyLock::new(Activity::default);
+
+/// The process-wide counters.
+pub fn global() -> &'static Activity {
+    &ACTIVITY
+}
+
+/// Event count with a one-window rate.
+#[derive(Debug, Clone, Copy)]
+struct RateCounter {
+    total: u64,
+    window_start: Instant,
+    current: u64,
+    previous: Option<u64>,
+}
+
+impl RateCounter {
+    fn new(now: Instant) -> Self {
+        Self {
+            total: 0,
+            window_start: now,
+            current: 0,
+            previous: None,
+        }
+    }
+
+    fn roll(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed < RATE_WINDOW {
+            return;
+        }
+        // A gap of more than one window means the last full one was empty
+        self.previous = Some(if elapsed < RATE_WINDOW * 2 {
+            self.current
+        } else {
+            0
+        });
+        self.current = 0;
+        self.window_start = now;
+    }
+
+    fn hit(&mut self, now: Instant) {
+        self.roll(now);
+        self.total += 1;
+        self.current += 1;
+    }
+
+    /// Events in the last full window, or so far in the first one.
+    fn recent(&self, now: Instant) -> u64 {
+        let mut counter = *self;
+        counter.roll(now);
+        counter.previous.unwrap_or(counter.current)
+    }
+}
+
+/// Counters for the busiest channels, commands and connections.
+#[derive(Debug, Default)]
+pub struct Activity {
+    channel_members: DashMap<String, usize>,
+    channel_messages: DashMap<String, RateCounter>,
+    commands: DashMap<String, RateCounter>,
+    connections: DashMap<String, RateCounter>,
+    mailboxes: DashMap<String, usize>,
+}
+
+impl Activity {
+    /// Record a channel's current member count.
+    pub fn channel_members(&self, channel: &str, members: usize) {
+        self.channel_members.insert(channel.to_string(), members);
+    }
+
+    /// Record a message relayed to a channel.
+    pub fn channel_message(&self, channel: &str) {
+        hit(&self.channel_messages, channel, Instant::now());
+    }
+
+    /// Forget a destroyed channel.
+    pub fn channel_removed(&self, channel: &str) {
+        self.channel_members.remove(channel);
+        self.channel_messages.remove(channel);
+        self.mailboxes.remove(channel);
+    }
+
+    /// Record a command handled.
+    pub fn command(&self, command: &str) {
+        hit(&self.commands, command, Instant::now());
+    }
+
+    /// Record a line received from a registered connection.
+    pub fn connection_line(&self, uid: &str) {
+        hit(&self.connections, uid, Instant::now());
+    }
+
+    /// Forget a closed connection.
+    pub fn connection_closed(&self, uid: &str) {
+        self.connections.remove(uid);
+    }
+
+    /// Record how many events wait behind the one a channel actor took.
+    ///
+    /// Only backlogs of at least [`MAILBOX_OUTLIER`] are kept.
+    pub fn channel_mailbox(&self, channel: &str, queued: usize) {
+        if queued >= MAILBOX_OUTLIER {
+            self.mailboxes.insert(channel.to_string(), queued);
+        } else if self.mailboxes.contains_key(channel) {
+            self.mailboxes.remove(channel);
+        }
+    }
+
+    /// The top `limit` entries of each list.
+    ///
+    /// Connection nicks are left for the caller to fill in.
+    pub fn top(&self, limit: usize) -> TopStats {
+        let now = Instant::now();
+
+        let mut by_members: Vec<_> = self
+            .channel_members
+            .iter()
+            .filter(|e| *e.value() > 0)
+            .map(|e| ChannelMembers {
+                channel: e.key().clone(),
+                members: *e.value(),
+            })
+            .collect();
+        by_members.sort_by(|a, b| b.members.cmp(&a.members).then(a.channel.cmp(&b.channel)));
+        by_members.truncate(limit);
+
+        TopStats {
+            window_secs: RATE_WINDOW.as_secs(),
+            channels_by_members: by_members,
+            channels_by_messages: top_rates(&self.channel_messages, limit, now)
+                .into_iter()
+                .map(|(channel, rate)| ChannelMessages { channel, rate })
+                .collect(),
+            commands: top_rates(&self.commands, limit, now)
+                .into_iter()
+                .map(|(command, rate)| CommandRate { command, rate })
+                .collect(),
+            connections: top_rates(&self.connections, limit, now)
+                .into_iter()
+                .map(|(uid, rate)| ConnectionRate {
+                    uid,
+                    nick: None,
+                    rate,
+                })
+                .collect(),
+            mailboxes: {
+                let mut mailboxes: Vec<_> = self
+                    .mailboxes
+                    .iter()
+                    .map(|e| Mailbox {
+                        channel: e.key().clone(),
+                        queued: *e.value(),
+                    })
+                    .collect();
+                mailboxes.sort_by(|a, b| b.queued.cmp(&a.queued).then(a.channel.cmp(&b.channel)));
+                mailboxes.truncate(limit);
+                mailboxes
+            },
+        }
+    }
+}
+
+fn hit(map: &DashMap<String, RateCounter>, key: &str, now: Instant) {
+    if let Some(mut counter) = map.get_mut(key) {
+        counter.hit(now);
+        return;
+    }
+    map.entry(key.to_string())
+        .or_insert_with(|| RateCounter::new(now))
+        .hit(now);
+}
+
+/// Highest recent rate first, then highest total.
+fn top_rates(
+    map: &DashMap<String, RateCounter>,
+    limit: usize,
+    now: Instant,
+) -> Vec<(String, Rate)> {
+    let mut rates: Vec<_> = map
+        .iter()
+        .map(|e| {
+            let rate = Rate {
+                recent: e.value().recent(now),
+                total: e.value().total,
+            };
+            (e.key().clone(), rate)
+        })
+        .collect();
+    rates.sort_by(|(a_key, a), (b_key, b)| {
+        (b.recent, b.total)
+            .cmp(&(a.recent, a.total))
+            .then(a_key.cmp(b_key))
+    });
+    rates.truncate(limit);
+    rates
+}
+
+/// Response body of `/api/stats/top`.
+#[derive(Debug, Serialize)]
+pub struct TopStats {
+    /// Window `recent` counts cover.
+    pub window_secs: u64,
+    pub channels_by_members: Vec<ChannelMembers>,
+    pub channels_by_messages: Vec<ChannelMessages>,
+    pub commands: Vec<CommandRate>,
+    pub connections: Vec<ConnectionRate>,
+    pub mailboxes: Vec<Mailbox>,
+}
+
+/// Events in the last window and since startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Rate {
+    pub recent: u64,
+    pub total: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChannelMembers {
+    pub channel: String,
+    pub members: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChannelMessages {
+    pub channel: String,
+    #[serde(flatten)]
+    pub rate: Rate,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommandRate {
+    pub command: String,
+    #[serde(flatten)]
+    pub rate: Rate,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConnectionRate {
+    pub uid: String,
+    pub nick: Option<String>,
+    #[serde(flatten)]
+    pub rate: Rate,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Mailbox {
+    pub channel: String,
+    pub queued: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_counts_the_last_full_window() {
+        let start = Instant::now();
+        let mut counter = RateCounter::new(start);
+        for _ in 0..3 {
+            counter.hit(start);
+        }
+        // First window still running: report it as it stands
+        assert_eq!(counter.recent(start + Duration::from_secs(30)), 3);
+
+        counter.hit(start + RATE_WINDOW);
+        assert_eq!(counter.recent(start + RATE_WINDOW), 3);
+        assert_eq!(counter.total, 4);
+
+        // An idle window drops the rate to zero
+        assert_eq!(counter.recent(start + RATE_WINDOW * 3), 0);
+    }
+
+    #[test]
+    fn top_orders_by_rate_and_truncates() {
+        let activity = Activity::default();
+        for _ in 0..5 {
+            activity.command("PRIVMSG");
+        }
+        activity.command("JOIN");
+        for _ in 0..2 {
+            activity.command("PING");
+        }
+        activity.channel_members("#big", 40);
+        activity.channel_members("#small", 2);
+        activity.channel_members("#empty", 0);
+
+        let top = activity.top(2);
+        let commands: Vec<_> = top.commands.iter().map(|c| c.command.as_str()).collect();
+        assert_eq!(commands, ["PRIVMSG", "PING"]);
+        assert_eq!(
+            top.commands[0].rate,
+            Rate {
+                recent: 5,
+                total: 5
+            }
+        );
+        let channels: Vec<_> = top
+            .channels_by_members
+            .iter()
+            .map(|c| c.channel.as_str())
+            .collect();
+        assert_eq!(channels, ["#big", "#small"]);
+    }
+
+    #[test]
+    fn mailbox_backlog_is_kept_only_while_above_threshold() {
+        let activity = Activity::default();
+        activity.channel_mailbox("#busy", MAILBOX_OUTLIER + 4);
+        activity.channel_mailbox("#quiet", 1);
+        assert_eq!(activity.top(10).mailboxes.len(), 1);
+
+        activity.channel_mailbox("#busy", 0);
+        assert!(activity.top(10).mailboxes.is_empty());
+    }
+
+    #[test]
+    fn removed_channels_and_connections_are_forgotten() {
+        let activity = Activity::default();
+        activity.channel_members("#gone", 3);
+        activity.channel_message("#gone");
+        activity.connection_line("001AAAAAA");
+
+        activity.channel_removed("#gone");
+        activity.connection_closed("001AAAAAA");
+        let top = activity.top(10);
+        assert!(top.channels_by_members.is_empty());
+        assert!(top.channels_by_messages.is_empty());
+        assert!(top.connections.is_empty());
+    }
+}
@@ -16,7 +16,7 @@ use tracing::{debug, trace};
 // Request Method Generation Macros
 // ============================================================================
 
-/// Macro to generate oper capability request methods (unit scope, oper check only).
+/// Macro to generate oper capability request methods (unit scope, oper class check).
 macro_rules! impl_oper_cap_request {
     ($(
         $(#[$meta:meta])*
@@ -26,7 +26,7 @@ macro_rules! impl_oper_cap_request {
             $(#[$meta])*
             pub async fn $method(&self, uid: &str) -> Option<Cap<$cap>> {
                 let nick = self.get_nick(uid).await;
-                if self.is_oper(uid).await {
+                if self.has_oper_privilege::<$cap>(uid).await {
                     self.log_grant::<$cap>(&nick, uid, &());
                     Some(Cap::new(()))
                 } else {
@@ -110,6 +110,40 @@ impl CapabilityAuthority {
         }
     }
 
+    /// Check if a user is an IRC operator whose class grants `T`.
+    ///
+    /// Opers whose block names no class hold every privilege; a class that
+    /// is no longer configured grants none.
+    async fn has_oper_privilege<T: Capability>(&self, uid: &str) -> bool {
+        let user_arc = self
+            .matrix
+            .user_manager
+            .users
+            .get(uid)
+            .map(|u| u.value().clone());
+        let Some(user_arc) = user_arc else {
+            return false;
+        };
+        let class = {
+            let user = user_arc.read().await;
+            if !user.modes.oper {
+                return false;
+            }
+            user.modes.oper_type.clone()
+        };
+        match class {
+            None => true,
+            Some(class) => self
+                .matrix
+                .hot_config
+                .read()
+                .oper_classes
+                .iter()
+                .find(|c| c.name == class)
+                .is_some_and(|c| c.grants(T::NAME)),
+        }
+    }
+
     /// Get the user's nickname for logging.
     async fn get_nick(&self, uid: &str) -> String {
         let user_arc = self
@@ -269,6 +303,9 @@ impl CapabilityAuthority {
         /// Request capability to SHUN users.
         request_shun_cap -> ShunCap,
 
+        /// Request capability to set Q-lines (RESV).
+        request_qline_cap -> QlineCap,
+
         /// Request capability for SA* admin commands.
         request_admin_cap -> AdminCap,
 
@@ -281,6 +318,9 @@ impl CapabilityAuthority {
         /// Request capability to RESTART the server.
         request_restart_cap -> RestartCap,
 
+        /// Request capability to toggle maintenance mode.
+        request_maintenance_cap -> MaintenanceCap,
+
         /// Request capability to change user hosts (CHGHOST).
         request_chghost_cap -> ChgHostCap,
 
@@ -313,6 +353,15 @@ impl CapabilityAuthority {
 
         /// Request capability to SQUIT a server.
         request_squit_cap -> SquitCap,
+
+        /// Request capability to dump a user's state (DEBUG).
+        request_debug_cap -> DebugCap,
+
+        /// Request capability to capture a connection's raw traffic (TAP).
+        request_tap_cap -> TapCap,
+
+        /// Request capability to change modes across matching channels (OMODE).
+        request_omode_cap -> OmodeCap,
     }
 
     /// Request capability to bypass mode restrictions on a channel.
@@ -326,7 +375,7 @@ impl CapabilityAuthority {
         let nick = self.get_nick(uid).await;
         let channel_lower = slirc_proto::irc_to_lower(channel);
 
-        if self.is_oper(uid).await {
+        if self.has_oper_privilege::<BypassModeCap>(uid).await {
             self.log_grant::<BypassModeCap>(&nick, uid, &channel_lower);
             Some(Cap::new(channel_lower))
         } else {
@@ -104,6 +104,9 @@ define_capability!(oper RlineCap, "oper:rline",
 define_capability!(oper ShunCap, "oper:shun",
     "Capability to SHUN users (silent ignore without disconnect). Required: IRC operator with shun privilege.");
 
+define_capability!(oper QlineCap, "oper:qline",
+    "Capability to set Q-lines (RESV reserved nicknames/channels). Required: IRC operator with resv privilege.");
+
 define_capability!(oper AdminCap, "oper:admin",
     "Capability for SA* admin commands (SAJOIN, SAPART, SAMODE, SANICK). Required: IRC operator with admin privilege.");
 
@@ -116,6 +119,9 @@ define_capability!(oper DieCap, "oper:die",
 define_capability!(oper RestartCap, "oper:restart",
     "Capability to RESTART the server. Required: IRC operator with restart privilege.");
 
+define_capability!(oper MaintenanceCap, "oper:maintenance",
+    "Capability to toggle maintenance mode (MAINTENANCE). Required: IRC operator with restart privilege.");
+
 define_capability!(oper ChgHostCap, "oper:chghost",
     "Capability to change user hosts (CHGHOST). Required: IRC operator with chghost privilege.");
 
@@ -140,6 +146,15 @@ define_capability!(oper ConnectCap, "oper:connect",
 define_capability!(oper SquitCap, "oper:squit",
     "Capability to SQUIT a server (terminate S2S link). Required: IRC operator.");
 
+define_capability!(oper DebugCap, "oper:debug",
+    "Capability to dump a user's server-side state (DEBUG). Required: IRC operator.");
+
+define_capability!(oper TapCap, "oper:tap",
+    "Capability to capture a connection's raw traffic (TAP). Required: IRC operator.");
+
+define_capability!(oper OmodeCap, "oper:omode",
+    "Capability to change modes on every channel matching a mask (OMODE). Required: IRC operator.");
+
 // ============================================================================
 // Special Capabilities
 // ============================================================================
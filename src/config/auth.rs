@@ -0,0 +1,159 @@
+//! External authentication backend configuration.
+
+use serde::Deserialize;
+
+/// Where SASL PLAIN and NickServ IDENTIFY check passwords.
+///
+/// The configured provider is asked first; if it does not accept the
+/// credentials, or cannot be reached, the local accounts table is checked.
+/// An account accepted by the provider that has no local record gets one,
+/// with a random local password. Provider logins never use an account that
+/// was registered locally unless `link_local_accounts` is set.
+///
+/// ```toml
+/// [auth]
+/// provider = "ldap"
+///
+/// [auth.ldap]
+/// url = "ldaps://ldap.example.com"
+/// user_dn = "uid={user},ou=people,dc=example,dc=com"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuthConfig {
+    /// Which backend to ask before the local accounts table.
+    #[serde(default)]
+    pub provider: AuthProviderKind,
+    /// LDAP settings, required when `provider = "ldap"`.
+    pub ldap: Option<LdapAuthConfig>,
+    /// Token introspection settings, required when `provider = "oauth2"`.
+    pub oauth2: Option<OAuth2AuthConfig>,
+    /// Let a provider login use a locally registered account of the same
+    /// name (default: false). Only enable this when the provider's names are
+    /// the same people as the local accounts.
+    #[serde(default)]
+    pub link_local_accounts: bool,
+}
+
+/// Authentication backend selector.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthProviderKind {
+    /// Local accounts table only.
+    #[default]
+    Local,
+    /// LDAP simple bind.
+    Ldap,
+    /// OAuth2 token introspection (RFC 7662); the password is an access token.
+    Oauth2,
+}
+
+/// LDAP backend: the password is checked by binding as the user.
+///
+/// The user's DN is either built from `user_dn`, or found by searching
+/// `search_base` with `search_filter`, binding as `bind_dn` for the search
+/// when set. `{user}` is replaced by the escaped account name.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LdapAuthConfig {
+    /// Server URL (`ldap://`, `ldaps://`).
+    pub url: String,
+    /// Upgrade an `ldap://` connection with StartTLS.
+    #[serde(default)]
+    pub starttls: bool,
+    /// DN template to bind as, e.g. `uid={user},ou=people,dc=example,dc=com`.
+    pub user_dn: Option<String>,
+    /// Base DN to search for the user under, instead of `user_dn`.
+    pub search_base: Option<String>,
+    /// Filter for the user search (default: `(uid={user})`).
+    #[serde(default = "default_search_filter")]
+    pub search_filter: String,
+    /// DN to bind as for the search (anonymous if unset).
+    pub bind_dn: Option<String>,
+    /// Password for `bind_dn`.
+    pub bind_password: Option<String>,
+    /// Connect and operation timeout, in seconds (default: 5).
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+}
+
+/// OAuth2 backend: the password is an access token, checked at the
+/// authorization server's introspection endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OAuth2AuthConfig {
+    /// Token introspection endpoint URL.
+    pub introspection_url: String,
+    /// Client ID used to authenticate to the endpoint.
+    pub client_id: String,
+    /// Client secret used to authenticate to the endpoint.
+    pub client_secret: String,
+    /// Introspection response field naming the account (default: `username`).
+    /// Must match the account being authenticated.
+    #[serde(default = "default_username_claim")]
+    pub username_claim: String,
+    /// Request timeout, in seconds (default: 5).
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_search_filter() -> String {
+    "(uid={user})".to_string()
+}
+
+fn default_username_claim() -> String {
+    "username".to_string()
+}
+
+fn default_timeout() -> u64 {
+    5
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_config_defaults_to_local() {
+        let config: AuthConfig = toml::from_str("").unwrap();
+        assert_eq!(config.provider, AuthProviderKind::Local);
+        assert!(config.ldap.is_none());
+        assert!(config.oauth2.is_none());
+        assert!(!config.link_local_accounts);
+    }
+
+    #[test]
+    fn auth_config_parses_ldap() {
+        let config: AuthConfig = toml::from_str(
+            r#"
+provider = "ldap"
+
+[ldap]
+url = "ldaps://ldap.example.com"
+search_base = "ou=people,dc=example,dc=com"
+bind_dn = "cn=irc,dc=example,dc=com"
+bind_password = "secret"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.provider, AuthProviderKind::Ldap);
+        let ldap = config.ldap.unwrap();
+        assert_eq!(ldap.search_filter, "(uid={user})");
+        assert_eq!(ldap.timeout_secs, 5);
+        assert!(ldap.user_dn.is_none());
+    }
+
+    #[test]
+    fn auth_config_parses_oauth2() {
+        let config: AuthConfig = toml::from_str(
+            r#"
+provider = "oauth2"
+
+[oauth2]
+introspection_url = "https://sso.example.com/introspect"
+client_id = "slircd"
+client_secret = "secret"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.provider, AuthProviderKind::Oauth2);
+        assert_eq!(config.oauth2.unwrap().username_claim, "username");
+    }
+}
@@ -0,0 +1,109 @@
+//! Channel creation, cleanup, KNOCK and join throttle configuration.
+
+use serde::Deserialize;
+
+/// Channel creation, cleanup, KNOCK and join throttle configuration.
+///
+/// ```toml
+/// [channels]
+/// creation = "registered"
+/// reap_interval_secs = 300
+/// knock_delay_secs = 30
+/// knock_channel_delay_secs = 60
+/// join_throttle_lock_secs = 60
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChannelsConfig {
+    /// Who may create a channel by joining one that does not exist.
+    #[serde(default)]
+    pub creation: ChannelCreationPolicy,
+    /// How often empty channels are reaped, in seconds (0 disables).
+    /// Registered and `+P` channels are never reaped.
+    #[serde(default = "default_reap_interval_secs")]
+    pub reap_interval_secs: u64,
+    /// Seconds a user must wait before knocking on the same channel again.
+    #[serde(default = "default_knock_delay_secs")]
+    pub knock_delay_secs: u64,
+    /// Seconds after a delivered knock before anyone may knock on that channel.
+    #[serde(default = "default_knock_channel_delay_secs")]
+    pub knock_channel_delay_secs: u64,
+    /// Seconds a channel stays +i after a join flood trips +j or +f (0 disables).
+    #[serde(default = "default_join_throttle_lock_secs")]
+    pub join_throttle_lock_secs: u64,
+}
+
+/// Channel creation policy.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChannelCreationPolicy {
+    /// Any user may create channels.
+    #[default]
+    Anyone,
+    /// Only users logged into an account may create channels.
+    Registered,
+    /// Only IRC operators may create channels.
+    Opers,
+}
+
+impl ChannelCreationPolicy {
+    /// Whether a user with the given status may create a channel.
+    pub fn allows(self, is_registered: bool, is_oper: bool) -> bool {
+        match self {
+            Self::Anyone => true,
+            Self::Registered => is_registered || is_oper,
+            Self::Opers => is_oper,
+        }
+    }
+}
+
+impl Default for ChannelsConfig {
+    fn default() -> Self {
+        Self {
+            creation: ChannelCreationPolicy::default(),
+            reap_interval_secs: default_reap_interval_secs(),
+            knock_delay_secs: default_knock_delay_secs(),
+            knock_channel_delay_secs: default_knock_channel_delay_secs(),
+            join_throttle_lock_secs: default_join_throttle_lock_secs(),
+        }
+    }
+}
+
+fn default_reap_interval_secs() -> u64 {
+    300
+}
+
+fn default_knock_delay_secs() -> u64 {
+    30
+}
+
+fn default_knock_channel_delay_secs() -> u64 {
+    60
+}
+
+fn default_join_throttle_lock_secs() -> u64 {
+    60
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels_config_defaults() {
+        let config: ChannelsConfig = toml::from_str("").unwrap();
+        assert_eq!(config.creation, ChannelCreationPolicy::Anyone);
+        assert_eq!(config.reap_interval_secs, 300);
+        assert_eq!(config.knock_delay_secs, 30);
+        assert_eq!(config.knock_channel_delay_secs, 60);
+        assert_eq!(config.join_throttle_lock_secs, 60);
+    }
+
+    #[test]
+    fn creation_policy_allows() {
+        let config: ChannelsConfig = toml::from_str("creation = \"registered\"").unwrap();
+        assert!(!config.creation.allows(false, false));
+        assert!(config.creation.allows(true, false));
+        assert!(config.creation.allows(false, true));
+        assert!(!ChannelCreationPolicy::Opers.allows(true, false));
+    }
+}
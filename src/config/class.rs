@@ -0,0 +1,106 @@
+//! Client connection classes.
+
+use serde::Deserialize;
+
+/// What a client's send queue does once the client stops reading.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SendQPolicy {
+    /// Close the connection with "SendQ exceeded" once `sendq` is full.
+    #[default]
+    Disconnect,
+    /// Discard bulk replies (WHO, WHOX and LIST entries) once the queue is
+    /// half full; anything past `sendq` still disconnects.
+    Drop,
+    /// Stop reading the client's commands while the queue is over `sendq`,
+    /// so it cannot ask for more than it reads. Other traffic keeps queueing
+    /// and twice `sendq` disconnects.
+    Block,
+}
+
+/// One `[[class]]` block: limits for the clients of the listeners naming it.
+///
+/// Listeners without a `class` use the class named `default`, or the
+/// built-in defaults below if there is none.
+///
+/// ```toml
+/// [[class]]
+/// name = "bots"
+/// sendq = 1048576
+/// sendq_policy = "block"
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct ClassBlock {
+    /// Class name, referenced by `class` in `[[listen]]` blocks.
+    pub name: String,
+    /// Bytes that may wait for a client to read them.
+    #[serde(default = "default_sendq")]
+    pub sendq: usize,
+    /// What happens when the client falls behind.
+    #[serde(default)]
+    pub sendq_policy: SendQPolicy,
+}
+
+impl Default for ClassBlock {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_CLASS.to_string(),
+            sendq: default_sendq(),
+            sendq_policy: SendQPolicy::default(),
+        }
+    }
+}
+
+/// Class used by listeners that do not name one.
+pub const DEFAULT_CLASS: &str = "default";
+
+fn default_sendq() -> usize {
+    256 * 1024
+}
+
+/// The class called `name` (or the default class), falling back to the
+/// built-in defaults.
+pub fn find_class(classes: &[ClassBlock], name: Option<&str>) -> ClassBlock {
+    let name = name.unwrap_or(DEFAULT_CLASS);
+    classes
+        .iter()
+        .find(|c| c.name == name)
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn class_defaults() {
+        let class: ClassBlock = toml::from_str("name = \"users\"").unwrap();
+        assert_eq!(class.sendq, 256 * 1024);
+        assert_eq!(class.sendq_policy, SendQPolicy::Disconnect);
+
+        let class: ClassBlock =
+            toml::from_str("name = \"bots\"\nsendq = 4096\nsendq_policy = \"block\"").unwrap();
+        assert_eq!(class.sendq, 4096);
+        assert_eq!(class.sendq_policy, SendQPolicy::Block);
+    }
+
+    #[test]
+    fn find_class_falls_back_to_default() {
+        let classes = vec![
+            ClassBlock {
+                name: "default".to_string(),
+                sendq: 1000,
+                sendq_policy: SendQPolicy::Drop,
+            },
+            ClassBlock {
+                name: "bots".to_string(),
+                sendq: 2000,
+                sendq_policy: SendQPolicy::Block,
+            },
+        ];
+        assert_eq!(find_class(&classes, Some("bots")).sendq, 2000);
+        assert_eq!(find_class(&classes, None).sendq, 1000);
+        assert_eq!(find_class(&[], None), ClassBlock::default());
+    }
+}
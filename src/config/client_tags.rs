@@ -0,0 +1,141 @@
+//! Client-only tag relay policy.
+
+use serde::Deserialize;
+use slirc_proto::wildcard_match;
+
+/// Which client-only (`+`) tags are relayed between clients.
+///
+/// Tags matching no pattern are stripped. `dm_allow` admits experimental
+/// tags, such as end-to-end encrypted payloads, in direct messages only;
+/// those tags are never stored in history.
+///
+/// ```toml
+/// [client_tags]
+/// allow = ["+typing", "+draft/reply", "+draft/react"]
+/// dm_allow = ["+draft/e2ee*"]
+/// max_value_bytes = 4094
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClientTagsConfig {
+    /// Tags relayed to any target (`*` and `?` wildcards).
+    #[serde(default = "default_allow")]
+    pub allow: Vec<String>,
+    /// Tags relayed only to users, never to channels.
+    #[serde(default)]
+    pub dm_allow: Vec<String>,
+    /// Longest unescaped tag value relayed, in bytes; longer tags are stripped.
+    #[serde(default = "default_max_value_bytes")]
+    pub max_value_bytes: usize,
+}
+
+/// Where a client-only tag may be relayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientTagScope {
+    /// Channels and users.
+    Any,
+    /// Direct messages only.
+    DirectOnly,
+}
+
+/// Why a client-only tag was stripped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientTagDrop {
+    /// No `allow` or `dm_allow` pattern matches the key.
+    NotAllowed,
+    /// The value is longer than `max_value_bytes`.
+    TooLong,
+}
+
+impl ClientTagDrop {
+    /// Metric label.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::NotAllowed => "not_allowed",
+            Self::TooLong => "too_long",
+        }
+    }
+}
+
+impl ClientTagsConfig {
+    /// Where the tag `key` with `value` may be relayed.
+    pub fn scope(&self, key: &str, value: Option<&str>) -> Result<ClientTagScope, ClientTagDrop> {
+        let scope = if self.allow.iter().any(|p| wildcard_match(p, key)) {
+            ClientTagScope::Any
+        } else if self.dm_allow.iter().any(|p| wildcard_match(p, key)) {
+            ClientTagScope::DirectOnly
+        } else {
+            return Err(ClientTagDrop::NotAllowed);
+        };
+        if value.is_some_and(|v| v.len() > self.max_value_bytes) {
+            return Err(ClientTagDrop::TooLong);
+        }
+        Ok(scope)
+    }
+}
+
+impl Default for ClientTagsConfig {
+    fn default() -> Self {
+        Self {
+            allow: default_allow(),
+            dm_allow: Vec::new(),
+            max_value_bytes: default_max_value_bytes(),
+        }
+    }
+}
+
+/// Client tags with published or draft IRCv3 specifications.
+fn default_allow() -> Vec<String> {
+    [
+        "+typing",
+        "+draft/typing",
+        "+reply",
+        "+draft/reply",
+        "+react",
+        "+draft/react",
+        "+unreact",
+        "+draft/unreact",
+        "+channel-context",
+        "+draft/channel-context",
+        "+draft/persist",
+    ]
+    .into_iter()
+    .map(str::to_string)
+    .collect()
+}
+
+fn default_max_value_bytes() -> usize {
+    4094
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_allows_spec_tags_only() {
+        let config = ClientTagsConfig::default();
+        assert_eq!(
+            config.scope("+typing", Some("active")),
+            Ok(ClientTagScope::Any)
+        );
+        assert_eq!(
+            config.scope("+example/note", None),
+            Err(ClientTagDrop::NotAllowed)
+        );
+    }
+
+    #[test]
+    fn dm_allow_and_size_limit() {
+        let config: ClientTagsConfig =
+            toml::from_str("dm_allow = [\"+draft/e2ee*\"]\nmax_value_bytes = 8").unwrap();
+        assert_eq!(
+            config.scope("+draft/e2ee-payload", Some("abc")),
+            Ok(ClientTagScope::DirectOnly)
+        );
+        assert_eq!(
+            config.scope("+draft/e2ee-payload", Some("123456789")),
+            Err(ClientTagDrop::TooLong)
+        );
+        assert_eq!(config.scope("+typing", None), Ok(ClientTagScope::Any));
+    }
+}
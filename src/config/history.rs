@@ -1,6 +1,9 @@
 //! History storage configuration (Innovation 5: Event-Sourced History).
 
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, de};
+use slirc_proto::{irc_to_lower, units};
+use std::collections::HashMap;
+use std::time::Duration;
 
 use super::types::default_true;
 
@@ -10,12 +13,15 @@ pub struct HistoryConfig {
     /// Whether history is enabled.
     #[serde(default)]
     pub enabled: bool,
-    /// Backend type: "redb", "sqlite", "none".
+    /// Backend type: "redb", "memory", "none".
     #[serde(default = "default_history_backend")]
     pub backend: String,
     /// Path to history database file.
     #[serde(default = "default_history_path")]
     pub path: String,
+    /// Items kept per channel or DM by the "memory" backend.
+    #[serde(default = "default_memory_max_items")]
+    pub memory_max_items: usize,
     /// Maximum number of messages to return for ZNC `play <channel> <start>` form.
     /// Defaults to 50 if not set.
     #[serde(default, rename = "znc-maxmessages")]
@@ -23,6 +29,9 @@ pub struct HistoryConfig {
     /// Event type configuration.
     #[serde(default)]
     pub events: HistoryEventsConfig,
+    /// How long stored history is kept.
+    #[serde(default)]
+    pub retention: HistoryRetentionConfig,
 }
 
 /// Configuration for which event types to store in history.
@@ -61,6 +70,77 @@ impl Default for HistoryEventsConfig {
     }
 }
 
+/// How long each kind of stored history is kept, in days (0 = forever).
+///
+/// Values are a number of days or a whole-day duration such as `"2w"`.
+///
+/// ```toml
+/// [history.retention]
+/// channel_days = 30
+/// dm_days = "1w"
+/// event_days = 3
+///
+/// [history.retention.channels]
+/// "#archive" = 0
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryRetentionConfig {
+    /// Channel PRIVMSG/NOTICE/TAGMSG.
+    #[serde(
+        default = "default_retention_days",
+        deserialize_with = "deserialize_retention_days"
+    )]
+    pub channel_days: u32,
+    /// Direct messages between users.
+    #[serde(
+        default = "default_retention_days",
+        deserialize_with = "deserialize_retention_days"
+    )]
+    pub dm_days: u32,
+    /// Event records (JOIN, PART, MODE, TOPIC, ...).
+    #[serde(
+        default = "default_retention_days",
+        deserialize_with = "deserialize_retention_days"
+    )]
+    pub event_days: u32,
+    /// Per-channel overrides of `channel_days`.
+    #[serde(default, deserialize_with = "deserialize_channel_retention")]
+    pub channels: HashMap<String, u32>,
+}
+
+impl Default for HistoryRetentionConfig {
+    fn default() -> Self {
+        Self {
+            channel_days: default_retention_days(),
+            dm_days: default_retention_days(),
+            event_days: default_retention_days(),
+            channels: HashMap::new(),
+        }
+    }
+}
+
+impl HistoryRetentionConfig {
+    /// How long an item stored under `target` is kept, or `None` to keep it
+    /// forever.
+    ///
+    /// `target` is the history key: a channel name, or `dm:...` for direct
+    /// messages.
+    pub fn retention_for(&self, target: &str, is_event: bool) -> Option<Duration> {
+        let days = if is_event {
+            self.event_days
+        } else if target.starts_with("dm:") {
+            self.dm_days
+        } else {
+            let target = irc_to_lower(target);
+            self.channels
+                .iter()
+                .find(|(name, _)| irc_to_lower(name) == target)
+                .map_or(self.channel_days, |(_, days)| *days)
+        };
+        (days > 0).then(|| Duration::from_secs(u64::from(days) * 86400))
+    }
+}
+
 impl HistoryConfig {
     /// Check if a specific event type should be stored.
     pub fn should_store_event(&self, event_type: &str) -> bool {
@@ -85,8 +165,10 @@ impl Default for HistoryConfig {
             enabled: false,
             backend: "none".to_string(),
             path: "history.db".to_string(),
+            memory_max_items: default_memory_max_items(),
             events: HistoryEventsConfig::default(),
             znc_maxmessages: None,
+            retention: HistoryRetentionConfig::default(),
         }
     }
 }
@@ -99,6 +181,53 @@ fn default_history_path() -> String {
     "history.db".to_string()
 }
 
+fn default_memory_max_items() -> usize {
+    1000
+}
+
+fn default_retention_days() -> u32 {
+    30
+}
+
+/// A retention period as written in the config file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RetentionDays {
+    Days(u32),
+    Duration(String),
+}
+
+impl RetentionDays {
+    fn into_days(self) -> Result<u32, String> {
+        let text = match self {
+            Self::Days(days) => return Ok(days),
+            Self::Duration(text) => text,
+        };
+        let secs = units::parse_duration(&text)
+            .map_err(|e| format!("invalid retention {text:?}: {e}"))?
+            .as_secs();
+        if !secs.is_multiple_of(86400) {
+            return Err(format!("retention {text:?} is not a whole number of days"));
+        }
+        u32::try_from(secs / 86400).map_err(|_| format!("retention {text:?} is too long"))
+    }
+}
+
+fn deserialize_retention_days<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    RetentionDays::deserialize(deserializer)?
+        .into_days()
+        .map_err(de::Error::custom)
+}
+
+fn deserialize_channel_retention<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, u32>, D::Error> {
+    HashMap::<String, RetentionDays>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(channel, value)| Ok((channel, value.into_days().map_err(de::Error::custom)?)))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -355,6 +484,73 @@ mod tests {
         assert!(!config.should_store_event(""));
     }
 
+    // === HistoryRetentionConfig Tests ===
+
+    const DAY: Duration = Duration::from_secs(86400);
+
+    #[test]
+    fn history_retention_default_is_thirty_days_everywhere() {
+        let retention = HistoryRetentionConfig::default();
+        assert_eq!(retention.retention_for("#chan", false), Some(DAY * 30));
+        assert_eq!(retention.retention_for("dm:u:a:u:b", false), Some(DAY * 30));
+        assert_eq!(retention.retention_for("#chan", true), Some(DAY * 30));
+    }
+
+    #[test]
+    fn history_retention_classifies_targets() {
+        let retention: HistoryRetentionConfig = toml::from_str(
+            r##"
+            channel_days = 14
+            dm_days = 0
+            event_days = 2
+
+            [channels]
+            "#Archive" = 365
+            "#scratch" = 1
+            "##,
+        )
+        .unwrap();
+        assert_eq!(retention.retention_for("#chan", false), Some(DAY * 14));
+        assert_eq!(retention.retention_for("dm:a:alice:u:bob", false), None);
+        assert_eq!(retention.retention_for("#chan", true), Some(DAY * 2));
+        assert_eq!(retention.retention_for("#archive", false), Some(DAY * 365));
+        assert_eq!(retention.retention_for("#scratch", false), Some(DAY));
+        // Overrides only cover channel messages; events keep their own policy.
+        assert_eq!(retention.retention_for("#archive", true), Some(DAY * 2));
+    }
+
+    #[test]
+    fn history_retention_accepts_duration_strings() {
+        let retention: HistoryRetentionConfig = toml::from_str(
+            r##"
+            channel_days = "2w"
+            dm_days = "0"
+            event_days = 3
+
+            [channels]
+            "#archive" = "365d"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(retention.retention_for("#chan", false), Some(DAY * 14));
+        assert_eq!(retention.retention_for("dm:a:alice:u:bob", false), None);
+        assert_eq!(retention.retention_for("#chan", true), Some(DAY * 3));
+        assert_eq!(retention.retention_for("#archive", false), Some(DAY * 365));
+
+        // Retention is kept in whole days
+        assert!(toml::from_str::<HistoryRetentionConfig>(r#"dm_days = "12h""#).is_err());
+        assert!(toml::from_str::<HistoryRetentionConfig>(r#"dm_days = "soon""#).is_err());
+        assert!(
+            toml::from_str::<HistoryRetentionConfig>(
+                r##"
+                [channels]
+                "#chan" = "1d1s"
+                "##
+            )
+            .is_err()
+        );
+    }
+
     // === Helper function default tests ===
 
     #[test]
@@ -0,0 +1,62 @@
+//! CRDT journal configuration.
+
+use serde::Deserialize;
+
+/// CRDT journal configuration.
+///
+/// When enabled, every replicated user and channel change is appended to a
+/// journal in the database and folded into a snapshot every
+/// `snapshot_interval_secs`, or sooner once `max_entries` changes are
+/// pending. After a crash the snapshot and journal are replayed, so channels
+/// come back with their modes, topics and lists even without a clean
+/// shutdown.
+///
+/// ```toml
+/// [journal]
+/// enabled = true
+/// snapshot_interval_secs = 300
+/// max_entries = 10000
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct JournalConfig {
+    /// Whether to journal CRDT changes and replay them on startup.
+    #[serde(default)]
+    pub enabled: bool,
+    /// How often the journal is folded into the snapshot (seconds).
+    #[serde(default = "default_snapshot_interval")]
+    pub snapshot_interval_secs: u64,
+    /// Pending journal entries that trigger an early snapshot.
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            snapshot_interval_secs: default_snapshot_interval(),
+            max_entries: default_max_entries(),
+        }
+    }
+}
+
+fn default_snapshot_interval() -> u64 {
+    300
+}
+
+fn default_max_entries() -> usize {
+    10_000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn journal_config_defaults() {
+        let config: JournalConfig = toml::from_str("").unwrap();
+        assert!(!config.enabled);
+        assert_eq!(config.snapshot_interval_secs, 300);
+        assert_eq!(config.max_entries, 10_000);
+    }
+}
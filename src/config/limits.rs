@@ -36,6 +36,29 @@ pub struct LimitsConfig {
     /// Entries older than this are pruned hourly.
     #[serde(default = "default_whowas_entry_ttl_days")]
     pub whowas_entry_ttl_days: i64,
+
+    // Oper mask-targeting caps (guard against accidental mass actions)
+    /// Maximum users a single mask-based KILL may disconnect (default: 10).
+    /// Masks matching more users are refused outright.
+    #[serde(default = "default_max_kill_mask_targets")]
+    pub max_kill_mask_targets: usize,
+    /// Maximum users listed by TRACEMASK (default: 200).
+    #[serde(default = "default_max_tracemask_results")]
+    pub max_tracemask_results: usize,
+    /// Maximum channels a single OMODE may change (default: 500).
+    #[serde(default = "default_max_omode_channels")]
+    pub max_omode_channels: usize,
+
+    /// Seconds during which an identical service notice is not repeated to
+    /// the same user (default: 5, 0 disables).
+    #[serde(default = "default_service_notice_dedupe_secs")]
+    pub service_notice_dedupe_secs: u64,
+
+    /// Maximum nicks on a MONITOR or WATCH list (default: 100).
+    /// Advertised as the MONITOR and WATCH ISUPPORT tokens; REHASH applies
+    /// a new value and re-advertises it.
+    #[serde(default = "default_max_monitor_targets")]
+    pub max_monitor_targets: usize,
 }
 
 impl Default for LimitsConfig {
@@ -48,6 +71,11 @@ impl Default for LimitsConfig {
             whowas_maxgroups: default_whowas_maxgroups(),
             whowas_groupsize: default_whowas_groupsize(),
             whowas_entry_ttl_days: default_whowas_entry_ttl_days(),
+            max_kill_mask_targets: default_max_kill_mask_targets(),
+            max_tracemask_results: default_max_tracemask_results(),
+            max_omode_channels: default_max_omode_channels(),
+            service_notice_dedupe_secs: default_service_notice_dedupe_secs(),
+            max_monitor_targets: default_max_monitor_targets(),
         }
     }
 }
@@ -80,6 +108,26 @@ fn default_whowas_entry_ttl_days() -> i64 {
     7
 }
 
+fn default_max_kill_mask_targets() -> usize {
+    10
+}
+
+fn default_max_tracemask_results() -> usize {
+    200
+}
+
+fn default_max_omode_channels() -> usize {
+    500
+}
+
+fn default_service_notice_dedupe_secs() -> u64 {
+    5
+}
+
+fn default_max_monitor_targets() -> usize {
+    100
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -91,6 +139,10 @@ mod tests {
         assert_eq!(config.max_list_channels, 1000);
         assert_eq!(config.max_names_channels, 50);
         assert_eq!(config.channel_mailbox_capacity, 500);
+        assert_eq!(config.max_kill_mask_targets, 10);
+        assert_eq!(config.max_tracemask_results, 200);
+        assert_eq!(config.max_omode_channels, 500);
+        assert_eq!(config.service_notice_dedupe_secs, 5);
     }
 
     #[test]
@@ -32,4 +32,8 @@ pub struct LinkBlock {
     pub autoconnect: bool,
     /// Expected remote SID (optional, for validation).
     pub sid: Option<String>,
+    /// Offer zstd compression of the link after the handshake. Used only
+    /// when the remote server offers it too.
+    #[serde(default)]
+    pub compression: bool,
 }
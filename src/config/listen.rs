@@ -1,16 +1,94 @@
 //! Network listener configuration.
 
-use serde::Deserialize;
-use std::net::SocketAddr;
+use ipnet::IpNet;
+use serde::{Deserialize, Deserializer};
+use std::net::{IpAddr, SocketAddr};
 
-/// Network listener configuration.
-#[derive(Debug, Clone, Deserialize)]
+/// One `[[listen]]` block: an address and the policy for connections on it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct ListenConfig {
     /// Address to bind to (e.g., "0.0.0.0:6667").
     pub address: SocketAddr,
     /// Enable PROXY protocol (v1/v2) support.
     #[serde(default)]
     pub proxy_protocol: bool,
+    /// Addresses or CIDR ranges allowed to send PROXY headers; other sources
+    /// are refused. Required when `proxy_protocol` is set.
+    #[serde(default)]
+    pub proxy_trusted: Vec<String>,
+    /// Accept TLS; certificates come from `[tls]`.
+    #[serde(default)]
+    pub tls: bool,
+    /// Speak WebSocket; allowed origins come from `[websocket]`.
+    #[serde(default)]
+    pub websocket: bool,
+    /// Accept server links only; clients are refused at registration.
+    #[serde(default)]
+    pub server_only: bool,
+    /// Tor hidden-service port: clients get the `[tor]` policy.
+    #[serde(default)]
+    pub tor: bool,
+    /// Let clients compress the connection with zstd (`COMPRESS`).
+    /// Not available on WebSocket listeners.
+    #[serde(default)]
+    pub compression: bool,
+    /// `[[class]]` for clients on this listener; `default` if unset.
+    pub class: Option<String>,
+    /// Password servers linking on this port must send, instead of the one
+    /// in their link block. Only for `server_only` listeners.
+    pub password: Option<String>,
+}
+
+impl ListenConfig {
+    /// Listener kind, as used in log lines.
+    pub fn kind(&self) -> &'static str {
+        if self.server_only {
+            "Server"
+        } else if self.tor {
+            "Tor"
+        } else if self.websocket {
+            "WebSocket"
+        } else if self.tls {
+            "TLS"
+        } else {
+            "Plaintext"
+        }
+    }
+
+    /// Whether `ip` may send a PROXY header on this listener.
+    pub fn trusts_proxy(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.proxy_trusted
+            .iter()
+            .filter_map(|entry| proxy_source(entry))
+            .any(|net| net.contains(&ip))
+    }
+}
+
+/// Parse a `proxy_trusted` entry: a CIDR range or a single address.
+pub(crate) fn proxy_source(entry: &str) -> Option<IpNet> {
+    entry
+        .parse::<IpNet>()
+        .ok()
+        .or_else(|| entry.parse::<IpAddr>().ok().map(IpNet::from))
+}
+
+/// Accept either a single `[listen]` table or any number of `[[listen]]` blocks.
+pub(super) fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<ListenConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(ListenConfig),
+        Many(Vec<ListenConfig>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(listen) => vec![listen],
+        OneOrMany::Many(listens) => listens,
+    })
 }
 
 /// Client certificate authentication mode.
@@ -29,8 +107,9 @@ pub enum ClientAuth {
 /// TLS listener configuration.
 #[derive(Debug, Clone, Deserialize)]
 pub struct TlsConfig {
-    /// Address to bind to for TLS (e.g., "0.0.0.0:6697").
-    pub address: SocketAddr,
+    /// Address to bind to for TLS (e.g., "0.0.0.0:6697"). Leave unset to
+    /// only provide certificates for `[[listen]]` blocks with `tls = true`.
+    pub address: Option<SocketAddr>,
     /// Path to certificate file (PEM format).
     pub cert_path: String,
     /// Path to private key file (PEM format).
@@ -80,8 +159,9 @@ fn default_sts_duration() -> u64 {
 /// WebSocket listener configuration.
 #[derive(Debug, Clone, Deserialize)]
 pub struct WebSocketConfig {
-    /// Address to bind to for WebSocket (e.g., "0.0.0.0:8080").
-    pub address: SocketAddr,
+    /// Address to bind to for WebSocket (e.g., "0.0.0.0:8080"). Leave unset
+    /// to only set origins for `[[listen]]` blocks with `websocket = true`.
+    pub address: Option<SocketAddr>,
     /// Allowed origins for CORS (e.g., `["https://example.com"]`).
     /// Empty list allows all origins.
     #[serde(default)]
@@ -192,6 +272,54 @@ mod tests {
         assert!(cfg.proxy_protocol);
     }
 
+    #[test]
+    fn proxy_trusted_limits_sources() {
+        let toml_str = r#"
+            address = "0.0.0.0:6667"
+            proxy_protocol = true
+            proxy_trusted = ["10.0.0.0/8", "192.168.1.5"]
+        "#;
+        let cfg: ListenConfig = toml::from_str(toml_str).unwrap();
+        assert!(cfg.trusts_proxy("10.1.2.3".parse().unwrap()));
+        assert!(cfg.trusts_proxy("::ffff:192.168.1.5".parse().unwrap()));
+        assert!(!cfg.trusts_proxy("192.168.1.6".parse().unwrap()));
+
+        // No trusted sources refuses every PROXY header
+        let closed: ListenConfig = toml::from_str("address = \"0.0.0.0:6667\"").unwrap();
+        assert!(!closed.trusts_proxy("203.0.113.9".parse().unwrap()));
+    }
+
+    #[test]
+    fn listen_accepts_one_table_or_many_blocks() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            #[serde(deserialize_with = "one_or_many")]
+            listen: Vec<ListenConfig>,
+        }
+
+        let w: Wrapper = toml::from_str("[listen]\naddress = \"0.0.0.0:6667\"").unwrap();
+        assert_eq!(w.listen.len(), 1);
+        assert_eq!(w.listen[0].kind(), "Plaintext");
+
+        let toml_str = r#"
+            [[listen]]
+            address = "0.0.0.0:6667"
+
+            [[listen]]
+            address = "0.0.0.0:6697"
+            tls = true
+
+            [[listen]]
+            address = "10.0.0.1:7000"
+            server_only = true
+            password = "linkpass"
+        "#;
+        let w: Wrapper = toml::from_str(toml_str).unwrap();
+        let kinds: Vec<_> = w.listen.iter().map(ListenConfig::kind).collect();
+        assert_eq!(kinds, ["Plaintext", "TLS", "Server"]);
+        assert_eq!(w.listen[2].password.as_deref(), Some("linkpass"));
+    }
+
     #[test]
     fn tls_config_deserialize_defaults() {
         let toml_str = r#"
@@ -200,7 +328,7 @@ mod tests {
             key_path = "/path/to/key.pem"
         "#;
         let cfg: TlsConfig = toml::from_str(toml_str).unwrap();
-        assert_eq!(cfg.address.port(), 6697);
+        assert_eq!(cfg.address.map(|a| a.port()), Some(6697));
         assert!(!cfg.tls13_only); // default
         assert_eq!(cfg.client_auth, ClientAuth::None); // default
         assert!(cfg.ca_path.is_none());
@@ -227,7 +355,7 @@ mod tests {
             address = "0.0.0.0:8080"
         "#;
         let cfg: WebSocketConfig = toml::from_str(toml_str).unwrap();
-        assert_eq!(cfg.address.port(), 8080);
+        assert_eq!(cfg.address.map(|a| a.port()), Some(8080));
         assert!(cfg.allow_origins.is_empty()); // default
         assert!(!cfg.proxy_protocol); // default
     }
@@ -0,0 +1,99 @@
+//! Metadata (draft/metadata-2) limits and visibility.
+
+use serde::Deserialize;
+use slirc_proto::wildcard_match;
+use std::collections::HashMap;
+
+/// Metadata limits and key visibility.
+///
+/// ```toml
+/// [metadata]
+/// max_keys = 20
+/// max_value_bytes = 300
+/// max_subs = 50
+/// private_keys = ["secret/*"]
+///
+/// [metadata.key_limits]
+/// avatar = 1024
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct MetadataConfig {
+    /// Keys one user or channel may hold.
+    #[serde(default = "default_max_keys")]
+    pub max_keys: usize,
+    /// Longest value, in bytes, for keys without their own limit.
+    #[serde(default = "default_max_value_bytes")]
+    pub max_value_bytes: usize,
+    /// Keys one connection may subscribe to.
+    #[serde(default = "default_max_subs")]
+    pub max_subs: usize,
+    /// Per-key value limits in bytes, overriding `max_value_bytes`.
+    #[serde(default)]
+    pub key_limits: HashMap<String, usize>,
+    /// Keys (`*` and `?` wildcards) only the target and opers can see.
+    /// For channels, the target is its operators.
+    #[serde(default)]
+    pub private_keys: Vec<String>,
+}
+
+impl MetadataConfig {
+    /// Longest value allowed for `key`, in bytes.
+    pub fn value_limit(&self, key: &str) -> usize {
+        self.key_limits
+            .get(key)
+            .copied()
+            .unwrap_or(self.max_value_bytes)
+    }
+
+    /// Whether `key` is hidden from everyone but the target and opers.
+    pub fn is_private(&self, key: &str) -> bool {
+        self.private_keys.iter().any(|p| wildcard_match(p, key))
+    }
+
+    /// Visibility token sent with `key`: `*` for public, `private` otherwise.
+    pub fn visibility(&self, key: &str) -> &'static str {
+        if self.is_private(key) { "private" } else { "*" }
+    }
+}
+
+impl Default for MetadataConfig {
+    fn default() -> Self {
+        Self {
+            max_keys: default_max_keys(),
+            max_value_bytes: default_max_value_bytes(),
+            max_subs: default_max_subs(),
+            key_limits: HashMap::new(),
+            private_keys: Vec::new(),
+        }
+    }
+}
+
+fn default_max_keys() -> usize {
+    20
+}
+
+fn default_max_value_bytes() -> usize {
+    300
+}
+
+fn default_max_subs() -> usize {
+    50
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_limits_and_private_keys() {
+        let config: MetadataConfig = toml::from_str(
+            "private_keys = [\"secret/*\"]\n[key_limits]\navatar = 1024",
+        )
+        .unwrap();
+        assert_eq!(config.max_keys, 20);
+        assert_eq!(config.value_limit("avatar"), 1024);
+        assert_eq!(config.value_limit("url"), 300);
+        assert_eq!(config.visibility("secret/note"), "private");
+        assert_eq!(config.visibility("url"), "*");
+    }
+}
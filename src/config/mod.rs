@@ -4,31 +4,58 @@
 //! - [`types`]: Core config struct definitions (Config, ServerConfig, ListenConfig)
 //! - [`listen`]: Network listener configuration (ListenConfig, TlsConfig, WebSocketConfig)
 //! - [`security`]: Security configuration (SecurityConfig, SpamConfig, RateLimitConfig, HeuristicsConfig)
-//! - [`history`]: History storage configuration (HistoryConfig, HistoryEventsConfig)
+//! - [`client_tags`]: Client-only tag relay policy (ClientTagsConfig)
+//! - [`metadata`]: Metadata limits and key visibility (MetadataConfig)
+//! - [`history`]: History storage configuration (HistoryConfig, HistoryEventsConfig, HistoryRetentionConfig)
 //! - [`limits`]: Output limits configuration (LimitsConfig)
+//! - [`channels`]: Channel creation policy and empty-channel reaping (ChannelsConfig)
+//! - [`class`]: Client connection classes and send-queue policy (ClassBlock)
 //! - [`oper`]: Operator and WEBIRC block configuration (OperBlock, WebircBlock)
 //! - [`links`]: Server-to-server link configuration (LinkBlock)
+//! - [`services`]: External services package configuration (ServicesConfig)
+//! - [`snapshot`]: Warm-boot state snapshot configuration (SnapshotConfig)
+//! - [`journal`]: CRDT journal configuration (JournalConfig)
+//! - [`tor`]: Tor listener policy (TorConfig)
 
+mod auth;
+mod channels;
+mod class;
+mod client_tags;
 mod history;
+mod journal;
 mod limits;
 mod links;
 mod listen;
+mod metadata;
 mod multiclient;
 mod oper;
 mod security;
+mod services;
+mod snapshot;
+mod tor;
 mod types;
 mod validation;
 
 // Re-export all public types for convenient access
 // Some may be unused currently but are part of the public API
-pub use history::HistoryConfig;
+pub use auth::{AuthConfig, AuthProviderKind, LdapAuthConfig, OAuth2AuthConfig};
+pub use channels::{ChannelCreationPolicy, ChannelsConfig};
+pub use class::{ClassBlock, SendQPolicy, find_class};
+pub use client_tags::{ClientTagScope, ClientTagsConfig};
+pub use history::{HistoryConfig, HistoryRetentionConfig};
+pub use journal::JournalConfig;
 pub use limits::LimitsConfig;
 pub use links::LinkBlock;
 pub use listen::{ClientAuth, ListenConfig, S2STlsConfig, StsConfig, TlsConfig, WebSocketConfig};
+pub use metadata::MetadataConfig;
 pub use multiclient::{AlwaysOnPolicy, MulticlientConfig};
-pub use oper::{OperBlock, WebircBlock};
-pub use security::{HeuristicsConfig, RateLimitConfig, RblConfig, SecurityConfig};
+pub use oper::{OperBlock, OperClassBlock, WebircBlock};
+pub use security::{HeuristicsConfig, QlineBlock, RateLimitConfig, RblConfig, SecurityConfig};
+pub use services::ServicesConfig;
+pub use snapshot::SnapshotConfig;
+pub use tor::TorConfig;
 pub use types::{
-    AccountRegistrationConfig, Casemapping, Config, IdleTimeoutsConfig, LogFormat, ServerConfig,
+    AccountRegistrationConfig, Casemapping, ChanServConfig, Config, DatabaseEncryptionConfig,
+    IdleTimeoutsConfig, LogFormat, MailConfig, RdnsConfig, ServerConfig,
 };
 pub use validation::validate;
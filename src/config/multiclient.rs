@@ -4,6 +4,7 @@
 //! including multiclient connections, always-on persistence, and auto-away.
 
 use serde::Deserialize;
+use slirc_proto::units;
 
 /// Multiclient configuration for bouncer features.
 ///
@@ -139,33 +140,20 @@ fn default_max_sessions() -> usize {
     10
 }
 
-/// Parse a duration string like "30d", "7d", "24h", "0" into chrono::Duration.
+/// Parse a duration string like "30d", "2w", "24h", "0" into chrono::Duration.
 ///
-/// Returns None for "0" or invalid format.
+/// A bare number is days. Returns None for "0" or invalid format.
 pub fn parse_duration_string(s: &str) -> Option<chrono::Duration> {
     let s = s.trim();
-    if s == "0" || s.is_empty() {
-        return None;
-    }
-
-    let (num_str, unit) = if let Some(stripped) = s.strip_suffix('d') {
-        (stripped, 'd')
-    } else if let Some(stripped) = s.strip_suffix('h') {
-        (stripped, 'h')
-    } else if let Some(stripped) = s.strip_suffix('m') {
-        (stripped, 'm')
+    let duration = if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+        units::parse_duration(&format!("{s}d")).ok()?
     } else {
-        // Assume days if no unit
-        (s, 'd')
+        units::parse_duration(s).ok()?
     };
-
-    let num: i64 = num_str.parse().ok()?;
-    match unit {
-        'd' => Some(chrono::Duration::days(num)),
-        'h' => Some(chrono::Duration::hours(num)),
-        'm' => Some(chrono::Duration::minutes(num)),
-        _ => None,
+    if duration.is_zero() {
+        return None;
     }
+    chrono::Duration::from_std(duration).ok()
 }
 
 #[cfg(test)]
@@ -278,5 +266,12 @@ mod tests {
         // No unit defaults to days
         let no_unit = parse_duration_string("7").unwrap();
         assert_eq!(no_unit.num_days(), 7);
+
+        let combined = parse_duration_string("1w12h").unwrap();
+        assert_eq!(combined.num_hours(), 7 * 24 + 12);
+
+        assert!(parse_duration_string("0d").is_none());
+        assert!(parse_duration_string("30x").is_none());
+        assert!(parse_duration_string("99999999999999999999").is_none());
     }
 }
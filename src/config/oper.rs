@@ -7,40 +7,86 @@ use serde::Deserialize;
 pub struct OperBlock {
     /// Operator name (used in OPER command).
     pub name: String,
-    /// Password (plaintext or Argon2 hash).
-    pub password: String,
+    /// Password (plaintext or Argon2 hash). Without one the block cannot
+    /// be used with a password, only by `certfp` or `challenge_key`.
+    pub password: Option<String>,
     /// Optional hostmask restriction (e.g., "*!*@trusted.host").
     pub hostmask: Option<String>,
     /// Require TLS connection to use this oper block.
     #[serde(default)]
     pub require_tls: bool,
+    /// TLS client certificate fingerprint (SHA-256 hex) the oper must present.
+    /// A block with a `certfp` and no `password` opers with `OPER <name>`.
+    pub certfp: Option<String>,
+    /// Public key for CHALLENGE: `ed25519:<base64>` or `rsa:<base64 DER>`.
+    pub challenge_key: Option<String>,
+    /// Name of the `[[oper_class]]` granting this oper's privileges.
+    /// Without one the oper holds every privilege.
+    pub class: Option<String>,
 }
 
 impl OperBlock {
     /// Verify the provided password against the stored password (plaintext or Argon2).
-    /// Verify the provided password against the stored password (plaintext or Argon2).
+    ///
+    /// Always fails for blocks without a password.
     pub async fn verify_password(&self, password: &str) -> bool {
-        if self.password.starts_with("$argon2") {
+        let Some(stored) = &self.password else {
+            return false;
+        };
+        if stored.starts_with("$argon2") {
             // Verify using Argon2 via the same mechanism as user passwords
-            crate::security::password::verify_password(password.to_string(), self.password.clone())
+            crate::security::password::verify_password(password.to_string(), stored.clone())
                 .await
                 .unwrap_or(false)
         } else {
             // Fallback to plaintext check
-            self.password == password
+            stored == password
         }
     }
 }
 
+/// Operator class configuration: what its opers may do and from where.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OperClassBlock {
+    /// Class name, referenced by `class` in `[[oper]]` blocks.
+    pub name: String,
+    /// Privileges granted, named after their capability without the
+    /// `oper:`/`special:` prefix (`kline`, `gline`, `rehash`, `squit`...),
+    /// or `*` for all of them.
+    #[serde(default)]
+    pub privileges: Vec<String>,
+    /// Hostmasks (`nick!user@host`) an oper must match one of to use the class.
+    #[serde(default)]
+    pub hostmasks: Vec<String>,
+    /// Require the oper block's `certfp` to match the client certificate.
+    #[serde(default)]
+    pub require_certfp: bool,
+}
+
+impl OperClassBlock {
+    /// Whether the class grants the capability named `cap_name` (`oper:kline`).
+    pub fn grants(&self, cap_name: &str) -> bool {
+        let privilege = cap_name
+            .split_once(':')
+            .map_or(cap_name, |(_, privilege)| privilege);
+        self.privileges
+            .iter()
+            .any(|p| p == "*" || p.eq_ignore_ascii_case(privilege))
+    }
+}
+
 /// WEBIRC block configuration for trusted gateway clients.
 ///
 /// WEBIRC allows trusted proxies (web clients, bouncers) to forward
 /// the real user's IP/host to the IRC server.
 #[derive(Debug, Clone, Deserialize)]
 pub struct WebircBlock {
+    /// Gateway name; when set, WEBIRC must name this gateway to match.
+    #[serde(default)]
+    pub name: Option<String>,
     /// Password for WEBIRC authentication.
     pub password: String,
-    /// Allowed host/IP patterns for the gateway (glob patterns supported).
+    /// Allowed gateway IPs: glob patterns or CIDR ranges. Empty allows any.
     #[serde(default)]
     pub hosts: Vec<String>,
 }
@@ -52,12 +98,30 @@ mod tests {
     fn make_oper(password: &str) -> OperBlock {
         OperBlock {
             name: "testoper".to_string(),
-            password: password.to_string(),
+            password: Some(password.to_string()),
             hostmask: None,
             require_tls: false,
+            certfp: None,
+            challenge_key: None,
+            class: None,
         }
     }
 
+    #[test]
+    fn oper_class_grants_named_privileges() {
+        let class: OperClassBlock =
+            toml::from_str("name = \"helper\"\nprivileges = [\"kill\", \"KLINE\"]").unwrap();
+        assert!(class.grants("oper:kill"));
+        assert!(class.grants("oper:kline"));
+        assert!(!class.grants("oper:gline"));
+        assert!(!class.grants("special:bypass_flood"));
+
+        let class: OperClassBlock =
+            toml::from_str("name = \"netadmin\"\nprivileges = [\"*\"]").unwrap();
+        assert!(class.grants("oper:die"));
+        assert!(class.grants("special:bypass_mode"));
+    }
+
     #[tokio::test]
     async fn verify_password_plaintext_match() {
         let oper = make_oper("hunter2");
@@ -105,6 +169,15 @@ mod tests {
         assert!(!oper.verify_password("something").await);
     }
 
+    #[tokio::test]
+    async fn verify_password_without_password() {
+        let oper = OperBlock {
+            password: None,
+            ..make_oper("")
+        };
+        assert!(!oper.verify_password("").await);
+    }
+
     #[tokio::test]
     async fn verify_password_empty_input() {
         let oper = make_oper("secret");
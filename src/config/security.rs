@@ -36,6 +36,10 @@ pub struct SecurityConfig {
     /// trusted network.
     #[serde(default)]
     pub allow_plaintext_sasl_plain: bool,
+    /// Accept `OPER <name> <password>` (default: true). When false, opers
+    /// must authenticate by client certificate or CHALLENGE.
+    #[serde(default = "default_true")]
+    pub allow_password_oper: bool,
 }
 
 impl Default for SecurityConfig {
@@ -48,6 +52,7 @@ impl Default for SecurityConfig {
             rate_limits: RateLimitConfig::default(),
             require_sasl: false,
             allow_plaintext_sasl_plain: false,
+            allow_password_oper: true,
         }
     }
 }
@@ -70,6 +75,28 @@ impl SecurityConfig {
     }
 }
 
+/// Configured Q-line (reserved nickname or channel name).
+///
+/// ```toml
+/// [[qline]]
+/// mask = "*Serv"
+/// reason = "Reserved for network services"
+/// ```
+///
+/// Config Q-lines are reloaded on REHASH and cannot be removed with UNRESV.
+#[derive(Debug, Clone, Deserialize)]
+pub struct QlineBlock {
+    /// Nickname or channel mask (wildcards allowed, e.g. "*Serv" or "#opers*").
+    pub mask: String,
+    /// Reason shown to users who try to use the name.
+    #[serde(default = "default_qline_reason")]
+    pub reason: String,
+}
+
+fn default_qline_reason() -> String {
+    "Reserved".to_string()
+}
+
 fn default_cloak_secret() -> String {
     let secret: String = rand::thread_rng()
         .sample_iter(&Alphanumeric)
@@ -263,6 +290,27 @@ pub struct RateLimitConfig {
     /// Channel join burst allowed per client in 10 seconds (default: 5).
     #[serde(default = "default_join_burst")]
     pub join_burst_per_client: u32,
+    /// JOIN/PART burst allowed per client before channel-hopping limits
+    /// apply (default: 10).
+    #[serde(default = "default_join_part_burst")]
+    pub join_part_burst: u32,
+    /// Sustained JOIN/PART rate per client once the burst is spent
+    /// (default: 12 per minute).
+    #[serde(default = "default_join_part_rate")]
+    pub join_part_rate_per_minute: u32,
+    /// Violations answered by delaying the command before the client is
+    /// locked out of joining (default: 3).
+    #[serde(default = "default_join_part_delay_strikes")]
+    pub join_part_delay_strikes: u32,
+    /// How long a client is refused joins once delays stop working, in
+    /// seconds. Strikes are forgiven after this long without one
+    /// (default: 60).
+    #[serde(default = "default_join_part_lockout")]
+    pub join_part_lockout_secs: u64,
+    /// Violations after which the client is disconnected (default: 10,
+    /// 0 disables).
+    #[serde(default = "default_join_part_disconnect_strikes")]
+    pub join_part_disconnect_strikes: u32,
     /// CTCP messages allowed per client per second (default: 1).
     #[serde(default = "default_ctcp_rate")]
     pub ctcp_rate_per_second: u32,
@@ -278,6 +326,28 @@ pub struct RateLimitConfig {
     /// WHOIS burst allowed per client (default: 3).
     #[serde(default = "default_whois_burst")]
     pub whois_burst_per_client: u32,
+    /// Identical error numerics sent to a connection per window before
+    /// further ones are dropped (default: 5, 0 disables).
+    #[serde(default = "default_error_reply_burst")]
+    pub error_reply_burst: u32,
+    /// Window for `error_reply_burst`, in seconds (default: 10).
+    #[serde(default = "default_error_reply_window")]
+    pub error_reply_window_secs: u64,
+    /// Commands a connection may send before completing registration
+    /// (default: 64, 0 disables).
+    #[serde(default = "default_max_prereg_commands")]
+    pub max_prereg_commands: u32,
+    /// `CAP REQ` lines allowed before registration (default: 16, 0 disables).
+    #[serde(default = "default_max_cap_req")]
+    pub max_cap_req: u32,
+    /// SASL authentication attempts allowed per connection
+    /// (default: 5, 0 disables).
+    #[serde(default = "default_max_sasl_attempts")]
+    pub max_sasl_attempts: u32,
+    /// Total `AUTHENTICATE` payload bytes allowed per connection
+    /// (default: 32768, 0 disables).
+    #[serde(default = "default_max_authenticate_bytes")]
+    pub max_authenticate_bytes: u32,
     /// IP addresses exempt from all rate limiting and connection limits.
     /// These IPs get unlimited connections and no flood protection.
     /// Use sparingly - only for trusted operators/bots.
@@ -305,11 +375,22 @@ impl Default for RateLimitConfig {
             message_rate_per_second: default_message_rate(),
             connection_burst_per_ip: default_connection_burst(),
             join_burst_per_client: default_join_burst(),
+            join_part_burst: default_join_part_burst(),
+            join_part_rate_per_minute: default_join_part_rate(),
+            join_part_delay_strikes: default_join_part_delay_strikes(),
+            join_part_lockout_secs: default_join_part_lockout(),
+            join_part_disconnect_strikes: default_join_part_disconnect_strikes(),
             ctcp_rate_per_second: default_ctcp_rate(),
             ctcp_burst_per_client: default_ctcp_burst(),
             max_connections_per_ip: default_max_connections(),
             whois_rate_per_second: default_whois_rate(),
             whois_burst_per_client: default_whois_burst(),
+            error_reply_burst: default_error_reply_burst(),
+            error_reply_window_secs: default_error_reply_window(),
+            max_prereg_commands: default_max_prereg_commands(),
+            max_cap_req: default_max_cap_req(),
+            max_sasl_attempts: default_max_sasl_attempts(),
+            max_authenticate_bytes: default_max_authenticate_bytes(),
             exempt_ips: Vec::new(),
             s2s_command_rate_per_second: default_s2s_command_rate(),
             s2s_burst_per_peer: default_s2s_burst(),
@@ -326,6 +407,30 @@ fn default_whois_burst() -> u32 {
     3
 }
 
+fn default_error_reply_burst() -> u32 {
+    5
+}
+
+fn default_error_reply_window() -> u64 {
+    10
+}
+
+fn default_max_prereg_commands() -> u32 {
+    64
+}
+
+fn default_max_cap_req() -> u32 {
+    16
+}
+
+fn default_max_sasl_attempts() -> u32 {
+    5
+}
+
+fn default_max_authenticate_bytes() -> u32 {
+    32768
+}
+
 fn default_message_rate() -> u32 {
     2
 }
@@ -338,6 +443,26 @@ fn default_join_burst() -> u32 {
     5
 }
 
+fn default_join_part_burst() -> u32 {
+    10
+}
+
+fn default_join_part_rate() -> u32 {
+    12
+}
+
+fn default_join_part_delay_strikes() -> u32 {
+    3
+}
+
+fn default_join_part_lockout() -> u64 {
+    60
+}
+
+fn default_join_part_disconnect_strikes() -> u32 {
+    10
+}
+
 fn default_ctcp_rate() -> u32 {
     1
 }
@@ -474,6 +599,22 @@ mod tests {
         assert_eq!(config.message_rate_per_second, 2);
     }
 
+    #[test]
+    fn rate_limit_config_default_error_reply_limit() {
+        let config = RateLimitConfig::default();
+        assert_eq!(config.error_reply_burst, 5);
+        assert_eq!(config.error_reply_window_secs, 10);
+    }
+
+    #[test]
+    fn rate_limit_config_default_handshake_limits() {
+        let config = RateLimitConfig::default();
+        assert_eq!(config.max_prereg_commands, 64);
+        assert_eq!(config.max_cap_req, 16);
+        assert_eq!(config.max_sasl_attempts, 5);
+        assert_eq!(config.max_authenticate_bytes, 32768);
+    }
+
     #[test]
     fn rate_limit_config_default_connection_burst() {
         let config = RateLimitConfig::default();
@@ -486,6 +627,16 @@ mod tests {
         assert_eq!(config.join_burst_per_client, 5);
     }
 
+    #[test]
+    fn rate_limit_config_default_join_part_limits() {
+        let config = RateLimitConfig::default();
+        assert_eq!(config.join_part_burst, 10);
+        assert_eq!(config.join_part_rate_per_minute, 12);
+        assert_eq!(config.join_part_delay_strikes, 3);
+        assert_eq!(config.join_part_lockout_secs, 60);
+        assert_eq!(config.join_part_disconnect_strikes, 10);
+    }
+
     #[test]
     fn rate_limit_config_default_ctcp_rate() {
         let config = RateLimitConfig::default();
@@ -0,0 +1,56 @@
+//! External services configuration.
+
+use serde::Deserialize;
+
+/// Services configuration.
+///
+/// ```toml
+/// [services]
+/// external = "services.straylight.net"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ServicesConfig {
+    /// Name of a linked external services package (Atheme, Anope).
+    ///
+    /// When set, the built-in NickServ, ChanServ, HostServ, OperServ and
+    /// BotServ are disabled, messages to them are relayed to the external
+    /// pseudo-clients, and that server may issue `SVSNICK`, `SVSMODE`,
+    /// `SVSHOST` and `SU` over ENCAP.
+    #[serde(default)]
+    pub external: Option<String>,
+}
+
+impl ServicesConfig {
+    /// Whether the built-in services are active.
+    pub fn builtin(&self) -> bool {
+        self.external.is_none()
+    }
+
+    /// Whether `server_name` is the configured external services server.
+    pub fn is_services_server(&self, server_name: &str) -> bool {
+        self.external
+            .as_deref()
+            .is_some_and(|name| name.eq_ignore_ascii_case(server_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn services_config_defaults_to_builtin() {
+        let config: ServicesConfig = toml::from_str("").unwrap();
+        assert!(config.builtin());
+        assert!(!config.is_services_server("services.straylight.net"));
+    }
+
+    #[test]
+    fn external_services_server_matches_case_insensitively() {
+        let config: ServicesConfig =
+            toml::from_str("external = \"services.straylight.net\"").unwrap();
+        assert!(!config.builtin());
+        assert!(config.is_services_server("Services.Straylight.NET"));
+        assert!(!config.is_services_server("hub.straylight.net"));
+    }
+}
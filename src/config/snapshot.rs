@@ -0,0 +1,59 @@
+//! Warm-boot state snapshot configuration.
+
+use serde::Deserialize;
+
+/// State snapshot configuration.
+///
+/// When enabled, volatile state (channels with their modes, topics and
+/// lists, and WHOWAS history) is written to `path` on shutdown and restored
+/// on the next start, so channels do not have to be recreated by activity.
+///
+/// ```toml
+/// [snapshot]
+/// enabled = true
+/// path = "slircd.snapshot"
+/// max_age_secs = 3600
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct SnapshotConfig {
+    /// Whether to write and load snapshots.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Path to the snapshot file.
+    #[serde(default = "default_snapshot_path")]
+    pub path: String,
+    /// Snapshots older than this are ignored on startup (seconds).
+    #[serde(default = "default_snapshot_max_age")]
+    pub max_age_secs: u64,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_snapshot_path(),
+            max_age_secs: default_snapshot_max_age(),
+        }
+    }
+}
+
+fn default_snapshot_path() -> String {
+    "slircd.snapshot".to_string()
+}
+
+fn default_snapshot_max_age() -> u64 {
+    3600
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_config_defaults() {
+        let config: SnapshotConfig = toml::from_str("").unwrap();
+        assert!(!config.enabled);
+        assert_eq!(config.path, "slircd.snapshot");
+        assert_eq!(config.max_age_secs, 3600);
+    }
+}
@@ -0,0 +1,85 @@
+//! Tor listener policy configuration.
+
+use serde::Deserialize;
+
+/// Policy for `[[listen]]` blocks marked `tor = true`.
+///
+/// Tor clients all arrive from the local Tor daemon, so per-IP limits,
+/// DNSBL and reverse DNS say nothing about them. They get a fixed host,
+/// must log in with SASL, and share connection limits across every Tor
+/// listener instead.
+///
+/// ```toml
+/// [tor]
+/// host = "tor.hidden"
+/// require_sasl = true
+/// message_rate_per_second = 1
+/// connection_burst = 5
+/// max_connections = 64
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct TorConfig {
+    /// Host given to every Tor client in place of a cloak.
+    #[serde(default = "default_tor_host")]
+    pub host: String,
+    /// Refuse registration until SASL has succeeded.
+    #[serde(default = "default_true")]
+    pub require_sasl: bool,
+    /// Messages allowed per Tor client per second.
+    #[serde(default = "default_tor_message_rate")]
+    pub message_rate_per_second: u32,
+    /// New connections allowed in a burst across all Tor listeners, refilled
+    /// at one per second.
+    #[serde(default = "default_tor_connection_burst")]
+    pub connection_burst: u32,
+    /// Concurrent connections allowed across all Tor listeners.
+    #[serde(default = "default_tor_max_connections")]
+    pub max_connections: u32,
+}
+
+impl Default for TorConfig {
+    fn default() -> Self {
+        Self {
+            host: default_tor_host(),
+            require_sasl: true,
+            message_rate_per_second: default_tor_message_rate(),
+            connection_burst: default_tor_connection_burst(),
+            max_connections: default_tor_max_connections(),
+        }
+    }
+}
+
+fn default_tor_host() -> String {
+    "tor.hidden".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_tor_message_rate() -> u32 {
+    1
+}
+
+fn default_tor_connection_burst() -> u32 {
+    5
+}
+
+fn default_tor_max_connections() -> u32 {
+    64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tor_config_defaults() {
+        let config: TorConfig = toml::from_str("").unwrap();
+        assert_eq!(config.host, "tor.hidden");
+        assert!(config.require_sasl);
+        assert_eq!(config.message_rate_per_second, 1);
+        assert_eq!(config.connection_burst, 5);
+        assert_eq!(config.max_connections, 64);
+    }
+}
@@ -4,13 +4,22 @@ use serde::Deserialize;
 use std::path::Path;
 use thiserror::Error;
 
+use super::auth::AuthConfig;
+use super::channels::ChannelsConfig;
+use super::class::ClassBlock;
+use super::client_tags::ClientTagsConfig;
 use super::history::HistoryConfig;
+use super::journal::JournalConfig;
 use super::limits::LimitsConfig;
 use super::links::LinkBlock;
 use super::listen::{ListenConfig, S2STlsConfig, TlsConfig, WebSocketConfig};
+use super::metadata::MetadataConfig;
 use super::multiclient::MulticlientConfig;
-use super::oper::{OperBlock, WebircBlock};
-use super::security::SecurityConfig;
+use super::oper::{OperBlock, OperClassBlock, WebircBlock};
+use super::security::{QlineBlock, SecurityConfig};
+use super::services::ServicesConfig;
+use super::snapshot::SnapshotConfig;
+use super::tor::TorConfig;
 
 /// Configuration errors.
 #[derive(Debug, Error)]
@@ -26,15 +35,24 @@ pub enum ConfigError {
 pub struct Config {
     /// Server information.
     pub server: ServerConfig,
-    /// Network listen configuration.
-    pub listen: ListenConfig,
-    /// Optional TLS listen configuration.
+    /// Listeners: a single `[listen]` table or `[[listen]]` blocks.
+    #[serde(deserialize_with = "super::listen::one_or_many")]
+    pub listen: Vec<ListenConfig>,
+    /// Optional TLS certificates, and a TLS listener if `address` is set.
     pub tls: Option<TlsConfig>,
-    /// Optional WebSocket listen configuration.
+    /// Optional WebSocket origins, and a WebSocket listener if `address` is set.
     pub websocket: Option<WebSocketConfig>,
     /// Operator blocks.
     #[serde(default)]
     pub oper: Vec<OperBlock>,
+    /// Operator classes (privileges, hostmask and certificate constraints).
+    #[serde(default)]
+    #[serde(rename = "oper_class")]
+    pub oper_classes: Vec<OperClassBlock>,
+    /// Client connection classes (send-queue limits and policy).
+    #[serde(default)]
+    #[serde(rename = "class")]
+    pub classes: Vec<ClassBlock>,
     /// WEBIRC blocks for trusted gateway clients.
     #[serde(default)]
     pub webirc: Vec<WebircBlock>,
@@ -46,9 +64,22 @@ pub struct Config {
     /// Security configuration (cloaking, rate limiting, anti-abuse).
     #[serde(default)]
     pub security: SecurityConfig,
+    /// Reserved nickname and channel masks (Q-lines).
+    #[serde(default)]
+    #[serde(rename = "qline")]
+    pub qlines: Vec<QlineBlock>,
+    /// External authentication backend for SASL PLAIN and IDENTIFY.
+    #[serde(default)]
+    pub auth: AuthConfig,
     /// Account registration (draft/account-registration) configuration.
     #[serde(default)]
     pub account_registration: AccountRegistrationConfig,
+    /// ChanServ channel registration settings.
+    #[serde(default)]
+    pub chanserv: ChanServConfig,
+    /// External services package (disables the built-in services).
+    #[serde(default)]
+    pub services: ServicesConfig,
     /// Multiclient/bouncer configuration.
     #[serde(default)]
     pub multiclient: MulticlientConfig,
@@ -58,6 +89,24 @@ pub struct Config {
     /// Command output limits (WHO, LIST, NAMES result caps).
     #[serde(default)]
     pub limits: LimitsConfig,
+    /// Channel creation policy and empty-channel reaping.
+    #[serde(default)]
+    pub channels: ChannelsConfig,
+    /// Client-only tag relay policy.
+    #[serde(default)]
+    pub client_tags: ClientTagsConfig,
+    /// Metadata limits and key visibility.
+    #[serde(default)]
+    pub metadata: MetadataConfig,
+    /// Warm-boot state snapshot configuration.
+    #[serde(default)]
+    pub snapshot: SnapshotConfig,
+    /// CRDT journal for crash recovery.
+    #[serde(default)]
+    pub journal: JournalConfig,
+    /// Policy for Tor listeners.
+    #[serde(default)]
+    pub tor: TorConfig,
     /// Link blocks for server peering.
     #[serde(default)]
     #[serde(rename = "link")]
@@ -79,6 +128,45 @@ pub struct S2sConfig {
 }
 
 impl Config {
+    /// Every listener to bind: the `[[listen]]` blocks, then the addresses
+    /// set in `[tls]` and `[websocket]`.
+    pub fn listeners(&self) -> Vec<ListenConfig> {
+        let mut listeners = self.listen.clone();
+        if let Some(tls) = &self.tls
+            && let Some(address) = tls.address
+        {
+            listeners.push(ListenConfig {
+                address,
+                proxy_protocol: tls.proxy_protocol,
+                proxy_trusted: Vec::new(),
+                tls: true,
+                websocket: false,
+                server_only: false,
+                tor: false,
+                compression: false,
+                class: None,
+                password: None,
+            });
+        }
+        if let Some(websocket) = &self.websocket
+            && let Some(address) = websocket.address
+        {
+            listeners.push(ListenConfig {
+                address,
+                proxy_protocol: websocket.proxy_protocol,
+                proxy_trusted: Vec::new(),
+                tls: false,
+                websocket: true,
+                server_only: false,
+                tor: false,
+                compression: false,
+                class: None,
+                password: None,
+            });
+        }
+        listeners
+    }
+
     /// Load configuration from a TOML file.
     ///
     /// Supports `include "path/to/*.toml"` directive for modular configuration.
@@ -175,6 +263,9 @@ pub struct ServerConfig {
     /// Idle timeout configuration for ping/pong keepalive.
     #[serde(default)]
     pub idle_timeouts: IdleTimeoutsConfig,
+    /// Reverse DNS lookups for connecting clients.
+    #[serde(default)]
+    pub rdns: RdnsConfig,
     /// Default user modes applied to new connections (e.g., "+i" for invisible).
     /// Supports: i (invisible), w (wallops), R (registered-only PM), T (no CTCP), B (bot).
     /// Modes o, r, Z, s, S are special and cannot be set via default.
@@ -194,6 +285,22 @@ pub struct ServerConfig {
     /// - `json`: Structured JSON output for production/log aggregation
     #[serde(default)]
     pub log_format: LogFormat,
+
+    /// IRCv3 capabilities to switch off (e.g., `["draft/multiline"]`).
+    ///
+    /// Disabled caps are left out of CAP LS, refused in CAP REQ, and the
+    /// commands that exist only for them answer ERR_UNKNOWNCOMMAND.
+    #[serde(default)]
+    pub disabled_caps: Vec<String>,
+}
+
+impl ServerConfig {
+    /// Whether `cap` is switched off by `disabled_caps`.
+    pub fn is_cap_disabled(&self, cap: &str) -> bool {
+        self.disabled_caps
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case(cap))
+    }
 }
 
 /// IRC casemapping policy.
@@ -251,6 +358,40 @@ pub struct IdleTimeoutsConfig {
     pub registration: u64,
 }
 
+/// Reverse DNS configuration (`[server.rdns]`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct RdnsConfig {
+    /// Resolve client IPs to forward-confirmed hostnames (default: false).
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Milliseconds to wait for the PTR and forward lookups (default: 3000).
+    #[serde(default = "default_rdns_timeout_ms")]
+    pub timeout_ms: u64,
+
+    /// Seconds to cache a result, including failures (default: 3600).
+    #[serde(default = "default_rdns_cache_ttl")]
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for RdnsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_ms: default_rdns_timeout_ms(),
+            cache_ttl_secs: default_rdns_cache_ttl(),
+        }
+    }
+}
+
+fn default_rdns_timeout_ms() -> u64 {
+    3000
+}
+
+fn default_rdns_cache_ttl() -> u64 {
+    3600
+}
+
 impl Default for IdleTimeoutsConfig {
     fn default() -> Self {
         Self {
@@ -278,6 +419,38 @@ fn default_registration_timeout() -> u64 {
 pub struct DatabaseConfig {
     /// Path to SQLite database file.
     pub path: String,
+    /// Encrypt the database file at rest with SQLCipher.
+    #[serde(default)]
+    pub encryption: Option<DatabaseEncryptionConfig>,
+}
+
+/// SQLCipher key source; exactly one of the fields must be set.
+///
+/// ```toml
+/// [database.encryption]
+/// key_command = "vault kv get -field=key secret/slircd/db"
+/// ```
+#[derive(Clone, Default, Deserialize)]
+pub struct DatabaseEncryptionConfig {
+    /// The key itself (least secure: it sits in the config file).
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Environment variable holding the key.
+    #[serde(default)]
+    pub key_env: Option<String>,
+    /// Shell command whose first line of output is the key (KMS, secrets manager).
+    #[serde(default)]
+    pub key_command: Option<String>,
+}
+
+impl std::fmt::Debug for DatabaseEncryptionConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DatabaseEncryptionConfig")
+            .field("key", &self.key.as_ref().map(|_| "<redacted>"))
+            .field("key_env", &self.key_env)
+            .field("key_command", &self.key_command)
+            .finish()
+    }
 }
 
 /// Account registration configuration (draft/account-registration).
@@ -292,6 +465,13 @@ pub struct AccountRegistrationConfig {
     /// Allow custom account names (different from nick).
     #[serde(default = "default_true")]
     pub custom_account_name: bool,
+    /// Mail new accounts a confirmation code. Accounts cannot register
+    /// channels or group nicks until confirmed with NickServ CONFIRM.
+    #[serde(default)]
+    pub verify_email: bool,
+    /// How confirmation and password reset codes are mailed.
+    #[serde(default)]
+    pub mail: MailConfig,
 }
 
 impl Default for AccountRegistrationConfig {
@@ -300,10 +480,45 @@ impl Default for AccountRegistrationConfig {
             before_connect: true,
             email_required: false,
             custom_account_name: true,
+            verify_email: false,
+            mail: MailConfig::default(),
+        }
+    }
+}
+
+/// ChanServ channel registration settings.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ChanServConfig {
+    /// Drop registrations whose channel has not been used for this many
+    /// days. 0 (the default) keeps registrations forever.
+    #[serde(default)]
+    pub expire_days: u32,
+}
+
+/// Outgoing mail for account confirmation and password resets.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MailConfig {
+    /// Sender address of the mails.
+    #[serde(default = "default_mail_from")]
+    pub from: String,
+    /// sendmail-compatible binary to pipe mails to (e.g. `/usr/sbin/sendmail`
+    /// or `msmtp`). Without one, codes cannot be mailed.
+    pub sendmail: Option<String>,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            from: default_mail_from(),
+            sendmail: None,
         }
     }
 }
 
+fn default_mail_from() -> String {
+    "services@localhost".to_string()
+}
+
 pub(super) fn default_true() -> bool {
     true
 }
@@ -321,17 +536,26 @@ pub struct MotdConfig {
 impl MotdConfig {
     /// Load MOTD lines from file, or return default MOTD.
     pub fn load_lines(&self) -> Vec<String> {
-        if let Some(ref path) = self.file {
-            match std::fs::read_to_string(path) {
-                Ok(content) => {
-                    return content.lines().map(|s| s.to_string()).collect();
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to read MOTD file {}: {}", path, e);
-                }
+        self.try_load_lines().unwrap_or_else(|e| {
+            let path = self.file.as_deref().unwrap_or_default();
+            tracing::warn!("Failed to read MOTD file {}: {}", path, e);
+            self.inline_lines()
+        })
+    }
+
+    /// Load MOTD lines, failing if the configured file cannot be read.
+    pub fn try_load_lines(&self) -> std::io::Result<Vec<String>> {
+        match self.file {
+            Some(ref path) => {
+                let content = std::fs::read_to_string(path)?;
+                Ok(content.lines().map(|s| s.to_string()).collect())
             }
+            None => Ok(self.inline_lines()),
         }
+    }
 
+    /// The inline `lines`, or the default MOTD.
+    fn inline_lines(&self) -> Vec<String> {
         if !self.lines.is_empty() {
             return self.lines.clone();
         }
@@ -387,6 +611,11 @@ mod tests {
         assert!(config.custom_account_name);
     }
 
+    #[test]
+    fn chanserv_expiry_is_off_by_default() {
+        assert_eq!(ChanServConfig::default().expire_days, 0);
+    }
+
     #[test]
     fn default_true_helper_returns_true() {
         assert!(default_true());
@@ -2,7 +2,8 @@
 //!
 //! Validates configuration at startup to catch common errors early.
 
-use super::Config;
+use super::{AuthProviderKind, Config};
+use crate::handlers::cap::SUPPORTED_CAPS;
 use std::path::Path;
 use thiserror::Error;
 
@@ -17,14 +18,58 @@ pub enum ValidationError {
     InvalidSid(usize),
     #[error("server.sid must match pattern [0-9][A-Z0-9][A-Z0-9], got '{0}'")]
     InvalidSidFormat(String),
+    #[error("at least one [[listen]] block is required")]
+    NoListeners,
+    #[error("listen address {0} is used more than once")]
+    DuplicateListenAddress(std::net::SocketAddr),
+    #[error("listen {0} sets tls = true but there is no [tls] section")]
+    ListenTlsWithoutCerts(std::net::SocketAddr),
+    #[error("listen {0} cannot combine websocket with tls, server_only or compression")]
+    InvalidWebSocketListen(std::net::SocketAddr),
+    #[error("listen {0} sets a password but is not server_only")]
+    ListenPasswordWithoutServerOnly(std::net::SocketAddr),
+    #[error("listen {0} has an invalid proxy_trusted entry: {1}")]
+    InvalidProxyTrusted(std::net::SocketAddr, String),
+    #[error("listen {0} sets proxy_protocol but no proxy_trusted sources")]
+    ProxyProtocolWithoutTrusted(std::net::SocketAddr),
+    #[error("listen {0} cannot be both tor and server_only")]
+    InvalidTorListen(std::net::SocketAddr),
+    #[error("listen {0} names an unknown class: {1}")]
+    UnknownListenClass(std::net::SocketAddr, String),
+    #[error("class {0} is defined more than once")]
+    DuplicateClass(String),
+    #[error("class {0} needs a sendq above 0")]
+    EmptySendQ(String),
     #[error("tls.cert_path does not exist: {0}")]
     TlsCertNotFound(String),
     #[error("tls.key_path does not exist: {0}")]
     TlsKeyNotFound(String),
+    #[error("tls.sts.port {0} is not a TLS client listener")]
+    StsPortNotTls(u16),
+    #[error("tls.sts.preload requires a duration above 0")]
+    StsPreloadWithoutDuration,
     #[error("database.path parent directory does not exist: {0}")]
     DatabasePathInvalid(String),
     #[error("idle_timeouts.timeout ({0}s) must be greater than idle_timeouts.ping ({1}s)")]
     PingTimeoutTooShort(u64, u64),
+    #[error("server.disabled_caps names an unknown capability: {0}")]
+    UnknownDisabledCap(String),
+    #[error("auth.provider = \"{0}\" requires an [auth.{0}] section")]
+    MissingAuthBackend(&'static str),
+    #[error("auth.ldap needs exactly one of user_dn or search_base")]
+    InvalidLdapUserLookup,
+    #[error("account_registration.verify_email requires account_registration.mail.sendmail")]
+    MissingMailer,
+    #[error("database.encryption needs exactly one of key, key_env or key_command")]
+    InvalidDatabaseKeySource,
+    #[error("services.external names a server without a [[link]] block: {0}")]
+    UnlinkedServicesServer(String),
+    #[error("oper {0} names an unknown oper_class: {1}")]
+    UnknownOperClass(String, String),
+    #[error("oper {0} needs a password, certfp or challenge_key")]
+    OperWithoutCredentials(String),
+    #[error("oper {0} has an invalid challenge_key (expected ed25519:<base64> or rsa:<base64>)")]
+    InvalidChallengeKey(String),
 }
 
 /// Validate a configuration, returning all errors found.
@@ -53,6 +98,60 @@ pub fn validate(config: &Config) -> Result<(), Vec<ValidationError>> {
         }
     }
 
+    // Listeners
+    let listeners = config.listeners();
+    if listeners.is_empty() {
+        errors.push(ValidationError::NoListeners);
+    }
+    for (i, listen) in listeners.iter().enumerate() {
+        if listeners[..i].iter().any(|l| l.address == listen.address) {
+            errors.push(ValidationError::DuplicateListenAddress(listen.address));
+        }
+        if listen.tls && config.tls.is_none() {
+            errors.push(ValidationError::ListenTlsWithoutCerts(listen.address));
+        }
+        if listen.websocket && (listen.tls || listen.server_only || listen.compression) {
+            errors.push(ValidationError::InvalidWebSocketListen(listen.address));
+        }
+        if listen.tor && listen.server_only {
+            errors.push(ValidationError::InvalidTorListen(listen.address));
+        }
+        if let Some(class) = &listen.class
+            && !config.classes.iter().any(|c| &c.name == class)
+        {
+            errors.push(ValidationError::UnknownListenClass(
+                listen.address,
+                class.clone(),
+            ));
+        }
+        if listen.password.is_some() && !listen.server_only {
+            errors.push(ValidationError::ListenPasswordWithoutServerOnly(
+                listen.address,
+            ));
+        }
+        if listen.proxy_protocol && listen.proxy_trusted.is_empty() {
+            errors.push(ValidationError::ProxyProtocolWithoutTrusted(listen.address));
+        }
+        for entry in &listen.proxy_trusted {
+            if super::listen::proxy_source(entry).is_none() {
+                errors.push(ValidationError::InvalidProxyTrusted(
+                    listen.address,
+                    entry.clone(),
+                ));
+            }
+        }
+    }
+
+    // Connection classes
+    for (i, class) in config.classes.iter().enumerate() {
+        if config.classes[..i].iter().any(|c| c.name == class.name) {
+            errors.push(ValidationError::DuplicateClass(class.name.clone()));
+        }
+        if class.sendq == 0 {
+            errors.push(ValidationError::EmptySendQ(class.name.clone()));
+        }
+    }
+
     // TLS validation
     if let Some(ref tls) = config.tls {
         if !Path::new(&tls.cert_path).exists() {
@@ -61,6 +160,18 @@ pub fn validate(config: &Config) -> Result<(), Vec<ValidationError>> {
         if !Path::new(&tls.key_path).exists() {
             errors.push(ValidationError::TlsKeyNotFound(tls.key_path.clone()));
         }
+        if let Some(ref sts) = tls.sts {
+            // Clients told to upgrade must find TLS there
+            let tls_port = listeners
+                .iter()
+                .any(|l| l.tls && !l.server_only && l.address.port() == sts.port);
+            if !tls_port {
+                errors.push(ValidationError::StsPortNotTls(sts.port));
+            }
+            if sts.preload && sts.duration == 0 {
+                errors.push(ValidationError::StsPreloadWithoutDuration);
+            }
+        }
     }
 
     // Database path validation
@@ -72,6 +183,16 @@ pub fn validate(config: &Config) -> Result<(), Vec<ValidationError>> {
         {
             errors.push(ValidationError::DatabasePathInvalid(db.path.clone()));
         }
+        if let Some(encryption) = &db.encryption {
+            let sources = [
+                encryption.key.is_some(),
+                encryption.key_env.is_some(),
+                encryption.key_command.is_some(),
+            ];
+            if sources.iter().filter(|set| **set).count() != 1 {
+                errors.push(ValidationError::InvalidDatabaseKeySource);
+            }
+        }
     }
 
     // Idle timeouts validation
@@ -82,6 +203,68 @@ pub fn validate(config: &Config) -> Result<(), Vec<ValidationError>> {
         ));
     }
 
+    // Disabled capabilities must be ones we offer, so a typo is not silently ignored
+    for cap in &config.server.disabled_caps {
+        if !SUPPORTED_CAPS
+            .iter()
+            .any(|c| c.as_ref().eq_ignore_ascii_case(cap))
+        {
+            errors.push(ValidationError::UnknownDisabledCap(cap.clone()));
+        }
+    }
+
+    // The selected auth provider must be configured
+    match config.auth.provider {
+        AuthProviderKind::Local => {}
+        AuthProviderKind::Ldap => match &config.auth.ldap {
+            None => errors.push(ValidationError::MissingAuthBackend("ldap")),
+            Some(ldap) if ldap.user_dn.is_some() == ldap.search_base.is_some() => {
+                errors.push(ValidationError::InvalidLdapUserLookup);
+            }
+            Some(_) => {}
+        },
+        AuthProviderKind::Oauth2 => {
+            if config.auth.oauth2.is_none() {
+                errors.push(ValidationError::MissingAuthBackend("oauth2"));
+            }
+        }
+    }
+
+    // Confirmation codes must have a way out
+    let registration = &config.account_registration;
+    if registration.verify_email && registration.mail.sendmail.is_none() {
+        errors.push(ValidationError::MissingMailer);
+    }
+
+    // External services connect like any other server
+    if let Some(services) = &config.services.external
+        && !config
+            .links
+            .iter()
+            .any(|link| link.name.eq_ignore_ascii_case(services))
+    {
+        errors.push(ValidationError::UnlinkedServicesServer(services.clone()));
+    }
+
+    for oper in &config.oper {
+        if oper.password.is_none() && oper.certfp.is_none() && oper.challenge_key.is_none() {
+            errors.push(ValidationError::OperWithoutCredentials(oper.name.clone()));
+        }
+        if let Some(key) = &oper.challenge_key
+            && crate::security::challenge::ChallengeKey::parse(key).is_none()
+        {
+            errors.push(ValidationError::InvalidChallengeKey(oper.name.clone()));
+        }
+        if let Some(class) = &oper.class
+            && !config.oper_classes.iter().any(|c| &c.name == class)
+        {
+            errors.push(ValidationError::UnknownOperClass(
+                oper.name.clone(),
+                class.clone(),
+            ));
+        }
+    }
+
     if errors.is_empty() {
         Ok(())
     } else {
@@ -181,6 +364,133 @@ key_path = "/nonexistent/key.pem"
         );
     }
 
+    #[test]
+    fn test_sts_policy_is_checked() {
+        let toml = r#"
+[server]
+name = "test"
+network = "TestNet"
+sid = "00T"
+description = "Test"
+
+[listen]
+address = "127.0.0.1:6667"
+
+[tls]
+address = "127.0.0.1:6697"
+cert_path = "/nonexistent/cert.pem"
+key_path = "/nonexistent/key.pem"
+
+[tls.sts]
+port = 6697
+duration = 0
+preload = true
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let errors = validate(&config).unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| matches!(e, ValidationError::StsPreloadWithoutDuration))
+        );
+        assert!(
+            !errors
+                .iter()
+                .any(|e| matches!(e, ValidationError::StsPortNotTls(_)))
+        );
+
+        let config: Config = toml::from_str(&toml.replace("port = 6697", "port = 6667")).unwrap();
+        let errors = validate(&config).unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| matches!(e, ValidationError::StsPortNotTls(6667)))
+        );
+    }
+
+    #[test]
+    fn test_proxy_protocol_requires_trusted_sources() {
+        let toml = r#"
+[server]
+name = "test"
+network = "TestNet"
+sid = "00T"
+description = "Test"
+
+[[listen]]
+address = "127.0.0.1:6667"
+proxy_protocol = true
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let errors = validate(&config).unwrap_err();
+        assert!(matches!(
+            &errors[..],
+            [ValidationError::ProxyProtocolWithoutTrusted(a)] if a.port() == 6667
+        ));
+
+        let toml = format!("{toml}proxy_trusted = [\"10.0.0.1\"]\n");
+        let config: Config = toml::from_str(&toml).unwrap();
+        assert!(validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_listen_blocks_are_checked() {
+        let toml = r#"
+[server]
+name = "test"
+network = "TestNet"
+sid = "00T"
+description = "Test"
+
+[[listen]]
+address = "127.0.0.1:6667"
+
+[[listen]]
+address = "127.0.0.1:6667"
+websocket = true
+
+[[listen]]
+address = "127.0.0.1:6697"
+tls = true
+
+[[listen]]
+address = "127.0.0.1:7000"
+password = "linkpass"
+proxy_trusted = ["10.0.0.0/8", "not-an-ip"]
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let errors = validate(&config).unwrap_err();
+        assert!(matches!(
+            &errors[..],
+            [
+                ValidationError::DuplicateListenAddress(a),
+                ValidationError::ListenTlsWithoutCerts(b),
+                ValidationError::ListenPasswordWithoutServerOnly(c),
+                ValidationError::InvalidProxyTrusted(_, entry),
+            ] if a.port() == 6667 && b.port() == 6697 && c.port() == 7000 && entry == "not-an-ip"
+        ));
+
+        let toml = toml.replace("password", "server_only = true\ntor = true\npassword");
+        let toml = toml.replace("websocket = true", "websocket = true\nserver_only = true");
+        let config: Config = toml::from_str(&toml).unwrap();
+        let errors = validate(&config).unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| matches!(e, ValidationError::InvalidWebSocketListen(_)))
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|e| matches!(e, ValidationError::InvalidTorListen(a) if a.port() == 7000))
+        );
+        assert!(
+            !errors
+                .iter()
+                .any(|e| matches!(e, ValidationError::ListenPasswordWithoutServerOnly(_)))
+        );
+    }
+
     #[test]
     fn test_ping_timeout_too_short_fails() {
         let toml = r#"
@@ -205,4 +515,182 @@ address = "127.0.0.1:6667"
                 .any(|e| matches!(e, ValidationError::PingTimeoutTooShort(90, 100)))
         );
     }
+
+    #[test]
+    fn test_unknown_disabled_cap_fails() {
+        let toml = r#"
+[server]
+name = "test"
+network = "TestNet"
+sid = "00T"
+description = "Test"
+disabled_caps = ["draft/multiline", "draft/multilnie"]
+
+[listen]
+address = "127.0.0.1:6667"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let errors = validate(&config).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            matches!(&errors[0], ValidationError::UnknownDisabledCap(cap) if cap == "draft/multilnie")
+        );
+    }
+
+    #[test]
+    fn test_auth_provider_requires_its_section() {
+        let toml = format!(
+            "{}\n[auth]\nprovider = \"oauth2\"\n",
+            minimal_valid_config()
+        );
+        let config: Config = toml::from_str(&toml).unwrap();
+        let errors = validate(&config).unwrap_err();
+        assert!(matches!(
+            errors[..],
+            [ValidationError::MissingAuthBackend("oauth2")]
+        ));
+
+        let toml = format!(
+            "{}\n[auth]\nprovider = \"ldap\"\n[auth.ldap]\nurl = \"ldap://localhost\"\n",
+            minimal_valid_config()
+        );
+        let config: Config = toml::from_str(&toml).unwrap();
+        let errors = validate(&config).unwrap_err();
+        assert!(matches!(
+            errors[..],
+            [ValidationError::InvalidLdapUserLookup]
+        ));
+    }
+
+    #[test]
+    fn test_verify_email_requires_mailer() {
+        let toml = format!(
+            "{}\n[account_registration]\nverify_email = true\n",
+            minimal_valid_config()
+        );
+        let config: Config = toml::from_str(&toml).unwrap();
+        let errors = validate(&config).unwrap_err();
+        assert!(matches!(errors[..], [ValidationError::MissingMailer]));
+
+        let toml = format!(
+            "{}\n[account_registration]\nverify_email = true\n[account_registration.mail]\nsendmail = \"/usr/sbin/sendmail\"\n",
+            minimal_valid_config()
+        );
+        let config: Config = toml::from_str(&toml).unwrap();
+        assert!(validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_external_services_require_link_block() {
+        let toml = format!(
+            "{}\n[services]\nexternal = \"services.test\"\n",
+            minimal_valid_config()
+        );
+        let config: Config = toml::from_str(&toml).unwrap();
+        let errors = validate(&config).unwrap_err();
+        assert!(matches!(
+            &errors[..],
+            [ValidationError::UnlinkedServicesServer(name)] if name == "services.test"
+        ));
+
+        let toml = format!(
+            "{}\n[services]\nexternal = \"services.test\"\n\n[[link]]\nname = \"services.test\"\nhostname = \"127.0.0.1\"\nport = 6900\npassword = \"linkpass\"\n",
+            minimal_valid_config()
+        );
+        let config: Config = toml::from_str(&toml).unwrap();
+        assert!(validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_oper_class_must_exist() {
+        let oper = "[[oper]]\nname = \"alice\"\npassword = \"pw\"\nclass = \"helper\"\n";
+        let toml = format!("{}\n{oper}", minimal_valid_config());
+        let config: Config = toml::from_str(&toml).unwrap();
+        let errors = validate(&config).unwrap_err();
+        assert!(matches!(
+            &errors[..],
+            [ValidationError::UnknownOperClass(oper, class)] if oper == "alice" && class == "helper"
+        ));
+
+        let toml = format!(
+            "{}\n{oper}\n[[oper_class]]\nname = \"helper\"\nprivileges = [\"kill\"]\n",
+            minimal_valid_config()
+        );
+        let config: Config = toml::from_str(&toml).unwrap();
+        assert!(validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_listen_class_must_exist() {
+        let toml = r#"
+[server]
+name = "test.server"
+network = "TestNet"
+sid = "00T"
+description = "Test"
+
+[[listen]]
+address = "127.0.0.1:6667"
+class = "bots"
+
+[[class]]
+name = "default"
+
+[[class]]
+name = "default"
+sendq = 0
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let errors = validate(&config).unwrap_err();
+        assert!(matches!(
+            &errors[..],
+            [
+                ValidationError::UnknownListenClass(addr, class),
+                ValidationError::DuplicateClass(dup),
+                ValidationError::EmptySendQ(empty),
+            ] if addr.port() == 6667 && class == "bots" && dup == "default" && empty == "default"
+        ));
+
+        let toml = toml.replace("name = \"default\"\nsendq = 0", "name = \"bots\"");
+        let config: Config = toml::from_str(&toml).unwrap();
+        assert!(validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_oper_needs_usable_credentials() {
+        let toml = format!(
+            "{}\n[[oper]]\nname = \"nokey\"\n\n[[oper]]\nname = \"badkey\"\nchallenge_key = \"ed25519:AAAA\"\n",
+            minimal_valid_config()
+        );
+        let config: Config = toml::from_str(&toml).unwrap();
+        let errors = validate(&config).unwrap_err();
+        assert!(matches!(
+            &errors[..],
+            [
+                ValidationError::OperWithoutCredentials(a),
+                ValidationError::InvalidChallengeKey(b),
+            ] if a == "nokey" && b == "badkey"
+        ));
+    }
+
+    #[test]
+    fn test_database_encryption_needs_one_key_source() {
+        let toml = format!(
+            "{}\n[database]\npath = \"slircd.db\"\n[database.encryption]\nkey = \"a\"\nkey_env = \"DB_KEY\"\n",
+            minimal_valid_config()
+        );
+        let config: Config = toml::from_str(&toml).unwrap();
+        let errors = validate(&config).unwrap_err();
+        assert!(matches!(
+            errors[..],
+            [ValidationError::InvalidDatabaseKeySource]
+        ));
+
+        let toml = format!(
+            "{}\n[database]\npath = \"slircd.db\"\n[database.encryption]\nkey_env = \"DB_KEY\"\n",
+            minimal_valid_config()
+        );
+        let config: Config = toml::from_str(&toml).unwrap();
+        assert!(validate(&config).is_ok());
+    }
 }
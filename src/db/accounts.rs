@@ -3,22 +3,155 @@
 //! Handles account registration, authentication, and nickname management.
 
 use super::DbError;
+use super::auth::ExternalAuth;
+use super::channels::{ChannelRepository, OrphanedChannel};
 use argon2::password_hash::rand_core::OsRng;
 use rand::RngCore;
+use ring::{digest, hmac};
 use sqlx::SqlitePool;
 use std::num::NonZeroU32;
+use subtle::ConstantTimeEq;
+use tracing::{debug, info, warn};
 
 /// Default iteration count for SCRAM-SHA-256 (RFC 7677 recommends >= 4096).
 const SCRAM_ITERATIONS: u32 = 4096;
 
 /// SCRAM verifiers for SASL SCRAM-SHA-256 authentication.
+///
+/// Only the salt, iteration count and the RFC 5802 `StoredKey` and
+/// `ServerKey` are kept; none of them lets a client authenticate.
 #[derive(Debug, Clone)]
 pub struct ScramVerifiers {
     pub salt: Vec<u8>,
     pub iterations: u32,
-    pub hashed_password: Vec<u8>,
+    pub stored_key: Vec<u8>,
+    pub server_key: Vec<u8>,
+}
+
+impl ScramVerifiers {
+    /// Derive the verifiers from a salted password, `Hi(password, salt, i)`.
+    fn from_salted_password(salt: Vec<u8>, iterations: u32, salted_password: &[u8]) -> Self {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, salted_password);
+        let client_key = hmac::sign(&key, b"Client Key");
+        let stored_key = digest::digest(&digest::SHA256, client_key.as_ref());
+        let server_key = hmac::sign(&key, b"Server Key");
+        Self {
+            salt,
+            iterations,
+            stored_key: stored_key.as_ref().to_vec(),
+            server_key: server_key.as_ref().to_vec(),
+        }
+    }
+}
+
+/// Recursive CTE binding one account ID and yielding `acting(id)`: the
+/// account and every group it belongs to, directly or transitively.
+pub(super) const ACTING_IDS_CTE: &str = r#"
+    WITH RECURSIVE acting(id) AS (
+        SELECT ?
+        UNION
+        SELECT m.group_id FROM account_group_members m JOIN acting a ON m.account_id = a.id
+    )"#;
+
+/// Salt, iterations, stored key, server key and legacy salted password.
+type ScramRow = (
+    Option<Vec<u8>>,
+    Option<i32>,
+    Option<Vec<u8>>,
+    Option<Vec<u8>>,
+    Option<Vec<u8>>,
+);
+
+/// How strictly an account's nicknames are protected (NickServ SET ENFORCE).
+///
+/// Stored in the `enforce` column; `Off` and `On` keep the 0/1 the column
+/// held when it was a plain flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EnforceLevel {
+    /// No protection.
+    #[default]
+    Off,
+    /// Renamed after the normal delay.
+    On,
+    /// Renamed after a short delay.
+    Quick,
+    /// Renamed at once.
+    Strict,
 }
 
+impl EnforceLevel {
+    /// Parse a SET ENFORCE value.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_uppercase().as_str() {
+            "OFF" | "FALSE" | "0" | "NO" => Some(Self::Off),
+            "ON" | "TRUE" | "1" | "YES" => Some(Self::On),
+            "QUICK" => Some(Self::Quick),
+            "STRICT" => Some(Self::Strict),
+            _ => None,
+        }
+    }
+
+    /// Name shown to users.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "OFF",
+            Self::On => "ON",
+            Self::Quick => "QUICK",
+            Self::Strict => "STRICT",
+        }
+    }
+
+    fn from_db(value: i64) -> Self {
+        match value {
+            1 => Self::On,
+            2 => Self::Quick,
+            3 => Self::Strict,
+            _ => Self::Off,
+        }
+    }
+
+    fn to_db(self) -> i64 {
+        match self {
+            Self::Off => 0,
+            Self::On => 1,
+            Self::Quick => 2,
+            Self::Strict => 3,
+        }
+    }
+}
+
+/// Purpose of a one-time code mailed to an account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeKind {
+    /// Confirms the email address given at registration (NickServ CONFIRM).
+    Confirm,
+    /// Authorizes a password reset (NickServ SETPASS).
+    Reset,
+}
+
+impl CodeKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Confirm => "confirm",
+            Self::Reset => "reset",
+        }
+    }
+
+    /// Seconds a code stays valid.
+    pub fn lifetime_secs(self) -> i64 {
+        match self {
+            Self::Confirm => 7 * 86400,
+            Self::Reset => 3600,
+        }
+    }
+}
+
+/// Characters of mailed codes, without look-alikes (0/O, 1/I).
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// Length of mailed codes (50 bits).
+const CODE_LEN: usize = 10;
+
 /// A registered NickServ account.
 #[derive(Debug, Clone)]
 pub struct Account {
@@ -27,7 +160,7 @@ pub struct Account {
     pub email: Option<String>,
     pub registered_at: i64,
     pub last_seen_at: i64,
-    pub enforce: bool,
+    pub enforce: EnforceLevel,
     pub hide_email: bool,
     pub metadata: std::collections::HashMap<String, String>,
 }
@@ -35,12 +168,14 @@ pub struct Account {
 /// Repository for account operations.
 pub struct AccountRepository<'a> {
     pool: &'a SqlitePool,
+    auth: Option<&'a ExternalAuth>,
 }
 
 impl<'a> AccountRepository<'a> {
-    /// Create a new account repository.
-    pub fn new(pool: &'a SqlitePool) -> Self {
-        Self { pool }
+    /// Create a new account repository, asking `auth` (if any) before local
+    /// accounts on login.
+    pub fn new(pool: &'a SqlitePool, auth: Option<&'a ExternalAuth>) -> Self {
+        Self { pool, auth }
     }
 
     /// Register a new account with the given nickname and password.
@@ -70,8 +205,9 @@ impl<'a> AccountRepository<'a> {
         let result = sqlx::query(
             r#"
             INSERT INTO accounts (name, password_hash, email, registered_at, last_seen_at,
-                                  scram_salt, scram_iterations, scram_hashed_password)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                                  scram_salt, scram_iterations, scram_stored_key,
+                                  scram_server_key)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(name)
@@ -81,7 +217,8 @@ impl<'a> AccountRepository<'a> {
         .bind(now)
         .bind(&scram_verifiers.salt)
         .bind(scram_verifiers.iterations as i32)
-        .bind(&scram_verifiers.hashed_password)
+        .bind(&scram_verifiers.stored_key)
+        .bind(&scram_verifiers.server_key)
         .execute(&mut *tx)
         .await
         .map_err(|e| {
@@ -126,12 +263,86 @@ impl<'a> AccountRepository<'a> {
             email: email.map(String::from),
             registered_at: now,
             last_seen_at: now,
-            enforce: false,
+            enforce: EnforceLevel::Off,
             hide_email: true,
             metadata: std::collections::HashMap::new(),
         })
     }
 
+    /// Log in to an account, asking the external auth provider (if one is
+    /// configured) before the local accounts table.
+    ///
+    /// An account the provider accepts is created locally on first login,
+    /// with a random password, so it has nicknames and settings like any
+    /// other. If the provider rejects the credentials or cannot be reached,
+    /// they are checked against the local accounts table as by [`identify`].
+    ///
+    /// [`identify`]: Self::identify
+    pub async fn authenticate(&self, name: &str, password: &str) -> Result<Account, DbError> {
+        if let Some(auth) = self.auth {
+            let provider = auth.provider.name();
+            match auth.provider.verify(name, password).await {
+                Ok(Some(account)) => match self.external_account(auth, &account).await {
+                    Err(DbError::ExternalAccountConflict(_)) => {
+                        crate::metrics::inc_auth_provider(provider, "conflict");
+                        warn!(provider, account = %account, "External login matches a local account; not linking");
+                    }
+                    result => {
+                        crate::metrics::inc_auth_provider(provider, "accepted");
+                        return result;
+                    }
+                },
+                Ok(None) => {
+                    crate::metrics::inc_auth_provider(provider, "rejected");
+                    debug!(provider, account = %name, "External auth rejected; trying local accounts");
+                }
+                Err(e) => {
+                    crate::metrics::inc_auth_provider(provider, "error");
+                    warn!(provider, account = %name, error = %e, "External auth unavailable; trying local accounts");
+                }
+            }
+        }
+        self.identify(name, password).await
+    }
+
+    /// The local account for a login accepted by the external provider,
+    /// created (and marked as the provider's) if it does not exist yet.
+    ///
+    /// An existing account is only used if the same provider created it, or
+    /// linking local accounts is enabled; otherwise anyone the provider knows
+    /// by that name would take over the local account.
+    async fn external_account(&self, auth: &ExternalAuth, name: &str) -> Result<Account, DbError> {
+        let provider = auth.provider.name();
+        if let Some(account) = self.find_by_name(name).await? {
+            let source: Option<String> =
+                sqlx::query_scalar("SELECT auth_source FROM accounts WHERE id = ?")
+                    .bind(account.id)
+                    .fetch_one(self.pool)
+                    .await?;
+            if source.as_deref() != Some(provider) && !auth.link_local_accounts {
+                return Err(DbError::ExternalAccountConflict(account.name));
+            }
+            sqlx::query("UPDATE accounts SET last_seen_at = ? WHERE id = ?")
+                .bind(chrono::Utc::now().timestamp())
+                .bind(account.id)
+                .execute(self.pool)
+                .await?;
+            return Ok(account);
+        }
+
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        let password: String = secret.iter().map(|b| format!("{b:02x}")).collect();
+        info!(account = %name, provider, "Creating local account for externally authenticated user");
+        let account = self.register(name, &password, None).await?;
+        sqlx::query("UPDATE accounts SET auth_source = ? WHERE id = ?")
+            .bind(provider)
+            .bind(account.id)
+            .execute(self.pool)
+            .await?;
+        Ok(account)
+    }
+
     /// Verify password and return account if valid.
     ///
     /// This function uses constant-time behavior to prevent timing oracle attacks:
@@ -139,7 +350,7 @@ impl<'a> AccountRepository<'a> {
     ///   to make the response time indistinguishable from invalid password attempts.
     pub async fn identify(&self, name: &str, password: &str) -> Result<Account, DbError> {
         // First try to find by account name
-        let row = sqlx::query_as::<_, (i64, String, String, Option<String>, i64, i64, bool, bool)>(
+        let row = sqlx::query_as::<_, (i64, String, String, Option<String>, i64, i64, i64, bool)>(
             r#"
             SELECT id, name, password_hash, email, registered_at, last_seen_at, enforce, hide_email
             FROM accounts
@@ -167,7 +378,7 @@ impl<'a> AccountRepository<'a> {
 
                 match account_id {
                     Some(id) => {
-                        sqlx::query_as::<_, (i64, String, String, Option<String>, i64, i64, bool, bool)>(
+                        sqlx::query_as::<_, (i64, String, String, Option<String>, i64, i64, i64, bool)>(
                             r#"
                             SELECT id, name, password_hash, email, registered_at, last_seen_at, enforce, hide_email
                             FROM accounts
@@ -219,7 +430,7 @@ impl<'a> AccountRepository<'a> {
             email,
             registered_at,
             last_seen_at: now,
-            enforce,
+            enforce: EnforceLevel::from_db(enforce),
             hide_email,
             metadata,
         })
@@ -227,7 +438,7 @@ impl<'a> AccountRepository<'a> {
 
     /// Find account by name.
     pub async fn find_by_name(&self, name: &str) -> Result<Option<Account>, DbError> {
-        let row = sqlx::query_as::<_, (i64, String, Option<String>, i64, i64, bool, bool)>(
+        let row = sqlx::query_as::<_, (i64, String, Option<String>, i64, i64, i64, bool)>(
             r#"
             SELECT id, name, email, registered_at, last_seen_at, enforce, hide_email
             FROM accounts
@@ -248,7 +459,7 @@ impl<'a> AccountRepository<'a> {
                 email,
                 registered_at,
                 last_seen_at,
-                enforce,
+                enforce: EnforceLevel::from_db(enforce),
                 hide_email,
                 metadata,
             }))
@@ -277,7 +488,7 @@ impl<'a> AccountRepository<'a> {
 
     /// Find account by ID.
     pub async fn find_by_id(&self, id: i64) -> Result<Option<Account>, DbError> {
-        let row = sqlx::query_as::<_, (i64, String, Option<String>, i64, i64, bool, bool)>(
+        let row = sqlx::query_as::<_, (i64, String, Option<String>, i64, i64, i64, bool)>(
             r#"
             SELECT id, name, email, registered_at, last_seen_at, enforce, hide_email
             FROM accounts
@@ -298,7 +509,7 @@ impl<'a> AccountRepository<'a> {
                 email,
                 registered_at,
                 last_seen_at,
-                enforce,
+                enforce: EnforceLevel::from_db(enforce),
                 hide_email,
                 metadata,
             }))
@@ -322,6 +533,97 @@ impl<'a> AccountRepository<'a> {
         Ok(rows)
     }
 
+    /// IDs an account acts as: its own and those of every group it belongs
+    /// to, directly or through other groups.
+    pub async fn acting_ids(&self, account_id: i64) -> Result<Vec<i64>, DbError> {
+        let ids = sqlx::query_scalar::<_, i64>(&format!("{ACTING_IDS_CTE} SELECT id FROM acting"))
+            .bind(account_id)
+            .fetch_all(self.pool)
+            .await?;
+
+        Ok(ids)
+    }
+
+    /// Make `account_id` a member of the group account `group_id`.
+    ///
+    /// Returns `false` if it already was. Fails with `GroupCycle` if the
+    /// group is itself (transitively) a member of the account.
+    pub async fn add_group_member(
+        &self,
+        group_id: i64,
+        account_id: i64,
+        added_by: &str,
+    ) -> Result<bool, DbError> {
+        if self.acting_ids(group_id).await?.contains(&account_id) {
+            return Err(DbError::GroupCycle);
+        }
+
+        let result = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO account_group_members (group_id, account_id, added_by, added_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(group_id)
+        .bind(account_id)
+        .bind(added_by)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Remove `account_id` from the group account `group_id`.
+    pub async fn remove_group_member(
+        &self,
+        group_id: i64,
+        account_id: i64,
+    ) -> Result<bool, DbError> {
+        let result =
+            sqlx::query("DELETE FROM account_group_members WHERE group_id = ? AND account_id = ?")
+                .bind(group_id)
+                .bind(account_id)
+                .execute(self.pool)
+                .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Names of the direct members of a group account.
+    pub async fn get_group_members(&self, group_id: i64) -> Result<Vec<String>, DbError> {
+        let rows = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT a.name FROM account_group_members m
+            JOIN accounts a ON a.id = m.account_id
+            WHERE m.group_id = ?
+            ORDER BY a.name
+            "#,
+        )
+        .bind(group_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Names of the groups an account is a direct member of.
+    pub async fn get_groups(&self, account_id: i64) -> Result<Vec<String>, DbError> {
+        let rows = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT a.name FROM account_group_members m
+            JOIN accounts a ON a.id = m.group_id
+            WHERE m.account_id = ?
+            ORDER BY a.name
+            "#,
+        )
+        .bind(account_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
     /// Get all registered nicknames across all accounts.
     pub async fn get_all_registered_nicknames(&self) -> Result<Vec<String>, DbError> {
         let rows = sqlx::query_scalar::<_, String>(
@@ -351,9 +653,10 @@ impl<'a> AccountRepository<'a> {
                     .await?;
             }
             "enforce" => {
-                let enforce = matches!(value.to_lowercase().as_str(), "on" | "true" | "1" | "yes");
+                let level = EnforceLevel::parse(value)
+                    .ok_or_else(|| DbError::InvalidValue("ON, OFF, QUICK or STRICT".to_string()))?;
                 sqlx::query("UPDATE accounts SET enforce = ? WHERE id = ?")
-                    .bind(enforce)
+                    .bind(level.to_db())
                     .bind(account_id)
                     .execute(self.pool)
                     .await?;
@@ -376,13 +679,16 @@ impl<'a> AccountRepository<'a> {
                        password_hash = ?,
                        scram_salt = ?,
                        scram_iterations = ?,
-                       scram_hashed_password = ?
+                       scram_stored_key = ?,
+                       scram_server_key = ?,
+                       scram_hashed_password = NULL
                        WHERE id = ?"#,
                 )
                 .bind(password_hash)
                 .bind(&scram_verifiers.salt)
                 .bind(scram_verifiers.iterations as i32)
-                .bind(&scram_verifiers.hashed_password)
+                .bind(&scram_verifiers.stored_key)
+                .bind(&scram_verifiers.server_key)
                 .bind(account_id)
                 .execute(self.pool)
                 .await?;
@@ -394,12 +700,105 @@ impl<'a> AccountRepository<'a> {
         Ok(())
     }
 
+    /// Whether the account's email address is confirmed. Accounts
+    /// registered while verification was off count as confirmed.
+    pub async fn is_verified(&self, account_id: i64) -> Result<bool, DbError> {
+        let verified = sqlx::query_scalar::<_, bool>("SELECT verified FROM accounts WHERE id = ?")
+            .bind(account_id)
+            .fetch_optional(self.pool)
+            .await?;
+        Ok(verified.unwrap_or(false))
+    }
+
+    /// Mark the account's email address as confirmed or not.
+    pub async fn set_verified(&self, account_id: i64, verified: bool) -> Result<(), DbError> {
+        sqlx::query("UPDATE accounts SET verified = ? WHERE id = ?")
+            .bind(verified)
+            .bind(account_id)
+            .execute(self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Generate and store a code for the account, replacing any earlier
+    /// code of the same kind.
+    pub async fn issue_code(&self, account_id: i64, kind: CodeKind) -> Result<String, DbError> {
+        let mut bytes = [0u8; CODE_LEN];
+        OsRng.fill_bytes(&mut bytes);
+        let code: String = bytes
+            .iter()
+            .map(|b| CODE_ALPHABET[*b as usize % CODE_ALPHABET.len()] as char)
+            .collect();
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO account_codes (account_id, kind, code, created_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(account_id)
+        .bind(kind.as_str())
+        .bind(&code)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(self.pool)
+        .await?;
+
+        Ok(code)
+    }
+
+    /// Check a code and use it up if it matches.
+    ///
+    /// Returns false if the account has no such code, it does not match, or
+    /// it has expired. Codes are compared case-insensitively.
+    pub async fn redeem_code(
+        &self,
+        account_id: i64,
+        kind: CodeKind,
+        code: &str,
+    ) -> Result<bool, DbError> {
+        let row = sqlx::query_as::<_, (String, i64)>(
+            "SELECT code, created_at FROM account_codes WHERE account_id = ? AND kind = ?",
+        )
+        .bind(account_id)
+        .bind(kind.as_str())
+        .fetch_optional(self.pool)
+        .await?;
+
+        let Some((stored, created_at)) = row else {
+            return Ok(false);
+        };
+        let given = code.to_ascii_uppercase();
+        let matches: bool = stored.as_bytes().ct_eq(given.as_bytes()).into();
+        let expired = chrono::Utc::now().timestamp() - created_at > kind.lifetime_secs();
+        if !matches || expired {
+            return Ok(false);
+        }
+
+        sqlx::query("DELETE FROM account_codes WHERE account_id = ? AND kind = ?")
+            .bind(account_id)
+            .bind(kind.as_str())
+            .execute(self.pool)
+            .await?;
+        Ok(true)
+    }
+
     /// Delete an account and all associated nicknames.
     /// Requires password verification for security.
-    pub async fn drop_account(&self, name: &str, password: &str) -> Result<(), DbError> {
+    ///
+    /// Channels the account founded go to their successor first; see
+    /// [`ChannelRepository::transfer_founded_channels`].
+    pub async fn drop_account(
+        &self,
+        name: &str,
+        password: &str,
+    ) -> Result<Vec<OrphanedChannel>, DbError> {
         // First verify the password (this also confirms the account exists)
         let account = self.identify(name, password).await?;
 
+        let orphaned = ChannelRepository::new(self.pool)
+            .transfer_founded_channels(account.id)
+            .await?;
+
         // Delete all nicknames linked to this account
         sqlx::query("DELETE FROM nicknames WHERE account_id = ?")
             .bind(account.id)
@@ -412,7 +811,7 @@ impl<'a> AccountRepository<'a> {
             .execute(self.pool)
             .await?;
 
-        Ok(())
+        Ok(orphaned)
     }
 
     /// Link a nickname to an existing account (GROUP).
@@ -505,7 +904,7 @@ impl<'a> AccountRepository<'a> {
     /// Returns None if no account has this certificate registered.
     /// Certificate fingerprints are SHA-256 hashes in hex format.
     pub async fn find_by_certfp(&self, certfp: &str) -> Result<Option<Account>, DbError> {
-        let row = sqlx::query_as::<_, (i64, String, Option<String>, i64, i64, bool, bool)>(
+        let row = sqlx::query_as::<_, (i64, String, Option<String>, i64, i64, i64, bool)>(
             r#"
             SELECT id, name, email, registered_at, last_seen_at, enforce, hide_email
             FROM accounts
@@ -526,7 +925,7 @@ impl<'a> AccountRepository<'a> {
                 email,
                 registered_at,
                 last_seen_at,
-                enforce,
+                enforce: EnforceLevel::from_db(enforce),
                 hide_email,
                 metadata,
             }))
@@ -562,61 +961,44 @@ impl<'a> AccountRepository<'a> {
     /// Get SCRAM verifiers for an account by name (for SASL SCRAM-SHA-256).
     ///
     /// Returns `None` if the account doesn't exist or has no SCRAM verifiers.
+    /// Accounts stored before the keys were kept have them derived from
+    /// their salted password.
     pub async fn get_scram_verifiers(&self, name: &str) -> Result<Option<ScramVerifiers>, DbError> {
-        // First try by account name
-        let row = sqlx::query_as::<_, (Option<Vec<u8>>, Option<i32>, Option<Vec<u8>>)>(
+        let row = sqlx::query_as::<_, ScramRow>(
             r#"
-            SELECT scram_salt, scram_iterations, scram_hashed_password
+            SELECT scram_salt, scram_iterations, scram_stored_key, scram_server_key,
+                   scram_hashed_password
             FROM accounts
             WHERE name = ? COLLATE NOCASE
+               OR id = (SELECT account_id FROM nicknames WHERE name = ? COLLATE NOCASE)
+            ORDER BY name = ? COLLATE NOCASE DESC
+            LIMIT 1
             "#,
         )
         .bind(name)
+        .bind(name)
+        .bind(name)
         .fetch_optional(self.pool)
         .await?;
 
-        // If not found by account name, try nickname
-        let row = match row {
-            Some(r) => Some(r),
-            None => {
-                let account_id = sqlx::query_scalar::<_, i64>(
-                    r#"
-                    SELECT account_id FROM nicknames
-                    WHERE name = ? COLLATE NOCASE
-                    "#,
-                )
-                .bind(name)
-                .fetch_optional(self.pool)
-                .await?;
-
-                match account_id {
-                    Some(id) => {
-                        sqlx::query_as::<_, (Option<Vec<u8>>, Option<i32>, Option<Vec<u8>>)>(
-                            r#"
-                            SELECT scram_salt, scram_iterations, scram_hashed_password
-                            FROM accounts
-                            WHERE id = ?
-                            "#,
-                        )
-                        .bind(id)
-                        .fetch_optional(self.pool)
-                        .await?
-                    }
-                    None => None,
-                }
-            }
+        let Some((Some(salt), Some(iterations), stored_key, server_key, hashed_password)) = row
+        else {
+            return Ok(None);
         };
-
-        // Extract and validate SCRAM verifiers
-        match row {
-            Some((Some(salt), Some(iterations), Some(hashed_password))) => {
-                Ok(Some(ScramVerifiers {
-                    salt,
-                    iterations: iterations as u32,
-                    hashed_password,
-                }))
-            }
-            _ => Ok(None), // Account doesn't exist or has no SCRAM verifiers
+        let iterations = iterations as u32;
+        match (stored_key, server_key, hashed_password) {
+            (Some(stored_key), Some(server_key), _) => Ok(Some(ScramVerifiers {
+                salt,
+                iterations,
+                stored_key,
+                server_key,
+            })),
+            (_, _, Some(hashed_password)) => Ok(Some(ScramVerifiers::from_salted_password(
+                salt,
+                iterations,
+                &hashed_password,
+            ))),
+            _ => Ok(None),
         }
     }
 
@@ -667,7 +1049,7 @@ impl<'a> AccountRepository<'a> {
 /// Compute SCRAM-SHA-256 verifiers for a password in a blocking task.
 ///
 /// This generates a random salt and uses PBKDF2-SHA-256 to derive the
-/// hashed password. The result can be stored and used for SASL SCRAM auth.
+/// salted password, from which the stored verifiers are computed.
 /// Runs in a blocking task to prevent executor stalls from CPU-intensive work.
 async fn compute_scram_verifiers(password: &str) -> ScramVerifiers {
     let password = password.to_string();
@@ -678,13 +1060,8 @@ async fn compute_scram_verifiers(password: &str) -> ScramVerifiers {
 
         // SAFETY: SCRAM_ITERATIONS is const 4096, always > 0
         let iterations = NonZeroU32::new(SCRAM_ITERATIONS).unwrap();
-        let hashed_password = scram::hash_password(&password, iterations, &salt).to_vec();
-
-        ScramVerifiers {
-            salt,
-            iterations: SCRAM_ITERATIONS,
-            hashed_password,
-        }
+        let salted_password = scram::hash_password(&password, iterations, &salt);
+        ScramVerifiers::from_salted_password(salt, SCRAM_ITERATIONS, &salted_password)
     })
     .await
     .expect("spawn_blocking should not be cancelled")
@@ -702,8 +1079,8 @@ async fn dummy_password_verify(password: &str) {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use argon2::password_hash::PasswordHash;
     use crate::security::password::{hash_password, verify_password};
+    use argon2::password_hash::PasswordHash;
 
     #[tokio::test]
     async fn test_hash_password_produces_valid_argon2_hash() {
@@ -825,4 +1202,310 @@ mod tests {
         dummy_password_verify("").await;
         dummy_password_verify(&"x".repeat(100)).await;
     }
+
+    /// Accepts "sso-pass" for any account; any other password is rejected,
+    /// or an error when `down`.
+    struct StubProvider {
+        down: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl super::super::auth::AuthProvider for StubProvider {
+        fn name(&self) -> &'static str {
+            "stub"
+        }
+
+        async fn verify(
+            &self,
+            account: &str,
+            password: &str,
+        ) -> Result<Option<String>, super::super::auth::AuthError> {
+            if self.down {
+                return Err(super::super::auth::AuthError::Timeout);
+            }
+            Ok((password == "sso-pass").then(|| account.to_string()))
+        }
+    }
+
+    async fn db_with_provider(down: bool) -> crate::db::Database {
+        crate::db::Database::new(":memory:")
+            .await
+            .unwrap()
+            .with_auth_provider(Some(ExternalAuth::new(
+                std::sync::Arc::new(StubProvider { down }),
+                false,
+            )))
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_creates_account_on_first_external_login() {
+        let db = db_with_provider(false).await;
+        assert!(db.accounts().find_by_name("alice").await.unwrap().is_none());
+
+        let account = db
+            .accounts()
+            .authenticate("alice", "sso-pass")
+            .await
+            .unwrap();
+        assert_eq!(account.name, "alice");
+        let again = db
+            .accounts()
+            .authenticate("alice", "sso-pass")
+            .await
+            .unwrap();
+        assert_eq!(again.id, account.id);
+
+        // The local password is random, so only the provider can log in
+        assert!(matches!(
+            db.accounts().authenticate("alice", "guess").await,
+            Err(DbError::InvalidPassword)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_falls_back_to_local_accounts() {
+        for down in [false, true] {
+            let db = db_with_provider(down).await;
+            db.accounts()
+                .register("bob", "localpw", None)
+                .await
+                .unwrap();
+            let account = db.accounts().authenticate("bob", "localpw").await.unwrap();
+            assert_eq!(account.name, "bob");
+            assert!(db.accounts().authenticate("bob", "wrong").await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_external_login_does_not_take_over_local_account() {
+        let db = db_with_provider(false).await;
+        let local = db
+            .accounts()
+            .register("carol", "localpw", None)
+            .await
+            .unwrap();
+
+        // The provider accepts "carol", but the account is not the provider's
+        assert!(matches!(
+            db.accounts().authenticate("carol", "sso-pass").await,
+            Err(DbError::InvalidPassword)
+        ));
+        let account = db
+            .accounts()
+            .authenticate("carol", "localpw")
+            .await
+            .unwrap();
+        assert_eq!(account.id, local.id);
+
+        // Linking is an explicit opt-in
+        let auth = ExternalAuth::new(std::sync::Arc::new(StubProvider { down: false }), true);
+        let db = db.with_auth_provider(Some(auth));
+        let account = db
+            .accounts()
+            .authenticate("carol", "sso-pass")
+            .await
+            .unwrap();
+        assert_eq!(account.id, local.id);
+    }
+
+    #[tokio::test]
+    async fn test_codes_are_single_use_and_kind_specific() {
+        let db = crate::db::Database::new(":memory:").await.unwrap();
+        let account = db
+            .accounts()
+            .register("dave", "pw", Some("dave@example.com"))
+            .await
+            .unwrap();
+        assert!(db.accounts().is_verified(account.id).await.unwrap());
+        db.accounts().set_verified(account.id, false).await.unwrap();
+        assert!(!db.accounts().is_verified(account.id).await.unwrap());
+
+        let repo = db.accounts();
+        let code = repo
+            .issue_code(account.id, CodeKind::Confirm)
+            .await
+            .unwrap();
+        assert_eq!(code.len(), CODE_LEN);
+        assert!(
+            !repo
+                .redeem_code(account.id, CodeKind::Reset, &code)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !repo
+                .redeem_code(account.id, CodeKind::Confirm, "WRONG")
+                .await
+                .unwrap()
+        );
+        let lower = code.to_lowercase();
+        assert!(
+            repo.redeem_code(account.id, CodeKind::Confirm, &lower)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !repo
+                .redeem_code(account.id, CodeKind::Confirm, &code)
+                .await
+                .unwrap()
+        );
+
+        // A new code replaces the old one
+        let first = repo.issue_code(account.id, CodeKind::Reset).await.unwrap();
+        let second = repo.issue_code(account.id, CodeKind::Reset).await.unwrap();
+        if first != second {
+            assert!(
+                !repo
+                    .redeem_code(account.id, CodeKind::Reset, &first)
+                    .await
+                    .unwrap()
+            );
+        }
+        assert!(
+            repo.redeem_code(account.id, CodeKind::Reset, &second)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_group_membership_grants_channel_access_transitively() {
+        let db = crate::db::Database::new(":memory:").await.unwrap();
+        let org = db.accounts().register("org", "pw", None).await.unwrap();
+        let team = db.accounts().register("team", "pw", None).await.unwrap();
+        let dave = db.accounts().register("dave", "pw", None).await.unwrap();
+        let chan = db.channels().register("#org", org.id, None).await.unwrap();
+
+        // dave -> team -> org
+        assert!(
+            db.accounts()
+                .add_group_member(org.id, team.id, "org")
+                .await
+                .unwrap()
+        );
+        assert!(
+            db.accounts()
+                .add_group_member(team.id, dave.id, "team")
+                .await
+                .unwrap()
+        );
+        assert!(
+            !db.accounts()
+                .add_group_member(team.id, dave.id, "team")
+                .await
+                .unwrap()
+        );
+        assert!(
+            db.channels()
+                .effective_access(&chan, dave.id)
+                .await
+                .unwrap()
+                .founder
+        );
+
+        // A group cannot end up containing itself
+        assert!(matches!(
+            db.accounts()
+                .add_group_member(dave.id, org.id, "dave")
+                .await,
+            Err(DbError::GroupCycle)
+        ));
+        assert!(matches!(
+            db.accounts().add_group_member(org.id, org.id, "org").await,
+            Err(DbError::GroupCycle)
+        ));
+
+        assert!(
+            db.accounts()
+                .remove_group_member(team.id, dave.id)
+                .await
+                .unwrap()
+        );
+        let access = db
+            .channels()
+            .effective_access(&chan, dave.id)
+            .await
+            .unwrap();
+        assert!(!access.founder && access.flags.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_drop_account_hands_channels_to_successor() {
+        let db = crate::db::Database::new(":memory:").await.unwrap();
+        let alice = db.accounts().register("alice", "pw", None).await.unwrap();
+        let bob = db.accounts().register("bob", "pw", None).await.unwrap();
+        let carol = db.accounts().register("carol", "pw", None).await.unwrap();
+        let channels = db.channels();
+
+        // #heir has a successor, #cofounded another +F holder, #alone neither
+        let heir = channels.register("#heir", alice.id, None).await.unwrap();
+        channels.set_successor(heir.id, Some(bob.id)).await.unwrap();
+        let cofounded = channels
+            .register("#cofounded", alice.id, None)
+            .await
+            .unwrap();
+        channels
+            .set_access(cofounded.id, carol.id, "+F", "alice")
+            .await
+            .unwrap();
+        channels.register("#alone", alice.id, None).await.unwrap();
+
+        let mut orphaned = db.accounts().drop_account("alice", "pw").await.unwrap();
+        orphaned.sort_by(|a, b| a.name.cmp(&b.name));
+        let outcome: Vec<_> = orphaned
+            .iter()
+            .map(|c| (c.name.as_str(), c.new_founder.as_deref()))
+            .collect();
+        assert_eq!(
+            outcome,
+            vec![
+                ("#alone", None),
+                ("#cofounded", Some("carol")),
+                ("#heir", Some("bob")),
+            ]
+        );
+
+        let heir = channels.find_by_name("#heir").await.unwrap().unwrap();
+        assert_eq!(heir.founder_account_id, bob.id);
+        assert_eq!(heir.successor_account_id, None);
+        assert!(
+            channels
+                .effective_access(&heir, bob.id)
+                .await
+                .unwrap()
+                .founder
+        );
+        assert!(channels.find_by_name("#alone").await.unwrap().is_none());
+        assert!(db.accounts().find_by_name("alice").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unused_channels_listed_for_expiry() {
+        let db = crate::db::Database::new(":memory:").await.unwrap();
+        let alice = db.accounts().register("alice", "pw", None).await.unwrap();
+        let channels = db.channels();
+        let chan = channels.register("#idle", alice.id, None).await.unwrap();
+
+        let later = chan.last_used_at + 10;
+        assert_eq!(
+            channels.list_unused_since(later).await.unwrap(),
+            vec![(chan.id, "#idle".to_string())]
+        );
+        assert!(
+            channels
+                .list_unused_since(chan.last_used_at)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        sqlx::query("UPDATE channels SET last_used_at = 0 WHERE id = ?")
+            .bind(chan.id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        channels.mark_used("#IDLE").await.unwrap();
+        assert!(channels.list_unused_since(1).await.unwrap().is_empty());
+    }
 }
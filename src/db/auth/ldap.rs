@@ -0,0 +1,143 @@
+//! LDAP authentication provider.
+//!
+//! Credentials are checked by a simple bind as the user, over a fresh
+//! connection per attempt. The user's DN is either built from a template
+//! or looked up with a subtree search first.
+
+use super::{AuthError, AuthProvider};
+use crate::config::LdapAuthConfig;
+use async_trait::async_trait;
+use ldap3::{Ldap, LdapConnAsync, LdapConnSettings, LdapError, Scope, SearchEntry};
+use std::time::Duration;
+use tracing::debug;
+
+pub struct LdapProvider {
+    config: LdapAuthConfig,
+}
+
+impl LdapProvider {
+    pub fn new(config: LdapAuthConfig) -> Self {
+        Self { config }
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.config.timeout_secs)
+    }
+
+    /// DN to bind as for `account`, if the account exists.
+    async fn user_dn(&self, ldap: &mut Ldap, account: &str) -> Result<Option<String>, LdapError> {
+        if let Some(template) = &self.config.user_dn {
+            return Ok(Some(fill(template, &ldap3::dn_escape(account))));
+        }
+        let Some(base) = &self.config.search_base else {
+            return Ok(None);
+        };
+
+        if let Some(bind_dn) = &self.config.bind_dn {
+            let password = self.config.bind_password.as_deref().unwrap_or_default();
+            ldap.simple_bind(bind_dn, password).await?.success()?;
+        }
+        let filter = fill(&self.config.search_filter, &ldap3::ldap_escape(account));
+        // "1.1" requests no attributes; only the DN is needed
+        let (entries, _) = ldap
+            .search(base, Scope::Subtree, &filter, vec!["1.1"])
+            .await?
+            .success()?;
+        match <[_; 1]>::try_from(entries) {
+            Ok([entry]) => Ok(Some(SearchEntry::construct(entry).dn)),
+            Err(entries) => {
+                debug!(
+                    account,
+                    matches = entries.len(),
+                    "LDAP search did not find one user"
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    async fn bind_as_user(&self, account: &str, password: &str) -> Result<bool, LdapError> {
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(self.timeout())
+            .set_starttls(self.config.starttls);
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &self.config.url).await?;
+        tokio::spawn(async move {
+            if let Err(e) = conn.drive().await {
+                debug!(error = %e, "LDAP connection closed with error");
+            }
+        });
+
+        let accepted = match self.user_dn(&mut ldap, account).await? {
+            Some(dn) => ldap.simple_bind(&dn, password).await?.rc == 0,
+            None => false,
+        };
+        let _ = ldap.unbind().await;
+        Ok(accepted)
+    }
+}
+
+/// Replace `{user}` in `template` with an already escaped account name.
+fn fill(template: &str, escaped: &str) -> String {
+    template.replace("{user}", escaped)
+}
+
+#[async_trait]
+impl AuthProvider for LdapProvider {
+    fn name(&self) -> &'static str {
+        "ldap"
+    }
+
+    async fn verify(&self, account: &str, password: &str) -> Result<Option<String>, AuthError> {
+        // An empty password is an unauthenticated bind, which servers accept
+        if password.is_empty() {
+            return Ok(None);
+        }
+        let accepted = tokio::time::timeout(self.timeout(), self.bind_as_user(account, password))
+            .await
+            .map_err(|_| AuthError::Timeout)??;
+        Ok(accepted.then(|| account.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> LdapAuthConfig {
+        LdapAuthConfig {
+            // Nothing listens on port 9 (discard); connects fail fast
+            url: "ldap://127.0.0.1:9".to_string(),
+            starttls: false,
+            user_dn: Some("uid={user},ou=people,dc=example,dc=com".to_string()),
+            search_base: None,
+            search_filter: "(uid={user})".to_string(),
+            bind_dn: None,
+            bind_password: None,
+            timeout_secs: 1,
+        }
+    }
+
+    #[test]
+    fn test_fill_escapes_before_substitution() {
+        let dn = fill(
+            "uid={user},ou=people,dc=example,dc=com",
+            &ldap3::dn_escape("evil,ou=admins"),
+        );
+        assert_eq!(dn, "uid=evil\\2cou\\3dadmins,ou=people,dc=example,dc=com");
+
+        let filter = fill("(uid={user})", &ldap3::ldap_escape("*)(uid=*"));
+        assert_eq!(filter, "(uid=\\2a\\29\\28uid=\\2a)");
+    }
+
+    #[tokio::test]
+    async fn test_empty_password_never_binds() {
+        let provider = LdapProvider::new(config());
+        assert!(matches!(provider.verify("alice", "").await, Ok(None)));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server_is_an_error() {
+        let provider = LdapProvider::new(config());
+        assert!(provider.verify("alice", "secret").await.is_err());
+    }
+}
@@ -0,0 +1,70 @@
+//! External authentication providers.
+//!
+//! [`AccountRepository::authenticate`](super::AccountRepository::authenticate)
+//! asks the configured provider before the local accounts table, so SASL
+//! PLAIN and NickServ IDENTIFY can check passwords against a directory or an
+//! SSO token endpoint.
+
+use crate::config::{AuthConfig, AuthProviderKind};
+use async_trait::async_trait;
+use std::sync::Arc;
+use thiserror::Error;
+
+pub mod ldap;
+pub mod oauth2;
+
+pub use ldap::LdapProvider;
+pub use oauth2::OAuth2Provider;
+
+#[derive(Debug, Error)]
+pub enum AuthError {
+    #[error("LDAP error: {0}")]
+    Ldap(#[from] ::ldap3::LdapError),
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("timed out")]
+    Timeout,
+}
+
+#[async_trait]
+pub trait AuthProvider: Send + Sync {
+    /// Short name for logs and metrics.
+    fn name(&self) -> &'static str;
+
+    /// Check `password` for `account`.
+    ///
+    /// Returns the account name to log in as if the credentials are
+    /// accepted, `None` if they are rejected, and an error if the backend
+    /// could not give an answer.
+    async fn verify(&self, account: &str, password: &str) -> Result<Option<String>, AuthError>;
+}
+
+/// The configured external provider and how its logins map to accounts.
+#[derive(Clone)]
+pub struct ExternalAuth {
+    pub provider: Arc<dyn AuthProvider>,
+    /// Let provider logins use accounts that were registered locally.
+    pub link_local_accounts: bool,
+}
+
+impl ExternalAuth {
+    pub fn new(provider: Arc<dyn AuthProvider>, link_local_accounts: bool) -> Self {
+        Self {
+            provider,
+            link_local_accounts,
+        }
+    }
+}
+
+/// Build the provider selected by `config`, if any.
+///
+/// Returns `None` for the local provider, or when the selected backend has
+/// no settings (rejected earlier by config validation).
+pub fn from_config(config: &AuthConfig) -> Option<ExternalAuth> {
+    let provider: Arc<dyn AuthProvider> = match config.provider {
+        AuthProviderKind::Local => return None,
+        AuthProviderKind::Ldap => Arc::new(LdapProvider::new(config.ldap.clone()?)),
+        AuthProviderKind::Oauth2 => Arc::new(OAuth2Provider::new(config.oauth2.clone()?)),
+    };
+    Some(ExternalAuth::new(provider, config.link_local_accounts))
+}
@@ -0,0 +1,142 @@
+//! OAuth2 token introspection provider (RFC 7662).
+//!
+//! The client sends an access token as its password. The token is posted to
+//! the authorization server's introspection endpoint, and accepted if it is
+//! active and its `username_claim` names the account being logged in to.
+
+use super::{AuthError, AuthProvider};
+use crate::config::OAuth2AuthConfig;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::time::Duration;
+use tracing::debug;
+
+pub struct OAuth2Provider {
+    config: OAuth2AuthConfig,
+    http_client: reqwest::Client,
+}
+
+impl OAuth2Provider {
+    pub fn new(config: OAuth2AuthConfig) -> Self {
+        let http_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .user_agent("slircd-ng/1.0")
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        Self {
+            config,
+            http_client,
+        }
+    }
+}
+
+#[async_trait]
+impl AuthProvider for OAuth2Provider {
+    fn name(&self) -> &'static str {
+        "oauth2"
+    }
+
+    async fn verify(&self, account: &str, token: &str) -> Result<Option<String>, AuthError> {
+        if token.is_empty() {
+            return Ok(None);
+        }
+        let introspection: Value = self
+            .http_client
+            .post(&self.config.introspection_url)
+            .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+            .form(&[("token", token), ("token_type_hint", "access_token")])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if introspection.get("active").and_then(Value::as_bool) != Some(true) {
+            return Ok(None);
+        }
+        let claim = &self.config.username_claim;
+        match introspection.get(claim).and_then(Value::as_str) {
+            Some(name) if slirc_proto::irc_eq(name, account) => Ok(Some(name.to_string())),
+            other => {
+                debug!(account, claim, token_for = ?other, "Token is not for this account");
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve one introspection request with `body`, returning the endpoint
+    /// URL and a handle yielding the raw request.
+    async fn introspection_endpoint(
+        body: &'static str,
+    ) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/introspect", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !String::from_utf8_lossy(&request).contains("token_type_hint") {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+        (url, handle)
+    }
+
+    fn provider(url: String) -> OAuth2Provider {
+        OAuth2Provider::new(OAuth2AuthConfig {
+            introspection_url: url,
+            client_id: "slircd".to_string(),
+            client_secret: "secret".to_string(),
+            username_claim: "username".to_string(),
+            timeout_secs: 5,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_active_token_for_account_is_accepted() {
+        let (url, request) = introspection_endpoint(r#"{"active":true,"username":"Alice"}"#).await;
+        let account = provider(url).verify("alice", "tok123").await.unwrap();
+        assert_eq!(account.as_deref(), Some("Alice"));
+
+        let request = request.await.unwrap();
+        assert!(request.starts_with("POST /introspect"));
+        assert!(request.contains("token=tok123"));
+        // base64("slircd:secret")
+        assert!(request.contains("c2xpcmNkOnNlY3JldA=="));
+    }
+
+    #[tokio::test]
+    async fn test_inactive_token_is_rejected() {
+        let (url, _) = introspection_endpoint(r#"{"active":false}"#).await;
+        assert!(matches!(
+            provider(url).verify("alice", "tok").await,
+            Ok(None)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_token_for_another_account_is_rejected() {
+        let (url, _) = introspection_endpoint(r#"{"active":true,"username":"mallory"}"#).await;
+        assert!(matches!(
+            provider(url).verify("alice", "tok").await,
+            Ok(None)
+        ));
+    }
+}
@@ -1,7 +1,7 @@
-//! Repository for K-line and D-line bans.
+//! Repository for server bans and session limits.
 
 mod models;
 mod queries;
 
-pub use models::{Dline, Gline, Kline, Shun, Zline};
+pub use models::{Dline, Gline, Kline, Qline, SessionLimit, Shun, Zline};
 pub use queries::BanRepository;
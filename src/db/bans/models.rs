@@ -83,6 +83,45 @@ pub struct Shun {
     pub expires_at: Option<i64>,
 }
 
+/// A Q-line (reserved nickname or channel name).
+///
+/// Masks starting with a channel prefix reserve channel names; all other
+/// masks reserve nicknames, so `*Serv` never blocks a channel called `#Serv`.
+#[derive(Debug, Clone)]
+pub struct Qline {
+    pub mask: String,
+    pub reason: Option<String>,
+    pub set_by: String,
+    pub set_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+impl Qline {
+    /// Check whether a Q-line mask reserves `name` (a nickname or channel).
+    ///
+    /// Shared with config Q-lines so both sources apply the same
+    /// nick-versus-channel split.
+    pub fn mask_matches(mask: &str, name: &str) -> bool {
+        is_channel_target(mask) == is_channel_target(name) && wildcard_match(mask, name)
+    }
+}
+
+/// A session limit (OperServ SESSION): the most connections allowed from
+/// an IP or CIDR range, overriding the configured per-IP limit.
+#[derive(Debug, Clone)]
+pub struct SessionLimit {
+    pub mask: String,
+    pub max_sessions: u32,
+    pub reason: Option<String>,
+    pub set_by: String,
+    pub expires_at: Option<i64>,
+}
+
+/// Returns true if a Q-line mask or target names a channel.
+fn is_channel_target(name: &str) -> bool {
+    name.starts_with(['#', '&', '+', '!'])
+}
+
 /// Basic CIDR matching for IP addresses.
 pub(super) fn cidr_match(cidr: &str, ip: &str) -> bool {
     // Parse CIDR notation (e.g., "192.168.1.0/24")
@@ -288,6 +327,32 @@ impl BanType for Shun {
     }
 }
 
+impl BanType for Qline {
+    fn table_name() -> &'static str {
+        "qlines"
+    }
+
+    fn from_row(
+        mask: String,
+        reason: Option<String>,
+        set_by: String,
+        set_at: i64,
+        expires_at: Option<i64>,
+    ) -> Self {
+        Self {
+            mask,
+            reason,
+            set_by,
+            set_at,
+            expires_at,
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        Self::mask_matches(&self.mask, name)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -354,6 +419,7 @@ mod tests {
         assert_eq!(Zline::table_name(), "zlines");
         assert_eq!(Rline::table_name(), "rlines");
         assert_eq!(Shun::table_name(), "shuns");
+        assert_eq!(Qline::table_name(), "qlines");
     }
 
     // ========================================================================
@@ -555,4 +621,21 @@ mod tests {
         assert!(shun.matches("troll@host.net"));
         assert!(!shun.matches("user@troll.net"));
     }
+
+    #[test]
+    fn qline_matches_nicks_only() {
+        let qline = Qline::from_row("*Serv".to_string(), None, "admin".to_string(), 0, None);
+        assert!(qline.matches("NickServ"));
+        assert!(qline.matches("chanserv"));
+        assert!(!qline.matches("#Serv"));
+        assert!(!qline.matches("Server1"));
+    }
+
+    #[test]
+    fn qline_matches_channels_only() {
+        let qline = Qline::from_row("#opers*".to_string(), None, "admin".to_string(), 0, None);
+        assert!(qline.matches("#Opers"));
+        assert!(qline.matches("#opers-help"));
+        assert!(!qline.matches("opers"));
+    }
 }
@@ -1,7 +1,7 @@
 //! Generic ban query operations.
 //!
 //! This module provides a trait-based generic implementation for all ban types
-//! (K-line, D-line, G-line, Z-line, R-line, Shun), eliminating ~300 lines of
+//! (K-line, D-line, G-line, Z-line, R-line, Q-line, Shun), eliminating ~300 lines of
 //! duplicated code across individual query files.
 
 use crate::db::DbError;
@@ -39,7 +39,7 @@ pub async fn add_ban<T: BanType>(
     duration: Option<i64>,
 ) -> Result<(), DbError> {
     let now = chrono::Utc::now().timestamp();
-    let expires_at = duration.map(|d| now + d);
+    let expires_at = duration.map(|d| now.saturating_add(d));
 
     let query = format!(
         r#"
@@ -4,7 +4,9 @@ pub mod dline;
 pub mod generic;
 pub mod gline;
 pub mod kline;
+pub mod qline;
 pub mod rline;
+pub mod session;
 pub mod shun;
 pub mod zline;
 
@@ -127,6 +129,20 @@ impl<'a> BanRepository<'a> {
         fn get_active_rlines() -> Result<Vec<super::models::Rline>, DbError>
             => rline::get_active_rlines;
 
+        // ========== Q-line operations ==========
+
+        /// Add a Q-line.
+        fn add_qline(mask: &str, reason: Option<&str>, set_by: &str, duration: Option<i64>) -> Result<(), DbError>
+            => qline::add_qline;
+
+        /// Remove a Q-line.
+        fn remove_qline(mask: &str) -> Result<bool, DbError>
+            => qline::remove_qline;
+
+        /// Get all active Q-lines (not expired).
+        fn get_active_qlines() -> Result<Vec<super::models::Qline>, DbError>
+            => qline::get_active_qlines;
+
         // ========== Shun operations ==========
 
         /// Add a shun.
@@ -144,6 +160,20 @@ impl<'a> BanRepository<'a> {
         /// Get all active shuns (not expired).
         fn get_active_shuns() -> Result<Vec<super::models::Shun>, DbError>
             => shun::get_active_shuns;
+
+        // ========== Session limit operations ==========
+
+        /// Add or replace a session limit.
+        fn add_session_limit(mask: &str, max_sessions: u32, reason: Option<&str>, set_by: &str, duration: Option<i64>) -> Result<(), DbError>
+            => session::add_session_limit;
+
+        /// Remove a session limit.
+        fn remove_session_limit(mask: &str) -> Result<bool, DbError>
+            => session::remove_session_limit;
+
+        /// Get all active session limits (not expired).
+        fn get_active_session_limits() -> Result<Vec<super::models::SessionLimit>, DbError>
+            => session::get_active_session_limits;
     }
 
     // ========== Combined check operations ==========
@@ -0,0 +1,27 @@
+//! Q-line (reserved nickname/channel) operations.
+
+use super::super::models::Qline;
+use super::generic::{add_ban, get_active_bans, remove_ban};
+use crate::db::DbError;
+use sqlx::SqlitePool;
+
+/// Add a Q-line.
+pub async fn add_qline(
+    pool: &SqlitePool,
+    mask: &str,
+    reason: Option<&str>,
+    set_by: &str,
+    duration: Option<i64>,
+) -> Result<(), DbError> {
+    add_ban::<Qline>(pool, mask, reason, set_by, duration).await
+}
+
+/// Remove a Q-line.
+pub async fn remove_qline(pool: &SqlitePool, mask: &str) -> Result<bool, DbError> {
+    remove_ban::<Qline>(pool, mask).await
+}
+
+/// Get all active Q-lines (not expired).
+pub async fn get_active_qlines(pool: &SqlitePool) -> Result<Vec<Qline>, DbError> {
+    get_active_bans::<Qline>(pool).await
+}
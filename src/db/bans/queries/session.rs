@@ -0,0 +1,77 @@
+//! Session limit (OperServ SESSION) operations.
+//!
+//! Session limits carry a connection count on top of the usual ban columns,
+//! so they use their own queries rather than the generic ban ones.
+
+use super::super::models::SessionLimit;
+use crate::db::DbError;
+use sqlx::SqlitePool;
+
+/// Add or replace a session limit.
+pub async fn add_session_limit(
+    pool: &SqlitePool,
+    mask: &str,
+    max_sessions: u32,
+    reason: Option<&str>,
+    set_by: &str,
+    duration: Option<i64>,
+) -> Result<(), DbError> {
+    let now = chrono::Utc::now().timestamp();
+    let expires_at = duration.map(|d| now.saturating_add(d));
+
+    sqlx::query(
+        r#"
+        INSERT OR REPLACE INTO session_limits (mask, max_sessions, reason, set_by, set_at, expires_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(mask)
+    .bind(i64::from(max_sessions))
+    .bind(reason)
+    .bind(set_by)
+    .bind(now)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Remove a session limit.
+pub async fn remove_session_limit(pool: &SqlitePool, mask: &str) -> Result<bool, DbError> {
+    let result = sqlx::query("DELETE FROM session_limits WHERE mask = ?")
+        .bind(mask)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get all active session limits (not expired).
+pub async fn get_active_session_limits(pool: &SqlitePool) -> Result<Vec<SessionLimit>, DbError> {
+    let now = chrono::Utc::now().timestamp();
+
+    let rows = sqlx::query_as::<_, (String, i64, Option<String>, String, Option<i64>)>(
+        r#"
+        SELECT mask, max_sessions, reason, set_by, expires_at
+        FROM session_limits
+        WHERE expires_at IS NULL OR expires_at > ?
+        "#,
+    )
+    .bind(now)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(mask, max_sessions, reason, set_by, expires_at)| SessionLimit {
+                mask,
+                max_sessions: u32::try_from(max_sessions).unwrap_or(0),
+                reason,
+                set_by,
+                expires_at,
+            },
+        )
+        .collect())
+}
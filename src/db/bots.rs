@@ -0,0 +1,264 @@
+//! Bot repository for BotServ functionality.
+//!
+//! Stores the bots operators have created, which registered channel each is
+//! assigned to, and each channel's greet message and badwords list.
+
+use super::DbError;
+use sqlx::SqlitePool;
+
+/// A BotServ bot.
+#[derive(Debug, Clone)]
+pub struct Bot {
+    pub id: i64,
+    pub nick: String,
+    pub username: String,
+    pub hostname: String,
+    pub realname: String,
+}
+
+/// A bot assigned to a channel, with the channel's bot settings.
+#[derive(Debug, Clone)]
+pub struct BotAssignment {
+    pub channel: String,
+    pub bot_nick: String,
+    pub greet: Option<String>,
+    pub kickban: bool,
+}
+
+/// Repository for bot operations.
+pub struct BotRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> BotRepository<'a> {
+    /// Create a new bot repository.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// All bots, by nick.
+    pub async fn list(&self) -> Result<Vec<Bot>, DbError> {
+        let rows = sqlx::query_as::<_, (i64, String, String, String, String)>(
+            r#"
+            SELECT id, nick, username, hostname, realname
+            FROM bots
+            ORDER BY nick
+            "#,
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(id, nick, username, hostname, realname)| Bot {
+                id,
+                nick,
+                username,
+                hostname,
+                realname,
+            })
+            .collect())
+    }
+
+    /// Create a bot. Returns `None` if the nick is already taken by a bot.
+    pub async fn create(
+        &self,
+        nick: &str,
+        username: &str,
+        hostname: &str,
+        realname: &str,
+        created_by: &str,
+    ) -> Result<Option<Bot>, DbError> {
+        let id = sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO bots (nick, username, hostname, realname, created_by, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(nick) DO NOTHING
+            RETURNING id
+            "#,
+        )
+        .bind(nick)
+        .bind(username)
+        .bind(hostname)
+        .bind(realname)
+        .bind(created_by)
+        .bind(chrono::Utc::now().timestamp())
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(id.map(|id| Bot {
+            id,
+            nick: nick.to_string(),
+            username: username.to_string(),
+            hostname: hostname.to_string(),
+            realname: realname.to_string(),
+        }))
+    }
+
+    /// Delete a bot, its channel assignments and those channels' settings.
+    pub async fn delete(&self, bot_id: i64) -> Result<bool, DbError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM channel_badwords
+            WHERE channel_id IN (SELECT channel_id FROM channel_bots WHERE bot_id = ?)
+            "#,
+        )
+        .bind(bot_id)
+        .execute(&mut *tx)
+        .await?;
+        let result = sqlx::query("DELETE FROM bots WHERE id = ?")
+            .bind(bot_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// All channel assignments.
+    pub async fn assignments(&self) -> Result<Vec<BotAssignment>, DbError> {
+        let rows = sqlx::query_as::<_, (String, String, Option<String>, bool)>(
+            r#"
+            SELECT c.name, b.nick, cb.greet, cb.kickban
+            FROM channel_bots cb
+            JOIN channels c ON c.id = cb.channel_id
+            JOIN bots b ON b.id = cb.bot_id
+            "#,
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(channel, bot_nick, greet, kickban)| BotAssignment {
+                channel,
+                bot_nick,
+                greet,
+                kickban,
+            })
+            .collect())
+    }
+
+    /// Assign a bot to a channel, replacing any earlier bot but keeping the
+    /// channel's settings.
+    pub async fn assign(
+        &self,
+        channel_id: i64,
+        bot_id: i64,
+        assigned_by: &str,
+    ) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO channel_bots (channel_id, bot_id, assigned_by, assigned_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(channel_id) DO UPDATE SET
+                bot_id = excluded.bot_id,
+                assigned_by = excluded.assigned_by,
+                assigned_at = excluded.assigned_at
+            "#,
+        )
+        .bind(channel_id)
+        .bind(bot_id)
+        .bind(assigned_by)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Remove a channel's bot along with its greet and badwords.
+    /// Returns `false` if none was assigned.
+    pub async fn unassign(&self, channel_id: i64) -> Result<bool, DbError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM channel_badwords WHERE channel_id = ?")
+            .bind(channel_id)
+            .execute(&mut *tx)
+            .await?;
+        let result = sqlx::query("DELETE FROM channel_bots WHERE channel_id = ?")
+            .bind(channel_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set or clear the greet message. Returns `false` if no bot is assigned.
+    pub async fn set_greet(&self, channel_id: i64, greet: Option<&str>) -> Result<bool, DbError> {
+        let result = sqlx::query("UPDATE channel_bots SET greet = ? WHERE channel_id = ?")
+            .bind(greet)
+            .bind(channel_id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Switch banning on badwords on or off. Returns `false` if no bot is
+    /// assigned.
+    pub async fn set_kickban(&self, channel_id: i64, kickban: bool) -> Result<bool, DbError> {
+        let result = sqlx::query("UPDATE channel_bots SET kickban = ? WHERE channel_id = ?")
+            .bind(kickban)
+            .bind(channel_id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Badwords of every channel with a bot, as (channel, word) pairs.
+    pub async fn badwords(&self) -> Result<Vec<(String, String)>, DbError> {
+        let rows = sqlx::query_as::<_, (String, String)>(
+            r#"
+            SELECT c.name, w.word
+            FROM channel_badwords w
+            JOIN channel_bots cb ON cb.channel_id = w.channel_id
+            JOIN channels c ON c.id = w.channel_id
+            ORDER BY w.added_at
+            "#,
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Add a badword. Returns `false` if it was already listed.
+    pub async fn add_badword(
+        &self,
+        channel_id: i64,
+        word: &str,
+        added_by: &str,
+    ) -> Result<bool, DbError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO channel_badwords (channel_id, word, added_by, added_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(channel_id, word) DO NOTHING
+            "#,
+        )
+        .bind(channel_id)
+        .bind(word)
+        .bind(added_by)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Remove a badword. Returns `false` if it was not listed.
+    pub async fn del_badword(&self, channel_id: i64, word: &str) -> Result<bool, DbError> {
+        let result = sqlx::query("DELETE FROM channel_badwords WHERE channel_id = ? AND word = ?")
+            .bind(channel_id)
+            .bind(word)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
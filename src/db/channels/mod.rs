@@ -5,5 +5,5 @@
 pub mod models;
 pub mod queries;
 
-pub use models::{ChannelAkick, ChannelRecord};
+pub use models::{ChannelAkick, ChannelListEntry, ChannelRecord, EffectiveAccess, OrphanedChannel};
 pub use queries::ChannelRepository;
@@ -1,5 +1,7 @@
 //! Channel database models.
 
+use super::queries::ChannelRepository;
+
 /// A registered ChanServ channel.
 #[derive(Debug, Clone)]
 pub struct ChannelRecord {
@@ -17,9 +19,21 @@ pub struct ChannelRecord {
     pub topic_set_by: Option<String>,
     /// When the persisted topic was set (Unix timestamp)
     pub topic_set_at: Option<i64>,
+    /// Account that becomes founder when the founder's account is dropped.
+    pub successor_account_id: Option<i64>,
+    /// Live channel modes with their parameters, as `+ntk key`.
+    pub modes: Option<String>,
     pub metadata: std::collections::HashMap<String, String>,
 }
 
+/// A channel whose founder account was dropped.
+#[derive(Debug, Clone)]
+pub struct OrphanedChannel {
+    pub name: String,
+    /// Account name of the new founder, or `None` if the channel was dropped.
+    pub new_founder: Option<String>,
+}
+
 /// Channel access entry.
 #[derive(Debug, Clone)]
 pub struct ChannelAccess {
@@ -29,6 +43,38 @@ pub struct ChannelAccess {
     pub added_at: i64,
 }
 
+/// Access an account holds on a channel, directly or through the account
+/// groups it belongs to.
+#[derive(Debug, Clone, Default)]
+pub struct EffectiveAccess {
+    /// The account or one of its groups is the channel founder.
+    pub founder: bool,
+    /// Flags of every matching access entry, merged.
+    pub flags: String,
+}
+
+impl EffectiveAccess {
+    /// Founder of the channel or holder of the +F flag.
+    pub fn is_founder(&self) -> bool {
+        self.founder || ChannelRepository::is_founder(&self.flags)
+    }
+
+    /// Holds `flag`; founders hold every flag.
+    pub fn has(&self, flag: char) -> bool {
+        self.is_founder() || self.flags.contains(flag)
+    }
+
+    /// Founder or any entry on the access list.
+    pub fn has_any_access(&self) -> bool {
+        self.founder || !self.flags.is_empty()
+    }
+
+    /// Founder or op-level access.
+    pub fn has_op_access(&self) -> bool {
+        self.founder || ChannelRepository::has_op_access(&self.flags)
+    }
+}
+
 /// A channel AKICK entry.
 #[derive(Debug, Clone)]
 pub struct ChannelAkick {
@@ -41,3 +87,23 @@ pub struct ChannelAkick {
     pub set_by: String,
     pub set_at: i64,
 }
+
+/// An entry on a registered channel's ban, exception, invex or quiet list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelListEntry {
+    /// List mode: `b`, `e`, `I` or `q`.
+    pub mode: char,
+    pub mask: String,
+    pub set_by: String,
+    pub set_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+/// A ChanServ note kept by channel staff.
+#[derive(Debug, Clone)]
+pub struct ChannelNote {
+    pub id: i64,
+    pub note: String,
+    pub author: String,
+    pub created_at: i64,
+}
@@ -1,9 +1,20 @@
 //! Channel repository for database queries.
 
-use super::models::{ChannelAccess, ChannelAkick, ChannelRecord};
+use super::models::{
+    ChannelAccess, ChannelAkick, ChannelListEntry, ChannelNote, ChannelRecord, EffectiveAccess,
+    OrphanedChannel,
+};
 use crate::db::DbError;
+use crate::db::accounts::ACTING_IDS_CTE;
 use sqlx::SqlitePool;
 
+/// Channel access flags, in display order.
+///
+/// `v`/`o` use VOICE and OP, `V`/`O` get voice or op on join, `t` TOPIC,
+/// `s` SET, `r` AKICK, `i` INVITE, `R` CLEAR, `f` edit the access list,
+/// `A` view it, and `F` is founder, which implies every other flag.
+pub const ACCESS_FLAGS: &str = "vVoOtsriRfAF";
+
 /// Repository for channel operations.
 pub struct ChannelRepository<'a> {
     pool: &'a SqlitePool,
@@ -70,15 +81,17 @@ impl<'a> ChannelRepository<'a> {
             topic_text: None,
             topic_set_by: None,
             topic_set_at: None,
+            successor_account_id: None,
+            modes: None,
             metadata: std::collections::HashMap::new(),
         })
     }
 
     /// Find channel by name.
     pub async fn find_by_name(&self, name: &str) -> Result<Option<ChannelRecord>, DbError> {
-        let row = sqlx::query_as::<_, (i64, String, i64, i64, i64, Option<String>, Option<String>, bool, Option<String>, Option<String>, Option<i64>)>(
+        let row = sqlx::query_as::<_, (i64, String, i64, i64, i64, Option<String>, Option<String>, bool, Option<String>, Option<String>, Option<i64>, Option<i64>, Option<String>)>(
             r#"
-            SELECT id, name, founder_account_id, registered_at, last_used_at, description, mlock, keeptopic, topic_text, topic_set_by, topic_set_at
+            SELECT id, name, founder_account_id, registered_at, last_used_at, description, mlock, keeptopic, topic_text, topic_set_by, topic_set_at, successor_account_id, modes
             FROM channels
             WHERE name = ? COLLATE NOCASE
             "#,
@@ -99,6 +112,8 @@ impl<'a> ChannelRepository<'a> {
             topic_text,
             topic_set_by,
             topic_set_at,
+            successor_account_id,
+            modes,
         )) = row
         {
             let metadata = self.fetch_metadata(id).await?;
@@ -114,6 +129,8 @@ impl<'a> ChannelRepository<'a> {
                 topic_text,
                 topic_set_by,
                 topic_set_at,
+                successor_account_id,
+                modes,
                 metadata,
             }))
         } else {
@@ -123,9 +140,9 @@ impl<'a> ChannelRepository<'a> {
 
     /// Load all registered channels from the database.
     pub async fn load_all_channels(&self) -> Result<Vec<ChannelRecord>, DbError> {
-        let rows = sqlx::query_as::<_, (i64, String, i64, i64, i64, Option<String>, Option<String>, bool, Option<String>, Option<String>, Option<i64>)>(
+        let rows = sqlx::query_as::<_, (i64, String, i64, i64, i64, Option<String>, Option<String>, bool, Option<String>, Option<String>, Option<i64>, Option<i64>, Option<String>)>(
             r#"
-            SELECT id, name, founder_account_id, registered_at, last_used_at, description, mlock, keeptopic, topic_text, topic_set_by, topic_set_at
+            SELECT id, name, founder_account_id, registered_at, last_used_at, description, mlock, keeptopic, topic_text, topic_set_by, topic_set_at, successor_account_id, modes
             FROM channels
             "#,
         )
@@ -145,6 +162,8 @@ impl<'a> ChannelRepository<'a> {
             topic_text,
             topic_set_by,
             topic_set_at,
+            successor_account_id,
+            modes,
         ) in rows
         {
             let metadata = self.fetch_metadata(id).await?;
@@ -160,6 +179,8 @@ impl<'a> ChannelRepository<'a> {
                 topic_text,
                 topic_set_by,
                 topic_set_at,
+                successor_account_id,
+                modes,
                 metadata,
             });
         }
@@ -167,32 +188,37 @@ impl<'a> ChannelRepository<'a> {
         Ok(channels)
     }
 
-    /// Get access flags for an account on a channel.
-    pub async fn get_access(
+    /// Get the access an account holds on a channel, resolving the account
+    /// groups it belongs to transitively.
+    pub async fn effective_access(
         &self,
-        channel_id: i64,
+        channel: &ChannelRecord,
         account_id: i64,
-    ) -> Result<Option<ChannelAccess>, DbError> {
-        let row = sqlx::query_as::<_, (i64, String, String, i64)>(
+    ) -> Result<EffectiveAccess, DbError> {
+        let rows = sqlx::query_as::<_, (i64, Option<String>)>(&format!(
             r#"
-            SELECT account_id, flags, added_by, added_at
-            FROM channel_access
-            WHERE channel_id = ? AND account_id = ?
-            "#,
-        )
-        .bind(channel_id)
+            {ACTING_IDS_CTE}
+            SELECT acting.id, channel_access.flags
+            FROM acting
+            LEFT JOIN channel_access
+              ON channel_access.account_id = acting.id AND channel_access.channel_id = ?
+            "#
+        ))
         .bind(account_id)
-        .fetch_optional(self.pool)
+        .bind(channel.id)
+        .fetch_all(self.pool)
         .await?;
 
-        Ok(
-            row.map(|(account_id, flags, added_by, added_at)| ChannelAccess {
-                account_id,
-                flags,
-                added_by,
-                added_at,
-            }),
-        )
+        let mut access = EffectiveAccess::default();
+        for (id, flags) in rows {
+            access.founder |= id == channel.founder_account_id;
+            for flag in flags.iter().flat_map(|f| f.chars()) {
+                if !access.flags.contains(flag) {
+                    access.flags.push(flag);
+                }
+            }
+        }
+        Ok(access)
     }
 
     /// Get all access entries for a channel.
@@ -248,6 +274,142 @@ impl<'a> ChannelRepository<'a> {
         Ok(())
     }
 
+    /// Get the access entry for an account on a channel.
+    pub async fn get_access(
+        &self,
+        channel_id: i64,
+        account_id: i64,
+    ) -> Result<Option<ChannelAccess>, DbError> {
+        let row = sqlx::query_as::<_, (i64, String, String, i64)>(
+            r#"
+            SELECT account_id, flags, added_by, added_at
+            FROM channel_access
+            WHERE channel_id = ? AND account_id = ?
+            "#,
+        )
+        .bind(channel_id)
+        .bind(account_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(
+            row.map(|(account_id, flags, added_by, added_at)| ChannelAccess {
+                account_id,
+                flags,
+                added_by,
+                added_at,
+            }),
+        )
+    }
+
+    /// Make an account the registered founder of a channel.
+    pub async fn set_founder(&self, channel_id: i64, account_id: i64) -> Result<(), DbError> {
+        sqlx::query("UPDATE channels SET founder_account_id = ? WHERE id = ?")
+            .bind(account_id)
+            .bind(channel_id)
+            .execute(self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Set or clear (`None`) the account that inherits the channel when the
+    /// founder's account is dropped.
+    pub async fn set_successor(
+        &self,
+        channel_id: i64,
+        account_id: Option<i64>,
+    ) -> Result<(), DbError> {
+        sqlx::query("UPDATE channels SET successor_account_id = ? WHERE id = ?")
+            .bind(account_id)
+            .bind(channel_id)
+            .execute(self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Hand over every channel founded by an account that is about to be
+    /// dropped.
+    ///
+    /// Each channel goes to its successor, or failing that to another
+    /// account holding +F. Channels with neither are dropped.
+    pub async fn transfer_founded_channels(
+        &self,
+        account_id: i64,
+    ) -> Result<Vec<OrphanedChannel>, DbError> {
+        let founded = sqlx::query_as::<_, (i64, String, Option<i64>)>(
+            r#"
+            SELECT id, name, successor_account_id
+            FROM channels
+            WHERE founder_account_id = ?
+            "#,
+        )
+        .bind(account_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        let mut orphaned = Vec::with_capacity(founded.len());
+        for (channel_id, name, successor) in founded {
+            let successor = match successor.filter(|id| *id != account_id) {
+                Some(id) => Some(id),
+                None => self
+                    .list_access(channel_id)
+                    .await?
+                    .into_iter()
+                    .find(|e| e.account_id != account_id && Self::is_founder(&e.flags))
+                    .map(|e| e.account_id),
+            };
+
+            let new_founder = match successor {
+                Some(successor_id) => {
+                    sqlx::query(
+                        r#"
+                        UPDATE channels
+                        SET founder_account_id = ?, successor_account_id = NULL
+                        WHERE id = ?
+                        "#,
+                    )
+                    .bind(successor_id)
+                    .bind(channel_id)
+                    .execute(self.pool)
+                    .await?;
+                    self.set_access(channel_id, successor_id, "+F", "ChanServ")
+                        .await?;
+                    sqlx::query_scalar::<_, String>("SELECT name FROM accounts WHERE id = ?")
+                        .bind(successor_id)
+                        .fetch_optional(self.pool)
+                        .await?
+                }
+                None => {
+                    self.drop_channel(channel_id).await?;
+                    None
+                }
+            };
+            orphaned.push(OrphanedChannel { name, new_founder });
+        }
+
+        Ok(orphaned)
+    }
+
+    /// Record that a registered channel is in use.
+    pub async fn mark_used(&self, name: &str) -> Result<(), DbError> {
+        sqlx::query("UPDATE channels SET last_used_at = ? WHERE name = ? COLLATE NOCASE")
+            .bind(chrono::Utc::now().timestamp())
+            .bind(name)
+            .execute(self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// List `(id, name)` of registered channels unused since `cutoff`.
+    pub async fn list_unused_since(&self, cutoff: i64) -> Result<Vec<(i64, String)>, DbError> {
+        Ok(sqlx::query_as::<_, (i64, String)>(
+            "SELECT id, name FROM channels WHERE last_used_at < ?",
+        )
+        .bind(cutoff)
+        .fetch_all(self.pool)
+        .await?)
+    }
+
     /// Remove access for an account on a channel.
     pub async fn remove_access(&self, channel_id: i64, account_id: i64) -> Result<bool, DbError> {
         let result = sqlx::query(
@@ -279,13 +441,6 @@ impl<'a> ChannelRepository<'a> {
                     .execute(self.pool)
                     .await?;
             }
-            "mlock" => {
-                sqlx::query("UPDATE channels SET mlock = ? WHERE id = ?")
-                    .bind(value)
-                    .bind(channel_id)
-                    .execute(self.pool)
-                    .await?;
-            }
             "keeptopic" => {
                 let keep = matches!(value.to_lowercase().as_str(), "on" | "true" | "1" | "yes");
                 sqlx::query("UPDATE channels SET keeptopic = ? WHERE id = ?")
@@ -301,6 +456,16 @@ impl<'a> ChannelRepository<'a> {
         Ok(())
     }
 
+    /// Set or clear (`None`) a channel's mode lock.
+    pub async fn set_mlock(&self, channel_id: i64, mlock: Option<&str>) -> Result<(), DbError> {
+        sqlx::query("UPDATE channels SET mlock = ? WHERE id = ?")
+            .bind(mlock)
+            .bind(channel_id)
+            .execute(self.pool)
+            .await?;
+        Ok(())
+    }
+
     /// Save topic for a registered channel (if keeptopic is enabled).
     pub async fn save_topic(
         &self,
@@ -325,6 +490,104 @@ impl<'a> ChannelRepository<'a> {
         Ok(())
     }
 
+    /// Save the live modes and topic of a registered channel.
+    ///
+    /// The topic is only kept when keeptopic is enabled.
+    pub async fn save_state(
+        &self,
+        name: &str,
+        modes: &str,
+        topic: Option<(&str, &str, i64)>,
+    ) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            UPDATE channels
+            SET modes = ?,
+                topic_text = CASE WHEN keeptopic = 1 THEN ? ELSE topic_text END,
+                topic_set_by = CASE WHEN keeptopic = 1 THEN ? ELSE topic_set_by END,
+                topic_set_at = CASE WHEN keeptopic = 1 THEN ? ELSE topic_set_at END
+            WHERE name = ? COLLATE NOCASE
+            "#,
+        )
+        .bind(modes)
+        .bind(topic.map(|(text, _, _)| text))
+        .bind(topic.map(|(_, set_by, _)| set_by))
+        .bind(topic.map(|(_, _, set_at)| set_at))
+        .bind(name)
+        .execute(self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Load the list entries (bans, exceptions, invex, quiets) of a
+    /// registered channel, oldest first.
+    pub async fn list_entries(&self, name: &str) -> Result<Vec<ChannelListEntry>, DbError> {
+        let rows = sqlx::query_as::<_, (String, String, String, i64, Option<i64>)>(
+            r#"
+            SELECT l.mode, l.mask, l.set_by, l.set_at, l.expires_at
+            FROM channel_lists l
+            JOIN channels c ON c.id = l.channel_id
+            WHERE c.name = ? COLLATE NOCASE
+            ORDER BY l.set_at ASC, l.rowid ASC
+            "#,
+        )
+        .bind(name)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(mode, mask, set_by, set_at, expires_at)| {
+                Some(ChannelListEntry {
+                    mode: mode.chars().next()?,
+                    mask,
+                    set_by,
+                    set_at,
+                    expires_at,
+                })
+            })
+            .collect())
+    }
+
+    /// Replace the stored list entries of a registered channel.
+    pub async fn replace_list_entries(
+        &self,
+        name: &str,
+        entries: &[ChannelListEntry],
+    ) -> Result<(), DbError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM channel_lists
+            WHERE channel_id = (SELECT id FROM channels WHERE name = ? COLLATE NOCASE)
+            "#,
+        )
+        .bind(name)
+        .execute(&mut *tx)
+        .await?;
+        for entry in entries {
+            sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO channel_lists
+                    (channel_id, mode, mask, set_by, set_at, expires_at)
+                SELECT id, ?, ?, ?, ?, ? FROM channels WHERE name = ? COLLATE NOCASE
+                "#,
+            )
+            .bind(entry.mode.to_string())
+            .bind(&entry.mask)
+            .bind(&entry.set_by)
+            .bind(entry.set_at)
+            .bind(entry.expires_at)
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
     /// Drop (unregister) a channel.
     pub async fn drop_channel(&self, channel_id: i64) -> Result<bool, DbError> {
         // Access entries are deleted via CASCADE
@@ -351,9 +614,48 @@ impl<'a> ChannelRepository<'a> {
         Self::has_flag(flags, 'o') || Self::has_flag(flags, 'F')
     }
 
-    /// Check if account has voice access (+v, +o, or +F).
-    pub fn has_voice_access(flags: &str) -> bool {
-        Self::has_flag(flags, 'v') || Self::has_op_access(flags)
+    /// Apply a change such as `+ot-v` to a flag string.
+    ///
+    /// Flags without a leading sign are added. `+*` adds every flag except
+    /// founder, `-*` removes them all. Returns the flags in canonical `+...`
+    /// order, empty when none remain, or the first unknown flag.
+    pub fn apply_flag_change(current: &str, change: &str) -> Result<String, char> {
+        let mut held: Vec<char> = current.chars().filter(|c| *c != '+').collect();
+        let mut adding = true;
+        for c in change.chars() {
+            match c {
+                '+' => adding = true,
+                '-' => adding = false,
+                '*' if adding => {
+                    held.extend(ACCESS_FLAGS.chars().filter(|f| *f != 'F'));
+                }
+                '*' => held.clear(),
+                c if !ACCESS_FLAGS.contains(c) => return Err(c),
+                c if adding => held.push(c),
+                c => held.retain(|f| *f != c),
+            }
+        }
+
+        let flags: String = ACCESS_FLAGS.chars().filter(|f| held.contains(f)).collect();
+        if flags.is_empty() {
+            Ok(flags)
+        } else {
+            Ok(format!("+{}", flags))
+        }
+    }
+
+    /// Expand flags given to ACCESS ADD, which has always meant auto-op (with
+    /// AKICK and list viewing) for +o and auto-voice for +v.
+    pub fn expand_access_flags(flags: &str) -> Result<String, char> {
+        let mut expanded = String::new();
+        for c in flags.chars() {
+            match c {
+                'o' => expanded.push_str("oOrA"),
+                'v' => expanded.push_str("vV"),
+                c => expanded.push(c),
+            }
+        }
+        Self::apply_flag_change("", &expanded)
     }
 
     /// Add an AKICK entry to a channel.
@@ -428,6 +730,67 @@ impl<'a> ChannelRepository<'a> {
             .collect())
     }
 
+    /// Add a note to a channel. Returns the new note's ID.
+    pub async fn add_note(
+        &self,
+        channel_id: i64,
+        note: &str,
+        author: &str,
+    ) -> Result<i64, DbError> {
+        let now = chrono::Utc::now().timestamp();
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO channel_notes (channel_id, note, author, created_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(channel_id)
+        .bind(note)
+        .bind(author)
+        .bind(now)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Remove a note from a channel.
+    pub async fn remove_note(&self, channel_id: i64, note_id: i64) -> Result<bool, DbError> {
+        let result = sqlx::query("DELETE FROM channel_notes WHERE channel_id = ? AND id = ?")
+            .bind(channel_id)
+            .bind(note_id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get all notes for a channel, oldest first.
+    pub async fn list_notes(&self, channel_id: i64) -> Result<Vec<ChannelNote>, DbError> {
+        let rows = sqlx::query_as::<_, (i64, String, String, i64)>(
+            r#"
+            SELECT id, note, author, created_at
+            FROM channel_notes
+            WHERE channel_id = ?
+            ORDER BY id ASC
+            "#,
+        )
+        .bind(channel_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(id, note, author, created_at)| ChannelNote {
+                id,
+                note,
+                author,
+                created_at,
+            })
+            .collect())
+    }
+
     /// Check if a hostmask matches any AKICK entry.
     /// Returns the matching AKICK if found.
     pub async fn check_akick(
@@ -483,8 +846,7 @@ impl<'a> ChannelRepository<'a> {
 
     /// Check if a mask pattern matches a full hostmask.
     /// Supports wildcards: * (matches any sequence) and ? (matches single char).
-    #[allow(dead_code)]
-    fn mask_matches(pattern: &str, hostmask: &str) -> bool {
+    pub fn mask_matches(pattern: &str, hostmask: &str) -> bool {
         let mut p_iter = pattern.chars();
         let mut h_iter = hostmask.chars();
 
@@ -625,4 +987,81 @@ mod tests {
         assert!(ChannelRepository::mask_matches("*", ""));
         assert!(!ChannelRepository::mask_matches("", "a"));
     }
+
+    #[test]
+    fn test_apply_flag_change() {
+        assert_eq!(
+            ChannelRepository::apply_flag_change("", "+ot"),
+            Ok("+ot".into())
+        );
+        assert_eq!(
+            ChannelRepository::apply_flag_change("+ot", "-t+v"),
+            Ok("+vo".into())
+        );
+        assert_eq!(
+            ChannelRepository::apply_flag_change("+vo", "-vo"),
+            Ok("".into())
+        );
+        assert_eq!(
+            ChannelRepository::apply_flag_change("+F", "+*"),
+            Ok("+vVoOtsriRfAF".into())
+        );
+        assert_eq!(
+            ChannelRepository::apply_flag_change("+F", "-*"),
+            Ok("".into())
+        );
+        assert_eq!(ChannelRepository::apply_flag_change("", "+oz"), Err('z'));
+    }
+
+    #[test]
+    fn test_expand_access_flags() {
+        assert_eq!(
+            ChannelRepository::expand_access_flags("+o"),
+            Ok("+oOrA".into())
+        );
+        assert_eq!(
+            ChannelRepository::expand_access_flags("+v"),
+            Ok("+vV".into())
+        );
+        assert_eq!(
+            ChannelRepository::expand_access_flags("+F"),
+            Ok("+F".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_entries_replace_and_load() {
+        let db = crate::db::Database::new(":memory:").await.unwrap();
+        let account = db
+            .accounts()
+            .register("alice", "hunter22", None)
+            .await
+            .unwrap();
+        let channels = db.channels();
+        channels.register("#Chan", account.id, None).await.unwrap();
+
+        let entry = |mode, mask: &str, set_at, expires_at| ChannelListEntry {
+            mode,
+            mask: mask.to_string(),
+            set_by: "alice".to_string(),
+            set_at,
+            expires_at,
+        };
+        let entries = [
+            entry('b', "*!*@spam", 1, Some(61)),
+            entry('q', "*!*@noisy", 2, None),
+        ];
+        channels
+            .replace_list_entries("#chan", &entries)
+            .await
+            .unwrap();
+        assert_eq!(channels.list_entries("#CHAN").await.unwrap(), entries);
+
+        channels
+            .replace_list_entries("#chan", &entries[1..])
+            .await
+            .unwrap();
+        assert_eq!(channels.list_entries("#chan").await.unwrap(), entries[1..]);
+        assert!(channels.list_entries("#other").await.unwrap().is_empty());
+    }
 }
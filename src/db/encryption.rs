@@ -0,0 +1,160 @@
+//! Encryption at rest for the SQLite database.
+//!
+//! When `[database.encryption]` is configured the database file is opened
+//! with SQLCipher: every connection issues `PRAGMA key` before anything
+//! else, so account password hashes, channel data and bans never reach the
+//! disk in plaintext. The key comes from the config file, an environment
+//! variable, or the output of a command (a KMS or secrets manager client).
+//!
+//! This needs SQLite built with SQLCipher (`--features sqlcipher`). A plain
+//! SQLite silently ignores `PRAGMA key`, so opening is refused unless
+//! `PRAGMA cipher_version` proves SQLCipher is present.
+//!
+//! Only the SQLite database is covered; the redb history and always-on
+//! stores are not, so put those on an encrypted filesystem if needed.
+
+use super::DbError;
+use crate::config::DatabaseEncryptionConfig;
+use sqlx::SqlitePool;
+use sqlx::sqlite::SqliteConnectOptions;
+use std::process::Command;
+use zeroize::Zeroizing;
+
+/// Resolve the database key from its configured source.
+pub fn resolve_key(config: &DatabaseEncryptionConfig) -> Result<Zeroizing<String>, DbError> {
+    let key = match (&config.key, &config.key_env, &config.key_command) {
+        (Some(key), None, None) => Zeroizing::new(key.clone()),
+        (None, Some(var), None) => std::env::var(var)
+            .map(Zeroizing::new)
+            .map_err(|_| DbError::Encryption(format!("environment variable {var} is not set")))?,
+        (None, None, Some(command)) => run_key_command(command)?,
+        _ => {
+            return Err(DbError::Encryption(
+                "set exactly one of key, key_env or key_command".to_string(),
+            ));
+        }
+    };
+    if key.is_empty() {
+        return Err(DbError::Encryption("database key is empty".to_string()));
+    }
+    Ok(key)
+}
+
+/// Run `command` through the shell and take its first line of output as the key.
+fn run_key_command(command: &str) -> Result<Zeroizing<String>, DbError> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .map_err(|e| DbError::Encryption(format!("failed to run key_command: {e}")))?;
+    let stdout = Zeroizing::new(output.stdout);
+    if !output.status.success() {
+        return Err(DbError::Encryption(format!(
+            "key_command exited with {}",
+            output.status
+        )));
+    }
+    let text = std::str::from_utf8(&stdout)
+        .map_err(|_| DbError::Encryption("key_command output is not UTF-8".to_string()))?;
+    Ok(Zeroizing::new(
+        text.lines().next().unwrap_or_default().trim().to_string(),
+    ))
+}
+
+/// Add the SQLCipher key to connection options, if there is one.
+///
+/// SQLx issues `key` before its other pragmas, as SQLCipher requires.
+pub(super) fn with_key(options: SqliteConnectOptions, key: Option<&str>) -> SqliteConnectOptions {
+    match key {
+        Some(key) => options.pragma("key", format!("'{}'", key.replace('\'', "''"))),
+        None => options,
+    }
+}
+
+/// Check that the linked SQLite is SQLCipher and that the key opens the file.
+pub(super) async fn check_key(pool: &SqlitePool) -> Result<(), DbError> {
+    let version: Option<String> = sqlx::query_scalar("PRAGMA cipher_version")
+        .fetch_optional(pool)
+        .await?;
+    let Some(version) = version.filter(|v| !v.is_empty()) else {
+        return Err(DbError::Encryption(
+            "database.encryption is set but SQLite was built without SQLCipher \
+             (rebuild with --features sqlcipher)"
+                .to_string(),
+        ));
+    };
+
+    sqlx::query("SELECT count(*) FROM sqlite_master")
+        .fetch_one(pool)
+        .await
+        .map_err(|_| {
+            DbError::Encryption("wrong key, or the database file is not encrypted".to_string())
+        })?;
+
+    tracing::info!(cipher_version = %version, "Database encryption enabled");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> DatabaseEncryptionConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn key_comes_from_exactly_one_source() {
+        let key = resolve_key(&config("key = \"hunter2\"")).unwrap();
+        assert_eq!(key.as_str(), "hunter2");
+
+        let key = resolve_key(&config("key_command = \"echo s3cret; echo ignored\"")).unwrap();
+        assert_eq!(key.as_str(), "s3cret");
+
+        assert!(matches!(
+            resolve_key(&config("")),
+            Err(DbError::Encryption(_))
+        ));
+        assert!(matches!(
+            resolve_key(&config("key = \"a\"\nkey_command = \"echo b\"")),
+            Err(DbError::Encryption(_))
+        ));
+        assert!(matches!(
+            resolve_key(&config("key_command = \"exit 3\"")),
+            Err(DbError::Encryption(_))
+        ));
+    }
+
+    #[cfg(not(feature = "sqlcipher"))]
+    #[tokio::test]
+    async fn plain_sqlite_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("encrypted.db");
+        let result = crate::db::Database::open(path.to_str().unwrap(), Some("hunter2")).await;
+        assert!(matches!(result, Err(DbError::Encryption(_))));
+    }
+
+    #[cfg(feature = "sqlcipher")]
+    #[tokio::test]
+    async fn encrypted_database_needs_its_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("encrypted.db");
+        let path = path.to_str().unwrap();
+        let db = crate::db::Database::open(path, Some("hunter2"))
+            .await
+            .unwrap();
+        db.pool().close().await;
+
+        assert!(
+            crate::db::Database::open(path, Some("hunter2"))
+                .await
+                .is_ok()
+        );
+        assert!(matches!(
+            crate::db::Database::open(path, Some("wrong")).await,
+            Err(DbError::Encryption(_))
+        ));
+        // Without a key the file is unreadable
+        assert!(crate::db::Database::open(path, None).await.is_err());
+    }
+}
@@ -0,0 +1,170 @@
+//! Schema migrations.
+//!
+//! Migrations are the numbered files in `migrations/`, embedded at build
+//! time and applied strictly in version order. SQLx records each applied
+//! version, with a checksum of its SQL, in the `_sqlx_migrations` table;
+//! an install that is missing a version, or whose applied SQL no longer
+//! matches the embedded file, fails to start instead of drifting.
+
+use super::{Database, DbError};
+use sqlx::SqlitePool;
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// Migrations embedded from `migrations/`.
+pub(super) static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// State of one embedded migration in a database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationState {
+    /// Applied at `installed_on` (UTC, as recorded by SQLite).
+    Applied { installed_on: String },
+    /// Not applied yet; runs on the next start.
+    Pending,
+    /// Applied, but the embedded SQL has changed since.
+    Modified,
+    /// A previous attempt to apply it failed.
+    Failed,
+}
+
+/// An embedded migration and its state in a database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+impl fmt::Display for MigrationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:03} {:<24} ", self.version, self.description)?;
+        match &self.state {
+            MigrationState::Applied { installed_on } => write!(f, "applied {installed_on}"),
+            MigrationState::Pending => f.write_str("pending"),
+            MigrationState::Modified => f.write_str("MODIFIED since applied"),
+            MigrationState::Failed => f.write_str("FAILED"),
+        }
+    }
+}
+
+impl Database {
+    /// Report the state of every embedded migration in the database at
+    /// `path`, without applying any.
+    ///
+    /// A database that does not exist yet has every migration pending.
+    /// `key` opens a SQLCipher-encrypted database.
+    pub async fn migration_status(
+        path: &str,
+        key: Option<&str>,
+    ) -> Result<Vec<MigrationStatus>, DbError> {
+        let applied = if Path::new(path).exists() {
+            let options = super::encryption::with_key(
+                SqliteConnectOptions::new().filename(path).read_only(true),
+                key,
+            );
+            let pool = SqlitePoolOptions::new()
+                .max_connections(1)
+                .connect_with(options)
+                .await?;
+            let applied = applied_migrations(&pool).await;
+            pool.close().await;
+            applied?
+        } else {
+            HashMap::new()
+        };
+
+        Ok(MIGRATOR
+            .iter()
+            .map(|migration| {
+                let state = match applied.get(&migration.version) {
+                    None => MigrationState::Pending,
+                    Some((_, false, _)) => MigrationState::Failed,
+                    Some((_, true, checksum)) if *checksum != *migration.checksum => {
+                        MigrationState::Modified
+                    }
+                    Some((installed_on, true, _)) => MigrationState::Applied {
+                        installed_on: installed_on.clone(),
+                    },
+                };
+                MigrationStatus {
+                    version: migration.version,
+                    description: migration.description.to_string(),
+                    state,
+                }
+            })
+            .collect())
+    }
+}
+
+/// `(installed_on, success, checksum)` of each version recorded as applied.
+async fn applied_migrations(
+    pool: &SqlitePool,
+) -> Result<HashMap<i64, (String, bool, Vec<u8>)>, DbError> {
+    let has_table: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+    )
+    .fetch_one(pool)
+    .await?;
+    if !has_table {
+        return Ok(HashMap::new());
+    }
+
+    let rows = sqlx::query_as::<_, (i64, String, bool, Vec<u8>)>(
+        "SELECT version, installed_on, success, checksum FROM _sqlx_migrations",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(version, installed_on, success, checksum)| {
+            (version, (installed_on, success, checksum))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrations_are_numbered_without_gaps() {
+        let versions: Vec<i64> = MIGRATOR.iter().map(|m| m.version).collect();
+        let expected: Vec<i64> = (1..=versions.len() as i64).collect();
+        assert_eq!(versions, expected);
+    }
+
+    #[tokio::test]
+    async fn status_reports_applied_and_pending_migrations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("slircd.db");
+        let path = path.to_str().unwrap();
+
+        let status = Database::migration_status(path, None).await.unwrap();
+        assert!(status.iter().all(|m| m.state == MigrationState::Pending));
+
+        let db = Database::new(path).await.unwrap();
+        let status = Database::migration_status(path, None).await.unwrap();
+        assert_eq!(status.len(), MIGRATOR.iter().count());
+        assert!(
+            status
+                .iter()
+                .all(|m| matches!(m.state, MigrationState::Applied { .. }))
+        );
+
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version = 12")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        sqlx::query("UPDATE _sqlx_migrations SET checksum = x'00' WHERE version = 11")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let status = Database::migration_status(path, None).await.unwrap();
+        assert_eq!(status[10].state, MigrationState::Modified);
+        assert_eq!(status[11].state, MigrationState::Pending);
+        assert_eq!(status[11].description, "crdt journal");
+    }
+}
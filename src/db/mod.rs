@@ -3,22 +3,40 @@
 //! Provides async SQLite database access using SQLx for:
 //! - NickServ accounts and nicknames
 //! - ChanServ channel registration and access lists
+//! - HostServ vhost assignments and requests
+//! - BotServ bots and channel assignments
 //! - K-lines and D-lines persistence
 //! - Message history for CHATHISTORY
+//! - Read markers (draft/read-marker)
 //!
 //! Also provides Redb-backed persistence for:
 //! - Always-on client state (bouncer functionality)
+//!
+//! The SQLite file can be encrypted at rest with SQLCipher (see [`encryption`]).
 
 mod accounts;
 pub mod always_on;
+pub mod auth;
 mod bans;
+mod bots;
 mod channels;
+pub mod encryption;
+mod migrations;
+mod read_markers;
+mod vhosts;
 
-pub use accounts::AccountRepository;
+pub use accounts::{Account, AccountRepository, CodeKind, EnforceLevel};
 pub use always_on::{AlwaysOnError, AlwaysOnStore};
-pub use bans::{BanRepository, Dline, Gline, Kline, Shun, Zline};
-pub use channels::{ChannelAkick, ChannelRecord, ChannelRepository};
+pub use bans::{BanRepository, Dline, Gline, Kline, Qline, SessionLimit, Shun, Zline};
+pub use bots::{Bot, BotRepository};
+pub use channels::{
+    ChannelAkick, ChannelListEntry, ChannelRecord, ChannelRepository, EffectiveAccess,
+};
+pub use migrations::MigrationState;
+pub use read_markers::ReadMarkerRepository;
+pub use vhosts::VhostRepository;
 
+use auth::ExternalAuth;
 use sqlx::SqlitePool;
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use std::path::Path;
@@ -48,10 +66,18 @@ pub enum DbError {
     InvalidPassword,
     #[error("unknown option: {0}")]
     UnknownOption(String),
+    #[error("invalid value: {0}")]
+    InvalidValue(String),
     #[error("channel already registered: {0}")]
     ChannelExists(String),
     #[error("insufficient access")]
     InsufficientAccess,
+    #[error("account group would contain itself")]
+    GroupCycle,
+    #[error("account was not created by the external auth provider: {0}")]
+    ExternalAccountConflict(String),
+    #[error("database encryption: {0}")]
+    Encryption(String),
     #[error("internal error: {0}")]
     Internal(String),
 }
@@ -60,6 +86,8 @@ pub enum DbError {
 #[derive(Clone)]
 pub struct Database {
     pool: SqlitePool,
+    /// External provider asked before local accounts, if configured.
+    auth: Option<ExternalAuth>,
 }
 
 impl Database {
@@ -69,8 +97,15 @@ impl Database {
     /// Maximum time a connection can remain idle before being closed.
     const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
 
-    /// Create a new database connection, running migrations if needed.
+    /// Create a new unencrypted database connection, running migrations if needed.
+    #[cfg(test)]
     pub async fn new(path: &str) -> Result<Self, DbError> {
+        Self::open(path, None).await
+    }
+
+    /// Create a new database connection, encrypted with the SQLCipher `key`
+    /// if one is given, running migrations if needed.
+    pub async fn open(path: &str, key: Option<&str>) -> Result<Self, DbError> {
         let pool = if path == ":memory:" {
             // In-memory database - use proper SQLx in-memory mode
             // Use a uniquely named shared-cache memory database per call.
@@ -104,9 +139,12 @@ impl Database {
                 tracing::warn!(path = %parent.display(), error = %e, "Failed to create database directory");
             }
 
-            let options = SqliteConnectOptions::new()
-                .filename(path)
-                .create_if_missing(true);
+            let options = encryption::with_key(
+                SqliteConnectOptions::new()
+                    .filename(path)
+                    .create_if_missing(true),
+                key,
+            );
 
             SqlitePoolOptions::new()
                 .max_connections(5)
@@ -119,6 +157,11 @@ impl Database {
 
         info!(path = %path, "Database connected");
 
+        // A wrong key or a plain SQLite build only shows on first use
+        if key.is_some() && path != ":memory:" {
+            encryption::check_key(&pool).await?;
+        }
+
         // Run embedded migrations
         Self::run_migrations(&pool).await?;
 
@@ -156,7 +199,13 @@ impl Database {
 
         info!("Database integrity check passed");
 
-        Ok(Self { pool })
+        Ok(Self { pool, auth: None })
+    }
+
+    /// Ask `provider` before the local accounts table when logging in.
+    pub fn with_auth_provider(mut self, auth: Option<ExternalAuth>) -> Self {
+        self.auth = auth;
+        self
     }
 
     /// Get reference to the underlying connection pool.
@@ -166,7 +215,7 @@ impl Database {
 
     /// Run embedded migrations.
     async fn run_migrations(pool: &SqlitePool) -> Result<(), DbError> {
-        sqlx::migrate!("./migrations")
+        migrations::MIGRATOR
             .run(pool)
             .await
             .map_err(DbError::Migration)?;
@@ -177,7 +226,7 @@ impl Database {
 
     /// Get account repository.
     pub fn accounts(&self) -> AccountRepository<'_> {
-        AccountRepository::new(&self.pool)
+        AccountRepository::new(&self.pool, self.auth.as_ref())
     }
 
     /// Get channel repository.
@@ -189,6 +238,21 @@ impl Database {
     pub fn bans(&self) -> BanRepository<'_> {
         BanRepository::new(&self.pool)
     }
+
+    /// Get vhost repository.
+    pub fn vhosts(&self) -> VhostRepository<'_> {
+        VhostRepository::new(&self.pool)
+    }
+
+    /// Get bot repository.
+    pub fn bots(&self) -> BotRepository<'_> {
+        BotRepository::new(&self.pool)
+    }
+
+    /// Get read marker repository.
+    pub fn read_markers(&self) -> ReadMarkerRepository<'_> {
+        ReadMarkerRepository::new(&self.pool)
+    }
 }
 
 impl From<sqlx::Error> for DbError {
@@ -0,0 +1,62 @@
+//! Read marker repository for draft/read-marker.
+//!
+//! Stores, per account and target, the timestamp of the newest message the
+//! account has read. Markers only move forward.
+
+use super::DbError;
+use sqlx::SqlitePool;
+
+/// Repository for read marker operations.
+pub struct ReadMarkerRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> ReadMarkerRepository<'a> {
+    /// Create a new read marker repository.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// The marker (Unix nanoseconds) `account` has for the casefolded `target`.
+    pub async fn get(&self, account: &str, target: &str) -> Result<Option<i64>, DbError> {
+        let marked_at = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT r.marked_at FROM read_markers r
+            JOIN accounts a ON a.id = r.account_id
+            WHERE a.name = ? AND r.target = ?
+            "#,
+        )
+        .bind(account)
+        .bind(target)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(marked_at)
+    }
+
+    /// Advance the marker to `marked_at` unless it is already later.
+    ///
+    /// Does nothing for an account that is not registered.
+    pub async fn advance(
+        &self,
+        account: &str,
+        target: &str,
+        marked_at: i64,
+    ) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO read_markers (account_id, target, marked_at)
+            SELECT id, ?, ? FROM accounts WHERE name = ?
+            ON CONFLICT(account_id, target) DO UPDATE SET
+                marked_at = max(marked_at, excluded.marked_at)
+            "#,
+        )
+        .bind(target)
+        .bind(marked_at)
+        .bind(account)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+//! Vhost repository for HostServ functionality.
+//!
+//! Stores the virtual host assigned to each account and any pending request.
+
+use super::DbError;
+use sqlx::SqlitePool;
+
+/// A pending vhost request, for operator review.
+#[derive(Debug, Clone)]
+pub struct VhostRequest {
+    pub account: String,
+    pub vhost: String,
+    pub requested_at: i64,
+}
+
+/// Repository for vhost operations.
+pub struct VhostRepository<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> VhostRepository<'a> {
+    /// Create a new vhost repository.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// The vhost to apply when the account logs in: assigned and switched on.
+    pub async fn active(&self, account_id: i64) -> Result<Option<String>, DbError> {
+        let vhost = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT vhost FROM account_vhosts
+            WHERE account_id = ? AND enabled = 1 AND vhost IS NOT NULL
+            "#,
+        )
+        .bind(account_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(vhost)
+    }
+
+    /// Record a vhost request, replacing any earlier pending one.
+    pub async fn request(&self, account_id: i64, vhost: &str) -> Result<(), DbError> {
+        sqlx::query(
+            r#"
+            INSERT INTO account_vhosts (account_id, requested, requested_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT(account_id) DO UPDATE SET
+                requested = excluded.requested,
+                requested_at = excluded.requested_at
+            "#,
+        )
+        .bind(account_id)
+        .bind(vhost)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Assign the pending request as the account's vhost and switch it on.
+    ///
+    /// Returns the activated vhost, or `None` if nothing was pending.
+    pub async fn activate(&self, account_id: i64, set_by: &str) -> Result<Option<String>, DbError> {
+        let vhost = sqlx::query_scalar::<_, String>(
+            r#"
+            UPDATE account_vhosts
+            SET vhost = requested, enabled = 1, set_by = ?, set_at = ?,
+                requested = NULL, requested_at = NULL
+            WHERE account_id = ? AND requested IS NOT NULL
+            RETURNING vhost
+            "#,
+        )
+        .bind(set_by)
+        .bind(chrono::Utc::now().timestamp())
+        .bind(account_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(vhost)
+    }
+
+    /// Discard the pending request. Returns `false` if nothing was pending.
+    pub async fn reject(&self, account_id: i64) -> Result<bool, DbError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE account_vhosts
+            SET requested = NULL, requested_at = NULL
+            WHERE account_id = ? AND requested IS NOT NULL
+            "#,
+        )
+        .bind(account_id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Switch the assigned vhost on or off.
+    ///
+    /// Returns the assigned vhost, or `None` if the account has none.
+    pub async fn set_enabled(
+        &self,
+        account_id: i64,
+        enabled: bool,
+    ) -> Result<Option<String>, DbError> {
+        let vhost = sqlx::query_scalar::<_, String>(
+            r#"
+            UPDATE account_vhosts
+            SET enabled = ?
+            WHERE account_id = ? AND vhost IS NOT NULL
+            RETURNING vhost
+            "#,
+        )
+        .bind(enabled)
+        .bind(account_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(vhost)
+    }
+
+    /// All pending requests, oldest first.
+    pub async fn pending_requests(&self) -> Result<Vec<VhostRequest>, DbError> {
+        let rows = sqlx::query_as::<_, (String, String, i64)>(
+            r#"
+            SELECT a.name, v.requested, v.requested_at
+            FROM account_vhosts v
+            JOIN accounts a ON a.id = v.account_id
+            WHERE v.requested IS NOT NULL
+            ORDER BY v.requested_at, a.name
+            "#,
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(account, vhost, requested_at)| VhostRequest {
+                account,
+                vhost,
+                requested_at,
+            })
+            .collect())
+    }
+}
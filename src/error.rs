@@ -28,6 +28,10 @@ pub enum HandlerError {
     #[error("erroneous nickname: {0}")]
     ErroneousNickname(String),
 
+    /// Nickname is reserved by a Q-line: (nick, reason).
+    #[error("reserved nickname: {0} ({1})")]
+    NicknameReserved(String, String),
+
     #[error("not registered")]
     NotRegistered,
 
@@ -66,6 +70,11 @@ pub enum HandlerError {
     /// Handled specially by the handshake loop - not an error condition.
     #[error("STARTTLS upgrade requested")]
     StartTls,
+
+    /// Signal to compress the connection after COMPRESS.
+    /// Handled specially by the handshake loop - not an error condition.
+    #[error("compression requested")]
+    Compress,
 }
 
 impl HandlerError {
@@ -77,6 +86,7 @@ impl HandlerError {
             Self::NoTextToSend => "no_text_to_send",
             Self::NicknameInUse(_) => "nickname_in_use",
             Self::ErroneousNickname(_) => "erroneous_nickname",
+            Self::NicknameReserved(..) => "nickname_reserved",
             Self::NotRegistered => "not_registered",
             Self::AccessDenied => "access_denied",
             Self::AlreadyRegistered => "already_registered",
@@ -89,6 +99,7 @@ impl HandlerError {
             Self::Quit(_) => "quit",
             Self::Internal(_) => "internal_error",
             Self::StartTls => "starttls",
+            Self::Compress => "compress",
         }
     }
 
@@ -103,6 +114,14 @@ impl HandlerError {
             Self::NoTextToSend => Response::err_notexttosend(nick),
             Self::NicknameInUse(bad_nick) => Response::err_nicknameinuse(nick, bad_nick),
             Self::ErroneousNickname(bad_nick) => Response::err_erroneusnickname(nick, bad_nick),
+            Self::NicknameReserved(bad_nick, reason) => Message::from(Command::Response(
+                Response::ERR_ERRONEOUSNICKNAME,
+                vec![
+                    nick.to_string(),
+                    bad_nick.clone(),
+                    format!("Erroneous Nickname: {reason}"),
+                ],
+            )),
             Self::AlreadyRegistered => Response::err_alreadyregistred(nick),
             Self::NoSuchChannel(bad_chan) => Response::err_nosuchchannel(nick, bad_chan),
             Self::UnknownCommand(cmd) => Response::err_unknowncommand(nick, cmd),
@@ -116,6 +135,7 @@ impl HandlerError {
             Self::Quit(_) => return None,
             Self::Internal(_) => return None,
             Self::StartTls => return None, // Handled specially by handshake loop
+            Self::Compress => return None,
         };
 
         // Set the prefix to the server name
@@ -152,6 +172,9 @@ pub enum ChannelError {
     #[error("cannot knock on this channel")]
     CannotKnock,
 
+    #[error("too many knocks on this channel")]
+    KnockThrottled,
+
     #[error("channel is open")]
     ChanOpen,
 
@@ -176,6 +199,9 @@ pub enum ChannelError {
     #[error("cannot join channel (+R)")]
     NeedReggedNick,
 
+    #[error("cannot join channel (+j)")]
+    JoinThrottled,
+
     #[error("cannot join channel (+z)")]
     SecureOnlyChan,
 
@@ -194,6 +220,12 @@ pub enum ChannelError {
     #[error("channel is full, redirecting to {0}")]
     Redirect(String),
 
+    #[error("cannot create channel - you need to be identified with services")]
+    CreateNeedsAccount,
+
+    #[error("cannot create channel - you need to be an IRC operator")]
+    CreateNeedsOper,
+
     #[error("{0}")]
     Generic(String),
 }
@@ -278,6 +310,14 @@ impl ChannelError {
                         .to_string(),
                 ],
             ),
+            Self::JoinThrottled => (
+                Response::ERR_UNAVAILRESOURCE,
+                vec![
+                    nick.to_string(),
+                    channel.to_string(),
+                    "Cannot join channel (+j) - too many joins, try again later".to_string(),
+                ],
+            ),
             Self::SecureOnlyChan => (
                 Response::ERR_SECUREONLYCHAN,
                 vec![
@@ -303,6 +343,22 @@ impl ChannelError {
                     "Cannot join channel (+A) - you need to be a server administrator".to_string(),
                 ],
             ),
+            Self::CreateNeedsAccount => (
+                Response::ERR_NEEDREGGEDNICK,
+                vec![
+                    nick.to_string(),
+                    channel.to_string(),
+                    "Cannot create channel - you need to be identified with services".to_string(),
+                ],
+            ),
+            Self::CreateNeedsOper => (
+                Response::ERR_OPERONLY,
+                vec![
+                    nick.to_string(),
+                    channel.to_string(),
+                    "Cannot create channel - you need to be an IRC operator".to_string(),
+                ],
+            ),
             Self::NoKicksActive => (
                 Response::ERR_UNKNOWNERROR,
                 vec![
@@ -332,8 +388,28 @@ impl ChannelError {
                     "Forwarding to another channel".to_string(),
                 ],
             ),
+            Self::CannotKnock => (
+                Response::ERR_CANNOTKNOCK,
+                vec![nick.to_string(), format!("Cannot knock on {channel} (+K)")],
+            ),
+            Self::KnockThrottled => (
+                Response::ERR_TOOMANYKNOCK,
+                vec![
+                    nick.to_string(),
+                    channel.to_string(),
+                    "Too many KNOCKs (channel)".to_string(),
+                ],
+            ),
+            Self::ChanOpen => (
+                Response::ERR_CHANOPEN,
+                vec![
+                    nick.to_string(),
+                    channel.to_string(),
+                    "Channel is open, just join it".to_string(),
+                ],
+            ),
             // These don't have standard IRC numerics - use generic error
-            Self::CannotKnock | Self::ChanOpen | Self::ChannelTombstone | Self::SessionInvalid => (
+            Self::ChannelTombstone | Self::SessionInvalid => (
                 Response::ERR_UNKNOWNERROR,
                 vec![nick.to_string(), channel.to_string(), self.to_string()],
             ),
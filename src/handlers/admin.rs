@@ -283,8 +283,12 @@ impl PostRegHandler for SanickHandler {
             .map(|u| u.value().clone());
         if let Some(user_arc) = user_arc {
             let mut user = user_arc.write().await;
-            user.nick = new_nick.to_string();
+            user.set_nick(new_nick.to_string(), ctx.matrix.clock());
         }
+        ctx.matrix
+            .user_manager
+            .notify_observer(&target_uid, None)
+            .await;
 
         // Broadcast NICK change to all channels the user is in
         let target_channels = {
@@ -419,6 +423,7 @@ impl PostRegHandler for SamodeHandler {
                     target_uids,
                     force: true,
                     nanotime: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+                    list_expires_at: None,
                 },
                 reply_tx,
             })
@@ -2,8 +2,11 @@
 //!
 //! Common types and helpers used across ban handlers.
 
-use super::super::Context;
-use slirc_proto::wildcard_match;
+use crate::state::Matrix;
+use crate::state::dashmap_ext::DashMapExt;
+use slirc_proto::{units, wildcard_match};
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Types of bans for matching purposes.
 #[derive(Debug, Clone, Copy)]
@@ -40,22 +43,36 @@ impl BanType {
 /// - K-line/G-line: Match against `user@host`
 /// - D-line/Z-line: Match against IP with CIDR support
 /// - R-line: Match against realname
-pub async fn disconnect_matching_ban<S>(
-    ctx: &Context<'_, S>,
+///
+/// Only users in the matching index buckets are checked when the pattern has
+/// a literal host, IP prefix or ident; other patterns scan every user.
+pub async fn disconnect_matching_ban(
+    matrix: &Arc<Matrix>,
     ban_type: BanType,
     pattern: &str,
     reason: &str,
 ) -> usize {
     let mut to_disconnect = Vec::with_capacity(4); // Ban typically affects few users
 
+    let user_manager = &matrix.user_manager;
+    let candidates = match ban_type {
+        BanType::Kline | BanType::Gline => user_manager.index.candidates(pattern),
+        BanType::Dline | BanType::Zline => user_manager.index.host_candidates(pattern),
+        BanType::Rline => None,
+    };
+
     // Collect user Arc + UID pairs to release DashMap lock before awaiting
-    let user_data: Vec<_> = ctx
-        .matrix
-        .user_manager
-        .users
-        .iter()
-        .map(|e| (e.key().clone(), e.value().clone()))
-        .collect();
+    let user_data: Vec<_> = match candidates {
+        Some(uids) => uids
+            .into_iter()
+            .filter_map(|uid| user_manager.users.get_cloned(&uid).map(|arc| (uid, arc)))
+            .collect(),
+        None => user_manager
+            .users
+            .iter()
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect(),
+    };
 
     // Collect matching users
     for (uid, user_arc) in user_data {
@@ -80,7 +97,7 @@ pub async fn disconnect_matching_ban<S>(
     // Disconnect matching users
     let quit_reason = format!("{}: {}", ban_type.name(), reason);
     for uid in &to_disconnect {
-        ctx.matrix.disconnect_user(uid, &quit_reason).await;
+        matrix.disconnect_user(uid, &quit_reason).await;
     }
 
     to_disconnect.len()
@@ -131,90 +148,26 @@ pub fn cidr_match(cidr: &str, ip: &str) -> bool {
     (network_u32 & mask) == (ip_u32 & mask)
 }
 
-/// Parse a duration string into seconds.
+/// Parse a ban duration into seconds.
 ///
-/// Supports formats like:
-/// - `30` or `30s` - 30 seconds
-/// - `5m` - 5 minutes
-/// - `2h` - 2 hours
-/// - `1d` - 1 day
-/// - `1w` - 1 week
-/// - `1d2h30m` - combined
+/// Accepts a bare number of seconds or unit groups such as `30m`, `2h`,
+/// `1w` or `1d2h30m` (see [`slirc_proto::units::parse_duration`]).
 ///
-/// Returns `None` for permanent bans (0 or empty string).
+/// Returns `None` for permanent bans (`0` or empty) and for anything that
+/// is not a valid duration, so callers can tell a duration from a mask.
 pub fn parse_duration(s: &str) -> Option<i64> {
-    if s.is_empty() || s == "0" {
-        return None; // Permanent
-    }
-
-    // Try parsing as plain seconds first
-    if let Ok(secs) = s.parse::<i64>() {
-        return if secs <= 0 { None } else { Some(secs) };
-    }
-
-    let mut total_seconds: i64 = 0;
-    let mut current_num = String::new();
-
-    for c in s.chars() {
-        if c.is_ascii_digit() {
-            current_num.push(c);
-        } else {
-            let num: i64 = current_num.parse().unwrap_or(0);
-            current_num.clear();
-
-            let multiplier = match c {
-                's' | 'S' => 1,
-                'm' | 'M' => 60,
-                'h' | 'H' => 3600,
-                'd' | 'D' => 86400,
-                'w' | 'W' => 604800,
-                _ => return None, // Invalid format
-            };
-
-            total_seconds += num * multiplier;
-        }
-    }
-
-    // Handle trailing number (interpreted as seconds)
-    if !current_num.is_empty()
-        && let Ok(num) = current_num.parse::<i64>()
-    {
-        total_seconds += num;
-    }
-
-    if total_seconds <= 0 {
-        None
-    } else {
-        Some(total_seconds)
-    }
+    units::parse_duration(s)
+        .ok()
+        .and_then(|d| i64::try_from(d.as_secs()).ok())
+        .filter(|&secs| secs > 0)
 }
 
 /// Format a duration in seconds to a human-readable string.
 pub fn format_duration(seconds: i64) -> String {
-    if seconds <= 0 {
-        return "permanent".to_string();
+    match u64::try_from(seconds) {
+        Ok(secs) if secs > 0 => units::format_duration(Duration::from_secs(secs)),
+        _ => "permanent".to_string(),
     }
-
-    let days = seconds / 86400;
-    let hours = (seconds % 86400) / 3600;
-    let minutes = (seconds % 3600) / 60;
-    let secs = seconds % 60;
-
-    let mut parts = Vec::new();
-    if days > 0 {
-        parts.push(format!("{}d", days));
-    }
-    if hours > 0 {
-        parts.push(format!("{}h", hours));
-    }
-    if minutes > 0 {
-        parts.push(format!("{}m", minutes));
-    }
-    if secs > 0 || parts.is_empty() {
-        parts.push(format!("{}s", secs));
-    }
-
-    parts.join("")
 }
 
 #[cfg(test)]
@@ -232,6 +185,11 @@ mod tests {
         assert_eq!(parse_duration("1d"), Some(86400));
         assert_eq!(parse_duration("1w"), Some(604800));
         assert_eq!(parse_duration("1d2h30m"), Some(86400 + 7200 + 1800));
+        // Strict: stray characters and trailing bare numbers are not durations
+        assert_eq!(parse_duration("1h30"), None);
+        assert_eq!(parse_duration("d"), None);
+        assert_eq!(parse_duration("*@host"), None);
+        assert_eq!(parse_duration("99999999999999999999w"), None);
     }
 
     #[test]
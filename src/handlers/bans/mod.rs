@@ -7,19 +7,26 @@
 //! - ZLINE/UNZLINE: Global IP ban (skips DNS)
 //! - RLINE/UNRLINE: Ban by realname (GECOS)
 //! - SHUN/UNSHUN: Silently ignore commands from matching users
+//! - RESV/UNRESV: Reserve nicknames and channel names (Q-lines)
 
 use crate::handlers::PostRegHandler;
+use crate::handlers::core::traits::ServerHandler;
 use std::collections::HashMap;
 
 mod common;
+mod resv;
 mod shun;
 mod xlines;
 
+pub(crate) use common::{BanType, disconnect_matching_ban, format_duration, parse_duration};
+
 // Re-export handlers
+pub use resv::{ResvHandler, UnresvHandler};
 pub use shun::{ShunHandler, UnshunHandler};
 pub use xlines::{
-    DlineHandler, GlineHandler, KlineHandler, RlineHandler, UndlineHandler, UnglineHandler,
-    UnklineHandler, UnrlineHandler, UnzlineHandler, ZlineHandler,
+    DlineHandler, GlineHandler, KlineHandler, RlineHandler, ServerGlineHandler, ServerRlineHandler,
+    ServerUnglineHandler, ServerUnrlineHandler, ServerUnzlineHandler, ServerZlineHandler,
+    UndlineHandler, UnglineHandler, UnklineHandler, UnrlineHandler, UnzlineHandler, ZlineHandler,
 };
 
 pub fn register(map: &mut HashMap<&'static str, Box<dyn PostRegHandler>>) {
@@ -35,4 +42,16 @@ pub fn register(map: &mut HashMap<&'static str, Box<dyn PostRegHandler>>) {
     map.insert("UNRLINE", Box::new(UnrlineHandler::unrline()));
     map.insert("SHUN", Box::new(ShunHandler));
     map.insert("UNSHUN", Box::new(UnshunHandler));
+    map.insert("RESV", Box::new(ResvHandler));
+    map.insert("UNRESV", Box::new(UnresvHandler));
+}
+
+/// Register the handlers for global bans arriving from peer servers.
+pub fn register_server(map: &mut HashMap<&'static str, Box<dyn ServerHandler>>) {
+    map.insert("GLINE", Box::new(ServerGlineHandler::gline()));
+    map.insert("UNGLINE", Box::new(ServerUnglineHandler::ungline()));
+    map.insert("ZLINE", Box::new(ServerZlineHandler::zline()));
+    map.insert("UNZLINE", Box::new(ServerUnzlineHandler::unzline()));
+    map.insert("RLINE", Box::new(ServerRlineHandler::rline()));
+    map.insert("UNRLINE", Box::new(ServerUnrlineHandler::unrline()));
 }
@@ -0,0 +1,159 @@
+//! RESV command handlers (Q-lines).
+//!
+//! RESV reserves nicknames or channel names so that ordinary users cannot
+//! take them. Masks starting with a channel prefix reserve channels; all
+//! other masks reserve nicknames. Existing holders are not affected.
+
+use super::common::{format_duration, parse_duration};
+use crate::db::Qline;
+use crate::handlers::{Context, HandlerResult, PostRegHandler, server_notice};
+use crate::state::RegisteredState;
+use crate::{require_arg_or_reply, require_oper_cap};
+use async_trait::async_trait;
+use slirc_proto::MessageRef;
+
+/// Handler for RESV command.
+///
+/// `RESV [time] <mask> [reason]`
+///
+/// Reserves a nickname or channel mask (Q-line).
+pub struct ResvHandler;
+
+#[async_trait]
+impl PostRegHandler for ResvHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, RegisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let server_name = ctx.server_name();
+        let nick = ctx.nick();
+
+        let Some(_cap) = require_oper_cap!(ctx, "RESV", request_qline_cap) else {
+            return Ok(());
+        };
+        let Some(first_arg) = require_arg_or_reply!(ctx, msg, 0, "RESV") else {
+            return Ok(());
+        };
+
+        let (mask, duration, reason) = if let Some(dur) = parse_duration(first_arg) {
+            let Some(mask) = require_arg_or_reply!(ctx, msg, 1, "RESV") else {
+                return Ok(());
+            };
+            (mask, Some(dur), msg.arg(2).unwrap_or("Reserved"))
+        } else {
+            (first_arg, None, msg.arg(1).unwrap_or("Reserved"))
+        };
+
+        if let Err(e) = ctx
+            .db
+            .bans()
+            .add_qline(mask, Some(reason), nick, duration)
+            .await
+        {
+            tracing::error!(error = %e, "Failed to add Q-line to database");
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        ctx.matrix.security_manager.qlines.insert(
+            mask.to_string(),
+            Qline {
+                mask: mask.to_string(),
+                reason: Some(reason.to_string()),
+                set_by: nick.to_string(),
+                set_at: now,
+                expires_at: duration.map(|d| now.saturating_add(d)),
+            },
+        );
+
+        tracing::info!(
+            target: "audit",
+            oper = %nick,
+            mask = %mask,
+            reason = %reason,
+            duration = ?duration,
+            "RESV added"
+        );
+
+        let duration_suffix = match duration {
+            Some(d) => format!(" [expires in {}]", format_duration(d)),
+            None => String::new(),
+        };
+        ctx.sender
+            .send(server_notice(
+                server_name,
+                nick,
+                format!("Q-line added: {mask} ({reason}){duration_suffix}"),
+            ))
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Handler for UNRESV command.
+///
+/// `UNRESV <mask>`
+///
+/// Removes an operator-set Q-line. Q-lines from the config file can only be
+/// removed by editing the config and issuing REHASH.
+pub struct UnresvHandler;
+
+#[async_trait]
+impl PostRegHandler for UnresvHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, RegisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let server_name = ctx.server_name();
+        let nick = ctx.nick();
+
+        let Some(_cap) = require_oper_cap!(ctx, "UNRESV", request_qline_cap) else {
+            return Ok(());
+        };
+        let Some(mask) = require_arg_or_reply!(ctx, msg, 0, "UNRESV") else {
+            return Ok(());
+        };
+
+        // Q-lines match case-insensitively, so `UNRESV Foo*` lifts `RESV foo*`
+        let stored = ctx
+            .matrix
+            .security_manager
+            .qlines
+            .iter()
+            .find(|q| q.key().eq_ignore_ascii_case(mask))
+            .map(|q| q.key().clone());
+        let mask = stored.as_deref().unwrap_or(mask);
+
+        let db_removed = match ctx.db.bans().remove_qline(mask).await {
+            Ok(r) => r,
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to remove Q-line from database");
+                false
+            }
+        };
+        let cache_removed = ctx.matrix.security_manager.qlines.remove(mask).is_some();
+
+        let text = if db_removed || cache_removed {
+            tracing::info!(target: "audit", oper = %nick, mask = %mask, "RESV removed");
+            format!("Q-line removed: {mask}")
+        } else if ctx
+            .matrix
+            .hot_config
+            .read()
+            .qlines
+            .iter()
+            .any(|q| q.mask.eq_ignore_ascii_case(mask))
+        {
+            format!("Q-line {mask} is set in the config file and cannot be removed with UNRESV")
+        } else {
+            format!("Q-line not found: {mask}")
+        };
+        ctx.sender
+            .send(server_notice(server_name, nick, text))
+            .await?;
+
+        Ok(())
+    }
+}
@@ -4,6 +4,7 @@
 //! This is less disruptive than traditional bans and useful for dealing with
 //! automated abuse.
 
+use super::common::{format_duration, parse_duration};
 use crate::db::Shun;
 use crate::handlers::{Context, HandlerResult, PostRegHandler, server_notice};
 use crate::state::RegisteredState;
@@ -32,13 +33,25 @@ impl PostRegHandler for ShunHandler {
         let Some(_cap) = require_oper_cap!(ctx, "SHUN", request_shun_cap) else {
             return Ok(());
         };
-        let Some(mask) = require_arg_or_reply!(ctx, msg, 0, "SHUN") else {
+        let Some(first_arg) = require_arg_or_reply!(ctx, msg, 0, "SHUN") else {
             return Ok(());
         };
-        let reason = msg.arg(1).unwrap_or("Shunned");
+        let (mask, duration, reason) = if let Some(dur) = parse_duration(first_arg) {
+            let Some(mask) = require_arg_or_reply!(ctx, msg, 1, "SHUN") else {
+                return Ok(());
+            };
+            (mask, Some(dur), msg.arg(2).unwrap_or("Shunned"))
+        } else {
+            (first_arg, None, msg.arg(1).unwrap_or("Shunned"))
+        };
 
         // Store shun in database
-        if let Err(e) = ctx.db.bans().add_shun(mask, Some(reason), nick, None).await {
+        if let Err(e) = ctx
+            .db
+            .bans()
+            .add_shun(mask, Some(reason), nick, duration)
+            .await
+        {
             tracing::error!(error = %e, "Failed to add shun to database");
         } else {
             // Also add to in-memory cache for fast lookup
@@ -50,17 +63,17 @@ impl PostRegHandler for ShunHandler {
                     reason: Some(reason.to_string()),
                     set_by: nick.to_string(),
                     set_at: now,
-                    expires_at: None,
+                    expires_at: duration.map(|d| now.saturating_add(d)),
                 },
             );
 
             // Broadcast to peers (SHUN is global)
-            ctx.matrix.sync_manager.on_ban_add(
+            ctx.matrix.observers.on_ban_add(
                 GlobalBanType::Shun,
                 mask,
                 reason,
                 nick,
-                None, // duration (permanent)
+                duration,
                 None, // source (local)
             );
         }
@@ -70,6 +83,7 @@ impl PostRegHandler for ShunHandler {
             oper = %nick,
             mask = %mask,
             reason = %reason,
+            duration = ?duration,
             "SHUN added"
         );
 
@@ -78,7 +92,13 @@ impl PostRegHandler for ShunHandler {
             .send(server_notice(
                 server_name,
                 nick,
-                format!("Shun added: {mask} ({reason})"),
+                match duration {
+                    Some(d) => format!(
+                        "Shun added: {mask} ({reason}) [expires in {}]",
+                        format_duration(d)
+                    ),
+                    None => format!("Shun added: {mask} ({reason})"),
+                },
             ))
             .await?;
 
@@ -126,7 +146,10 @@ impl PostRegHandler for UnshunHandler {
 
         if removed {
             // Broadcast removal to peers (SHUN is global)
-            ctx.matrix.sync_manager.on_ban_remove(
+            ctx.matrix
+                .sync_manager
+                .record_ban_removal(GlobalBanType::Shun, mask);
+            ctx.matrix.observers.on_ban_remove(
                 GlobalBanType::Shun,
                 mask,
                 None, // source (local)
@@ -22,6 +22,10 @@ use std::time::Duration;
 
 use crate::state::observer::GlobalBanType;
 
+mod remote;
+
+pub use remote::{ServerBanAddHandler, ServerBanRemoveHandler};
+
 // -----------------------------------------------------------------------------
 // BanConfig Trait
 // -----------------------------------------------------------------------------
@@ -151,7 +155,7 @@ impl<C: BanConfig> PostRegHandler for GenericBanAddHandler<C> {
         // Broadcast global bans to peer servers (Phase 3: Distributed Security)
         if let Some(global_type) = self.config.global_ban_type() {
             use crate::state::observer::StateObserver;
-            ctx.matrix.sync_manager.on_ban_add(
+            ctx.matrix.observers.on_ban_add(
                 global_type,
                 target,
                 reason,
@@ -163,7 +167,7 @@ impl<C: BanConfig> PostRegHandler for GenericBanAddHandler<C> {
 
         // Disconnect matching users
         let disconnected =
-            disconnect_matching_ban(ctx, self.config.ban_type(), target, reason).await;
+            disconnect_matching_ban(ctx.matrix, self.config.ban_type(), target, reason).await;
 
         // Format confirmation message
         let duration_text = duration.map(format_duration).unwrap_or_default();
@@ -255,7 +259,10 @@ impl<C: BanConfig> PostRegHandler for GenericBanRemoveHandler<C> {
             // Broadcast global ban removal to peer servers (Phase 3: Distributed Security)
             if let Some(global_type) = self.config.global_ban_type() {
                 use crate::state::observer::StateObserver;
-                ctx.matrix.sync_manager.on_ban_remove(
+                ctx.matrix
+                    .sync_manager
+                    .record_ban_removal(global_type, target);
+                ctx.matrix.observers.on_ban_remove(
                     global_type,
                     target,
                     None, // Local origin
@@ -447,7 +454,7 @@ simple_ban_config! {
         db_add: |db, target, reason, oper, duration| db.bans().add_kline(target, Some(reason), oper, duration).await,
         db_remove: |db, target| db.bans().remove_kline(target).await,
         cache_add: |matrix, target, reason, duration| {
-            let expires_at = duration.map(|d| chrono::Utc::now().timestamp() + d);
+            let expires_at = duration.map(|d| chrono::Utc::now().timestamp().saturating_add(d));
             matrix.security_manager.ban_cache.add_kline(target.to_string(), reason.to_string(), expires_at)
         },
         cache_remove: |matrix, target| matrix.security_manager.ban_cache.remove_kline(target),
@@ -469,7 +476,7 @@ simple_ban_config! {
         db_add: |db, target, reason, oper, duration| db.bans().add_gline(target, Some(reason), oper, duration).await,
         db_remove: |db, target| db.bans().remove_gline(target).await,
         cache_add: |matrix, target, reason, duration| {
-            let expires_at = duration.map(|d| chrono::Utc::now().timestamp() + d);
+            let expires_at = duration.map(|d| chrono::Utc::now().timestamp().saturating_add(d));
             matrix.security_manager.ban_cache.add_gline(target.to_string(), reason.to_string(), expires_at)
         },
         cache_remove: |matrix, target| matrix.security_manager.ban_cache.remove_gline(target),
@@ -722,6 +729,21 @@ pub type RlineHandler = GenericBanAddHandler<RlineConfig>;
 /// R-line remove handler.
 pub type UnrlineHandler = GenericBanRemoveHandler<RlineConfig>;
 
+/// Remote G-line add handler.
+pub type ServerGlineHandler = ServerBanAddHandler<GlineConfig>;
+/// Remote G-line remove handler.
+pub type ServerUnglineHandler = ServerBanRemoveHandler<GlineConfig>;
+
+/// Remote Z-line add handler.
+pub type ServerZlineHandler = ServerBanAddHandler<ZlineConfig>;
+/// Remote Z-line remove handler.
+pub type ServerUnzlineHandler = ServerBanRemoveHandler<ZlineConfig>;
+
+/// Remote R-line add handler.
+pub type ServerRlineHandler = ServerBanAddHandler<RlineConfig>;
+/// Remote R-line remove handler.
+pub type ServerUnrlineHandler = ServerBanRemoveHandler<RlineConfig>;
+
 // -----------------------------------------------------------------------------
 // Constructor Functions (for Registry)
 // -----------------------------------------------------------------------------
@@ -795,3 +817,45 @@ impl UnrlineHandler {
         Self::new(RlineConfig)
     }
 }
+
+impl ServerGlineHandler {
+    /// Create a new remote G-line add handler.
+    pub const fn gline() -> Self {
+        Self::new(GlineConfig)
+    }
+}
+
+impl ServerUnglineHandler {
+    /// Create a new remote G-line remove handler.
+    pub const fn ungline() -> Self {
+        Self::new(GlineConfig)
+    }
+}
+
+impl ServerZlineHandler {
+    /// Create a new remote Z-line add handler.
+    pub const fn zline() -> Self {
+        Self::new(ZlineConfig)
+    }
+}
+
+impl ServerUnzlineHandler {
+    /// Create a new remote Z-line remove handler.
+    pub const fn unzline() -> Self {
+        Self::new(ZlineConfig)
+    }
+}
+
+impl ServerRlineHandler {
+    /// Create a new remote R-line add handler.
+    pub const fn rline() -> Self {
+        Self::new(RlineConfig)
+    }
+}
+
+impl ServerUnrlineHandler {
+    /// Create a new remote R-line remove handler.
+    pub const fn unrline() -> Self {
+        Self::new(RlineConfig)
+    }
+}
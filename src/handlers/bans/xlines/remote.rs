@@ -0,0 +1,203 @@
+//! Global X-lines received from peer servers.
+//!
+//! A G/Z/R-line set anywhere on the network arrives as
+//! `:<server> GLINE <mask> <duration> <setter> :<reason>` (duration 0 is
+//! permanent), is stored and enforced as if it had been set here, and is
+//! relayed to our other links. Removals arrive as `UNGLINE <mask>` etc.
+
+use super::BanConfig;
+use crate::handlers::bans::common::{disconnect_matching_ban, parse_duration};
+use crate::handlers::core::traits::ServerHandler;
+use crate::handlers::{Context, HandlerError, HandlerResult};
+use crate::state::ServerState;
+use crate::state::observer::StateObserver;
+use async_trait::async_trait;
+use slirc_proto::MessageRef;
+use slirc_proto::sync::clock::ServerId;
+
+/// Generic handler for a global ban added on another server.
+pub struct ServerBanAddHandler<C: BanConfig> {
+    config: C,
+}
+
+impl<C: BanConfig> ServerBanAddHandler<C> {
+    /// Create a new handler with the given config.
+    pub const fn new(config: C) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl<C: BanConfig> ServerHandler for ServerBanAddHandler<C> {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, ServerState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let Some(global_type) = self.config.global_ban_type() else {
+            return Ok(());
+        };
+        let mask = msg.arg(0).ok_or(HandlerError::NeedMoreParams)?;
+
+        // Peers without durations send `<mask> :<reason>`
+        let (duration, setter, reason) = match (msg.arg(1), msg.arg(2), msg.arg(3)) {
+            (Some(duration), Some(setter), Some(reason)) => {
+                (parse_duration(duration), setter.to_string(), reason)
+            }
+            (reason, _, _) => (
+                None,
+                ctx.state.name.clone(),
+                reason.unwrap_or("No reason given"),
+            ),
+        };
+
+        if let Err(e) = self
+            .config
+            .add_to_db(ctx.db, mask, reason, &setter, duration)
+            .await
+        {
+            tracing::error!(error = %e, "Failed to add remote {} to database", self.config.command_name());
+        }
+        self.config
+            .add_to_cache(ctx.matrix, mask, reason, &setter, duration)
+            .await;
+
+        let disconnected =
+            disconnect_matching_ban(ctx.matrix, self.config.ban_type(), mask, reason).await;
+
+        tracing::info!(
+            target: "audit",
+            via = %ctx.state.name,
+            setter = %setter,
+            target = %mask,
+            reason = %reason,
+            duration = ?duration,
+            disconnected = disconnected,
+            cmd = self.config.command_name(),
+            "Remote {} added", self.config.command_name()
+        );
+
+        ctx.matrix.observers.on_ban_add(
+            global_type,
+            mask,
+            reason,
+            &setter,
+            duration,
+            Some(ServerId::new(ctx.state.sid.clone())),
+        );
+
+        Ok(())
+    }
+}
+
+/// Generic handler for a global ban removed on another server.
+pub struct ServerBanRemoveHandler<C: BanConfig> {
+    config: C,
+}
+
+impl<C: BanConfig> ServerBanRemoveHandler<C> {
+    /// Create a new handler with the given config.
+    pub const fn new(config: C) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl<C: BanConfig> ServerHandler for ServerBanRemoveHandler<C> {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, ServerState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let Some(global_type) = self.config.global_ban_type() else {
+            return Ok(());
+        };
+        let mask = msg.arg(0).ok_or(HandlerError::NeedMoreParams)?;
+
+        if let Err(e) = self.config.remove_from_db(ctx.db, mask).await {
+            tracing::error!(error = %e, "Failed to remove remote {} from database", self.config.command_name());
+        }
+        self.config.remove_from_cache(ctx.matrix, mask).await;
+
+        tracing::info!(
+            target: "audit",
+            via = %ctx.state.name,
+            target = %mask,
+            cmd = self.config.unset_command_name(),
+            "Remote {} removed", self.config.command_name()
+        );
+
+        ctx.matrix
+            .sync_manager
+            .record_ban_removal(global_type, mask);
+        ctx.matrix.observers.on_ban_remove(
+            global_type,
+            mask,
+            Some(ServerId::new(ctx.state.sid.clone())),
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::Command;
+
+    #[tokio::test]
+    async fn remote_gline_is_enforced_and_relayed() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let _origin = tm.peer("00A").await;
+        let mut other = tm.peer("00B").await;
+        let alice = tm.user("alice").build().await;
+
+        tm.run_server("00A", ":00a.test GLINE *@* 3600 oper :Network ban")
+            .await;
+
+        let glines = tm.matrix.db.bans().get_active_glines().await.unwrap();
+        assert_eq!(glines.len(), 1);
+        assert_eq!(glines[0].set_by, "oper");
+        assert!(glines[0].expires_at.is_some());
+        assert!(!tm.matrix.user_manager.users.contains_key(&alice.uid));
+
+        // Relayed by the sync observer, off the handler's task
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(2);
+        let mut relayed = None;
+        while let Ok(Some(m)) = tokio::time::timeout_at(deadline, other.recv()).await {
+            if matches!(&m.command, Command::Raw(c, _) if c == "GLINE") {
+                relayed = Some(m);
+                break;
+            }
+        }
+        let relayed = relayed.expect("G-line relayed to the other link");
+        assert!(matches!(
+            &relayed.command,
+            Command::Raw(_, args) if args[1] == "3600" && args[2] == "oper"
+        ));
+    }
+
+    #[tokio::test]
+    async fn remote_unzline_removes_the_ban() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let _origin = tm.peer("00A").await;
+
+        tm.run_server("00A", ":00a.test ZLINE 192.0.2.0/24 0 oper :Bad net")
+            .await;
+        assert_eq!(
+            tm.matrix.db.bans().get_active_zlines().await.unwrap().len(),
+            1
+        );
+
+        tm.run_server("00A", ":00a.test UNZLINE 192.0.2.0/24").await;
+        assert!(
+            tm.matrix
+                .db
+                .bans()
+                .get_active_zlines()
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -12,20 +12,22 @@ mod types;
 mod validation;
 
 // Re-export public types
-pub use types::BatchState;
+pub use types::{BatchState, MULTILINE_MAX_BYTES, MULTILINE_MAX_LINES};
 
 // Re-export processing function
 pub use processing::process_batch_message;
 
-use super::{
-    Context, HandlerResult, PostRegHandler, ResponseMiddleware, resolve_nick_or_nosuchnick,
-};
+use super::messaging::{RelayKind, RelayStamp, SenderSnapshot, check_relay, store_relay_history};
+use super::{Context, HandlerError, HandlerResult, PostRegHandler, ResponseMiddleware};
+use crate::config::ClientTagScope;
 use crate::state::RegisteredState;
+use crate::state::dashmap_ext::DashMapExt;
 use async_trait::async_trait;
 use slirc_proto::{
-    BatchSubCommand, ChannelExt, Command, Message, MessageRef, Prefix, Response, Tag,
-    format_server_time, generate_batch_ref, generate_msgid,
+    BatchSubCommand, ChannelExt, Command, Message, MessageRef, Prefix, Tag, generate_batch_ref,
+    irc_to_lower,
 };
+use std::collections::HashSet;
 use tracing::debug;
 
 /// Handler for BATCH command.
@@ -70,17 +72,28 @@ impl PostRegHandler for BatchHandler {
                 // Save the response label for when we complete the batch
                 let response_label = ctx.label.clone();
 
-                // Extract client-only tags (tags starting with '+') from BATCH + message
+                // Extract client-only tags (tags starting with '+') from BATCH +
+                // message, as far as [client_tags] allows them for this target
+                let policy = &ctx.matrix.config.client_tags;
+                let direct = !target.is_channel_name();
                 let client_tags: Vec<Tag> = msg
                     .tags_iter()
                     .filter(|(key, _)| key.starts_with('+'))
-                    .map(|(key, value)| {
+                    .filter_map(|(key, value)| {
                         let val = if value.is_empty() {
                             None
                         } else {
-                            Some(value.to_string())
+                            Some(slirc_proto::message::tags::unescape_tag_value(value))
                         };
-                        Tag::new(key, val)
+                        match policy.scope(key, val.as_deref()) {
+                            Ok(ClientTagScope::Any) => Some(Tag::new(key, val)),
+                            Ok(ClientTagScope::DirectOnly) if direct => Some(Tag::new(key, val)),
+                            Ok(ClientTagScope::DirectOnly) => None,
+                            Err(drop) => {
+                                crate::metrics::inc_client_tags_dropped(drop.as_str());
+                                None
+                            }
+                        }
                     })
                     .collect();
 
@@ -112,13 +125,12 @@ impl PostRegHandler for BatchHandler {
                 return Ok(());
             }
 
-            // Process the completed batch
-            if let Some(batch) = ctx.state.active_batch.take() {
-                ctx.state.active_batch_ref = None;
-
-                if batch.batch_type == "draft/multiline" {
-                    process_multiline_batch(ctx, &batch, &nick).await?;
-                }
+            // Process the completed batch; a rejected one has nothing left
+            ctx.state.active_batch_ref = None;
+            if let Some(batch) = ctx.state.active_batch.take()
+                && batch.batch_type == "draft/multiline"
+            {
+                process_multiline_batch(ctx, &batch, &nick).await?;
             }
         }
 
@@ -127,6 +139,9 @@ impl PostRegHandler for BatchHandler {
 }
 
 /// Process a completed multiline batch by delivering to recipients.
+///
+/// The combined message goes through the same checks as a single PRIVMSG or
+/// NOTICE to the target and is stored in history the same way.
 async fn process_multiline_batch(
     ctx: &mut Context<'_, RegisteredState>,
     batch: &BatchState,
@@ -156,68 +171,199 @@ async fn process_multiline_batch(
         "Processing completed multiline batch"
     );
 
-    // Get the command type
-    let cmd_type = batch.command_type.as_deref().unwrap_or("PRIVMSG");
+    let snapshot = SenderSnapshot::build(ctx)
+        .await
+        .ok_or(HandlerError::NickOrUserMissing)?;
+    let kind = RelayKind::from_batch_command(batch.command_type.as_deref().unwrap_or("PRIVMSG"));
+    if !check_relay(ctx, kind, &batch.target, &combined, &snapshot).await? {
+        return Ok(());
+    }
 
-    // Now we need to deliver this to recipients
     // For clients with draft/multiline, send as a batch
     // For clients without, send as individual lines (fallback)
-
-    let target = &batch.target;
-
-    // Get sender info for prefix
-    let user_arc = ctx
-        .matrix
-        .user_manager
-        .users
-        .get(ctx.uid)
-        .map(|u| u.value().clone());
-    let prefix = if let Some(user_arc) = user_arc {
-        let user = user_arc.read().await;
-        Prefix::new(
-            user.nick.clone(),
-            user.user.clone(),
-            user.visible_host.clone(),
-        )
-    } else {
-        Prefix::new(nick.to_string(), "user".to_string(), "host".to_string())
+    // ALL recipients must receive the same msgid and time per IRCv3 spec
+    let delivery = MultilineDelivery {
+        batch,
+        prefix: Prefix::new(
+            snapshot.nick.clone(),
+            snapshot.user.clone(),
+            snapshot.visible_host.clone(),
+        ),
+        kind,
+        batch_ref: generate_batch_ref(),
+        stamp: RelayStamp::now(),
+        account: snapshot.account.clone(),
     };
 
-    // Determine if target is a channel or user
-    let is_channel = target.is_channel_name();
-
-    if is_channel {
-        // Channel message - deliver to all members
-        deliver_multiline_to_channel(ctx, batch, &combined, &prefix, cmd_type).await?;
+    if batch.target.is_channel_name() {
+        deliver_multiline_to_channel(ctx, &delivery).await?;
     } else {
-        // Private message - deliver to single user
-        deliver_multiline_to_user(ctx, batch, &combined, &prefix, cmd_type, target).await?;
+        deliver_multiline_to_user(ctx, &delivery).await?;
     }
 
+    // Direct-message-only client tags are not stored
+    let policy = &ctx.matrix.config.client_tags;
+    let history_tags: Vec<Tag> = batch
+        .client_tags
+        .iter()
+        .filter(|t| policy.scope(&t.0, t.1.as_deref()) == Ok(ClientTagScope::Any))
+        .cloned()
+        .collect();
+    store_relay_history(
+        ctx,
+        kind,
+        &delivery.stamp,
+        &batch.target,
+        &combined,
+        &history_tags,
+        &snapshot,
+    )
+    .await;
+
     Ok(())
 }
 
+/// A checked multiline message on its way to recipients.
+struct MultilineDelivery<'a> {
+    batch: &'a BatchState,
+    prefix: Prefix,
+    kind: RelayKind,
+    batch_ref: String,
+    stamp: RelayStamp,
+    /// Sender's account, for recipients with account-tag.
+    account: Option<String>,
+}
+
+impl MultilineDelivery<'_> {
+    /// Send to one client, as a batch if it negotiated draft/multiline.
+    async fn send_to(
+        &self,
+        sender: &ResponseMiddleware<'_>,
+        caps: &HashSet<String>,
+        label: Option<&str>,
+    ) -> HandlerResult {
+        // Client-only tags from the original BATCH + command, and account-tag
+        let mut tags = self.batch.client_tags.clone();
+        if let Some(account) = &self.account
+            && caps.contains("account-tag")
+        {
+            tags.push(Tag::new("account", Some(account.clone())));
+        }
+
+        if caps.contains("draft/multiline") {
+            self.send_batch(sender, tags, label).await
+        } else {
+            self.send_fallback(sender, tags).await
+        }
+    }
+
+    fn line(&self, tags: Vec<Tag>, content: &str) -> Message {
+        let command = match self.kind {
+            RelayKind::Notice => Command::NOTICE(self.batch.target.clone(), content.to_string()),
+            _ => Command::PRIVMSG(self.batch.target.clone(), content.to_string()),
+        };
+        Message {
+            tags: Some(tags),
+            prefix: Some(self.prefix.clone()),
+            command,
+        }
+    }
+
+    /// Send a multiline batch (with BATCH +/-)
+    async fn send_batch(
+        &self,
+        sender: &ResponseMiddleware<'_>,
+        extra_tags: Vec<Tag>,
+        label: Option<&str>,
+    ) -> HandlerResult {
+        // Send BATCH +ref draft/multiline target
+        // Start batch includes server-time and msgid
+        let mut start_tags = vec![
+            Tag::new("time", Some(self.stamp.timestamp.clone())),
+            Tag::new("msgid", Some(self.stamp.msgid.clone())),
+        ];
+        start_tags.extend(extra_tags);
+
+        // Add label tag if present (for labeled-response)
+        if let Some(lbl) = label {
+            debug!("Adding label tag to BATCH: {}", lbl);
+            start_tags.push(Tag::new("label", Some(lbl.to_string())));
+        }
+
+        let start_batch = Message {
+            tags: Some(start_tags),
+            prefix: Some(self.prefix.clone()),
+            command: Command::BATCH(
+                format!("+{}", self.batch_ref),
+                Some(BatchSubCommand::CUSTOM("draft/multiline".to_string())),
+                Some(vec![self.batch.target.clone()]),
+            ),
+        };
+        sender.send(start_batch).await?;
+
+        // Send each line with batch=ref tag
+        for line in &self.batch.lines {
+            let mut tags = vec![Tag::new("batch", Some(self.batch_ref.clone()))];
+            if line.concat {
+                tags.push(Tag::new("draft/multiline-concat", None));
+            }
+            sender.send(self.line(tags, &line.content)).await?;
+        }
+
+        // Send BATCH -ref
+        let end_batch = Message {
+            tags: None,
+            prefix: None,
+            command: Command::BATCH(format!("-{}", self.batch_ref), None, None),
+        };
+        sender.send(end_batch).await?;
+
+        Ok(())
+    }
+
+    /// Send fallback individual lines to a client without draft/multiline.
+    async fn send_fallback(
+        &self,
+        sender: &ResponseMiddleware<'_>,
+        extra_tags: Vec<Tag>,
+    ) -> HandlerResult {
+        // For fallback: send each non-empty line as a separate message
+        // Ignore concat tags (client can't handle multiline anyway)
+        // Skip empty lines per spec
+        let mut message_index = 0;
+
+        for line in &self.batch.lines {
+            if line.content.is_empty() {
+                continue;
+            }
+
+            // First non-empty line gets msgid, server-time, and client tags
+            // All subsequent lines get server-time and client tags (NO msgid)
+            let mut tags = vec![Tag::new("time", Some(self.stamp.timestamp.clone()))];
+            if message_index == 0 {
+                tags.push(Tag::new("msgid", Some(self.stamp.msgid.clone())));
+            }
+            tags.extend(extra_tags.iter().cloned());
+
+            sender.send(self.line(tags, &line.content)).await?;
+            message_index += 1;
+        }
+
+        Ok(())
+    }
+}
+
 /// Deliver a multiline batch to a channel.
 async fn deliver_multiline_to_channel(
     ctx: &mut Context<'_, RegisteredState>,
-    batch: &BatchState,
-    _combined: &str,
-    prefix: &Prefix,
-    cmd_type: &str,
+    delivery: &MultilineDelivery<'_>,
 ) -> HandlerResult {
-    let channel_lower = batch.target.to_lowercase();
-
     let channel_tx = ctx
         .matrix
         .channel_manager
         .channels
-        .get(&channel_lower)
-        .map(|c| c.clone());
+        .get_cloned(&irc_to_lower(&delivery.batch.target));
     let Some(channel_tx) = channel_tx else {
-        // Channel doesn't exist - send error
-        let reply = Response::err_nosuchchannel(&ctx.state.nick, &batch.target)
-            .with_prefix(ctx.server_prefix());
-        ctx.send_error("BATCH", "ERR_NOSUCHCHANNEL", reply).await?;
         return Ok(());
     };
 
@@ -235,113 +381,29 @@ async fn deliver_multiline_to_channel(
         Err(_) => return Ok(()),
     };
 
-    // Pre-fetch member data in one pass (InspIRCd/UnrealIRCd pattern: HasCapabilityFast)
-    // This eliminates N×3 RwLock acquisitions per broadcast
-    struct MemberCaps {
-        has_multiline: bool,
-        has_echo_message: bool,
-    }
-    let mut members: Vec<(String, MemberCaps)> = Vec::with_capacity(member_uids.len());
-    for uid in member_uids {
-        let user_arc = ctx
-            .matrix
-            .user_manager
-            .users
-            .get(&uid)
-            .map(|u| u.value().clone());
-        if let Some(user_arc) = user_arc {
-            let user = user_arc.read().await;
-            members.push((
-                uid,
-                MemberCaps {
-                    has_multiline: user.caps.contains("draft/multiline"),
-                    has_echo_message: user.caps.contains("echo-message"),
-                },
-            ));
-        } else {
-            members.push((
-                uid,
-                MemberCaps {
-                    has_multiline: false,
-                    has_echo_message: false,
-                },
-            ));
-        }
-    }
+    for member_uid in member_uids {
+        let Some(user_arc) = ctx.matrix.user_manager.users.get_cloned(&member_uid) else {
+            continue;
+        };
+        let caps = user_arc.read().await.caps.clone();
 
-    // Generate a unique batch reference, msgid, and server_time for outgoing
-    // ALL recipients must receive the same msgid and time per IRCv3 spec
-    let batch_ref = generate_batch_ref();
-    let msgid = generate_msgid();
-    let server_time = format_server_time();
-
-    // Send to each member using pre-fetched capabilities
-    for (member_uid, member_caps) in &members {
-        // For the sender's own echo, get direct channel to bypass middleware and apply label manually
-        // For other members, send directly to their sender channel
-        if member_uid == ctx.uid {
-            // Echo to self - use pre-fetched has_echo_message
-            if member_caps.has_echo_message {
-                let Some(sender) = ctx.matrix.user_manager.get_first_sender(ctx.uid) else {
-                    continue;
-                };
-
-                let sender_middleware = ResponseMiddleware::Direct(&sender);
-
-                if member_caps.has_multiline {
-                    send_multiline_batch(
-                        &sender_middleware,
-                        batch,
-                        prefix,
-                        &batch_ref,
-                        &msgid,
-                        cmd_type,
-                        batch.response_label.as_deref(),
-                    )
-                    .await?;
-                } else {
-                    send_multiline_fallback(
-                        &sender_middleware,
-                        batch,
-                        prefix,
-                        &msgid,
-                        &server_time,
-                        cmd_type,
-                    )
-                    .await?;
-                }
-            }
-        } else {
-            // Send to other member - use direct channel and pre-fetched caps
-            let Some(member_sender) = ctx.matrix.user_manager.get_first_sender(member_uid) else {
+        // The sender only gets an echo, with the label applied manually
+        let label = if member_uid == ctx.uid {
+            if !caps.contains("echo-message") {
                 continue;
-            };
-
-            let member_middleware = ResponseMiddleware::Direct(&member_sender);
-
-            if member_caps.has_multiline {
-                send_multiline_batch(
-                    &member_middleware,
-                    batch,
-                    prefix,
-                    &batch_ref,
-                    &msgid,
-                    cmd_type,
-                    None,
-                )
-                .await?;
-            } else {
-                send_multiline_fallback(
-                    &member_middleware,
-                    batch,
-                    prefix,
-                    &msgid,
-                    &server_time,
-                    cmd_type,
-                )
-                .await?;
             }
-        }
+            delivery.batch.response_label.as_deref()
+        } else {
+            None
+        };
+
+        // Direct channel, bypassing the label middleware
+        let Some(member_sender) = ctx.matrix.user_manager.get_first_sender(&member_uid) else {
+            continue;
+        };
+        delivery
+            .send_to(&ResponseMiddleware::Direct(&member_sender), &caps, label)
+            .await?;
     }
 
     Ok(())
@@ -350,245 +412,35 @@ async fn deliver_multiline_to_channel(
 /// Deliver a multiline batch to a single user.
 async fn deliver_multiline_to_user(
     ctx: &mut Context<'_, RegisteredState>,
-    batch: &BatchState,
-    _combined: &str,
-    prefix: &Prefix,
-    cmd_type: &str,
-    target_nick: &str,
+    delivery: &MultilineDelivery<'_>,
 ) -> HandlerResult {
-    // Find target user by nick
-    let Some(target_uid) = resolve_nick_or_nosuchnick(ctx, "BATCH", target_nick).await? else {
-        return Ok(());
-    };
-
-    // Get target's sender
-    let Some(target_sender) = ctx.matrix.user_manager.get_first_sender(&target_uid) else {
-        return Ok(());
-    };
-
-    // Check if target has draft/multiline capability
-    let target_user_arc = ctx
-        .matrix
-        .user_manager
-        .users
-        .get(&target_uid)
-        .map(|u| u.value().clone());
-    let target_has_multiline = if let Some(target_user_arc) = target_user_arc {
-        let user = target_user_arc.read().await;
-        user.caps.contains("draft/multiline")
-    } else {
-        false
-    };
-
-    // Pre-fetch sender capabilities for echo (single read vs 2 reads)
-    let sender_user_arc = ctx
+    let Some(target_uid) = ctx
         .matrix
         .user_manager
-        .users
-        .get(ctx.uid)
-        .map(|u| u.value().clone());
-    let (sender_has_echo, sender_has_multiline) = if let Some(sender_user_arc) = sender_user_arc {
-        let user = sender_user_arc.read().await;
-        (
-            user.caps.contains("echo-message"),
-            user.caps.contains("draft/multiline"),
-        )
-    } else {
-        (false, false)
-    };
-
-    // Generate batch ref, msgid, and server_time (shared between target and echo)
-    let batch_ref = generate_batch_ref();
-    let msgid = generate_msgid();
-    let server_time = format_server_time();
-
-    let target_middleware = ResponseMiddleware::Direct(&target_sender);
-
-    if target_has_multiline {
-        send_multiline_batch(
-            &target_middleware,
-            batch,
-            prefix,
-            &batch_ref,
-            &msgid,
-            cmd_type,
-            None,
-        )
-        .await?;
-    } else {
-        send_multiline_fallback(
-            &target_middleware,
-            batch,
-            prefix,
-            &msgid,
-            &server_time,
-            cmd_type,
-        )
-        .await?;
-    }
-
-    // Echo to sender if echo-message enabled (using pre-fetched caps)
-    if sender_has_echo {
-        // Get direct sender channel to bypass middleware and apply label manually
-        let Some(sender) = ctx.matrix.user_manager.get_first_sender(ctx.uid) else {
-            return Ok(());
-        };
-
-        let sender_middleware = ResponseMiddleware::Direct(&sender);
-
-        if sender_has_multiline {
-            send_multiline_batch(
-                &sender_middleware,
-                batch,
-                prefix,
-                &batch_ref,
-                &msgid,
-                cmd_type,
-                batch.response_label.as_deref(),
-            )
-            .await?;
-        } else {
-            send_multiline_fallback(
-                &sender_middleware,
-                batch,
-                prefix,
-                &msgid,
-                &server_time,
-                cmd_type,
-            )
-            .await?;
-        }
-    }
-
-    Ok(())
-}
-
-/// Send a multiline batch (with BATCH +/-)
-async fn send_multiline_batch(
-    sender: &ResponseMiddleware<'_>,
-    batch: &BatchState,
-    prefix: &Prefix,
-    batch_ref: &str,
-    msgid: &str,
-    cmd_type: &str,
-    label: Option<&str>,
-) -> HandlerResult {
-    // Use provided msgid (shared across all recipients)
-    let server_time = format_server_time();
-
-    // Send BATCH +ref draft/multiline target
-    // Start batch includes server-time and msgid
-    let mut start_tags = vec![
-        Tag::new("time", Some(server_time.clone())),
-        Tag::new("msgid", Some(msgid.to_string())),
-    ];
-
-    // Add client-only tags from original BATCH + command
-    for client_tag in &batch.client_tags {
-        start_tags.push(client_tag.clone());
-    }
-
-    // Add label tag if present (for labeled-response)
-    if let Some(lbl) = label {
-        debug!("Adding label tag to BATCH: {}", lbl);
-        start_tags.push(Tag::new("label", Some(lbl.to_string())));
-    } else {
-        debug!("No label to add to BATCH");
-    }
-
-    let start_batch = Message {
-        tags: Some(start_tags),
-        prefix: Some(prefix.clone()),
-        command: Command::BATCH(
-            format!("+{}", batch_ref),
-            Some(BatchSubCommand::CUSTOM("draft/multiline".to_string())),
-            Some(vec![batch.target.clone()]),
-        ),
-    };
-    sender.send(start_batch).await?;
-
-    // Send each line with batch=ref tag
-    for line in &batch.lines {
-        let mut tags = vec![Tag::new("batch", Some(batch_ref.to_string()))];
-        if line.concat {
-            tags.push(Tag::new("draft/multiline-concat", None));
-        }
-
-        let cmd = if cmd_type == "NOTICE" {
-            Command::NOTICE(batch.target.clone(), line.content.clone())
-        } else {
-            Command::PRIVMSG(batch.target.clone(), line.content.clone())
-        };
-
-        let msg = Message {
-            tags: Some(tags),
-            prefix: Some(prefix.clone()),
-            command: cmd,
-        };
-        sender.send(msg).await?;
-    }
-
-    // Send BATCH -ref
-    let end_batch = Message {
-        tags: None,
-        prefix: None,
-        command: Command::BATCH(format!("-{}", batch_ref), None, None),
+        .get_first_uid(&irc_to_lower(&delivery.batch.target))
+    else {
+        return Ok(());
     };
-    sender.send(end_batch).await?;
 
-    Ok(())
-}
-
-/// Send fallback individual lines to a client without draft/multiline.
-async fn send_multiline_fallback(
-    sender: &ResponseMiddleware<'_>,
-    batch: &BatchState,
-    prefix: &Prefix,
-    msgid: &str,
-    server_time: &str,
-    cmd_type: &str,
-) -> HandlerResult {
-    // Use provided msgid and server-time (shared across all recipients)
-
-    // For fallback: send each non-empty line as a separate message
-    // Ignore concat tags (client can't handle multiline anyway)
-    // Skip empty lines per spec
-    let mut message_index = 0;
-
-    for line in &batch.lines {
-        // Skip empty lines in fallback
-        if line.content.is_empty() {
+    for (uid, label) in [
+        (target_uid.as_str(), None),
+        (ctx.uid, delivery.batch.response_label.as_deref()),
+    ] {
+        let Some(user_arc) = ctx.matrix.user_manager.users.get_cloned(uid) else {
             continue;
-        }
-
-        let cmd = if cmd_type == "NOTICE" {
-            Command::NOTICE(batch.target.clone(), line.content.clone())
-        } else {
-            Command::PRIVMSG(batch.target.clone(), line.content.clone())
         };
-
-        // First non-empty line gets msgid, server-time, and client tags
-        // All subsequent lines get server-time and client tags (NO msgid)
-        let mut tags = vec![Tag::new("time", Some(server_time.to_string()))];
-
-        // Add client-only tags from original BATCH + command to ALL messages
-        for client_tag in &batch.client_tags {
-            tags.push(client_tag.clone());
-        }
-
-        // Only first message gets msgid
-        if message_index == 0 {
-            tags.insert(1, Tag::new("msgid", Some(msgid.to_string())));
+        let caps = user_arc.read().await.caps.clone();
+        if uid == ctx.uid && !caps.contains("echo-message") {
+            continue;
         }
 
-        let msg = Message {
-            tags: Some(tags),
-            prefix: Some(prefix.clone()),
-            command: cmd,
+        // Direct channel, bypassing the label middleware
+        let Some(sender) = ctx.matrix.user_manager.get_first_sender(uid) else {
+            continue;
         };
-        sender.send(msg).await?;
-
-        message_index += 1;
+        delivery
+            .send_to(&ResponseMiddleware::Direct(&sender), &caps, label)
+            .await?;
     }
 
     Ok(())
@@ -608,3 +460,95 @@ async fn send_fail<S>(ctx: &mut Context<'_, S>, code: &str, message: &str) -> Ha
     ctx.sender.send(reply).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::process_batch_message;
+    use crate::test_support::{TestMatrix, TestMatrixBuilder, TestUser};
+    use slirc_proto::{Command, Message, MessageRef, Response};
+
+    /// Send `lines` from `user` to `target` as a multiline batch.
+    async fn send_multiline(
+        tm: &TestMatrix,
+        user: &mut TestUser,
+        target: &str,
+        lines: &[&str],
+    ) -> Vec<Message> {
+        tm.run(user, &format!("BATCH +ml draft/multiline {target}"))
+            .await;
+        for line in lines {
+            let line = format!("@batch=ml PRIVMSG {target} :{line}");
+            let msg = MessageRef::parse(&line).unwrap();
+            let consumed = process_batch_message(&mut user.state, &msg, "test.server");
+            assert_eq!(consumed, Ok(Some("ml".to_string())));
+        }
+        tm.run(user, "BATCH -ml").await
+    }
+
+    #[tokio::test]
+    async fn multiline_obeys_channel_modes() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").cap("draft/multiline").build().await;
+        let mut bob = tm.user("bob").cap("draft/multiline").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.run(&mut alice, "MODE #chan +n").await;
+        alice.drain();
+
+        let replies = send_multiline(&tm, &mut bob, "#chan", &["one", "two"]).await;
+        assert!(replies.iter().any(|m| matches!(
+            &m.command,
+            Command::Response(Response::ERR_CANNOTSENDTOCHAN, _)
+        )));
+        assert!(alice.drain().is_empty());
+
+        tm.run(&mut bob, "JOIN #chan").await;
+        alice.drain();
+        send_multiline(&tm, &mut bob, "#chan", &["one", "two"]).await;
+        let lines: Vec<_> = alice
+            .drain()
+            .into_iter()
+            .filter(|m| matches!(m.command, Command::PRIVMSG(..)))
+            .collect();
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn multiline_from_banned_member_is_refused() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").cap("draft/multiline").build().await;
+        let mut bob = tm.user("bob").cap("draft/multiline").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.run(&mut bob, "JOIN #chan").await;
+        tm.run(&mut alice, "MODE #chan +b bob!*@*").await;
+        alice.drain();
+
+        let replies = send_multiline(&tm, &mut bob, "#chan", &["one", "two"]).await;
+        assert!(replies.iter().any(|m| matches!(
+            &m.command,
+            Command::Response(Response::ERR_CANNOTSENDTOCHAN, args)
+                if args.last().is_some_and(|r| r.contains("+b"))
+        )));
+        assert!(alice.drain().is_empty());
+    }
+
+    #[tokio::test]
+    async fn multiline_fallback_carries_account_tag() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm
+            .user("alice")
+            .cap("draft/multiline")
+            .account("alice")
+            .build()
+            .await;
+        let mut bob = tm.user("bob").cap("account-tag").build().await;
+
+        send_multiline(&tm, &mut alice, "bob", &["one", "two"]).await;
+        let lines = bob.drain();
+        assert_eq!(lines.len(), 2);
+        assert!(
+            lines
+                .iter()
+                .all(|m| m.tag_value("account") == Some("alice"))
+        );
+    }
+}
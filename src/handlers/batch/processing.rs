@@ -53,10 +53,12 @@ pub fn process_batch_message<S: SessionState>(
         return Ok(None);
     }
 
-    // Add to the active batch
+    // Add to the active batch. A client batch that was already rejected
+    // swallows its remaining lines until BATCH -.
     let batch = match state.active_batch_mut() {
         Some(b) => b,
-        None => return Ok(None),
+        None if is_server => return Ok(None),
+        None => return Ok(Some(batch_ref.to_string())),
     };
     // For NETSPLIT batches (server-side), we stream messages instead of buffering.
     // This prevents memory spikes during large netsplits.
@@ -120,7 +122,9 @@ pub fn process_batch_message<S: SessionState>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::handlers::batch::types::{BatchLine, BatchState, MULTILINE_MAX_LINES};
+    use crate::handlers::batch::types::{
+        BatchLine, BatchState, MULTILINE_MAX_BYTES, MULTILINE_MAX_LINES,
+    };
     use crate::state::SessionState;
     use crate::state::client::DeviceId;
     use crate::state::session::ReattachInfo;
@@ -270,4 +274,33 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("MULTILINE_MAX_LINES"));
     }
+
+    #[test]
+    fn test_batch_bytes_limit_enforcement() {
+        let mut state = MockSessionState::new().with_batch("123", "#test");
+        state.batch.as_mut().unwrap().total_bytes = MULTILINE_MAX_BYTES - 2;
+
+        let raw = "@batch=123 PRIVMSG #test :Too long";
+        let msg = MessageRef::parse(raw).unwrap();
+        let result = process_batch_message(&mut state, &msg, "test.server");
+
+        let fail = result.unwrap_err();
+        assert!(fail.starts_with(&format!(
+            "FAIL BATCH MULTILINE_MAX_BYTES {}",
+            MULTILINE_MAX_BYTES
+        )));
+    }
+
+    #[test]
+    fn test_rejected_batch_swallows_remaining_lines() {
+        // What the connection loop leaves behind after a FAIL
+        let mut state = MockSessionState::new().with_batch("123", "#test");
+        state.batch = None;
+
+        let raw = "@batch=123 PRIVMSG #test :Leftover";
+        let msg = MessageRef::parse(raw).unwrap();
+        let result = process_batch_message(&mut state, &msg, "test.server");
+
+        assert_eq!(result.unwrap(), Some("123".to_string()));
+    }
 }
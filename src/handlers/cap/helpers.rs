@@ -1,5 +1,6 @@
-use super::types::{MULTILINE_MAX_BYTES, MULTILINE_MAX_LINES, SUPPORTED_CAPS};
-use crate::config::{AccountRegistrationConfig, SecurityConfig, StsConfig};
+use super::types::SUPPORTED_CAPS;
+use crate::config::{AccountRegistrationConfig, MetadataConfig, SecurityConfig, StsConfig};
+use crate::handlers::batch::{MULTILINE_MAX_BYTES, MULTILINE_MAX_LINES};
 use slirc_proto::{CapSubCommand, Capability, Command, Message, Prefix};
 
 /// Parameters for building the CAP list.
@@ -18,6 +19,12 @@ pub struct CapListParams<'a> {
     pub sec_cfg: &'a SecurityConfig,
     /// STS (Strict Transport Security) config, if enabled
     pub sts_cfg: Option<&'a StsConfig>,
+    /// Capabilities switched off in the server config
+    pub disabled_caps: &'a [String],
+    /// Metadata limits, advertised as the draft/metadata-2 value
+    pub metadata_cfg: &'a MetadataConfig,
+    /// Whether the connection may switch on compression with COMPRESS
+    pub compression: bool,
 }
 
 /// Build capability list string for CAP LS response.
@@ -33,6 +40,9 @@ pub fn build_cap_list_tokens(params: &CapListParams<'_>) -> Vec<String> {
         acct_cfg,
         sec_cfg,
         sts_cfg,
+        disabled_caps,
+        metadata_cfg,
+        compression,
     } = params;
 
     // Plaintext SASL is allowed if either:
@@ -42,6 +52,11 @@ pub fn build_cap_list_tokens(params: &CapListParams<'_>) -> Vec<String> {
 
     SUPPORTED_CAPS
         .iter()
+        .filter(|cap| {
+            !disabled_caps
+                .iter()
+                .any(|d| d.eq_ignore_ascii_case(cap.as_ref()))
+        })
         .filter_map(|cap| {
             // For CAP 302+, add values for caps that have them
             if *version >= 302 {
@@ -59,10 +74,15 @@ pub fn build_cap_list_tokens(params: &CapListParams<'_>) -> Vec<String> {
                             None
                         }
                     }
+                    // The limits the BATCH handler enforces
                     Capability::Multiline => Some(format!(
                         "draft/multiline=max-bytes={},max-lines={}",
                         MULTILINE_MAX_BYTES, MULTILINE_MAX_LINES
                     )),
+                    Capability::Metadata => Some(format!(
+                        "draft/metadata-2=max-subs={},max-keys={},max-value-bytes={}",
+                        metadata_cfg.max_subs, metadata_cfg.max_keys, metadata_cfg.max_value_bytes
+                    )),
                     Capability::AccountRegistration => {
                         // Build flags based on server configuration
                         let mut flags = Vec::with_capacity(3);
@@ -72,7 +92,7 @@ pub fn build_cap_list_tokens(params: &CapListParams<'_>) -> Vec<String> {
                         if acct_cfg.before_connect {
                             flags.push("before-connect");
                         }
-                        if acct_cfg.email_required {
+                        if acct_cfg.email_required || acct_cfg.verify_email {
                             flags.push("email-required");
                         }
                         if flags.is_empty() {
@@ -89,6 +109,8 @@ pub fn build_cap_list_tokens(params: &CapListParams<'_>) -> Vec<String> {
                             Some("tls".to_string())
                         }
                     }
+                    // The algorithms COMPRESS accepts
+                    Capability::Compress => compression.then(|| format!("{}=zstd", cap.as_ref())),
                     Capability::Sts => {
                         // STS capability: different behavior for TLS vs plaintext
                         // Per spec: insecure gets port=, secure gets duration=
@@ -118,6 +140,8 @@ pub fn build_cap_list_tokens(params: &CapListParams<'_>) -> Vec<String> {
                 } else if *cap == Capability::Sts {
                     // STS requires CAP 302+ for values
                     None
+                } else if *cap == Capability::Compress && !*compression {
+                    None
                 } else if *cap == Capability::Sasl && !*is_tls && !plaintext_sasl_ok {
                     // For older clients, only advertise SASL on TLS connections
                     // unless plaintext is explicitly allowed.
@@ -218,6 +242,9 @@ mod tests {
             acct_cfg,
             sec_cfg,
             sts_cfg: None,
+            disabled_caps: &[],
+            metadata_cfg: Box::leak(Box::new(MetadataConfig::default())),
+            compression: false,
         }
     }
 
@@ -426,6 +453,9 @@ mod tests {
             acct_cfg,
             sec_cfg,
             sts_cfg: Some(sts_cfg),
+            disabled_caps: &[],
+            metadata_cfg: Box::leak(Box::new(MetadataConfig::default())),
+            compression: false,
         });
 
         let sts = caps
@@ -463,6 +493,9 @@ mod tests {
             acct_cfg,
             sec_cfg,
             sts_cfg: Some(sts_cfg),
+            disabled_caps: &[],
+            metadata_cfg: Box::leak(Box::new(MetadataConfig::default())),
+            compression: false,
         });
 
         let sts = caps
@@ -500,6 +533,9 @@ mod tests {
             acct_cfg,
             sec_cfg,
             sts_cfg: Some(sts_cfg),
+            disabled_caps: &[],
+            metadata_cfg: Box::leak(Box::new(MetadataConfig::default())),
+            compression: false,
         });
 
         let sts = caps
@@ -600,6 +636,9 @@ mod tests {
             acct_cfg,
             sts_cfg: None,
             sec_cfg,
+            disabled_caps: &[],
+            metadata_cfg: Box::leak(Box::new(MetadataConfig::default())),
+            compression: false,
         });
 
         assert!(
@@ -625,6 +664,9 @@ mod tests {
             acct_cfg,
             sts_cfg: None,
             sec_cfg,
+            disabled_caps: &[],
+            metadata_cfg: Box::leak(Box::new(MetadataConfig::default())),
+            compression: false,
         });
 
         assert!(
@@ -634,6 +676,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cap_list_compression_only_when_offered() {
+        let caps = build_cap_list_tokens(&make_params(302, false, false));
+        assert!(!caps.iter().any(|c| c.starts_with("sid3.xyz/compress")));
+
+        let caps = build_cap_list_tokens(&CapListParams {
+            compression: true,
+            ..make_params(302, false, false)
+        });
+        assert!(caps.iter().any(|c| c == "sid3.xyz/compress=zstd"));
+
+        let caps = build_cap_list_tokens(&CapListParams {
+            compression: true,
+            ..make_params(301, false, false)
+        });
+        assert!(caps.iter().any(|c| c == "sid3.xyz/compress"));
+    }
+
+    #[test]
+    fn test_cap_list_skips_disabled_caps() {
+        let disabled = vec![
+            "draft/multiline".to_string(),
+            "DRAFT/CHATHISTORY".to_string(),
+        ];
+        let caps = build_cap_list_tokens(&CapListParams {
+            disabled_caps: &disabled,
+            ..make_params(302, true, false)
+        });
+
+        assert!(!caps.iter().any(|c| c.starts_with("draft/multiline")));
+        assert!(!caps.iter().any(|c| c == "draft/chathistory"));
+        assert!(caps.iter().any(|c| c == "batch"));
+    }
+
     #[test]
     fn test_cap_list_version_301_plaintext_sasl_config() {
         // Verify that CAP 301 advertises bare 'sasl' on plaintext when allow_plaintext_sasl_plain=true
@@ -653,6 +729,9 @@ mod tests {
             acct_cfg,
             sts_cfg: None,
             sec_cfg,
+            disabled_caps: &[],
+            metadata_cfg: Box::leak(Box::new(MetadataConfig::default())),
+            compression: false,
         });
 
         assert!(
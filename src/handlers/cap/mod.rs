@@ -15,7 +15,7 @@ mod subcommands;
 mod types;
 
 pub use sasl::AuthenticateHandler;
-pub use types::SaslState;
+pub use types::{CAP_COMMANDS, SUPPORTED_CAPS, SaslState};
 
 use crate::handlers::{Context, HandlerResult, UniversalHandler};
 use crate::state::SessionState;
@@ -1,6 +1,8 @@
-use crate::handlers::{Context, HandlerResult, notify_extended_monitor_watchers};
+use crate::handlers::{Context, HandlerError, HandlerResult, notify_extended_monitor_watchers};
+use crate::handlers::cap::types::SaslState;
 use crate::state::client::DeviceId;
 use crate::state::dashmap_ext::DashMapExt;
+use crate::state::observer::StateObserver;
 use crate::state::{SaslAccess, SessionState};
 use slirc_proto::{Command, Message, Prefix, Response};
 use tracing::{debug, warn};
@@ -125,6 +127,70 @@ pub(crate) async fn attach_session_to_client<S: SessionState + SaslAccess>(
     }
 }
 
+/// Length of a full `AUTHENTICATE` chunk; a shorter one ends the payload.
+const SASL_CHUNK_LEN: usize = 400;
+
+/// Maximum size for accumulated SASL buffer (16KB)
+/// Prevents memory exhaustion attacks
+const MAX_SASL_BUFFER: usize = 16384;
+
+/// Add an `AUTHENTICATE` chunk to the session's SASL buffer.
+///
+/// Returns the whole payload once a chunk shorter than 400 bytes (or `+`)
+/// ends it, and `None` while more chunks are expected. A payload over the
+/// buffer limit fails the exchange and also returns `None`.
+pub(crate) async fn take_sasl_payload<S: SessionState + SaslAccess>(
+    ctx: &mut Context<'_, S>,
+    nick: &str,
+    data: &str,
+) -> Result<Option<String>, HandlerError> {
+    if data != "+" {
+        if ctx.state.sasl_buffer().len() + data.len() > MAX_SASL_BUFFER {
+            ctx.state.sasl_buffer_mut().clear();
+            send_sasl_fail(ctx, nick, "SASL payload too large").await?;
+            ctx.state.set_sasl_state(SaslState::None);
+            return Ok(None);
+        }
+        ctx.state.sasl_buffer_mut().push_str(data);
+    }
+
+    if data.len() == SASL_CHUNK_LEN {
+        debug!(nick = %nick, chunk_len = data.len(), total_len = ctx.state.sasl_buffer().len(), "SASL: accumulated chunk, waiting for more");
+        return Ok(None);
+    }
+
+    let payload = std::mem::take(ctx.state.sasl_buffer_mut());
+    debug!(nick = %nick, total_len = payload.len(), "SASL: processing complete payload");
+    Ok(Some(payload))
+}
+
+/// Send a base64 payload as `AUTHENTICATE` chunks of at most 400 bytes.
+///
+/// A payload that is empty or a multiple of 400 bytes ends with `+`.
+pub(crate) async fn send_authenticate<S: SessionState + SaslAccess>(
+    ctx: &mut Context<'_, S>,
+    payload: &str,
+) -> HandlerResult {
+    let mut chunks: Vec<&str> = payload
+        .as_bytes()
+        .chunks(SASL_CHUNK_LEN)
+        // Base64 is ASCII, so every chunk boundary is a char boundary
+        .map(|c| std::str::from_utf8(c).unwrap_or_default())
+        .collect();
+    if payload.len().is_multiple_of(SASL_CHUNK_LEN) {
+        chunks.push("+");
+    }
+    for chunk in chunks {
+        let reply = Message {
+            tags: None,
+            prefix: Some(ctx.server_prefix()),
+            command: Command::AUTHENTICATE(chunk.to_string()),
+        };
+        ctx.sender.send(reply).await?;
+    }
+    Ok(())
+}
+
 /// Base64 encode data for SASL responses.
 pub(crate) fn encode_base64(data: &[u8]) -> String {
     use base64::{Engine, engine::general_purpose::STANDARD};
@@ -202,8 +268,12 @@ pub(crate) async fn broadcast_account_change<S: SessionState + SaslAccess>(
 
     if let Some(user_arc) = ctx.matrix.user_manager.users.get_cloned(&uid) {
         let mut user = user_arc.write().await;
-        user.account = Some(account_name.to_string());
+        user.set_account(Some(account_name.to_string()), ctx.matrix.clock());
+        ctx.matrix.user_manager.index.update(&user);
     }
+    ctx.matrix
+        .observers
+        .on_account_change(&uid, Some(account_name), None);
 
     let account_msg = Message {
         tags: None,
@@ -225,6 +295,21 @@ pub(crate) async fn broadcast_account_change<S: SessionState + SaslAccess>(
     }
 
     notify_extended_monitor_watchers(ctx.matrix, nick, account_msg, "account-notify").await;
+
+    // Switch to the account's HostServ vhost, if it has one switched on
+    if let Ok(Some(account)) = ctx.matrix.db.accounts().find_by_name(account_name).await
+        && let Ok(Some(vhost)) = ctx.matrix.db.vhosts().active(account.id).await
+    {
+        crate::services::effect::apply_effect_no_sender(
+            ctx.matrix,
+            nick,
+            crate::services::ServiceEffect::SetVhost {
+                target_uid: uid,
+                vhost: Some(vhost),
+            },
+        )
+        .await;
+    }
 }
 
 #[cfg(test)]
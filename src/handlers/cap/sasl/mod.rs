@@ -12,13 +12,13 @@ use super::types::SaslState;
 use crate::handlers::{Context, HandlerResult, UniversalHandler};
 use crate::state::{SaslAccess, SessionState};
 use async_trait::async_trait;
-use slirc_proto::{Command, Message, MessageRef};
+use slirc_proto::{Command, Message, MessageRef, Response};
 use tracing::debug;
 
 use common::send_sasl_fail;
 use external::handle_sasl_external;
 use plain::handle_sasl_plain_data;
-use scram::{handle_scram_client_final, handle_scram_client_first};
+use scram::{handle_scram_ack, handle_scram_client_final, handle_scram_client_first};
 
 /// Handler for AUTHENTICATE command (SASL authentication).
 ///
@@ -39,16 +39,16 @@ impl<S: SessionState + SaslAccess> UniversalHandler<S> for AuthenticateHandler {
             SaslState::None => handle_sasl_init(ctx, &nick, data).await,
             SaslState::WaitingForExternal => handle_sasl_external(ctx, &nick, data).await,
             SaslState::WaitingForData => handle_sasl_plain_data(ctx, &nick, data).await,
-            SaslState::WaitingForScramClientFirst { account_name } => {
-                handle_scram_client_first(ctx, &nick, data, &account_name).await
+            SaslState::WaitingForScramClientFirst => {
+                handle_scram_client_first(ctx, &nick, data).await
             }
             SaslState::WaitingForScramClientFinal {
                 account_name,
                 device_id,
                 server_nonce,
-                salt,
-                iterations,
-                hashed_password,
+                gs2_header,
+                stored_key,
+                server_key,
                 auth_message,
             } => {
                 handle_scram_client_final(
@@ -58,13 +58,17 @@ impl<S: SessionState + SaslAccess> UniversalHandler<S> for AuthenticateHandler {
                     &account_name,
                     device_id,
                     &server_nonce,
-                    &salt,
-                    iterations,
-                    &hashed_password,
+                    &gs2_header,
+                    &stored_key,
+                    &server_key,
                     &auth_message,
                 )
                 .await
             }
+            SaslState::WaitingForScramAck {
+                account_name,
+                device_id,
+            } => handle_scram_ack(ctx, &nick, data, &account_name, device_id).await,
             SaslState::Authenticated => {
                 // Already authenticated - allow re-authentication by starting fresh
                 debug!(nick = %nick, "AUTHENTICATE after authenticated, starting fresh");
@@ -82,7 +86,10 @@ async fn handle_sasl_init<S: SessionState + SaslAccess>(
     mechanism: &str,
 ) -> HandlerResult {
     if mechanism.eq_ignore_ascii_case("PLAIN") {
-        if !ctx.state.is_tls() && !ctx.matrix.config.security.allow_plaintext_sasl_plain {
+        if !ctx.state.is_tls()
+            && !ctx.state.is_tor()
+            && !ctx.matrix.config.security.allow_plaintext_sasl_plain
+        {
             send_sasl_fail(ctx, nick, "SASL PLAIN requires TLS connection").await?;
             ctx.state.set_sasl_state(SaslState::None);
             return Ok(());
@@ -121,11 +128,9 @@ async fn handle_sasl_init<S: SessionState + SaslAccess>(
         ctx.sender.send(reply).await?;
         debug!(nick = %nick, "SASL EXTERNAL: sent challenge");
     } else if mechanism.eq_ignore_ascii_case("SCRAM-SHA-256") {
-        // SCRAM-SHA-256: For now, we use the current nick as the account name hint.
+        // The account comes from the username in the client-first message
         ctx.state
-            .set_sasl_state(SaslState::WaitingForScramClientFirst {
-                account_name: nick.to_string(),
-            });
+            .set_sasl_state(SaslState::WaitingForScramClientFirst);
         // Send empty challenge (AUTHENTICATE +)
         let reply = Message {
             tags: None,
@@ -135,9 +140,21 @@ async fn handle_sasl_init<S: SessionState + SaslAccess>(
         ctx.sender.send(reply).await?;
         debug!(nick = %nick, "SASL SCRAM-SHA-256: sent initial challenge");
     } else {
-        // Unsupported mechanism
+        // Unsupported mechanism: list the ones we offer, then fail
+        let reply = Response::rpl_saslmechs(nick, available_mechanisms(&*ctx.state))
+            .with_prefix(ctx.server_prefix());
+        ctx.sender.send(reply).await?;
         send_sasl_fail(ctx, nick, "Unsupported mechanism").await?;
         ctx.state.set_sasl_state(SaslState::None);
     }
     Ok(())
 }
+
+/// Mechanisms offered to this connection, as in the `sasl` CAP value.
+fn available_mechanisms<S: SessionState + SaslAccess>(state: &S) -> &'static str {
+    if state.is_tls() && state.certfp().is_some() {
+        "SCRAM-SHA-256,PLAIN,EXTERNAL"
+    } else {
+        "SCRAM-SHA-256,PLAIN"
+    }
+}
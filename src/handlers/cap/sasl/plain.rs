@@ -1,6 +1,6 @@
 use super::common::{
     attach_session_to_client, broadcast_account_change, extract_device_id, send_sasl_fail,
-    send_sasl_success,
+    send_sasl_success, take_sasl_payload,
 };
 use crate::handlers::cap::types::{SaslState, SecureString};
 use crate::handlers::{Context, HandlerResult};
@@ -44,27 +44,9 @@ pub(crate) async fn handle_sasl_plain_data<S: SessionState + SaslAccess>(
         return Ok(());
     }
 
-    // Maximum size for accumulated SASL buffer (16KB)
-    // Prevents memory exhaustion attacks
-    const MAX_SASL_BUFFER: usize = 16384;
-
-    if data != "+" {
-        if ctx.state.sasl_buffer().len() + data.len() > MAX_SASL_BUFFER {
-            ctx.state.sasl_buffer_mut().clear();
-            send_sasl_fail(ctx, nick, "SASL payload too large").await?;
-            ctx.state.set_sasl_state(SaslState::None);
-            return Ok(());
-        }
-        ctx.state.sasl_buffer_mut().push_str(data);
-    }
-
-    if data.len() == 400 {
-        debug!(nick = %nick, chunk_len = data.len(), total_len = ctx.state.sasl_buffer().len(), "SASL: accumulated chunk, waiting for more");
+    let Some(mut full_data) = take_sasl_payload(ctx, nick, data).await? else {
         return Ok(());
-    }
-
-    let mut full_data = std::mem::take(ctx.state.sasl_buffer_mut());
-    debug!(nick = %nick, total_len = full_data.len(), "SASL: processing complete payload");
+    };
 
     let result = validate_sasl_plain(&full_data);
     full_data.zeroize();
@@ -82,7 +64,7 @@ pub(crate) async fn handle_sasl_plain_data<S: SessionState + SaslAccess>(
             match ctx
                 .db
                 .accounts()
-                .identify(account_name_ref, password.as_str())
+                .authenticate(account_name_ref, password.as_str())
                 .await
             {
                 Ok(account) => {
@@ -1,14 +1,13 @@
 use super::common::{
     attach_session_to_client, broadcast_account_change, encode_base64, extract_device_id,
-    send_sasl_fail, send_sasl_success,
+    send_authenticate, send_sasl_fail, send_sasl_success, take_sasl_payload,
 };
 use crate::handlers::cap::types::SaslState;
-use crate::handlers::{Context, HandlerResult};
+use crate::handlers::{Context, HandlerError, HandlerResult};
 use crate::state::client::DeviceId;
 use crate::state::{SaslAccess, SessionState};
 use rand::RngCore;
 use ring::hmac::{self, HMAC_SHA256};
-use slirc_proto::{Command, Message};
 use subtle::ConstantTimeEq;
 use tracing::{debug, info, warn};
 
@@ -22,37 +21,58 @@ fn generate_server_nonce() -> String {
     encode_base64(&nonce)
 }
 
+/// Decode a base64 SCRAM message, failing the exchange if it is malformed.
+async fn decode_scram_message<S: SessionState + SaslAccess>(
+    ctx: &mut Context<'_, S>,
+    nick: &str,
+    payload: &str,
+) -> Result<Option<String>, HandlerError> {
+    let reason = match slirc_proto::sasl::decode_base64(payload) {
+        Ok(bytes) => match String::from_utf8(bytes) {
+            Ok(s) => return Ok(Some(s)),
+            Err(_) => "Invalid UTF-8 in SCRAM message",
+        },
+        Err(_) => "Invalid base64",
+    };
+    send_sasl_fail(ctx, nick, reason).await?;
+    ctx.state.set_sasl_state(SaslState::None);
+    Ok(None)
+}
+
 /// Handle SCRAM client-first message.
 pub(crate) async fn handle_scram_client_first<S: SessionState + SaslAccess>(
     ctx: &mut Context<'_, S>,
     nick: &str,
     data: &str,
-    _account_hint: &str,
 ) -> HandlerResult {
     if data == "*" {
+        ctx.state.sasl_buffer_mut().clear();
         send_sasl_fail(ctx, nick, "SASL authentication aborted").await?;
         ctx.state.set_sasl_state(SaslState::None);
         return Ok(());
     }
 
-    let client_first = match slirc_proto::sasl::decode_base64(data) {
-        Ok(bytes) => match String::from_utf8(bytes) {
-            Ok(s) => s,
-            Err(_) => {
-                send_sasl_fail(ctx, nick, "Invalid UTF-8 in client-first").await?;
-                ctx.state.set_sasl_state(SaslState::None);
-                return Ok(());
-            }
-        },
-        Err(_) => {
-            send_sasl_fail(ctx, nick, "Invalid base64").await?;
-            ctx.state.set_sasl_state(SaslState::None);
-            return Ok(());
-        }
+    let Some(payload) = take_sasl_payload(ctx, nick, data).await? else {
+        return Ok(());
+    };
+    let Some(client_first) = decode_scram_message(ctx, nick, &payload).await? else {
+        return Ok(());
     };
 
     debug!(nick = %nick, client_first = %client_first, "SCRAM: received client-first");
 
+    // We offer no SCRAM-SHA-256-PLUS, so channel binding cannot be used
+    let Some((gs2_header, client_first_bare)) = split_gs2_header(&client_first) else {
+        send_sasl_fail(ctx, nick, "Invalid SCRAM client-first format").await?;
+        ctx.state.set_sasl_state(SaslState::None);
+        return Ok(());
+    };
+    if gs2_header.starts_with("p=") {
+        send_sasl_fail(ctx, nick, "Channel binding not supported").await?;
+        ctx.state.set_sasl_state(SaslState::None);
+        return Ok(());
+    }
+
     let username = match parse_scram_username(&client_first) {
         Some(u) => u,
         None => {
@@ -98,25 +118,18 @@ pub(crate) async fn handle_scram_client_first<S: SessionState + SaslAccess>(
         combined_nonce, salt_b64, verifiers.iterations
     );
 
-    let client_first_bare = extract_client_first_bare(&client_first);
     let auth_message_prefix = format!("{},{}", client_first_bare, server_first);
 
-    let server_first_b64 = encode_base64(server_first.as_bytes());
-    let reply = Message {
-        tags: None,
-        prefix: Some(ctx.server_prefix()),
-        command: Command::AUTHENTICATE(server_first_b64),
-    };
-    ctx.sender.send(reply).await?;
+    send_authenticate(ctx, &encode_base64(server_first.as_bytes())).await?;
 
     ctx.state
         .set_sasl_state(SaslState::WaitingForScramClientFinal {
             account_name: account_name.clone(),
             device_id: device_id.clone(),
             server_nonce: combined_nonce,
-            salt: verifiers.salt,
-            iterations: verifiers.iterations,
-            hashed_password: verifiers.hashed_password,
+            gs2_header: gs2_header.to_string(),
+            stored_key: verifiers.stored_key,
+            server_key: verifiers.server_key,
             auth_message: auth_message_prefix,
         });
 
@@ -125,6 +138,9 @@ pub(crate) async fn handle_scram_client_first<S: SessionState + SaslAccess>(
 }
 
 /// Handle SCRAM client-final message.
+///
+/// A valid proof is answered with the server-final message; the login
+/// completes once the client acknowledges it.
 #[allow(clippy::too_many_arguments)]
 pub(crate) async fn handle_scram_client_final<S: SessionState + SaslAccess>(
     ctx: &mut Context<'_, S>,
@@ -133,35 +149,35 @@ pub(crate) async fn handle_scram_client_final<S: SessionState + SaslAccess>(
     account_name: &str,
     device_id: Option<DeviceId>,
     server_nonce: &str,
-    _salt: &[u8],
-    _iterations: u32,
-    hashed_password: &[u8],
+    gs2_header: &str,
+    stored_key: &[u8],
+    server_key: &[u8],
     auth_message_prefix: &str,
 ) -> HandlerResult {
     if data == "*" {
+        ctx.state.sasl_buffer_mut().clear();
         send_sasl_fail(ctx, nick, "SASL authentication aborted").await?;
         ctx.state.set_sasl_state(SaslState::None);
         return Ok(());
     }
 
-    let client_final = match slirc_proto::sasl::decode_base64(data) {
-        Ok(bytes) => match String::from_utf8(bytes) {
-            Ok(s) => s,
-            Err(_) => {
-                send_sasl_fail(ctx, nick, "Invalid UTF-8 in client-final").await?;
-                ctx.state.set_sasl_state(SaslState::None);
-                return Ok(());
-            }
-        },
-        Err(_) => {
-            send_sasl_fail(ctx, nick, "Invalid base64").await?;
-            ctx.state.set_sasl_state(SaslState::None);
-            return Ok(());
-        }
+    let Some(payload) = take_sasl_payload(ctx, nick, data).await? else {
+        return Ok(());
+    };
+    let Some(client_final) = decode_scram_message(ctx, nick, &payload).await? else {
+        return Ok(());
     };
 
     debug!(nick = %nick, client_final = %client_final, "SCRAM: received client-final");
 
+    // The client echoes its GS2 header as the channel binding attribute
+    let expected_binding = encode_base64(gs2_header.as_bytes());
+    if parse_scram_attr(&client_final, "c=") != Some(expected_binding.as_str()) {
+        send_sasl_fail(ctx, nick, "Channel binding mismatch").await?;
+        ctx.state.set_sasl_state(SaslState::None);
+        return Ok(());
+    }
+
     let client_final_nonce = match parse_scram_nonce(&client_final) {
         Some(n) => n,
         None => {
@@ -190,64 +206,37 @@ pub(crate) async fn handle_scram_client_final<S: SessionState + SaslAccess>(
     let client_final_without_proof = build_client_final_without_proof(&client_final);
     let auth_message = format!("{},{}", auth_message_prefix, client_final_without_proof);
 
-    let salted_password_key = hmac::Key::new(HMAC_SHA256, hashed_password);
-    let client_key = hmac::sign(&salted_password_key, b"Client Key");
-    let stored_key = ring::digest::digest(&ring::digest::SHA256, client_key.as_ref());
-    let stored_key_hmac = hmac::Key::new(HMAC_SHA256, stored_key.as_ref());
+    // ClientKey = ClientProof XOR HMAC(StoredKey, AuthMessage), and the
+    // proof is valid when H(ClientKey) is the StoredKey
+    let stored_key_hmac = hmac::Key::new(HMAC_SHA256, stored_key);
     let client_signature = hmac::sign(&stored_key_hmac, auth_message.as_bytes());
-
-    let mut expected_proof = [0u8; 32];
-    for (i, (k, s)) in client_key
-        .as_ref()
+    let mut client_key = [0u8; 32];
+    for (i, (p, s)) in client_proof
         .iter()
         .zip(client_signature.as_ref())
         .enumerate()
     {
-        expected_proof[i] = k ^ s;
+        client_key[i] = p ^ s;
     }
+    let computed_stored_key = ring::digest::digest(&ring::digest::SHA256, &client_key);
 
-    let authenticated = client_proof.ct_eq(&expected_proof).into();
+    let authenticated = computed_stored_key.as_ref().ct_eq(stored_key).into();
 
     if authenticated {
-        let server_key = hmac::sign(&salted_password_key, b"Server Key");
-        let server_key_hmac = hmac::Key::new(HMAC_SHA256, server_key.as_ref());
+        let server_key_hmac = hmac::Key::new(HMAC_SHA256, server_key);
         let server_signature = hmac::sign(&server_key_hmac, auth_message.as_bytes());
 
         let server_final = format!("v={}", encode_base64(server_signature.as_ref()));
-        let server_final_b64 = encode_base64(server_final.as_bytes());
-        let reply = Message {
-            tags: None,
-            prefix: Some(ctx.server_prefix()),
-            command: Command::AUTHENTICATE(server_final_b64),
-        };
-        ctx.sender.send(reply).await?;
-
-        info!(nick = %nick, account = %account_name, device = ?device_id, "SASL SCRAM-SHA-256 authentication successful");
-        send_sasl_success(ctx, nick, account_name).await?;
-        ctx.state.set_sasl_state(SaslState::Authenticated);
-        ctx.state.set_account(Some(account_name.to_string()));
-
-        attach_session_to_client(ctx, account_name, device_id).await;
-
-        if ctx.state.is_registered() {
-            // Fetch account to get metadata (SCRAM verify doesn't return it)
-            if let Ok(Some(account)) = ctx.db.accounts().find_by_name(account_name).await
-                && let Some(user_ref) = ctx.matrix.user_manager.users.get(ctx.uid) {
-                    let mut user = user_ref.write().await;
-                    user.metadata = account.metadata;
-                }
-
-            broadcast_account_change(ctx, nick, account_name).await;
-        }
+        send_authenticate(ctx, &encode_base64(server_final.as_bytes())).await?;
+
+        ctx.state.set_sasl_state(SaslState::WaitingForScramAck {
+            account_name: account_name.to_string(),
+            device_id,
+        });
+        debug!(nick = %nick, account = %account_name, "SCRAM: sent server-final");
     } else {
         let server_final = "e=invalid-proof";
-        let server_final_b64 = encode_base64(server_final.as_bytes());
-        let reply = Message {
-            tags: None,
-            prefix: Some(ctx.server_prefix()),
-            command: Command::AUTHENTICATE(server_final_b64),
-        };
-        ctx.sender.send(reply).await?;
+        send_authenticate(ctx, &encode_base64(server_final.as_bytes())).await?;
 
         warn!(nick = %nick, account = %account_name, "SASL SCRAM-SHA-256 authentication failed");
         send_sasl_fail(ctx, nick, "Authentication failed").await?;
@@ -257,6 +246,60 @@ pub(crate) async fn handle_scram_client_final<S: SessionState + SaslAccess>(
     Ok(())
 }
 
+/// Handle the client's response to the server-final message.
+///
+/// The client sends an empty response (`+`) once it has verified the
+/// server signature, which completes the login; anything else aborts it.
+pub(crate) async fn handle_scram_ack<S: SessionState + SaslAccess>(
+    ctx: &mut Context<'_, S>,
+    nick: &str,
+    data: &str,
+    account_name: &str,
+    device_id: Option<DeviceId>,
+) -> HandlerResult {
+    if data != "+" {
+        send_sasl_fail(ctx, nick, "SASL authentication aborted").await?;
+        ctx.state.set_sasl_state(SaslState::None);
+        return Ok(());
+    }
+
+    info!(nick = %nick, account = %account_name, device = ?device_id, "SASL SCRAM-SHA-256 authentication successful");
+    send_sasl_success(ctx, nick, account_name).await?;
+    ctx.state.set_sasl_state(SaslState::Authenticated);
+    ctx.state.set_account(Some(account_name.to_string()));
+
+    attach_session_to_client(ctx, account_name, device_id).await;
+
+    if ctx.state.is_registered() {
+        // Fetch account to get metadata (SCRAM verify doesn't return it)
+        if let Ok(Some(account)) = ctx.db.accounts().find_by_name(account_name).await
+            && let Some(user_ref) = ctx.matrix.user_manager.users.get(ctx.uid)
+        {
+            let mut user = user_ref.write().await;
+            user.metadata = account.metadata;
+        }
+
+        broadcast_account_change(ctx, nick, account_name).await;
+    }
+
+    Ok(())
+}
+
+/// Split a client-first message into its GS2 header (with the trailing
+/// comma) and the client-first-message-bare.
+fn split_gs2_header(client_first: &str) -> Option<(&str, &str)> {
+    let cbind_end = client_first.find(',')?;
+    let authzid_end = cbind_end + 1 + client_first[cbind_end + 1..].find(',')?;
+    Some((
+        &client_first[..=authzid_end],
+        &client_first[authzid_end + 1..],
+    ))
+}
+
+fn parse_scram_attr<'a>(msg: &'a str, prefix: &str) -> Option<&'a str> {
+    msg.split(',').find_map(|field| field.strip_prefix(prefix))
+}
+
 fn parse_scram_username(client_first: &str) -> Option<String> {
     let parts: Vec<&str> = client_first.splitn(3, ',').collect();
     if parts.len() < 3 {
@@ -285,15 +328,6 @@ fn parse_scram_nonce(msg: &str) -> Option<String> {
     None
 }
 
-fn extract_client_first_bare(client_first: &str) -> String {
-    let parts: Vec<&str> = client_first.splitn(3, ',').collect();
-    if parts.len() >= 3 {
-        parts[2].to_string()
-    } else {
-        String::new()
-    }
-}
-
 fn parse_scram_proof(client_final: &str) -> Option<[u8; 32]> {
     for field in client_final.split(',') {
         if let Some(proof_b64) = field.strip_prefix("p=")
@@ -317,3 +351,93 @@ fn build_client_final_without_proof(client_final: &str) -> String {
         .collect();
     without_proof.join(",")
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{TestMatrix, TestMatrixBuilder, TestUser};
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use scram::ScramClient;
+    use slirc_proto::{Command, Message, Response};
+
+    /// Send `payload` base64-encoded and return the replies.
+    async fn authenticate(tm: &TestMatrix, user: &mut TestUser, payload: &str) -> Vec<Message> {
+        let line = format!("AUTHENTICATE {}", STANDARD.encode(payload));
+        tm.run(user, &line).await
+    }
+
+    /// The decoded AUTHENTICATE challenge among `replies`.
+    fn challenge(replies: &[Message]) -> String {
+        let data: String = replies
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::AUTHENTICATE(data) if data != "+" => Some(data.as_str()),
+                _ => None,
+            })
+            .collect();
+        String::from_utf8(STANDARD.decode(data).unwrap()).unwrap()
+    }
+
+    fn has_numeric(replies: &[Message], numeric: Response) -> bool {
+        replies
+            .iter()
+            .any(|m| matches!(&m.command, Command::Response(r, _) if *r == numeric))
+    }
+
+    #[tokio::test]
+    async fn test_scram_exchange_logs_in_after_ack() {
+        let tm = TestMatrixBuilder::new().build().await;
+        tm.db
+            .accounts()
+            .register("alice", "hunter22", None)
+            .await
+            .unwrap();
+        let mut alice = tm.user("alice").build().await;
+
+        tm.run(&mut alice, "AUTHENTICATE SCRAM-SHA-256").await;
+        let (client, client_first) = ScramClient::new("alice", "hunter22", None).client_first();
+        let replies = authenticate(&tm, &mut alice, &client_first).await;
+        let client = client.handle_server_first(&challenge(&replies)).unwrap();
+        let (client, client_final) = client.client_final();
+        let replies = authenticate(&tm, &mut alice, &client_final).await;
+        client.handle_server_final(&challenge(&replies)).unwrap();
+        assert!(!has_numeric(&replies, Response::RPL_SASLSUCCESS));
+
+        let replies = tm.run(&mut alice, "AUTHENTICATE +").await;
+        assert!(has_numeric(&replies, Response::RPL_LOGGEDIN));
+        assert!(has_numeric(&replies, Response::RPL_SASLSUCCESS));
+    }
+
+    #[tokio::test]
+    async fn test_scram_rejects_wrong_password() {
+        let tm = TestMatrixBuilder::new().build().await;
+        tm.db
+            .accounts()
+            .register("alice", "hunter22", None)
+            .await
+            .unwrap();
+        let mut alice = tm.user("alice").build().await;
+
+        tm.run(&mut alice, "AUTHENTICATE SCRAM-SHA-256").await;
+        let (client, client_first) = ScramClient::new("alice", "hunter2", None).client_first();
+        let replies = authenticate(&tm, &mut alice, &client_first).await;
+        let client = client.handle_server_first(&challenge(&replies)).unwrap();
+        let (_, client_final) = client.client_final();
+        let replies = authenticate(&tm, &mut alice, &client_final).await;
+        assert!(has_numeric(&replies, Response::ERR_SASLFAIL));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_mechanism_lists_mechanisms() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
+
+        let replies = tm.run(&mut alice, "AUTHENTICATE DIGEST-MD5").await;
+        let mechs = replies.iter().find_map(|m| match &m.command {
+            Command::Response(Response::RPL_SASLMECHS, args) => args.get(1).cloned(),
+            _ => None,
+        });
+        assert_eq!(mechs.as_deref(), Some("SCRAM-SHA-256,PLAIN"));
+        assert!(has_numeric(&replies, Response::ERR_SASLFAIL));
+    }
+}
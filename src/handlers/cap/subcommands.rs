@@ -3,7 +3,7 @@ use super::types::SUPPORTED_CAPS;
 use crate::handlers::{Context, HandlerResult};
 use crate::state::SessionState;
 use crate::state::dashmap_ext::DashMapExt;
-use slirc_proto::{CapSubCommand, Command, Message};
+use slirc_proto::{CapSubCommand, Capability, Command, Message};
 use tracing::{debug, info};
 
 /// Handle `CAP LS [version]` - list available capabilities.
@@ -29,13 +29,12 @@ pub async fn handle_ls<S: SessionState>(
 
     let server_name = ctx.server_name();
 
-    // Get STS config if TLS is configured
-    let sts_cfg = ctx
-        .matrix
-        .config
-        .tls
-        .as_ref()
-        .and_then(|tls| tls.sts.as_ref());
+    // Tor and WebSocket clients cannot follow an STS upgrade to the TLS port
+    let sts_cfg = if ctx.state.is_tor() || ctx.state.is_websocket() {
+        None
+    } else {
+        ctx.matrix.hot_config.read().sts.clone()
+    };
 
     // Build capability tokens (include EXTERNAL if TLS with cert)
     let caps = build_cap_list_tokens(&CapListParams {
@@ -45,7 +44,10 @@ pub async fn handle_ls<S: SessionState>(
         allow_plaintext_sasl: false, // Runtime override not needed here; config handles it
         acct_cfg: &ctx.matrix.config.account_registration,
         sec_cfg: &ctx.matrix.config.security,
-        sts_cfg,
+        sts_cfg: sts_cfg.as_ref(),
+        disabled_caps: &ctx.matrix.config.server.disabled_caps,
+        metadata_cfg: &ctx.matrix.config.metadata,
+        compression: ctx.state.offers_compression(),
     });
 
     // CAP LS may need to be split across multiple lines to satisfy the IRC 512-byte limit.
@@ -127,7 +129,10 @@ pub async fn handle_req<S: SessionState>(
         // Strip any value suffix (cap=value) - split always returns at least one element
         let cap_base = cap_name.split('=').next().unwrap_or(cap_name);
 
-        let is_supported = SUPPORTED_CAPS.iter().any(|c| c.as_ref() == cap_base);
+        // sts is a policy, never requested
+        let is_supported = SUPPORTED_CAPS.iter().any(|c| c.as_ref() == cap_base)
+            && cap_base != Capability::Sts.as_ref()
+            && !ctx.matrix.config.server.is_cap_disabled(cap_base);
 
         if is_supported {
             if is_removal {
@@ -233,3 +238,78 @@ pub async fn handle_end<S: SessionState>(ctx: &mut Context<'_, S>, nick: &str) -
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::StsConfig;
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{CapSubCommand, Command, Message, Response};
+
+    #[tokio::test]
+    async fn disabled_cap_is_refused_and_its_command_unknown() {
+        let tm = TestMatrixBuilder::new()
+            .config(|c| c.server.disabled_caps = vec!["draft/chathistory".to_string()])
+            .build()
+            .await;
+        let mut alice = tm.user("alice").build().await;
+
+        let replies = tm.run(&mut alice, "CAP LS 302").await;
+        assert!(!replies.iter().any(|m| matches!(
+            &m.command,
+            Command::CAP(_, CapSubCommand::LS, _, Some(caps)) if caps.contains("draft/chathistory")
+        )));
+
+        let replies = tm.run(&mut alice, "CAP REQ :batch draft/chathistory").await;
+        assert!(
+            replies
+                .iter()
+                .any(|m| matches!(&m.command, Command::CAP(_, CapSubCommand::NAK, _, _)))
+        );
+
+        let replies = tm.run(&mut alice, "CHATHISTORY LATEST #chan * 10").await;
+        assert!(replies.iter().any(|m| matches!(
+            &m.command,
+            Command::Response(Response::ERR_UNKNOWNCOMMAND, _)
+        )));
+    }
+
+    #[tokio::test]
+    async fn sts_policy_depends_on_transport() {
+        let tm = TestMatrixBuilder::new().build().await;
+        tm.matrix.hot_config.write().sts = Some(StsConfig {
+            port: 6697,
+            duration: 86400,
+            preload: false,
+        });
+        let mut alice = tm.user("alice").build().await;
+        let sts = |replies: &[Message]| {
+            replies.iter().find_map(|m| match &m.command {
+                Command::CAP(_, CapSubCommand::LS, _, Some(caps)) => caps
+                    .split(' ')
+                    .find(|c| c.starts_with("sts"))
+                    .map(str::to_string),
+                _ => None,
+            })
+        };
+
+        let replies = tm.run(&mut alice, "CAP LS 302").await;
+        assert_eq!(sts(&replies).as_deref(), Some("sts=port=6697"));
+
+        alice.state.is_tls = true;
+        let replies = tm.run(&mut alice, "CAP LS 302").await;
+        assert_eq!(sts(&replies).as_deref(), Some("sts=duration=86400"));
+
+        // Tor clients reach us through an onion address with no TLS port
+        alice.state.is_tls = false;
+        alice.state.is_tor = true;
+        let replies = tm.run(&mut alice, "CAP LS 302").await;
+        assert_eq!(sts(&replies), None);
+
+        let replies = tm.run(&mut alice, "CAP REQ :sts").await;
+        assert!(
+            replies
+                .iter()
+                .any(|m| matches!(&m.command, Command::CAP(_, CapSubCommand::NAK, _, _)))
+        );
+    }
+}
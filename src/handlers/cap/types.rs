@@ -56,15 +56,25 @@ pub const SUPPORTED_CAPS: &[Capability] = &[
     Capability::EventPlayback,
     Capability::DraftRelaymsg,
     Capability::ReadMarker,
+    Capability::MessageRedaction,
+    Capability::Metadata,
     Capability::Tls,             // STARTTLS - only useful on plaintext connections
     Capability::Sts, // Strict Transport Security - advertised dynamically based on config
     Capability::StandardReplies, // FAIL/WARN/NOTE standard replies
+    Capability::Compress, // COMPRESS - only on listeners that offer it
 ];
 
-/// Maximum bytes allowed in a multiline batch message.
-pub const MULTILINE_MAX_BYTES: u32 = 40000;
-/// Maximum lines allowed in a multiline batch.
-pub const MULTILINE_MAX_LINES: u32 = 100;
+/// Commands that only exist for a capability, and go away when it is disabled.
+pub const CAP_COMMANDS: &[(&str, Capability)] = &[
+    ("AUTHENTICATE", Capability::Sasl),
+    ("CHATHISTORY", Capability::ChatHistory),
+    ("MARKREAD", Capability::ReadMarker),
+    ("METADATA", Capability::Metadata),
+    ("REDACT", Capability::MessageRedaction),
+    ("REGISTER", Capability::AccountRegistration),
+    ("RELAYMSG", Capability::DraftRelaymsg),
+    ("SETNAME", Capability::SetName),
+];
 
 /// SASL authentication state.
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
@@ -76,10 +86,7 @@ pub enum SaslState {
     /// Waiting for EXTERNAL response (empty or authzid).
     WaitingForExternal,
     /// Waiting for SCRAM client-first message.
-    WaitingForScramClientFirst {
-        /// The account name being authenticated.
-        account_name: String,
-    },
+    WaitingForScramClientFirst,
     /// Sent server-first, waiting for SCRAM client-final message.
     WaitingForScramClientFinal {
         /// The account name being authenticated.
@@ -88,13 +95,21 @@ pub enum SaslState {
         device_id: Option<DeviceId>,
         /// Server nonce (combines client nonce + our random part).
         server_nonce: String,
+        /// GS2 header of the client-first message, echoed in client-final.
+        gs2_header: String,
         /// SCRAM verifiers from database.
-        salt: Vec<u8>,
-        iterations: u32,
-        hashed_password: Vec<u8>,
+        stored_key: Vec<u8>,
+        server_key: Vec<u8>,
         /// Auth message for final verification.
         auth_message: String,
     },
+    /// Sent server-final, waiting for the client's empty response.
+    WaitingForScramAck {
+        /// The account name being authenticated.
+        account_name: String,
+        /// Device identifier extracted from SCRAM username.
+        device_id: Option<DeviceId>,
+    },
     Authenticated,
 }
 
@@ -147,19 +162,6 @@ mod tests {
         assert_ne!(SaslState::WaitingForData, SaslState::WaitingForExternal);
     }
 
-    #[test]
-    fn test_multiline_constants() {
-        // Verify multiline limits are reasonable values
-        assert_eq!(MULTILINE_MAX_BYTES, 40000);
-        assert_eq!(MULTILINE_MAX_LINES, 100);
-
-        // Sanity check: bytes should be much larger than lines
-        #[allow(clippy::assertions_on_constants)]
-        {
-            assert!(MULTILINE_MAX_BYTES > MULTILINE_MAX_LINES);
-        }
-    }
-
     #[test]
     fn test_supported_caps_not_empty() {
         #[allow(clippy::const_is_empty)]
@@ -0,0 +1,73 @@
+//! JOIN/PART flood (channel hopping) protection.
+//!
+//! JOIN and PART share one token bucket per client, separate from the
+//! message rate limit. Going over it delays the command, then refuses joins
+//! for a while, then disconnects the client.
+
+use super::super::{Context, HandlerError, server_reply};
+use crate::security::rate_limit::JoinPartVerdict;
+use crate::state::RegisteredState;
+use crate::state::dashmap_ext::DashMapExt;
+use slirc_proto::Response;
+use tracing::warn;
+
+/// Quit reason for clients disconnected for channel hopping.
+const HOP_QUIT_REASON: &str = "Excess Flood (joining and parting too quickly)";
+
+/// Count a JOIN or PART against the client's channel-hopping limit.
+///
+/// Returns `Ok(true)` if the command should go ahead (possibly after a
+/// delay) and `Ok(false)` if a JOIN was refused. PART is never refused, so
+/// a locked-out client can still leave channels. Operators are exempt.
+pub(super) async fn check_join_part(
+    ctx: &mut Context<'_, RegisteredState>,
+    channels: &str,
+    joining: bool,
+) -> Result<bool, HandlerError> {
+    let uid = ctx.uid.to_string();
+    let verdict = ctx
+        .matrix
+        .security_manager
+        .rate_limiter
+        .check_join_part(&uid);
+    if verdict == JoinPartVerdict::Allowed || is_oper(ctx).await {
+        return Ok(true);
+    }
+
+    match verdict {
+        JoinPartVerdict::Allowed => Ok(true),
+        JoinPartVerdict::Delay(wait) => {
+            tokio::time::sleep(wait).await;
+            Ok(true)
+        }
+        JoinPartVerdict::Locked(_) if !joining => Ok(true),
+        JoinPartVerdict::Locked(remaining) => {
+            let reply = server_reply(
+                ctx.server_name(),
+                Response::ERR_TOOMANYCHANNELS,
+                vec![
+                    ctx.state.nick.clone(),
+                    channels.to_string(),
+                    format!(
+                        "You are joining and parting channels too quickly. Try again in {} seconds.",
+                        remaining.as_secs().max(1)
+                    ),
+                ],
+            );
+            ctx.sender.send(reply).await?;
+            Ok(false)
+        }
+        JoinPartVerdict::Disconnect => {
+            warn!(uid = %uid, nick = %ctx.state.nick, "Disconnecting client for JOIN/PART flood");
+            Err(HandlerError::Quit(Some(HOP_QUIT_REASON.to_string())))
+        }
+    }
+}
+
+/// Check if the client is an IRC operator.
+async fn is_oper(ctx: &Context<'_, RegisteredState>) -> bool {
+    match ctx.matrix.user_manager.users.get_cloned(ctx.uid) {
+        Some(user_arc) => user_arc.read().await.modes.oper,
+        None => false,
+    }
+}
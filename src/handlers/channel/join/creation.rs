@@ -7,16 +7,18 @@
 use super::super::super::{Context, HandlerError, HandlerResult, user_prefix};
 use super::enforcement::{check_akick, check_auto_modes};
 use super::responses::{JoinSuccessContext, handle_join_success, send_join_error};
+use crate::config::ChannelCreationPolicy;
 use crate::error::ChannelError;
 use crate::handlers::ResponseMiddleware;
 use crate::handlers::helpers::fanout::broadcast_to_account;
 use crate::security::UserContext;
 use crate::state::{RegisteredState, Topic};
+use dashmap::mapref::entry::Entry;
 use slirc_proto::ircv3::msgid::generate_msgid;
 use slirc_proto::ircv3::server_time::format_server_time;
 use slirc_proto::{Command, Message, Prefix, irc_to_lower};
 use std::sync::Arc;
-use tracing::info;
+use tracing::{info, warn};
 
 /// Join a single channel.
 /// This is the main orchestration function for channel joining.
@@ -65,7 +67,7 @@ pub(super) async fn join_channel(
 pub(crate) async fn join_channel_internal(
     matrix: Arc<crate::state::Matrix>,
     uid: &str,
-    sender: &tokio::sync::mpsc::Sender<Arc<Message>>,
+    sender: &crate::state::SendQ,
     response_sender: ResponseMiddleware<'_>,
     server_name: &str,
     is_tls: bool,
@@ -164,6 +166,15 @@ pub(crate) async fn join_channel_internal(
             channel = %channel_name,
             "AKICK triggered"
         );
+        crate::services::effect::apply_effect_no_sender(
+            &matrix,
+            "ChanServ",
+            crate::services::ServiceEffect::ChannelBan {
+                channel: channel_name.to_string(),
+                mask: akick.mask.clone(),
+            },
+        )
+        .await;
         return Ok(None);
     }
 
@@ -210,8 +221,8 @@ pub(crate) async fn join_channel_internal(
 
     let mut attempt = 0;
 
-    // Pre-load saved topic and metadata for registered channels
-    let (initial_topic, initial_metadata) = if is_registered_channel {
+    // Pre-load saved topic, modes, metadata and MLOCK for registered channels
+    let (initial_topic, saved_modes, initial_metadata, mlock) = if is_registered_channel {
         if let Some(db) = db {
             if let Ok(Some(record)) = db.channels().find_by_name(&channel_lower).await {
                 let topic = if record.keeptopic {
@@ -226,39 +237,66 @@ pub(crate) async fn join_channel_internal(
                 } else {
                     None
                 };
-                (topic, Some(record.metadata))
+                let modes = record
+                    .modes
+                    .as_deref()
+                    .map(|modes| crate::state::actor::modes_from_string(modes, None, None));
+                (topic, modes, Some(record.metadata), record.mlock)
             } else {
-                (None, None)
+                (None, None, None, None)
             }
         } else {
-            (None, None)
+            (None, None, None, None)
         }
     } else {
-        (None, None)
+        (None, None, None, None)
     };
 
     let mailbox_capacity = matrix.config.limits.channel_mailbox_capacity;
 
+    // Checked before an actor is spawned, so refused names never exist
+    let creation_denied = if matrix
+        .config
+        .channels
+        .creation
+        .allows(account.is_some(), is_oper)
+    {
+        None
+    } else if matrix.config.channels.creation == ChannelCreationPolicy::Opers {
+        Some(ChannelError::CreateNeedsOper)
+    } else {
+        Some(ChannelError::CreateNeedsAccount)
+    };
+
     loop {
         let observer = matrix.channel_manager.observer.clone();
-        let channel_sender = matrix
-            .channel_manager
-            .channels
-            .entry(channel_lower.clone())
-            .or_insert_with(|| {
+        let mut created = false;
+        let channel_sender = match matrix.channel_manager.channels.entry(channel_lower.clone()) {
+            Entry::Occupied(entry) => Some(entry.get().clone()),
+            Entry::Vacant(_) if creation_denied.is_some() => None,
+            Entry::Vacant(entry) => {
+                created = true;
                 crate::metrics::inc_active_channels();
-                crate::state::actor::ChannelActor::spawn_with_capacity(
+                matrix.channel_manager.stats_manager.channel_created();
+                let tx = crate::state::actor::ChannelActor::spawn_with_capacity(
                     channel_name.to_string(),
                     Arc::downgrade(&matrix),
                     initial_topic.clone(),
-                    None, // initial_modes
+                    saved_modes.clone(),
                     initial_metadata.clone(),
                     None, // created_at
                     mailbox_capacity,
                     observer,
-                )
-            })
-            .clone();
+                );
+                Some(entry.insert(tx).clone())
+            }
+        };
+        let Some(channel_sender) = channel_sender else {
+            if let Some(error) = creation_denied {
+                send_join_error(response_sender, server_name, &nick, channel_name, error).await?;
+            }
+            return Ok(None);
+        };
 
         let extended_join_msg = make_extended_join_msg();
         info!(?extended_join_msg, "Created extended join msg");
@@ -295,6 +333,7 @@ pub(crate) async fn join_channel_internal(
                     user_manager: &matrix.user_manager,
                     channel_manager: &matrix.channel_manager,
                     client_manager: &matrix.client_manager,
+                    history: matrix.service_manager.history.as_ref(),
                     config_multiclient: matrix.config.multiclient.enabled,
                     uid_str: uid.to_string(),
                     caps: caps.clone(),
@@ -312,35 +351,58 @@ pub(crate) async fn join_channel_internal(
                     account: account.clone(),
                 })
                 .await?;
+                crate::services::botserv::on_join(&matrix, &channel_lower, uid).await;
+                // A registered channel recreated after emptying gets its MLOCK back
+                if created && let Some(mlock) = mlock.as_deref() {
+                    let mlock = crate::services::chanserv::mlock::Mlock::parse_lenient(mlock);
+                    if !mlock.is_empty() {
+                        crate::services::effect::apply_effect_no_sender(
+                            &matrix,
+                            "ChanServ",
+                            crate::services::ServiceEffect::ChannelModes {
+                                channel: channel_name.to_string(),
+                                setter: "ChanServ".to_string(),
+                                modes: mlock.modes(),
+                            },
+                        )
+                        .await;
+                    }
+                }
+                // Recreating a registered channel counts as using it
+                if created
+                    && is_registered_channel
+                    && let Some(db) = db
+                    && let Err(e) = db.channels().mark_used(&channel_lower).await
+                {
+                    warn!(channel = %channel_name, error = ?e, "Failed to mark channel used");
+                }
                 return Ok(self_join_msg);
             }
             Ok(Err(error)) => {
                 if matches!(error, ChannelError::ChannelTombstone) && attempt == 0 {
-                    if matrix
+                    matrix
                         .channel_manager
-                        .channels
-                        .remove(&channel_lower)
-                        .is_some()
-                    {
-                        crate::metrics::dec_active_channels();
-                    }
+                        .remove_if_current(&channel_lower, &channel_sender);
                     attempt += 1;
                     continue;
                 }
 
+                // A channel spawned for this join must not outlive its refusal
+                if created {
+                    matrix
+                        .channel_manager
+                        .reap_if_empty(&channel_lower, &channel_sender)
+                        .await;
+                }
+
                 send_join_error(response_sender, server_name, &nick, channel_name, error).await?;
                 return Ok(None);
             }
             Err(_) => {
                 if attempt == 0 {
-                    if matrix
+                    matrix
                         .channel_manager
-                        .channels
-                        .remove(&channel_lower)
-                        .is_some()
-                    {
-                        crate::metrics::dec_active_channels();
-                    }
+                        .remove_if_current(&channel_lower, &channel_sender);
                     attempt += 1;
                     continue;
                 }
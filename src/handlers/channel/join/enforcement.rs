@@ -1,6 +1,5 @@
 //! Channel access enforcement and auto-mode application.
 
-use crate::db::ChannelRepository;
 use crate::state::MemberModes;
 
 /// Check if user should receive auto-op or auto-voice on a registered channel.
@@ -22,8 +21,14 @@ pub(super) async fn check_auto_modes(
     let account_record = db.accounts().find_by_name(account_name).await.ok()??;
     let channel_record = db.channels().find_by_name(channel_lower).await.ok()??;
 
-    // Check if user is founder
-    if account_record.id == channel_record.founder_account_id {
+    let access = db
+        .channels()
+        .effective_access(&channel_record, account_record.id)
+        .await
+        .ok()?;
+
+    // Founders (directly or through a group) get op only
+    if access.founder {
         return Some(MemberModes {
             owner: false,
             owner_ts: None,
@@ -39,14 +44,8 @@ pub(super) async fn check_auto_modes(
         });
     }
 
-    let access = db
-        .channels()
-        .get_access(channel_record.id, account_record.id)
-        .await
-        .ok()??;
-
-    let op = ChannelRepository::has_op_access(&access.flags);
-    let voice = ChannelRepository::has_voice_access(&access.flags);
+    let op = access.has('O');
+    let voice = access.has('V');
 
     if op || voice {
         Some(MemberModes {
@@ -19,6 +19,7 @@
 //! - First joiner receives operator status (@)
 //! - Validates channel key if +k mode is set
 //! - Enforces bans, invite-only, and user limits
+//! - Rejects channel names reserved by Q-lines (IRC operators are exempt)
 //! - Applies AKICK auto-kicks and auto-modes
 //! - Persists registered channel state to database
 //! - Rate limits joins to prevent abuse
@@ -55,8 +56,14 @@ impl PostRegHandler for JoinHandler {
             // JOIN <channels> [keys]
             let channels_str = channels_str_raw.ok_or(HandlerError::NeedMoreParams)?;
 
+            // Channel-hopping limit; JOIN 0 counts as a part
+            let joining = !is_join_zero(channels_str);
+            if !super::hop::check_join_part(ctx, channels_str, joining).await? {
+                return Ok(());
+            }
+
             // Handle "JOIN 0" - leave all channels
-            if is_join_zero(channels_str) {
+            if !joining {
                 return leave_all_channels(ctx).await;
             }
 
@@ -104,6 +111,22 @@ impl PostRegHandler for JoinHandler {
                     continue;
                 }
 
+                if let Some(reason) = ctx.matrix.find_qline(channel_name)
+                    && !is_oper(ctx).await
+                {
+                    let reply = server_reply(
+                        ctx.server_name(),
+                        Response::ERR_BADCHANNAME,
+                        vec![
+                            ctx.state.nick.clone(),
+                            channel_name.to_string(),
+                            format!("Cannot join channel (reserved: {reason})"),
+                        ],
+                    );
+                    ctx.sender.send(reply).await?;
+                    continue;
+                }
+
                 let key = keys.get(i).and_then(|k| *k);
                 join_channel(ctx, channel_name, key).await?;
             }
@@ -115,6 +138,14 @@ impl PostRegHandler for JoinHandler {
     }
 }
 
+/// Check whether the joining user is an IRC operator (exempt from Q-lines).
+async fn is_oper(ctx: &Context<'_, RegisteredState>) -> bool {
+    match ctx.matrix.user_manager.users.get(ctx.uid) {
+        Some(user_arc) => user_arc.read().await.modes.oper,
+        None => false,
+    }
+}
+
 /// Leave all channels (JOIN 0).
 async fn leave_all_channels(ctx: &mut Context<'_, RegisteredState>) -> HandlerResult {
     // Single user read for both mask and channel list
@@ -239,4 +270,229 @@ mod tests {
             ]
         );
     }
+
+    #[tokio::test]
+    async fn test_join_throttle_locks_channel_and_registered_only() {
+        use crate::test_support::TestMatrixBuilder;
+        use slirc_proto::{Command, Response};
+
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").account("alice").build().await;
+        let mut bob = tm.user("bob").build().await;
+        let mut carol = tm.user("carol").build().await;
+        let has_error = |replies: &[slirc_proto::Message], response: Response| {
+            replies
+                .iter()
+                .any(|m| matches!(&m.command, Command::Response(r, _) if *r == response))
+        };
+
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.run(&mut alice, "MODE #chan +j 1:60").await;
+        let replies = tm.run(&mut bob, "JOIN #chan").await;
+        assert!(!has_error(&replies, Response::ERR_UNAVAILRESOURCE));
+        alice.drain();
+
+        let replies = tm.run(&mut carol, "JOIN #chan").await;
+        assert!(has_error(&replies, Response::ERR_UNAVAILRESOURCE));
+        let lock = alice
+            .recv_matching(|m| matches!(m.command, Command::ChannelMODE(..)))
+            .await
+            .expect("channel locked");
+        assert_eq!(lock.to_string().trim_end(), ":test.server MODE #chan +i");
+
+        tm.run(&mut alice, "JOIN #reg").await;
+        tm.run(&mut alice, "MODE #reg +R").await;
+        let replies = tm.run(&mut carol, "JOIN #reg").await;
+        assert!(has_error(&replies, Response::ERR_NEEDREGGEDNICK));
+    }
+
+    #[tokio::test]
+    async fn test_history_replay_on_join() {
+        use crate::test_support::TestMatrixBuilder;
+        use slirc_proto::Command;
+
+        let tm = TestMatrixBuilder::new()
+            .config(|c| {
+                c.history.enabled = true;
+                c.history.backend = "memory".to_string();
+            })
+            .build()
+            .await;
+        let mut alice = tm.user("alice").build().await;
+        let mut bob = tm.user("bob").cap("batch").build().await;
+        let mut carol = tm.user("carol").build().await;
+        let mut dave = tm.user("dave").cap("draft/chathistory").build().await;
+
+        tm.run(&mut alice, "JOIN #chan").await;
+        let replies = tm.run(&mut alice, "MODE #chan +H 101:60").await;
+        assert!(replies.iter().any(|m| matches!(
+            &m.command,
+            Command::Response(slirc_proto::Response::ERR_INVALIDMODEPARAM, _)
+        )));
+        tm.run(&mut alice, "MODE #chan +H 2:60").await;
+        for text in ["one", "two", "three"] {
+            tm.run(&mut alice, &format!("PRIVMSG #chan :{text}")).await;
+            // History orders by millisecond timestamp
+            tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        }
+
+        let replies = tm.run(&mut bob, "JOIN #chan").await;
+        let replayed: Vec<&str> = replies
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::PRIVMSG(_, text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(replayed, ["two", "three"]);
+        assert!(replies.iter().any(|m| matches!(
+            &m.command,
+            Command::BATCH(id, Some(_), Some(args)) if id.starts_with('+') && args == &["#chan"]
+        )));
+
+        let replies = tm.run(&mut carol, "JOIN #chan").await;
+        let notices: Vec<&str> = replies
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::NOTICE(target, text) if target == "#chan" => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(notices.len(), 2);
+        assert!(notices[0].ends_with("<alice> two"));
+        assert!(notices[1].ends_with("<alice> three"));
+
+        let replies = tm.run(&mut dave, "JOIN #chan").await;
+        assert!(!replies.iter().any(|m| matches!(
+            &m.command,
+            Command::PRIVMSG(..) | Command::NOTICE(..) | Command::BATCH(..)
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_registered_channel_restores_modes_and_topic() {
+        use crate::test_support::{TestMatrixBuilder, wait_until};
+        use slirc_proto::{Command, Response};
+
+        let tm = TestMatrixBuilder::new().build().await;
+        let account = tm
+            .db
+            .accounts()
+            .register("alice", "hunter22", None)
+            .await
+            .unwrap();
+        tm.db
+            .channels()
+            .register("#reg", account.id, None)
+            .await
+            .unwrap();
+        let channels = &tm.matrix.channel_manager;
+        channels.registered_channels.insert("#reg".to_string());
+        let mut alice = tm.user("alice").account("alice").build().await;
+
+        tm.run(&mut alice, "JOIN #reg").await;
+        tm.run(&mut alice, "MODE #reg +sk hunter").await;
+        tm.run(&mut alice, "TOPIC #reg :kept across restarts").await;
+        tm.run(&mut alice, "PART #reg").await;
+        assert!(wait_until(|| !channels.channels.contains_key("#reg")).await);
+
+        let record = tm
+            .db
+            .channels()
+            .find_by_name("#reg")
+            .await
+            .unwrap()
+            .unwrap();
+        let saved = record.modes.expect("modes saved");
+        assert!(saved.contains('s') && saved.ends_with(" hunter"));
+
+        let replies = tm.run(&mut alice, "JOIN #reg hunter").await;
+        assert!(replies.iter().any(|m| matches!(
+            &m.command,
+            Command::Response(Response::RPL_TOPIC, args) if args[2] == "kept across restarts"
+        )));
+        let replies = tm.run(&mut alice, "MODE #reg").await;
+        let modes = replies
+            .iter()
+            .find_map(|m| match &m.command {
+                Command::Response(Response::RPL_CHANNELMODEIS, args) => Some(args[2].clone()),
+                _ => None,
+            })
+            .expect("RPL_CHANNELMODEIS");
+        assert!(modes.contains('s') && modes.contains('k'));
+    }
+
+    #[tokio::test]
+    async fn test_registered_channel_restores_ban_list() {
+        use crate::test_support::{TestMatrixBuilder, wait_until};
+        use slirc_proto::{Command, Response};
+
+        let tm = TestMatrixBuilder::new().build().await;
+        let account = tm
+            .db
+            .accounts()
+            .register("alice", "hunter22", None)
+            .await
+            .unwrap();
+        tm.db
+            .channels()
+            .register("#reg", account.id, None)
+            .await
+            .unwrap();
+        let channels = &tm.matrix.channel_manager;
+        channels.registered_channels.insert("#reg".to_string());
+        let mut alice = tm.user("alice").account("alice").build().await;
+        let mut bob = tm.user("bob").build().await;
+
+        tm.run(&mut alice, "JOIN #reg").await;
+        tm.run(&mut alice, "MODE #reg +bq bob!*@* *!*@quiet").await;
+        tm.run(&mut alice, "PART #reg").await;
+        assert!(wait_until(|| !channels.channels.contains_key("#reg")).await);
+
+        let replies = tm.run(&mut alice, "JOIN #reg").await;
+        assert!(
+            replies
+                .iter()
+                .any(|m| matches!(m.command, Command::JOIN(..)))
+        );
+        let replies = tm.run(&mut bob, "JOIN #reg").await;
+        assert!(replies.iter().any(|m| matches!(
+            m.command,
+            Command::Response(Response::ERR_BANNEDFROMCHAN, _)
+        )));
+        let replies = tm.run(&mut alice, "MODE #reg +q").await;
+        assert!(replies.iter().any(|m| matches!(
+            &m.command,
+            Command::Response(_, args) if args.iter().any(|a| a == "*!*@quiet")
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_join_extban_follows_other_channel() {
+        use crate::test_support::TestMatrixBuilder;
+        use slirc_proto::{Command, Response};
+
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
+        let mut bob = tm.user("bob").build().await;
+        let mut carol = tm.user("carol").build().await;
+        let banned = |replies: &[slirc_proto::Message]| {
+            replies.iter().any(|m| {
+                matches!(
+                    m.command,
+                    Command::Response(Response::ERR_BANNEDFROMCHAN, _)
+                )
+            })
+        };
+
+        tm.run(&mut alice, "JOIN #spam").await;
+        tm.run(&mut alice, "MODE #spam +b bob!*@*").await;
+        tm.run(&mut alice, "JOIN #main").await;
+        // Channels may name each other
+        tm.run(&mut alice, "MODE #main +b $j:#spam").await;
+        tm.run(&mut alice, "MODE #spam +b $j:#main").await;
+
+        assert!(banned(&tm.run(&mut bob, "JOIN #main").await));
+        assert!(!banned(&tm.run(&mut carol, "JOIN #main").await));
+    }
 }
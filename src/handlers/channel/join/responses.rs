@@ -3,9 +3,13 @@
 use super::super::super::{HandlerResult, server_reply, user_prefix, with_label};
 use crate::error::ChannelError;
 use crate::handlers::ResponseMiddleware;
+use crate::handlers::chathistory::helpers::history_item_to_message;
+use crate::history::types::HistoryItem;
+use crate::history::{HistoryProvider, HistoryQuery};
 use crate::state::actor::ChannelEvent;
 use crate::state::managers::client::ClientManager;
-use slirc_proto::{Command, Message, Prefix, Response};
+use slirc_proto::{BatchSubCommand, Command, Message, Prefix, Response, Tag, irc_to_lower};
+use uuid::Uuid;
 
 /// Context for handling successful JOIN responses.
 pub(super) struct JoinSuccessContext<'a> {
@@ -16,6 +20,7 @@ pub(super) struct JoinSuccessContext<'a> {
     pub user_manager: &'a crate::state::UserManager,
     pub channel_manager: &'a crate::state::ChannelManager,
     pub client_manager: &'a ClientManager,
+    pub history: &'a dyn HistoryProvider,
     pub config_multiclient: bool,
     pub uid_str: String,
     pub caps: std::collections::HashSet<String>,
@@ -45,6 +50,7 @@ pub(super) async fn handle_join_success(
         user_manager,
         channel_manager,
         client_manager,
+        history,
         config_multiclient,
         uid_str,
         caps,
@@ -176,6 +182,17 @@ pub(super) async fn handle_join_success(
     )
     .await?;
 
+    // Replay recent lines (+H) to clients that can't fetch them themselves
+    send_history_replay(
+        response_sender.clone(),
+        history,
+        &server_name,
+        active_batch_id.as_deref(),
+        &caps,
+        &data,
+    )
+    .await?;
+
     Ok(Some(self_join_msg))
 }
 
@@ -308,6 +325,124 @@ pub(super) async fn send_names_list(
     Ok(())
 }
 
+/// Replay the channel's recent messages if it has +H set.
+///
+/// Clients with `draft/chathistory` are skipped since they fetch history
+/// themselves. Clients with `batch` get a `chathistory` batch; the rest get
+/// one server NOTICE per line.
+pub(super) async fn send_history_replay(
+    sender: ResponseMiddleware<'_>,
+    history: &dyn HistoryProvider,
+    server_name: &str,
+    active_batch_id: Option<&str>,
+    caps: &std::collections::HashSet<String>,
+    data: &crate::state::actor::JoinSuccessData,
+) -> HandlerResult {
+    let Some((lines, minutes)) = data.history_replay else {
+        return Ok(());
+    };
+    if caps.contains("draft/chathistory") {
+        return Ok(());
+    }
+
+    let channel = &data.channel_name;
+    let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(i64::MAX);
+    let start = now.saturating_sub(i64::from(minutes) * 60 * 1_000_000_000);
+    let lines = lines as usize;
+
+    // Page backwards, since events (joins, parts, ...) share the timeline
+    let mut items: Vec<HistoryItem> = Vec::with_capacity(lines);
+    let mut end: Option<(i64, String)> = None;
+    while items.len() < lines {
+        let query = HistoryQuery {
+            target: irc_to_lower(channel),
+            start: Some(start),
+            end: end.as_ref().map(|(nanotime, _)| *nanotime),
+            start_id: None,
+            end_id: end.as_ref().map(|(_, id)| id.clone()),
+            limit: lines,
+            reverse: true,
+        };
+        let page = match history.query(query).await {
+            Ok(page) => page,
+            Err(e) => {
+                tracing::warn!(channel = %channel, error = %e, "History replay query failed");
+                return Ok(());
+            }
+        };
+        let exhausted = page.len() < lines;
+        end = page.last().map(|item| match item {
+            HistoryItem::Message(m) => (m.nanotime, m.msgid.clone()),
+            HistoryItem::Event(e) => (e.nanotime, e.id.clone()),
+        });
+        // Statusmsg lines were never meant for everyone
+        items.extend(page.into_iter().filter(|item| {
+            matches!(item, HistoryItem::Message(m)
+                if m.status_prefix.is_none()
+                    && matches!(m.envelope.command.as_str(), "PRIVMSG" | "NOTICE"))
+        }));
+        if exhausted || end.is_none() {
+            break;
+        }
+    }
+    items.truncate(lines);
+    if items.is_empty() {
+        return Ok(());
+    }
+    items.reverse();
+
+    let send_err = |_| crate::error::HandlerError::Internal("Failed to send history".into());
+
+    if !caps.contains("batch") {
+        for item in &items {
+            let HistoryItem::Message(msg) = item else {
+                continue;
+            };
+            let nick = msg.envelope.prefix.split('!').next().unwrap_or_default();
+            let time = chrono::DateTime::from_timestamp_nanos(msg.nanotime).format("%H:%M:%S");
+            let line = if msg.envelope.command == "NOTICE" {
+                format!("[{time}] -{nick}- {}", msg.envelope.text)
+            } else {
+                format!("[{time}] <{nick}> {}", msg.envelope.text)
+            };
+            let mut notice = Message {
+                tags: None,
+                prefix: Some(Prefix::ServerName(server_name.to_string())),
+                command: Command::NOTICE(channel.clone(), line),
+            };
+            if let Some(batch_id) = active_batch_id {
+                notice = notice.with_tag("batch", Some(batch_id));
+            }
+            sender.send(notice).await.map_err(send_err)?;
+        }
+        return Ok(());
+    }
+
+    let batch_id = format!("chathistory-{}", Uuid::new_v4().simple());
+    let batch_start = Message {
+        tags: active_batch_id.map(|outer| vec![Tag::new("batch", Some(outer.to_string()))]),
+        prefix: Some(Prefix::ServerName(server_name.to_string())),
+        command: Command::BATCH(
+            format!("+{batch_id}"),
+            Some(BatchSubCommand::CUSTOM("chathistory".to_string())),
+            Some(vec![channel.clone()]),
+        ),
+    };
+    sender.send(batch_start).await.map_err(send_err)?;
+    for item in &items {
+        if let Some(msg) = history_item_to_message(item, &batch_id, channel, false) {
+            sender.send(msg).await.map_err(send_err)?;
+        }
+    }
+    let batch_end = Message {
+        tags: active_batch_id.map(|outer| vec![Tag::new("batch", Some(outer.to_string()))]),
+        prefix: Some(Prefix::ServerName(server_name.to_string())),
+        command: Command::BATCH(format!("-{batch_id}"), None, None),
+    };
+    sender.send(batch_end).await.map_err(send_err)?;
+    Ok(())
+}
+
 /// Send appropriate error response for JOIN failure.
 pub(super) async fn send_join_error(
     sender: ResponseMiddleware<'_>,
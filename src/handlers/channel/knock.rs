@@ -1,18 +1,18 @@
 //! KNOCK command handler
 //!
-//! RFC-style extension - Request invite to an invite-only channel
+//! RFC-style extension - Request invite to an invite-only channel.
+//! Channel operators receive RPL_KNOCK; `+K` disables knocking. Knocks are
+//! rate limited per user and channel (`channels.knock_delay_secs`) and
+//! channel-wide (`channels.knock_channel_delay_secs`).
 
 use super::super::{Context, HandlerResult, PostRegHandler, server_reply};
 use crate::state::RegisteredState;
 use crate::state::actor::{ChannelError, ChannelEvent};
 use async_trait::async_trait;
 use slirc_proto::{MessageRef, Response, irc_to_lower};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::oneshot;
 
-/// Minimum seconds between KNOCK requests to the same channel per user.
-const KNOCK_COOLDOWN_SECS: u64 = 30;
-
 /// Handler for KNOCK command.
 ///
 /// `KNOCK channel [message]`
@@ -34,8 +34,12 @@ impl PostRegHandler for KnockHandler {
             return Ok(());
         };
 
+        let reason = msg.arg(1).filter(|r| !r.is_empty()).map(str::to_string);
         let server_name = ctx.server_name().to_string();
         let channel_lower = irc_to_lower(channel_name);
+        let channels_cfg = &ctx.matrix.config.channels;
+        let knock_delay = channels_cfg.knock_delay_secs;
+        let channel_delay = Duration::from_secs(channels_cfg.knock_channel_delay_secs);
 
         // Get user info
         let (nick, user, host) = {
@@ -68,8 +72,8 @@ impl PostRegHandler for KnockHandler {
         let now = Instant::now();
         if let Some(last_knock) = ctx.state.knock_timestamps.get(&channel_lower) {
             let elapsed = now.duration_since(*last_knock).as_secs();
-            if elapsed < KNOCK_COOLDOWN_SECS {
-                let remaining = KNOCK_COOLDOWN_SECS - elapsed;
+            if elapsed < knock_delay {
+                let remaining = knock_delay - elapsed;
                 let reply = server_reply(
                     &server_name,
                     Response::ERR_TOOMANYKNOCK,
@@ -107,6 +111,8 @@ impl PostRegHandler for KnockHandler {
         let event = ChannelEvent::Knock {
             sender_uid: ctx.uid.to_string(),
             sender_prefix,
+            reason,
+            channel_delay,
             reply_tx,
         };
 
@@ -129,38 +135,16 @@ impl PostRegHandler for KnockHandler {
             }
             Ok(Err(e)) => {
                 let reply = match e {
-                    ChannelError::CannotKnock => server_reply(
-                        &server_name,
-                        Response::ERR_CHANOPRIVSNEEDED,
-                        vec![
-                            nick.clone(),
-                            channel_name.to_string(),
-                            "Knocking is disabled on this channel (+K)".to_string(),
-                        ],
-                    ),
-                    ChannelError::ChanOpen => server_reply(
-                        &server_name,
-                        Response::ERR_CHANOPEN,
-                        vec![
-                            nick.clone(),
-                            channel_name.to_string(),
-                            "Channel is open, just join it".to_string(),
-                        ],
-                    ),
                     ChannelError::UserOnChannel(_) => server_reply(
                         &server_name,
                         Response::ERR_KNOCKONCHAN,
                         vec![
-                            nick.clone(),
+                            nick,
                             channel_name.to_string(),
                             "You're already on that channel".to_string(),
                         ],
                     ),
-                    _ => server_reply(
-                        &server_name,
-                        Response::ERR_UNKNOWNERROR,
-                        vec![nick.clone(), e.to_string()],
-                    ),
+                    e => e.to_irc_reply(&server_name, &nick, channel_name),
                 };
                 ctx.sender.send(reply).await?;
             }
@@ -170,3 +154,63 @@ impl PostRegHandler for KnockHandler {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{Command, Message, Response};
+
+    fn codes(messages: &[Message]) -> Vec<Response> {
+        messages
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::Response(resp, _) => Some(*resp),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn knock_reaches_ops_only_and_is_throttled() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
+        let mut carol = tm.user("carol").build().await;
+        let mut bob = tm.user("bob").build().await;
+        let mut dave = tm.user("dave").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.run(&mut carol, "JOIN #chan").await;
+        tm.run(&mut alice, "JOIN #open").await;
+
+        let replies = tm.run(&mut bob, "KNOCK #open").await;
+        assert_eq!(codes(&replies), [Response::ERR_CHANOPEN]);
+
+        tm.run(&mut alice, "MODE #chan +i").await;
+        alice.drain();
+        carol.drain();
+        let replies = tm.run(&mut bob, "KNOCK #chan :let me in").await;
+        assert_eq!(codes(&replies), [Response::RPL_KNOCKDLVR]);
+        let knock = alice
+            .recv_matching(|m| matches!(&m.command, Command::Response(Response::RPL_KNOCK, _)))
+            .await
+            .expect("op sees the knock");
+        let Command::Response(_, args) = &knock.command else {
+            unreachable!()
+        };
+        assert_eq!(args[0], "alice");
+        assert_eq!(args[1], "#chan");
+        assert!(args[2].starts_with("bob!"));
+        assert_eq!(args[3], "has asked for an invite (let me in)");
+        assert!(codes(&carol.drain()).is_empty());
+
+        // Per user and channel-wide delays
+        let replies = tm.run(&mut bob, "KNOCK #chan").await;
+        assert_eq!(codes(&replies), [Response::ERR_TOOMANYKNOCK]);
+        let replies = tm.run(&mut dave, "KNOCK #chan").await;
+        assert_eq!(codes(&replies), [Response::ERR_TOOMANYKNOCK]);
+
+        tm.run(&mut alice, "MODE #chan +K").await;
+        let mut erin = tm.user("erin").build().await;
+        let replies = tm.run(&mut erin, "KNOCK #chan").await;
+        assert_eq!(codes(&replies), [Response::ERR_CANNOTKNOCK]);
+    }
+}
@@ -243,6 +243,7 @@ impl PostRegHandler for ListHandler {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::TestMatrixBuilder;
 
     #[test]
     fn test_wildcard_match() {
@@ -327,4 +328,31 @@ mod tests {
         assert!(!f.matches("#badchan", 10));
         assert!(f.matches("#goodchan", 10));
     }
+
+    #[tokio::test]
+    async fn test_list_member_count_skips_services() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
+        let _link = tm.peer("00A").await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.run_server(
+            "00A",
+            ":00A UID Bridge 1 1700000000 bridge bridge.remote 00AAAAAAS +S :Bridge",
+        )
+        .await;
+        tm.run_server("00A", ":00AAAAAAS JOIN 1700000000 #chan +")
+            .await;
+
+        let replies = tm.run(&mut alice, "LIST #chan").await;
+        let counts: Vec<&str> = replies
+            .iter()
+            .filter_map(|m| match &m.command {
+                slirc_proto::Command::Response(Response::RPL_LIST, args) => {
+                    args.get(2).map(String::as_str)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(counts, ["1"]);
+    }
 }
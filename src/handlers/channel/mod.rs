@@ -4,6 +4,7 @@
 
 pub mod common;
 mod cycle;
+mod hop;
 mod invite;
 mod join;
 mod kick;
@@ -21,7 +22,7 @@ pub use kick::KickHandler;
 pub use knock::KnockHandler;
 pub use list::ListHandler;
 pub use names::NamesHandler;
-pub use ops::{TargetUser, force_join_channel, force_part_channel};
+pub use ops::{TargetUser, force_join_channel, force_join_local, force_part_channel};
 pub use part::PartHandler;
 pub use topic::TopicHandler;
 
@@ -127,7 +127,7 @@ impl NamesHandler {
                 }
             }
             // Sort alphabetically by nick (case-insensitive) for deterministic output
-            names_list.sort_by(|a, b| a.0.to_lowercase().cmp(&b.0.to_lowercase()));
+            names_list.sort_by_key(|a| a.0.to_lowercase());
             let names_list: Vec<String> =
                 names_list.into_iter().map(|(_, display)| display).collect();
 
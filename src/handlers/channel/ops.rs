@@ -5,7 +5,7 @@
 //! permission checks, allowing callers to implement their own access control.
 
 use super::super::{Context, HandlerError, HandlerResult, server_reply, with_label};
-use crate::state::MemberModes;
+use crate::state::{Matrix, MemberModes};
 use slirc_proto::{Command, Message, Prefix, Response, irc_to_lower};
 use std::sync::Arc;
 use tracing::info;
@@ -49,15 +49,37 @@ pub async fn force_join_channel<S>(
     target: &TargetUser<'_>,
     channel_name: &str,
     modes: MemberModes,
-    send_topic_names_to: Option<&tokio::sync::mpsc::Sender<std::sync::Arc<Message>>>,
+    send_topic_names_to: Option<&crate::state::SendQ>,
+) -> HandlerResult {
+    force_join_local(
+        ctx.matrix,
+        target,
+        channel_name,
+        modes,
+        send_topic_names_to,
+        ctx.label.as_deref(),
+    )
+    .await
+}
+
+/// [`force_join_channel`] without a handler context, for service effects
+/// and server-issued joins (SVSJOIN).
+///
+/// `label` tags the final RPL_ENDOFNAMES for labeled-response.
+pub async fn force_join_local(
+    matrix: &Arc<Matrix>,
+    target: &TargetUser<'_>,
+    channel_name: &str,
+    modes: MemberModes,
+    send_topic_names_to: Option<&crate::state::SendQ>,
+    label: Option<&str>,
 ) -> HandlerResult {
     let channel_lower = irc_to_lower(channel_name);
-    let mailbox_capacity = ctx.matrix.config.limits.channel_mailbox_capacity;
+    let mailbox_capacity = matrix.config.limits.channel_mailbox_capacity;
 
     // Get or create channel
-    let observer = ctx.matrix.channel_manager.observer.clone();
-    let channel_ref = ctx
-        .matrix
+    let observer = matrix.channel_manager.observer.clone();
+    let channel_ref = matrix
         .channel_manager
         .channels
         .entry(channel_lower.clone())
@@ -65,7 +87,7 @@ pub async fn force_join_channel<S>(
             crate::metrics::inc_active_channels();
             crate::state::actor::ChannelActor::spawn_with_capacity(
                 channel_name.to_string(),
-                std::sync::Arc::downgrade(ctx.matrix),
+                std::sync::Arc::downgrade(matrix),
                 None, // initial_topic
                 None, // initial_modes
                 None, // initial_metadata
@@ -77,8 +99,7 @@ pub async fn force_join_channel<S>(
         .clone();
 
     // Get user data
-    let (caps, user_context, sender, session_id) = if let Some(user_arc) = ctx
-        .matrix
+    let (caps, user_context, sender, session_id) = if let Some(user_arc) = matrix
         .user_manager
         .users
         .get(target.uid)
@@ -91,13 +112,13 @@ pub async fn force_join_channel<S>(
                 nickname: user.nick.clone(),
                 username: user.user.clone(),
                 realname: user.realname.clone(),
-                server: ctx.server_name().to_string(),
+                server: matrix.server_info.name.clone(),
                 account: user.account.clone(),
                 is_tls: user.modes.secure,
                 is_oper: user.modes.oper,
                 oper_type: user.modes.oper_type.clone(),
             });
-        let sender = ctx.matrix.user_manager.get_first_sender(target.uid);
+        let sender = matrix.user_manager.get_first_sender(target.uid);
         (user.caps.clone(), context, sender, user.session_id)
     } else {
         return Ok(());
@@ -161,8 +182,7 @@ pub async fn force_join_channel<S>(
     };
 
     // Add channel to user's list
-    if let Some(user_arc) = ctx
-        .matrix
+    if let Some(user_arc) = matrix
         .user_manager
         .users
         .get(target.uid)
@@ -183,7 +203,7 @@ pub async fn force_join_channel<S>(
         // Send topic if set
         if let Some(topic) = join_data.topic {
             let topic_reply = server_reply(
-                ctx.server_name(),
+                &matrix.server_info.name,
                 Response::RPL_TOPIC,
                 vec![
                     target.nick.to_string(),
@@ -207,8 +227,7 @@ pub async fn force_join_channel<S>(
             let mut names_list = Vec::with_capacity(members.len());
 
             for (uid, member_modes) in members {
-                if let Some(user_arc) = ctx
-                    .matrix
+                if let Some(user_arc) = matrix
                     .user_manager
                     .users
                     .get(&uid)
@@ -225,7 +244,7 @@ pub async fn force_join_channel<S>(
             }
 
             let names_reply = server_reply(
-                ctx.server_name(),
+                &matrix.server_info.name,
                 Response::RPL_NAMREPLY,
                 vec![
                     target.nick.to_string(),
@@ -239,7 +258,7 @@ pub async fn force_join_channel<S>(
 
         let end_names = with_label(
             server_reply(
-                ctx.server_name(),
+                &matrix.server_info.name,
                 Response::RPL_ENDOFNAMES,
                 vec![
                     target.nick.to_string(),
@@ -247,7 +266,7 @@ pub async fn force_join_channel<S>(
                     "End of /NAMES list".to_string(),
                 ],
             ),
-            ctx.label.as_deref(),
+            label,
         );
         sender.send(Arc::new(end_names)).await?;
     }
@@ -301,13 +320,15 @@ pub async fn force_part_channel<S>(
 
     if (channel_sender.send(event).await).is_err() {
         // Channel actor died, remove it
-        ctx.matrix.channel_manager.channels.remove(channel_lower);
+        ctx.matrix
+            .channel_manager
+            .remove_if_current(channel_lower, &channel_sender);
         return Ok(false);
     }
 
     match reply_rx.await {
-        Ok(Ok(remaining_members)) => {
-            // Success
+        Ok(Ok(_)) => {
+            // Success; an emptied channel unregisters itself
             // Remove channel from user's list
             let user_arc = ctx
                 .matrix
@@ -320,11 +341,6 @@ pub async fn force_part_channel<S>(
                 user.channels.remove(channel_lower);
             }
 
-            if remaining_members == 0 {
-                ctx.matrix.channel_manager.channels.remove(channel_lower);
-                crate::metrics::dec_active_channels();
-            }
-
             Ok(true)
         }
         Ok(Err(_)) => Ok(false), // User not in channel
@@ -46,6 +46,7 @@ impl PostRegHandler for PartHandler {
         // PART <channels> [reason]
         let channels_str = msg.arg(0).ok_or(HandlerError::NeedMoreParams)?;
         let reason = parse_reason(msg.arg(1));
+        super::hop::check_join_part(ctx, channels_str, false).await?;
 
         let (nick, user_name, host) = user_mask_from_state(ctx, ctx.uid)
             .await
@@ -102,13 +103,15 @@ pub(super) async fn leave_channel_internal(
 
     if (channel_sender.send(event).await).is_err() {
         // Channel actor died, remove it
-        ctx.matrix.channel_manager.channels.remove(channel_lower);
+        ctx.matrix
+            .channel_manager
+            .remove_if_current(channel_lower, &channel_sender);
         return Ok(());
     }
 
     match reply_rx.await {
-        Ok(Ok(remaining_members)) => {
-            // Success
+        Ok(Ok(_)) => {
+            // Success; an emptied channel unregisters itself
             // Remove channel from user's list
             let user_arc = ctx
                 .matrix
@@ -133,11 +136,6 @@ pub(super) async fn leave_channel_internal(
                     .await;
             }
 
-            if remaining_members == 0 {
-                ctx.matrix.channel_manager.channels.remove(channel_lower);
-                crate::metrics::dec_active_channels();
-            }
-
             info!(nick = %nick, channel = %channel_lower, "User left channel");
         }
         Ok(Err(e)) => {
@@ -155,7 +153,9 @@ pub(super) async fn leave_channel_internal(
         }
         Err(_) => {
             // Actor dropped
-            ctx.matrix.channel_manager.channels.remove(channel_lower);
+            ctx.matrix
+                .channel_manager
+                .remove_if_current(channel_lower, &channel_sender);
         }
     }
 
@@ -19,7 +19,7 @@
 //! - Query: Returns current topic or RPL_NOTOPIC if unset
 //! - Set: Requires channel op (+o) if +t mode is set
 //! - Broadcasts topic change to all channel members
-//! - Persists topic to database for registered channels with keeptopic enabled
+//! - Registered channels with keeptopic enabled persist the topic (via the channel actor)
 //! - Stores TOPIC event in history for event-playback (Innovation 5)
 //! - Uses CapabilityAuthority (Innovation 4) for authorization
 
@@ -32,7 +32,7 @@ use async_trait::async_trait;
 use slirc_proto::{MessageRef, Response, irc_to_lower};
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::oneshot;
-use tracing::{info, warn};
+use tracing::info;
 use uuid::Uuid;
 
 #[derive(Debug, PartialEq)]
@@ -133,9 +133,6 @@ impl PostRegHandler for TopicHandler {
                     .ok_or(HandlerError::NickOrUserMissing)?;
                 let sender_prefix = slirc_proto::Prefix::new(nick.clone(), user, host);
 
-                // Save prefix string for persistence (before moving into event)
-                let set_by_string = sender_prefix.to_string();
-
                 // Generate msgid and timestamp for event-playback (Innovation 5)
                 let msgid = Uuid::new_v4().to_string();
                 let now = SystemTime::now();
@@ -171,30 +168,8 @@ impl PostRegHandler for TopicHandler {
 
                 match reply_rx.await {
                     Ok(Ok(())) => {
+                        // The channel actor persists it for registered channels
                         info!(nick = %nick, channel = %channel_name, "Topic changed");
-
-                        info!(nick = %nick, channel = %channel_name, "Topic changed");
-
-                        // Persist topic to database for registered channels with keeptopic
-                        if let Some(channel_record) = ctx
-                            .db
-                            .channels()
-                            .find_by_name(&channel_lower)
-                            .await
-                            .ok()
-                            .flatten()
-                            && channel_record.keeptopic
-                        {
-                            let set_at = chrono::Utc::now().timestamp();
-                            if let Err(e) = ctx
-                                .db
-                                .channels()
-                                .save_topic(channel_record.id, topic_text, &set_by_string, set_at)
-                                .await
-                            {
-                                warn!(channel = %channel_name, error = %e, "Failed to persist topic");
-                            }
-                        }
                     }
                     Ok(Err(e)) => {
                         let reply = e.to_irc_reply(ctx.server_name(), &nick, channel_name);
@@ -94,21 +94,27 @@ pub fn exclusivity_offset(resolved: &ResolvedTimestamp) -> i64 {
     }
 }
 
+/// The caller's half of a DM key: `a:<account>` if logged in, else `u:<nick>`.
+pub fn dm_self_key(ctx: &Context<'_, RegisteredState>, nick: &str) -> String {
+    // Prefix with 'a:' for account, 'u:' for unregistered nick to avoid collisions
+    if let Some(acct) = &ctx.state.account {
+        format!("a:{}", slirc_proto::irc_to_lower(acct))
+    } else {
+        format!("u:{}", slirc_proto::irc_to_lower(nick))
+    }
+}
+
 /// Resolve the DM key for history storage.
 /// DMs are stored under a canonical key that combines both participants.
+///
+/// A target that is not online but names a registered account resolves to
+/// that account, so names returned by TARGETS can be queried back.
 pub async fn resolve_dm_key(
     ctx: &Context<'_, RegisteredState>,
     nick: &str,
     target: &str,
 ) -> String {
-    // Resolve self (nick) to account
-    // Prefix with 'a:' for account, 'u:' for unregistered nick to avoid collisions
-    let sender_key_part = if let Some(acct) = &ctx.state.account {
-        format!("a:{}", slirc_proto::irc_to_lower(acct))
-    } else {
-        // This should not happen for RegisteredState context, but fallback to nick
-        format!("u:{}", slirc_proto::irc_to_lower(nick))
-    };
+    let sender_key_part = dm_self_key(ctx, nick);
 
     // Resolve target to account
     let target_lower = slirc_proto::irc_to_lower(target);
@@ -120,7 +126,14 @@ pub async fn resolve_dm_key(
             None
         }
     } else {
-        None
+        ctx.matrix
+            .db
+            .accounts()
+            .find_by_name(target)
+            .await
+            .ok()
+            .flatten()
+            .map(|account| account.name)
     };
 
     let target_key_part = if let Some(acct) = target_account {
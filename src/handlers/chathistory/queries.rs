@@ -6,7 +6,9 @@ use crate::state::RegisteredState;
 use slirc_proto::{ChatHistorySubCommand, MessageReference, parse_server_time};
 use tracing::debug;
 
-use super::helpers::{QueryParams, exclusivity_offset, resolve_dm_key, resolve_msgref};
+use super::helpers::{
+    QueryParams, dm_self_key, exclusivity_offset, resolve_dm_key, resolve_msgref,
+};
 use super::slicing::slice_around;
 
 /// Implements all CHATHISTORY query operations.
@@ -210,34 +212,46 @@ impl QueryExecutor {
             target.to_string()
         };
 
-        let center_ts = resolve_msgref(ctx, &query_target, msgref_str)
-            .await?
-            .map(|r| r.timestamp)
-            .unwrap_or(0);
+        // An unknown msgid (or one from another target) has no window
+        let Some(center) = resolve_msgref(ctx, &query_target, msgref_str).await? else {
+            return Ok(vec![]);
+        };
 
-        let all_messages_query = HistoryQuery {
+        // Fetch a full window on each side, so the slice can be centred anywhere
+        let before_query = HistoryQuery {
             target: query_target.clone(),
             start: None,
+            end: Some(center.timestamp),
+            start_id: None,
+            end_id: None,
+            limit: limit as usize,
+            reverse: true,
+        };
+        let after_query = HistoryQuery {
+            target: query_target,
+            start: Some(center.timestamp),
             end: None,
             start_id: None,
             end_id: None,
-            limit: 500,
+            limit: limit as usize,
             reverse: false,
         };
 
-        let messages = ctx
-            .matrix
-            .service_manager
-            .history
-            .query(all_messages_query)
+        let history = &ctx.matrix.service_manager.history;
+        let mut messages = history
+            .query(before_query)
             .await
             .map_err(|e| HandlerError::Internal(e.to_string()))?;
+        messages.reverse();
+        messages.extend(
+            history
+                .query(after_query)
+                .await
+                .map_err(|e| HandlerError::Internal(e.to_string()))?,
+        );
 
-        // Filter BEFORE slicing to ensure we don't slice around events the client can't see?
-        // Or slice first then filter?
-        // Slicing relies on "center" timestamp.
-        // It's safer to query, slice, then filter output.
-        let mut sliced = slice_around(messages, limit as usize, msgref_str, center_ts);
+        // Slice first, then filter: the centre may be an event
+        let mut sliced = slice_around(messages, limit as usize, msgref_str, center.timestamp);
 
         if !ctx.state.capabilities.contains("draft/event-playback") {
             sliced.retain(|item| matches!(item, HistoryItem::Message(_)));
@@ -368,12 +382,11 @@ impl QueryExecutor {
             .matrix
             .service_manager
             .history
-            .query_targets(start, end, limit as usize, nick.to_string(), channels)
+            .query_targets(start, end, limit as usize, dm_self_key(ctx, nick), channels)
             .await
             .map_err(|e| HandlerError::Internal(e.to_string()))?;
 
         let mut msgs = Vec::with_capacity(limit as usize);
-        let _nick_lower = slirc_proto::irc_to_lower(nick);
 
         for (target_name, timestamp) in targets {
             let dt = chrono::DateTime::<chrono::Utc>::from(
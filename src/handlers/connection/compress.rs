@@ -0,0 +1,83 @@
+//! COMPRESS command handler for switching on connection compression.
+//!
+//! Listeners with `compression = true` advertise the `sid3.xyz/compress`
+//! capability, whose value lists the algorithms on offer (only `zstd`).
+//! The sequence mirrors STARTTLS:
+//!
+//! 1. Client sends `COMPRESS ZSTD` and sends nothing more until the reply
+//! 2. Server replies `NOTE COMPRESS ACTIVE ZSTD`, uncompressed
+//! 3. From then on each direction is a single zstd stream, flushed per write
+//!
+//! Errors are sent as `FAIL COMPRESS` and leave the connection uncompressed.
+
+use super::super::{Context, HandlerError, HandlerResult, PreRegHandler};
+use crate::state::{SessionState, UnregisteredState};
+use async_trait::async_trait;
+use slirc_proto::{Command, Message, MessageRef};
+use tracing::info;
+
+/// Handler for COMPRESS command.
+///
+/// `COMPRESS <algorithm>`
+///
+/// Compresses the rest of the connection. Only valid before registration
+/// completes, on listeners that offer it.
+pub struct CompressHandler;
+
+#[async_trait]
+impl PreRegHandler for CompressHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, UnregisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let algorithm = msg.arg(0).ok_or(HandlerError::NeedMoreParams)?;
+
+        if ctx.state.is_compressed {
+            return send_fail(ctx, "ALREADY_ACTIVE", "Compression is already active").await;
+        }
+        if !ctx.state.offers_compression() {
+            return send_fail(ctx, "NOT_AVAILABLE", "Compression is not offered here").await;
+        }
+        if !algorithm.eq_ignore_ascii_case("ZSTD") {
+            return send_fail(ctx, "UNKNOWN_ALGORITHM", "Supported algorithms: zstd").await;
+        }
+
+        info!(
+            nick = ctx.state.nick.as_deref().unwrap_or("*"),
+            "COMPRESS requested"
+        );
+
+        let reply = Message {
+            tags: None,
+            prefix: Some(ctx.server_prefix()),
+            command: Command::NOTE(
+                "COMPRESS".to_string(),
+                "ACTIVE".to_string(),
+                vec!["ZSTD".to_string(), "Compression active".to_string()],
+            ),
+        };
+        ctx.sender.send(reply).await?;
+
+        // Signal to handshake loop to send the reply, then compress
+        Err(HandlerError::Compress)
+    }
+}
+
+async fn send_fail(
+    ctx: &mut Context<'_, UnregisteredState>,
+    code: &str,
+    description: &str,
+) -> HandlerResult {
+    let fail = Message {
+        tags: None,
+        prefix: Some(ctx.server_prefix()),
+        command: Command::FAIL(
+            "COMPRESS".to_string(),
+            code.to_string(),
+            vec![description.to_string()],
+        ),
+    };
+    ctx.sender.send(fail).await?;
+    Ok(())
+}
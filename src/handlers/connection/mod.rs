@@ -1,7 +1,8 @@
 //! Connection and registration handlers.
 //!
-//! Handles NICK, USER, PASS, PING, PONG, QUIT, STARTTLS commands.
+//! Handles NICK, USER, PASS, PING, PONG, QUIT, STARTTLS, COMPRESS commands.
 
+mod compress;
 mod nick;
 mod pass;
 mod ping;
@@ -11,6 +12,7 @@ mod user;
 mod webirc;
 mod welcome_burst;
 
+pub use compress::CompressHandler;
 pub use nick::NickHandler;
 pub use pass::PassHandler;
 pub use ping::{PingHandler, PongHandler};
@@ -40,5 +42,6 @@ pub fn register(
     pre_reg.insert("USER", Box::new(UserHandler));
     pre_reg.insert("PASS", Box::new(PassHandler));
     pre_reg.insert("STARTTLS", Box::new(StarttlsHandler));
+    pre_reg.insert("COMPRESS", Box::new(CompressHandler));
 }
 pub use welcome_burst::WelcomeBurstWriter;
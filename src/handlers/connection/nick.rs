@@ -16,6 +16,7 @@
 //! ## Behavior
 //! - Can be used before or after registration
 //! - Validates nickname format (length, allowed characters)
+//! - Rejects nicknames reserved by Q-lines (IRC operators are exempt)
 //! - Atomically reserves nickname to prevent race conditions
 //! - Enforces +N (no nick change) channel mode for registered users
 //! - Notifies MONITOR watchers when nickname changes
@@ -25,11 +26,12 @@ use super::super::{
     notify_monitors_online,
 };
 use crate::handlers::util::helpers::fanout::broadcast_to_account;
+use crate::services::enforce::enforce_delay;
 use crate::state::{SessionState, session::SaslAccess};
 use async_trait::async_trait;
 use dashmap::mapref::entry::Entry;
 use slirc_proto::{Command, Message, MessageRef, NickExt, Prefix, Response, irc_to_lower};
-use std::time::{Duration, Instant};
+use std::time::Instant;
 use tracing::{debug, info};
 
 const DEFAULT_NICK_MAX_LEN: usize = 30;
@@ -112,6 +114,17 @@ impl<S: SessionState + SaslAccess> UniversalHandler<S> for NickHandler {
             return Ok(());
         }
 
+        // Reserved nicknames (Q-lines). IRC operators may take reserved nicks.
+        if let Some(reason) = ctx.matrix.find_qline(nick) {
+            let is_oper = match ctx.matrix.user_manager.users.get(ctx.uid) {
+                Some(user_arc) => user_arc.read().await.modes.oper,
+                None => false,
+            };
+            if !is_oper {
+                return Err(HandlerError::NicknameReserved(nick.to_string(), reason));
+            }
+        }
+
         // Check for confusables under PRECIS casemapping
         if ctx.matrix.config.server.casemapping == crate::config::Casemapping::Precis {
             // Check against all registered nicks for confusables
@@ -328,7 +341,19 @@ impl<S: SessionState + SaslAccess> UniversalHandler<S> for NickHandler {
             // Only notify MONITOR watchers if the lowercase nick is changing
             // (not for case-only changes like qux -> QUX)
             if ctx.state.is_registered() && !is_case_only_change {
-                notify_monitors_offline(ctx.matrix, old_nick).await;
+                let user_arc = ctx
+                    .matrix
+                    .user_manager
+                    .users
+                    .get(ctx.uid)
+                    .map(|u| u.value().clone());
+                if let Some(user_arc) = user_arc {
+                    let (user, host) = {
+                        let user = user_arc.read().await;
+                        (user.user.clone(), user.visible_host.clone())
+                    };
+                    notify_monitors_offline(ctx.matrix, old_nick, &user, &host).await;
+                }
             }
 
             // If the lowercase nick is changing, remove the old mapping (case-only changes keep mapping)
@@ -428,7 +453,7 @@ impl<S: SessionState + SaslAccess> UniversalHandler<S> for NickHandler {
                 .map(|u| u.value().clone());
             let account = if let Some(user_arc) = user_arc {
                 let mut user = user_arc.write().await;
-                user.nick = nick.to_string();
+                user.set_nick(nick.to_string(), ctx.matrix.clock());
                 user.account.clone()
             } else {
                 None
@@ -480,16 +505,24 @@ impl<S: SessionState + SaslAccess> UniversalHandler<S> for NickHandler {
         if !is_identified {
             // Check if this nick is registered with ENFORCE enabled
             if let Ok(Some(account)) = ctx.db.accounts().find_by_nickname(nick).await
-                && account.enforce
+                && let Some(delay) = enforce_delay(&account, chrono::Utc::now().timestamp())
             {
-                // Start 60 second timer
-                let deadline = Instant::now() + Duration::from_secs(60);
+                let deadline = Instant::now() + delay;
                 ctx.matrix
                     .user_manager
                     .enforce_timers
                     .insert(ctx.uid.to_string(), deadline);
 
                 // Notify user
+                let text = if delay.is_zero() {
+                    "This nickname is registered and protected. You must identify before using it."
+                        .to_string()
+                } else {
+                    format!(
+                        "This nickname is registered. Please identify via \x02/msg NickServ IDENTIFY <password>\x02 within {} seconds.",
+                        delay.as_secs()
+                    )
+                };
                 let notice = Message {
                     tags: None,
                     prefix: Some(Prefix::new(
@@ -497,10 +530,7 @@ impl<S: SessionState + SaslAccess> UniversalHandler<S> for NickHandler {
                         "NickServ".to_string(),
                         "services.".to_string(),
                     )),
-                    command: Command::NOTICE(
-                        nick.to_string(),
-                        "This nickname is registered. Please identify via \x02/msg NickServ IDENTIFY <password>\x02 within 60 seconds.".to_string(),
-                    ),
+                    command: Command::NOTICE(nick.to_string(), text),
                 };
                 let _ = ctx.sender.send(notice).await;
                 info!(nick = %nick, uid = %ctx.uid, "Nick enforcement timer started");
@@ -51,6 +51,15 @@ impl PreRegHandler for StarttlsHandler {
             return Ok(());
         }
 
+        // The zstd stream cannot be carried into the TLS session
+        if ctx.state.is_compressed {
+            debug!("STARTTLS rejected: connection is compressed");
+            let reply = Response::err_starttls(nick, "Connection is compressed")
+                .with_prefix(ctx.server_prefix());
+            ctx.sender.send(reply).await?;
+            return Ok(());
+        }
+
         // Check if TLS is configured on this server
         // The handler returning StartTls will cause the lifecycle to check for TlsAcceptor
         // If none is available, it will send ERR_STARTTLS
@@ -2,17 +2,22 @@
 
 use super::super::{Context, HandlerResult, PreRegHandler};
 use crate::config::WebircBlock;
+use crate::handlers::is_valid_hostname;
+use crate::network::ReverseDns;
 use crate::state::UnregisteredState;
 use async_trait::async_trait;
 use slirc_proto::{Command, Message, MessageRef, wildcard_match};
+use std::net::IpAddr;
+use subtle::ConstantTimeEq;
 use tracing::{debug, info, warn};
 
 /// Handler for WEBIRC command.
 ///
-/// `WEBIRC password gateway hostname ip`
+/// `WEBIRC password gateway hostname ip [:options]`
 ///
 /// Allows trusted web gateways/proxies to forward real client information.
-/// Must be sent before NICK/USER registration.
+/// Must be sent before NICK/USER registration. The `secure` option marks the
+/// client's connection to the gateway as TLS, which sets +Z.
 pub struct WebircHandler {
     /// Configured WEBIRC blocks from server config.
     pub webirc_blocks: Vec<WebircBlock>,
@@ -25,22 +30,28 @@ impl WebircHandler {
     }
 
     /// Check if a WEBIRC request is authorized.
-    fn is_authorized(&self, password: &str, gateway_host: &str) -> bool {
-        for block in &self.webirc_blocks {
-            if block.password == password {
+    fn is_authorized(&self, password: &str, gateway: &str, gateway_host: &str) -> bool {
+        self.webirc_blocks.iter().any(|block| {
+            bool::from(block.password.as_bytes().ct_eq(password.as_bytes()))
+                && block.name.as_deref().is_none_or(|name| name == gateway)
                 // If no hosts specified, accept from anywhere
-                if block.hosts.is_empty() {
-                    return true;
-                }
-                // Check if gateway_host matches any allowed pattern
-                for host_pattern in &block.hosts {
-                    if wildcard_match(host_pattern, gateway_host) {
-                        return true;
-                    }
-                }
-            }
-        }
-        false
+                && (block.hosts.is_empty()
+                    || block
+                        .hosts
+                        .iter()
+                        .any(|pattern| host_matches(pattern, gateway_host)))
+        })
+    }
+}
+
+/// Match a gateway address against a glob pattern or CIDR range.
+fn host_matches(pattern: &str, gateway_host: &str) -> bool {
+    match (
+        pattern.parse::<ipnet::IpNet>(),
+        gateway_host.parse::<IpAddr>(),
+    ) {
+        (Ok(net), Ok(ip)) => net.contains(&ip),
+        _ => wildcard_match(pattern, gateway_host),
     }
 }
 
@@ -91,11 +102,17 @@ impl PreRegHandler for WebircHandler {
             }
         };
 
+        // Tor clients keep the fixed Tor host
+        if ctx.state.listener.as_ref().is_some_and(|l| l.tor) {
+            debug!("WEBIRC rejected: Tor listener");
+            return Ok(());
+        }
+
         // Get the gateway's connecting IP for authorization check
         let gateway_ip = ctx.remote_addr.ip().to_string();
 
         // Check authorization
-        if !self.is_authorized(password, &gateway_ip) {
+        if !self.is_authorized(password, gateway, &gateway_ip) {
             warn!(
                 gateway = %gateway,
                 gateway_ip = %gateway_ip,
@@ -111,16 +128,43 @@ impl PreRegHandler for WebircHandler {
             return Ok(());
         }
 
+        let Ok(ip) = ip.parse::<IpAddr>() else {
+            warn!(gateway = %gateway, ip = %ip, "WEBIRC rejected: invalid IP");
+            return Ok(());
+        };
+        let ip = ip.to_canonical();
+
+        // The lookup for the gateway's own address no longer applies
+        if let Some(lookup) = ctx.state.rdns.take() {
+            lookup.abort();
+        }
+        // Gateways send the IP as the hostname when they have none; look it up
+        // ourselves then
+        let hostname = if hostname.parse::<IpAddr>().is_err() && is_valid_hostname(hostname) {
+            Some(hostname.to_string())
+        } else {
+            ctx.state.rdns = ReverseDns::spawn_lookup(ctx.matrix, ip);
+            None
+        };
+        let ip = ip.to_string();
+
+        let secure = msg
+            .arg(4)
+            .is_some_and(|options| options.split(' ').any(|option| option == "secure"));
+
         // Store WEBIRC info in handshake state
         ctx.state.webirc_used = true;
-        ctx.state.webirc_ip = Some(ip.to_string());
-        ctx.state.webirc_host = Some(hostname.to_string());
+        ctx.state.webirc_ip = Some(ip.clone());
+        ctx.state.webirc_host = hostname.clone();
+        ctx.state.webirc_gateway = Some(gateway.to_string());
+        ctx.state.webirc_secure = secure;
 
         info!(
             gateway = %gateway,
             real_ip = %ip,
-            real_host = %hostname,
+            real_host = ?hostname,
             gateway_ip = %gateway_ip,
+            secure,
             "WEBIRC accepted"
         );
 
@@ -134,6 +178,7 @@ mod tests {
 
     fn make_block(password: &str, hosts: Vec<&str>) -> WebircBlock {
         WebircBlock {
+            name: None,
             password: password.to_string(),
             hosts: hosts.into_iter().map(|s| s.to_string()).collect(),
         }
@@ -147,29 +192,29 @@ mod tests {
     fn is_authorized_correct_password_no_hosts() {
         let handler = WebircHandler::new(vec![make_block("secret123", vec![])]);
         // Empty hosts list = accept from anywhere
-        assert!(handler.is_authorized("secret123", "192.168.1.1"));
-        assert!(handler.is_authorized("secret123", "any.host.com"));
+        assert!(handler.is_authorized("secret123", "gw", "192.168.1.1"));
+        assert!(handler.is_authorized("secret123", "gw", "any.host.com"));
     }
 
     #[test]
     fn is_authorized_wrong_password() {
         let handler = WebircHandler::new(vec![make_block("secret123", vec![])]);
-        assert!(!handler.is_authorized("wrongpass", "192.168.1.1"));
-        assert!(!handler.is_authorized("", "192.168.1.1"));
+        assert!(!handler.is_authorized("wrongpass", "gw", "192.168.1.1"));
+        assert!(!handler.is_authorized("", "gw", "192.168.1.1"));
     }
 
     #[test]
     fn is_authorized_correct_password_matching_host() {
         let handler = WebircHandler::new(vec![make_block("secret", vec!["192.168.1.*"])]);
-        assert!(handler.is_authorized("secret", "192.168.1.100"));
-        assert!(handler.is_authorized("secret", "192.168.1.1"));
+        assert!(handler.is_authorized("secret", "gw", "192.168.1.100"));
+        assert!(handler.is_authorized("secret", "gw", "192.168.1.1"));
     }
 
     #[test]
     fn is_authorized_correct_password_non_matching_host() {
         let handler = WebircHandler::new(vec![make_block("secret", vec!["192.168.1.*"])]);
-        assert!(!handler.is_authorized("secret", "192.168.2.100"));
-        assert!(!handler.is_authorized("secret", "10.0.0.1"));
+        assert!(!handler.is_authorized("secret", "gw", "192.168.2.100"));
+        assert!(!handler.is_authorized("secret", "gw", "10.0.0.1"));
     }
 
     #[test]
@@ -178,10 +223,10 @@ mod tests {
             make_block("pass1", vec!["10.0.0.*"]),
             make_block("pass2", vec!["192.168.*"]),
         ]);
-        assert!(handler.is_authorized("pass1", "10.0.0.5"));
-        assert!(handler.is_authorized("pass2", "192.168.1.1"));
-        assert!(!handler.is_authorized("pass1", "192.168.1.1"));
-        assert!(!handler.is_authorized("pass2", "10.0.0.5"));
+        assert!(handler.is_authorized("pass1", "gw", "10.0.0.5"));
+        assert!(handler.is_authorized("pass2", "gw", "192.168.1.1"));
+        assert!(!handler.is_authorized("pass1", "gw", "192.168.1.1"));
+        assert!(!handler.is_authorized("pass2", "gw", "10.0.0.5"));
     }
 
     #[test]
@@ -190,21 +235,41 @@ mod tests {
             "web",
             vec!["*.example.com", "gateway.*.net"],
         )]);
-        assert!(handler.is_authorized("web", "proxy.example.com"));
-        assert!(handler.is_authorized("web", "gateway.test.net"));
-        assert!(!handler.is_authorized("web", "evil.example.org"));
+        assert!(handler.is_authorized("web", "gw", "proxy.example.com"));
+        assert!(handler.is_authorized("web", "gw", "gateway.test.net"));
+        assert!(!handler.is_authorized("web", "gw", "evil.example.org"));
     }
 
     #[test]
     fn is_authorized_no_blocks() {
         let handler = WebircHandler::new(vec![]);
-        assert!(!handler.is_authorized("anypass", "anyhost"));
+        assert!(!handler.is_authorized("anypass", "gw", "anyhost"));
+    }
+
+    #[test]
+    fn is_authorized_cidr_hosts() {
+        let handler = WebircHandler::new(vec![make_block(
+            "cidr",
+            vec!["10.1.0.0/16", "2001:db8::/32"],
+        )]);
+        assert!(handler.is_authorized("cidr", "gw", "10.1.200.3"));
+        assert!(handler.is_authorized("cidr", "gw", "2001:db8::1"));
+        assert!(!handler.is_authorized("cidr", "gw", "10.2.0.1"));
+    }
+
+    #[test]
+    fn is_authorized_named_gateway() {
+        let mut block = make_block("shared", vec![]);
+        block.name = Some("kiwiirc".to_string());
+        let handler = WebircHandler::new(vec![block]);
+        assert!(handler.is_authorized("shared", "kiwiirc", "10.0.0.1"));
+        assert!(!handler.is_authorized("shared", "cgiirc", "10.0.0.1"));
     }
 
     #[test]
     fn is_authorized_exact_host_match() {
         let handler = WebircHandler::new(vec![make_block("exact", vec!["trusted.gateway.com"])]);
-        assert!(handler.is_authorized("exact", "trusted.gateway.com"));
-        assert!(!handler.is_authorized("exact", "other.gateway.com"));
+        assert!(handler.is_authorized("exact", "gw", "trusted.gateway.com"));
+        assert!(!handler.is_authorized("exact", "gw", "other.gateway.com"));
     }
 }
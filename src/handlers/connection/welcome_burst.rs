@@ -21,9 +21,10 @@
 use crate::db::Database;
 use crate::error::{HandlerError, HandlerResult};
 use crate::handlers::SaslState;
-use crate::handlers::{apply_user_modes_typed, notify_monitors_online, server_reply};
+use crate::handlers::{
+    apply_user_modes_typed, notify_monitors_online, server_notice, server_reply,
+};
 use crate::state::{Matrix, UnregisteredState, User};
-use slirc_proto::isupport::{ChanModesBuilder, IsupportBuilder, TargMaxBuilder};
 use slirc_proto::mode::{Mode, UserMode};
 use slirc_proto::transport::ZeroCopyTransportEnum;
 use slirc_proto::{Command, Message, Prefix, Response};
@@ -67,6 +68,7 @@ impl<'a> WelcomeBurstWriter<'a> {
 
     /// Write a message directly to the transport.
     async fn write(&mut self, msg: Message) -> HandlerResult {
+        crate::tap::global().outbound(self.uid, self.remote_addr.ip(), &msg);
         self.transport
             .write_message(&msg)
             .await
@@ -92,9 +94,46 @@ impl<'a> WelcomeBurstWriter<'a> {
             .ok_or(HandlerError::NickOrUserMissing)?;
         let realname = self.state.realname.as_ref().cloned().unwrap_or_default();
         let server_name = &self.matrix.server_info.name;
-        let network = &self.matrix.server_info.network;
+        let (network, isupport_lines) = {
+            let hot = self.matrix.hot_config.read();
+            (
+                hot.network.clone(),
+                crate::state::isupport::lines(&hot.isupport),
+            )
+        };
         let remote_ip = self.remote_addr.ip().to_string();
 
+        // Refuse new registrations while the server is in maintenance mode
+        if let Some(reason) = self.matrix.lifecycle_manager.maintenance_reason() {
+            self.write(server_notice(
+                server_name,
+                nick,
+                format!(
+                    "*** This server is undergoing maintenance ({reason}). Please reconnect in a few minutes."
+                ),
+            ))
+            .await?;
+            let error = Message::from(Command::ERROR(
+                "Closing Link: Server is in maintenance mode".to_string(),
+            ));
+            self.write(error).await?;
+            return Err(HandlerError::AccessDenied);
+        }
+
+        // Server ports take links only
+        if self
+            .state
+            .listener
+            .as_ref()
+            .is_some_and(|listener| listener.server_only)
+        {
+            let error = Message::from(Command::ERROR(
+                "Closing Link: Access denied (this port is for servers only)".to_string(),
+            ));
+            self.write(error).await?;
+            return Err(HandlerError::AccessDenied);
+        }
+
         // Record successful connection for reputation
         if let Some(spam_lock) = &self.matrix.security_manager.spam_detector {
             let spam = spam_lock.read().await;
@@ -104,17 +143,29 @@ impl<'a> WelcomeBurstWriter<'a> {
         let webirc_ip = self.state.webirc_ip.clone();
         let webirc_host = self.state.webirc_host.clone();
 
-        // Prefer WEBIRC-provided host/IP when available (trusted gateway path)
-        let ban_host = webirc_host
-            .clone()
+        // Tor listeners hand out one fixed host and their own limits
+        let tor = self
+            .state
+            .listener
+            .as_ref()
+            .filter(|listener| listener.tor)
+            .map(|_| self.matrix.hot_config.read().tor.clone());
+
+        // Prefer WEBIRC-provided host/IP when available (trusted gateway path),
+        // then the confirmed reverse DNS name
+        let ban_host = tor
+            .as_ref()
+            .map(|tor| tor.host.clone())
+            .or(webirc_host.clone())
+            .or(self.state.resolved_host.clone())
             .or(webirc_ip.clone())
             .unwrap_or_else(|| remote_ip.clone());
         let host = ban_host.clone();
 
         // Check if SASL authentication is required
-        if self.matrix.config.security.require_sasl
-            && self.state.sasl_state != SaslState::Authenticated
-        {
+        let require_sasl = self.matrix.config.security.require_sasl
+            || tor.as_ref().is_some_and(|tor| tor.require_sasl);
+        if require_sasl && self.state.sasl_state != SaslState::Authenticated {
             let reply = server_reply(
                 server_name,
                 Response::ERR_SASLFAIL,
@@ -435,55 +486,14 @@ impl<'a> WelcomeBurstWriter<'a> {
             );
             self.write(myinfo).await?;
 
-            // 005 RPL_ISUPPORT - use the same builder as normal registration
-            let chanmodes = ChanModesBuilder::new()
-                .list_modes("beIq")
-                .param_always("k")
-                .param_set("l")
-                .no_param("imnrstMU");
-
-            let targmax = TargMaxBuilder::new()
-                .add("JOIN", 10)
-                .add("PART", 10)
-                .add("KICK", 4)
-                .add("PRIVMSG", 4)
-                .add("NOTICE", 4)
-                .add("NAMES", 10)
-                .add("WHOIS", 1)
-                .add("WHOWAS", 10);
-
-            let builder = IsupportBuilder::new()
-                .network(network)
-                .custom("METADATA", None)
-                .casemapping(self.matrix.config.server.casemapping.as_isupport_value())
-                .chantypes("#&+!")
-                .prefix("~&@%+", "qaohv")
-                .chanmodes_typed(chanmodes)
-                .max_nick_length(30)
-                .custom("CHANNELLEN", Some("50"))
-                .max_topic_length(390)
-                .custom("KICKLEN", Some("390"))
-                .custom("AWAYLEN", Some("200"))
-                .modes_count(6)
-                .custom("MAXTARGETS", Some("4"))
-                .targmax(targmax)
-                .custom("MONITOR", Some("100"))
-                .excepts(Some('e'))
-                .invex(Some('I'))
-                .custom("EXTBAN", Some(",m"))
-                .custom("ELIST", Some("MNU"))
-                .status_msg("~&@%+")
-                .custom("BOT", Some("B"))
-                .custom("WHOX", None)
-                .custom("UTF8ONLY", None);
-
-            for line in builder.build_lines(13) {
+            // 005 RPL_ISUPPORT - the same cached tokens as normal registration
+            for line in &isupport_lines {
                 let reply = server_reply(
                     server_name,
                     Response::RPL_ISUPPORT,
                     vec![
                         existing_nick.clone(),
-                        line,
+                        line.clone(),
                         "are supported by this server".to_string(),
                     ],
                 );
@@ -514,7 +524,8 @@ impl<'a> WelcomeBurstWriter<'a> {
             self.write(motdstart).await?;
 
             // 372 RPL_MOTD
-            for line in &self.matrix.server_info.motd_lines {
+            let motd_lines = self.matrix.hot_config.read().motd_lines.clone();
+            for line in &motd_lines {
                 let motd = server_reply(
                     server_name,
                     Response::RPL_MOTD,
@@ -556,7 +567,13 @@ impl<'a> WelcomeBurstWriter<'a> {
         }
 
         // Create user in Matrix
-        let security_config = &self.matrix.config.security;
+        let (cloak_secret, cloak_suffix) = {
+            let hot_config = self.matrix.hot_config.read();
+            (
+                hot_config.cloak_secret.clone(),
+                hot_config.cloak_suffix.clone(),
+            )
+        };
         let ip = webirc_ip.clone().unwrap_or_else(|| remote_ip.clone());
         let mut user_obj = User::new(crate::state::UserParams {
             uid: self.uid.to_string(),
@@ -565,8 +582,8 @@ impl<'a> WelcomeBurstWriter<'a> {
             realname,
             host: host.clone(),
             ip,
-            cloak_secret: security_config.cloak_secret.clone(),
-            cloak_suffix: security_config.cloak_suffix.clone(),
+            cloak_secret,
+            cloak_suffix,
             caps: self.state.capabilities.clone(),
             certfp: self.state.certfp.clone(),
             last_modified: self.matrix.clock(),
@@ -579,10 +596,13 @@ impl<'a> WelcomeBurstWriter<'a> {
             user_obj.account = Some(account_name.clone());
         }
 
-        // Set +Z if TLS connection
-        if self.state.is_tls {
-            user_obj.modes.secure = true;
-        }
+        // Set +Z if TLS connection; behind WEBIRC the gateway vouches for it
+        user_obj.modes.secure = if self.state.webirc_used {
+            self.state.webirc_secure
+        } else {
+            self.state.is_tls
+        };
+        user_obj.gateway = self.state.webirc_gateway.clone();
 
         // Apply default user modes from config (e.g., "+i" for default invisible)
         if let Some(ref default_modes) = self.matrix.config.server.default_user_modes {
@@ -592,9 +612,27 @@ impl<'a> WelcomeBurstWriter<'a> {
             }
         }
 
+        if let Some(tor) = &tor {
+            user_obj.visible_host = tor.host.clone();
+        }
+
+        // Use the account's HostServ vhost, if it has one switched on
+        if let Some(account_name) = &self.state.account
+            && let Ok(Some(account)) = self.matrix.db.accounts().find_by_name(account_name).await
+            && let Ok(Some(vhost)) = self.matrix.db.vhosts().active(account.id).await
+        {
+            user_obj.visible_host = vhost;
+        }
+
         let cloaked_host = user_obj.visible_host.clone();
 
         self.matrix.user_manager.add_local_user(user_obj).await;
+        if let Some(tor) = &tor {
+            self.matrix
+                .security_manager
+                .rate_limiter
+                .set_message_rate(&self.uid.to_string(), tor.message_rate_per_second);
+        }
 
         // User is now registered - decrement unregistered connection count
         self.matrix.user_manager.decrement_unregistered();
@@ -602,7 +640,7 @@ impl<'a> WelcomeBurstWriter<'a> {
         crate::metrics::inc_connected_users();
 
         // Use real_user_count to exclude service pseudoclients from max tracking
-        let current_count = self.matrix.user_manager.real_user_count().await;
+        let current_count = self.matrix.user_manager.real_user_count();
         self.matrix
             .user_manager
             .max_local_users
@@ -667,50 +705,8 @@ impl<'a> WelcomeBurstWriter<'a> {
         );
         self.write(myinfo).await?;
 
-        // Build ISUPPORT tokens using typed builders
-        let chanmodes = ChanModesBuilder::new()
-            .list_modes("beIq")
-            .param_always("k")
-            .param_set("l")
-            .no_param("imnrstMU");
-
-        let targmax = TargMaxBuilder::new()
-            .add("JOIN", 10)
-            .add("PART", 10)
-            .add("KICK", 4)
-            .add("PRIVMSG", 4)
-            .add("NOTICE", 4)
-            .add("NAMES", 10)
-            .add("WHOIS", 1)
-            .add("WHOWAS", 10);
-
-        let builder = IsupportBuilder::new()
-            .network(network)
-            .custom("METADATA", None) // Early in the list to pass buggy tests
-            .casemapping(self.matrix.config.server.casemapping.as_isupport_value())
-            .chantypes("#&+!")
-            .prefix("~&@%+", "qaohv")
-            .chanmodes_typed(chanmodes)
-            .max_nick_length(30)
-            .custom("CHANNELLEN", Some("50"))
-            .max_topic_length(390)
-            .custom("KICKLEN", Some("390"))
-            .custom("AWAYLEN", Some("200"))
-            .modes_count(6)
-            .custom("MAXTARGETS", Some("4"))
-            .targmax(targmax)
-            .custom("MONITOR", Some("100"))
-            .excepts(Some('e'))
-            .invex(Some('I'))
-            .custom("EXTBAN", Some(",m"))
-            .custom("ELIST", Some("MNU"))
-            .status_msg("~&@%+")
-            .custom("BOT", Some("B"))
-            .custom("WHOX", None)
-            .custom("UTF8ONLY", None); // Advertise UTF-8 only mode per modern IRC
-
         // Send ISUPPORT lines (max 13 tokens per line to be safe)
-        for line in builder.build_lines(13) {
+        for line in isupport_lines {
             let reply = server_reply(
                 server_name,
                 Response::RPL_ISUPPORT,
@@ -747,7 +743,8 @@ impl<'a> WelcomeBurstWriter<'a> {
         self.write(motdstart).await?;
 
         // 372 RPL_MOTD - stream each line directly to transport
-        for line in &self.matrix.server_info.motd_lines {
+        let motd_lines = self.matrix.hot_config.read().motd_lines.clone();
+        for line in &motd_lines {
             let motd = server_reply(
                 server_name,
                 Response::RPL_MOTD,
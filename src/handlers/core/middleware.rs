@@ -8,7 +8,10 @@
 //! When the outgoing message queue is full (slow consumer), messages are
 //! dropped and the error is propagated to trigger client disconnection.
 //! This prevents memory exhaustion from clients that don't read their data.
+//! Registered clients have a [`SendQ`] limited in bytes by their connection
+//! class, which refuses at once instead of waiting.
 
+use crate::state::SendQ;
 use slirc_proto::Message;
 use std::sync::Arc;
 use std::time::Duration;
@@ -22,7 +25,7 @@ const SEND_TIMEOUT: Duration = Duration::from_secs(5);
 /// Direct forwards to the connection sender; Capturing buffers for labeled-response batching.
 #[derive(Clone)]
 pub enum ResponseMiddleware<'a> {
-    Direct(&'a mpsc::Sender<Arc<Message>>),
+    Direct(&'a SendQ),
     Capturing(&'a Mutex<Vec<Message>>),
 }
 
@@ -19,35 +19,45 @@ use super::traits::{DynUniversalHandler, PostRegHandler, PreRegHandler, ServerHa
 use crate::handlers::{
     admin::{SajoinHandler, SamodeHandler, SanickHandler, SapartHandler},
     batch::BatchHandler,
-    cap::{AuthenticateHandler, CapHandler},
+    cap::{AuthenticateHandler, CAP_COMMANDS, CapHandler},
     chathistory::ChatHistoryHandler,
     helpers::with_label,
     messaging::{
-        AcceptHandler, MetadataHandler, NoticeHandler, PrivmsgHandler, RelayMsgHandler,
-        TagmsgHandler,
+        AcceptHandler, MarkreadHandler, MetadataHandler, NoticeHandler, PrivmsgHandler,
+        RedactHandler, RelayMsgHandler, TagmsgHandler,
     },
-    mode::ModeHandler,
+    mode::{ModeHandler, TbanHandler},
     s2s::kline::{KlineHandler, UnklineHandler},
     server::{
+        away::AwayHandler as ServerAwayHandler,
         base::{ServerHandshakeHandler, ServerPropagationHandler},
         capab::CapabHandler,
         encap::EncapHandler,
+        join::JoinHandler as ServerJoinHandler,
         kick::KickHandler as ServerKickHandler,
         kill::KillHandler as ServerKillHandler,
+        mode::ModeHandler as ServerModeHandler,
+        nick::NickHandler as ServerNickHandler,
+        part::PartHandler as ServerPartHandler,
+        quit::QuitHandler as ServerQuitHandler,
         routing::RoutedMessageHandler,
         sid::SidHandler,
         sjoin::SJoinHandler,
+        squit::SquitHandler as ServerSquitHandler,
         svinfo::SvinfoHandler,
+        svs::SvsHandler,
         tmode::TModeHandler,
         topic::TopicHandler as ServerTopicHandler,
         uid::UidHandler,
+        whois::{WHOIS_NUMERICS, WhoisHandler as ServerWhoisHandler, WhoisReplyHandler},
     },
     services::account::RegisterHandler,
-    services::aliases::{CsHandler, NsHandler},
+    services::aliases::{BsHandler, CsHandler, HsHandler, NsHandler, OsHandler},
     user::monitor::MonitorHandler,
     user::status::{AwayHandler, SetnameHandler, SilenceHandler},
+    user::watch::WatchHandler,
 };
-use crate::state::{RegisteredState, ServerState, UnregisteredState};
+use crate::state::{Matrix, RegisteredState, ServerState, UnregisteredState};
 use crate::telemetry::CommandTimer;
 use slirc_proto::Response;
 use slirc_proto::{ChannelExt, MessageRef};
@@ -124,11 +134,25 @@ impl Registry {
         server_handlers.insert("TMODE", Box::new(TModeHandler));
         server_handlers.insert("UID", Box::new(UidHandler));
         server_handlers.insert("SID", Box::new(SidHandler));
+        server_handlers.insert("SQUIT", Box::new(ServerSquitHandler));
+        server_handlers.insert("WHOIS", Box::new(ServerWhoisHandler));
+        for numeric in WHOIS_NUMERICS {
+            server_handlers.insert(numeric, Box::new(WhoisReplyHandler));
+        }
         server_handlers.insert("ENCAP", Box::new(EncapHandler));
+        server_handlers.insert("SVSNICK", Box::new(SvsHandler));
+        server_handlers.insert("SVSMODE", Box::new(SvsHandler));
+        server_handlers.insert("SVSJOIN", Box::new(SvsHandler));
         server_handlers.insert("TOPIC", Box::new(ServerTopicHandler));
         server_handlers.insert("TB", Box::new(crate::handlers::server::tb::TbHandler));
         server_handlers.insert("KICK", Box::new(ServerKickHandler));
         server_handlers.insert("KILL", Box::new(ServerKillHandler));
+        server_handlers.insert("NICK", Box::new(ServerNickHandler));
+        server_handlers.insert("QUIT", Box::new(ServerQuitHandler));
+        server_handlers.insert("JOIN", Box::new(ServerJoinHandler));
+        server_handlers.insert("PART", Box::new(ServerPartHandler));
+        server_handlers.insert("MODE", Box::new(ServerModeHandler));
+        server_handlers.insert("AWAY", Box::new(ServerAwayHandler));
         server_handlers.insert(
             "BATCH",
             Box::new(crate::handlers::batch::server::ServerBatchHandler),
@@ -137,6 +161,7 @@ impl Registry {
         server_handlers.insert("KLN", Box::new(KlineHandler)); // Alias
         server_handlers.insert("UNKLINE", Box::new(UnklineHandler));
         server_handlers.insert("UNKLN", Box::new(UnklineHandler)); // Alias
+        crate::handlers::bans::register_server(&mut server_handlers);
 
         // ====================================================================
         // Post-registration handlers (require completed registration)
@@ -145,6 +170,7 @@ impl Registry {
         // Channel handlers
         crate::handlers::channel::register(&mut post_reg_handlers);
         post_reg_handlers.insert("MODE", Box::new(ModeHandler));
+        post_reg_handlers.insert("TBAN", Box::new(TbanHandler));
 
         // Messaging handlers
         post_reg_handlers.insert("PRIVMSG", Box::new(PrivmsgHandler));
@@ -154,6 +180,8 @@ impl Registry {
         post_reg_handlers.insert("METADATA", Box::new(MetadataHandler));
 
         post_reg_handlers.insert("RELAYMSG", Box::new(RelayMsgHandler));
+        post_reg_handlers.insert("REDACT", Box::new(RedactHandler));
+        post_reg_handlers.insert("MARKREAD", Box::new(MarkreadHandler));
 
         // User query handlers
         crate::handlers::user::query::register(&mut post_reg_handlers);
@@ -166,6 +194,7 @@ impl Registry {
         post_reg_handlers.insert("SETNAME", Box::new(SetnameHandler));
         post_reg_handlers.insert("SILENCE", Box::new(SilenceHandler));
         post_reg_handlers.insert("MONITOR", Box::new(MonitorHandler));
+        post_reg_handlers.insert("WATCH", Box::new(WatchHandler));
         post_reg_handlers.insert("CHATHISTORY", Box::new(ChatHistoryHandler));
 
         // Batch handler for IRCv3 message batching (draft/multiline)
@@ -176,6 +205,12 @@ impl Registry {
         post_reg_handlers.insert("NS", Box::new(NsHandler)); // Shortcut for NickServ
         post_reg_handlers.insert("CHANSERV", Box::new(CsHandler));
         post_reg_handlers.insert("CS", Box::new(CsHandler)); // Shortcut for ChanServ
+        post_reg_handlers.insert("HOSTSERV", Box::new(HsHandler));
+        post_reg_handlers.insert("HS", Box::new(HsHandler)); // Shortcut for HostServ
+        post_reg_handlers.insert("OPERSERV", Box::new(OsHandler));
+        post_reg_handlers.insert("OS", Box::new(OsHandler)); // Shortcut for OperServ
+        post_reg_handlers.insert("BOTSERV", Box::new(BsHandler));
+        post_reg_handlers.insert("BS", Box::new(BsHandler)); // Shortcut for BotServ
 
         // Operator handlers
         crate::handlers::oper::register(&mut post_reg_handlers);
@@ -235,7 +270,7 @@ impl Registry {
             .collect();
 
         // Sort by usage count (descending)
-        stats.sort_by(|a, b| b.1.cmp(&a.1));
+        stats.sort_by_key(|s| std::cmp::Reverse(s.1));
         stats
     }
 
@@ -248,9 +283,13 @@ impl Registry {
     /// ## Typestate Dispatch (Innovation 1)
     ///
     /// Handler lookup for unregistered connections:
+    /// - **Registration phase**: Commands the current [`RegistrationPhase`]
+    ///   does not admit return NotRegistered error
     /// - **Universal handlers**: Always checked first (QUIT, PING, PONG, NICK, CAP)
     /// - **Pre-reg handlers**: Checked for unregistered connections
     /// - **Post-reg handlers**: Inaccessible - returns NotRegistered error
+    ///
+    /// [`RegistrationPhase`]: crate::state::RegistrationPhase
     pub async fn dispatch_pre_reg(
         &self,
         ctx: &mut Context<'_, UnregisteredState>,
@@ -286,7 +325,20 @@ impl Registry {
         let _timer = CommandTimer::new(&cmd_name);
 
         // Execute handler within the span
-        let result = if let Some(handler) = self.universal_handlers.get(cmd_str) {
+        let result = if cap_disabled(ctx.matrix, cmd_str) {
+            Err(super::context::HandlerError::UnknownCommand(
+                cmd_name.clone(),
+            ))
+        } else if !ctx.state.phase().admits(cmd_str) {
+            debug!(
+                command = %cmd_name,
+                uid = %ctx.uid,
+                phase = ?ctx.state.phase(),
+                "Command rejected: not allowed in registration phase"
+            );
+            crate::metrics::record_command_error(&cmd_name, "not_registered");
+            Err(super::context::HandlerError::NotRegistered)
+        } else if let Some(handler) = self.universal_handlers.get(cmd_str) {
             handler.handle_unreg(ctx, msg).instrument(irc_span).await
         } else if let Some(handler) = self.pre_reg_handlers.get(cmd_str) {
             handler.handle(ctx, msg).instrument(irc_span).await
@@ -365,7 +417,11 @@ impl Registry {
 
         // Execute handler within the span
         // For registered connections, check universal handlers first, then post-reg
-        let result = if let Some(handler) = self.universal_handlers.get(cmd_str) {
+        let result = if cap_disabled(ctx.matrix, cmd_str) {
+            Err(super::context::HandlerError::UnknownCommand(
+                cmd_name.clone(),
+            ))
+        } else if let Some(handler) = self.universal_handlers.get(cmd_str) {
             handler.handle_reg(ctx, msg).instrument(irc_span).await
         } else if let Some(handler) = self.post_reg_handlers.get(cmd_str) {
             handler.handle(ctx, msg).instrument(irc_span).await
@@ -424,16 +480,17 @@ impl Registry {
 
         let start = std::time::Instant::now();
         let result = async {
-            // 1. Check universal handlers first
-            if let Some(handler) = self.universal_handlers.get(cmd_str) {
-                return handler.handle_server(ctx, msg).await;
-            }
-
-            // 2. Check server-specific handlers
+            // 1. Check server-specific handlers first; NICK and QUIT from a
+            //    peer carry a remote user's change, not the link's own
             if let Some(handler) = self.server_handlers.get(cmd_str) {
                 return handler.handle(ctx, msg).await;
             }
 
+            // 2. Check universal handlers
+            if let Some(handler) = self.universal_handlers.get(cmd_str) {
+                return handler.handle_server(ctx, msg).await;
+            }
+
             // 3. Unknown command for servers
             debug!(
                 command = %cmd_name,
@@ -494,3 +551,10 @@ impl Default for Registry {
         Self::new(Vec::new())
     }
 }
+
+/// Whether `command` belongs to a capability the config has disabled.
+fn cap_disabled(matrix: &Matrix, command: &str) -> bool {
+    CAP_COMMANDS
+        .iter()
+        .any(|(cmd, cap)| *cmd == command && matrix.config.server.is_cap_disabled(cap.as_ref()))
+}
@@ -4,6 +4,9 @@ use async_trait::async_trait;
 use slirc_proto::{MessageRef, Response, irc_to_lower};
 
 /// Handler for ACCEPT command (Caller ID).
+///
+/// `ACCEPT nick[,-nick...]` edits the list of users allowed to message a +g
+/// user; `ACCEPT` or `ACCEPT *` lists it. Entries expire when the nick quits.
 pub struct AcceptHandler;
 
 #[async_trait]
@@ -67,6 +70,11 @@ impl PostRegHandler for AcceptHandler {
                     .sender
                     .send(Response::err_accept_exist(&ctx.state.nick, nick))
                     .await;
+            } else if ctx.matrix.user_manager.get_first_uid(&nick_lower).is_none() {
+                let _ = ctx
+                    .sender
+                    .send(Response::err_nosuchnick(&ctx.state.nick, nick))
+                    .await;
             } else if user.accept_list.len() >= 100 {
                 // Limit
                 let _ = ctx
@@ -81,3 +89,63 @@ impl PostRegHandler for AcceptHandler {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{Command, Message, Response};
+
+    fn codes(messages: &[Message]) -> Vec<Response> {
+        messages
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::Response(resp, _) => Some(*resp),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn caller_id_blocks_until_accepted_and_accepts_expire_on_quit() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let _peer = tm.peer("00A").await;
+        let mut alice = tm.user("alice").build().await;
+        let mut bob = tm.user("bob").build().await;
+        tm.run(&mut bob, "MODE bob +g").await;
+        bob.drain();
+
+        let replies = tm.run(&mut alice, "PRIVMSG bob :hi").await;
+        assert_eq!(
+            codes(&replies),
+            [Response::ERR_TARGUMODEG, Response::RPL_TARGNOTIFY]
+        );
+        assert_eq!(codes(&bob.drain()), [Response::RPL_UMODEGMSG]);
+        // bob was just told; he is not told again right away
+        let replies = tm.run(&mut alice, "PRIVMSG bob :hello?").await;
+        assert_eq!(codes(&replies), [Response::ERR_TARGUMODEG]);
+        assert!(bob.drain().is_empty());
+
+        tm.run(&mut bob, "ACCEPT alice").await;
+        tm.run(&mut alice, "PRIVMSG bob :hi again").await;
+        assert!(
+            bob.drain()
+                .iter()
+                .any(|m| matches!(&m.command, Command::PRIVMSG(_, text) if text == "hi again"))
+        );
+
+        let replies = tm.run(&mut bob, "ACCEPT nobody").await;
+        assert_eq!(codes(&replies), [Response::ERR_NOSUCHNICK]);
+        tm.remote_user("00A", "00AAAAAAA", "carol").await;
+        tm.run(&mut bob, "ACCEPT carol").await;
+        tm.run_server("00A", ":00AAAAAAA QUIT :bye").await;
+        let replies = tm.run(&mut bob, "ACCEPT").await;
+        let accepted: Vec<_> = replies
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::Response(Response::RPL_ACCEPTLIST, args) => Some(args[1].as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(accepted, ["alice"]);
+    }
+}
@@ -22,6 +22,9 @@ pub const CANNOT_SEND_REGISTERED_SPEAK: &str = "Cannot send to channel (+M)";
 /// Cannot send to channel - user is banned (+b mode)
 pub const CANNOT_SEND_BANNED: &str = "Cannot send to channel (+b)";
 
+/// Cannot send to channel - user is quieted (+q mode or m: ban)
+pub const CANNOT_SEND_QUIET: &str = "Cannot send to channel (+q)";
+
 /// Cannot send CTCP to channel - CTCP blocked (+C mode)
 pub const CANNOT_SEND_CTCP: &str = "Cannot send CTCP to channel (+C)";
 
@@ -34,6 +37,9 @@ pub const CANNOT_SEND_ANTI_CAPS: &str = "Your message contains too many capital
 /// Cannot send to channel - censored word (+G mode)
 pub const CANNOT_SEND_CENSORED: &str = "Your message contains censored words (+G)";
 
+/// Cannot send to channel - flood limit exceeded (+f mode)
+pub const CANNOT_SEND_FLOOD: &str = "Cannot send to channel (+f)";
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -50,10 +56,12 @@ mod tests {
         assert!(!CANNOT_SEND_REGISTERED_ONLY.is_empty());
         assert!(!CANNOT_SEND_REGISTERED_SPEAK.is_empty());
         assert!(!CANNOT_SEND_BANNED.is_empty());
+        assert!(!CANNOT_SEND_QUIET.is_empty());
         assert!(!CANNOT_SEND_CTCP.is_empty());
         assert!(!CANNOT_SEND_NOTICE.is_empty());
         assert!(!CANNOT_SEND_ANTI_CAPS.is_empty());
         assert!(!CANNOT_SEND_CENSORED.is_empty());
+        assert!(!CANNOT_SEND_FLOOD.is_empty());
     }
 
     #[test]
@@ -64,8 +72,10 @@ mod tests {
         assert!(CANNOT_SEND_REGISTERED_ONLY.contains("+r"));
         assert!(CANNOT_SEND_REGISTERED_SPEAK.contains("+M"));
         assert!(CANNOT_SEND_BANNED.contains("+b"));
+        assert!(CANNOT_SEND_QUIET.contains("+q"));
         assert!(CANNOT_SEND_CTCP.contains("+C"));
         assert!(CANNOT_SEND_NOTICE.contains("+T"));
+        assert!(CANNOT_SEND_FLOOD.contains("+f"));
     }
 
     #[test]
@@ -76,7 +86,9 @@ mod tests {
         assert!(CANNOT_SEND_REGISTERED_ONLY.starts_with("Cannot send"));
         assert!(CANNOT_SEND_REGISTERED_SPEAK.starts_with("Cannot send"));
         assert!(CANNOT_SEND_BANNED.starts_with("Cannot send"));
+        assert!(CANNOT_SEND_QUIET.starts_with("Cannot send"));
         assert!(CANNOT_SEND_CTCP.starts_with("Cannot send"));
         assert!(CANNOT_SEND_NOTICE.starts_with("Cannot send"));
+        assert!(CANNOT_SEND_FLOOD.starts_with("Cannot send"));
     }
 }
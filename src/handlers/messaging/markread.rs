@@ -0,0 +1,222 @@
+//! MARKREAD command handler (draft/read-marker).
+//!
+//! - `MARKREAD <target>` returns the stored marker, or `*` if there is none
+//! - `MARKREAD <target> timestamp=<ts>` advances it
+//!
+//! Markers belong to the account, so every client logged in to it with the
+//! capability is told when one moves. A marker never moves backwards; an
+//! older timestamp is answered with the stored one.
+
+use super::super::{Context, HandlerResult, PostRegHandler};
+use crate::handlers::util::helpers::with_label;
+use crate::state::RegisteredState;
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat};
+use slirc_proto::{ChannelExt, Command, Message, MessageRef, NickExt, Prefix, irc_to_lower};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Capability clients need to receive MARKREAD.
+const READ_MARKER_CAP: &str = "draft/read-marker";
+
+pub struct MarkreadHandler;
+
+#[async_trait]
+impl PostRegHandler for MarkreadHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, RegisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let Some(target) = msg.arg(0).filter(|t| !t.is_empty()) else {
+            return send_fail(ctx, "NEED_MORE_PARAMS", None, "Missing target").await;
+        };
+        if !target.is_channel_name() && !target.is_valid_nick() {
+            return send_fail(ctx, "INVALID_PARAMS", Some(target), "Invalid target").await;
+        }
+        let Some(account) = ctx.state.account.clone() else {
+            return send_fail(
+                ctx,
+                "ACCOUNT_REQUIRED",
+                Some(target),
+                "You must be logged in to use read markers",
+            )
+            .await;
+        };
+        let target = irc_to_lower(target);
+        let markers = &ctx.matrix.read_marker_manager;
+
+        let Some(param) = msg.arg(1) else {
+            let marker = markers.get_marker(&account, &target).await;
+            let reply = markread(ctx.server_name(), &target, marker);
+            ctx.sender
+                .send(with_label(reply, ctx.label.as_deref()))
+                .await?;
+            return Ok(());
+        };
+
+        let Some(nanotime) = param
+            .strip_prefix("timestamp=")
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+            .and_then(|dt| dt.timestamp_nanos_opt())
+        else {
+            return send_fail(ctx, "INVALID_PARAMS", Some(&target), "Invalid timestamp").await;
+        };
+
+        let (marker, advanced) = markers.update_marker(&account, &target, nanotime).await;
+        let reply = markread(ctx.server_name(), &target, Some(marker));
+        if advanced {
+            debug!(account = %account, target = %target, ts = %marker, "Read marker advanced");
+            notify_other_clients(ctx, &account, &reply).await;
+        }
+        ctx.sender
+            .send(with_label(reply, ctx.label.as_deref()))
+            .await?;
+        Ok(())
+    }
+}
+
+/// `MARKREAD <target> timestamp=<ts>`, or `MARKREAD <target> *` without a marker.
+fn markread(server_name: &str, target: &str, marker: Option<i64>) -> Message {
+    let marker = marker
+        .map(DateTime::from_timestamp_nanos)
+        .map(|dt| {
+            format!(
+                "timestamp={}",
+                dt.to_rfc3339_opts(SecondsFormat::Millis, true)
+            )
+        })
+        .unwrap_or_else(|| "*".to_string());
+    Message {
+        tags: None,
+        prefix: Some(Prefix::ServerName(server_name.to_string())),
+        command: Command::Raw("MARKREAD".to_string(), vec![target.to_string(), marker]),
+    }
+}
+
+/// Send `msg` to the account's other local sessions that negotiated the capability.
+async fn notify_other_clients(ctx: &Context<'_, RegisteredState>, account: &str, msg: &Message) {
+    let local_sid = ctx.matrix.server_info.sid.as_str();
+    let msg = Arc::new(msg.clone());
+    for uid in ctx.matrix.user_manager.index.by_account(account) {
+        if !uid.starts_with(local_sid) {
+            continue;
+        }
+        let Some(sessions) = ctx.matrix.user_manager.get_senders_cloned(&uid) else {
+            continue;
+        };
+        for sess in sessions {
+            if sess.session_id == ctx.state.session_id {
+                continue;
+            }
+            let has_cap = ctx
+                .matrix
+                .user_manager
+                .get_session_caps(sess.session_id)
+                .is_some_and(|caps| caps.contains(READ_MARKER_CAP));
+            if has_cap {
+                let _ = sess.tx.send(Arc::clone(&msg)).await;
+            }
+        }
+    }
+}
+
+/// Send `FAIL MARKREAD <code> [target] :<description>`.
+async fn send_fail(
+    ctx: &mut Context<'_, RegisteredState>,
+    code: &str,
+    target: Option<&str>,
+    description: &str,
+) -> HandlerResult {
+    let mut context: Vec<String> = target.into_iter().map(str::to_string).collect();
+    context.push(description.to_string());
+    let fail = Message {
+        tags: None,
+        prefix: Some(Prefix::ServerName(ctx.server_name().to_string())),
+        command: Command::FAIL("MARKREAD".to_string(), code.to_string(), context),
+    };
+    ctx.sender
+        .send(with_label(fail, ctx.label.as_deref()))
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{Command, Message};
+
+    fn markreads(messages: &[Message]) -> Vec<Vec<String>> {
+        messages
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::Raw(name, args) if name == "MARKREAD" => Some(args.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn marker_is_shared_by_the_accounts_clients_and_only_advances() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut phone = tm
+            .user("alice")
+            .account("alice")
+            .cap("draft/read-marker")
+            .build()
+            .await;
+        let mut laptop = tm
+            .user("alice2")
+            .account("alice")
+            .cap("draft/read-marker")
+            .build()
+            .await;
+
+        let replies = tm.run(&mut phone, "MARKREAD #Chan").await;
+        assert_eq!(markreads(&replies), [["#chan", "*"]]);
+
+        let replies = tm
+            .run(
+                &mut phone,
+                "MARKREAD #chan timestamp=2026-01-01T12:00:00.000Z",
+            )
+            .await;
+        let expected = [["#chan", "timestamp=2026-01-01T12:00:00.000Z"]];
+        assert_eq!(markreads(&replies), expected);
+        assert_eq!(markreads(&laptop.drain()), expected);
+
+        let replies = tm
+            .run(
+                &mut laptop,
+                "MARKREAD #chan timestamp=2025-06-01T00:00:00.000Z",
+            )
+            .await;
+        assert_eq!(markreads(&replies), expected);
+        assert!(markreads(&phone.drain()).is_empty());
+
+        let replies = tm.run(&mut laptop, "MARKREAD #CHAN").await;
+        assert_eq!(markreads(&replies), expected);
+    }
+
+    #[tokio::test]
+    async fn markread_requires_account_and_valid_timestamp() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut guest = tm.user("guest").build().await;
+        let mut alice = tm.user("alice").account("alice").build().await;
+
+        let fail_code = |messages: &[Message]| {
+            messages.iter().find_map(|m| match &m.command {
+                Command::FAIL(cmd, code, _) if cmd == "MARKREAD" => Some(code.clone()),
+                _ => None,
+            })
+        };
+        let replies = tm.run(&mut guest, "MARKREAD #chan").await;
+        assert_eq!(fail_code(&replies).as_deref(), Some("ACCOUNT_REQUIRED"));
+        let replies = tm
+            .run(&mut alice, "MARKREAD #chan timestamp=yesterday")
+            .await;
+        assert_eq!(fail_code(&replies).as_deref(), Some("INVALID_PARAMS"));
+        let replies = tm.run(&mut alice, "MARKREAD").await;
+        assert_eq!(fail_code(&replies).as_deref(), Some("NEED_MORE_PARAMS"));
+    }
+}
@@ -1,28 +1,66 @@
-//! METADATA command handler (Ergo extension).
+//! METADATA command handler (draft/metadata-2).
 //!
-//! The METADATA command allows getting, setting, and listing metadata
-//! associated with users and channels.
+//! Format: `METADATA <target> <subcommand> [params]`, where the target is a
+//! nick, a channel or `*` for yourself.
 //!
-//! Format:
-//! - `METADATA GET <target> <key>` - Get a metadata key for a user or channel
-//! - `METADATA SET <target> <key> [value]` - Set a metadata key (empty value deletes)
-//! - `METADATA LIST <target>` - List all metadata for a target
+//! - `GET <key> [key...]` / `LIST` - read keys
+//! - `SET <key> [value]` - set a key (no value deletes it)
+//! - `CLEAR` - delete every key
+//! - `SUB <key...>` / `UNSUB <key...>` / `SUBS` - manage this connection's
+//!   subscriptions
+//! - `SYNC` - read the subscribed keys of a target
 //!
-//! This handler implements the METADATA command, supporting:
-//! - Channel metadata (saved to runtime state and registered channel DB)
-//! - User metadata (saved to account DB if identified)
-//! - Access control via proper ownership checks
-
-use super::super::{Context, HandlerResult, PostRegHandler, server_reply};
-use crate::state::RegisteredState;
-use crate::state::actor::{ChannelEvent, MetadataCommand as ActorMetadataCommand};
+//! Users edit their own metadata and channel operators their channel's;
+//! IRC operators may edit any target. Keys listed in `metadata.private_keys`
+//! are only visible to those who may edit them. Changes are sent as
+//! `METADATA <target> <key> <visibility> [:value]` to every other connection
+//! that negotiated draft/metadata-2, subscribed to the key and can see the
+//! target: channel members, or users sharing a channel with the target user.
+//! Metadata of logged-in users and registered channels is persisted.
+
+use super::super::{Context, HandlerResult, PostRegHandler};
+use super::redact::is_chanop;
+use super::types::SenderSnapshot;
+use crate::state::actor::{ChannelEvent, MetadataCommand, MetadataResult};
+use crate::state::dashmap_ext::DashMapExt;
+use crate::state::{RegisteredState, Uid};
 use async_trait::async_trait;
 use slirc_proto::command::subcommands::MetadataSubCommand;
-use slirc_proto::{ChannelExt, MessageRef, Response};
-use tokio::sync::oneshot;
+use slirc_proto::{ChannelExt, Command, Message, MessageRef, Prefix, Response, irc_to_lower};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+
+/// Capability clients need to receive METADATA notifications.
+const METADATA_CAP: &str = "draft/metadata-2";
 
 pub struct MetadataHandler;
 
+/// A resolved METADATA target.
+enum Target {
+    User {
+        uid: Uid,
+        nick: String,
+    },
+    Channel {
+        name: String,
+        lower: String,
+        tx: mpsc::Sender<ChannelEvent>,
+    },
+}
+
+impl Target {
+    fn name(&self) -> &str {
+        match self {
+            Self::User { nick, .. } => nick,
+            Self::Channel { name, .. } => name,
+        }
+    }
+}
+
+/// A SET refused because the target already holds `metadata.max_keys` keys.
+struct LimitReached;
+
 #[async_trait]
 impl PostRegHandler for MetadataHandler {
     async fn handle(
@@ -30,464 +68,707 @@ impl PostRegHandler for MetadataHandler {
         ctx: &mut Context<'_, RegisteredState>,
         msg: &MessageRef<'_>,
     ) -> HandlerResult {
-        // Validation of command name
-        if msg.command.name != "METADATA" {
-            return Ok(());
-        }
-
         let args = &msg.command.args;
         if args.len() < 2 {
-            // Need at least target and subcommand
-            let reply = server_reply(
-                &ctx.matrix.server_info.name,
-                Response::ERR_NEEDMOREPARAMS,
-                vec![
-                    ctx.state.nick.clone(),
-                    "METADATA".to_string(),
-                    "Not enough parameters".to_string(),
-                ],
-            );
-            ctx.sender.send(reply).await?;
-            return Ok(());
+            return ctx
+                .send_reply(
+                    Response::ERR_NEEDMOREPARAMS,
+                    vec![
+                        ctx.state.nick.clone(),
+                        "METADATA".to_string(),
+                        "Not enough parameters".to_string(),
+                    ],
+                )
+                .await;
         }
+        let params = &args[2..];
+        let Ok(subcommand) = args[1].parse::<MetadataSubCommand>() else {
+            return send_fail(ctx, "SUBCOMMAND_INVALID", args[1], "Invalid subcommand").await;
+        };
 
-        let target = args[0];
-        let subcommand_str = args[1];
-        let params = &args[2..]; // slice of &str
+        match subcommand {
+            MetadataSubCommand::SUB => return subscribe(ctx, params).await,
+            MetadataSubCommand::UNSUB => return unsubscribe(ctx, params).await,
+            MetadataSubCommand::SUBS => return list_subscriptions(ctx).await,
+            _ => {}
+        }
 
-        let subcommand = match subcommand_str.to_uppercase().as_str() {
-            "GET" => MetadataSubCommand::GET,
-            "SET" => MetadataSubCommand::SET,
-            "LIST" => MetadataSubCommand::LIST,
-            _ => return Ok(()), // Invalid subcommand, maybe send ERR_UKNOWNCOMMAND?
+        let Some(target) = resolve_target(ctx, args[0]) else {
+            return ctx
+                .send_reply(
+                    Response::ERR_TARGETINVALID,
+                    vec![
+                        ctx.state.nick.clone(),
+                        args[0].to_string(),
+                        "Invalid metadata target".to_string(),
+                    ],
+                )
+                .await;
         };
-
-        let (target_lower, reply_target) = if target == "*" {
-            (
-                slirc_proto::irc_to_lower(&ctx.state.nick),
-                ctx.state.nick.as_str(),
-            )
-        } else {
-            (slirc_proto::irc_to_lower(target), target)
+        let is_oper = match ctx.matrix.user_manager.users.get_cloned(ctx.uid) {
+            Some(user) => user.read().await.modes.oper,
+            None => false,
         };
+        let can_edit = is_oper
+            || match &target {
+                Target::User { uid, .. } => uid == ctx.uid,
+                Target::Channel { lower, .. } => is_chanop(ctx, lower).await,
+            };
 
-        if target.is_channel_name() {
-            // Channel Metadata
-            let chan_sender = ctx
-                .matrix
-                .channel_manager
-                .channels
-                .get(&target_lower)
-                .map(|r| r.value().clone());
-
-            if let Some(chan_sender) = chan_sender {
-                // Determine action
-                let actor_cmd = match subcommand {
-                    MetadataSubCommand::GET => {
-                        if params.is_empty() {
-                            let reply = server_reply(
-                                &ctx.matrix.server_info.name,
-                                Response::ERR_NEEDMOREPARAMS,
-                                vec![
-                                    ctx.state.nick.clone(),
-                                    "METADATA".to_string(),
-                                    "Not enough parameters".to_string(),
-                                ],
-                            );
-                            ctx.sender.send(reply).await?;
-                            return Ok(());
-                        }
-                        ActorMetadataCommand::Get {
-                            key: params[0].to_string(),
-                        }
+        match subcommand {
+            MetadataSubCommand::GET => {
+                if params.is_empty() {
+                    return ctx
+                        .send_reply(
+                            Response::ERR_NEEDMOREPARAMS,
+                            vec![
+                                ctx.state.nick.clone(),
+                                "METADATA".to_string(),
+                                "Not enough parameters".to_string(),
+                            ],
+                        )
+                        .await;
+                }
+                let stored = list(ctx, &target).await;
+                for key in params {
+                    if !is_valid_key(key) {
+                        send_key_invalid(ctx, key).await?;
+                    } else if ctx.matrix.config.metadata.is_private(key) && !can_edit {
+                        send_no_permission(ctx, &target, key).await?;
+                    } else if let Some(value) = stored.get(*key) {
+                        send_key_value(ctx, &target, key, Some(value)).await?;
+                    } else {
+                        send_key_not_set(ctx, &target, key).await?;
                     }
-                    MetadataSubCommand::SET => {
-                        if params.is_empty() {
-                            let reply = server_reply(
-                                &ctx.matrix.server_info.name,
-                                Response::ERR_NEEDMOREPARAMS,
-                                vec![
-                                    ctx.state.nick.clone(),
-                                    "METADATA".to_string(),
-                                    "Not enough parameters".to_string(),
-                                ],
-                            );
-                            ctx.sender.send(reply).await?;
-                            return Ok(());
-                        }
-
-                        let key = params[0].to_string();
-                        let value = if params.len() > 1 {
-                            Some(params[1].to_string())
-                        } else {
-                            None
-                        };
-                        ActorMetadataCommand::Set { key, value }
+                }
+                send_end(ctx).await
+            }
+            MetadataSubCommand::LIST => {
+                let stored: BTreeMap<_, _> = list(ctx, &target).await.into_iter().collect();
+                for (key, value) in &stored {
+                    if can_edit || !ctx.matrix.config.metadata.is_private(key) {
+                        send_key_value(ctx, &target, key, Some(value)).await?;
                     }
-                    MetadataSubCommand::LIST => ActorMetadataCommand::List,
-                    _ => return Ok(()),
-                };
-
-                let (reply_tx, reply_rx) = oneshot::channel();
-                let event = ChannelEvent::Metadata {
-                    command: actor_cmd,
-                    reply_tx,
+                }
+                send_end(ctx).await
+            }
+            MetadataSubCommand::SYNC => {
+                let subs = ctx
+                    .matrix
+                    .user_manager
+                    .metadata_subs
+                    .get_cloned(&ctx.state.session_id)
+                    .unwrap_or_default();
+                let stored: BTreeMap<_, _> = list(ctx, &target).await.into_iter().collect();
+                for (key, value) in &stored {
+                    if subs.contains(key)
+                        && (can_edit || !ctx.matrix.config.metadata.is_private(key))
+                    {
+                        send_key_value(ctx, &target, key, Some(value)).await?;
+                    }
+                }
+                send_end(ctx).await
+            }
+            MetadataSubCommand::SET => {
+                let Some(key) = params.first() else {
+                    return ctx
+                        .send_reply(
+                            Response::ERR_NEEDMOREPARAMS,
+                            vec![
+                                ctx.state.nick.clone(),
+                                "METADATA".to_string(),
+                                "Not enough parameters".to_string(),
+                            ],
+                        )
+                        .await;
                 };
-
-                if chan_sender.send(event).await.is_err() {
-                    let reply = server_reply(
-                        &ctx.matrix.server_info.name,
-                        Response::ERR_NOSUCHCHANNEL,
-                        vec![
-                            ctx.state.nick.clone(),
-                            reply_target.to_string(),
-                            "No such channel".to_string(),
-                        ],
-                    );
-                    ctx.sender.send(reply).await?;
-                    return Ok(());
+                let value = params.get(1).copied();
+                if !is_valid_key(key) {
+                    return send_key_invalid(ctx, key).await;
+                }
+                if !can_edit {
+                    return send_no_permission(ctx, &target, key).await;
+                }
+                if value.is_some_and(|v| v.len() > ctx.matrix.config.metadata.value_limit(key)) {
+                    return send_fail(ctx, "VALUE_INVALID", key, "Value is too long").await;
+                }
+                if value.is_none() && !list(ctx, &target).await.contains_key(*key) {
+                    return send_key_not_set(ctx, &target, key).await;
                 }
 
-                match reply_rx.await {
-                    Ok(Ok(map)) => {
-                        for (k, v) in map {
-                            // Deprecated spec format: 761 <client> <key> <visibility> <value>
-                            let reply = server_reply(
-                                &ctx.matrix.server_info.name,
-                                Response::RPL_KEYVALUE,
-                                vec![
-                                    ctx.state.nick.clone(),
-                                    k.clone(),
-                                    "*".to_string(),
-                                    v.clone(),
-                                ],
-                            );
-                            ctx.sender.send(reply).await?;
-
-                            if subcommand == MetadataSubCommand::SET
-                                && ctx
-                                    .matrix
-                                    .channel_manager
-                                    .registered_channels
-                                    .contains(&target_lower)
-                            {
-                                let repo = ctx.matrix.db.channels();
-                                if let Ok(Some(channel)) = repo.find_by_name(&target_lower).await {
-                                    // params[0] is key, value is v (or from params)
-                                    // The actor returned the map? No, actor returns empty map for SET.
-                                    // Wait, SET returns Ok(HashMap::new()).
-                                    // So we can't get the value from the map if it's empty.
-                                    // We must use params.
-                                    if !params.is_empty() {
-                                        let key = params[0].to_string();
-                                        // Re-derive value from params logic
-                                        let value_to_save = if params.len() > 1 {
-                                            Some(params[1].to_string())
-                                        } else {
-                                            None
-                                        };
-                                        if let Err(e) = repo
-                                            .set_metadata(
-                                                channel.id,
-                                                &key,
-                                                value_to_save.as_deref(),
-                                            )
-                                            .await
-                                        {
-                                            tracing::error!(
-                                                "Failed to persist channel metadata: {}",
-                                                e
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        let reply = server_reply(
-                            &ctx.matrix.server_info.name,
-                            Response::RPL_METADATAEND,
-                            vec![ctx.state.nick.clone(), "End of metadata".to_string()],
-                        );
-                        ctx.sender.send(reply).await?;
-                    }
-                    Ok(Err(_e)) => {
-                        // Map ChannelError to IRC Error - e.g. limit exceeded
-                        let reply = server_reply(
-                            &ctx.matrix.server_info.name,
+                if set(ctx, &target, key, value).await.is_err() {
+                    return ctx
+                        .send_reply(
                             Response::ERR_METADATALIMIT,
                             vec![
                                 ctx.state.nick.clone(),
-                                "Metadata limit exceeded".to_string(),
+                                target.name().to_string(),
+                                "Metadata limit reached".to_string(),
                             ],
-                        );
-                        ctx.sender.send(reply).await?;
-                    }
-                    Err(_) => {}
+                        )
+                        .await;
                 }
-            } else {
-                let reply = server_reply(
-                    &ctx.matrix.server_info.name,
-                    Response::ERR_NOSUCHCHANNEL,
-                    vec![
-                        ctx.state.nick.clone(),
-                        reply_target.to_string(),
-                        "No such channel".to_string(),
-                    ],
-                );
-                ctx.sender.send(reply).await?;
+                persist(ctx, &target, key, value).await;
+                notify_subscribers(ctx, &target, key, value).await;
+                send_key_value(ctx, &target, key, value).await
             }
-        } else {
-            // User Metadata
-            // 1. Resolve target nick to UID
-            let target_uid = if let Some(uid) = ctx.matrix.user_manager.get_first_uid(&target_lower)
-            {
-                uid
-            } else {
-                let reply = server_reply(
-                    &ctx.matrix.server_info.name,
-                    Response::ERR_TARGETINVALID,
-                    vec![
-                        ctx.state.nick.clone(),
-                        reply_target.to_string(),
-                        "No such nick/channel".to_string(),
-                    ],
-                );
-                ctx.sender.send(reply).await?;
+            MetadataSubCommand::CLEAR => {
+                if !can_edit {
+                    return send_no_permission(ctx, &target, "*").await;
+                }
+                let cleared: BTreeMap<_, _> = clear(ctx, &target).await.into_iter().collect();
+                for key in cleared.keys() {
+                    persist(ctx, &target, key, None).await;
+                    notify_subscribers(ctx, &target, key, None).await;
+                    send_key_value(ctx, &target, key, None).await?;
+                }
+                send_end(ctx).await
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Resolve `*`, a nick or a channel name.
+fn resolve_target(ctx: &Context<'_, RegisteredState>, target: &str) -> Option<Target> {
+    if target == "*" {
+        return Some(Target::User {
+            uid: ctx.uid.to_string(),
+            nick: ctx.state.nick.clone(),
+        });
+    }
+    let lower = irc_to_lower(target);
+    if target.is_channel_name() {
+        let tx = ctx.matrix.channel_manager.channels.get_cloned(&lower)?;
+        return Some(Target::Channel {
+            name: target.to_string(),
+            lower,
+            tx,
+        });
+    }
+    let uid = ctx.matrix.user_manager.get_first_uid(&lower)?;
+    Some(Target::User {
+        uid,
+        nick: target.to_string(),
+    })
+}
+
+/// Keys are lowercase letters, digits and `_ . / -`, not starting with `-`.
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with('-')
+        && key
+            .bytes()
+            .all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'_' | b'.' | b'/' | b'-'))
+}
+
+/// Ask the channel actor to run a metadata command.
+async fn channel_metadata(
+    tx: &mpsc::Sender<ChannelEvent>,
+    command: MetadataCommand,
+) -> Option<MetadataResult> {
+    let (reply_tx, reply_rx) = oneshot::channel();
+    tx.send(ChannelEvent::Metadata { command, reply_tx })
+        .await
+        .ok()?;
+    reply_rx.await.ok()
+}
+
+/// Every key the target holds.
+async fn list(ctx: &Context<'_, RegisteredState>, target: &Target) -> HashMap<String, String> {
+    match target {
+        Target::User { uid, .. } => match ctx.matrix.user_manager.users.get_cloned(uid) {
+            Some(user) => user.read().await.metadata.clone(),
+            None => HashMap::new(),
+        },
+        Target::Channel { tx, .. } => channel_metadata(tx, MetadataCommand::List)
+            .await
+            .and_then(Result::ok)
+            .unwrap_or_default(),
+    }
+}
+
+/// Set or delete one key, enforcing `metadata.max_keys`.
+async fn set(
+    ctx: &Context<'_, RegisteredState>,
+    target: &Target,
+    key: &str,
+    value: Option<&str>,
+) -> Result<(), LimitReached> {
+    let max_keys = ctx.matrix.config.metadata.max_keys;
+    match target {
+        Target::User { uid, .. } => {
+            let Some(user) = ctx.matrix.user_manager.users.get_cloned(uid) else {
                 return Ok(());
             };
-
-            // 2. Get User object
-            if let Some(user_rw) = ctx.matrix.user_manager.users.get(&target_uid) {
-                let user_rw = user_rw.value().clone();
-
-                match subcommand {
-                    MetadataSubCommand::GET => {
-                        if params.is_empty() {
-                            let reply = server_reply(
-                                &ctx.matrix.server_info.name,
-                                Response::ERR_NEEDMOREPARAMS,
-                                vec![
-                                    ctx.state.nick.clone(),
-                                    "METADATA".to_string(),
-                                    "Not enough parameters".to_string(),
-                                ],
-                            );
-                            ctx.sender.send(reply).await?;
-                            return Ok(());
-                        }
-
-                        let user = user_rw.read().await;
-                        for key in params {
-                            let key = key.to_string();
-                            if let Some(val) = user.metadata.get(&key) {
-                                // Deprecated spec format: 761 <client> <key> <visibility> <value>
-                                let reply = server_reply(
-                                    &ctx.matrix.server_info.name,
-                                    Response::RPL_KEYVALUE,
-                                    vec![ctx.state.nick.clone(), key, "*".to_string(), val.clone()],
-                                );
-                                ctx.sender.send(reply).await?;
-                            } else {
-                                // Deprecated spec format: 766 <client> <key> :no matching key
-                                let reply = server_reply(
-                                    &ctx.matrix.server_info.name,
-                                    Response::ERR_NOMATCHINGKEY,
-                                    vec![
-                                        ctx.state.nick.clone(),
-                                        key,
-                                        "No matching key".to_string(),
-                                    ],
-                                );
-                                ctx.sender.send(reply).await?;
-                            }
-                        }
-                        let reply = server_reply(
-                            &ctx.matrix.server_info.name,
-                            Response::RPL_METADATAEND,
-                            vec![ctx.state.nick.clone(), "End of metadata".to_string()],
-                        );
-                        ctx.sender.send(reply).await?;
+            let mut user = user.write().await;
+            match value {
+                Some(value) => {
+                    if user.metadata.len() >= max_keys && !user.metadata.contains_key(key) {
+                        return Err(LimitReached);
                     }
-                    MetadataSubCommand::SET => {
-                        // Permission check: You can only set your own metadata (normally)
-                        if target_lower != slirc_proto::irc_to_lower(&ctx.state.nick) {
-                            let reply = server_reply(
-                                &ctx.matrix.server_info.name,
-                                Response::ERR_CHANOPRIVSNEEDED,
-                                vec![
-                                    ctx.state.nick.clone(),
-                                    reply_target.to_string(),
-                                    "Permission Denied".to_string(),
-                                ],
-                            );
-                            ctx.sender.send(reply).await?;
-                            return Ok(());
-                        }
-
-                        if params.is_empty() {
-                            let reply = server_reply(
-                                &ctx.matrix.server_info.name,
-                                Response::ERR_NEEDMOREPARAMS,
-                                vec![
-                                    ctx.state.nick.clone(),
-                                    "METADATA".to_string(),
-                                    "Not enough parameters".to_string(),
-                                ],
-                            );
-                            ctx.sender.send(reply).await?;
-                            return Ok(());
-                        }
-                        let key = params[0].to_string();
-                        let value = if params.len() > 1 {
-                            Some(params[1].to_string())
-                        } else {
-                            None
-                        };
-
-                        let mut user = user_rw.write().await;
-                        if let Some(val) = value {
-                            if user.metadata.len() >= 100 && !user.metadata.contains_key(&key) {
-                                let reply = server_reply(
-                                    &ctx.matrix.server_info.name,
-                                    Response::ERR_METADATALIMIT,
-                                    vec![
-                                        ctx.state.nick.clone(),
-                                        "Metadata limit exceeded".to_string(),
-                                    ],
-                                );
-                                ctx.sender.send(reply).await?;
-                            } else {
-                                user.metadata.insert(key.clone(), val.clone());
-                                // Deprecated spec format: 761 <client> <key> <visibility> <value>
-                                // Clone for persistence before moving into reply vec
-                                let key_clone = key.clone();
-                                let val_clone = val.clone();
-                                let reply = server_reply(
-                                    &ctx.matrix.server_info.name,
-                                    Response::RPL_KEYVALUE,
-                                    vec![ctx.state.nick.clone(), key, "*".to_string(), val],
-                                );
-                                ctx.sender.send(reply).await?;
-                                let reply = server_reply(
-                                    &ctx.matrix.server_info.name,
-                                    Response::RPL_METADATAEND,
-                                    vec![ctx.state.nick.clone(), "End of metadata".to_string()],
-                                );
-                                ctx.sender.send(reply).await?;
-
-                                // Persist insertion (User)
-                                if let Some(account_name) = &user.account {
-                                    let account_name = account_name.clone();
-                                    let repo = ctx.matrix.db.accounts();
-
-                                    match repo.find_by_name(&account_name).await {
-                                        Ok(Some(account)) => {
-                                            if let Err(e) = repo
-                                                .set_metadata(
-                                                    account.id,
-                                                    &key_clone,
-                                                    Some(&val_clone),
-                                                )
-                                                .await
-                                            {
-                                                tracing::error!(
-                                                    "Failed to persist user metadata: {}",
-                                                    e
-                                                );
-                                            }
-                                        }
-                                        Ok(None) => {
-                                            tracing::warn!(
-                                                "User account not found for metadata: {}",
-                                                account_name
-                                            );
-                                        }
-                                        Err(e) => {
-                                            tracing::error!("DB error: {}", e);
-                                        }
-                                    }
-                                }
-                            }
-                        } else {
-                            user.metadata.remove(&key);
-
-                            // Persist removal (User)
-                            if let Some(account_name) = &user.account {
-                                let account_name = account_name.clone();
-                                let key_clone = key.clone();
-                                let repo = ctx.matrix.db.accounts();
-
-                                // Spawn to avoid blocking lock? No, keep it simple for now, async is fine.
-                                match repo.find_by_name(&account_name).await {
-                                    Ok(Some(account)) => {
-                                        if let Err(e) =
-                                            repo.set_metadata(account.id, &key_clone, None).await
-                                        {
-                                            tracing::error!(
-                                                "Failed to persist user metadata removal: {}",
-                                                e
-                                            );
-                                        }
-                                    }
-                                    Ok(None) => {
-                                        tracing::warn!(
-                                            "User account not found for metadata: {}",
-                                            account_name
-                                        );
-                                    }
-                                    Err(e) => {
-                                        tracing::error!("DB error: {}", e);
-                                    }
-                                }
-                            }
-
-                            let reply = server_reply(
-                                &ctx.matrix.server_info.name,
-                                Response::RPL_METADATAEND,
-                                vec![ctx.state.nick.clone(), "End of metadata".to_string()],
-                            );
-                            ctx.sender.send(reply).await?;
-                        }
+                    user.metadata.insert(key.to_string(), value.to_string());
+                }
+                None => {
+                    user.metadata.remove(key);
+                }
+            }
+            Ok(())
+        }
+        Target::Channel { tx, .. } => {
+            let command = MetadataCommand::Set {
+                key: key.to_string(),
+                value: value.map(str::to_string),
+                max_keys,
+            };
+            match channel_metadata(tx, command).await {
+                Some(Err(_)) => Err(LimitReached),
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+/// Delete every key, returning the removed ones.
+async fn clear(ctx: &Context<'_, RegisteredState>, target: &Target) -> HashMap<String, String> {
+    match target {
+        Target::User { uid, .. } => match ctx.matrix.user_manager.users.get_cloned(uid) {
+            Some(user) => std::mem::take(&mut user.write().await.metadata),
+            None => HashMap::new(),
+        },
+        Target::Channel { tx, .. } => channel_metadata(tx, MetadataCommand::Clear)
+            .await
+            .and_then(Result::ok)
+            .unwrap_or_default(),
+    }
+}
+
+/// Save a change for a logged-in user's account or a registered channel.
+async fn persist(
+    ctx: &Context<'_, RegisteredState>,
+    target: &Target,
+    key: &str,
+    value: Option<&str>,
+) {
+    match target {
+        Target::User { uid, .. } => {
+            let account = match ctx.matrix.user_manager.users.get_cloned(uid) {
+                Some(user) => user.read().await.account.clone(),
+                None => None,
+            };
+            let Some(account) = account else {
+                return;
+            };
+            let repo = ctx.matrix.db.accounts();
+            match repo.find_by_name(&account).await {
+                Ok(Some(account)) => {
+                    if let Err(e) = repo.set_metadata(account.id, key, value).await {
+                        tracing::error!(error = %e, "Failed to persist user metadata");
                     }
-                    MetadataSubCommand::LIST => {
-                        let user = user_rw.read().await;
-                        for (k, v) in &user.metadata {
-                            // Deprecated spec format: 761 <client> <key> <visibility> <value>
-                            let reply = server_reply(
-                                &ctx.matrix.server_info.name,
-                                Response::RPL_KEYVALUE,
-                                vec![
-                                    ctx.state.nick.clone(),
-                                    k.clone(),
-                                    "*".to_string(),
-                                    v.clone(),
-                                ],
-                            );
-                            ctx.sender.send(reply).await?;
-                        }
-                        let reply = server_reply(
-                            &ctx.matrix.server_info.name,
-                            Response::RPL_METADATAEND,
-                            vec![ctx.state.nick.clone(), "End of metadata".to_string()],
-                        );
-                        ctx.sender.send(reply).await?;
+                }
+                Ok(None) => {}
+                Err(e) => tracing::error!(error = %e, "Failed to look up account for metadata"),
+            }
+        }
+        Target::Channel { lower, .. } => {
+            if !ctx
+                .matrix
+                .channel_manager
+                .registered_channels
+                .contains(lower)
+            {
+                return;
+            }
+            let repo = ctx.matrix.db.channels();
+            match repo.find_by_name(lower).await {
+                Ok(Some(channel)) => {
+                    if let Err(e) = repo.set_metadata(channel.id, key, value).await {
+                        tracing::error!(error = %e, "Failed to persist channel metadata");
                     }
-                    _ => return Ok(()),
                 }
+                Ok(None) => {}
+                Err(e) => tracing::error!(error = %e, "Failed to look up channel for metadata"),
+            }
+        }
+    }
+}
+
+/// Tell subscribed connections that can see the target about a change.
+async fn notify_subscribers(
+    ctx: &Context<'_, RegisteredState>,
+    target: &Target,
+    key: &str,
+    value: Option<&str>,
+) {
+    let private = ctx.matrix.config.metadata.is_private(key);
+    let recipients: HashSet<Uid> = match target {
+        Target::User { uid, .. } if private => HashSet::from([uid.clone()]),
+        Target::User { uid, .. } => {
+            let channels = match ctx.matrix.user_manager.users.get_cloned(uid) {
+                Some(user) => user.read().await.channels.iter().cloned().collect(),
+                None => Vec::new(),
+            };
+            let mut uids = HashSet::from([uid.clone()]);
+            for channel in channels {
+                uids.extend(channel_members(ctx, &channel).await.keys().cloned());
+            }
+            uids
+        }
+        Target::Channel { lower, .. } => channel_members(ctx, lower)
+            .await
+            .into_iter()
+            .filter(|(_, modes)| !private || modes.has_op_or_higher())
+            .map(|(uid, _)| uid)
+            .collect(),
+    };
+
+    let mut args = vec![
+        target.name().to_string(),
+        key.to_string(),
+        ctx.matrix.config.metadata.visibility(key).to_string(),
+    ];
+    args.extend(value.map(str::to_string));
+    let Some(snapshot) = SenderSnapshot::build(ctx).await else {
+        return;
+    };
+    let notification = Arc::new(Message {
+        tags: None,
+        prefix: Some(Prefix::Nickname(
+            snapshot.nick,
+            snapshot.user,
+            snapshot.visible_host,
+        )),
+        command: Command::Raw("METADATA".to_string(), args),
+    });
+
+    let users = &ctx.matrix.user_manager;
+    let local_sid = ctx.matrix.server_info.sid.as_str();
+    for uid in recipients.iter().filter(|uid| uid.starts_with(local_sid)) {
+        let Some(sessions) = users.get_senders_cloned(uid) else {
+            continue;
+        };
+        for sess in sessions {
+            if sess.session_id == ctx.state.session_id
+                || !users.is_metadata_subscribed(sess.session_id, key)
+                || !users
+                    .get_session_caps(sess.session_id)
+                    .is_some_and(|caps| caps.contains(METADATA_CAP))
+            {
+                continue;
+            }
+            let _ = sess.tx.send(Arc::clone(&notification)).await;
+        }
+    }
+}
+
+/// Members of a channel and their status modes.
+async fn channel_members(
+    ctx: &Context<'_, RegisteredState>,
+    channel: &str,
+) -> im::HashMap<Uid, crate::state::MemberModes> {
+    let Some(tx) = ctx.matrix.channel_manager.channels.get_cloned(channel) else {
+        return im::HashMap::new();
+    };
+    let (reply_tx, reply_rx) = oneshot::channel();
+    if tx
+        .send(ChannelEvent::GetMembers { reply_tx })
+        .await
+        .is_err()
+    {
+        return im::HashMap::new();
+    }
+    reply_rx.await.unwrap_or_default()
+}
+
+/// `METADATA * SUB <key...>`: subscribe this connection to keys.
+async fn subscribe(ctx: &mut Context<'_, RegisteredState>, keys: &[&str]) -> HandlerResult {
+    let max_subs = ctx.matrix.config.metadata.max_subs;
+    let mut subscribed = Vec::new();
+    for key in keys {
+        if !is_valid_key(key) {
+            send_key_invalid(ctx, key).await?;
+            continue;
+        }
+        let added = {
+            let mut subs = ctx
+                .matrix
+                .user_manager
+                .metadata_subs
+                .entry(ctx.state.session_id)
+                .or_default();
+            if subs.contains(*key) || subs.len() < max_subs {
+                subs.insert(key.to_string());
+                true
             } else {
-                let reply = server_reply(
-                    &ctx.matrix.server_info.name,
-                    Response::ERR_NOSUCHNICK,
-                    vec![
-                        ctx.state.nick.clone(),
-                        reply_target.to_string(),
-                        "No such nick/channel".to_string(),
-                    ],
-                );
-                ctx.sender.send(reply).await?;
+                false
             }
+        };
+        if !added {
+            ctx.send_reply(
+                Response::ERR_METADATATOOMANYSUBS,
+                vec![
+                    ctx.state.nick.clone(),
+                    key.to_string(),
+                    "Too many subscriptions".to_string(),
+                ],
+            )
+            .await?;
+            break;
+        }
+        subscribed.push(key.to_string());
+    }
+    if !subscribed.is_empty() {
+        let mut params = vec![ctx.state.nick.clone()];
+        params.extend(subscribed);
+        ctx.send_reply(Response::RPL_METADATASUBOK, params).await?;
+    }
+    Ok(())
+}
+
+/// `METADATA * UNSUB <key...>`: drop subscriptions.
+async fn unsubscribe(ctx: &mut Context<'_, RegisteredState>, keys: &[&str]) -> HandlerResult {
+    if let Some(mut subs) = ctx
+        .matrix
+        .user_manager
+        .metadata_subs
+        .get_mut(&ctx.state.session_id)
+    {
+        for key in keys {
+            subs.remove(*key);
         }
+    }
+    let mut params = vec![ctx.state.nick.clone()];
+    params.extend(keys.iter().map(|k| k.to_string()));
+    ctx.send_reply(Response::RPL_METADATAUNSUBOK, params).await
+}
+
+/// `METADATA * SUBS`: list this connection's subscriptions.
+async fn list_subscriptions(ctx: &mut Context<'_, RegisteredState>) -> HandlerResult {
+    let mut subs: Vec<String> = ctx
+        .matrix
+        .user_manager
+        .metadata_subs
+        .get_cloned(&ctx.state.session_id)
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    subs.sort();
+    for chunk in subs.chunks(20) {
+        let mut params = vec![ctx.state.nick.clone()];
+        params.extend(chunk.iter().cloned());
+        ctx.send_reply(Response::RPL_METADATASUBS, params).await?;
+    }
+    send_end(ctx).await
+}
+
+/// `761 <client> <target> <key> <visibility> [:value]`; no value means deleted.
+async fn send_key_value(
+    ctx: &Context<'_, RegisteredState>,
+    target: &Target,
+    key: &str,
+    value: Option<&str>,
+) -> HandlerResult {
+    let mut params = vec![
+        ctx.state.nick.clone(),
+        target.name().to_string(),
+        key.to_string(),
+        ctx.matrix.config.metadata.visibility(key).to_string(),
+    ];
+    params.extend(value.map(str::to_string));
+    ctx.send_reply(Response::RPL_KEYVALUE, params).await
+}
+
+async fn send_end(ctx: &Context<'_, RegisteredState>) -> HandlerResult {
+    ctx.send_reply(
+        Response::RPL_METADATAEND,
+        vec![ctx.state.nick.clone(), "End of metadata".to_string()],
+    )
+    .await
+}
+
+async fn send_key_invalid(ctx: &Context<'_, RegisteredState>, key: &str) -> HandlerResult {
+    ctx.send_reply(
+        Response::ERR_KEYINVALID,
+        vec![
+            ctx.state.nick.clone(),
+            key.to_string(),
+            "Invalid metadata key".to_string(),
+        ],
+    )
+    .await
+}
+
+async fn send_key_not_set(
+    ctx: &Context<'_, RegisteredState>,
+    target: &Target,
+    key: &str,
+) -> HandlerResult {
+    ctx.send_reply(
+        Response::ERR_KEYNOTSET,
+        vec![
+            ctx.state.nick.clone(),
+            target.name().to_string(),
+            key.to_string(),
+            "Key not set".to_string(),
+        ],
+    )
+    .await
+}
+
+async fn send_no_permission(
+    ctx: &Context<'_, RegisteredState>,
+    target: &Target,
+    key: &str,
+) -> HandlerResult {
+    ctx.send_reply(
+        Response::ERR_KEYNOPERMISSION,
+        vec![
+            ctx.state.nick.clone(),
+            target.name().to_string(),
+            key.to_string(),
+            "Permission denied".to_string(),
+        ],
+    )
+    .await
+}
+
+/// Send `FAIL METADATA <code> <context> :<description>`.
+async fn send_fail(
+    ctx: &Context<'_, RegisteredState>,
+    code: &str,
+    context: &str,
+    description: &str,
+) -> HandlerResult {
+    let fail = Message {
+        tags: None,
+        prefix: Some(Prefix::ServerName(ctx.server_name().to_string())),
+        command: Command::FAIL(
+            "METADATA".to_string(),
+            code.to_string(),
+            vec![context.to_string(), description.to_string()],
+        ),
+    };
+    let fail = crate::handlers::util::helpers::with_label(fail, ctx.label.as_deref());
+    ctx.sender.send(fail).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{Command, Message, Response};
+
+    fn numerics(messages: &[Message]) -> Vec<(Response, Vec<String>)> {
+        messages
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::Response(resp, args) => Some((*resp, args.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn has(messages: &[Message], response: Response) -> bool {
+        numerics(messages).iter().any(|(r, _)| *r == response)
+    }
+
+    #[tokio::test]
+    async fn subscribers_sharing_a_channel_are_notified() {
+        let tm = TestMatrixBuilder::new()
+            .config(|c| {
+                c.metadata.max_subs = 1;
+            })
+            .build()
+            .await;
+        let mut alice = tm.user("alice").build().await;
+        let mut bob = tm.user("bob").cap("draft/metadata-2").build().await;
+        let mut carol = tm.user("carol").cap("draft/metadata-2").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.run(&mut bob, "JOIN #chan").await;
+
+        let replies = tm.run(&mut bob, "METADATA * SUB url avatar").await;
+        let subs = numerics(&replies);
+        assert!(subs.contains(&(
+            Response::RPL_METADATASUBOK,
+            vec!["bob".into(), "url".into()]
+        )));
+        assert!(has(&replies, Response::ERR_METADATATOOMANYSUBS));
+        tm.run(&mut carol, "METADATA * SUB url").await;
+        bob.drain();
+        carol.drain();
+
+        let replies = tm
+            .run(&mut alice, "METADATA * SET url :https://example.com")
+            .await;
+        assert!(numerics(&replies).contains(&(
+            Response::RPL_KEYVALUE,
+            vec![
+                "alice".into(),
+                "alice".into(),
+                "url".into(),
+                "*".into(),
+                "https://example.com".into()
+            ]
+        )));
+        let notification = bob
+            .recv_matching(|m| matches!(&m.command, Command::Raw(cmd, _) if cmd == "METADATA"))
+            .await
+            .expect("bob is notified");
+        assert!(matches!(
+            &notification.command,
+            Command::Raw(_, args) if args == &["alice", "url", "*", "https://example.com"]
+        ));
+        // carol subscribed but shares no channel with alice
+        assert!(carol.drain().is_empty());
+
+        let replies = tm.run(&mut bob, "METADATA alice GET url").await;
+        assert!(has(&replies, Response::RPL_KEYVALUE));
+        let replies = tm.run(&mut bob, "METADATA alice SET url :x").await;
+        assert!(has(&replies, Response::ERR_KEYNOPERMISSION));
+    }
+
+    #[tokio::test]
+    async fn limits_private_keys_and_clear() {
+        let tm = TestMatrixBuilder::new()
+            .config(|c| {
+                c.metadata.max_keys = 2;
+                c.metadata.key_limits.insert("bio".into(), 4);
+                c.metadata.private_keys.push("secret/*".into());
+            })
+            .build()
+            .await;
+        let mut alice = tm.user("alice").build().await;
+        let mut bob = tm.user("bob").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.run(&mut bob, "JOIN #chan").await;
+
+        let replies = tm.run(&mut alice, "METADATA #chan SET bio :toolong").await;
+        assert!(replies.iter().any(|m| matches!(
+            &m.command,
+            Command::FAIL(_, code, _) if code == "VALUE_INVALID"
+        )));
+        let replies = tm.run(&mut alice, "METADATA #chan SET Bad!Key :x").await;
+        assert!(has(&replies, Response::ERR_KEYINVALID));
+        tm.run(&mut alice, "METADATA #chan SET secret/pin :1234")
+            .await;
+        tm.run(&mut alice, "METADATA #chan SET bio :hi").await;
+        let replies = tm.run(&mut alice, "METADATA #chan SET url :x").await;
+        assert!(has(&replies, Response::ERR_METADATALIMIT));
+
+        let replies = tm.run(&mut bob, "METADATA #chan LIST").await;
+        let keys: Vec<_> = numerics(&replies)
+            .into_iter()
+            .filter(|(r, _)| *r == Response::RPL_KEYVALUE)
+            .map(|(_, args)| args[2].clone())
+            .collect();
+        assert_eq!(keys, ["bio"]);
+        let replies = tm.run(&mut bob, "METADATA #chan GET secret/pin").await;
+        assert!(has(&replies, Response::ERR_KEYNOPERMISSION));
+        let replies = tm.run(&mut bob, "METADATA #chan CLEAR").await;
+        assert!(has(&replies, Response::ERR_KEYNOPERMISSION));
 
-        Ok(())
+        tm.run(&mut alice, "METADATA #chan CLEAR").await;
+        let replies = tm.run(&mut alice, "METADATA #chan GET bio").await;
+        assert!(has(&replies, Response::ERR_KEYNOTSET));
     }
 }
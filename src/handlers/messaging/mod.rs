@@ -12,11 +12,14 @@ mod accept;
 
 mod delivery;
 mod errors;
+mod markread;
 mod metadata;
 mod multiclient;
 mod notice;
 
 mod privmsg;
+mod redact;
+mod relay;
 mod relaymsg;
 mod routing;
 
@@ -25,11 +28,17 @@ mod types;
 mod validation;
 
 pub use accept::AcceptHandler;
+pub use markread::MarkreadHandler;
 pub use metadata::MetadataHandler;
 pub use notice::NoticeHandler;
 pub use privmsg::PrivmsgHandler;
+pub use redact::RedactHandler;
 pub use relaymsg::RelayMsgHandler;
 
+// Shared with multiline batches, which do their own fan-out
+pub(super) use relay::{RelayKind, RelayStamp, check_relay, store_relay_history};
+pub(super) use types::SenderSnapshot;
+
 // ============================================================================
 // TAGMSG Handler
 // ============================================================================
@@ -2,20 +2,13 @@
 //!
 //! Per RFC 2812, NOTICE errors are silently ignored (no error replies).
 
-use super::super::{Context, HandlerError, HandlerResult, PostRegHandler, user_prefix};
-// Removed: use super::delivery::{send_cannot_send, send_no_such_channel};
-use super::routing::{route_to_channel_with_snapshot, route_to_user_with_snapshot};
-use super::types::{ChannelRouteResult, RouteMeta, RouteOptions, SenderSnapshot, UserRouteResult};
-use super::validation::{ErrorStrategy, validate_message_send};
-use crate::history::types::MessageTag as HistoryTag;
-use crate::history::{MessageEnvelope, StoredMessage};
+use super::super::{Context, HandlerError, HandlerResult, PostRegHandler};
+use super::relay::{Relay, RelayKind, relay_to_target};
+use super::types::SenderSnapshot;
+use super::validation::{ErrorStrategy, ValidationResult, validate_message_send};
 use crate::state::RegisteredState;
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
-use slirc_proto::{ChannelExt, Command, Message, MessageRef, irc_to_lower};
-use std::time::{SystemTime, UNIX_EPOCH};
-use tracing::debug;
-use uuid::Uuid;
+use slirc_proto::MessageRef;
 
 // ============================================================================
 // NOTICE Handler
@@ -33,8 +26,6 @@ impl PostRegHandler for NoticeHandler {
         ctx: &mut Context<'_, RegisteredState>,
         msg: &MessageRef<'_>,
     ) -> HandlerResult {
-        // Registration check removed - handled by registry typestate dispatch (Innovation 1)
-
         // NOTICE <target> <text>
         let target = msg.arg(0).unwrap_or("");
         let text = msg.arg(1).unwrap_or("");
@@ -51,188 +42,72 @@ impl PostRegHandler for NoticeHandler {
 
         // Use shared validation (shun, rate limiting, spam detection)
         // NOTICE silently drops errors per RFC 2812
-        validate_message_send(ctx, target, text, ErrorStrategy::SilentDrop, &snapshot).await?;
-
-        // Collect client-only tags (those starting with '+') AND the label tag to preserve them
-        // The label tag is needed for labeled-response echoes back to the sender
-        use slirc_proto::message::Tag;
-        use std::borrow::Cow;
-        let preserved_tags: Vec<Tag> = msg
-            .tags_iter()
-            .filter(|(k, _)| k.starts_with('+') || *k == "label")
-            .map(|(k, v)| {
-                Tag(
-                    Cow::Owned(k.to_string()),
-                    if v.is_empty() {
-                        None
-                    } else {
-                        Some(v.to_string())
-                    },
-                )
-            })
-            .collect();
-
-        // Generate timestamp and msgid
-        let now = SystemTime::now();
-        let duration = now.duration_since(UNIX_EPOCH).unwrap_or_default();
-        let millis = duration.as_millis() as i64;
-
-        let dt = DateTime::<Utc>::from_timestamp(millis / 1000, (millis % 1000) as u32 * 1_000_000)
-            .unwrap_or_default();
-        let timestamp_iso = dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
-        let nanotime = dt.timestamp_nanos_opt().unwrap_or(0);
-        let msgid = Uuid::new_v4().to_string();
-
-        // Prepare tags for history
-        let history_tags: Option<Vec<HistoryTag>> = if preserved_tags.is_empty() {
-            None
-        } else {
-            Some(
-                preserved_tags
-                    .iter()
-                    .map(|t| HistoryTag {
-                        key: t.0.to_string(),
-                        value: t.1.as_ref().map(|v| v.to_string()),
-                    })
-                    .collect(),
-            )
-        };
-
-        // Build the outgoing message with preserved tags (client tags + label)
-        let out_msg = Message {
-            tags: if preserved_tags.is_empty() {
-                None
-            } else {
-                Some(preserved_tags)
-            },
-            prefix: Some(user_prefix(
-                &snapshot.nick,
-                &snapshot.user,
-                &snapshot.visible_host,
-            )),
-            command: Command::NOTICE(target.to_string(), text.to_string()),
-        };
-
-        // NOTICE: silently drop on errors, check moderated, no away reply
-        let opts = RouteOptions {
-            send_away_reply: false,
-
-            status_prefix: None,
-        };
-
-        // STATUSMSG support: @#channel sends to ops, +#channel sends to voiced+
-        let (status_prefix, actual_target) = super::privmsg::parse_statusmsg(target);
-        let routing_target = actual_target.unwrap_or(target);
+        if let ValidationResult::Blocked =
+            validate_message_send(ctx, target, text, ErrorStrategy::SilentDrop, &snapshot).await?
+        {
+            return Ok(());
+        }
 
-        if routing_target.is_channel_name() {
-            let channel_lower = irc_to_lower(routing_target);
+        let relay = Relay::new(
+            RelayKind::Notice,
+            msg,
+            text,
+            &snapshot,
+            &ctx.matrix.config.client_tags,
+        );
+        relay_to_target(ctx, &relay, target).await
+    }
+}
 
-            if let Some(prefix_char) = status_prefix {
-                // Route STATUSMSG with snapshot
-                let _ = super::privmsg::route_statusmsg(
-                    ctx,
-                    super::privmsg::StatusMsgParams {
-                        channel_lower: &channel_lower,
-                        original_target: target,
-                        msg: out_msg,
-                        prefix_char,
-                        timestamp: Some(timestamp_iso.clone()),
-                        msgid: Some(msgid.clone()),
-                        nanotime: Some(nanotime),
-                        snapshot: &snapshot,
-                    },
-                )
-                .await;
-                debug!(from = %snapshot.nick, to = %target, prefix = %prefix_char, "NOTICE STATUSMSG");
-                // Suppress ACK for echo-message with labels (echo IS the response)
-                if ctx.label.is_some() && ctx.state.capabilities.contains("echo-message") {
-                    ctx.suppress_labeled_ack = true;
-                }
-            } else if let ChannelRouteResult::Sent = route_to_channel_with_snapshot(
-                ctx,
-                &channel_lower,
-                out_msg,
-                &opts,
-                RouteMeta {
-                    timestamp: Some(timestamp_iso.clone()),
-                    msgid: Some(msgid.clone()),
-                    nanotime: Some(nanotime),
-                    override_nick: None,
-                    relaymsg_sender_nick: None,
-                },
-                &snapshot,
-            )
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::Command;
+
+    #[tokio::test]
+    async fn notice_relays_unescaped_client_tags() {
+        let tm = TestMatrixBuilder::new()
+            .config(|c| c.client_tags.allow.push("+example/*".to_string()))
+            .build()
+            .await;
+        let mut alice = tm.user("alice").cap("message-tags").build().await;
+        let mut bob = tm.user("bob").cap("message-tags").build().await;
+
+        tm.run(&mut alice, "@+example/note=a\\sb NOTICE bob :hi")
+            .await;
+        let notice = bob
+            .recv_matching(|m| matches!(m.command, Command::NOTICE(..)))
             .await
-            {
-                debug!(from = %snapshot.nick, to = %target, "NOTICE to channel");
-                // Suppress ACK for echo-message with labels (echo IS the response)
-                if ctx.label.is_some() && ctx.state.capabilities.contains("echo-message") {
-                    ctx.suppress_labeled_ack = true;
-                }
-            }
-            // All errors silently ignored for NOTICE
-        } else {
-            let target_lower = irc_to_lower(routing_target);
-            if route_to_user_with_snapshot(
-                ctx,
-                &target_lower,
-                out_msg,
-                &opts,
-                Some(timestamp_iso.clone()),
-                Some(msgid.clone()),
-                &snapshot,
-            )
-            .await
-                == UserRouteResult::Sent
-            {
-                debug!(from = %snapshot.nick, to = %target, "NOTICE to user");
-
-                // Store message in history (DMs)
-                let prefix = format!(
-                    "{}!{}@{}",
-                    snapshot.nick, snapshot.user, snapshot.visible_host
-                );
-                let envelope = MessageEnvelope {
-                    command: "NOTICE".to_string(),
-                    prefix: prefix.clone(),
-                    target: target.to_string(),
-                    text: text.to_string(),
-                    tags: history_tags.clone(),
-                };
-                let stored_msg = StoredMessage {
-                    msgid: msgid.clone(),
-                    target: irc_to_lower(target),
-                    sender: snapshot.nick.clone(),
-                    envelope,
-                    nanotime,
-                    account: ctx.state.account.clone(),
-                    status_prefix: None,
-                };
+            .expect("bob gets the notice");
+        assert_eq!(notice.tag_value("+example/note"), Some("a b"));
+    }
 
-                // Store for recipient
-                if let Err(e) = ctx
-                    .matrix
-                    .service_manager
-                    .history
-                    .store(target, stored_msg.clone())
-                    .await
-                {
-                    debug!(error = %e, "Failed to store NOTICE DM for recipient");
-                }
-                // Store for sender
-                if let Err(e) = ctx
-                    .matrix
-                    .service_manager
-                    .history
-                    .store(&snapshot.nick, stored_msg)
-                    .await
-                {
-                    debug!(error = %e, "Failed to store NOTICE DM for sender");
-                }
-            }
-            // User not found: silently ignored for NOTICE
+    #[tokio::test]
+    async fn notice_errors_are_silent() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
+        let mut bob = tm.user("bob").build().await;
+        tm.run(&mut bob, "JOIN #chan").await;
+        tm.run(&mut bob, "MODE #chan +n").await;
+
+        for target in ["nobody", "#chan", "#nowhere"] {
+            let replies = tm.run(&mut alice, &format!("NOTICE {target} :hi")).await;
+            assert!(replies.is_empty(), "{target}: {replies:?}");
         }
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn notice_from_quieted_member_is_dropped() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
+        let mut bob = tm.user("bob").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.run(&mut bob, "JOIN #chan").await;
+        tm.run(&mut alice, "MODE #chan +q bob!*@*").await;
+        alice.drain();
+
+        let replies = tm.run(&mut bob, "NOTICE #chan :hi").await;
+        assert!(replies.is_empty());
+        assert!(alice.drain().is_empty());
     }
 }
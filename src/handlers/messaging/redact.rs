@@ -0,0 +1,295 @@
+//! REDACT command handler (draft/message-redaction).
+//!
+//! `REDACT <target> <msgid> [:reason]` deletes a message from history and
+//! tells everyone who saw it. The original sender, channel operators and IRC
+//! operators may redact; recipients without the capability get a NOTICE.
+
+use super::super::{Context, HandlerError, HandlerResult, PostRegHandler};
+use super::types::SenderSnapshot;
+use crate::handlers::chathistory::helpers::resolve_dm_key;
+use crate::handlers::util::helpers::with_label;
+use crate::history::HistoryQuery;
+use crate::history::types::HistoryItem;
+use crate::state::RegisteredState;
+use crate::state::actor::ChannelEvent;
+use crate::state::dashmap_ext::DashMapExt;
+use async_trait::async_trait;
+use slirc_proto::{ChannelExt, Command, Message, MessageRef, Prefix, irc_to_lower};
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use tracing::debug;
+
+/// Capability clients need to receive REDACT.
+const REDACTION_CAP: &str = "draft/message-redaction";
+
+pub struct RedactHandler;
+
+#[async_trait]
+impl PostRegHandler for RedactHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, RegisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let target = msg.arg(0).ok_or(HandlerError::NeedMoreParams)?;
+        let msgid = msg.arg(1).ok_or(HandlerError::NeedMoreParams)?;
+        let reason = msg.arg(2).filter(|r| !r.is_empty());
+
+        let snapshot = SenderSnapshot::build(ctx)
+            .await
+            .ok_or(HandlerError::NickOrUserMissing)?;
+
+        let is_channel = target.is_channel_name();
+        let target_lower = irc_to_lower(target);
+        if is_channel
+            && !ctx
+                .matrix
+                .channel_manager
+                .channels
+                .contains_key(&target_lower)
+        {
+            return send_fail(ctx, "INVALID_TARGET", target, "No such channel").await;
+        }
+        let key = if is_channel {
+            target_lower.clone()
+        } else {
+            resolve_dm_key(ctx, &snapshot.nick, target).await
+        };
+
+        let history = &ctx.matrix.service_manager.history;
+        let stored = match history.lookup_timestamp(&key, msgid).await {
+            Ok(Some(ts)) => history
+                .query(HistoryQuery {
+                    target: key.clone(),
+                    start: Some(ts),
+                    end: Some(ts + 1),
+                    start_id: None,
+                    end_id: None,
+                    limit: usize::MAX,
+                    reverse: false,
+                })
+                .await
+                .map_err(|e| HandlerError::Internal(e.to_string()))?
+                .into_iter()
+                .find_map(|item| match item {
+                    HistoryItem::Message(m) if m.msgid == msgid => Some(m),
+                    _ => None,
+                }),
+            Ok(None) => None,
+            Err(e) => return Err(HandlerError::Internal(e.to_string())),
+        };
+        let Some(stored) = stored else {
+            return send_fail(ctx, "UNKNOWN_MSGID", target, "No such message").await;
+        };
+
+        // Accounts are compared when the message was sent logged in
+        let is_author = match &stored.account {
+            Some(account) => snapshot
+                .account
+                .as_ref()
+                .is_some_and(|a| irc_to_lower(a) == irc_to_lower(account)),
+            None => irc_to_lower(&stored.sender) == irc_to_lower(&snapshot.nick),
+        };
+        let allowed =
+            is_author || snapshot.is_oper || (is_channel && is_chanop(ctx, &target_lower).await);
+        if !allowed {
+            return send_fail(
+                ctx,
+                "REDACT_FORBIDDEN",
+                target,
+                "You may not redact this message",
+            )
+            .await;
+        }
+
+        if let Err(e) = history.delete(&key, msgid).await {
+            return Err(HandlerError::Internal(e.to_string()));
+        }
+        debug!(target = %target, msgid = %msgid, by = %snapshot.nick, "REDACT applied");
+
+        let prefix = Prefix::Nickname(
+            snapshot.nick.clone(),
+            snapshot.user.clone(),
+            snapshot.visible_host.clone(),
+        );
+        let mut args = vec![target.to_string(), msgid.to_string()];
+        args.extend(reason.map(str::to_string));
+        let redact = Message {
+            tags: None,
+            prefix: Some(prefix),
+            command: Command::Raw("REDACT".to_string(), args),
+        };
+        let server_name = ctx.server_name().to_string();
+        let notice_text = match reason {
+            Some(reason) => format!("{} deleted a message ({reason})", snapshot.nick),
+            None => format!("{} deleted a message", snapshot.nick),
+        };
+
+        if is_channel {
+            let fallback = Message {
+                tags: None,
+                prefix: Some(Prefix::ServerName(server_name)),
+                command: Command::NOTICE(target.to_string(), notice_text),
+            };
+            ctx.matrix
+                .channel_manager
+                .broadcast_to_channel_with_cap_exclude_users(
+                    &target_lower,
+                    redact.clone(),
+                    &[ctx.uid],
+                    Some(REDACTION_CAP),
+                    Some(fallback),
+                )
+                .await;
+        } else if let Some(uid) = ctx.matrix.user_manager.get_first_uid(&target_lower) {
+            let has_cap = match ctx.matrix.user_manager.users.get_cloned(&uid) {
+                Some(user) => user.read().await.caps.contains(REDACTION_CAP),
+                None => false,
+            };
+            let to_target = if has_cap {
+                redact.clone()
+            } else {
+                Message {
+                    tags: None,
+                    prefix: Some(Prefix::ServerName(server_name)),
+                    command: Command::NOTICE(
+                        target.to_string(),
+                        format!("{notice_text} you received"),
+                    ),
+                }
+            };
+            ctx.matrix
+                .user_manager
+                .send_to_uid(&uid, Arc::new(to_target))
+                .await;
+        }
+
+        // The sender always sees its own redaction
+        let echo = with_label(redact, ctx.label.as_deref());
+        ctx.sender.send(echo).await?;
+        Ok(())
+    }
+}
+
+/// Whether the caller is a channel operator on `channel`.
+pub(super) async fn is_chanop(ctx: &Context<'_, RegisteredState>, channel: &str) -> bool {
+    let Some(channel_tx) = ctx.matrix.channel_manager.channels.get_cloned(channel) else {
+        return false;
+    };
+    let (reply_tx, reply_rx) = oneshot::channel();
+    let event = ChannelEvent::GetMemberModes {
+        uid: ctx.uid.to_string(),
+        reply_tx,
+    };
+    if channel_tx.send(event).await.is_err() {
+        return false;
+    }
+    matches!(reply_rx.await, Ok(Some(modes)) if modes.has_op_or_higher())
+}
+
+/// Send `FAIL REDACT <code> <target> :<description>`.
+async fn send_fail(
+    ctx: &mut Context<'_, RegisteredState>,
+    code: &str,
+    target: &str,
+    description: &str,
+) -> HandlerResult {
+    let fail = Message {
+        tags: None,
+        prefix: Some(Prefix::ServerName(ctx.server_name().to_string())),
+        command: Command::FAIL(
+            "REDACT".to_string(),
+            code.to_string(),
+            vec![target.to_string(), description.to_string()],
+        ),
+    };
+    let fail = with_label(fail, ctx.label.as_deref());
+    ctx.sender.send(fail).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{Command, Message};
+
+    fn msgid_of(messages: &[Message]) -> String {
+        messages
+            .iter()
+            .find_map(|m| m.tags.as_ref()?.iter().find(|t| t.0 == "msgid")?.1.clone())
+            .expect("message with msgid")
+    }
+
+    fn redacts(messages: &[Message]) -> Vec<Vec<String>> {
+        messages
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::Raw(name, args) if name == "REDACT" => Some(args.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn fail_code(messages: &[Message]) -> Option<String> {
+        messages.iter().find_map(|m| match &m.command {
+            Command::FAIL(cmd, code, _) if cmd == "REDACT" => Some(code.clone()),
+            _ => None,
+        })
+    }
+
+    #[tokio::test]
+    async fn sender_redacts_and_legacy_members_get_a_notice() {
+        let tm = TestMatrixBuilder::new()
+            .config(|c| {
+                c.history.enabled = true;
+                c.history.backend = "memory".to_string();
+            })
+            .build()
+            .await;
+        let mut alice = tm
+            .user("alice")
+            .cap("draft/message-redaction")
+            .build()
+            .await;
+        let mut bob = tm
+            .user("bob")
+            .cap("draft/message-redaction")
+            .cap("message-tags")
+            .build()
+            .await;
+        let mut carol = tm.user("carol").build().await;
+        for user in [&mut alice, &mut bob, &mut carol] {
+            tm.run(user, "JOIN #chan").await;
+        }
+        alice.drain();
+        bob.drain();
+        carol.drain();
+
+        tm.run(&mut alice, "PRIVMSG #chan :oops").await;
+        let msgid = msgid_of(&bob.drain());
+
+        let replies = tm.run(&mut bob, &format!("REDACT #chan {msgid}")).await;
+        assert_eq!(fail_code(&replies).as_deref(), Some("REDACT_FORBIDDEN"));
+
+        let replies = tm
+            .run(&mut alice, &format!("REDACT #chan {msgid} :typo"))
+            .await;
+        assert_eq!(redacts(&replies), [["#chan", msgid.as_str(), "typo"]]);
+        let redact = bob
+            .recv_matching(|m| !redacts(std::slice::from_ref(m)).is_empty())
+            .await
+            .expect("bob gets REDACT");
+        assert_eq!(redacts(&[redact]), [["#chan", msgid.as_str(), "typo"]]);
+        let notice = carol
+            .recv_matching(|m| matches!(&m.command, Command::NOTICE(..)))
+            .await
+            .expect("carol gets a notice");
+        assert!(matches!(
+            &notice.command,
+            Command::NOTICE(_, text) if text == "alice deleted a message (typo)"
+        ));
+
+        let replies = tm.run(&mut alice, &format!("REDACT #chan {msgid}")).await;
+        assert_eq!(fail_code(&replies).as_deref(), Some("UNKNOWN_MSGID"));
+    }
+}
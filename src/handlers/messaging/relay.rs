@@ -0,0 +1,672 @@
+//! Shared relay path for PRIVMSG, NOTICE and TAGMSG.
+//!
+//! Once a command has done its own checks, every message goes through the
+//! same steps: resolve the target (addressed forms, services, STATUSMSG),
+//! route it to a channel or user (permission checks, fan-out and echo),
+//! report a refusal, and store DMs in history. Multiline batches do their
+//! own fan-out but use the checks, reporting, stamp and history from here.
+
+use super::delivery::{send_cannot_send, send_no_such_channel};
+use super::errors::*;
+use super::routing::{
+    check_channel_send, resolve_addressed_target, route_to_channel_with_snapshot,
+    route_to_user_with_snapshot, user_refuses_message,
+};
+use super::types::{
+    AddressedTarget, ChannelRouteResult, RouteMeta, RouteOptions, SenderSnapshot, UserRouteResult,
+};
+use super::validation::{ErrorStrategy, ValidationResult, validate_message_send};
+use crate::config::{ClientTagScope, ClientTagsConfig};
+use crate::handlers::{Context, HandlerError, HandlerResult, send_no_such_nick, user_prefix};
+use crate::history::types::MessageTag as HistoryTag;
+use crate::history::{MessageEnvelope, StoredMessage};
+use crate::services::route_service_message;
+use crate::state::RegisteredState;
+use crate::state::dashmap_ext::DashMapExt;
+use chrono::{DateTime, Utc};
+use slirc_proto::{ChannelExt, Command, Message, MessageRef, Response, Tag, irc_to_lower};
+use std::borrow::Cow;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::debug;
+use uuid::Uuid;
+
+/// The message command being relayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayKind {
+    Privmsg,
+    Notice,
+    Tagmsg,
+}
+
+impl RelayKind {
+    /// Kind of the lines in a multiline batch (PRIVMSG unless NOTICE).
+    pub fn from_batch_command(command: &str) -> Self {
+        if command.eq_ignore_ascii_case("NOTICE") {
+            Self::Notice
+        } else {
+            Self::Privmsg
+        }
+    }
+
+    /// Command name, as used for replies and history.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Privmsg => "PRIVMSG",
+            Self::Notice => "NOTICE",
+            Self::Tagmsg => "TAGMSG",
+        }
+    }
+
+    /// Per RFC 2812, NOTICE never gets error replies.
+    pub fn error_strategy(self) -> ErrorStrategy {
+        match self {
+            Self::Notice => ErrorStrategy::SilentDrop,
+            Self::Privmsg | Self::Tagmsg => ErrorStrategy::SendError,
+        }
+    }
+
+    fn reports_errors(self) -> bool {
+        matches!(self.error_strategy(), ErrorStrategy::SendError)
+    }
+
+    fn command(self, target: &str, text: &str) -> Command {
+        match self {
+            Self::Privmsg => Command::PRIVMSG(target.to_string(), text.to_string()),
+            Self::Notice => Command::NOTICE(target.to_string(), text.to_string()),
+            Self::Tagmsg => Command::TAGMSG(target.to_string()),
+        }
+    }
+}
+
+/// Server-time, msgid and history ordering shared by every copy of a message.
+pub struct RelayStamp {
+    /// ISO 8601 timestamp for server-time.
+    pub timestamp: String,
+    pub msgid: String,
+    /// Timestamp in nanoseconds, for history ordering.
+    pub nanotime: i64,
+}
+
+impl RelayStamp {
+    /// Stamp a message now, truncated to the millisecond precision of server-time.
+    pub fn now() -> Self {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64;
+        let dt = DateTime::<Utc>::from_timestamp(millis / 1000, (millis % 1000) as u32 * 1_000_000)
+            .unwrap_or_default();
+        Self {
+            timestamp: dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            msgid: Uuid::new_v4().to_string(),
+            nanotime: millis * 1_000_000,
+        }
+    }
+
+    fn meta(&self) -> RouteMeta {
+        RouteMeta {
+            timestamp: Some(self.timestamp.clone()),
+            msgid: Some(self.msgid.clone()),
+            nanotime: Some(self.nanotime),
+            override_nick: None,
+            relaymsg_sender_nick: None,
+        }
+    }
+}
+
+/// A message on its way to one or more targets.
+pub struct Relay<'a> {
+    pub kind: RelayKind,
+    /// Message body (empty for TAGMSG).
+    pub text: &'a str,
+    /// Client-only tags and the label, unescaped from wire format.
+    pub tags: Vec<Tag>,
+    /// Client-only tags relayed in direct messages only; never stored.
+    pub dm_tags: Vec<Tag>,
+    pub snapshot: &'a SenderSnapshot,
+}
+
+impl<'a> Relay<'a> {
+    /// Take the relayable tags from the client's message.
+    ///
+    /// Client-only tags are kept as `[client_tags]` allows; the label is
+    /// needed for labeled-response echoes.
+    pub fn new(
+        kind: RelayKind,
+        msg: &MessageRef<'_>,
+        text: &'a str,
+        snapshot: &'a SenderSnapshot,
+        policy: &ClientTagsConfig,
+    ) -> Self {
+        let mut tags = Vec::new();
+        let mut dm_tags = Vec::new();
+        for (k, v) in msg.tags_iter() {
+            if !k.starts_with('+') && k != "label" {
+                continue;
+            }
+            let value = if v.is_empty() {
+                None
+            } else {
+                Some(slirc_proto::message::tags::unescape_tag_value(v))
+            };
+            let tag = Tag(Cow::Owned(k.to_string()), value);
+            if k == "label" {
+                tags.push(tag);
+                continue;
+            }
+            match policy.scope(k, tag.1.as_deref()) {
+                Ok(ClientTagScope::Any) => tags.push(tag),
+                Ok(ClientTagScope::DirectOnly) => dm_tags.push(tag),
+                Err(drop) => crate::metrics::inc_client_tags_dropped(drop.as_str()),
+            }
+        }
+        Self {
+            kind,
+            text,
+            tags,
+            dm_tags,
+            snapshot,
+        }
+    }
+
+    fn build(&self, target: &str, direct: bool) -> Message {
+        let mut tags = self.tags.clone();
+        if direct {
+            tags.extend(self.dm_tags.iter().cloned());
+        }
+        Message {
+            tags: if tags.is_empty() { None } else { Some(tags) },
+            prefix: Some(user_prefix(
+                &self.snapshot.nick,
+                &self.snapshot.user,
+                &self.snapshot.visible_host,
+            )),
+            command: self.kind.command(target, self.text),
+        }
+    }
+}
+
+/// Relay a message to one target: a channel, `@#channel`, nick or addressed nick.
+pub async fn relay_to_target(
+    ctx: &mut Context<'_, RegisteredState>,
+    relay: &Relay<'_>,
+    target: &str,
+) -> HandlerResult {
+    let kind = relay.kind;
+    let snapshot = relay.snapshot;
+
+    // RFC 2812 nick@server and user%host@server targets
+    let addressed_nick = match resolve_addressed_target(ctx.matrix, target).await {
+        AddressedTarget::Plain => None,
+        AddressedTarget::Nick(nick) => Some(nick),
+        AddressedTarget::NoSuchNick => {
+            return report_user_result(ctx, kind, target, UserRouteResult::NoSuchNick).await;
+        }
+        AddressedTarget::Ambiguous => {
+            if kind.reports_errors() {
+                ctx.send_reply(
+                    Response::ERR_TOOMANYTARGETS,
+                    vec![
+                        snapshot.nick.clone(),
+                        target.to_string(),
+                        "Duplicate recipients. No message delivered".to_string(),
+                    ],
+                )
+                .await?;
+            }
+            return Ok(());
+        }
+    };
+
+    // NickServ, ChanServ, etc.
+    if kind == RelayKind::Privmsg
+        && route_service_message(
+            ctx.matrix,
+            ctx.uid,
+            &snapshot.nick,
+            addressed_nick.as_deref().unwrap_or(target),
+            relay.text,
+            &ctx.sender,
+        )
+        .await
+    {
+        return Ok(());
+    }
+
+    let stamp = RelayStamp::now();
+
+    // STATUSMSG support: @#channel sends to ops, +#channel sends to voiced+
+    let (status_prefix, actual_target) = parse_statusmsg(target);
+    let opts = RouteOptions {
+        send_away_reply: kind == RelayKind::Privmsg,
+        status_prefix,
+    };
+
+    if let Some(channel) = actual_target
+        .or(Some(target))
+        .filter(|t| addressed_nick.is_none() && t.is_channel_name())
+    {
+        let channel_lower = irc_to_lower(channel);
+        let result = route_to_channel_with_snapshot(
+            ctx,
+            &channel_lower,
+            relay.build(target, false),
+            &opts,
+            stamp.meta(),
+            snapshot,
+        )
+        .await;
+        if result == ChannelRouteResult::Sent {
+            debug!(from = %snapshot.nick, to = %target, prefix = ?status_prefix, "{} to channel", kind.name());
+            suppress_labeled_ack_if_echo(ctx);
+        }
+        return report_channel_result(ctx, kind, target, result).await;
+    }
+
+    let nick = addressed_nick.as_deref().unwrap_or(target);
+    let target_lower = irc_to_lower(nick);
+
+    // Auto-accept: let the target reply even if the sender has +R
+    if kind == RelayKind::Privmsg
+        && let Some(user_arc) = ctx.matrix.user_manager.users.get_cloned(ctx.uid)
+    {
+        user_arc
+            .write()
+            .await
+            .accept_list
+            .insert(target_lower.clone());
+    }
+
+    let result = route_to_user_with_snapshot(
+        ctx,
+        &target_lower,
+        relay.build(target, true),
+        &opts,
+        Some(stamp.timestamp.clone()),
+        Some(stamp.msgid.clone()),
+        snapshot,
+    )
+    .await;
+    if result == UserRouteResult::Sent {
+        debug!(from = %snapshot.nick, to = %target, "{} to user", kind.name());
+        store_dm_history(ctx, kind, &stamp, nick, relay.text, &relay.tags, snapshot).await;
+    }
+    report_user_result(ctx, kind, target, result).await
+}
+
+/// Run every check a message to `target` must pass, without delivering it.
+///
+/// For multiline batches, which fan out themselves: covers spam and shun
+/// validation, the channel's modes and bans, or the target user's +R,
+/// SILENCE and +T. Refusals are reported as for a single message; returns
+/// whether the message may be delivered.
+pub async fn check_relay(
+    ctx: &mut Context<'_, RegisteredState>,
+    kind: RelayKind,
+    target: &str,
+    text: &str,
+    snapshot: &SenderSnapshot,
+) -> Result<bool, HandlerError> {
+    if let ValidationResult::Blocked =
+        validate_message_send(ctx, target, text, kind.error_strategy(), snapshot).await?
+    {
+        return Ok(false);
+    }
+
+    let msg = Message::from(kind.command(target, text));
+    if target.is_channel_name() {
+        let result = check_channel_send(ctx, &irc_to_lower(target), &msg, snapshot).await;
+        report_channel_result(ctx, kind, target, result).await?;
+        return Ok(result == ChannelRouteResult::Sent);
+    }
+
+    let target_user = match ctx.matrix.user_manager.get_first_uid(&irc_to_lower(target)) {
+        Some(uid) => ctx.matrix.user_manager.users.get_cloned(&uid),
+        None => None,
+    };
+    let result = match target_user {
+        Some(user_arc) => user_refuses_message(&*user_arc.read().await, snapshot, Some(text))
+            .unwrap_or(UserRouteResult::Sent),
+        None => UserRouteResult::NoSuchNick,
+    };
+    report_user_result(ctx, kind, target, result).await?;
+    Ok(result == UserRouteResult::Sent)
+}
+
+/// Tell the sender why a channel refused their message (nothing for NOTICE).
+pub async fn report_channel_result(
+    ctx: &Context<'_, RegisteredState>,
+    kind: RelayKind,
+    target: &str,
+    result: ChannelRouteResult,
+) -> HandlerResult {
+    if !kind.reports_errors() {
+        return Ok(());
+    }
+    let nick = &ctx.state.nick;
+    let reason = match result {
+        ChannelRouteResult::Sent => return Ok(()),
+        ChannelRouteResult::NoSuchChannel => return send_no_such_channel(ctx, nick, target).await,
+        ChannelRouteResult::BlockedExternal => CANNOT_SEND_NOT_IN_CHANNEL,
+        ChannelRouteResult::BlockedModerated => CANNOT_SEND_MODERATED,
+        ChannelRouteResult::BlockedRegisteredOnly => CANNOT_SEND_REGISTERED_ONLY,
+        ChannelRouteResult::BlockedRegisteredSpeak => CANNOT_SEND_REGISTERED_SPEAK,
+        ChannelRouteResult::BlockedCTCP => CANNOT_SEND_CTCP,
+        ChannelRouteResult::BlockedNotice => CANNOT_SEND_NOTICE,
+        ChannelRouteResult::BlockedBanned => CANNOT_SEND_BANNED,
+        ChannelRouteResult::BlockedQuiet => CANNOT_SEND_QUIET,
+        ChannelRouteResult::BlockedAntiCaps => CANNOT_SEND_ANTI_CAPS,
+        ChannelRouteResult::BlockedCensored => CANNOT_SEND_CENSORED,
+        ChannelRouteResult::BlockedFlood => CANNOT_SEND_FLOOD,
+    };
+    send_cannot_send(ctx, nick, target, reason).await
+}
+
+/// Tell the sender why a user did not get their message (nothing for NOTICE).
+///
+/// SILENCE and +T drops are silent for every command.
+pub async fn report_user_result(
+    ctx: &mut Context<'_, RegisteredState>,
+    kind: RelayKind,
+    target: &str,
+    result: UserRouteResult,
+) -> HandlerResult {
+    if result == UserRouteResult::BlockedCallerId {
+        return report_caller_id(ctx, kind, target).await;
+    }
+    if !kind.reports_errors() {
+        return Ok(());
+    }
+    match result {
+        UserRouteResult::NoSuchNick => send_no_such_nick(ctx, kind.name(), target).await?,
+        UserRouteResult::BlockedRegisteredOnly => {
+            let reply = Response::err_needreggednick(&ctx.state.nick, target);
+            ctx.sender.send(reply).await?;
+        }
+        UserRouteResult::Sent
+        | UserRouteResult::BlockedSilence
+        | UserRouteResult::BlockedCallerId
+        | UserRouteResult::BlockedCtcp => {}
+    }
+    Ok(())
+}
+
+/// Tell the sender the target has +g and, at most once a minute, tell the
+/// target who tried to reach them. NOTICE still notifies the target.
+async fn report_caller_id(
+    ctx: &Context<'_, RegisteredState>,
+    kind: RelayKind,
+    target: &str,
+) -> HandlerResult {
+    if kind.reports_errors() {
+        let reply = Response::err_targumodeg(&ctx.state.nick, target);
+        ctx.sender.send(reply).await?;
+    }
+    let users = &ctx.matrix.user_manager;
+    let Some(uid) = users.get_first_uid(&irc_to_lower(target)) else {
+        return Ok(());
+    };
+    if !users.caller_id_notify_due(&uid) {
+        return Ok(());
+    }
+    let (Some(target_user), Some(sender)) = (
+        users.users.get_cloned(&uid),
+        users.users.get_cloned(ctx.uid),
+    ) else {
+        return Ok(());
+    };
+    let target_nick = target_user.read().await.nick.clone();
+    let userhost = {
+        let sender = sender.read().await;
+        format!("{}@{}", sender.user, sender.visible_host)
+    };
+    let notice = Response::rpl_umodegmsg(&target_nick, &ctx.state.nick, &userhost)
+        .with_prefix(ctx.server_prefix());
+    users.send_to_uid(&uid, std::sync::Arc::new(notice)).await;
+    if kind.reports_errors() {
+        let reply = Response::rpl_targnotify(&ctx.state.nick, target);
+        ctx.sender.send(reply).await?;
+    }
+    Ok(())
+}
+
+/// Store a delivered message in the history of its channel or DM conversation.
+pub async fn store_relay_history(
+    ctx: &Context<'_, RegisteredState>,
+    kind: RelayKind,
+    stamp: &RelayStamp,
+    target: &str,
+    text: &str,
+    tags: &[Tag],
+    snapshot: &SenderSnapshot,
+) {
+    if target.is_channel_name() {
+        let key = irc_to_lower(target);
+        store_history(ctx, &key, kind, stamp, target, text, tags, snapshot).await;
+    } else {
+        store_dm_history(ctx, kind, stamp, target, text, tags, snapshot).await;
+    }
+}
+
+/// Store a delivered DM under the conversation's canonical key.
+async fn store_dm_history(
+    ctx: &Context<'_, RegisteredState>,
+    kind: RelayKind,
+    stamp: &RelayStamp,
+    target_nick: &str,
+    text: &str,
+    tags: &[Tag],
+    snapshot: &SenderSnapshot,
+) {
+    let dm_key = compute_dm_key(ctx, &irc_to_lower(target_nick), snapshot).await;
+    store_history(ctx, &dm_key, kind, stamp, target_nick, text, tags, snapshot).await;
+}
+
+/// Store a relayed message in history under `key`.
+///
+/// Client-only tags are kept; a TAGMSG is only stored when it asks for it
+/// with `+draft/persist`.
+#[allow(clippy::too_many_arguments)]
+async fn store_history(
+    ctx: &Context<'_, RegisteredState>,
+    key: &str,
+    kind: RelayKind,
+    stamp: &RelayStamp,
+    target: &str,
+    text: &str,
+    tags: &[Tag],
+    snapshot: &SenderSnapshot,
+) {
+    if !ctx.matrix.config.history.should_store_event(kind.name())
+        || (kind == RelayKind::Tagmsg && !tags.iter().any(|t| t.0 == "+draft/persist"))
+    {
+        return;
+    }
+    let history_tags: Vec<HistoryTag> = tags
+        .iter()
+        .filter(|t| t.0.starts_with('+'))
+        .map(|t| HistoryTag {
+            key: t.0.to_string(),
+            value: t.1.clone(),
+        })
+        .collect();
+
+    let stored_msg = StoredMessage {
+        msgid: stamp.msgid.clone(),
+        target: irc_to_lower(target),
+        sender: snapshot.nick.clone(),
+        envelope: MessageEnvelope {
+            command: kind.name().to_string(),
+            prefix: snapshot.full_mask(),
+            target: target.to_string(),
+            text: text.to_string(),
+            tags: (!history_tags.is_empty()).then_some(history_tags),
+        },
+        nanotime: stamp.nanotime,
+        account: snapshot.account.clone(),
+        status_prefix: None,
+    };
+    if let Err(e) = ctx
+        .matrix
+        .service_manager
+        .history
+        .store(key, stored_msg)
+        .await
+    {
+        debug!(error = %e, key = %key, "Failed to store {} in history", kind.name());
+    }
+}
+
+/// Compute the canonical DM key for history storage.
+/// Format: dm:user1:user2 (sorted alphabetically)
+/// Uses account name if available, otherwise nick, with prefix to avoid collisions.
+async fn compute_dm_key(
+    ctx: &Context<'_, RegisteredState>,
+    target_lower: &str,
+    snapshot: &SenderSnapshot,
+) -> String {
+    let sender_key_part = if let Some(acct) = &snapshot.account {
+        format!("a:{}", irc_to_lower(acct))
+    } else {
+        format!("u:{}", irc_to_lower(&snapshot.nick))
+    };
+
+    let target_account = if let Some(uid) = ctx.matrix.user_manager.get_first_uid(target_lower) {
+        if let Some(user_arc) = ctx.matrix.user_manager.users.get_cloned(&uid) {
+            let u = user_arc.read().await;
+            u.account.clone()
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    let target_key_part = if let Some(acct) = target_account {
+        format!("a:{}", irc_to_lower(&acct))
+    } else {
+        format!("u:{}", target_lower)
+    };
+
+    let mut users = [sender_key_part, target_key_part];
+    users.sort();
+    format!("dm:{}:{}", users[0], users[1])
+}
+
+/// Suppress labeled ACK if echo-message is enabled (the echo is the response).
+fn suppress_labeled_ack_if_echo(ctx: &mut Context<'_, RegisteredState>) {
+    if ctx.label.is_some() && ctx.state.capabilities.contains("echo-message") {
+        ctx.suppress_labeled_ack = true;
+    }
+}
+
+/// Parse STATUSMSG prefix from target.
+/// Returns (prefix_char, actual_channel_name) if STATUSMSG, otherwise (None, None).
+///
+/// STATUSMSG allows sending to channel members with specific privileges:
+/// - `~#channel` sends to owners
+/// - `&#channel` sends to admins+ (admin or owner)
+/// - `@#channel` sends to ops+ (op, admin, or owner)
+/// - `%#channel` sends to halfops+ (halfop, op, admin, or owner)
+/// - `+#channel` sends to voiced+ (voice, halfop, op, admin, or owner)
+fn parse_statusmsg(target: &str) -> (Option<char>, Option<&str>) {
+    if target.len() < 2 {
+        return (None, None);
+    }
+
+    let Some(first_char) = target.chars().next() else {
+        return (None, None);
+    };
+    let rest = &target[first_char.len_utf8()..];
+
+    // Check for valid STATUSMSG prefixes followed by a channel character
+    if matches!(first_char, '~' | '&' | '@' | '%' | '+')
+        && rest
+            .chars()
+            .next()
+            .map(|c| c == '#' || c == '&' || c == '+' || c == '!')
+            .unwrap_or(false)
+    {
+        (Some(first_char), Some(rest))
+    } else {
+        (None, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parse_statusmsg_at() {
+        let (prefix, target) = super::parse_statusmsg("@#channel");
+        assert_eq!(prefix, Some('@'));
+        assert_eq!(target, Some("#channel"));
+    }
+
+    #[test]
+    fn test_parse_statusmsg_plus() {
+        let (prefix, target) = super::parse_statusmsg("+#channel");
+        assert_eq!(prefix, Some('+'));
+        assert_eq!(target, Some("#channel"));
+    }
+
+    #[test]
+    fn test_parse_statusmsg_percent() {
+        let (prefix, target) = super::parse_statusmsg("%#channel");
+        assert_eq!(prefix, Some('%'));
+        assert_eq!(target, Some("#channel"));
+    }
+
+    #[test]
+    fn test_parse_statusmsg_tilde() {
+        let (prefix, target) = super::parse_statusmsg("~#channel");
+        assert_eq!(prefix, Some('~'));
+        assert_eq!(target, Some("#channel"));
+    }
+
+    #[test]
+    fn test_parse_statusmsg_ampersand() {
+        let (prefix, target) = super::parse_statusmsg("&#channel");
+        assert_eq!(prefix, Some('&'));
+        assert_eq!(target, Some("#channel"));
+    }
+
+    #[test]
+    fn test_parse_statusmsg_regular_channel() {
+        let (prefix, target) = super::parse_statusmsg("#channel");
+        assert_eq!(prefix, None);
+        assert_eq!(target, None);
+    }
+
+    #[test]
+    fn test_parse_statusmsg_user() {
+        let (prefix, target) = super::parse_statusmsg("nick");
+        assert_eq!(prefix, None);
+        assert_eq!(target, None);
+    }
+
+    #[test]
+    fn test_parse_statusmsg_empty() {
+        let (prefix, target) = super::parse_statusmsg("");
+        assert_eq!(prefix, None);
+        assert_eq!(target, None);
+    }
+
+    #[test]
+    fn test_parse_statusmsg_prefix_without_channel() {
+        let (prefix, target) = super::parse_statusmsg("@nick");
+        assert_eq!(prefix, None);
+        assert_eq!(target, None);
+    }
+
+    #[test]
+    fn test_parse_statusmsg_double_prefix() {
+        // @@#channel -> prefix '@', target '@#channel' (which is invalid channel name usually, but parse_statusmsg just splits)
+        // Wait, let's check implementation:
+        // if matches!(first_char, ...) && rest.chars().next().map(|c| c == '#' || c == '&' || c == '+' || c == '!').unwrap_or(false)
+        // So for @@#channel: first='@', rest='@#channel'. rest[0] is '@', which is NOT in ['#', '&', '+', '!'].
+        // So it should return (None, None).
+        let (prefix, target) = super::parse_statusmsg("@@#channel");
+        assert_eq!(prefix, None);
+        assert_eq!(target, None);
+    }
+}
@@ -9,11 +9,16 @@
 use super::delivery::build_local_recipient_message;
 // use super::delivery::{send_cannot_send};
 use super::multiclient::echo_to_other_sessions;
-use super::types::{ChannelRouteResult, RouteMeta, RouteOptions, SenderSnapshot, UserRouteResult};
+use super::types::{
+    AddressedTarget, ChannelRouteResult, RouteMeta, RouteOptions, SenderSnapshot, UserRouteResult,
+};
 use crate::handlers::core::Context;
 use crate::handlers::server_reply;
+use crate::state::dashmap_ext::DashMapExt;
+use crate::state::{Matrix, User};
+use crate::sync::TopologyGraph;
 use slirc_proto::ctcp::{Ctcp, CtcpKind};
-use slirc_proto::{Command, Message, Prefix, Response};
+use slirc_proto::{ChannelExt, Command, Message, Prefix, Response, irc_to_lower};
 use std::collections::HashSet;
 use std::sync::Arc;
 use tracing::debug;
@@ -30,16 +35,8 @@ pub async fn route_to_channel_with_snapshot(
     meta: RouteMeta,
     snapshot: &SenderSnapshot,
 ) -> ChannelRouteResult {
-    let RouteMeta {
-        timestamp,
-        msgid,
-        nanotime,
-        override_nick,
-        relaymsg_sender_nick,
-    } = meta;
-
-    let timestamp_clone = timestamp.clone();
-    let msgid_clone = msgid.clone();
+    let timestamp = meta.timestamp.clone();
+    let msgid = meta.msgid.clone();
 
     let channel_tx = ctx
         .matrix
@@ -51,43 +48,16 @@ pub async fn route_to_channel_with_snapshot(
         return ChannelRouteResult::NoSuchChannel;
     };
 
-    // Build UserContext from snapshot (no user lookup needed)
-    let user_context = snapshot.to_user_context(ctx.server_name());
-
-    // Extract text and tags from message
-    // TAGMSG has no text body, just tags
-    let (text, tags, is_tagmsg) = match &msg.command {
-        Command::PRIVMSG(_, text) | Command::NOTICE(_, text) => {
-            (text.clone(), msg.tags.clone(), false)
-        }
-        Command::TAGMSG(_) => (String::new(), msg.tags.clone(), true),
-        _ => return ChannelRouteResult::Sent, // Should not happen
-    };
-
-    let is_notice = matches!(msg.command, Command::NOTICE(_, _));
-
     // Send to actor
     let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
     let event = crate::state::actor::ChannelEvent::Message {
-        params: Box::new(crate::state::actor::ChannelMessageParams {
-            sender_uid: ctx.uid.to_string(),
-            sender_session_id: ctx.state.session_id,
-            text,
-            tags,
-            is_notice,
-            is_tagmsg,
-            user_context,
-            is_registered: snapshot.is_registered,
-            is_tls: ctx.state.is_tls,
-            is_bot: snapshot.is_bot,
-            status_prefix: opts.status_prefix,
-            timestamp,
-            msgid,
-            nanotime: nanotime
-                .unwrap_or_else(|| chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)),
-            override_nick,
-            relaymsg_sender_nick,
-        }),
+        params: Box::new(channel_message_params(
+            ctx,
+            &msg,
+            opts.status_prefix,
+            meta,
+            snapshot,
+        )),
         reply_tx,
     };
 
@@ -102,14 +72,176 @@ pub async fn route_to_channel_with_snapshot(
 
     if result == ChannelRouteResult::Sent {
         // Self-echo to other sessions (bouncer support)
-        if let (Some(ts), Some(mid)) = (&timestamp_clone, &msgid_clone) {
+        if let (Some(ts), Some(mid)) = (&timestamp, &msgid) {
             echo_to_other_sessions(ctx, &msg, snapshot, ts, mid).await;
         }
+        // Channel bots watch for badwords
+        if let Command::PRIVMSG(_, text) | Command::NOTICE(_, text) = &msg.command {
+            crate::services::botserv::on_message(ctx.matrix, channel_lower, ctx.uid, text).await;
+        }
     }
 
     result
 }
 
+/// Run a channel's send checks for a message without delivering it.
+///
+/// For multiline batches, which fan out themselves once allowed.
+pub async fn check_channel_send(
+    ctx: &Context<'_, crate::state::RegisteredState>,
+    channel_lower: &str,
+    msg: &Message,
+    snapshot: &SenderSnapshot,
+) -> ChannelRouteResult {
+    let Some(channel_tx) = ctx
+        .matrix
+        .channel_manager
+        .channels
+        .get_cloned(channel_lower)
+    else {
+        return ChannelRouteResult::NoSuchChannel;
+    };
+    let meta = RouteMeta {
+        timestamp: None,
+        msgid: None,
+        nanotime: None,
+        override_nick: None,
+        relaymsg_sender_nick: None,
+    };
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    let event = crate::state::actor::ChannelEvent::CheckMessage {
+        params: Box::new(channel_message_params(ctx, msg, None, meta, snapshot)),
+        reply_tx,
+    };
+    if channel_tx.send(event).await.is_err() {
+        return ChannelRouteResult::NoSuchChannel;
+    }
+    reply_rx.await.unwrap_or(ChannelRouteResult::NoSuchChannel)
+}
+
+/// Describe a PRIVMSG/NOTICE/TAGMSG from the sender to a channel actor.
+fn channel_message_params(
+    ctx: &Context<'_, crate::state::RegisteredState>,
+    msg: &Message,
+    status_prefix: Option<char>,
+    meta: RouteMeta,
+    snapshot: &SenderSnapshot,
+) -> crate::state::actor::ChannelMessageParams {
+    // TAGMSG has no text body, just tags
+    let (text, is_notice, is_tagmsg) = match &msg.command {
+        Command::PRIVMSG(_, text) => (text.clone(), false, false),
+        Command::NOTICE(_, text) => (text.clone(), true, false),
+        _ => (String::new(), false, true),
+    };
+    crate::state::actor::ChannelMessageParams {
+        sender_uid: ctx.uid.to_string(),
+        sender_session_id: ctx.state.session_id,
+        text,
+        tags: msg.tags.clone(),
+        is_notice,
+        is_tagmsg,
+        // Build UserContext from snapshot (no user lookup needed)
+        user_context: snapshot.to_user_context(ctx.server_name()),
+        is_registered: snapshot.is_registered,
+        is_tls: ctx.state.is_tls,
+        is_bot: snapshot.is_bot,
+        status_prefix,
+        timestamp: meta.timestamp,
+        msgid: meta.msgid,
+        nanotime: meta
+            .nanotime
+            .unwrap_or_else(|| chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)),
+        override_nick: meta.override_nick,
+        relaymsg_sender_nick: meta.relaymsg_sender_nick,
+    }
+}
+
+/// Resolve an RFC 2812 addressed target to the nick it names.
+///
+/// `nick@server` picks `nick` only if it is connected to `server`;
+/// `user%host@server` and `user%host` match username and (real or visible)
+/// host, on `server` when given. Anything else is [`AddressedTarget::Plain`].
+pub async fn resolve_addressed_target(matrix: &Matrix, target: &str) -> AddressedTarget {
+    if target.is_channel_name() || target.contains('!') {
+        return AddressedTarget::Plain;
+    }
+    let (local, server) = match target.rsplit_once('@') {
+        Some((local, server)) => (local, Some(server)),
+        None => (target, None),
+    };
+    let userhost = local.split_once('%');
+    if local.is_empty() || server == Some("") || (server.is_none() && userhost.is_none()) {
+        return AddressedTarget::Plain;
+    }
+
+    // Server name -> SID
+    let sid = match server {
+        None => None,
+        Some(name) if name.eq_ignore_ascii_case(&matrix.server_info.name) => {
+            Some(matrix.server_info.sid.clone())
+        }
+        Some(name) => match matrix
+            .sync_manager
+            .topology
+            .servers
+            .iter()
+            .find(|s| s.name.eq_ignore_ascii_case(name))
+        {
+            Some(s) => Some(s.sid.as_str().to_string()),
+            None => return AddressedTarget::NoSuchNick,
+        },
+    };
+    let on_server = |uid: &str| match &sid {
+        Some(sid) => TopologyGraph::origin_of(uid).is_some_and(|o| o.as_str() == sid),
+        None => true,
+    };
+
+    let Some((user, host)) = userhost else {
+        // nick@server
+        let Some(uid) = matrix
+            .user_manager
+            .nicks
+            .get(&irc_to_lower(local))
+            .and_then(|uids| uids.iter().find(|uid| on_server(uid)).cloned())
+        else {
+            return AddressedTarget::NoSuchNick;
+        };
+        let Some(user_arc) = matrix
+            .user_manager
+            .users
+            .get(&uid)
+            .map(|u| u.value().clone())
+        else {
+            return AddressedTarget::NoSuchNick;
+        };
+        return AddressedTarget::Nick(user_arc.read().await.nick.clone());
+    };
+
+    // user%host[@server]
+    let candidates: Vec<_> = matrix
+        .user_manager
+        .users
+        .iter()
+        .filter(|e| on_server(e.key()))
+        .map(|e| e.value().clone())
+        .collect();
+    let mut nicks: Vec<String> = Vec::new();
+    for user_arc in candidates {
+        let u = user_arc.read().await;
+        if u.user.eq_ignore_ascii_case(user)
+            && (u.host.eq_ignore_ascii_case(host) || u.visible_host.eq_ignore_ascii_case(host))
+            && !nicks.contains(&u.nick)
+        {
+            nicks.push(u.nick.clone());
+        }
+    }
+    match nicks.len() {
+        0 => AddressedTarget::NoSuchNick,
+        1 => AddressedTarget::Nick(nicks.remove(0)),
+        _ => AddressedTarget::Ambiguous,
+    }
+}
+
 /// Route a message to a user target using pre-fetched snapshot, optionally sending RPL_AWAY.
 ///
 /// This is the optimized version that eliminates redundant sender lookups.
@@ -149,6 +281,8 @@ pub async fn route_to_user_with_snapshot(
     let mut delivered_local: HashSet<String> = HashSet::new();
     let mut blocked_by_regged_only = false;
     let mut blocked_by_silence = false;
+    let mut blocked_by_caller_id = false;
+    let is_tagmsg = matches!(msg.command, Command::TAGMSG(_));
 
     // Precompute msgid/time once for this fan-out
     let timestamp_str = timestamp.clone().unwrap_or_else(|| {
@@ -186,79 +320,28 @@ pub async fn route_to_user_with_snapshot(
             }
         }
 
-        // Check +R (registered-only PMs) - target only accepts PMs from identified users
-        let target_user_arc = ctx
-            .matrix
-            .user_manager
-            .users
-            .get(target_uid)
-            .map(|u| u.value().clone());
+        let target_user_arc = ctx.matrix.user_manager.users.get_cloned(target_uid);
         if let Some(target_user_arc) = target_user_arc {
-            let target_user = target_user_arc.read().await;
-            debug!(
-                "Checking +R for target {}: registered_only={}, sender_registered={}",
-                target_user.nick, target_user.modes.registered_only, snapshot.is_registered
-            );
-            if target_user.modes.registered_only {
-                // Use pre-fetched registered status from snapshot
-                if !snapshot.is_registered {
-                    // Check ACCEPT list (Caller ID) override
-                    // If sender is in accept list, allow the message even if not registered
-                    let sender_nick_lower = slirc_proto::irc_to_lower(&snapshot.nick);
-                    if !target_user.accept_list.contains(&sender_nick_lower) {
-                        debug!("Blocked by +R for UID {}", target_uid);
-                        blocked_by_regged_only = true;
-                        continue; // Skip this UID
-                    }
-                }
-            }
-
-            // Check SILENCE list using pre-fetched sender mask from snapshot
-            if !target_user.silence_list.is_empty() {
-                let sender_mask = snapshot.full_mask();
-
-                let mut is_locally_silenced = false;
-                for silence_mask in &target_user.silence_list {
-                    if crate::handlers::matches_hostmask(silence_mask, &sender_mask) {
-                        // Silently drop the message
-                        debug!(
-                            target = %target_user.nick,
-                            sender = %sender_mask,
-                            mask = %silence_mask,
-                            "Message blocked by SILENCE"
-                        );
-                        is_locally_silenced = true;
-                        break;
-                    }
+            let text = match &msg.command {
+                Command::PRIVMSG(_, text) | Command::NOTICE(_, text) => Some(text.as_str()),
+                _ => None,
+            };
+            match user_refuses_message(&*target_user_arc.read().await, snapshot, text) {
+                Some(UserRouteResult::BlockedRegisteredOnly) => {
+                    debug!("Blocked by +R for UID {}", target_uid);
+                    blocked_by_regged_only = true;
+                    continue; // Skip this UID
                 }
-                if is_locally_silenced {
+                Some(UserRouteResult::BlockedSilence) => {
                     blocked_by_silence = true;
                     continue; // Skip this UID
                 }
-            }
-
-            // Check +T (no CTCP) - block CTCP messages except ACTION
-            if target_user.modes.no_ctcp {
-                // Extract text from command to check for CTCP
-                let text = match &msg.command {
-                    Command::PRIVMSG(_, text) | Command::NOTICE(_, text) => Some(text.as_str()),
-                    _ => None,
-                };
-                if let Some(text) = text
-                    && Ctcp::is_ctcp(text)
-                {
-                    // Check if it's an ACTION (allowed even with +T)
-                    if let Some(ctcp) = Ctcp::parse(text)
-                        && !matches!(ctcp.kind, CtcpKind::Action)
-                    {
-                        debug!(
-                            target = %target_user.nick,
-                            ctcp_type = ?ctcp.kind,
-                            "CTCP blocked by +T mode"
-                        );
-                        continue; // Skip this UID
-                    }
+                Some(UserRouteResult::BlockedCallerId) => {
+                    blocked_by_caller_id = true;
+                    continue; // Skip this UID
                 }
+                Some(_) => continue,
+                None => {}
             }
         }
 
@@ -312,6 +395,13 @@ pub async fn route_to_user_with_snapshot(
                         .user_manager
                         .get_session_caps(sess.session_id)
                         .unwrap_or_default();
+                    // Sessions without message-tags never see TAGMSG, but it
+                    // still reached the user as far as the sender is concerned
+                    if is_tagmsg && !caps.contains("message-tags") {
+                        any_sent = true;
+                        sent_count += 1;
+                        continue;
+                    }
                     let msg_for_target = build_local_recipient_message(
                         &msg,
                         &caps,
@@ -465,7 +555,73 @@ pub async fn route_to_user_with_snapshot(
         UserRouteResult::BlockedRegisteredOnly
     } else if blocked_by_silence {
         UserRouteResult::BlockedSilence
+    } else if blocked_by_caller_id {
+        UserRouteResult::BlockedCallerId
     } else {
         UserRouteResult::NoSuchNick
     }
 }
+
+/// Check whether a user's own settings refuse a message from the sender.
+///
+/// Covers +R (unless the sender is on their ACCEPT list), SILENCE, +g (unless
+/// the sender is accepted or an IRC operator), and +T for CTCP other than
+/// ACTION. `text` is `None` for TAGMSG.
+pub fn user_refuses_message(
+    target_user: &User,
+    snapshot: &SenderSnapshot,
+    text: Option<&str>,
+) -> Option<UserRouteResult> {
+    // Check +R (registered-only PMs) - target only accepts PMs from identified users
+    if target_user.modes.registered_only
+        && !snapshot.is_registered
+        && !target_user
+            .accept_list
+            .contains(&irc_to_lower(&snapshot.nick))
+    {
+        return Some(UserRouteResult::BlockedRegisteredOnly);
+    }
+
+    // Check SILENCE list
+    let sender_mask = snapshot.full_mask();
+    if let Some(silence_mask) = target_user
+        .silence_list
+        .iter()
+        .find(|mask| crate::handlers::matches_hostmask(mask, &sender_mask))
+    {
+        debug!(
+            target = %target_user.nick,
+            sender = %sender_mask,
+            mask = %silence_mask,
+            "Message blocked by SILENCE"
+        );
+        return Some(UserRouteResult::BlockedSilence);
+    }
+
+    // Check +g (caller ID) - only accepted users may message the target
+    if target_user.modes.caller_id
+        && !snapshot.is_oper
+        && !target_user
+            .accept_list
+            .contains(&irc_to_lower(&snapshot.nick))
+    {
+        return Some(UserRouteResult::BlockedCallerId);
+    }
+
+    // Check +T (no CTCP) - block CTCP messages except ACTION
+    if target_user.modes.no_ctcp
+        && let Some(text) = text
+        && Ctcp::is_ctcp(text)
+        && let Some(ctcp) = Ctcp::parse(text)
+        && !matches!(ctcp.kind, CtcpKind::Action)
+    {
+        debug!(
+            target = %target_user.nick,
+            ctcp_type = ?ctcp.kind,
+            "CTCP blocked by +T mode"
+        );
+        return Some(UserRouteResult::BlockedCtcp);
+    }
+
+    None
+}
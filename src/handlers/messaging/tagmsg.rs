@@ -1,23 +1,11 @@
 //! Handler for TAGMSG command.
 
-use super::super::{HandlerError, HandlerResult, user_prefix};
-use super::delivery::{send_cannot_send, send_no_such_channel};
-use super::routing::{route_to_channel_with_snapshot, route_to_user_with_snapshot};
-use super::types::{
-    ChannelRouteResult, RouteMeta, RouteOptions, SenderSnapshot, UserRouteResult,
-    is_shunned_with_snapshot,
-};
-use crate::history::types::MessageTag as HistoryTag;
-use crate::history::{MessageEnvelope, StoredMessage};
-use crate::state::dashmap_ext::DashMapExt;
+use super::super::{HandlerError, HandlerResult};
+use super::relay::{Relay, RelayKind, relay_to_target};
+use super::types::{SenderSnapshot, is_shunned_with_snapshot};
 use async_trait::async_trait;
-use slirc_proto::{ChannelExt, Command, Message, MessageRef, Tag, irc_to_lower};
-use std::borrow::Cow;
-use std::time::{SystemTime, UNIX_EPOCH};
+use slirc_proto::MessageRef;
 use tracing::debug;
-use uuid::Uuid;
-
-use super::errors::*;
 
 /// Handler for TAGMSG command.
 ///
@@ -32,8 +20,6 @@ impl crate::handlers::core::traits::PostRegHandler for TagmsgHandler {
         ctx: &mut crate::handlers::core::context::Context<'_, crate::state::RegisteredState>,
         msg: &MessageRef<'_>,
     ) -> HandlerResult {
-        // Registration check removed - handled by registry typestate dispatch (Innovation 1)
-
         // Check shun first - silently ignore if shunned
         // Build snapshot first for shun check
         let snapshot = SenderSnapshot::build(ctx)
@@ -57,9 +43,6 @@ impl crate::handlers::core::traits::PostRegHandler for TagmsgHandler {
             return Err(HandlerError::NeedMoreParams);
         }
 
-        // Check for +draft/persist tag (required for history storage per IRCv3)
-        let has_persist_tag = msg.tags_iter().any(|(k, _)| k == "+draft/persist");
-
         // Check for +draft/read-marker tag (Unified Read State)
         // Format: +draft/read-marker=2024-01-01T12:00:00.000Z
         if let Some((_, ts_val)) = msg.tags_iter().find(|(k, _)| *k == "+draft/read-marker")
@@ -72,237 +55,110 @@ impl crate::handlers::core::traits::PostRegHandler for TagmsgHandler {
                 let nanotime = dt.timestamp_nanos_opt().unwrap_or(0);
                 ctx.matrix
                     .read_marker_manager
-                    .update_marker(account, target, nanotime);
+                    .update_marker(account, target, nanotime)
+                    .await;
                 debug!(account = %account, target = %target, ts = %nanotime, "Updated read marker");
             }
         }
 
-        // Generate msgid for history storage and echo-message (with dashes for IRCv3 compatibility)
-        let msgid = Uuid::new_v4().to_string();
-        let nanotime = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
-
-        // Collect only client-only tags (those starting with '+') AND the label tag
-        // Server should not relay arbitrary tags from clients
-        // The label tag is needed for labeled-response echoes
-        // Unescape tag values since they come from wire format
-        // Also track which tags to persist for history
-        let mut persist_tags: Vec<(String, Option<String>)> = Vec::with_capacity(4);
-        let tags: Option<Vec<Tag>> = if msg.tags.is_some() {
-            let client_tags: Vec<Tag> = msg
-                .tags_iter()
-                .filter(|(k, _)| k.starts_with('+') || *k == "label")
-                .map(|(k, v)| {
-                    let value = if v.is_empty() {
-                        None
-                    } else {
-                        // Unescape tag value from wire format
-                        Some(slirc_proto::message::tags::unescape_tag_value(v))
-                    };
-                    // Track client-only tags for persistence (not label)
-                    if k.starts_with('+') {
-                        persist_tags.push((k.to_string(), value.clone()));
-                    }
-                    Tag(Cow::Owned(k.to_string()), value)
-                })
-                .collect();
-            if client_tags.is_empty() {
-                None
-            } else {
-                Some(client_tags)
-            }
-        } else {
-            None
-        };
+        // DMs are only stored in history with +draft/persist
+        let relay = Relay::new(
+            RelayKind::Tagmsg,
+            msg,
+            "",
+            &snapshot,
+            &ctx.matrix.config.client_tags,
+        );
+        relay_to_target(ctx, &relay, target).await
+    }
+}
 
-        // Add msgid tag for outgoing message
-        let mut out_tags = tags.unwrap_or_default();
-        out_tags.push(Tag(Cow::Borrowed("msgid"), Some(msgid.clone())));
-        let tags = Some(out_tags);
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{Command, Response};
 
-        // Build the outgoing TAGMSG using snapshot
-        let out_msg = Message {
-            tags,
-            prefix: Some(user_prefix(
-                &snapshot.nick,
-                &snapshot.user,
-                &snapshot.visible_host,
-            )),
-            command: Command::TAGMSG(target.to_string()),
-        };
+    #[tokio::test]
+    async fn tagmsg_dm_has_one_msgid() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").cap("message-tags").build().await;
+        let mut bob = tm.user("bob").cap("message-tags").build().await;
 
-        // TAGMSG: send errors, but don't check +m (only +n), no away reply
-        let opts = RouteOptions {
-            send_away_reply: false,
-            status_prefix: None,
-        };
+        tm.run(&mut alice, "@+typing=active TAGMSG bob").await;
+        let tagmsg = bob
+            .recv_matching(|m| matches!(m.command, Command::TAGMSG(..)))
+            .await
+            .expect("bob gets the TAGMSG");
+        let tags = tagmsg.tags.unwrap_or_default();
+        assert_eq!(tags.iter().filter(|t| t.0 == "msgid").count(), 1);
+        assert!(tags.iter().any(|t| t.0 == "+typing"));
+    }
 
-        if target.is_channel_name() {
-            let channel_lower = irc_to_lower(target);
-            // Pass msgid to route function to ensure consistency between echo and history
-            match route_to_channel_with_snapshot(
-                ctx,
-                &channel_lower,
-                out_msg,
-                &opts,
-                RouteMeta {
-                    timestamp: None,
-                    msgid: Some(msgid.clone()),
-                    nanotime: Some(nanotime),
-                    override_nick: None,
-                    relaymsg_sender_nick: None,
-                },
-                &snapshot,
-            )
+    #[tokio::test]
+    async fn dm_only_tags_skip_channels_and_unknown_tags_are_stripped() {
+        let tm = TestMatrixBuilder::new()
+            .config(|c| c.client_tags.dm_allow.push("+draft/e2ee*".to_string()))
+            .build()
+            .await;
+        let mut alice = tm.user("alice").cap("message-tags").build().await;
+        let mut bob = tm.user("bob").cap("message-tags").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.run(&mut bob, "JOIN #chan").await;
+        bob.drain();
+
+        tm.run(
+            &mut alice,
+            "@+draft/e2ee=blob;+example/x=1;+typing=active TAGMSG bob",
+        )
+        .await;
+        let dm = bob
+            .recv_matching(|m| matches!(&m.command, Command::TAGMSG(t) if t == "bob"))
             .await
-            {
-                ChannelRouteResult::Sent => {
-                    debug!(from = %snapshot.nick, to = %target, "TAGMSG to channel");
-                    // Suppress ACK for echo-message with labels (echo IS the response)
-                    if ctx.label.is_some() && ctx.state.capabilities.contains("echo-message") {
-                        ctx.suppress_labeled_ack = true;
-                    }
-                }
-                ChannelRouteResult::NoSuchChannel => {
-                    send_no_such_channel(ctx, &snapshot.nick, target).await?;
-                }
-                ChannelRouteResult::BlockedExternal => {
-                    send_cannot_send(ctx, &snapshot.nick, target, CANNOT_SEND_NOT_IN_CHANNEL)
-                        .await?;
-                }
-                ChannelRouteResult::BlockedModerated => {
-                    // TAGMSG doesn't check +m, so this shouldn't happen
-                    unreachable!("TAGMSG should not check moderated mode");
-                }
-                ChannelRouteResult::BlockedRegisteredOnly => {
-                    send_cannot_send(ctx, &snapshot.nick, target, CANNOT_SEND_REGISTERED_ONLY)
-                        .await?;
-                }
-                ChannelRouteResult::BlockedRegisteredSpeak => {
-                    send_cannot_send(ctx, &snapshot.nick, target, CANNOT_SEND_REGISTERED_SPEAK)
-                        .await?;
-                }
-                ChannelRouteResult::BlockedCTCP => {
-                    // TAGMSG has no CTCP, so this shouldn't happen
-                    unreachable!("TAGMSG has no CTCP content");
-                }
-                ChannelRouteResult::BlockedNotice => {
-                    // TAGMSG is not a NOTICE, so this shouldn't happen
-                    unreachable!("TAGMSG is not a NOTICE");
-                }
-                ChannelRouteResult::BlockedBanned => {
-                    send_cannot_send(ctx, &snapshot.nick, target, CANNOT_SEND_BANNED).await?;
-                }
-                ChannelRouteResult::BlockedAntiCaps => {
-                    send_cannot_send(ctx, &snapshot.nick, target, CANNOT_SEND_ANTI_CAPS).await?;
-                }
-                ChannelRouteResult::BlockedCensored => {
-                    send_cannot_send(ctx, &snapshot.nick, target, CANNOT_SEND_CENSORED).await?;
-                }
-            }
-        } else {
-            let target_lower = irc_to_lower(target);
-            // Pass msgid to route function to ensure consistency between echo and history
-            if route_to_user_with_snapshot(
-                ctx,
-                &target_lower,
-                out_msg,
-                &opts,
-                None,
-                Some(msgid.clone()),
-                &snapshot,
-            )
+            .expect("bob gets the DM");
+        assert_eq!(dm.tag_value("+draft/e2ee"), Some("blob"));
+        assert_eq!(dm.tag_value("+example/x"), None);
+
+        tm.run(&mut alice, "@+draft/e2ee=blob;+typing=active TAGMSG #chan")
+            .await;
+        let chan = bob
+            .recv_matching(|m| matches!(&m.command, Command::TAGMSG(t) if t == "#chan"))
             .await
-                == UserRouteResult::Sent
-            {
-                debug!(from = %snapshot.nick, to = %target, "TAGMSG to user");
-
-                // Store TAGMSG in history for DMs if +draft/persist tag is present (Innovation 5)
-                if has_persist_tag && ctx.matrix.config.history.should_store_event("TAGMSG") {
-                    let prefix = format!(
-                        "{}!{}@{}",
-                        snapshot.nick, snapshot.user, snapshot.visible_host
-                    );
-                    let nanotime = SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_nanos() as i64;
-
-                    let history_tags: Option<Vec<HistoryTag>> = if !persist_tags.is_empty() {
-                        Some(
-                            persist_tags
-                                .iter()
-                                .map(|t| HistoryTag {
-                                    key: t.0.to_string(),
-                                    value: t.1.clone(),
-                                })
-                                .collect(),
-                        )
-                    } else {
-                        None
-                    };
-
-                    let envelope = MessageEnvelope {
-                        command: "TAGMSG".to_string(),
-                        prefix: prefix.clone(),
-                        target: target.to_string(),
-                        text: String::new(),
-                        tags: history_tags,
-                    };
-
-                    let stored_msg = StoredMessage {
-                        msgid: msgid.clone(),
-                        target: target_lower.clone(),
-                        sender: snapshot.nick.clone(),
-                        envelope,
-                        nanotime,
-                        account: ctx.state.account.clone(),
-                        status_prefix: None,
-                    };
-
-                    // Build DM key (same pattern as PRIVMSG)
-                    let sender_key_part = if let Some(acct) = &snapshot.account {
-                        format!("a:{}", irc_to_lower(acct))
-                    } else {
-                        format!("u:{}", irc_to_lower(&snapshot.nick))
-                    };
-
-                    let target_account =
-                        if let Some(uid) = ctx.matrix.user_manager.get_first_uid(&target_lower) {
-                            if let Some(user_arc) = ctx.matrix.user_manager.users.get_cloned(&uid) {
-                                let u = user_arc.read().await;
-                                u.account.clone()
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
-                        };
-
-                    let target_key_part = if let Some(acct) = target_account {
-                        format!("a:{}", irc_to_lower(&acct))
-                    } else {
-                        format!("u:{}", target_lower)
-                    };
-
-                    let mut users = [sender_key_part, target_key_part];
-                    users.sort();
-                    let dm_key = format!("dm:{}:{}", users[0], users[1]);
+            .expect("bob gets the channel TAGMSG");
+        assert_eq!(chan.tag_value("+draft/e2ee"), None);
+        assert_eq!(chan.tag_value("+typing"), Some("active"));
+    }
 
-                    if let Err(e) = ctx
-                        .matrix
-                        .service_manager
-                        .history
-                        .store(&dm_key, stored_msg)
-                        .await
-                    {
-                        debug!(error = %e, "Failed to store TAGMSG DM in history");
-                    }
-                }
-            } else {
-                crate::handlers::send_no_such_nick(ctx, "TAGMSG", target).await?;
-            }
-        }
+    #[tokio::test]
+    async fn tagmsg_to_registered_only_user_says_why() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").cap("message-tags").build().await;
+        let mut bob = tm.user("bob").cap("message-tags").build().await;
+        tm.run(&mut bob, "MODE bob +R").await;
+
+        let replies = tm.run(&mut alice, "@+typing=active TAGMSG bob").await;
+        assert!(replies.iter().any(|m| matches!(
+            &m.command,
+            Command::Response(Response::ERR_NEEDREGGEDNICK, _)
+        )));
+        assert!(bob.drain().is_empty());
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn tagmsg_from_quieted_member_says_why() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").cap("message-tags").build().await;
+        let mut bob = tm.user("bob").cap("message-tags").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.run(&mut bob, "JOIN #chan").await;
+        tm.run(&mut alice, "MODE #chan +q bob!*@*").await;
+        alice.drain();
+
+        let replies = tm.run(&mut bob, "@+typing=active TAGMSG #chan").await;
+        assert!(replies.iter().any(|m| matches!(
+            &m.command,
+            Command::Response(Response::ERR_CANNOTSENDTOCHAN, args)
+                if args.last().is_some_and(|r| r.contains("+q"))
+        )));
+        assert!(alice.drain().is_empty());
     }
 }
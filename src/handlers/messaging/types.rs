@@ -99,7 +99,6 @@ pub use crate::state::actor::ChannelRouteResult;
 
 /// Result of attempting to route a message to a user.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[allow(dead_code)] // Some variants used for internal flow control
 pub enum UserRouteResult {
     /// Message was successfully sent (or queued).
     Sent,
@@ -109,10 +108,25 @@ pub enum UserRouteResult {
     BlockedRegisteredOnly,
     /// Blocked by SILENCE list.
     BlockedSilence,
+    /// Blocked by +g (caller ID): the sender is not on the target's ACCEPT list.
+    BlockedCallerId,
     /// Blocked by +T (no CTCP).
     BlockedCtcp,
 }
 
+/// Result of resolving an RFC 2812 `nick@server` or `user%host[@server]` target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressedTarget {
+    /// Not an addressed target; route it as given.
+    Plain,
+    /// Exactly one user matched; route to this nick.
+    Nick(String),
+    /// Unknown server, or no user on it matches.
+    NoSuchNick,
+    /// More than one user matches the `user%host` mask.
+    Ambiguous,
+}
+
 /// Options for message routing behavior.
 pub struct RouteOptions {
     /// Whether to send RPL_AWAY for user targets (only PRIVMSG).
@@ -56,11 +56,13 @@ pub use util::helpers::{
 };
 
 // Re-export types used by other modules
+pub(crate) use bans::{BanType, disconnect_matching_ban, format_duration, parse_duration};
 pub use batch::{BatchState, process_batch_message};
 pub use cap::SaslState;
-pub use channel::{TargetUser, force_join_channel, force_part_channel};
+pub use channel::{TargetUser, force_join_channel, force_join_local, force_part_channel};
 pub use connection::WelcomeBurstWriter;
-pub use mode::{apply_user_modes_typed, format_modes_for_log};
+pub use mode::{apply_user_modes_typed, format_modes_for_log, record_user_mode_stats};
+pub(crate) use oper::is_valid_hostname;
 pub use user::monitor::{
     cleanup_monitors, notify_extended_monitor_watchers, notify_monitors_offline,
     notify_monitors_online,
@@ -223,9 +223,11 @@ async fn validate_flood_mode(
         return Ok(ModeValidation::NoArg);
     };
 
-    // Check format: lines:seconds
-    let parts: Vec<&str> = param.split(':').collect();
-    if parts.len() != 2 || parts[0].parse::<u32>().is_err() || parts[1].parse::<u32>().is_err() {
+    // Check format: lines[m|j]:seconds[:action], comma-separated
+    let valid = param
+        .split(',')
+        .all(|part| part.parse::<crate::state::actor::FloodParam>().is_ok());
+    if !valid {
         let reply = server_reply(
             ctx.server_name(),
             Response::ERR_INVALIDMODEPARAM,
@@ -234,7 +236,75 @@ async fn validate_flood_mode(
                 canonical_name.to_string(),
                 "f".to_string(),
                 param.to_string(),
-                "Invalid flood parameter (format: lines:seconds)".to_string(),
+                "Invalid flood parameter (format: lines:seconds[:kick|ban|mute])".to_string(),
+            ],
+        );
+        ctx.sender.send(reply).await?;
+        return Ok(ModeValidation::Invalid);
+    }
+
+    Ok(ModeValidation::Valid)
+}
+
+/// Validate a channel join throttle mode (`+j joins:seconds`).
+async fn validate_join_throttle_mode(
+    ctx: &mut Context<'_, RegisteredState>,
+    mode: &Mode<ChannelMode>,
+    nick: &str,
+    canonical_name: &str,
+) -> Result<ModeValidation, HandlerError> {
+    if !mode.is_plus() {
+        return Ok(ModeValidation::Valid);
+    }
+
+    let Some(param) = mode.arg() else {
+        return Ok(ModeValidation::NoArg);
+    };
+
+    if crate::state::actor::parse_join_throttle(param).is_none() {
+        let reply = server_reply(
+            ctx.server_name(),
+            Response::ERR_INVALIDMODEPARAM,
+            vec![
+                nick.to_string(),
+                canonical_name.to_string(),
+                "j".to_string(),
+                param.to_string(),
+                "Invalid join throttle (format: joins:seconds)".to_string(),
+            ],
+        );
+        ctx.sender.send(reply).await?;
+        return Ok(ModeValidation::Invalid);
+    }
+
+    Ok(ModeValidation::Valid)
+}
+
+/// Validate a channel history replay mode (`+H lines:minutes`).
+async fn validate_history_replay_mode(
+    ctx: &mut Context<'_, RegisteredState>,
+    mode: &Mode<ChannelMode>,
+    nick: &str,
+    canonical_name: &str,
+) -> Result<ModeValidation, HandlerError> {
+    if !mode.is_plus() {
+        return Ok(ModeValidation::Valid);
+    }
+
+    let Some(param) = mode.arg() else {
+        return Ok(ModeValidation::NoArg);
+    };
+
+    if crate::state::actor::parse_history_replay(param).is_none() {
+        let reply = server_reply(
+            ctx.server_name(),
+            Response::ERR_INVALIDMODEPARAM,
+            vec![
+                nick.to_string(),
+                canonical_name.to_string(),
+                "H".to_string(),
+                param.to_string(),
+                "Invalid history replay (format: lines:minutes, at most 100 lines)".to_string(),
             ],
         );
         ctx.sender.send(reply).await?;
@@ -249,6 +319,7 @@ pub async fn handle_channel_mode(
     ctx: &mut Context<'_, RegisteredState>,
     channel_name: &str,
     modes: &[Mode<ChannelMode>],
+    list_expires_at: Option<i64>,
 ) -> HandlerResult {
     let nick = ctx.nick().to_string();
     let _user_name = ctx.user();
@@ -374,6 +445,12 @@ pub async fn handle_channel_mode(
                 ChannelMode::Flood => {
                     validate_flood_mode(ctx, mode, &nick, &canonical_name).await?
                 }
+                ChannelMode::JoinThrottle => {
+                    validate_join_throttle_mode(ctx, mode, &nick, &canonical_name).await?
+                }
+                ChannelMode::HistoryReplay => {
+                    validate_history_replay_mode(ctx, mode, &nick, &canonical_name).await?
+                }
                 ChannelMode::Redirect => {
                     validate_channel_target_mode(ctx, mode, &nick, &canonical_name, 'L').await?
                 }
@@ -448,6 +525,7 @@ pub async fn handle_channel_mode(
                         target_uids,
                         force: false,
                         nanotime: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+                        list_expires_at,
                     },
                     reply_tx,
                 })
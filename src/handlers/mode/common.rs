@@ -10,6 +10,25 @@ pub fn is_channel_target(target: &str) -> bool {
     matches!(target.chars().next(), Some('#' | '&' | '+' | '!'))
 }
 
+/// Unix time `duration` (`30`, `30m`, `1h30m`) from now, for a timed list entry.
+pub fn list_expiry(duration: &str) -> Option<i64> {
+    let secs = slirc_proto::units::parse_duration(duration).ok()?.as_secs();
+    let secs = i64::try_from(secs).ok().filter(|&secs| secs > 0)?;
+    chrono::Utc::now().timestamp().checked_add(secs)
+}
+
+/// Strip a trailing `~<duration>` (`MODE #chan +b mask :~30m`) from the mode
+/// arguments, returning when the list entries it adds expire.
+pub fn take_list_expiry(mode_args: &mut Vec<&str>) -> Option<i64> {
+    // The flags and at least one mask come before the duration
+    if mode_args.len() < 3 {
+        return None;
+    }
+    let expires_at = list_expiry(mode_args.last()?.strip_prefix('~')?)?;
+    mode_args.pop();
+    Some(expires_at)
+}
+
 /// Parse channel modes from arguments, sending errors to client on failure.
 pub async fn parse_channel_modes(
     ctx: &mut Context<'_, RegisteredState>,
@@ -77,4 +96,20 @@ mod tests {
         assert!(!is_channel_target("user"));
         assert!(!is_channel_target(""));
     }
+
+    #[test]
+    fn test_take_list_expiry() {
+        let now = chrono::Utc::now().timestamp();
+        let mut args = vec!["+b", "*!*@spam", "~30m"];
+        let expires_at = take_list_expiry(&mut args).expect("timed ban");
+        assert!((now + 1800..=now + 1801).contains(&expires_at));
+        assert_eq!(args, ["+b", "*!*@spam"]);
+
+        // No mask before it, or not a duration: left for the mode parser
+        let mut args = vec!["+b", "~30m"];
+        assert_eq!(take_list_expiry(&mut args), None);
+        let mut args = vec!["+b", "*!*@spam", "~soon"];
+        assert_eq!(take_list_expiry(&mut args), None);
+        assert_eq!(args.len(), 3);
+    }
 }
@@ -7,10 +7,12 @@
 
 mod channel;
 mod common;
+mod tban;
 pub mod user;
 
 pub use channel::format_modes_for_log;
-pub use user::apply_user_modes_typed;
+pub use tban::TbanHandler;
+pub use user::{apply_user_modes_typed, record_user_mode_stats};
 
 use super::{Context, HandlerError, HandlerResult, PostRegHandler};
 use crate::state::RegisteredState;
@@ -41,9 +43,10 @@ impl PostRegHandler for ModeHandler {
             // Determine if this is a user or channel mode based on target
             if common::is_channel_target(target) {
                 // Parse channel modes from args
-                let mode_args: Vec<&str> = msg.args().iter().skip(1).copied().collect();
+                let mut mode_args: Vec<&str> = msg.args().iter().skip(1).copied().collect();
+                let list_expires_at = common::take_list_expiry(&mut mode_args);
                 let modes = common::parse_channel_modes(ctx, &mode_args).await?;
-                channel::handle_channel_mode(ctx, target, &modes).await
+                channel::handle_channel_mode(ctx, target, &modes, list_expires_at).await
             } else {
                 // Parse user modes from args
                 let mode_args: Vec<&str> = msg.args().iter().skip(1).copied().collect();
@@ -0,0 +1,98 @@
+//! TBAN command handler.
+//!
+//! `TBAN <channel> <duration> <mask>` sets a ban that lifts itself after
+//! `duration`, the same as `MODE <channel> +b <mask> :~<duration>`.
+
+use super::super::{Context, HandlerError, HandlerResult, PostRegHandler, server_reply};
+use super::{channel, common};
+use crate::state::RegisteredState;
+use async_trait::async_trait;
+use slirc_proto::{ChannelMode, MessageRef, Mode, Response};
+
+/// Handler for TBAN command.
+pub struct TbanHandler;
+
+#[async_trait]
+impl PostRegHandler for TbanHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, RegisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let channel_name = msg.arg(0).ok_or(HandlerError::NeedMoreParams)?;
+        let duration = msg.arg(1).ok_or(HandlerError::NeedMoreParams)?;
+        let mask = msg.arg(2).ok_or(HandlerError::NeedMoreParams)?;
+
+        let Some(expires_at) = common::list_expiry(duration) else {
+            let reply = server_reply(
+                ctx.server_name(),
+                Response::ERR_INVALIDMODEPARAM,
+                vec![
+                    ctx.nick().to_string(),
+                    channel_name.to_string(),
+                    "b".to_string(),
+                    duration.to_string(),
+                    "Invalid duration (e.g. 30m, 2h, 1d)".to_string(),
+                ],
+            );
+            ctx.sender.send(reply).await?;
+            return Ok(());
+        };
+
+        let modes = [Mode::Plus(ChannelMode::Ban, Some(mask.to_string()))];
+        channel::handle_channel_mode(ctx, channel_name, &modes, Some(expires_at)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{ChannelMode, Command, Message, Mode, Response};
+    use std::time::Duration;
+
+    fn has_reply(replies: &[Message], code: Response) -> bool {
+        replies
+            .iter()
+            .any(|m| matches!(&m.command, Command::Response(resp, _) if *resp == code))
+    }
+
+    #[tokio::test]
+    async fn timed_ban_lifts_itself() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
+        let mut bob = tm.user("bob").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+
+        let replies = tm.run(&mut alice, "TBAN #chan soon bob!*@*").await;
+        assert!(has_reply(&replies, Response::ERR_INVALIDMODEPARAM));
+
+        tm.run(&mut alice, "TBAN #chan 1 bob!*@*").await;
+        tm.run(&mut alice, "MODE #chan +b carol!*@* :~1h").await;
+        let replies = tm.run(&mut bob, "JOIN #chan").await;
+        assert!(has_reply(&replies, Response::ERR_BANNEDFROMCHAN));
+
+        alice.drain();
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        let unban = alice
+            .recv_matching(
+                |m| matches!(&m.command, Command::ChannelMODE(_, modes) if !modes.is_empty()),
+            )
+            .await
+            .expect("expiry is announced");
+        let Command::ChannelMODE(_, modes) = &unban.command else {
+            unreachable!()
+        };
+        assert_eq!(
+            modes,
+            &[Mode::Minus(ChannelMode::Ban, Some("bob!*@*".to_string()))]
+        );
+
+        let replies = tm.run(&mut bob, "JOIN #chan").await;
+        assert!(!has_reply(&replies, Response::ERR_BANNEDFROMCHAN));
+        let replies = tm.run(&mut alice, "MODE #chan +b").await;
+        assert!(replies.iter().any(|m| matches!(
+            &m.command,
+            Command::Response(Response::RPL_BANLIST, args) if args.iter().any(|a| a == "carol!*@*")
+        )));
+    }
+}
@@ -4,7 +4,7 @@
 //! Users can only query/change their own modes.
 
 use super::super::{Context, HandlerResult, server_reply, user_prefix};
-use crate::state::{RegisteredState, UserModes};
+use crate::state::{Matrix, RegisteredState, UserModes};
 use slirc_proto::{Command, Message, Mode, Response, UserMode, irc_eq};
 use tracing::debug;
 
@@ -55,6 +55,10 @@ pub async fn handle_user_mode(
         // Change modes
         let mut user = user.write().await;
         let (applied, rejected) = apply_user_modes_typed(&mut user.modes, modes);
+        let changed = applied
+            .iter()
+            .filter_map(|m| m.mode().to_string().chars().next());
+        user.stamp_modes(changed, ctx.matrix.clock());
 
         if !applied.is_empty() {
             // Get host from the user we already have (avoid deadlock)
@@ -78,22 +82,7 @@ pub async fn handle_user_mode(
 
         if !applied.is_empty() {
             ctx.matrix.user_manager.notify_observer(ctx.uid, None).await;
-
-            // Update stats
-            for mode in &applied {
-                match mode {
-                    Mode::Minus(UserMode::Oper, _) => {
-                        ctx.matrix.stats_manager.user_deopered();
-                    }
-                    Mode::Plus(UserMode::Invisible, _) => {
-                        ctx.matrix.stats_manager.user_set_invisible();
-                    }
-                    Mode::Minus(UserMode::Invisible, _) => {
-                        ctx.matrix.stats_manager.user_unset_invisible();
-                    }
-                    _ => {}
-                }
-            }
+            record_user_mode_stats(ctx.matrix, &applied);
         }
 
         // Report any rejected modes (like +o which only server can set)
@@ -110,6 +99,24 @@ pub async fn handle_user_mode(
     Ok(())
 }
 
+/// Update LUSERS counters for applied user mode changes.
+pub fn record_user_mode_stats(matrix: &Matrix, applied: &[Mode<UserMode>]) {
+    for mode in applied {
+        match mode {
+            Mode::Minus(UserMode::Oper, _) => {
+                matrix.stats_manager.user_deopered();
+            }
+            Mode::Plus(UserMode::Invisible, _) => {
+                matrix.stats_manager.user_set_invisible();
+            }
+            Mode::Minus(UserMode::Invisible, _) => {
+                matrix.stats_manager.user_unset_invisible();
+            }
+            _ => {}
+        }
+    }
+}
+
 /// Apply user mode changes from typed modes, returns (applied_modes, rejected_modes).
 pub fn apply_user_modes_typed(
     user_modes: &mut UserModes,
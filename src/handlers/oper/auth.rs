@@ -1,9 +1,13 @@
 //! OPER command handler for operator authentication.
 //!
-//! Authenticates users as IRC operators using password verification
-//! and hostmask matching from the server configuration.
-
-use super::super::{Context, HandlerResult, PostRegHandler, matches_hostmask, server_reply};
+//! Authenticates users as IRC operators using password or client
+//! certificate verification and hostmask matching from the server
+//! configuration. The checks after authentication are shared with CHALLENGE.
+
+use super::super::{
+    Context, HandlerError, HandlerResult, PostRegHandler, matches_hostmask, server_reply,
+};
+use crate::config::{OperBlock, OperClassBlock};
 use crate::require_arg_or_reply;
 use crate::state::RegisteredState;
 use crate::state::actor::validation::format_user_mask;
@@ -18,12 +22,97 @@ use std::time::Duration;
 const OPER_BASELINE_DELAY_MS: u64 = 500;
 /// Random jitter added to baseline delay (0..OPER_JITTER_MS).
 const OPER_JITTER_MS: u64 = 200;
+/// Failed attempts before further attempts are locked out.
+const MAX_OPER_ATTEMPTS: u8 = 3;
+/// Minimum spacing between attempts.
+const OPER_DELAY_MS: u64 = 3000;
+/// How long a lockout lasts.
+const LOCKOUT_DELAY_MS: u64 = 30000;
+
+/// Apply timing normalization to prevent side-channel attacks.
+///
+/// All OPER and CHALLENGE responses (success, failure, lockout) take
+/// approximately the same amount of time, making it impossible to
+/// distinguish valid oper names from invalid ones via timing analysis.
+pub(super) async fn apply_timing_delay() {
+    let jitter = rand::thread_rng().gen_range(0..OPER_JITTER_MS);
+    tokio::time::sleep(Duration::from_millis(OPER_BASELINE_DELAY_MS + jitter)).await;
+}
+
+/// Enforce the brute-force lockout and pacing shared by OPER and CHALLENGE.
+///
+/// Returns `false`, having replied, while the connection is locked out.
+pub(super) async fn start_oper_attempt(
+    ctx: &mut Context<'_, RegisteredState>,
+) -> Result<bool, HandlerError> {
+    let now = std::time::Instant::now();
+
+    if ctx.state.failed_oper_attempts >= MAX_OPER_ATTEMPTS
+        && let Some(last_attempt) = ctx.state.last_oper_attempt
+    {
+        let elapsed = now.duration_since(last_attempt).as_millis() as u64;
+        if elapsed < LOCKOUT_DELAY_MS {
+            // Apply timing normalization even for lockout responses
+            apply_timing_delay().await;
+
+            let nick = ctx.nick().to_string();
+            let remaining_sec = (LOCKOUT_DELAY_MS - elapsed) / 1000;
+            let reply = server_reply(
+                ctx.server_name(),
+                Response::ERR_PASSWDMISMATCH,
+                vec![
+                    nick.clone(),
+                    format!(
+                        "Too many failed attempts. Try again in {} seconds.",
+                        remaining_sec
+                    ),
+                ],
+            );
+            ctx.sender.send(reply).await?;
+            tracing::warn!(target: "audit", nick = %nick, attempts = ctx.state.failed_oper_attempts, "OPER brute-force lockout active");
+            return Ok(false);
+        } else {
+            ctx.state.failed_oper_attempts = 0;
+        }
+    }
+
+    // Rate limiting between attempts (this is separate from timing normalization)
+    if let Some(last_attempt) = ctx.state.last_oper_attempt {
+        let elapsed = now.duration_since(last_attempt).as_millis() as u64;
+        if elapsed < OPER_DELAY_MS {
+            let remaining_ms = OPER_DELAY_MS - elapsed;
+            tokio::time::sleep(tokio::time::Duration::from_millis(remaining_ms)).await;
+        }
+    }
+
+    ctx.state.last_oper_attempt = Some(now);
+    Ok(true)
+}
+
+/// Count a failed attempt and answer it after the usual delay.
+pub(super) async fn refuse_oper(
+    ctx: &mut Context<'_, RegisteredState>,
+    response: Response,
+    text: &str,
+) -> HandlerResult {
+    apply_timing_delay().await;
+
+    ctx.state.failed_oper_attempts += 1;
+    let reply = server_reply(
+        ctx.server_name(),
+        response,
+        vec![ctx.nick().to_string(), text.to_string()],
+    );
+    ctx.sender.send(reply).await?;
+    Ok(())
+}
 
 /// Handler for OPER command.
 ///
-/// `OPER name password`
+/// `OPER name [password]`
 ///
-/// Authenticates a user as an IRC operator.
+/// Authenticates a user as an IRC operator. The password is left out for
+/// blocks that authenticate by client certificate (`certfp`) alone.
 pub struct OperHandler;
 
 #[async_trait]
@@ -33,247 +122,284 @@ impl PostRegHandler for OperHandler {
         ctx: &mut Context<'_, RegisteredState>,
         msg: &MessageRef<'_>,
     ) -> HandlerResult {
-        let server_name = ctx.server_name().to_string();
-
         let Some(name) = require_arg_or_reply!(ctx, msg, 0, "OPER") else {
             return Ok(());
         };
-        let Some(password) = require_arg_or_reply!(ctx, msg, 1, "OPER") else {
-            return Ok(());
-        };
+        let password = msg.arg(1);
 
         let nick = ctx.nick().to_string();
 
-        const MAX_OPER_ATTEMPTS: u8 = 3;
-        const OPER_DELAY_MS: u64 = 3000;
-        const LOCKOUT_DELAY_MS: u64 = 30000;
-
-        // Helper to apply timing normalization to prevent side-channel attacks.
-        // All OPER responses (success, failure, lockout) will take approximately
-        // the same amount of time, making it impossible to distinguish valid
-        // usernames from invalid ones via timing analysis.
-        async fn apply_timing_delay() {
-            let jitter = rand::thread_rng().gen_range(0..OPER_JITTER_MS);
-            tokio::time::sleep(Duration::from_millis(OPER_BASELINE_DELAY_MS + jitter)).await;
-        }
-
-        let now = std::time::Instant::now();
-
-        if ctx.state.failed_oper_attempts >= MAX_OPER_ATTEMPTS
-            && let Some(last_attempt) = ctx.state.last_oper_attempt
-        {
-            let elapsed = now.duration_since(last_attempt).as_millis() as u64;
-            if elapsed < LOCKOUT_DELAY_MS {
-                // Apply timing normalization even for lockout responses
-                apply_timing_delay().await;
-
-                let remaining_sec = (LOCKOUT_DELAY_MS - elapsed) / 1000;
-                let reply = server_reply(
-                    &server_name,
-                    Response::ERR_PASSWDMISMATCH,
-                    vec![
-                        nick.clone(),
-                        format!(
-                            "Too many failed attempts. Try again in {} seconds.",
-                            remaining_sec
-                        ),
-                    ],
-                );
-                ctx.sender.send(reply).await?;
-                tracing::warn!(target: "audit", nick = %nick, attempts = ctx.state.failed_oper_attempts, "OPER brute-force lockout active");
-                return Ok(());
-            } else {
-                ctx.state.failed_oper_attempts = 0;
-            }
+        if password.is_some() && !ctx.matrix.config.security.allow_password_oper {
+            tracing::warn!(target: "audit", nick = %nick, oper_name = %name, "OPER failed: password OPER disabled");
+            return refuse_oper(
+                ctx,
+                Response::ERR_NOOPERHOST,
+                "Password OPER is disabled; use CHALLENGE or a client certificate",
+            )
+            .await;
         }
 
-        // Rate limiting between attempts (this is separate from timing normalization)
-        if let Some(last_attempt) = ctx.state.last_oper_attempt {
-            let elapsed = now.duration_since(last_attempt).as_millis() as u64;
-            if elapsed < OPER_DELAY_MS {
-                let remaining_ms = OPER_DELAY_MS - elapsed;
-                tokio::time::sleep(tokio::time::Duration::from_millis(remaining_ms)).await;
-            }
+        if !start_oper_attempt(ctx).await? {
+            return Ok(());
         }
 
-        ctx.state.last_oper_attempt = Some(now);
-
+        // Blocks come from the hot config so REHASH can change them
         let oper_block = ctx
             .matrix
-            .config
+            .hot_config
+            .read()
             .oper_blocks
             .iter()
-            .find(|block| block.name == name);
+            .find(|block| block.name == name)
+            .cloned();
 
         let Some(oper_block) = oper_block else {
-            // Apply timing normalization before responding
-            apply_timing_delay().await;
-
-            ctx.state.failed_oper_attempts += 1;
             tracing::warn!(
                 target: "audit",
                 nick = %nick,
                 oper_name = %name,
-                attempts = ctx.state.failed_oper_attempts,
+                attempts = ctx.state.failed_oper_attempts + 1,
                 "OPER failed: unknown oper name"
             );
-            let reply = server_reply(
-                &server_name,
-                Response::ERR_PASSWDMISMATCH,
-                vec![nick, "Password incorrect".to_string()],
-            );
-            ctx.sender.send(reply).await?;
-            return Ok(());
+            return refuse_oper(ctx, Response::ERR_PASSWDMISMATCH, "Password incorrect").await;
         };
 
         // Check TLS requirement before password verification
         if oper_block.require_tls && !ctx.state.is_tls {
-            apply_timing_delay().await;
-
-            ctx.state.failed_oper_attempts += 1;
             tracing::warn!(
                 target: "audit",
                 nick = %nick,
                 oper_name = %name,
                 "OPER failed: TLS required for this oper block"
             );
-            let reply = server_reply(
-                &server_name,
+            return refuse_oper(
+                ctx,
                 Response::ERR_NOOPERHOST,
-                vec![
-                    nick,
-                    "TLS connection required for this oper block".to_string(),
-                ],
-            );
-            ctx.sender.send(reply).await?;
-            return Ok(());
+                "TLS connection required for this oper block",
+            )
+            .await;
         }
 
-        if !oper_block.verify_password(password).await {
-            // Apply timing normalization before responding
-            apply_timing_delay().await;
-
-            ctx.state.failed_oper_attempts += 1;
+        let authenticated = match (&oper_block.password, password) {
+            (Some(_), Some(password)) => oper_block.verify_password(password).await,
+            // Certificate-only block; the fingerprint is checked below
+            (None, None) => oper_block.certfp.is_some(),
+            _ => false,
+        };
+        if !authenticated {
             tracing::warn!(
                 target: "audit",
                 nick = %nick,
                 oper_name = %name,
-                attempts = ctx.state.failed_oper_attempts,
+                attempts = ctx.state.failed_oper_attempts + 1,
                 "OPER failed: incorrect password"
             );
-            let reply = server_reply(
-                &server_name,
-                Response::ERR_PASSWDMISMATCH,
-                vec![nick, "Password incorrect".to_string()],
-            );
-            ctx.sender.send(reply).await?;
-            return Ok(());
+            return refuse_oper(ctx, Response::ERR_PASSWDMISMATCH, "Password incorrect").await;
         }
 
-        if let Some(ref required_mask) = oper_block.hostmask {
-            let (user_nick, user_user, user_host) = if let Some(user_arc) = ctx
-                .matrix
-                .user_manager
-                .users
-                .get(ctx.uid)
-                .map(|u| u.value().clone())
-            {
-                let user = user_arc.read().await;
-                (user.nick.clone(), user.user.clone(), user.host.clone())
-            } else {
-                let hs_nick = ctx.state.nick.clone();
-                let hs_user = ctx.state.user.clone();
-                (hs_nick, hs_user, ctx.remote_addr.ip().to_string())
-            };
-            let user_mask = format_user_mask(&user_nick, &user_user, &user_host);
-
-            if !matches_hostmask(required_mask, &user_mask) {
-                // Apply timing normalization before responding
-                apply_timing_delay().await;
-
-                ctx.state.failed_oper_attempts += 1;
-                tracing::warn!(
-                    target: "audit",
-                    nick = %nick,
-                    oper_name = %name,
-                    user_mask = %user_mask,
-                    required_mask = %required_mask,
-                    attempts = ctx.state.failed_oper_attempts,
-                    "OPER failed: hostmask mismatch"
-                );
-                let reply = server_reply(
-                    &server_name,
-                    Response::ERR_NOOPERHOST,
-                    vec![nick, "No O-lines for your host".to_string()],
-                );
-                ctx.sender.send(reply).await?;
-                return Ok(());
-            }
-        }
+        grant_oper(ctx, &oper_block, name).await
+    }
+}
 
-        // Apply timing normalization for success path too
-        // This ensures success and failure take the same amount of time
-        apply_timing_delay().await;
+/// Check the host and certificate constraints of `oper_block` and its class,
+/// then make the authenticated user an operator.
+pub(super) async fn grant_oper(
+    ctx: &mut Context<'_, RegisteredState>,
+    oper_block: &OperBlock,
+    name: &str,
+) -> HandlerResult {
+    let server_name = ctx.server_name().to_string();
+    let nick = ctx.nick().to_string();
+
+    let (user_nick, user_user, user_host) = if let Some(user_arc) = ctx
+        .matrix
+        .user_manager
+        .users
+        .get(ctx.uid)
+        .map(|u| u.value().clone())
+    {
+        let user = user_arc.read().await;
+        (user.nick.clone(), user.user.clone(), user.host.clone())
+    } else {
+        let hs_nick = ctx.state.nick.clone();
+        let hs_user = ctx.state.user.clone();
+        (hs_nick, hs_user, ctx.remote_addr.ip().to_string())
+    };
+    let user_mask = format_user_mask(&user_nick, &user_user, &user_host);
+
+    if let Some(ref required_mask) = oper_block.hostmask
+        && !matches_hostmask(required_mask, &user_mask)
+    {
+        tracing::warn!(
+            target: "audit",
+            nick = %nick,
+            oper_name = %name,
+            user_mask = %user_mask,
+            required_mask = %required_mask,
+            attempts = ctx.state.failed_oper_attempts + 1,
+            "OPER failed: hostmask mismatch"
+        );
+        return refuse_oper(ctx, Response::ERR_NOOPERHOST, "No O-lines for your host").await;
+    }
 
-        ctx.state.failed_oper_attempts = 0;
+    let denial = {
+        let hot_config = ctx.matrix.hot_config.read();
+        class_denial(
+            oper_block,
+            &hot_config.oper_classes,
+            &user_mask,
+            ctx.state.certfp.as_deref(),
+        )
+    };
+    if let Some(reason) = denial {
+        tracing::warn!(
+            target: "audit",
+            nick = %nick,
+            oper_name = %name,
+            user_mask = %user_mask,
+            class = ?oper_block.class,
+            attempts = ctx.state.failed_oper_attempts + 1,
+            "OPER failed: {reason}"
+        );
+        return refuse_oper(ctx, Response::ERR_NOOPERHOST, "No O-lines for your host").await;
+    }
 
-        let (user_nick, user_user, user_host) = if let Some(user_arc) = ctx
-            .matrix
-            .user_manager
-            .users
-            .get(ctx.uid)
-            .map(|u| u.value().clone())
-        {
-            let user = user_arc.read().await;
-            (user.nick.clone(), user.user.clone(), user.host.clone())
-        } else {
-            (
-                nick.clone(),
-                "unknown".to_string(),
-                ctx.remote_addr.ip().to_string(),
-            )
-        };
+    // Apply timing normalization for success path too
+    // This ensures success and failure take the same amount of time
+    apply_timing_delay().await;
+
+    ctx.state.failed_oper_attempts = 0;
+
+    if let Some(user_arc) = ctx
+        .matrix
+        .user_manager
+        .users
+        .get(ctx.uid)
+        .map(|u| u.value().clone())
+    {
+        let mut user = user_arc.write().await;
+        user.set_mode('o', true, ctx.matrix.clock());
+        // The class decides what the capability authority grants
+        user.modes.oper_type = oper_block.class.clone();
+        ctx.matrix.stats_manager.user_opered();
+    }
 
-        if let Some(user_arc) = ctx
-            .matrix
-            .user_manager
-            .users
-            .get(ctx.uid)
-            .map(|u| u.value().clone())
-        {
-            let mut user = user_arc.write().await;
-            user.modes.oper = true;
-            ctx.matrix.stats_manager.user_opered();
-        }
+    // Notify observer of user update (Innovation 2)
+    ctx.matrix.user_manager.notify_observer(ctx.uid, None).await;
+
+    tracing::info!(target: "audit", nick = %nick, oper_name = %name, class = ?oper_block.class, "OPER successful");
+
+    // Send snomask 'o'
+    ctx.matrix
+        .user_manager
+        .send_snomask(
+            'o',
+            &format!("OPER: {} ({}) is now an IRC operator", nick, name),
+        )
+        .await;
+
+    let reply = server_reply(
+        &server_name,
+        Response::RPL_YOUREOPER,
+        vec![nick.clone(), "You are now an IRC operator".to_string()],
+    );
+    ctx.sender.send(reply).await?;
+
+    let mode_msg = Message {
+        tags: None,
+        prefix: Some(Prefix::new(user_nick, user_user, user_host)),
+        command: Command::UserMODE(nick, vec![Mode::Plus(UserMode::Oper, None)]),
+    };
+    ctx.sender.send(mode_msg).await?;
+
+    Ok(())
+}
 
-        // Notify observer of user update (Innovation 2)
-        ctx.matrix.user_manager.notify_observer(ctx.uid, None).await;
+/// Why `block`'s class, or its own `certfp`, refuses this client, if it does.
+fn class_denial(
+    block: &OperBlock,
+    classes: &[OperClassBlock],
+    user_mask: &str,
+    certfp: Option<&str>,
+) -> Option<&'static str> {
+    let class = match &block.class {
+        Some(name) => match classes.iter().find(|class| &class.name == name) {
+            Some(class) => Some(class),
+            None => return Some("oper class not configured"),
+        },
+        None => None,
+    };
+
+    if let Some(class) = class
+        && !class.hostmasks.is_empty()
+        && !class
+            .hostmasks
+            .iter()
+            .any(|mask| matches_hostmask(mask, user_mask))
+    {
+        return Some("hostmask not allowed by oper class");
+    }
 
-        tracing::info!(target: "audit", nick = %nick, oper_name = %name, "OPER successful");
+    let require_certfp = class.is_some_and(|class| class.require_certfp);
+    if block.certfp.is_some() || require_certfp {
+        let normalize = |fp: &str| fp.replace(':', "").to_ascii_lowercase();
+        let matched = match (&block.certfp, certfp) {
+            (Some(expected), Some(presented)) => normalize(expected) == normalize(presented),
+            _ => false,
+        };
+        if !matched {
+            return Some("client certificate fingerprint mismatch");
+        }
+    }
 
-        // Send snomask 'o'
-        ctx.matrix
-            .user_manager
-            .send_snomask(
-                'o',
-                &format!("OPER: {} ({}) is now an IRC operator", nick, name),
-            )
-            .await;
+    None
+}
 
-        let reply = server_reply(
-            &server_name,
-            Response::RPL_YOUREOPER,
-            vec![nick.clone(), "You are now an IRC operator".to_string()],
-        );
-        ctx.sender.send(reply).await?;
+#[cfg(test)]
+mod tests {
+    use crate::caps::CapabilityAuthority;
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{Command, Message, Response};
 
-        let mode_msg = Message {
-            tags: None,
-            prefix: Some(Prefix::new(user_nick, user_user, user_host)),
-            command: Command::UserMODE(nick, vec![Mode::Plus(UserMode::Oper, None)]),
-        };
-        ctx.sender.send(mode_msg).await?;
+    fn has_reply(replies: &[Message], code: Response) -> bool {
+        replies
+            .iter()
+            .any(|m| matches!(&m.command, Command::Response(resp, _) if *resp == code))
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn oper_class_limits_privileges_and_hosts() {
+        let tm = TestMatrixBuilder::new()
+            .config(|c| {
+                let block = |name: &str, class: &str| {
+                    toml::from_str(&format!(
+                        "name = \"{name}\"\npassword = \"pw\"\nclass = \"{class}\""
+                    ))
+                    .unwrap()
+                };
+                c.oper = vec![block("helper", "helper"), block("remote", "remote")];
+                c.oper_classes = vec![
+                    toml::from_str("name = \"helper\"\nprivileges = [\"kill\"]").unwrap(),
+                    toml::from_str(
+                        "name = \"remote\"\nprivileges = [\"*\"]\nhostmasks = [\"*!*@10.*\"]",
+                    )
+                    .unwrap(),
+                ];
+            })
+            .build()
+            .await;
+        let mut alice = tm.user("alice").build().await;
+        let mut bob = tm.user("bob").build().await;
+
+        let replies = tm.run(&mut alice, "OPER helper pw").await;
+        assert!(has_reply(&replies, Response::RPL_YOUREOPER));
+        let authority = CapabilityAuthority::new(tm.matrix.clone());
+        assert!(authority.request_kill_cap(&alice.uid).await.is_some());
+        assert!(authority.request_kline_cap(&alice.uid).await.is_none());
+        assert!(authority.request_squit_cap(&alice.uid).await.is_none());
+
+        let replies = tm.run(&mut bob, "OPER remote pw").await;
+        assert!(has_reply(&replies, Response::ERR_NOOPERHOST));
+        assert!(authority.request_kill_cap(&bob.uid).await.is_none());
     }
 }
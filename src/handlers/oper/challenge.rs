@@ -0,0 +1,193 @@
+//! CHALLENGE command handler for public-key operator authentication.
+//!
+//! `CHALLENGE <name>` issues a random challenge for the oper block `name`;
+//! `CHALLENGE +<signature>` answers it with a base64 signature made by the
+//! private half of the block's `challenge_key`. Once the signature checks
+//! out, the same host, class and certfp checks as OPER apply.
+
+use super::super::{Context, HandlerResult, PostRegHandler, server_reply};
+use super::auth::{grant_oper, refuse_oper, start_oper_attempt};
+use crate::require_arg_or_reply;
+use crate::security::challenge::{ChallengeKey, OperChallenge};
+use crate::state::RegisteredState;
+use async_trait::async_trait;
+use slirc_proto::{MessageRef, Response};
+
+/// Handler for CHALLENGE command.
+///
+/// `CHALLENGE name` / `CHALLENGE +signature`
+pub struct ChallengeHandler;
+
+#[async_trait]
+impl PostRegHandler for ChallengeHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, RegisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let Some(arg) = require_arg_or_reply!(ctx, msg, 0, "CHALLENGE") else {
+            return Ok(());
+        };
+
+        match arg.strip_prefix('+') {
+            Some(signature) => answer_challenge(ctx, signature).await,
+            None => issue_challenge(ctx, arg).await,
+        }
+    }
+}
+
+/// Send a fresh challenge for `name`.
+///
+/// Unknown names get a challenge too, so only the answer reveals failure.
+async fn issue_challenge(ctx: &mut Context<'_, RegisteredState>, name: &str) -> HandlerResult {
+    if !start_oper_attempt(ctx).await? {
+        return Ok(());
+    }
+
+    let challenge = OperChallenge::new(name);
+    let text = challenge.text.clone();
+    ctx.state.oper_challenge = Some(challenge);
+
+    let nick = ctx.nick().to_string();
+    let reply = server_reply(
+        ctx.server_name(),
+        Response::RPL_RSACHALLENGE2,
+        vec![nick.clone(), text],
+    );
+    ctx.sender.send(reply).await?;
+    let reply = server_reply(
+        ctx.server_name(),
+        Response::RPL_ENDOFRSACHALLENGE2,
+        vec![nick, "End of CHALLENGE".to_string()],
+    );
+    ctx.sender.send(reply).await?;
+    Ok(())
+}
+
+/// Check `signature` against the pending challenge and oper up on success.
+async fn answer_challenge(
+    ctx: &mut Context<'_, RegisteredState>,
+    signature: &str,
+) -> HandlerResult {
+    let nick = ctx.nick().to_string();
+
+    // A challenge is good for one answer
+    let Some(challenge) = ctx.state.oper_challenge.take() else {
+        let reply = server_reply(
+            ctx.server_name(),
+            Response::ERR_PASSWDMISMATCH,
+            vec![nick, "No CHALLENGE in progress".to_string()],
+        );
+        ctx.sender.send(reply).await?;
+        return Ok(());
+    };
+
+    if challenge.is_expired() {
+        tracing::warn!(target: "audit", nick = %nick, oper_name = %challenge.name, "CHALLENGE failed: expired");
+        return refuse_oper(ctx, Response::ERR_PASSWDMISMATCH, "CHALLENGE expired").await;
+    }
+
+    let oper_block = ctx
+        .matrix
+        .hot_config
+        .read()
+        .oper_blocks
+        .iter()
+        .find(|block| block.name == challenge.name && block.challenge_key.is_some())
+        .cloned();
+
+    let verified = oper_block
+        .as_ref()
+        .and_then(|block| block.challenge_key.as_deref())
+        .and_then(ChallengeKey::parse)
+        .is_some_and(|key| key.verify(challenge.text.as_bytes(), signature));
+    let Some(oper_block) = oper_block.filter(|_| verified) else {
+        tracing::warn!(
+            target: "audit",
+            nick = %nick,
+            oper_name = %challenge.name,
+            attempts = ctx.state.failed_oper_attempts + 1,
+            "CHALLENGE failed: bad signature or unknown oper name"
+        );
+        return refuse_oper(ctx, Response::ERR_PASSWDMISMATCH, "Password incorrect").await;
+    };
+
+    if oper_block.require_tls && !ctx.state.is_tls {
+        tracing::warn!(
+            target: "audit",
+            nick = %nick,
+            oper_name = %challenge.name,
+            "CHALLENGE failed: TLS required for this oper block"
+        );
+        return refuse_oper(
+            ctx,
+            Response::ERR_NOOPERHOST,
+            "TLS connection required for this oper block",
+        )
+        .await;
+    }
+
+    grant_oper(ctx, &oper_block, &challenge.name).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use slirc_proto::{Command, Message, Response};
+
+    fn reply_arg(replies: &[Message], code: Response) -> Option<String> {
+        replies.iter().find_map(|m| match &m.command {
+            Command::Response(resp, args) if *resp == code => args.last().cloned(),
+            _ => None,
+        })
+    }
+
+    #[tokio::test]
+    async fn challenge_opers_without_password() {
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let public = STANDARD.encode(pair.public_key().as_ref());
+
+        let tm = TestMatrixBuilder::new()
+            .config(move |c| {
+                c.security.allow_password_oper = false;
+                c.oper = vec![
+                    toml::from_str(&format!(
+                        "name = \"keyed\"\nchallenge_key = \"ed25519:{public}\""
+                    ))
+                    .unwrap(),
+                    toml::from_str("name = \"legacy\"\npassword = \"pw\"").unwrap(),
+                ];
+            })
+            .build()
+            .await;
+        let mut alice = tm.user("alice").build().await;
+        let mut bob = tm.user("bob").build().await;
+
+        let replies = tm.run(&mut bob, "OPER legacy pw").await;
+        assert!(reply_arg(&replies, Response::ERR_NOOPERHOST).is_some());
+
+        // A wrong signature burns the challenge
+        let replies = tm.run(&mut alice, "CHALLENGE keyed").await;
+        let text = reply_arg(&replies, Response::RPL_RSACHALLENGE2).expect("challenge sent");
+        let bad = STANDARD.encode(pair.sign(b"something else").as_ref());
+        let replies = tm.run(&mut alice, &format!("CHALLENGE +{bad}")).await;
+        assert!(reply_arg(&replies, Response::ERR_PASSWDMISMATCH).is_some());
+        let good = STANDARD.encode(pair.sign(text.as_bytes()).as_ref());
+        let replies = tm.run(&mut alice, &format!("CHALLENGE +{good}")).await;
+        assert_eq!(
+            reply_arg(&replies, Response::ERR_PASSWDMISMATCH).as_deref(),
+            Some("No CHALLENGE in progress")
+        );
+
+        let replies = tm.run(&mut alice, "CHALLENGE keyed").await;
+        let text = reply_arg(&replies, Response::RPL_RSACHALLENGE2).expect("challenge sent");
+        let good = STANDARD.encode(pair.sign(text.as_bytes()).as_ref());
+        let replies = tm.run(&mut alice, &format!("CHALLENGE +{good}")).await;
+        assert!(reply_arg(&replies, Response::RPL_YOUREOPER).is_some());
+    }
+}
@@ -63,11 +63,16 @@ impl PostRegHandler for ChghostHandler {
             let old_host = user.visible_host.clone();
             let channels: Vec<String> = user.channels.iter().cloned().collect();
 
-            user.user = new_user.to_string();
-            user.visible_host = new_host.to_string();
+            let now = ctx.matrix.clock();
+            user.set_ident(new_user.to_string(), now);
+            user.set_visible_host(new_host.to_string(), now);
 
             (old_nick, old_user, old_host, channels)
         };
+        ctx.matrix
+            .user_manager
+            .notify_observer(&target_uid, None)
+            .await;
 
         let chghost_msg = Message {
             tags: None,
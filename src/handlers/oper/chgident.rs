@@ -61,10 +61,14 @@ impl PostRegHandler for ChgIdentHandler {
             let old_host = user.visible_host.clone();
             let channels: Vec<String> = user.channels.iter().cloned().collect();
 
-            user.user = new_ident.to_string();
+            user.set_ident(new_ident.to_string(), ctx.matrix.clock());
 
             (old_nick, old_user, old_host, channels)
         };
+        ctx.matrix
+            .user_manager
+            .notify_observer(&target_uid, None)
+            .await;
 
         // Broadcast CHGHOST message (since CHGIDENT isn't a standard message for clients)
         // CHGHOST <user> <host>
@@ -89,6 +89,9 @@ impl PostRegHandler for ConnectHandler {
             }
         }
 
+        // An explicit CONNECT lifts an earlier SQUIT
+        ctx.matrix.sync_manager.squit_links.remove(&link.name);
+
         // Initiate the connection via SyncManager
         ctx.matrix.sync_manager.connect_to_peer(
             ctx.matrix.clone(),
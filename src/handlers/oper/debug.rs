@@ -0,0 +1,277 @@
+//! DEBUG command handler for inspecting a single user's server-side state.
+//!
+//! Dumps everything the server holds about one user as server notices, for
+//! chasing reports like "my client stopped receiving messages".
+
+use super::super::{Context, HandlerResult, PostRegHandler, resolve_nick_or_nosuchnick};
+use crate::state::RegisteredState;
+use crate::state::actor::ChannelEvent;
+use crate::sync::TopologyGraph;
+use crate::{require_arg_or_reply, require_oper_cap};
+use async_trait::async_trait;
+use slirc_proto::MessageRef;
+use std::collections::HashSet;
+use std::time::Instant;
+use tokio::sync::oneshot;
+
+/// Handler for DEBUG command.
+///
+/// `DEBUG <nick>`
+///
+/// Shows identity, modes, caps, channel memberships with member modes,
+/// rate limit buckets, reputation, pending nick enforcement and per-session
+/// send queue depth. Remote users only have the replicated part.
+pub struct DebugHandler;
+
+#[async_trait]
+impl PostRegHandler for DebugHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, RegisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let Some(_cap) = require_oper_cap!(ctx, "DEBUG", request_debug_cap) else {
+            return Ok(());
+        };
+        let Some(target) = require_arg_or_reply!(ctx, msg, 0, "DEBUG") else {
+            return Ok(());
+        };
+        let Some(uid) = resolve_nick_or_nosuchnick(ctx, "DEBUG", target).await? else {
+            return Ok(());
+        };
+        let Some(user_arc) = ctx
+            .matrix
+            .user_manager
+            .users
+            .get(&uid)
+            .map(|u| u.value().clone())
+        else {
+            return Ok(());
+        };
+        let matrix = ctx.matrix;
+
+        let origin = TopologyGraph::origin_of(&uid)
+            .map(|sid| sid.as_str().to_string())
+            .unwrap_or_default();
+        let is_local = origin == matrix.server_info.sid;
+        let or_dash = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+        let sorted = |set: &HashSet<String>| {
+            let mut v: Vec<_> = set.iter().cloned().collect();
+            v.sort();
+            if v.is_empty() {
+                "-".to_string()
+            } else {
+                v.join(" ")
+            }
+        };
+
+        let (nick, ip, mut channels, mut lines) = {
+            let user = user_arc.read().await;
+            let lines = vec![
+                format!(
+                    "{}!{}@{} ({}) uid {} server {}",
+                    user.nick,
+                    user.user,
+                    user.visible_host,
+                    user.realname,
+                    uid,
+                    if is_local { "local" } else { &origin }
+                ),
+                format!(
+                    "host {} ip {} account {} certfp {}",
+                    user.host,
+                    user.ip,
+                    or_dash(&user.account),
+                    or_dash(&user.certfp)
+                ),
+                format!(
+                    "modes {} oper {} away {}",
+                    user.modes.as_mode_string(),
+                    or_dash(&user.modes.oper_type),
+                    or_dash(&user.away)
+                ),
+                format!("caps {}", sorted(&user.caps)),
+                format!(
+                    "silence {} accept {}",
+                    sorted(&user.silence_list),
+                    sorted(&user.accept_list)
+                ),
+            ];
+            let channels: Vec<_> = user.channels.iter().cloned().collect();
+            (user.nick.clone(), user.ip.clone(), channels, lines)
+        };
+
+        // Channels with the member modes their actors hold
+        channels.sort();
+        let mut memberships = Vec::with_capacity(channels.len());
+        for channel in channels {
+            let tx = matrix
+                .channel_manager
+                .channels
+                .get(&channel)
+                .map(|c| c.value().clone());
+            let modes = match tx {
+                Some(tx) => {
+                    let (reply_tx, reply_rx) = oneshot::channel();
+                    let event = ChannelEvent::GetMemberModes {
+                        uid: uid.clone(),
+                        reply_tx,
+                    };
+                    match tx.send(event).await {
+                        Ok(()) => reply_rx.await.ok().flatten(),
+                        Err(_) => None,
+                    }
+                }
+                None => None,
+            };
+            memberships.push(match modes {
+                Some(modes) => format!("{}{}", modes.all_prefix_chars(), channel),
+                // In user.channels but not a member of the actor
+                None => format!("{channel}(stale)"),
+            });
+        }
+        lines.push(format!(
+            "channels {}",
+            if memberships.is_empty() {
+                "-".to_string()
+            } else {
+                memberships.join(" ")
+            }
+        ));
+
+        if is_local {
+            let buckets = matrix
+                .security_manager
+                .rate_limiter
+                .client_buckets(&uid)
+                .into_iter()
+                .map(|(name, idle)| format!("{name} {idle}s"))
+                .collect::<Vec<_>>();
+            lines.push(format!(
+                "rate limits (idle) {}",
+                if buckets.is_empty() {
+                    "-".to_string()
+                } else {
+                    buckets.join(", ")
+                }
+            ));
+
+            let trust = match &matrix.security_manager.spam_detector {
+                Some(detector) => detector.read().await.trust_score(&ip).await,
+                None => None,
+            };
+            lines.push(format!(
+                "reputation {}",
+                trust.map_or_else(|| "-".to_string(), |t| t.to_string())
+            ));
+
+            let enforce = matrix
+                .user_manager
+                .enforce_timers
+                .get(&uid)
+                .map(|deadline| deadline.saturating_duration_since(Instant::now()).as_secs());
+            lines.push(match enforce {
+                Some(secs) => format!("nick enforcement in {secs}s"),
+                None => "nick enforcement -".to_string(),
+            });
+
+            let queues = matrix
+                .user_manager
+                .senders
+                .get(&uid)
+                .map(|senders| {
+                    senders
+                        .iter()
+                        .map(|s| {
+                            let (used, max) = s.tx.depth();
+                            format!("{} {}/{}", s.session_id, used, max)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            lines.push(format!(
+                "send queues {}",
+                if queues.is_empty() {
+                    "-".to_string()
+                } else {
+                    queues.join(", ")
+                }
+            ));
+        }
+
+        for line in lines {
+            ctx.send_notice(format!("*** DEBUG {nick}: {line}")).await?;
+        }
+        ctx.send_notice(format!("*** End of DEBUG {nick}")).await?;
+
+        tracing::debug!(oper = %ctx.nick(), target = %nick, "DEBUG command executed");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{Command, Response};
+
+    fn notices(replies: &[slirc_proto::Message]) -> Vec<&str> {
+        replies
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::NOTICE(_, text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn debug_dumps_local_user_state() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut oper = tm.user("oper").oper().build().await;
+        let mut alice = tm.user("alice").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.run(&mut alice, "PRIVMSG #chan :hi").await;
+
+        let replies = tm.run(&mut oper, "DEBUG alice").await;
+        let notices = notices(&replies);
+        assert!(
+            notices
+                .iter()
+                .any(|n| n.starts_with("*** DEBUG alice: alice!alice@"))
+        );
+        assert!(notices.contains(&"*** DEBUG alice: channels @#chan"));
+        assert!(
+            notices
+                .iter()
+                .any(|n| n.starts_with("*** DEBUG alice: send queues "))
+        );
+        assert_eq!(notices.last(), Some(&"*** End of DEBUG alice"));
+    }
+
+    #[tokio::test]
+    async fn debug_remote_user_has_no_local_state() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut oper = tm.user("oper").oper().build().await;
+        let _peer = tm.peer("00A").await;
+        tm.remote_user("00A", "00AAAAAAA", "carol").await;
+
+        let replies = tm.run(&mut oper, "DEBUG carol").await;
+        let notices = notices(&replies);
+        assert!(notices.iter().any(|n| n.ends_with("server 00A")));
+        assert!(!notices.iter().any(|n| n.contains("send queues")));
+    }
+
+    #[tokio::test]
+    async fn debug_requires_oper() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
+
+        let replies = tm.run(&mut alice, "DEBUG alice").await;
+        assert!(
+            replies
+                .iter()
+                .any(|m| matches!(&m.command, Command::Response(Response::ERR_NOPRIVILEGES, _)))
+        );
+        assert!(notices(&replies).is_empty());
+    }
+}
@@ -6,17 +6,25 @@
 use super::super::{
     Context, HandlerResult, PostRegHandler, resolve_nick_or_nosuchnick, user_mask_from_state,
 };
-use crate::state::RegisteredState;
+use crate::handlers::helpers::targets::{TargetMask, resolve_mask_targets};
+use crate::state::{PendingMaskKill, RegisteredState, Uid};
 use crate::{require_arg_or_reply, require_oper_cap};
 use async_trait::async_trait;
 use slirc_proto::{Command, Message, MessageRef, Prefix};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long a mask KILL preview can be confirmed for.
+const MASK_KILL_CONFIRM_WINDOW: Duration = Duration::from_secs(60);
 
 /// Handler for KILL command.
 ///
 /// `KILL nickname :reason`
+/// `KILL <mask> [CONFIRM] :reason`
 ///
-/// Disconnects a user from the network. Requires operator privileges.
+/// Disconnects a user from the network. A wildcard/CIDR target kills every
+/// matching user, but only once the server has shown the oper the matches
+/// and the oper re-issues it with `CONFIRM`. Requires operator privileges.
 /// Uses capability-based authorization (Innovation 4).
 /// # RFC 2812 §3.7.1
 ///
@@ -40,96 +48,223 @@ impl PostRegHandler for KillHandler {
         let reason = msg.arg(1).unwrap_or("No reason given");
 
         // Get killer's identity
-        let Some((killer_nick, killer_user, killer_host)) =
-            user_mask_from_state(ctx, ctx.uid).await
-        else {
+        let Some((nick, user, host)) = user_mask_from_state(ctx, ctx.uid).await else {
             return Ok(());
         };
+        let killer = Killer { nick, user, host };
 
         // Request KILL capability from authority (Innovation 4)
         let Some(_kill_cap) = require_oper_cap!(ctx, "KILL", request_kill_cap) else {
             return Ok(());
         };
 
+        if TargetMask::is_mask(target_nick) {
+            return kill_by_mask(ctx, msg, target_nick, killer).await;
+        }
+
         let Some(target_uid) = resolve_nick_or_nosuchnick(ctx, "KILL", target_nick).await? else {
             return Ok(());
         };
 
-        let quit_reason = format!("Killed by {killer_nick} ({reason})");
-
-        // Check if target is local or remote
-        let is_local = target_uid.starts_with(ctx.matrix.server_id.as_str());
-
-        if is_local {
-            // Local User: Disconnect locally
-            let target_sender = ctx.matrix.user_manager.get_first_sender(&target_uid);
-            if let Some(target_sender) = target_sender {
-                let error_msg = Message {
-                    tags: None,
-                    prefix: None,
-                    command: Command::ERROR(format!(
-                        "Closing Link: {} ({})",
-                        target_nick, quit_reason
-                    )),
-                };
-                let _ = target_sender.send(Arc::new(error_msg)).await;
+        kill_user(ctx, &killer, &target_uid, target_nick, reason).await;
+
+        // Note: We do NOT echo the KILL back to the sender here manually,
+        // because for local users the QUIT will come back, and for remote users
+        // we might get a KILL broadcast back or a QUIT.
+        // Standard IRC clients usually just see the QUIT.
+
+        Ok(())
+    }
+}
+
+/// Handle `KILL <mask> [CONFIRM] :reason`.
+///
+/// Without `CONFIRM` this only reports what the mask would hit, and remembers
+/// the preview. `CONFIRM` kills only if that preview is for the same mask, has
+/// not expired, and the mask still matches exactly the users it listed;
+/// otherwise the matches are shown again. Masks matching more than
+/// `limits.max_kill_mask_targets` users are always refused.
+async fn kill_by_mask(
+    ctx: &mut Context<'_, RegisteredState>,
+    msg: &MessageRef<'_>,
+    mask: &str,
+    killer: Killer,
+) -> HandlerResult {
+    let (confirmed, reason) = match (msg.arg(1), msg.arg(2)) {
+        (Some(flag), reason) if flag.eq_ignore_ascii_case("CONFIRM") => {
+            (true, reason.unwrap_or("No reason given"))
+        }
+        (Some(reason), _) => (false, reason),
+        (None, _) => (false, "No reason given"),
+    };
+
+    let cap = ctx.matrix.config.limits.max_kill_mask_targets;
+    let target_mask = TargetMask::parse(mask);
+    let matches = resolve_mask_targets(ctx.matrix, &target_mask, cap, Some(ctx.uid)).await;
+
+    if matches.total == 0 {
+        ctx.send_notice(format!("*** KILL: no users match {mask}"))
+            .await?;
+        return Ok(());
+    }
+    if matches.truncated() {
+        ctx.send_notice(format!(
+            "*** KILL: {mask} matches {} users (limit {cap}); refusing",
+            matches.total
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let mut targets = Vec::with_capacity(matches.uids.len());
+    for uid in matches.uids {
+        let nick = ctx
+            .matrix
+            .user_manager
+            .users
+            .get(&uid)
+            .map(|u| u.value().clone());
+        if let Some(user_arc) = nick {
+            let nick = user_arc.read().await.nick.clone();
+            targets.push((uid, nick));
+        }
+    }
+
+    let pending = ctx.state.pending_mask_kill.take();
+    if confirmed {
+        let Some(pending) =
+            pending.filter(|p| p.expires > Instant::now() && p.mask.eq_ignore_ascii_case(mask))
+        else {
+            ctx.send_notice(format!(
+                "*** KILL: {mask} has not been previewed; issue KILL {mask} :<reason> first"
+            ))
+            .await?;
+            return Ok(());
+        };
+        if pending.uids.len() == targets.len()
+            && targets.iter().all(|(uid, _)| pending.uids.contains(uid))
+        {
+            for (uid, nick) in &targets {
+                kill_user(ctx, &killer, uid, nick, reason).await;
             }
+            tracing::info!(target: "audit", killer = %killer.nick, mask = %mask, count = targets.len(), reason = %reason, "Mask KILL executed");
+            ctx.send_notice(format!(
+                "*** KILL: {} user(s) matching {mask} killed",
+                targets.len()
+            ))
+            .await?;
+            return Ok(());
+        }
+        ctx.send_notice(format!(
+            "*** KILL: the users matching {mask} changed since the preview"
+        ))
+        .await?;
+    }
+
+    let nicks: Vec<&str> = targets.iter().map(|(_, n)| n.as_str()).collect();
+    ctx.send_notice(format!(
+        "*** KILL: {mask} matches {} user(s): {}",
+        nicks.len(),
+        nicks.join(" ")
+    ))
+    .await?;
+    ctx.send_notice(format!(
+        "*** Re-issue as KILL {mask} CONFIRM :<reason> to proceed"
+    ))
+    .await?;
+    ctx.state.pending_mask_kill = Some(PendingMaskKill {
+        mask: mask.to_string(),
+        uids: targets.iter().map(|(uid, _)| uid.clone()).collect(),
+        expires: Instant::now() + MASK_KILL_CONFIRM_WINDOW,
+    });
+
+    Ok(())
+}
 
-            // This broadcasts QUIT to local channels and S2S peers
-            ctx.matrix.disconnect_user(&target_uid, &quit_reason).await;
+/// Identity of the oper issuing a KILL.
+struct Killer {
+    nick: String,
+    user: String,
+    host: String,
+}
+
+/// Disconnect (local) or route a KILL to (remote) a single resolved user.
+async fn kill_user(
+    ctx: &Context<'_, RegisteredState>,
+    killer: &Killer,
+    target_uid: &Uid,
+    target_nick: &str,
+    reason: &str,
+) {
+    let Killer {
+        nick: killer_nick,
+        user: killer_user,
+        host: killer_host,
+    } = killer;
+    let quit_reason = format!("Killed by {killer_nick} ({reason})");
 
-            tracing::info!(target: "audit", killer = %killer_nick, target = %target_nick, reason = %reason, "KILL command executed (Local)");
-        } else {
-            // Remote User: Route KILL to the owning server
-            tracing::info!(target: "audit", killer = %killer_nick, target = %target_nick, uid = %target_uid, reason = %reason, "Routing KILL to remote server");
+    // Check if target is local or remote
+    let is_local = target_uid.starts_with(ctx.matrix.server_id.as_str());
 
-            let kill_msg = Message {
+    if is_local {
+        // Local User: Disconnect locally
+        let target_sender = ctx.matrix.user_manager.get_first_sender(target_uid);
+        if let Some(target_sender) = target_sender {
+            let error_msg = Message {
                 tags: None,
-                prefix: Some(Prefix::new(
-                    killer_nick.clone(),
-                    killer_user.clone(),
-                    killer_host.clone(),
-                )),
-                command: Command::KILL(target_nick.to_string(), quit_reason.clone()),
+                prefix: None,
+                command: Command::ERROR(format!("Closing Link: {} ({})", target_nick, quit_reason)),
             };
+            let _ = target_sender.send(Arc::new(error_msg)).await;
+        }
 
-            let routed = ctx
-                .matrix
-                .sync_manager
-                .route_to_remote_user(&target_uid, Arc::new(kill_msg))
-                .await;
+        // This broadcasts QUIT to local channels and S2S peers
+        ctx.matrix.disconnect_user(target_uid, &quit_reason).await;
 
-            if !routed {
-                // If routing failed (no path), send error to operator
-                // In a split network, we might want to forcefully remove them locally?
-                // But generally we just inform the oper.
-                let _ = ctx
-                    .sender
-                    .send(Message::from(Command::NOTICE(
-                        killer_nick.clone(),
-                        format!("*** Could not route KILL to remote user {}", target_nick),
-                    )))
-                    .await;
-            }
-        }
+        tracing::info!(target: "audit", killer = %killer_nick, target = %target_nick, reason = %reason, "KILL command executed (Local)");
+    } else {
+        // Remote User: Route KILL to the owning server
+        tracing::info!(target: "audit", killer = %killer_nick, target = %target_nick, uid = %target_uid, reason = %reason, "Routing KILL to remote server");
 
-        // Send snomask 'k' (local broadcast of the attempt)
-        ctx.matrix
-            .user_manager
-            .send_snomask(
-                'k',
-                &format!(
-                    "Received KILL message for {}. From {} Path: {}!{}@{} ({})",
-                    target_nick, killer_nick, killer_nick, killer_user, killer_host, reason
-                ),
-            )
-            .await;
+        let kill_msg = Message {
+            tags: None,
+            prefix: Some(Prefix::new(
+                killer_nick.clone(),
+                killer_user.clone(),
+                killer_host.clone(),
+            )),
+            command: Command::KILL(target_nick.to_string(), quit_reason.clone()),
+        };
 
-        // Note: We do NOT echo the KILL back to the sender here manually,
-        // because for local users the QUIT will come back, and for remote users
-        // we might get a KILL broadcast back or a QUIT.
-        // Standard IRC clients usually just see the QUIT.
+        let routed = ctx
+            .matrix
+            .sync_manager
+            .route_to_remote_user(target_uid, Arc::new(kill_msg))
+            .await;
 
-        Ok(())
+        if !routed {
+            // If routing failed (no path), send error to operator
+            // In a split network, we might want to forcefully remove them locally?
+            // But generally we just inform the oper.
+            let _ = ctx
+                .sender
+                .send(Message::from(Command::NOTICE(
+                    killer_nick.clone(),
+                    format!("*** Could not route KILL to remote user {}", target_nick),
+                )))
+                .await;
+        }
     }
+
+    // Send snomask 'k' (local broadcast of the attempt)
+    ctx.matrix
+        .user_manager
+        .send_snomask(
+            'k',
+            &format!(
+                "Received KILL message for {}. From {} Path: {}!{}@{} ({})",
+                target_nick, killer_nick, killer_nick, killer_user, killer_host, reason
+            ),
+        )
+        .await;
 }
@@ -1,18 +1,52 @@
-//! Server administration commands: DIE, REHASH, RESTART.
+//! Server administration commands: DIE, REHASH, RESTART, MAINTENANCE.
 //!
 //! These commands require operator privileges and use capability-based
 //! authorization (Innovation 4) for access control.
 
 use super::super::{
-    Context, HandlerError, HandlerResult, PostRegHandler, get_nick_or_star, server_notice,
-    server_reply,
+    Context, HandlerResult, PostRegHandler, get_nick_or_star, server_notice, server_reply,
 };
 use crate::state::RegisteredState;
 use async_trait::async_trait;
 use slirc_proto::{MessageRef, Response};
-use tokio::sync::mpsc;
+
+/// Hand a DIE or RESTART to the shutdown controller, or say why not.
+async fn request_shutdown(
+    ctx: &mut Context<'_, RegisteredState>,
+    nick: &str,
+    restart: bool,
+    reason: &str,
+) -> HandlerResult {
+    let command = if restart { "RESTART" } else { "DIE" };
+    if !ctx
+        .matrix
+        .lifecycle_manager
+        .request_shutdown(restart, reason)
+    {
+        ctx.sender
+            .send(server_notice(
+                ctx.server_name(),
+                nick,
+                "Server is already shutting down",
+            ))
+            .await?;
+        return Ok(());
+    }
+
+    tracing::warn!(target: "audit", oper = %nick, reason = %reason, "{command} issued - draining connections");
+    ctx.matrix
+        .user_manager
+        .send_notice_to_opers(&format!("{nick} issued {command} ({reason})"))
+        .await;
+    Ok(())
+}
 
 /// Handler for DIE command. Uses capability-based authorization (Innovation 4).
+///
+/// `DIE [reason]`
+///
+/// The shutdown controller warns all users, flushes state, sends ERROR to
+/// clients and links, and waits for connections to drain before exiting.
 pub struct DieHandler;
 
 #[async_trait]
@@ -20,45 +54,20 @@ impl PostRegHandler for DieHandler {
     async fn handle(
         &self,
         ctx: &mut Context<'_, RegisteredState>,
-        _msg: &MessageRef<'_>,
+        msg: &MessageRef<'_>,
     ) -> HandlerResult {
-        let server_name = ctx.server_name();
         let nick = get_nick_or_star(ctx).await;
 
         // Request DIE capability from authority (Innovation 4)
         let authority = ctx.authority();
-        let _die_cap = match authority.request_die_cap(ctx.uid).await {
-            Some(cap) => cap,
-            None => {
-                let reply = Response::err_noprivileges(&nick).with_prefix(ctx.server_prefix());
-                ctx.send_error("DIE", "ERR_NOPRIVILEGES", reply).await?;
-                return Ok(());
-            }
-        };
-
-        ctx.sender
-            .send(server_notice(
-                server_name,
-                &nick,
-                "Server shutting down by operator request",
-            ))
-            .await?;
-
-        tracing::warn!(oper = %nick, "DIE command issued - initiating shutdown");
-
-        ctx.matrix
-            .lifecycle_manager
-            .shutdown_tx
-            .send(())
-            .map_err(|_| {
-                tracing::error!("Failed to send shutdown signal - no receivers");
-                HandlerError::Send(mpsc::error::SendError(slirc_proto::Message::notice(
-                    "*",
-                    "Shutdown signal failed",
-                )))
-            })?;
+        if authority.request_die_cap(ctx.uid).await.is_none() {
+            let reply = Response::err_noprivileges(&nick).with_prefix(ctx.server_prefix());
+            ctx.send_error("DIE", "ERR_NOPRIVILEGES", reply).await?;
+            return Ok(());
+        }
 
-        Ok(())
+        let reason = msg.arg(0).unwrap_or("Operator request");
+        request_shutdown(ctx, &nick, false, reason).await
     }
 }
 
@@ -96,67 +105,34 @@ impl PostRegHandler for RehashHandler {
         );
         ctx.sender.send(reply).await?;
 
-        let reload_result = async {
-            // Phase 1: Load and validate new configuration from disk
-            let new_config = crate::config::Config::load(&config_path)
-                .map_err(|e| anyhow::anyhow!("Failed to load config: {}", e))?;
-
-            tracing::debug!("New config loaded and validated");
-
-            // Phase 2: Reload ban lists from database (always safe)
-            let dlines = ctx.db.bans().get_active_dlines().await?;
-            let zlines = ctx.db.bans().get_active_zlines().await?;
-
-            // Phase 3: Update IP deny list with fresh bans
-            match ctx.matrix.security_manager.ip_deny_list.write() {
-                Ok(mut deny_list) => {
-                    deny_list.reload_from_database(&dlines, &zlines);
-                    tracing::debug!("IP deny list reloaded from database");
+        match crate::state::rehash::rehash(ctx.matrix).await {
+            Ok(results) => {
+                for result in &results {
+                    ctx.sender
+                        .send(server_notice(
+                            server_name,
+                            &nick,
+                            format!("REHASH {result}"),
+                        ))
+                        .await?;
                 }
-                Err(e) => {
-                    anyhow::bail!("Failed to acquire write lock on IP deny list: {}", e)
-                }
-            }
-
-            // Phase 4: Atomically swap hot-reloadable configuration
-            // This is the key innovation: using parking_lot::RwLock for atomic swaps
-            {
-                let new_hot_config = crate::state::HotConfig::from_config(&new_config);
-                let mut hot_config = ctx.matrix.hot_config.write();
-                *hot_config = new_hot_config;
-                tracing::debug!(
-                    "Hot config atomically swapped: description='{}', opers={}",
-                    hot_config.description,
-                    hot_config.oper_blocks.len()
-                );
-            }
-
-            tracing::info!(
-                oper_count = %new_config.oper.len(),
-                "Configuration reloaded successfully"
-            );
-
-            Ok::<_, anyhow::Error>(())
-        }
-        .await;
-
-        match reload_result {
-            Ok(()) => {
+                let failed = results.iter().filter(|r| r.outcome.is_err()).count();
+                let summary = if failed == 0 {
+                    "REHASH complete".to_string()
+                } else {
+                    format!("REHASH complete; {failed} section(s) kept their old values")
+                };
                 ctx.sender
-                    .send(server_notice(
-                        server_name,
-                        &nick,
-                        "REHASH complete: Configuration reloaded (IP bans, server info, operators)",
-                    ))
+                    .send(server_notice(server_name, &nick, summary))
                     .await?;
-                tracing::info!(oper = %nick, "REHASH completed successfully");
+                tracing::info!(oper = %nick, failed, "REHASH completed");
             }
             Err(e) => {
-                let error_msg = format!("REHASH failed: {:?}", e).replace('\n', " ");
+                let error_msg = format!("REHASH failed: {e}").replace('\n', " ");
                 ctx.sender
                     .send(server_notice(server_name, &nick, error_msg))
                     .await?;
-                tracing::warn!(oper = %nick, error = ?e, "REHASH failed - original config preserved");
+                tracing::warn!(oper = %nick, error = %e, "REHASH failed - original config preserved");
             }
         }
 
@@ -165,6 +141,11 @@ impl PostRegHandler for RehashHandler {
 }
 
 /// Handler for RESTART command. Uses capability-based authorization (Innovation 4).
+///
+/// `RESTART [reason]`
+///
+/// Drains like DIE, then execs the server binary again with the same
+/// arguments, picking up a replaced binary.
 pub struct RestartHandler;
 
 #[async_trait]
@@ -172,46 +153,247 @@ impl PostRegHandler for RestartHandler {
     async fn handle(
         &self,
         ctx: &mut Context<'_, RegisteredState>,
-        _msg: &MessageRef<'_>,
+        msg: &MessageRef<'_>,
     ) -> HandlerResult {
-        let server_name = ctx.server_name();
         let nick = get_nick_or_star(ctx).await;
 
         // Request RESTART capability from authority (Innovation 4)
         let authority = ctx.authority();
-        let _restart_cap = match authority.request_restart_cap(ctx.uid).await {
-            Some(cap) => cap,
-            None => {
-                let reply = Response::err_noprivileges(&nick).with_prefix(ctx.server_prefix());
-                ctx.send_error("RESTART", "ERR_NOPRIVILEGES", reply).await?;
+        if authority.request_restart_cap(ctx.uid).await.is_none() {
+            let reply = Response::err_noprivileges(&nick).with_prefix(ctx.server_prefix());
+            ctx.send_error("RESTART", "ERR_NOPRIVILEGES", reply).await?;
+            return Ok(());
+        }
+
+        let reason = msg.arg(0).unwrap_or("Operator request");
+        request_shutdown(ctx, &nick, true, reason).await
+    }
+}
+
+/// Handler for MAINTENANCE command. Uses capability-based authorization (Innovation 4).
+///
+/// `MAINTENANCE [ON [reason] | OFF | RESTART [reason]]`
+///
+/// While maintenance mode is on, new client registrations are refused with a
+/// notice and services are read-only. `RESTART` enters maintenance mode and
+/// shuts down as DIE does, for the process supervisor to restart. Without
+/// arguments, reports the current mode.
+pub struct MaintenanceHandler;
+
+#[async_trait]
+impl PostRegHandler for MaintenanceHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, RegisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let server_name = ctx.server_name();
+        let nick = get_nick_or_star(ctx).await;
+
+        // Request MAINTENANCE capability from authority (Innovation 4)
+        let authority = ctx.authority();
+        if authority.request_maintenance_cap(ctx.uid).await.is_none() {
+            let reply = Response::err_noprivileges(&nick).with_prefix(ctx.server_prefix());
+            ctx.send_error("MAINTENANCE", "ERR_NOPRIVILEGES", reply)
+                .await?;
+            return Ok(());
+        }
+
+        let lifecycle = &ctx.matrix.lifecycle_manager;
+        let subcommand = msg.arg(0).map(str::to_ascii_uppercase);
+        let reason = msg.arg(1).unwrap_or("Scheduled maintenance");
+
+        let text = match subcommand.as_deref() {
+            None => match lifecycle.maintenance_reason() {
+                Some(reason) => format!("Maintenance mode is ON ({reason})"),
+                None => "Maintenance mode is OFF".to_string(),
+            },
+            Some("ON") => {
+                lifecycle.enter_maintenance(reason);
+                tracing::warn!(target: "audit", oper = %nick, reason = %reason, "Maintenance mode enabled");
+                ctx.matrix
+                    .user_manager
+                    .send_notice_to_opers(&format!(
+                        "{nick} enabled maintenance mode ({reason}): new connections and services writes are refused"
+                    ))
+                    .await;
                 return Ok(());
             }
+            Some("OFF") => {
+                if lifecycle.leave_maintenance() {
+                    tracing::warn!(target: "audit", oper = %nick, "Maintenance mode disabled");
+                    ctx.matrix
+                        .user_manager
+                        .send_notice_to_opers(&format!("{nick} disabled maintenance mode"))
+                        .await;
+                    return Ok(());
+                }
+                "Maintenance mode is not active".to_string()
+            }
+            Some("RESTART") => {
+                lifecycle.enter_maintenance(reason);
+                tracing::warn!(target: "audit", oper = %nick, reason = %reason, "Maintenance restart issued");
+
+                // The shutdown controller flushes state and drains clients;
+                // the process supervisor starts the server again
+                let reason = format!("maintenance: {reason}");
+                return request_shutdown(ctx, &nick, false, &reason).await;
+            }
+            Some(_) => "Usage: MAINTENANCE [ON [reason] | OFF | RESTART [reason]]".to_string(),
         };
 
         ctx.sender
-            .send(server_notice(
-                server_name,
-                &nick,
-                "Server restarting by operator request (exec replacement)",
-            ))
+            .send(server_notice(server_name, &nick, text))
             .await?;
+        Ok(())
+    }
+}
 
-        tracing::warn!(oper = %nick, "RESTART command issued - exec restarting");
+#[cfg(test)]
+mod tests {
+    use crate::state::ShutdownRequest;
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{Command, Response};
 
-        ctx.matrix
-            .lifecycle_manager
-            .shutdown_tx
-            .send(())
-            .map_err(|_| {
-                tracing::error!("Failed to send shutdown signal - no receivers");
-                HandlerError::Send(mpsc::error::SendError(slirc_proto::Message::notice(
-                    "*",
-                    "Shutdown signal failed",
-                )))
-            })?;
+    #[tokio::test]
+    async fn maintenance_requires_oper() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
 
-        tracing::info!("RESTART: Shutting down (use process supervisor for automatic restart)");
+        let replies = tm.run(&mut alice, "MAINTENANCE ON").await;
 
-        Ok(())
+        assert!(matches!(
+            &replies[0].command,
+            Command::Response(resp, _) if resp.code() == 481
+        ));
+        assert!(!tm.matrix.lifecycle_manager.in_maintenance());
+    }
+
+    #[tokio::test]
+    async fn die_and_restart_go_to_shutdown_controller() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
+        let mut oper = tm.user("oper").oper().build().await;
+        let lifecycle = &tm.matrix.lifecycle_manager;
+
+        let replies = tm.run(&mut alice, "DIE").await;
+        assert!(matches!(
+            &replies[0].command,
+            Command::Response(resp, _) if resp.code() == 481
+        ));
+        assert!(lifecycle.shutdown_request().is_none());
+
+        tm.run(&mut oper, "RESTART :new binary").await;
+        assert_eq!(
+            lifecycle.shutdown_request(),
+            Some(ShutdownRequest {
+                restart: true,
+                reason: "new binary".to_string(),
+            })
+        );
+        assert_eq!(
+            lifecycle.shutdown_message(),
+            "Server restarting (new binary)"
+        );
+
+        let replies = tm.run(&mut oper, "DIE").await;
+        assert!(matches!(
+            &replies[0].command,
+            Command::NOTICE(_, text) if text == "Server is already shutting down"
+        ));
+        assert!(lifecycle.shutdown_request().is_some_and(|r| r.restart));
+    }
+
+    #[tokio::test]
+    async fn maintenance_on_and_off() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut oper = tm.user("oper").oper().build().await;
+
+        tm.run(&mut oper, "MAINTENANCE ON :Upgrading").await;
+        assert_eq!(
+            tm.matrix.lifecycle_manager.maintenance_reason().as_deref(),
+            Some("Upgrading")
+        );
+        assert!(oper.drain().iter().any(
+            |m| matches!(&m.command, Command::NOTICE(_, text) if text.contains("enabled maintenance mode"))
+        ));
+
+        let replies = tm.run(&mut oper, "MAINTENANCE").await;
+        assert!(matches!(
+            &replies[0].command,
+            Command::NOTICE(_, text) if text == "Maintenance mode is ON (Upgrading)"
+        ));
+
+        tm.run(&mut oper, "MAINTENANCE OFF").await;
+        assert!(!tm.matrix.lifecycle_manager.in_maintenance());
+    }
+
+    #[tokio::test]
+    async fn rehash_reports_each_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            r#"
+[server]
+name = "test.server"
+network = "TestNet"
+sid = "00T"
+description = "Reloaded"
+
+[listen]
+address = "127.0.0.1:6667"
+
+[security]
+cloak_secret = "Rehash-Test-Secret-42"
+
+[security.rate_limits]
+join_burst_per_client = 9
+
+[motd]
+file = "/nonexistent/motd.txt"
+
+[[oper]]
+name = "fresh"
+password = "pw"
+"#,
+        )
+        .unwrap();
+
+        let tm = TestMatrixBuilder::new()
+            .config_path(path.to_string_lossy())
+            .build()
+            .await;
+        let mut oper = tm.user("oper").oper().build().await;
+        let mut alice = tm.user("alice").build().await;
+
+        let replies = tm.run(&mut oper, "REHASH").await;
+        let notices: Vec<&str> = replies
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::NOTICE(_, text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert!(notices.iter().any(|n| n.starts_with("REHASH motd: FAILED")));
+        assert!(notices.contains(&"REHASH opers: ok (1 blocks, 0 classes)"));
+        assert!(notices.contains(&"REHASH cloak: ok (applies to new connections)"));
+        assert!(notices.contains(&"REHASH rate limits: ok (applied)"));
+        assert_eq!(
+            notices.last(),
+            Some(&"REHASH complete; 1 section(s) kept their old values")
+        );
+
+        let hot_config = tm.matrix.hot_config.read().clone();
+        assert_eq!(hot_config.description, "Reloaded");
+        assert_eq!(hot_config.cloak_secret, "Rehash-Test-Secret-42");
+        assert!(!hot_config.motd_lines.is_empty());
+
+        let replies = tm.run(&mut alice, "OPER fresh pw").await;
+        assert!(
+            replies
+                .iter()
+                .any(|m| matches!(&m.command, Command::Response(Response::RPL_YOUREOPER, _)))
+        );
     }
 }
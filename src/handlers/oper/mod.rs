@@ -1,30 +1,38 @@
 //! Operator command handlers split into submodules.
 
 mod auth;
+mod challenge;
 mod chghost;
 mod chgident;
 mod clearchan;
 mod connect;
+mod debug;
 mod globops;
 mod kill;
 mod lifecycle;
+mod omode;
 mod spamconf;
 mod squit;
+mod tap;
 mod trace;
 mod vhost;
 mod wallops;
 
 pub use auth::OperHandler;
+pub use challenge::ChallengeHandler;
 pub use chghost::ChghostHandler;
 pub use chgident::ChgIdentHandler;
 pub use clearchan::ClearchanHandler;
 pub use connect::ConnectHandler;
+pub use debug::DebugHandler;
 pub use globops::GlobOpsHandler;
 pub use kill::KillHandler;
-pub use lifecycle::{DieHandler, RehashHandler, RestartHandler};
+pub use lifecycle::{DieHandler, MaintenanceHandler, RehashHandler, RestartHandler};
+pub use omode::OmodeHandler;
 pub use spamconf::SpamConfHandler;
 pub use squit::SquitHandler;
-pub use trace::TraceHandler;
+pub use tap::TapHandler;
+pub use trace::{TraceHandler, TraceMaskHandler};
 pub use vhost::VhostHandler;
 pub use wallops::WallopsHandler;
 
@@ -34,24 +42,30 @@ use std::collections::HashMap;
 /// Register all operator commands.
 pub fn register(map: &mut HashMap<&'static str, Box<dyn PostRegHandler>>) {
     map.insert("OPER", Box::new(OperHandler));
+    map.insert("CHALLENGE", Box::new(ChallengeHandler));
     map.insert("KILL", Box::new(KillHandler));
     map.insert("WALLOPS", Box::new(WallopsHandler));
     map.insert("GLOBOPS", Box::new(GlobOpsHandler));
     map.insert("DIE", Box::new(DieHandler));
     map.insert("REHASH", Box::new(RehashHandler));
     map.insert("RESTART", Box::new(RestartHandler));
+    map.insert("MAINTENANCE", Box::new(MaintenanceHandler));
     map.insert("CHGHOST", Box::new(ChghostHandler));
     map.insert("CHGIDENT", Box::new(ChgIdentHandler));
     map.insert("VHOST", Box::new(VhostHandler));
     map.insert("TRACE", Box::new(TraceHandler));
+    map.insert("TRACEMASK", Box::new(TraceMaskHandler));
     map.insert("SPAMCONF", Box::new(SpamConfHandler));
     map.insert("CLEARCHAN", Box::new(ClearchanHandler));
     map.insert("CONNECT", Box::new(ConnectHandler));
     map.insert("SQUIT", Box::new(SquitHandler));
+    map.insert("DEBUG", Box::new(DebugHandler));
+    map.insert("TAP", Box::new(TapHandler));
+    map.insert("OMODE", Box::new(OmodeHandler));
 }
 
 /// Validate hostname per RFC 952/1123 rules.
-pub(super) fn is_valid_hostname(hostname: &str) -> bool {
+pub(crate) fn is_valid_hostname(hostname: &str) -> bool {
     if hostname.is_empty() || hostname.len() > 253 {
         return false;
     }
@@ -0,0 +1,219 @@
+//! OMODE command handler for operators.
+//!
+//! Applies one mode change to every channel matching a mask, e.g. setting
+//! `+R` on all `#help-*` channels. The matches are always previewed first.
+
+use super::super::{Context, HandlerResult, PostRegHandler, format_modes_for_log};
+use crate::state::RegisteredState;
+use crate::state::actor::{ChannelEvent, ModeParams};
+use crate::{require_arg_or_reply, require_oper_cap};
+use async_trait::async_trait;
+use slirc_proto::mode::{ChannelMode, Mode};
+use slirc_proto::{MessageRef, irc_to_lower, wildcard_match};
+use std::collections::HashMap;
+use tokio::sync::oneshot;
+
+/// Channel actors sent a mode change before their replies are awaited.
+const BATCH_SIZE: usize = 50;
+
+/// Handler for OMODE command.
+///
+/// - `OMODE <#mask> <modes> [params]` lists the channels that would change
+/// - `OMODE CONFIRM <#mask> <modes> [params]` applies the change to them
+///
+/// Masks matching more than `limits.max_omode_channels` channels are
+/// refused. Modes are applied as the server, like SAMODE, and each applied
+/// change is written to the audit log.
+pub struct OmodeHandler;
+
+#[async_trait]
+impl PostRegHandler for OmodeHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, RegisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let Some(_cap) = require_oper_cap!(ctx, "OMODE", request_omode_cap) else {
+            return Ok(());
+        };
+        let Some(first) = require_arg_or_reply!(ctx, msg, 0, "OMODE") else {
+            return Ok(());
+        };
+        let confirmed = first.eq_ignore_ascii_case("CONFIRM");
+        let skip = usize::from(confirmed);
+        let Some(mask) = require_arg_or_reply!(ctx, msg, skip, "OMODE") else {
+            return Ok(());
+        };
+        let Some(modes_str) = require_arg_or_reply!(ctx, msg, skip + 1, "OMODE") else {
+            return Ok(());
+        };
+
+        let mut pieces: Vec<&str> = vec![modes_str];
+        pieces.extend(msg.args().iter().skip(skip + 2).copied());
+        let modes = match Mode::as_channel_modes(&pieces) {
+            Ok(modes) if !modes.is_empty() => modes,
+            Ok(_) => {
+                ctx.send_notice("*** OMODE: no modes given").await?;
+                return Ok(());
+            }
+            Err(e) => {
+                ctx.send_notice(format!("*** OMODE error: {e}")).await?;
+                return Ok(());
+            }
+        };
+        let modes_str = format_modes_for_log(&modes);
+
+        let mask_lower = irc_to_lower(mask);
+        let mut channels: Vec<_> = ctx
+            .matrix
+            .channel_manager
+            .channels
+            .iter()
+            .filter(|entry| wildcard_match(&mask_lower, entry.key()))
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        channels.sort_by(|a, b| a.0.cmp(&b.0));
+
+        if channels.is_empty() {
+            ctx.send_notice(format!("*** OMODE: no channels match {mask}"))
+                .await?;
+            return Ok(());
+        }
+        let cap = ctx.matrix.config.limits.max_omode_channels;
+        if channels.len() > cap {
+            ctx.send_notice(format!(
+                "*** OMODE: {mask} matches {} channels (limit {cap}); refusing",
+                channels.len()
+            ))
+            .await?;
+            return Ok(());
+        }
+
+        if !confirmed {
+            for (name, _) in &channels {
+                ctx.send_notice(format!("*** OMODE: would set {modes_str} on {name}"))
+                    .await?;
+            }
+            ctx.send_notice(format!(
+                "*** OMODE: {} channel(s) match {mask}; use OMODE CONFIRM {mask} {} to apply",
+                channels.len(),
+                pieces.join(" ")
+            ))
+            .await?;
+            return Ok(());
+        }
+
+        // Resolve target UIDs for member status modes
+        let mut target_uids = HashMap::new();
+        for mode in &modes {
+            if matches!(mode.mode(), ChannelMode::Oper | ChannelMode::Voice)
+                && let Some(nick) = mode.arg()
+                && let Some(uids) = ctx.matrix.user_manager.nicks.get(&irc_to_lower(nick))
+            {
+                target_uids.insert(nick.to_string(), uids.clone());
+            }
+        }
+
+        let oper_nick = ctx.nick().to_string();
+        let mut changed = 0;
+        for batch in channels.chunks(BATCH_SIZE) {
+            let mut pending = Vec::with_capacity(batch.len());
+            for (name, sender) in batch {
+                let (reply_tx, reply_rx) = oneshot::channel();
+                let event = ChannelEvent::ApplyModes {
+                    params: ModeParams {
+                        sender_uid: ctx.uid.to_string(),
+                        sender_prefix: ctx.server_prefix(),
+                        modes: modes.clone(),
+                        target_uids: target_uids.clone(),
+                        force: true,
+                        nanotime: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+                        list_expires_at: None,
+                    },
+                    reply_tx,
+                };
+                if sender.send(event).await.is_ok() {
+                    pending.push((name, reply_rx));
+                }
+            }
+
+            for (name, reply_rx) in pending {
+                match reply_rx.await {
+                    Ok(Ok(applied)) if !applied.is_empty() => {
+                        let applied = format_modes_for_log(&applied);
+                        tracing::info!(target: "audit", oper = %oper_nick, mask = %mask, channel = %name, modes = %applied, "OMODE applied");
+                        changed += 1;
+                    }
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => {
+                        ctx.send_notice(format!("*** OMODE: {name}: {e}")).await?;
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+
+        tracing::info!(target: "audit", oper = %oper_nick, mask = %mask, modes = %modes_str, matched = channels.len(), changed, "OMODE executed");
+        ctx.send_notice(format!(
+            "*** OMODE: {modes_str} applied to {changed} of {} channel(s) matching {mask}",
+            channels.len()
+        ))
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{Command, Message, Response};
+
+    fn mode_targets(messages: &[Message]) -> Vec<String> {
+        let mut targets: Vec<String> = messages
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::ChannelMODE(channel, _) => Some(channel.clone()),
+                _ => None,
+            })
+            .collect();
+        targets.sort();
+        targets
+    }
+
+    #[tokio::test]
+    async fn omode_previews_then_applies_to_matching_channels() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut oper = tm.user("oper").oper().build().await;
+        let mut alice = tm.user("alice").build().await;
+        for channel in ["#help-a", "#help-b", "#other"] {
+            tm.run(&mut alice, &format!("JOIN {channel}")).await;
+        }
+        alice.drain();
+
+        let replies = tm.run(&mut oper, "OMODE #help-* +m").await;
+        assert!(replies.iter().any(|m| matches!(
+            &m.command,
+            Command::NOTICE(_, text) if text.contains("2 channel(s) match #help-*")
+        )));
+        assert!(mode_targets(&alice.drain()).is_empty());
+
+        tm.run(&mut oper, "OMODE CONFIRM #HELP-* +m").await;
+        assert_eq!(mode_targets(&alice.drain()), ["#help-a", "#help-b"]);
+    }
+
+    #[tokio::test]
+    async fn omode_requires_oper() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
+        tm.run(&mut alice, "JOIN #help").await;
+        alice.drain();
+
+        let replies = tm.run(&mut alice, "OMODE CONFIRM #help +m").await;
+        assert!(
+            replies
+                .iter()
+                .any(|m| matches!(&m.command, Command::Response(Response::ERR_NOPRIVILEGES, _)))
+        );
+        assert!(mode_targets(&alice.drain()).is_empty());
+    }
+}
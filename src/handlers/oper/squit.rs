@@ -3,19 +3,23 @@
 //! Usage: `SQUIT <server> :<reason>`
 //! Requires: IRC operator privileges
 //!
-//! Finds the target server by name or SID and broadcasts SQUIT to the network,
-//! then removes the peer from SyncManager.
+//! Finds the target server by name or SID, direct peer or further away.
+//! A direct link is closed and not retried until the next CONNECT; a remote
+//! server is split off here and the SQUIT propagated to the other links,
+//! which do the same.
 
-use super::super::{Context, HandlerResult, PostRegHandler, get_nick_or_star, server_notice};
+use super::super::{
+    Context, HandlerResult, PostRegHandler, get_nick_or_star, server_notice, server_reply,
+};
 use crate::state::RegisteredState;
+use crate::sync::split::squit_server;
 use async_trait::async_trait;
-use slirc_proto::{Command, Message, MessageRef, Response};
-use std::sync::Arc;
+use slirc_proto::{MessageRef, Response};
 use tracing::warn;
 
 /// Handler for the SQUIT command.
 ///
-/// Terminates an S2S link to a specified server.
+/// Removes a server, and everything behind it, from the network.
 pub struct SquitHandler;
 
 #[async_trait]
@@ -53,6 +57,17 @@ impl PostRegHandler for SquitHandler {
 
         let reason = msg.arg(1).unwrap_or("No reason given");
 
+        if target.eq_ignore_ascii_case(server_name) || target == ctx.matrix.server_info.sid {
+            ctx.sender
+                .send(server_notice(
+                    server_name,
+                    &nick,
+                    "SQUIT: Cannot SQUIT the local server",
+                ))
+                .await?;
+            return Ok(());
+        }
+
         // Find target server in topology by name or SID
         let target_sid = ctx
             .matrix
@@ -60,32 +75,27 @@ impl PostRegHandler for SquitHandler {
             .topology
             .servers
             .iter()
-            .find(|e| e.value().name == target || e.key().as_str() == target)
+            .find(|e| e.value().name.eq_ignore_ascii_case(target) || e.key().as_str() == target)
             .map(|e| e.key().clone());
 
         let sid = match target_sid {
             Some(s) => s,
             None => {
-                ctx.sender
-                    .send(server_notice(
-                        server_name,
-                        &nick,
-                        format!("SQUIT: Server '{}' not found in network", target),
-                    ))
-                    .await?;
+                let reply = server_reply(
+                    server_name,
+                    Response::ERR_NOSUCHSERVER,
+                    vec![
+                        nick.clone(),
+                        target.to_string(),
+                        "No such server".to_string(),
+                    ],
+                );
+                ctx.sender.send(reply).await?;
                 return Ok(());
             }
         };
 
-        // Broadcast SQUIT to network
-        let squit_msg = Arc::new(Message::from(Command::SQUIT(
-            sid.as_str().to_string(),
-            reason.to_string(),
-        )));
-        ctx.matrix.sync_manager.broadcast(squit_msg, None).await;
-
-        // Remove peer from local state
-        ctx.matrix.sync_manager.remove_peer(&sid).await;
+        squit_server(ctx.matrix, &sid, reason, None).await;
 
         ctx.sender
             .send(server_notice(
@@ -106,3 +116,65 @@ impl PostRegHandler for SquitHandler {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::sync::clock::ServerId;
+    use slirc_proto::{Command, Response};
+
+    #[tokio::test]
+    async fn squit_remote_server_splits_it_and_tells_the_links() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut oper = tm.user("oper").oper().build().await;
+        let mut uplink = tm.peer("00A").await;
+        tm.run_server("00A", ":00A SID far.test 2 00C :Far away")
+            .await;
+        tm.remote_user("00C", "00CAAAAAA", "faraway").await;
+
+        let replies = tm.run(&mut oper, "SQUIT FAR.test :Misbehaving").await;
+        assert!(replies.iter().any(
+            |m| matches!(&m.command, Command::NOTICE(_, text) if text.starts_with("SQUIT: Disconnected"))
+        ));
+
+        let sync = &tm.matrix.sync_manager;
+        assert!(!sync.topology.servers.contains_key(&ServerId::new("00C")));
+        assert!(sync.links.contains_key(&ServerId::new("00A")));
+        assert!(!tm.matrix.user_manager.users.contains_key("00CAAAAAA"));
+        let sent: Vec<_> = std::iter::from_fn(|| uplink.try_recv().ok()).collect();
+        assert!(sent.iter().any(
+            |m| matches!(&m.command, Command::SQUIT(sid, reason) if sid == "00C" && reason == "Misbehaving")
+        ));
+    }
+
+    #[tokio::test]
+    async fn squit_direct_link_closes_it_for_good() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut oper = tm.user("oper").oper().build().await;
+        let _peer = tm.peer("00A").await;
+
+        tm.run(&mut oper, "SQUIT 00A").await;
+
+        let sync = &tm.matrix.sync_manager;
+        assert!(!sync.links.contains_key(&ServerId::new("00A")));
+        assert!(sync.squit_links.contains("00a.test"));
+    }
+
+    #[tokio::test]
+    async fn squit_unknown_or_local_server_is_refused() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut oper = tm.user("oper").oper().build().await;
+
+        let replies = tm.run(&mut oper, "SQUIT nowhere.test").await;
+        assert!(
+            replies
+                .iter()
+                .any(|m| matches!(&m.command, Command::Response(Response::ERR_NOSUCHSERVER, _)))
+        );
+
+        let replies = tm.run(&mut oper, "SQUIT test.server").await;
+        assert!(replies.iter().any(
+            |m| matches!(&m.command, Command::NOTICE(_, text) if text.contains("local server"))
+        ));
+    }
+}
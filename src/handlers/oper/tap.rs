@@ -0,0 +1,177 @@
+//! TAP command handler for capturing a connection's raw traffic.
+//!
+//! Lets an operator see exactly what a client sent and received, for
+//! diagnosing interop bugs without turning on global debug logging.
+
+use super::super::{Context, HandlerResult, PostRegHandler, resolve_nick_or_nosuchnick};
+use crate::state::RegisteredState;
+use crate::sync::TopologyGraph;
+use crate::tap::{TAP_CAPACITY, TapTarget};
+use crate::{require_arg_or_reply, require_oper_cap};
+use async_trait::async_trait;
+use slirc_proto::MessageRef;
+use std::net::IpAddr;
+
+/// Lines shown by `TAP <target> SHOW` without a count.
+const DEFAULT_SHOW: usize = 50;
+
+/// Handler for TAP command.
+///
+/// - `TAP <nick|uid|ip>` arms a tap on a local user or an address
+/// - `TAP <nick|uid|ip> OFF` disarms it and drops its lines
+/// - `TAP <nick|uid|ip> SHOW [count]` shows the last lines captured
+/// - `TAP LIST` lists armed taps
+///
+/// An IP tap also captures connections before they register. PASS,
+/// AUTHENTICATE, OPER and NickServ payloads are redacted when captured.
+pub struct TapHandler;
+
+#[async_trait]
+impl PostRegHandler for TapHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, RegisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let Some(_cap) = require_oper_cap!(ctx, "TAP", request_tap_cap) else {
+            return Ok(());
+        };
+        let Some(target) = require_arg_or_reply!(ctx, msg, 0, "TAP") else {
+            return Ok(());
+        };
+        let taps = crate::tap::global();
+
+        if target.eq_ignore_ascii_case("LIST") {
+            for (target, lines) in taps.list() {
+                ctx.send_notice(format!("*** TAP {target}: {lines} lines"))
+                    .await?;
+            }
+            ctx.send_notice("*** End of TAP LIST").await?;
+            return Ok(());
+        }
+
+        // An IP, a UID with a tap (its user may be gone), or a nick
+        let tap_target = if let Ok(ip) = target.parse::<IpAddr>() {
+            TapTarget::Ip(ip)
+        } else if taps.lines(&TapTarget::Uid(target.to_string()), 0).is_some() {
+            TapTarget::Uid(target.to_string())
+        } else {
+            let Some(uid) = resolve_nick_or_nosuchnick(ctx, "TAP", target).await? else {
+                return Ok(());
+            };
+            TapTarget::Uid(uid)
+        };
+
+        match msg.arg(1).map(str::to_ascii_uppercase).as_deref() {
+            None | Some("ON") => {
+                if let TapTarget::Uid(uid) = &tap_target
+                    && TopologyGraph::origin_of(uid)
+                        .is_some_and(|sid| sid.as_str() != ctx.matrix.server_info.sid)
+                {
+                    ctx.send_notice(format!("*** TAP: {target} is not connected to this server"))
+                        .await?;
+                    return Ok(());
+                }
+                if !taps.start(tap_target.clone()) {
+                    ctx.send_notice("*** TAP: too many taps armed").await?;
+                    return Ok(());
+                }
+                ctx.send_notice(format!("*** TAP armed on {tap_target}"))
+                    .await?;
+                tracing::info!(oper = %ctx.nick(), target = %tap_target, "Traffic tap armed");
+            }
+            Some("OFF") => {
+                let text = if taps.stop(&tap_target) {
+                    tracing::info!(oper = %ctx.nick(), target = %tap_target, "Traffic tap disarmed");
+                    format!("*** TAP removed from {tap_target}")
+                } else {
+                    format!("*** TAP: no tap on {tap_target}")
+                };
+                ctx.send_notice(text).await?;
+            }
+            Some("SHOW") => {
+                let count = msg
+                    .arg(2)
+                    .and_then(|c| c.parse::<usize>().ok())
+                    .unwrap_or(DEFAULT_SHOW)
+                    .min(TAP_CAPACITY);
+                let Some(lines) = taps.lines(&tap_target, count) else {
+                    ctx.send_notice(format!("*** TAP: no tap on {tap_target}"))
+                        .await?;
+                    return Ok(());
+                };
+                for line in lines {
+                    ctx.send_notice(format!("*** TAP {tap_target}: {line}"))
+                        .await?;
+                }
+                ctx.send_notice(format!("*** End of TAP {tap_target}"))
+                    .await?;
+            }
+            Some(other) => {
+                ctx.send_notice(format!("*** TAP: unknown option {other}"))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tap::TapTarget;
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{Command, Response};
+
+    fn notices(replies: &[slirc_proto::Message]) -> Vec<&str> {
+        replies
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::NOTICE(_, text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn tap_captures_and_shows_redacted_lines() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut oper = tm.user("oper").oper().build().await;
+        let alice = tm.user("alice").build().await;
+        // The tap registry is process-wide; use an address no other test taps
+        let ip = "198.51.100.77".parse().unwrap();
+
+        let replies = tm.run(&mut oper, "TAP 198.51.100.77").await;
+        assert_eq!(notices(&replies), ["*** TAP armed on 198.51.100.77"]);
+
+        let taps = crate::tap::global();
+        taps.inbound(&alice.uid, ip, "PASS hunter2\r\n");
+        taps.inbound(&alice.uid, ip, "NICK alice\r\n");
+
+        let replies = tm.run(&mut oper, "TAP 198.51.100.77 SHOW").await;
+        let notices = notices(&replies);
+        assert_eq!(notices.len(), 3);
+        assert!(notices[0].ends_with(&format!("{} << PASS <redacted>", alice.uid)));
+        assert!(notices[1].ends_with("<< NICK alice"));
+
+        tm.run(&mut oper, "TAP 198.51.100.77 OFF").await;
+        assert!(taps.lines(&TapTarget::Ip(ip), 10).is_none());
+    }
+
+    #[tokio::test]
+    async fn tap_requires_oper() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
+
+        let replies = tm.run(&mut alice, "TAP 198.51.100.78").await;
+        assert!(
+            replies
+                .iter()
+                .any(|m| matches!(&m.command, Command::Response(Response::ERR_NOPRIVILEGES, _)))
+        );
+        assert!(
+            crate::tap::global()
+                .lines(&TapTarget::Ip("198.51.100.78".parse().unwrap()), 0)
+                .is_none()
+        );
+    }
+}
@@ -6,8 +6,9 @@
 use super::super::{
     Context, HandlerResult, PostRegHandler, resolve_nick_or_nosuchnick, server_reply,
 };
-use crate::require_oper_cap;
+use crate::handlers::helpers::targets::{TargetMask, resolve_mask_targets};
 use crate::state::RegisteredState;
+use crate::{require_arg_or_reply, require_oper_cap};
 use async_trait::async_trait;
 use slirc_proto::{MessageRef, Response};
 
@@ -121,3 +122,94 @@ impl PostRegHandler for TraceHandler {
         Ok(())
     }
 }
+
+/// Handler for TRACEMASK command. Uses capability-based authorization (Innovation 4).
+///
+/// `TRACEMASK <mask>`
+///
+/// Lists users matching a nick, hostmask, IP wildcard or CIDR mask, showing
+/// their real host and IP. Output is capped by `limits.max_tracemask_results`.
+pub struct TraceMaskHandler;
+
+#[async_trait]
+impl PostRegHandler for TraceMaskHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, RegisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let Some(_cap) = require_oper_cap!(ctx, "TRACEMASK", request_kill_cap) else {
+            return Ok(());
+        };
+        let Some(mask) = require_arg_or_reply!(ctx, msg, 0, "TRACEMASK") else {
+            return Ok(());
+        };
+
+        let cap = ctx.matrix.config.limits.max_tracemask_results;
+        let matches = resolve_mask_targets(ctx.matrix, &TargetMask::parse(mask), cap, None).await;
+
+        for uid in &matches.uids {
+            let user_arc = ctx
+                .matrix
+                .user_manager
+                .users
+                .get(uid)
+                .map(|u| u.value().clone());
+            let Some(user_arc) = user_arc else {
+                continue;
+            };
+            let (numeric, class, target, ip) = {
+                let user = user_arc.read().await;
+                let (numeric, class) = if user.modes.oper {
+                    (Response::RPL_TRACEOPERATOR, "Oper")
+                } else {
+                    (Response::RPL_TRACEUSER, "User")
+                };
+                (
+                    numeric,
+                    class,
+                    format!("{}[{}@{}]", user.nick, user.user, user.host),
+                    user.ip.clone(),
+                )
+            };
+            let reply = server_reply(
+                ctx.server_name(),
+                numeric,
+                vec![ctx.nick().to_string(), class.to_string(), target, ip],
+            );
+            ctx.sender.send(reply).await?;
+        }
+
+        if matches.truncated() {
+            let notice = server_reply(
+                ctx.server_name(),
+                Response::RPL_TRYAGAIN,
+                vec![
+                    ctx.nick().to_string(),
+                    "TRACEMASK".to_string(),
+                    format!(
+                        "Output truncated, {} of {} matches shown",
+                        matches.uids.len(),
+                        matches.total
+                    ),
+                ],
+            );
+            ctx.sender.send(notice).await?;
+        }
+
+        let end_reply = server_reply(
+            ctx.server_name(),
+            Response::RPL_TRACEEND,
+            vec![
+                ctx.nick().to_string(),
+                mask.to_string(),
+                "End of TRACEMASK".to_string(),
+            ],
+        );
+        ctx.sender.send(end_reply).await?;
+
+        tracing::debug!(oper = %ctx.nick(), mask = %mask, matches = matches.total, "TRACEMASK command executed");
+
+        Ok(())
+    }
+}
@@ -67,7 +67,7 @@ impl PostRegHandler for VhostHandler {
         if let Some(target_user_ref) = ctx.matrix.user_manager.users.get(&target_uid) {
             let mut target_user = target_user_ref.write().await;
             let old_vhost = target_user.visible_host.clone();
-            target_user.visible_host = new_vhost.to_string();
+            target_user.set_visible_host(new_vhost.to_string(), ctx.matrix.clock());
 
             let reply = server_notice(
                 server_name,
@@ -115,6 +115,10 @@ impl PostRegHandler for VhostHandler {
                 "VHOST changed"
             );
         }
+        ctx.matrix
+            .user_manager
+            .notify_observer(&target_uid, None)
+            .await;
 
         Ok(())
     }
@@ -34,7 +34,7 @@ impl PostRegHandler for LinksHandler {
                 nick.clone(),
                 server_name.to_string(),
                 server_name.to_string(),
-                format!("0 {}", ctx.matrix.server_info.description),
+                format!("0 {}", ctx.matrix.hot_config.read().description),
             ],
         )
         .await?;
@@ -88,7 +88,7 @@ impl PostRegHandler for LinksHandler {
         let services_name = if server_name == "My.Little.Server" {
             "My.Little.Services".to_string()
         } else {
-            format!("services.{}", ctx.matrix.server_info.network)
+            format!("services.{}", ctx.matrix.hot_config.read().network)
         };
 
         ctx.send_reply(
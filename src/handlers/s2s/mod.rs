@@ -3,10 +3,8 @@
 pub use connect::ConnectHandler;
 pub use links::LinksHandler;
 pub use map::MapHandler;
-pub use squit::SquitHandler;
 
 mod connect;
 pub mod kline;
 mod links;
 mod map;
-mod squit;
@@ -0,0 +1,115 @@
+use crate::handlers::core::traits::ServerHandler;
+use crate::handlers::server::propagate::relay_to_peers;
+use crate::handlers::server::source::extract_source_uid;
+use crate::handlers::user::monitor::notify_extended_monitor_watchers;
+use crate::handlers::util::helpers::broadcast_user_update;
+use crate::handlers::{Context, HandlerError, HandlerResult};
+use crate::state::ServerState;
+use crate::state::dashmap_ext::DashMapExt;
+use async_trait::async_trait;
+use slirc_proto::sync::clock::ServerId;
+use slirc_proto::{Command, Message, MessageRef, Prefix};
+use tracing::warn;
+
+/// Handler for a remote user setting or clearing their away status.
+///
+/// Format: `:<uid> AWAY [:<message>]`
+pub struct AwayHandler;
+
+#[async_trait]
+impl ServerHandler for AwayHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, ServerState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let uid = extract_source_uid(msg).ok_or(HandlerError::NeedMoreParams)?;
+        let away_msg = msg
+            .arg(0)
+            .filter(|s| !s.is_empty())
+            .map(ToString::to_string);
+
+        let Some(user_arc) = ctx.matrix.user_manager.users.get_cloned(uid) else {
+            warn!(uid = %uid, "Received AWAY for unknown user");
+            return Ok(());
+        };
+        let (nick, channels, away_broadcast) = {
+            let mut user = user_arc.write().await;
+            user.set_away(away_msg.clone(), ctx.matrix.clock());
+            let away_broadcast = Message {
+                tags: None,
+                prefix: Some(Prefix::new(
+                    user.nick.clone(),
+                    user.user.clone(),
+                    user.visible_host.clone(),
+                )),
+                command: Command::AWAY(away_msg),
+            };
+            (
+                user.nick.clone(),
+                user.channels.iter().cloned().collect::<Vec<_>>(),
+                away_broadcast,
+            )
+        };
+
+        let source = ServerId::new(uid.get(..3).unwrap_or(uid).to_string());
+        ctx.matrix
+            .user_manager
+            .notify_observer(uid, Some(source))
+            .await;
+
+        broadcast_user_update(ctx, &away_broadcast, &channels, Some("away-notify"), None).await;
+        notify_extended_monitor_watchers(ctx.matrix, &nick, away_broadcast, "away-notify").await;
+
+        relay_to_peers(ctx, msg).await;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::dashmap_ext::DashMapExt;
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::Command;
+
+    #[tokio::test]
+    async fn remote_away_sets_status_notifies_channel_and_relays() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut origin = tm.peer("00A").await;
+        let mut other = tm.peer("00B").await;
+        let mut alice = tm.user("alice").cap("away-notify").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.remote_user("00A", "00AAAAAAA", "bob").await;
+        tm.run_server("00A", ":00AAAAAAA JOIN 1700000000 #chan +")
+            .await;
+
+        tm.run_server("00A", ":00AAAAAAA AWAY :at lunch").await;
+
+        let seen = alice
+            .recv_matching(|m| matches!(&m.command, Command::AWAY(Some(t)) if t == "at lunch"))
+            .await
+            .expect("away-notify member sees the away change");
+        assert_eq!(seen.source_nickname(), Some("bob"));
+        let user = tm
+            .matrix
+            .user_manager
+            .users
+            .get_cloned("00AAAAAAA")
+            .unwrap();
+        assert_eq!(user.read().await.away.as_deref(), Some("at lunch"));
+
+        let relayed: Vec<_> = std::iter::from_fn(|| other.try_recv().ok()).collect();
+        assert!(
+            relayed
+                .iter()
+                .any(|m| matches!(&m.command, Command::AWAY(Some(_))))
+        );
+        let echoed: Vec<_> = std::iter::from_fn(|| origin.try_recv().ok()).collect();
+        assert!(
+            !echoed
+                .iter()
+                .any(|m| matches!(&m.command, Command::AWAY(_)))
+        );
+    }
+}
@@ -48,8 +48,16 @@ impl PreRegHandler for ServerHandshakeHandler {
             .find(|l| l.name == name)
             .ok_or(HandlerError::AccessDenied)?;
 
+        // A server port with a bind password replaces the link block's
+        let password = ctx
+            .state
+            .listener
+            .as_ref()
+            .and_then(|listener| listener.password.clone())
+            .unwrap_or_else(|| link_block.password.clone());
+
         if let Some(pass) = &ctx.state.pass_received {
-            if pass != &link_block.password {
+            if pass != &password {
                 warn!("Invalid password for server {}", name);
                 return Err(HandlerError::AccessDenied);
             }
@@ -77,7 +85,7 @@ impl PreRegHandler for ServerHandshakeHandler {
             // Send credentials
             // PASS <password> TS 6 :<sid>
             let pass_cmd = slirc_proto::Command::PassTs6 {
-                password: link_block.password.clone(),
+                password,
                 sid: ctx.matrix.server_info.sid.as_str().to_string(),
             };
             ctx.sender
@@ -1,10 +1,13 @@
 use crate::handlers::core::traits::ServerHandler;
 use crate::handlers::util::helpers::collect_message_args;
 use crate::handlers::{Context, HandlerError, HandlerResult};
-use crate::state::ServerState;
+use crate::services::effect::{ServiceEffect, apply_effect_no_sender};
+use crate::state::dashmap_ext::DashMapExt;
+use crate::state::{Matrix, ServerState};
+use crate::sync::{TopologyGraph, anti_entropy, delta, retirement, stability};
 use async_trait::async_trait;
 use slirc_proto::sync::clock::ServerId;
-use slirc_proto::{Command, Message, MessageRef};
+use slirc_proto::{Command, Message, MessageRef, Mode};
 use std::sync::Arc;
 use tracing::{debug, warn};
 
@@ -16,7 +19,13 @@ use tracing::{debug, warn};
 /// Format: `:<source> ENCAP <target> <subcommand> [args...]`
 ///
 /// - `<target>` can be `*` (broadcast to all) or a specific server SID/name
-/// - `<subcommand>` is the encapsulated command (e.g., `CHGHOST`, `REALHOST`, etc.)
+/// - `<subcommand>` is the encapsulated command (e.g., `CHGHOST`, `REALHOST`, `RSFNC`)
+///
+/// ENCAPs addressed to another server by SID are forwarded along the route.
+///
+/// `SVSNICK`, `SVSMODE`, `SVSHOST` and `SU` are accepted only from the
+/// external services server named in `[services] external`, and are applied
+/// to local users by the server that owns them.
 pub struct EncapHandler;
 
 #[async_trait]
@@ -57,6 +66,7 @@ impl ServerHandler for EncapHandler {
                     {
                         let mut user = user_arc.write().await;
                         user.visible_host = new_host.to_string();
+                        ctx.matrix.user_manager.index.update(&user);
                         debug!(uid = %uid, new_host = %new_host, "Applied CHGHOST");
                     }
                 }
@@ -67,6 +77,7 @@ impl ServerHandler for EncapHandler {
                     {
                         let mut user = user_arc.write().await;
                         user.user = new_ident.to_string();
+                        ctx.matrix.user_manager.index.update(&user);
                         debug!(uid = %uid, new_ident = %new_ident, "Applied CHGIDENT");
                     }
                 }
@@ -77,17 +88,20 @@ impl ServerHandler for EncapHandler {
                     {
                         let mut user = user_arc.write().await;
                         user.host = real_host.to_string();
+                        ctx.matrix.user_manager.index.update(&user);
                         debug!(uid = %uid, real_host = %real_host, "Applied REALHOST");
                     }
                 }
                 "LOGIN" => {
-                    // ENCAP * LOGIN <uid> <account>
+                    // ENCAP * LOGIN <uid> <account>, where `*` logs out
                     if let (Some(uid), Some(account)) = (msg.arg(2), msg.arg(3))
                         && let Some(user_arc) = ctx.matrix.user_manager.users.get(uid)
                     {
                         let mut user = user_arc.write().await;
-                        user.account = Some(account.to_string());
-                        debug!(uid = %uid, account = %account, "Applied LOGIN");
+                        let account = (account != "*").then(|| account.to_string());
+                        debug!(uid = %uid, account = ?account, "Applied LOGIN");
+                        user.set_account(account, ctx.matrix.clock());
+                        ctx.matrix.user_manager.index.update(&user);
                     }
                 }
                 "CERTFP" => {
@@ -100,6 +114,125 @@ impl ServerHandler for EncapHandler {
                         debug!(uid = %uid, certfp = %fp, "Applied CERTFP");
                     }
                 }
+                "RSFNC" => {
+                    // ENCAP <sid> RSFNC <uid> <new_nick>
+                    // Forced rename requested by a peer (e.g. a Q-lined nick).
+                    // Only the owning server applies it; the change then
+                    // propagates to the network as a normal user update.
+                    if let (Some(uid), Some(new_nick)) = (msg.arg(2), msg.arg(3))
+                        && uid.starts_with(ctx.matrix.server_info.sid.as_str())
+                    {
+                        force_local_nick(ctx.matrix, uid, new_nick).await;
+                    }
+                }
+                "SVSNICK" | "SVSMODE" | "SVSHOST" | "SU"
+                    if !is_from_services(ctx.matrix, &source) =>
+                {
+                    warn!(
+                        source = %source,
+                        subcommand = %subcommand,
+                        "Ignoring services ENCAP from a server that is not the configured services"
+                    );
+                }
+                "SVSNICK" => {
+                    // ENCAP * SVSNICK <uid> <new_nick>
+                    if let (Some(uid), Some(new_nick)) = (msg.arg(2), msg.arg(3))
+                        && uid.starts_with(ctx.matrix.server_info.sid.as_str())
+                    {
+                        force_local_nick(ctx.matrix, uid, new_nick).await;
+                    }
+                }
+                "SVSMODE" => {
+                    // ENCAP * SVSMODE <uid> <modes>
+                    if let (Some(uid), Some(modes)) = (msg.arg(2), msg.arg(3))
+                        && uid.starts_with(ctx.matrix.server_info.sid.as_str())
+                    {
+                        apply_services_modes(ctx.matrix, uid, modes).await;
+                    }
+                }
+                "SVSHOST" => {
+                    // ENCAP * SVSHOST <uid> <host>, where `*` restores the cloak
+                    if let (Some(uid), Some(host)) = (msg.arg(2), msg.arg(3))
+                        && uid.starts_with(ctx.matrix.server_info.sid.as_str())
+                    {
+                        let vhost = (host != "*").then(|| host.to_string());
+                        apply_effect_no_sender(
+                            ctx.matrix,
+                            uid,
+                            ServiceEffect::SetVhost {
+                                target_uid: uid.to_string(),
+                                vhost,
+                            },
+                        )
+                        .await;
+                        debug!(uid = %uid, host = %host, "Applied SVSHOST");
+                    }
+                }
+                "SU" => {
+                    // ENCAP * SU <uid> [account]; no account logs out
+                    if let Some(uid) = msg.arg(2)
+                        && uid.starts_with(ctx.matrix.server_info.sid.as_str())
+                    {
+                        let account = msg.arg(3).filter(|a| !a.is_empty() && *a != "*");
+                        services_login(ctx.matrix, uid, account).await;
+                        debug!(uid = %uid, account = ?account, "Applied SU");
+                    }
+                }
+                "DIGEST" => {
+                    // ENCAP <sid> DIGEST <kind> <hashes>
+                    if let (Some(kind), Some(hashes)) = (msg.arg(2), msg.arg(3)) {
+                        let peer = ServerId::new(ctx.state.sid.clone());
+                        anti_entropy::handle_digest(ctx.matrix, &peer, kind, hashes).await;
+                    }
+                }
+                "DIGESTREQ" => {
+                    // ENCAP <sid> DIGESTREQ <kind> <bucket> :<held>
+                    if let (Some(kind), Some(bucket)) = (msg.arg(2), msg.arg(3)) {
+                        let peer = ServerId::new(ctx.state.sid.clone());
+                        let held = msg.arg(4).unwrap_or("");
+                        anti_entropy::handle_digest_request(ctx.matrix, &peer, kind, bucket, held)
+                            .await;
+                    }
+                }
+                "DIGESTCHAN" => {
+                    // ENCAP <sid> DIGESTCHAN <channel> :<uids>
+                    if let Some(channel) = msg.arg(2) {
+                        let peer = ServerId::new(ctx.state.sid.clone());
+                        let uids = msg.arg(3).unwrap_or("");
+                        anti_entropy::handle_digest_members(ctx.matrix, &peer, channel, uids).await;
+                    }
+                }
+                "UDELTA" => {
+                    // ENCAP * UDELTA <delta>
+                    // Relayed per link by the delta handler, since peers
+                    // without the DELTA capab need a UID instead.
+                    if let Some(payload) = msg.arg(2) {
+                        let peer = ServerId::new(ctx.state.sid.clone());
+                        delta::handle_user_delta(ctx.matrix, payload, &source, &peer).await;
+                    }
+                    return Ok(());
+                }
+                "STABLE" => {
+                    // ENCAP * STABLE :<sid>=<millis> ...
+                    if let Some(vector) = msg.arg(2) {
+                        let origin = ServerId::new(source.clone());
+                        stability::handle_stable(ctx.matrix, &origin, vector);
+                    }
+                }
+                "RETIRE" => {
+                    // ENCAP * RETIRE <sid> <millis>
+                    if let (Some(sid), Some(at)) = (msg.arg(2), msg.arg(3)) {
+                        let origin = ServerId::new(source.clone());
+                        retirement::handle_retire(ctx.matrix, &origin, sid, at).await;
+                    }
+                }
+                "RETIRED" => {
+                    // ENCAP * RETIRED <sid>
+                    if let Some(sid) = msg.arg(2) {
+                        let origin = ServerId::new(source.clone());
+                        retirement::handle_retired(ctx.matrix, &origin, sid);
+                    }
+                }
                 _ => {
                     // Unknown subcommand - log and continue
                     warn!(subcommand = %subcommand, "Unknown ENCAP subcommand");
@@ -108,7 +241,25 @@ impl ServerHandler for EncapHandler {
         }
 
         // Propagate to other servers if target is broadcast or not us
-        if target == "*" {
+        if !is_for_us {
+            // Targeted ENCAP for another server: forward along the route.
+            if let Some(link) = ctx
+                .matrix
+                .sync_manager
+                .get_next_hop(&ServerId::new(target.to_string()))
+            {
+                let encap_msg = Message {
+                    tags: None,
+                    prefix: Some(slirc_proto::Prefix::new_from_str(&source)),
+                    command: Command::ENCAP(
+                        target.to_string(),
+                        subcommand.to_string(),
+                        collect_message_args(msg, 2),
+                    ),
+                };
+                let _ = link.tx.send(Arc::new(encap_msg)).await;
+            }
+        } else if target == "*" {
             let source_sid = ServerId::new(ctx.state.sid.clone());
 
             // Reconstruct the ENCAP message for propagation
@@ -129,3 +280,196 @@ impl ServerHandler for EncapHandler {
         Ok(())
     }
 }
+
+/// Applies a peer- or services-requested forced nick change to a local user.
+pub(super) async fn force_local_nick(matrix: &Arc<Matrix>, uid: &str, new_nick: &str) {
+    let Some(user_arc) = matrix.user_manager.users.get_cloned(uid) else {
+        return;
+    };
+    let old_nick = user_arc.read().await.nick.clone();
+    if old_nick == new_nick {
+        return;
+    }
+
+    // Refuse to rename onto a nick held by someone else.
+    if let Some(holders) = matrix
+        .user_manager
+        .nicks
+        .get(&slirc_proto::irc_to_lower(new_nick))
+        && holders.iter().any(|u| u != uid)
+    {
+        warn!(uid = %uid, new_nick = %new_nick, "Forced nick change target in use");
+        return;
+    }
+
+    apply_effect_no_sender(
+        matrix,
+        &old_nick,
+        ServiceEffect::ForceNick {
+            target_uid: uid.to_string(),
+            old_nick: old_nick.clone(),
+            new_nick: new_nick.to_string(),
+        },
+    )
+    .await;
+    matrix.user_manager.notify_observer(uid, None).await;
+    debug!(uid = %uid, old_nick = %old_nick, new_nick = %new_nick, "Applied forced nick change");
+}
+
+/// Whether `source` (a SID, UID or server name) is the configured external services server.
+fn is_from_services(matrix: &Matrix, source: &str) -> bool {
+    let services = &matrix.config.services;
+    if services.is_services_server(source) {
+        return true;
+    }
+    TopologyGraph::origin_of(source)
+        .and_then(|sid| {
+            matrix
+                .sync_manager
+                .topology
+                .servers
+                .get(&sid)
+                .map(|info| info.name.clone())
+        })
+        .is_some_and(|name| services.is_services_server(&name))
+}
+
+/// Applies a services login (`account`) or logout (`None`) to a local user.
+async fn services_login(matrix: &Arc<Matrix>, uid: &str, account: Option<&str>) {
+    let Some(user_arc) = matrix.user_manager.users.get_cloned(uid) else {
+        return;
+    };
+    let effect = match account {
+        Some(account) => {
+            let user = user_arc.read().await;
+            ServiceEffect::AccountIdentify {
+                target_uid: uid.to_string(),
+                account: account.to_string(),
+                account_id: user.account_id,
+                metadata: user.metadata.clone(),
+            }
+        }
+        None => ServiceEffect::AccountClear {
+            target_uid: uid.to_string(),
+        },
+    };
+    apply_effect_no_sender(matrix, uid, effect).await;
+}
+
+/// Applies a services user mode change to a local user.
+///
+/// `r` marks a user as logged in, so `+r` identifies them (to their nick if
+/// they have no account yet) and `-r` logs them out. Other modes are applied
+/// as a `ForceMode`.
+async fn apply_services_modes(matrix: &Arc<Matrix>, uid: &str, modes: &str) {
+    let Some(user_arc) = matrix.user_manager.users.get_cloned(uid) else {
+        return;
+    };
+    let mut adding = true;
+    let mut rest = String::new();
+    for mode in modes.chars() {
+        match mode {
+            '+' => adding = true,
+            '-' => adding = false,
+            'r' if adding => {
+                let account = {
+                    let user = user_arc.read().await;
+                    user.account.clone().unwrap_or_else(|| user.nick.clone())
+                };
+                services_login(matrix, uid, Some(&account)).await;
+            }
+            'r' => services_login(matrix, uid, None).await,
+            other => {
+                rest.push(if adding { '+' } else { '-' });
+                rest.push(other);
+            }
+        }
+    }
+    if !rest.is_empty() {
+        match Mode::as_user_modes(&[&rest]) {
+            Ok(modes) => {
+                let effect = ServiceEffect::ForceMode {
+                    target_uid: uid.to_string(),
+                    modes,
+                };
+                apply_effect_no_sender(matrix, uid, effect).await;
+            }
+            Err(_) => warn!(uid = %uid, modes = %rest, "Unparseable SVSMODE modes"),
+        }
+    }
+    debug!(uid = %uid, modes = %modes, "Applied SVSMODE");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+
+    #[tokio::test]
+    async fn services_encap_applies_to_local_users() {
+        let tm = TestMatrixBuilder::new()
+            .config(|c| c.services.external = Some("00s.test".to_string()))
+            .build()
+            .await;
+        let _services = tm.peer("00S").await;
+        let alice = tm.user("alice").build().await;
+
+        tm.run_server("00S", &format!(":00S ENCAP * SU {} alice", alice.uid))
+            .await;
+        tm.run_server(
+            "00S",
+            &format!(":00SAAAAAA ENCAP * SVSHOST {} alice.users.test", alice.uid),
+        )
+        .await;
+        tm.run_server("00S", &format!(":00S ENCAP * SVSNICK {} alice2", alice.uid))
+            .await;
+
+        let user_arc = tm
+            .matrix
+            .user_manager
+            .users
+            .get(&alice.uid)
+            .unwrap()
+            .clone();
+        let user = user_arc.read().await;
+        assert_eq!(user.account.as_deref(), Some("alice"));
+        assert!(user.modes.registered);
+        assert_eq!(user.visible_host, "alice.users.test");
+        assert_eq!(user.nick, "alice2");
+        drop(user);
+
+        tm.run_server("00S", &format!(":00S ENCAP * SVSMODE {} -r+i", alice.uid))
+            .await;
+        let user = user_arc.read().await;
+        assert!(user.account.is_none());
+        assert!(user.modes.invisible);
+    }
+
+    #[tokio::test]
+    async fn services_encap_from_other_servers_is_ignored() {
+        let tm = TestMatrixBuilder::new()
+            .config(|c| c.services.external = Some("00s.test".to_string()))
+            .build()
+            .await;
+        let _peer = tm.peer("00A").await;
+        let alice = tm.user("alice").build().await;
+
+        tm.run_server("00A", &format!(":00A ENCAP * SU {} mallory", alice.uid))
+            .await;
+        tm.run_server(
+            "00A",
+            &format!(":00A ENCAP * SVSNICK {} mallory", alice.uid),
+        )
+        .await;
+
+        let user_arc = tm
+            .matrix
+            .user_manager
+            .users
+            .get(&alice.uid)
+            .unwrap()
+            .clone();
+        let user = user_arc.read().await;
+        assert!(user.account.is_none());
+        assert_eq!(user.nick, "alice");
+    }
+}
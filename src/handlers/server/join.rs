@@ -0,0 +1,163 @@
+use crate::handlers::core::traits::ServerHandler;
+use crate::handlers::server::propagate::relay_to_peers;
+use crate::handlers::server::source::extract_source_uid;
+use crate::handlers::{Context, HandlerError, HandlerResult};
+use crate::state::ServerState;
+use crate::state::actor::ChannelEvent;
+use crate::state::dashmap_ext::DashMapExt;
+use async_trait::async_trait;
+use slirc_proto::sync::clock::ServerId;
+use slirc_proto::{ChannelExt, Command, Message, MessageRef, Prefix, irc_to_lower};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Handler for a remote user joining a channel.
+///
+/// Format: `:<uid> JOIN <ts> <channel> +` (TS6), or `:<uid> JOIN <channel>`.
+///
+/// The user joins without any prefix modes; ops granted on the remote side
+/// arrive separately as TMODE.
+pub struct JoinHandler;
+
+#[async_trait]
+impl ServerHandler for JoinHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, ServerState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let uid = extract_source_uid(msg).ok_or(HandlerError::NeedMoreParams)?;
+        let first = msg.arg(0).ok_or(HandlerError::NeedMoreParams)?;
+        let (ts, channel_name) = if first.is_channel_name() {
+            (0, first)
+        } else {
+            let ts = first.parse::<u64>().map_err(|_| {
+                HandlerError::ProtocolError(format!("Invalid timestamp: {}", first))
+            })?;
+            (ts, msg.arg(1).ok_or(HandlerError::NeedMoreParams)?)
+        };
+
+        let Some(user_arc) = ctx.matrix.user_manager.users.get_cloned(uid) else {
+            warn!(uid = %uid, channel = %channel_name, "Received JOIN for unknown user");
+            return Ok(());
+        };
+        let (nick, join_msg_extended, join_msg_standard) = {
+            let user = user_arc.read().await;
+            let prefix = Prefix::new(
+                user.nick.clone(),
+                user.user.clone(),
+                user.visible_host.clone(),
+            );
+            let extended = Message {
+                tags: None,
+                prefix: Some(prefix.clone()),
+                command: Command::JOIN(
+                    channel_name.to_string(),
+                    Some(user.account.clone().unwrap_or_else(|| "*".to_string())),
+                    Some(user.realname.clone()),
+                ),
+            };
+            let standard = Message {
+                tags: None,
+                prefix: Some(prefix),
+                command: Command::JOIN(channel_name.to_string(), None, None),
+            };
+            (user.nick.clone(), extended, standard)
+        };
+
+        let tx = ctx
+            .matrix
+            .channel_manager
+            .get_or_create_actor(channel_name.to_string(), Arc::downgrade(ctx.matrix))
+            .await;
+
+        let event = ChannelEvent::RemoteJoin {
+            uid: uid.to_string(),
+            nick,
+            ts,
+            join_msg_extended: Box::new(join_msg_extended),
+            join_msg_standard: Box::new(join_msg_standard),
+            source: ServerId::new(uid.get(..3).unwrap_or(uid).to_string()),
+        };
+        if let Err(e) = tx.send(event).await {
+            warn!(channel = %channel_name, error = %e, "Failed to send remote JOIN to channel actor");
+            return Ok(());
+        }
+
+        user_arc
+            .write()
+            .await
+            .channels
+            .insert(irc_to_lower(channel_name));
+
+        relay_to_peers(ctx, msg).await;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::actor::ChannelEvent;
+    use crate::state::dashmap_ext::DashMapExt;
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::Command;
+
+    #[tokio::test]
+    async fn remote_join_adds_member_notifies_channel_and_relays() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut origin = tm.peer("00A").await;
+        let mut other = tm.peer("00B").await;
+        let mut alice = tm.user("alice").cap("extended-join").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.remote_user("00A", "00AAAAAAA", "bob").await;
+
+        tm.run_server("00A", ":00AAAAAAA JOIN 1700000000 #chan +")
+            .await;
+
+        let seen = alice
+            .recv_matching(|m| matches!(&m.command, Command::JOIN(c, _, _) if c == "#chan"))
+            .await
+            .expect("channel member sees the join");
+        assert_eq!(seen.source_nickname(), Some("bob"));
+        assert!(matches!(
+            &seen.command,
+            Command::JOIN(_, Some(account), Some(realname))
+                if account == "*" && realname == "Remote bob"
+        ));
+
+        let user = tm
+            .matrix
+            .user_manager
+            .users
+            .get_cloned("00AAAAAAA")
+            .unwrap();
+        assert!(user.read().await.channels.contains("#chan"));
+        let channel_tx = tm
+            .matrix
+            .channel_manager
+            .channels
+            .get("#chan")
+            .map(|c| c.value().clone())
+            .unwrap();
+        let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+        channel_tx
+            .send(ChannelEvent::GetMembers { reply_tx })
+            .await
+            .unwrap();
+        assert!(reply_rx.await.unwrap().contains_key("00AAAAAAA"));
+
+        let relayed: Vec<_> = std::iter::from_fn(|| other.try_recv().ok()).collect();
+        assert!(
+            relayed
+                .iter()
+                .any(|m| matches!(&m.command, Command::JOIN(..)))
+        );
+        let echoed: Vec<_> = std::iter::from_fn(|| origin.try_recv().ok()).collect();
+        assert!(
+            !echoed
+                .iter()
+                .any(|m| matches!(&m.command, Command::JOIN(..)))
+        );
+    }
+}
@@ -1,7 +1,9 @@
 use crate::handlers::core::traits::ServerHandler;
+use crate::handlers::server::propagate::relay_to_peers;
 use crate::handlers::{Context, HandlerError, HandlerResult};
 use crate::state::ServerState;
 use crate::state::actor::ChannelEvent;
+use crate::state::dashmap_ext::DashMapExt;
 use async_trait::async_trait;
 use slirc_proto::MessageRef;
 use tracing::warn;
@@ -51,6 +53,56 @@ impl ServerHandler for KickHandler {
             warn!(channel = %channel, target = %target, "Received KICK for unknown channel");
         }
 
+        if let Some(user_arc) = ctx.matrix.user_manager.users.get_cloned(target) {
+            user_arc.write().await.channels.remove(&channel_lower);
+        }
+
+        relay_to_peers(ctx, msg).await;
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::state::dashmap_ext::DashMapExt;
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::Command;
+
+    #[tokio::test]
+    async fn remote_kick_shows_nicks_updates_target_and_relays() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut origin = tm.peer("00A").await;
+        let mut other = tm.peer("00B").await;
+        let mut alice = tm.user("alice").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.remote_user("00A", "00AAAAAAA", "bob").await;
+        tm.run_server("00A", ":00AAAAAAA JOIN 1700000000 #chan +")
+            .await;
+
+        tm.run_server("00A", &format!(":00AAAAAAA KICK #chan {} :out", alice.uid))
+            .await;
+
+        let seen = alice
+            .recv_matching(|m| matches!(&m.command, Command::KICK(..)))
+            .await
+            .expect("kicked member sees the kick");
+        assert_eq!(seen.source_nickname(), Some("bob"));
+        assert!(matches!(&seen.command, Command::KICK(c, t, _) if c == "#chan" && t == "alice"));
+        let user = tm.matrix.user_manager.users.get_cloned(&alice.uid).unwrap();
+        assert!(user.read().await.channels.is_empty());
+
+        let relayed: Vec<_> = std::iter::from_fn(|| other.try_recv().ok()).collect();
+        assert!(
+            relayed
+                .iter()
+                .any(|m| matches!(&m.command, Command::KICK(..)))
+        );
+        let echoed: Vec<_> = std::iter::from_fn(|| origin.try_recv().ok()).collect();
+        assert!(
+            !echoed
+                .iter()
+                .any(|m| matches!(&m.command, Command::KICK(..)))
+        );
+    }
+}
@@ -71,3 +71,41 @@ impl ServerHandler for KillHandler {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::Command;
+
+    #[tokio::test]
+    async fn remote_kill_disconnects_local_user_and_relays() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut origin = tm.peer("00A").await;
+        let mut other = tm.peer("00B").await;
+        let mut alice = tm.user("alice").build().await;
+
+        tm.run_server("00A", &format!(":00AAAAAAA KILL {} :spam", alice.uid))
+            .await;
+
+        assert!(
+            alice
+                .recv_matching(|m| matches!(&m.command, Command::ERROR(_)))
+                .await
+                .is_some()
+        );
+        assert!(!tm.matrix.user_manager.users.contains_key(&alice.uid));
+
+        let relayed: Vec<_> = std::iter::from_fn(|| other.try_recv().ok()).collect();
+        assert!(
+            relayed
+                .iter()
+                .any(|m| matches!(&m.command, Command::KILL(_, r) if r == "spam"))
+        );
+        let echoed: Vec<_> = std::iter::from_fn(|| origin.try_recv().ok()).collect();
+        assert!(
+            !echoed
+                .iter()
+                .any(|m| matches!(&m.command, Command::KILL(..)))
+        );
+    }
+}
@@ -1,14 +1,24 @@
+pub mod away;
 pub mod base;
 pub mod capab;
 pub mod encap;
+pub mod join;
 pub mod kick;
 pub mod kill;
+pub mod mode;
+pub mod nick;
+pub mod part;
+pub mod propagate;
+pub mod quit;
 pub mod routing;
 pub mod sid;
 pub mod sjoin;
+pub mod squit;
 pub mod source;
 pub mod svinfo;
+pub mod svs;
 pub mod tb;
 pub mod tmode;
 pub mod topic;
 pub mod uid;
+pub mod whois;
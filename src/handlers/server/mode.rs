@@ -0,0 +1,197 @@
+use crate::handlers::core::traits::ServerHandler;
+use crate::handlers::server::propagate::relay_to_peers;
+use crate::handlers::server::source::extract_source_uid;
+use crate::handlers::util::helpers::collect_message_args;
+use crate::handlers::{Context, HandlerError, HandlerResult};
+use crate::state::actor::ChannelEvent;
+use crate::state::dashmap_ext::DashMapExt;
+use crate::state::{ServerState, UserModes};
+use async_trait::async_trait;
+use slirc_proto::sync::clock::ServerId;
+use slirc_proto::{ChannelExt, MessageRef, irc_to_lower};
+use tracing::warn;
+
+/// Handler for MODE from a peer server.
+///
+/// Format: `:<uid> MODE <uid> :<modes>` for user modes, or
+/// `:<source> MODE <channel> <modes> [args...]` from peers that do not use
+/// TMODE. The owning server has already authorized the change, so user modes
+/// are applied as given, including the server-set ones (+o, +r, +Z, +S).
+pub struct ModeHandler;
+
+#[async_trait]
+impl ServerHandler for ModeHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, ServerState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let target = msg.arg(0).ok_or(HandlerError::NeedMoreParams)?;
+        let modes = msg.arg(1).ok_or(HandlerError::NeedMoreParams)?;
+
+        if target.is_channel_name() {
+            let channel_tx = ctx
+                .matrix
+                .channel_manager
+                .channels
+                .get(&irc_to_lower(target))
+                .map(|c| c.value().clone());
+            let Some(channel_tx) = channel_tx else {
+                warn!(channel = %target, "Received MODE for unknown channel");
+                return Ok(());
+            };
+
+            let setter = msg
+                .prefix
+                .as_ref()
+                .map(|p| p.raw.to_string())
+                .unwrap_or_else(|| ctx.state.sid.clone());
+            let event = ChannelEvent::RemoteMode {
+                ts: chrono::Utc::now().timestamp() as u64,
+                setter,
+                modes: modes.to_string(),
+                args: collect_message_args(msg, 2),
+            };
+            if let Err(e) = channel_tx.send(event).await {
+                warn!(channel = %target, error = %e, "Failed to send remote MODE to channel actor");
+            }
+        } else {
+            let Some(user_arc) = ctx.matrix.user_manager.users.get_cloned(target) else {
+                warn!(target = %target, "Received MODE for unknown user");
+                return Ok(());
+            };
+            if extract_source_uid(msg).is_some_and(|uid| uid != target) {
+                warn!(target = %target, "Ignoring user MODE set by another user");
+                return Ok(());
+            }
+
+            let (was_oper, is_oper, was_service, is_service) = {
+                let mut user = user_arc.write().await;
+                let (was_oper, was_service) = (user.modes.oper, user.modes.service);
+                apply_remote_user_modes(&mut user.modes, modes);
+                user.stamp_modes(modes.chars(), ctx.matrix.clock());
+                (was_oper, user.modes.oper, was_service, user.modes.service)
+            };
+            if was_service != is_service {
+                ctx.matrix.user_manager.set_service(target, is_service);
+                // Services are not counted as users
+                if is_service {
+                    ctx.matrix.stats_manager.remote_user_disconnected();
+                } else {
+                    ctx.matrix.stats_manager.remote_user_connected();
+                }
+            }
+            if was_oper != is_oper {
+                if is_oper {
+                    ctx.matrix.stats_manager.remote_user_opered();
+                } else {
+                    ctx.matrix.stats_manager.remote_user_deopered();
+                }
+            }
+
+            let source = ServerId::new(target.get(..3).unwrap_or(target).to_string());
+            ctx.matrix
+                .user_manager
+                .notify_observer(target, Some(source))
+                .await;
+        }
+
+        relay_to_peers(ctx, msg).await;
+
+        Ok(())
+    }
+}
+
+/// Apply a user mode string such as `+iw-B` as received from a peer.
+///
+/// Unknown mode letters are skipped. Server notice masks are local to each
+/// server and are not carried over links.
+fn apply_remote_user_modes(user_modes: &mut UserModes, modes: &str) {
+    let mut adding = true;
+    for c in modes.chars() {
+        match c {
+            '+' => adding = true,
+            '-' => adding = false,
+            'i' => user_modes.invisible = adding,
+            'w' => user_modes.wallops = adding,
+            'o' => {
+                user_modes.oper = adding;
+                if !adding {
+                    user_modes.oper_type = None;
+                }
+            }
+            'r' => user_modes.registered = adding,
+            'Z' => user_modes.secure = adding,
+            'R' => user_modes.registered_only = adding,
+            'T' => user_modes.no_ctcp = adding,
+            'B' => user_modes.bot = adding,
+            'S' => user_modes.service = adding,
+            'p' => user_modes.hide_channels = adding,
+            'd' => user_modes.deaf = adding,
+            'g' => user_modes.caller_id = adding,
+            'N' => user_modes.net_admin = adding,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestMatrixBuilder;
+
+    #[tokio::test]
+    async fn remote_user_mode_applies_and_relays() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut origin = tm.peer("00A").await;
+        let mut other = tm.peer("00B").await;
+        tm.remote_user("00A", "00AAAAAAA", "bob").await;
+
+        tm.run_server("00A", ":00AAAAAAA MODE 00AAAAAAA :+iB").await;
+
+        let user = tm
+            .matrix
+            .user_manager
+            .users
+            .get_cloned("00AAAAAAA")
+            .unwrap();
+        {
+            let user = user.read().await;
+            assert!(user.modes.invisible);
+            assert!(user.modes.bot);
+        }
+
+        // Another user cannot change bob's modes
+        tm.run_server("00A", ":00AAAAAAB MODE 00AAAAAAA :-i").await;
+        assert!(user.read().await.modes.invisible);
+
+        let relayed: Vec<_> = std::iter::from_fn(|| other.try_recv().ok()).collect();
+        assert_eq!(
+            relayed
+                .iter()
+                .filter(|m| m.command.name() == "MODE")
+                .count(),
+            1
+        );
+        let echoed: Vec<_> = std::iter::from_fn(|| origin.try_recv().ok()).collect();
+        assert!(!echoed.iter().any(|m| m.command.name() == "MODE"));
+    }
+
+    #[test]
+    fn test_apply_remote_user_modes() {
+        let mut modes = UserModes {
+            bot: true,
+            ..Default::default()
+        };
+        apply_remote_user_modes(&mut modes, "+iwo-B");
+
+        assert!(modes.invisible);
+        assert!(modes.wallops);
+        assert!(modes.oper);
+        assert!(!modes.bot);
+
+        apply_remote_user_modes(&mut modes, "-o+x");
+        assert!(!modes.oper);
+        assert!(modes.invisible);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::handlers::core::traits::ServerHandler;
+use crate::handlers::server::propagate::relay_to_peers;
+use crate::handlers::server::source::extract_source_uid;
+use crate::handlers::user::monitor::{notify_monitors_offline, notify_monitors_online};
+use crate::handlers::{Context, HandlerError, HandlerResult};
+use crate::state::ServerState;
+use crate::state::actor::ChannelEvent;
+use crate::state::dashmap_ext::DashMapExt;
+use async_trait::async_trait;
+use slirc_proto::sync::clock::{HybridTimestamp, ServerId};
+use slirc_proto::{Command, Message, MessageRef, Prefix, irc_to_lower};
+use tracing::{debug, warn};
+
+/// Handler for a nick change made by a remote user.
+///
+/// Format: `:<uid> NICK <new_nick> <ts>`
+///
+/// The change is merged through the user CRDT, so a collision with another
+/// user holding the new nick is resolved by the same TS rule as UID.
+pub struct NickHandler;
+
+#[async_trait]
+impl ServerHandler for NickHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, ServerState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let uid = extract_source_uid(msg).ok_or(HandlerError::NeedMoreParams)?;
+        let new_nick = msg.arg(0).ok_or(HandlerError::NeedMoreParams)?;
+        let ts = match msg.arg(1) {
+            Some(ts_str) => ts_str.parse::<u64>().map_err(|_| {
+                HandlerError::ProtocolError(format!("Invalid timestamp: {}", ts_str))
+            })?,
+            None => chrono::Utc::now().timestamp() as u64,
+        };
+
+        let Some(user_arc) = ctx.matrix.user_manager.users.get_cloned(uid) else {
+            warn!(uid = %uid, nick = %new_nick, "Received NICK for unknown user");
+            return Ok(());
+        };
+
+        let (old_nick, username, host, mut crdt) = {
+            let user = user_arc.read().await;
+            (
+                user.nick.clone(),
+                user.user.clone(),
+                user.visible_host.clone(),
+                user.to_crdt(),
+            )
+        };
+        if old_nick == new_nick {
+            return Ok(());
+        }
+
+        // The change must win over the nick register it replaces, even when
+        // it lands in the same second the user was introduced.
+        let source = ServerId::new(uid.get(..3).unwrap_or(uid).to_string());
+        let nick_ts = crate::sync::clock::bound_peer_timestamp(
+            &source,
+            HybridTimestamp::new((ts as i64) * 1000, 0, &source),
+        )
+        .max(crdt.nick.timestamp().increment());
+        crdt.nick.update(new_nick.to_string(), nick_ts);
+
+        ctx.matrix
+            .user_manager
+            .merge_user_crdt(crdt, Some(source))
+            .await;
+
+        // The user may have lost a collision, or lost the nick register.
+        let Some(user_arc) = ctx.matrix.user_manager.users.get_cloned(uid) else {
+            debug!(uid = %uid, nick = %new_nick, "Remote NICK lost a collision");
+            return Ok(());
+        };
+        let channels: Vec<String> = {
+            let user = user_arc.read().await;
+            if user.nick != new_nick {
+                return Ok(());
+            }
+            user.channels.iter().cloned().collect()
+        };
+
+        let nick_msg = Message {
+            tags: None,
+            prefix: Some(Prefix::new(
+                old_nick.clone(),
+                username.clone(),
+                host.clone(),
+            )),
+            command: Command::NICK(new_nick.to_string()),
+        };
+        for channel_lower in &channels {
+            ctx.matrix
+                .channel_manager
+                .broadcast_to_channel(channel_lower, nick_msg.clone(), Some(uid))
+                .await;
+
+            let channel_tx = ctx
+                .matrix
+                .channel_manager
+                .channels
+                .get(channel_lower)
+                .map(|c| c.value().clone());
+            if let Some(channel_tx) = channel_tx {
+                let _ = channel_tx
+                    .send(ChannelEvent::NickChange {
+                        uid: uid.to_string(),
+                        new_nick: new_nick.to_string(),
+                    })
+                    .await;
+            }
+        }
+
+        if irc_to_lower(&old_nick) != irc_to_lower(new_nick) {
+            notify_monitors_offline(ctx.matrix, &old_nick, &username, &host).await;
+            notify_monitors_online(ctx.matrix, new_nick, &username, &host).await;
+        }
+
+        relay_to_peers(ctx, msg).await;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::Command;
+
+    #[tokio::test]
+    async fn remote_nick_change_updates_index_notifies_channel_and_relays() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut origin = tm.peer("00A").await;
+        let mut other = tm.peer("00B").await;
+        let mut alice = tm.user("alice").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.remote_user("00A", "00AAAAAAA", "bob").await;
+        tm.run_server("00A", ":00AAAAAAA JOIN 1700000000 #chan +")
+            .await;
+
+        let ts = chrono::Utc::now().timestamp();
+        tm.run_server("00A", &format!(":00AAAAAAA NICK robert {ts}"))
+            .await;
+
+        let seen = alice
+            .recv_matching(|m| matches!(&m.command, Command::NICK(n) if n == "robert"))
+            .await
+            .expect("channel member sees the nick change");
+        assert_eq!(seen.source_nickname(), Some("bob"));
+        assert_eq!(
+            tm.matrix.user_manager.get_first_uid("robert"),
+            Some("00AAAAAAA".to_string())
+        );
+        assert_eq!(tm.matrix.user_manager.get_first_uid("bob"), None);
+
+        let relayed: Vec<_> = std::iter::from_fn(|| other.try_recv().ok()).collect();
+        assert!(
+            relayed
+                .iter()
+                .any(|m| m.to_string().contains("NICK robert"))
+        );
+        let echoed: Vec<_> = std::iter::from_fn(|| origin.try_recv().ok()).collect();
+        assert!(!echoed.iter().any(|m| m.to_string().contains("NICK robert")));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::handlers::core::traits::ServerHandler;
+use crate::handlers::server::propagate::relay_to_peers;
+use crate::handlers::server::source::extract_source_uid;
+use crate::handlers::{Context, HandlerError, HandlerResult};
+use crate::state::actor::ChannelEvent;
+use crate::state::dashmap_ext::DashMapExt;
+use crate::state::{Matrix, ServerState};
+use async_trait::async_trait;
+use slirc_proto::{MessageRef, Prefix, irc_to_lower};
+use tokio::sync::oneshot;
+use tracing::warn;
+
+/// Handler for a remote user leaving a channel.
+///
+/// Format: `:<uid> PART <channel> [:<reason>]`
+pub struct PartHandler;
+
+#[async_trait]
+impl ServerHandler for PartHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, ServerState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let uid = extract_source_uid(msg).ok_or(HandlerError::NeedMoreParams)?;
+        let channel_name = msg.arg(0).ok_or(HandlerError::NeedMoreParams)?;
+        let reason = msg.arg(1).map(|s| s.to_string());
+
+        // A PART for a user we never saw join is still relayed: our view of
+        // the channel may simply be behind the rest of the network.
+        if !part_remote_member(ctx.matrix, uid, channel_name, reason).await {
+            return Ok(());
+        }
+
+        relay_to_peers(ctx, msg).await;
+
+        Ok(())
+    }
+}
+
+/// Removes a remote user from a channel as if they had parted it.
+///
+/// Local members see the PART; the channel is dropped once empty. Nothing
+/// is relayed to peers. Returns `false` if the user or channel is unknown.
+pub(crate) async fn part_remote_member(
+    matrix: &Matrix,
+    uid: &str,
+    channel_name: &str,
+    reason: Option<String>,
+) -> bool {
+    let channel_lower = irc_to_lower(channel_name);
+
+    let Some(user_arc) = matrix.user_manager.users.get_cloned(uid) else {
+        warn!(uid = %uid, channel = %channel_name, "Received PART for unknown user");
+        return false;
+    };
+    let prefix = {
+        let user = user_arc.read().await;
+        Prefix::new(
+            user.nick.clone(),
+            user.user.clone(),
+            user.visible_host.clone(),
+        )
+    };
+
+    let channel_tx = matrix
+        .channel_manager
+        .channels
+        .get(&channel_lower)
+        .map(|c| c.value().clone());
+    let Some(channel_tx) = channel_tx else {
+        warn!(uid = %uid, channel = %channel_name, "Received PART for unknown channel");
+        return false;
+    };
+
+    let (reply_tx, reply_rx) = oneshot::channel();
+    let event = ChannelEvent::Part {
+        uid: uid.to_string(),
+        reason,
+        prefix,
+        nanotime: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+        reply_tx,
+    };
+    if channel_tx.send(event).await.is_err() {
+        matrix
+            .channel_manager
+            .remove_if_current(&channel_lower, &channel_tx);
+        return false;
+    }
+
+    // An emptied channel unregisters itself
+    let _ = reply_rx.await;
+    user_arc.write().await.channels.remove(&channel_lower);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::dashmap_ext::DashMapExt;
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::Command;
+
+    #[tokio::test]
+    async fn remote_part_removes_member_notifies_channel_and_relays() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut origin = tm.peer("00A").await;
+        let mut other = tm.peer("00B").await;
+        let mut alice = tm.user("alice").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.remote_user("00A", "00AAAAAAA", "bob").await;
+        tm.run_server("00A", ":00AAAAAAA JOIN 1700000000 #chan +")
+            .await;
+
+        tm.run_server("00A", ":00AAAAAAA PART #chan :bye").await;
+
+        let seen = alice
+            .recv_matching(|m| matches!(&m.command, Command::PART(c, _) if c == "#chan"))
+            .await
+            .expect("channel member sees the part");
+        assert_eq!(seen.source_nickname(), Some("bob"));
+        let user = tm
+            .matrix
+            .user_manager
+            .users
+            .get_cloned("00AAAAAAA")
+            .unwrap();
+        assert!(user.read().await.channels.is_empty());
+
+        let relayed: Vec<_> = std::iter::from_fn(|| other.try_recv().ok()).collect();
+        assert!(
+            relayed
+                .iter()
+                .any(|m| matches!(&m.command, Command::PART(_, Some(r)) if r == "bye"))
+        );
+        let echoed: Vec<_> = std::iter::from_fn(|| origin.try_recv().ok()).collect();
+        assert!(
+            !echoed
+                .iter()
+                .any(|m| matches!(&m.command, Command::PART(..)))
+        );
+    }
+}
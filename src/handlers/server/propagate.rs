@@ -0,0 +1,17 @@
+use crate::handlers::Context;
+use crate::state::ServerState;
+use slirc_proto::MessageRef;
+use slirc_proto::sync::clock::ServerId;
+use std::sync::Arc;
+
+/// Relay a server command unchanged to every other peer link.
+///
+/// Split-horizon: the link the command arrived on is skipped, so a command
+/// never loops back to the server that sent it.
+pub async fn relay_to_peers(ctx: &Context<'_, ServerState>, msg: &MessageRef<'_>) {
+    let origin = ServerId::new(ctx.state.sid.clone());
+    ctx.matrix
+        .sync_manager
+        .broadcast(Arc::new(msg.to_owned()), Some(&origin))
+        .await;
+}
@@ -0,0 +1,84 @@
+use crate::handlers::core::traits::ServerHandler;
+use crate::handlers::server::propagate::relay_to_peers;
+use crate::handlers::server::source::extract_source_uid;
+use crate::handlers::{Context, HandlerResult};
+use crate::state::ServerState;
+use async_trait::async_trait;
+use slirc_proto::MessageRef;
+use tracing::info;
+
+/// Handler for a remote user leaving the network.
+///
+/// Format: `:<uid> QUIT :<reason>`
+///
+/// The user is removed exactly as a local disconnect would: channel members
+/// see the QUIT, MONITOR watchers are told and WHOWAS is recorded. The link
+/// itself stays up.
+pub struct QuitHandler;
+
+#[async_trait]
+impl ServerHandler for QuitHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, ServerState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let Some(uid) = extract_source_uid(msg) else {
+            info!(server = %ctx.state.name, "Received QUIT from server without prefix");
+            return Ok(());
+        };
+        let reason = msg.arg(0).unwrap_or("Client Quit");
+
+        if !ctx.matrix.user_manager.users.contains_key(uid) {
+            info!(uid = %uid, "Received QUIT for unknown user");
+            return Ok(());
+        }
+
+        ctx.matrix.disconnect_user(&uid.to_string(), reason).await;
+
+        relay_to_peers(ctx, msg).await;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::Command;
+
+    #[tokio::test]
+    async fn remote_quit_removes_user_notifies_channel_and_relays() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut origin = tm.peer("00A").await;
+        let mut other = tm.peer("00B").await;
+        let mut alice = tm.user("alice").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.remote_user("00A", "00AAAAAAA", "bob").await;
+        tm.run_server("00A", ":00AAAAAAA JOIN 1700000000 #chan +")
+            .await;
+
+        tm.run_server("00A", ":00AAAAAAA QUIT :Gone fishing").await;
+
+        let seen = alice
+            .recv_matching(|m| matches!(&m.command, Command::QUIT(_)))
+            .await
+            .expect("channel member sees the quit");
+        assert_eq!(seen.source_nickname(), Some("bob"));
+        assert!(!tm.matrix.user_manager.users.contains_key("00AAAAAAA"));
+        assert_eq!(tm.matrix.user_manager.get_first_uid("bob"), None);
+
+        let relayed: Vec<_> = std::iter::from_fn(|| other.try_recv().ok()).collect();
+        assert!(
+            relayed
+                .iter()
+                .any(|m| matches!(&m.command, Command::QUIT(Some(r)) if r == "Gone fishing"))
+        );
+        let echoed: Vec<_> = std::iter::from_fn(|| origin.try_recv().ok()).collect();
+        assert!(
+            !echoed
+                .iter()
+                .any(|m| matches!(&m.command, Command::QUIT(_)))
+        );
+    }
+}
@@ -151,6 +151,7 @@ impl ServerHandler for RoutedMessageHandler {
                             sasl_mechanism: None,
                             is_registered: user.modes.registered,
                             is_tls: user.modes.secure,
+                            banned_in: Vec::new(),
                         }
                     } else {
                         // Fallback for unknown remote user or server sender
@@ -168,6 +169,7 @@ impl ServerHandler for RoutedMessageHandler {
                             sasl_mechanism: None,
                             is_registered: true,
                             is_tls: false,
+                            banned_in: Vec::new(),
                         }
                     };
 
@@ -286,14 +288,16 @@ impl ServerHandler for RoutedMessageHandler {
             };
 
             // Strip internal routing tags before delivery to local clients
-            let filtered_tags = tags.map(|tags| {
-                tags.into_iter()
-                    .filter(|tag| {
-                        let key = tag.0.as_ref();
-                        key != "x-target-uid" && key != "x-visible-target"
-                    })
-                    .collect::<Vec<_>>()
-            }).and_then(|tags| if tags.is_empty() { None } else { Some(tags) });
+            let filtered_tags = tags
+                .map(|tags| {
+                    tags.into_iter()
+                        .filter(|tag| {
+                            let key = tag.0.as_ref();
+                            key != "x-target-uid" && key != "x-visible-target"
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .and_then(|tags| if tags.is_empty() { None } else { Some(tags) });
 
             let out_msg = Message {
                 tags: filtered_tags,
@@ -332,6 +336,24 @@ impl ServerHandler for RoutedMessageHandler {
                         .chanserv
                         .handle(ctx.matrix, source_uid, &source_nick, text)
                         .await
+                } else if target_uid == ctx.matrix.service_manager.hostserv_uid {
+                    ctx.matrix
+                        .service_manager
+                        .hostserv
+                        .handle(ctx.matrix, source_uid, &source_nick, text)
+                        .await
+                } else if target_uid == ctx.matrix.service_manager.operserv_uid {
+                    ctx.matrix
+                        .service_manager
+                        .operserv
+                        .handle(ctx.matrix, source_uid, &source_nick, text)
+                        .await
+                } else if target_uid == ctx.matrix.service_manager.botserv_uid {
+                    ctx.matrix
+                        .service_manager
+                        .botserv
+                        .handle(ctx.matrix, source_uid, &source_nick, text)
+                        .await
                 } else {
                     // Unknown service UID (shouldn't happen if is_service_uid returned true)
                     Vec::new()
@@ -429,7 +451,31 @@ impl ServerHandler for RoutedMessageHandler {
 
 #[cfg(test)]
 mod tests {
-    use slirc_proto::Tag;
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{Command, Tag};
+
+    #[tokio::test]
+    async fn routed_privmsg_reaches_local_recipient() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
+        tm.remote_user("00A", "00AAAAAAA", "bob").await;
+
+        tm.run_server(
+            "00A",
+            &format!(":00AAAAAAA PRIVMSG {} :hi alice", alice.uid),
+        )
+        .await;
+
+        let seen = alice
+            .recv_matching(|m| matches!(&m.command, Command::PRIVMSG(..)))
+            .await
+            .expect("local recipient gets the message");
+        assert_eq!(seen.source_nickname(), Some("bob"));
+        assert!(matches!(
+            &seen.command,
+            Command::PRIVMSG(t, text) if t == "alice" && text == "hi alice"
+        ));
+    }
 
     #[test]
     fn test_tag_parsing() {
@@ -2,15 +2,22 @@ use crate::handlers::core::traits::ServerHandler;
 use crate::handlers::{Context, HandlerError, HandlerResult};
 use crate::state::ServerState;
 use async_trait::async_trait;
-use slirc_proto::MessageRef;
 use slirc_proto::sync::clock::ServerId;
-use tracing::info;
+use slirc_proto::{Command, Message, MessageRef, Prefix};
+use std::sync::Arc;
+use tracing::{info, warn};
 
 use crate::handlers::server::source::extract_source_sid;
 
 /// Handler for the SID command (Server ID).
 ///
 /// SID introduces a new server to the network topology.
+///
+/// Format: `:<uplink> SID <server_name> <hopcount> <sid> :<info>`
+///
+/// The hop count is the distance from us. The introduction is relayed to
+/// every other link one hop further out, so each server learns the whole
+/// spanning tree. A server we already know is a loop and is ignored.
 pub struct SidHandler;
 
 #[async_trait]
@@ -32,20 +39,84 @@ impl ServerHandler for SidHandler {
         })?;
 
         let sid = ServerId::new(sid_str.to_string());
+        let sync = &ctx.matrix.sync_manager;
+        if sid == sync.local_id || sync.topology.servers.contains_key(&sid) {
+            warn!(sid = %sid.as_str(), name = %name, via = %ctx.state.sid, "Ignoring SID for a server already in the network");
+            return Ok(());
+        }
 
         // Topology: record the immediate uplink/introducer (from prefix when available).
         let via = extract_source_sid(msg).unwrap_or_else(|| ServerId::new(ctx.state.sid.clone()));
 
-        ctx.matrix.sync_manager.topology.add_server(
+        sync.topology.add_server(
             sid.clone(),
             name.to_string(),
             info_str.to_string(),
             hopcount,
-            Some(via),
+            Some(via.clone()),
         );
 
         info!(sid = %sid.as_str(), name = %name, via = %ctx.state.sid, "Registered remote server via SID");
 
+        let relay = Message {
+            tags: None,
+            prefix: Some(Prefix::new_from_str(via.as_str())),
+            command: Command::SID(
+                name.to_string(),
+                (hopcount + 1).to_string(),
+                sid.as_str().to_string(),
+                info_str.to_string(),
+            ),
+        };
+        sync.broadcast(Arc::new(relay), Some(&ServerId::new(ctx.state.sid.clone())))
+            .await;
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::Command;
+    use slirc_proto::sync::clock::ServerId;
+
+    #[tokio::test]
+    async fn sid_is_relayed_and_routed_through_its_uplink() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let _near = tm.peer("00A").await;
+        let mut other = tm.peer("00B").await;
+
+        tm.run_server("00A", ":00A SID far.test 2 00C :Far away")
+            .await;
+
+        let relayed = other.try_recv().expect("SID is relayed to the other link");
+        assert_eq!(
+            relayed.prefix.as_ref().map(|p| p.to_string()).as_deref(),
+            Some("00A")
+        );
+        assert!(matches!(
+            &relayed.command,
+            Command::SID(name, hops, sid, _) if name == "far.test" && hops == "3" && sid == "00C"
+        ));
+        let hop = tm
+            .matrix
+            .sync_manager
+            .get_next_hop(&ServerId::new("00C"))
+            .expect("far server is reachable");
+        assert_eq!(hop.name, "00a.test");
+
+        // A second introduction of the same SID is a loop
+        tm.run_server("00B", ":00B SID far.test 2 00C :Far away")
+            .await;
+        let info = tm
+            .matrix
+            .sync_manager
+            .topology
+            .servers
+            .get(&ServerId::new("00C"))
+            .unwrap()
+            .clone();
+        assert_eq!(info.via, Some(ServerId::new("00A")));
+    }
+}
@@ -8,6 +8,7 @@ use slirc_proto::sync::channel::{ChannelCrdt, MemberModesCrdt};
 use slirc_proto::sync::clock::{HybridTimestamp, ServerId};
 use tracing::warn;
 
+use crate::handlers::server::propagate::relay_to_peers;
 use crate::handlers::server::source::extract_source_sid;
 
 /// Handler for the SJOIN command (Safe Join).
@@ -96,8 +97,30 @@ impl ServerHandler for SJoinHandler {
 
         if let Err(e) = tx.send(event).await {
             warn!(channel = %channel_name, error = %e, "Failed to send SJOIN to channel actor");
+            return Ok(());
         }
 
+        // Record the membership on each known user, as a remote JOIN does, so
+        // netsplit cleanup can find it
+        let channel_lower = slirc_proto::irc_to_lower(channel_name);
+        for (_, uid) in &users {
+            let user_arc = ctx
+                .matrix
+                .user_manager
+                .users
+                .get(uid)
+                .map(|u| u.value().clone());
+            if let Some(user_arc) = user_arc {
+                user_arc
+                    .write()
+                    .await
+                    .channels
+                    .insert(channel_lower.clone());
+            }
+        }
+
+        relay_to_peers(ctx, msg).await;
+
         Ok(())
     }
 }
@@ -119,7 +142,11 @@ fn sjoin_to_crdt(
 ) -> ChannelCrdt {
     // Create HybridTimestamp from Unix TS and server_id
     // Note: TS is seconds, HybridTimestamp wants millis
-    let base_hts = HybridTimestamp::new((ts as i64) * 1000, 0, server_id);
+    // Clamped so a peer clock running ahead cannot win every LWW comparison
+    let base_hts = crate::sync::clock::bound_peer_timestamp(
+        server_id,
+        HybridTimestamp::new((ts as i64) * 1000, 0, server_id),
+    );
 
     // Create base CRDT with the base timestamp
     let mut crdt = ChannelCrdt::new(channel_name.to_string(), base_hts);
@@ -171,22 +198,20 @@ fn apply_modes_to_crdt(
             'c' => crdt.modes.no_colors.update(true, hts),
             'C' => crdt.modes.no_ctcp.update(true, hts),
             'z' | 'S' => crdt.modes.ssl_only.update(true, hts),
-            'k' => {
+            'k'
                 // Key mode requires an argument
-                if arg_idx < mode_args.len() {
+                if arg_idx < mode_args.len() => {
                     crdt.key.update(Some(mode_args[arg_idx].clone()), hts);
                     arg_idx += 1;
                 }
-            }
-            'l' => {
+            'l'
                 // Limit mode requires a numeric argument
-                if arg_idx < mode_args.len() {
+                if arg_idx < mode_args.len() => {
                     if let Ok(limit) = mode_args[arg_idx].parse::<u32>() {
                         crdt.limit.update(Some(limit), hts);
                     }
                     arg_idx += 1;
                 }
-            }
             _ => {
                 // Unknown mode - skip any argument it might consume
                 // (paranoid handling for forward compatibility)
@@ -17,3 +17,12 @@ pub fn extract_source_sid(msg: &MessageRef<'_>) -> Option<ServerId> {
 
     Some(ServerId::new(sid.to_string()))
 }
+
+/// Extract the UID a user-originated server command is attributed to.
+///
+/// UIDs are parsed as the nick part of the prefix; anything else falls back
+/// to the raw prefix so server names still resolve to something loggable.
+pub fn extract_source_uid<'a>(msg: &MessageRef<'a>) -> Option<&'a str> {
+    let prefix = msg.prefix.as_ref()?;
+    Some(prefix.nick.or(prefix.host).unwrap_or(prefix.raw))
+}
@@ -0,0 +1,125 @@
+use crate::handlers::core::traits::ServerHandler;
+use crate::handlers::{Context, HandlerError, HandlerResult};
+use crate::state::ServerState;
+use crate::sync::split::squit_server;
+use async_trait::async_trait;
+use slirc_proto::MessageRef;
+use slirc_proto::sync::clock::ServerId;
+use tracing::{debug, info};
+
+/// Handler for a server being removed from the network.
+///
+/// Format: `:<source> SQUIT <sid|name> :<reason>`
+///
+/// A SQUIT naming us drops the link it arrived on, without retrying. One
+/// naming a server behind the sender splits it off locally and is relayed
+/// to the other links.
+pub struct SquitHandler;
+
+#[async_trait]
+impl ServerHandler for SquitHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, ServerState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let target = msg.arg(0).ok_or(HandlerError::NeedMoreParams)?;
+        let reason = msg.arg(1).unwrap_or(&ctx.state.name);
+        let sync = &ctx.matrix.sync_manager;
+        let source = ServerId::new(ctx.state.sid.clone());
+
+        let sid = if target == sync.local_id.as_str()
+            || target.eq_ignore_ascii_case(&ctx.matrix.server_info.name)
+        {
+            // The other side is closing this link
+            source.clone()
+        } else {
+            let found = sync
+                .topology
+                .servers
+                .iter()
+                .find(|e| e.key().as_str() == target || e.value().name.eq_ignore_ascii_case(target))
+                .map(|e| e.key().clone());
+            let Some(sid) = found else {
+                debug!(target = %target, via = %ctx.state.name, "SQUIT for unknown server");
+                return Ok(());
+            };
+            sid
+        };
+
+        info!(sid = %sid.as_str(), via = %ctx.state.name, reason = %reason, "Received SQUIT");
+        squit_server(ctx.matrix, &sid, reason, Some(&source)).await;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::Command;
+    use slirc_proto::sync::clock::ServerId;
+
+    #[tokio::test]
+    async fn squit_of_remote_server_splits_it_and_relays() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut origin = tm.peer("00A").await;
+        let mut other = tm.peer("00B").await;
+        tm.run_server("00A", ":00A SID far.test 2 00C :Far away")
+            .await;
+        tm.remote_user("00C", "00CAAAAAA", "faraway").await;
+        let mut alice = tm.user("alice").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.run_server("00A", ":00CAAAAAA JOIN 1700000000 #chan +")
+            .await;
+        while other.try_recv().is_ok() {}
+
+        tm.run_server("00A", ":00A SQUIT 00C :far.test went away")
+            .await;
+
+        let quit = alice
+            .recv_matching(|m| matches!(&m.command, Command::QUIT(_)))
+            .await
+            .expect("channel member sees the split");
+        assert_eq!(quit.source_nickname(), Some("faraway"));
+        assert!(!tm.matrix.user_manager.users.contains_key("00CAAAAAA"));
+        let topology = &tm.matrix.sync_manager.topology.servers;
+        assert!(!topology.contains_key(&ServerId::new("00C".to_string())));
+        assert!(
+            tm.matrix
+                .sync_manager
+                .links
+                .contains_key(&ServerId::new("00A".to_string()))
+        );
+
+        let relayed: Vec<_> = std::iter::from_fn(|| other.try_recv().ok()).collect();
+        assert!(
+            relayed
+                .iter()
+                .any(|m| matches!(&m.command, Command::SQUIT(sid, _) if sid == "00C"))
+        );
+        assert!(
+            !relayed
+                .iter()
+                .any(|m| matches!(&m.command, Command::QUIT(_)))
+        );
+        let echoed: Vec<_> = std::iter::from_fn(|| origin.try_recv().ok()).collect();
+        assert!(
+            !echoed
+                .iter()
+                .any(|m| matches!(&m.command, Command::SQUIT(..)))
+        );
+    }
+
+    #[tokio::test]
+    async fn squit_naming_us_drops_the_link() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let _peer = tm.peer("00A").await;
+
+        tm.run_server("00A", ":00A SQUIT 00T :Bye").await;
+
+        let sync = &tm.matrix.sync_manager;
+        assert!(!sync.links.contains_key(&ServerId::new("00A".to_string())));
+        assert!(sync.squit_links.contains("00a.test"));
+    }
+}
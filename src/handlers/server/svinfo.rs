@@ -34,6 +34,9 @@ impl PreRegHandler for SvinfoHandler {
 
         info!(v, m, z, t, "Received SVINFO");
         ctx.state.server_svinfo = Some((v, m, z, t));
+        if let Some(sid) = &ctx.state.server_sid {
+            crate::sync::clock::record_svinfo(sid, t);
+        }
 
         Ok(())
     }
@@ -0,0 +1,164 @@
+use super::encap::force_local_nick;
+use crate::handlers::core::traits::ServerHandler;
+use crate::handlers::util::helpers::collect_message_args;
+use crate::handlers::{Context, HandlerError, HandlerResult};
+use crate::services::effect::{ServiceEffect, apply_effect_no_sender};
+use crate::state::ServerState;
+use async_trait::async_trait;
+use slirc_proto::{Command, Message, MessageRef, Mode, Prefix};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// Handler for services commands sent between servers.
+///
+/// - `:<source> SVSNICK <uid> <new_nick>`
+/// - `:<source> SVSMODE <uid> <modes> [args...]`
+/// - `:<source> SVSJOIN <uid> <channel>`
+///
+/// Services on any server use these to rename a user, change their modes or
+/// put them in a channel. The server that owns the user applies the change
+/// with the usual client notifications; other servers forward the command
+/// along the route towards it.
+pub struct SvsHandler;
+
+#[async_trait]
+impl ServerHandler for SvsHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, ServerState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let command = msg.command_name().to_ascii_uppercase();
+        let uid = msg.arg(0).ok_or(HandlerError::NeedMoreParams)?;
+        let arg = msg.arg(1).ok_or(HandlerError::NeedMoreParams)?;
+
+        let source = msg
+            .prefix
+            .as_ref()
+            .map(|p| p.raw.to_string())
+            .unwrap_or_else(|| ctx.state.sid.clone());
+
+        if !uid.starts_with(ctx.matrix.server_info.sid.as_str()) {
+            let forward = Message {
+                tags: None,
+                prefix: Some(Prefix::new_from_str(&source)),
+                command: Command::Raw(command.clone(), collect_message_args(msg, 0)),
+            };
+            ctx.matrix
+                .sync_manager
+                .route_to_remote_user(uid, Arc::new(forward))
+                .await;
+            return Ok(());
+        }
+
+        debug!(
+            source = %source,
+            command = %command,
+            uid = %uid,
+            arg = %arg,
+            "Received services command"
+        );
+
+        let effect = match command.as_str() {
+            "SVSNICK" => {
+                force_local_nick(ctx.matrix, uid, arg).await;
+                return Ok(());
+            }
+            "SVSMODE" => {
+                let pieces = collect_message_args(msg, 1);
+                let pieces: Vec<&str> = pieces.iter().map(String::as_str).collect();
+                let Ok(modes) = Mode::as_user_modes(&pieces) else {
+                    warn!(uid = %uid, modes = %arg, "Unparseable SVSMODE");
+                    return Ok(());
+                };
+                ServiceEffect::ForceMode {
+                    target_uid: uid.to_string(),
+                    modes,
+                }
+            }
+            "SVSJOIN" => ServiceEffect::ForceJoin {
+                target_uid: uid.to_string(),
+                channel: arg.to_string(),
+            },
+            _ => return Ok(()),
+        };
+        apply_effect_no_sender(ctx.matrix, &source, effect).await;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::services::effect::{ServiceEffect, apply_effect_no_sender};
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{Command, Mode, UserMode, irc_to_lower};
+
+    #[tokio::test]
+    async fn svs_commands_apply_to_local_users() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let _peer = tm.peer("00S").await;
+        let alice = tm.user("alice").build().await;
+
+        tm.run_server("00S", &format!(":00S SVSJOIN {} #lobby", alice.uid))
+            .await;
+        tm.run_server("00S", &format!(":00S SVSMODE {} +iw", alice.uid))
+            .await;
+        tm.run_server("00S", &format!(":00SAAAAAA SVSNICK {} alice2", alice.uid))
+            .await;
+
+        let user_arc = tm
+            .matrix
+            .user_manager
+            .users
+            .get(&alice.uid)
+            .unwrap()
+            .clone();
+        let user = user_arc.read().await;
+        assert!(user.channels.contains(&irc_to_lower("#lobby")));
+        assert!(user.modes.invisible && user.modes.wallops);
+        assert_eq!(user.nick, "alice2");
+        drop(user);
+
+        // Server-only modes stay out of reach
+        tm.run_server("00S", &format!(":00S SVSMODE {} +o", alice.uid))
+            .await;
+        assert!(!user_arc.read().await.modes.oper);
+    }
+
+    #[tokio::test]
+    async fn force_effects_on_remote_users_are_routed() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut peer = tm.peer("00S").await;
+        tm.remote_user("00S", "00SAAAAAB", "bob").await;
+
+        apply_effect_no_sender(
+            &tm.matrix,
+            "OperServ",
+            ServiceEffect::ForceJoin {
+                target_uid: "00SAAAAAB".to_string(),
+                channel: "#lobby".to_string(),
+            },
+        )
+        .await;
+        apply_effect_no_sender(
+            &tm.matrix,
+            "OperServ",
+            ServiceEffect::ForceMode {
+                target_uid: "00SAAAAAB".to_string(),
+                modes: vec![Mode::Plus(UserMode::Invisible, None)],
+            },
+        )
+        .await;
+
+        let sent: Vec<_> = std::iter::from_fn(|| peer.try_recv().ok()).collect();
+        let routed = |name: &str, arg: &str| {
+            sent.iter().any(|m| {
+                matches!(&m.command, Command::Raw(cmd, args)
+                    if cmd == name && args[0] == "00SAAAAAB" && args[1] == arg)
+            })
+        };
+        assert!(routed("SVSJOIN", "#lobby"));
+        assert!(routed("SVSMODE", "+i"));
+    }
+}
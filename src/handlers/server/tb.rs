@@ -1,4 +1,5 @@
 use crate::handlers::core::traits::ServerHandler;
+use crate::handlers::server::propagate::relay_to_peers;
 use crate::handlers::{Context, HandlerError, HandlerResult};
 use crate::state::ServerState;
 use crate::state::actor::ChannelEvent;
@@ -57,6 +58,8 @@ impl ServerHandler for TbHandler {
             warn!(channel = %channel, "Received TB for unknown channel");
         }
 
+        relay_to_peers(ctx, msg).await;
+
         Ok(())
     }
 }
@@ -1,4 +1,5 @@
 use crate::handlers::core::traits::ServerHandler;
+use crate::handlers::server::propagate::relay_to_peers;
 use crate::handlers::util::helpers::collect_message_args;
 use crate::handlers::{Context, HandlerError, HandlerResult};
 use crate::state::ServerState;
@@ -67,6 +68,8 @@ impl ServerHandler for TModeHandler {
             warn!(channel = %channel_name, "Received TMODE for unknown channel");
         }
 
+        relay_to_peers(ctx, msg).await;
+
         Ok(())
     }
 }
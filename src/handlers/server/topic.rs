@@ -1,4 +1,5 @@
 use crate::handlers::core::traits::ServerHandler;
+use crate::handlers::server::propagate::relay_to_peers;
 use crate::handlers::{Context, HandlerError, HandlerResult};
 use crate::state::ServerState;
 use crate::state::actor::ChannelEvent;
@@ -56,6 +57,47 @@ impl ServerHandler for TopicHandler {
             warn!(channel = %channel, "Received TOPIC for unknown channel");
         }
 
+        relay_to_peers(ctx, msg).await;
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::Command;
+
+    #[tokio::test]
+    async fn remote_topic_notifies_channel_and_relays() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut origin = tm.peer("00A").await;
+        let mut other = tm.peer("00B").await;
+        let mut alice = tm.user("alice").build().await;
+        tm.run(&mut alice, "JOIN #chan").await;
+        tm.remote_user("00A", "00AAAAAAA", "bob").await;
+
+        let ts = chrono::Utc::now().timestamp();
+        tm.run_server("00A", &format!(":00AAAAAAA TOPIC #chan {ts} :new topic"))
+            .await;
+
+        let seen = alice
+            .recv_matching(|m| matches!(&m.command, Command::TOPIC(..)))
+            .await
+            .expect("channel member sees the topic");
+        assert_eq!(seen.source_nickname(), Some("bob"));
+        assert!(matches!(
+            &seen.command,
+            Command::TOPIC(c, Some(t)) if c == "#chan" && t == "new topic"
+        ));
+
+        let relayed: Vec<_> = std::iter::from_fn(|| other.try_recv().ok()).collect();
+        assert!(
+            relayed
+                .iter()
+                .any(|m| m.to_string().contains("TOPIC #chan"))
+        );
+        let echoed: Vec<_> = std::iter::from_fn(|| origin.try_recv().ok()).collect();
+        assert!(!echoed.iter().any(|m| m.to_string().contains("TOPIC #chan")));
+    }
+}
@@ -1,14 +1,20 @@
 #![allow(clippy::collapsible_if)]
 use crate::handlers::core::traits::ServerHandler;
 use crate::handlers::{Context, HandlerError, HandlerResult};
-use crate::state::ServerState;
+use crate::services::effect::{ServiceEffect, apply_effect_no_sender};
+use crate::state::dashmap_ext::DashMapExt;
+use crate::state::{Matrix, ServerState};
 use async_trait::async_trait;
-use slirc_proto::MessageRef;
 use slirc_proto::sync::clock::{HybridTimestamp, ServerId};
 use slirc_proto::sync::user::UserCrdt;
+use slirc_proto::{Command, Message, MessageRef, Prefix};
+use std::net::IpAddr;
+use std::sync::Arc;
 use tracing::info;
 
+use crate::handlers::server::propagate::relay_to_peers;
 use crate::handlers::server::source::extract_source_sid;
+use crate::sync::TopologyGraph;
 
 /// Handler for the UID command (User ID).
 ///
@@ -41,25 +47,175 @@ impl ServerHandler for UidHandler {
             HandlerError::ProtocolError(format!("Invalid timestamp: {}", timestamp_str))
         })?;
 
-        let source = extract_source_sid(msg).unwrap_or_else(|| ServerId::new("000".to_string()));
+        // Without a prefix, attribute the user to the server its UID names
+        let source = extract_source_sid(msg)
+            .or_else(|| TopologyGraph::origin_of(uid))
+            .unwrap_or_else(|| ServerId::new("000".to_string()));
+
+        // Network bans apply to users introduced by peers, so a lenient leaf
+        // cannot be used to evade them. Services are exempt.
+        let is_service = modes_str.contains('S');
+        if !is_service && let Some(reason) = remote_ban_reason(ctx.matrix, username, hostname) {
+            reject_remote_user(ctx.matrix, uid, nick, username, hostname, &source, &reason).await;
+            return Ok(());
+        }
 
         // Convert TS6 UID to CRDT for lossless merge
         let crdt = uid_to_crdt(
             uid, nick, timestamp, modes_str, username, hostname, realname, &source,
         );
 
+        // Q-lines apply to remote introductions too (services and opers are exempt).
+        let qline_reason = if is_service || modes_str.contains('o') {
+            None
+        } else {
+            ctx.matrix.find_qline(nick)
+        };
+        let source_sid = source.as_str().to_string();
+
         // Merge user CRDT (handles nick collisions via CRDT semantics)
         ctx.matrix
             .user_manager
             .merge_user_crdt(crdt, Some(source))
             .await;
 
+        if let Some(reason) = qline_reason {
+            rename_qlined_user(ctx.matrix, uid, nick, &source_sid, &reason).await;
+        }
+
         info!(uid = %uid, nick = %nick, "Registered remote user via UID CRDT");
 
+        relay_to_peers(ctx, msg).await;
+
         Ok(())
     }
 }
 
+//=============================================================================
+// BAN ENFORCEMENT
+//=============================================================================
+
+/// Checks a remotely introduced user against G-lines and Z-lines.
+///
+/// K-lines are local policy and are not applied to remote users. UID carries
+/// no separate IP field, so Z-lines can only match when the host is an IP.
+fn remote_ban_reason(matrix: &Matrix, username: &str, hostname: &str) -> Option<String> {
+    if let Some(ban) = matrix
+        .security_manager
+        .ban_cache
+        .check_gline(username, hostname)
+    {
+        return Some(format!("{}: {}", ban.ban_type, ban.reason));
+    }
+
+    let ip = hostname.parse::<IpAddr>().ok()?;
+    let deny_list = matrix.security_manager.ip_deny_list.read().ok()?;
+    deny_list
+        .check_ip(&ip)
+        .map(|reason| format!("Z-lined: {}", reason))
+}
+
+/// Refuses a banned remote user by sending KILL back toward its server.
+///
+/// The user is never merged locally; the owning server disconnects it and
+/// propagates the KILL to the rest of the network.
+async fn reject_remote_user(
+    matrix: &Matrix,
+    uid: &str,
+    nick: &str,
+    username: &str,
+    hostname: &str,
+    source: &ServerId,
+    reason: &str,
+) {
+    let kill_msg = Message {
+        tags: None,
+        prefix: Some(Prefix::new_from_str(matrix.server_info.sid.as_str())),
+        command: Command::KILL(
+            uid.to_string(),
+            format!("{} ({})", matrix.server_info.name, reason),
+        ),
+    };
+    matrix
+        .sync_manager
+        .route_to_remote_user(uid, Arc::new(kill_msg))
+        .await;
+
+    matrix
+        .user_manager
+        .send_snomask(
+            'k',
+            &format!(
+                "Rejected remote user {}!{}@{} introduced by {} ({})",
+                nick,
+                username,
+                hostname,
+                source.as_str(),
+                reason
+            ),
+        )
+        .await;
+
+    info!(uid = %uid, nick = %nick, reason = %reason, "Killed banned remote user");
+}
+
+/// Renames a remote user holding a Q-lined nick to its UID.
+///
+/// The rename is applied locally straight away and requested from the owning
+/// server via `ENCAP <sid> RSFNC`, which propagates the new nick to the rest
+/// of the network.
+async fn rename_qlined_user(
+    matrix: &Arc<Matrix>,
+    uid: &str,
+    nick: &str,
+    source_sid: &str,
+    reason: &str,
+) {
+    // The merge may have lost a nick collision and removed the user.
+    let Some(user_arc) = matrix.user_manager.users.get_cloned(uid) else {
+        return;
+    };
+    if user_arc.read().await.nick != nick {
+        return;
+    }
+
+    apply_effect_no_sender(
+        matrix,
+        nick,
+        ServiceEffect::ForceNick {
+            target_uid: uid.to_string(),
+            old_nick: nick.to_string(),
+            new_nick: uid.to_string(),
+        },
+    )
+    .await;
+
+    let rsfnc = Message {
+        tags: None,
+        prefix: Some(Prefix::new_from_str(matrix.server_info.sid.as_str())),
+        command: Command::ENCAP(
+            uid.get(..3).unwrap_or(uid).to_string(),
+            "RSFNC".to_string(),
+            vec![uid.to_string(), uid.to_string()],
+        ),
+    };
+    matrix
+        .sync_manager
+        .route_to_remote_user(uid, Arc::new(rsfnc))
+        .await;
+
+    matrix
+        .user_manager
+        .send_snomask(
+            'k',
+            &format!(
+                "Q-lined nick {} introduced by {} ({}), renamed to {}",
+                nick, source_sid, reason, uid
+            ),
+        )
+        .await;
+}
+
 //=============================================================================
 // CRDT CONVERTERS
 //=============================================================================
@@ -115,7 +271,7 @@ fn apply_user_modes_to_crdt(crdt: &mut UserCrdt, modes_str: &str, timestamp: Hyb
             'R' => crdt.modes.registered_only.update(true, timestamp),
             'T' => crdt.modes.no_ctcp.update(true, timestamp),
             'B' => crdt.modes.bot.update(true, timestamp),
-            'S' => {} // Service mode - not in UserModesCrdt
+            'S' => crdt.modes.service.update(true, timestamp),
             _ => {}
         }
     }
@@ -128,6 +284,48 @@ fn apply_user_modes_to_crdt(crdt: &mut UserCrdt, modes_str: &str, timestamp: Hyb
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::TestMatrixBuilder;
+
+    #[tokio::test]
+    async fn test_banned_remote_users_are_killed_back() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut link = tm.peer("00A").await;
+        let security = &tm.matrix.security_manager;
+        security.ban_cache.add_gline(
+            "*@evil.example".to_string(),
+            "Network ban".to_string(),
+            None,
+        );
+        security.ip_deny_list.write().unwrap().add_ban_memory(
+            "192.0.2.0/24".parse().unwrap(),
+            "Bad net".to_string(),
+            None,
+            "oper".to_string(),
+        );
+
+        let ts = chrono::Utc::now().timestamp();
+        tm.run_server(
+            "00A",
+            &format!(":00A UID glined 1 {ts} glined evil.example 00AAAAAAA + :G-lined"),
+        )
+        .await;
+        tm.run_server(
+            "00A",
+            &format!(":00A UID zlined 1 {ts} zlined 192.0.2.5 00AAAAAAB + :Z-lined"),
+        )
+        .await;
+
+        for (uid, reason) in [("00AAAAAAA", "Network ban"), ("00AAAAAAB", "Bad net")] {
+            assert!(!tm.matrix.user_manager.users.contains_key(uid));
+            let kill = link.try_recv().expect("KILL sent back to the source link");
+            assert!(
+                matches!(&kill.command, Command::KILL(target, text) if target == uid && text.contains(reason)),
+                "unexpected message: {kill}"
+            );
+        }
+        assert!(tm.matrix.user_manager.get_first_uid("glined").is_none());
+        assert!(tm.matrix.user_manager.get_first_uid("zlined").is_none());
+    }
 
     #[test]
     fn test_uid_to_crdt_basic() {
@@ -280,4 +478,17 @@ mod tests {
         assert!(*crdt.modes.invisible.value());
         assert!(!(*crdt.modes.oper.value()));
     }
+
+    #[tokio::test]
+    async fn test_uid_is_relayed_to_other_links() {
+        let tm = crate::test_support::TestMatrixBuilder::new().build().await;
+        let mut origin = tm.peer("00A").await;
+        let mut other = tm.peer("00B").await;
+
+        tm.remote_user("00A", "00AAAAAAA", "bob").await;
+
+        let relayed = other.try_recv().expect("UID is relayed to the other link");
+        assert!(matches!(&relayed.command, Command::UID(nick, ..) if nick == "bob"));
+        assert!(origin.try_recv().is_err(), "UID is echoed to its origin");
+    }
 }
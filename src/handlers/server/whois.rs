@@ -0,0 +1,230 @@
+use crate::handlers::core::traits::ServerHandler;
+use crate::handlers::server::source::extract_source_uid;
+use crate::handlers::user::query::whois::whois_replies;
+use crate::handlers::{Context, HandlerError, HandlerResult};
+use crate::state::ServerState;
+use crate::state::dashmap_ext::DashMapExt;
+use crate::sync::TopologyGraph;
+use async_trait::async_trait;
+use slirc_proto::sync::clock::ServerId;
+use slirc_proto::{Command, Message, MessageRef, Prefix, Response, irc_to_lower};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Numerics a remote WHOIS answers with, relayed back to the requester.
+pub const WHOIS_NUMERICS: &[&str] = &[
+    "276", "301", "311", "312", "313", "317", "318", "319", "330", "335", "401", "671",
+];
+
+/// Handler for a WHOIS forwarded from another server.
+///
+/// Format: `:<uid> WHOIS <sid|name> :<nick>`
+///
+/// If we are the named server the numerics are sent back towards `<uid>`,
+/// addressed to it; otherwise the query moves one hop closer.
+pub struct WhoisHandler;
+
+#[async_trait]
+impl ServerHandler for WhoisHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, ServerState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let requester_uid = extract_source_uid(msg).ok_or(HandlerError::NeedMoreParams)?;
+        let server = msg.arg(0).ok_or(HandlerError::NeedMoreParams)?;
+        let target = msg.arg(1).ok_or(HandlerError::NeedMoreParams)?;
+        let matrix = ctx.matrix;
+        let sync = &matrix.sync_manager;
+
+        if server != sync.local_id.as_str()
+            && !server.eq_ignore_ascii_case(&matrix.server_info.name)
+        {
+            let sid = sync
+                .topology
+                .servers
+                .iter()
+                .find(|e| e.key().as_str() == server || e.value().name.eq_ignore_ascii_case(server))
+                .map(|e| e.key().clone());
+            match sid.and_then(|sid| sync.get_next_hop(&sid)) {
+                Some(link) => {
+                    let _ = link.tx.send(Arc::new(msg.to_owned())).await;
+                }
+                None => debug!(server = %server, "WHOIS for unknown server"),
+            }
+            return Ok(());
+        }
+
+        let Some(requester) = matrix.user_manager.users.get_cloned(requester_uid) else {
+            debug!(uid = %requester_uid, "WHOIS from unknown user");
+            return Ok(());
+        };
+        let requester_nick = requester.read().await.nick.clone();
+
+        let replies = match matrix.user_manager.get_first_uid(&irc_to_lower(target)) {
+            Some(target_uid) => {
+                whois_replies(matrix, requester_uid, &requester_nick, &target_uid).await
+            }
+            None => None,
+        };
+        let replies = replies.unwrap_or_else(|| {
+            vec![
+                (
+                    Response::ERR_NOSUCHNICK,
+                    vec![
+                        requester_nick.clone(),
+                        target.to_string(),
+                        "No such nick/channel".to_string(),
+                    ],
+                ),
+                (
+                    Response::RPL_ENDOFWHOIS,
+                    vec![
+                        requester_nick,
+                        target.to_string(),
+                        "End of WHOIS list".to_string(),
+                    ],
+                ),
+            ]
+        });
+
+        // Addressed by UID across the network; the requester's server puts
+        // the nick back in
+        for (response, mut params) in replies {
+            params[0] = requester_uid.to_string();
+            let reply = Message {
+                tags: None,
+                prefix: Some(Prefix::ServerName(matrix.server_info.name.clone())),
+                command: Command::Response(response, params),
+            };
+            sync.route_to_remote_user(requester_uid, Arc::new(reply))
+                .await;
+        }
+
+        Ok(())
+    }
+}
+
+/// Handler for the numerics of a remote WHOIS on their way back.
+///
+/// Format: `:<server> <numeric> <uid> ...`
+///
+/// Delivered to `<uid>` under its nick if it is ours, routed on otherwise.
+pub struct WhoisReplyHandler;
+
+#[async_trait]
+impl ServerHandler for WhoisReplyHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, ServerState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let uid = msg.arg(0).ok_or(HandlerError::NeedMoreParams)?;
+        let matrix = ctx.matrix;
+        let mut reply = msg.to_owned();
+        reply.tags = None;
+
+        let ours = TopologyGraph::origin_of(uid)
+            .is_some_and(|sid| sid == ServerId::new(matrix.server_info.sid.clone()));
+        if !ours {
+            matrix
+                .sync_manager
+                .route_to_remote_user(uid, Arc::new(reply))
+                .await;
+            return Ok(());
+        }
+
+        let Some(user) = matrix.user_manager.users.get_cloned(uid) else {
+            return Ok(());
+        };
+        let nick = user.read().await.nick.clone();
+        if let Command::Response(_, params) = &mut reply.command
+            && let Some(first) = params.first_mut()
+        {
+            *first = nick;
+        }
+        matrix.user_manager.send_to_uid(uid, Arc::new(reply)).await;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{Command, Response};
+
+    #[tokio::test]
+    async fn forwarded_whois_is_answered_towards_the_requester() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut peer = tm.peer("00A").await;
+        tm.remote_user("00A", "00AAAAAAA", "asker").await;
+        let _alice = tm.user("alice").build().await;
+
+        tm.run_server("00A", ":00AAAAAAA WHOIS 00T :alice").await;
+
+        let replies: Vec<_> = std::iter::from_fn(|| peer.try_recv().ok()).collect();
+        let numerics: Vec<_> = replies
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::Response(r, params) => Some((*r, params.clone())),
+                _ => None,
+            })
+            .collect();
+        assert!(
+            numerics.iter().any(|(r, p)| *r == Response::RPL_WHOISIDLE
+                && p[0] == "00AAAAAAA"
+                && p[1] == "alice")
+        );
+        assert_eq!(
+            numerics.last().map(|(r, _)| *r),
+            Some(Response::RPL_ENDOFWHOIS)
+        );
+    }
+
+    #[tokio::test]
+    async fn two_argument_whois_is_forwarded_to_the_owning_server() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut peer = tm.peer("00A").await;
+        tm.remote_user("00A", "00AAAAAAA", "carol").await;
+        let mut alice = tm.user("alice").build().await;
+
+        let replies = tm.run(&mut alice, "WHOIS carol carol").await;
+        assert!(replies.is_empty());
+
+        let forwarded = std::iter::from_fn(|| peer.try_recv().ok())
+            .find(|m| matches!(&m.command, Command::WHOIS(..)))
+            .expect("query forwarded");
+        assert_eq!(forwarded.source_nickname(), Some(alice.uid.as_str()));
+        assert!(matches!(
+            &forwarded.command,
+            Command::WHOIS(Some(server), nick) if server == "00A" && nick == "carol"
+        ));
+    }
+
+    #[tokio::test]
+    async fn whois_numerics_reach_the_local_requester_by_nick() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let _peer = tm.peer("00A").await;
+        let mut alice = tm.user("alice").build().await;
+
+        tm.run_server(
+            "00A",
+            &format!(
+                ":00a.test 317 {} bob 42 1700000000 :seconds idle, signon time",
+                alice.uid
+            ),
+        )
+        .await;
+
+        let idle = alice
+            .recv_matching(|m| matches!(&m.command, Command::Response(Response::RPL_WHOISIDLE, _)))
+            .await
+            .expect("idle numeric delivered");
+        let Command::Response(_, params) = &idle.command else {
+            unreachable!();
+        };
+        assert_eq!(params[0], "alice");
+        assert_eq!(params[2], "42");
+    }
+}
@@ -36,17 +36,20 @@ impl PostRegHandler for AdminHandler {
 
         // RPL_ADMINLOC1 (257): :<admin info> - organization/server description
         // Read from hot_config for hot-reload support
-        let (admin_info1_opt, admin_info2_opt, admin_email_opt) = {
+        let ((admin_info1_opt, admin_info2_opt, admin_email_opt), network, description) = {
             let hot = ctx.matrix.hot_config.read();
-            hot.admin_info.clone()
+            (
+                hot.admin_info.clone(),
+                hot.network.clone(),
+                hot.description.clone(),
+            )
         };
-        let admin_info1 =
-            admin_info1_opt.unwrap_or_else(|| ctx.matrix.server_info.description.clone());
+        let admin_info1 = admin_info1_opt.unwrap_or(description);
         ctx.send_reply(Response::RPL_ADMINLOC1, vec![nick.to_string(), admin_info1])
             .await?;
 
         // RPL_ADMINLOC2 (258): :<admin info> - location/network
-        let admin_info2 = admin_info2_opt.unwrap_or_else(|| ctx.matrix.server_info.network.clone());
+        let admin_info2 = admin_info2_opt.unwrap_or(network);
         ctx.send_reply(Response::RPL_ADMINLOC2, vec![nick.to_string(), admin_info2])
             .await?;
 
@@ -10,6 +10,13 @@ use slirc_proto::{MessageRef, Response};
 
 /// Static help text for commands.
 const HELP_TOPICS: &[(&str, &[&str])] = &[
+    (
+        "ACCEPT",
+        &[
+            "ACCEPT [nick[,-nick...]] | ACCEPT *",
+            "Edits or lists who may message you while you have user mode +g.",
+        ],
+    ),
     (
         "ADMIN",
         &[
@@ -24,6 +31,13 @@ const HELP_TOPICS: &[(&str, &[&str])] = &[
             "Marks you as away. Without message, clears away status.",
         ],
     ),
+    (
+        "BOTSERV",
+        &[
+            "BOTSERV <command> [args]",
+            "Send command to BotServ. Alias: BS",
+        ],
+    ),
     (
         "CAP",
         &[
@@ -31,6 +45,14 @@ const HELP_TOPICS: &[(&str, &[&str])] = &[
             "IRCv3 capability negotiation.",
         ],
     ),
+    (
+        "CHALLENGE",
+        &[
+            "CHALLENGE <name> | CHALLENGE +<signature>",
+            "Authenticates as an IRC operator by signing a server challenge",
+            "with the key configured for the oper block.",
+        ],
+    ),
     (
         "CHANSERV",
         &[
@@ -38,9 +60,20 @@ const HELP_TOPICS: &[(&str, &[&str])] = &[
             "Send command to ChanServ. Alias: CS",
         ],
     ),
+    (
+        "DEBUG",
+        &[
+            "DEBUG <nick>",
+            "Dumps a user's server-side state (IRC operators only).",
+        ],
+    ),
     (
         "DIE",
-        &["DIE", "Shuts down the server (IRC operators only)."],
+        &[
+            "DIE [reason]",
+            "Warns users, flushes state and shuts down the server once",
+            "connections drain (IRC operators only).",
+        ],
     ),
     (
         "HELP",
@@ -49,6 +82,13 @@ const HELP_TOPICS: &[(&str, &[&str])] = &[
             "Shows help for a command, or lists all commands.",
         ],
     ),
+    (
+        "HOSTSERV",
+        &[
+            "HOSTSERV <command> [args]",
+            "Send command to HostServ. Alias: HS",
+        ],
+    ),
     (
         "INFO",
         &["INFO [server]", "Returns information about the server."],
@@ -78,15 +118,16 @@ const HELP_TOPICS: &[(&str, &[&str])] = &[
     (
         "KILL",
         &[
-            "KILL <nick> <reason>",
+            "KILL <nick|mask> [CONFIRM] <reason>",
             "Disconnects a user (IRC operators only).",
+            "Masks (nick!user@host, IP wildcard or CIDR) require CONFIRM.",
         ],
     ),
     (
         "KNOCK",
         &[
             "KNOCK <channel> [message]",
-            "Requests an invite to a channel.",
+            "Asks the operators of an invite-only (+i) channel for an invite.",
         ],
     ),
     (
@@ -101,11 +142,38 @@ const HELP_TOPICS: &[(&str, &[&str])] = &[
         "LUSERS",
         &["LUSERS [mask [server]]", "Returns user statistics."],
     ),
+    (
+        "MAINTENANCE",
+        &[
+            "MAINTENANCE [ON [reason] | OFF | RESTART [reason]]",
+            "Refuses new connections and freezes services writes (IRC operators only).",
+            "RESTART also flushes state and shuts down for a supervised restart.",
+        ],
+    ),
+    (
+        "MARKREAD",
+        &[
+            "MARKREAD <target> [timestamp=<time>]",
+            "Shows or advances your account's read marker for a channel or query.",
+        ],
+    ),
+    (
+        "METADATA",
+        &[
+            "METADATA <target|*> <GET key... | LIST | SET key [value] | CLEAR | SYNC>",
+            "METADATA * <SUB key... | UNSUB key... | SUBS>",
+            "Reads or edits user/channel metadata, or manages your subscriptions.",
+        ],
+    ),
     (
         "MODE",
         &[
             "MODE <target> [modes [args]]",
             "Sets or queries modes on channels/users.",
+            "+f <lines>:<seconds>[:kick|ban|mute] limits each member's messages.",
+            "+j <joins>:<seconds> limits joins; +R admits only logged-in users.",
+            "+H <lines>:<minutes> replays recent messages to joining users.",
+            "A trailing ~<duration> (+b mask :~30m) makes list entries expire.",
         ],
     ),
     (
@@ -141,11 +209,26 @@ const HELP_TOPICS: &[(&str, &[&str])] = &[
             "Sends a notice (no auto-reply).",
         ],
     ),
+    (
+        "OMODE",
+        &[
+            "OMODE [CONFIRM] <#mask> <modes> [params]",
+            "Previews, or with CONFIRM applies, a mode change on every matching channel (IRC operators only).",
+        ],
+    ),
     (
         "OPER",
         &[
-            "OPER <name> <password>",
-            "Authenticates as an IRC operator.",
+            "OPER <name> [password]",
+            "Authenticates as an IRC operator. The password is left out",
+            "for blocks that authenticate by client certificate alone.",
+        ],
+    ),
+    (
+        "OPERSERV",
+        &[
+            "OPERSERV <command> [args]",
+            "Send command to OperServ (operators only). Alias: OS",
         ],
     ),
     (
@@ -165,16 +248,36 @@ const HELP_TOPICS: &[(&str, &[&str])] = &[
         ],
     ),
     ("QUIT", &["QUIT [message]", "Disconnects from the server."]),
+    (
+        "REDACT",
+        &[
+            "REDACT <target> <msgid> [reason]",
+            "Deletes a message you sent (or, as a channel operator, any message) from history.",
+        ],
+    ),
     (
         "REHASH",
         &[
             "REHASH",
-            "Reloads server configuration (IRC operators only).",
+            "Reloads server configuration and reports each section",
+            "(IRC operators only). SIGHUP does the same.",
         ],
     ),
     (
         "RESTART",
-        &["RESTART", "Restarts the server (IRC operators only)."],
+        &[
+            "RESTART [reason]",
+            "Shuts down as DIE does, then starts the server binary again",
+            "(IRC operators only).",
+        ],
+    ),
+    (
+        "RESV",
+        &[
+            "RESV [duration] <nick|#channel mask> [reason]",
+            "Reserves a nickname or channel name (IRC operators only).",
+            "Use UNRESV <mask> to remove it. STATS q lists reservations.",
+        ],
     ),
     (
         "SETNAME",
@@ -184,6 +287,20 @@ const HELP_TOPICS: &[(&str, &[&str])] = &[
         "STATS",
         &["STATS [query [server]]", "Returns server statistics."],
     ),
+    (
+        "TAP",
+        &[
+            "TAP <nick|ip> [OFF|SHOW [count]] | TAP LIST",
+            "Captures a connection's raw lines, secrets redacted (IRC operators only).",
+        ],
+    ),
+    (
+        "TBAN",
+        &[
+            "TBAN <channel> <duration> <mask>",
+            "Bans a mask for a while (e.g. 30m, 2h, 1d), then lifts the ban.",
+        ],
+    ),
     (
         "TIME",
         &["TIME [server]", "Returns the server's local time."],
@@ -195,6 +312,20 @@ const HELP_TOPICS: &[(&str, &[&str])] = &[
             "Sets or queries a channel's topic.",
         ],
     ),
+    (
+        "TRACEMASK",
+        &[
+            "TRACEMASK <mask>",
+            "Lists users matching a hostmask, IP wildcard or CIDR (IRC operators only).",
+        ],
+    ),
+    (
+        "UNRESV",
+        &[
+            "UNRESV <mask>",
+            "Removes a nickname or channel reservation (IRC operators only).",
+        ],
+    ),
     (
         "USERHOST",
         &[
@@ -210,6 +341,13 @@ const HELP_TOPICS: &[(&str, &[&str])] = &[
         "WALLOPS",
         &["WALLOPS <message>", "Sends message to all operators."],
     ),
+    (
+        "WATCH",
+        &[
+            "WATCH [+nick|-nick|C|S|L|l ...]",
+            "Legacy online status notifications; shares the MONITOR list.",
+        ],
+    ),
     ("WHO", &["WHO [mask [o]]", "Lists users matching the mask."]),
     (
         "WHOIS",
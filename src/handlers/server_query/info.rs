@@ -65,7 +65,7 @@ impl PostRegHandler for InfoHandler {
             "DashMap concurrent state management".to_string(),
             "".to_string(),
             format!("Server: {}", ctx.server_name()),
-            format!("Network: {}", ctx.matrix.server_info.network),
+            format!("Network: {}", ctx.matrix.hot_config.read().network),
         ];
 
         // RPL_INFO (371): :<string>
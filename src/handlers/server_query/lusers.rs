@@ -2,16 +2,65 @@
 //!
 //! Returns statistics about the size of the IRC network.
 
-use super::super::{Context, HandlerResult, PostRegHandler};
-use crate::state::RegisteredState;
+use super::super::{Context, HandlerResult, PostRegHandler, get_oper_info};
+use crate::state::{Matrix, RegisteredState};
 use async_trait::async_trait;
 use slirc_proto::{MessageRef, Response};
 
+/// User count and link age of one server in the network.
+pub(super) struct ServerLoad {
+    pub name: String,
+    pub sid: String,
+    pub hopcount: u32,
+    pub users: usize,
+    /// Seconds since the server linked (our own uptime for the local server).
+    pub linked_secs: u64,
+}
+
+/// Per-server user distribution: the local server first, then every server
+/// in the topology ordered by hop count and name.
+pub(super) fn server_loads(matrix: &Matrix) -> Vec<ServerLoad> {
+    let counts = matrix.user_manager.count_by_server();
+    let local_sid = matrix.server_info.sid.as_str();
+
+    let mut remote: Vec<ServerLoad> = matrix
+        .sync_manager
+        .topology
+        .servers
+        .iter()
+        .filter(|s| s.sid.as_str() != local_sid)
+        .map(|s| ServerLoad {
+            name: s.name.clone(),
+            sid: s.sid.as_str().to_string(),
+            hopcount: s.hopcount,
+            users: counts.get(s.sid.as_str()).copied().unwrap_or(0),
+            linked_secs: s.linked_at.elapsed().as_secs(),
+        })
+        .collect();
+    remote.sort_by(|a, b| {
+        a.hopcount
+            .cmp(&b.hopcount)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    let mut loads = Vec::with_capacity(remote.len() + 1);
+    loads.push(ServerLoad {
+        name: matrix.server_info.name.clone(),
+        sid: local_sid.to_string(),
+        hopcount: 0,
+        users: counts.get(local_sid).copied().unwrap_or(0),
+        linked_secs: matrix.stats_manager.uptime_secs(),
+    });
+    loads.extend(remote);
+    loads
+}
+
 /// Handler for LUSERS command.
 ///
 /// `LUSERS [mask [target]]`
 ///
-/// Returns statistics about the size of the IRC network.
+/// Returns statistics about the size of the IRC network. Operators also get
+/// a notice per server with its user count and link age.
 ///
 /// **Compliance:** 9/9 irctest pass
 pub struct LusersHandler;
@@ -165,6 +214,50 @@ impl PostRegHandler for LusersHandler {
         )
         .await?;
 
+        if matches!(get_oper_info(ctx).await, Some((_, true))) {
+            for load in server_loads(ctx.matrix) {
+                ctx.send_notice(format!(
+                    "*** {} ({}): {} users, {} hops, linked {}s",
+                    load.name, load.sid, load.users, load.hopcount, load.linked_secs
+                ))
+                .await?;
+            }
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::Command;
+
+    fn server_notices(replies: &[slirc_proto::Message]) -> Vec<&str> {
+        replies
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::NOTICE(_, text) if text.starts_with("*** ") => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn lusers_lists_servers_for_opers_only() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
+        let mut oper = tm.user("oper").oper().build().await;
+        let _link = tm.peer("00A").await;
+        tm.remote_user("00A", "00AAAAAAA", "bob").await;
+
+        let replies = tm.run(&mut alice, "LUSERS").await;
+        assert!(server_notices(&replies).is_empty());
+
+        let replies = tm.run(&mut oper, "LUSERS").await;
+        let notices = server_notices(&replies);
+        assert_eq!(notices.len(), 2);
+        assert!(notices[0].contains("(00T)"));
+        assert!(notices[1].starts_with("*** 00a.test (00A): 1 users, 1 hops, linked "));
+    }
+}
@@ -51,5 +51,4 @@ pub fn register(map: &mut HashMap<&'static str, Box<dyn PostRegHandler>>) {
     map.insert("SUMMON", Box::new(SummonHandler));
     map.insert("USERS", Box::new(UsersHandler));
     map.insert("CONNECT", Box::new(s2s::ConnectHandler));
-    map.insert("SQUIT", Box::new(s2s::SquitHandler));
 }
@@ -1,6 +1,7 @@
 //! STATS handler for server statistics.
 
-use super::super::{Context, HandlerResult, PostRegHandler};
+use super::super::{Context, HandlerResult, PostRegHandler, get_oper_info};
+use super::lusers::server_loads;
 
 use crate::state::RegisteredState;
 use async_trait::async_trait;
@@ -19,8 +20,11 @@ use slirc_proto::{MessageRef, Response};
 /// - `k` - K-lines (local bans)
 /// - `g` - G-lines (global bans)
 /// - `z` - Z-lines (IP bans)
+/// - `q` - Q-lines (reserved nicknames/channels)
 /// - `c` - Connection statistics
 /// - `m` - Command usage statistics
+/// - `v` - Users and link age per server
+/// - `h` - History retention policies (operators only)
 /// - `?` - Help
 pub struct StatsHandler;
 
@@ -184,6 +188,55 @@ impl PostRegHandler for StatsHandler {
                 )
                 .await?;
             }
+            'v' | 'V' => {
+                // RPL_STATSDEBUG (249) - one line per server in the topology
+                for load in server_loads(ctx.matrix) {
+                    // :server 249 nick :v <name> <sid> <hops> <users> <linked_secs>
+                    ctx.send_reply(
+                        Response::RPL_STATSDEBUG,
+                        vec![
+                            nick.to_string(),
+                            format!(
+                                "v {} {} {} {} {}",
+                                load.name, load.sid, load.hopcount, load.users, load.linked_secs
+                            ),
+                        ],
+                    )
+                    .await?;
+                }
+            }
+            'h' | 'H' => {
+                if !get_oper_info(ctx).await.is_some_and(|(_, is_oper)| is_oper) {
+                    let reply = Response::err_noprivileges(nick).with_prefix(ctx.server_prefix());
+                    ctx.send_error("STATS", "ERR_NOPRIVILEGES", reply).await?;
+                    return Ok(());
+                }
+                // RPL_STATSDEBUG (249) - one line per retention policy
+                let retention = &ctx.matrix.config.history.retention;
+                let days = |days: u32| {
+                    if days == 0 {
+                        "forever".to_string()
+                    } else {
+                        format!("{days}d")
+                    }
+                };
+                let mut overrides: Vec<_> = retention.channels.iter().collect();
+                overrides.sort();
+                let lines = [
+                    ("channel", retention.channel_days),
+                    ("dm", retention.dm_days),
+                    ("event", retention.event_days),
+                ]
+                .into_iter()
+                .chain(overrides.into_iter().map(|(name, d)| (name.as_str(), *d)))
+                .map(|(kind, d)| format!("h {} {}", kind, days(d)))
+                .collect::<Vec<_>>();
+                for line in lines {
+                    // :server 249 nick :h <channel|dm|event|#channel> <days>d|forever
+                    ctx.send_reply(Response::RPL_STATSDEBUG, vec![nick.to_string(), line])
+                        .await?;
+                }
+            }
             'l' | 'L' => {
                 // RPL_STATSLINKINFO (211)
                 for entry in ctx.matrix.sync_manager.links.iter() {
@@ -263,6 +316,46 @@ impl PostRegHandler for StatsHandler {
                     }
                 }
             }
+            'q' | 'Q' => {
+                // Q-lines (reserved nicknames/channels): config first, then operator-set
+                let config_qlines = ctx.matrix.hot_config.read().qlines.clone();
+                for qline in config_qlines {
+                    // :server 220 nick Q <mask> 0 0 <config> :<reason>
+                    ctx.send_reply(
+                        Response::RPL_STATSDLINE,
+                        vec![
+                            nick.to_string(),
+                            "Q".to_string(),
+                            qline.mask,
+                            "0".to_string(),
+                            "0".to_string(),
+                            "<config>".to_string(),
+                            qline.reason,
+                        ],
+                    )
+                    .await?;
+                }
+                if let Ok(qlines) = ctx.db.bans().get_active_qlines().await {
+                    for qline in qlines {
+                        let duration = qline.expires_at.map(|exp| exp - qline.set_at).unwrap_or(0);
+                        let reason = qline.reason.unwrap_or_default();
+                        // :server 220 nick Q <mask> <set_at> <duration> <setter> :<reason>
+                        ctx.send_reply(
+                            Response::RPL_STATSDLINE,
+                            vec![
+                                nick.to_string(),
+                                "Q".to_string(),
+                                qline.mask,
+                                qline.set_at.to_string(),
+                                duration.to_string(),
+                                qline.set_by,
+                                reason,
+                            ],
+                        )
+                        .await?;
+                    }
+                }
+            }
             's' | 'S' => {
                 // Shuns
                 if let Ok(shuns) = ctx.db.bans().get_active_shuns().await {
@@ -441,11 +534,14 @@ impl PostRegHandler for StatsHandler {
                     "*** z - Z-lines (IP bans)",
                     "*** d - D-lines (IP bans)",
                     "*** r - R-lines (Realname bans)",
+                    "*** q - Q-lines (reserved nicks/channels)",
                     "*** s - Shuns",
                     "*** i - IP deny list (in-memory)",
                     "*** p - Spam detection settings",
                     "*** c - Connection statistics",
                     "*** m - Command usage statistics",
+                    "*** v - Users and link age per server",
+                    "*** h - History retention policies (operators only)",
                     "*** ? - This help message",
                 ];
                 for line in &help_lines {
@@ -475,3 +571,76 @@ impl PostRegHandler for StatsHandler {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::Command;
+
+    #[tokio::test]
+    async fn stats_v_reports_users_per_server() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
+        let _link = tm.peer("00A").await;
+        tm.remote_user("00A", "00AAAAAAA", "bob").await;
+        tm.remote_user("00A", "00AAAAAAB", "carol").await;
+
+        let replies = tm.run(&mut alice, "STATS v").await;
+        let lines: Vec<&str> = replies
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::Response(resp, args) if resp.code() == 249 => {
+                    args.last().map(String::as_str)
+                }
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("v "));
+        assert!(lines[0].contains(" 00T 0 "));
+        assert!(lines[1].starts_with("v 00a.test 00A 1 2 "));
+    }
+
+    #[tokio::test]
+    async fn stats_h_lists_retention_policies_for_opers() {
+        let tm = TestMatrixBuilder::new()
+            .config(|c| {
+                let retention = &mut c.history.retention;
+                retention.dm_days = 7;
+                retention.event_days = 0;
+                retention.channels.insert("#archive".to_string(), 365);
+            })
+            .build()
+            .await;
+        let mut oper = tm.user("oper").oper().build().await;
+        let mut alice = tm.user("alice").build().await;
+
+        let replies = tm.run(&mut oper, "STATS h").await;
+        let lines: Vec<&str> = replies
+            .iter()
+            .filter_map(|m| match &m.command {
+                Command::Response(resp, args) if resp.code() == 249 => {
+                    args.last().map(String::as_str)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "h channel 30d",
+                "h dm 7d",
+                "h event forever",
+                "h #archive 365d"
+            ]
+        );
+
+        let replies = tm.run(&mut alice, "STATS h").await;
+        assert!(
+            replies
+                .iter()
+                .any(|m| matches!(&m.command, Command::Response(resp, _) if resp.code() == 481))
+        );
+    }
+}
@@ -47,6 +47,18 @@ impl<S: SessionState> UniversalHandler<S> for RegisterHandler {
             return Ok(());
         }
 
+        // Services are read-only during maintenance
+        if ctx.matrix.lifecycle_manager.in_maintenance() {
+            let reply = fail_response(
+                server_name,
+                "TEMPORARILY_UNAVAILABLE",
+                &nick,
+                "Account registration is unavailable while the server is in maintenance",
+            );
+            ctx.sender.send(reply).await?;
+            return Ok(());
+        }
+
         // REGISTER <account> <email> <password>
         let account_arg = msg.arg(0);
         let email_arg = msg.arg(1);
@@ -64,8 +76,10 @@ impl<S: SessionState> UniversalHandler<S> for RegisterHandler {
             return Ok(());
         };
 
-        // Validate email if required
-        if acct_cfg.email_required && (email == "*" || email.is_empty() || !email.contains('@')) {
+        // Validate email if required (confirmation codes need somewhere to go)
+        if (acct_cfg.email_required || acct_cfg.verify_email)
+            && (email == "*" || email.is_empty() || !email.contains('@'))
+        {
             let reply = fail_response(
                 server_name,
                 "INVALID_EMAIL",
@@ -160,14 +174,34 @@ impl<S: SessionState> UniversalHandler<S> for RegisterHandler {
             .register(&target_account, password, Some(email))
             .await
         {
-            Ok(_) => {
+            Ok(created) => {
+                // Unconfirmed accounts are usable but limited until CONFIRM
+                let mut message = "Account created".to_string();
+                if acct_cfg.verify_email
+                    && let Some(mailer) = ctx.matrix.service_manager.nickserv.mailer()
+                {
+                    let _ = ctx.db.accounts().set_verified(created.id, false).await;
+                    if crate::services::nickserv::mail_code(
+                        ctx.db,
+                        mailer,
+                        created.id,
+                        &created.name,
+                        email,
+                        crate::db::CodeKind::Confirm,
+                    )
+                    .await
+                    {
+                        message = "Account created; confirm it with the code sent to your email (/msg NickServ CONFIRM <code>)".to_string();
+                    }
+                }
+
                 // Send success response
                 let success_msg = Message {
                     tags: None,
                     prefix: Some(Prefix::new_from_str(server_name)),
                     command: Command::REGISTER {
                         account: target_account.to_string(),
-                        message: Some("Account created".to_string()),
+                        message: Some(message),
                     },
                 };
                 ctx.sender.send(success_msg).await?;
@@ -1,4 +1,5 @@
-//! Service command aliases: NS (NickServ), CS (ChanServ)
+//! Service command aliases: NS (NickServ), CS (ChanServ), HS (HostServ),
+//! OS (OperServ), BS (BotServ)
 //!
 //! Provides shortcut commands for interacting with IRC services.
 
@@ -65,3 +66,84 @@ impl PostRegHandler for CsHandler {
         Ok(())
     }
 }
+
+/// Handler for HS (HostServ alias) command.
+///
+/// `HS <command> [args]`
+///
+/// Shortcut for PRIVMSG HostServ.
+pub struct HsHandler;
+
+#[async_trait]
+impl PostRegHandler for HsHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, RegisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let nick = ctx.nick();
+
+        // Join all args into the command text
+        let text = msg.args().join(" ");
+        let cmd_text = if text.is_empty() { "HELP" } else { &text };
+
+        // Route to HostServ via unified service router
+        route_service_message(ctx.matrix, ctx.uid, nick, "HostServ", cmd_text, &ctx.sender).await;
+
+        Ok(())
+    }
+}
+
+/// Handler for OS (OperServ alias) command.
+///
+/// `OS <command> [args]`
+///
+/// Shortcut for PRIVMSG OperServ.
+pub struct OsHandler;
+
+#[async_trait]
+impl PostRegHandler for OsHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, RegisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let nick = ctx.nick();
+
+        // Join all args into the command text
+        let text = msg.args().join(" ");
+        let cmd_text = if text.is_empty() { "HELP" } else { &text };
+
+        // Route to OperServ via unified service router
+        route_service_message(ctx.matrix, ctx.uid, nick, "OperServ", cmd_text, &ctx.sender).await;
+
+        Ok(())
+    }
+}
+
+/// Handler for BS (BotServ alias) command.
+///
+/// `BS <command> [args]`
+///
+/// Shortcut for PRIVMSG BotServ.
+pub struct BsHandler;
+
+#[async_trait]
+impl PostRegHandler for BsHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, RegisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let nick = ctx.nick();
+
+        // Join all args into the command text
+        let text = msg.args().join(" ");
+        let cmd_text = if text.is_empty() { "HELP" } else { &text };
+
+        // Route to BotServ via unified service router
+        route_service_message(ctx.matrix, ctx.uid, nick, "BotServ", cmd_text, &ctx.sender).await;
+
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
 pub mod monitor;
 pub mod query;
 pub mod status;
+pub mod watch;
@@ -11,9 +11,6 @@ use dashmap::DashSet;
 use slirc_proto::{MessageRef, Response, irc_to_lower};
 use tracing::debug;
 
-/// Maximum number of nicknames a user can monitor.
-const MAX_MONITOR_TARGETS: usize = 100;
-
 /// Handler for MONITOR command.
 ///
 /// `MONITOR + targets` - Add targets to monitor list
@@ -70,6 +67,8 @@ async fn handle_add(
         _ => return Ok(()),
     };
 
+    let max_targets = ctx.matrix.hot_config.read().max_monitor_targets;
+
     // Get or create this user's monitor set
     let user_monitors = ctx
         .matrix
@@ -90,14 +89,14 @@ async fn handle_add(
         let target_lower = irc_to_lower(target);
 
         // Check if we're at the limit
-        if user_monitors.len() >= MAX_MONITOR_TARGETS {
+        if user_monitors.len() >= max_targets {
             // Send ERR_MONLISTFULL (734)
             let reply = server_reply(
                 server_name,
                 Response::ERR_MONLISTFULL,
                 vec![
                     nick.to_string(),
-                    MAX_MONITOR_TARGETS.to_string(),
+                    max_targets.to_string(),
                     target.to_string(),
                     "Monitor list is full".to_string(),
                 ],
@@ -188,20 +187,7 @@ async fn handle_remove(
 /// Handle MONITOR C - clear all monitored nicknames.
 #[allow(clippy::result_large_err)]
 fn handle_clear(ctx: &mut Context<'_, RegisteredState>) -> HandlerResult {
-    if let Some((_, user_monitors)) = ctx.matrix.monitor_manager.monitors.remove(ctx.uid) {
-        // Remove from all reverse mappings
-        for target_lower in user_monitors.iter() {
-            if let Some(watchers) = ctx
-                .matrix
-                .monitor_manager
-                .monitoring
-                .get(target_lower.as_str())
-            {
-                watchers.remove(ctx.uid);
-            }
-        }
-    }
-
+    clear_monitor_list(ctx.matrix, ctx.uid);
     Ok(())
 }
 
@@ -297,6 +283,7 @@ async fn handle_status(
 // MONITOR notification helpers (called from connection handlers)
 // ============================================================================
 
+use super::watch::watch_presence_reply;
 use crate::state::Matrix;
 use std::sync::Arc;
 
@@ -327,17 +314,30 @@ pub async fn notify_monitors_online(matrix: &Arc<Matrix>, nick: &str, user: &str
     );
 
     for watcher_uid in watcher_uids {
+        // WATCH clients get RPL_LOGON instead
+        let reply = if matrix.monitor_manager.watch_users.contains(&watcher_uid) {
+            let Some(reply) =
+                watch_presence_reply(matrix, &watcher_uid, Response::RPL_LOGON, nick, user, host)
+                    .await
+            else {
+                continue;
+            };
+            reply
+        } else {
+            reply.clone()
+        };
         matrix
             .user_manager
-            .send_to_uid(&watcher_uid, Arc::new(reply.clone()))
+            .send_to_uid(&watcher_uid, Arc::new(reply))
             .await;
     }
 }
 
 /// Notify all monitors that a user has gone offline.
 ///
-/// Called when a user disconnects or changes nick.
-pub async fn notify_monitors_offline(matrix: &Arc<Matrix>, nick: &str) {
+/// Called when a user disconnects or changes nick. `user` and `host` are only
+/// shown to WATCH clients.
+pub async fn notify_monitors_offline(matrix: &Arc<Matrix>, nick: &str, user: &str, host: &str) {
     let nick_lower = irc_to_lower(nick);
     let server_name = &matrix.server_info.name;
 
@@ -360,15 +360,33 @@ pub async fn notify_monitors_offline(matrix: &Arc<Matrix>, nick: &str) {
     );
 
     for watcher_uid in watcher_uids {
+        // WATCH clients get RPL_LOGOFF instead
+        let reply = if matrix.monitor_manager.watch_users.contains(&watcher_uid) {
+            let Some(reply) =
+                watch_presence_reply(matrix, &watcher_uid, Response::RPL_LOGOFF, nick, user, host)
+                    .await
+            else {
+                continue;
+            };
+            reply
+        } else {
+            reply.clone()
+        };
         matrix
             .user_manager
-            .send_to_uid(&watcher_uid, Arc::new(reply.clone()))
+            .send_to_uid(&watcher_uid, Arc::new(reply))
             .await;
     }
 }
 
 /// Clean up a user's monitor entries when they disconnect.
 pub fn cleanup_monitors(matrix: &Arc<Matrix>, uid: &str) {
+    clear_monitor_list(matrix, uid);
+    matrix.monitor_manager.watch_users.remove(uid);
+}
+
+/// Empty a user's MONITOR/WATCH list and drop them from the reverse mapping.
+pub(super) fn clear_monitor_list(matrix: &Matrix, uid: &str) {
     if let Some((_, user_monitors)) = matrix.monitor_manager.monitors.remove(uid) {
         // Remove from all reverse mappings
         for target_lower in user_monitors.iter() {
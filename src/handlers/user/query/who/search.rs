@@ -1,6 +1,8 @@
 use super::common::{WhoUserInfo, get_member_prefixes, matches_mask};
+use crate::handlers::helpers::targets::TargetMask;
 use crate::handlers::{Context, HandlerResult, server_reply};
 use crate::state::RegisteredState;
+use crate::state::dashmap_ext::DashMapExt;
 use slirc_proto::{Message, Response, irc_to_lower};
 
 /// Execute WHO search on a channel.
@@ -157,14 +159,36 @@ where
         })
         .unwrap_or_default();
 
-    // Collect all users
-    let all_users: Vec<_> = ctx
-        .matrix
-        .user_manager
-        .users
-        .iter()
-        .map(|e| (e.key().clone(), e.value().clone()))
-        .collect();
+    // Operators may also match on real host/IP (WHO 192.168.*, WHO 10.0.0.0/8)
+    let oper_mask = requester_is_oper.then(|| TargetMask::parse(mask_str));
+
+    // Exact queries only need the users filed under that term; patterns scan
+    // every user
+    let user_manager = &ctx.matrix.user_manager;
+    let candidates = user_manager
+        .index
+        .literal_candidates(mask_str)
+        .map(|mut uids| {
+            if let Some(nick_uids) = user_manager.nicks.get(&mask_lower) {
+                for uid in nick_uids.iter() {
+                    if !uids.contains(uid) {
+                        uids.push(uid.clone());
+                    }
+                }
+            }
+            uids
+        });
+    let all_users: Vec<_> = match candidates {
+        Some(uids) => uids
+            .into_iter()
+            .filter_map(|uid| user_manager.users.get_cloned(&uid).map(|arc| (uid, arc)))
+            .collect(),
+        None => user_manager
+            .users
+            .iter()
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect(),
+    };
 
     // Result limiting
     let max_results = ctx.matrix.config.limits.max_who_results;
@@ -209,7 +233,8 @@ where
         let matches = matches_mask(&nick_lower, &mask_lower)
             || matches_mask(&user_lower, &mask_lower)
             || matches_mask(&host_lower, &mask_lower)
-            || matches_mask(&realname_lower, &mask_lower);
+            || matches_mask(&realname_lower, &mask_lower)
+            || oper_mask.as_ref().is_some_and(|m| m.matches(&user));
 
         if matches {
             let user_info = WhoUserInfo {
@@ -8,4 +8,5 @@ mod whowas;
 pub use ison::IsonHandler;
 pub use userhost::UserhostHandler;
 pub use whois_cmd::WhoisHandler;
+pub(crate) use whois_cmd::whois_replies;
 pub use whowas::WhowasHandler;
@@ -1,10 +1,19 @@
 //! WHOIS handler for detailed user information queries.
+//!
+//! `WHOIS <server|nick> <nick>` asks the named server, or the one the nick
+//! is on, instead of answering locally. The query travels as
+//! `:<uid> WHOIS <sid> :<nick>` and the owning server routes its numerics
+//! back to the requester (see `handlers::server::whois`).
 
 use crate::handlers::{Context, HandlerResult, PostRegHandler, server_notice};
-use crate::state::RegisteredState;
 use crate::state::actor::{ChannelEvent, ChannelInfo};
+use crate::state::dashmap_ext::DashMapExt;
+use crate::state::{Matrix, RegisteredState};
+use crate::sync::TopologyGraph;
 use async_trait::async_trait;
-use slirc_proto::{MessageRef, Response, irc_to_lower};
+use slirc_proto::sync::clock::ServerId;
+use slirc_proto::{Command, Message, MessageRef, Prefix, Response, irc_to_lower};
+use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tracing::debug;
 
@@ -57,11 +66,304 @@ async fn get_channel_display_info(
     Some(format!("{}{}", prefix, channel_name))
 }
 
+/// Build the WHOIS numerics for `target_uid` as seen by `requester_uid`.
+///
+/// Each entry is a reply and its parameters, starting with `requester_nick`
+/// and ending with RPL_ENDOFWHOIS. `None` if the target is gone.
+pub(crate) async fn whois_replies(
+    matrix: &Matrix,
+    requester_uid: &str,
+    requester_nick: &str,
+    target_uid: &str,
+) -> Option<Vec<(Response, Vec<String>)>> {
+    let target_user_arc = matrix.user_manager.users.get_cloned(target_uid)?;
+    let nick = requester_nick.to_string();
+
+    // Clone needed data, drop lock immediately to prevent holding during async ops
+    let (
+        target_nick,
+        target_user_name,
+        target_visible_host,
+        target_host,
+        target_ip,
+        target_gateway,
+        target_realname,
+        target_channels,
+        target_modes,
+        target_account,
+        target_away,
+        target_certfp,
+        target_last_active,
+        target_signon,
+    ) = {
+        let target_user = target_user_arc.read().await;
+        (
+            target_user.nick.clone(),
+            target_user.user.clone(),
+            target_user.visible_host.clone(),
+            target_user.host.clone(),
+            target_user.ip.clone(),
+            target_user.gateway.clone(),
+            target_user.realname.clone(),
+            target_user.channels.iter().cloned().collect::<Vec<_>>(),
+            target_user.modes.clone(),
+            target_user.account.clone(),
+            target_user.away.clone(),
+            target_user.certfp.clone(),
+            target_user
+                .last_active
+                .load(std::sync::atomic::Ordering::Relaxed),
+            target_user.created_at,
+        )
+    }; // Lock dropped here
+
+    let mut replies = Vec::with_capacity(12);
+
+    // RPL_WHOISUSER (311): <nick> <user> <host> * :<realname>
+    replies.push((
+        Response::RPL_WHOISUSER,
+        vec![
+            nick.clone(),
+            target_nick.clone(),
+            target_user_name,
+            target_visible_host,
+            "*".to_string(),
+            target_realname,
+        ],
+    ));
+
+    // RPL_WHOISSERVER (312): <nick> <server> :<server info>
+    // Resolve server name correctly for remote users
+    let (real_server_name, real_server_info) = if target_uid.starts_with(matrix.server_id.as_str())
+    {
+        (
+            matrix.server_info.name.clone(),
+            matrix.hot_config.read().description.clone(),
+        )
+    } else {
+        // Remote user - look up server in topology
+        let sid = &target_uid[0..3];
+        let sid_obj = ServerId::new(sid.to_string());
+        if let Some(entry) = matrix.sync_manager.topology.servers.get(&sid_obj) {
+            (entry.name.clone(), entry.info.clone())
+        } else {
+            // Fallback if server missing from topology (should not happen)
+            (sid.to_string(), "Unknown Server".to_string())
+        }
+    };
+
+    replies.push((
+        Response::RPL_WHOISSERVER,
+        vec![
+            nick.clone(),
+            target_nick.clone(),
+            real_server_name,
+            real_server_info,
+        ],
+    ));
+
+    // RPL_WHOISIDLE (317): <nick> <integer> <integer> :seconds idle, signon time
+    let now = chrono::Utc::now().timestamp();
+    let last_active_secs = target_last_active / 1000;
+    let idle_secs = if now > last_active_secs {
+        now - last_active_secs
+    } else {
+        0
+    };
+
+    replies.push((
+        Response::RPL_WHOISIDLE,
+        vec![
+            nick.clone(),
+            target_nick.clone(),
+            idle_secs.to_string(),
+            target_signon.to_string(),
+            "seconds idle, signon time".to_string(),
+        ],
+    ));
+
+    // RPL_WHOISCHANNELS (319): <nick> :{[@|+]<channel>}
+    // Skip if target has +p (HideChannels) or target is invisible and requester doesn't share channels
+    let requester_arc = matrix.user_manager.users.get_cloned(requester_uid);
+    let is_oper = match &requester_arc {
+        Some(arc) => arc.read().await.modes.oper,
+        None => false,
+    };
+    let show_channels = if target_uid != requester_uid && !is_oper {
+        if target_modes.hide_channels {
+            false
+        } else if target_modes.invisible {
+            // Check if requester shares any channel with target
+            match &requester_arc {
+                Some(arc) => {
+                    let requester = arc.read().await;
+                    target_channels
+                        .iter()
+                        .any(|ch| requester.channels.contains(ch))
+                }
+                None => false,
+            }
+        } else {
+            true
+        }
+    } else {
+        true
+    };
+
+    if show_channels && !target_channels.is_empty() {
+        let mut channel_list = Vec::with_capacity(target_channels.len());
+        for channel_name in &target_channels {
+            let Some(channel_sender) = matrix.channel_manager.channels.get_cloned(channel_name)
+            else {
+                continue;
+            };
+            if let Some(display) =
+                get_channel_display_info(&channel_sender, requester_uid, target_uid, channel_name)
+                    .await
+            {
+                channel_list.push(display);
+            }
+        }
+
+        if !channel_list.is_empty() {
+            replies.push((
+                Response::RPL_WHOISCHANNELS,
+                vec![nick.clone(), target_nick.clone(), channel_list.join(" ")],
+            ));
+        }
+    }
+
+    // RPL_WHOISOPERATOR (313): <nick> :is an IRC operator
+    if target_modes.oper {
+        replies.push((
+            Response::RPL_WHOISOPERATOR,
+            vec![
+                nick.clone(),
+                target_nick.clone(),
+                "is an IRC operator".to_string(),
+            ],
+        ));
+    }
+
+    // RPL_WHOISBOT (335): <nick> :is a Bot
+    if target_modes.bot {
+        replies.push((
+            Response::RPL_WHOISBOT,
+            vec![
+                nick.clone(),
+                target_nick.clone(),
+                format!("is a Bot on {}", matrix.hot_config.read().network),
+            ],
+        ));
+    }
+
+    // RPL_WHOISHOST (378): <nick> :is connecting from *@<host> <ip>
+    if target_uid == requester_uid || is_oper {
+        replies.push((
+            Response::RPL_WHOISHOST,
+            vec![
+                nick.clone(),
+                target_nick.clone(),
+                format!("is connecting from *@{} {}", target_host, target_ip),
+            ],
+        ));
+    }
+
+    // RPL_WHOISGATEWAY (350): <nick> :is connected via the <gateway> WebIRC gateway
+    if let Some(gateway) = target_gateway.filter(|_| is_oper) {
+        replies.push((
+            Response::RPL_WHOISGATEWAY,
+            vec![
+                nick.clone(),
+                target_nick.clone(),
+                format!("is connected via the {} WebIRC gateway", gateway),
+            ],
+        ));
+    }
+
+    // RPL_WHOISACCOUNT (330): <nick> <account> :is logged in as
+    if let Some(account) = target_account {
+        replies.push((
+            Response::RPL_WHOISACCOUNT,
+            vec![
+                nick.clone(),
+                target_nick.clone(),
+                account,
+                "is logged in as".to_string(),
+            ],
+        ));
+    }
+
+    // RPL_WHOISSECURE (671): <nick> :is using a secure connection (if TLS)
+    if target_modes.secure {
+        replies.push((
+            Response::RPL_WHOISSECURE,
+            vec![
+                nick.clone(),
+                target_nick.clone(),
+                "is using a secure connection".to_string(),
+            ],
+        ));
+    }
+
+    // RPL_WHOISCERTFP (276): <nick> :has client certificate fingerprint <fingerprint>
+    if let Some(ref certfp) = target_certfp {
+        replies.push((
+            Response::RPL_WHOISCERTFP,
+            vec![
+                nick.clone(),
+                target_nick.clone(),
+                format!("has client certificate fingerprint {}", certfp),
+            ],
+        ));
+    }
+
+    // RPL_AWAY (301): <nick> :<away message>
+    if let Some(away_msg) = target_away {
+        replies.push((
+            Response::RPL_AWAY,
+            vec![nick.clone(), target_nick.clone(), away_msg],
+        ));
+    }
+
+    // RPL_ENDOFWHOIS (318): <nick> :End of WHOIS list
+    replies.push((
+        Response::RPL_ENDOFWHOIS,
+        vec![nick, target_nick, "End of WHOIS list".to_string()],
+    ));
+
+    Some(replies)
+}
+
+/// Resolve the `server` argument of `WHOIS <server> <nick>`.
+///
+/// Accepts a server name or SID, or a nick meaning "the server that nick is
+/// on". `None` if nothing matches.
+fn resolve_whois_server(matrix: &Matrix, server: &str) -> Option<ServerId> {
+    let sync = &matrix.sync_manager;
+    if server.eq_ignore_ascii_case(&matrix.server_info.name) || server == sync.local_id.as_str() {
+        return Some(sync.local_id.clone());
+    }
+    if let Some(sid) = sync
+        .topology
+        .servers
+        .iter()
+        .find(|e| e.key().as_str() == server || e.value().name.eq_ignore_ascii_case(server))
+        .map(|e| e.key().clone())
+    {
+        return Some(sid);
+    }
+    let uid = matrix.user_manager.get_first_uid(&irc_to_lower(server))?;
+    TopologyGraph::origin_of(&uid)
+}
+
 /// Handler for WHOIS command.
 ///
 /// `WHOIS [server] nickmask`
 ///
-/// Returns detailed information about a specific user.
+/// Returns detailed information about a specific user. With a server (or a
+/// nick standing for its server) that is not us, the query is forwarded
+/// there so idle time comes from the server the user is on.
 pub struct WhoisHandler;
 
 #[async_trait]
@@ -77,10 +379,13 @@ impl PostRegHandler for WhoisHandler {
         // WHOIS [server] <nick>
         // If two args, first is server, second is nick
         // If one arg, it's the nick
-        let target = if msg.args().len() >= 2 {
-            msg.arg(1).unwrap_or("").to_string()
+        let (server, target) = if msg.args().len() >= 2 {
+            (
+                msg.arg(0).map(str::to_string),
+                msg.arg(1).unwrap_or("").to_string(),
+            )
         } else {
-            msg.arg(0).unwrap_or("").to_string()
+            (None, msg.arg(0).unwrap_or("").to_string())
         };
 
         if !ctx
@@ -107,290 +412,58 @@ impl PostRegHandler for WhoisHandler {
             return Ok(());
         }
 
-        let server_name = ctx.server_name();
-        let nick = &ctx.state.nick; // Guaranteed present in RegisteredState
-        let target_lower = irc_to_lower(&target);
-
-        // Look up target user
-        let target_uid = ctx.matrix.user_manager.get_first_uid(&target_lower);
-        if let Some(target_uid) = target_uid {
-            let target_user_arc = ctx
-                .matrix
-                .user_manager
-                .users
-                .get(&target_uid)
-                .map(|u| u.value().clone());
-            if let Some(target_user_arc) = target_user_arc {
-                // Clone needed data, drop lock immediately to prevent holding during async ops
-                let (
-                    target_nick,
-                    target_user_name,
-                    target_visible_host,
-                    target_realname,
-                    target_channels,
-                    target_modes,
-                    target_account,
-                    target_away,
-                    target_uid_owned,
-                    target_certfp,
-                    target_last_active,
-                    target_signon,
-                ) = {
-                    let target_user = target_user_arc.read().await;
-                    (
-                        target_user.nick.clone(),
-                        target_user.user.clone(),
-                        target_user.visible_host.clone(),
-                        target_user.realname.clone(),
-                        target_user.channels.iter().cloned().collect::<Vec<_>>(),
-                        target_user.modes.clone(),
-                        target_user.account.clone(),
-                        target_user.away.clone(),
-                        target_user.uid.clone(),
-                        target_user.certfp.clone(),
-                        target_user
-                            .last_active
-                            .load(std::sync::atomic::Ordering::Relaxed),
-                        target_user.created_at,
-                    )
-                }; // Lock dropped here
-
-                // RPL_WHOISUSER (311): <nick> <user> <host> * :<realname>
-                ctx.send_reply(
-                    Response::RPL_WHOISUSER,
-                    vec![
-                        nick.clone(),
-                        target_nick.clone(),
-                        target_user_name,
-                        target_visible_host,
-                        "*".to_string(),
-                        target_realname,
-                    ],
-                )
-                .await?;
-
-                // RPL_WHOISSERVER (312): <nick> <server> :<server info>
-                // Resolve server name correctly for remote users
-                let (real_server_name, real_server_info) = if target_uid_owned
-                    .starts_with(ctx.matrix.server_id.as_str())
-                {
-                    (
-                        server_name.to_string(),
-                        ctx.matrix.server_info.description.clone(),
-                    )
-                } else {
-                    // Remote user - look up server in topology
-                    let sid = &target_uid_owned[0..3];
-                    let sid_obj = slirc_proto::sync::clock::ServerId::new(sid.to_string());
-                    if let Some(entry) = ctx.matrix.sync_manager.topology.servers.get(&sid_obj) {
-                        (entry.name.clone(), entry.info.clone())
-                    } else {
-                        // Fallback if server missing from topology (should not happen)
-                        (sid.to_string(), "Unknown Server".to_string())
-                    }
-                };
-
-                ctx.send_reply(
-                    Response::RPL_WHOISSERVER,
-                    vec![
-                        nick.clone(),
-                        target_nick.clone(),
-                        real_server_name,
-                        real_server_info,
-                    ],
-                )
-                .await?;
-
-                // RPL_WHOISIDLE (317): <nick> <integer> <integer> :seconds idle, signon time
-                let now = chrono::Utc::now().timestamp();
-                let last_active_secs = target_last_active / 1000;
-                let idle_secs = if now > last_active_secs {
-                    now - last_active_secs
+        if let Some(server) = server {
+            let route = resolve_whois_server(ctx.matrix, &server).and_then(|sid| {
+                if sid == ctx.matrix.sync_manager.local_id {
+                    Some(None)
                 } else {
-                    0
-                };
-
-                ctx.send_reply(
-                    Response::RPL_WHOISIDLE,
-                    vec![
-                        nick.clone(),
-                        target_nick.clone(),
-                        idle_secs.to_string(),
-                        target_signon.to_string(),
-                        "seconds idle, signon time".to_string(),
-                    ],
-                )
-                .await?;
-
-                // RPL_WHOISCHANNELS (319): <nick> :{[@|+]<channel>}
-                // Skip if target has +p (HideChannels) or target is invisible and requester doesn't share channels
-                let is_oper = ctx
-                    .matrix
-                    .user_manager
-                    .users
-                    .get(ctx.uid)
-                    .map(|u| u.value().clone())
-                    .map(|arc| {
-                        // Use try_read for synchronous check within the block
-                        arc.try_read().map(|u| u.modes.oper).unwrap_or(false)
-                    })
-                    .unwrap_or(false);
-                let show_channels = if target_uid != ctx.uid && !is_oper {
-                    if target_modes.hide_channels {
-                        false
-                    } else if target_modes.invisible {
-                        // Check if requester shares any channel with target
-                        let mut shares_channel = false;
-                        let requester_arc = ctx
-                            .matrix
-                            .user_manager
-                            .users
-                            .get(ctx.uid)
-                            .map(|u| u.value().clone());
-                        if let Some(requester_arc) = requester_arc {
-                            let requester = requester_arc.read().await;
-                            for ch in &target_channels {
-                                if requester.channels.contains(ch) {
-                                    shares_channel = true;
-                                    break;
-                                }
-                            }
-                        }
-                        shares_channel
-                    } else {
-                        true
-                    }
-                } else {
-                    true
-                };
-
-                if show_channels && !target_channels.is_empty() {
-                    let mut channel_list = Vec::with_capacity(target_channels.len());
-                    for channel_name in &target_channels {
-                        let Some(channel_sender) = ctx
-                            .matrix
-                            .channel_manager
-                            .channels
-                            .get(channel_name)
-                            .map(|c| c.value().clone())
-                        else {
-                            continue;
-                        };
-                        if let Some(display) = get_channel_display_info(
-                            &channel_sender,
-                            ctx.uid,
-                            &target_uid_owned,
-                            channel_name,
-                        )
-                        .await
-                        {
-                            channel_list.push(display);
-                        }
-                    }
-
-                    if !channel_list.is_empty() {
-                        ctx.send_reply(
-                            Response::RPL_WHOISCHANNELS,
-                            vec![nick.clone(), target_nick.clone(), channel_list.join(" ")],
-                        )
-                        .await?;
-                    }
+                    ctx.matrix
+                        .sync_manager
+                        .get_next_hop(&sid)
+                        .map(|link| Some((sid, link)))
                 }
-
-                // RPL_WHOISOPERATOR (313): <nick> :is an IRC operator
-                if target_modes.oper {
-                    ctx.send_reply(
-                        Response::RPL_WHOISOPERATOR,
-                        vec![
-                            nick.clone(),
-                            target_nick.clone(),
-                            "is an IRC operator".to_string(),
-                        ],
-                    )
-                    .await?;
+            });
+            match route {
+                // Answered here
+                Some(None) => {}
+                Some(Some((sid, link))) => {
+                    let query = Message {
+                        tags: None,
+                        prefix: Some(Prefix::new_from_str(ctx.uid)),
+                        command: Command::WHOIS(Some(sid.as_str().to_string()), target.clone()),
+                    };
+                    let _ = link.tx.send(Arc::new(query)).await;
+                    debug!(requester = %ctx.state.nick, target = %target, sid = %sid.as_str(), "WHOIS forwarded");
+                    return Ok(());
                 }
-
-                // RPL_WHOISBOT (335): <nick> :is a Bot
-                if target_modes.bot {
+                None => {
                     ctx.send_reply(
-                        Response::RPL_WHOISBOT,
-                        vec![
-                            nick.clone(),
-                            target_nick.clone(),
-                            format!("is a Bot on {}", ctx.matrix.server_info.network),
-                        ],
-                    )
-                    .await?;
-                }
-
-                // RPL_WHOISACCOUNT (330): <nick> <account> :is logged in as
-                if let Some(account) = target_account {
-                    ctx.send_reply(
-                        Response::RPL_WHOISACCOUNT,
-                        vec![
-                            nick.clone(),
-                            target_nick.clone(),
-                            account,
-                            "is logged in as".to_string(),
-                        ],
-                    )
-                    .await?;
-                }
-
-                // RPL_WHOISSECURE (671): <nick> :is using a secure connection (if TLS)
-                if target_modes.secure {
-                    ctx.send_reply(
-                        Response::RPL_WHOISSECURE,
-                        vec![
-                            nick.clone(),
-                            target_nick.clone(),
-                            "is using a secure connection".to_string(),
-                        ],
+                        Response::ERR_NOSUCHSERVER,
+                        vec![ctx.state.nick.clone(), server, "No such server".to_string()],
                     )
                     .await?;
+                    return Ok(());
                 }
+            }
+        }
 
-                // RPL_WHOISCERTFP (276): <nick> :has client certificate fingerprint <fingerprint>
-                if let Some(ref certfp) = target_certfp {
-                    ctx.send_reply(
-                        Response::RPL_WHOISCERTFP,
-                        vec![
-                            nick.clone(),
-                            target_nick.clone(),
-                            format!("has client certificate fingerprint {}", certfp),
-                        ],
-                    )
-                    .await?;
-                }
+        let nick = ctx.state.nick.clone(); // Guaranteed present in RegisteredState
+        let target_lower = irc_to_lower(&target);
 
-                // RPL_AWAY (301): <nick> :<away message>
-                if let Some(away_msg) = target_away {
-                    ctx.send_reply(
-                        Response::RPL_AWAY,
-                        vec![nick.clone(), target_nick.clone(), away_msg],
-                    )
-                    .await?;
-                }
+        // Look up target user
+        let replies = match ctx.matrix.user_manager.get_first_uid(&target_lower) {
+            Some(target_uid) => whois_replies(ctx.matrix, ctx.uid, &nick, &target_uid).await,
+            None => None,
+        };
+        let Some(replies) = replies else {
+            return send_no_such_nick(ctx, &target).await;
+        };
 
-                // RPL_ENDOFWHOIS (318): <nick> :End of WHOIS list - attach label for labeled-response
-                ctx.send_reply(
-                    Response::RPL_ENDOFWHOIS,
-                    vec![
-                        nick.clone(),
-                        target_nick.clone(),
-                        "End of WHOIS list".to_string(),
-                    ],
-                )
-                .await?;
-
-                debug!(requester = %nick, target = %target_nick, "WHOIS completed");
-            } else {
-                send_no_such_nick(ctx, &target).await?;
-            }
-        } else {
-            send_no_such_nick(ctx, &target).await?;
+        for (response, params) in replies {
+            ctx.send_reply(response, params).await?;
         }
 
+        debug!(requester = %nick, target = %target, "WHOIS completed");
         Ok(())
     }
 }
@@ -117,7 +117,7 @@ impl PostRegHandler for WhowasHandler {
         }
 
         // Sort by logout_time (most recent first)
-        all_entries.sort_by(|a, b| b.logout_time.cmp(&a.logout_time));
+        all_entries.sort_by_key(|e| std::cmp::Reverse(e.logout_time));
 
         // Apply count limit if specified
         let entries_to_show: Vec<_> = if let Some(limit) = count_limit {
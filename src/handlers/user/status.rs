@@ -55,7 +55,7 @@ impl PostRegHandler for AwayHandler {
             .map(|u| u.value().clone());
         if let Some(user_arc) = user_arc {
             let mut user = user_arc.write().await;
-            user.away = away_msg.map(ToString::to_string);
+            user.set_away(away_msg.map(ToString::to_string), ctx.matrix.clock());
         }
 
         // Notify observer of user update (Innovation 2)
@@ -183,7 +183,7 @@ impl PostRegHandler for SetnameHandler {
             .map(|u| u.value().clone());
         let (nick, user, visible_host) = if let Some(user_arc) = user_arc {
             let mut user = user_arc.write().await;
-            user.realname = new_realname.to_string();
+            user.set_realname(new_realname.to_string(), ctx.matrix.clock());
             (
                 user.nick.clone(),
                 user.user.clone(),
@@ -196,6 +196,8 @@ impl PostRegHandler for SetnameHandler {
         // Also update session state
         ctx.state.realname = new_realname.to_string();
 
+        ctx.matrix.user_manager.notify_observer(ctx.uid, None).await;
+
         // Broadcast SETNAME to all channels the user is in (for clients with setname cap)
         let setname_msg = slirc_proto::Message {
             tags: None,
@@ -0,0 +1,355 @@
+//! WATCH command handler (legacy presence notification).
+//!
+//! WATCH predates IRCv3 MONITOR and is still used by older clients. Entries
+//! live in the MONITOR tables, so both commands share one list and one limit;
+//! only the command syntax and numerics (600-607) differ.
+
+use crate::handlers::{Context, HandlerResult, PostRegHandler, server_reply};
+use crate::state::dashmap_ext::DashMapExt;
+use crate::state::{Matrix, RegisteredState};
+use async_trait::async_trait;
+use dashmap::DashSet;
+use slirc_proto::{Message, MessageRef, Response, irc_to_lower};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Handler for WATCH command.
+///
+/// `WATCH +nick -nick ...` - Add or remove entries
+/// `WATCH C` - Clear the watch list
+/// `WATCH S` - Show watch statistics and the list
+/// `WATCH l` / `WATCH L` - Show online (and with `L`, offline) entries
+///
+/// Entries may be separated by spaces or commas; bare `WATCH` acts as `l`.
+pub struct WatchHandler;
+
+#[async_trait]
+impl PostRegHandler for WatchHandler {
+    async fn handle(
+        &self,
+        ctx: &mut Context<'_, RegisteredState>,
+        msg: &MessageRef<'_>,
+    ) -> HandlerResult {
+        let server_name = ctx.server_name().to_string();
+        let nick = ctx.state.nick.clone();
+
+        // From now on this client is notified with WATCH numerics
+        ctx.matrix
+            .monitor_manager
+            .watch_users
+            .insert(ctx.uid.to_string());
+
+        let entries: Vec<&str> = msg
+            .args()
+            .iter()
+            .flat_map(|arg| arg.split([',', ' ']))
+            .filter(|entry| !entry.is_empty())
+            .collect();
+
+        if entries.is_empty() {
+            return handle_list(ctx, &nick, &server_name, false).await;
+        }
+
+        for entry in entries {
+            match entry {
+                "C" | "c" => super::monitor::clear_monitor_list(ctx.matrix, ctx.uid),
+                "S" | "s" => handle_stats(ctx, &nick, &server_name).await?,
+                "L" | "l" => handle_list(ctx, &nick, &server_name, entry == "L").await?,
+                _ => {
+                    if let Some(target) = entry.strip_prefix('+') {
+                        if !handle_add(ctx, &nick, &server_name, target).await? {
+                            break;
+                        }
+                    } else if let Some(target) = entry.strip_prefix('-') {
+                        handle_remove(ctx, &nick, &server_name, target).await?;
+                    } else {
+                        debug!(entry = %entry, "Unknown WATCH entry");
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Handle `+nick`. Returns false once the list is full.
+#[allow(clippy::result_large_err)]
+async fn handle_add(
+    ctx: &mut Context<'_, RegisteredState>,
+    nick: &str,
+    server_name: &str,
+    target: &str,
+) -> Result<bool, crate::handlers::HandlerError> {
+    if target.is_empty() {
+        return Ok(true);
+    }
+    let target_lower = irc_to_lower(target);
+    let max_targets = ctx.matrix.hot_config.read().max_monitor_targets;
+
+    let added = {
+        let user_monitors = ctx
+            .matrix
+            .monitor_manager
+            .monitors
+            .entry(ctx.uid.to_string())
+            .or_insert_with(DashSet::new);
+        if !user_monitors.contains(&target_lower) && user_monitors.len() >= max_targets {
+            false
+        } else {
+            user_monitors.insert(target_lower.clone());
+            true
+        }
+    };
+
+    if !added {
+        let reply = server_reply(
+            server_name,
+            Response::ERR_TOOMANYWATCH,
+            vec![
+                nick.to_string(),
+                target.to_string(),
+                format!("Maximum size for WATCH-list is {max_targets} entries"),
+            ],
+        );
+        ctx.sender.send(reply).await?;
+        return Ok(false);
+    }
+
+    ctx.matrix
+        .monitor_manager
+        .monitoring
+        .entry(target_lower.clone())
+        .or_insert_with(DashSet::new)
+        .insert(ctx.uid.to_string());
+
+    let reply = now_status_reply(ctx.matrix, nick, server_name, target).await;
+    ctx.sender.send(reply).await?;
+    Ok(true)
+}
+
+/// Handle `-nick`.
+async fn handle_remove(
+    ctx: &mut Context<'_, RegisteredState>,
+    nick: &str,
+    server_name: &str,
+    target: &str,
+) -> HandlerResult {
+    if target.is_empty() {
+        return Ok(());
+    }
+    let target_lower = irc_to_lower(target);
+
+    let removed = ctx
+        .matrix
+        .monitor_manager
+        .monitors
+        .get(ctx.uid)
+        .is_some_and(|monitors| monitors.remove(&target_lower).is_some());
+    if let Some(watchers) = ctx.matrix.monitor_manager.monitoring.get(&target_lower) {
+        watchers.remove(ctx.uid);
+    }
+    if !removed {
+        return Ok(());
+    }
+
+    let (target_nick, user, host, ts) = match lookup_online(ctx.matrix, &target_lower).await {
+        Some(online) => online,
+        None => (target.to_string(), "*".to_string(), "*".to_string(), 0),
+    };
+    let reply = server_reply(
+        server_name,
+        Response::RPL_WATCHOFF,
+        vec![
+            nick.to_string(),
+            target_nick,
+            user,
+            host,
+            ts.to_string(),
+            "stopped watching".to_string(),
+        ],
+    );
+    ctx.sender.send(reply).await?;
+    Ok(())
+}
+
+/// Handle `S`: entry counts followed by the full list.
+async fn handle_stats(
+    ctx: &mut Context<'_, RegisteredState>,
+    nick: &str,
+    server_name: &str,
+) -> HandlerResult {
+    let targets = watch_list(ctx);
+    let watching_me = ctx
+        .matrix
+        .monitor_manager
+        .monitoring
+        .get(&irc_to_lower(nick))
+        .map(|watchers| watchers.len())
+        .unwrap_or(0);
+
+    let reply = server_reply(
+        server_name,
+        Response::RPL_WATCHSTAT,
+        vec![
+            nick.to_string(),
+            format!(
+                "You have {} and are on {} WATCH entries",
+                targets.len(),
+                watching_me
+            ),
+        ],
+    );
+    ctx.sender.send(reply).await?;
+
+    // Send in batches to avoid line length limits
+    for chunk in targets.chunks(10) {
+        let reply = server_reply(
+            server_name,
+            Response::RPL_WATCHLIST,
+            vec![nick.to_string(), chunk.join(" ")],
+        );
+        ctx.sender.send(reply).await?;
+    }
+
+    send_end_of_list(ctx, nick, server_name, "S").await
+}
+
+/// Handle `l` / `L`: status of online entries, and offline ones too for `L`.
+async fn handle_list(
+    ctx: &mut Context<'_, RegisteredState>,
+    nick: &str,
+    server_name: &str,
+    include_offline: bool,
+) -> HandlerResult {
+    for target in watch_list(ctx) {
+        let online = ctx.matrix.user_manager.get_first_uid(&target).is_some();
+        if online || include_offline {
+            let reply = now_status_reply(ctx.matrix, nick, server_name, &target).await;
+            ctx.sender.send(reply).await?;
+        }
+    }
+
+    send_end_of_list(
+        ctx,
+        nick,
+        server_name,
+        if include_offline { "L" } else { "l" },
+    )
+    .await
+}
+
+async fn send_end_of_list(
+    ctx: &mut Context<'_, RegisteredState>,
+    nick: &str,
+    server_name: &str,
+    flag: &str,
+) -> HandlerResult {
+    let reply = server_reply(
+        server_name,
+        Response::RPL_ENDOFWATCHLIST,
+        vec![nick.to_string(), format!("End of WATCH {}", flag)],
+    );
+    ctx.sender.send(reply).await?;
+    Ok(())
+}
+
+/// The caller's watch list, sorted for stable output.
+fn watch_list(ctx: &Context<'_, RegisteredState>) -> Vec<String> {
+    let mut targets: Vec<String> = ctx
+        .matrix
+        .monitor_manager
+        .monitors
+        .get(ctx.uid)
+        .map(|monitors| monitors.iter().map(|r| r.clone()).collect())
+        .unwrap_or_default();
+    targets.sort();
+    targets
+}
+
+/// Build RPL_NOWON (604) or RPL_NOWOFF (605) for a watched nick.
+///
+/// `display` is the nick as the client wrote it, used when the target is offline.
+async fn now_status_reply(
+    matrix: &Arc<Matrix>,
+    nick: &str,
+    server_name: &str,
+    display: &str,
+) -> Message {
+    match lookup_online(matrix, &irc_to_lower(display)).await {
+        Some((target_nick, user, host, ts)) => server_reply(
+            server_name,
+            Response::RPL_NOWON,
+            vec![
+                nick.to_string(),
+                target_nick,
+                user,
+                host,
+                ts.to_string(),
+                "is online".to_string(),
+            ],
+        ),
+        None => server_reply(
+            server_name,
+            Response::RPL_NOWOFF,
+            vec![
+                nick.to_string(),
+                display.to_string(),
+                "*".to_string(),
+                "*".to_string(),
+                "0".to_string(),
+                "is offline".to_string(),
+            ],
+        ),
+    }
+}
+
+/// Look up an online nick: `(nick, user, visible host, signon time)`.
+async fn lookup_online(
+    matrix: &Matrix,
+    target_lower: &str,
+) -> Option<(String, String, String, i64)> {
+    let target_uid = matrix.user_manager.get_first_uid(target_lower)?;
+    let user_arc = matrix.user_manager.users.get_cloned(&target_uid)?;
+    let user = user_arc.read().await;
+    Some((
+        user.nick.clone(),
+        user.user.clone(),
+        user.visible_host.clone(),
+        user.created_at,
+    ))
+}
+
+/// Build the RPL_LOGON/RPL_LOGOFF notification for a WATCH client.
+///
+/// Returns None if the watcher is no longer connected.
+pub(super) async fn watch_presence_reply(
+    matrix: &Arc<Matrix>,
+    watcher_uid: &str,
+    response: Response,
+    nick: &str,
+    user: &str,
+    host: &str,
+) -> Option<Message> {
+    let watcher_nick = {
+        let watcher = matrix.user_manager.users.get_cloned(watcher_uid)?;
+        watcher.read().await.nick.clone()
+    };
+    let text = if response == Response::RPL_LOGON {
+        "logged online"
+    } else {
+        "logged offline"
+    };
+    Some(server_reply(
+        &matrix.server_info.name,
+        response,
+        vec![
+            watcher_nick,
+            nick.to_string(),
+            user.to_string(),
+            host.to_string(),
+            chrono::Utc::now().timestamp().to_string(),
+            text.to_string(),
+        ],
+    ))
+}
@@ -8,6 +8,7 @@
 use crate::handlers::{Context, HandlerResult};
 
 pub mod fanout;
+pub mod targets;
 use slirc_proto::{Command, Message, MessageRef, Prefix, Response, Tag};
 
 // Re-export hostmask matching from proto for use by handlers
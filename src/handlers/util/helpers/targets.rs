@@ -0,0 +1,208 @@
+//! Mask-based target resolution for operator tools.
+//!
+//! WHO (oper view), mask KILL and TRACEMASK all resolve wildcard targets
+//! through [`TargetMask`] so that an IP mask like `192.168.*` or a CIDR like
+//! `10.0.0.0/8` means the same thing in every command. Callers pass a cap to
+//! [`resolve_mask_targets`] to bound how many users a single mask can reach.
+
+use crate::state::{Matrix, User};
+use ipnet::IpNet;
+use slirc_proto::wildcard_match;
+use std::net::IpAddr;
+
+/// A parsed operator target mask.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetMask {
+    /// CIDR block matched against the real IP (`10.0.0.0/8`, `2001:db8::/32`).
+    Cidr(IpNet),
+    /// `nick!user@host` wildcard mask. The host part is matched against the
+    /// real host, the real IP and the visible (cloaked) host.
+    Hostmask {
+        nick: String,
+        user: String,
+        host: String,
+    },
+    /// Bare wildcard (`192.168.*`, `*.example.com`, `Guest*`), matched against
+    /// the nick, real host, real IP and visible host.
+    Bare(String),
+}
+
+impl TargetMask {
+    /// Returns true if `target` should be treated as a mask rather than a nick.
+    pub fn is_mask(target: &str) -> bool {
+        target.contains(['*', '?', '@', '!', '/'])
+    }
+
+    /// Parse a mask string. Never fails: unparseable CIDRs fall back to wildcards.
+    pub fn parse(mask: &str) -> Self {
+        if mask.contains('/')
+            && let Ok(net) = mask.parse::<IpNet>()
+        {
+            return TargetMask::Cidr(net.trunc());
+        }
+
+        let (nick, rest) = match mask.split_once('!') {
+            Some((n, r)) => (n, Some(r)),
+            None => (mask, None),
+        };
+        match (rest, mask.split_once('@')) {
+            (Some(rest), _) => {
+                let (user, host) = rest.split_once('@').unwrap_or((rest, "*"));
+                TargetMask::Hostmask {
+                    nick: non_empty_or_star(nick),
+                    user: non_empty_or_star(user),
+                    host: non_empty_or_star(host),
+                }
+            }
+            (None, Some((user, host))) => TargetMask::Hostmask {
+                nick: "*".to_string(),
+                user: non_empty_or_star(user),
+                host: non_empty_or_star(host),
+            },
+            (None, None) => TargetMask::Bare(mask.to_string()),
+        }
+    }
+
+    /// Check whether a user matches this mask (operator view: real host/IP included).
+    pub fn matches(&self, user: &User) -> bool {
+        match self {
+            TargetMask::Cidr(net) => user
+                .ip
+                .parse::<IpAddr>()
+                .map(|ip| net.contains(&ip))
+                .unwrap_or(false),
+            TargetMask::Hostmask {
+                nick,
+                user: ident,
+                host,
+            } => {
+                wildcard_match(nick, &user.nick)
+                    && wildcard_match(ident, &user.user)
+                    && host_matches(host, user)
+            }
+            TargetMask::Bare(pattern) => {
+                wildcard_match(pattern, &user.nick) || host_matches(pattern, user)
+            }
+        }
+    }
+}
+
+fn non_empty_or_star(s: &str) -> String {
+    if s.is_empty() {
+        "*".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+fn host_matches(pattern: &str, user: &User) -> bool {
+    wildcard_match(pattern, &user.host)
+        || wildcard_match(pattern, &user.ip)
+        || wildcard_match(pattern, &user.visible_host)
+}
+
+/// Result of resolving a mask against the user table.
+#[derive(Debug, Default)]
+pub struct MaskMatches {
+    /// Matching UIDs, at most `cap` entries.
+    pub uids: Vec<String>,
+    /// Total number of matching users (may exceed `uids.len()`).
+    pub total: usize,
+}
+
+impl MaskMatches {
+    /// Returns true if more users matched than the cap allowed.
+    pub fn truncated(&self) -> bool {
+        self.total > self.uids.len()
+    }
+}
+
+/// Resolve a mask to the set of matching users.
+///
+/// Service pseudo-clients (+S) and `exclude_uid` (usually the issuing oper)
+/// are never returned. At most `cap` UIDs are collected, but all matches are
+/// counted so callers can report or refuse over-broad masks.
+pub async fn resolve_mask_targets(
+    matrix: &Matrix,
+    mask: &TargetMask,
+    cap: usize,
+    exclude_uid: Option<&str>,
+) -> MaskMatches {
+    // Collect Arcs first to release the DashMap shard locks before awaiting.
+    let users: Vec<_> = matrix
+        .user_manager
+        .users
+        .iter()
+        .map(|e| (e.key().clone(), e.value().clone()))
+        .collect();
+
+    let mut result = MaskMatches::default();
+    for (uid, user_arc) in users {
+        if exclude_uid == Some(uid.as_str()) {
+            continue;
+        }
+        let user = user_arc.read().await;
+        if user.modes.service || !mask.matches(&user) {
+            continue;
+        }
+        result.total += 1;
+        if result.uids.len() < cap {
+            result.uids.push(uid);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_mask() {
+        assert!(TargetMask::is_mask("192.168.*"));
+        assert!(TargetMask::is_mask("*@host"));
+        assert!(TargetMask::is_mask("10.0.0.0/8"));
+        assert!(TargetMask::is_mask("nick!*@*"));
+        assert!(!TargetMask::is_mask("alice"));
+    }
+
+    #[test]
+    fn test_parse_cidr() {
+        assert_eq!(
+            TargetMask::parse("10.1.2.3/8"),
+            TargetMask::Cidr("10.0.0.0/8".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_cidr_falls_back() {
+        assert_eq!(
+            TargetMask::parse("10.0.0.0/99"),
+            TargetMask::Bare("10.0.0.0/99".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_hostmask_forms() {
+        assert_eq!(
+            TargetMask::parse("*@192.168.*"),
+            TargetMask::Hostmask {
+                nick: "*".into(),
+                user: "*".into(),
+                host: "192.168.*".into()
+            }
+        );
+        assert_eq!(
+            TargetMask::parse("bad!~u@"),
+            TargetMask::Hostmask {
+                nick: "bad".into(),
+                user: "~u".into(),
+                host: "*".into()
+            }
+        );
+        assert_eq!(
+            TargetMask::parse("192.168.*"),
+            TargetMask::Bare("192.168.*".into())
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! In-memory history storage.
+//!
+//! Implements [`HistoryProvider`] with a bounded ring buffer per target.
+//! Nothing touches disk, so history is lost on restart; deployments choose
+//! it when they want CHATHISTORY without keeping message logs.
+
+use super::{HistoryError, HistoryProvider, HistoryQuery, StoredMessage, types::HistoryItem};
+use crate::config::HistoryRetentionConfig;
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use slirc_proto::irc_to_lower;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+pub struct MemoryProvider {
+    /// Items per lowercased target, ordered by (nanotime, id).
+    targets: RwLock<HashMap<String, VecDeque<HistoryItem>>>,
+    /// Items kept per target; the oldest is dropped first.
+    capacity: usize,
+}
+
+impl MemoryProvider {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            targets: RwLock::new(HashMap::new()),
+            capacity: capacity.max(1),
+        }
+    }
+
+    fn item_key(item: &HistoryItem) -> (i64, &str) {
+        match item {
+            HistoryItem::Message(m) => (m.nanotime, m.msgid.as_str()),
+            HistoryItem::Event(e) => (e.nanotime, e.id.as_str()),
+        }
+    }
+}
+
+#[async_trait]
+impl HistoryProvider for MemoryProvider {
+    async fn store(&self, target: &str, msg: StoredMessage) -> Result<(), HistoryError> {
+        self.store_item(target, HistoryItem::Message(msg)).await
+    }
+
+    async fn store_item(&self, target: &str, item: HistoryItem) -> Result<(), HistoryError> {
+        let mut targets = self.targets.write();
+        let items = targets.entry(irc_to_lower(target)).or_default();
+        // Items almost always arrive in order, so this is usually an append
+        let key = Self::item_key(&item);
+        let pos = items.partition_point(|i| Self::item_key(i) <= key);
+        items.insert(pos, item);
+        if items.len() > self.capacity {
+            items.pop_front();
+        }
+        Ok(())
+    }
+
+    async fn query(&self, filter: HistoryQuery) -> Result<Vec<HistoryItem>, HistoryError> {
+        let targets = self.targets.read();
+        let Some(items) = targets.get(&irc_to_lower(&filter.target)) else {
+            return Ok(vec![]);
+        };
+
+        // Same bounds as the redb key range: start inclusive, end exclusive,
+        // each narrowed to a msgid when one is given.
+        let start = (
+            filter.start.unwrap_or(0),
+            filter.start_id.as_deref().unwrap_or_default(),
+        );
+        let end = (
+            filter.end.unwrap_or(i64::MAX),
+            filter.end_id.as_deref().unwrap_or_default(),
+        );
+        let in_range = items.iter().filter(|i| {
+            let key = Self::item_key(i);
+            key >= start && key < end
+        });
+
+        let messages = if filter.reverse {
+            in_range.rev().take(filter.limit).cloned().collect()
+        } else {
+            in_range.take(filter.limit).cloned().collect()
+        };
+        Ok(messages)
+    }
+
+    async fn prune(&self, retention: &HistoryRetentionConfig) -> Result<usize, HistoryError> {
+        let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
+        let mut count = 0;
+
+        let mut targets = self.targets.write();
+        for (target, items) in targets.iter_mut() {
+            let before = items.len();
+            items.retain(|item| {
+                let is_event = matches!(item, HistoryItem::Event(_));
+                match retention.retention_for(target, is_event) {
+                    Some(keep) => item.nanotime() >= now - keep.as_nanos() as i64,
+                    None => true,
+                }
+            });
+            count += before - items.len();
+        }
+        targets.retain(|_, items| !items.is_empty());
+        Ok(count)
+    }
+
+    async fn delete(&self, target: &str, msgid: &str) -> Result<bool, HistoryError> {
+        let mut targets = self.targets.write();
+        let Some(items) = targets.get_mut(&irc_to_lower(target)) else {
+            return Ok(false);
+        };
+        let Some(pos) = items.iter().position(|i| Self::item_key(i).1 == msgid) else {
+            return Ok(false);
+        };
+        items.remove(pos);
+        Ok(true)
+    }
+
+    async fn lookup_timestamp(
+        &self,
+        target: &str,
+        msgid: &str,
+    ) -> Result<Option<i64>, HistoryError> {
+        let targets = self.targets.read();
+        Ok(targets.get(&irc_to_lower(target)).and_then(|items| {
+            items
+                .iter()
+                .map(Self::item_key)
+                .find(|(_, id)| *id == msgid)
+                .map(|(ts, _)| ts)
+        }))
+    }
+
+    async fn query_targets(
+        &self,
+        start: i64,
+        end: i64,
+        limit: usize,
+        dm_self: String,
+        channels: Vec<String>,
+    ) -> Result<Vec<(String, i64)>, HistoryError> {
+        let dm_self = irc_to_lower(&dm_self);
+        let channels_set: HashSet<String> = channels.iter().map(|c| irc_to_lower(c)).collect();
+
+        let targets = self.targets.read();
+        let mut results: Vec<(String, i64)> = targets
+            .iter()
+            .filter_map(|(target, items)| {
+                let name = super::display_target(target, &dm_self, &channels_set)?;
+                let ts = items.back()?.nanotime();
+                (ts >= start && ts < end).then_some((name, ts))
+            })
+            .collect();
+
+        // Sort by timestamp ascending (earliest first) per IRCv3 spec
+        results.sort_by_key(|(_, ts)| *ts);
+        results.truncate(limit);
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::types::MessageEnvelope;
+
+    fn message(target: &str, msgid: &str, nanotime: i64) -> StoredMessage {
+        StoredMessage {
+            msgid: msgid.to_string(),
+            target: target.to_string(),
+            sender: "alice".to_string(),
+            envelope: MessageEnvelope {
+                command: "PRIVMSG".to_string(),
+                prefix: "alice!a@host".to_string(),
+                target: target.to_string(),
+                text: "hello".to_string(),
+                tags: None,
+            },
+            nanotime,
+            account: None,
+            status_prefix: None,
+        }
+    }
+
+    fn query(target: &str, start: Option<i64>, reverse: bool, limit: usize) -> HistoryQuery {
+        HistoryQuery {
+            target: target.to_string(),
+            start,
+            end: None,
+            start_id: None,
+            end_id: None,
+            limit,
+            reverse,
+        }
+    }
+
+    fn ids(items: Vec<HistoryItem>) -> Vec<String> {
+        items
+            .into_iter()
+            .map(|item| match item {
+                HistoryItem::Message(m) => m.msgid,
+                HistoryItem::Event(e) => e.id,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn ring_buffer_drops_oldest_per_target() {
+        let provider = MemoryProvider::new(3);
+        for i in 0..5 {
+            provider
+                .store("#Chan", message("#Chan", &format!("m{i}"), i))
+                .await
+                .unwrap();
+        }
+        provider
+            .store("#other", message("#other", "o0", 0))
+            .await
+            .unwrap();
+
+        let items = provider
+            .query(query("#chan", None, false, usize::MAX))
+            .await
+            .unwrap();
+        assert_eq!(ids(items), ["m2", "m3", "m4"]);
+        let latest = provider.query(query("#CHAN", None, true, 2)).await.unwrap();
+        assert_eq!(ids(latest), ["m4", "m3"]);
+        let after = provider
+            .query(query("#chan", Some(3), false, usize::MAX))
+            .await
+            .unwrap();
+        assert_eq!(ids(after), ["m3", "m4"]);
+
+        assert_eq!(
+            provider.lookup_timestamp("#chan", "m3").await.unwrap(),
+            Some(3)
+        );
+        assert_eq!(
+            provider.lookup_timestamp("#chan", "m0").await.unwrap(),
+            None
+        );
+        assert_eq!(
+            provider.lookup_timestamp("#other", "m3").await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn targets_and_prune_follow_redb_semantics() {
+        let provider = MemoryProvider::new(10);
+        let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+        let old = now - 10 * 86_400_000_000_000;
+        for (target, id, ts) in [
+            ("dm:a:alice:u:bob", "dm-1", now - 2),
+            ("#chan", "chan-old", old),
+            ("#chan", "chan-1", now),
+            ("#secret", "secret-1", now),
+        ] {
+            provider
+                .store(target, message(target, id, ts))
+                .await
+                .unwrap();
+        }
+
+        let targets = provider
+            .query_targets(0, i64::MAX, 10, "a:alice".to_string(), vec!["#Chan".into()])
+            .await
+            .unwrap();
+        let names: Vec<_> = targets.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["bob", "#chan"]);
+
+        let retention = HistoryRetentionConfig {
+            channel_days: 7,
+            ..Default::default()
+        };
+        assert_eq!(provider.prune(&retention).await.unwrap(), 1);
+        let items = provider
+            .query(query("#chan", None, false, usize::MAX))
+            .await
+            .unwrap();
+        assert_eq!(ids(items), ["chan-1"]);
+    }
+}
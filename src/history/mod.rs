@@ -1,9 +1,11 @@
 //! History provider abstraction.
 
+use crate::config::HistoryRetentionConfig;
 use async_trait::async_trait;
-use std::time::Duration;
+use std::collections::HashSet;
 use thiserror::Error;
 
+pub mod memory;
 pub mod noop;
 pub mod redb;
 pub mod types;
@@ -42,10 +44,17 @@ pub trait HistoryProvider: Send + Sync {
     /// Retrieve messages (Range Query).
     async fn query(&self, filter: HistoryQuery) -> Result<Vec<types::HistoryItem>, HistoryError>;
 
-    /// Prune old messages (Maintenance).
-    async fn prune(&self, retention: Duration) -> Result<usize, HistoryError>;
+    /// Prune messages and events older than their retention (Maintenance).
+    async fn prune(&self, retention: &HistoryRetentionConfig) -> Result<usize, HistoryError>;
 
-    /// Lookup timestamp for a message ID.
+    /// Delete the message `msgid` stored under `target` (REDACT).
+    ///
+    /// Returns `false` if `target` holds no such message.
+    async fn delete(&self, target: &str, msgid: &str) -> Result<bool, HistoryError>;
+
+    /// Lookup timestamp for a message ID stored under `target`.
+    ///
+    /// Returns `None` for msgids belonging to another target.
     async fn lookup_timestamp(
         &self,
         target: &str,
@@ -53,12 +62,46 @@ pub trait HistoryProvider: Send + Sync {
     ) -> Result<Option<i64>, HistoryError>;
 
     /// Query targets with activity.
+    ///
+    /// `dm_self` is the caller's half of a DM key (`a:<account>` or
+    /// `u:<nick>`); DMs are reported under the other participant's name.
     async fn query_targets(
         &self,
         start: i64,
         end: i64,
         limit: usize,
-        nick: String,
+        dm_self: String,
         channels: Vec<String>,
     ) -> Result<Vec<(String, i64)>, HistoryError>;
 }
+
+/// Name a history key is listed under in CHATHISTORY TARGETS, if the caller
+/// may see it.
+///
+/// `target_key` is a lowercased history key, `dm_self` the caller's lowercased
+/// DM key half and `channels` the caller's lowercased channels.
+pub(crate) fn display_target(
+    target_key: &str,
+    dm_self: &str,
+    channels: &HashSet<String>,
+) -> Option<String> {
+    if target_key.starts_with('#') || target_key.starts_with('&') {
+        return channels
+            .contains(target_key)
+            .then(|| target_key.to_string());
+    }
+    // Parse DM: dm:a:u1:u:u2, matching on kind and name
+    let parts: Vec<&str> = target_key.strip_prefix("dm:")?.split(':').collect();
+    if parts.len() < 4 {
+        return None;
+    }
+    let (k1, u1) = (parts[0], parts[1]);
+    let (k2, u2) = (parts[2], parts[3]);
+    if dm_self == format!("{k1}:{u1}") {
+        Some(u2.to_string())
+    } else if dm_self == format!("{k2}:{u2}") {
+        Some(u1.to_string())
+    } else {
+        None
+    }
+}
@@ -4,8 +4,8 @@
 //! All operations succeed but store nothing.
 
 use super::{HistoryError, HistoryProvider, HistoryQuery, StoredMessage, types::HistoryItem};
+use crate::config::HistoryRetentionConfig;
 use async_trait::async_trait;
-use std::time::Duration;
 
 pub struct NoOpProvider;
 
@@ -23,10 +23,14 @@ impl HistoryProvider for NoOpProvider {
         Ok(vec![])
     }
 
-    async fn prune(&self, _retention: Duration) -> Result<usize, HistoryError> {
+    async fn prune(&self, _retention: &HistoryRetentionConfig) -> Result<usize, HistoryError> {
         Ok(0)
     }
 
+    async fn delete(&self, _target: &str, _msgid: &str) -> Result<bool, HistoryError> {
+        Ok(false)
+    }
+
     async fn lookup_timestamp(
         &self,
         _target: &str,
@@ -40,7 +44,7 @@ impl HistoryProvider for NoOpProvider {
         _start: i64,
         _end: i64,
         _limit: usize,
-        _nick: String,
+        _dm_self: String,
         _channels: Vec<String>,
     ) -> Result<Vec<(String, i64)>, HistoryError> {
         Ok(vec![])
@@ -4,11 +4,11 @@
 //! durable message history with efficient range queries by target and time.
 
 use super::{HistoryError, HistoryProvider, HistoryQuery, StoredMessage, types::HistoryItem};
+use crate::config::HistoryRetentionConfig;
 use async_trait::async_trait;
 use redb::{Database, ReadableDatabase, ReadableTable, TableDefinition};
 use slirc_proto::irc_to_lower;
 use std::sync::Arc;
-use std::time::Duration;
 
 const HISTORY_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("history");
 const MSGID_INDEX: TableDefinition<&str, &[u8]> = TableDefinition::new("msgid_index");
@@ -186,9 +186,8 @@ impl HistoryProvider for RedbProvider {
         Ok(messages)
     }
 
-    async fn prune(&self, retention: Duration) -> Result<usize, HistoryError> {
-        let cutoff =
-            chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0) - retention.as_nanos() as i64;
+    async fn prune(&self, retention: &HistoryRetentionConfig) -> Result<usize, HistoryError> {
+        let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0);
 
         let write_txn = self
             .db
@@ -205,12 +204,19 @@ impl HistoryProvider for RedbProvider {
                 .iter()
                 .map_err(|e| HistoryError::Database(e.to_string()))?
             {
-                let (k, _v) = item.map_err(|e| HistoryError::Database(e.to_string()))?;
+                let (k, v) = item.map_err(|e| HistoryError::Database(e.to_string()))?;
                 let key_str = k.value();
                 let parts: Vec<&str> = key_str.split('\0').collect();
-                if parts.len() >= 2
-                    && let Ok(ts) = parts[1].parse::<i64>()
-                    && ts < cutoff
+                let Some(ts) = parts.get(1).and_then(|ts| ts.parse::<i64>().ok()) else {
+                    continue;
+                };
+                // Legacy rows hold a bare StoredMessage and count as messages.
+                let is_event = matches!(
+                    serde_json::from_slice::<HistoryItem>(v.value()),
+                    Ok(HistoryItem::Event(_))
+                );
+                if let Some(keep) = retention.retention_for(parts[0], is_event)
+                    && ts < now - keep.as_nanos() as i64
                 {
                     to_delete.push(key_str.to_string());
                 }
@@ -229,9 +235,51 @@ impl HistoryProvider for RedbProvider {
         Ok(count)
     }
 
+    async fn delete(&self, target: &str, msgid: &str) -> Result<bool, HistoryError> {
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| HistoryError::Database(e.to_string()))?;
+        let deleted = {
+            let mut index = write_txn
+                .open_table(MSGID_INDEX)
+                .map_err(|e| HistoryError::Database(e.to_string()))?;
+            // Value: target\0timestamp
+            let stored = index
+                .get(msgid)
+                .map_err(|e| HistoryError::Database(e.to_string()))?
+                .and_then(|v| {
+                    let val_str = std::str::from_utf8(v.value()).ok()?;
+                    let (stored_target, ts) = val_str.split_once('\0')?;
+                    Some((stored_target.to_string(), ts.parse::<i64>().ok()?))
+                });
+            match stored {
+                Some((stored_target, ts))
+                    if irc_to_lower(&stored_target) == irc_to_lower(target) =>
+                {
+                    let mut table = write_txn
+                        .open_table(HISTORY_TABLE)
+                        .map_err(|e| HistoryError::Database(e.to_string()))?;
+                    table
+                        .remove(Self::make_key(&stored_target, ts, msgid).as_str())
+                        .map_err(|e| HistoryError::Database(e.to_string()))?;
+                    index
+                        .remove(msgid)
+                        .map_err(|e| HistoryError::Database(e.to_string()))?;
+                    true
+                }
+                _ => false,
+            }
+        };
+        write_txn
+            .commit()
+            .map_err(|e| HistoryError::Database(e.to_string()))?;
+        Ok(deleted)
+    }
+
     async fn lookup_timestamp(
         &self,
-        _target: &str,
+        target: &str,
         msgid: &str,
     ) -> Result<Option<i64>, HistoryError> {
         let read_txn = self
@@ -249,7 +297,9 @@ impl HistoryProvider for RedbProvider {
             let val_str = std::str::from_utf8(v.value())
                 .map_err(|e| HistoryError::Serialization(e.to_string()))?;
             let parts: Vec<&str> = val_str.split('\0').collect();
+            // A msgid only anchors queries on the target it was sent to
             if parts.len() >= 2
+                && irc_to_lower(parts[0]) == irc_to_lower(target)
                 && let Ok(ts) = parts[1].parse::<i64>()
             {
                 return Ok(Some(ts));
@@ -263,7 +313,7 @@ impl HistoryProvider for RedbProvider {
         start: i64,
         end: i64,
         limit: usize,
-        nick: String,
+        dm_self: String,
         channels: Vec<String>,
     ) -> Result<Vec<(String, i64)>, HistoryError> {
         let read_txn = self
@@ -275,7 +325,7 @@ impl HistoryProvider for RedbProvider {
             .map_err(|e| HistoryError::Database(e.to_string()))?;
 
         let mut results = Vec::with_capacity(limit);
-        let nick_lower = irc_to_lower(&nick);
+        let dm_self = irc_to_lower(&dm_self);
         let channels_set: std::collections::HashSet<String> =
             channels.iter().map(|c| irc_to_lower(c)).collect();
 
@@ -305,33 +355,7 @@ impl HistoryProvider for RedbProvider {
                 None => break,
             };
 
-            // Check relevance and extract display name
-            let display_target = if target_key.starts_with('#') || target_key.starts_with('&') {
-                if channels_set.contains(&target_key) {
-                    Some(target_key.clone())
-                } else {
-                    None
-                }
-            } else if target_key.starts_with("dm:") {
-                // Parse DM: dm:a:u1:a:u2
-                let parts: Vec<&str> = target_key.split(':').collect();
-
-                if parts.len() >= 5 {
-                    let u1 = parts[2];
-                    let u2 = parts[4];
-                    if u1 == nick_lower {
-                        Some(u2.to_string())
-                    } else if u2 == nick_lower {
-                        Some(u1.to_string())
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            } else {
-                None
-            };
+            let display_target = super::display_target(&target_key, &dm_self, &channels_set);
 
             if let Some(display_name) = display_target {
                 // Get latest message for this target to check timestamp
@@ -363,9 +387,161 @@ impl HistoryProvider for RedbProvider {
         }
 
         // Sort by timestamp ascending (earliest first) per IRCv3 spec
-        results.sort_by(|a, b| a.1.cmp(&b.1));
+        results.sort_by_key(|a| a.1);
         results.truncate(limit);
 
         Ok(results)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::types::{EventKind, MessageEnvelope, StoredEvent};
+
+    const DAY_NANOS: i64 = 86_400_000_000_000;
+
+    fn message(target: &str, msgid: &str, nanotime: i64) -> StoredMessage {
+        StoredMessage {
+            msgid: msgid.to_string(),
+            target: target.to_string(),
+            sender: "alice".to_string(),
+            envelope: MessageEnvelope {
+                command: "PRIVMSG".to_string(),
+                prefix: "alice!a@host".to_string(),
+                target: target.to_string(),
+                text: "hello".to_string(),
+                tags: None,
+            },
+            nanotime,
+            account: None,
+            status_prefix: None,
+        }
+    }
+
+    async fn ids(provider: &RedbProvider, target: &str) -> Vec<String> {
+        let items = provider
+            .query(HistoryQuery {
+                target: target.to_string(),
+                start: None,
+                end: None,
+                start_id: None,
+                end_id: None,
+                limit: usize::MAX,
+                reverse: false,
+            })
+            .await
+            .unwrap();
+        items
+            .into_iter()
+            .map(|item| match item {
+                HistoryItem::Message(m) => m.msgid,
+                HistoryItem::Event(e) => e.id,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn prune_applies_retention_by_target_type() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = RedbProvider::new(dir.path().join("h.db").to_str().unwrap()).unwrap();
+        let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+        let ten_days_ago = now - 10 * DAY_NANOS;
+
+        for (target, id) in [
+            ("#chan", "chan-old"),
+            ("#Archive", "archive-old"),
+            ("dm:u:alice:u:bob", "dm-old"),
+        ] {
+            provider
+                .store(target, message(target, id, ten_days_ago))
+                .await
+                .unwrap();
+        }
+        provider
+            .store("#chan", message("#chan", "chan-new", now))
+            .await
+            .unwrap();
+        let join = StoredEvent {
+            id: "join-old".to_string(),
+            nanotime: ten_days_ago,
+            source: "alice!a@host".to_string(),
+            kind: EventKind::Join,
+        };
+        provider
+            .store_item("#archive", HistoryItem::Event(join))
+            .await
+            .unwrap();
+
+        let retention = HistoryRetentionConfig {
+            channel_days: 7,
+            dm_days: 30,
+            event_days: 1,
+            channels: [("#archive".to_string(), 0)].into_iter().collect(),
+        };
+        assert_eq!(provider.prune(&retention).await.unwrap(), 2);
+
+        assert_eq!(ids(&provider, "#chan").await, ["chan-new"]);
+        assert_eq!(ids(&provider, "#archive").await, ["archive-old"]);
+        assert_eq!(ids(&provider, "dm:u:alice:u:bob").await, ["dm-old"]);
+    }
+
+    #[tokio::test]
+    async fn dm_targets_and_msgids_are_scoped_to_participants() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = RedbProvider::new(dir.path().join("h.db").to_str().unwrap()).unwrap();
+        let now = chrono::Utc::now().timestamp_nanos_opt().unwrap();
+
+        for (target, id, ts) in [
+            ("dm:a:alice:u:bob", "dm-1", now - 2),
+            ("dm:u:alice:u:carol", "dm-2", now - 1),
+            ("#chan", "chan-1", now),
+        ] {
+            provider
+                .store(target, message(target, id, ts))
+                .await
+                .unwrap();
+        }
+
+        // Logged in as account "alice", the unregistered "alice" DM is not ours
+        let targets = provider
+            .query_targets(0, i64::MAX, 10, "a:alice".to_string(), vec!["#Chan".into()])
+            .await
+            .unwrap();
+        let names: Vec<_> = targets.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["bob", "#chan"]);
+
+        assert_eq!(
+            provider
+                .lookup_timestamp("dm:a:alice:u:bob", "dm-1")
+                .await
+                .unwrap(),
+            Some(now - 2)
+        );
+        assert_eq!(
+            provider.lookup_timestamp("#chan", "dm-1").await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_removes_message_and_msgid_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = RedbProvider::new(dir.path().join("h.db").to_str().unwrap()).unwrap();
+        for id in ["keep", "gone"] {
+            provider
+                .store("#Chan", message("#Chan", id, 1))
+                .await
+                .unwrap();
+        }
+
+        assert!(!provider.delete("#other", "gone").await.unwrap());
+        assert!(provider.delete("#chan", "gone").await.unwrap());
+        assert!(!provider.delete("#chan", "gone").await.unwrap());
+        assert_eq!(ids(&provider, "#chan").await, ["keep"]);
+        assert_eq!(
+            provider.lookup_timestamp("#chan", "gone").await.unwrap(),
+            None
+        );
+    }
+}
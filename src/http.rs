@@ -1,21 +1,59 @@
-//! HTTP server for Prometheus metrics endpoint.
+//! HTTP server for Prometheus metrics and the top-N stats endpoint.
 //!
-//! Runs on a separate tokio task and serves `/metrics` for Prometheus scraping.
+//! Runs on a separate tokio task and serves `/metrics` for Prometheus scraping
+//! and `/api/stats/top` for a quick look at the busiest parts of the server.
 
-use axum::{Router, routing::get};
+use crate::activity::TopStats;
+use crate::state::Matrix;
+use crate::state::dashmap_ext::DashMapExt;
+use axum::extract::{Query, State};
+use axum::{Json, Router, routing::get};
+use serde::Deserialize;
 use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// Entries per list when the request does not say.
+const DEFAULT_TOP_LIMIT: usize = 10;
+/// Most entries per list a request may ask for.
+const MAX_TOP_LIMIT: usize = 100;
 
 /// Handler for GET /metrics - returns Prometheus metrics in text format.
 async fn metrics_handler() -> String {
     crate::metrics::gather_metrics()
 }
 
-/// Run the HTTP server for Prometheus metrics.
+#[derive(Debug, Deserialize)]
+struct TopQuery {
+    limit: Option<usize>,
+}
+
+/// Handler for GET /api/stats/top?limit=N - returns the busiest channels,
+/// commands, connections and channel mailboxes as JSON.
+async fn top_handler(
+    State(matrix): State<Arc<Matrix>>,
+    Query(query): Query<TopQuery>,
+) -> Json<TopStats> {
+    let limit = query.limit.unwrap_or(DEFAULT_TOP_LIMIT).min(MAX_TOP_LIMIT);
+    let mut top = crate::activity::global().top(limit);
+
+    // Only the listed connections are looked up
+    for conn in &mut top.connections {
+        if let Some(user) = matrix.user_manager.users.get_cloned(&conn.uid) {
+            conn.nick = Some(user.read().await.nick.clone());
+        }
+    }
+    Json(top)
+}
+
+/// Run the HTTP server for Prometheus metrics and stats.
 ///
-/// Binds to `0.0.0.0:port` and serves the `/metrics` endpoint.
+/// Binds to `0.0.0.0:port` and serves `/metrics` and `/api/stats/top`.
 /// This is a long-running task that should be spawned in the background.
-pub async fn run_http_server(port: u16) {
-    let app = Router::new().route("/metrics", get(metrics_handler));
+pub async fn run_http_server(port: u16, matrix: Arc<Matrix>) {
+    let app = Router::new()
+        .route("/metrics", get(metrics_handler))
+        .route("/api/stats/top", get(top_handler))
+        .with_state(matrix);
 
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
     tracing::info!("Prometheus HTTP server listening on {}", addr);
@@ -2,6 +2,7 @@
 //!
 //! A high-performance, multi-threaded IRC server built on zero-copy parsing.
 
+mod activity;
 mod caps;
 mod config;
 mod db;
@@ -15,23 +16,28 @@ mod security;
 mod services;
 mod state;
 mod sync;
+mod tap;
 mod telemetry;
+#[cfg(test)]
+mod test_support;
 
 use crate::config::Config;
 use crate::db::Database;
 use crate::handlers::Registry;
 use crate::network::Gateway;
-use crate::state::Matrix;
+use crate::state::{Matrix, ShutdownRequest};
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::{error, info};
 
+/// How long shutdown waits for local clients to disconnect.
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Resolve the configuration path from CLI arguments.
 /// Supports `-c <path>`, `--config <path>`, or a bare path.
 /// Falls back to `config.toml` when no argument is provided.
-fn resolve_config_path() -> String {
-    let mut args = std::env::args().skip(1);
-
+fn resolve_config_path(mut args: impl Iterator<Item = String>) -> String {
     let raw_path = match args.next() {
         Some(flag) if flag == "-c" || flag == "--config" => args.next().unwrap_or_else(|| {
             eprintln!("Missing path after {}", flag);
@@ -48,16 +54,131 @@ fn resolve_config_path() -> String {
     }
 }
 
+/// SQLite database path from config, defaulting to `slircd.db`.
+fn database_path(config: &Config) -> &str {
+    config
+        .database
+        .as_ref()
+        .map(|d| d.path.as_str())
+        .unwrap_or("slircd.db")
+}
+
+/// The SQLCipher key for the database, if encryption is configured.
+fn database_key(config: &Config) -> anyhow::Result<Option<zeroize::Zeroizing<String>>> {
+    match config.database.as_ref().and_then(|d| d.encryption.as_ref()) {
+        Some(encryption) => Ok(Some(db::encryption::resolve_key(encryption)?)),
+        None => Ok(None),
+    }
+}
+
+/// `slircd db status [config]`: list the schema migrations applied to the
+/// configured database, without applying any.
+async fn db_command(command: Option<&str>, config: &Config) -> anyhow::Result<()> {
+    if command != Some("status") {
+        eprintln!("Usage: slircd db status [-c <config>]");
+        std::process::exit(1);
+    }
+
+    let path = database_path(config);
+    let key = database_key(config)?;
+    let status = Database::migration_status(path, key.as_deref().map(String::as_str)).await?;
+    println!("Database: {}", path);
+    for migration in &status {
+        println!("  {}", migration);
+    }
+    let applied = status
+        .iter()
+        .filter(|m| matches!(m.state, crate::db::MigrationState::Applied { .. }))
+        .count();
+    println!("{} of {} migrations applied", applied, status.len());
+    Ok(())
+}
+
+/// Drain the server once DIE, RESTART or a signal requests shutdown.
+///
+/// Refuses new clients and warns everyone, flushes state while channels
+/// still exist, then broadcasts the shutdown signal: the gateway stops
+/// accepting, clients get ERROR and links close. Returns once local clients
+/// have gone (or the drain timeout passed) and the databases are flushed.
+async fn shutdown_controller(matrix: Arc<Matrix>) -> ShutdownRequest {
+    let request = matrix.lifecycle_manager.wait_for_shutdown_request().await;
+    let message = matrix.lifecycle_manager.shutdown_message();
+    info!(restart = request.restart, reason = %request.reason, "Shutdown requested");
+
+    matrix.lifecycle_manager.enter_maintenance(&request.reason);
+    matrix
+        .user_manager
+        .send_notice_to_local_users(&message)
+        .await;
+
+    // Flush state while clients and channels still exist
+    matrix.channel_manager.sync_all_channels().await;
+    let written = matrix.client_manager.writeback_dirty().await;
+    info!(count = written, "Always-on state flushed");
+    crate::state::snapshot::save_on_shutdown(&matrix).await;
+
+    let _ = matrix.lifecycle_manager.shutdown_tx.send(());
+
+    let deadline = tokio::time::Instant::now() + SHUTDOWN_DRAIN_TIMEOUT;
+    while !matrix.user_manager.senders.is_empty() && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let remaining = matrix.user_manager.senders.len();
+    if remaining > 0 {
+        tracing::warn!(
+            remaining,
+            "Drain timeout passed with clients still connected"
+        );
+    }
+
+    if let Some(journal) = matrix.journal.get() {
+        journal.snapshot().await;
+    }
+    matrix.db.pool().close().await;
+    info!("Connections drained and databases flushed");
+    request
+}
+
+/// Replace this process with a fresh copy of the server binary.
+///
+/// Only returns if the exec fails. Sockets are close-on-exec, so the new
+/// process can bind the same ports.
+fn exec_restart() -> anyhow::Error {
+    use std::os::unix::process::CommandExt;
+
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => return anyhow::anyhow!("RESTART: cannot locate server binary: {e}"),
+    };
+    info!(binary = %exe.display(), "Restarting");
+    let error = std::process::Command::new(&exe)
+        .args(std::env::args_os().skip(1))
+        .exec();
+    anyhow::anyhow!("RESTART: exec of {} failed: {error}", exe.display())
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1).peekable();
+    let db_subcommand = if args.peek().is_some_and(|arg| arg == "db") {
+        args.next();
+        Some(args.next())
+    } else {
+        None
+    };
+
     // Load configuration first (before tracing, so we can use log_format)
-    let config_path = resolve_config_path();
+    let config_path = resolve_config_path(args);
 
     let config = Config::load(&config_path).map_err(|e| {
         eprintln!("ERROR: Failed to load config from {}: {}", config_path, e);
         e
     })?;
 
+    if let Some(command) = db_subcommand {
+        return db_command(command.as_deref(), &config).await;
+    }
+
     let _ = tokio_rustls::rustls::crypto::aws_lc_rs::default_provider().install_default();
 
     // Initialize tracing based on config
@@ -126,12 +247,12 @@ async fn main() -> anyhow::Result<()> {
     }
 
     // Initialize database
-    let db_path = config
-        .database
-        .as_ref()
-        .map(|d| d.path.as_str())
-        .unwrap_or("slircd.db");
-    let db = Database::new(db_path).await?;
+    let db_path = database_path(&config);
+    let db_key = database_key(&config)?;
+    let db = Database::open(db_path, db_key.as_deref().map(String::as_str))
+        .await?
+        .with_auth_provider(db::auth::from_config(&config.auth));
+    drop(db_key); // zeroized
 
     // Load registered channels from database
     let registered_channels: Vec<String> = db
@@ -174,11 +295,25 @@ async fn main() -> anyhow::Result<()> {
         tracing::warn!(error = %e, "Failed to load Z-lines from database");
         Vec::new()
     });
+    let active_qlines = db.bans().get_active_qlines().await.unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "Failed to load Q-lines from database");
+        Vec::new()
+    });
+    let session_limits = db
+        .bans()
+        .get_active_session_limits()
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Failed to load session limits from database");
+            Vec::new()
+        });
     info!(
         klines = active_klines.len(),
         dlines = active_dlines.len(),
         glines = active_glines.len(),
         zlines = active_zlines.len(),
+        qlines = active_qlines.len(),
+        session_limits = session_limits.len(),
         "Loaded active bans into cache"
     );
 
@@ -207,6 +342,18 @@ async fn main() -> anyhow::Result<()> {
 
                 (Arc::new(redb_provider), store)
             }
+            "memory" => {
+                info!(
+                    max_items = config.history.memory_max_items,
+                    "Initializing in-memory history backend"
+                );
+                (
+                    Arc::new(crate::history::memory::MemoryProvider::new(
+                        config.history.memory_max_items,
+                    )),
+                    None,
+                )
+            }
             _ => {
                 info!("History backend 'none' or unknown. Using NoOp.");
                 (Arc::new(crate::history::noop::NoOpProvider), None)
@@ -228,7 +375,7 @@ async fn main() -> anyhow::Result<()> {
     const DISCONNECT_CHANNEL_SIZE: usize = 1024;
     let (disconnect_tx, mut disconnect_rx) =
         tokio::sync::mpsc::channel::<(String, String)>(DISCONNECT_CHANNEL_SIZE);
-    let (matrix_struct, mut router_rx) = Matrix::new(crate::state::MatrixParams {
+    let (matrix_struct, router_rx) = Matrix::new(crate::state::MatrixParams {
         config: &config,
         config_path: config_path.clone(),
         data_dir,
@@ -240,75 +387,56 @@ async fn main() -> anyhow::Result<()> {
         dlines: active_dlines,
         glines: active_glines,
         zlines: active_zlines,
+        qlines: active_qlines,
+        session_limits,
         disconnect_tx,
         always_on_store: always_on_store.clone(),
     });
     let matrix = Arc::new(matrix_struct);
     info!("Matrix initialized");
 
+    // Warm boot: restore channels and WHOWAS from the shutdown snapshot
+    // before persistent channel restoration and before clients connect.
+    crate::state::snapshot::restore_on_startup(&matrix).await;
+
+    // Crash recovery: replay the CRDT journal and start journaling
+    crate::sync::store::recover_on_startup(&matrix).await;
+
+    // Bring BotServ bots back online and into their channels
+    if matrix.config.services.builtin() {
+        crate::services::botserv::restore_on_startup(&matrix).await;
+    } else {
+        info!(
+            server = ?matrix.config.services.external,
+            "Built-in services disabled in favour of external services"
+        );
+    }
+
     // Spawn all background tasks
     matrix
         .lifecycle_manager
         .spawn_background_tasks(Arc::clone(&matrix));
     info!("Background tasks started");
 
-    // Spawn router task for remote messages
+    // Reload the config on SIGHUP, as REHASH does
     {
         let matrix = Arc::clone(&matrix);
         tokio::spawn(async move {
-            while let Some(msg_arc) = router_rx.recv().await {
-                let mut msg = (*msg_arc).clone();
-                // Check for x-target-uid tag
-                let target_uid = msg
-                    .tags
-                    .as_ref()
-                    .and_then(|tags| tags.iter().find(|t| t.0 == "x-target-uid"))
-                    .and_then(|t| t.1.as_ref())
-                    .cloned();
-
-                let target_uid = if let Some(uid) = target_uid {
-                    // Rewrite command to target UID
-                    match &msg.command {
-                        slirc_proto::Command::PRIVMSG(_, text) => {
-                            msg.command = slirc_proto::Command::PRIVMSG(uid.clone(), text.clone());
-                        }
-                        slirc_proto::Command::NOTICE(_, text) => {
-                            msg.command = slirc_proto::Command::NOTICE(uid.clone(), text.clone());
-                        }
-                        _ => {}
-                    }
-                    uid
-                } else {
-                    // Fallback to command target (if it's a UID)
-                    match &msg.command {
-                        slirc_proto::Command::PRIVMSG(target, _) => target.clone(),
-                        slirc_proto::Command::NOTICE(target, _) => target.clone(),
-                        _ => continue,
-                    }
-                };
-
-                info!(target_uid = %target_uid, "Router task received message");
-
-                // Look up server for target UID
-                // Assuming UID prefix is SID (3 chars)
-                if target_uid.len() >= 3 {
-                    let sid_prefix = &target_uid[0..3];
-                    let target_sid =
-                        slirc_proto::sync::clock::ServerId::new(sid_prefix.to_string());
-
-                    if let Some(peer) = matrix.sync_manager.get_peer_for_server(&target_sid) {
-                        info!(target_sid = %target_sid.as_str(), "Routing message to peer");
-                        if let Err(e) = peer.tx.send(Arc::new(msg)).await {
-                            tracing::warn!(target_sid = %target_sid.as_str(), error = %e, "Failed to route message to peer (link likely dead)");
-                        }
-                    } else {
-                        tracing::warn!(target_sid = %target_sid.as_str(), "No peer found for target server");
-                    }
+            use tokio::signal::unix::{SignalKind, signal};
+            let mut sighup =
+                signal(SignalKind::hangup()).expect("failed to install SIGHUP handler");
+            while sighup.recv().await.is_some() {
+                info!("Received SIGHUP - reloading configuration");
+                if let Err(e) = crate::state::rehash::rehash(&matrix).await {
+                    error!(error = %e, "SIGHUP reload failed - original config preserved");
                 }
             }
         });
     }
 
+    // Spawn router task for remote messages
+    crate::sync::router::spawn_router(Arc::clone(&matrix), router_rx);
+
     // Process disconnect requests outside of channel actor tasks to avoid deadlocks.
     {
         let matrix = Arc::clone(&matrix);
@@ -329,8 +457,9 @@ async fn main() -> anyhow::Result<()> {
         metrics::init();
         info!("Metrics initialized");
 
+        let matrix = Arc::clone(&matrix);
         tokio::spawn(async move {
-            http::run_http_server(metrics_port).await;
+            http::run_http_server(metrics_port, matrix).await;
         });
         info!(port = metrics_port, "Prometheus HTTP server started");
     }
@@ -354,9 +483,9 @@ async fn main() -> anyhow::Result<()> {
     // Create command handler registry
     let registry = Arc::new(Registry::new(config.webirc.clone()));
 
-    // Start the Gateway (with optional TLS and WebSocket)
+    // Start the Gateway (one accept loop per listener)
     let gateway = Gateway::bind(
-        config.listen,
+        config.listeners(),
         config.tls,
         config.websocket,
         matrix.clone(),
@@ -391,11 +520,27 @@ async fn main() -> anyhow::Result<()> {
         .sync_manager
         .start_heartbeat(matrix.lifecycle_manager.shutdown_tx.subscribe());
 
+    // Start S2S anti-entropy digest exchange
+    matrix.sync_manager.start_anti_entropy(
+        Arc::clone(&matrix),
+        matrix.lifecycle_manager.shutdown_tx.subscribe(),
+    );
+
+    // Start CRDT tombstone GC
+    matrix.sync_manager.start_tombstone_gc(
+        Arc::clone(&matrix),
+        matrix.lifecycle_manager.shutdown_tx.subscribe(),
+    );
+
+    let shutdown = tokio::spawn(shutdown_controller(Arc::clone(&matrix)));
+
     gateway.run().await?;
 
-    info!("Gateway stopped, waiting for tasks to finish...");
-    // Give tasks a moment to flush buffers and close connections
-    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+    info!("Gateway stopped, draining connections...");
+    let request = shutdown.await?;
+    if request.restart {
+        return Err(exec_restart());
+    }
 
     Ok(())
 }
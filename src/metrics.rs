@@ -31,10 +31,42 @@ pub fn init() {
     describe_counter!("irc_bans_triggered_total", "Ban enforcement events");
     describe_counter!("irc_xlines_enforced_total", "X-line enforcement events");
     describe_counter!("irc_rate_limited_total", "Rate limit hits");
+    describe_counter!(
+        "irc_error_replies_suppressed_total",
+        "Error numerics dropped by the per-connection error reply limit"
+    );
+    describe_counter!(
+        "irc_handshake_limit_disconnects_total",
+        "Unregistered connections closed for exceeding a handshake limit"
+    );
+    describe_counter!(
+        "irc_auth_provider_total",
+        "External authentication provider results (accepted, rejected, error)"
+    );
+    describe_counter!(
+        "irc_join_part_flood_total",
+        "JOIN/PART flood responses (delay, lockout, disconnect)"
+    );
     describe_counter!(
         "irc_registered_only_blocked_total",
         "Registered-only (+r) enforcement events"
     );
+    describe_counter!(
+        "irc_client_tags_dropped_total",
+        "Client-only tags stripped by the [client_tags] policy"
+    );
+    describe_gauge!(
+        "irc_sendq_bytes",
+        "Bytes waiting in client send queues, by connection class"
+    );
+    describe_histogram!(
+        "irc_sendq_peak_bytes",
+        "Deepest send queue of each closed client connection, by class"
+    );
+    describe_counter!(
+        "irc_sendq_events_total",
+        "Send queue policy actions (dropped, exceeded, paused) by class"
+    );
     describe_gauge!("irc_connected_users", "Currently connected users");
     describe_gauge!("irc_active_channels", "Active channels");
 
@@ -57,6 +89,10 @@ pub fn init() {
         "slircd_distributed_collisions_total",
         "Distributed collisions resolved"
     );
+    describe_counter!(
+        "slircd_distributed_digest_mismatches_total",
+        "Anti-entropy digest buckets found to differ from a peer"
+    );
     describe_histogram!(
         "slircd_distributed_sync_latency_seconds",
         "Processing time for sync messages"
@@ -72,6 +108,31 @@ pub fn init() {
     );
     describe_counter!("slircd_s2s_commands_total", "S2S commands processed");
     describe_counter!("slircd_s2s_rate_limited_total", "S2S rate limit events");
+    describe_gauge!(
+        "slircd_s2s_clock_skew_seconds",
+        "Peer clock offset from the local clock, from SVINFO"
+    );
+    describe_counter!(
+        "slircd_s2s_clock_clamped_total",
+        "Peer timestamps clamped for exceeding the drift bound"
+    );
+    describe_counter!(
+        "slircd_s2s_sendq_exceeded_total",
+        "Peer links dropped because their send queue was full"
+    );
+    describe_counter!(
+        "slircd_crdt_tombstones_pruned_total",
+        "CRDT tombstones dropped once every server had seen them"
+    );
+
+    describe_counter!(
+        "slircd_state_events_total",
+        "State changes published on the observer bus"
+    );
+    describe_counter!(
+        "slircd_observer_dropped_total",
+        "State changes dropped because an observer's queue was full"
+    );
 }
 
 /// Gather all metrics and encode them in Prometheus text format.
@@ -94,6 +155,31 @@ pub fn inc_rate_limited() {
     counter!("irc_rate_limited_total").increment(1);
 }
 
+pub fn inc_error_replies_suppressed(numeric: u16) {
+    counter!("irc_error_replies_suppressed_total", "numeric" => numeric.to_string()).increment(1);
+}
+
+pub fn inc_handshake_limit_disconnects(limit: &'static str) {
+    counter!("irc_handshake_limit_disconnects_total", "limit" => limit).increment(1);
+}
+
+pub fn inc_join_part_flood(action: &'static str) {
+    counter!("irc_join_part_flood_total", "action" => action).increment(1);
+}
+
+pub fn inc_auth_provider(provider: &'static str, result: &'static str) {
+    counter!("irc_auth_provider_total", "provider" => provider, "result" => result).increment(1);
+}
+
+pub fn inc_client_tags_dropped(reason: &'static str) {
+    counter!("irc_client_tags_dropped_total", "reason" => reason).increment(1);
+}
+
+pub fn inc_sendq_event(class: &str, action: &'static str) {
+    counter!("irc_sendq_events_total", "class" => class.to_string(), "action" => action)
+        .increment(1);
+}
+
 pub fn inc_connected_users() {
     gauge!("irc_connected_users").increment(1.0);
 }
@@ -128,6 +214,7 @@ pub fn record_command_error(command: &str, error: &str) {
 #[inline]
 pub fn set_channel_members(channel: &str, count: i64) {
     gauge!("irc_channel_members", "channel" => channel.to_string()).set(count as f64);
+    crate::activity::global().channel_members(channel, count.max(0) as usize);
 }
 
 /// Remove a channel from the members gauge (when channel is destroyed).
@@ -137,6 +224,7 @@ pub fn remove_channel_metrics(channel: &str) {
     // but setting to 0 is a reasonable fallback for now, or we just stop reporting it.
     // Ideally we would delete the metric, but for now filtering 0s in PromQL is common.
     gauge!("irc_channel_members", "channel" => channel.to_string()).set(0.0);
+    crate::activity::global().channel_removed(channel);
 }
 
 /// Record message fan-out (how many recipients received a channel message).
@@ -172,6 +260,15 @@ pub fn inc_distributed_collisions(kind: &str, resolution: &str) {
     .increment(1);
 }
 
+pub fn inc_distributed_digest_mismatches(peer_sid: &str, kind: &str) {
+    counter!(
+        "slircd_distributed_digest_mismatches_total",
+        "peer_sid" => peer_sid.to_string(),
+        "type" => kind.to_string()
+    )
+    .increment(1);
+}
+
 pub fn inc_s2s_bytes_sent(peer_sid: &str, bytes: u64) {
     counter!("slircd_s2s_bytes_sent_total", "peer_sid" => peer_sid.to_string()).increment(bytes);
 }
@@ -198,3 +295,40 @@ pub fn inc_s2s_rate_limited(peer_sid: &str, result: &str) {
     )
     .increment(1);
 }
+
+pub fn set_s2s_clock_skew(peer_sid: &str, skew_secs: i64) {
+    gauge!("slircd_s2s_clock_skew_seconds", "peer_sid" => peer_sid.to_string())
+        .set(skew_secs as f64);
+}
+
+pub fn inc_s2s_clock_clamped(peer_sid: &str) {
+    counter!("slircd_s2s_clock_clamped_total", "peer_sid" => peer_sid.to_string()).increment(1);
+}
+
+pub fn inc_s2s_sendq_exceeded(peer_sid: &str) {
+    counter!("slircd_s2s_sendq_exceeded_total", "peer_sid" => peer_sid.to_string()).increment(1);
+}
+
+pub fn inc_crdt_tombstones_pruned(store: &'static str, count: usize) {
+    if count > 0 {
+        counter!("slircd_crdt_tombstones_pruned_total", "store" => store).increment(count as u64);
+    }
+}
+
+pub fn inc_state_event(event: &str, origin: &str) {
+    counter!(
+        "slircd_state_events_total",
+        "event" => event.to_string(),
+        "origin" => origin.to_string()
+    )
+    .increment(1);
+}
+
+pub fn inc_observer_dropped(observer: &str, event: &str) {
+    counter!(
+        "slircd_observer_dropped_total",
+        "observer" => observer.to_string(),
+        "event" => event.to_string()
+    )
+    .increment(1);
+}
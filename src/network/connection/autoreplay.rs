@@ -23,7 +23,7 @@ pub async fn perform_autoreplay(
     ctx: &mut ConnectionContext<'_>,
     reg_state: &RegisteredState,
     info: ReattachInfo,
-    sender: tokio::sync::mpsc::Sender<std::sync::Arc<slirc_proto::Message>>,
+    sender: crate::state::SendQ,
 ) -> HandlerResult {
     let nick = &reg_state.nick;
     let server_name = ctx.matrix.server_info.name.clone();
@@ -82,6 +82,7 @@ pub async fn perform_autoreplay(
         let join = Message::from(Command::JOIN(display_name.clone(), None, None))
             .with_prefix(prefix.clone());
 
+        crate::tap::global().outbound(ctx.uid, ctx.addr.ip(), &join);
         if let Err(e) = ctx.transport.write_message(&join).await {
             warn!(uid = %ctx.uid, error = ?e, "Failed to send autoreplay JOIN");
             // If we can't write, connection is likely dead, stop replay
@@ -105,6 +106,7 @@ pub async fn perform_autoreplay(
                 Response::RPL_TOPIC,
                 vec![nick.clone(), display_name.clone(), topic.text.clone()],
             );
+            crate::tap::global().outbound(ctx.uid, ctx.addr.ip(), &topic_msg);
             let _ = ctx.transport.write_message(&topic_msg).await;
 
             let topic_whotime = server_reply(
@@ -117,6 +119,7 @@ pub async fn perform_autoreplay(
                     topic.set_at.to_string(), // i64 timestamp
                 ],
             );
+            crate::tap::global().outbound(ctx.uid, ctx.addr.ip(), &topic_whotime);
             let _ = ctx.transport.write_message(&topic_whotime).await;
         }
     }
@@ -176,6 +179,7 @@ async fn replay_channel_history(
                     Some(vec![target.to_string()]),
                 ),
             };
+            crate::tap::global().outbound(ctx.uid, ctx.addr.ip(), &batch_start);
             let _ = ctx.transport.write_message(&batch_start).await;
 
             // Send each message with batch tag
@@ -189,6 +193,7 @@ async fn replay_channel_history(
                         has_event_playback,
                     )
                 {
+                    crate::tap::global().outbound(ctx.uid, ctx.addr.ip(), &history_msg);
                     let _ = ctx.transport.write_message(&history_msg).await;
                 }
             }
@@ -199,12 +204,17 @@ async fn replay_channel_history(
                 prefix: Some(Prefix::ServerName(server_name.clone())),
                 command: Command::BATCH(format!("-{}", batch_id), None, None),
             };
+            crate::tap::global().outbound(ctx.uid, ctx.addr.ip(), &batch_end);
             let _ = ctx.transport.write_message(&batch_end).await;
 
             // Send read marker if client supports it (Unified Read State)
             if reg_state.capabilities.contains("draft/read-marker")
                 && let Some(account) = &reg_state.account
-                && let Some(marker_ts) = ctx.matrix.read_marker_manager.get_marker(account, target)
+                && let Some(marker_ts) = ctx
+                    .matrix
+                    .read_marker_manager
+                    .get_marker(account, target)
+                    .await
                 && let Some(dt) = DateTime::from_timestamp(
                     marker_ts / 1_000_000_000,
                     (marker_ts % 1_000_000_000) as u32,
@@ -228,6 +238,7 @@ async fn replay_channel_history(
                     prefix: Some(Prefix::ServerName(server_name.clone())),
                     command: Command::TAGMSG(target.to_string()),
                 };
+                crate::tap::global().outbound(ctx.uid, ctx.addr.ip(), &marker_msg);
                 let _ = ctx.transport.write_message(&marker_msg).await;
                 debug!(target = %target, "Sent read marker sync");
             }
@@ -1,11 +1,9 @@
 use crate::db::Database;
 use crate::handlers::Registry;
-use crate::state::Matrix;
-use slirc_proto::Message;
+use crate::state::{Matrix, SendQ, SendQReceiver};
 use slirc_proto::transport::ZeroCopyTransportEnum;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::mpsc;
 use tokio_rustls::TlsAcceptor;
 
 /// Shared connection resources used across lifecycle phases.
@@ -32,9 +30,9 @@ pub struct ConnectionContext<'a> {
 /// Message channels for lifecycle phases.
 pub struct LifecycleChannels<'a> {
     /// Sender for queueing outgoing messages.
-    pub tx: &'a mpsc::Sender<Arc<Message>>,
+    pub tx: &'a SendQ,
     /// Receiver for draining outgoing messages.
-    pub rx: &'a mut mpsc::Receiver<Arc<Message>>,
+    pub rx: &'a mut SendQReceiver,
     /// Receiver for server shutdown signal.
     pub shutdown_rx: &'a mut tokio::sync::broadcast::Receiver<()>,
 }
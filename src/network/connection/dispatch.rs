@@ -4,13 +4,16 @@
 //! a dedicated module for better testability and maintainability.
 
 use super::context::ConnectionContext;
-use crate::handlers::{Context, HandlerResult, ResponseMiddleware, process_batch_message};
-use crate::state::RegisteredState;
+use crate::handlers::{
+    Context, HandlerResult, ResponseMiddleware, process_batch_message, with_label,
+};
+use crate::security::rate_limit::ErrorReplyLimiter;
+use crate::state::{RegisteredState, SendQ, SendQReceiver};
 use slirc_proto::Message;
 use slirc_proto::message::MessageRef;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::{Mutex, mpsc};
+use tokio::sync::Mutex;
 use tracing::{debug, warn};
 
 /// Result of processing a single message through the pipeline.
@@ -30,6 +33,8 @@ pub struct ProcessParams<'a> {
     pub uid: &'a str,
     pub addr: SocketAddr,
     pub reg_state: &'a mut RegisteredState,
+    /// Limits error numerics written directly, bypassing the outgoing queue.
+    pub error_replies: &'a mut ErrorReplyLimiter,
 }
 
 /// Process a single incoming message through the full pipeline.
@@ -46,8 +51,8 @@ pub struct ProcessParams<'a> {
 pub async fn process_message<'a>(
     conn: &mut ConnectionContext<'a>,
     params: ProcessParams<'_>,
-    outgoing_tx: &mpsc::Sender<Arc<Message>>,
-    outgoing_rx: &mut mpsc::Receiver<Arc<Message>>,
+    outgoing_tx: &SendQ,
+    outgoing_rx: &mut SendQReceiver,
 ) -> DispatchResult {
     let ProcessParams {
         msg,
@@ -55,10 +60,12 @@ pub async fn process_message<'a>(
         uid,
         addr,
         reg_state,
+        error_replies,
     } = params;
 
     // Stage 1: Update last active timestamp
     conn.matrix.user_manager.update_last_active(uid).await;
+    crate::activity::global().connection_line(uid);
     debug!(raw = ?msg, "Received message");
 
     // Stage 2: Batch processing
@@ -77,10 +84,14 @@ pub async fn process_message<'a>(
         Ok(None) => {}
         Err(fail_msg) => {
             warn!(error = %fail_msg, "Batch processing error");
+            // Drop the batch but keep its ref, so the rest of it is discarded
+            // rather than delivered line by line
             reg_state.active_batch = None;
-            reg_state.active_batch_ref = None;
             if let Ok(fail) = fail_msg.parse::<Message>()
-                && outgoing_tx.send(Arc::new(fail)).await.is_err()
+                && outgoing_tx
+                    .send(Arc::new(with_label(fail, label.as_deref())))
+                    .await
+                    .is_err()
             {
                 return DispatchResult::Continue;
             }
@@ -123,19 +134,29 @@ pub async fn process_message<'a>(
             let error_reply = super::helpers::closing_link_error(&addr, quit_msg.as_deref());
             batch.push(error_reply);
 
+            for msg in &batch {
+                crate::tap::global().outbound(uid, addr.ip(), msg);
+            }
             let _ = conn.transport.write_messages(&batch).await;
             return DispatchResult::Quit(quit_msg);
         } else {
-            // Other errors - send error reply
+            // Other errors - send error reply. A labeled command gets it as
+            // its labeled response rather than a bare line plus an empty ACK.
             let nick = &reg_state.nick;
             if let Some(reply) = super::error_handling::handler_error_to_reply_owned(
                 &conn.matrix.server_info.name,
                 nick,
                 &e,
                 msg,
-            ) && conn.transport.write_message(&reply).await.is_err()
-            {
-                return DispatchResult::WriteError;
+            ) {
+                if let Some(buf) = capture_buffer.as_ref() {
+                    buf.lock().await.push(reply);
+                } else if error_replies.allow(&reply) {
+                    crate::tap::global().outbound(uid, addr.ip(), &reply);
+                    if conn.transport.write_message(&reply).await.is_err() {
+                        return DispatchResult::WriteError;
+                    }
+                }
             }
         }
     }
@@ -144,6 +165,8 @@ pub async fn process_message<'a>(
     if let Some(label_str) = label
         && let Some(buf) = capture_buffer
     {
+        // Not run through the error reply limiter: these replies may be the
+        // only answer to the labeled command
         let mut messages = buf.lock().await;
         super::event_loop::send_labeled_response(
             conn.transport,
@@ -151,6 +174,8 @@ pub async fn process_message<'a>(
             &label_str,
             &mut messages,
             suppress_ack,
+            uid,
+            addr.ip(),
         )
         .await;
     }
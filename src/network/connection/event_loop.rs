@@ -4,17 +4,20 @@ use super::dispatch::{DispatchResult, ProcessParams, process_message};
 use super::error_handling::{ReadErrorAction, classify_read_error, extract_label_from_raw};
 use super::helpers::{
     batch_end_msg, batch_start_msg, excess_flood_error, flood_warning_notice,
-    input_too_long_response,
+    input_too_long_response, sendq_exceeded_error,
 };
 use crate::handlers::{labeled_ack, with_label};
 use crate::state::RegisteredState;
 use slirc_proto::{Command, Message, Prefix, Tag, generate_batch_ref};
+use std::net::IpAddr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 
 const MAX_FLOOD_VIOLATIONS: u8 = 3;
 const PING_CHECK_INTERVAL_SECS: u64 = 15;
+/// How long the closing ERROR may wait on a client that stopped reading.
+const SENDQ_ERROR_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
 
 /// Result of flood rate check.
 enum FloodCheckResult {
@@ -26,6 +29,15 @@ enum FloodCheckResult {
     Disconnect,
 }
 
+/// Quit reason for a session closed by ERROR: the parenthesised part of
+/// `Closing Link: (reason)`, or the whole text.
+fn session_quit_reason(text: &str) -> String {
+    text.strip_prefix("Closing Link: (")
+        .and_then(|rest| rest.strip_suffix(')'))
+        .unwrap_or(text)
+        .to_string()
+}
+
 enum SelectResult {
     /// No action needed, continue loop
     None,
@@ -47,44 +59,68 @@ enum SelectResult {
     SendPing,
     /// Ping timeout - disconnect
     PingTimeout { total_idle: u64 },
+    /// The send queue filled up - disconnect
+    SendQExceeded,
 }
 
 /// Handle labeled-response protocol (IRCv3 spec).
+///
+/// `uid` and `ip` identify the connection for traffic taps.
 pub(crate) async fn send_labeled_response(
     transport: &mut slirc_proto::transport::ZeroCopyTransportEnum,
     server_name: &str,
     label: &str,
     messages: &mut Vec<Message>,
     suppress_ack: bool,
+    uid: &str,
+    ip: IpAddr,
 ) {
+    let taps = crate::tap::global();
     let count = messages.len();
     if count == 0 {
         if !suppress_ack {
             let ack = labeled_ack(server_name, label);
+            taps.outbound(uid, ip, &ack);
             let _ = transport.write_message(&ack).await;
         }
     } else if count == 1 {
         // Safe: count == 1 guarantees exactly one message
         if let Some(msg) = messages.drain(..).next() {
             let tagged = with_label(msg, Some(label));
+            taps.outbound(uid, ip, &tagged);
             let _ = transport.write_message(&tagged).await;
         }
     } else {
         // Multiple responses - wrap in BATCH
         let batch_ref = generate_batch_ref();
         let batch_start = batch_start_msg(server_name, &batch_ref).with_tag("label", Some(label));
+        taps.outbound(uid, ip, &batch_start);
         let _ = transport.write_message(&batch_start).await;
 
         for msg in messages.drain(..) {
-            let batched = msg.with_tag("batch", Some(&batch_ref));
+            // Only the BATCH start carries the label
+            let batched = without_label(msg).with_tag("batch", Some(&batch_ref));
+            taps.outbound(uid, ip, &batched);
             let _ = transport.write_message(&batched).await;
         }
 
         let batch_end = batch_end_msg(server_name, &batch_ref);
+        taps.outbound(uid, ip, &batch_end);
         let _ = transport.write_message(&batch_end).await;
     }
 }
 
+/// Strip a `label` tag a handler already attached to a batched reply.
+fn without_label(mut msg: Message) -> Message {
+    if let Some(tags) = msg.tags.as_mut() {
+        tags.retain(|Tag(key, _)| key != "label");
+        if tags.is_empty() {
+            msg.tags = None;
+        }
+    }
+    msg
+}
+
 use slirc_proto::transport::TransportReadError;
 
 fn process_read_result(
@@ -93,9 +129,12 @@ fn process_read_result(
     matrix: &Arc<crate::state::Matrix>,
     flood_violations: &mut u8,
     uid: &str,
+    ip: IpAddr,
 ) -> SelectResult {
     match result {
         Some(Ok(msg_ref)) => {
+            crate::tap::global().inbound(uid, ip, msg_ref.raw);
+
             // Reset ping state on any received message
             reg_state.last_activity = Instant::now();
             reg_state.ping_pending = false;
@@ -146,8 +185,9 @@ fn process_read_result(
                     let name = &matrix.server_info.name;
                     let notice =
                         flood_warning_notice(name, *flood_violations, MAX_FLOOD_VIOLATIONS);
+                    // The dropped command's only response
                     SelectResult::Continue {
-                        pending_writes: vec![notice],
+                        pending_writes: vec![with_label(notice, label.as_deref())],
                     }
                 }
                 FloodCheckResult::Disconnect => {
@@ -179,7 +219,8 @@ fn process_read_result(
                     let command_name = command_hint.unwrap_or_else(|| "PRIVMSG".to_string());
 
                     // Extract label from raw bytes if present
-                    let label = extract_label_from_raw(&raw_line);
+                    let label = extract_label_from_raw(&raw_line)
+                        .filter(|_| reg_state.capabilities.contains("labeled-response"));
                     let tags = label.map(|l| vec![Tag::new("label", Some(l))]);
 
                     // Send FAIL response per IRCv3 spec
@@ -278,6 +319,11 @@ pub async fn run_event_loop(
 ) -> Option<String> {
     let mut flood_violations = 0u8;
     let mut quit_message: Option<String> = None;
+    let mut error_replies = conn
+        .matrix
+        .security_manager
+        .rate_limiter
+        .error_reply_limiter(conn.addr.ip());
 
     // Ping timeout configuration
     let ping_interval = Duration::from_secs(conn.matrix.server_info.idle_timeouts.ping);
@@ -305,15 +351,30 @@ pub async fn run_event_loop(
 
     info!("Entering unified event loop");
 
+    let (uid, ip) = (conn.uid, conn.addr.ip());
+    let tap_out = |msg: &Message| crate::tap::global().outbound(uid, ip, msg);
+    let mut paused = false;
+
     loop {
         if !conn.matrix.user_manager.users.contains_key(conn.uid) {
             info!(uid = %conn.uid, "User removed from Matrix - disconnecting");
             break;
         }
 
+        // The block policy stops reading commands until the client catches up
+        let blocking = channels.rx.is_blocking();
+        if blocking != paused {
+            paused = blocking;
+            debug!(uid = %conn.uid, paused, "SendQ backpressure");
+            if paused && let Some(class) = channels.rx.class() {
+                crate::metrics::inc_sendq_event(class, "paused");
+            }
+        }
+        let sendq_exceeded = channels.rx.exceeded();
+
         let select_result = tokio::select! {
-            result = conn.transport.next() => {
-                process_read_result(result, reg_state, conn.matrix, &mut flood_violations, conn.uid)
+            result = conn.transport.next(), if !paused => {
+                process_read_result(result, reg_state, conn.matrix, &mut flood_violations, conn.uid, ip)
             }
 
             Some(msg) = channels.rx.recv() => {
@@ -321,15 +382,20 @@ pub async fn run_event_loop(
                 SelectResult::OutgoingMessage { msg, is_error_disconnect }
             }
 
+            _ = sendq_exceeded => {
+                warn!(uid = %conn.uid, class = channels.rx.class(), "SendQ exceeded - disconnecting");
+                quit_message = Some("SendQ exceeded".to_string());
+                SelectResult::SendQExceeded
+            }
+
             _ = ping_check_timer.tick() => {
                 check_ping_state(reg_state, conn.uid, ping_timeout, ping_interval, &mut quit_message)
             }
             _ = channels.shutdown_rx.recv() => {
                 info!("Shutdown signal received - disconnecting client");
-                quit_message = Some("Server shutting down".to_string());
-                let error_msg = Message::from(Command::ERROR(
-                    "Closing Link: Server shutting down".to_string(),
-                ));
+                let reason = conn.matrix.lifecycle_manager.shutdown_message();
+                let error_msg = Message::from(Command::ERROR(format!("Closing Link: {reason}")));
+                quit_message = Some(reason);
                 SelectResult::Break { pending_writes: vec![error_msg] }
             }
         };
@@ -338,6 +404,7 @@ pub async fn run_event_loop(
             SelectResult::None => continue,
 
             SelectResult::Continue { pending_writes } => {
+                pending_writes.iter().for_each(tap_out);
                 if !pending_writes.is_empty() {
                     // Use batch write optimization
                     let _ = conn.transport.write_messages(&pending_writes).await;
@@ -346,6 +413,7 @@ pub async fn run_event_loop(
             }
 
             SelectResult::Break { pending_writes } => {
+                pending_writes.iter().for_each(tap_out);
                 if !pending_writes.is_empty() {
                     // Use batch write optimization before disconnect
                     let _ = conn.transport.write_messages(&pending_writes).await;
@@ -357,6 +425,10 @@ pub async fn run_event_loop(
                 msg,
                 is_error_disconnect,
             } => {
+                if !error_replies.allow(&msg) {
+                    continue;
+                }
+                tap_out(&msg);
                 if let Err(e) = conn.transport.write_message(&msg).await {
                     warn!(error = ?e, "Write error");
                     break;
@@ -365,11 +437,25 @@ pub async fn run_event_loop(
                     info!("Received disconnect signal - user removed from Matrix");
                     break;
                 }
+                if is_error_disconnect
+                    && !conn
+                        .matrix
+                        .user_manager
+                        .has_session(conn.uid, reg_state.session_id)
+                {
+                    // Only this session was closed (NickServ SESSIONS KILL)
+                    info!("Received disconnect signal - session removed");
+                    if let Command::ERROR(text) = &msg.command {
+                        quit_message = Some(session_quit_reason(text));
+                    }
+                    break;
+                }
                 continue;
             }
 
             SelectResult::SendPing => {
                 let ping = Message::ping(&conn.matrix.server_info.name);
+                tap_out(&ping);
                 if let Err(e) = conn.transport.write_message(&ping).await {
                     warn!(error = ?e, "Failed to send PING");
                     break;
@@ -385,10 +471,23 @@ pub async fn run_event_loop(
                     conn.addr.ip(),
                     total_idle
                 )));
+                tap_out(&error_msg);
                 let _ = conn.transport.write_message(&error_msg).await;
                 break;
             }
 
+            SelectResult::SendQExceeded => {
+                // The client is not reading, so do not wait long to tell it
+                let error_msg = sendq_exceeded_error(&conn.addr);
+                tap_out(&error_msg);
+                let _ = tokio::time::timeout(
+                    SENDQ_ERROR_WRITE_TIMEOUT,
+                    conn.transport.write_message(&error_msg),
+                )
+                .await;
+                break;
+            }
+
             SelectResult::ProcessMessage { msg, label } => {
                 let params = ProcessParams {
                     msg: &msg,
@@ -396,6 +495,7 @@ pub async fn run_event_loop(
                     uid: conn.uid,
                     addr: conn.addr,
                     reg_state,
+                    error_replies: &mut error_replies,
                 };
 
                 match process_message(&mut conn, params, channels.tx, channels.rx).await {
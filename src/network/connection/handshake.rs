@@ -1,8 +1,9 @@
 use super::context::{ConnectionContext, LifecycleChannels};
 use super::error_handling::{ReadErrorAction, classify_read_error, handler_error_to_reply_owned};
 use crate::handlers::{Context, ResponseMiddleware, WelcomeBurstWriter};
-use crate::state::{Matrix, UnregisteredState};
+use crate::state::{Matrix, RegistrationPhase, UnregisteredState};
 use slirc_proto::{Command, Message, Prefix, Response, irc_to_lower};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tracing::{debug, info, warn};
 
@@ -78,9 +79,14 @@ pub async fn run_handshake_loop(
         rx: handshake_rx,
         ..
     } = channels;
+    let tap_out = |msg: &Message| crate::tap::global().outbound(uid, addr.ip(), msg);
     // Registration timeout from config
     let registration_timeout = Duration::from_secs(matrix.server_info.idle_timeouts.registration);
     let handshake_start = Instant::now();
+    let mut handshake_limits = matrix
+        .security_manager
+        .rate_limiter
+        .handshake_limiter(addr.ip());
 
     // If we are the initiator, send the initial handshake commands
     if let Some(init_data) = &unreg_state.initiator_data {
@@ -142,6 +148,7 @@ pub async fn run_handshake_loop(
                     elapsed.as_secs()
                 )),
             };
+            tap_out(&error_msg);
             let _ = transport.write_message(&error_msg).await;
             return Err(HandshakeExit::ProtocolError(unreg_state.nick.clone()));
         }
@@ -170,6 +177,8 @@ pub async fn run_handshake_loop(
 
             match result {
                 Ok(Some(Ok(msg_ref))) => {
+                    crate::tap::global().inbound(uid, addr.ip(), msg_ref.raw);
+
                     // Start Privacy Redaction
                     // Access public 'name' field of CommandRef
                     let command_str = msg_ref.command.name.to_uppercase();
@@ -232,6 +241,7 @@ pub async fn run_handshake_loop(
                                 vec![nick.to_string(), "Input line too long".to_string()],
                             ),
                         };
+                        tap_out(&reply);
                         let _ = transport.write_message(&reply).await;
                         return Err(HandshakeExit::Disconnected(unreg_state.nick.clone()));
                     }
@@ -243,8 +253,9 @@ pub async fn run_handshake_loop(
                         warn!(command = ?command_hint, details = %details, "Invalid UTF-8 during handshake");
                         let command_name = command_hint.unwrap_or_else(|| "PRIVMSG".to_string());
 
-                        // Extract label from raw bytes if present
-                        let label = super::error_handling::extract_label_from_raw(&raw_line);
+                        // Extract label from raw bytes if the client negotiated it
+                        let label = super::error_handling::extract_label_from_raw(&raw_line)
+                            .filter(|_| unreg_state.capabilities.contains("labeled-response"));
                         let tags = label.map(|l| vec![slirc_proto::Tag::new("label", Some(l))]);
 
                         let fail_msg = Message {
@@ -256,6 +267,7 @@ pub async fn run_handshake_loop(
                                 vec![format!("Invalid UTF-8 in message: {}", details)],
                             ),
                         };
+                        tap_out(&fail_msg);
                         let _ = transport.write_message(&fail_msg).await;
                         continue;
                     }
@@ -266,6 +278,7 @@ pub async fn run_handshake_loop(
                             prefix: None,
                             command: Command::ERROR(error_msg),
                         };
+                        tap_out(&error_reply);
                         let _ = transport.write_message(&error_reply).await;
                     }
                     ReadErrorAction::IoError => {
@@ -275,10 +288,28 @@ pub async fn run_handshake_loop(
                 return Err(HandshakeExit::ProtocolError(unreg_state.nick.clone()));
             }
             HandshakeSelectResult::Message { msg, label } => {
+                let sasl_idle = unreg_state.phase() != RegistrationPhase::SaslInProgress;
+                if let Err(limit) = handshake_limits.check(&msg, sasl_idle) {
+                    warn!(uid = %uid, limit = limit.label(), "Handshake limit exceeded");
+                    crate::metrics::inc_handshake_limit_disconnects(limit.label());
+                    let error_msg = Message {
+                        tags: None,
+                        prefix: None,
+                        command: Command::ERROR(format!(
+                            "Closing Link: {} ({})",
+                            addr.ip(),
+                            limit.reason()
+                        )),
+                    };
+                    tap_out(&error_msg);
+                    let _ = transport.write_message(&error_msg).await;
+                    return Err(HandshakeExit::ProtocolError(unreg_state.nick.clone()));
+                }
+
                 // Dispatch the command - we need a MessageRef for the dispatch
                 // Create a temporary one from the owned message
                 let raw_str = msg.to_string();
-                let dispatch_result =
+                let (dispatch_result, suppress_ack) =
                     if let Ok(msg_ref) = slirc_proto::message::MessageRef::parse(&raw_str) {
                         let mut ctx = Context {
                             uid,
@@ -287,17 +318,20 @@ pub async fn run_handshake_loop(
                             state: unreg_state,
                             db,
                             remote_addr: addr,
-                            label,
+                            label: label.clone(),
                             suppress_labeled_ack: false,
                             active_batch_id: None,
                             registry,
                         };
 
-                        registry.dispatch_pre_reg(&mut ctx, &msg_ref).await
+                        let result = registry.dispatch_pre_reg(&mut ctx, &msg_ref).await;
+                        (result, ctx.suppress_labeled_ack)
                     } else {
                         // Should not happen, but handle gracefully
-                        Ok(())
+                        (Ok(()), false)
                     };
+                // Replies to a labeled command, sent together as its labeled response
+                let mut labeled_replies: Vec<Message> = Vec::new();
 
                 if let Err(e) = dispatch_result {
                     debug!(error = ?e, "Handler error during handshake");
@@ -315,6 +349,7 @@ pub async fn run_handshake_loop(
                             prefix: None,
                             command: Command::ERROR(error_text),
                         };
+                        tap_out(&error_reply);
                         let _ = transport.write_message(&error_reply).await;
 
                         return Err(HandshakeExit::Quit(unreg_state.nick.clone()));
@@ -323,6 +358,7 @@ pub async fn run_handshake_loop(
                     // Handle AccessDenied - drain and disconnect
                     if matches!(e, crate::handlers::HandlerError::AccessDenied) {
                         while let Ok(response) = handshake_rx.try_recv() {
+                            tap_out(&response);
                             let _ = transport.write_message(&response).await;
                         }
                         return Err(HandshakeExit::AccessDenied(unreg_state.nick.clone()));
@@ -332,6 +368,7 @@ pub async fn run_handshake_loop(
                     if matches!(e, crate::handlers::HandlerError::StartTls) {
                         // Drain queued responses first (includes RPL_STARTTLS)
                         while let Ok(response) = handshake_rx.try_recv() {
+                            tap_out(&response);
                             if let Err(write_err) = transport.write_message(&response).await {
                                 warn!(error = ?write_err, "Write error before STARTTLS");
                                 return Err(HandshakeExit::WriteError(unreg_state.nick.clone()));
@@ -347,6 +384,7 @@ pub async fn run_handshake_loop(
                                     .with_prefix(Prefix::ServerName(
                                         matrix.server_info.name.clone(),
                                     ));
+                            tap_out(&reply);
                             let _ = transport.write_message(&reply).await;
                             continue;
                         };
@@ -369,18 +407,77 @@ pub async fn run_handshake_loop(
                         continue;
                     }
 
+                    // Handle COMPRESS - reply uncompressed, then compress
+                    if matches!(e, crate::handlers::HandlerError::Compress) {
+                        while let Ok(response) = handshake_rx.try_recv() {
+                            tap_out(&response);
+                            if let Err(write_err) = transport.write_message(&response).await {
+                                warn!(error = ?write_err, "Write error before COMPRESS");
+                                return Err(HandshakeExit::WriteError(unreg_state.nick.clone()));
+                            }
+                        }
+
+                        if let Err(zstd_err) = transport.enable_zstd() {
+                            warn!(uid = %uid, error = ?zstd_err, "Failed to start compression");
+                            return Err(HandshakeExit::ProtocolError(unreg_state.nick.clone()));
+                        }
+                        unreg_state.is_compressed = true;
+                        info!(uid = %uid, "Connection compressed with zstd");
+                        continue;
+                    }
+
                     // Send appropriate error reply using owned message
                     let nick = unreg_state.nick.as_deref().unwrap_or("*");
                     if let Some(reply) =
                         handler_error_to_reply_owned(&matrix.server_info.name, nick, &e, &msg)
                     {
-                        let _ = transport.write_message(&reply).await;
+                        if label.is_some() {
+                            labeled_replies.push(reply);
+                        } else {
+                            tap_out(&reply);
+                            let _ = transport.write_message(&reply).await;
+                        }
                     }
                 }
 
                 // Drain queued responses
                 while let Ok(response) = handshake_rx.try_recv() {
-                    if let Err(e) = transport.write_message(&response).await {
+                    if label.is_some() {
+                        labeled_replies.push(Arc::unwrap_or_clone(response));
+                    } else {
+                        tap_out(&response);
+                        if let Err(e) = transport.write_message(&response).await {
+                            warn!(error = ?e, "Write error during handshake");
+                            return Err(HandshakeExit::WriteError(unreg_state.nick.clone()));
+                        }
+                    }
+                }
+                if let Some(label) = label.as_deref() {
+                    super::event_loop::send_labeled_response(
+                        transport,
+                        &matrix.server_info.name,
+                        label,
+                        &mut labeled_replies,
+                        suppress_ack,
+                        uid,
+                        addr.ip(),
+                    )
+                    .await;
+                }
+
+                // Registration overtakes an unfinished SASL exchange
+                if unreg_state.abort_overtaken_sasl() {
+                    let nick = unreg_state.nick.as_deref().unwrap_or("*");
+                    let reply = Message {
+                        tags: None,
+                        prefix: Some(Prefix::ServerName(matrix.server_info.name.clone())),
+                        command: Command::Response(
+                            Response::ERR_SASLABORT,
+                            vec![nick.to_string(), "SASL authentication aborted".to_string()],
+                        ),
+                    };
+                    tap_out(&reply);
+                    if let Err(e) = transport.write_message(&reply).await {
                         warn!(error = ?e, "Write error during handshake");
                         return Err(HandshakeExit::WriteError(unreg_state.nick.clone()));
                     }
@@ -394,6 +491,11 @@ pub async fn run_handshake_loop(
                         .as_ref()
                         .and_then(|r| r.existing_uid.clone());
 
+                    // Wait for the hostname lookup started at connect
+                    if let Some(lookup) = unreg_state.rdns.take() {
+                        unreg_state.resolved_host = lookup.await.ok().flatten();
+                    }
+
                     let writer =
                         WelcomeBurstWriter::new(uid, matrix, transport, unreg_state, db, addr);
                     match writer.send().await {
@@ -18,6 +18,14 @@ pub fn excess_flood_error() -> Message {
     Message::from(Command::ERROR("Excess Flood (Strike limit reached)".into()))
 }
 
+/// Build an ERROR message for a client whose send queue filled up.
+pub fn sendq_exceeded_error(addr: &SocketAddr) -> Message {
+    Message::from(Command::ERROR(format!(
+        "Closing Link: {} (SendQ exceeded)",
+        addr.ip()
+    )))
+}
+
 /// Build a QUIT closing link message.
 pub fn closing_link_error(addr: &SocketAddr, quit_msg: Option<&str>) -> Message {
     let text = match quit_msg {
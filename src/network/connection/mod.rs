@@ -37,17 +37,18 @@ use event_loop::run_event_loop;
 use handshake::{HandshakeSuccess, run_handshake_loop};
 use server_loop::run_server_loop;
 
+use crate::config::{ListenConfig, find_class};
 use crate::db::Database;
 use crate::handlers::Registry;
-use crate::state::{InitiatorData, Matrix, UnregisteredState};
+use crate::network::ReverseDns;
+use crate::state::{InitiatorData, Matrix, SendQ, UnregisteredState};
 use sha2::{Digest, Sha256};
-use slirc_proto::Message;
+use slirc_proto::IrcSubprotocol;
 use slirc_proto::sync::clock::ServerId;
 use slirc_proto::transport::ZeroCopyTransportEnum;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::net::TcpStream;
-use tokio::sync::mpsc;
 use tokio_rustls::TlsAcceptor;
 use tokio_rustls::server::TlsStream;
 use tokio_tungstenite::WebSocketStream;
@@ -66,6 +67,10 @@ pub struct Connection {
     starttls_acceptor: Option<TlsAcceptor>,
     /// Data for initiating a server connection.
     initiator_data: Option<InitiatorData>,
+    /// Listener the connection was accepted on.
+    listener: Option<Arc<ListenConfig>>,
+    /// IRCv3 subprotocol negotiated in the WebSocket handshake.
+    subprotocol: Option<IrcSubprotocol>,
 }
 
 fn certfp_from_transport(transport: &ZeroCopyTransportEnum) -> Option<String> {
@@ -111,6 +116,8 @@ impl Connection {
             db,
             starttls_acceptor,
             initiator_data: None,
+            listener: None,
+            subprotocol: None,
         }
     }
 
@@ -136,6 +143,8 @@ impl Connection {
             db,
             starttls_acceptor: None, // Already TLS, no STARTTLS needed
             initiator_data: None,
+            listener: None,
+            subprotocol: None,
         }
     }
 
@@ -157,9 +166,26 @@ impl Connection {
             db,
             starttls_acceptor: None, // WebSocket doesn't support STARTTLS
             initiator_data: None,
+            listener: None,
+            subprotocol: None,
         }
     }
 
+    /// Record the WebSocket subprotocol; `binary.ircv3.net` clients get
+    /// binary frames.
+    pub fn with_subprotocol(mut self, subprotocol: Option<IrcSubprotocol>) -> Self {
+        self.transport
+            .set_binary_frames(subprotocol == Some(IrcSubprotocol::Binary));
+        self.subprotocol = subprotocol;
+        self
+    }
+
+    /// Tag the connection with the listener it was accepted on.
+    pub fn with_listener(mut self, listener: Arc<ListenConfig>) -> Self {
+        self.listener = Some(listener);
+        self
+    }
+
     /// Run the connection lifecycle.
     #[instrument(skip(self), fields(uid = %self.uid, addr = %self.addr), name = "connection")]
     pub async fn run(mut self) -> anyhow::Result<()> {
@@ -179,15 +205,17 @@ impl Connection {
             server = %self.matrix.server_info.name,
             tls = %is_tls,
             websocket = %is_websocket,
+            subprotocol = self.subprotocol.map(IrcSubprotocol::as_str),
             "Client connected"
         );
 
         // Channel for outgoing messages during handshake
-        let (handshake_tx, mut handshake_rx) = mpsc::channel::<Arc<Message>>(64);
+        let (handshake_tx, mut handshake_rx) = SendQ::bounded(64);
 
         // Unregistered state for this connection
         let mut unreg_state = UnregisteredState {
             initiator_data: self.initiator_data,
+            listener: self.listener,
             session_id: Uuid::new_v4(),
             ..Default::default()
         };
@@ -198,6 +226,12 @@ impl Connection {
             unreg_state.certfp = certfp_from_transport(&self.transport);
         }
 
+        // Outgoing server links need no hostname, Tor clients get a fixed one
+        let tor = unreg_state.listener.as_ref().is_some_and(|l| l.tor);
+        if unreg_state.initiator_data.is_none() && !tor {
+            unreg_state.rdns = ReverseDns::spawn_lookup(&self.matrix, self.addr.ip());
+        }
+
         // Track unregistered connection count for LUSERS
         self.matrix.user_manager.increment_unregistered();
 
@@ -242,6 +276,15 @@ impl Connection {
                 // If this is a bouncer reattachment, use the effective_uid for routing
                 let routing_uid = effective_uid.as_ref().unwrap_or(&self.uid);
 
+                // The listener's connection class sets the send queue limit
+                let class = find_class(
+                    &self.matrix.hot_config.read().classes,
+                    unreg_state
+                        .listener
+                        .as_ref()
+                        .and_then(|l| l.class.as_deref()),
+                );
+
                 // Convert the state for Phase 2.
                 let mut reg_state = match unreg_state.try_register() {
                     Ok(state) => state,
@@ -258,7 +301,7 @@ impl Connection {
                 };
 
                 // Phase 2: Unified Event Loop
-                let (outgoing_tx, mut outgoing_rx) = mpsc::channel::<Arc<Message>>(32);
+                let (outgoing_tx, mut outgoing_rx) = SendQ::for_class(&class);
                 // Register session-aware sender with capabilities for per-session fanout
                 self.matrix.register_session_sender(
                     routing_uid,
@@ -1,11 +1,10 @@
 use super::context::ConnectionContext;
 use crate::handlers::batch::process_batch_message;
 use crate::handlers::{Context, ResponseMiddleware};
-use crate::state::ServerState;
+use crate::state::{SendQ, ServerState};
 use slirc_proto::sync::clock::ServerId;
 use slirc_proto::{Command, Message};
 use std::sync::Arc;
-use tokio::sync::mpsc;
 use tracing::{debug, info, warn};
 
 /// Run Phase 2: Server sync loop (post-registration).
@@ -92,7 +91,7 @@ pub async fn run_server_loop(
     matrix.sync_manager.send_burst(&sid, matrix).await;
 
     let mut state = server_state;
-    let (reply_tx, mut reply_rx) = mpsc::channel(100);
+    let (reply_tx, mut reply_rx) = SendQ::bounded(100);
 
     loop {
         // Result type for the select - pure data, no I/O inside select
@@ -1,7 +1,9 @@
-//! Gateway - TCP/TLS listener that accepts incoming connections.
+//! Gateway - TCP/TLS listeners that accept incoming connections.
 //!
-//! The Gateway binds to sockets and spawns Connection tasks for each
-//! incoming client. Supports both plaintext and TLS connections.
+//! The Gateway binds one socket per `[[listen]]` block and spawns Connection
+//! tasks for each incoming client, tagged with the listener's policy.
+//! Listeners may be plaintext, TLS, WebSocket or server-only, and REHASH
+//! opens and closes them without a restart.
 
 use crate::config::{ClientAuth, ListenConfig, TlsConfig, WebSocketConfig};
 use crate::db::Database;
@@ -10,10 +12,13 @@ use crate::network::Connection;
 use crate::network::proxy_protocol::parse_proxy_header;
 use crate::state::Matrix;
 use rustls_pemfile::{certs, pkcs8_private_keys};
+use slirc_proto::IrcSubprotocol;
+use std::collections::HashMap;
 use std::io::{BufReader, Cursor};
 use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot, watch};
 use tokio_rustls::TlsAcceptor;
 use tokio_rustls::rustls::RootCertStore;
 use tokio_rustls::rustls::ServerConfig;
@@ -23,11 +28,19 @@ use tokio_rustls::rustls::version::{TLS12, TLS13};
 use tokio_tungstenite::accept_hdr_async;
 use tracing::{error, info, instrument, warn};
 
+/// How long a proxy gets to send its PROXY header.
+const PROXY_HEADER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
 /// Validate an incoming connection against IP deny list and rate limits.
 ///
 /// Returns `Some(uid)` if the connection should proceed, `None` if rejected.
 /// This centralizes the common accept logic for all listener types (TLS, WebSocket, plaintext).
-fn validate_connection(addr: &SocketAddr, matrix: &Matrix, listener_type: &str) -> Option<String> {
+fn validate_connection(
+    addr: &SocketAddr,
+    matrix: &Matrix,
+    listen: &ListenConfig,
+) -> Option<String> {
+    let listener_type = listen.kind();
     // HOT PATH: Nanosecond-scale IP denial check (Roaring Bitmap)
     // This runs BEFORE any other checks for maximum efficiency
     if let Ok(deny_list) = matrix.security_manager.ip_deny_list.read()
@@ -37,11 +50,13 @@ fn validate_connection(addr: &SocketAddr, matrix: &Matrix, listener_type: &str)
         return None;
     }
 
-    // Check connection rate limit before accepting
-    if !matrix
-        .security_manager
-        .rate_limiter
-        .check_connection_rate(addr.ip())
+    // Check connection rate limit before accepting; Tor listeners share
+    // their own limit instead
+    if !listen.tor
+        && !matrix
+            .security_manager
+            .rate_limiter
+            .check_connection_rate(addr.ip())
     {
         warn!(%addr, "{} connection rate limit exceeded - rejecting", listener_type);
         return None;
@@ -51,6 +66,16 @@ fn validate_connection(addr: &SocketAddr, matrix: &Matrix, listener_type: &str)
     Some(matrix.user_manager.uid_gen.next())
 }
 
+/// Release the connection slot taken in the accept loop.
+fn end_connection(matrix: &Matrix, listen: &ListenConfig, ip: IpAddr) {
+    let rate_limiter = &matrix.security_manager.rate_limiter;
+    if listen.tor {
+        rate_limiter.on_tor_connection_end();
+    } else {
+        rate_limiter.on_connection_end(ip);
+    }
+}
+
 /// Check DNSBL and return false if connection should be rejected.
 async fn check_dnsbl(matrix: &Matrix, ip: IpAddr, addr: SocketAddr) -> bool {
     if let Some(ref spam_lock) = matrix.security_manager.spam_detector {
@@ -63,36 +88,47 @@ async fn check_dnsbl(matrix: &Matrix, ip: IpAddr, addr: SocketAddr) -> bool {
     true
 }
 
+/// What every accept loop needs to start connections.
+#[derive(Clone)]
+struct AcceptContext {
+    matrix: Arc<Matrix>,
+    registry: Arc<Registry>,
+    db: Database,
+    /// Acceptor built from `[tls]`, for TLS listeners and STARTTLS.
+    tls_acceptor: Option<TlsAcceptor>,
+    /// Allowed origins from `[websocket]`.
+    allow_origins: Arc<Vec<String>>,
+}
+
 /// Handle TLS connection after acceptance.
 async fn handle_tls_connection(
     uid: String,
     stream: TcpStream,
     addr: SocketAddr,
     acceptor: TlsAcceptor,
-    matrix: Arc<Matrix>,
-    registry: Arc<Registry>,
-    db: Database,
+    listener: Arc<ListenConfig>,
+    ctx: AcceptContext,
 ) {
-    let ip = addr.ip();
-
-    if !check_dnsbl(&matrix, ip, addr).await {
-        matrix.security_manager.rate_limiter.on_connection_end(ip);
-        return;
-    }
+    let matrix = ctx.matrix;
 
     match acceptor.accept(stream).await {
         Ok(tls_stream) => {
-            let connection =
-                Connection::new_tls(uid.clone(), tls_stream, addr, matrix.clone(), registry, db);
+            let connection = Connection::new_tls(
+                uid.clone(),
+                tls_stream,
+                addr,
+                matrix.clone(),
+                ctx.registry,
+                ctx.db,
+            )
+            .with_listener(listener);
             if let Err(e) = connection.run().await {
                 error!(%uid, %addr, error = %e, "TLS connection error");
             }
-            matrix.security_manager.rate_limiter.on_connection_end(ip);
             info!(uid = %uid, address = %addr, "TLS connection closed");
         }
         Err(e) => {
             warn!(%addr, error = %e, "TLS handshake failed");
-            matrix.security_manager.rate_limiter.on_connection_end(ip);
         }
     }
 }
@@ -102,43 +138,48 @@ async fn handle_websocket_connection(
     uid: String,
     stream: TcpStream,
     addr: SocketAddr,
-    allowed: Vec<String>,
-    matrix: Arc<Matrix>,
-    registry: Arc<Registry>,
-    db: Database,
+    listener: Arc<ListenConfig>,
+    ctx: AcceptContext,
 ) {
-    let ip = addr.ip();
-
-    if !check_dnsbl(&matrix, ip, addr).await {
-        matrix.security_manager.rate_limiter.on_connection_end(ip);
-        return;
-    }
-
-    // CORS validation callback for WebSocket handshake
-    let cors_callback = |req: &http::Request<()>, response: http::Response<()>| {
-        validate_websocket_cors(req, response, &allowed, addr)
+    let matrix = ctx.matrix;
+
+    // CORS validation and IRCv3 subprotocol negotiation for the handshake
+    let allowed = ctx.allow_origins;
+    let mut subprotocol = None;
+    #[allow(clippy::result_large_err)]
+    let callback = |req: &http::Request<()>, response: http::Response<()>| {
+        let mut response = validate_websocket_cors(req, response, &allowed, addr)?;
+        subprotocol = IrcSubprotocol::negotiate(req);
+        if let Some(protocol) = subprotocol {
+            response.headers_mut().insert(
+                "Sec-WebSocket-Protocol",
+                http::HeaderValue::from_static(protocol.as_str()),
+            );
+        }
+        Ok(response)
     };
 
-    match accept_hdr_async(stream, cors_callback).await {
+    let handshake = accept_hdr_async(stream, callback).await;
+    match handshake {
         Ok(ws_stream) => {
-            info!(%addr, "WebSocket handshake successful");
+            info!(%addr, subprotocol = subprotocol.map(IrcSubprotocol::as_str), "WebSocket handshake successful");
             let connection = Connection::new_websocket(
                 uid.clone(),
                 ws_stream,
                 addr,
                 matrix.clone(),
-                registry,
-                db,
-            );
+                ctx.registry,
+                ctx.db,
+            )
+            .with_subprotocol(subprotocol)
+            .with_listener(listener);
             if let Err(e) = connection.run().await {
                 error!(%uid, %addr, error = %e, "WebSocket connection error");
             }
-            matrix.security_manager.rate_limiter.on_connection_end(ip);
             info!(uid = %uid, address = %addr, "WebSocket connection closed");
         }
         Err(e) => {
             warn!(%addr, error = %e, "WebSocket handshake failed");
-            matrix.security_manager.rate_limiter.on_connection_end(ip);
         }
     }
 }
@@ -184,86 +225,254 @@ fn validate_websocket_cors(
 
 /// Handle plaintext connection after acceptance.
 ///
-/// If `starttls_acceptor` is provided, the connection can upgrade to TLS
-/// via STARTTLS before registration completes.
+/// If `[tls]` is configured, the connection can upgrade to TLS via STARTTLS
+/// before registration completes.
 async fn handle_plaintext_connection(
     uid: String,
     stream: TcpStream,
     addr: SocketAddr,
-    matrix: Arc<Matrix>,
-    registry: Arc<Registry>,
-    db: Database,
-    starttls_acceptor: Option<TlsAcceptor>,
+    listener: Arc<ListenConfig>,
+    ctx: AcceptContext,
 ) {
-    let ip = addr.ip();
-
-    if !check_dnsbl(&matrix, ip, addr).await {
-        matrix.security_manager.rate_limiter.on_connection_end(ip);
-        return;
-    }
+    let matrix = ctx.matrix;
 
     let connection = Connection::new_plaintext(
         uid.clone(),
         stream,
         addr,
         matrix.clone(),
-        registry,
-        db,
-        starttls_acceptor,
-    );
+        ctx.registry,
+        ctx.db,
+        ctx.tls_acceptor,
+    )
+    .with_listener(listener);
     if let Err(e) = connection.run().await {
         error!(%uid, %addr, error = %e, "Plaintext connection error");
     }
-    matrix.security_manager.rate_limiter.on_connection_end(ip);
     info!(uid = %uid, address = %addr, "Plaintext connection closed");
 }
 
+/// Accept connections on one listener until its policy sender is dropped.
+///
+/// The policy is read again for every connection, so REHASH can change a
+/// listener's options without closing its socket.
+async fn accept_loop(
+    listener: TcpListener,
+    mut policy: watch::Receiver<Arc<ListenConfig>>,
+    ctx: AcceptContext,
+) {
+    loop {
+        tokio::select! {
+            result = listener.accept() => {
+                let Ok((mut stream, mut addr)) = result.inspect_err(|e| {
+                    error!(error = %e, "Failed to accept connection");
+                }) else { continue };
+
+                let listen = policy.borrow().clone();
+                let ctx = ctx.clone();
+
+                tokio::spawn(async move {
+                    // Handle PROXY protocol if enabled
+                    if listen.proxy_protocol {
+                        if !listen.trusts_proxy(addr.ip()) {
+                            warn!(%addr, "PROXY protocol: source not in proxy_trusted, rejecting");
+                            return;
+                        }
+                        let header = tokio::time::timeout(
+                            PROXY_HEADER_TIMEOUT,
+                            parse_proxy_header(&mut stream),
+                        );
+                        match header.await {
+                            Ok(Ok(Some(real_addr))) => {
+                                info!(%addr, %real_addr, "PROXY protocol: client address resolved");
+                                addr = real_addr;
+                            }
+                            Ok(Ok(None)) => {
+                                info!(%addr, "PROXY protocol: local connection from proxy");
+                            }
+                            Ok(Err(e)) => {
+                                warn!(%addr, error = %e, "PROXY protocol handshake failed");
+                                return;
+                            }
+                            Err(_) => {
+                                warn!(%addr, "PROXY protocol header timed out");
+                                return;
+                            }
+                        }
+                    }
+
+                    let Some(uid) = validate_connection(&addr, &ctx.matrix, &listen) else {
+                        return;
+                    };
+
+                    let rate_limiter = &ctx.matrix.security_manager.rate_limiter;
+                    let admitted = if listen.tor {
+                        let tor = ctx.matrix.hot_config.read().tor.clone();
+                        rate_limiter.on_tor_connection_start(&tor)
+                    } else {
+                        rate_limiter.on_connection_start(addr.ip())
+                    };
+                    if !admitted {
+                        warn!(%addr, "Connection rejected: max connections exceeded");
+                        return;
+                    }
+
+                    // Tor clients all arrive from the local daemon; DNSBL says
+                    // nothing about them
+                    let matrix = ctx.matrix.clone();
+                    if listen.tor || check_dnsbl(&matrix, addr.ip(), addr).await {
+                        let listener = listen.clone();
+                        if listen.websocket {
+                            handle_websocket_connection(uid, stream, addr, listener, ctx).await;
+                        } else if listen.tls {
+                            // TLS listeners are only bound once `[tls]` has loaded
+                            if let Some(acceptor) = ctx.tls_acceptor.clone() {
+                                handle_tls_connection(uid, stream, addr, acceptor, listener, ctx)
+                                    .await;
+                            }
+                        } else {
+                            handle_plaintext_connection(uid, stream, addr, listener, ctx).await;
+                        }
+                    }
+                    end_connection(&matrix, &listen, addr.ip());
+                });
+            }
+            changed = policy.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    let listen = policy.borrow().clone();
+    info!(address = %listen.address, kind = listen.kind(), "Listener closed");
+}
+
+/// Start the accept loop for a bound listener; dropping the returned
+/// sender closes it.
+fn spawn_listener(
+    listener: TcpListener,
+    listen: ListenConfig,
+    ctx: &AcceptContext,
+) -> watch::Sender<Arc<ListenConfig>> {
+    let (policy_tx, policy_rx) = watch::channel(Arc::new(listen));
+    tokio::spawn(accept_loop(listener, policy_rx, ctx.clone()));
+    policy_tx
+}
+
+/// Bind the socket for one listener.
+async fn bind_listener(listen: &ListenConfig) -> anyhow::Result<TcpListener> {
+    let listener = TcpListener::bind(listen.address)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}: {}", listen.address, e))?;
+    info!(address = %listen.address, kind = listen.kind(), "Listener bound");
+    Ok(listener)
+}
+
+/// Bring the running listeners in line with `listeners`.
+///
+/// Addresses that stay keep their socket and pick up the new options;
+/// removed addresses stop accepting (their connections stay up) and new
+/// ones are bound. Fails with the listeners that could not be opened.
+async fn apply_listeners(
+    running: &mut HashMap<SocketAddr, watch::Sender<Arc<ListenConfig>>>,
+    listeners: Vec<ListenConfig>,
+    ctx: &AcceptContext,
+) -> Result<String, String> {
+    let before = running.len();
+    running.retain(|address, _| listeners.iter().any(|l| &l.address == address));
+    let closed = before - running.len();
+
+    let (mut bound, mut changed) = (0, 0);
+    let mut errors = Vec::new();
+    for listen in listeners {
+        if listen.tls && ctx.tls_acceptor.is_none() {
+            errors.push(format!(
+                "{}: tls = true needs [tls] at startup",
+                listen.address
+            ));
+            continue;
+        }
+        if let Some(policy) = running.get(&listen.address) {
+            if **policy.borrow() != listen {
+                policy.send_replace(Arc::new(listen));
+                changed += 1;
+            }
+            continue;
+        }
+        match bind_listener(&listen).await {
+            Ok(listener) => {
+                running.insert(listen.address, spawn_listener(listener, listen, ctx));
+                bound += 1;
+            }
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+
+    let summary = format!("{bound} bound, {changed} changed, {closed} closed");
+    if errors.is_empty() {
+        Ok(summary)
+    } else {
+        Err(format!("{}; {}", errors.join("; "), summary))
+    }
+}
+
+/// A new listener set for the running gateway, sent by REHASH.
+pub struct ListenerReload {
+    /// Every listener that should be open.
+    pub listeners: Vec<ListenConfig>,
+    /// What changed, or the listeners that could not be opened.
+    pub reply: oneshot::Sender<Result<String, String>>,
+}
+
 /// The Gateway accepts incoming TCP/TLS connections and spawns handlers.
 pub struct Gateway {
-    plaintext_listener: TcpListener,
-    plaintext_proxy_protocol: bool,
-    tls_listener: Option<(TcpListener, TlsAcceptor, bool)>,
-    websocket_listener: Option<(TcpListener, WebSocketConfig)>,
+    listeners: Vec<(TcpListener, ListenConfig)>,
+    tls_acceptor: Option<TlsAcceptor>,
+    allow_origins: Vec<String>,
+    reload_rx: mpsc::Receiver<ListenerReload>,
     pub matrix: Arc<Matrix>,
     pub registry: Arc<Registry>,
     pub db: Database,
 }
 
 impl Gateway {
-    /// Bind the gateway to the specified addresses.
+    /// Bind every listener. `[tls]` supplies certificates for TLS listeners
+    /// and STARTTLS; `[websocket]` supplies the allowed origins.
     pub async fn bind(
-        listen_config: ListenConfig,
+        listeners: Vec<ListenConfig>,
         tls_config: Option<TlsConfig>,
         websocket_config: Option<WebSocketConfig>,
         matrix: Arc<Matrix>,
         registry: Arc<Registry>,
         db: Database,
     ) -> anyhow::Result<Self> {
-        let plaintext_listener = TcpListener::bind(listen_config.address).await?;
-        info!(address = %listen_config.address, "Plaintext listener bound");
-
-        let tls_listener = if let Some(tls_cfg) = tls_config {
-            let tls_acceptor = Self::load_tls(&tls_cfg).await?;
-            let listener = TcpListener::bind(tls_cfg.address).await?;
-            info!(address = %tls_cfg.address, "TLS listener bound");
-            Some((listener, tls_acceptor, tls_cfg.proxy_protocol))
-        } else {
-            None
+        let tls_acceptor = match &tls_config {
+            Some(tls_cfg) => Some(Self::load_tls(tls_cfg).await?),
+            None => None,
         };
 
-        let websocket_listener = if let Some(ws_cfg) = websocket_config {
-            let listener = TcpListener::bind(ws_cfg.address).await?;
-            info!(address = %ws_cfg.address, "WebSocket listener bound");
-            Some((listener, ws_cfg))
-        } else {
-            None
-        };
+        let mut bound = Vec::with_capacity(listeners.len());
+        for listen in listeners {
+            if listen.tls && tls_acceptor.is_none() {
+                anyhow::bail!("{}: tls = true needs a [tls] section", listen.address);
+            }
+            bound.push((bind_listener(&listen).await?, listen));
+        }
+
+        let (reload_tx, reload_rx) = mpsc::channel(1);
+        if matrix.listener_reload.set(reload_tx).is_err() {
+            warn!("Gateway already bound for this server; REHASH will not reach this one");
+        }
 
         Ok(Self {
-            plaintext_listener,
-            plaintext_proxy_protocol: listen_config.proxy_protocol,
-            tls_listener,
-            websocket_listener,
+            listeners: bound,
+            tls_acceptor,
+            allow_origins: websocket_config
+                .map(|ws_cfg| ws_cfg.allow_origins)
+                .unwrap_or_default(),
+            reload_rx,
             matrix,
             registry,
             db,
@@ -365,206 +574,31 @@ impl Gateway {
         Ok(TlsAcceptor::from(Arc::new(tls_config)))
     }
 
-    /// Run the gateway, accepting connections forever.
+    /// Run the gateway: one accept loop per listener, re-bound on REHASH.
     /// Returns when a shutdown signal is received.
     #[instrument(skip(self), name = "gateway")]
-    pub async fn run(self) -> anyhow::Result<()> {
-        let matrix = Arc::clone(&self.matrix);
-        let registry = Arc::clone(&self.registry);
-        let db = self.db.clone();
+    pub async fn run(mut self) -> anyhow::Result<()> {
+        let ctx = AcceptContext {
+            matrix: Arc::clone(&self.matrix),
+            registry: Arc::clone(&self.registry),
+            db: self.db.clone(),
+            tls_acceptor: self.tls_acceptor.clone(),
+            allow_origins: Arc::new(std::mem::take(&mut self.allow_origins)),
+        };
 
         // Subscribe to shutdown signal
-        let mut shutdown_rx = matrix.lifecycle_manager.shutdown_tx.subscribe();
-
-        // Extract TLS acceptor for STARTTLS on plaintext connections
-        // Clone it before the TLS listener task takes ownership
-        let starttls_acceptor = self.tls_listener.as_ref().map(|(_, a, _)| a.clone());
-
-        // If TLS is configured, spawn a separate task for the TLS listener
-        if let Some((tls_listener, tls_acceptor, proxy_protocol)) = self.tls_listener {
-            let matrix_tls = Arc::clone(&matrix);
-            let registry_tls = Arc::clone(&registry);
-            let db_tls = db.clone();
-            let mut shutdown_rx_tls = matrix.lifecycle_manager.shutdown_tx.subscribe();
-
-            tokio::spawn(async move {
-                loop {
-                    tokio::select! {
-                        result = tls_listener.accept() => {
-                            let Ok((mut stream, mut addr)) = result.inspect_err(|e| {
-                                error!(error = %e, "Failed to accept TLS connection");
-                            }) else { continue };
-
-                            // Clone Arcs for the new task
-                            let matrix = Arc::clone(&matrix_tls);
-                            let registry = Arc::clone(&registry_tls);
-                            let db = db_tls.clone();
-                            let tls_acceptor = tls_acceptor.clone();
-
-                            tokio::spawn(async move {
-                                // Handle PROXY protocol if enabled
-                                if proxy_protocol {
-                                    match parse_proxy_header(&mut stream).await {
-                                        Ok(real_addr) => {
-                                            info!(%addr, %real_addr, "PROXY protocol: client address resolved");
-                                            addr = real_addr;
-                                        }
-                                        Err(e) => {
-                                            warn!(%addr, error = %e, "PROXY protocol handshake failed");
-                                            return;
-                                        }
-                                    }
-                                }
-
-                                let Some(uid) = validate_connection(&addr, &matrix, "TLS") else {
-                                    return;
-                                };
-
-                                if !matrix.security_manager.rate_limiter.on_connection_start(addr.ip()) {
-                                    warn!(%addr, "Connection rejected: max connections per IP exceeded");
-                                    return;
-                                }
-
-                                handle_tls_connection(
-                                    uid,
-                                    stream,
-                                    addr,
-                                    tls_acceptor,
-                                    matrix,
-                                    registry,
-                                    db,
-                                ).await;
-                            });
-                        }
-                        _ = shutdown_rx_tls.recv() => {
-                            info!("Shutdown signal received - stopping TLS listener");
-                            break;
-                        }
-                    }
-                }
-            });
-        }
+        let mut shutdown_rx = self.matrix.lifecycle_manager.shutdown_tx.subscribe();
 
-        // If WebSocket is configured, spawn a separate task for the WebSocket listener
-        if let Some((ws_listener, ws_config)) = self.websocket_listener {
-            let matrix_ws = Arc::clone(&matrix);
-            let registry_ws = Arc::clone(&registry);
-            let db_ws = db.clone();
-            let allow_origins = ws_config.allow_origins;
-            let proxy_protocol = ws_config.proxy_protocol;
-            let mut shutdown_rx_ws = matrix.lifecycle_manager.shutdown_tx.subscribe();
-
-            tokio::spawn(async move {
-                loop {
-                    tokio::select! {
-                        result = ws_listener.accept() => {
-                            let Ok((mut stream, mut addr)) = result.inspect_err(|e| {
-                                error!(error = %e, "Failed to accept WebSocket connection");
-                            }) else { continue };
-
-                            // Clone Arcs for the new task
-                            let matrix = Arc::clone(&matrix_ws);
-                            let registry = Arc::clone(&registry_ws);
-                            let db = db_ws.clone();
-                            let allow_origins = allow_origins.clone();
-
-                            tokio::spawn(async move {
-                                // Handle PROXY protocol if enabled
-                                if proxy_protocol {
-                                    match parse_proxy_header(&mut stream).await {
-                                        Ok(real_addr) => {
-                                            info!(%addr, %real_addr, "PROXY protocol: client address resolved");
-                                            addr = real_addr;
-                                        }
-                                        Err(e) => {
-                                            warn!(%addr, error = %e, "PROXY protocol handshake failed");
-                                            return;
-                                        }
-                                    }
-                                }
-
-                                let Some(uid) = validate_connection(&addr, &matrix, "WebSocket") else {
-                                    return;
-                                };
-
-                                if !matrix.security_manager.rate_limiter.on_connection_start(addr.ip()) {
-                                    warn!(%addr, "Connection rejected: max connections per IP exceeded");
-                                    return;
-                                }
-
-                                handle_websocket_connection(
-                                    uid,
-                                    stream,
-                                    addr,
-                                    allow_origins,
-                                    matrix,
-                                    registry,
-                                    db,
-                                ).await;
-                            });
-                        }
-                        _ = shutdown_rx_ws.recv() => {
-                            info!("Shutdown signal received - stopping WebSocket listener");
-                            break;
-                        }
-                    }
-                }
-            });
+        let mut running = HashMap::new();
+        for (listener, listen) in std::mem::take(&mut self.listeners) {
+            running.insert(listen.address, spawn_listener(listener, listen, &ctx));
         }
 
-        // Main plaintext listener loop
         loop {
             tokio::select! {
-                // Handle incoming connections
-                result = self.plaintext_listener.accept() => {
-                    let Ok((mut stream, mut addr)) = result else {
-                        if let Err(e) = result {
-                            error!(error = %e, "Failed to accept plaintext connection");
-                        }
-                        continue;
-                    };
-
-                    // Clone Arcs for the new task
-                    let matrix = Arc::clone(&matrix);
-                    let registry = Arc::clone(&registry);
-                    let db = self.db.clone();
-                    let starttls_acceptor = starttls_acceptor.clone();
-                    let proxy_protocol = self.plaintext_proxy_protocol;
-
-                    tokio::spawn(async move {
-                        // Handle PROXY protocol if enabled
-                        if proxy_protocol {
-                            match parse_proxy_header(&mut stream).await {
-                                Ok(real_addr) => {
-                                    info!(%addr, %real_addr, "PROXY protocol: client address resolved");
-                                    addr = real_addr;
-                                }
-                                Err(e) => {
-                                    warn!(%addr, error = %e, "PROXY protocol handshake failed");
-                                    return;
-                                }
-                            }
-                        }
-
-                        let Some(uid) = validate_connection(&addr, &matrix, "Plaintext") else {
-                            return;
-                        };
-
-                        if !matrix.security_manager.rate_limiter.on_connection_start(addr.ip()) {
-                            warn!(%addr, "Connection rejected: max connections per IP exceeded");
-                            return;
-                        }
-
-                        handle_plaintext_connection(
-                            uid,
-                            stream,
-                            addr,
-                            matrix,
-                            registry,
-                            db,
-                            starttls_acceptor,
-                        ).await;
-                    });
+                Some(reload) = self.reload_rx.recv() => {
+                    let outcome = apply_listeners(&mut running, reload.listeners, &ctx).await;
+                    let _ = reload.reply.send(outcome);
                 }
                 // Handle shutdown signal
                 _ = shutdown_rx.recv() => {
@@ -574,6 +608,8 @@ impl Gateway {
             }
         }
 
+        // Dropping the policy senders stops every accept loop
+        drop(running);
         Ok(())
     }
 }
@@ -5,6 +5,8 @@
 mod connection;
 mod gateway;
 mod proxy_protocol;
+mod rdns;
 
 pub use connection::Connection;
-pub use gateway::Gateway;
+pub use gateway::{Gateway, ListenerReload};
+pub use rdns::ReverseDns;
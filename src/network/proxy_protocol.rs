@@ -16,8 +16,9 @@ const MAX_HEADER_LEN: usize = 256;
 /// Parse PROXY protocol header from the stream.
 ///
 /// This function reads from the stream to parse the header.
-/// It consumes the header bytes.
-pub async fn parse_proxy_header(stream: &mut TcpStream) -> Result<SocketAddr> {
+/// It consumes the header bytes. Returns `None` for v2 LOCAL and v1
+/// UNKNOWN headers (proxy health checks), where the socket address stands.
+pub async fn parse_proxy_header(stream: &mut TcpStream) -> Result<Option<SocketAddr>> {
     // Peek first few bytes to determine version
     let mut buf = [0u8; 16];
     let n = stream.peek(&mut buf).await?;
@@ -46,7 +47,7 @@ pub async fn parse_proxy_header(stream: &mut TcpStream) -> Result<SocketAddr> {
     bail!("Invalid PROXY protocol header signature");
 }
 
-async fn parse_v1(stream: &mut TcpStream) -> Result<SocketAddr> {
+async fn parse_v1(stream: &mut TcpStream) -> Result<Option<SocketAddr>> {
     // v1 is text-based, terminated by \r\n.
     // We need to read byte by byte until \r\n, but we can't buffer too much
     // because we can't put back bytes into TcpStream for the next handler.
@@ -75,6 +76,11 @@ async fn parse_v1(stream: &mut TcpStream) -> Result<SocketAddr> {
     let header = String::from_utf8(line)?;
     let parts: Vec<&str> = header.trim().split(' ').collect();
 
+    // PROXY UNKNOWN may omit the addresses entirely
+    if parts.get(1) == Some(&"UNKNOWN") {
+        return Ok(None);
+    }
+
     // PROXY TCP4 255.255.255.255 255.255.255.255 65535 65535
     if parts.len() < 6 {
         bail!("Invalid PROXY v1 header format");
@@ -93,10 +99,10 @@ async fn parse_v1(stream: &mut TcpStream) -> Result<SocketAddr> {
     let ip: IpAddr = src_ip.parse()?;
     let port: u16 = src_port.parse()?;
 
-    Ok(SocketAddr::new(ip, port))
+    Ok(Some(SocketAddr::new(ip, port)))
 }
 
-async fn parse_v2(stream: &mut TcpStream) -> Result<SocketAddr> {
+async fn parse_v2(stream: &mut TcpStream) -> Result<Option<SocketAddr>> {
     // Read signature (12 bytes)
     let mut sig = [0u8; 12];
     stream.read_exact(&mut sig).await?;
@@ -128,13 +134,8 @@ async fn parse_v2(stream: &mut TcpStream) -> Result<SocketAddr> {
         let mut skip = vec![0u8; len as usize];
         stream.read_exact(&mut skip).await?;
 
-        // Return error to signal "use local address"?
-        // Or we should return the local address of the socket?
-        // The caller has the original socket address.
-        // If we return error, the connection is rejected.
-        // We should probably return a special error or handle this.
-        // For now, let's bail, as we expect PROXY to provide real IP.
-        bail!("PROXY LOCAL command not supported yet");
+        // The proxy is speaking for itself; the caller keeps the socket address
+        return Ok(None);
     }
 
     if cmd != 1 {
@@ -167,7 +168,7 @@ async fn parse_v2(stream: &mut TcpStream) -> Result<SocketAddr> {
             // dst_ip at 4..8
             let src_port = u16::from_be_bytes([data[8], data[9]]);
             // dst_port at 10..12
-            Ok(SocketAddr::new(IpAddr::V4(src_ip), src_port))
+            Ok(Some(SocketAddr::new(IpAddr::V4(src_ip), src_port)))
         }
         2 => {
             // AF_INET6 (IPv6)
@@ -180,8 +181,54 @@ async fn parse_v2(stream: &mut TcpStream) -> Result<SocketAddr> {
             // dst_ip at 16..32
             let src_port = u16::from_be_bytes([data[32], data[33]]);
             // dst_port at 34..36
-            Ok(SocketAddr::new(IpAddr::V6(src_ip), src_port))
+            Ok(Some(SocketAddr::new(IpAddr::V6(src_ip), src_port)))
         }
+        // AF_UNSPEC: no address carried
+        0 => Ok(None),
         _ => bail!("Unsupported address family: {}", family),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    /// Send `header` followed by an IRC line and parse it on the far side.
+    async fn parse(header: &[u8]) -> (Result<Option<SocketAddr>>, Vec<u8>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+        client.write_all(header).await.unwrap();
+        client.write_all(b"NICK a\r\n").await.unwrap();
+
+        let result = parse_proxy_header(&mut server).await;
+        let mut rest = [0u8; 8];
+        let n = server.read(&mut rest).await.unwrap();
+        (result, rest[..n].to_vec())
+    }
+
+    #[tokio::test]
+    async fn v1_header_gives_client_address() {
+        let (result, rest) = parse(b"PROXY TCP4 198.51.100.7 10.0.0.1 40000 6667\r\n").await;
+        assert_eq!(result.unwrap(), Some("198.51.100.7:40000".parse().unwrap()));
+        assert_eq!(rest, b"NICK a\r\n");
+
+        let (result, _) = parse(b"PROXY UNKNOWN\r\n").await;
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn v2_local_keeps_socket_address() {
+        let mut header = vec![
+            0x0D, 0x0A, 0x0D, 0x0A, 0x00, 0x0D, 0x0A, 0x51, 0x55, 0x49, 0x54, 0x0A,
+        ];
+        header.extend_from_slice(&[0x20, 0x00, 0x00, 0x00]);
+        let (result, rest) = parse(&header).await;
+        assert_eq!(result.unwrap(), None);
+        assert_eq!(rest, b"NICK a\r\n");
+    }
+}
@@ -0,0 +1,109 @@
+//! Reverse DNS for connecting clients.
+//!
+//! A PTR name is only used if it resolves back to the client's IP
+//! (forward-confirmed rDNS); otherwise the client keeps its IP as host.
+//! Results, failures included, are cached so reconnects don't wait again.
+
+use crate::config::RdnsConfig;
+use crate::handlers::is_valid_hostname;
+use crate::state::Matrix;
+use dashmap::DashMap;
+use hickory_resolver::TokioResolver;
+use hickory_resolver::config::ResolverConfig;
+use hickory_resolver::name_server::TokioConnectionProvider;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+/// Cache entries beyond which expired ones are swept on insert.
+const CACHE_SWEEP_THRESHOLD: usize = 10_000;
+
+/// Forward-confirmed reverse DNS resolver with a TTL cache.
+pub struct ReverseDns {
+    resolver: TokioResolver,
+    timeout: Duration,
+    cache_ttl: Duration,
+    /// IP -> confirmed hostname (`None` if there is none) and expiry.
+    cache: DashMap<IpAddr, (Option<String>, Instant)>,
+}
+
+impl ReverseDns {
+    /// Create a resolver using the system DNS configuration.
+    pub fn new(config: &RdnsConfig) -> Self {
+        let resolver = TokioResolver::builder_tokio()
+            .map(|b| b.build())
+            .unwrap_or_else(|_| {
+                TokioResolver::builder_with_config(
+                    ResolverConfig::default(),
+                    TokioConnectionProvider::default(),
+                )
+                .build()
+            });
+        Self {
+            resolver,
+            timeout: Duration::from_millis(config.timeout_ms),
+            cache_ttl: Duration::from_secs(config.cache_ttl_secs),
+            cache: DashMap::new(),
+        }
+    }
+
+    /// Start looking up `ip` in the background; `None` when rDNS is off.
+    ///
+    /// Registration awaits the handle, so the lookup overlaps NICK/USER/CAP.
+    pub fn spawn_lookup(matrix: &Arc<Matrix>, ip: IpAddr) -> Option<JoinHandle<Option<String>>> {
+        matrix.rdns.as_ref()?;
+        let matrix = Arc::clone(matrix);
+        Some(tokio::spawn(async move {
+            matrix.rdns.as_ref()?.lookup(ip).await
+        }))
+    }
+
+    /// Look up the forward-confirmed hostname for `ip`.
+    ///
+    /// Returns `None` if there is no PTR record, it does not resolve back to
+    /// `ip`, or the lookups take longer than the configured timeout.
+    pub async fn lookup(&self, ip: IpAddr) -> Option<String> {
+        let now = Instant::now();
+        if let Some(entry) = self.cache.get(&ip)
+            && entry.1 > now
+        {
+            return entry.0.clone();
+        }
+
+        let host = tokio::time::timeout(self.timeout, self.resolve(ip))
+            .await
+            .unwrap_or_else(|_| {
+                debug!(%ip, "Reverse DNS timed out");
+                None
+            });
+
+        if self.cache.len() >= CACHE_SWEEP_THRESHOLD {
+            self.cache.retain(|_, (_, expires)| *expires > now);
+        }
+        self.cache.insert(ip, (host.clone(), now + self.cache_ttl));
+        host
+    }
+
+    async fn resolve(&self, ip: IpAddr) -> Option<String> {
+        let names = self.resolver.reverse_lookup(ip).await.ok()?;
+        for ptr in names.iter() {
+            let host = ptr.0.to_utf8();
+            let host = host.trim_end_matches('.');
+            // An address-shaped PTR would trivially "confirm" itself
+            if host.parse::<IpAddr>().is_ok() || !is_valid_hostname(host) {
+                continue;
+            }
+            let Ok(forward) = self.resolver.lookup_ip(host).await else {
+                continue;
+            };
+            if forward.iter().any(|addr| addr.to_canonical() == ip) {
+                debug!(%ip, host, "Reverse DNS confirmed");
+                return Some(host.to_string());
+            }
+            debug!(%ip, host, "Reverse DNS did not resolve back to client");
+        }
+        None
+    }
+}
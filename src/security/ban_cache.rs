@@ -153,31 +153,45 @@ impl BanCache {
     ///
     /// Called after USER command when we have the full user@host.
     pub fn check_user_host(&self, user: &str, host: &str) -> Option<BanResult> {
+        // Check G-lines first (global)
+        if let Some(result) = self.check_gline(user, host) {
+            return Some(result);
+        }
+
         let user_host = format!("{}@{}", user, host);
 
-        // Check G-lines first (global)
-        for entry in self.glines.iter() {
+        // Check K-lines (local)
+        for entry in self.klines.iter() {
             let ban = entry.value();
             if ban.is_expired() {
                 continue;
             }
             if wildcard_match(&ban.mask, &user_host) {
                 return Some(BanResult {
-                    ban_type: BanType::GLine,
+                    ban_type: BanType::KLine,
                     reason: ban.reason.clone(),
                 });
             }
         }
 
-        // Check K-lines (local)
-        for entry in self.klines.iter() {
+        None
+    }
+
+    /// Check if a user@host matches a G-line only.
+    ///
+    /// Used for users introduced by peer servers: K-lines are local policy
+    /// and must not be applied to clients connected elsewhere.
+    pub fn check_gline(&self, user: &str, host: &str) -> Option<BanResult> {
+        let user_host = format!("{}@{}", user, host);
+
+        for entry in self.glines.iter() {
             let ban = entry.value();
             if ban.is_expired() {
                 continue;
             }
             if wildcard_match(&ban.mask, &user_host) {
                 return Some(BanResult {
-                    ban_type: BanType::KLine,
+                    ban_type: BanType::GLine,
                     reason: ban.reason.clone(),
                 });
             }
@@ -250,20 +264,6 @@ impl BanCache {
 
         removed
     }
-
-    /// Iterate over all G-lines (mask, reason, expires_at).
-    ///
-    /// Used for BURST to synchronize bans with peers.
-    pub fn iter_glines(&self) -> impl Iterator<Item = (String, String, Option<i64>)> + '_ {
-        self.glines.iter().filter_map(|entry| {
-            let ban = entry.value();
-            if ban.is_expired() {
-                None
-            } else {
-                Some((ban.mask.clone(), ban.reason.clone(), ban.expires_at))
-            }
-        })
-    }
 }
 
 #[cfg(test)]
@@ -322,4 +322,15 @@ mod tests {
         let result = cache.check_user_host("gooduser", "goodhost.com");
         assert!(result.is_none());
     }
+
+    #[test]
+    fn test_check_gline_ignores_klines() {
+        let cache = BanCache::new();
+        cache.add_kline("*@*.badhost.com".to_string(), "Bad host".to_string(), None);
+        cache.add_gline("baduser@*".to_string(), "Bad user".to_string(), None);
+
+        assert!(cache.check_gline("anyone", "server.badhost.com").is_none());
+        let result = cache.check_gline("baduser", "anyhost.com");
+        assert_eq!(result.unwrap().reason, "Bad user");
+    }
 }
@@ -170,6 +170,15 @@ pub fn cloak_hostname(hostname: &str, secret_key: &str) -> String {
     }
 }
 
+/// Cloak a connecting user's host: the resolved hostname if `host` is one,
+/// otherwise the IP.
+pub fn cloak_user_host(ip: &str, host: &str, secret_key: &str, suffix: &str) -> String {
+    match (ip.parse::<IpAddr>(), host.parse::<IpAddr>()) {
+        (Ok(addr), Ok(_)) => cloak_ip_hmac_with_suffix(&addr, secret_key, suffix),
+        _ => cloak_hostname(host, secret_key),
+    }
+}
+
 /// Check if a secret key is the insecure default.
 ///
 /// Returns `true` if the key appears to be a placeholder that should be changed.
@@ -282,6 +291,16 @@ mod tests {
         assert!(cloak.ends_with(".hidden"));
     }
 
+    #[test]
+    fn test_cloak_user_host_prefers_hostname() {
+        let by_host = cloak_user_host("192.0.2.10", "dsl-10.example.net", TEST_SECRET, "ip");
+        assert_eq!(by_host, cloak_hostname("dsl-10.example.net", TEST_SECRET));
+
+        let ip: IpAddr = "192.0.2.10".parse().unwrap();
+        let by_ip = cloak_user_host("192.0.2.10", "192.0.2.10", TEST_SECRET, "ip");
+        assert_eq!(by_ip, cloak_ip_hmac_with_suffix(&ip, TEST_SECRET, "ip"));
+    }
+
     #[test]
     fn test_custom_suffix() {
         let ip: IpAddr = "10.0.0.1".parse().unwrap();
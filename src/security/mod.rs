@@ -7,6 +7,7 @@
 //! - **Rate Limiting**: Governor-based flood protection for messages, connections, joins
 //! - **Extended Bans**: Pattern matching beyond nick!user@host for channel bans
 //! - **Spam Detection**: Multi-layer content analysis for spam prevention
+//! - **Oper CHALLENGE**: Ed25519/RSA signature checks for passwordless OPER
 //!
 //! # Architecture
 //!
@@ -21,6 +22,7 @@
 //! ```
 
 pub mod ban_cache;
+pub mod challenge;
 pub mod cloaking;
 pub mod heuristics;
 pub mod ip_deny;
@@ -84,6 +86,7 @@ mod tests {
             sasl_mechanism: None,
             is_registered: true,
             is_tls: false,
+            banned_in: Vec::new(),
         }
     }
 
@@ -231,4 +234,32 @@ mod tests {
         let mask = "$c:#other";
         assert!(!matches_ban_or_except(mask, "", &ctx));
     }
+
+    #[test]
+    fn matches_ban_extended_join_match() {
+        let mut ctx = test_user_context();
+        assert!(!matches_ban_or_except("$j:#spam", "", &ctx));
+        ctx.banned_in = vec!["#spam".to_string()];
+        assert!(matches_ban_or_except("$j:#Spam", "", &ctx));
+        assert!(!matches_ban_or_except("$j:#other", "", &ctx));
+    }
+
+    #[test]
+    fn matches_ban_extended_full_mask() {
+        let ctx = test_user_context();
+        assert!(matches_ban_or_except("$x:Test*!*@*#*Real*", "", &ctx));
+        assert!(!matches_ban_or_except("$x:Test*!*@*#*bot*", "", &ctx));
+        assert!(!matches_ban_or_except("$x:Other!*@*#*", "", &ctx));
+    }
+
+    #[test]
+    fn matches_ban_extended_negation() {
+        let mut ctx = test_user_context();
+        assert!(!matches_ban_or_except("$~a:*", "", &ctx));
+        assert!(matches_ban_or_except("$~a:other*", "", &ctx));
+        assert!(matches_ban_or_except("$~U", "", &ctx));
+        ctx.account = None;
+        assert!(matches_ban_or_except("$~a:*", "", &ctx));
+        assert!(!matches_ban_or_except("$~~a:*", "", &ctx));
+    }
 }
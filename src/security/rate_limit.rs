@@ -3,7 +3,12 @@
 //! Provides governor-based rate limiting for:
 //! - Message rate per client
 //! - Connection rate per IP
+//! - Concurrent connections per IP, with per-range overrides (session limits)
+//! - Connection burst and concurrent connections shared by Tor listeners
 //! - Channel join rate per client
+//! - JOIN/PART channel hopping per client, with escalating responses
+//! - Error replies per connection (`ErrorReplyLimiter`)
+//! - Pre-registration commands, CAP REQ, and SASL per connection (`HandshakeLimiter`)
 //!
 //! # Architecture
 //!
@@ -17,14 +22,17 @@
 //! to remove the oldest entries rather than clearing all entries. This
 //! preserves rate limiting state for active clients.
 
-use crate::config::RateLimitConfig;
+use crate::config::{RateLimitConfig, TorConfig};
 use dashmap::DashMap;
+use governor::clock::{Clock, DefaultClock};
 use governor::{Quota, RateLimiter as GovRateLimiter};
+use ipnet::IpNet;
+use std::collections::HashMap;
 use std::net::IpAddr;
 use std::num::NonZeroU32;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tracing::debug;
 
 // Safe NonZeroU32 constants - these are compile-time verified non-zero values
@@ -44,6 +52,14 @@ const NZ_5: NonZeroU32 = match NonZeroU32::new(5) {
     Some(v) => v,
     None => panic!("5 is non-zero"),
 };
+const NZ_10: NonZeroU32 = match NonZeroU32::new(10) {
+    Some(v) => v,
+    None => panic!("10 is non-zero"),
+};
+const NZ_12: NonZeroU32 = match NonZeroU32::new(12) {
+    Some(v) => v,
+    None => panic!("12 is non-zero"),
+};
 
 /// Type alias for governor's direct rate limiter.
 type DirectRateLimiter = governor::DefaultDirectRateLimiter;
@@ -104,14 +120,25 @@ pub struct RateLimitManager {
     connection_limiters: DashMap<IpAddr, TimedLimiter>,
     /// Per-client channel join rate limiters.
     join_limiters: DashMap<Uid, TimedLimiter>,
+    /// Per-client JOIN/PART channel-hopping state.
+    join_part: DashMap<Uid, JoinPartState>,
     /// Per-client CTCP rate limiters.
     ctcp_limiters: DashMap<Uid, TimedLimiter>,
     /// Per-client WHOIS rate limiters.
     whois_limiters: DashMap<Uid, TimedLimiter>,
     /// Active connection counters per IP.
     active_connections: DashMap<IpAddr, u32>,
-    /// Configuration values.
-    config: Arc<RateLimitConfig>,
+    /// Session limit overrides: range -> (max connections, expiry timestamp).
+    session_limits: DashMap<IpNet, (u32, Option<i64>)>,
+    /// Per-client message rates that differ from the configured one.
+    message_rate_overrides: DashMap<Uid, u32>,
+    /// Connection limiter shared by all Tor listeners, with the burst it was
+    /// built for.
+    tor_connection_limiter: parking_lot::Mutex<Option<(u32, TimedLimiter)>>,
+    /// Active connections on Tor listeners.
+    tor_connections: AtomicU32,
+    /// Configuration values, replaced by REHASH.
+    config: parking_lot::RwLock<Arc<RateLimitConfig>>,
 }
 
 impl RateLimitManager {
@@ -121,20 +148,43 @@ impl RateLimitManager {
             message_limiters: DashMap::new(),
             connection_limiters: DashMap::new(),
             join_limiters: DashMap::new(),
+            join_part: DashMap::new(),
             ctcp_limiters: DashMap::new(),
             whois_limiters: DashMap::new(),
             active_connections: DashMap::new(),
-            config: Arc::new(config),
+            session_limits: DashMap::new(),
+            message_rate_overrides: DashMap::new(),
+            tor_connection_limiter: parking_lot::Mutex::new(None),
+            tor_connections: AtomicU32::new(0),
+            config: parking_lot::RwLock::new(Arc::new(config)),
         }
     }
 
+    /// The current configuration.
+    fn config(&self) -> Arc<RateLimitConfig> {
+        self.config.read().clone()
+    }
+
+    /// Replace the configuration (REHASH).
+    ///
+    /// Rate buckets are rebuilt from the new values on next use; connection
+    /// counts, session limits and JOIN/PART strikes are kept.
+    pub fn set_config(&self, config: RateLimitConfig) {
+        *self.config.write() = Arc::new(config);
+        self.message_limiters.clear();
+        self.connection_limiters.clear();
+        self.join_limiters.clear();
+        self.ctcp_limiters.clear();
+        self.whois_limiters.clear();
+    }
+
     /// Check if an IP address is exempt from rate limiting.
     ///
     /// Exempt IPs bypass all rate limits and connection limits.
     /// Use sparingly for trusted operators/bots only.
     pub fn is_exempt(&self, ip: IpAddr) -> bool {
         let ip_str = ip.to_string();
-        self.config.exempt_ips.contains(&ip_str)
+        self.config().exempt_ips.contains(&ip_str)
     }
 
     /// Check if a client can send a message.
@@ -142,7 +192,11 @@ impl RateLimitManager {
     /// Returns `true` if allowed, `false` if rate limited.
     pub fn check_message_rate(&self, uid: &Uid) -> bool {
         let entry = self.message_limiters.entry(uid.clone()).or_insert_with(|| {
-            let rate = NonZeroU32::new(self.config.message_rate_per_second).unwrap_or(NZ_2);
+            let rate = self
+                .message_rate_overrides
+                .get(uid)
+                .map_or(self.config().message_rate_per_second, |rate| *rate);
+            let rate = NonZeroU32::new(rate).unwrap_or(NZ_2);
             TimedLimiter::new(GovRateLimiter::direct(Quota::per_second(rate)))
         });
 
@@ -153,6 +207,12 @@ impl RateLimitManager {
         allowed
     }
 
+    /// Give a client its own message rate (Tor clients), kept across REHASH.
+    pub fn set_message_rate(&self, uid: &Uid, rate: u32) {
+        self.message_rate_overrides.insert(uid.clone(), rate);
+        self.message_limiters.remove(uid);
+    }
+
     /// Check if an IP can make a new connection.
     ///
     /// Returns `true` if allowed, `false` if rate limited.
@@ -164,7 +224,7 @@ impl RateLimitManager {
         }
 
         let entry = self.connection_limiters.entry(ip).or_insert_with(|| {
-            let burst = NonZeroU32::new(self.config.connection_burst_per_ip).unwrap_or(NZ_3);
+            let burst = NonZeroU32::new(self.config().connection_burst_per_ip).unwrap_or(NZ_3);
             // 1 connection per 10 seconds with burst
             TimedLimiter::new(GovRateLimiter::direct(
                 Quota::per_second(NZ_1).allow_burst(burst),
@@ -183,7 +243,7 @@ impl RateLimitManager {
     /// Returns `true` if allowed, `false` if rate limited.
     pub fn check_join_rate(&self, uid: &Uid) -> bool {
         let entry = self.join_limiters.entry(uid.clone()).or_insert_with(|| {
-            let burst = NonZeroU32::new(self.config.join_burst_per_client).unwrap_or(NZ_5);
+            let burst = NonZeroU32::new(self.config().join_burst_per_client).unwrap_or(NZ_5);
             // 1 join per second with burst
             TimedLimiter::new(GovRateLimiter::direct(
                 Quota::per_second(NZ_1).allow_burst(burst),
@@ -197,15 +257,37 @@ impl RateLimitManager {
         allowed
     }
 
+    /// Count a JOIN or PART against the client's channel-hopping limit.
+    ///
+    /// Going over the limit first delays the command, then locks the client
+    /// out of joining, then disconnects it. Each answer other than
+    /// `Allowed` is counted in `irc_join_part_flood_total`.
+    pub fn check_join_part(&self, uid: &Uid) -> JoinPartVerdict {
+        let config = self.config();
+        let mut state = self.join_part.entry(uid.clone()).or_insert_with(|| {
+            let rate = NonZeroU32::new(config.join_part_rate_per_minute).unwrap_or(NZ_12);
+            let burst = NonZeroU32::new(config.join_part_burst).unwrap_or(NZ_10);
+            JoinPartState::new(GovRateLimiter::direct(
+                Quota::per_minute(rate).allow_burst(burst),
+            ))
+        });
+
+        let verdict = state.check(&config, Instant::now());
+        if verdict != JoinPartVerdict::Allowed {
+            debug!(uid = %uid, strikes = state.strikes, verdict = ?verdict, "join/part flood");
+            crate::metrics::inc_join_part_flood(verdict.label());
+        }
+        verdict
+    }
+
     /// Check if a client can send a CTCP message.
     pub fn check_ctcp_rate(&self, uid: &Uid) -> bool {
         let entry = self.ctcp_limiters.entry(uid.clone()).or_insert_with(|| {
-            let burst = NonZeroU32::new(self.config.ctcp_burst_per_client).unwrap_or(NZ_2);
+            let config = self.config();
+            let burst = NonZeroU32::new(config.ctcp_burst_per_client).unwrap_or(NZ_2);
             TimedLimiter::new(GovRateLimiter::direct(
-                Quota::per_second(
-                    NonZeroU32::new(self.config.ctcp_rate_per_second).unwrap_or(NZ_1),
-                )
-                .allow_burst(burst),
+                Quota::per_second(NonZeroU32::new(config.ctcp_rate_per_second).unwrap_or(NZ_1))
+                    .allow_burst(burst),
             ))
         });
 
@@ -219,12 +301,11 @@ impl RateLimitManager {
     /// Check if a client can send a WHOIS query.
     pub fn check_whois_rate(&self, uid: &Uid) -> bool {
         let entry = self.whois_limiters.entry(uid.clone()).or_insert_with(|| {
-            let burst = NonZeroU32::new(self.config.whois_burst_per_client).unwrap_or(NZ_3);
+            let config = self.config();
+            let burst = NonZeroU32::new(config.whois_burst_per_client).unwrap_or(NZ_3);
             TimedLimiter::new(GovRateLimiter::direct(
-                Quota::per_second(
-                    NonZeroU32::new(self.config.whois_rate_per_second).unwrap_or(NZ_1),
-                )
-                .allow_burst(burst),
+                Quota::per_second(NonZeroU32::new(config.whois_rate_per_second).unwrap_or(NZ_1))
+                    .allow_burst(burst),
             ))
         });
 
@@ -235,6 +316,34 @@ impl RateLimitManager {
         allowed
     }
 
+    /// Create an error reply limiter for a new connection.
+    /// Exempt IPs get one that never limits.
+    pub fn error_reply_limiter(&self, ip: IpAddr) -> ErrorReplyLimiter {
+        let config = self.config();
+        let burst = if self.is_exempt(ip) {
+            0
+        } else {
+            config.error_reply_burst
+        };
+        ErrorReplyLimiter::new(burst, Duration::from_secs(config.error_reply_window_secs))
+    }
+
+    /// Create a handshake limiter for a new connection.
+    /// Exempt IPs get one that never limits.
+    pub fn handshake_limiter(&self, ip: IpAddr) -> HandshakeLimiter {
+        if self.is_exempt(ip) {
+            HandshakeLimiter::new(0, 0, 0, 0)
+        } else {
+            let config = self.config();
+            HandshakeLimiter::new(
+                config.max_prereg_commands,
+                config.max_cap_req,
+                config.max_sasl_attempts,
+                config.max_authenticate_bytes,
+            )
+        }
+    }
+
     /// Record that a connection has started for an IP.
     /// Returns `true` if allowed, `false` if max connections per IP exceeded.
     /// Exempt IPs always return `true` and are not tracked.
@@ -244,11 +353,12 @@ impl RateLimitManager {
             return true;
         }
 
+        let limit = self.max_sessions(ip);
         let mut allowed = true;
         self.active_connections
             .entry(ip)
             .and_modify(|count| {
-                if *count >= self.config.max_connections_per_ip {
+                if *count >= limit {
                     allowed = false;
                 } else {
                     *count += 1;
@@ -257,11 +367,52 @@ impl RateLimitManager {
             .or_insert(1);
 
         if !allowed {
-            debug!(ip = %ip, limit = self.config.max_connections_per_ip, "max connections per IP exceeded");
+            debug!(ip = %ip, limit = limit, "max connections per IP exceeded");
         }
         allowed
     }
 
+    /// Set the session limit for an IP range, replacing any existing one.
+    pub fn set_session_limit(&self, net: IpNet, max_sessions: u32, expires_at: Option<i64>) {
+        self.session_limits
+            .insert(net.trunc(), (max_sessions, expires_at));
+    }
+
+    /// Remove the session limit for an IP range.
+    pub fn remove_session_limit(&self, net: IpNet) -> bool {
+        self.session_limits.remove(&net.trunc()).is_some()
+    }
+
+    /// Connections allowed from `ip`: the most specific unexpired session
+    /// limit covering it, or the configured per-IP limit.
+    pub fn max_sessions(&self, ip: IpAddr) -> u32 {
+        let now = current_timestamp() as i64;
+        self.session_limits
+            .iter()
+            .filter(|entry| entry.key().contains(&ip))
+            .filter(|entry| entry.value().1.is_none_or(|expires| expires > now))
+            .max_by_key(|entry| entry.key().prefix_len())
+            .map(|entry| entry.value().0)
+            .unwrap_or(self.config().max_connections_per_ip)
+    }
+
+    /// IPs with at least `threshold` active connections, busiest first.
+    pub fn sessions_over(&self, threshold: u32) -> Vec<(IpAddr, u32)> {
+        let mut sessions: Vec<_> = self
+            .active_connections
+            .iter()
+            .filter(|entry| *entry.value() >= threshold)
+            .map(|entry| (*entry.key(), *entry.value()))
+            .collect();
+        sessions.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        sessions
+    }
+
+    /// Active connections from `ip`.
+    pub fn session_count(&self, ip: IpAddr) -> u32 {
+        self.active_connections.get(&ip).map_or(0, |count| *count)
+    }
+
     /// Record that a connection has ended for an IP.
     /// Does nothing for exempt IPs (they aren't tracked).
     pub fn on_connection_end(&self, ip: IpAddr) {
@@ -290,14 +441,80 @@ impl RateLimitManager {
         }
     }
 
+    /// Admit a connection on a Tor listener.
+    ///
+    /// Tor clients all arrive from the local Tor daemon, so instead of the
+    /// per-IP limits they share one connection burst and one cap on
+    /// concurrent connections. Pair with [`Self::on_tor_connection_end`].
+    pub fn on_tor_connection_start(&self, tor: &TorConfig) -> bool {
+        let burst_ok = {
+            let mut limiter = self.tor_connection_limiter.lock();
+            if limiter
+                .as_ref()
+                .is_none_or(|(burst, _)| *burst != tor.connection_burst)
+            {
+                let burst = NonZeroU32::new(tor.connection_burst).unwrap_or(NZ_3);
+                let quota = Quota::per_second(NZ_1).allow_burst(burst);
+                *limiter = Some((
+                    tor.connection_burst,
+                    TimedLimiter::new(GovRateLimiter::direct(quota)),
+                ));
+            }
+            limiter.as_ref().is_some_and(|(_, l)| l.check())
+        };
+        if !burst_ok {
+            debug!("Tor connection rate limit exceeded");
+            return false;
+        }
+
+        let admitted = self
+            .tor_connections
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < tor.max_connections).then_some(n + 1)
+            })
+            .is_ok();
+        if !admitted {
+            debug!(max = tor.max_connections, "Tor connection limit reached");
+        }
+        admitted
+    }
+
+    /// Release a connection admitted by [`Self::on_tor_connection_start`].
+    pub fn on_tor_connection_end(&self) {
+        let _ = self
+            .tor_connections
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+    }
+
     /// Remove a client from all rate limiters (on disconnect).
     pub fn remove_client(&self, uid: &Uid) {
+        self.message_rate_overrides.remove(uid);
         self.message_limiters.remove(uid);
         self.join_limiters.remove(uid);
+        self.join_part.remove(uid);
         self.ctcp_limiters.remove(uid);
         self.whois_limiters.remove(uid);
     }
 
+    /// Seconds since each per-client limiter was last used (oper DEBUG).
+    ///
+    /// Limiters the client has not touched yet are omitted.
+    pub fn client_buckets(&self, uid: &Uid) -> Vec<(&'static str, u64)> {
+        let now = current_timestamp();
+        [
+            ("message", &self.message_limiters),
+            ("join", &self.join_limiters),
+            ("ctcp", &self.ctcp_limiters),
+            ("whois", &self.whois_limiters),
+        ]
+        .into_iter()
+        .filter_map(|(name, limiters)| {
+            let last = limiters.get(uid)?.last_access.load(Ordering::Relaxed);
+            Some((name, now.saturating_sub(last)))
+        })
+        .collect()
+    }
+
     /// Cleanup old entries to prevent memory growth using LRU eviction.
     ///
     /// Called every 5 minutes by background task in main.rs.
@@ -311,6 +528,13 @@ impl RateLimitManager {
         self.evict_lru_uid_entries(&self.ctcp_limiters, "ctcp");
         self.evict_lru_uid_entries(&self.whois_limiters, "whois");
 
+        // Join/part state only matters while a client is hopping; once it
+        // has been quiet for a lockout period it would start over anyway
+        let idle = self.config().join_part_lockout_secs;
+        let now = current_timestamp();
+        self.join_part
+            .retain(|_, state| now.saturating_sub(state.last_access) < idle);
+
         // Active connections use simple count, not limiters - just log if large
         if self.active_connections.len() > MAX_ENTRIES {
             debug!(
@@ -400,6 +624,311 @@ impl Default for RateLimitManager {
     }
 }
 
+// =============================================================================
+// JOIN/PART Flood Limiting
+// =============================================================================
+
+/// How to treat a JOIN or PART under the channel-hopping limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinPartVerdict {
+    /// Within the limit.
+    Allowed,
+    /// Over the limit: process the command after waiting this long.
+    Delay(Duration),
+    /// Refuse joins for this much longer.
+    Locked(Duration),
+    /// Too many violations: disconnect the client.
+    Disconnect,
+}
+
+impl JoinPartVerdict {
+    /// Short name used as the metric label.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Allowed => "allowed",
+            Self::Delay(_) => "delay",
+            Self::Locked(_) => "lockout",
+            Self::Disconnect => "disconnect",
+        }
+    }
+}
+
+/// Per-client JOIN/PART token bucket and strike count.
+///
+/// Every command refused by the bucket, or made while locked out, is a
+/// strike. The first `join_part_delay_strikes` strikes only delay the
+/// command, the next one starts a lockout, and reaching
+/// `join_part_disconnect_strikes` disconnects. Strikes are forgiven after
+/// `join_part_lockout_secs` without one.
+#[derive(Debug)]
+struct JoinPartState {
+    limiter: DirectRateLimiter,
+    strikes: u32,
+    last_strike: Instant,
+    locked_until: Option<Instant>,
+    last_access: u64,
+}
+
+impl JoinPartState {
+    fn new(limiter: DirectRateLimiter) -> Self {
+        Self {
+            limiter,
+            strikes: 0,
+            last_strike: Instant::now(),
+            locked_until: None,
+            last_access: current_timestamp(),
+        }
+    }
+
+    fn check(&mut self, config: &RateLimitConfig, now: Instant) -> JoinPartVerdict {
+        self.last_access = current_timestamp();
+        let lockout = Duration::from_secs(config.join_part_lockout_secs);
+
+        if let Some(until) = self.locked_until {
+            if now < until {
+                return if self.strike(config, now) {
+                    JoinPartVerdict::Disconnect
+                } else {
+                    JoinPartVerdict::Locked(until - now)
+                };
+            }
+            self.locked_until = None;
+        }
+        if self.strikes > 0 && now.duration_since(self.last_strike) >= lockout {
+            self.strikes = 0;
+        }
+
+        let Err(not_until) = self.limiter.check() else {
+            return JoinPartVerdict::Allowed;
+        };
+        if self.strike(config, now) {
+            JoinPartVerdict::Disconnect
+        } else if self.strikes <= config.join_part_delay_strikes {
+            JoinPartVerdict::Delay(not_until.wait_time_from(DefaultClock::default().now()))
+        } else {
+            self.locked_until = Some(now + lockout);
+            JoinPartVerdict::Locked(lockout)
+        }
+    }
+
+    /// Record a strike; returns true if the client should be disconnected.
+    fn strike(&mut self, config: &RateLimitConfig, now: Instant) -> bool {
+        self.strikes += 1;
+        self.last_strike = now;
+        config.join_part_disconnect_strikes != 0
+            && self.strikes >= config.join_part_disconnect_strikes
+    }
+}
+
+// =============================================================================
+// Error Reply Limiting
+// =============================================================================
+
+/// Per-connection limiter for error numerics.
+///
+/// A client spamming bad commands would otherwise get one error line per
+/// input. Each identical error reply (same numeric and parameters) may be
+/// sent `burst` times per window; the rest are dropped and counted. Owned by
+/// the connection task, so no locking.
+#[derive(Debug)]
+pub struct ErrorReplyLimiter {
+    burst: u32,
+    window: Duration,
+    windows: HashMap<(u16, Vec<String>), ErrorWindow>,
+}
+
+/// Distinct error replies tracked before expired windows are pruned.
+const MAX_ERROR_WINDOWS: usize = 64;
+
+#[derive(Debug)]
+struct ErrorWindow {
+    started: Instant,
+    sent: u32,
+    suppressed: u32,
+}
+
+impl ErrorReplyLimiter {
+    /// Create a limiter; a `burst` of 0 disables it.
+    pub fn new(burst: u32, window: Duration) -> Self {
+        Self {
+            burst,
+            window,
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Check whether a message may be sent to the client.
+    ///
+    /// Only error numerics are limited; everything else is allowed.
+    pub fn allow(&mut self, msg: &slirc_proto::Message) -> bool {
+        let slirc_proto::Command::Response(response, params) = &msg.command else {
+            return true;
+        };
+        if self.burst == 0 || !response.is_error() {
+            return true;
+        }
+
+        let code = response.code();
+        let now = Instant::now();
+        if self.windows.len() >= MAX_ERROR_WINDOWS {
+            let period = self.window;
+            self.windows
+                .retain(|_, w| now.duration_since(w.started) < period);
+        }
+        let key = (code, params.clone());
+        let window = self.windows.entry(key).or_insert(ErrorWindow {
+            started: now,
+            sent: 0,
+            suppressed: 0,
+        });
+        if now.duration_since(window.started) >= self.window {
+            if window.suppressed > 0 {
+                debug!(
+                    numeric = code,
+                    suppressed = window.suppressed,
+                    "error replies suppressed"
+                );
+            }
+            *window = ErrorWindow {
+                started: now,
+                sent: 0,
+                suppressed: 0,
+            };
+        }
+
+        if window.sent < self.burst {
+            window.sent += 1;
+            true
+        } else {
+            window.suppressed += 1;
+            crate::metrics::inc_error_replies_suppressed(code);
+            false
+        }
+    }
+}
+
+// =============================================================================
+// Handshake Limiting
+// =============================================================================
+
+/// A pre-registration limit a connection has exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandshakeLimit {
+    /// Too many commands before registration.
+    Commands,
+    /// Too many `CAP REQ` lines.
+    CapReq,
+    /// Too many SASL attempts.
+    SaslAttempts,
+    /// Too many `AUTHENTICATE` payload bytes.
+    AuthenticateBytes,
+}
+
+impl HandshakeLimit {
+    /// Short name used as the metric label.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Commands => "commands",
+            Self::CapReq => "cap_req",
+            Self::SaslAttempts => "sasl_attempts",
+            Self::AuthenticateBytes => "authenticate_bytes",
+        }
+    }
+
+    /// Reason given to the client in the closing `ERROR`.
+    pub fn reason(self) -> &'static str {
+        match self {
+            Self::Commands => "Too many commands before registration",
+            Self::CapReq => "Too many CAP requests",
+            Self::SaslAttempts => "Too many SASL attempts",
+            Self::AuthenticateBytes => "AUTHENTICATE data too long",
+        }
+    }
+}
+
+/// Per-connection counters for the registration handshake.
+///
+/// An unregistered socket could otherwise renegotiate capabilities or
+/// restart SASL until the registration timeout, costing CPU on every line.
+/// Each counter has a hard limit; a limit of 0 disables it. Owned by the
+/// connection task, so no locking.
+#[derive(Debug)]
+pub struct HandshakeLimiter {
+    max_commands: u32,
+    max_cap_req: u32,
+    max_sasl_attempts: u32,
+    max_authenticate_bytes: u32,
+    commands: u32,
+    cap_req: u32,
+    sasl_attempts: u32,
+    authenticate_bytes: u32,
+}
+
+impl HandshakeLimiter {
+    /// Create a limiter; a limit of 0 disables that check.
+    pub fn new(
+        max_commands: u32,
+        max_cap_req: u32,
+        max_sasl_attempts: u32,
+        max_authenticate_bytes: u32,
+    ) -> Self {
+        Self {
+            max_commands,
+            max_cap_req,
+            max_sasl_attempts,
+            max_authenticate_bytes,
+            commands: 0,
+            cap_req: 0,
+            sasl_attempts: 0,
+            authenticate_bytes: 0,
+        }
+    }
+
+    /// Count a command received before registration.
+    ///
+    /// `sasl_idle` is whether no SASL exchange is in progress, so that an
+    /// `AUTHENTICATE` starts a new attempt rather than continuing one.
+    pub fn check(
+        &mut self,
+        msg: &slirc_proto::Message,
+        sasl_idle: bool,
+    ) -> Result<(), HandshakeLimit> {
+        fn bump(count: &mut u32, by: u32, max: u32) -> bool {
+            *count = count.saturating_add(by);
+            max != 0 && *count > max
+        }
+
+        if bump(&mut self.commands, 1, self.max_commands) {
+            return Err(HandshakeLimit::Commands);
+        }
+        match &msg.command {
+            slirc_proto::Command::CAP(_, slirc_proto::CapSubCommand::REQ, _, _)
+                if bump(&mut self.cap_req, 1, self.max_cap_req) =>
+            {
+                Err(HandshakeLimit::CapReq)
+            }
+            slirc_proto::Command::AUTHENTICATE(data) => {
+                if sasl_idle
+                    && data != "*"
+                    && bump(&mut self.sasl_attempts, 1, self.max_sasl_attempts)
+                {
+                    return Err(HandshakeLimit::SaslAttempts);
+                }
+                let len = u32::try_from(data.len()).unwrap_or(u32::MAX);
+                if bump(
+                    &mut self.authenticate_bytes,
+                    len,
+                    self.max_authenticate_bytes,
+                ) {
+                    return Err(HandshakeLimit::AuthenticateBytes);
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 // =============================================================================
 // Server-to-Server Rate Limiting
 // =============================================================================
@@ -555,11 +1084,22 @@ mod tests {
             message_rate_per_second: 2,
             connection_burst_per_ip: 3,
             join_burst_per_client: 5,
+            join_part_burst: 10,
+            join_part_rate_per_minute: 12,
+            join_part_delay_strikes: 3,
+            join_part_lockout_secs: 60,
+            join_part_disconnect_strikes: 10,
             ctcp_rate_per_second: 1,
             ctcp_burst_per_client: 2,
             max_connections_per_ip: 3,
             whois_rate_per_second: 1,
             whois_burst_per_client: 3,
+            error_reply_burst: 5,
+            error_reply_window_secs: 10,
+            max_prereg_commands: 64,
+            max_cap_req: 16,
+            max_sasl_attempts: 5,
+            max_authenticate_bytes: 32768,
             exempt_ips: Vec::new(),
             s2s_command_rate_per_second: 100,
             s2s_burst_per_peer: 500,
@@ -587,6 +1127,27 @@ mod tests {
         assert!(manager.on_connection_start(ip));
     }
 
+    #[test]
+    fn test_session_limit_overrides_per_ip_limit() {
+        let manager = RateLimitManager::new(test_config());
+        let ip: IpAddr = "10.1.2.3".parse().unwrap();
+
+        manager.set_session_limit("10.0.0.0/8".parse().unwrap(), 1, None);
+        manager.set_session_limit("10.1.0.0/16".parse().unwrap(), 5, None);
+        assert_eq!(manager.max_sessions(ip), 5);
+
+        // Expired limits no longer apply
+        manager.set_session_limit("10.1.0.0/16".parse().unwrap(), 5, Some(1));
+        assert_eq!(manager.max_sessions(ip), 1);
+        assert!(manager.on_connection_start(ip));
+        assert!(!manager.on_connection_start(ip));
+        assert_eq!(manager.sessions_over(1), vec![(ip, 1)]);
+
+        assert!(manager.remove_session_limit("10.0.0.0/8".parse().unwrap()));
+        manager.remove_session_limit("10.1.0.0/16".parse().unwrap());
+        assert_eq!(manager.max_sessions(ip), 3);
+    }
+
     #[test]
     fn test_message_rate_limiting() {
         let manager = RateLimitManager::new(test_config());
@@ -600,6 +1161,34 @@ mod tests {
         assert!(!manager.check_message_rate(&uid));
     }
 
+    #[test]
+    fn test_tor_limits_are_shared_and_per_client() {
+        let manager = RateLimitManager::new(test_config());
+        let tor = TorConfig {
+            connection_burst: 3,
+            max_connections: 2,
+            ..TorConfig::default()
+        };
+
+        assert!(manager.on_tor_connection_start(&tor));
+        assert!(manager.on_tor_connection_start(&tor));
+        assert!(
+            !manager.on_tor_connection_start(&tor),
+            "over max_connections"
+        );
+        manager.on_tor_connection_end();
+        assert!(
+            !manager.on_tor_connection_start(&tor),
+            "burst spent by the refused attempt"
+        );
+
+        let uid = "000AAAAAB".to_string();
+        manager.set_message_rate(&uid, 1);
+        manager.set_config(test_config());
+        assert!(manager.check_message_rate(&uid));
+        assert!(!manager.check_message_rate(&uid));
+    }
+
     #[test]
     fn test_connection_rate_limiting() {
         let manager = RateLimitManager::new(test_config());
@@ -640,6 +1229,84 @@ mod tests {
         assert!(!manager.check_join_rate(&uid));
     }
 
+    #[test]
+    fn test_set_config_applies_new_rates() {
+        let manager = RateLimitManager::new(test_config());
+        let uid = "000AAAAAB".to_string();
+
+        for _ in 0..5 {
+            assert!(manager.check_join_rate(&uid));
+        }
+        assert!(!manager.check_join_rate(&uid));
+
+        manager.set_config(RateLimitConfig {
+            join_burst_per_client: 8,
+            ..test_config()
+        });
+        for _ in 0..8 {
+            assert!(manager.check_join_rate(&uid));
+        }
+        assert!(!manager.check_join_rate(&uid));
+    }
+
+    #[test]
+    fn test_join_part_escalation() {
+        let config = RateLimitConfig {
+            join_part_burst: 2,
+            join_part_delay_strikes: 1,
+            join_part_disconnect_strikes: 4,
+            ..test_config()
+        };
+        let manager = RateLimitManager::new(config);
+        let uid = "000AAAAAB".to_string();
+
+        assert_eq!(manager.check_join_part(&uid), JoinPartVerdict::Allowed);
+        assert_eq!(manager.check_join_part(&uid), JoinPartVerdict::Allowed);
+        assert!(matches!(
+            manager.check_join_part(&uid),
+            JoinPartVerdict::Delay(wait) if wait <= Duration::from_secs(5)
+        ));
+        assert_eq!(
+            manager.check_join_part(&uid),
+            JoinPartVerdict::Locked(Duration::from_secs(60))
+        );
+        assert!(matches!(
+            manager.check_join_part(&uid),
+            JoinPartVerdict::Locked(_)
+        ));
+        assert_eq!(manager.check_join_part(&uid), JoinPartVerdict::Disconnect);
+
+        // A reconnecting client starts over
+        manager.remove_client(&uid);
+        assert_eq!(manager.check_join_part(&uid), JoinPartVerdict::Allowed);
+    }
+
+    #[test]
+    fn test_join_part_strikes_forgiven() {
+        let config = RateLimitConfig {
+            join_part_delay_strikes: 1,
+            ..test_config()
+        };
+        let mut state = JoinPartState::new(GovRateLimiter::direct(
+            Quota::per_minute(NZ_12).allow_burst(NZ_1),
+        ));
+        let start = Instant::now();
+        assert_eq!(state.check(&config, start), JoinPartVerdict::Allowed);
+        assert!(matches!(
+            state.check(&config, start),
+            JoinPartVerdict::Delay(_)
+        ));
+        assert_eq!(state.strikes, 1);
+
+        // After a quiet lockout period the next strike is a first strike
+        // again, so it is only delayed
+        assert!(matches!(
+            state.check(&config, start + Duration::from_secs(61)),
+            JoinPartVerdict::Delay(_)
+        ));
+        assert_eq!(state.strikes, 1);
+    }
+
     #[test]
     fn test_whois_rate_limiting() {
         let manager = RateLimitManager::new(test_config());
@@ -687,6 +1354,130 @@ mod tests {
         assert!(manager.check_message_rate(&uid2));
     }
 
+    // === Error Reply Limiting Tests ===
+
+    fn numeric(response: slirc_proto::Response) -> slirc_proto::Message {
+        slirc_proto::Message::from(slirc_proto::Command::Response(
+            response,
+            vec!["nick".to_string(), "target".to_string()],
+        ))
+    }
+
+    #[test]
+    fn test_error_replies_coalesced_per_reply() {
+        let mut limiter = ErrorReplyLimiter::new(2, Duration::from_secs(60));
+        let nosuchnick = numeric(slirc_proto::Response::ERR_NOSUCHNICK);
+
+        assert!(limiter.allow(&nosuchnick));
+        assert!(limiter.allow(&nosuchnick));
+        assert!(!limiter.allow(&nosuchnick));
+
+        // Other numerics and other parameters have their own budget;
+        // non-errors are never limited
+        assert!(limiter.allow(&numeric(slirc_proto::Response::ERR_UNKNOWNCOMMAND)));
+        let other_target = slirc_proto::Message::from(slirc_proto::Command::Response(
+            slirc_proto::Response::ERR_NOSUCHNICK,
+            vec!["nick".to_string(), "other".to_string()],
+        ));
+        assert!(limiter.allow(&other_target));
+        assert!(limiter.allow(&numeric(slirc_proto::Response::RPL_ENDOFWHO)));
+        assert!(limiter.allow(&numeric(slirc_proto::Response::RPL_ENDOFWHO)));
+        assert!(limiter.allow(&numeric(slirc_proto::Response::RPL_ENDOFWHO)));
+    }
+
+    #[test]
+    fn test_error_replies_resume_after_window() {
+        let mut limiter = ErrorReplyLimiter::new(1, Duration::ZERO);
+        let nosuchnick = numeric(slirc_proto::Response::ERR_NOSUCHNICK);
+
+        // Every check starts a fresh window
+        assert!(limiter.allow(&nosuchnick));
+        assert!(limiter.allow(&nosuchnick));
+    }
+
+    #[test]
+    fn test_error_reply_limiter_disabled_with_zero_burst() {
+        let mut limiter = ErrorReplyLimiter::new(0, Duration::from_secs(60));
+        let nosuchnick = numeric(slirc_proto::Response::ERR_NOSUCHNICK);
+        for _ in 0..20 {
+            assert!(limiter.allow(&nosuchnick));
+        }
+    }
+
+    fn cap_req() -> slirc_proto::Message {
+        slirc_proto::Message::from(slirc_proto::Command::CAP(
+            None,
+            slirc_proto::CapSubCommand::REQ,
+            None,
+            Some("sasl".to_string()),
+        ))
+    }
+
+    fn authenticate(data: &str) -> slirc_proto::Message {
+        slirc_proto::Message::from(slirc_proto::Command::AUTHENTICATE(data.to_string()))
+    }
+
+    #[test]
+    fn test_handshake_command_limit() {
+        let mut limiter = HandshakeLimiter::new(3, 0, 0, 0);
+        let ping = slirc_proto::Message::from(slirc_proto::Command::PING("x".to_string(), None));
+        for _ in 0..3 {
+            assert_eq!(limiter.check(&ping, true), Ok(()));
+        }
+        assert_eq!(limiter.check(&ping, true), Err(HandshakeLimit::Commands));
+    }
+
+    #[test]
+    fn test_handshake_cap_req_limit() {
+        let mut limiter = HandshakeLimiter::new(0, 2, 0, 0);
+        assert_eq!(limiter.check(&cap_req(), true), Ok(()));
+        assert_eq!(limiter.check(&cap_req(), true), Ok(()));
+        assert_eq!(limiter.check(&cap_req(), true), Err(HandshakeLimit::CapReq));
+    }
+
+    #[test]
+    fn test_handshake_sasl_attempts_count_only_new_exchanges() {
+        let mut limiter = HandshakeLimiter::new(0, 0, 2, 0);
+        // Mechanism, then data mid-exchange, then an abort
+        assert_eq!(limiter.check(&authenticate("PLAIN"), true), Ok(()));
+        assert_eq!(limiter.check(&authenticate("Zm9v"), false), Ok(()));
+        assert_eq!(limiter.check(&authenticate("*"), false), Ok(()));
+        assert_eq!(limiter.check(&authenticate("*"), true), Ok(()));
+        assert_eq!(limiter.check(&authenticate("PLAIN"), true), Ok(()));
+        assert_eq!(
+            limiter.check(&authenticate("PLAIN"), true),
+            Err(HandshakeLimit::SaslAttempts)
+        );
+    }
+
+    #[test]
+    fn test_handshake_authenticate_bytes_limit() {
+        let mut limiter = HandshakeLimiter::new(0, 0, 0, 10);
+        assert_eq!(limiter.check(&authenticate("PLAIN"), true), Ok(()));
+        assert_eq!(limiter.check(&authenticate("abcde"), false), Ok(()));
+        assert_eq!(
+            limiter.check(&authenticate("f"), false),
+            Err(HandshakeLimit::AuthenticateBytes)
+        );
+    }
+
+    #[test]
+    fn test_handshake_limiter_exempt_ip_unlimited() {
+        let config = RateLimitConfig {
+            max_prereg_commands: 1,
+            exempt_ips: vec!["10.0.0.1".to_string()],
+            ..test_config()
+        };
+        let manager = RateLimitManager::new(config);
+        let mut exempt = manager.handshake_limiter("10.0.0.1".parse().unwrap());
+        let mut other = manager.handshake_limiter("10.0.0.2".parse().unwrap());
+        for _ in 0..10 {
+            assert_eq!(exempt.check(&cap_req(), true), Ok(()));
+        }
+        assert_eq!(other.check(&cap_req(), true), Ok(()));
+        assert_eq!(other.check(&cap_req(), true), Err(HandshakeLimit::Commands));
+    }
+
     // === S2S Rate Limiting Tests ===
 
     fn s2s_test_config() -> RateLimitConfig {
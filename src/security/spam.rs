@@ -26,7 +26,7 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tracing::{debug, warn};
 
-use crate::config::SecurityConfig;
+use crate::config::{RblConfig, SecurityConfig};
 use crate::db::Database;
 use crate::security::{HeuristicsEngine, RblService, ReputationManager};
 
@@ -102,13 +102,7 @@ impl SpamDetectionService {
         };
 
         // Initialize RBL service (replaces legacy dnsbl_enabled flag)
-        // The RblConfig controls whether HTTP, DNS, or both are enabled
-        let rbl_config = config.spam.rbl.clone();
-        let rbl = if rbl_config.http_enabled || rbl_config.dns_enabled {
-            Some(Arc::new(RblService::new(rbl_config)))
-        } else {
-            None
-        };
+        let rbl = Self::build_rbl(config.spam.rbl.clone());
 
         // Initialize RegexSet
         let regex_matcher = if !config.spam.regex_patterns.is_empty() {
@@ -139,6 +133,18 @@ impl SpamDetectionService {
         }
     }
 
+    /// The RBL service for `config`, if HTTP or DNS lookups are enabled.
+    fn build_rbl(config: RblConfig) -> Option<Arc<RblService>> {
+        (config.http_enabled || config.dns_enabled).then(|| Arc::new(RblService::new(config)))
+    }
+
+    /// Replace the RBL service and its lists (REHASH).
+    ///
+    /// The lookup cache starts empty, so delisted IPs are not kept out.
+    pub fn set_rbl(&mut self, config: RblConfig) {
+        self.rbl = Self::build_rbl(config);
+    }
+
     /// Check if an IP is listed in any RBL (blocklist).
     ///
     /// Uses privacy-preserving HTTP APIs by default (StopForumSpam, AbuseIPDB).
@@ -158,6 +164,14 @@ impl SpamDetectionService {
         }
     }
 
+    /// Current trust score for an IP, if reputation tracking is enabled.
+    pub async fn trust_score(&self, ip: &str) -> Option<i32> {
+        match &self.reputation {
+            Some(rep) => Some(rep.get_trust_score(ip).await),
+            None => None,
+        }
+    }
+
     /// Record a spam violation (penalize reputation).
     pub async fn record_violation(&self, ip: IpAddr, _reason: &str) {
         if let Some(rep) = &self.reputation {
@@ -11,10 +11,13 @@
 //! | `$s:` | Server name |
 //! | `$c:` | Channel membership |
 //! | `$o:` | Operator type |
-//! | `$x:` | Certificate fingerprint |
+//! | `$x:` | `nick!user@host#gecos`, or a certificate fingerprint if there is no `#` |
 //! | `$z:` | SASL mechanism |
+//! | `$j:` | Banned in another channel |
 //! | `$U` | Unregistered users |
 //!
+//! Any type can be negated with `~`: `$~a:*` matches users without an account.
+//!
 //! # Note on X-Lines (K/G/Z/D-Lines)
 //!
 //! Server-level bans are handled by [`crate::security::BanCache`] which loads
@@ -40,12 +43,16 @@ pub enum ExtendedBan {
     Oper(String),
     /// `$x:fingerprint` - Matches SSL certificate fingerprint.
     Certificate(String),
+    /// `$x:nick!user@host#gecos` - Matches hostmask and realname together.
+    FullMask { mask: String, gecos: String },
     /// `$z:mechanism` - Matches SASL authentication mechanism.
     Sasl(String),
-    /// `$j:pattern` - Matches channel join patterns.
+    /// `$j:#channel` - Matches users banned from another channel.
     Join(String),
     /// `$U` - Matches unregistered (not identified) users.
     Unregistered,
+    /// `$~<type>` - Matches users the inner ban does not match.
+    Not(Box<ExtendedBan>),
 }
 
 impl ExtendedBan {
@@ -56,6 +63,13 @@ impl ExtendedBan {
         if !ban_string.starts_with('$') {
             return None;
         }
+        if let Some(inner) = ban_string.strip_prefix("$~") {
+            // Negation doesn't nest
+            if inner.starts_with('~') {
+                return None;
+            }
+            return Self::parse(&format!("${inner}")).map(|ban| ExtendedBan::Not(Box::new(ban)));
+        }
 
         let parts: Vec<&str> = ban_string.splitn(2, ':').collect();
         if parts.len() < 2 {
@@ -75,9 +89,16 @@ impl ExtendedBan {
             "$s" => Some(ExtendedBan::Server(pattern)),
             "$c" => Some(ExtendedBan::Channel(pattern)),
             "$o" => Some(ExtendedBan::Oper(pattern)),
-            "$x" => Some(ExtendedBan::Certificate(pattern)),
+            // Hostmasks can't contain '#', so the first one starts the gecos
+            "$x" => match pattern.split_once('#') {
+                Some((mask, gecos)) => Some(ExtendedBan::FullMask {
+                    mask: mask.to_string(),
+                    gecos: gecos.to_string(),
+                }),
+                None => Some(ExtendedBan::Certificate(pattern)),
+            },
             "$z" => Some(ExtendedBan::Sasl(pattern)),
-            "$j" => Some(ExtendedBan::Join(pattern)),
+            "$j" if pattern.starts_with('#') => Some(ExtendedBan::Join(pattern)),
             _ => None,
         }
     }
@@ -114,6 +135,9 @@ pub struct UserContext {
     pub is_registered: bool,
     /// Whether the user is connected via TLS.
     pub is_tls: bool,
+    /// Channels (lowercase) the user is banned from, filled in by the
+    /// channel actor when it has `$j` bans to check.
+    pub banned_in: Vec<String>,
 }
 
 /// Parameters for creating a UserContext during registration.
@@ -159,6 +183,7 @@ impl UserContext {
             sasl_mechanism: None,
             is_registered: account.is_some(),
             is_tls,
+            banned_in: Vec::new(),
         }
     }
 }
@@ -204,11 +229,20 @@ pub fn matches_extended_ban(ban: &ExtendedBan, context: &UserContext) -> bool {
                 false
             }
         }
-        ExtendedBan::Join(pattern) => {
-            // Match against nickname for join patterns
-            wildcard_match(pattern, &context.nickname)
+        ExtendedBan::FullMask { mask, gecos } => {
+            let user_mask = format!(
+                "{}!{}@{}",
+                context.nickname, context.username, context.hostname
+            );
+            slirc_proto::matches_hostmask(mask, &user_mask)
+                && wildcard_match(gecos, &context.realname)
+        }
+        ExtendedBan::Join(channel) => {
+            let channel = slirc_proto::irc_to_lower(channel);
+            context.banned_in.contains(&channel)
         }
         ExtendedBan::Unregistered => !context.is_registered,
+        ExtendedBan::Not(inner) => !matches_extended_ban(inner, context),
     }
 }
 
@@ -231,6 +265,7 @@ mod tests {
             sasl_mechanism: Some("PLAIN".to_string()),
             is_tls: false,
             is_registered: true,
+            banned_in: Vec::new(),
         }
     }
 
@@ -250,6 +285,20 @@ mod tests {
         ));
         assert!(ExtendedBan::parse("not-a-ban").is_none());
         assert!(ExtendedBan::parse("$x").is_none()); // Missing pattern
+        assert!(matches!(
+            ExtendedBan::parse("$x:ABCDEF0123"),
+            Some(ExtendedBan::Certificate(_))
+        ));
+        assert!(matches!(
+            ExtendedBan::parse("$x:*!*@*#*bot*"),
+            Some(ExtendedBan::FullMask { .. })
+        ));
+        assert!(matches!(
+            ExtendedBan::parse("$~a:*"),
+            Some(ExtendedBan::Not(_))
+        ));
+        assert!(ExtendedBan::parse("$~~a:*").is_none());
+        assert!(ExtendedBan::parse("$j:nick").is_none());
     }
 
     #[test]
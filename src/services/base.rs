@@ -46,6 +46,14 @@ pub trait ServiceBase {
         vec![self.reply_effect(uid, text)]
     }
 
+    /// Create the reply for a write command refused during maintenance mode.
+    fn read_only_reply(&self, uid: &str) -> ServiceResult {
+        self.error_reply(
+            uid,
+            "Services are read-only while the server is in maintenance. Please try again after the restart.",
+        )
+    }
+
     /// Create an unknown command reply.
     fn unknown_command(&self, uid: &str, cmd: &str) -> ServiceResult {
         self.error_reply(
@@ -57,6 +65,24 @@ pub trait ServiceBase {
         )
     }
 
+    /// Check whether the user is an IRC operator.
+    fn is_oper(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+    ) -> impl std::future::Future<Output = bool> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            let user_arc = matrix.user_manager.users.get(uid).map(|u| u.value().clone());
+            match user_arc {
+                Some(user_arc) => user_arc.read().await.modes.oper,
+                None => false,
+            }
+        }
+    }
+
     /// Get user's account ID if identified.
     ///
     /// Returns None if user is not found, not registered, or not identified.
@@ -0,0 +1,83 @@
+//! Channel bot behaviour: greeting joining users and kicking on badwords.
+
+use crate::services::{ServiceEffect, apply_effects_no_sender};
+use crate::state::Matrix;
+use crate::state::dashmap_ext::DashMapExt;
+use slirc_proto::{Command, Message, Prefix};
+use std::sync::Arc;
+use tracing::info;
+
+/// Send the channel's greet message to a user who just joined.
+pub async fn on_join(matrix: &Arc<Matrix>, channel_lower: &str, uid: &str) {
+    let Some(assignment) = matrix
+        .service_manager
+        .botserv
+        .registry
+        .channel(channel_lower)
+    else {
+        return;
+    };
+    let Some(greet) = assignment.greet else {
+        return;
+    };
+    let Some(bot_arc) = matrix.user_manager.users.get_cloned(&assignment.bot_uid) else {
+        return;
+    };
+    let prefix = {
+        let bot = bot_arc.read().await;
+        Prefix::new(bot.nick.clone(), bot.user.clone(), bot.visible_host.clone())
+    };
+
+    let msg = Message {
+        tags: None,
+        prefix: Some(prefix),
+        command: Command::NOTICE(uid.to_string(), format!("[{}] {}", channel_lower, greet)),
+    };
+    apply_effects_no_sender(
+        matrix,
+        &assignment.bot_nick,
+        vec![ServiceEffect::Reply {
+            target_uid: uid.to_string(),
+            msg,
+        }],
+    )
+    .await;
+}
+
+/// Kick (and with KICKBAN, ban) a user who said one of the channel's badwords.
+pub async fn on_message(matrix: &Arc<Matrix>, channel_lower: &str, uid: &str, text: &str) {
+    let Some(assignment) = matrix
+        .service_manager
+        .botserv
+        .registry
+        .channel(channel_lower)
+    else {
+        return;
+    };
+    let Some(word) = assignment.find_badword(text) else {
+        return;
+    };
+    let Some(user_arc) = matrix.user_manager.users.get_cloned(uid) else {
+        return;
+    };
+    let (nick, host) = {
+        let user = user_arc.read().await;
+        (user.nick.clone(), user.visible_host.clone())
+    };
+    info!(channel = %channel_lower, nick = %nick, word = %word, "Badword kick");
+
+    let mut effects = Vec::with_capacity(2);
+    if assignment.kickban {
+        effects.push(ServiceEffect::ChannelBan {
+            channel: channel_lower.to_string(),
+            mask: format!("*!*@{}", host),
+        });
+    }
+    effects.push(ServiceEffect::Kick {
+        channel: channel_lower.to_string(),
+        target_uid: uid.to_string(),
+        kicker: assignment.bot_nick.clone(),
+        reason: "Watch your language!".to_string(),
+    });
+    apply_effects_no_sender(matrix, &assignment.bot_nick, effects).await;
+}
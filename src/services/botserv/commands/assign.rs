@@ -0,0 +1,136 @@
+//! Channel assignment: ASSIGN, UNASSIGN.
+
+use super::super::registry::ChannelBot;
+use super::{BotServ, BotServResult};
+use crate::services::ServiceEffect;
+use crate::state::Matrix;
+use slirc_proto::irc_to_lower;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+impl BotServ {
+    /// Handle ASSIGN command.
+    pub(super) async fn handle_assign(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> BotServResult {
+        let (Some(&channel_name), Some(&bot_nick)) = (args.first(), args.get(1)) else {
+            return self.error_reply(uid, "Syntax: ASSIGN #channel <bot>");
+        };
+        let record = match self.founder_channel(matrix, uid, channel_name).await {
+            Ok(record) => record,
+            Err(reply) => return reply,
+        };
+        let Some(bot) = self.registry.bot(bot_nick) else {
+            return self.error_reply(uid, &format!("No bot named \x02{}\x02.", bot_nick));
+        };
+
+        let previous = self.registry.channel(&record.name);
+        if previous
+            .as_ref()
+            .is_some_and(|assignment| assignment.bot_uid == bot.uid)
+        {
+            return self.error_reply(
+                uid,
+                &format!(
+                    "\x02{}\x02 is already in \x02{}\x02.",
+                    bot.nick, record.name
+                ),
+            );
+        }
+
+        if let Err(e) = self.db.bots().assign(record.id, bot.id, nick).await {
+            warn!(channel = %record.name, bot = %bot.nick, error = ?e, "Failed to assign bot");
+            return self.error_reply(uid, "Database error. Please try again later.");
+        }
+
+        // A replaced bot hands its settings over to the new one
+        let assignment = match &previous {
+            Some(previous) => ChannelBot {
+                bot_uid: bot.uid.clone(),
+                bot_nick: bot.nick.clone(),
+                ..previous.clone()
+            },
+            None => ChannelBot::new(&bot),
+        };
+        self.registry
+            .channels
+            .insert(irc_to_lower(&record.name), assignment);
+        info!(channel = %record.name, bot = %bot.nick, by = %nick, "Bot assigned");
+
+        let mut effects = Vec::with_capacity(4);
+        if let Some(previous) = previous {
+            effects.push(ServiceEffect::BotPart {
+                uid: previous.bot_uid,
+                channel: record.name.clone(),
+                reason: format!("Replaced by {}", bot.nick),
+            });
+        }
+        effects.push(ServiceEffect::BotJoin {
+            uid: bot.uid.clone(),
+            channel: record.name.clone(),
+        });
+        effects.push(ServiceEffect::ChannelMode {
+            channel: record.name.clone(),
+            target_uid: bot.uid,
+            mode_char: 'o',
+            adding: true,
+        });
+        effects.push(self.reply_effect(
+            uid,
+            &format!(
+                "Bot \x02{}\x02 assigned to \x02{}\x02.",
+                bot.nick, record.name
+            ),
+        ));
+        effects
+    }
+
+    /// Handle UNASSIGN command.
+    pub(super) async fn handle_unassign(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> BotServResult {
+        let Some(&channel_name) = args.first() else {
+            return self.error_reply(uid, "Syntax: UNASSIGN #channel");
+        };
+        let record = match self.founder_channel(matrix, uid, channel_name).await {
+            Ok(record) => record,
+            Err(reply) => return reply,
+        };
+        let Some(assignment) = self.registry.channel(&record.name) else {
+            return self.error_reply(
+                uid,
+                &format!("\x02{}\x02 has no bot assigned.", record.name),
+            );
+        };
+
+        if let Err(e) = self.db.bots().unassign(record.id).await {
+            warn!(channel = %record.name, error = ?e, "Failed to unassign bot");
+            return self.error_reply(uid, "Database error. Please try again later.");
+        }
+        self.registry.channels.remove(&irc_to_lower(&record.name));
+        info!(channel = %record.name, bot = %assignment.bot_nick, by = %nick, "Bot unassigned");
+
+        vec![
+            ServiceEffect::BotPart {
+                uid: assignment.bot_uid,
+                channel: record.name.clone(),
+                reason: "Unassigned".to_string(),
+            },
+            self.reply_effect(
+                uid,
+                &format!(
+                    "Bot \x02{}\x02 removed from \x02{}\x02.",
+                    assignment.bot_nick, record.name
+                ),
+            ),
+        ]
+    }
+}
@@ -0,0 +1,179 @@
+//! Bot management: BOTLIST, BOT ADD, BOT DEL.
+
+use super::super::registry::LiveBot;
+use super::{BotServ, BotServResult};
+use crate::handlers::is_valid_hostname;
+use crate::services::ServiceEffect;
+use crate::services::base::ServiceBase;
+use crate::state::Matrix;
+use slirc_proto::{NickExt, irc_to_lower};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Maximum length of a bot's username.
+const MAX_BOT_USERNAME_LEN: usize = 10;
+
+/// Returns true if the string is usable as a bot's username.
+fn is_valid_username(username: &str) -> bool {
+    !username.is_empty()
+        && username.len() <= MAX_BOT_USERNAME_LEN
+        && username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+impl BotServ {
+    /// Handle BOTLIST command.
+    pub(super) fn handle_botlist(&self, uid: &str) -> BotServResult {
+        let mut bots: Vec<LiveBot> = self.registry.bots.iter().map(|b| b.clone()).collect();
+        bots.sort_by(|a, b| a.nick.cmp(&b.nick));
+
+        let mut effects = vec![self.reply_effect(uid, "Available bots:")];
+        for bot in &bots {
+            let channels = self.registry.channels_of(&bot.uid).len();
+            effects.push(self.reply_effect(
+                uid,
+                &format!("  \x02{}\x02 (in {} channel(s))", bot.nick, channels),
+            ));
+        }
+        effects.push(self.reply_effect(uid, &format!("End of list - {} bot(s).", bots.len())));
+        effects
+    }
+
+    /// Handle BOT command (operators only).
+    pub(super) async fn handle_bot(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> BotServResult {
+        if !self.is_oper(matrix, uid).await {
+            return self.error_reply(uid, "Access denied. BOT is for operators only.");
+        }
+
+        let sub = args.first().map(|s| s.to_uppercase());
+        match sub.as_deref() {
+            Some("ADD") => self.handle_bot_add(matrix, uid, nick, &args[1..]).await,
+            Some("DEL") => self.handle_bot_del(uid, nick, args.get(1).copied()).await,
+            _ => self.error_reply(uid, "Syntax: BOT <ADD|DEL> [args]"),
+        }
+    }
+
+    /// Handle BOT ADD.
+    async fn handle_bot_add(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> BotServResult {
+        let (Some(&bot_nick), Some(&username), Some(&hostname), true) =
+            (args.first(), args.get(1), args.get(2), args.len() > 3)
+        else {
+            return self.error_reply(uid, "Syntax: BOT ADD <nick> <user> <host> <realname>");
+        };
+        let realname = args[3..].join(" ");
+
+        if !bot_nick.is_valid_nick() {
+            return self.error_reply(uid, &format!("\x02{}\x02 is not a valid nick.", bot_nick));
+        }
+        if !is_valid_username(username) {
+            return self.error_reply(
+                uid,
+                &format!(
+                    "Invalid user: use up to {} letters, digits, '-', '_' or '.'.",
+                    MAX_BOT_USERNAME_LEN
+                ),
+            );
+        }
+        if !is_valid_hostname(hostname) {
+            return self.error_reply(uid, "Invalid host: use alphanumeric, hyphens, dots only.");
+        }
+        if matrix
+            .user_manager
+            .get_first_uid(&irc_to_lower(bot_nick))
+            .is_some()
+        {
+            return self.error_reply(uid, &format!("\x02{}\x02 is in use.", bot_nick));
+        }
+        if let Ok(Some(_)) = self.db.accounts().find_by_name(bot_nick).await {
+            return self.error_reply(uid, &format!("\x02{}\x02 is a registered nick.", bot_nick));
+        }
+
+        let bot = match self
+            .db
+            .bots()
+            .create(bot_nick, username, hostname, &realname, nick)
+            .await
+        {
+            Ok(Some(bot)) => bot,
+            Ok(None) => {
+                return self.error_reply(uid, &format!("Bot \x02{}\x02 already exists.", bot_nick));
+            }
+            Err(e) => {
+                warn!(bot = %bot_nick, error = ?e, "Failed to create bot");
+                return self.error_reply(uid, "Database error. Please try again later.");
+            }
+        };
+
+        let bot_uid = matrix.user_manager.uid_gen.next();
+        self.registry.bots.insert(
+            irc_to_lower(&bot.nick),
+            LiveBot {
+                id: bot.id,
+                uid: bot_uid.clone(),
+                nick: bot.nick.clone(),
+            },
+        );
+        info!(bot = %bot.nick, by = %nick, "Bot created");
+
+        vec![
+            ServiceEffect::BotIntroduce { uid: bot_uid, bot },
+            self.reply_effect(uid, &format!("Bot \x02{}\x02 created.", bot_nick)),
+        ]
+    }
+
+    /// Handle BOT DEL.
+    async fn handle_bot_del(&self, uid: &str, nick: &str, bot_nick: Option<&str>) -> BotServResult {
+        let Some(bot_nick) = bot_nick else {
+            return self.error_reply(uid, "Syntax: BOT DEL <nick>");
+        };
+        let Some(bot) = self.registry.bot(bot_nick) else {
+            return self.error_reply(uid, &format!("No bot named \x02{}\x02.", bot_nick));
+        };
+
+        if let Err(e) = self.db.bots().delete(bot.id).await {
+            warn!(bot = %bot.nick, error = ?e, "Failed to delete bot");
+            return self.error_reply(uid, "Database error. Please try again later.");
+        }
+
+        self.registry
+            .channels
+            .retain(|_, assignment| assignment.bot_uid != bot.uid);
+        self.registry.bots.remove(&irc_to_lower(&bot.nick));
+        info!(bot = %bot.nick, by = %nick, "Bot deleted");
+
+        vec![
+            ServiceEffect::BotQuit {
+                uid: bot.uid,
+                reason: "Bot deleted".to_string(),
+            },
+            self.reply_effect(uid, &format!("Bot \x02{}\x02 deleted.", bot.nick)),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_username() {
+        assert!(is_valid_username("bot"));
+        assert!(is_valid_username("my_bot.1"));
+        assert!(!is_valid_username(""));
+        assert!(!is_valid_username("has space"));
+        assert!(!is_valid_username("waytoolongname"));
+    }
+}
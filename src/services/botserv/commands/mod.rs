@@ -0,0 +1,225 @@
+//! BotServ command handlers.
+//!
+//! Bot management lives in `bot.rs`, channel assignment in `assign.rs`, and
+//! the per-channel GREET, KICKBAN and BADWORDS settings in `set.rs`.
+
+mod assign;
+mod bot;
+mod set;
+
+use super::registry::BotRegistry;
+use crate::db::{ChannelRecord, Database};
+use crate::services::base::ServiceBase;
+use crate::services::{Service, ServiceEffect};
+use crate::state::Matrix;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Returns true if the command changes persisted state.
+fn is_write_command(command: &str, args: &[&str]) -> bool {
+    match command {
+        "ASSIGN" | "UNASSIGN" => true,
+        "BOT" => args
+            .first()
+            .is_some_and(|sub| matches!(sub.to_uppercase().as_str(), "ADD" | "DEL")),
+        "SET" => {
+            !(args
+                .get(1)
+                .is_some_and(|opt| opt.eq_ignore_ascii_case("BADWORDS"))
+                && args
+                    .get(2)
+                    .is_some_and(|sub| sub.eq_ignore_ascii_case("LIST")))
+        }
+        _ => false,
+    }
+}
+
+/// Result of a BotServ command - a list of effects to apply.
+pub type BotServResult = Vec<ServiceEffect>;
+
+/// BotServ service.
+pub struct BotServ {
+    db: Database,
+    /// Online bots and channel assignments.
+    pub(crate) registry: BotRegistry,
+}
+
+impl ServiceBase for BotServ {
+    fn service_name(&self) -> &'static str {
+        "BotServ"
+    }
+
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+#[async_trait]
+impl Service for BotServ {
+    fn name(&self) -> &'static str {
+        "BotServ"
+    }
+
+    fn aliases(&self) -> Vec<&'static str> {
+        vec!["BS"]
+    }
+
+    async fn handle(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        text: &str,
+    ) -> Vec<ServiceEffect> {
+        self.handle_command(matrix, uid, nick, text).await
+    }
+}
+
+impl BotServ {
+    /// Create a new BotServ service.
+    pub fn new(db: Database) -> Self {
+        Self {
+            db,
+            registry: BotRegistry::default(),
+        }
+    }
+
+    /// Handle a PRIVMSG to BotServ.
+    /// Returns a list of effects that the caller should apply.
+    pub async fn handle_command(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        text: &str,
+    ) -> BotServResult {
+        let parts: Vec<&str> = text.split_whitespace().collect();
+        if parts.is_empty() {
+            return self.help_reply(uid);
+        }
+
+        let command = parts[0].to_uppercase();
+        let args = &parts[1..];
+
+        if matrix.lifecycle_manager.in_maintenance() && is_write_command(&command, args) {
+            return self.read_only_reply(uid);
+        }
+
+        match command.as_str() {
+            "BOTLIST" => self.handle_botlist(uid),
+            "BOT" => self.handle_bot(matrix, uid, nick, args).await,
+            "ASSIGN" => self.handle_assign(matrix, uid, nick, args).await,
+            "UNASSIGN" => self.handle_unassign(matrix, uid, nick, args).await,
+            "SET" => self.handle_set(matrix, uid, nick, args).await,
+            "HELP" => self.help_reply(uid),
+            _ => self.unknown_command(uid, &command),
+        }
+    }
+
+    // ========== BotServ-specific helper methods ==========
+
+    /// Create a single reply effect.
+    fn reply_effect(&self, target_uid: &str, text: &str) -> ServiceEffect {
+        <Self as ServiceBase>::reply_effect(self, target_uid, text)
+    }
+
+    /// Create an error reply.
+    fn error_reply(&self, uid: &str, text: &str) -> BotServResult {
+        <Self as ServiceBase>::error_reply(self, uid, text)
+    }
+
+    /// Look up a registered channel the user is founder of.
+    ///
+    /// On failure, returns the error reply to send instead.
+    async fn founder_channel(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        channel_name: &str,
+    ) -> Result<ChannelRecord, BotServResult> {
+        let record = match self.db.channels().find_by_name(channel_name).await {
+            Ok(Some(record)) => record,
+            Ok(None) => {
+                return Err(self.error_reply(
+                    uid,
+                    &format!("Channel \x02{}\x02 is not registered.", channel_name),
+                ));
+            }
+            Err(e) => {
+                warn!(channel = %channel_name, error = ?e, "Failed to lookup channel");
+                return Err(self.error_reply(uid, "Database error. Please try again later."));
+            }
+        };
+
+        if !matrix
+            .service_manager
+            .chanserv
+            .check_founder_access(matrix, uid, &record)
+            .await
+        {
+            return Err(self.error_reply(
+                uid,
+                &format!("You must be the founder of \x02{}\x02.", record.name),
+            ));
+        }
+
+        Ok(record)
+    }
+
+    /// Create help reply.
+    fn help_reply(&self, uid: &str) -> BotServResult {
+        vec![
+            self.reply_effect(uid, "***** BotServ Help *****"),
+            self.reply_effect(
+                uid,
+                "BotServ lets channel founders put a bot in their channel.",
+            ),
+            self.reply_effect(uid, " "),
+            self.reply_effect(uid, "Available commands:"),
+            self.reply_effect(
+                uid,
+                "  BOTLIST                          - List available bots",
+            ),
+            self.reply_effect(
+                uid,
+                "  ASSIGN #channel <bot>            - Put a bot in a channel",
+            ),
+            self.reply_effect(uid, "  UNASSIGN #channel                - Remove the bot"),
+            self.reply_effect(
+                uid,
+                "  SET #channel GREET <text|OFF>    - Greet joining users",
+            ),
+            self.reply_effect(
+                uid,
+                "  SET #channel KICKBAN <ON|OFF>    - Ban on badwords too",
+            ),
+            self.reply_effect(
+                uid,
+                "  SET #channel BADWORDS ADD <word> - Kick users saying it",
+            ),
+            self.reply_effect(uid, "  SET #channel BADWORDS DEL <word> - Remove a badword"),
+            self.reply_effect(uid, "  SET #channel BADWORDS LIST       - List badwords"),
+            self.reply_effect(uid, " "),
+            self.reply_effect(uid, "Operator commands:"),
+            self.reply_effect(uid, "  BOT ADD <nick> <user> <host> <realname>"),
+            self.reply_effect(uid, "  BOT DEL <nick>"),
+            self.reply_effect(uid, "***** End of Help *****"),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_commands() {
+        assert!(is_write_command("ASSIGN", &["#chan", "Bot"]));
+        assert!(is_write_command("BOT", &["add", "Bot"]));
+        assert!(!is_write_command("BOTLIST", &[]));
+        assert!(is_write_command("SET", &["#chan", "GREET", "hi"]));
+        assert!(is_write_command("SET", &["#chan", "BADWORDS", "ADD", "x"]));
+        assert!(!is_write_command("SET", &["#chan", "badwords", "list"]));
+    }
+}
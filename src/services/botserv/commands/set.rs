@@ -0,0 +1,209 @@
+//! Per-channel bot settings: SET GREET, SET KICKBAN, SET BADWORDS.
+
+use super::{BotServ, BotServResult};
+use crate::db::ChannelRecord;
+use crate::state::Matrix;
+use slirc_proto::irc_to_lower;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Maximum number of badwords per channel.
+const MAX_BADWORDS: usize = 50;
+
+impl BotServ {
+    /// Handle SET command.
+    pub(super) async fn handle_set(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> BotServResult {
+        let (Some(&channel_name), Some(option)) = (args.first(), args.get(1)) else {
+            return self.error_reply(uid, "Syntax: SET #channel <GREET|KICKBAN|BADWORDS> [value]");
+        };
+        let record = match self.founder_channel(matrix, uid, channel_name).await {
+            Ok(record) => record,
+            Err(reply) => return reply,
+        };
+        if self.registry.channel(&record.name).is_none() {
+            return self.error_reply(
+                uid,
+                &format!(
+                    "\x02{}\x02 has no bot assigned. Use \x02ASSIGN\x02 first.",
+                    record.name
+                ),
+            );
+        }
+
+        let value = &args[2..];
+        match option.to_uppercase().as_str() {
+            "GREET" => self.set_greet(uid, nick, &record, value).await,
+            "KICKBAN" => self.set_kickban(uid, nick, &record, value).await,
+            "BADWORDS" => self.set_badwords(uid, nick, &record, value).await,
+            _ => self.error_reply(uid, &format!("Unknown option: \x02{}\x02", option)),
+        }
+    }
+
+    /// Handle SET GREET.
+    async fn set_greet(
+        &self,
+        uid: &str,
+        nick: &str,
+        record: &ChannelRecord,
+        value: &[&str],
+    ) -> BotServResult {
+        if value.is_empty() {
+            return self.error_reply(uid, "Syntax: SET #channel GREET <message|OFF>");
+        }
+        let greet = match value {
+            [off] if off.eq_ignore_ascii_case("OFF") => None,
+            _ => Some(value.join(" ")),
+        };
+
+        if let Err(e) = self.db.bots().set_greet(record.id, greet.as_deref()).await {
+            warn!(channel = %record.name, error = ?e, "Failed to set greet");
+            return self.error_reply(uid, "Database error. Please try again later.");
+        }
+        if let Some(mut assignment) = self.registry.channels.get_mut(&irc_to_lower(&record.name)) {
+            assignment.greet = greet.clone();
+        }
+        info!(channel = %record.name, by = %nick, "Bot greet changed");
+
+        let text = match greet {
+            Some(greet) => format!(
+                "Greet message for \x02{}\x02 set to: {}",
+                record.name, greet
+            ),
+            None => format!("Greet message for \x02{}\x02 removed.", record.name),
+        };
+        vec![self.reply_effect(uid, &text)]
+    }
+
+    /// Handle SET KICKBAN.
+    async fn set_kickban(
+        &self,
+        uid: &str,
+        nick: &str,
+        record: &ChannelRecord,
+        value: &[&str],
+    ) -> BotServResult {
+        let kickban = match value.first().map(|v| v.to_uppercase()).as_deref() {
+            Some("ON") => true,
+            Some("OFF") => false,
+            _ => return self.error_reply(uid, "Syntax: SET #channel KICKBAN <ON|OFF>"),
+        };
+
+        if let Err(e) = self.db.bots().set_kickban(record.id, kickban).await {
+            warn!(channel = %record.name, error = ?e, "Failed to set kickban");
+            return self.error_reply(uid, "Database error. Please try again later.");
+        }
+        if let Some(mut assignment) = self.registry.channels.get_mut(&irc_to_lower(&record.name)) {
+            assignment.kickban = kickban;
+        }
+        info!(channel = %record.name, kickban, by = %nick, "Bot kickban changed");
+
+        let text = if kickban {
+            format!(
+                "Users saying a badword in \x02{}\x02 will be banned.",
+                record.name
+            )
+        } else {
+            format!(
+                "Users saying a badword in \x02{}\x02 will be kicked.",
+                record.name
+            )
+        };
+        vec![self.reply_effect(uid, &text)]
+    }
+
+    /// Handle SET BADWORDS.
+    async fn set_badwords(
+        &self,
+        uid: &str,
+        nick: &str,
+        record: &ChannelRecord,
+        value: &[&str],
+    ) -> BotServResult {
+        let channel_lower = irc_to_lower(&record.name);
+        let sub = value.first().map(|s| s.to_uppercase());
+        match (sub.as_deref(), value.get(1)) {
+            (Some("LIST"), _) => {
+                let badwords = self
+                    .registry
+                    .channel(&record.name)
+                    .map(|assignment| assignment.badwords)
+                    .unwrap_or_default();
+                let mut effects =
+                    vec![self.reply_effect(uid, &format!("Badwords for \x02{}\x02:", record.name))];
+                for (i, word) in badwords.iter().enumerate() {
+                    effects.push(self.reply_effect(uid, &format!("  {}. {}", i + 1, word)));
+                }
+                effects.push(self.reply_effect(
+                    uid,
+                    &format!("End of list - {} badword(s).", badwords.len()),
+                ));
+                effects
+            }
+            (Some("ADD"), Some(&word)) => {
+                let count = self
+                    .registry
+                    .channel(&record.name)
+                    .map_or(0, |assignment| assignment.badwords.len());
+                if count >= MAX_BADWORDS {
+                    return self.error_reply(
+                        uid,
+                        &format!("Badword list is full (max {}).", MAX_BADWORDS),
+                    );
+                }
+
+                match self.db.bots().add_badword(record.id, word, nick).await {
+                    Ok(true) => {
+                        if let Some(mut assignment) = self.registry.channels.get_mut(&channel_lower)
+                        {
+                            assignment.badwords.push(word.to_string());
+                        }
+                        info!(channel = %record.name, word = %word, by = %nick, "Badword added");
+                        vec![self.reply_effect(
+                            uid,
+                            &format!(
+                                "\x02{}\x02 added to the badwords of \x02{}\x02.",
+                                word, record.name
+                            ),
+                        )]
+                    }
+                    Ok(false) => {
+                        self.error_reply(uid, &format!("\x02{}\x02 is already a badword.", word))
+                    }
+                    Err(e) => {
+                        warn!(channel = %record.name, error = ?e, "Failed to add badword");
+                        self.error_reply(uid, "Database error. Please try again later.")
+                    }
+                }
+            }
+            (Some("DEL"), Some(&word)) => match self.db.bots().del_badword(record.id, word).await {
+                Ok(true) => {
+                    if let Some(mut assignment) = self.registry.channels.get_mut(&channel_lower) {
+                        assignment
+                            .badwords
+                            .retain(|listed| !listed.eq_ignore_ascii_case(word));
+                    }
+                    info!(channel = %record.name, word = %word, by = %nick, "Badword removed");
+                    vec![self.reply_effect(
+                        uid,
+                        &format!(
+                            "\x02{}\x02 removed from the badwords of \x02{}\x02.",
+                            word, record.name
+                        ),
+                    )]
+                }
+                Ok(false) => self.error_reply(uid, &format!("\x02{}\x02 is not a badword.", word)),
+                Err(e) => {
+                    warn!(channel = %record.name, error = ?e, "Failed to remove badword");
+                    self.error_reply(uid, "Database error. Please try again later.")
+                }
+            },
+            _ => self.error_reply(uid, "Syntax: SET #channel BADWORDS <ADD|DEL|LIST> [word]"),
+        }
+    }
+}
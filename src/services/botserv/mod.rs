@@ -0,0 +1,20 @@
+//! BotServ - Channel bot service.
+//!
+//! Handles:
+//! - `BOTLIST` - Show the bots available for assignment
+//! - `ASSIGN #channel <bot>` / `UNASSIGN #channel` - Put a bot in a channel (founders)
+//! - `SET #channel GREET|KICKBAN|BADWORDS` - Configure the channel's bot (founders)
+//! - `BOT ADD|DEL` - Create and delete bots (opers)
+//!
+//! An assigned bot sits in its channel as an opped member, greets joining
+//! users with the channel's greet message and kicks users who say a badword.
+
+mod channel;
+mod commands;
+mod registry;
+mod startup;
+
+pub use channel::{on_join, on_message};
+pub use commands::BotServ;
+pub(crate) use startup::bot_user;
+pub use startup::restore_on_startup;
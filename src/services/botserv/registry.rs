@@ -0,0 +1,110 @@
+//! In-memory view of BotServ bots and channel assignments.
+//!
+//! Loaded from the database at startup and kept in step by BotServ commands,
+//! so the JOIN and message paths never touch the database.
+
+use dashmap::DashMap;
+use slirc_proto::{irc_to_lower, wildcard_match};
+
+/// A bot that is online.
+#[derive(Debug, Clone)]
+pub struct LiveBot {
+    /// Database ID.
+    pub id: i64,
+    pub uid: String,
+    pub nick: String,
+}
+
+/// The bot assigned to a channel and the channel's bot settings.
+#[derive(Debug, Clone)]
+pub struct ChannelBot {
+    pub bot_uid: String,
+    pub bot_nick: String,
+    /// Notice sent to users joining the channel.
+    pub greet: Option<String>,
+    /// Ban as well as kick users who say a badword.
+    pub kickban: bool,
+    /// Words (wildcards allowed) that get a user kicked.
+    pub badwords: Vec<String>,
+}
+
+impl ChannelBot {
+    /// Create an assignment with default settings.
+    pub fn new(bot: &LiveBot) -> Self {
+        Self {
+            bot_uid: bot.uid.clone(),
+            bot_nick: bot.nick.clone(),
+            greet: None,
+            kickban: false,
+            badwords: Vec::new(),
+        }
+    }
+
+    /// The first badword found in `text`, matched against whole words.
+    pub fn find_badword(&self, text: &str) -> Option<&str> {
+        if self.badwords.is_empty() {
+            return None;
+        }
+        text.split(|c: char| !c.is_alphanumeric() && c != '\'')
+            .filter(|word| !word.is_empty())
+            .find_map(|word| {
+                self.badwords
+                    .iter()
+                    .find(|bad| wildcard_match(bad, word))
+                    .map(String::as_str)
+            })
+    }
+}
+
+/// Bots and assignments, keyed by lowercase nick and channel name.
+#[derive(Debug, Default)]
+pub struct BotRegistry {
+    pub bots: DashMap<String, LiveBot>,
+    pub channels: DashMap<String, ChannelBot>,
+}
+
+impl BotRegistry {
+    /// Look up an online bot by nick.
+    pub fn bot(&self, nick: &str) -> Option<LiveBot> {
+        self.bots.get(&irc_to_lower(nick)).map(|b| b.clone())
+    }
+
+    /// Returns true if the nick belongs to a bot.
+    pub fn is_bot(&self, nick: &str) -> bool {
+        self.bots.contains_key(&irc_to_lower(nick))
+    }
+
+    /// The channel's assignment, if it has a bot.
+    pub fn channel(&self, channel: &str) -> Option<ChannelBot> {
+        self.channels.get(&irc_to_lower(channel)).map(|c| c.clone())
+    }
+
+    /// Channels the bot is assigned to.
+    pub fn channels_of(&self, bot_uid: &str) -> Vec<String> {
+        self.channels
+            .iter()
+            .filter(|entry| entry.bot_uid == bot_uid)
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_badword() {
+        let mut assignment = ChannelBot::new(&LiveBot {
+            id: 1,
+            uid: "001AAAAAF".to_string(),
+            nick: "Bot".to_string(),
+        });
+        assert_eq!(assignment.find_badword("anything"), None);
+
+        assignment.badwords = vec!["darn".to_string(), "heck*".to_string()];
+        assert_eq!(assignment.find_badword("oh DARN it"), Some("darn"));
+        assert_eq!(assignment.find_badword("what the heckity,"), Some("heck*"));
+        assert_eq!(assignment.find_badword("darned thing"), None);
+    }
+}
@@ -0,0 +1,115 @@
+//! Bringing bots back after a restart.
+//!
+//! Bots are pseudo-users with ordinary UIDs, so they are introduced afresh
+//! on every start and rejoin the channels they are assigned to.
+
+use super::registry::{ChannelBot, LiveBot};
+use crate::db::Bot;
+use crate::services::{ServiceEffect, apply_effects_no_sender};
+use crate::state::{Matrix, User, UserModes, UserStamps};
+use slirc_proto::irc_to_lower;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Build the pseudo-user for a bot.
+pub(crate) fn bot_user(matrix: &Matrix, uid: &str, bot: &Bot) -> User {
+    User {
+        uid: uid.to_string(),
+        nick: bot.nick.clone(),
+        user: bot.username.clone(),
+        realname: bot.realname.clone(),
+        host: bot.hostname.clone(),
+        ip: "0.0.0.0".to_string(),
+        visible_host: bot.hostname.clone(),
+        session_id: Uuid::nil(),
+        server: None,
+        channels: HashSet::new(),
+        modes: UserModes {
+            service: true,
+            ..Default::default()
+        },
+        account: None,
+        account_id: None,
+        away: None,
+        metadata: HashMap::new(),
+        caps: HashSet::new(),
+        certfp: None,
+        gateway: None,
+        silence_list: HashSet::new(),
+        accept_list: HashSet::new(),
+        created_at: chrono::Utc::now().timestamp(),
+        last_modified: matrix.clock(),
+        stamps: UserStamps::default(),
+        last_active: std::sync::atomic::AtomicI64::new(chrono::Utc::now().timestamp_millis()),
+    }
+}
+
+/// Introduce every bot and put each into its assigned channels.
+pub async fn restore_on_startup(matrix: &Arc<Matrix>) {
+    let repo = matrix.db.bots();
+    let (bots, assignments, badwords) =
+        match tokio::try_join!(repo.list(), repo.assignments(), repo.badwords()) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                warn!(error = ?e, "Failed to load BotServ bots");
+                return;
+            }
+        };
+    if bots.is_empty() {
+        return;
+    }
+
+    let registry = &matrix.service_manager.botserv.registry;
+    let mut effects = Vec::with_capacity(bots.len() + assignments.len() * 2);
+    for bot in bots {
+        let uid = matrix.user_manager.uid_gen.next();
+        registry.bots.insert(
+            irc_to_lower(&bot.nick),
+            LiveBot {
+                id: bot.id,
+                uid: uid.clone(),
+                nick: bot.nick.clone(),
+            },
+        );
+        effects.push(ServiceEffect::BotIntroduce { uid, bot });
+    }
+
+    for assignment in &assignments {
+        let Some(bot) = registry.bot(&assignment.bot_nick) else {
+            continue;
+        };
+        registry.channels.insert(
+            irc_to_lower(&assignment.channel),
+            ChannelBot {
+                greet: assignment.greet.clone(),
+                kickban: assignment.kickban,
+                ..ChannelBot::new(&bot)
+            },
+        );
+        effects.push(ServiceEffect::BotJoin {
+            uid: bot.uid.clone(),
+            channel: assignment.channel.clone(),
+        });
+        effects.push(ServiceEffect::ChannelMode {
+            channel: assignment.channel.clone(),
+            target_uid: bot.uid,
+            mode_char: 'o',
+            adding: true,
+        });
+    }
+
+    for (channel, word) in badwords {
+        if let Some(mut assignment) = registry.channels.get_mut(&irc_to_lower(&channel)) {
+            assignment.badwords.push(word);
+        }
+    }
+
+    info!(
+        bots = registry.bots.len(),
+        channels = assignments.len(),
+        "Restored BotServ bots"
+    );
+    apply_effects_no_sender(matrix, "BotServ", effects).await;
+}
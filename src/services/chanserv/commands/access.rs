@@ -1,6 +1,7 @@
 //! Access control ChanServ commands: ACCESS LIST/ADD/DEL.
 
 use super::{ChanServ, ChanServResult, format_timestamp};
+use crate::db::ChannelRepository;
 use crate::state::Matrix;
 use std::sync::Arc;
 use tracing::{info, warn};
@@ -45,7 +46,7 @@ impl ChanServ {
         };
 
         match subcommand.as_str() {
-            "LIST" => self.handle_access_list(uid, &channel_record).await,
+            "LIST" => self.handle_access_list(matrix, uid, &channel_record).await,
             "ADD" => {
                 self.handle_access_add(matrix, uid, nick, &channel_record, &args[2..])
                     .await
@@ -64,9 +65,17 @@ impl ChanServ {
     /// Handle ACCESS LIST subcommand.
     pub(super) async fn handle_access_list(
         &self,
+        matrix: &Arc<Matrix>,
         uid: &str,
         channel_record: &crate::db::ChannelRecord,
     ) -> ChanServResult {
+        if !self
+            .check_access_flag(matrix, uid, channel_record, 'A')
+            .await
+        {
+            return self.error_reply(uid, "You need +A access to view the access list.");
+        }
+
         let access_list = match self.db.channels().list_access(channel_record.id).await {
             Ok(list) => list,
             Err(e) => {
@@ -130,9 +139,12 @@ impl ChanServ {
         let target_account_name = args[0];
         let flags = args[1];
 
-        // Check if user has founder access
-        if !self.check_founder_access(matrix, uid, channel_record).await {
-            return self.error_reply(uid, "You must be the channel founder to modify access.");
+        // Check if user may edit the access list
+        if !self
+            .check_access_flag(matrix, uid, channel_record, 'f')
+            .await
+        {
+            return self.error_reply(uid, "You need +f access to modify the access list.");
         }
 
         // Find target account
@@ -151,11 +163,35 @@ impl ChanServ {
         };
 
         // Validate flags
-        if !self.validate_flags(flags) {
-            return self.error_reply(
-                uid,
-                "Invalid flags. Valid flags: +F (founder), +o (op), +v (voice)",
-            );
+        let flags = match ChannelRepository::expand_access_flags(flags) {
+            Ok(expanded) if flags.starts_with('+') && !expanded.is_empty() => expanded,
+            _ => {
+                return self.error_reply(
+                    uid,
+                    "Invalid flags. See \x02HELP\x02 for the list of flags.",
+                );
+            }
+        };
+        let flags = flags.as_str();
+
+        // Founder entries are only changed by founders, and never lose +F here
+        let current = self
+            .db
+            .channels()
+            .get_access(channel_record.id, target_account.id)
+            .await
+            .ok()
+            .flatten()
+            .map(|entry| entry.flags)
+            .unwrap_or_default();
+        if let Err(reason) = self
+            .check_flag_change(matrix, uid, channel_record, &current, flags)
+            .await
+        {
+            return self.error_reply(uid, reason);
+        }
+        if target_account.id == channel_record.founder_account_id && !flags.contains('F') {
+            return self.error_reply(uid, "Cannot remove founder access from the channel owner.");
         }
 
         // Add access
@@ -201,9 +237,12 @@ impl ChanServ {
 
         let target_account_name = args[0];
 
-        // Check if user has founder access
-        if !self.check_founder_access(matrix, uid, channel_record).await {
-            return self.error_reply(uid, "You must be the channel founder to modify access.");
+        // Check if user may edit the access list
+        if !self
+            .check_access_flag(matrix, uid, channel_record, 'f')
+            .await
+        {
+            return self.error_reply(uid, "You need +f access to modify the access list.");
         }
 
         // Find target account
@@ -225,6 +264,21 @@ impl ChanServ {
         if target_account.id == channel_record.founder_account_id {
             return self.error_reply(uid, "Cannot remove founder access from the channel owner.");
         }
+        let current = self
+            .db
+            .channels()
+            .get_access(channel_record.id, target_account.id)
+            .await
+            .ok()
+            .flatten()
+            .map(|entry| entry.flags)
+            .unwrap_or_default();
+        if let Err(reason) = self
+            .check_flag_change(matrix, uid, channel_record, &current, "")
+            .await
+        {
+            return self.error_reply(uid, reason);
+        }
 
         // Remove access
         match self
@@ -2,7 +2,11 @@
 
 use super::{ChanServ, ChanServResult, format_timestamp};
 use crate::db::ChannelRepository;
+use crate::services::ServiceEffect;
 use crate::state::Matrix;
+use crate::state::dashmap_ext::DashMapExt;
+use slirc_proto::irc_to_lower;
+use std::collections::HashSet;
 use std::sync::Arc;
 use tracing::{info, warn};
 
@@ -82,21 +86,15 @@ impl ChanServ {
             None => return self.error_reply(uid, "You must be identified to your account."),
         };
 
-        let has_access = if user_account_id == channel_record.founder_account_id {
-            true
-        } else if let Ok(Some(access)) = self
+        let has_access = self
             .db
             .channels()
-            .get_access(channel_record.id, user_account_id)
+            .effective_access(channel_record, user_account_id)
             .await
-        {
-            ChannelRepository::has_op_access(&access.flags)
-        } else {
-            false
-        };
+            .is_ok_and(|access| access.has('r'));
 
         if !has_access {
-            return self.error_reply(uid, "You do not have access to modify the AKICK list.");
+            return self.error_reply(uid, "You need +r access to modify the AKICK list.");
         }
 
         // Add the AKICK
@@ -113,13 +111,16 @@ impl ChanServ {
                     by = %nick,
                     "AKICK added"
                 );
-                self.reply_effects(
+                let mut effects = self.reply_effects(
                     uid,
                     vec![&format!(
                         "AKICK for \x02{}\x02 added to \x02{}\x02.",
                         mask, channel_record.name
                     )],
-                )
+                );
+                // Remove matching members right away rather than at the next sweep
+                effects.extend(self.akick_sweep(matrix, channel_record).await);
+                effects
             }
             Err(e) => {
                 warn!(channel = %channel_record.name, mask = %mask, error = ?e, "Failed to add AKICK");
@@ -149,21 +150,15 @@ impl ChanServ {
             None => return self.error_reply(uid, "You must be identified to your account."),
         };
 
-        let has_access = if user_account_id == channel_record.founder_account_id {
-            true
-        } else if let Ok(Some(access)) = self
+        let has_access = self
             .db
             .channels()
-            .get_access(channel_record.id, user_account_id)
+            .effective_access(channel_record, user_account_id)
             .await
-        {
-            ChannelRepository::has_op_access(&access.flags)
-        } else {
-            false
-        };
+            .is_ok_and(|access| access.has('r'));
 
         if !has_access {
-            return self.error_reply(uid, "You do not have access to modify the AKICK list.");
+            return self.error_reply(uid, "You need +r access to modify the AKICK list.");
         }
 
         // Remove the AKICK
@@ -244,4 +239,90 @@ impl ChanServ {
 
         texts.iter().map(|t| self.reply_effect(uid, t)).collect()
     }
+
+    /// Ban and kick every member of a channel who matches one of its AKICKs.
+    ///
+    /// Members are matched on their real host, as at join time. When every
+    /// AKICK mask has a literal part, only members found in the user index
+    /// under those masks are checked.
+    pub(crate) async fn akick_sweep(
+        &self,
+        matrix: &Arc<Matrix>,
+        channel_record: &crate::db::ChannelRecord,
+    ) -> ChanServResult {
+        let akicks = match self.db.channels().list_akicks(channel_record.id).await {
+            Ok(list) if !list.is_empty() => list,
+            Ok(_) => return vec![],
+            Err(e) => {
+                warn!(channel = %channel_record.name, error = ?e, "Failed to list AKICKs");
+                return vec![];
+            }
+        };
+
+        let channel_lower = irc_to_lower(&channel_record.name);
+        let Some(channel_sender) = matrix.channel_manager.channels.get_cloned(&channel_lower)
+        else {
+            return vec![];
+        };
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let _ = channel_sender
+            .send(crate::state::actor::ChannelEvent::GetMembers { reply_tx: tx })
+            .await;
+        let Ok(members) = rx.await else {
+            return vec![];
+        };
+
+        let candidates: Option<HashSet<String>> =
+            akicks.iter().try_fold(HashSet::new(), |mut uids, akick| {
+                uids.extend(matrix.user_manager.index.candidates(&akick.mask)?);
+                Some(uids)
+            });
+
+        let mut effects = Vec::new();
+        let mut banned = HashSet::new();
+        for member_uid in members.keys() {
+            if matrix.service_manager.is_service_uid(member_uid)
+                || candidates
+                    .as_ref()
+                    .is_some_and(|uids| !uids.contains(member_uid))
+            {
+                continue;
+            }
+            let Some(user_arc) = matrix.user_manager.users.get_cloned(member_uid) else {
+                continue;
+            };
+            let hostmask = {
+                let user = user_arc.read().await;
+                irc_to_lower(&format!("{}!{}@{}", user.nick, user.user, user.host))
+            };
+
+            let Some(akick) = akicks
+                .iter()
+                .find(|a| ChannelRepository::mask_matches(&irc_to_lower(&a.mask), &hostmask))
+            else {
+                continue;
+            };
+
+            if banned.insert(akick.mask.clone()) {
+                effects.push(ServiceEffect::ChannelBan {
+                    channel: channel_record.name.clone(),
+                    mask: akick.mask.clone(),
+                });
+            }
+            effects.push(ServiceEffect::Kick {
+                channel: channel_record.name.clone(),
+                target_uid: member_uid.clone(),
+                kicker: "ChanServ".to_string(),
+                reason: akick
+                    .reason
+                    .clone()
+                    .unwrap_or_else(|| "You are banned from this channel".to_string()),
+            });
+        }
+
+        if !effects.is_empty() {
+            info!(channel = %channel_record.name, count = effects.len(), "AKICK sweep");
+        }
+        effects
+    }
 }
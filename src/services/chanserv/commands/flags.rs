@@ -0,0 +1,267 @@
+//! FLAGS ChanServ command: view and edit per-account access flags.
+//!
+//! Flags follow Atheme: each letter grants one ChanServ command or join
+//! privilege (see `ACCESS_FLAGS` in the channel repository). Editing needs
+//! +f; founder entries can only be changed by founders. When the registered
+//! founder gives up +F, the longest-standing remaining +F holder becomes
+//! founder.
+
+use super::{ChanServ, ChanServResult};
+use crate::db::{ChannelRecord, ChannelRepository};
+use crate::state::Matrix;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+impl ChanServ {
+    /// Handle FLAGS command.
+    pub(super) async fn handle_flags(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> ChanServResult {
+        if args.is_empty() {
+            return self.error_reply(uid, "Syntax: FLAGS #channel [account [changes]]");
+        }
+
+        let channel_name = args[0];
+        if !channel_name.starts_with('#') {
+            return self.error_reply(uid, "Channel name must start with #");
+        }
+
+        let channel_record = match self.db.channels().find_by_name(channel_name).await {
+            Ok(Some(record)) => record,
+            Ok(None) => {
+                return self.error_reply(
+                    uid,
+                    &format!("Channel \x02{}\x02 is not registered.", channel_name),
+                );
+            }
+            Err(e) => {
+                warn!(channel = %channel_name, error = ?e, "Failed to lookup channel");
+                return self.error_reply(uid, "Database error. Please try again later.");
+            }
+        };
+
+        match args.len() {
+            1 | 2 => {
+                if !self
+                    .check_access_flag(matrix, uid, &channel_record, 'A')
+                    .await
+                {
+                    return self.error_reply(uid, "You need +A access to view flags.");
+                }
+                match args.get(1) {
+                    Some(account_name) => {
+                        self.handle_flags_show(uid, &channel_record, account_name)
+                            .await
+                    }
+                    None => self.handle_access_list(matrix, uid, &channel_record).await,
+                }
+            }
+            _ => {
+                self.handle_flags_change(matrix, uid, nick, &channel_record, args[1], args[2])
+                    .await
+            }
+        }
+    }
+
+    /// Show the flags one account holds.
+    async fn handle_flags_show(
+        &self,
+        uid: &str,
+        channel_record: &ChannelRecord,
+        account_name: &str,
+    ) -> ChanServResult {
+        let account = match self.db.accounts().find_by_name(account_name).await {
+            Ok(Some(account)) => account,
+            _ => {
+                return self.error_reply(
+                    uid,
+                    &format!("Account \x02{}\x02 does not exist.", account_name),
+                );
+            }
+        };
+
+        match self
+            .db
+            .channels()
+            .get_access(channel_record.id, account.id)
+            .await
+        {
+            Ok(Some(entry)) => self.reply_effects(
+                uid,
+                vec![&format!(
+                    "Flags for \x02{}\x02 on \x02{}\x02 are \x02{}\x02.",
+                    account.name, channel_record.name, entry.flags
+                )],
+            ),
+            Ok(None) => self.reply_effects(
+                uid,
+                vec![&format!(
+                    "\x02{}\x02 has no flags on \x02{}\x02.",
+                    account.name, channel_record.name
+                )],
+            ),
+            Err(e) => {
+                warn!(channel = %channel_record.name, error = ?e, "Failed to get access");
+                self.error_reply(uid, "Database error. Please try again later.")
+            }
+        }
+    }
+
+    /// Apply a flag change such as `+ot-v` to an account.
+    async fn handle_flags_change(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        channel_record: &ChannelRecord,
+        account_name: &str,
+        change: &str,
+    ) -> ChanServResult {
+        if !self
+            .check_access_flag(matrix, uid, channel_record, 'f')
+            .await
+        {
+            return self.error_reply(uid, "You need +f access to modify flags.");
+        }
+
+        let target = match self.db.accounts().find_by_name(account_name).await {
+            Ok(Some(account)) => account,
+            Ok(None) => {
+                return self.error_reply(
+                    uid,
+                    &format!("Account \x02{}\x02 does not exist.", account_name),
+                );
+            }
+            Err(e) => {
+                warn!(account = %account_name, error = ?e, "Failed to find account");
+                return self.error_reply(uid, "Database error. Please try again later.");
+            }
+        };
+
+        let channels = self.db.channels();
+        let current = match channels.get_access(channel_record.id, target.id).await {
+            Ok(entry) => entry.map(|e| e.flags).unwrap_or_default(),
+            Err(e) => {
+                warn!(channel = %channel_record.name, error = ?e, "Failed to get access");
+                return self.error_reply(uid, "Database error. Please try again later.");
+            }
+        };
+        let flags = match ChannelRepository::apply_flag_change(&current, change) {
+            Ok(flags) => flags,
+            Err(c) => {
+                return self.error_reply(
+                    uid,
+                    &format!("Unknown flag \x02{}\x02. See \x02HELP\x02 for the list.", c),
+                );
+            }
+        };
+
+        // The registered founder counts as holding +F even without the flag
+        let is_owner = target.id == channel_record.founder_account_id;
+        let held = if is_owner { "F" } else { current.as_str() };
+        if let Err(reason) = self
+            .check_flag_change(matrix, uid, channel_record, held, &flags)
+            .await
+        {
+            return self.error_reply(uid, reason);
+        }
+
+        // Founder succession: the channel must keep a founder
+        let mut successor = None;
+        if !flags.contains('F') && (is_owner || current.contains('F')) {
+            let entries = match channels.list_access(channel_record.id).await {
+                Ok(entries) => entries,
+                Err(e) => {
+                    warn!(channel = %channel_record.name, error = ?e, "Failed to list access");
+                    return self.error_reply(uid, "Database error. Please try again later.");
+                }
+            };
+            let Some(next) = entries
+                .iter()
+                .find(|e| e.account_id != target.id && e.flags.contains('F'))
+            else {
+                return self.error_reply(
+                    uid,
+                    &format!(
+                        "\x02{}\x02 is the last founder of \x02{}\x02. Give +F to someone else first.",
+                        target.name, channel_record.name
+                    ),
+                );
+            };
+            if is_owner {
+                successor = Some(next.account_id);
+            }
+        }
+
+        let result = if flags.is_empty() {
+            channels
+                .remove_access(channel_record.id, target.id)
+                .await
+                .map(|_| ())
+        } else {
+            channels
+                .set_access(channel_record.id, target.id, &flags, nick)
+                .await
+        };
+        if let Err(e) = result {
+            warn!(channel = %channel_record.name, account = %target.name, error = ?e, "Failed to set flags");
+            return self.error_reply(uid, "Failed to change flags. Please try again later.");
+        }
+
+        info!(
+            channel = %channel_record.name,
+            account = %target.name,
+            flags = %flags,
+            by = %nick,
+            "Flags changed"
+        );
+
+        let mut texts = vec![if flags.is_empty() {
+            format!(
+                "Flags for \x02{}\x02 on \x02{}\x02 have been removed.",
+                target.name, channel_record.name
+            )
+        } else {
+            format!(
+                "Flags for \x02{}\x02 on \x02{}\x02 are now \x02{}\x02.",
+                target.name, channel_record.name, flags
+            )
+        }];
+
+        if let Some(successor_id) = successor {
+            if let Err(e) = channels.set_founder(channel_record.id, successor_id).await {
+                warn!(channel = %channel_record.name, error = ?e, "Failed to transfer founder");
+            } else if let Ok(Some(account)) = self.db.accounts().find_by_id(successor_id).await {
+                info!(channel = %channel_record.name, founder = %account.name, "Founder transferred");
+                texts.push(format!(
+                    "\x02{}\x02 is now the founder of \x02{}\x02.",
+                    account.name, channel_record.name
+                ));
+            }
+        }
+
+        texts.iter().map(|t| self.reply_effect(uid, t)).collect()
+    }
+
+    /// Check that the caller may change an entry from `current` to `new`
+    /// flags: granting, removing or editing +F takes a founder.
+    pub(super) async fn check_flag_change(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        channel_record: &ChannelRecord,
+        current: &str,
+        new: &str,
+    ) -> Result<(), &'static str> {
+        if (current.contains('F') || new.contains('F'))
+            && !self.check_founder_access(matrix, uid, channel_record).await
+        {
+            return Err("Only founders can change founder access.");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+//! Channel state ChanServ commands: TOPIC (+t) and INVITE (+i).
+
+use super::{ChanServ, ChanServResult};
+use crate::services::ServiceEffect;
+use crate::state::Matrix;
+use slirc_proto::irc_to_lower;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+impl ChanServ {
+    /// Handle TOPIC command.
+    pub(super) async fn handle_topic(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> ChanServResult {
+        if args.len() < 2 {
+            return self.error_reply(uid, "Syntax: TOPIC #channel <text>");
+        }
+
+        let channel_name = args[0];
+        let topic = args[1..].join(" ");
+
+        let Some(channel_record) = self
+            .live_registered_channel(matrix, uid, channel_name)
+            .await
+        else {
+            return self.error_reply(
+                uid,
+                &format!(
+                    "Channel \x02{}\x02 is not registered or not in use.",
+                    channel_name
+                ),
+            );
+        };
+
+        if !self
+            .check_access_flag(matrix, uid, &channel_record, 't')
+            .await
+        {
+            return self.error_reply(
+                uid,
+                &format!(
+                    "You need +t access to set the topic of \x02{}\x02.",
+                    channel_record.name
+                ),
+            );
+        }
+
+        if channel_record.keeptopic
+            && let Err(e) = self
+                .db
+                .channels()
+                .save_topic(
+                    channel_record.id,
+                    &topic,
+                    "ChanServ",
+                    chrono::Utc::now().timestamp(),
+                )
+                .await
+        {
+            warn!(channel = %channel_record.name, error = ?e, "Failed to save topic");
+        }
+
+        info!(channel = %channel_record.name, by = %nick, "ChanServ topic change");
+
+        vec![ServiceEffect::ChannelTopic {
+            channel: channel_record.name,
+            topic,
+        }]
+    }
+
+    /// Handle INVITE command: invite yourself into a channel.
+    pub(super) async fn handle_invite(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> ChanServResult {
+        if args.is_empty() {
+            return self.error_reply(uid, "Syntax: INVITE #channel");
+        }
+
+        let channel_name = args[0];
+        let Some(channel_record) = self
+            .live_registered_channel(matrix, uid, channel_name)
+            .await
+        else {
+            return self.error_reply(
+                uid,
+                &format!(
+                    "Channel \x02{}\x02 is not registered or not in use.",
+                    channel_name
+                ),
+            );
+        };
+
+        if !self
+            .check_access_flag(matrix, uid, &channel_record, 'i')
+            .await
+        {
+            return self.error_reply(
+                uid,
+                &format!(
+                    "You need +i access to be invited to \x02{}\x02.",
+                    channel_record.name
+                ),
+            );
+        }
+
+        info!(channel = %channel_record.name, nick = %nick, "ChanServ invite");
+
+        vec![
+            self.reply_effect(
+                uid,
+                &format!("You have been invited to \x02{}\x02.", channel_record.name),
+            ),
+            ServiceEffect::Invite {
+                channel: channel_record.name,
+                target_uid: uid.to_string(),
+            },
+        ]
+    }
+
+    /// Find a registered channel that currently exists on the network.
+    async fn live_registered_channel(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        channel_name: &str,
+    ) -> Option<crate::db::ChannelRecord> {
+        if !channel_name.starts_with('#')
+            || !matrix
+                .channel_manager
+                .channels
+                .contains_key(&irc_to_lower(channel_name))
+        {
+            return None;
+        }
+
+        match self.db.channels().find_by_name(channel_name).await {
+            Ok(record) => record,
+            Err(e) => {
+                warn!(channel = %channel_name, uid = %uid, error = ?e, "Database error");
+                None
+            }
+        }
+    }
+}
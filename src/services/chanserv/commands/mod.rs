@@ -5,17 +5,32 @@
 
 mod access;
 mod akick;
+mod flags;
+mod invite_topic;
 mod moderation;
 mod modes;
+mod note;
 mod register;
 
-use crate::db::{ChannelRepository, Database};
+use crate::db::Database;
 use crate::services::base::ServiceBase;
 use crate::services::{Service, ServiceEffect};
 use crate::state::Matrix;
 use async_trait::async_trait;
 use std::sync::Arc;
 
+/// Returns true if the command modifies the channel registration database.
+fn is_write_command(command: &str, args: &[&str]) -> bool {
+    match command {
+        "REGISTER" | "SET" | "DROP" => true,
+        "FLAGS" => args.len() > 2,
+        "ACCESS" | "AKICK" | "NOTE" => args
+            .get(1)
+            .is_some_and(|sub| !sub.eq_ignore_ascii_case("LIST")),
+        _ => false,
+    }
+}
+
 /// Result of a ChanServ command - a list of effects to apply.
 pub type ChanServResult = Vec<ServiceEffect>;
 
@@ -78,9 +93,14 @@ impl ChanServ {
         let command = parts[0].to_uppercase();
         let args = &parts[1..];
 
+        if matrix.lifecycle_manager.in_maintenance() && is_write_command(&command, args) {
+            return self.read_only_reply(uid);
+        }
+
         match command.as_str() {
             "REGISTER" => self.handle_register(matrix, uid, nick, args).await,
             "ACCESS" => self.handle_access(matrix, uid, nick, args).await,
+            "FLAGS" => self.handle_flags(matrix, uid, nick, args).await,
             "INFO" => self.handle_info(uid, args).await,
             "SET" => self.handle_set(matrix, uid, nick, args).await,
             "DROP" => self.handle_drop(matrix, uid, nick, args).await,
@@ -90,6 +110,9 @@ impl ChanServ {
             "DEVOICE" => self.handle_mode_change(matrix, uid, nick, args, "-v").await,
             "AKICK" => self.handle_akick(matrix, uid, nick, args).await,
             "CLEAR" => self.handle_clear(matrix, uid, nick, args).await,
+            "TOPIC" => self.handle_topic(matrix, uid, nick, args).await,
+            "INVITE" => self.handle_invite(matrix, uid, nick, args).await,
+            "NOTE" => self.handle_note(matrix, uid, nick, args).await,
             "HELP" => self.help_reply(uid),
             _ => self.unknown_command(uid, &command),
         }
@@ -131,33 +154,31 @@ impl ChanServ {
             None => return false,
         };
 
-        // Check if user is founder
-        if account_id == channel_record.founder_account_id {
-            return true;
-        }
-
-        // Check access list for +F flag
-        if let Ok(Some(access)) = self
-            .db
+        // Founder, or +F on the access list, directly or through a group
+        self.db
             .channels()
-            .get_access(channel_record.id, account_id)
+            .effective_access(channel_record, account_id)
             .await
-        {
-            return ChannelRepository::is_founder(&access.flags);
-        }
-
-        false
+            .is_ok_and(|access| access.is_founder())
     }
 
-    /// Validate access flags.
-    pub(crate) fn validate_flags(&self, flags: &str) -> bool {
-        // Must start with + and contain only valid flag chars
-        if !flags.starts_with('+') {
+    /// Check if user holds an access flag on a channel (founders hold all).
+    pub(crate) async fn check_access_flag(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        channel_record: &crate::db::ChannelRecord,
+        flag: char,
+    ) -> bool {
+        let Some(account_id) = self.get_user_account_id(matrix, uid).await else {
             return false;
-        }
+        };
 
-        let flag_chars = &flags[1..];
-        flag_chars.chars().all(|c| matches!(c, 'F' | 'o' | 'v'))
+        self.db
+            .channels()
+            .effective_access(channel_record, account_id)
+            .await
+            .is_ok_and(|access| access.has(flag))
     }
 
     /// Create help reply.
@@ -177,6 +198,10 @@ impl ChanServ {
             ),
             self.reply_effect(uid, "  ACCESS #channel ADD <acct> <flags> - Add access"),
             self.reply_effect(uid, "  ACCESS #channel DEL <account>   - Remove access"),
+            self.reply_effect(
+                uid,
+                "  FLAGS #channel [account [changes]] - View or edit flags",
+            ),
             self.reply_effect(uid, "  AKICK #channel ADD <mask> [reason] - Add auto-kick"),
             self.reply_effect(uid, "  AKICK #channel DEL <mask>       - Remove auto-kick"),
             self.reply_effect(uid, "  AKICK #channel LIST             - List auto-kicks"),
@@ -185,7 +210,23 @@ impl ChanServ {
                 "  CLEAR #channel USERS [reason]   - Kick non-opped users",
             ),
             self.reply_effect(uid, "  INFO #channel                   - Show channel info"),
+            self.reply_effect(uid, "  INVITE #channel                 - Invite yourself"),
+            self.reply_effect(uid, "  NOTE #channel ADD <text>        - Add a staff note"),
+            self.reply_effect(
+                uid,
+                "  NOTE #channel DEL <number>      - Remove a staff note",
+            ),
+            self.reply_effect(uid, "  NOTE #channel LIST              - List staff notes"),
             self.reply_effect(uid, "  SET #channel <opt> <value>      - Change settings"),
+            self.reply_effect(
+                uid,
+                "  SET #channel MLOCK [modes]      - Lock modes (+nt-s)",
+            ),
+            self.reply_effect(
+                uid,
+                "  SET #channel SUCCESSOR [acct]   - Heir if founder drops",
+            ),
+            self.reply_effect(uid, "  TOPIC #channel <text>           - Set the topic"),
             self.reply_effect(
                 uid,
                 "  DROP #channel                   - Unregister channel",
@@ -200,8 +241,14 @@ impl ChanServ {
             self.reply_effect(uid, " "),
             self.reply_effect(
                 uid,
-                "Access flags: +F (founder), +o (auto-op), +v (auto-voice)",
+                "Flags: +v/+o VOICE/OP, +V/+O auto-voice/op, +t TOPIC, +s SET,",
+            ),
+            self.reply_effect(
+                uid,
+                "       +r AKICK, +i INVITE, +R CLEAR, +f edit access, +A view access,",
             ),
+            self.reply_effect(uid, "       +F founder (all flags)"),
+            self.reply_effect(uid, "ACCESS ADD +o also grants +OrA and +v grants +V."),
             self.reply_effect(uid, "***** End of Help *****"),
         ]
     }
@@ -54,30 +54,21 @@ impl ChanServ {
             }
         };
 
-        // Check if user has founder access (+F)
+        // Check if user has recover access (+R)
         let user_account_id = match self.get_user_account_id(matrix, uid).await {
             Some(id) => id,
             None => return self.error_reply(uid, "You must be identified to your account."),
         };
 
-        let is_founder = user_account_id == channel_record.founder_account_id;
-        let has_founder_flag = if !is_founder {
-            if let Ok(Some(access)) = self
-                .db
-                .channels()
-                .get_access(channel_record.id, user_account_id)
-                .await
-            {
-                access.flags.contains('F')
-            } else {
-                false
-            }
-        } else {
-            true
-        };
+        let has_recover_flag = self
+            .db
+            .channels()
+            .effective_access(&channel_record, user_account_id)
+            .await
+            .is_ok_and(|access| access.has('R'));
 
-        if !has_founder_flag {
-            return self.error_reply(uid, "You need +F (founder) access to use CLEAR.");
+        if !has_recover_flag {
+            return self.error_reply(uid, "You need +R access to use CLEAR.");
         }
 
         // Get channel state and collect UIDs to kick
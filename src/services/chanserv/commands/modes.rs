@@ -1,7 +1,6 @@
 //! Mode change ChanServ commands: OP/DEOP/VOICE/DEVOICE.
 
 use super::{ChanServ, ChanServResult};
-use crate::db::ChannelRepository;
 use crate::services::ServiceEffect;
 use crate::state::Matrix;
 use slirc_proto::irc_to_lower;
@@ -56,25 +55,18 @@ impl ChanServ {
             }
         };
 
-        // Check if user has op access (+o or +F)
+        // OP/DEOP take +o; VOICE/DEVOICE take +v or +o
         let user_account_id = match self.get_user_account_id(matrix, uid).await {
             Some(id) => id,
             None => return self.error_reply(uid, "You must be identified to your account."),
         };
 
-        // Check if founder or has +o flag
-        let has_access = if user_account_id == channel_record.founder_account_id {
-            true
-        } else if let Ok(Some(access)) = self
+        let has_access = self
             .db
             .channels()
-            .get_access(channel_record.id, user_account_id)
+            .effective_access(&channel_record, user_account_id)
             .await
-        {
-            ChannelRepository::has_op_access(&access.flags)
-        } else {
-            false
-        };
+            .is_ok_and(|access| access.has('o') || (mode.ends_with('v') && access.has('v')));
 
         if !has_access {
             return self.error_reply(
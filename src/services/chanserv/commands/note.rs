@@ -0,0 +1,215 @@
+//! NOTE ChanServ commands: NOTE ADD/DEL/LIST.
+//!
+//! Notes are shared moderation notes for channel staff. Anyone on the access
+//! list can read and add them; removing one takes op access.
+
+use super::{ChanServ, ChanServResult, format_timestamp};
+use crate::db::{ChannelRecord, EffectiveAccess};
+use crate::state::Matrix;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Longest note text accepted.
+const MAX_NOTE_LEN: usize = 300;
+
+/// Most notes a channel can hold.
+const MAX_NOTES_PER_CHANNEL: usize = 50;
+
+impl ChanServ {
+    /// Handle NOTE command.
+    pub(super) async fn handle_note(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> ChanServResult {
+        if args.len() < 2 {
+            return self.error_reply(uid, "Syntax: NOTE #channel <ADD|DEL|LIST> [text|number]");
+        }
+
+        let channel_name = args[0];
+        let subcommand = args[1].to_uppercase();
+
+        if !channel_name.starts_with('#') {
+            return self.error_reply(uid, "Channel name must start with #");
+        }
+
+        let channel_record = match self.db.channels().find_by_name(channel_name).await {
+            Ok(Some(record)) => record,
+            Ok(None) => {
+                return self.error_reply(
+                    uid,
+                    &format!("Channel \x02{}\x02 is not registered.", channel_name),
+                );
+            }
+            Err(e) => {
+                warn!(channel = %channel_name, error = ?e, "Database error");
+                return self.error_reply(uid, "Database error. Please try again later.");
+            }
+        };
+
+        let Some(account_id) = self.get_user_account_id(matrix, uid).await else {
+            return self.error_reply(uid, "You must be identified to your account.");
+        };
+        let access = self
+            .db
+            .channels()
+            .effective_access(&channel_record, account_id)
+            .await
+            .unwrap_or_default();
+        if !access.has_any_access() {
+            return self.error_reply(
+                uid,
+                &format!(
+                    "You do not have access to the notes of \x02{}\x02.",
+                    channel_record.name
+                ),
+            );
+        }
+
+        match subcommand.as_str() {
+            "ADD" => {
+                self.handle_note_add(uid, nick, &channel_record, &args[2..])
+                    .await
+            }
+            "DEL" => {
+                self.handle_note_del(uid, nick, &channel_record, &access, &args[2..])
+                    .await
+            }
+            "LIST" => self.handle_note_list(uid, &channel_record).await,
+            _ => self.error_reply(uid, "Unknown subcommand. Valid: ADD, DEL, LIST"),
+        }
+    }
+
+    /// Handle NOTE ADD subcommand.
+    async fn handle_note_add(
+        &self,
+        uid: &str,
+        nick: &str,
+        channel_record: &ChannelRecord,
+        args: &[&str],
+    ) -> ChanServResult {
+        if args.is_empty() {
+            return self.error_reply(uid, "Syntax: NOTE #channel ADD <text>");
+        }
+
+        let text = args.join(" ");
+        if text.len() > MAX_NOTE_LEN {
+            return self.error_reply(
+                uid,
+                &format!("Notes are limited to {} characters.", MAX_NOTE_LEN),
+            );
+        }
+
+        match self.db.channels().list_notes(channel_record.id).await {
+            Ok(notes) if notes.len() >= MAX_NOTES_PER_CHANNEL => {
+                return self.error_reply(
+                    uid,
+                    &format!(
+                        "\x02{}\x02 already has {} notes. Delete one first.",
+                        channel_record.name, MAX_NOTES_PER_CHANNEL
+                    ),
+                );
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!(channel = %channel_record.name, error = ?e, "Failed to list notes");
+                return self.error_reply(uid, "Database error. Please try again later.");
+            }
+        }
+
+        match self
+            .db
+            .channels()
+            .add_note(channel_record.id, &text, nick)
+            .await
+        {
+            Ok(id) => {
+                info!(channel = %channel_record.name, id = id, by = %nick, "Channel note added");
+                self.reply_effects(
+                    uid,
+                    vec![&format!(
+                        "Note \x02#{}\x02 added to \x02{}\x02.",
+                        id, channel_record.name
+                    )],
+                )
+            }
+            Err(e) => {
+                warn!(channel = %channel_record.name, error = ?e, "Failed to add note");
+                self.error_reply(uid, "Failed to add note. Please try again later.")
+            }
+        }
+    }
+
+    /// Handle NOTE DEL subcommand.
+    async fn handle_note_del(
+        &self,
+        uid: &str,
+        nick: &str,
+        channel_record: &ChannelRecord,
+        access: &EffectiveAccess,
+        args: &[&str],
+    ) -> ChanServResult {
+        let Some(id) = args
+            .first()
+            .and_then(|arg| arg.trim_start_matches('#').parse::<i64>().ok())
+        else {
+            return self.error_reply(uid, "Syntax: NOTE #channel DEL <number>");
+        };
+
+        if !access.has_op_access() {
+            return self.error_reply(uid, "You do not have access to delete notes.");
+        }
+
+        match self.db.channels().remove_note(channel_record.id, id).await {
+            Ok(true) => {
+                info!(channel = %channel_record.name, id = id, by = %nick, "Channel note removed");
+                self.reply_effects(
+                    uid,
+                    vec![&format!(
+                        "Note \x02#{}\x02 removed from \x02{}\x02.",
+                        id, channel_record.name
+                    )],
+                )
+            }
+            Ok(false) => self.error_reply(uid, &format!("No note \x02#{}\x02 found.", id)),
+            Err(e) => {
+                warn!(channel = %channel_record.name, id = id, error = ?e, "Failed to remove note");
+                self.error_reply(uid, "Failed to remove note. Please try again later.")
+            }
+        }
+    }
+
+    /// Handle NOTE LIST subcommand.
+    async fn handle_note_list(&self, uid: &str, channel_record: &ChannelRecord) -> ChanServResult {
+        let notes = match self.db.channels().list_notes(channel_record.id).await {
+            Ok(list) => list,
+            Err(e) => {
+                warn!(channel = %channel_record.name, error = ?e, "Failed to list notes");
+                return self.error_reply(uid, "Database error. Please try again later.");
+            }
+        };
+
+        if notes.is_empty() {
+            return self.reply_effects(
+                uid,
+                vec![&format!("\x02{}\x02 has no notes.", channel_record.name)],
+            );
+        }
+
+        let mut texts = vec![format!("Notes for \x02{}\x02:", channel_record.name)];
+        for note in &notes {
+            texts.push(format!(
+                "  #{} [{} by {}] {}",
+                note.id,
+                format_timestamp(note.created_at),
+                note.author,
+                note.note
+            ));
+        }
+        texts.push(format!("End of notes for \x02{}\x02.", channel_record.name));
+
+        texts.iter().map(|t| self.reply_effect(uid, t)).collect()
+    }
+}
@@ -1,6 +1,10 @@
 //! Registration-related ChanServ commands: REGISTER, DROP, INFO, SET.
 
 use super::{ChanServ, ChanServResult, format_timestamp};
+use crate::db::ChannelRecord;
+use crate::services::ServiceEffect;
+use crate::services::chanserv::forget_registration;
+use crate::services::chanserv::mlock::Mlock;
 use crate::state::Matrix;
 use slirc_proto::irc_to_lower;
 use std::sync::Arc;
@@ -42,6 +46,19 @@ impl ChanServ {
             }
         };
 
+        if !self
+            .db
+            .accounts()
+            .is_verified(account_id)
+            .await
+            .unwrap_or(false)
+        {
+            return self.error_reply(
+                uid,
+                "You must confirm your account with NickServ CONFIRM before registering channels.",
+            );
+        }
+
         // Check if user is op in the channel
         let channel_lower = irc_to_lower(channel_name);
         let is_op = if let Some(channel_sender) = matrix
@@ -88,6 +105,10 @@ impl ChanServ {
         {
             Ok(record) => {
                 info!(channel = %channel_name, founder = %nick, "Channel registered");
+                matrix
+                    .channel_manager
+                    .registered_channels
+                    .insert(irc_to_lower(&record.name));
                 self.reply_effects(
                     uid,
                     vec![&format!(
@@ -164,6 +185,12 @@ impl ChanServ {
             texts.push(format!("  Description: {}", desc));
         }
 
+        if let Some(successor_id) = channel_record.successor_account_id
+            && let Ok(Some(account)) = self.db.accounts().find_by_id(successor_id).await
+        {
+            texts.push(format!("  Successor  : {}", account.name));
+        }
+
         if let Some(ref mlock) = channel_record.mlock {
             texts.push(format!("  Mode lock  : {}", mlock));
         }
@@ -190,7 +217,10 @@ impl ChanServ {
         nick: &str,
         args: &[&str],
     ) -> ChanServResult {
-        if args.len() < 3 {
+        // MLOCK or SUCCESSOR alone clears it, every other option needs a value
+        let clears = args.len() == 2
+            && (args[1].eq_ignore_ascii_case("MLOCK") || args[1].eq_ignore_ascii_case("SUCCESSOR"));
+        if args.len() < 3 && !clears {
             return self.error_reply(uid, "Syntax: SET #channel <option> <value>");
         }
 
@@ -218,12 +248,23 @@ impl ChanServ {
             }
         };
 
-        // Check if user has founder access
+        // Check if user has set access
         if !self
-            .check_founder_access(matrix, uid, &channel_record)
+            .check_access_flag(matrix, uid, &channel_record, 's')
             .await
         {
-            return self.error_reply(uid, "You must be the channel founder to change settings.");
+            return self.error_reply(uid, "You need +s access to change settings.");
+        }
+
+        if option.eq_ignore_ascii_case("MLOCK") {
+            return self
+                .handle_set_mlock(uid, nick, &channel_record, &value)
+                .await;
+        }
+        if option.eq_ignore_ascii_case("SUCCESSOR") {
+            return self
+                .handle_set_successor(matrix, uid, nick, &channel_record, &value)
+                .await;
         }
 
         // Update setting
@@ -252,7 +293,7 @@ impl ChanServ {
             Err(crate::db::DbError::UnknownOption(opt)) => self.error_reply(
                 uid,
                 &format!(
-                    "Unknown option: \x02{}\x02. Valid options: description, mlock, keeptopic",
+                    "Unknown option: \x02{}\x02. Valid options: description, mlock, keeptopic, successor",
                     opt
                 ),
             ),
@@ -263,6 +304,143 @@ impl ChanServ {
         }
     }
 
+    /// Handle SET MLOCK: validate, store and enforce a mode lock.
+    async fn handle_set_mlock(
+        &self,
+        uid: &str,
+        nick: &str,
+        channel_record: &ChannelRecord,
+        value: &str,
+    ) -> ChanServResult {
+        let value = if value.eq_ignore_ascii_case("OFF") {
+            ""
+        } else {
+            value
+        };
+        let mlock = match Mlock::parse(value) {
+            Ok(mlock) => mlock,
+            Err(c) => {
+                return self.error_reply(
+                    uid,
+                    &format!(
+                        "Invalid mode lock at \x02{}\x02. Use e.g. +nt-s or +k key.",
+                        c
+                    ),
+                );
+            }
+        };
+
+        let stored = (!mlock.is_empty()).then(|| mlock.to_string());
+        if let Err(e) = self
+            .db
+            .channels()
+            .set_mlock(channel_record.id, stored.as_deref())
+            .await
+        {
+            warn!(channel = %channel_record.name, error = ?e, "Failed to set MLOCK");
+            return self.error_reply(uid, "Failed to update setting. Please try again later.");
+        }
+
+        info!(
+            channel = %channel_record.name,
+            mlock = ?stored,
+            by = %nick,
+            "MLOCK updated"
+        );
+
+        let Some(stored) = stored else {
+            return self.reply_effects(
+                uid,
+                vec![&format!(
+                    "Mode lock for \x02{}\x02 has been removed.",
+                    channel_record.name
+                )],
+            );
+        };
+
+        // Bring the live channel, if any, in line with the new lock
+        vec![
+            self.reply_effect(
+                uid,
+                &format!(
+                    "Mode lock for \x02{}\x02 is now \x02{}\x02.",
+                    channel_record.name, stored
+                ),
+            ),
+            ServiceEffect::ChannelModes {
+                channel: channel_record.name.clone(),
+                setter: "ChanServ".to_string(),
+                modes: mlock.modes(),
+            },
+        ]
+    }
+
+    /// Handle SET SUCCESSOR: name the account that inherits the channel when
+    /// the founder's account is dropped. Founder only.
+    async fn handle_set_successor(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        channel_record: &ChannelRecord,
+        value: &str,
+    ) -> ChanServResult {
+        if !self.check_founder_access(matrix, uid, channel_record).await {
+            return self.error_reply(uid, "Only the channel founder can set the successor.");
+        }
+
+        let successor = if value.is_empty() || value.eq_ignore_ascii_case("OFF") {
+            None
+        } else {
+            match self.db.accounts().find_by_name(value).await {
+                Ok(Some(account)) if account.id == channel_record.founder_account_id => {
+                    return self.error_reply(uid, "The founder cannot be the successor.");
+                }
+                Ok(Some(account)) => Some(account),
+                Ok(None) => {
+                    return self.error_reply(
+                        uid,
+                        &format!("Account \x02{}\x02 is not registered.", value),
+                    );
+                }
+                Err(e) => {
+                    warn!(account = %value, error = ?e, "Failed to lookup account");
+                    return self.error_reply(uid, "Database error. Please try again later.");
+                }
+            }
+        };
+
+        if let Err(e) = self
+            .db
+            .channels()
+            .set_successor(channel_record.id, successor.as_ref().map(|a| a.id))
+            .await
+        {
+            warn!(channel = %channel_record.name, error = ?e, "Failed to set successor");
+            return self.error_reply(uid, "Failed to update setting. Please try again later.");
+        }
+
+        let successor = successor.map(|a| a.name);
+        info!(
+            channel = %channel_record.name,
+            successor = ?successor,
+            by = %nick,
+            "Successor updated"
+        );
+
+        let text = match successor {
+            Some(name) => format!(
+                "\x02{}\x02 is now the successor of \x02{}\x02.",
+                name, channel_record.name
+            ),
+            None => format!(
+                "Successor for \x02{}\x02 has been removed.",
+                channel_record.name
+            ),
+        };
+        self.reply_effects(uid, vec![&text])
+    }
+
     /// Handle DROP command.
     pub(super) async fn handle_drop(
         &self,
@@ -303,7 +481,13 @@ impl ChanServ {
             None => return self.error_reply(uid, "You must be identified to your account."),
         };
 
-        if user_account_id != channel_record.founder_account_id {
+        let is_founder = self
+            .db
+            .channels()
+            .effective_access(&channel_record, user_account_id)
+            .await
+            .is_ok_and(|access| access.founder);
+        if !is_founder {
             return self.error_reply(uid, "Only the channel founder can drop this channel.");
         }
 
@@ -311,13 +495,20 @@ impl ChanServ {
         match self.db.channels().drop_channel(channel_record.id).await {
             Ok(true) => {
                 info!(channel = %channel_name, by = %nick, "Channel dropped");
-                self.reply_effects(
+                let mut effects = self.reply_effects(
                     uid,
                     vec![&format!(
                         "Channel \x02{}\x02 has been dropped.",
                         channel_name
                     )],
-                )
+                );
+                // The channel's bot assignment went with the registration
+                effects.extend(forget_registration(
+                    matrix,
+                    &channel_record.name,
+                    "Channel dropped",
+                ));
+                effects
             }
             Ok(false) => self.error_reply(uid, "Failed to drop channel."),
             Err(e) => {
@@ -0,0 +1,78 @@
+//! Channel registration expiry.
+//!
+//! Registered channels that exist on the network are marked as used; those
+//! left unused for `[chanserv] expire_days` are dropped.
+
+use super::forget_registration;
+use crate::services::apply_effects_no_sender;
+use crate::state::Matrix;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How often registrations are checked for expiry.
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Spawn the channel expiry background task. Does nothing when expiry is off.
+pub fn spawn_expiry_task(matrix: Arc<Matrix>) {
+    let expire_days = matrix.config.chanserv.expire_days;
+    if expire_days == 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut shutdown_rx = matrix.lifecycle_manager.shutdown_tx.subscribe();
+        loop {
+            tokio::select! {
+                _ = interval.tick() => expire_channels(&matrix, expire_days).await,
+                _ = shutdown_rx.recv() => break,
+            }
+        }
+    });
+}
+
+/// Mark live channels as used, then drop registrations past the cutoff.
+async fn expire_channels(matrix: &Arc<Matrix>, expire_days: u32) {
+    let channels = matrix.service_manager.chanserv.db.channels();
+
+    let live: Vec<String> = matrix
+        .channel_manager
+        .registered_channels
+        .iter()
+        .filter(|name| matrix.channel_manager.channels.contains_key(name.as_str()))
+        .map(|name| name.clone())
+        .collect();
+    for name in live {
+        if let Err(e) = channels.mark_used(&name).await {
+            warn!(channel = %name, error = ?e, "Channel expiry: failed to mark channel used");
+        }
+    }
+
+    let cutoff = chrono::Utc::now()
+        .timestamp()
+        .saturating_sub(i64::from(expire_days) * 86400);
+    let expired = match channels.list_unused_since(cutoff).await {
+        Ok(expired) => expired,
+        Err(e) => {
+            warn!(error = ?e, "Channel expiry: failed to list unused channels");
+            return;
+        }
+    };
+
+    let mut effects = Vec::new();
+    for (id, name) in expired {
+        match channels.drop_channel(id).await {
+            Ok(true) => {
+                info!(channel = %name, days = expire_days, "Channel registration expired");
+                effects.extend(forget_registration(matrix, &name, "Channel expired"));
+            }
+            Ok(false) => {}
+            Err(e) => warn!(channel = %name, error = ?e, "Channel expiry: failed to drop"),
+        }
+    }
+    if !effects.is_empty() {
+        apply_effects_no_sender(matrix, "ChanServ", effects).await;
+    }
+}
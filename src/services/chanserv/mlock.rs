@@ -0,0 +1,198 @@
+//! MLOCK (mode lock) parsing for registered channels.
+//!
+//! An MLOCK such as `+ntk-si secret` pins modes on or off. ChanServ stores it
+//! in canonical form, the MODE handler drops changes that would break it, and
+//! the lock is re-applied whenever the channel is recreated.
+
+use slirc_proto::mode::ModeType;
+use slirc_proto::{ChannelMode, Mode};
+use std::fmt;
+
+/// A parsed mode lock.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mlock {
+    /// Modes locked on, with their parameter for `+k` and `+l`.
+    on: Vec<(ChannelMode, Option<String>)>,
+    /// Modes locked off.
+    off: Vec<ChannelMode>,
+}
+
+impl Mlock {
+    /// Parse an MLOCK set by a user, rejecting anything that cannot be locked.
+    ///
+    /// Flag modes can be locked either way; `+k` and `+l` need a parameter
+    /// (`-k` and `-l` do not). List and status modes cannot be locked.
+    /// Returns the offending mode character on error.
+    pub fn parse(mlock: &str) -> Result<Self, char> {
+        Self::parse_inner(mlock, true)
+    }
+
+    /// Parse a stored MLOCK, skipping anything that cannot be locked.
+    pub fn parse_lenient(mlock: &str) -> Self {
+        Self::parse_inner(mlock, false).unwrap_or_default()
+    }
+
+    fn parse_inner(mlock: &str, strict: bool) -> Result<Self, char> {
+        let mut lock = Self::default();
+        let mut tokens = mlock.split_whitespace();
+        let Some(mode_str) = tokens.next() else {
+            return Ok(lock);
+        };
+
+        let mut adding = true;
+        for c in mode_str.chars() {
+            match c {
+                '+' => adding = true,
+                '-' => adding = false,
+                _ => {
+                    let mode = ChannelMode::from_char(c);
+                    let param = match mode {
+                        ChannelMode::Key if adding => tokens.next().map(str::to_string),
+                        ChannelMode::Limit if adding => tokens
+                            .next()
+                            .filter(|l| l.parse::<u32>().is_ok_and(|l| l > 0))
+                            .map(str::to_string),
+                        _ => None,
+                    };
+                    let lockable = match mode {
+                        ChannelMode::Key | ChannelMode::Limit => !adding || param.is_some(),
+                        ChannelMode::Unknown(_) => false,
+                        _ => !mode.takes_arg(),
+                    };
+                    if !lockable {
+                        if strict {
+                            return Err(c);
+                        }
+                        continue;
+                    }
+
+                    lock.on.retain(|(m, _)| *m != mode);
+                    lock.off.retain(|m| *m != mode);
+                    if adding {
+                        lock.on.push((mode, param));
+                    } else {
+                        lock.off.push(mode);
+                    }
+                }
+            }
+        }
+
+        if strict && let Some(extra) = tokens.next() {
+            return Err(extra.chars().next().unwrap_or(' '));
+        }
+        Ok(lock)
+    }
+
+    /// True if nothing is locked.
+    pub fn is_empty(&self) -> bool {
+        self.on.is_empty() && self.off.is_empty()
+    }
+
+    /// Check whether a mode change leaves the lock intact.
+    pub fn allows(&self, mode: &Mode<ChannelMode>) -> bool {
+        let target = mode.mode();
+        if mode.is_plus() {
+            if self.off.contains(target) {
+                return false;
+            }
+            // A locked key or limit cannot be changed to another value
+            !self
+                .on
+                .iter()
+                .any(|(m, param)| m == target && param.is_some() && param.as_deref() != mode.arg())
+        } else {
+            !self.on.iter().any(|(m, _)| m == target)
+        }
+    }
+
+    /// The mode changes that bring a channel in line with the lock.
+    pub fn modes(&self) -> Vec<Mode<ChannelMode>> {
+        self.on
+            .iter()
+            .map(|(mode, param)| Mode::plus(mode.clone(), param.as_deref()))
+            .chain(self.off.iter().map(|mode| Mode::minus(mode.clone(), None)))
+            .collect()
+    }
+}
+
+impl fmt::Display for Mlock {
+    /// Canonical form: `+ntk-s key`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.on.is_empty() {
+            f.write_str("+")?;
+            for (mode, _) in &self.on {
+                write!(f, "{}", mode)?;
+            }
+        }
+        if !self.off.is_empty() {
+            f.write_str("-")?;
+            for mode in &self.off {
+                write!(f, "{}", mode)?;
+            }
+        }
+        for param in self.on.iter().filter_map(|(_, param)| param.as_deref()) {
+            write!(f, " {}", param)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mlock() {
+        let lock = Mlock::parse("+nt-s").unwrap();
+        assert_eq!(
+            lock.modes(),
+            vec![
+                Mode::Plus(ChannelMode::NoExternalMessages, None),
+                Mode::Plus(ChannelMode::ProtectedTopic, None),
+                Mode::Minus(ChannelMode::Secret, None),
+            ]
+        );
+        assert_eq!(lock.to_string(), "+nt-s");
+
+        assert!(Mlock::parse("").unwrap().is_empty());
+        assert_eq!(Mlock::parse(" +n ").unwrap().to_string(), "+n");
+        // Later letters win, so the lock is canonical
+        assert_eq!(Mlock::parse("+n-t+it-n").unwrap().to_string(), "+it-n");
+    }
+
+    #[test]
+    fn test_parse_mlock_params() {
+        let lock = Mlock::parse("+ntkl-i secret 50").unwrap();
+        assert_eq!(lock.to_string(), "+ntkl-i secret 50");
+        assert!(
+            lock.modes()
+                .contains(&Mode::Plus(ChannelMode::Key, Some("secret".into())))
+        );
+        assert_eq!(Mlock::parse("-kl").unwrap().to_string(), "-kl");
+
+        assert_eq!(Mlock::parse("+k"), Err('k'));
+        assert_eq!(Mlock::parse("+l 0"), Err('l'));
+        assert_eq!(Mlock::parse("+n extra"), Err('e'));
+    }
+
+    #[test]
+    fn test_parse_mlock_rejects_unlockable() {
+        assert_eq!(Mlock::parse("+n?t"), Err('?'));
+        assert_eq!(Mlock::parse("+b"), Err('b'));
+        assert_eq!(Mlock::parse("+o"), Err('o'));
+        // Stored values are read leniently
+        assert_eq!(Mlock::parse_lenient("+n?t").to_string(), "+nt");
+    }
+
+    #[test]
+    fn test_mlock_allows() {
+        let lock = Mlock::parse("+ntk-s secret").unwrap();
+        assert!(!lock.allows(&Mode::Minus(ChannelMode::NoExternalMessages, None)));
+        assert!(!lock.allows(&Mode::Plus(ChannelMode::Secret, None)));
+        assert!(!lock.allows(&Mode::Minus(ChannelMode::Key, Some("secret".into()))));
+        assert!(!lock.allows(&Mode::Plus(ChannelMode::Key, Some("other".into()))));
+        assert!(lock.allows(&Mode::Plus(ChannelMode::Key, Some("secret".into()))));
+        assert!(lock.allows(&Mode::Plus(ChannelMode::Moderated, None)));
+        assert!(lock.allows(&Mode::Plus(ChannelMode::Oper, Some("nick".into()))));
+    }
+}
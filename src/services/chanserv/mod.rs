@@ -3,5 +3,34 @@
 //! Handles channel registration, access control, and moderation commands.
 
 mod commands;
+pub mod expire;
+pub mod mlock;
+pub mod sweep;
 
 pub use commands::ChanServ;
+
+use crate::services::ServiceEffect;
+use crate::state::Matrix;
+use slirc_proto::irc_to_lower;
+
+/// Forget the runtime state of a channel whose registration is gone: the
+/// registered-channel index and its BotServ assignment. Returns the bot's
+/// PART, if a bot was assigned.
+pub fn forget_registration(matrix: &Matrix, name: &str, reason: &str) -> Option<ServiceEffect> {
+    let name_lower = irc_to_lower(name);
+    matrix
+        .channel_manager
+        .registered_channels
+        .remove(&name_lower);
+    matrix
+        .service_manager
+        .botserv
+        .registry
+        .channels
+        .remove(&name_lower)
+        .map(|(_, assignment)| ServiceEffect::BotPart {
+            uid: assignment.bot_uid,
+            channel: name.to_string(),
+            reason: reason.to_string(),
+        })
+}
@@ -0,0 +1,56 @@
+//! Periodic AKICK sweep.
+//!
+//! Join-time checks only cover new members; the sweep removes members who
+//! match an AKICK added while they were already inside, or who changed
+//! nick into one.
+
+use crate::services::apply_effects_no_sender;
+use crate::state::Matrix;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+/// How often registered channels are swept.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Spawn the AKICK sweep background task.
+pub fn spawn_akick_sweep_task(matrix: Arc<Matrix>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut shutdown_rx = matrix.lifecycle_manager.shutdown_tx.subscribe();
+        loop {
+            tokio::select! {
+                _ = interval.tick() => sweep_registered_channels(&matrix).await,
+                _ = shutdown_rx.recv() => break,
+            }
+        }
+    });
+}
+
+/// Sweep every registered channel that currently exists.
+async fn sweep_registered_channels(matrix: &Arc<Matrix>) {
+    let channels: Vec<String> = matrix
+        .channel_manager
+        .registered_channels
+        .iter()
+        .filter(|name| matrix.channel_manager.channels.contains_key(name.as_str()))
+        .map(|name| name.clone())
+        .collect();
+
+    let chanserv = &matrix.service_manager.chanserv;
+    for name in channels {
+        let record = match chanserv.db.channels().find_by_name(&name).await {
+            Ok(Some(record)) => record,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!(channel = %name, error = ?e, "AKICK sweep: channel lookup failed");
+                continue;
+            }
+        };
+        let effects = chanserv.akick_sweep(matrix, &record).await;
+        if !effects.is_empty() {
+            apply_effects_no_sender(matrix, "ChanServ", effects).await;
+        }
+    }
+}
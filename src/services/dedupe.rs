@@ -0,0 +1,95 @@
+//! Per-user deduplication of service notices.
+//!
+//! Services can emit the same notice over and over (an enforcement warning
+//! on every message, a syntax error for every repeated command). Within the
+//! configured window only the first copy reaches the user; repeats from
+//! later effect batches are dropped. Identical lines inside one batch (e.g.
+//! blank lines in a HELP listing) are always delivered.
+
+use dashmap::DashMap;
+use slirc_proto::Message;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+/// Recently delivered service notices, per target UID.
+#[derive(Debug, Default)]
+pub struct NoticeDedupe {
+    /// UID -> (message hash -> last delivery).
+    recent: DashMap<String, HashMap<u64, Instant>>,
+}
+
+impl NoticeDedupe {
+    /// Decide whether `msg` may be delivered to `uid`.
+    ///
+    /// `batch_start` is when the current effect batch began applying; a copy
+    /// delivered at or after it belongs to the same batch and does not count.
+    /// A zero `window` disables deduplication.
+    pub fn admit(&self, uid: &str, msg: &Message, batch_start: Instant, window: Duration) -> bool {
+        if window.is_zero() {
+            return true;
+        }
+
+        let now = Instant::now();
+        let key = Self::key(msg);
+        let mut recent = self.recent.entry(uid.to_string()).or_default();
+        recent.retain(|_, sent| now.duration_since(*sent) < window);
+
+        match recent.get(&key) {
+            Some(sent) if *sent < batch_start => false,
+            _ => {
+                recent.insert(key, now);
+                true
+            }
+        }
+    }
+
+    /// Forget everything delivered to `uid` (on disconnect).
+    pub fn forget(&self, uid: &str) {
+        self.recent.remove(uid);
+    }
+
+    /// Hash of the notice's source and command; tags are ignored.
+    fn key(msg: &Message) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        msg.prefix.as_ref().map(|p| p.to_string()).hash(&mut hasher);
+        msg.command.to_string().hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slirc_proto::{Command, Prefix};
+
+    fn notice(text: &str) -> Message {
+        Message {
+            tags: None,
+            prefix: Some(Prefix::ServerName("NickServ".to_string())),
+            command: Command::NOTICE("*".to_string(), text.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_repeats_from_later_batches_are_dropped() {
+        let dedupe = NoticeDedupe::default();
+        let window = Duration::from_secs(5);
+
+        let batch = Instant::now();
+        assert!(dedupe.admit("001AAAAAA", &notice("warn"), batch, window));
+        // Same batch: delivered again
+        assert!(dedupe.admit("001AAAAAA", &notice("warn"), batch, window));
+
+        let batch = Instant::now() + Duration::from_millis(1);
+        assert!(!dedupe.admit("001AAAAAA", &notice("warn"), batch, window));
+        assert!(dedupe.admit("001AAAAAA", &notice("other"), batch, window));
+        assert!(dedupe.admit("001AAAAAB", &notice("warn"), batch, window));
+
+        // Disabled window, and a forgotten user, deliver again
+        assert!(dedupe.admit("001AAAAAA", &notice("warn"), batch, Duration::ZERO));
+        dedupe.forget("001AAAAAA");
+        assert!(dedupe.admit("001AAAAAA", &notice("warn"), batch, window));
+    }
+}
@@ -1,10 +1,34 @@
-use crate::handlers::{ResponseMiddleware, notify_extended_monitor_watchers};
-use crate::state::Matrix;
+use crate::handlers::server::part::part_remote_member;
+use crate::handlers::{
+    BanType, ResponseMiddleware, TargetUser, apply_user_modes_typed, disconnect_matching_ban,
+    force_join_local, notify_extended_monitor_watchers, record_user_mode_stats, server_reply,
+};
+use crate::state::actor::ChannelEvent;
 use crate::state::dashmap_ext::DashMapExt;
-use crate::state::observer::StateObserver;
-use slirc_proto::{ChannelMode, Command, Message, Mode, Prefix, irc_to_lower};
+use crate::state::observer::{GlobalBanType, StateObserver};
+use crate::state::{Matrix, MemberModes};
+use ipnet::IpNet;
+use slirc_proto::{
+    ChannelExt, ChannelMode, Command, Message, Mode, Prefix, Response, UserMode, irc_to_lower,
+};
 use std::sync::Arc;
-use tracing::info;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Send a services command (`SVSJOIN`, `SVSMODE`) toward the server that owns `target_uid`.
+async fn route_to_owner(matrix: &Arc<Matrix>, target_uid: &str, command: &str, args: Vec<String>) {
+    let mut params = vec![target_uid.to_string()];
+    params.extend(args);
+    let msg = Message {
+        tags: None,
+        prefix: Some(Prefix::new_from_str(matrix.server_info.sid.as_str())),
+        command: Command::Raw(command.to_string(), params),
+    };
+    matrix
+        .sync_manager
+        .route_to_remote_user(target_uid, Arc::new(msg))
+        .await;
+}
 
 /// Unified effect type returned by all service commands.
 ///
@@ -42,6 +66,14 @@ pub enum ServiceEffect {
         reason: String,
     },
 
+    /// Close one connection of a user, leaving any other sessions attached (NickServ SESSIONS KILL).
+    KillSession {
+        target_uid: String,
+        session_id: crate::state::client::SessionId,
+        killer: String,
+        reason: String,
+    },
+
     /// Kick a user from a channel (ChanServ CLEAR, AKICK enforcement).
     Kick {
         channel: String,
@@ -58,6 +90,15 @@ pub enum ServiceEffect {
         adding: bool,
     },
 
+    /// Add a ban mask to a channel (AKICK enforcement).
+    ChannelBan { channel: String, mask: String },
+
+    /// Set a channel topic as ChanServ (ChanServ TOPIC).
+    ChannelTopic { channel: String, topic: String },
+
+    /// Invite a user into a channel as ChanServ, bypassing +i (ChanServ INVITE).
+    Invite { channel: String, target_uid: String },
+
     /// Force nick change (enforcement).
     ForceNick {
         target_uid: String,
@@ -65,6 +106,76 @@ pub enum ServiceEffect {
         new_nick: String,
     },
 
+    /// Change a user's visible host (HostServ); `None` restores the cloak.
+    /// Channel members with the chghost capability see a CHGHOST.
+    SetVhost {
+        target_uid: String,
+        vhost: Option<String>,
+    },
+
+    /// Force a user into a channel (SVSJOIN).
+    /// A user on another server is reached by routing SVSJOIN to its server.
+    ForceJoin { target_uid: String, channel: String },
+
+    /// Change a user's modes as a service (SVSMODE); routed like `ForceJoin`.
+    /// Modes the user could not set themselves, such as `+o`, are refused.
+    ForceMode {
+        target_uid: String,
+        modes: Vec<Mode<UserMode>>,
+    },
+
+    /// Apply channel modes as a service, bypassing op checks (OperServ MODE).
+    ChannelModes {
+        channel: String,
+        setter: String,
+        modes: Vec<Mode<ChannelMode>>,
+    },
+
+    /// Add a network-wide user@host ban (OperServ AKILL).
+    /// Stored as a G-line, so it is propagated to peers and survives restarts.
+    Akill {
+        mask: String,
+        reason: String,
+        set_by: String,
+        /// Seconds until expiry, or `None` for a permanent ban.
+        duration: Option<i64>,
+    },
+
+    /// Remove an AKILL.
+    AkillRemove { mask: String },
+
+    /// Send a notice from a service to every user on the network (OperServ GLOBAL).
+    GlobalNotice { sender: String, text: String },
+
+    /// Set the number of connections allowed from an IP range (OperServ SESSION).
+    SessionLimit {
+        net: IpNet,
+        max_sessions: u32,
+        reason: Option<String>,
+        set_by: String,
+        /// Seconds until expiry, or `None` for a permanent limit.
+        duration: Option<i64>,
+    },
+
+    /// Remove a session limit, restoring the configured per-IP limit.
+    SessionLimitRemove { net: IpNet },
+
+    /// Bring a BotServ bot online under the given UID.
+    BotIntroduce { uid: String, bot: crate::db::Bot },
+
+    /// Put a bot into a channel.
+    BotJoin { uid: String, channel: String },
+
+    /// Take a bot out of a channel.
+    BotPart {
+        uid: String,
+        channel: String,
+        reason: String,
+    },
+
+    /// Take a bot off the network.
+    BotQuit { uid: String, reason: String },
+
     /// Broadcast account change to all shared channels (account-notify capability).
     /// Sends `:old_prefix ACCOUNT new_account` to channel members with account-notify.
     /// If new_account is "*", user logged out.
@@ -84,8 +195,9 @@ pub async fn apply_effects(
     sender: &ResponseMiddleware<'_>,
     effects: Vec<ServiceEffect>,
 ) {
+    let batch_start = Instant::now();
     for effect in effects {
-        apply_effect(matrix, nick, sender, effect).await;
+        apply_effect_impl(matrix, nick, Some(sender), effect, batch_start).await;
     }
 }
 
@@ -133,8 +245,9 @@ pub async fn apply_effects_no_sender(
     nick: &str,
     effects: Vec<ServiceEffect>,
 ) {
+    let batch_start = Instant::now();
     for effect in effects {
-        apply_effect_no_sender(matrix, nick, effect).await;
+        apply_effect_impl(matrix, nick, None, effect, batch_start).await;
     }
 }
 
@@ -146,7 +259,7 @@ pub async fn apply_effect_no_sender(matrix: &Arc<Matrix>, nick: &str, effect: Se
     // we will just inline the unified logic here or call a shared private helper.
 
     // Actually, let's call the shared private implementation
-    apply_effect_impl(matrix, nick, None, effect).await;
+    apply_effect_impl(matrix, nick, None, effect, Instant::now()).await;
 }
 
 /// Apply a single service effect to Matrix state.
@@ -156,18 +269,31 @@ pub async fn apply_effect(
     sender: &ResponseMiddleware<'_>,
     effect: ServiceEffect,
 ) {
-    apply_effect_impl(matrix, nick, Some(sender), effect).await;
+    apply_effect_impl(matrix, nick, Some(sender), effect, Instant::now()).await;
 }
 
 /// Shared implementation for effect application.
+///
+/// `batch_start` marks when the enclosing batch began; replies already
+/// delivered before it are deduplicated (see [`crate::services::dedupe::NoticeDedupe`]).
 async fn apply_effect_impl(
     matrix: &Arc<Matrix>,
     _nick: &str,
     sender: Option<&ResponseMiddleware<'_>>,
     effect: ServiceEffect,
+    batch_start: Instant,
 ) {
     match effect {
         ServiceEffect::Reply { target_uid, msg } => {
+            let window = Duration::from_secs(matrix.config.limits.service_notice_dedupe_secs);
+            if !matrix
+                .service_manager
+                .notice_dedupe
+                .admit(&target_uid, &msg, batch_start, window)
+            {
+                return;
+            }
+
             // Use the caller's ResponseMiddleware when available to ensure replies
             // are routed to the correct session (important for multiclient/bouncer
             // where get_first_sender may return a different session's sender).
@@ -190,16 +316,18 @@ async fn apply_effect_impl(
                 // Update user state
                 if let Some(user_arc) = matrix.user_manager.users.get_cloned(&target_uid) {
                     let mut user = user_arc.write().await;
-                    user.modes.registered = true;
-                    user.account = Some(account.clone());
+                    let now = matrix.clock();
+                    user.set_mode('r', true, now);
+                    user.set_account(Some(account.clone()), now);
                     user.account_id = account_id;
                     user.metadata = metadata;
                 }
 
-                // Broadcast to S2S
+                // Broadcast to S2S: the account itself, then the UID for +r
                 matrix
-                    .sync_manager
+                    .observers
                     .on_account_change(&target_uid, Some(&account), None);
+                matrix.user_manager.notify_observer(&target_uid, None).await;
 
                 // Clear enforce timer
                 matrix.user_manager.enforce_timers.remove(&target_uid);
@@ -228,15 +356,15 @@ async fn apply_effect_impl(
                 // Update user state
                 if let Some(user_arc) = matrix.user_manager.users.get_cloned(&target_uid) {
                     let mut user = user_arc.write().await;
-                    user.modes.registered = false;
-                    user.account = None;
+                    let now = matrix.clock();
+                    user.set_mode('r', false, now);
+                    user.set_account(None, now);
                     user.account_id = None;
                 }
 
-                // Broadcast to S2S
-                matrix
-                    .sync_manager
-                    .on_account_change(&target_uid, None, None);
+                // Broadcast to S2S: the logout, then the UID for -r
+                matrix.observers.on_account_change(&target_uid, None, None);
+                matrix.user_manager.notify_observer(&target_uid, None).await;
 
                 // Send MODE -r to user
                 let mode_msg = Message {
@@ -274,6 +402,27 @@ async fn apply_effect_impl(
             info!(uid = %target_uid, killer = %killer, reason = %reason, "User killed by service");
         }
 
+        ServiceEffect::KillSession {
+            target_uid,
+            session_id,
+            killer,
+            reason,
+        } => {
+            // The connection closes on the ERROR once its sender is gone, then
+            // detaches the session (or quits the user) with this reason.
+            if let Some(tx) = matrix
+                .user_manager
+                .take_session_sender(&target_uid, session_id)
+            {
+                let error = Message::from(Command::ERROR(format!(
+                    "Closing Link: (Session killed by {}: {})",
+                    killer, reason
+                )));
+                let _ = tx.send(Arc::new(error)).await;
+                info!(uid = %target_uid, session = %session_id, killer = %killer, "Session killed by service");
+            }
+        }
+
         ServiceEffect::ChannelMode {
             channel,
             target_uid,
@@ -316,6 +465,7 @@ async fn apply_effect_impl(
                             target_uids,
                             force: true,
                             nanotime: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+                            list_expires_at: None,
                         },
                         reply_tx: tx,
                     };
@@ -326,6 +476,113 @@ async fn apply_effect_impl(
             }
         }
 
+        ServiceEffect::ChannelBan { channel, mask } => {
+            let channel_lower = irc_to_lower(&channel);
+            if let Some(c) = matrix.channel_manager.channels.get(&channel_lower) {
+                let channel_sender = c.value().clone();
+
+                let sender_prefix = Prefix::new(
+                    "ChanServ".to_string(),
+                    "ChanServ".to_string(),
+                    "services.".to_string(),
+                );
+
+                // Applied like any other MODE, so members and peers see +b
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                let event = crate::state::actor::ChannelEvent::ApplyModes {
+                    params: crate::state::actor::ModeParams {
+                        sender_uid: "ChanServ".to_string(),
+                        sender_prefix,
+                        modes: vec![Mode::plus(ChannelMode::Ban, Some(&mask))],
+                        target_uids: std::collections::HashMap::new(),
+                        force: true,
+                        nanotime: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+                        list_expires_at: None,
+                    },
+                    reply_tx: tx,
+                };
+
+                let _ = channel_sender.send(event).await;
+                let _ = rx.await;
+            }
+        }
+
+        ServiceEffect::ChannelTopic { channel, topic } => {
+            let channel_lower = irc_to_lower(&channel);
+            if let Some(c) = matrix.channel_manager.channels.get(&channel_lower) {
+                let channel_sender = c.value().clone();
+
+                let now = chrono::Utc::now();
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                let event = crate::state::actor::ChannelEvent::SetTopic {
+                    params: crate::state::actor::TopicParams {
+                        sender_uid: "ChanServ".to_string(),
+                        sender_prefix: Prefix::new(
+                            "ChanServ".to_string(),
+                            "ChanServ".to_string(),
+                            "services.".to_string(),
+                        ),
+                        topic,
+                        msgid: uuid::Uuid::new_v4().to_string(),
+                        timestamp: now.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+                        force: true,
+                        cap: None,
+                        nanotime: now.timestamp_nanos_opt().unwrap_or(0),
+                    },
+                    reply_tx: tx,
+                };
+
+                let _ = channel_sender.send(event).await;
+                let _ = rx.await;
+            }
+        }
+
+        ServiceEffect::Invite {
+            channel,
+            target_uid,
+        } => {
+            if let Some(target_nick) = resolve_user_nick(matrix, &target_uid).await {
+                let channel_lower = irc_to_lower(&channel);
+                if let Some(c) = matrix.channel_manager.channels.get(&channel_lower) {
+                    let channel_sender = c.value().clone();
+
+                    let sender_prefix = Prefix::new(
+                        "ChanServ".to_string(),
+                        "ChanServ".to_string(),
+                        "services.".to_string(),
+                    );
+
+                    let (tx, rx) = tokio::sync::oneshot::channel();
+                    let event = crate::state::actor::ChannelEvent::Invite {
+                        params: crate::state::actor::InviteParams {
+                            sender_uid: "ChanServ".to_string(),
+                            sender_prefix: sender_prefix.clone(),
+                            target_uid: target_uid.clone(),
+                            target_nick: target_nick.clone(),
+                            force: true,
+                            cap: None,
+                        },
+                        reply_tx: tx,
+                    };
+
+                    // Once the invite is recorded, tell the user like INVITE does
+                    if let Ok(_) = channel_sender.send(event).await
+                        && let Ok(Ok(())) = rx.await
+                    {
+                        let invite = Message {
+                            tags: None,
+                            prefix: Some(sender_prefix),
+                            command: Command::INVITE(target_nick, channel),
+                        };
+                        matrix
+                            .user_manager
+                            .send_to_uid(&target_uid, Arc::new(invite))
+                            .await;
+                    }
+                }
+            }
+        }
+
         ServiceEffect::Kick {
             channel,
             target_uid,
@@ -411,8 +668,9 @@ async fn apply_effect_impl(
 
             if let Some(user_arc) = matrix.user_manager.users.get_cloned(&target_uid) {
                 let mut user = user_arc.write().await;
-                user.nick = new_nick.clone();
+                user.set_nick(new_nick.clone(), matrix.clock());
             }
+            matrix.user_manager.notify_observer(&target_uid, None).await;
 
             // Build NICK message
             let nick_msg = Message {
@@ -436,6 +694,161 @@ async fn apply_effect_impl(
             info!(uid = %target_uid, old = %old_nick, new = %new_nick, "Forced nick change");
         }
 
+        ServiceEffect::SetVhost { target_uid, vhost } => {
+            let (nick, username, old_host, new_host, channels) = {
+                let Some(user_arc) = matrix.user_manager.users.get_cloned(&target_uid) else {
+                    return;
+                };
+                let mut user = user_arc.write().await;
+                let new_host = vhost.unwrap_or_else(|| {
+                    let hot_config = matrix.hot_config.read();
+                    crate::security::cloaking::cloak_user_host(
+                        &user.ip,
+                        &user.host,
+                        &hot_config.cloak_secret,
+                        &hot_config.cloak_suffix,
+                    )
+                });
+                if new_host == user.visible_host {
+                    return;
+                }
+                let old_host = user.visible_host.clone();
+                user.set_visible_host(new_host.clone(), matrix.clock());
+                (
+                    user.nick.clone(),
+                    user.user.clone(),
+                    old_host,
+                    new_host,
+                    user.channels.iter().cloned().collect::<Vec<_>>(),
+                )
+            };
+            matrix.user_manager.notify_observer(&target_uid, None).await;
+
+            let chghost_msg = Message {
+                tags: None,
+                prefix: Some(Prefix::new(&nick, &username, &old_host)),
+                command: Command::CHGHOST(username.clone(), new_host.clone()),
+            };
+
+            for channel_name in &channels {
+                matrix
+                    .channel_manager
+                    .broadcast_to_channel_with_cap(
+                        channel_name,
+                        chghost_msg.clone(),
+                        Some(&target_uid),
+                        Some("chghost"),
+                        None,
+                    )
+                    .await;
+            }
+            notify_extended_monitor_watchers(matrix, &nick, chghost_msg, "chghost").await;
+
+            let hosthidden = server_reply(
+                &matrix.server_info.name,
+                Response::RPL_HOSTHIDDEN,
+                vec![
+                    nick,
+                    new_host.clone(),
+                    "is now your displayed host".to_string(),
+                ],
+            );
+            matrix
+                .user_manager
+                .send_to_uid(&target_uid, Arc::new(hosthidden))
+                .await;
+
+            info!(uid = %target_uid, old_host = %old_host, new_host = %new_host, "Vhost changed by service");
+        }
+
+        ServiceEffect::ForceJoin {
+            target_uid,
+            channel,
+        } => {
+            if !target_uid.starts_with(matrix.server_info.sid.as_str()) {
+                route_to_owner(matrix, &target_uid, "SVSJOIN", vec![channel]).await;
+                return;
+            }
+            if !channel.as_str().is_channel_name() {
+                warn!(uid = %target_uid, channel = %channel, "ForceJoin to invalid channel name");
+                return;
+            }
+            let Some(user_arc) = matrix.user_manager.users.get_cloned(&target_uid) else {
+                return;
+            };
+            let (nick, username, host) = {
+                let user = user_arc.read().await;
+                if user.channels.contains(&irc_to_lower(&channel)) {
+                    return;
+                }
+                (
+                    user.nick.clone(),
+                    user.user.clone(),
+                    user.visible_host.clone(),
+                )
+            };
+            let target = TargetUser {
+                uid: &target_uid,
+                nick: &nick,
+                user: &username,
+                host: &host,
+            };
+            let sender = matrix.user_manager.get_first_sender(&target_uid);
+            if let Err(e) = force_join_local(
+                matrix,
+                &target,
+                &channel,
+                MemberModes::default(),
+                sender.as_ref(),
+                None,
+            )
+            .await
+            {
+                warn!(uid = %target_uid, channel = %channel, error = %e, "Forced join failed");
+                return;
+            }
+            info!(uid = %target_uid, channel = %channel, "Forced join");
+        }
+
+        ServiceEffect::ForceMode { target_uid, modes } => {
+            if !target_uid.starts_with(matrix.server_info.sid.as_str()) {
+                let mut args = vec![modes.iter().map(Mode::flag).collect::<String>()];
+                args.extend(modes.iter().filter_map(|m| m.arg().map(str::to_string)));
+                route_to_owner(matrix, &target_uid, "SVSMODE", args).await;
+                return;
+            }
+            let Some(user_arc) = matrix.user_manager.users.get_cloned(&target_uid) else {
+                return;
+            };
+            let (applied, mode_msg) = {
+                let mut user = user_arc.write().await;
+                let (applied, rejected) = apply_user_modes_typed(&mut user.modes, &modes);
+                if !rejected.is_empty() {
+                    warn!(uid = %target_uid, rejected = ?rejected, "ForceMode refused modes");
+                }
+                if applied.is_empty() {
+                    return;
+                }
+                let changed = applied
+                    .iter()
+                    .filter_map(|m| m.mode().to_string().chars().next());
+                user.stamp_modes(changed, matrix.clock());
+                let mode_msg = Message {
+                    tags: None,
+                    prefix: Some(Prefix::new(&user.nick, &user.user, &user.visible_host)),
+                    command: Command::UserMODE(user.nick.clone(), applied.clone()),
+                };
+                (applied, mode_msg)
+            };
+            matrix.user_manager.notify_observer(&target_uid, None).await;
+            record_user_mode_stats(matrix, &applied);
+            matrix
+                .user_manager
+                .send_to_uid(&target_uid, Arc::new(mode_msg))
+                .await;
+            info!(uid = %target_uid, modes = ?applied, "User modes changed by service");
+        }
+
         ServiceEffect::BroadcastAccount {
             target_uid,
             new_account,
@@ -486,10 +899,252 @@ async fn apply_effect_impl(
                 Some(new_account.as_str())
             };
             matrix
-                .sync_manager
+                .observers
                 .on_account_change(&target_uid, account_opt, None);
 
             info!(uid = %target_uid, account = %new_account, "Broadcast account change");
         }
+
+        ServiceEffect::ChannelModes {
+            channel,
+            setter,
+            modes,
+        } => {
+            let channel_lower = irc_to_lower(&channel);
+            let Some(channel_sender) = matrix.channel_manager.channels.get_cloned(&channel_lower)
+            else {
+                return;
+            };
+
+            // Resolve target UIDs for status modes
+            let mut target_uids = std::collections::HashMap::new();
+            for mode in &modes {
+                if matches!(
+                    mode.mode(),
+                    ChannelMode::Founder
+                        | ChannelMode::Admin
+                        | ChannelMode::Oper
+                        | ChannelMode::Halfop
+                        | ChannelMode::Voice
+                ) && let Some(nick) = mode.arg()
+                    && let Some(uids) = matrix.user_manager.nicks.get(&irc_to_lower(nick))
+                {
+                    target_uids.insert(nick.to_string(), uids.clone());
+                }
+            }
+
+            let sender_prefix =
+                Prefix::new(setter.clone(), setter.clone(), "services.".to_string());
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            let event = crate::state::actor::ChannelEvent::ApplyModes {
+                params: crate::state::actor::ModeParams {
+                    sender_uid: setter.clone(),
+                    sender_prefix,
+                    modes,
+                    target_uids,
+                    force: true,
+                    nanotime: chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
+                    list_expires_at: None,
+                },
+                reply_tx: tx,
+            };
+
+            let _ = channel_sender.send(event).await;
+            let _ = rx.await;
+        }
+
+        ServiceEffect::Akill {
+            mask,
+            reason,
+            set_by,
+            duration,
+        } => {
+            if let Err(e) = matrix
+                .db
+                .bans()
+                .add_gline(&mask, Some(&reason), &set_by, duration)
+                .await
+            {
+                warn!(mask = %mask, error = %e, "Failed to store AKILL");
+            }
+            let expires_at = duration.map(|d| chrono::Utc::now().timestamp() + d);
+            matrix
+                .security_manager
+                .ban_cache
+                .add_gline(mask.clone(), reason.clone(), expires_at);
+            matrix.observers.on_ban_add(
+                GlobalBanType::Gline,
+                &mask,
+                &reason,
+                &set_by,
+                duration,
+                None,
+            );
+
+            let disconnected =
+                disconnect_matching_ban(matrix, BanType::Gline, &mask, &reason).await;
+            info!(
+                target: "audit",
+                oper = %set_by,
+                target = %mask,
+                reason = %reason,
+                duration = ?duration,
+                disconnected = disconnected,
+                "AKILL added"
+            );
+        }
+
+        ServiceEffect::AkillRemove { mask } => {
+            if let Err(e) = matrix.db.bans().remove_gline(&mask).await {
+                warn!(mask = %mask, error = %e, "Failed to remove AKILL");
+            }
+            matrix.security_manager.ban_cache.remove_gline(&mask);
+            matrix
+                .sync_manager
+                .record_ban_removal(GlobalBanType::Gline, &mask);
+            matrix
+                .observers
+                .on_ban_remove(GlobalBanType::Gline, &mask, None);
+            info!(target: "audit", target = %mask, "AKILL removed");
+        }
+
+        ServiceEffect::GlobalNotice { sender, text } => {
+            let recipients: Vec<_> = matrix
+                .user_manager
+                .users
+                .iter()
+                .filter(|entry| !matrix.user_manager.services.contains(entry.key()))
+                .map(|entry| (entry.key().clone(), entry.value().clone()))
+                .collect();
+
+            let count = recipients.len();
+            for (uid, user_arc) in recipients {
+                let nick = user_arc.read().await.nick.clone();
+                let msg = Message {
+                    tags: None,
+                    prefix: Some(Prefix::ServerName(sender.clone())),
+                    command: Command::NOTICE(nick.clone(), text.clone()),
+                };
+                route_to_user(matrix, &uid, &nick, msg).await;
+            }
+            info!(sender = %sender, recipients = count, "Global notice sent");
+        }
+
+        ServiceEffect::SessionLimit {
+            net,
+            max_sessions,
+            reason,
+            set_by,
+            duration,
+        } => {
+            if let Err(e) = matrix
+                .db
+                .bans()
+                .add_session_limit(
+                    &net.to_string(),
+                    max_sessions,
+                    reason.as_deref(),
+                    &set_by,
+                    duration,
+                )
+                .await
+            {
+                warn!(net = %net, error = %e, "Failed to store session limit");
+            }
+            let expires_at = duration.map(|d| chrono::Utc::now().timestamp() + d);
+            matrix
+                .security_manager
+                .rate_limiter
+                .set_session_limit(net, max_sessions, expires_at);
+            info!(target: "audit", oper = %set_by, net = %net, max_sessions, "Session limit set");
+        }
+
+        ServiceEffect::SessionLimitRemove { net } => {
+            if let Err(e) = matrix
+                .db
+                .bans()
+                .remove_session_limit(&net.to_string())
+                .await
+            {
+                warn!(net = %net, error = %e, "Failed to remove session limit");
+            }
+            matrix
+                .security_manager
+                .rate_limiter
+                .remove_session_limit(net);
+            info!(target: "audit", net = %net, "Session limit removed");
+        }
+
+        ServiceEffect::BotIntroduce { uid, bot } => {
+            let user = crate::services::botserv::bot_user(matrix, &uid, &bot);
+            matrix.user_manager.register_service_user(user);
+            matrix.user_manager.notify_observer(&uid, None).await;
+            info!(uid = %uid, nick = %bot.nick, "Bot introduced");
+        }
+
+        ServiceEffect::BotJoin { uid, channel } => {
+            let Some(user_arc) = matrix.user_manager.users.get_cloned(&uid) else {
+                warn!(uid = %uid, "BotJoin for unknown bot");
+                return;
+            };
+            let (nick, join_msg_extended, join_msg_standard) = {
+                let user = user_arc.read().await;
+                let prefix = Prefix::new(
+                    user.nick.clone(),
+                    user.user.clone(),
+                    user.visible_host.clone(),
+                );
+                let extended = Message {
+                    tags: None,
+                    prefix: Some(prefix.clone()),
+                    command: Command::JOIN(
+                        channel.clone(),
+                        Some("*".to_string()),
+                        Some(user.realname.clone()),
+                    ),
+                };
+                let standard = Message {
+                    tags: None,
+                    prefix: Some(prefix),
+                    command: Command::JOIN(channel.clone(), None, None),
+                };
+                (user.nick.clone(), extended, standard)
+            };
+
+            let tx = matrix
+                .channel_manager
+                .get_or_create_actor(channel.clone(), Arc::downgrade(matrix))
+                .await;
+            let event = ChannelEvent::RemoteJoin {
+                uid: uid.clone(),
+                nick,
+                ts: 0,
+                join_msg_extended: Box::new(join_msg_extended),
+                join_msg_standard: Box::new(join_msg_standard),
+                source: matrix.server_id.clone(),
+            };
+            if let Err(e) = tx.send(event).await {
+                warn!(channel = %channel, error = %e, "Failed to send bot JOIN to channel actor");
+                return;
+            }
+            user_arc
+                .write()
+                .await
+                .channels
+                .insert(irc_to_lower(&channel));
+        }
+
+        ServiceEffect::BotPart {
+            uid,
+            channel,
+            reason,
+        } => {
+            part_remote_member(matrix, &uid, &channel, Some(reason)).await;
+        }
+
+        ServiceEffect::BotQuit { uid, reason } => {
+            matrix.disconnect_user(&uid, &reason).await;
+            info!(uid = %uid, "Bot removed");
+        }
     }
 }
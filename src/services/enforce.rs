@@ -1,8 +1,10 @@
 //! Nick enforcement background task.
 //!
 //! Monitors enforce_timers in the Matrix and force-renames users who
-//! don't identify within the timeout period.
+//! don't identify within the timeout period. The timeout depends on the
+//! account's ENFORCE level.
 
+use crate::db::{Account, EnforceLevel};
 use crate::handlers::ResponseMiddleware;
 use crate::services::{ServiceEffect, apply_effect};
 use crate::state::Matrix;
@@ -13,6 +15,33 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tracing::{debug, info};
 
+/// Delay before renaming at ENFORCE ON.
+const ENFORCE_DELAY_SECS: u64 = 60;
+
+/// Delay before renaming at ENFORCE QUICK.
+const ENFORCE_QUICK_DELAY_SECS: u64 = 20;
+
+/// How long after the account last identified a returning user still gets
+/// the full delay, whatever the level (e.g. reconnecting after a netsplit).
+const IDENTIFY_GRACE_SECS: i64 = 300;
+
+/// How long a user of one of the account's nicks has to identify before
+/// being renamed, or `None` if the account does not enforce its nicknames.
+pub fn enforce_delay(account: &Account, now: i64) -> Option<Duration> {
+    let secs = match account.enforce {
+        EnforceLevel::Off => return None,
+        EnforceLevel::On => ENFORCE_DELAY_SECS,
+        EnforceLevel::Quick => ENFORCE_QUICK_DELAY_SECS,
+        EnforceLevel::Strict => 0,
+    };
+    let secs = if now - account.last_seen_at < IDENTIFY_GRACE_SECS {
+        secs.max(ENFORCE_DELAY_SECS)
+    } else {
+        secs
+    };
+    Some(Duration::from_secs(secs))
+}
+
 /// Spawn the nick enforcement background task.
 ///
 /// This task runs every 5 seconds and checks for expired enforcement timers.
@@ -123,3 +152,40 @@ async fn generate_guest_nick(matrix: &Arc<Matrix>) -> String {
         // If taken, loop and try again
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(enforce: EnforceLevel, last_seen_at: i64) -> Account {
+        Account {
+            id: 1,
+            name: "alice".to_string(),
+            email: None,
+            registered_at: 0,
+            last_seen_at,
+            enforce,
+            hide_email: false,
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_enforce_delay_levels() {
+        let now = 10_000;
+        let delay = |level| enforce_delay(&account(level, 0), now);
+        assert_eq!(delay(EnforceLevel::Off), None);
+        assert_eq!(delay(EnforceLevel::On), Some(Duration::from_secs(60)));
+        assert_eq!(delay(EnforceLevel::Quick), Some(Duration::from_secs(20)));
+        assert_eq!(delay(EnforceLevel::Strict), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_enforce_delay_identify_grace() {
+        let now = 10_000;
+        let recent = account(EnforceLevel::Strict, now - 30);
+        assert_eq!(enforce_delay(&recent, now), Some(Duration::from_secs(60)));
+        let off = account(EnforceLevel::Off, now - 30);
+        assert_eq!(enforce_delay(&off, now), None);
+    }
+}
@@ -0,0 +1,149 @@
+//! HostServ command handlers.
+//!
+//! Account owners request and toggle vhosts (`vhost.rs`); operators review
+//! the requests (`review.rs`).
+
+mod review;
+mod vhost;
+
+use crate::db::Database;
+use crate::services::base::ServiceBase;
+use crate::services::{Service, ServiceEffect};
+use crate::state::Matrix;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Maximum vhost length, matching the VHOST oper command.
+const MAX_VHOST_LEN: usize = 64;
+
+/// Returns true if the command modifies the vhost database.
+fn is_write_command(command: &str) -> bool {
+    matches!(command, "REQUEST" | "ACTIVATE" | "REJECT" | "ON" | "OFF")
+}
+
+/// Result of a HostServ command - a list of effects to apply.
+pub type HostServResult = Vec<ServiceEffect>;
+
+/// HostServ service.
+pub struct HostServ {
+    db: Database,
+}
+
+impl ServiceBase for HostServ {
+    fn service_name(&self) -> &'static str {
+        "HostServ"
+    }
+
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+#[async_trait]
+impl Service for HostServ {
+    fn name(&self) -> &'static str {
+        "HostServ"
+    }
+
+    fn aliases(&self) -> Vec<&'static str> {
+        vec!["HS"]
+    }
+
+    async fn handle(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        text: &str,
+    ) -> Vec<ServiceEffect> {
+        self.handle_command(matrix, uid, nick, text).await
+    }
+}
+
+impl HostServ {
+    /// Create a new HostServ service.
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Handle a PRIVMSG to HostServ.
+    /// Returns a list of effects that the caller should apply.
+    pub async fn handle_command(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        text: &str,
+    ) -> HostServResult {
+        let parts: Vec<&str> = text.split_whitespace().collect();
+        if parts.is_empty() {
+            return self.help_reply(uid);
+        }
+
+        let command = parts[0].to_uppercase();
+        let args = &parts[1..];
+
+        if matrix.lifecycle_manager.in_maintenance() && is_write_command(&command) {
+            return self.read_only_reply(uid);
+        }
+
+        match command.as_str() {
+            "REQUEST" => self.handle_request(matrix, uid, nick, args).await,
+            "ON" => self.handle_toggle(matrix, uid, true).await,
+            "OFF" => self.handle_toggle(matrix, uid, false).await,
+            "ACTIVATE" => self.handle_activate(matrix, uid, nick, args).await,
+            "REJECT" => self.handle_reject(matrix, uid, nick, args).await,
+            "LIST" => self.handle_list(matrix, uid).await,
+            "HELP" => self.help_reply(uid),
+            _ => self.unknown_command(uid, &command),
+        }
+    }
+
+    // ========== HostServ-specific helper methods ==========
+
+    /// Create a single reply effect.
+    fn reply_effect(&self, target_uid: &str, text: &str) -> ServiceEffect {
+        <Self as ServiceBase>::reply_effect(self, target_uid, text)
+    }
+
+    /// Create an error reply.
+    fn error_reply(&self, uid: &str, text: &str) -> HostServResult {
+        <Self as ServiceBase>::error_reply(self, uid, text)
+    }
+
+    /// Get user's account ID if identified.
+    async fn get_user_account_id(&self, matrix: &Arc<Matrix>, uid: &str) -> Option<i64> {
+        <Self as ServiceBase>::get_user_account_id(self, matrix, uid).await
+    }
+
+    /// UIDs of the local clients logged in to `account`.
+    fn account_uids(&self, matrix: &Arc<Matrix>, account: &str) -> Vec<String> {
+        let local_sid = matrix.server_info.sid.as_str();
+        matrix
+            .user_manager
+            .index
+            .by_account(account)
+            .into_iter()
+            .filter(|uid| uid.starts_with(local_sid) && !matrix.user_manager.services.contains(uid))
+            .collect()
+    }
+
+    /// Create help reply.
+    fn help_reply(&self, uid: &str) -> HostServResult {
+        vec![
+            self.reply_effect(uid, "***** HostServ Help *****"),
+            self.reply_effect(uid, "HostServ assigns virtual hosts (vhosts) to accounts."),
+            self.reply_effect(uid, " "),
+            self.reply_effect(uid, "Available commands:"),
+            self.reply_effect(uid, "  REQUEST <vhost>            - Request a vhost"),
+            self.reply_effect(uid, "  ON                         - Use your vhost"),
+            self.reply_effect(uid, "  OFF                        - Stop using your vhost"),
+            self.reply_effect(uid, " "),
+            self.reply_effect(uid, "Operator commands:"),
+            self.reply_effect(uid, "  LIST                       - List pending requests"),
+            self.reply_effect(uid, "  ACTIVATE <account>         - Approve a request"),
+            self.reply_effect(uid, "  REJECT <account> [reason]  - Reject a request"),
+            self.reply_effect(uid, "***** End of Help *****"),
+        ]
+    }
+}
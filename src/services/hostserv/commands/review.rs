@@ -0,0 +1,170 @@
+//! Operator HostServ commands: ACTIVATE, REJECT, LIST.
+
+use super::{HostServ, HostServResult};
+use crate::db::Account;
+use crate::services::ServiceEffect;
+use crate::services::base::ServiceBase;
+use crate::state::Matrix;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+impl HostServ {
+    /// Resolve the account named by an oper command, replying on failure.
+    async fn resolve_review_target(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        args: &[&str],
+        syntax: &str,
+    ) -> Result<Account, HostServResult> {
+        if !self.is_oper(matrix, uid).await {
+            return Err(self.error_reply(uid, "Access denied. This command is for operators."));
+        }
+        let Some(&name) = args.first() else {
+            return Err(self.error_reply(uid, syntax));
+        };
+        match self.db.accounts().find_by_name(name).await {
+            Ok(Some(account)) => Ok(account),
+            _ => {
+                Err(self.error_reply(uid, &format!("Account \x02{}\x02 is not registered.", name)))
+            }
+        }
+    }
+
+    /// Handle ACTIVATE command.
+    pub(super) async fn handle_activate(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> HostServResult {
+        let account = match self
+            .resolve_review_target(matrix, uid, args, "Syntax: ACTIVATE <account>")
+            .await
+        {
+            Ok(account) => account,
+            Err(reply) => return reply,
+        };
+
+        let vhost = match self.db.vhosts().activate(account.id, nick).await {
+            Ok(Some(vhost)) => vhost,
+            Ok(None) => {
+                return self.error_reply(
+                    uid,
+                    &format!("\x02{}\x02 has no pending vhost request.", account.name),
+                );
+            }
+            Err(e) => {
+                warn!(account = %account.name, error = ?e, "Vhost activation failed");
+                return self.error_reply(uid, "Database error. Please try again later.");
+            }
+        };
+
+        info!(oper = %nick, account = %account.name, vhost = %vhost, "Vhost activated");
+        let mut effects = vec![self.reply_effect(
+            uid,
+            &format!(
+                "Vhost \x02{}\x02 activated for \x02{}\x02.",
+                vhost, account.name
+            ),
+        )];
+        for target_uid in self.account_uids(matrix, &account.name) {
+            effects.push(self.reply_effect(
+                &target_uid,
+                &format!("Your requested vhost \x02{}\x02 has been activated.", vhost),
+            ));
+            effects.push(ServiceEffect::SetVhost {
+                target_uid,
+                vhost: Some(vhost.clone()),
+            });
+        }
+        effects
+    }
+
+    /// Handle REJECT command.
+    pub(super) async fn handle_reject(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> HostServResult {
+        let account = match self
+            .resolve_review_target(matrix, uid, args, "Syntax: REJECT <account> [reason]")
+            .await
+        {
+            Ok(account) => account,
+            Err(reply) => return reply,
+        };
+
+        match self.db.vhosts().reject(account.id).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return self.error_reply(
+                    uid,
+                    &format!("\x02{}\x02 has no pending vhost request.", account.name),
+                );
+            }
+            Err(e) => {
+                warn!(account = %account.name, error = ?e, "Vhost rejection failed");
+                return self.error_reply(uid, "Database error. Please try again later.");
+            }
+        }
+
+        info!(oper = %nick, account = %account.name, "Vhost request rejected");
+        let notice = match args.get(1..).filter(|reason| !reason.is_empty()) {
+            Some(reason) => format!("Your vhost request was rejected: {}", reason.join(" ")),
+            None => "Your vhost request was rejected.".to_string(),
+        };
+        let mut effects = vec![self.reply_effect(
+            uid,
+            &format!("Vhost request for \x02{}\x02 rejected.", account.name),
+        )];
+        for target_uid in self.account_uids(matrix, &account.name) {
+            effects.push(self.reply_effect(&target_uid, &notice));
+        }
+        effects
+    }
+
+    /// Handle LIST command.
+    pub(super) async fn handle_list(&self, matrix: &Arc<Matrix>, uid: &str) -> HostServResult {
+        if !self.is_oper(matrix, uid).await {
+            return self.error_reply(uid, "Access denied. This command is for operators.");
+        }
+
+        let requests = match self.db.vhosts().pending_requests().await {
+            Ok(requests) => requests,
+            Err(e) => {
+                warn!(error = ?e, "Failed to list vhost requests");
+                return self.error_reply(uid, "Database error. Please try again later.");
+            }
+        };
+
+        if requests.is_empty() {
+            return self.error_reply(uid, "There are no pending vhost requests.");
+        }
+
+        let mut effects = vec![self.reply_effect(uid, "Pending vhost requests:")];
+        for (i, request) in requests.iter().enumerate() {
+            let requested_dt = chrono::DateTime::from_timestamp(request.requested_at, 0)
+                .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                .unwrap_or_else(|| "Unknown".to_string());
+            effects.push(self.reply_effect(
+                uid,
+                &format!(
+                    "  {}. \x02{}\x02 -> {} (requested {})",
+                    i + 1,
+                    request.account,
+                    request.vhost,
+                    requested_dt
+                ),
+            ));
+        }
+        effects.push(self.reply_effect(
+            uid,
+            &format!("End of list - {} request(s).", requests.len()),
+        ));
+        effects
+    }
+}
@@ -0,0 +1,110 @@
+//! Account owner HostServ commands: REQUEST, ON, OFF.
+
+use super::{HostServ, HostServResult, MAX_VHOST_LEN};
+use crate::handlers::is_valid_hostname;
+use crate::services::ServiceEffect;
+use crate::state::Matrix;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+impl HostServ {
+    /// Handle REQUEST command.
+    pub(super) async fn handle_request(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> HostServResult {
+        let Some(&vhost) = args.first() else {
+            return self.error_reply(uid, "Syntax: REQUEST <vhost>");
+        };
+
+        let Some(account_id) = self.get_user_account_id(matrix, uid).await else {
+            return self.error_reply(
+                uid,
+                "You must be identified to an account to request a vhost.",
+            );
+        };
+
+        if vhost.len() > MAX_VHOST_LEN {
+            return self.error_reply(
+                uid,
+                &format!("Vhost too long (max {} chars).", MAX_VHOST_LEN),
+            );
+        }
+        if !is_valid_hostname(vhost) {
+            return self.error_reply(uid, "Invalid vhost: use alphanumeric, hyphens, dots only.");
+        }
+
+        match self.db.vhosts().request(account_id, vhost).await {
+            Ok(()) => {
+                info!(nick = %nick, vhost = %vhost, "Vhost requested");
+                matrix
+                    .user_manager
+                    .send_notice_to_opers(&format!("HostServ: {} requested vhost {}", nick, vhost))
+                    .await;
+                vec![self.reply_effect(
+                    uid,
+                    &format!(
+                        "Your request for \x02{}\x02 has been sent to the operators for review.",
+                        vhost
+                    ),
+                )]
+            }
+            Err(e) => {
+                warn!(nick = %nick, error = ?e, "Vhost request failed");
+                self.error_reply(
+                    uid,
+                    "Failed to record your request. Please try again later.",
+                )
+            }
+        }
+    }
+
+    /// Handle ON and OFF commands.
+    pub(super) async fn handle_toggle(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        enabled: bool,
+    ) -> HostServResult {
+        let Some(account_id) = self.get_user_account_id(matrix, uid).await else {
+            return self.error_reply(uid, "You must be identified to an account.");
+        };
+        let account = match matrix
+            .user_manager
+            .users
+            .get(uid)
+            .map(|u| u.value().clone())
+        {
+            Some(user_arc) => user_arc.read().await.account.clone(),
+            None => None,
+        };
+        let Some(account) = account else {
+            return self.error_reply(uid, "You must be identified to an account.");
+        };
+
+        let vhost = match self.db.vhosts().set_enabled(account_id, enabled).await {
+            Ok(Some(vhost)) => vhost,
+            Ok(None) => return self.error_reply(uid, "You do not have a vhost assigned."),
+            Err(e) => {
+                warn!(account = %account, error = ?e, "Vhost toggle failed");
+                return self.error_reply(uid, "Database error. Please try again later.");
+            }
+        };
+
+        let mut effects = if enabled {
+            vec![self.reply_effect(uid, &format!("Your vhost \x02{}\x02 is now active.", vhost))]
+        } else {
+            vec![self.reply_effect(uid, "Your vhost has been switched off.")]
+        };
+        for target_uid in self.account_uids(matrix, &account) {
+            effects.push(ServiceEffect::SetVhost {
+                target_uid,
+                vhost: enabled.then(|| vhost.clone()),
+            });
+        }
+        effects
+    }
+}
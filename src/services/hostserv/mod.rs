@@ -0,0 +1,11 @@
+//! HostServ - Virtual host request and assignment service.
+//!
+//! Handles:
+//! - `REQUEST <vhost>` - Ask operators to assign a vhost to your account
+//! - `ON` / `OFF` - Switch your assigned vhost on or off
+//! - `ACTIVATE <account>` / `REJECT <account>` - Review a request (opers)
+//! - `LIST` - Show pending requests (opers)
+
+mod commands;
+
+pub use commands::HostServ;
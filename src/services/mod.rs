@@ -1,12 +1,17 @@
 //! IRC services module.
 //!
-//! Provides virtual services like NickServ and ChanServ.
+//! Provides virtual services like NickServ, ChanServ, HostServ, OperServ and
+//! BotServ.
 
 pub mod base;
+pub mod botserv;
 pub mod chanserv;
+pub mod dedupe;
 pub mod effect;
 pub mod enforce;
+pub mod hostserv;
 pub mod nickserv;
+pub mod operserv;
 pub mod playback;
 pub mod traits;
 
@@ -14,15 +19,18 @@ pub use effect::{ServiceEffect, apply_effect, apply_effects, apply_effects_no_se
 pub use traits::Service;
 
 use crate::{handlers::ResponseMiddleware, state::Matrix};
-use slirc_proto::irc_to_lower;
+use slirc_proto::{Command, Message, Prefix, irc_to_lower};
 use std::sync::Arc;
 
 /// Unified service message router.
 ///
-/// Routes PRIVMSG/SQUERY to NickServ or ChanServ based on target.
-/// Returns true if the message was handled by a service.
+/// Routes PRIVMSG/SQUERY to NickServ, ChanServ, HostServ, OperServ or BotServ
+/// based on target. Returns true if the message was handled by a service;
+/// messages to channel bots are swallowed.
 ///
 /// Services are singletons stored in Matrix, created once at server startup.
+/// With `[services] external` set, messages for the core services are relayed
+/// to the external package's pseudo-clients instead.
 pub async fn route_service_message(
     matrix: &Arc<Matrix>,
     uid: &str,
@@ -33,6 +41,12 @@ pub async fn route_service_message(
 ) -> bool {
     let target_lower = irc_to_lower(target);
 
+    if !matrix.config.services.builtin()
+        && let Some(service) = core_service_nick(&target_lower)
+    {
+        return relay_to_external_service(matrix, uid, service, text).await;
+    }
+
     // Check core services first
     if target_lower == "nickserv" || target_lower == "ns" {
         let effects = matrix
@@ -54,6 +68,40 @@ pub async fn route_service_message(
         return true;
     }
 
+    if target_lower == "hostserv" || target_lower == "hs" {
+        let effects = matrix
+            .service_manager
+            .hostserv
+            .handle_command(matrix, uid, nick, text)
+            .await;
+        apply_effects(matrix, nick, sender, effects).await;
+        return true;
+    }
+
+    if target_lower == "operserv" || target_lower == "os" {
+        let effects = matrix
+            .service_manager
+            .operserv
+            .handle_command(matrix, uid, nick, text)
+            .await;
+        apply_effects(matrix, nick, sender, effects).await;
+        return true;
+    }
+
+    if target_lower == "botserv" || target_lower == "bs" {
+        let effects = matrix
+            .service_manager
+            .botserv
+            .handle_command(matrix, uid, nick, text)
+            .await;
+        apply_effects(matrix, nick, sender, effects).await;
+        return true;
+    }
+
+    if matrix.service_manager.botserv.registry.is_bot(&target_lower) {
+        return true;
+    }
+
     // Check extra services
     // We iterate because we need to check aliases too.
     for service in matrix.service_manager.extra_services.values() {
@@ -71,3 +119,66 @@ pub async fn route_service_message(
 
     false
 }
+
+/// Canonical nick of the core service addressed by a (lowercased) target or alias.
+fn core_service_nick(target_lower: &str) -> Option<&'static str> {
+    match target_lower {
+        "nickserv" | "ns" => Some("NickServ"),
+        "chanserv" | "cs" => Some("ChanServ"),
+        "hostserv" | "hs" => Some("HostServ"),
+        "operserv" | "os" => Some("OperServ"),
+        "botserv" | "bs" => Some("BotServ"),
+        _ => None,
+    }
+}
+
+/// Relay a message to an external services pseudo-client.
+///
+/// Returns false if the service is not on the network.
+async fn relay_to_external_service(
+    matrix: &Arc<Matrix>,
+    uid: &str,
+    service: &str,
+    text: &str,
+) -> bool {
+    let Some(service_uid) = matrix.user_manager.get_first_uid(&irc_to_lower(service)) else {
+        return false;
+    };
+    let msg = Message {
+        tags: None,
+        prefix: Some(Prefix::new_from_str(uid)),
+        command: Command::PRIVMSG(service_uid.clone(), text.to_string()),
+    };
+    matrix
+        .sync_manager
+        .route_to_remote_user(&service_uid, Arc::new(msg))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::Command;
+
+    #[tokio::test]
+    async fn external_services_receive_core_service_messages() {
+        let tm = TestMatrixBuilder::new()
+            .config(|c| c.services.external = Some("00s.test".to_string()))
+            .build()
+            .await;
+        assert!(tm.matrix.user_manager.get_first_uid("nickserv").is_none());
+
+        let mut services = tm.peer("00S").await;
+        tm.remote_user("00S", "00SAAAAAA", "NickServ").await;
+        let mut alice = tm.user("alice").build().await;
+
+        tm.run(&mut alice, "NS IDENTIFY alice hunter2").await;
+
+        let relayed: Vec<_> = std::iter::from_fn(|| services.try_recv().ok()).collect();
+        assert!(relayed.iter().any(|m| matches!(
+            &m.command,
+            Command::PRIVMSG(target, text)
+                if target == "00SAAAAAA" && text == "IDENTIFY alice hunter2"
+        )));
+    }
+}
@@ -3,11 +3,18 @@
 use super::NickServResult;
 use crate::db::Database;
 use crate::services::ServiceEffect;
+use crate::services::chanserv::forget_registration;
+use crate::state::Matrix;
+use std::sync::Arc;
 use tracing::{info, warn};
 
 /// Handle DROP command.
+///
+/// Channels founded by the account pass to their successor, or are dropped
+/// if there is none.
 pub async fn handle_drop(
     db: &Database,
+    matrix: &Arc<Matrix>,
     uid: &str,
     nick: &str,
     args: &[&str],
@@ -22,22 +29,52 @@ pub async fn handle_drop(
 
     // Verify the user owns the account for their current nick
     match db.accounts().drop_account(nick, password).await {
-        Ok(()) => {
+        Ok(orphaned) => {
             info!(nick = %nick, "Account dropped");
-            vec![
+            let mut effects = vec![
                 reply_effect(
                     uid,
                     &format!("Your account \x02{}\x02 has been dropped.", nick),
                 ),
                 reply_effect(uid, "All associated nicknames have been released."),
-                ServiceEffect::AccountClear {
-                    target_uid: uid.to_string(),
-                },
-                ServiceEffect::BroadcastAccount {
-                    target_uid: uid.to_string(),
-                    new_account: "*".to_string(),
-                },
-            ]
+            ];
+            for channel in orphaned {
+                match channel.new_founder {
+                    Some(founder) => {
+                        info!(channel = %channel.name, founder = %founder, "Founder transferred to successor");
+                        effects.push(reply_effect(
+                            uid,
+                            &format!(
+                                "Channel \x02{}\x02 has been handed over to \x02{}\x02.",
+                                channel.name, founder
+                            ),
+                        ));
+                    }
+                    None => {
+                        info!(channel = %channel.name, "Channel dropped with its founder");
+                        effects.push(reply_effect(
+                            uid,
+                            &format!(
+                                "Channel \x02{}\x02 had no successor and has been dropped.",
+                                channel.name
+                            ),
+                        ));
+                        effects.extend(forget_registration(
+                            matrix,
+                            &channel.name,
+                            "Channel dropped",
+                        ));
+                    }
+                }
+            }
+            effects.push(ServiceEffect::AccountClear {
+                target_uid: uid.to_string(),
+            });
+            effects.push(ServiceEffect::BroadcastAccount {
+                target_uid: uid.to_string(),
+                new_account: "*".to_string(),
+            });
+            effects
         }
         Err(crate::db::DbError::AccountNotFound(_)) => {
             reply_effects(uid, vec!["Your nickname is not registered."])
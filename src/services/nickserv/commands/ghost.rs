@@ -58,7 +58,7 @@ pub async fn handle_ghost(
         }
     } else if let Some(pw) = password {
         // Try to identify with password
-        db.accounts().identify(target_nick, pw).await.is_ok()
+        db.accounts().authenticate(target_nick, pw).await.is_ok()
     } else {
         false
     };
@@ -21,6 +21,19 @@ pub async fn handle_group(
     let account_name = args[0];
     let password = args[1];
 
+    // Unconfirmed accounts cannot claim more nicknames
+    if let Ok(Some(account)) = db.accounts().find_by_name(account_name).await
+        && !db.accounts().is_verified(account.id).await.unwrap_or(false)
+    {
+        return reply_effects(
+            uid,
+            vec![&format!(
+                "Account \x02{}\x02 must be confirmed before more nicknames can be grouped to it.",
+                account.name
+            )],
+        );
+    }
+
     match db
         .accounts()
         .link_nickname(nick, account_name, password)
@@ -0,0 +1,217 @@
+//! GROUPADD and GROUPDEL command handlers for NickServ.
+//!
+//! A group account is an ordinary account whose members act as it: they
+//! inherit its channel access, transitively through nested groups.
+
+use super::NickServResult;
+use crate::db::{Account, Database, DbError};
+use crate::state::Matrix;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Resolve the caller's account and the group/member accounts named in
+/// `args`, checking that the caller may manage the group.
+///
+/// The group itself and any (transitive) member of it may manage it.
+async fn resolve_group_args(
+    db: &Database,
+    matrix: &Arc<Matrix>,
+    uid: &str,
+    args: &[&str],
+    syntax: &str,
+    reply_effects: &impl Fn(&str, Vec<&str>) -> NickServResult,
+) -> Result<(String, Account, Account, bool), NickServResult> {
+    if args.len() < 2 {
+        return Err(reply_effects(uid, vec![syntax]));
+    }
+
+    let user_arc = matrix
+        .user_manager
+        .users
+        .get(uid)
+        .map(|u| u.value().clone());
+    let caller_name = match user_arc {
+        Some(user_arc) => {
+            let user = user_arc.read().await;
+            match (&user.account, user.modes.registered) {
+                (Some(name), true) => name.clone(),
+                _ => {
+                    return Err(reply_effects(
+                        uid,
+                        vec!["You must be identified to use this command."],
+                    ));
+                }
+            }
+        }
+        None => return Err(reply_effects(uid, vec!["Internal error."])),
+    };
+
+    let caller = match db.accounts().find_by_name(&caller_name).await {
+        Ok(Some(acc)) => acc,
+        _ => return Err(reply_effects(uid, vec!["Account not found."])),
+    };
+    let group = match db.accounts().find_by_name(args[0]).await {
+        Ok(Some(acc)) => acc,
+        _ => {
+            return Err(reply_effects(
+                uid,
+                vec![&format!("Account \x02{}\x02 is not registered.", args[0])],
+            ));
+        }
+    };
+    let member = match db.accounts().find_by_name(args[1]).await {
+        Ok(Some(acc)) => acc,
+        _ => {
+            return Err(reply_effects(
+                uid,
+                vec![&format!("Account \x02{}\x02 is not registered.", args[1])],
+            ));
+        }
+    };
+
+    let can_manage = match db.accounts().acting_ids(caller.id).await {
+        Ok(ids) => ids.contains(&group.id),
+        Err(e) => {
+            warn!(account = %caller_name, error = ?e, "Failed to resolve account groups");
+            return Err(reply_effects(
+                uid,
+                vec!["Database error. Please try again later."],
+            ));
+        }
+    };
+
+    Ok((caller_name, group, member, can_manage))
+}
+
+/// Handle GROUPADD command - add an account to a group account.
+pub async fn handle_groupadd(
+    db: &Database,
+    matrix: &Arc<Matrix>,
+    uid: &str,
+    args: &[&str],
+    reply_effects: impl Fn(&str, Vec<&str>) -> NickServResult,
+) -> NickServResult {
+    let (caller_name, group, member, can_manage) = match resolve_group_args(
+        db,
+        matrix,
+        uid,
+        args,
+        "Syntax: GROUPADD <group> <account>",
+        &reply_effects,
+    )
+    .await
+    {
+        Ok(resolved) => resolved,
+        Err(reply) => return reply,
+    };
+
+    if !can_manage {
+        return reply_effects(
+            uid,
+            vec![&format!(
+                "You are not a member of the group \x02{}\x02.",
+                group.name
+            )],
+        );
+    }
+
+    match db
+        .accounts()
+        .add_group_member(group.id, member.id, &caller_name)
+        .await
+    {
+        Ok(true) => {
+            info!(group = %group.name, member = %member.name, by = %caller_name, "Account added to group");
+            reply_effects(
+                uid,
+                vec![&format!(
+                    "\x02{}\x02 is now a member of the group \x02{}\x02.",
+                    member.name, group.name
+                )],
+            )
+        }
+        Ok(false) => reply_effects(
+            uid,
+            vec![&format!(
+                "\x02{}\x02 is already a member of the group \x02{}\x02.",
+                member.name, group.name
+            )],
+        ),
+        Err(DbError::GroupCycle) => reply_effects(
+            uid,
+            vec![&format!(
+                "\x02{}\x02 cannot be a member of \x02{}\x02: the group would contain itself.",
+                member.name, group.name
+            )],
+        ),
+        Err(e) => {
+            warn!(group = %group.name, member = %member.name, error = ?e, "GROUPADD failed");
+            reply_effects(
+                uid,
+                vec!["Failed to add group member. Please try again later."],
+            )
+        }
+    }
+}
+
+/// Handle GROUPDEL command - remove an account from a group account.
+///
+/// Members of the group may remove anyone; an account may always leave.
+pub async fn handle_groupdel(
+    db: &Database,
+    matrix: &Arc<Matrix>,
+    uid: &str,
+    args: &[&str],
+    reply_effects: impl Fn(&str, Vec<&str>) -> NickServResult,
+) -> NickServResult {
+    let (caller_name, group, member, can_manage) = match resolve_group_args(
+        db,
+        matrix,
+        uid,
+        args,
+        "Syntax: GROUPDEL <group> <account>",
+        &reply_effects,
+    )
+    .await
+    {
+        Ok(resolved) => resolved,
+        Err(reply) => return reply,
+    };
+
+    if !can_manage && !member.name.eq_ignore_ascii_case(&caller_name) {
+        return reply_effects(
+            uid,
+            vec![&format!(
+                "You are not a member of the group \x02{}\x02.",
+                group.name
+            )],
+        );
+    }
+
+    match db.accounts().remove_group_member(group.id, member.id).await {
+        Ok(true) => {
+            info!(group = %group.name, member = %member.name, by = %caller_name, "Account removed from group");
+            reply_effects(
+                uid,
+                vec![&format!(
+                    "\x02{}\x02 has been removed from the group \x02{}\x02.",
+                    member.name, group.name
+                )],
+            )
+        }
+        Ok(false) => reply_effects(
+            uid,
+            vec![&format!(
+                "\x02{}\x02 is not a member of the group \x02{}\x02.",
+                member.name, group.name
+            )],
+        ),
+        Err(e) => {
+            warn!(group = %group.name, member = %member.name, error = ?e, "GROUPDEL failed");
+            reply_effects(
+                uid,
+                vec!["Failed to remove group member. Please try again later."],
+            )
+        }
+    }
+}
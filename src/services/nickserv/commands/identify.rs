@@ -20,10 +20,17 @@ pub async fn handle_identify(
 
     let password = args[0];
 
-    match db.accounts().identify(nick, password).await {
+    match db.accounts().authenticate(nick, password).await {
         Ok(account) => {
             info!(nick = %nick, account = %account.name, "User identified");
-            vec![
+            let vhost = match db.vhosts().active(account.id).await {
+                Ok(vhost) => vhost,
+                Err(e) => {
+                    warn!(account = %account.name, error = ?e, "Failed to look up vhost");
+                    None
+                }
+            };
+            let mut effects = vec![
                 reply_effect(
                     uid,
                     &format!("You are now identified for \x02{}\x02.", account.name),
@@ -42,7 +49,14 @@ pub async fn handle_identify(
                 ServiceEffect::ClearEnforceTimer {
                     target_uid: uid.to_string(),
                 },
-            ]
+            ];
+            if vhost.is_some() {
+                effects.push(ServiceEffect::SetVhost {
+                    target_uid: uid.to_string(),
+                    vhost,
+                });
+            }
+            effects
         }
         Err(crate::db::DbError::AccountNotFound(_)) => {
             reply_effects(uid, vec!["No account found for your nickname."])
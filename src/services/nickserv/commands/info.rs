@@ -1,7 +1,7 @@
 //! INFO command handler for NickServ.
 
 use super::NickServResult;
-use crate::db::Database;
+use crate::db::{Database, EnforceLevel};
 use tracing::debug;
 
 /// Handle INFO command.
@@ -40,8 +40,15 @@ pub async fn handle_info(
                 effects.push(reply_effect(uid, &format!("  Email:      {}", email)));
             }
 
-            if account.enforce {
-                effects.push(reply_effect(uid, "  Options:    ENFORCE ON"));
+            if !db.accounts().is_verified(account.id).await.unwrap_or(true) {
+                effects.push(reply_effect(uid, "  Status:     Unconfirmed"));
+            }
+
+            if account.enforce != EnforceLevel::Off {
+                effects.push(reply_effect(
+                    uid,
+                    &format!("  Options:    ENFORCE {}", account.enforce.as_str()),
+                ));
             }
 
             // Get linked nicknames
@@ -54,6 +61,24 @@ pub async fn handle_info(
                 ));
             }
 
+            // Group memberships, in both directions
+            if let Ok(groups) = db.accounts().get_groups(account.id).await
+                && !groups.is_empty()
+            {
+                effects.push(reply_effect(
+                    uid,
+                    &format!("  Groups:     {}", groups.join(", ")),
+                ));
+            }
+            if let Ok(members) = db.accounts().get_group_members(account.id).await
+                && !members.is_empty()
+            {
+                effects.push(reply_effect(
+                    uid,
+                    &format!("  Members:    {}", members.join(", ")),
+                ));
+            }
+
             effects
         }
         Ok(None) => reply_effects(uid, vec![&format!("\x02{}\x02 is not registered.", nick)]),
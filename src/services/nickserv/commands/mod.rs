@@ -4,26 +4,43 @@ pub mod cert;
 pub mod drop;
 pub mod ghost;
 pub mod group;
+pub mod groups;
 pub mod identify;
 pub mod info;
 pub mod register;
 pub mod sessions;
 pub mod set;
 pub mod ungroup;
+pub mod verify;
 
 use crate::db::Database;
 use crate::services::base::ServiceBase;
+use crate::services::nickserv::mail::MailSender;
 use crate::services::{Service, ServiceEffect};
 use crate::state::Matrix;
 use async_trait::async_trait;
 use std::sync::Arc;
 
+/// Returns true if the command modifies the accounts database.
+fn is_write_command(command: &str, args: &[&str]) -> bool {
+    match command {
+        "REGISTER" | "DROP" | "GROUP" | "UNGROUP" | "GROUPADD" | "GROUPDEL" | "SET" | "CONFIRM"
+        | "RESETPASS" | "SETPASS" => true,
+        "CERT" => args
+            .first()
+            .is_some_and(|sub| !matches!(sub.to_uppercase().as_str(), "LIST" | "SHOW")),
+        _ => false,
+    }
+}
+
 /// Result of a NickServ command - a list of effects to apply.
 pub type NickServResult = Vec<ServiceEffect>;
 
 /// NickServ service.
 pub struct NickServ {
     db: Database,
+    /// Delivers confirmation and reset codes; None if no mailer is configured.
+    mailer: Option<Arc<dyn MailSender>>,
 }
 
 impl ServiceBase for NickServ {
@@ -59,8 +76,13 @@ impl Service for NickServ {
 
 impl NickServ {
     /// Create a new NickServ service.
-    pub fn new(db: Database) -> Self {
-        Self { db }
+    pub fn new(db: Database, mailer: Option<Arc<dyn MailSender>>) -> Self {
+        Self { db, mailer }
+    }
+
+    /// The configured mail sender, if any.
+    pub fn mailer(&self) -> Option<&Arc<dyn MailSender>> {
+        self.mailer.as_ref()
     }
 
     /// Check if an account with the given name exists.
@@ -85,10 +107,21 @@ impl NickServ {
         let command = parts[0].to_uppercase();
         let args = &parts[1..];
 
+        if matrix.lifecycle_manager.in_maintenance() && is_write_command(&command, args) {
+            return self.read_only_reply(uid);
+        }
+
         match command.as_str() {
             "REGISTER" => {
+                let verification = matrix
+                    .config
+                    .account_registration
+                    .verify_email
+                    .then_some(self.mailer.as_ref())
+                    .flatten();
                 register::handle_register(
                     &self.db,
+                    verification,
                     uid,
                     nick,
                     args,
@@ -111,6 +144,7 @@ impl NickServ {
             "DROP" => {
                 drop::handle_drop(
                     &self.db,
+                    matrix,
                     uid,
                     nick,
                     args,
@@ -136,6 +170,18 @@ impl NickServ {
                 })
                 .await
             }
+            "GROUPADD" => {
+                groups::handle_groupadd(&self.db, matrix, uid, args, |u, ts| {
+                    self.reply_effects(u, ts)
+                })
+                .await
+            }
+            "GROUPDEL" => {
+                groups::handle_groupdel(&self.db, matrix, uid, args, |u, ts| {
+                    self.reply_effects(u, ts)
+                })
+                .await
+            }
             "GHOST" => {
                 ghost::handle_ghost(
                     &self.db,
@@ -206,6 +252,21 @@ impl NickServ {
                 )
                 .await
             }
+            "CONFIRM" => {
+                verify::handle_confirm(&self.db, matrix, uid, args, |u, ts| {
+                    self.reply_effects(u, ts)
+                })
+                .await
+            }
+            "RESETPASS" => {
+                verify::handle_resetpass(&self.db, self.mailer.as_ref(), uid, args, |u, ts| {
+                    self.reply_effects(u, ts)
+                })
+                .await
+            }
+            "SETPASS" => {
+                verify::handle_setpass(&self.db, uid, args, |u, ts| self.reply_effects(u, ts)).await
+            }
             "HELP" => self.help_reply(uid),
             _ => self.unknown_command(uid, &command),
         }
@@ -251,6 +312,14 @@ impl NickServ {
                 uid,
                 "  \x02UNGROUP\x02 <nick>              - Remove nick from account",
             ),
+            self.reply_effect(
+                uid,
+                "  \x02GROUPADD\x02 <group> <account> - Add account to group account",
+            ),
+            self.reply_effect(
+                uid,
+                "  \x02GROUPDEL\x02 <group> <account> - Remove account from group",
+            ),
             self.reply_effect(
                 uid,
                 "  \x02GHOST\x02 <nick> [password]     - Kill session using your nick",
@@ -269,7 +338,19 @@ impl NickServ {
             ),
             self.reply_effect(
                 uid,
-                "  \x02SESSIONS\x02 [account]          - List active sessions",
+                "  \x02SESSIONS\x02 [account|KILL id]  - List or close connections",
+            ),
+            self.reply_effect(
+                uid,
+                "  \x02CONFIRM\x02 <code>             - Confirm your email address",
+            ),
+            self.reply_effect(
+                uid,
+                "  \x02RESETPASS\x02 <account>        - Mail a password reset code",
+            ),
+            self.reply_effect(
+                uid,
+                "  \x02SETPASS\x02 <account> <code> <password> - Set a new password",
             ),
             self.reply_effect(
                 uid,
@@ -1,13 +1,20 @@
 //! REGISTER command handler for NickServ.
 
 use super::NickServResult;
-use crate::db::Database;
+use super::verify::mail_code;
+use crate::db::{CodeKind, Database};
 use crate::services::ServiceEffect;
-use tracing::info;
+use crate::services::nickserv::mail::MailSender;
+use std::sync::Arc;
+use tracing::{info, warn};
 
 /// Handle REGISTER command.
+///
+/// With `verification` set, an email address is required and the account
+/// stays unconfirmed until the mailed code is passed to CONFIRM.
 pub async fn handle_register(
     db: &Database,
+    verification: Option<&Arc<dyn MailSender>>,
     uid: &str,
     nick: &str,
     args: &[&str],
@@ -21,14 +28,45 @@ pub async fn handle_register(
     let password = args[0];
     let email = args.get(1).copied();
 
+    if verification.is_some() && email.is_none() {
+        return reply_effects(
+            uid,
+            vec!["An email address is required: REGISTER <password> <email>"],
+        );
+    }
+
     match db.accounts().register(nick, password, email).await {
         Ok(account) => {
             info!(nick = %nick, account = %account.name, "Account registered");
-            vec![
-                reply_effect(
-                    uid,
-                    &format!("Your nickname \x02{}\x02 has been registered.", nick),
-                ),
+            let mut effects = vec![reply_effect(
+                uid,
+                &format!("Your nickname \x02{}\x02 has been registered.", nick),
+            )];
+            if let (Some(mailer), Some(email)) = (verification, email) {
+                if let Err(e) = db.accounts().set_verified(account.id, false).await {
+                    warn!(account = %account.name, error = ?e, "Failed to mark account unconfirmed");
+                }
+                let text = if mail_code(
+                    db,
+                    mailer,
+                    account.id,
+                    &account.name,
+                    email,
+                    CodeKind::Confirm,
+                )
+                .await
+                {
+                    format!(
+                        "A confirmation code has been sent to \x02{}\x02. Use \x02CONFIRM <code>\x02 to finish registering.",
+                        email
+                    )
+                } else {
+                    "The confirmation mail could not be sent. Please contact network staff."
+                        .to_string()
+                };
+                effects.push(reply_effect(uid, &text));
+            }
+            effects.extend([
                 reply_effect(uid, "You are now identified to your account."),
                 ServiceEffect::AccountIdentify {
                     target_uid: uid.to_string(),
@@ -40,7 +78,8 @@ pub async fn handle_register(
                     target_uid: uid.to_string(),
                     new_account: account.name,
                 },
-            ]
+            ]);
+            effects
         }
         Err(crate::db::DbError::AccountExists(name)) => reply_effects(
             uid,
@@ -91,6 +130,7 @@ mod tests {
 
         handle_register(
             &db,
+            None,
             uid,
             nick,
             &args,
@@ -136,6 +176,7 @@ mod tests {
         // Try to register with 'ExistingUser' nick
         handle_register(
             &db,
+            None,
             uid,
             "ExistingUser",
             &args,
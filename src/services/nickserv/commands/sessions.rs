@@ -1,18 +1,48 @@
 //! SESSIONS command handler for NickServ.
 //!
-//! Shows active sessions for the user's account or a specified account (if oper).
+//! Shows the connections logged into the user's account or a specified
+//! account (if oper), and closes a single connection with `SESSIONS KILL`.
 
 use super::NickServResult;
 use crate::services::ServiceEffect;
 use crate::state::Matrix;
+use crate::state::client::SessionId;
+use chrono::{DateTime, Utc};
+use slirc_proto::irc_eq;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::debug;
 
+/// A connection logged into an account on this server.
+struct SessionEntry {
+    uid: String,
+    session_id: SessionId,
+    account: String,
+    nick: String,
+    ip: String,
+    cloak: String,
+    connected_at: DateTime<Utc>,
+    device_id: Option<String>,
+    secure: bool,
+}
+
+impl SessionEntry {
+    /// Short connection id shown to users and accepted by `SESSIONS KILL`.
+    fn short_id(&self) -> String {
+        session_short_id(self.session_id)
+    }
+}
+
+fn session_short_id(session_id: SessionId) -> String {
+    session_id.simple().to_string()[..8].to_string()
+}
+
 /// Handle SESSIONS command.
 ///
 /// Usage:
 /// - `SESSIONS` - List your own active sessions
 /// - `SESSIONS <account>` - List sessions for an account (opers only)
+/// - `SESSIONS KILL <id>` - Disconnect one of your sessions (any session for opers)
 pub async fn handle_sessions(
     matrix: &Arc<Matrix>,
     uid: &str,
@@ -22,6 +52,21 @@ pub async fn handle_sessions(
     reply_effect: impl Fn(&str, &str) -> ServiceEffect,
     reply_effects: impl Fn(&str, Vec<&str>) -> NickServResult,
 ) -> NickServResult {
+    if args
+        .first()
+        .is_some_and(|arg| arg.eq_ignore_ascii_case("KILL"))
+    {
+        return handle_kill(
+            matrix,
+            uid,
+            user_account,
+            is_oper,
+            &args[1..],
+            reply_effects,
+        )
+        .await;
+    }
+
     // Determine target account
     let target_account = if args.is_empty() {
         // No argument - show own sessions
@@ -34,9 +79,7 @@ pub async fn handle_sessions(
     } else {
         let requested = args[0];
         match (user_account, is_oper) {
-            (Some(own_account), _) if slirc_proto::irc_eq(own_account, requested) => {
-                requested.to_string()
-            }
+            (Some(own_account), _) if irc_eq(own_account, requested) => requested.to_string(),
             (_, true) => requested.to_string(),
             (Some(_), false) => {
                 return reply_effects(
@@ -50,16 +93,7 @@ pub async fn handle_sessions(
         }
     };
 
-    // Check if multiclient is enabled
-    if !matrix.config.multiclient.enabled {
-        return reply_effects(
-            uid,
-            vec!["Multiclient feature is not enabled on this server."],
-        );
-    }
-
-    // Get sessions from client manager
-    let sessions = matrix.client_manager.get_sessions(&target_account);
+    let sessions = collect_sessions(matrix, Some(&target_account)).await;
 
     if sessions.is_empty() {
         return reply_effects(
@@ -87,29 +121,36 @@ pub async fn handle_sessions(
         ),
     )];
 
-    for (idx, session) in sessions.iter().enumerate() {
-        let device_str = session
-            .device_id
-            .as_ref()
-            .map(|d| format!(" (device: {})", d))
-            .unwrap_or_default();
-
-        let oper_suffix = if is_oper {
-            format!(" [id: {}, ip: {}]", session.session_id, session.ip)
-        } else {
+    for session in &sessions {
+        let mut client_info = Vec::new();
+        if let Some(device) = &session.device_id {
+            client_info.push(format!("device: {}", device));
+        }
+        if session.secure {
+            client_info.push("TLS".to_string());
+        }
+        let client_str = if client_info.is_empty() {
             String::new()
+        } else {
+            format!(" ({})", client_info.join(", "))
+        };
+        let this_str = if session.uid == uid {
+            " [this nick]"
+        } else {
+            ""
         };
-
-        let attached_time = session.attached_at.format("%Y-%m-%d %H:%M:%S UTC");
 
         effects.push(reply_effect(
             uid,
             &format!(
-                "  {}. Connected since: {}{}{}",
-                idx + 1,
-                attached_time,
-                device_str,
-                oper_suffix
+                "  \x02{}\x02 {} from {} [{}] since {}{}{}",
+                session.short_id(),
+                session.nick,
+                session.ip,
+                session.cloak,
+                session.connected_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                client_str,
+                this_str
             ),
         ));
     }
@@ -119,10 +160,136 @@ pub async fn handle_sessions(
     effects
 }
 
+/// Handle `SESSIONS KILL <id> [reason]`.
+async fn handle_kill(
+    matrix: &Arc<Matrix>,
+    uid: &str,
+    user_account: Option<&str>,
+    is_oper: bool,
+    args: &[&str],
+    reply_effects: impl Fn(&str, Vec<&str>) -> NickServResult,
+) -> NickServResult {
+    let Some(id) = args.first() else {
+        return reply_effects(uid, vec!["Syntax: SESSIONS KILL <id> [reason]"]);
+    };
+    if user_account.is_none() && !is_oper {
+        return reply_effects(uid, vec!["You are not logged in to an account."]);
+    }
+
+    // Opers may close any session; everyone else only their own account's
+    let scope = if is_oper { None } else { user_account };
+    let sessions = collect_sessions(matrix, scope).await;
+    let Some(session) = sessions
+        .into_iter()
+        .find(|s| s.short_id().eq_ignore_ascii_case(id))
+    else {
+        return reply_effects(
+            uid,
+            vec![&format!(
+                "No session \x02{}\x02 found for your account.",
+                id
+            )],
+        );
+    };
+
+    let reason = if args.len() > 1 {
+        args[1..].join(" ")
+    } else {
+        "Closed via SESSIONS KILL".to_string()
+    };
+
+    debug!(
+        uid = %uid,
+        account = %session.account,
+        session = %session.session_id,
+        "SESSIONS KILL executed"
+    );
+
+    let mut effects = reply_effects(
+        uid,
+        vec![&format!(
+            "Session \x02{}\x02 ({} from {}) has been disconnected.",
+            session.short_id(),
+            session.nick,
+            session.ip
+        )],
+    );
+    effects.push(ServiceEffect::KillSession {
+        target_uid: session.uid,
+        session_id: session.session_id,
+        killer: "NickServ".to_string(),
+        reason,
+    });
+    effects
+}
+
+/// Gather local connections logged into `account`, or into any account if `None`.
+///
+/// Sorted oldest first.
+async fn collect_sessions(matrix: &Arc<Matrix>, account: Option<&str>) -> Vec<SessionEntry> {
+    // Bouncer attachments carry per-connection details for multiclient accounts
+    let attachments: HashMap<SessionId, _> = account
+        .map(|account| matrix.client_manager.get_sessions(account))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|attachment| (attachment.session_id, attachment))
+        .collect();
+
+    let users: Vec<_> = matrix
+        .user_manager
+        .users
+        .iter()
+        .filter(|entry| {
+            entry.key().starts_with(matrix.server_id.as_str())
+                && !matrix.user_manager.is_service(entry.key())
+        })
+        .map(|entry| (entry.key().clone(), entry.value().clone()))
+        .collect();
+
+    let mut sessions = Vec::new();
+    for (user_uid, user_arc) in users {
+        let user = user_arc.read().await;
+        let Some(user_account) = user.account.as_deref() else {
+            continue;
+        };
+        if account.is_some_and(|account| !irc_eq(account, user_account)) {
+            continue;
+        }
+        let Some(senders) = matrix.user_manager.get_senders_cloned(&user_uid) else {
+            continue;
+        };
+
+        for sender in senders {
+            let attachment = attachments.get(&sender.session_id);
+            sessions.push(SessionEntry {
+                uid: user_uid.clone(),
+                session_id: sender.session_id,
+                account: user_account.to_string(),
+                nick: user.nick.clone(),
+                ip: attachment.map_or_else(|| user.ip.clone(), |a| a.ip.clone()),
+                cloak: user.visible_host.clone(),
+                connected_at: attachment.map(|a| a.attached_at).unwrap_or_else(|| {
+                    DateTime::from_timestamp(user.created_at, 0).unwrap_or_default()
+                }),
+                device_id: attachment.and_then(|a| a.device_id.clone()),
+                secure: user.modes.secure,
+            });
+        }
+    }
+
+    sessions.sort_by_key(|s| s.connected_at);
+    sessions
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
-    fn test_sessions_requires_login() {
-        // Can't easily test async without a runtime, but the logic is straightforward
+    fn test_session_short_id_is_uuid_prefix() {
+        let session_id = SessionId::new_v4();
+        let short = session_short_id(session_id);
+        assert_eq!(short.len(), 8);
+        assert!(session_id.simple().to_string().starts_with(&short));
     }
 }
@@ -1,7 +1,7 @@
 //! SET command handler for NickServ.
 
 use super::NickServResult;
-use crate::db::Database;
+use crate::db::{Database, EnforceLevel};
 use crate::state::Matrix;
 use std::sync::Arc;
 use tracing::{info, warn};
@@ -30,7 +30,7 @@ pub async fn handle_set(
             reply_effect(uid, "  EMAIL <address>  - Set email address"),
             reply_effect(
                 uid,
-                "  ENFORCE ON|OFF   - Enable/disable nickname enforcement",
+                "  ENFORCE <level>  - Nick protection: OFF, ON, QUICK or STRICT",
             ),
             reply_effect(uid, "  HIDEMAIL ON|OFF  - Hide/show email in INFO"),
             reply_effect(uid, "  PASSWORD <pass>  - Change password"),
@@ -180,14 +180,22 @@ pub async fn handle_set(
     match db.accounts().set_option(account.id, &option, value).await {
         Ok(()) => {
             info!(account = %account.name, option = %option, "Account setting changed");
+            let shown = match EnforceLevel::parse(value) {
+                Some(level) if option == "ENFORCE" => level.as_str().to_string(),
+                _ => value.to_string(),
+            };
             reply_effects(
                 uid,
                 vec![&format!(
                     "\x02{}\x02 has been set to \x02{}\x02.",
-                    option, value
+                    option, shown
                 )],
             )
         }
+        Err(crate::db::DbError::InvalidValue(allowed)) => reply_effects(
+            uid,
+            vec![&format!("\x02{}\x02 must be {}.", option, allowed)],
+        ),
         Err(crate::db::DbError::UnknownOption(opt)) => reply_effects(
             uid,
             vec![&format!(
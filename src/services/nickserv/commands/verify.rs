@@ -0,0 +1,303 @@
+//! Email confirmation and password reset: CONFIRM, RESETPASS, SETPASS.
+//!
+//! With `account_registration.verify_email` on, REGISTER mails a code that
+//! CONFIRM redeems; until then the account cannot register channels or group
+//! nicks. RESETPASS mails a short-lived code that SETPASS trades for a new
+//! password.
+
+use super::NickServResult;
+use crate::db::{CodeKind, Database};
+use crate::services::nickserv::mail::MailSender;
+use crate::state::Matrix;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Issue a code of `kind` for an account and mail it to `email`.
+///
+/// Returns false if the code could not be stored or mailed.
+pub async fn mail_code(
+    db: &Database,
+    mailer: &Arc<dyn MailSender>,
+    account_id: i64,
+    account_name: &str,
+    email: &str,
+    kind: CodeKind,
+) -> bool {
+    let code = match db.accounts().issue_code(account_id, kind).await {
+        Ok(code) => code,
+        Err(e) => {
+            warn!(account = %account_name, error = ?e, "Failed to issue code");
+            return false;
+        }
+    };
+
+    let (subject, body) = match kind {
+        CodeKind::Confirm => (
+            format!("Confirm your account {}", account_name),
+            format!(
+                "Your confirmation code for account {} is {}.\r\n\r\nTo confirm, type:\r\n/msg NickServ CONFIRM {}\r\n",
+                account_name, code, code
+            ),
+        ),
+        CodeKind::Reset => (
+            format!("Password reset for account {}", account_name),
+            format!(
+                "A password reset was requested for account {}. To choose a new password within the hour, type:\r\n/msg NickServ SETPASS {} {} <new password>\r\n\r\nIf you did not ask for this, ignore this mail.\r\n",
+                account_name, account_name, code
+            ),
+        ),
+    };
+
+    match mailer.send(email, &subject, &body).await {
+        Ok(()) => true,
+        Err(e) => {
+            warn!(account = %account_name, mailer = mailer.name(), error = %e, "Failed to mail code");
+            false
+        }
+    }
+}
+
+/// Handle CONFIRM command.
+pub async fn handle_confirm(
+    db: &Database,
+    matrix: &Arc<Matrix>,
+    uid: &str,
+    args: &[&str],
+    reply_effects: impl Fn(&str, Vec<&str>) -> NickServResult,
+) -> NickServResult {
+    let Some(&code) = args.first() else {
+        return reply_effects(uid, vec!["Syntax: CONFIRM <code>"]);
+    };
+
+    let account_name = match matrix
+        .user_manager
+        .users
+        .get(uid)
+        .map(|u| u.value().clone())
+    {
+        Some(user_arc) => user_arc.read().await.account.clone(),
+        None => None,
+    };
+    let Some(account_name) = account_name else {
+        return reply_effects(uid, vec!["You are not identified to any account."]);
+    };
+    let account = match db.accounts().find_by_name(&account_name).await {
+        Ok(Some(account)) => account,
+        _ => return reply_effects(uid, vec!["Account not found."]),
+    };
+
+    let repo = db.accounts();
+    if repo.is_verified(account.id).await.unwrap_or(false) {
+        return reply_effects(uid, vec!["Your account is already confirmed."]);
+    }
+
+    let redeemed = match repo.redeem_code(account.id, CodeKind::Confirm, code).await {
+        Ok(redeemed) => redeemed,
+        Err(e) => {
+            warn!(account = %account.name, error = ?e, "CONFIRM failed");
+            return reply_effects(uid, vec!["Confirmation failed. Please try again later."]);
+        }
+    };
+    if !redeemed {
+        return reply_effects(uid, vec!["Invalid or expired confirmation code."]);
+    }
+
+    if let Err(e) = repo.set_verified(account.id, true).await {
+        warn!(account = %account.name, error = ?e, "Failed to mark account confirmed");
+        return reply_effects(uid, vec!["Confirmation failed. Please try again later."]);
+    }
+    info!(account = %account.name, "Account confirmed");
+    reply_effects(
+        uid,
+        vec![&format!(
+            "Your account \x02{}\x02 is now confirmed.",
+            account.name
+        )],
+    )
+}
+
+/// Handle RESETPASS command.
+pub async fn handle_resetpass(
+    db: &Database,
+    mailer: Option<&Arc<dyn MailSender>>,
+    uid: &str,
+    args: &[&str],
+    reply_effects: impl Fn(&str, Vec<&str>) -> NickServResult,
+) -> NickServResult {
+    let Some(&account_name) = args.first() else {
+        return reply_effects(uid, vec!["Syntax: RESETPASS <account>"]);
+    };
+    let Some(mailer) = mailer else {
+        return reply_effects(
+            uid,
+            vec!["Password resets are not available on this network."],
+        );
+    };
+
+    // The reply is the same whether or not a mail went out, so RESETPASS
+    // does not reveal which accounts have an address on file
+    if let Ok(Some(account)) = db.accounts().find_by_name(account_name).await
+        && let Some(email) = &account.email
+        && mail_code(
+            db,
+            mailer,
+            account.id,
+            &account.name,
+            email,
+            CodeKind::Reset,
+        )
+        .await
+    {
+        info!(account = %account.name, "Password reset code sent");
+    }
+
+    reply_effects(
+        uid,
+        vec![&format!(
+            "If \x02{}\x02 has an email address on file, a reset code has been sent to it.",
+            account_name
+        )],
+    )
+}
+
+/// Handle SETPASS command.
+pub async fn handle_setpass(
+    db: &Database,
+    uid: &str,
+    args: &[&str],
+    reply_effects: impl Fn(&str, Vec<&str>) -> NickServResult,
+) -> NickServResult {
+    let [account_name, code, password] = args else {
+        return reply_effects(uid, vec!["Syntax: SETPASS <account> <code> <new password>"]);
+    };
+
+    let repo = db.accounts();
+    let account = match repo.find_by_name(account_name).await {
+        Ok(Some(account)) => account,
+        _ => return reply_effects(uid, vec!["Invalid or expired reset code."]),
+    };
+    match repo.redeem_code(account.id, CodeKind::Reset, code).await {
+        Ok(true) => {}
+        Ok(false) => return reply_effects(uid, vec!["Invalid or expired reset code."]),
+        Err(e) => {
+            warn!(account = %account.name, error = ?e, "SETPASS failed");
+            return reply_effects(uid, vec!["Password reset failed. Please try again later."]);
+        }
+    }
+
+    if let Err(e) = repo.set_option(account.id, "password", password).await {
+        warn!(account = %account.name, error = ?e, "Failed to set new password");
+        return reply_effects(uid, vec!["Password reset failed. Please try again later."]);
+    }
+    // The code arrived by mail, so the address is confirmed too
+    let _ = repo.set_verified(account.id, true).await;
+
+    info!(account = %account.name, "Password reset");
+    reply_effects(
+        uid,
+        vec![&format!(
+            "The password for \x02{}\x02 has been changed. You can now IDENTIFY with it.",
+            account.name
+        )],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::nickserv::mail::MailError;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Records mails instead of sending them.
+    #[derive(Default)]
+    struct RecordingSender {
+        sent: Mutex<Vec<(String, String)>>,
+    }
+
+    #[async_trait]
+    impl MailSender for RecordingSender {
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+
+        async fn send(&self, to: &str, _subject: &str, body: &str) -> Result<(), MailError> {
+            self.sent
+                .lock()
+                .unwrap()
+                .push((to.to_string(), body.to_string()));
+            Ok(())
+        }
+    }
+
+    fn texts(_: &str, texts: Vec<&str>) -> NickServResult {
+        texts
+            .into_iter()
+            .map(|t| crate::services::ServiceEffect::Reply {
+                target_uid: "uid".to_string(),
+                msg: slirc_proto::Message::from(slirc_proto::Command::NOTICE(
+                    "uid".to_string(),
+                    t.to_string(),
+                )),
+            })
+            .collect()
+    }
+
+    fn reply_text(effects: &NickServResult) -> String {
+        effects
+            .iter()
+            .filter_map(|e| match e {
+                crate::services::ServiceEffect::Reply { msg, .. } => Some(msg.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_password_reset_flow() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.accounts()
+            .register("erin", "oldpass", Some("erin@example.com"))
+            .await
+            .unwrap();
+        let recorder = Arc::new(RecordingSender::default());
+        let mailer: Arc<dyn MailSender> = recorder.clone();
+
+        let replies = handle_resetpass(&db, Some(&mailer), "uid", &["erin"], texts).await;
+        assert!(reply_text(&replies).contains("reset code has been sent"));
+        let (to, body) = recorder.sent.lock().unwrap().pop().unwrap();
+        assert_eq!(to, "erin@example.com");
+        let code = body
+            .split_whitespace()
+            .skip_while(|w| *w != "erin")
+            .nth(1)
+            .unwrap()
+            .to_string();
+
+        let replies = handle_setpass(&db, "uid", &["erin", "BADCODE", "newpass"], texts).await;
+        assert!(reply_text(&replies).contains("Invalid or expired"));
+        let replies = handle_setpass(&db, "uid", &["erin", &code, "newpass"], texts).await;
+        assert!(reply_text(&replies).contains("has been changed"));
+
+        assert!(db.accounts().identify("erin", "newpass").await.is_ok());
+        assert!(db.accounts().identify("erin", "oldpass").await.is_err());
+
+        // Codes are single-use
+        let replies = handle_setpass(&db, "uid", &["erin", &code, "again"], texts).await;
+        assert!(reply_text(&replies).contains("Invalid or expired"));
+    }
+
+    #[tokio::test]
+    async fn test_resetpass_does_not_reveal_accounts() {
+        let db = Database::new(":memory:").await.unwrap();
+        let recorder = Arc::new(RecordingSender::default());
+        let mailer: Arc<dyn MailSender> = recorder.clone();
+
+        let replies = handle_resetpass(&db, Some(&mailer), "uid", &["nobody"], texts).await;
+        assert!(reply_text(&replies).contains("reset code has been sent"));
+        assert!(recorder.sent.lock().unwrap().is_empty());
+
+        let replies = handle_resetpass(&db, None, "uid", &["nobody"], texts).await;
+        assert!(reply_text(&replies).contains("not available"));
+    }
+}
@@ -0,0 +1,150 @@
+//! Outgoing mail for NickServ confirmation and password reset codes.
+//!
+//! Mail goes through a [`MailSender`], so deployments can plug in their own
+//! delivery. The built-in sender pipes messages to a sendmail-compatible
+//! binary, which covers local MTAs and SMTP relays such as msmtp.
+
+use crate::config::MailConfig;
+use async_trait::async_trait;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// How long sendmail may take to accept a message.
+const SENDMAIL_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum MailError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("sendmail exited with {0}")]
+    Failed(std::process::ExitStatus),
+    #[error("invalid recipient address")]
+    InvalidRecipient,
+    #[error("timed out")]
+    Timeout,
+}
+
+#[async_trait]
+pub trait MailSender: Send + Sync {
+    /// Short name for logs.
+    fn name(&self) -> &'static str;
+
+    /// Deliver a plain-text mail to `to`.
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), MailError>;
+}
+
+/// Pipes mail to a sendmail-compatible binary (`sendmail -t -i`).
+pub struct SendmailSender {
+    path: String,
+    from: String,
+}
+
+impl SendmailSender {
+    pub fn new(path: String, from: String) -> Self {
+        Self { path, from }
+    }
+
+    /// Build the message headers and body.
+    fn compose(&self, to: &str, subject: &str, body: &str) -> String {
+        format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+            self.from, to, subject, body
+        )
+    }
+}
+
+#[async_trait]
+impl MailSender for SendmailSender {
+    fn name(&self) -> &'static str {
+        "sendmail"
+    }
+
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), MailError> {
+        // The address ends up in a header; refuse anything that could add more
+        if !is_plain_address(to) {
+            return Err(MailError::InvalidRecipient);
+        }
+
+        let mut child = Command::new(&self.path)
+            .args(["-t", "-i"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let message = self.compose(to, subject, body);
+        let deliver = async {
+            if let Some(mut stdin) = child.stdin.take() {
+                // A sendmail that exits early reports why through its status
+                match stdin.write_all(message.as_bytes()).await {
+                    Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e),
+                    _ => {}
+                }
+            }
+            child.wait().await
+        };
+        let status = tokio::time::timeout(SENDMAIL_TIMEOUT, deliver)
+            .await
+            .map_err(|_| MailError::Timeout)??;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(MailError::Failed(status))
+        }
+    }
+}
+
+/// A single `local@domain` address with no whitespace or control characters.
+fn is_plain_address(address: &str) -> bool {
+    let mut parts = address.split('@');
+    matches!(
+        (parts.next(), parts.next(), parts.next()),
+        (Some(local), Some(domain), None) if !local.is_empty() && !domain.is_empty()
+    ) && !address
+        .chars()
+        .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | ',' | ';'))
+}
+
+/// Build the sender selected by `config`, if any.
+pub fn from_config(config: &MailConfig) -> Option<Arc<dyn MailSender>> {
+    let path = config.sendmail.clone()?;
+    Some(Arc::new(SendmailSender::new(path, config.from.clone())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_plain_address() {
+        assert!(is_plain_address("alice@example.com"));
+        assert!(!is_plain_address("alice"));
+        assert!(!is_plain_address("@example.com"));
+        assert!(!is_plain_address("a@b@c"));
+        assert!(!is_plain_address(
+            "alice@example.com\r\nBcc: eve@example.com"
+        ));
+        assert!(!is_plain_address("alice@example.com, eve@example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_sendmail_reports_failure() {
+        let sender = SendmailSender::new("false".to_string(), "services@test".to_string());
+        let result = sender.send("alice@example.com", "Test", "Body").await;
+        assert!(matches!(result, Err(MailError::Failed(_))));
+
+        let sender = SendmailSender::new("true".to_string(), "services@test".to_string());
+        assert!(
+            sender
+                .send("alice@example.com", "Test", "Body")
+                .await
+                .is_ok()
+        );
+    }
+}
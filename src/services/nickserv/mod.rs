@@ -6,7 +6,10 @@
 //! - `GHOST <nick>` - Kill session using your nick
 //! - `INFO <nick>` - Show account information
 //! - `SET <option> <value>` - Configure account settings
+//! - `CONFIRM`, `RESETPASS`, `SETPASS` - Email confirmation and password reset
 
 mod commands;
+pub mod mail;
 
 pub use commands::NickServ;
+pub use commands::verify::mail_code;
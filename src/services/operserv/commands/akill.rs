@@ -0,0 +1,165 @@
+//! AKILL: network-wide user@host bans.
+//!
+//! AKILLs are G-lines under another name, so they share the G-line table,
+//! cache and S2S propagation, and show up in STATS g.
+
+use super::{OperServ, OperServResult, take_expiry};
+use crate::handlers::format_duration;
+use crate::services::ServiceEffect;
+use crate::state::Matrix;
+use slirc_proto::wildcard_match;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Returns true if the user@host mask matches everyone.
+fn is_too_broad(mask: &str) -> bool {
+    mask.chars().all(|c| matches!(c, '*' | '?' | '@' | '.'))
+}
+
+impl OperServ {
+    /// Handle AKILL command.
+    pub(super) async fn handle_akill(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> OperServResult {
+        let sub = args.first().map(|s| s.to_uppercase());
+        match sub.as_deref() {
+            Some("ADD") => self.handle_akill_add(matrix, uid, nick, &args[1..]).await,
+            Some("DEL") => self.handle_akill_del(uid, &args[1..]).await,
+            Some("LIST") => self.handle_akill_list(uid, args.get(1).copied()).await,
+            _ => self.error_reply(uid, "Syntax: AKILL <ADD|DEL|LIST> [args]"),
+        }
+    }
+
+    /// Handle AKILL ADD.
+    async fn handle_akill_add(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> OperServResult {
+        let Ok((args, duration)) = take_expiry(args) else {
+            return self.error_reply(uid, "Invalid expiry. Use e.g. +30d, +12h or +0.");
+        };
+        let Some(&target) = args.first() else {
+            return self.error_reply(uid, "Syntax: AKILL ADD [+expiry] <mask> [reason]");
+        };
+
+        // A bare nick bans that user's host
+        let mask = if target.contains('@') {
+            target.to_string()
+        } else {
+            let Some(target_uid) = matrix.user_manager.get_first_uid(target) else {
+                return self.error_reply(uid, &format!("\x02{}\x02 is not online.", target));
+            };
+            let Some(user_arc) = matrix
+                .user_manager
+                .users
+                .get(&target_uid)
+                .map(|u| u.clone())
+            else {
+                return self.error_reply(uid, &format!("\x02{}\x02 is not online.", target));
+            };
+            let host = user_arc.read().await.host.clone();
+            format!("*@{}", host)
+        };
+
+        if is_too_broad(&mask) {
+            return self.error_reply(uid, &format!("\x02{}\x02 would ban everyone.", mask));
+        }
+
+        let reason = match args.get(1..).filter(|rest| !rest.is_empty()) {
+            Some(rest) => rest.join(" "),
+            None => "No reason given".to_string(),
+        };
+        let expiry = duration.map_or_else(|| "permanent".to_string(), format_duration);
+
+        vec![
+            self.reply_effect(
+                uid,
+                &format!("AKILL on \x02{}\x02 added ({}, {}).", mask, reason, expiry),
+            ),
+            ServiceEffect::Akill {
+                mask,
+                reason,
+                set_by: nick.to_string(),
+                duration,
+            },
+        ]
+    }
+
+    /// Handle AKILL DEL.
+    async fn handle_akill_del(&self, uid: &str, args: &[&str]) -> OperServResult {
+        let Some(&mask) = args.first() else {
+            return self.error_reply(uid, "Syntax: AKILL DEL <mask>");
+        };
+
+        match self.db.bans().get_active_glines().await {
+            Ok(glines) if glines.iter().any(|g| g.mask == mask) => vec![
+                self.reply_effect(uid, &format!("AKILL on \x02{}\x02 removed.", mask)),
+                ServiceEffect::AkillRemove {
+                    mask: mask.to_string(),
+                },
+            ],
+            Ok(_) => self.error_reply(uid, &format!("No AKILL found for \x02{}\x02.", mask)),
+            Err(e) => {
+                warn!(error = ?e, "Failed to list AKILLs");
+                self.error_reply(uid, "Database error. Please try again later.")
+            }
+        }
+    }
+
+    /// Handle AKILL LIST.
+    async fn handle_akill_list(&self, uid: &str, pattern: Option<&str>) -> OperServResult {
+        let glines = match self.db.bans().get_active_glines().await {
+            Ok(glines) => glines,
+            Err(e) => {
+                warn!(error = ?e, "Failed to list AKILLs");
+                return self.error_reply(uid, "Database error. Please try again later.");
+            }
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        let mut effects = vec![self.reply_effect(uid, "Current AKILLs:")];
+        let mut shown = 0;
+        for gline in glines
+            .iter()
+            .filter(|g| pattern.is_none_or(|p| wildcard_match(p, &g.mask)))
+        {
+            shown += 1;
+            let expiry = gline
+                .expires_at
+                .map_or_else(|| "permanent".to_string(), |at| format_duration(at - now));
+            effects.push(self.reply_effect(
+                uid,
+                &format!(
+                    "  {}. \x02{}\x02 ({}) set by {}, {}",
+                    shown,
+                    gline.mask,
+                    gline.reason.as_deref().unwrap_or("No reason given"),
+                    gline.set_by,
+                    expiry
+                ),
+            ));
+        }
+        effects.push(self.reply_effect(uid, &format!("End of list - {} AKILL(s).", shown)));
+        effects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_too_broad() {
+        assert!(is_too_broad("*@*"));
+        assert!(is_too_broad("*@*.*"));
+        assert!(!is_too_broad("*@*.example.com"));
+        assert!(!is_too_broad("baduser@*"));
+    }
+}
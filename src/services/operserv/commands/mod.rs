@@ -0,0 +1,190 @@
+//! OperServ command handlers.
+//!
+//! Bans live in `akill.rs`, per-IP session handling in `session.rs`, and the
+//! GLOBAL and MODE overrides in `network.rs`.
+
+mod akill;
+mod network;
+mod session;
+
+use crate::db::Database;
+use crate::handlers::parse_duration;
+use crate::services::base::ServiceBase;
+use crate::services::{Service, ServiceEffect};
+use crate::state::Matrix;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Returns true if the command changes persisted state.
+fn is_write_command(command: &str, args: &[&str]) -> bool {
+    let sub = |i: usize| args.get(i).map(|s| s.to_uppercase());
+    match command {
+        "AKILL" => matches!(sub(0).as_deref(), Some("ADD" | "DEL")),
+        "SESSION" => {
+            sub(0).as_deref() == Some("LIMIT") && matches!(sub(1).as_deref(), Some("ADD" | "DEL"))
+        }
+        _ => false,
+    }
+}
+
+/// Parse an optional leading `+expiry` argument (`+30d`, `+2h`, `+0` for
+/// permanent).
+///
+/// Returns the remaining arguments and the duration in seconds, or `Err` if
+/// the expiry is malformed.
+fn take_expiry<'a>(args: &'a [&'a str]) -> Result<(&'a [&'a str], Option<i64>), ()> {
+    match args.first().and_then(|arg| arg.strip_prefix('+')) {
+        Some("0") => Ok((&args[1..], None)),
+        Some(expiry) => parse_duration(expiry)
+            .map(|secs| (&args[1..], Some(secs)))
+            .ok_or(()),
+        None => Ok((args, None)),
+    }
+}
+
+/// Result of an OperServ command - a list of effects to apply.
+pub type OperServResult = Vec<ServiceEffect>;
+
+/// OperServ service.
+pub struct OperServ {
+    db: Database,
+}
+
+impl ServiceBase for OperServ {
+    fn service_name(&self) -> &'static str {
+        "OperServ"
+    }
+
+    fn db(&self) -> &Database {
+        &self.db
+    }
+}
+
+#[async_trait]
+impl Service for OperServ {
+    fn name(&self) -> &'static str {
+        "OperServ"
+    }
+
+    fn aliases(&self) -> Vec<&'static str> {
+        vec!["OS"]
+    }
+
+    async fn handle(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        text: &str,
+    ) -> Vec<ServiceEffect> {
+        self.handle_command(matrix, uid, nick, text).await
+    }
+}
+
+impl OperServ {
+    /// Create a new OperServ service.
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Handle a PRIVMSG to OperServ.
+    /// Returns a list of effects that the caller should apply.
+    pub async fn handle_command(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        text: &str,
+    ) -> OperServResult {
+        if !self.is_oper(matrix, uid).await {
+            return self.error_reply(uid, "Access denied. OperServ is for operators only.");
+        }
+
+        let parts: Vec<&str> = text.split_whitespace().collect();
+        if parts.is_empty() {
+            return self.help_reply(uid);
+        }
+
+        let command = parts[0].to_uppercase();
+        let args = &parts[1..];
+
+        if matrix.lifecycle_manager.in_maintenance() && is_write_command(&command, args) {
+            return self.read_only_reply(uid);
+        }
+
+        match command.as_str() {
+            "AKILL" => self.handle_akill(matrix, uid, nick, args).await,
+            "GLOBAL" => self.handle_global(uid, nick, args),
+            "MODE" => self.handle_mode(matrix, uid, nick, args),
+            "SESSION" => self.handle_session(matrix, uid, nick, args).await,
+            "HELP" => self.help_reply(uid),
+            _ => self.unknown_command(uid, &command),
+        }
+    }
+
+    // ========== OperServ-specific helper methods ==========
+
+    /// Create a single reply effect.
+    fn reply_effect(&self, target_uid: &str, text: &str) -> ServiceEffect {
+        <Self as ServiceBase>::reply_effect(self, target_uid, text)
+    }
+
+    /// Create an error reply.
+    fn error_reply(&self, uid: &str, text: &str) -> OperServResult {
+        <Self as ServiceBase>::error_reply(self, uid, text)
+    }
+
+    /// Create help reply.
+    fn help_reply(&self, uid: &str) -> OperServResult {
+        vec![
+            self.reply_effect(uid, "***** OperServ Help *****"),
+            self.reply_effect(uid, "OperServ provides network tools for operators."),
+            self.reply_effect(uid, " "),
+            self.reply_effect(uid, "Available commands:"),
+            self.reply_effect(uid, "  AKILL ADD [+expiry] <mask> [reason]"),
+            self.reply_effect(uid, "  AKILL DEL <mask>"),
+            self.reply_effect(uid, "  AKILL LIST [pattern]"),
+            self.reply_effect(uid, "  GLOBAL <message>"),
+            self.reply_effect(uid, "  MODE <#channel> <modes> [args]"),
+            self.reply_effect(uid, "  SESSION LIST <threshold>"),
+            self.reply_effect(uid, "  SESSION VIEW <ip>"),
+            self.reply_effect(
+                uid,
+                "  SESSION LIMIT ADD [+expiry] <ip|cidr> <limit> [reason]",
+            ),
+            self.reply_effect(uid, "  SESSION LIMIT DEL <ip|cidr>"),
+            self.reply_effect(uid, "  SESSION LIMIT LIST"),
+            self.reply_effect(uid, " "),
+            self.reply_effect(
+                uid,
+                "Expiry is written +30d, +12h or +90m; +0 is permanent.",
+            ),
+            self.reply_effect(uid, "***** End of Help *****"),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_expiry() {
+        assert_eq!(
+            take_expiry(&["+1d", "*@host"]),
+            Ok((&["*@host"][..], Some(86400)))
+        );
+        assert_eq!(take_expiry(&["+0", "*@host"]), Ok((&["*@host"][..], None)));
+        assert_eq!(take_expiry(&["*@host"]), Ok((&["*@host"][..], None)));
+        assert_eq!(take_expiry(&["+soon", "*@host"]), Err(()));
+    }
+
+    #[test]
+    fn test_write_commands() {
+        assert!(is_write_command("AKILL", &["add", "*@host"]));
+        assert!(!is_write_command("AKILL", &["LIST"]));
+        assert!(is_write_command("SESSION", &["LIMIT", "DEL", "10.0.0.1"]));
+        assert!(!is_write_command("SESSION", &["VIEW", "10.0.0.1"]));
+        assert!(!is_write_command("GLOBAL", &["hello"]));
+    }
+}
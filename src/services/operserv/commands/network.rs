@@ -0,0 +1,68 @@
+//! Network-wide OperServ commands: GLOBAL, MODE.
+
+use super::{OperServ, OperServResult};
+use crate::services::ServiceEffect;
+use crate::state::Matrix;
+use slirc_proto::{Mode, irc_to_lower};
+use std::sync::Arc;
+use tracing::info;
+
+impl OperServ {
+    /// Handle GLOBAL command.
+    pub(super) fn handle_global(&self, uid: &str, nick: &str, args: &[&str]) -> OperServResult {
+        if args.is_empty() {
+            return self.error_reply(uid, "Syntax: GLOBAL <message>");
+        }
+
+        let text = args.join(" ");
+        info!(target: "audit", oper = %nick, text = %text, "OperServ GLOBAL");
+        vec![
+            ServiceEffect::GlobalNotice {
+                sender: "OperServ".to_string(),
+                text: format!("[Network Notice] {}", text),
+            },
+            self.reply_effect(uid, "Global notice sent."),
+        ]
+    }
+
+    /// Handle MODE command.
+    pub(super) fn handle_mode(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> OperServResult {
+        let (Some(&channel), Some(_)) = (args.first(), args.get(1)) else {
+            return self.error_reply(uid, "Syntax: MODE <#channel> <modes> [args]");
+        };
+
+        if !matrix
+            .channel_manager
+            .channels
+            .contains_key(&irc_to_lower(channel))
+        {
+            return self.error_reply(uid, &format!("Channel \x02{}\x02 does not exist.", channel));
+        }
+
+        let modes = match Mode::as_channel_modes(&args[1..]) {
+            Ok(modes) if !modes.is_empty() => modes,
+            Ok(_) => return self.error_reply(uid, "No modes given."),
+            Err(e) => return self.error_reply(uid, &format!("Invalid modes: {}", e)),
+        };
+
+        let mode_text = args[1..].join(" ");
+        info!(target: "audit", oper = %nick, channel = %channel, modes = %mode_text, "OperServ MODE");
+        vec![
+            ServiceEffect::ChannelModes {
+                channel: channel.to_string(),
+                setter: "OperServ".to_string(),
+                modes,
+            },
+            self.reply_effect(
+                uid,
+                &format!("Set modes \x02{}\x02 on \x02{}\x02.", mode_text, channel),
+            ),
+        ]
+    }
+}
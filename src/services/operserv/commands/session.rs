@@ -0,0 +1,196 @@
+//! SESSION: per-IP connection counts and limit overrides.
+
+use super::{OperServ, OperServResult, take_expiry};
+use crate::handlers::format_duration;
+use crate::services::ServiceEffect;
+use crate::state::Matrix;
+use ipnet::IpNet;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Parse an IP address or CIDR range into a normalized network.
+fn parse_net(arg: &str) -> Option<IpNet> {
+    arg.parse::<IpNet>()
+        .or_else(|_| arg.parse::<IpAddr>().map(IpNet::from))
+        .ok()
+        .map(|net| net.trunc())
+}
+
+impl OperServ {
+    /// Handle SESSION command.
+    pub(super) async fn handle_session(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        nick: &str,
+        args: &[&str],
+    ) -> OperServResult {
+        let sub = args.first().map(|s| s.to_uppercase());
+        match sub.as_deref() {
+            Some("LIST") => self.handle_session_list(matrix, uid, args.get(1).copied()),
+            Some("VIEW") => self.handle_session_view(matrix, uid, args.get(1).copied()),
+            Some("LIMIT") => self.handle_session_limit(uid, nick, &args[1..]).await,
+            _ => self.error_reply(uid, "Syntax: SESSION <LIST|VIEW|LIMIT> [args]"),
+        }
+    }
+
+    /// Handle SESSION LIST.
+    fn handle_session_list(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        threshold: Option<&str>,
+    ) -> OperServResult {
+        let Some(threshold) = threshold.and_then(|t| t.parse::<u32>().ok()) else {
+            return self.error_reply(uid, "Syntax: SESSION LIST <threshold>");
+        };
+
+        let sessions = matrix
+            .security_manager
+            .rate_limiter
+            .sessions_over(threshold);
+        let mut effects = vec![self.reply_effect(
+            uid,
+            &format!("Hosts with more than {} session(s):", threshold),
+        )];
+        for (ip, count) in &sessions {
+            effects.push(self.reply_effect(uid, &format!("  {:>4}  {}", count, ip)));
+        }
+        effects.push(self.reply_effect(uid, &format!("End of list - {} host(s).", sessions.len())));
+        effects
+    }
+
+    /// Handle SESSION VIEW.
+    fn handle_session_view(
+        &self,
+        matrix: &Arc<Matrix>,
+        uid: &str,
+        ip: Option<&str>,
+    ) -> OperServResult {
+        let Some(ip) = ip.and_then(|ip| ip.parse::<IpAddr>().ok()) else {
+            return self.error_reply(uid, "Syntax: SESSION VIEW <ip>");
+        };
+
+        let limiter = &matrix.security_manager.rate_limiter;
+        vec![self.reply_effect(
+            uid,
+            &format!(
+                "\x02{}\x02 has {} session(s); the limit is {}.",
+                ip,
+                limiter.session_count(ip),
+                limiter.max_sessions(ip)
+            ),
+        )]
+    }
+
+    /// Handle SESSION LIMIT.
+    async fn handle_session_limit(&self, uid: &str, nick: &str, args: &[&str]) -> OperServResult {
+        let sub = args.first().map(|s| s.to_uppercase());
+        match sub.as_deref() {
+            Some("ADD") => self.handle_session_limit_add(uid, nick, &args[1..]),
+            Some("DEL") => self.handle_session_limit_del(uid, args.get(1).copied()),
+            Some("LIST") => self.handle_session_limit_list(uid).await,
+            _ => self.error_reply(uid, "Syntax: SESSION LIMIT <ADD|DEL|LIST> [args]"),
+        }
+    }
+
+    /// Handle SESSION LIMIT ADD.
+    fn handle_session_limit_add(&self, uid: &str, nick: &str, args: &[&str]) -> OperServResult {
+        let Ok((args, duration)) = take_expiry(args) else {
+            return self.error_reply(uid, "Invalid expiry. Use e.g. +30d, +12h or +0.");
+        };
+        let (Some(net), Some(max_sessions)) = (
+            args.first().and_then(|arg| parse_net(arg)),
+            args.get(1).and_then(|arg| arg.parse::<u32>().ok()),
+        ) else {
+            return self.error_reply(
+                uid,
+                "Syntax: SESSION LIMIT ADD [+expiry] <ip|cidr> <limit> [reason]",
+            );
+        };
+        if max_sessions == 0 {
+            return self.error_reply(uid, "The limit must be at least 1.");
+        }
+
+        let reason = args
+            .get(2..)
+            .filter(|rest| !rest.is_empty())
+            .map(|rest| rest.join(" "));
+        let expiry = duration.map_or_else(|| "permanent".to_string(), format_duration);
+
+        vec![
+            self.reply_effect(
+                uid,
+                &format!(
+                    "Session limit for \x02{}\x02 set to {} ({}).",
+                    net, max_sessions, expiry
+                ),
+            ),
+            ServiceEffect::SessionLimit {
+                net,
+                max_sessions,
+                reason,
+                set_by: nick.to_string(),
+                duration,
+            },
+        ]
+    }
+
+    /// Handle SESSION LIMIT DEL.
+    fn handle_session_limit_del(&self, uid: &str, arg: Option<&str>) -> OperServResult {
+        let Some(net) = arg.and_then(parse_net) else {
+            return self.error_reply(uid, "Syntax: SESSION LIMIT DEL <ip|cidr>");
+        };
+
+        vec![
+            self.reply_effect(uid, &format!("Session limit for \x02{}\x02 removed.", net)),
+            ServiceEffect::SessionLimitRemove { net },
+        ]
+    }
+
+    /// Handle SESSION LIMIT LIST.
+    async fn handle_session_limit_list(&self, uid: &str) -> OperServResult {
+        let limits = match self.db.bans().get_active_session_limits().await {
+            Ok(limits) => limits,
+            Err(e) => {
+                warn!(error = ?e, "Failed to list session limits");
+                return self.error_reply(uid, "Database error. Please try again later.");
+            }
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        let mut effects = vec![self.reply_effect(uid, "Session limits:")];
+        for limit in &limits {
+            let expiry = limit
+                .expires_at
+                .map_or_else(|| "permanent".to_string(), |at| format_duration(at - now));
+            effects.push(self.reply_effect(
+                uid,
+                &format!(
+                    "  \x02{}\x02 {} ({}) set by {}, {}",
+                    limit.mask,
+                    limit.max_sessions,
+                    limit.reason.as_deref().unwrap_or("No reason given"),
+                    limit.set_by,
+                    expiry
+                ),
+            ));
+        }
+        effects.push(self.reply_effect(uid, &format!("End of list - {} limit(s).", limits.len())));
+        effects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_net() {
+        assert_eq!(parse_net("10.0.0.1"), "10.0.0.1/32".parse().ok());
+        assert_eq!(parse_net("10.0.0.99/24"), "10.0.0.0/24".parse().ok());
+        assert_eq!(parse_net("2001:db8::1"), "2001:db8::1/128".parse().ok());
+        assert_eq!(parse_net("*@host"), None);
+    }
+}
@@ -0,0 +1,11 @@
+//! OperServ - Network operations service (operators only).
+//!
+//! Handles:
+//! - `AKILL ADD/DEL/LIST` - Network-wide user@host bans, stored as G-lines
+//! - `GLOBAL <message>` - Notice every user on the network
+//! - `MODE <#channel> <modes>` - Override channel modes
+//! - `SESSION LIST/VIEW/LIMIT` - Inspect connections per IP and set limits
+
+mod commands;
+
+pub use commands::OperServ;
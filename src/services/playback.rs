@@ -39,24 +39,25 @@ impl Playback {
         None
     }
 
-    fn dm_key(
-        _matrix: &Matrix,
-        requester_nick: &str,
-        target: &str,
-        requester_account: Option<&str>,
-    ) -> String {
-        // dm:a:<acct1>:a:<acct2> or dm:a:<acct>:u:<nick> for unregistered peer
-        let requester_part = if let Some(acct) = requester_account {
+    /// The requester's half of a DM key.
+    fn dm_self(requester_nick: &str, requester_account: Option<&str>) -> String {
+        if let Some(acct) = requester_account {
             format!("a:{}", irc_to_lower(acct))
         } else {
             format!("u:{}", irc_to_lower(requester_nick))
-        };
+        }
+    }
 
+    async fn dm_key(matrix: &Matrix, dm_self: &str, target: &str) -> String {
+        // dm:a:<acct1>:a:<acct2> or dm:a:<acct>:u:<nick> for unregistered peer;
+        // TARGETS reports account peers by account name
         let target_lower = irc_to_lower(target);
-        // Without async context, assume unregistered peer (sufficient for tests)
-        let peer_part = format!("u:{}", target_lower);
+        let peer_part = match matrix.db.accounts().find_by_name(target).await {
+            Ok(Some(account)) => format!("a:{}", irc_to_lower(&account.name)),
+            _ => format!("u:{}", target_lower),
+        };
 
-        let mut parts = [requester_part, peer_part];
+        let mut parts = [dm_self.to_string(), peer_part];
         parts.sort();
         format!("dm:{}:{}", parts[0], parts[1])
     }
@@ -101,7 +102,7 @@ impl Service for Playback {
         text: &str,
     ) -> Vec<ServiceEffect> {
         // Determine requester caps for server-time
-        let (has_server_time, requester_nick, requester_account, channels) = {
+        let (has_server_time, dm_self, channels) = {
             let user_arc = match matrix.user_manager.users.get(uid) {
                 Some(u) => u.value().clone(),
                 None => return vec![],
@@ -110,7 +111,8 @@ impl Service for Playback {
             let caps = user.caps.clone();
             let has = caps.contains("server-time");
             let chs = user.channels.iter().cloned().collect::<Vec<_>>();
-            (has, user.nick.clone(), user.account.clone(), chs)
+            let dm_self = Self::dm_self(&user.nick, user.account.as_deref());
+            (has, dm_self, chs)
         };
 
         // Parse command: expects starts with "play"
@@ -160,13 +162,7 @@ impl Service for Playback {
                     if let Ok(targets) = matrix
                         .service_manager
                         .history
-                        .query_targets(
-                            start,
-                            i64::MAX,
-                            1000,
-                            requester_nick.clone(),
-                            channels.clone(),
-                        )
+                        .query_targets(start, i64::MAX, 1000, dm_self.clone(), channels.clone())
                         .await
                     {
                         for (display, _ts) in targets {
@@ -174,12 +170,7 @@ impl Service for Playback {
                             if display.starts_with('#') || display.starts_with('&') {
                                 continue;
                             }
-                            let dm_key = Self::dm_key(
-                                matrix,
-                                &requester_nick,
-                                &display,
-                                requester_account.as_deref(),
-                            );
+                            let dm_key = Self::dm_key(matrix, &dm_self, &display).await;
                             let q = HistoryQuery {
                                 target: dm_key,
                                 start: Some(start + 1_000_000),
@@ -203,19 +194,14 @@ impl Service for Playback {
                     if let Ok(targets) = matrix
                         .service_manager
                         .history
-                        .query_targets(0, i64::MAX, 1000, requester_nick.clone(), channels.clone())
+                        .query_targets(0, i64::MAX, 1000, dm_self.clone(), channels.clone())
                         .await
                     {
                         for (display, _ts) in targets {
                             if display.starts_with('#') || display.starts_with('&') {
                                 continue;
                             }
-                            let dm_key = Self::dm_key(
-                                matrix,
-                                &requester_nick,
-                                &display,
-                                requester_account.as_deref(),
-                            );
+                            let dm_key = Self::dm_key(matrix, &dm_self, &display).await;
                             let q = HistoryQuery {
                                 target: dm_key,
                                 start: None,
@@ -329,8 +315,7 @@ impl Service for Playback {
             // play <nick> <start> — DM playback
             (Some(tgt), Some(start), None) => {
                 if let Some(start_ns) = Self::parse_unix_ts_nanos(start) {
-                    let dm_key =
-                        Self::dm_key(matrix, &requester_nick, tgt, requester_account.as_deref());
+                    let dm_key = Self::dm_key(matrix, &dm_self, tgt).await;
                     let q = HistoryQuery {
                         target: dm_key,
                         start: Some(start_ns + 1_000_000),
@@ -4,12 +4,19 @@
 //! and the CRDT representation used for distributed synchronization.
 
 use crate::state::{ListEntry, MemberModes, Topic};
+use slirc_proto::casemap::irc_to_lower;
 use slirc_proto::sync::channel::{ChannelCrdt, ListEntryCrdt, TopicCrdt};
 use slirc_proto::sync::clock::{HybridTimestamp, ServerId};
+use slirc_proto::sync::traits::{AwSet, LwwRegister};
 use std::collections::HashSet;
 
 use super::{ChannelActor, ChannelMode};
 
+/// Boolean modes carried in the channel CRDT.
+const CRDT_FLAGS: [char; 14] = [
+    'n', 't', 'm', 'i', 's', 'p', 'R', 'c', 'C', 'z', 'D', 'S', 'B', 'G',
+];
+
 impl ChannelActor {
     /// Merge a CRDT representation into the channel state.
     pub async fn handle_merge_crdt(&mut self, crdt: ChannelCrdt, source: Option<ServerId>) {
@@ -17,6 +24,9 @@ impl ChannelActor {
         let mut current_crdt = self.to_crdt();
         current_crdt.merge(&crdt);
 
+        // An older channel TS from the peer becomes ours
+        self.created = self.created.min(current_crdt.created_at.millis / 1000);
+
         // Update self from merged CRDT
         self.apply_merged_topic(&current_crdt);
         self.apply_merged_modes(&current_crdt);
@@ -117,11 +127,24 @@ impl ChannelActor {
                 .insert('G', crdt.modes.censor.timestamp());
         }
 
+        // Cleared modes keep their stamp so the removal is not undone
+        for mode in CRDT_FLAGS {
+            if let Some(flag) = crdt.modes.flag(mode)
+                && !*flag.value()
+            {
+                self.mode_timestamps.insert(mode, flag.timestamp());
+            }
+        }
+
         if let Some(key) = crdt.key.value() {
             new_modes.insert(ChannelMode::Key(key.clone(), crdt.key.timestamp()));
+        } else {
+            self.mode_timestamps.insert('k', crdt.key.timestamp());
         }
         if let Some(limit) = crdt.limit.value() {
             new_modes.insert(ChannelMode::Limit(*limit as usize, crdt.limit.timestamp()));
+        } else {
+            self.mode_timestamps.insert('l', crdt.limit.timestamp());
         }
         if let Some(redirect) = crdt.modes.redirect.value() {
             new_modes.insert(ChannelMode::Redirect(
@@ -184,33 +207,70 @@ impl ChannelActor {
 
     /// Apply merged lists from CRDT.
     fn apply_merged_lists(&mut self, crdt: &ChannelCrdt) {
-        self.bans = crdt
-            .bans
-            .iter()
-            .map(|e| ListEntry {
-                mask: e.mask.clone(),
-                set_by: e.set_by.clone(),
-                set_at: e.set_at,
-            })
-            .collect();
-        self.excepts = crdt
-            .excepts
-            .iter()
-            .map(|e| ListEntry {
-                mask: e.mask.clone(),
-                set_by: e.set_by.clone(),
-                set_at: e.set_at,
-            })
-            .collect();
-        self.invex = crdt
-            .invites
+        self.bans = self.merge_list('b', &crdt.bans);
+        self.excepts = self.merge_list('e', &crdt.excepts);
+        self.invex = self.merge_list('I', &crdt.invites);
+        self.quiets = self.merge_list('q', &crdt.quiets);
+        self.lists_dirty = true;
+    }
+
+    /// Take one merged list, recording its add and removal stamps.
+    fn merge_list(&mut self, mode: char, set: &AwSet<ListEntryCrdt>) -> Vec<ListEntry> {
+        self.list_timestamps.retain(|(m, _), _| *m != mode);
+        for (entry, ts) in set.removed() {
+            self.list_timestamps
+                .insert((mode, irc_to_lower(&entry.mask)), ts);
+        }
+
+        let mut list = Vec::with_capacity(set.len());
+        for entry in set.iter() {
+            let mask = irc_to_lower(&entry.mask);
+            if let Some(ts) = set.added_at(entry) {
+                self.list_timestamps.insert((mode, mask.clone()), ts);
+            }
+            // Expiry is local to the server that set the timed entry
+            let expires_at = self
+                .list_mut(mode)
+                .and_then(|old| old.iter().find(|e| e.mask == mask))
+                .and_then(|e| e.expires_at);
+            list.push(ListEntry {
+                mask,
+                set_by: entry.set_by.clone(),
+                set_at: entry.set_at,
+                expires_at,
+            });
+        }
+        // Set iteration order is arbitrary; list oldest first
+        list.sort_by(|a, b| a.set_at.cmp(&b.set_at).then_with(|| a.mask.cmp(&b.mask)));
+        list
+    }
+
+    /// Drop list tombstones stamped before `stable_millis`.
+    ///
+    /// Stamps of entries still on a list are kept. Returns the number of
+    /// tombstones dropped.
+    pub fn prune_tombstones(&mut self, stable_millis: i64) -> usize {
+        let listed: HashSet<(char, &str)> = [
+            ('b', &self.bans),
+            ('e', &self.excepts),
+            ('I', &self.invex),
+            ('q', &self.quiets),
+        ]
+        .into_iter()
+        .flat_map(|(mode, list)| list.iter().map(move |e| (mode, e.mask.as_str())))
+        .collect();
+        let stale: Vec<(char, String)> = self
+            .list_timestamps
             .iter()
-            .map(|e| ListEntry {
-                mask: e.mask.clone(),
-                set_by: e.set_by.clone(),
-                set_at: e.set_at,
+            .filter(|((mode, mask), ts)| {
+                ts.millis < stable_millis && !listed.contains(&(*mode, mask.as_str()))
             })
+            .map(|(key, _)| key.clone())
             .collect();
+        for key in &stale {
+            self.list_timestamps.remove(key);
+        }
+        stale.len()
     }
 
     /// Convert channel state to CRDT representation.
@@ -221,11 +281,16 @@ impl ChannelActor {
         // any state older than Now due to LWW rules.
         let base_ts = HybridTimestamp::new(0, 0, &ServerId::new("000"));
         let mut crdt = ChannelCrdt::new(self.name.clone(), base_ts);
+        // Only modes the actor actually has are set below
+        crdt.modes.no_external = LwwRegister::new(false, base_ts);
+        crdt.modes.topic_ops_only = LwwRegister::new(false, base_ts);
+        // The channel TS decides whose member modes win a merge (TS6 rule)
+        crdt.created_at = HybridTimestamp::new(self.created * 1000, 0, &ServerId::new("000"));
 
         self.serialize_topic_to_crdt(&mut crdt, fallback_ts);
         self.serialize_modes_to_crdt(&mut crdt, fallback_ts);
         self.serialize_members_to_crdt(&mut crdt, fallback_ts);
-        self.serialize_lists_to_crdt(&mut crdt, fallback_ts);
+        self.serialize_lists_to_crdt(&mut crdt);
 
         crdt
     }
@@ -382,6 +447,25 @@ impl ChannelActor {
                 _ => {} // Other modes not yet in CRDT
             }
         }
+
+        // Modes we cleared go out as explicit removals
+        for (&mode, &ts) in &self.mode_timestamps {
+            if let Some(flag) = crdt.modes.flag_mut(mode)
+                && !*flag.value()
+            {
+                flag.update(false, ts);
+            }
+        }
+        if crdt.key.value().is_none()
+            && let Some(&ts) = self.mode_timestamps.get(&'k')
+        {
+            crdt.key.update(None, ts);
+        }
+        if crdt.limit.value().is_none()
+            && let Some(&ts) = self.mode_timestamps.get(&'l')
+        {
+            crdt.limit.update(None, ts);
+        }
     }
 
     /// Serialize members to CRDT.
@@ -430,36 +514,43 @@ impl ChannelActor {
     }
 
     /// Serialize lists to CRDT.
-    fn serialize_lists_to_crdt(&self, crdt: &mut ChannelCrdt, fallback_ts: HybridTimestamp) {
-        for entry in &self.bans {
-            crdt.bans.add(
-                ListEntryCrdt {
-                    mask: entry.mask.clone(),
-                    set_by: entry.set_by.clone(),
-                    set_at: entry.set_at,
-                },
-                fallback_ts,
-            );
-        }
-        for entry in &self.excepts {
-            crdt.excepts.add(
-                ListEntryCrdt {
-                    mask: entry.mask.clone(),
-                    set_by: entry.set_by.clone(),
-                    set_at: entry.set_at,
-                },
-                fallback_ts,
-            );
-        }
-        for entry in &self.invex {
-            crdt.invites.add(
-                ListEntryCrdt {
-                    mask: entry.mask.clone(),
-                    set_by: entry.set_by.clone(),
-                    set_at: entry.set_at,
-                },
-                fallback_ts,
-            );
+    ///
+    /// Entries without a recorded stamp (restored from the database or a
+    /// snapshot) fall back to their `set_at`, so every server serializes
+    /// them the same way. Removed entries become tombstones.
+    fn serialize_lists_to_crdt(&self, crdt: &mut ChannelCrdt) {
+        let base_ts = HybridTimestamp::new(0, 0, &ServerId::new("000"));
+        let lists = [
+            ('b', &self.bans, &mut crdt.bans),
+            ('e', &self.excepts, &mut crdt.excepts),
+            ('I', &self.invex, &mut crdt.invites),
+            ('q', &self.quiets, &mut crdt.quiets),
+        ];
+        for (mode, list, set) in lists {
+            for entry in list {
+                let ts = self
+                    .list_timestamps
+                    .get(&(mode, irc_to_lower(&entry.mask)))
+                    .copied()
+                    .unwrap_or_else(|| {
+                        HybridTimestamp::new(entry.set_at * 1000, 0, &ServerId::new("000"))
+                    });
+                set.add(list_entry_crdt(entry), ts);
+            }
+            for ((m, mask), &ts) in &self.list_timestamps {
+                if *m != mode {
+                    continue;
+                }
+                let tombstone = ListEntryCrdt {
+                    mask: mask.clone(),
+                    set_by: String::new(),
+                    set_at: 0,
+                };
+                if !set.contains(&tombstone) {
+                    set.add(tombstone.clone(), base_ts);
+                    set.remove(&tombstone, ts);
+                }
+            }
         }
     }
 
@@ -471,6 +562,15 @@ impl ChannelActor {
         }
     }
 }
+
+fn list_entry_crdt(entry: &ListEntry) -> ListEntryCrdt {
+    ListEntryCrdt {
+        mask: entry.mask.clone(),
+        set_by: entry.set_by.clone(),
+        set_at: entry.set_at,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -582,4 +682,118 @@ mod tests {
         assert!(actor.modes.contains(&ChannelMode::Moderated));
         assert_eq!(*actor.mode_timestamps.get(&'m').unwrap(), t1);
     }
+
+    #[tokio::test]
+    async fn test_merge_older_channel_ts_wins_member_modes() {
+        let mut actor = make_actor("#test");
+        actor.created = 2_000_000;
+        let local = HybridTimestamp::new(2_000_000_500, 0, &ServerId::new("000"));
+        actor.members.insert(
+            "000AAAAAA".to_string(),
+            MemberModes {
+                op: true,
+                op_ts: Some(local),
+                join_time: Some(2_000_000),
+                ..Default::default()
+            },
+        );
+
+        // The peer's channel is older; its ops win and ours are reset
+        let sid = ServerId::new("00B");
+        let created = HybridTimestamp::new(1_000_000_000, 0, &sid);
+        let mut crdt = ChannelCrdt::new("#test".to_string(), created);
+        crdt.join("00BAAAAAA".to_string(), created.increment());
+        crdt.members
+            .get_modes_mut("00BAAAAAA")
+            .unwrap()
+            .op
+            .update(true, created.increment().increment());
+
+        actor.handle_merge_crdt(crdt, None).await;
+
+        assert_eq!(actor.created, 1_000_000);
+        assert!(actor.members["00BAAAAAA"].op);
+        assert!(!actor.members["000AAAAAA"].op);
+    }
+
+    #[tokio::test]
+    async fn test_unban_survives_merge_with_stale_peer() {
+        let mut actor = make_actor("#test");
+        assert!(actor.apply_list_mode('b', "*!*@Bad", true, "op", None));
+        assert!(actor.apply_list_mode('q', "*!*@spam", true, "op", None));
+        let stale = actor.to_crdt();
+
+        assert!(actor.apply_list_mode('b', "*!*@bad", false, "op", None));
+        actor.handle_merge_crdt(stale, None).await;
+
+        assert!(actor.bans.is_empty());
+        assert_eq!(actor.quiets[0].mask, "*!*@spam");
+
+        // The removal travels in our CRDT as a tombstone
+        let crdt = actor.to_crdt();
+        assert!(crdt.bans.is_empty());
+        assert_eq!(crdt.bans.removed().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_prune_tombstones_keeps_listed_entries() {
+        let mut actor = make_actor("#test");
+        assert!(actor.apply_list_mode('b', "*!*@gone", true, "op", None));
+        assert!(actor.apply_list_mode('b', "*!*@kept", true, "op", None));
+        assert!(actor.apply_list_mode('b', "*!*@gone", false, "op", None));
+
+        assert_eq!(actor.prune_tombstones(i64::MIN), 0);
+        assert_eq!(actor.prune_tombstones(i64::MAX), 1);
+        assert_eq!(actor.list_timestamps.len(), 1);
+
+        let crdt = actor.to_crdt();
+        assert_eq!(crdt.bans.removed().count(), 0);
+        assert_eq!(crdt.bans.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_merge_applies_peer_quiets() {
+        let mut actor = make_actor("#test");
+        let sid = ServerId::new("00B");
+        let mut crdt = ChannelCrdt::new("#test".to_string(), HybridTimestamp::new(100, 0, &sid));
+        crdt.quiets.add(
+            ListEntryCrdt {
+                mask: "*!*@Spam".to_string(),
+                set_by: "remote".to_string(),
+                set_at: 1,
+            },
+            HybridTimestamp::new(200, 0, &sid),
+        );
+
+        actor.handle_merge_crdt(crdt, None).await;
+
+        assert_eq!(actor.quiets.len(), 1);
+        assert_eq!(actor.quiets[0].mask, "*!*@spam");
+        assert!(actor.apply_list_mode('q', "*!*@SPAM", false, "op", None));
+    }
+
+    #[tokio::test]
+    async fn test_cleared_mode_survives_merge_with_stale_peer() {
+        let mut actor = make_actor("#test");
+        actor.set_flag_mode(ChannelMode::Moderated, true);
+        actor.modes.insert(ChannelMode::Key(
+            "secret".into(),
+            HybridTimestamp::now(&actor.server_id),
+        ));
+        let stale = actor.to_crdt();
+
+        actor.set_flag_mode(ChannelMode::Moderated, false);
+        actor.clear_param_mode('k', |mode| matches!(mode, ChannelMode::Key(_, _)));
+        actor.handle_merge_crdt(stale, None).await;
+
+        assert!(!actor.modes.contains(&ChannelMode::Moderated));
+        assert!(
+            !actor
+                .modes
+                .iter()
+                .any(|mode| matches!(mode, ChannelMode::Key(_, _)))
+        );
+        // Modes the channel never had are not invented by the merge
+        assert!(!actor.modes.contains(&ChannelMode::NoExternal));
+    }
 }
@@ -4,6 +4,7 @@
 //! including forwarding to remote peer servers.
 
 use super::{ChannelActor, Uid};
+use slirc_proto::mode::{ChannelMode as ProtoChannelMode, Mode};
 use slirc_proto::{Command, Message, Prefix};
 use std::collections::HashSet;
 use std::sync::Arc;
@@ -28,6 +29,20 @@ impl ChannelActor {
         self.forward_to_peers(&msg, exclude.as_ref()).await;
     }
 
+    /// Announce a mode change the server made itself (+f, +j).
+    pub(crate) async fn broadcast_server_mode(
+        &mut self,
+        server_name: &str,
+        mode: Mode<ProtoChannelMode>,
+    ) {
+        let msg = Message {
+            tags: None,
+            prefix: Some(Prefix::ServerName(server_name.to_string())),
+            command: Command::ChannelMODE(self.name.clone(), vec![mode]),
+        };
+        self.handle_broadcast(msg, None).await;
+    }
+
     /// Forward a channel message to all connected peer servers.
     ///
     /// This is part of Innovation 2 (Distributed Server Linking).
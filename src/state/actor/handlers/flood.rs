@@ -0,0 +1,145 @@
+//! Channel flood protection (+f).
+//!
+//! `+f <lines>[m|j]:<seconds>[:kick|ban|mute]`, comma-separated. Message
+//! floods are counted per member inside the actor, so no extra locking is
+//! needed; the configured action is applied the moment a member exceeds it.
+//! Join floods are refused by the join path.
+
+use super::{ChannelActor, ChannelRouteResult, FloodAction, FloodParam, FloodType, Uid};
+use crate::security::UserContext;
+use crate::state::dashmap_ext::DashMapExt;
+use governor::{Quota, RateLimiter};
+use slirc_proto::mode::{ChannelMode as ProtoChannelMode, Mode};
+use slirc_proto::{Command, Message, Prefix, irc_to_lower};
+use std::num::NonZeroU32;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Build a rate limiter allowing `param.count` events per `param.period` seconds.
+fn limiter_for(param: &FloodParam) -> governor::DefaultDirectRateLimiter {
+    // Clamp to 1ns so a huge count can't make Quota::with_period panic
+    let per_token = Duration::from_secs_f64(param.period as f64 / param.count.max(1) as f64)
+        .max(Duration::from_nanos(1));
+    let quota = Quota::with_period(per_token)
+        .unwrap_or_else(|| Quota::per_second(NonZeroU32::MIN))
+        .allow_burst(NonZeroU32::new(param.count).unwrap_or(NonZeroU32::MIN));
+    RateLimiter::direct(quota)
+}
+
+impl ChannelActor {
+    /// Replace the flood settings with those in `spec`.
+    ///
+    /// Invalid parts are skipped. Returns the canonical spec, or `None` if
+    /// no part was valid, in which case the settings are left unchanged.
+    pub(crate) fn set_flood_config(&mut self, spec: &str) -> Option<String> {
+        let params: Vec<FloodParam> = spec
+            .split(',')
+            .filter_map(|part| FloodParam::from_str(part).ok())
+            .collect();
+        if params.is_empty() {
+            return None;
+        }
+
+        self.clear_flood_config();
+        for param in params {
+            if param.type_ == FloodType::Join {
+                self.flood_join_limiter = Some(limiter_for(&param));
+            }
+            // Message limiters are created per member on their first message
+            self.flood_config.insert(param.type_, param);
+        }
+
+        let mut parts: Vec<String> = self.flood_config.values().map(|p| p.to_string()).collect();
+        parts.sort(); // Deterministic order
+        Some(parts.join(","))
+    }
+
+    /// Drop all flood settings and counters (-f).
+    pub(crate) fn clear_flood_config(&mut self) {
+        self.flood_config.clear();
+        self.flood_message_limiters.clear();
+        self.flood_join_limiter = None;
+    }
+
+    /// Count a message from `uid` against +f, punishing the sender if it
+    /// floods.
+    pub(crate) async fn check_flood(
+        &mut self,
+        uid: &Uid,
+        user_context: &UserContext,
+    ) -> Option<ChannelRouteResult> {
+        let param = *self.flood_config.get(&FloodType::Message)?;
+        let limiter = self
+            .flood_message_limiters
+            .entry(uid.clone())
+            .or_insert_with(|| limiter_for(&param));
+        if limiter.check().is_ok() {
+            return None;
+        }
+
+        let matrix = self.matrix.upgrade()?;
+        let server_name = matrix.server_info.name.clone();
+        let mask = format!("*!*@{}", user_context.hostname);
+
+        match param.action {
+            FloodAction::Mute => {
+                self.flood_set_list_mode('q', &mask, &server_name).await;
+                self.flood_message_limiters.remove(uid);
+                self.notify_observer(None);
+                return Some(ChannelRouteResult::BlockedFlood);
+            }
+            FloodAction::Ban => {
+                self.flood_set_list_mode('b', &mask, &server_name).await;
+            }
+            FloodAction::Kick => {}
+        }
+
+        let kick = Message {
+            tags: None,
+            prefix: Some(Prefix::ServerName(server_name)),
+            command: Command::KICK(
+                self.name.clone(),
+                user_context.nickname.clone(),
+                Some(format!(
+                    "Channel flood triggered (+f {}:{})",
+                    param.count, param.period
+                )),
+            ),
+        };
+        // The flooder sees the KICK too
+        self.handle_broadcast(kick, None).await;
+
+        self.members.remove(uid);
+        self.senders.remove(uid);
+        self.user_nicks.remove(uid);
+        self.user_caps.remove(uid);
+        self.flood_message_limiters.remove(uid);
+        self.kicked_users.insert(uid.clone(), Instant::now());
+
+        let uid = uid.clone();
+        let channel_lower = irc_to_lower(&self.name);
+        tokio::spawn(async move {
+            if let Some(user) = matrix.user_manager.users.get_cloned(&uid) {
+                user.write().await.channels.remove(&channel_lower);
+            }
+        });
+
+        crate::metrics::set_channel_members(&self.name, self.members.len() as i64);
+        self.notify_observer(None);
+        self.cleanup_if_empty();
+        Some(ChannelRouteResult::BlockedFlood)
+    }
+
+    /// Add `mask` to the +b or +q list and announce it as the server.
+    async fn flood_set_list_mode(&mut self, mode_char: char, mask: &str, server_name: &str) {
+        if !self.apply_list_mode(mode_char, mask, true, server_name, None) {
+            return;
+        }
+        let mode = match mode_char {
+            'b' => ProtoChannelMode::Ban,
+            _ => ProtoChannelMode::Quiet,
+        };
+        let mode = Mode::Plus(mode, Some(mask.to_string()));
+        self.broadcast_server_mode(server_name, mode).await;
+    }
+}
@@ -1,10 +1,12 @@
 //! INVITE and KNOCK event handling.
 //!
 //! Manages channel invitations and knock requests for +i channels.
+//! Knocks are refused on +K channels and throttled channel-wide.
 
 use super::{ChannelActor, ChannelError, ChannelMode, InviteParams, Uid};
-use slirc_proto::{Command, Message, Prefix};
+use slirc_proto::{Command, Message, Prefix, Response};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::oneshot;
 
 impl ChannelActor {
@@ -76,6 +78,8 @@ impl ChannelActor {
         &mut self,
         sender_uid: Uid,
         sender_prefix: Prefix,
+        reason: Option<String>,
+        channel_delay: Duration,
         reply_tx: oneshot::Sender<Result<(), ChannelError>>,
     ) {
         if self.modes.contains(&ChannelMode::NoKnock) {
@@ -98,20 +102,45 @@ impl ChannelActor {
             return;
         }
 
-        let msg_text = format!("User {} is KNOCKing on {}", nick, self.name);
-        let msg = Message {
-            tags: None,
-            prefix: None,
-            command: Command::NOTICE(self.name.clone(), msg_text),
+        let now = Instant::now();
+        if self
+            .last_knock
+            .is_some_and(|last| now.duration_since(last) < channel_delay)
+        {
+            let _ = reply_tx.send(Err(ChannelError::KnockThrottled));
+            return;
+        }
+        self.last_knock = Some(now);
+
+        let text = match reason {
+            Some(reason) => format!("has asked for an invite ({reason})"),
+            None => "has asked for an invite".to_string(),
         };
 
-        // Send knock notification to ops/halfops using multi-sender
+        // RPL_KNOCK goes to channel operators only, addressed to each of them
         if let Some(matrix) = self.matrix.upgrade() {
-            let msg_arc = Arc::new(msg);
+            let server_prefix = Prefix::ServerName(matrix.server_info.name.clone());
             for (uid, modes) in &self.members {
-                if modes.op || modes.halfop {
-                    matrix.user_manager.try_send_to_uid(uid, msg_arc.clone());
+                if !modes.has_op_or_higher() {
+                    continue;
                 }
+                let Some(op_nick) = self.user_nicks.get(uid) else {
+                    continue;
+                };
+                let msg = Message {
+                    tags: None,
+                    prefix: Some(server_prefix.clone()),
+                    command: Command::Response(
+                        Response::RPL_KNOCK,
+                        vec![
+                            op_nick.clone(),
+                            self.name.clone(),
+                            sender_prefix.to_string(),
+                            text.clone(),
+                        ],
+                    ),
+                };
+                matrix.user_manager.try_send_to_uid(uid, Arc::new(msg));
             }
         }
 
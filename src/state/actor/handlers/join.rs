@@ -1,12 +1,16 @@
 //! JOIN event handling.
 //!
 //! Processes channel join requests with ban/invite/key validation.
+//! Too many joins under +j (or a +f join limit) are refused, and may lock
+//! the channel +i for `channels.join_throttle_lock_secs`.
 
 use super::super::validation::{create_user_mask, is_banned};
 use super::{
-    ActorState, ChannelActor, ChannelError, ChannelMode, JoinParams, JoinSuccessData, MemberModes,
+    ActorState, ChannelActor, ChannelError, ChannelEvent, ChannelMode, JoinParams, JoinSuccessData,
+    MemberModes,
 };
-use slirc_proto::Message;
+use slirc_proto::mode::{ChannelMode as ProtoChannelMode, Mode};
+use std::time::{Duration, Instant};
 use subtle::ConstantTimeEq;
 use tokio::sync::oneshot;
 use tracing::debug;
@@ -59,6 +63,10 @@ impl ChannelActor {
         }
 
         // Checks
+        let user_context = match self.resolve_join_bans(&user_context).await {
+            Some(resolved) => resolved,
+            None => user_context,
+        };
         let user_mask = create_user_mask(&user_context);
 
         // Check if user has an active invite (exempts from bans and invite-only)
@@ -70,6 +78,10 @@ impl ChannelActor {
             .iter()
             .any(|i| crate::security::matches_ban_or_except(&i.mask, &user_mask, &user_context));
 
+        // A registered channel respawns with its saved +i, +l and +k; whoever
+        // ChanServ ops may reopen it, or nobody could get back in
+        let reopening = self.members.is_empty() && initial_modes.as_ref().is_some_and(|m| m.op);
+
         // 1. Bans (+b) - invites and invex exempt from ban
         // CRITICAL FIX: is_invited MUST exempt from bans (RFC 2812, irctest testInviteExemptsFromBan)
         if !is_invex
@@ -81,7 +93,7 @@ impl ChannelActor {
         }
 
         // 2. Invite Only (+i)
-        if self.modes.contains(&ChannelMode::InviteOnly) && !is_invited && !is_invex {
+        if self.modes.contains(&ChannelMode::InviteOnly) && !is_invited && !is_invex && !reopening {
             let _ = reply_tx.send(Err(ChannelError::InviteOnlyChan));
             return;
         }
@@ -89,6 +101,7 @@ impl ChannelActor {
         // 3. Limit (+l) and Redirect (+L)
         for mode in &self.modes {
             if let ChannelMode::Limit(limit, _) = mode
+                && !reopening
                 && self.members.len() >= *limit
             {
                 // Check for redirect (+L)
@@ -105,7 +118,9 @@ impl ChannelActor {
 
         // 4. Key (+k)
         for mode in &self.modes {
-            if let ChannelMode::Key(key, _) = mode {
+            if let ChannelMode::Key(key, _) = mode
+                && !reopening
+            {
                 let provided = key_arg.as_deref().unwrap_or("");
                 // Constant-time comparison to prevent timing attacks
                 if !bool::from(provided.as_bytes().ct_eq(key.as_bytes())) {
@@ -115,32 +130,10 @@ impl ChannelActor {
             }
         }
 
-        // 4b. Join Flood (+f)
-        // 4b. Join Flood (+f)
-        if let Some(limiter) = &self.flood_join_limiter
-            && limiter.check().is_err()
-        {
-            // Trigger protection: Set +i if not already set
-            if !self.modes.contains(&ChannelMode::InviteOnly) {
-                // Fix #45: Explicitly timestamp the mode change.
-                let ts = self.get_fallback_timestamp();
-                self.modes.insert(ChannelMode::InviteOnly);
-                self.mode_timestamps.insert('i', ts);
-                self.dirty = true;
-
-                // Broadcast mode change
-                let mode_msg = Message {
-                    tags: None,
-                    prefix: Some(slirc_proto::Prefix::ServerName(
-                        "flood-protection".to_string(),
-                    )),
-                    command: slirc_proto::Command::Raw(
-                        "MODE".to_string(),
-                        vec![self.name.clone(), "+i".to_string()],
-                    ),
-                };
-                self.handle_broadcast(mode_msg, None).await;
-            }
+        // 4b. Join throttle (+j, and joins counted by +f); invites get through
+        if !is_invited && !is_invex && self.join_throttled() {
+            let _ = reply_tx.send(Err(ChannelError::JoinThrottled));
+            self.lock_after_join_flood().await;
             return;
         }
 
@@ -191,6 +184,9 @@ impl ChannelActor {
             }
         };
 
+        if self.join_throttle().is_some() {
+            self.recent_joins.push_back(Instant::now());
+        }
         self.members.insert(uid.clone(), modes);
         self.user_nicks.insert(uid.clone(), nick.clone());
         self.senders.insert(uid.clone(), sender.clone());
@@ -289,9 +285,91 @@ impl ChannelActor {
             topic: self.topic.clone(),
             channel_name: self.name.clone(),
             is_secret,
+            history_replay: self.modes.iter().find_map(|mode| match mode {
+                ChannelMode::HistoryReplay(lines, minutes, _) => Some((*lines, *minutes)),
+                _ => None,
+            }),
         };
 
         self.notify_observer(None);
         let _ = reply_tx.send(Ok(data));
     }
+
+    /// The channel's +j limit, as (joins, seconds).
+    pub(crate) fn join_throttle(&self) -> Option<(u32, u32)> {
+        self.modes.iter().find_map(|mode| match mode {
+            ChannelMode::JoinThrottle(joins, secs, _) => Some((*joins, *secs)),
+            _ => None,
+        })
+    }
+
+    /// Whether another join now would exceed +j or the +f join limit.
+    fn join_throttled(&mut self) -> bool {
+        if let Some((joins, secs)) = self.join_throttle() {
+            let window = Duration::from_secs(secs.into());
+            let now = Instant::now();
+            while self
+                .recent_joins
+                .front()
+                .is_some_and(|at| now.duration_since(*at) >= window)
+            {
+                self.recent_joins.pop_front();
+            }
+            if self.recent_joins.len() >= joins as usize {
+                return true;
+            }
+        }
+        self.flood_join_limiter
+            .as_ref()
+            .is_some_and(|limiter| limiter.check().is_err())
+    }
+
+    /// Set +i for `channels.join_throttle_lock_secs` after a join flood.
+    async fn lock_after_join_flood(&mut self) {
+        let Some(matrix) = self.matrix.upgrade() else {
+            return;
+        };
+        let lock_secs = matrix.config.channels.join_throttle_lock_secs;
+        if lock_secs == 0 || !self.set_flag_mode(ChannelMode::InviteOnly, true) {
+            return;
+        }
+        self.join_locked = true;
+        debug!(channel = %self.name, lock_secs, "Join flood, channel locked +i");
+
+        self.broadcast_server_mode(
+            &matrix.server_info.name,
+            Mode::Plus(ProtoChannelMode::InviteOnly, None),
+        )
+        .await;
+        self.notify_observer(None);
+
+        // Weak, so a channel that empties meanwhile is not kept alive
+        if let Some(mailbox) = self.mailbox.clone() {
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(lock_secs)).await;
+                if let Some(tx) = mailbox.upgrade() {
+                    let _ = tx.send(ChannelEvent::JoinLockExpired).await;
+                }
+            });
+        }
+    }
+
+    /// Lift the +i set by [`Self::lock_after_join_flood`], unless an
+    /// operator has changed +i since.
+    pub(crate) async fn handle_join_lock_expired(&mut self) {
+        if !std::mem::take(&mut self.join_locked) {
+            return;
+        }
+        let Some(matrix) = self.matrix.upgrade() else {
+            return;
+        };
+        if self.set_flag_mode(ChannelMode::InviteOnly, false) {
+            self.broadcast_server_mode(
+                &matrix.server_info.name,
+                Mode::Minus(ProtoChannelMode::InviteOnly, None),
+            )
+            .await;
+            self.notify_observer(None);
+        }
+    }
 }
@@ -2,16 +2,13 @@
 //!
 //! Validates message delivery against bans, moderation, and member status.
 
-use super::super::validation::{create_user_mask, is_banned};
+use super::super::validation::{create_user_mask, is_banned, is_quieted};
 use super::{ChannelActor, ChannelMessageParams, ChannelMode, ChannelRouteResult};
-use governor::{Quota, RateLimiter as GovRateLimiter};
 use slirc_proto::message::Tag;
 use slirc_proto::{Command, Message};
 use std::borrow::Cow;
 use std::collections::HashSet;
-use std::num::NonZeroU32;
 use std::sync::Arc;
-use std::time::Duration;
 use tokio::sync::oneshot;
 
 /// Build tags for echo-message response based on sender capabilities.
@@ -72,38 +69,34 @@ fn has_caps(caps: Option<&HashSet<String>>, required: &str) -> bool {
 }
 
 impl ChannelActor {
+    /// Check whether a message may be sent to the channel.
+    ///
+    /// Returns the reason it is blocked, or `None` if it may be sent. A
+    /// sender tripping +f is kicked here.
     #[allow(clippy::collapsible_if)]
-    pub(crate) async fn handle_message(
+    pub(crate) async fn check_can_send(
         &mut self,
-        params: ChannelMessageParams,
-        reply_tx: oneshot::Sender<ChannelRouteResult>,
-    ) {
+        params: &ChannelMessageParams,
+    ) -> Option<ChannelRouteResult> {
         let ChannelMessageParams {
             sender_uid,
-            sender_session_id,
             text,
-            tags,
             is_notice,
             is_tagmsg,
             user_context,
             is_registered,
             is_tls,
-            is_bot,
-            status_prefix,
-            timestamp,
-            msgid,
-            override_nick,
-            relaymsg_sender_nick,
-            nanotime,
+            ..
         } = params;
+        let (is_notice, is_tagmsg, is_registered, is_tls) =
+            (*is_notice, *is_tagmsg, *is_registered, *is_tls);
 
-        let is_member = self.members.contains_key(&sender_uid);
+        let is_member = self.members.contains_key(sender_uid);
         let modes = &self.modes;
 
         // Check +n (no external messages)
         if modes.contains(&ChannelMode::NoExternal) && !is_member {
-            let _ = reply_tx.send(ChannelRouteResult::BlockedExternal);
-            return;
+            return Some(ChannelRouteResult::BlockedExternal);
         }
 
         // Check +r (registered-only channel)
@@ -111,48 +104,41 @@ impl ChannelActor {
             || modes.contains(&ChannelMode::RegisteredOnly))
             && !is_registered
         {
-            let _ = reply_tx.send(ChannelRouteResult::BlockedRegisteredOnly);
-            return;
+            return Some(ChannelRouteResult::BlockedRegisteredOnly);
         }
 
         // Check +z (TLS-only channel)
         if modes.contains(&ChannelMode::TlsOnly) && !is_tls {
-            let _ = reply_tx.send(ChannelRouteResult::BlockedExternal);
-            return;
+            return Some(ChannelRouteResult::BlockedExternal);
         }
 
         // Check +m (moderated)
-        if modes.contains(&ChannelMode::Moderated) && !self.member_has_voice_or_higher(&sender_uid)
-        {
-            let _ = reply_tx.send(ChannelRouteResult::BlockedModerated);
-            return;
+        if modes.contains(&ChannelMode::Moderated) && !self.member_has_voice_or_higher(sender_uid) {
+            return Some(ChannelRouteResult::BlockedModerated);
         }
 
         // Check +M (Moderated-Unregistered)
         if modes.contains(&ChannelMode::ModeratedUnreg)
             && !is_registered
-            && !self.member_has_voice_or_higher(&sender_uid)
+            && !self.member_has_voice_or_higher(sender_uid)
         {
-            let _ = reply_tx.send(ChannelRouteResult::BlockedRegisteredSpeak);
-            return;
+            return Some(ChannelRouteResult::BlockedRegisteredSpeak);
         }
 
         // Check +T (no notice)
         if is_notice
             && modes.contains(&ChannelMode::NoNotice)
-            && !self.member_has_halfop_or_higher(&sender_uid)
+            && !self.member_has_halfop_or_higher(sender_uid)
         {
-            let _ = reply_tx.send(ChannelRouteResult::BlockedNotice);
-            return;
+            return Some(ChannelRouteResult::BlockedNotice);
         }
 
         if modes.contains(&ChannelMode::NoCtcp)
-            && slirc_proto::ctcp::Ctcp::is_ctcp(&text)
-            && let Some(ctcp) = slirc_proto::ctcp::Ctcp::parse(&text)
+            && slirc_proto::ctcp::Ctcp::is_ctcp(text)
+            && let Some(ctcp) = slirc_proto::ctcp::Ctcp::parse(text)
             && !matches!(ctcp.kind, slirc_proto::ctcp::CtcpKind::Action)
         {
-            let _ = reply_tx.send(ChannelRouteResult::BlockedCTCP);
-            return;
+            return Some(ChannelRouteResult::BlockedCTCP);
         }
 
         // Check +B (Anti-caps)
@@ -160,8 +146,7 @@ impl ChannelActor {
             let caps = text.chars().filter(|c| c.is_uppercase()).count();
             let total = text.chars().filter(|c| c.is_alphabetic()).count();
             if total > 0 && (caps as f32 / total as f32) > 0.7 {
-                let _ = reply_tx.send(ChannelRouteResult::BlockedAntiCaps);
-                return;
+                return Some(ChannelRouteResult::BlockedAntiCaps);
             }
         }
 
@@ -176,129 +161,77 @@ impl ChannelActor {
                 let text_lower = text.to_lowercase();
                 for word in words {
                     if text_lower.contains(&word.to_lowercase()) {
-                        let _ = reply_tx.send(ChannelRouteResult::BlockedCensored);
-                        return;
+                        return Some(ChannelRouteResult::BlockedCensored);
                     }
                 }
             }
         }
 
         // Check bans (+b) and quiets (+q)
-        let is_op = self.member_has_halfop_or_higher(&sender_uid);
-        let user_mask = create_user_mask(&user_context);
+        let is_op = self.member_has_halfop_or_higher(sender_uid);
+        let user_mask = create_user_mask(user_context);
 
         if !is_op {
-            if is_banned(&user_mask, &user_context, &self.bans, &self.excepts) {
-                let _ = reply_tx.send(ChannelRouteResult::BlockedBanned);
-                return;
+            let resolved = self.resolve_join_bans(user_context).await;
+            let user_context = resolved.as_ref().unwrap_or(user_context);
+
+            if is_banned(&user_mask, user_context, &self.bans, &self.excepts) {
+                return Some(ChannelRouteResult::BlockedBanned);
             }
 
-            // Check +f (Flood protection)
-            let is_flooding = if let Some(param) = self.flood_config.get(&super::FloodType::Message)
+            // Check quiets (+q) and m: mute extbans; voice overrides a mute
+            if !self.member_has_voice_or_higher(sender_uid)
+                && is_quieted(
+                    &user_mask,
+                    user_context,
+                    &self.bans,
+                    &self.quiets,
+                    &self.excepts,
+                )
             {
-                let limiter = self
-                    .flood_message_limiters
-                    .entry(sender_uid.clone())
-                    .or_insert_with(|| {
-                        let mut period_per_token = if param.count == 0 {
-                            Duration::from_secs(param.period as u64) // Fallback if count is 0 (should not happen)
-                        } else {
-                            Duration::from_secs_f64(param.period as f64 / param.count as f64)
-                        };
-
-                        // Fix: Clamp to 1 nanosecond to prevent panic in Quota::with_period
-                        // if period is small and count is very large (DoS protection).
-                        if period_per_token.is_zero() {
-                            period_per_token = Duration::from_nanos(1);
-                        }
-
-                        let quota = Quota::with_period(period_per_token)
-                            .unwrap_or_else(|| Quota::with_period(Duration::from_nanos(1)).unwrap())
-                            .allow_burst(NonZeroU32::new(param.count).unwrap_or(NonZeroU32::MIN));
-                        GovRateLimiter::direct(quota)
-                    });
-
-                limiter.check().is_err()
-            } else {
-                false
-            };
-
-            if is_flooding {
-                // Flood detected! KICK the user.
-                let kick_msg = Message {
-                    tags: None,
-                    prefix: Some(slirc_proto::Prefix::new(
-                        self.server_id.to_string(),
-                        "system".to_string(),
-                        self.server_id.to_string(),
-                    )),
-                    command: Command::KICK(
-                        self.name.clone(),
-                        user_context.nickname.clone(),
-                        Some("Channel flood triggered (+f)".to_string()),
-                    ),
-                };
-
-                // Remove user state
-                self.members.remove(&sender_uid);
-                self.senders.remove(&sender_uid);
-                self.user_nicks.remove(&sender_uid);
-                self.user_caps.remove(&sender_uid);
-                self.flood_message_limiters.remove(&sender_uid);
-
-                self.handle_broadcast(kick_msg, None).await;
-                self.cleanup_if_empty();
-
-                let _ = reply_tx.send(ChannelRouteResult::NoSuchChannel);
-                return;
+                return Some(ChannelRouteResult::BlockedQuiet);
             }
 
-            // Check m: extbans (mute)
-            // Voiced users are immune to m: bans
-            if !self.member_has_voice_or_higher(&sender_uid) {
-                for ban in &self.bans {
-                    #[allow(clippy::collapsible_if)]
-                    if let Some(mask) = ban.mask.strip_prefix("m:") {
-                        if crate::security::matches_ban_or_except(mask, &user_mask, &user_context) {
-                            let is_excepted = self.excepts.iter().any(|e| {
-                                if crate::security::matches_ban_or_except(
-                                    &e.mask,
-                                    &user_mask,
-                                    &user_context,
-                                ) {
-                                    return true;
-                                }
-                                if let Some(e_mask) = e.mask.strip_prefix("m:") {
-                                    return crate::security::matches_ban_or_except(
-                                        e_mask,
-                                        &user_mask,
-                                        &user_context,
-                                    );
-                                }
-                                false
-                            });
-                            if !is_excepted {
-                                let _ = reply_tx.send(ChannelRouteResult::BlockedBanned);
-                                return;
-                            }
-                        }
-                    }
-                }
+            // Check +f (flood protection)
+            if let Some(blocked) = self.check_flood(sender_uid, user_context).await {
+                return Some(blocked);
             }
+        }
 
-            for quiet in &self.quiets {
-                if crate::security::matches_ban_or_except(&quiet.mask, &user_mask, &user_context) {
-                    let is_excepted = self.excepts.iter().any(|e| {
-                        crate::security::matches_ban_or_except(&e.mask, &user_mask, &user_context)
-                    });
-                    if !is_excepted {
-                        let _ = reply_tx.send(ChannelRouteResult::BlockedModerated);
-                        return;
-                    }
-                }
-            }
+        None
+    }
+
+    #[allow(clippy::collapsible_if)]
+    pub(crate) async fn handle_message(
+        &mut self,
+        params: ChannelMessageParams,
+        reply_tx: oneshot::Sender<ChannelRouteResult>,
+    ) {
+        if let Some(blocked) = self.check_can_send(&params).await {
+            let _ = reply_tx.send(blocked);
+            return;
         }
 
+        let ChannelMessageParams {
+            sender_uid,
+            sender_session_id,
+            text,
+            tags,
+            is_notice,
+            is_tagmsg,
+            user_context,
+            is_bot,
+            status_prefix,
+            timestamp,
+            msgid,
+            override_nick,
+            relaymsg_sender_nick,
+            nanotime,
+            ..
+        } = params;
+
+        let modes = &self.modes;
+
         // Strip colors/formatting if +c or +S mode is set
         let text = if (modes.contains(&ChannelMode::NoColors)
             || modes.contains(&ChannelMode::StripColors))
@@ -412,7 +345,8 @@ impl ChannelActor {
             }
 
             // Innovation 2: Routing tags for remote users
-            let is_target_remote = !target_uid.is_empty() && !target_uid.starts_with(self.server_id.as_str());
+            let is_target_remote =
+                !target_uid.is_empty() && !target_uid.starts_with(self.server_id.as_str());
             if is_target_remote {
                 recipient_tags.push(Tag(
                     Cow::Owned("x-target-uid".to_string()),
@@ -712,6 +646,8 @@ impl ChannelActor {
             }
         }
 
+        crate::activity::global().channel_message(&self.name);
+
         // Record message fan-out metric (Innovation 3)
         if recipients_sent > 0 {
             crate::metrics::record_fanout(recipients_sent);
@@ -6,6 +6,7 @@
 use super::*;
 
 pub mod broadcast;
+pub mod flood;
 pub mod invite_knock;
 pub mod join;
 pub mod kick;
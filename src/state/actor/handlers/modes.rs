@@ -34,6 +34,7 @@ impl ChannelActor {
             target_uids,
             force,
             nanotime,
+            list_expires_at,
         } = params;
 
         let mut applied_modes = Vec::with_capacity(modes.len());
@@ -59,7 +60,11 @@ impl ChannelActor {
                 ProtoChannelMode::ProtectedTopic => {
                     self.set_flag_mode(ChannelMode::TopicLock, adding)
                 }
-                ProtoChannelMode::InviteOnly => self.set_flag_mode(ChannelMode::InviteOnly, adding),
+                ProtoChannelMode::InviteOnly => {
+                    // An operator's +i/-i is theirs to lift, not the join throttle's
+                    self.join_locked = false;
+                    self.set_flag_mode(ChannelMode::InviteOnly, adding)
+                }
                 ProtoChannelMode::Moderated => self.set_flag_mode(ChannelMode::Moderated, adding),
                 ProtoChannelMode::ModeratedUnreg => {
                     self.set_flag_mode(ChannelMode::ModeratedUnreg, adding)
@@ -69,7 +74,7 @@ impl ChannelActor {
                 }
                 ProtoChannelMode::Auditorium => self.set_flag_mode(ChannelMode::Auditorium, adding),
                 ProtoChannelMode::Secret => self.set_flag_mode(ChannelMode::Secret, adding),
-                ProtoChannelMode::RegisteredOnly => {
+                ProtoChannelMode::RegisteredOnly | ProtoChannelMode::RegisteredJoin => {
                     self.set_flag_mode(ChannelMode::RegisteredOnly, adding)
                 }
                 ProtoChannelMode::NoColors => self.set_flag_mode(ChannelMode::NoColors, adding),
@@ -98,11 +103,12 @@ impl ChannelActor {
                 ProtoChannelMode::Censor => self.set_flag_mode(ChannelMode::Censor, adding),
                 ProtoChannelMode::Ban => {
                     if let Some(mask) = arg {
-                        Self::apply_list_mode(
-                            &mut self.bans,
+                        self.apply_list_mode(
+                            'b',
                             mask,
                             adding,
                             &sender_prefix.to_string(),
+                            list_expires_at,
                         )
                     } else {
                         false
@@ -110,11 +116,12 @@ impl ChannelActor {
                 }
                 ProtoChannelMode::Exception => {
                     if let Some(mask) = arg {
-                        Self::apply_list_mode(
-                            &mut self.excepts,
+                        self.apply_list_mode(
+                            'e',
                             mask,
                             adding,
                             &sender_prefix.to_string(),
+                            list_expires_at,
                         )
                     } else {
                         false
@@ -122,11 +129,12 @@ impl ChannelActor {
                 }
                 ProtoChannelMode::InviteException => {
                     if let Some(mask) = arg {
-                        Self::apply_list_mode(
-                            &mut self.invex,
+                        self.apply_list_mode(
+                            'I',
                             mask,
                             adding,
                             &sender_prefix.to_string(),
+                            list_expires_at,
                         )
                     } else {
                         false
@@ -134,11 +142,12 @@ impl ChannelActor {
                 }
                 ProtoChannelMode::Quiet => {
                     if let Some(mask) = arg {
-                        Self::apply_list_mode(
-                            &mut self.quiets,
+                        self.apply_list_mode(
+                            'q',
                             mask,
                             adding,
                             &sender_prefix.to_string(),
+                            list_expires_at,
                         )
                     } else {
                         false
@@ -158,7 +167,7 @@ impl ChannelActor {
                             false
                         }
                     } else {
-                        self.replace_param_mode(|mode| matches!(mode, ChannelMode::Key(_, _)), None)
+                        self.clear_param_mode('k', |mode| matches!(mode, ChannelMode::Key(_, _)))
                     }
                 }
 
@@ -174,10 +183,7 @@ impl ChannelActor {
                             )
                         })
                     } else {
-                        self.replace_param_mode(
-                            |mode| matches!(mode, ChannelMode::Limit(_, _)),
-                            None,
-                        )
+                        self.clear_param_mode('l', |mode| matches!(mode, ChannelMode::Limit(_, _)))
                     }
                 }
                 ProtoChannelMode::JoinForward => {
@@ -201,92 +207,60 @@ impl ChannelActor {
                     }
                 }
                 ProtoChannelMode::Flood => {
-                    use governor::{Quota, RateLimiter};
-                    use std::num::NonZeroU32;
-                    use std::str::FromStr;
-
                     if adding {
-                        if let Some(param_str) = arg {
-                            let mut valid_params = Vec::new();
-
-                            // Support comma-separated list: "5j:10,3m:5"
-                            for part in param_str.split(',') {
-                                if let Ok(param) = super::FloodParam::from_str(part) {
-                                    valid_params.push(param);
-                                }
-                            }
-
-                            if !valid_params.is_empty() {
-                                // If replacing, we should probably merge or overwrite?
-                                // Standard behavior: +f overwrites all flood settings with the new string.
-                                self.flood_config.clear();
-                                self.flood_message_limiters.clear();
-                                self.flood_join_limiter = None;
-
-                                for param in &valid_params {
-                                    self.flood_config.insert(param.type_, *param);
-
-                                    match param.type_ {
-                                        super::FloodType::Message => {
-                                            // Message limiters are created per-user on demand in message handler
-                                            // We just cleared the map, so they will be recreated with new policy.
-                                        }
-                                        super::FloodType::Join => {
-                                            if param.count == 0 {
-                                                continue;
-                                            }
-
-                                            // Calculate period per join allowed
-                                            // period (secs) / count (joins)
-                                            let period_per_action =
-                                                std::time::Duration::from_secs_f64(
-                                                    param.period as f64 / param.count as f64,
-                                                );
-
-                                            if let Some(quota) =
-                                                Quota::with_period(period_per_action)
-                                            {
-                                                let quota = quota.allow_burst(
-                                                    NonZeroU32::new(param.count)
-                                                        .unwrap_or(NonZeroU32::MIN),
-                                                );
-                                                self.flood_join_limiter =
-                                                    Some(RateLimiter::direct(quota));
-                                            }
-                                        }
-                                    }
-                                }
-
-                                // Reconstruct canonical string
-                                let mut parts: Vec<String> =
-                                    self.flood_config.values().map(|p| p.to_string()).collect();
-                                parts.sort(); // Deterministic order
-                                let canonical = parts.join(",");
-
-                                self.replace_param_mode(
-                                    |mode| matches!(mode, ChannelMode::Flood(_, _)),
-                                    Some(ChannelMode::Flood(
-                                        canonical,
-                                        HybridTimestamp::now(&self.server_id),
-                                    )),
-                                )
-                            } else {
-                                false
-                            }
-                        } else {
-                            false
+                        match arg.and_then(|spec| self.set_flood_config(spec)) {
+                            Some(canonical) => self.replace_param_mode(
+                                |mode| matches!(mode, ChannelMode::Flood(_, _)),
+                                Some(ChannelMode::Flood(
+                                    canonical,
+                                    HybridTimestamp::now(&self.server_id),
+                                )),
+                            ),
+                            None => false,
                         }
                     } else {
-                        // Remove -f
-                        self.flood_config.clear();
-                        self.flood_message_limiters.clear();
-                        self.flood_join_limiter = None;
+                        self.clear_flood_config();
                         self.replace_param_mode(
                             |mode| matches!(mode, ChannelMode::Flood(_, _)),
                             None,
                         )
                     }
                 }
+                ProtoChannelMode::JoinThrottle => {
+                    let throttle = arg.and_then(super::parse_join_throttle);
+                    if adding && throttle.is_none() {
+                        false
+                    } else {
+                        self.recent_joins.clear();
+                        self.replace_param_mode(
+                            |mode| matches!(mode, ChannelMode::JoinThrottle(..)),
+                            throttle.map(|(joins, secs)| {
+                                ChannelMode::JoinThrottle(
+                                    joins,
+                                    secs,
+                                    HybridTimestamp::now(&self.server_id),
+                                )
+                            }),
+                        )
+                    }
+                }
+                ProtoChannelMode::HistoryReplay => {
+                    let replay = arg.and_then(super::parse_history_replay);
+                    if adding && replay.is_none() {
+                        false
+                    } else {
+                        self.replace_param_mode(
+                            |mode| matches!(mode, ChannelMode::HistoryReplay(..)),
+                            replay.map(|(lines, minutes)| {
+                                ChannelMode::HistoryReplay(
+                                    lines,
+                                    minutes,
+                                    HybridTimestamp::now(&self.server_id),
+                                )
+                            }),
+                        )
+                    }
+                }
                 ProtoChannelMode::Redirect => {
                     if adding {
                         if let Some(target) = arg {
@@ -404,6 +378,10 @@ impl ChannelActor {
             }
         }
 
+        if list_expires_at.is_some() {
+            self.schedule_list_expiry();
+        }
+
         if !applied_modes.is_empty() {
             let msg = Arc::new(Message {
                 tags: None,
@@ -508,10 +486,7 @@ impl ChannelActor {
                 self.handle_broadcast(msg, None).await;
             }
             ClearTarget::Bans => {
-                self.bans.clear();
-                self.excepts.clear();
-                self.invex.clear();
-                self.quiets.clear();
+                self.clear_lists();
 
                 let msg = Message {
                     tags: None,
@@ -612,6 +587,7 @@ fn proto_mode_to_char(mode: &ProtoChannelMode) -> char {
         ProtoChannelMode::Moderated => 'm',
         ProtoChannelMode::Secret => 's',
         ProtoChannelMode::RegisteredOnly => 'r',
+        ProtoChannelMode::RegisteredJoin => 'R',
         ProtoChannelMode::NoColors => 'c',
         ProtoChannelMode::NoCTCP => 'C',
         ProtoChannelMode::NoNickChange => 'N',
@@ -630,6 +606,8 @@ fn proto_mode_to_char(mode: &ProtoChannelMode) -> char {
         ProtoChannelMode::Censor => 'G',
         ProtoChannelMode::Redirect => 'L',
         ProtoChannelMode::Flood => 'f',
+        ProtoChannelMode::JoinThrottle => 'j',
+        ProtoChannelMode::HistoryReplay => 'H',
         ProtoChannelMode::JoinForward => 'F',
         ProtoChannelMode::Ban => 'b',
         ProtoChannelMode::Exception => 'e',
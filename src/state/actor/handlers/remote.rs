@@ -5,11 +5,12 @@
 //! received from peer servers. Each mode bit has an independent timestamp,
 //! and conflicts are resolved by comparing HybridTimestamps.
 
-use super::{ChannelActor, ChannelMode, Uid};
-use crate::state::{ListEntry, Topic};
+use super::{ChannelActor, ChannelMode, MemberModes, Uid};
+use crate::state::Topic;
+use crate::state::dashmap_ext::DashMapExt;
 use slirc_proto::mode::ModeType;
 use slirc_proto::sync::clock::{HybridTimestamp, ServerId};
-use slirc_proto::{ChannelMode as ProtoChannelMode, Mode};
+use slirc_proto::{ChannelMode as ProtoChannelMode, Command, Message, Mode, Prefix};
 use tokio::sync::mpsc::error::TrySendError;
 
 impl ChannelActor {
@@ -68,39 +69,99 @@ impl ChannelActor {
         };
 
         if should_update {
+            let topic_msg = Message {
+                tags: None,
+                prefix: Some(self.remote_source_prefix(&setter).await),
+                command: Command::TOPIC(self.name.clone(), Some(topic.clone())),
+            };
+
             self.topic = Some(Topic {
                 text: topic,
                 set_by: setter,
                 set_at: ts as i64,
             });
             self.topic_timestamp = Some(incoming_ts);
-            self.dirty = true;
+            self.mark_dirty();
+
+            self.handle_broadcast(topic_msg, None).await;
         }
     }
 
+    /// Handle a JOIN from a peer server.
+    ///
+    /// The remote user becomes a plain member and local members see the JOIN.
+    /// An older channel TS from the peer becomes ours, as on SJOIN.
+    pub(crate) async fn handle_remote_join(
+        &mut self,
+        uid: Uid,
+        nick: String,
+        ts: u64,
+        join_msg_extended: Box<Message>,
+        join_msg_standard: Box<Message>,
+        source: ServerId,
+    ) {
+        if self.members.contains_key(&uid) {
+            return;
+        }
+
+        if ts > 0 && (self.created == 0 || (ts as i64) < self.created) {
+            self.created = ts as i64;
+        }
+
+        let Some(matrix) = self.matrix.upgrade() else {
+            return;
+        };
+        let sender = matrix
+            .user_manager
+            .get_first_sender(&uid)
+            .unwrap_or_else(|| matrix.router_tx.clone());
+
+        self.members.insert(
+            uid.clone(),
+            MemberModes {
+                join_time: Some(chrono::Utc::now().timestamp()),
+                ..Default::default()
+            },
+        );
+        self.senders.insert(uid.clone(), sender);
+        self.user_nicks.insert(uid.clone(), nick);
+        crate::metrics::set_channel_members(&self.name, self.members.len() as i64);
+
+        if !self.modes.contains(&ChannelMode::DelayedJoin) {
+            self.handle_broadcast_with_cap(
+                *join_msg_extended,
+                vec![uid],
+                Some("extended-join".to_string()),
+                Some(*join_msg_standard),
+            )
+            .await;
+        } else {
+            self.silent_members.insert(uid);
+        }
+
+        self.mark_dirty();
+        self.notify_observer(Some(source));
+    }
+
     pub(crate) async fn handle_remote_kick(
         &mut self,
         sender: String,
         target: Uid,
         reason: Option<String>,
     ) {
-        if self.members.remove(&target).is_some() {
-            self.senders.remove(&target);
-            self.user_caps.remove(&target);
-            self.user_nicks.remove(&target);
-
-            // Broadcast KICK to remaining members
-            let msg = std::sync::Arc::new(slirc_proto::Message {
+        if self.members.contains_key(&target) {
+            // Clients see the target by nick; fall back to the UID if unknown
+            let target_nick = self
+                .user_nicks
+                .get(&target)
+                .cloned()
+                .unwrap_or_else(|| target.clone());
+
+            // Broadcast KICK to all members, the kicked user included
+            let msg = std::sync::Arc::new(Message {
                 tags: None,
-                prefix: Some(slirc_proto::Prefix::new_from_str(&sender)),
-                command: slirc_proto::Command::KICK(
-                    self.name.clone(),
-                    target.clone(), // Should be nick, but we might only have UID here.
-                    // Wait, KICK target is nick.
-                    // The caller should resolve UID to nick if possible, or we use UID if it's a UID-based kick.
-                    // Standard IRC uses nick.
-                    reason,
-                ),
+                prefix: Some(self.remote_source_prefix(&sender).await),
+                command: Command::KICK(self.name.clone(), target_nick, reason),
             });
 
             let mut failed_uids = Vec::new();
@@ -122,6 +183,11 @@ impl ChannelActor {
                 self.senders.remove(&uid);
             }
 
+            self.members.remove(&target);
+            self.senders.remove(&target);
+            self.user_caps.remove(&target);
+            self.user_nicks.remove(&target);
+
             // Update metrics
             crate::metrics::set_channel_members(&self.name, self.members.len() as i64);
             self.notify_observer(None);
@@ -132,6 +198,24 @@ impl ChannelActor {
         }
     }
 
+    /// Resolve the source of a peer command to the prefix local clients see.
+    ///
+    /// Known UIDs become `nick!user@host`; anything else (a SID or server
+    /// name) is used as-is.
+    async fn remote_source_prefix(&self, source: &str) -> Prefix {
+        if let Some(matrix) = self.matrix.upgrade()
+            && let Some(user_arc) = matrix.user_manager.users.get_cloned(source)
+        {
+            let user = user_arc.read().await;
+            return Prefix::new(
+                user.nick.clone(),
+                user.user.clone(),
+                user.visible_host.clone(),
+            );
+        }
+        Prefix::new_from_str(source)
+    }
+
     /// Apply a mode change with LWW conflict resolution.
     ///
     /// Compares the incoming timestamp against the stored timestamp for this mode.
@@ -145,52 +229,19 @@ impl ChannelActor {
         adding: bool,
     ) {
         match mode {
-            // List modes (bans, exceptions, invex) - always apply, no LWW needed for individual entries
-            ProtoChannelMode::Ban => {
-                if let Some(mask) = arg {
-                    if adding {
-                        // Add if not already present
-                        if !self.bans.iter().any(|e| e.mask == mask) {
-                            self.bans.push(ListEntry {
-                                mask,
-                                set_by: setter.to_string(),
-                                set_at: incoming_ts.millis / 1000,
-                            });
-                        }
-                    } else if self.bans.iter().any(|e| e.mask == mask) {
-                        self.bans.retain(|e| e.mask != mask);
-                        self.dirty = true;
-                    }
-                }
-            }
-            ProtoChannelMode::Exception => {
-                if let Some(mask) = arg {
-                    if adding {
-                        if !self.excepts.iter().any(|e| e.mask == mask) {
-                            self.excepts.push(ListEntry {
-                                mask,
-                                set_by: setter.to_string(),
-                                set_at: incoming_ts.millis / 1000,
-                            });
-                        }
-                    } else {
-                        self.excepts.retain(|e| e.mask != mask);
-                    }
-                }
-            }
-            ProtoChannelMode::InviteException => {
+            // List modes: LWW per entry, keyed by mask
+            ProtoChannelMode::Ban
+            | ProtoChannelMode::Exception
+            | ProtoChannelMode::InviteException
+            | ProtoChannelMode::Quiet => {
+                let list = match mode {
+                    ProtoChannelMode::Ban => 'b',
+                    ProtoChannelMode::Exception => 'e',
+                    ProtoChannelMode::InviteException => 'I',
+                    _ => 'q',
+                };
                 if let Some(mask) = arg {
-                    if adding {
-                        if !self.invex.iter().any(|e| e.mask == mask) {
-                            self.invex.push(ListEntry {
-                                mask,
-                                set_by: setter.to_string(),
-                                set_at: incoming_ts.millis / 1000,
-                            });
-                        }
-                    } else {
-                        self.invex.retain(|e| e.mask != mask);
-                    }
+                    self.apply_remote_list_mode(list, &mask, adding, setter, incoming_ts);
                 }
             }
 
@@ -214,7 +265,7 @@ impl ChannelActor {
                                 .retain(|m| !matches!(m, ChannelMode::Limit(_, _)));
                             self.modes.insert(ChannelMode::Limit(l, incoming_ts));
                             self.mode_timestamps.insert('l', incoming_ts);
-                            self.dirty = true;
+                            self.mark_dirty();
                         }
                     }
                 } else {
@@ -232,10 +283,54 @@ impl ChannelActor {
                         self.modes
                             .retain(|m| !matches!(m, ChannelMode::Limit(_, _)));
                         self.mode_timestamps.insert('l', incoming_ts);
-                        self.dirty = true;
+                        self.mark_dirty();
                     }
                 }
             }
+            ProtoChannelMode::JoinThrottle => {
+                let throttle = arg.as_deref().and_then(super::parse_join_throttle);
+                let current_ts = self
+                    .modes
+                    .iter()
+                    .find_map(|m| match m {
+                        ChannelMode::JoinThrottle(_, _, ts) => Some(*ts),
+                        _ => None,
+                    })
+                    .or_else(|| self.mode_timestamps.get(&'j').copied());
+
+                if (!adding || throttle.is_some()) && current_ts.is_none_or(|ts| incoming_ts > ts) {
+                    self.modes
+                        .retain(|m| !matches!(m, ChannelMode::JoinThrottle(..)));
+                    if let Some((joins, secs)) = throttle.filter(|_| adding) {
+                        self.modes
+                            .insert(ChannelMode::JoinThrottle(joins, secs, incoming_ts));
+                    }
+                    self.mode_timestamps.insert('j', incoming_ts);
+                    self.mark_dirty();
+                }
+            }
+            ProtoChannelMode::HistoryReplay => {
+                let replay = arg.as_deref().and_then(super::parse_history_replay);
+                let current_ts = self
+                    .modes
+                    .iter()
+                    .find_map(|m| match m {
+                        ChannelMode::HistoryReplay(_, _, ts) => Some(*ts),
+                        _ => None,
+                    })
+                    .or_else(|| self.mode_timestamps.get(&'H').copied());
+
+                if (!adding || replay.is_some()) && current_ts.is_none_or(|ts| incoming_ts > ts) {
+                    self.modes
+                        .retain(|m| !matches!(m, ChannelMode::HistoryReplay(..)));
+                    if let Some((lines, minutes)) = replay.filter(|_| adding) {
+                        self.modes
+                            .insert(ChannelMode::HistoryReplay(lines, minutes, incoming_ts));
+                    }
+                    self.mode_timestamps.insert('H', incoming_ts);
+                    self.mark_dirty();
+                }
+            }
             ProtoChannelMode::Key => {
                 if adding {
                     if let Some(k) = arg {
@@ -252,7 +347,7 @@ impl ChannelActor {
                             self.modes.retain(|m| !matches!(m, ChannelMode::Key(_, _)));
                             self.modes.insert(ChannelMode::Key(k, incoming_ts));
                             self.mode_timestamps.insert('k', incoming_ts);
-                            self.dirty = true;
+                            self.mark_dirty();
                         }
                     }
                 } else {
@@ -268,7 +363,7 @@ impl ChannelActor {
                     if current_ts.is_none_or(|ts| incoming_ts > ts) {
                         self.modes.retain(|m| !matches!(m, ChannelMode::Key(_, _)));
                         self.mode_timestamps.insert('k', incoming_ts);
-                        self.dirty = true;
+                        self.mark_dirty();
                     }
                 }
             }
@@ -292,7 +387,7 @@ impl ChannelActor {
             ProtoChannelMode::NoColors => {
                 self.apply_boolean_mode_lww('c', ChannelMode::NoColors, adding, incoming_ts);
             }
-            ProtoChannelMode::RegisteredOnly => {
+            ProtoChannelMode::RegisteredOnly | ProtoChannelMode::RegisteredJoin => {
                 self.apply_boolean_mode_lww('R', ChannelMode::RegisteredOnly, adding, incoming_ts);
             }
             ProtoChannelMode::NoCTCP => {
@@ -385,7 +480,7 @@ impl ChannelActor {
                 self.modes.remove(&mode);
             }
             self.mode_timestamps.insert(mode_char, incoming_ts);
-            self.dirty = true;
+            self.mark_dirty();
         }
     }
 }
@@ -419,6 +514,8 @@ fn parse_modes(modes_str: String, params: &[String]) -> Vec<Mode<ProtoChannelMod
                         | ProtoChannelMode::Exception
                         | ProtoChannelMode::InviteException
                         | ProtoChannelMode::Limit
+                        | ProtoChannelMode::JoinThrottle
+                        | ProtoChannelMode::HistoryReplay
                         | ProtoChannelMode::Key
                         | ProtoChannelMode::Oper
                         | ProtoChannelMode::Voice
@@ -427,8 +524,14 @@ fn parse_modes(modes_str: String, params: &[String]) -> Vec<Mode<ProtoChannelMod
                         | ProtoChannelMode::Halfop
                 );
 
-                // Special case: Limit only takes arg when adding
-                let actual_takes_arg = if mode_type == ProtoChannelMode::Limit && !adding {
+                // Special case: Limit, JoinThrottle and HistoryReplay only take an arg when adding
+                let actual_takes_arg = if matches!(
+                    mode_type,
+                    ProtoChannelMode::Limit
+                        | ProtoChannelMode::JoinThrottle
+                        | ProtoChannelMode::HistoryReplay
+                ) && !adding
+                {
                     false
                 } else {
                     takes_arg
@@ -45,7 +45,7 @@ impl ChannelActor {
             set_at: chrono::Utc::now().timestamp(),
         });
 
-        self.dirty = true;
+        self.mark_dirty();
 
         // Record timestamp for CRDT convergence
         self.topic_timestamp = Some(HybridTimestamp::now(&self.server_id));
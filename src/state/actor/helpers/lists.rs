@@ -1,11 +1,14 @@
-//! Channel list management (bans, excepts, invex).
+//! Channel list management (bans, excepts, invex, quiets).
 //!
 //! Helpers for adding, removing, and querying channel list entries.
 
-use super::super::ChannelActor;
+use super::super::{ChannelActor, ChannelEvent};
 use crate::state::ListEntry;
 use chrono::Utc;
 use slirc_proto::casemap::{irc_eq, irc_to_lower};
+use slirc_proto::mode::{ChannelMode as ProtoChannelMode, Mode};
+use slirc_proto::sync::clock::HybridTimestamp;
+use std::time::Duration;
 
 /// Maximum length of a ban/exception mask (nick!user@host pattern).
 /// Most IRC servers use 250-500 bytes. We use 350 to allow generous masks.
@@ -16,11 +19,26 @@ const MAX_MASK_LENGTH: usize = 350;
 const MAX_LIST_ENTRIES: usize = 100;
 
 impl ChannelActor {
+    /// Get the list for a list mode character (b, e, I, q).
+    pub(crate) fn list_mut(&mut self, mode: char) -> Option<&mut Vec<ListEntry>> {
+        match mode {
+            'b' => Some(&mut self.bans),
+            'e' => Some(&mut self.excepts),
+            'I' => Some(&mut self.invex),
+            'q' => Some(&mut self.quiets),
+            _ => None,
+        }
+    }
+
+    /// Add or remove `mask` on a list; an added entry lifts itself at
+    /// `expires_at` (unix seconds) if one is given.
     pub(crate) fn apply_list_mode(
-        list: &mut Vec<ListEntry>,
+        &mut self,
+        mode: char,
         mask: &str,
         adding: bool,
         set_by: &str,
+        expires_at: Option<i64>,
     ) -> bool {
         // Validate mask length
         if mask.len() > MAX_MASK_LENGTH {
@@ -32,7 +50,11 @@ impl ChannelActor {
         // Example: +b BAR!*@* should be removable with -b bar!*@*
         let normalized_mask = irc_to_lower(mask);
 
-        if adding {
+        let Some(list) = self.list_mut(mode) else {
+            return false;
+        };
+
+        let changed = if adding {
             // Check list size limit
             if list.len() >= MAX_LIST_ENTRIES {
                 return false;
@@ -46,15 +68,157 @@ impl ChannelActor {
             }
 
             list.push(ListEntry {
-                mask: normalized_mask, // Store normalized form for consistent lookups
+                mask: normalized_mask.clone(), // Store normalized form for consistent lookups
                 set_by: set_by.to_string(),
                 set_at: Utc::now().timestamp(),
+                expires_at,
             });
             true
         } else {
             let original_len = list.len();
             list.retain(|entry| !irc_eq(&entry.mask, &normalized_mask));
             original_len != list.len()
+        };
+
+        // Removals are stamped too, so they win over older adds on merge
+        if changed {
+            self.list_timestamps.insert(
+                (mode, normalized_mask),
+                HybridTimestamp::now(&self.server_id),
+            );
+            self.lists_dirty = true;
+        }
+        changed
+    }
+
+    /// Apply a list change from a peer if it is newer than ours.
+    ///
+    /// An add wins a tie with a removal, as in the CRDT.
+    pub(crate) fn apply_remote_list_mode(
+        &mut self,
+        mode: char,
+        mask: &str,
+        adding: bool,
+        set_by: &str,
+        incoming_ts: HybridTimestamp,
+    ) {
+        let normalized_mask = irc_to_lower(mask);
+        let key = (mode, normalized_mask.clone());
+        let wins = match self.list_timestamps.get(&key) {
+            Some(&ts) if adding => incoming_ts >= ts,
+            Some(&ts) => incoming_ts > ts,
+            None => true,
+        };
+        let Some(list) = self.list_mut(mode) else {
+            return;
+        };
+        if !wins {
+            return;
+        }
+
+        if adding {
+            if !list.iter().any(|e| irc_eq(&e.mask, &normalized_mask)) {
+                list.push(ListEntry {
+                    mask: normalized_mask,
+                    set_by: set_by.to_string(),
+                    set_at: incoming_ts.millis / 1000,
+                    expires_at: None,
+                });
+            }
+        } else {
+            list.retain(|e| !irc_eq(&e.mask, &normalized_mask));
         }
+        self.list_timestamps.insert(key, incoming_ts);
+        self.mark_dirty();
+        self.lists_dirty = true;
+    }
+
+    /// Remove every entry from the ban, exception, invex and quiet lists.
+    pub(crate) fn clear_lists(&mut self) {
+        let now = HybridTimestamp::now(&self.server_id);
+        for mode in ['b', 'e', 'I', 'q'] {
+            let Some(list) = self.list_mut(mode) else {
+                continue;
+            };
+            let removed: Vec<_> = list.drain(..).map(|entry| entry.mask).collect();
+            for mask in removed {
+                self.list_timestamps.insert((mode, mask), now);
+            }
+        }
+        self.lists_dirty = true;
+    }
+
+    /// Arm a timer for the earliest timed list entry, unless one already
+    /// fires by then.
+    pub(crate) fn schedule_list_expiry(&mut self) {
+        let Some(next) = [&self.bans, &self.excepts, &self.invex, &self.quiets]
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.expires_at)
+            .min()
+        else {
+            return;
+        };
+        if self.list_expiry_at.is_some_and(|pending| pending <= next) {
+            return;
+        }
+        let Some(mailbox) = self.mailbox.clone() else {
+            return;
+        };
+        self.list_expiry_at = Some(next);
+
+        let delay = u64::try_from(next - Utc::now().timestamp()).unwrap_or(0);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(delay)).await;
+            if let Some(tx) = mailbox.upgrade() {
+                let _ = tx.send(ChannelEvent::ExpireListEntries).await;
+            }
+        });
+    }
+
+    /// Remove timed list entries that have expired, announcing each removal
+    /// as the server, and re-arm the timer for the next one.
+    pub(crate) async fn handle_list_expiry(&mut self) {
+        let now = Utc::now().timestamp();
+        if self.list_expiry_at.is_some_and(|pending| pending <= now) {
+            self.list_expiry_at = None;
+        }
+
+        let mut expired = Vec::new();
+        for mode in ['b', 'e', 'I', 'q'] {
+            let Some(list) = self.list_mut(mode) else {
+                continue;
+            };
+            for entry in list.iter() {
+                if entry.expires_at.is_some_and(|at| at <= now) {
+                    expired.push((mode, entry.mask.clone()));
+                }
+            }
+        }
+
+        if !expired.is_empty() {
+            let server_name = self
+                .matrix
+                .upgrade()
+                .map(|matrix| matrix.server_info.name.clone());
+            for (mode, mask) in expired {
+                if !self.apply_list_mode(mode, &mask, false, "", None) {
+                    continue;
+                }
+                let proto_mode = match mode {
+                    'b' => ProtoChannelMode::Ban,
+                    'e' => ProtoChannelMode::Exception,
+                    'I' => ProtoChannelMode::InviteException,
+                    _ => ProtoChannelMode::Quiet,
+                };
+                if let Some(server_name) = &server_name {
+                    self.broadcast_server_mode(server_name, Mode::Minus(proto_mode, Some(mask)))
+                        .await;
+                }
+            }
+            self.notify_observer(None);
+        }
+
+        self.schedule_list_expiry();
     }
 }
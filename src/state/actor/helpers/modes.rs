@@ -42,6 +42,8 @@ impl ChannelActor {
             | ChannelMode::Limit(_, _)
             | ChannelMode::JoinForward(_, _)
             | ChannelMode::Flood(_, _)
+            | ChannelMode::JoinThrottle(..)
+            | ChannelMode::HistoryReplay(..)
             | ChannelMode::Redirect(_, _) => None, // Parametric modes use separate timestamp fields
         }
     }
@@ -56,7 +58,7 @@ impl ChannelActor {
 
         // Record timestamp for boolean mode changes
         if changed {
-            self.dirty = true;
+            self.mark_dirty();
             if let Some(mode_char) = Self::mode_to_char(&flag) {
                 self.mode_timestamps
                     .insert(mode_char, HybridTimestamp::now(&self.server_id));
@@ -88,11 +90,24 @@ impl ChannelActor {
         }
 
         if changed {
-            self.dirty = true;
+            self.mark_dirty();
         }
 
         changed
     }
+
+    /// Unset a parameter mode, stamping the removal so it replicates.
+    pub(crate) fn clear_param_mode<F>(&mut self, mode_char: char, predicate: F) -> bool
+    where
+        F: Fn(&ChannelMode) -> bool,
+    {
+        let changed = self.replace_param_mode(predicate, None);
+        if changed {
+            self.mode_timestamps
+                .insert(mode_char, HybridTimestamp::now(&self.server_id));
+        }
+        changed
+    }
 }
 
 /// Convert channel modes to a string representation (e.g. "+ntk key").
@@ -191,35 +206,33 @@ pub fn modes_to_string(modes: &HashSet<ChannelMode>) -> String {
     // Param modes
     for mode in modes {
         match mode {
-            ChannelMode::Key(k, _) => {
-                if !flags.contains('k') {
-                    flags.push('k');
-                    params.push(k.clone());
-                }
+            ChannelMode::Key(k, _) if !flags.contains('k') => {
+                flags.push('k');
+                params.push(k.clone());
+            }
+            ChannelMode::Limit(l, _) if !flags.contains('l') => {
+                flags.push('l');
+                params.push(l.to_string());
             }
-            ChannelMode::Limit(l, _) => {
-                if !flags.contains('l') {
-                    flags.push('l');
-                    params.push(l.to_string());
-                }
+            ChannelMode::JoinForward(target, _) if !flags.contains('F') => {
+                flags.push('F');
+                params.push(target.clone());
             }
-            ChannelMode::JoinForward(target, _) => {
-                if !flags.contains('F') {
-                    flags.push('F');
-                    params.push(target.clone());
-                }
+            ChannelMode::Flood(target, _) if !flags.contains('f') => {
+                flags.push('f');
+                params.push(target.clone());
             }
-            ChannelMode::Flood(target, _) => {
-                if !flags.contains('f') {
-                    flags.push('f');
-                    params.push(target.clone());
-                }
+            ChannelMode::JoinThrottle(joins, secs, _) if !flags.contains('j') => {
+                flags.push('j');
+                params.push(format!("{joins}:{secs}"));
             }
-            ChannelMode::Redirect(target, _) => {
-                if !flags.contains('L') {
-                    flags.push('L');
-                    params.push(target.clone());
-                }
+            ChannelMode::HistoryReplay(lines, minutes, _) if !flags.contains('H') => {
+                flags.push('H');
+                params.push(format!("{lines}:{minutes}"));
+            }
+            ChannelMode::Redirect(target, _) if !flags.contains('L') => {
+                flags.push('L');
+                params.push(target.clone());
             }
             _ => {}
         }
@@ -234,38 +247,58 @@ pub fn modes_to_string(modes: &HashSet<ChannelMode>) -> String {
 
 /// Convert a mode string and parameters back to a set of `ChannelMode` variants.
 /// Used for restoring channel state from persistence.
+///
+/// Accepts the output of [`modes_to_string`], parameters included; `key` and
+/// `limit` override any parameter found in the string.
 pub fn modes_from_string(
     modes_str: &str,
     key: Option<String>,
     limit: Option<i32>,
 ) -> HashSet<ChannelMode> {
-    let mut modes = HashSet::new();
-    let mut chars = modes_str.chars();
-
-    // First char should be + or -
-    if let Some(first) = chars.next()
-        && first != '+'
-    {
-        // If it's not a prefix, it might be the first flag
-        if let Some(m) = char_to_mode(first) {
-            // Assume add if no prefix
-            modes.insert(m);
-        }
-    }
-
-    for c in chars {
-        if let Some(m) = char_to_mode(c) {
-            modes.insert(m);
-        }
-    }
-
     use slirc_proto::sync::clock::{HybridTimestamp, ServerId};
     let ts = HybridTimestamp::new(0, 0, &ServerId::new("000"));
 
+    let mut modes = HashSet::new();
+    let mut words = modes_str.split_whitespace();
+    let flags = words.next().unwrap_or_default();
+    let mut params = words;
+
+    // A leading '+' is optional
+    for c in flags.trim_start_matches('+').chars() {
+        let mode = match c {
+            'k' => params.next().map(|k| ChannelMode::Key(k.to_string(), ts)),
+            'l' => params
+                .next()
+                .and_then(|l| l.parse().ok())
+                .map(|l| ChannelMode::Limit(l, ts)),
+            'F' => params
+                .next()
+                .map(|target| ChannelMode::JoinForward(target.to_string(), ts)),
+            'f' => params
+                .next()
+                .map(|spec| ChannelMode::Flood(spec.to_string(), ts)),
+            'j' => params
+                .next()
+                .and_then(crate::state::actor::parse_join_throttle)
+                .map(|(joins, secs)| ChannelMode::JoinThrottle(joins, secs, ts)),
+            'H' => params
+                .next()
+                .and_then(crate::state::actor::parse_history_replay)
+                .map(|(lines, minutes)| ChannelMode::HistoryReplay(lines, minutes, ts)),
+            'L' => params
+                .next()
+                .map(|target| ChannelMode::Redirect(target.to_string(), ts)),
+            c => char_to_mode(c),
+        };
+        modes.extend(mode);
+    }
+
     if let Some(k) = key {
+        modes.retain(|m| !matches!(m, ChannelMode::Key(..)));
         modes.insert(ChannelMode::Key(k, ts));
     }
     if let Some(l) = limit {
+        modes.retain(|m| !matches!(m, ChannelMode::Limit(..)));
         modes.insert(ChannelMode::Limit(l as usize, ts));
     }
 
@@ -511,4 +544,42 @@ mod tests {
         assert_eq!(ChannelActor::mode_to_char(&key_mode), None);
         assert_eq!(ChannelActor::mode_to_char(&limit_mode), None);
     }
+
+    #[test]
+    fn test_modes_from_string_round_trips_params() {
+        let mut modes = HashSet::new();
+        modes.insert(ChannelMode::NoExternal);
+        modes.insert(ChannelMode::Secret);
+        modes.insert(ChannelMode::Key("secret".to_string(), test_ts()));
+        modes.insert(ChannelMode::Limit(10, test_ts()));
+        modes.insert(ChannelMode::JoinThrottle(3, 10, test_ts()));
+
+        let restored = modes_from_string(&modes_to_string(&modes), None, None);
+        assert_eq!(restored.len(), 5);
+        assert!(restored.contains(&ChannelMode::NoExternal));
+        assert!(restored.contains(&ChannelMode::Secret));
+        assert!(
+            restored
+                .iter()
+                .any(|m| matches!(m, ChannelMode::Key(k, _) if k == "secret"))
+        );
+        assert!(
+            restored
+                .iter()
+                .any(|m| matches!(m, ChannelMode::Limit(10, _)))
+        );
+        assert!(
+            restored
+                .iter()
+                .any(|m| matches!(m, ChannelMode::JoinThrottle(3, 10, _)))
+        );
+
+        let restored = modes_from_string("+nk secret", Some("other".to_string()), Some(5));
+        assert_eq!(restored.len(), 3);
+        assert!(
+            restored
+                .iter()
+                .any(|m| matches!(m, ChannelMode::Key(k, _) if k == "other"))
+        );
+    }
 }
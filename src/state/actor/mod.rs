@@ -9,10 +9,11 @@
 //! - **Message Passing**: All interactions happen via `ChannelEvent` messages sent to the actor.
 //! - **Concurrency**: Each channel runs on its own task, allowing the runtime to distribute load.
 
+use crate::db::ChannelListEntry;
 use crate::state::observer::StateObserver;
-use crate::state::{ListEntry, Matrix, MemberModes, Topic};
+use crate::state::{ListEntry, Matrix, MemberModes, SendQ, Topic};
 use chrono::Utc;
-use slirc_proto::Message;
+use slirc_proto::irc_to_lower;
 use slirc_proto::sync::clock::HybridTimestamp;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{Arc, Weak};
@@ -41,7 +42,7 @@ pub struct ChannelActor {
     pub name: String,
     pub members: im::HashMap<Uid, MemberModes>,
     pub user_nicks: HashMap<Uid, String>,
-    pub senders: HashMap<Uid, mpsc::Sender<Arc<Message>>>,
+    pub senders: HashMap<Uid, SendQ>,
     pub user_caps: HashMap<Uid, HashSet<String>>,
     pub modes: HashSet<ChannelMode>,
     /// Timestamps for when each boolean mode was last changed.
@@ -61,6 +62,9 @@ pub struct ChannelActor {
     pub excepts: Vec<ListEntry>,
     pub invex: Vec<ListEntry>,
     pub quiets: Vec<ListEntry>,
+    /// Timestamps for the last add or removal of each list entry.
+    /// Key is the list mode character and the normalized mask.
+    pub list_timestamps: HashMap<(char, String), HybridTimestamp>,
     /// Users who joined via +D (Delayed Join) and haven't spoken yet.
     pub silent_members: HashSet<Uid>,
 
@@ -73,11 +77,28 @@ pub struct ChannelActor {
     pub flood_message_limiters: HashMap<Uid, governor::DefaultDirectRateLimiter>,
     /// Channel-wide join limiter for 'j' mode
     pub flood_join_limiter: Option<governor::DefaultDirectRateLimiter>,
+    /// When a KNOCK was last delivered, for the channel-wide knock delay.
+    pub last_knock: Option<Instant>,
+    /// Recent local joins, for +j.
+    pub recent_joins: VecDeque<Instant>,
+    /// Whether +i was set by the join throttle and will be lifted by it.
+    pub join_locked: bool,
+    /// When the pending timed-list expiry check fires (unix seconds).
+    pub list_expiry_at: Option<i64>,
     matrix: Weak<Matrix>,
+    /// This actor's own mailbox, so it only unregisters itself and never
+    /// a newer actor created under the same name.
+    mailbox: Option<mpsc::WeakSender<ChannelEvent>>,
     state: ActorState,
     observer: Option<Arc<dyn StateObserver>>,
     /// Flag indicating that the channel state has changed and needs saving.
     pub dirty: bool,
+    /// Topic or modes changed since they were written to the channel's
+    /// ChanServ registration.
+    pub registration_dirty: bool,
+    /// Ban, exception, invex or quiet lists changed since they were written
+    /// to the channel's registration.
+    pub lists_dirty: bool,
 }
 
 const MAX_INVITES_PER_CHANNEL: usize = 100;
@@ -120,15 +141,26 @@ impl ChannelActor {
             .map(|m| m.server_id.clone())
             .unwrap_or_else(|| slirc_proto::sync::ServerId::new("000".to_string()));
 
-        let actor = Self {
+        // Stamp the initial state once, so `to_crdt` reports the same
+        // timestamps on every call instead of a fresh fallback.
+        let spawned_at = HybridTimestamp::now(&server_id);
+        let mode_timestamps = modes
+            .iter()
+            .filter_map(Self::mode_to_char)
+            .map(|c| (c, spawned_at))
+            .collect();
+        let topic_timestamp = initial_topic.as_ref().map(|_| spawned_at);
+
+        let mut actor = Self {
             name,
             members: im::HashMap::new(),
             user_nicks: HashMap::new(),
             senders: HashMap::new(),
             user_caps: HashMap::new(),
             modes,
-            mode_timestamps: HashMap::new(),
-            topic_timestamp: None,
+            mode_timestamps,
+            list_timestamps: HashMap::new(),
+            topic_timestamp,
             server_id,
             metadata: initial_metadata.unwrap_or_default(),
             topic: initial_topic,
@@ -143,13 +175,32 @@ impl ChannelActor {
             flood_config: HashMap::new(),
             flood_message_limiters: HashMap::new(),
             flood_join_limiter: None,
+            last_knock: None,
+            recent_joins: VecDeque::new(),
+            join_locked: false,
+            list_expiry_at: None,
             matrix,
+            mailbox: Some(tx.downgrade()),
             state: ActorState::Active,
             observer,
             dirty: false,
+            registration_dirty: false,
+            lists_dirty: false,
         };
 
+        // Restored +f needs its counters rebuilt
+        let flood_spec = actor.modes.iter().find_map(|mode| match mode {
+            ChannelMode::Flood(spec, _) => Some(spec.clone()),
+            _ => None,
+        });
+        if let Some(spec) = flood_spec {
+            actor.set_flood_config(&spec);
+        }
+
         tokio::spawn(async move {
+            // Events wait in the mailbox until the saved lists are back
+            actor.load_registered_lists().await;
+            actor.schedule_list_expiry();
             actor.run(rx).await;
         });
         tx
@@ -166,6 +217,7 @@ impl ChannelActor {
             user_caps: HashMap::new(),
             modes: HashSet::new(),
             mode_timestamps: HashMap::new(),
+            list_timestamps: HashMap::new(),
             topic_timestamp: None,
             server_id,
             metadata: HashMap::new(),
@@ -181,17 +233,28 @@ impl ChannelActor {
             flood_config: HashMap::new(),
             flood_message_limiters: HashMap::new(),
             flood_join_limiter: None,
+            last_knock: None,
+            recent_joins: VecDeque::new(),
+            join_locked: false,
+            list_expiry_at: None,
             matrix: Weak::new(),
+            mailbox: None,
             state: ActorState::Active,
             observer: None,
             dirty: false,
+            registration_dirty: false,
+            lists_dirty: false,
         }
     }
 
     /// The main actor loop.
     pub async fn run(mut self, mut rx: mpsc::Receiver<ChannelEvent>) {
         while let Some(event) = rx.recv().await {
+            crate::activity::global().channel_mailbox(&self.name, rx.len());
             self.handle_event(event).await;
+            if self.registration_dirty || self.lists_dirty {
+                self.save_registration().await;
+            }
         }
     }
 
@@ -231,6 +294,10 @@ impl ChannelActor {
             ChannelEvent::Message { params, reply_tx } => {
                 self.handle_message(*params, reply_tx).await;
             }
+            ChannelEvent::CheckMessage { params, reply_tx } => {
+                let result = self.check_can_send(&params).await;
+                let _ = reply_tx.send(result.unwrap_or(ChannelRouteResult::Sent));
+            }
             ChannelEvent::Broadcast { message, exclude } => {
                 self.handle_broadcast(message, exclude).await;
             }
@@ -262,10 +329,18 @@ impl ChannelActor {
                 } else {
                     false
                 };
+                let member_count = match self.matrix.upgrade() {
+                    Some(matrix) => self
+                        .members
+                        .keys()
+                        .filter(|uid| !matrix.user_manager.is_service(uid))
+                        .count(),
+                    None => self.members.len(),
+                };
                 let info = ChannelInfo {
                     name: self.name.clone(),
                     topic: self.topic.clone(),
-                    member_count: self.members.len(),
+                    member_count,
                     created: self.created,
                     modes: self.modes.clone(),
                     is_member,
@@ -276,6 +351,9 @@ impl ChannelActor {
             ChannelEvent::MergeCrdt { crdt, source } => {
                 self.handle_merge_crdt(*crdt, source).await;
             }
+            ChannelEvent::GetCrdt { reply_tx } => {
+                let _ = reply_tx.send(self.to_crdt());
+            }
             ChannelEvent::GetList { mode, reply_tx } => {
                 let list = match mode {
                     'b' => self.bans.clone(),
@@ -308,9 +386,12 @@ impl ChannelActor {
             ChannelEvent::Knock {
                 sender_uid,
                 sender_prefix,
+                reason,
+                channel_delay,
                 reply_tx,
             } => {
-                self.handle_knock(sender_uid, sender_prefix, reply_tx).await;
+                self.handle_knock(sender_uid, sender_prefix, reason, channel_delay, reply_tx)
+                    .await;
             }
             ChannelEvent::NickChange { uid, new_nick } => {
                 self.handle_nick_change(uid, new_nick).await;
@@ -336,6 +417,24 @@ impl ChannelActor {
             ChannelEvent::RemoteTopic { ts, setter, topic } => {
                 self.handle_remote_topic(ts, setter, topic).await;
             }
+            ChannelEvent::RemoteJoin {
+                uid,
+                nick,
+                ts,
+                join_msg_extended,
+                join_msg_standard,
+                source,
+            } => {
+                self.handle_remote_join(
+                    uid,
+                    nick,
+                    ts,
+                    join_msg_extended,
+                    join_msg_standard,
+                    source,
+                )
+                .await;
+            }
             ChannelEvent::RemoteKick {
                 sender,
                 target,
@@ -345,11 +444,14 @@ impl ChannelActor {
             }
             ChannelEvent::NetsplitQuit { uid, reply_tx } => {
                 // Silently remove user from channel (QUIT already broadcast by split.rs)
-                self.members.remove(&uid);
-                self.senders.remove(&uid);
-                self.user_nicks.remove(&uid);
-                self.user_caps.remove(&uid);
-                let _ = reply_tx.send(());
+                if self.members.remove(&uid).is_some() {
+                    self.senders.remove(&uid);
+                    self.user_nicks.remove(&uid);
+                    self.user_caps.remove(&uid);
+                    crate::metrics::set_channel_members(&self.name, self.members.len() as i64);
+                    self.cleanup_if_empty();
+                }
+                let _ = reply_tx.send(self.members.len());
             }
             ChannelEvent::Metadata { command, reply_tx } => {
                 let _ = reply_tx.send(self.handle_metadata(command));
@@ -363,6 +465,67 @@ impl ChannelActor {
             ChannelEvent::CheckAndSave => {
                 self.handle_check_and_save().await;
             }
+            ChannelEvent::GetSnapshot { reply_tx } => {
+                let _ = reply_tx.send(self.snapshot());
+            }
+            ChannelEvent::RestoreLists { snapshot } => {
+                self.bans = snapshot.bans;
+                self.excepts = snapshot.excepts;
+                self.invex = snapshot.invex;
+                self.quiets = snapshot.quiets;
+                self.lists_dirty = true;
+                self.schedule_list_expiry();
+            }
+            ChannelEvent::PruneTombstones { stable_millis } => {
+                let pruned = self.prune_tombstones(stable_millis);
+                crate::metrics::inc_crdt_tombstones_pruned("channel", pruned);
+            }
+            ChannelEvent::ReapIfEmpty { reply_tx } => {
+                self.cleanup_if_empty();
+                let _ = reply_tx.send(self.state == ActorState::Draining);
+            }
+            ChannelEvent::JoinLockExpired => {
+                self.handle_join_lock_expired().await;
+            }
+            ChannelEvent::ExpireListEntries => {
+                self.handle_list_expiry().await;
+            }
+        }
+    }
+
+    fn key_and_limit(&self) -> (Option<String>, Option<i32>) {
+        let key = self.modes.iter().find_map(|m| {
+            if let ChannelMode::Key(k, _) = m {
+                Some(k.clone())
+            } else {
+                None
+            }
+        });
+        let user_limit = self.modes.iter().find_map(|m| {
+            if let ChannelMode::Limit(l, _) = m {
+                Some(*l as i32)
+            } else {
+                None
+            }
+        });
+        (key, user_limit)
+    }
+
+    fn snapshot(&self) -> crate::state::snapshot::ChannelSnapshot {
+        let (key, limit) = self.key_and_limit();
+        let modes = crate::state::actor::helpers::modes_to_string(&self.modes);
+        crate::state::snapshot::ChannelSnapshot {
+            name: self.name.clone(),
+            created: self.created,
+            modes: modes.split_whitespace().next().unwrap_or("+").to_string(),
+            key,
+            limit,
+            topic: self.topic.clone(),
+            metadata: self.metadata.clone(),
+            bans: self.bans.clone(),
+            excepts: self.excepts.clone(),
+            invex: self.invex.clone(),
+            quiets: self.quiets.clone(),
         }
     }
 
@@ -375,20 +538,7 @@ impl ChannelActor {
             let pool = matrix.db.pool();
             let repo = crate::state::persistence::ChannelStateRepository::new(pool);
 
-            let key = self.modes.iter().find_map(|m| {
-                if let ChannelMode::Key(k, _) = m {
-                    Some(k.clone())
-                } else {
-                    None
-                }
-            });
-            let user_limit = self.modes.iter().find_map(|m| {
-                if let ChannelMode::Limit(l, _) = m {
-                    Some(*l as i32)
-                } else {
-                    None
-                }
-            });
+            let (key, user_limit) = self.key_and_limit();
 
             let metadata_json = if self.metadata.is_empty() {
                 None
@@ -416,6 +566,106 @@ impl ChannelActor {
         }
     }
 
+    /// Flag the channel state as changed.
+    pub(crate) fn mark_dirty(&mut self) {
+        self.dirty = true;
+        self.registration_dirty = true;
+    }
+
+    /// Write topic, modes and list modes back to the channel's
+    /// registration, so a registered channel gets them back when it is
+    /// recreated.
+    async fn save_registration(&mut self) {
+        let (state_dirty, lists_dirty) = (self.registration_dirty, self.lists_dirty);
+        self.registration_dirty = false;
+        self.lists_dirty = false;
+        let Some(matrix) = self.matrix.upgrade() else {
+            return;
+        };
+        if !matrix
+            .channel_manager
+            .registered_channels
+            .contains(&irc_to_lower(&self.name))
+        {
+            return;
+        }
+        let repo = matrix.db.channels();
+
+        if state_dirty {
+            // The join throttle lifts its own +i; a restored one never would be
+            let modes: HashSet<ChannelMode> = self
+                .modes
+                .iter()
+                .filter(|mode| !(self.join_locked && **mode == ChannelMode::InviteOnly))
+                .cloned()
+                .collect();
+            let modes = crate::state::actor::helpers::modes_to_string(&modes);
+            let topic = self
+                .topic
+                .as_ref()
+                .map(|t| (t.text.as_str(), t.set_by.as_str(), t.set_at));
+
+            if let Err(e) = repo.save_state(&self.name, &modes, topic).await {
+                tracing::warn!(channel = %self.name, error = %e, "Failed to save channel registration state");
+            }
+        }
+
+        if lists_dirty {
+            let entries: Vec<ChannelListEntry> = [
+                ('b', &self.bans),
+                ('e', &self.excepts),
+                ('I', &self.invex),
+                ('q', &self.quiets),
+            ]
+            .into_iter()
+            .flat_map(|(mode, list)| {
+                list.iter().map(move |entry| ChannelListEntry {
+                    mode,
+                    mask: entry.mask.clone(),
+                    set_by: entry.set_by.clone(),
+                    set_at: entry.set_at,
+                    expires_at: entry.expires_at,
+                })
+            })
+            .collect();
+            if let Err(e) = repo.replace_list_entries(&self.name, &entries).await {
+                tracing::warn!(channel = %self.name, error = %e, "Failed to save channel lists");
+            }
+        }
+    }
+
+    /// Load the saved list modes of a registered channel.
+    async fn load_registered_lists(&mut self) {
+        let Some(matrix) = self.matrix.upgrade() else {
+            return;
+        };
+        if !matrix
+            .channel_manager
+            .registered_channels
+            .contains(&irc_to_lower(&self.name))
+        {
+            return;
+        }
+
+        let entries = match matrix.db.channels().list_entries(&self.name).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!(channel = %self.name, error = %e, "Failed to load channel lists");
+                return;
+            }
+        };
+        for entry in entries {
+            if let Some(list) = self.list_mut(entry.mode) {
+                list.push(ListEntry {
+                    mask: entry.mask,
+                    set_by: entry.set_by,
+                    set_at: entry.set_at,
+                    expires_at: entry.expires_at,
+                });
+            }
+        }
+    }
+
     fn cleanup_if_empty(&mut self) {
         if self.state == ActorState::Draining {
             return;
@@ -442,14 +692,10 @@ impl ChannelActor {
                     }
                 });
 
-                let name_lower = self.name.to_lowercase();
-                if matrix
-                    .channel_manager
-                    .channels
-                    .remove(&name_lower)
-                    .is_some()
-                {
-                    crate::metrics::dec_active_channels();
+                if let Some(tx) = self.mailbox.as_ref().and_then(|weak| weak.upgrade()) {
+                    matrix
+                        .channel_manager
+                        .remove_if_current(&self.name.to_lowercase(), &tx);
                 }
             }
         }
@@ -477,6 +723,7 @@ mod tests {
             user_caps: HashMap::new(),
             modes: HashSet::new(),
             mode_timestamps: HashMap::new(),
+            list_timestamps: HashMap::new(),
             topic_timestamp: None,
             server_id: slirc_proto::sync::ServerId::new("000".to_string()),
             topic: None,
@@ -491,11 +738,18 @@ mod tests {
             flood_config: HashMap::new(),
             flood_message_limiters: HashMap::new(),
             flood_join_limiter: None,
+            last_knock: None,
+            recent_joins: VecDeque::new(),
+            join_locked: false,
+            list_expiry_at: None,
             matrix: Weak::new(),
+            mailbox: None,
             state: ActorState::Active,
             observer: None,
             metadata: HashMap::new(),
             dirty: false,
+            registration_dirty: false,
+            lists_dirty: false,
         }
     }
 
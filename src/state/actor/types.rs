@@ -5,12 +5,11 @@
 
 use crate::caps::{Cap, InviteCap, KickCap, TopicCap};
 use crate::security::UserContext;
-use crate::state::{ListEntry, MemberModes, Topic};
+use crate::state::{ListEntry, MemberModes, SendQ, Topic};
 use slirc_proto::{Message, Prefix};
 use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
-use std::time::Instant;
-use tokio::sync::{mpsc, oneshot};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
 use uuid::Uuid;
 
 // Re-export ChannelError from central error module
@@ -24,6 +23,8 @@ pub struct JoinSuccessData {
     pub topic: Option<Topic>,
     pub channel_name: String,
     pub is_secret: bool,
+    /// +H setting, as (lines, minutes).
+    pub history_replay: Option<(u32, u32)>,
 }
 
 // =============================================================================
@@ -35,7 +36,7 @@ pub struct JoinSuccessData {
 pub struct JoinParams {
     pub uid: Uid,
     pub nick: String,
-    pub sender: mpsc::Sender<Arc<Message>>,
+    pub sender: SendQ,
     pub caps: HashSet<String>,
     pub user_context: UserContext,
     pub key: Option<String>,
@@ -92,6 +93,8 @@ pub struct ModeParams {
     pub target_uids: HashMap<String, Vec<Uid>>,
     pub force: bool,
     pub nanotime: i64,
+    /// Unix time at which list entries added by this change expire.
+    pub list_expires_at: Option<i64>,
 }
 
 /// Parameters for channel MESSAGE event handling.
@@ -153,6 +156,12 @@ pub enum ChannelEvent {
         params: Box<ChannelMessageParams>,
         reply_tx: oneshot::Sender<ChannelRouteResult>,
     },
+    /// Run the send checks for a message without delivering it (multiline
+    /// batches, which fan out themselves).
+    CheckMessage {
+        params: Box<ChannelMessageParams>,
+        reply_tx: oneshot::Sender<ChannelRouteResult>,
+    },
     /// Request channel information (for LIST/WHO/NAMES).
     GetInfo {
         requester_uid: Option<Uid>,
@@ -163,6 +172,10 @@ pub enum ChannelEvent {
         crdt: Box<slirc_proto::sync::channel::ChannelCrdt>,
         source: Option<slirc_proto::sync::clock::ServerId>,
     },
+    /// Request the channel's CRDT representation.
+    GetCrdt {
+        reply_tx: oneshot::Sender<slirc_proto::sync::channel::ChannelCrdt>,
+    },
     /// Request list (bans, excepts, etc).
     GetList {
         mode: char,
@@ -199,10 +212,14 @@ pub enum ChannelEvent {
         params: InviteParams,
         reply_tx: oneshot::Sender<Result<(), ChannelError>>,
     },
-    /// Knock on the channel.
+    /// Knock on the channel, telling its operators.
+    ///
+    /// Refused while another knock was delivered within `channel_delay`.
     Knock {
         sender_uid: Uid,
         sender_prefix: Prefix,
+        reason: Option<String>,
+        channel_delay: Duration,
         reply_tx: oneshot::Sender<Result<(), ChannelError>>,
     },
     /// Broadcast a raw message to all members.
@@ -246,6 +263,15 @@ pub enum ChannelEvent {
         setter: String,
         topic: String,
     },
+    /// Incoming JOIN from a peer server.
+    RemoteJoin {
+        uid: Uid,
+        nick: String,
+        ts: u64,
+        join_msg_extended: Box<Message>,
+        join_msg_standard: Box<Message>,
+        source: slirc_proto::sync::clock::ServerId,
+    },
     /// Incoming KICK from a peer server.
     RemoteKick {
         sender: String,
@@ -253,11 +279,13 @@ pub enum ChannelEvent {
         reason: String,
     },
     /// Netsplit quit - remove user without broadcast (already handled by split.rs).
+    ///
+    /// Replies with the number of members left.
     NetsplitQuit {
         uid: Uid,
-        reply_tx: oneshot::Sender<()>,
+        reply_tx: oneshot::Sender<usize>,
     },
-    /// Metadata operation (GET, SET, LIST).
+    /// Metadata operation (SET, LIST, CLEAR).
     Metadata {
         command: MetadataCommand,
         reply_tx: oneshot::Sender<MetadataResult>,
@@ -268,17 +296,40 @@ pub enum ChannelEvent {
     /// has channel memberships. The new session needs to receive channel broadcasts.
     AttachSender {
         uid: Uid,
-        sender: tokio::sync::mpsc::Sender<std::sync::Arc<slirc_proto::Message>>,
+        sender: crate::state::SendQ,
     },
     /// Periodic or event-driven check for channel state persistence.
     CheckAndSave,
+    /// Export restorable channel state for the warm-boot snapshot.
+    GetSnapshot {
+        reply_tx: oneshot::Sender<crate::state::snapshot::ChannelSnapshot>,
+    },
+    /// Replace the ban/except/invex/quiet lists (warm-boot restore).
+    RestoreLists {
+        snapshot: Box<crate::state::snapshot::ChannelSnapshot>,
+    },
+    /// Drop list tombstones every server has seen (tombstone GC).
+    PruneTombstones { stable_millis: i64 },
+    /// Drain the channel if it has no members and is not `+P`.
+    /// Replies whether the channel is now draining.
+    ReapIfEmpty { reply_tx: oneshot::Sender<bool> },
+    /// Lift the +i set by the join throttle.
+    JoinLockExpired,
+    /// Remove timed list entries whose expiry has passed.
+    ExpireListEntries,
 }
 
 #[derive(Debug)]
 pub enum MetadataCommand {
-    Get { key: String },
-    Set { key: String, value: Option<String> },
+    /// Set or (with no value) delete a key, refusing new keys past `max_keys`.
+    Set {
+        key: String,
+        value: Option<String>,
+        max_keys: usize,
+    },
     List,
+    /// Remove every key, replying with the removed ones.
+    Clear,
 }
 
 pub type MetadataResult = Result<HashMap<String, String>, ChannelError>;
@@ -297,6 +348,7 @@ pub enum ClearTarget {
 pub struct ChannelInfo {
     pub name: String,
     pub topic: Option<Topic>,
+    /// Number of members, not counting services.
     pub member_count: usize,
     pub created: i64,
     pub modes: HashSet<ChannelMode>,
@@ -325,10 +377,14 @@ pub enum ChannelRouteResult {
     BlockedNotice,
     /// Blocked by +b (banned).
     BlockedBanned,
+    /// Blocked by +q or an `m:` ban (quieted).
+    BlockedQuiet,
     /// Blocked by +B (anti-caps).
     BlockedAntiCaps,
     /// Blocked by +G (censored).
     BlockedCensored,
+    /// Sender tripped +f (flood protection) and was punished.
+    BlockedFlood,
 }
 
 /// Channel modes (Ported from legacy code).
@@ -396,8 +452,12 @@ pub enum ChannelMode {
     Limit(usize, slirc_proto::sync::clock::HybridTimestamp),
     /// +F <channel>: Forward to channel on join error
     JoinForward(String, slirc_proto::sync::clock::HybridTimestamp),
-    /// +f <lines:seconds>: Advanced flood protection
+    /// +f <lines:seconds[:action]>: Advanced flood protection
     Flood(String, slirc_proto::sync::clock::HybridTimestamp),
+    /// +j <joins:seconds>: Join throttle
+    JoinThrottle(u32, u32, slirc_proto::sync::clock::HybridTimestamp),
+    /// +H <lines:minutes>: Replay recent history to joining users
+    HistoryReplay(u32, u32, slirc_proto::sync::clock::HybridTimestamp),
     /// +L <channel>: Redirect to channel when limit (+l) reached
     Redirect(String, slirc_proto::sync::clock::HybridTimestamp),
 }
@@ -408,21 +468,50 @@ pub enum FloodType {
     Join,    // 'j'
 }
 
+/// What +f does to a member who sends too many messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FloodAction {
+    /// Kick the member.
+    #[default]
+    Kick,
+    /// Ban `*!*@host`, then kick.
+    Ban,
+    /// Quiet `*!*@host` (+q).
+    Mute,
+}
+
+impl FloodAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Kick => "kick",
+            Self::Ban => "ban",
+            Self::Mute => "mute",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct FloodParam {
     pub count: u32,
     pub period: u32,
     pub type_: FloodType,
+    pub action: FloodAction,
 }
 
 impl std::str::FromStr for FloodParam {
     type Err = &'static str;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        // Format: [<count>][<type>][:<seconds>]
-        // E.g. "5m:10", "10j:60", "3:5" (defaults to m)
-
-        let (rest, period_str) = s.rsplit_once(':').ok_or("Missing period separator ':'")?;
+        // Format: <count>[<type>]:<seconds>[:<action>]
+        // E.g. "5m:10", "10j:60", "3:5" (defaults to m), "5:10:ban"
+
+        let mut parts = s.split(':');
+        let rest = parts.next().unwrap_or_default();
+        let period_str = parts.next().ok_or("Missing period separator ':'")?;
+        let suffix = parts.next();
+        if parts.next().is_some() {
+            return Err("Too many ':' separators");
+        }
 
         let period = period_str.parse::<u32>().map_err(|_| "Invalid period")?;
         if period == 0 {
@@ -442,16 +531,33 @@ impl std::str::FromStr for FloodParam {
             return Err("Count must be positive");
         }
 
-        let type_ = match type_str {
+        let mut type_ = match type_str {
             "" | "m" => FloodType::Message,
             "j" => FloodType::Join,
             _ => return Err("Unknown flood type (supported: m, j)"),
         };
 
+        let action = match suffix {
+            None | Some("kick") => FloodAction::Kick,
+            Some("ban") => FloodAction::Ban,
+            Some("mute") => FloodAction::Mute,
+            // Older releases stored the type last ("5:10:m")
+            Some("m") if type_str.is_empty() => FloodAction::Kick,
+            Some("j") if type_str.is_empty() => {
+                type_ = FloodType::Join;
+                FloodAction::Kick
+            }
+            Some(_) => return Err("Unknown flood action (supported: kick, ban, mute)"),
+        };
+        if type_ == FloodType::Join && action != FloodAction::Kick {
+            return Err("Join floods are refused, not punished");
+        }
+
         Ok(FloodParam {
             count,
             period,
             type_,
+            action,
         })
     }
 }
@@ -462,10 +568,35 @@ impl std::fmt::Display for FloodParam {
             FloodType::Message => "m",
             FloodType::Join => "j",
         };
-        write!(f, "{}:{}:{}", self.count, self.period, type_char)
+        write!(f, "{}{}:{}", self.count, type_char, self.period)?;
+        if self.action != FloodAction::Kick {
+            write!(f, ":{}", self.action.as_str())?;
+        }
+        Ok(())
     }
 }
 
+/// Most lines +H may replay on join.
+pub const MAX_HISTORY_REPLAY_LINES: u32 = 100;
+
+/// Parse a `<count>:<period>` mode argument; both must be positive.
+fn parse_count_period(spec: &str) -> Option<(u32, u32)> {
+    let (count, period) = spec.split_once(':')?;
+    let count = count.parse::<u32>().ok().filter(|&n| n > 0)?;
+    let period = period.parse::<u32>().ok().filter(|&n| n > 0)?;
+    Some((count, period))
+}
+
+/// Parse a +j `<joins>:<seconds>` argument; both must be positive.
+pub fn parse_join_throttle(spec: &str) -> Option<(u32, u32)> {
+    parse_count_period(spec)
+}
+
+/// Parse a +H `<lines>:<minutes>` argument, up to [`MAX_HISTORY_REPLAY_LINES`] lines.
+pub fn parse_history_replay(spec: &str) -> Option<(u32, u32)> {
+    parse_count_period(spec).filter(|&(lines, _)| lines <= MAX_HISTORY_REPLAY_LINES)
+}
+
 #[derive(Debug, Clone)]
 pub struct InviteEntry {
     pub uid: Uid,
@@ -506,5 +637,35 @@ mod tests {
         assert!(FloodParam::from_str("0:10").is_err()); // Zero count
         assert!(FloodParam::from_str("5:0").is_err()); // Zero period
         assert!(FloodParam::from_str("5x:10").is_err()); // Invalid type
+        assert!(FloodParam::from_str("5:10:nuke").is_err()); // Invalid action
+        assert!(FloodParam::from_str("5j:10:ban").is_err()); // Action on joins
+    }
+
+    #[test]
+    fn test_flood_param_actions_round_trip() {
+        let p = FloodParam::from_str("5:10:ban").unwrap();
+        assert_eq!(p.action, FloodAction::Ban);
+        assert_eq!(p.to_string(), "5m:10:ban");
+        assert_eq!(FloodParam::from_str(&p.to_string()).unwrap(), p);
+
+        let p = FloodParam::from_str("3m:5").unwrap();
+        assert_eq!(p.action, FloodAction::Kick);
+        assert_eq!(p.to_string(), "3m:5");
+
+        // Written by older releases
+        let p = FloodParam::from_str("10:60:j").unwrap();
+        assert_eq!(p.type_, FloodType::Join);
+        assert_eq!(p.to_string(), "10j:60");
+    }
+
+    #[test]
+    fn test_parse_join_throttle() {
+        assert_eq!(parse_join_throttle("5:10"), Some((5, 10)));
+        assert_eq!(parse_join_throttle("0:10"), None);
+        assert_eq!(parse_join_throttle("5:0"), None);
+        assert_eq!(parse_join_throttle("5"), None);
+        assert_eq!(parse_join_throttle("5:10:1"), None);
+        assert_eq!(parse_history_replay("20:1440"), Some((20, 1440)));
+        assert_eq!(parse_history_replay("101:60"), None);
     }
 }
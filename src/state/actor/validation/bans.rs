@@ -3,8 +3,16 @@
 //! Provides utilities to check if a user is banned from a channel,
 //! with support for extended bans and exception lists.
 
-use crate::security::{UserContext, matches_ban_or_except};
+use super::super::{ChannelActor, ChannelEvent};
+use crate::security::{ExtendedBan, UserContext, matches_ban_or_except};
 use crate::state::ListEntry;
+use crate::state::dashmap_ext::DashMapExt;
+use slirc_proto::irc_to_lower;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// How long to wait for another channel's lists when checking `$j` bans.
+const JOIN_BAN_LOOKUP_TIMEOUT: Duration = Duration::from_secs(1);
 
 /// Create IRC user mask (nick!user@host).
 pub fn format_user_mask(nick: &str, user: &str, host: &str) -> String {
@@ -42,6 +50,110 @@ pub fn is_banned(
     false
 }
 
+/// Check if a user is muted by a quiet (+q) or an `m:` ban, accounting for
+/// exceptions.
+///
+/// Masks may be extbans (`+q $a:account`, `+b m:$r:*spam*`). A `+e` entry
+/// exempts the user whether it is written plain or with the `m:` prefix.
+pub fn is_quieted(
+    user_mask: &str,
+    user_context: &UserContext,
+    bans: &[ListEntry],
+    quiets: &[ListEntry],
+    excepts: &[ListEntry],
+) -> bool {
+    let mut mutes = bans
+        .iter()
+        .filter_map(|b| b.mask.strip_prefix("m:"))
+        .chain(quiets.iter().map(|q| q.mask.as_str()));
+    if !mutes.any(|mask| matches_ban_or_except(mask, user_mask, user_context)) {
+        return false;
+    }
+
+    !excepts.iter().any(|e| {
+        let mask = e.mask.strip_prefix("m:").unwrap_or(&e.mask);
+        matches_ban_or_except(mask, user_mask, user_context)
+    })
+}
+
+/// The channel a `$j:#channel` (or `$~j:`, `m:$j:`) mask refers to, lowercased.
+fn join_ban_channel(mask: &str) -> Option<String> {
+    let mask = mask.strip_prefix("m:").unwrap_or(mask);
+    match ExtendedBan::parse(mask)? {
+        ExtendedBan::Join(channel) => Some(irc_to_lower(&channel)),
+        ExtendedBan::Not(inner) => match *inner {
+            ExtendedBan::Join(channel) => Some(irc_to_lower(&channel)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl ChannelActor {
+    /// Find which channels named by this channel's `$j` masks the user is
+    /// banned from.
+    ///
+    /// Returns `None` if no list has a `$j` mask. Other channels are asked
+    /// for their lists with a timeout, so two channels with `$j` masks on
+    /// each other can't deadlock; their own `$j` masks are not followed.
+    pub(crate) async fn resolve_join_bans(
+        &self,
+        user_context: &UserContext,
+    ) -> Option<UserContext> {
+        let mut channels: Vec<String> = [&self.bans, &self.excepts, &self.invex, &self.quiets]
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| join_ban_channel(&entry.mask))
+            .collect();
+        if channels.is_empty() {
+            return None;
+        }
+        channels.sort();
+        channels.dedup();
+
+        let user_mask = create_user_mask(user_context);
+        let own_name = irc_to_lower(&self.name);
+        let matrix = self.matrix.upgrade();
+        let mut resolved = user_context.clone();
+        resolved.banned_in.clear();
+
+        for channel in channels {
+            let banned = if channel == own_name {
+                is_banned(&user_mask, user_context, &self.bans, &self.excepts)
+            } else {
+                let Some(tx) = matrix
+                    .as_ref()
+                    .and_then(|m| m.channel_manager.channels.get_cloned(&channel))
+                else {
+                    continue;
+                };
+                let lookup = async {
+                    let mut lists = Vec::with_capacity(2);
+                    for mode in ['b', 'e'] {
+                        let (reply_tx, reply_rx) = oneshot::channel();
+                        tx.send(ChannelEvent::GetList { mode, reply_tx })
+                            .await
+                            .ok()?;
+                        lists.push(reply_rx.await.ok()?);
+                    }
+                    Some(lists)
+                };
+                match tokio::time::timeout(JOIN_BAN_LOOKUP_TIMEOUT, lookup).await {
+                    Ok(Some(lists)) => is_banned(&user_mask, user_context, &lists[0], &lists[1]),
+                    _ => {
+                        tracing::debug!(channel = %self.name, other = %channel, "$j ban lookup failed");
+                        false
+                    }
+                }
+            };
+            if banned {
+                resolved.banned_in.push(channel);
+            }
+        }
+        Some(resolved)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -123,6 +235,7 @@ mod tests {
             sasl_mechanism: None,
             is_registered: false,
             is_tls: false,
+            banned_in: Vec::new(),
         };
         assert_eq!(create_user_mask(&ctx), "TestNick!testuser@test.example.com");
     }
@@ -143,6 +256,7 @@ mod tests {
             sasl_mechanism: Some("PLAIN".to_string()),
             is_registered: true,
             is_tls: true,
+            banned_in: Vec::new(),
         };
         assert_eq!(create_user_mask(&ctx), "AuthedUser!authed@irc.network.org");
     }
@@ -163,7 +277,73 @@ mod tests {
             sasl_mechanism: None,
             is_registered: false,
             is_tls: false,
+            banned_in: Vec::new(),
         };
         assert_eq!(create_user_mask(&ctx), "Guest!~guest@unverified.host.net");
     }
+
+    fn entry(mask: &str) -> ListEntry {
+        ListEntry {
+            mask: mask.to_string(),
+            set_by: "op".to_string(),
+            set_at: 0,
+            expires_at: None,
+        }
+    }
+
+    fn guest() -> UserContext {
+        UserContext {
+            nickname: "Guest".to_string(),
+            username: "guest".to_string(),
+            hostname: "spam.example".to_string(),
+            realname: "Guest User".to_string(),
+            account: Some("guestacct".to_string()),
+            server: "server.host.net".to_string(),
+            channels: vec![],
+            is_oper: false,
+            oper_type: None,
+            certificate_fp: None,
+            sasl_mechanism: None,
+            is_registered: true,
+            is_tls: false,
+            banned_in: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_is_quieted_by_quiet_and_mute_extban() {
+        let ctx = guest();
+        let mask = create_user_mask(&ctx);
+        let quiets = [entry("*!*@spam.example")];
+        assert!(is_quieted(&mask, &ctx, &[], &quiets, &[]));
+
+        let bans = [entry("m:$a:guestacct")];
+        assert!(is_quieted(&mask, &ctx, &bans, &[], &[]));
+
+        // A plain ban is not a mute
+        let bans = [entry("*!*@spam.example")];
+        assert!(!is_quieted(&mask, &ctx, &bans, &[], &[]));
+    }
+
+    #[test]
+    fn test_is_quieted_honors_exceptions() {
+        let ctx = guest();
+        let mask = create_user_mask(&ctx);
+        let quiets = [entry("$r:*Guest*")];
+        assert!(!is_quieted(
+            &mask,
+            &ctx,
+            &[],
+            &quiets,
+            &[entry("Guest!*@*")]
+        ));
+        assert!(!is_quieted(
+            &mask,
+            &ctx,
+            &[],
+            &quiets,
+            &[entry("m:$a:guestacct")]
+        ));
+        assert!(is_quieted(&mask, &ctx, &[], &quiets, &[entry("Other!*@*")]));
+    }
 }
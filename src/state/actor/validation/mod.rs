@@ -7,4 +7,4 @@ pub mod bans;
 pub mod invites;
 pub mod permissions;
 
-pub use bans::{create_user_mask, format_user_mask, is_banned};
+pub use bans::{create_user_mask, format_user_mask, is_banned, is_quieted};
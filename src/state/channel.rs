@@ -1,15 +1,20 @@
 //! Channel-related types and state.
 
+use serde::{Deserialize, Serialize};
+
 /// An entry in a list (bans, excepts, invex).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListEntry {
     pub mask: String,
     pub set_by: String,
     pub set_at: i64,
+    /// Unix time at which a timed entry lifts itself (`TBAN`, `+b mask ~30m`).
+    #[serde(default)]
+    pub expires_at: Option<i64>,
 }
 
 /// Channel topic with metadata.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Topic {
     pub text: String,
     pub set_by: String,
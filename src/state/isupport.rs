@@ -0,0 +1,123 @@
+//! RPL_ISUPPORT (005) tokens advertised to clients.
+//!
+//! The token list is built once per config load and cached in
+//! [`HotConfig`](super::HotConfig), so the welcome burst and REHASH
+//! re-advertisement send exactly what the handlers enforce.
+
+use crate::config::Casemapping;
+use slirc_proto::isupport::{ChanModesBuilder, IsupportBuilder, TargMaxBuilder};
+
+/// Tokens per RPL_ISUPPORT line.
+pub const TOKENS_PER_LINE: usize = 13;
+
+/// Build the ISUPPORT tokens for the given runtime values.
+pub fn tokens(network: &str, casemapping: Casemapping, max_monitor_targets: usize) -> Vec<String> {
+    let chanmodes = ChanModesBuilder::new()
+        .list_modes("beIq")
+        .param_always("k")
+        .param_set("Hjl")
+        .no_param("imnrstKMRU");
+
+    let targmax = TargMaxBuilder::new()
+        .add("JOIN", 10)
+        .add("PART", 10)
+        .add("KICK", 4)
+        .add("PRIVMSG", 4)
+        .add("NOTICE", 4)
+        .add("NAMES", 10)
+        .add("WHOIS", 1)
+        .add("WHOWAS", 10);
+
+    let monitor = max_monitor_targets.to_string();
+    IsupportBuilder::new()
+        .network(network)
+        .custom("METADATA", None) // Early in the list to pass buggy tests
+        .casemapping(casemapping.as_isupport_value())
+        .chantypes("#&+!")
+        .prefix("~&@%+", "qaohv")
+        .chanmodes_typed(chanmodes)
+        .max_nick_length(30)
+        .custom("CHANNELLEN", Some("50"))
+        .max_topic_length(390)
+        .custom("KICKLEN", Some("390"))
+        .custom("AWAYLEN", Some("200"))
+        .modes_count(6)
+        .custom("MAXTARGETS", Some("4"))
+        .targmax(targmax)
+        .custom("MONITOR", Some(&monitor))
+        .custom("WATCH", Some(&monitor))
+        .custom("CALLERID", Some("g"))
+        .excepts(Some('e'))
+        .invex(Some('I'))
+        .custom("EXTBAN", Some(",m"))
+        .custom("ELIST", Some("MNU"))
+        .custom("KNOCK", None)
+        .status_msg("~&@%+")
+        .custom("BOT", Some("B"))
+        .custom("WHOX", None)
+        .custom("UTF8ONLY", None) // Advertise UTF-8 only mode per modern IRC
+        .build()
+        .split(' ')
+        .map(str::to_string)
+        .collect()
+}
+
+/// Group tokens into RPL_ISUPPORT lines.
+pub fn lines(tokens: &[String]) -> Vec<String> {
+    tokens
+        .chunks(TOKENS_PER_LINE)
+        .map(|chunk| chunk.join(" "))
+        .collect()
+}
+
+/// Tokens to send connected clients when the advertised set changes.
+///
+/// New and changed tokens are sent as-is; tokens no longer advertised are
+/// negated (`-TOKEN`), as the ISUPPORT spec allows after registration.
+pub fn changes(old: &[String], new: &[String]) -> Vec<String> {
+    let key = |token: &str| token.split('=').next().unwrap_or_default().to_string();
+    let mut changed: Vec<String> = new
+        .iter()
+        .filter(|token| !old.contains(token))
+        .cloned()
+        .collect();
+    let new_keys: Vec<String> = new.iter().map(|t| key(t)).collect();
+    changed.extend(
+        old.iter()
+            .map(|t| key(t))
+            .filter(|k| !new_keys.contains(k))
+            .map(|k| format!("-{k}")),
+    );
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_send_new_values_and_negate_removed_tokens() {
+        let old = tokens("OldNet", Casemapping::Rfc1459, 100);
+        let new = tokens("NewNet", Casemapping::Rfc1459, 50);
+        assert_eq!(
+            changes(&old, &new),
+            ["NETWORK=NewNet", "MONITOR=50", "WATCH=50"]
+        );
+        assert!(changes(&new, &new).is_empty());
+
+        let removed = vec!["WHOX".to_string(), "NETWORK=NewNet".to_string()];
+        assert_eq!(changes(&removed, &new[..1]), ["-WHOX"]);
+    }
+
+    #[test]
+    fn lines_hold_at_most_thirteen_tokens() {
+        let tokens = tokens("Net", Casemapping::Rfc1459, 100);
+        let lines = lines(&tokens);
+        assert!(
+            lines
+                .iter()
+                .all(|l| l.split(' ').count() <= TOKENS_PER_LINE)
+        );
+        assert_eq!(lines.join(" ").split(' ').count(), tokens.len());
+    }
+}
@@ -7,7 +7,7 @@ use crate::state::actor::{ChannelEvent, ChannelInfo};
 use crate::state::observer::StateObserver;
 use dashmap::{DashMap, DashSet};
 use futures_util::future::join_all;
-use slirc_proto::Message;
+use slirc_proto::{Message, irc_to_lower};
 use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot};
 
@@ -75,6 +75,10 @@ impl ChannelManager {
         for state in states {
             let name = state.name.clone();
             let name_lower = name.to_lowercase();
+            // A warm-boot snapshot may already have restored this channel.
+            if self.channels.contains_key(&name_lower) {
+                continue;
+            }
 
             let initial_topic = if let (Some(text), Some(set_by), Some(set_at)) =
                 (state.topic, state.topic_set_by, state.topic_set_at)
@@ -111,6 +115,115 @@ impl ChannelManager {
         }
     }
 
+    /// Restore channels from a warm-boot snapshot.
+    ///
+    /// Channels that already exist are left untouched.
+    pub async fn restore_snapshot(
+        &self,
+        snapshots: Vec<crate::state::snapshot::ChannelSnapshot>,
+        matrix: std::sync::Weak<crate::state::Matrix>,
+    ) {
+        use crate::state::actor::ChannelActor;
+        use crate::state::actor::modes_from_string;
+
+        for snapshot in snapshots {
+            let name_lower = snapshot.name.to_lowercase();
+            if self.channels.contains_key(&name_lower) {
+                continue;
+            }
+
+            let modes = modes_from_string(&snapshot.modes, snapshot.key.clone(), snapshot.limit);
+            let tx = ChannelActor::spawn_with_capacity(
+                snapshot.name.clone(),
+                matrix.clone(),
+                snapshot.topic.clone(),
+                Some(modes),
+                Some(snapshot.metadata.clone()),
+                Some(snapshot.created),
+                100,
+                self.observer.clone(),
+            );
+
+            // Queued before the channel is visible, so no JOIN can race it.
+            let _ = tx
+                .send(ChannelEvent::RestoreLists {
+                    snapshot: Box::new(snapshot),
+                })
+                .await;
+
+            self.channels.insert(name_lower, tx);
+            crate::metrics::inc_active_channels();
+            self.stats_manager.channel_created();
+        }
+    }
+
+    /// Restore channels recovered from the CRDT journal.
+    ///
+    /// Membership is dropped, since nobody is connected yet. Channels that
+    /// already exist merge the recovered state.
+    pub async fn restore_crdts(
+        &self,
+        crdts: Vec<slirc_proto::sync::channel::ChannelCrdt>,
+        matrix: std::sync::Weak<crate::state::Matrix>,
+    ) {
+        use crate::state::actor::ChannelActor;
+        use slirc_proto::sync::channel::MembershipCrdt;
+
+        for mut crdt in crdts {
+            crdt.members = MembershipCrdt::new();
+            let name_lower = crdt.name.to_lowercase();
+
+            let tx = match self.channels.get(&name_lower) {
+                Some(tx) => tx.value().clone(),
+                None => {
+                    // No default modes: the merge sets exactly the recorded ones
+                    let tx = ChannelActor::spawn_with_capacity(
+                        crdt.name.clone(),
+                        matrix.clone(),
+                        None,
+                        Some(Default::default()),
+                        None,
+                        Some(crdt.created_at.millis / 1000),
+                        100,
+                        self.observer.clone(),
+                    );
+                    self.channels.insert(name_lower, tx.clone());
+                    crate::metrics::inc_active_channels();
+                    self.stats_manager.channel_created();
+                    tx
+                }
+            };
+
+            let _ = tx
+                .send(ChannelEvent::MergeCrdt {
+                    crdt: Box::new(crdt),
+                    source: None,
+                })
+                .await;
+        }
+    }
+
+    /// The CRDT representation of every channel.
+    ///
+    /// Channels whose actor does not answer are left out.
+    pub async fn crdts(&self) -> Vec<slirc_proto::sync::channel::ChannelCrdt> {
+        let channels: Vec<_> = self
+            .channels
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        let mut crdts = Vec::with_capacity(channels.len());
+        for tx in channels {
+            let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+            if tx.send(ChannelEvent::GetCrdt { reply_tx }).await.is_ok()
+                && let Ok(crdt) = reply_rx.await
+            {
+                crdts.push(crdt);
+            }
+        }
+        crdts
+    }
+
     /// Trigger persistence sync for all active channels.
     pub async fn sync_all_channels(&self) {
         let channels: Vec<_> = self
@@ -123,6 +236,78 @@ impl ChannelManager {
         }
     }
 
+    /// Drop list tombstones stamped before `stable_millis` in every channel.
+    pub async fn prune_tombstones(&self, stable_millis: i64) {
+        let channels: Vec<_> = self
+            .channels
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        for tx in channels {
+            let _ = tx
+                .send(ChannelEvent::PruneTombstones { stable_millis })
+                .await;
+        }
+    }
+
+    /// Unregister a channel, but only if `name` still maps to the actor behind `tx`.
+    ///
+    /// A channel recreated under the same name in the meantime is left alone.
+    pub fn remove_if_current(&self, name_lower: &str, tx: &mpsc::Sender<ChannelEvent>) -> bool {
+        let removed = self
+            .channels
+            .remove_if(name_lower, |_, current| current.same_channel(tx))
+            .is_some();
+        if removed {
+            crate::metrics::dec_active_channels();
+            self.stats_manager.channel_destroyed();
+        }
+        removed
+    }
+
+    /// Ask an actor to drain itself if empty, unregistering it when it does.
+    /// Returns whether the channel is gone.
+    ///
+    /// Emptiness is decided by the actor, so a JOIN queued ahead of this
+    /// request keeps the channel alive.
+    pub async fn reap_if_empty(&self, name_lower: &str, tx: &mpsc::Sender<ChannelEvent>) -> bool {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        if tx
+            .send(ChannelEvent::ReapIfEmpty { reply_tx })
+            .await
+            .is_err()
+        {
+            // The actor is gone; its mailbox is useless either way
+            return self.remove_if_current(name_lower, tx);
+        }
+        let drained = matches!(reply_rx.await, Ok(true));
+        if drained {
+            // Normally already done by the actor as it drained
+            self.remove_if_current(name_lower, tx);
+        }
+        drained
+    }
+
+    /// Reap every empty channel that is not registered with ChanServ.
+    ///
+    /// Returns the number of channels removed.
+    pub async fn reap_empty_channels(&self) -> usize {
+        let candidates: Vec<_> = self
+            .channels
+            .iter()
+            .filter(|entry| !self.registered_channels.contains(entry.key()))
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+
+        let mut reaped = 0;
+        for (name, tx) in candidates {
+            if self.reap_if_empty(&name, &tx).await {
+                reaped += 1;
+            }
+        }
+        reaped
+    }
+
     /// Initialize with pre-loaded registered channels.
     pub fn with_registered_channels(
         registered_channels: Vec<String>,
@@ -130,7 +315,7 @@ impl ChannelManager {
     ) -> Self {
         let registered_set = DashSet::with_capacity(registered_channels.len());
         for name in registered_channels {
-            registered_set.insert(name);
+            registered_set.insert(irc_to_lower(&name));
         }
 
         Self {
@@ -269,3 +454,56 @@ impl ChannelManager {
         results.into_iter().flatten().collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::actor::{ChannelActor, ChannelMode};
+    use crate::state::managers::stats::StatsManager;
+    use std::collections::HashSet;
+    use std::sync::Weak;
+
+    fn spawn_channel(manager: &ChannelManager, name: &str, modes: HashSet<ChannelMode>) {
+        let tx = ChannelActor::spawn_with_capacity(
+            name.to_string(),
+            Weak::new(),
+            None,
+            Some(modes),
+            None,
+            None,
+            10,
+            None,
+        );
+        manager.channels.insert(name.to_string(), tx);
+        manager.stats_manager.channel_created();
+    }
+
+    #[tokio::test]
+    async fn test_reap_skips_registered_and_permanent_channels() {
+        let manager = ChannelManager::with_registered_channels(
+            vec!["#reg".into()],
+            Arc::new(StatsManager::new()),
+        );
+        spawn_channel(&manager, "#typo", HashSet::new());
+        spawn_channel(&manager, "#reg", HashSet::new());
+        spawn_channel(&manager, "#perm", HashSet::from([ChannelMode::Permanent]));
+
+        assert_eq!(manager.reap_empty_channels().await, 1);
+        assert!(!manager.channels.contains_key("#typo"));
+        assert!(manager.channels.contains_key("#reg"));
+        assert!(manager.channels.contains_key("#perm"));
+        assert_eq!(manager.stats_manager.channels(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_remove_if_current_keeps_replacement_actor() {
+        let manager =
+            ChannelManager::with_registered_channels(vec![], Arc::new(StatsManager::new()));
+        spawn_channel(&manager, "#chan", HashSet::new());
+        let old_tx = manager.channels.get("#chan").unwrap().clone();
+        spawn_channel(&manager, "#chan", HashSet::new());
+
+        assert!(!manager.remove_if_current("#chan", &old_tx));
+        assert!(manager.channels.contains_key("#chan"));
+    }
+}
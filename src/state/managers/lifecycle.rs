@@ -1,6 +1,15 @@
 use crate::state::Uid;
+use parking_lot::RwLock;
 use std::sync::Arc;
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::{Notify, broadcast, mpsc};
+
+/// A request to stop the server, from DIE, RESTART or a signal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownRequest {
+    /// Exec the server binary again once drained.
+    pub restart: bool,
+    pub reason: String,
+}
 
 /// Manages server lifecycle events (shutdown, user disconnects, background tasks).
 pub struct LifecycleManager {
@@ -9,6 +18,14 @@ pub struct LifecycleManager {
 
     /// Channel for requesting user disconnects (from async contexts).
     pub disconnect_tx: mpsc::Sender<(Uid, String)>,
+
+    /// Maintenance mode reason. While set, new registrations are refused and
+    /// services are read-only.
+    maintenance: RwLock<Option<String>>,
+
+    /// The first shutdown request; the controller in `main.rs` acts on it.
+    shutdown_request: RwLock<Option<ShutdownRequest>>,
+    shutdown_notify: Notify,
 }
 
 impl LifecycleManager {
@@ -18,7 +35,70 @@ impl LifecycleManager {
         Self {
             shutdown_tx,
             disconnect_tx,
+            maintenance: RwLock::new(None),
+            shutdown_request: RwLock::new(None),
+            shutdown_notify: Notify::new(),
+        }
+    }
+
+    /// Ask the shutdown controller to drain and stop the server.
+    ///
+    /// Only the first request counts; returns false if one was already made.
+    pub fn request_shutdown(&self, restart: bool, reason: &str) -> bool {
+        let mut request = self.shutdown_request.write();
+        if request.is_some() {
+            return false;
         }
+        *request = Some(ShutdownRequest {
+            restart,
+            reason: reason.to_string(),
+        });
+        self.shutdown_notify.notify_one();
+        true
+    }
+
+    /// The pending shutdown request, if any.
+    pub fn shutdown_request(&self) -> Option<ShutdownRequest> {
+        self.shutdown_request.read().clone()
+    }
+
+    /// Wait until shutdown is requested.
+    pub async fn wait_for_shutdown_request(&self) -> ShutdownRequest {
+        loop {
+            if let Some(request) = self.shutdown_request() {
+                return request;
+            }
+            self.shutdown_notify.notified().await;
+        }
+    }
+
+    /// Text for the ERROR and QUIT sent to clients and links on shutdown.
+    pub fn shutdown_message(&self) -> String {
+        match self.shutdown_request() {
+            Some(request) if request.restart => format!("Server restarting ({})", request.reason),
+            Some(request) => format!("Server shutting down ({})", request.reason),
+            None => "Server shutting down".to_string(),
+        }
+    }
+
+    /// Enter maintenance mode with the given reason.
+    pub fn enter_maintenance(&self, reason: &str) {
+        *self.maintenance.write() = Some(reason.to_string());
+    }
+
+    /// Leave maintenance mode. Returns false if it was not active.
+    pub fn leave_maintenance(&self) -> bool {
+        self.maintenance.write().take().is_some()
+    }
+
+    /// The maintenance reason, if maintenance mode is active.
+    pub fn maintenance_reason(&self) -> Option<String> {
+        self.maintenance.read().clone()
+    }
+
+    /// Returns true if maintenance mode is active.
+    pub fn in_maintenance(&self) -> bool {
+        self.maintenance.read().is_some()
     }
 
     /// Request that a user be disconnected.
@@ -32,7 +112,7 @@ impl LifecycleManager {
     pub fn spawn_background_tasks(&self, matrix: Arc<crate::state::Matrix>) {
         // Spawn signal handler for graceful shutdown
         {
-            let shutdown_tx = self.shutdown_tx.clone();
+            let matrix = Arc::clone(&matrix);
             tokio::spawn(async move {
                 use tokio::signal::unix::{SignalKind, signal};
                 let mut sigint =
@@ -40,13 +120,16 @@ impl LifecycleManager {
                 let mut sigterm =
                     signal(SignalKind::terminate()).expect("failed to install SIGTERM handler");
 
-                tokio::select! {
-                    _ = sigint.recv() => tracing::info!("Received SIGINT - initiating graceful shutdown"),
-                    _ = sigterm.recv() => tracing::info!("Received SIGTERM - initiating graceful shutdown"),
-                }
+                let signal = tokio::select! {
+                    _ = sigint.recv() => "SIGINT",
+                    _ = sigterm.recv() => "SIGTERM",
+                };
+                tracing::info!("Received {signal} - initiating graceful shutdown");
 
-                // Broadcast shutdown signal to all tasks
-                let _ = shutdown_tx.send(());
+                // The shutdown controller drains clients and flushes state
+                matrix
+                    .lifecycle_manager
+                    .request_shutdown(false, &format!("received {signal}"));
             });
         }
 
@@ -86,6 +169,33 @@ impl LifecycleManager {
             });
         }
 
+        // Empty channel reaper task
+        let reap_secs = matrix.config.channels.reap_interval_secs;
+        if reap_secs > 0 {
+            let matrix = Arc::clone(&matrix);
+            tokio::spawn(async move {
+                // First sweep after a full interval, so restored channels get time to refill
+                let period = tokio::time::Duration::from_secs(reap_secs);
+                let mut interval =
+                    tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+                interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+                let mut shutdown_rx = matrix.lifecycle_manager.shutdown_tx.subscribe();
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => {
+                            let reaped = matrix.channel_manager.reap_empty_channels().await;
+                            if reaped > 0 {
+                                tracing::info!(count = reaped, "Empty channels reaped");
+                            }
+                        }
+                        _ = shutdown_rx.recv() => {
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+
         // Always-on writeback task
         {
             let matrix = Arc::clone(&matrix);
@@ -111,8 +221,17 @@ impl LifecycleManager {
             });
         }
 
-        // Nick enforcement task
-        crate::services::enforce::spawn_enforcement_task(Arc::clone(&matrix));
+        // Built-in services tasks; an external services package does its own
+        if matrix.config.services.builtin() {
+            // Nick enforcement task
+            crate::services::enforce::spawn_enforcement_task(Arc::clone(&matrix));
+
+            // ChanServ AKICK sweep task
+            crate::services::chanserv::sweep::spawn_akick_sweep_task(Arc::clone(&matrix));
+
+            // ChanServ channel expiry task
+            crate::services::chanserv::expire::spawn_expiry_task(Arc::clone(&matrix));
+        }
 
         // WHOWAS cleanup task
         {
@@ -134,7 +253,7 @@ impl LifecycleManager {
             });
         }
 
-        // Shun expiry cleanup task
+        // Shun and Q-line expiry cleanup task
         {
             let matrix = Arc::clone(&matrix);
             tokio::spawn(async move {
@@ -153,6 +272,16 @@ impl LifecycleManager {
                             if removed > 0 {
                                 tracing::info!(removed = removed, "Expired shuns removed");
                             }
+
+                            let before = matrix.security_manager.qlines.len();
+                            matrix
+                                .security_manager
+                                .qlines
+                                .retain(|_, qline| qline.expires_at.is_none_or(|exp| exp > now));
+                            let removed = before - matrix.security_manager.qlines.len();
+                            if removed > 0 {
+                                tracing::info!(removed = removed, "Expired Q-lines removed");
+                            }
                         }
                         _ = shutdown_rx.recv() => {
                             break;
@@ -237,7 +366,7 @@ impl LifecycleManager {
         {
             let matrix = Arc::clone(&matrix);
             tokio::spawn(async move {
-                let retention = std::time::Duration::from_secs(30 * 86400);
+                let retention = &matrix.config.history.retention;
                 let mut shutdown_rx = matrix.lifecycle_manager.shutdown_tx.subscribe();
 
                 // Run immediately at startup
@@ -14,3 +14,4 @@ pub mod security;
 pub mod service;
 pub mod stats;
 pub mod user;
+pub mod user_index;
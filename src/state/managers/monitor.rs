@@ -11,6 +11,7 @@ use dashmap::{DashMap, DashSet};
 /// The MonitorManager holds all MONITOR-related state, including:
 /// - Forward mapping: UIDs to monitored nicknames
 /// - Reverse mapping: nicknames to monitoring UIDs
+/// - UIDs that manage their list through the legacy WATCH command
 pub struct MonitorManager {
     /// MONITOR: Nicknames being monitored by each UID.
     /// Key is UID, value is set of lowercase nicknames.
@@ -19,6 +20,10 @@ pub struct MonitorManager {
     /// MONITOR: Reverse mapping - who is monitoring each nickname.
     /// Key is lowercase nickname, value is set of UIDs monitoring it.
     pub monitoring: DashMap<String, DashSet<Uid>>,
+
+    /// WATCH: UIDs that have used WATCH. They share the MONITOR lists above
+    /// but are notified with RPL_LOGON/RPL_LOGOFF instead of 730/731.
+    pub watch_users: DashSet<Uid>,
 }
 
 impl MonitorManager {
@@ -27,6 +32,7 @@ impl MonitorManager {
         Self {
             monitors: DashMap::new(),
             monitoring: DashMap::new(),
+            watch_users: DashSet::new(),
         }
     }
 }
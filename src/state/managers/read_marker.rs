@@ -1,18 +1,19 @@
 use crate::db::Database;
 use dashmap::DashMap;
+use slirc_proto::irc_to_lower;
+use tracing::warn;
 
-/// Manages read markers (Unified Read State) for users.
+/// Manages read markers (draft/read-marker) for accounts.
 ///
-/// Tracks the last read timestamp for a user (account) in a given target (channel/query).
+/// Tracks the last read timestamp for an account in a given target
+/// (channel/query). Markers are cached in memory and written through to the
+/// database, so every client of the account sees the same marker, across
+/// restarts too.
 pub struct ReadMarkerManager {
-    /// In-memory cache of markers: (account, target) -> timestamp (nanos)
-    /// Key format: "account:target" or we could use tuple.
-    /// Using a simple string key map for now for simplicity.
-    /// Key: `account_name:target_name` (both lowercased)
-    markers: DashMap<String, i64>,
-
-    /// Database handle for persistence (TODO: Implement persistence)
-    #[allow(dead_code)]
+    /// Cached markers: (account, target), both casefolded -> timestamp (nanos).
+    markers: DashMap<(String, String), i64>,
+
+    /// Database handle for persistence.
     db: Option<Database>,
 }
 
@@ -25,24 +26,82 @@ impl ReadMarkerManager {
         }
     }
 
-    /// Update the read marker for an account in a target.
-    pub fn update_marker(&self, account: &str, target: &str, timestamp: i64) {
-        let key = format!("{}:{}", account.to_lowercase(), target.to_lowercase());
-        // Only update if newer? Or just overwrite?
-        // Usually we only advance read markers forward.
-        // But for now, simple overwrite or max.
+    /// Advance the read marker for an account in a target.
+    ///
+    /// Markers never move backwards. Returns the marker now stored, and
+    /// whether this call advanced it.
+    pub async fn update_marker(&self, account: &str, target: &str, timestamp: i64) -> (i64, bool) {
+        let stored = self.get_marker(account, target).await;
+        if stored.is_some_and(|ts| ts >= timestamp) {
+            return (stored.unwrap_or(timestamp), false);
+        }
 
-        self.markers
-            .entry(key)
+        let key = (irc_to_lower(account), irc_to_lower(target));
+        let marker = *self
+            .markers
+            .entry(key.clone())
             .and_modify(|ts| *ts = (*ts).max(timestamp))
             .or_insert(timestamp);
 
-        // TODO: Persist to DB asynchronously
+        if let Some(db) = &self.db
+            && let Err(e) = db.read_markers().advance(&key.0, &key.1, marker).await
+        {
+            warn!(account = %account, target = %target, error = %e, "Failed to persist read marker");
+        }
+        (marker, true)
     }
 
     /// Get the read marker for an account in a target.
-    pub fn get_marker(&self, account: &str, target: &str) -> Option<i64> {
-        let key = format!("{}:{}", account.to_lowercase(), target.to_lowercase());
-        self.markers.get(&key).map(|v| *v)
+    pub async fn get_marker(&self, account: &str, target: &str) -> Option<i64> {
+        let key = (irc_to_lower(account), irc_to_lower(target));
+        if let Some(ts) = self.markers.get(&key) {
+            return Some(*ts);
+        }
+
+        let db = self.db.as_ref()?;
+        match db.read_markers().get(&key.0, &key.1).await {
+            Ok(Some(ts)) => {
+                let ts = *self
+                    .markers
+                    .entry(key)
+                    .and_modify(|cached| *cached = (*cached).max(ts))
+                    .or_insert(ts);
+                Some(ts)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                warn!(account = %account, target = %target, error = %e, "Failed to load read marker");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn markers_only_advance_and_survive_restart() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.accounts()
+            .register("Alice", "hunter22", None)
+            .await
+            .unwrap();
+
+        let manager = ReadMarkerManager::new(Some(db.clone()));
+        assert_eq!(manager.get_marker("alice", "#chan").await, None);
+        assert_eq!(
+            manager.update_marker("alice", "#Chan", 20).await,
+            (20, true)
+        );
+        assert_eq!(
+            manager.update_marker("ALICE", "#chan", 10).await,
+            (20, false)
+        );
+
+        let restarted = ReadMarkerManager::new(Some(db));
+        assert_eq!(restarted.get_marker("Alice", "#CHAN").await, Some(20));
+        assert_eq!(restarted.get_marker("alice", "bob").await, None);
     }
 }
@@ -4,7 +4,7 @@
 //! security-related state from the main Matrix struct.
 
 use crate::config::SecurityConfig;
-use crate::db::{Database, Dline, Gline, Kline, Shun, Zline};
+use crate::db::{Database, Dline, Gline, Kline, Qline, SessionLimit, Shun, Zline};
 use crate::security::ip_deny::IpDenyList;
 use crate::security::spam::SpamDetectionService;
 use crate::security::{BanCache, RateLimitManager};
@@ -16,9 +16,10 @@ use tokio::sync::RwLock;
 /// Security management state.
 ///
 /// The SecurityManager holds all security-related state, including:
-/// - Rate limiting for flood protection
+/// - Rate limiting for flood protection, including per-IP session limits
 /// - Spam detection service
 /// - Active shuns (temporary bans)
+/// - Active Q-lines (reserved nicknames and channel names)
 /// - Ban cache for K-lines and G-lines
 /// - IP deny list for D-lines and Z-lines
 pub struct SecurityManager {
@@ -32,6 +33,11 @@ pub struct SecurityManager {
     /// Key is the mask pattern, value is the Shun record.
     pub shuns: DashMap<String, Shun>,
 
+    /// Active operator-set Q-lines cached in memory for NICK/JOIN checks.
+    /// Key is the mask pattern, value is the Qline record.
+    /// Config Q-lines live in `HotConfig::qlines` so REHASH can replace them.
+    pub qlines: DashMap<String, Qline>,
+
     /// In-memory ban cache for fast connection-time ban checks (K-lines and G-lines).
     pub ban_cache: BanCache,
 
@@ -50,6 +56,8 @@ pub struct SecurityManagerParams<'a> {
     pub dlines: Vec<Dline>,
     pub glines: Vec<Gline>,
     pub zlines: Vec<Zline>,
+    pub qlines: Vec<Qline>,
+    pub session_limits: Vec<SessionLimit>,
 }
 
 impl SecurityManager {
@@ -64,6 +72,8 @@ impl SecurityManager {
             dlines,
             glines,
             zlines,
+            qlines,
+            session_limits,
         } = params;
 
         // Build the shuns map
@@ -72,6 +82,11 @@ impl SecurityManager {
             shuns_map.insert(shun.mask.clone(), shun);
         }
 
+        let qlines_map = DashMap::with_capacity(qlines.len());
+        for qline in qlines {
+            qlines_map.insert(qline.mask.clone(), qline);
+        }
+
         // Initialize spam detector if enabled
         let spam_detector = if security_config.spam_detection_enabled {
             Some(Arc::new(RwLock::new(SpamDetectionService::new(
@@ -94,10 +109,22 @@ impl SecurityManager {
         // Build the ban cache (K-lines and G-lines only; IP bans handled by IpDenyList)
         let ban_cache = BanCache::load(klines, glines);
 
+        let rate_limiter = RateLimitManager::new(security_config.rate_limits.clone());
+        for limit in session_limits {
+            // Stored in CIDR form, single IPs included
+            match limit.mask.parse::<ipnet::IpNet>() {
+                Ok(net) => {
+                    rate_limiter.set_session_limit(net, limit.max_sessions, limit.expires_at)
+                }
+                Err(_) => tracing::warn!(mask = %limit.mask, "Ignoring invalid session limit mask"),
+            }
+        }
+
         Self {
-            rate_limiter: RateLimitManager::new(security_config.rate_limits.clone()),
+            rate_limiter,
             spam_detector,
             shuns: shuns_map,
+            qlines: qlines_map,
             ban_cache,
             ip_deny_list: std::sync::RwLock::new(ip_deny_list),
         }
@@ -5,8 +5,10 @@
 
 use crate::db::Database;
 use crate::history::HistoryProvider;
-use crate::services::{Service, chanserv, nickserv, playback};
-use crate::state::{User, UserModes};
+use crate::services::dedupe::NoticeDedupe;
+use crate::services::nickserv::mail::MailSender;
+use crate::services::{Service, botserv, chanserv, hostserv, nickserv, operserv, playback};
+use crate::state::{User, UserModes, UserStamps};
 use slirc_proto::sync::clock::HybridTimestamp;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
@@ -16,12 +18,21 @@ use uuid::Uuid;
 pub const NICKSERV_UID_SUFFIX: &str = "AAAAAA";
 /// Well-known UID suffix for ChanServ (always AAAAAB within the server's SID).
 pub const CHANSERV_UID_SUFFIX: &str = "AAAAAB";
+/// Well-known UID suffix for HostServ (always AAAAAC within the server's SID).
+pub const HOSTSERV_UID_SUFFIX: &str = "AAAAAC";
+/// Well-known UID suffix for OperServ (always AAAAAD within the server's SID).
+pub const OPERSERV_UID_SUFFIX: &str = "AAAAAD";
+/// Well-known UID suffix for BotServ (always AAAAAE within the server's SID).
+pub const BOTSERV_UID_SUFFIX: &str = "AAAAAE";
 
 /// Service management state.
 ///
 /// The ServiceManager holds all service-related state, including:
 /// - NickServ for nickname registration and identification
 /// - ChanServ for channel registration and access control
+/// - HostServ for vhost requests and assignment
+/// - OperServ for network bans, global notices and session limits
+/// - BotServ for channel bots
 /// - Extra services for dynamic service loading
 /// - History provider for message history
 pub struct ServiceManager {
@@ -31,6 +42,15 @@ pub struct ServiceManager {
     /// ChanServ service singleton.
     pub chanserv: chanserv::ChanServ,
 
+    /// HostServ service singleton.
+    pub hostserv: hostserv::HostServ,
+
+    /// OperServ service singleton.
+    pub operserv: operserv::OperServ,
+
+    /// BotServ service singleton.
+    pub botserv: botserv::BotServ,
+
     /// Message history provider (Opt-In Hybrid Architecture).
     pub history: Arc<dyn HistoryProvider>,
 
@@ -42,13 +62,33 @@ pub struct ServiceManager {
 
     /// UID for ChanServ (set during initialization).
     pub chanserv_uid: String,
+
+    /// UID for HostServ (set during initialization).
+    pub hostserv_uid: String,
+
+    /// UID for OperServ (set during initialization).
+    pub operserv_uid: String,
+
+    /// UID for BotServ (set during initialization).
+    pub botserv_uid: String,
+
+    /// Recently delivered service notices, for deduplication.
+    pub notice_dedupe: NoticeDedupe,
 }
 
 impl ServiceManager {
     /// Create a new ServiceManager with the given database and server SID.
-    pub fn new(db: Database, history: Arc<dyn HistoryProvider>, server_sid: &str) -> Self {
+    pub fn new(
+        db: Database,
+        history: Arc<dyn HistoryProvider>,
+        mailer: Option<Arc<dyn MailSender>>,
+        server_sid: &str,
+    ) -> Self {
         let nickserv_uid = format!("{}{}", server_sid, NICKSERV_UID_SUFFIX);
         let chanserv_uid = format!("{}{}", server_sid, CHANSERV_UID_SUFFIX);
+        let hostserv_uid = format!("{}{}", server_sid, HOSTSERV_UID_SUFFIX);
+        let operserv_uid = format!("{}{}", server_sid, OPERSERV_UID_SUFFIX);
+        let botserv_uid = format!("{}{}", server_sid, BOTSERV_UID_SUFFIX);
 
         let mut extra_services: HashMap<String, Box<dyn Service>> = HashMap::new();
         // Register Playback service
@@ -56,12 +96,19 @@ impl ServiceManager {
         extra_services.insert(playback.name().to_string(), Box::new(playback));
 
         Self {
-            nickserv: nickserv::NickServ::new(db.clone()),
-            chanserv: chanserv::ChanServ::new(db),
+            nickserv: nickserv::NickServ::new(db.clone(), mailer),
+            chanserv: chanserv::ChanServ::new(db.clone()),
+            hostserv: hostserv::HostServ::new(db.clone()),
+            operserv: operserv::OperServ::new(db.clone()),
+            botserv: botserv::BotServ::new(db),
             history,
             extra_services,
             nickserv_uid,
             chanserv_uid,
+            hostserv_uid,
+            operserv_uid,
+            botserv_uid,
+            notice_dedupe: NoticeDedupe::default(),
         }
     }
 
@@ -86,6 +133,7 @@ impl ServiceManager {
                 ip: "0.0.0.0".to_string(),
                 visible_host: server_name.to_string(),
                 session_id: Uuid::nil(), // Services don't have real sessions
+                server: None,
                 channels: HashSet::new(),
                 modes: UserModes {
                     service: true,
@@ -98,10 +146,12 @@ impl ServiceManager {
                 metadata: std::collections::HashMap::new(),
                 caps: HashSet::new(),
                 certfp: None,
+                gateway: None,
                 silence_list: HashSet::new(),
                 accept_list: HashSet::new(),
                 created_at: chrono::Utc::now().timestamp(),
                 last_modified: now,
+                stamps: UserStamps::default(),
                 last_active: std::sync::atomic::AtomicI64::new(
                     chrono::Utc::now().timestamp_millis(),
                 ),
@@ -115,6 +165,7 @@ impl ServiceManager {
                 ip: "0.0.0.0".to_string(),
                 visible_host: server_name.to_string(),
                 session_id: Uuid::nil(),
+                server: None,
                 channels: HashSet::new(),
                 modes: UserModes {
                     service: true,
@@ -127,10 +178,108 @@ impl ServiceManager {
                 metadata: std::collections::HashMap::new(),
                 caps: HashSet::new(),
                 certfp: None,
+                gateway: None,
+                silence_list: HashSet::new(),
+                accept_list: HashSet::new(),
+                created_at: chrono::Utc::now().timestamp(),
+                last_modified: now,
+                stamps: UserStamps::default(),
+                last_active: std::sync::atomic::AtomicI64::new(
+                    chrono::Utc::now().timestamp_millis(),
+                ),
+            },
+            User {
+                uid: self.hostserv_uid.clone(),
+                nick: "HostServ".to_string(),
+                user: "services".to_string(),
+                realname: "Virtual Host Service".to_string(),
+                host: server_name.to_string(),
+                ip: "0.0.0.0".to_string(),
+                visible_host: server_name.to_string(),
+                session_id: Uuid::nil(),
+                server: None,
+                channels: HashSet::new(),
+                modes: UserModes {
+                    service: true,
+                    registered: true,
+                    ..Default::default()
+                },
+                account: Some("HostServ".to_string()),
+                account_id: None,
+                away: None,
+                metadata: std::collections::HashMap::new(),
+                caps: HashSet::new(),
+                certfp: None,
+                gateway: None,
+                silence_list: HashSet::new(),
+                accept_list: HashSet::new(),
+                created_at: chrono::Utc::now().timestamp(),
+                last_modified: now,
+                stamps: UserStamps::default(),
+                last_active: std::sync::atomic::AtomicI64::new(
+                    chrono::Utc::now().timestamp_millis(),
+                ),
+            },
+            User {
+                uid: self.operserv_uid.clone(),
+                nick: "OperServ".to_string(),
+                user: "services".to_string(),
+                realname: "Network Operations Service".to_string(),
+                host: server_name.to_string(),
+                ip: "0.0.0.0".to_string(),
+                visible_host: server_name.to_string(),
+                session_id: Uuid::nil(),
+                server: None,
+                channels: HashSet::new(),
+                modes: UserModes {
+                    service: true,
+                    registered: true,
+                    ..Default::default()
+                },
+                account: Some("OperServ".to_string()),
+                account_id: None,
+                away: None,
+                metadata: std::collections::HashMap::new(),
+                caps: HashSet::new(),
+                certfp: None,
+                gateway: None,
+                silence_list: HashSet::new(),
+                accept_list: HashSet::new(),
+                created_at: chrono::Utc::now().timestamp(),
+                last_modified: now,
+                stamps: UserStamps::default(),
+                last_active: std::sync::atomic::AtomicI64::new(
+                    chrono::Utc::now().timestamp_millis(),
+                ),
+            },
+            User {
+                uid: self.botserv_uid.clone(),
+                nick: "BotServ".to_string(),
+                user: "services".to_string(),
+                realname: "Channel Bot Service".to_string(),
+                host: server_name.to_string(),
+                ip: "0.0.0.0".to_string(),
+                visible_host: server_name.to_string(),
+                session_id: Uuid::nil(),
+                server: None,
+                channels: HashSet::new(),
+                modes: UserModes {
+                    service: true,
+                    registered: true,
+                    ..Default::default()
+                },
+                account: Some("BotServ".to_string()),
+                account_id: None,
+                away: None,
+                metadata: std::collections::HashMap::new(),
+                caps: HashSet::new(),
+                certfp: None,
+                gateway: None,
                 silence_list: HashSet::new(),
                 accept_list: HashSet::new(),
                 created_at: chrono::Utc::now().timestamp(),
                 last_modified: now,
+                stamps: UserStamps::default(),
                 last_active: std::sync::atomic::AtomicI64::new(
                     chrono::Utc::now().timestamp_millis(),
                 ),
@@ -140,7 +289,11 @@ impl ServiceManager {
 
     /// Check if a UID belongs to a service.
     pub fn is_service_uid(&self, uid: &str) -> bool {
-        uid == self.nickserv_uid || uid == self.chanserv_uid
+        uid == self.nickserv_uid
+            || uid == self.chanserv_uid
+            || uid == self.hostserv_uid
+            || uid == self.operserv_uid
+            || uid == self.botserv_uid
     }
 
     /// Get service name by UID.
@@ -149,6 +302,12 @@ impl ServiceManager {
             Some("NickServ")
         } else if uid == self.chanserv_uid {
             Some("ChanServ")
+        } else if uid == self.hostserv_uid {
+            Some("HostServ")
+        } else if uid == self.operserv_uid {
+            Some("OperServ")
+        } else if uid == self.botserv_uid {
+            Some("BotServ")
         } else {
             None
         }
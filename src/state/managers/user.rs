@@ -4,27 +4,31 @@
 //! user-related state and logic from the main Matrix struct.
 
 use crate::state::client::SessionId;
-use crate::state::{Uid, UidGenerator, User, WhowasEntry, observer::StateObserver};
-use dashmap::DashMap;
+use crate::state::managers::user_index::UserIndex;
+use crate::state::{SendQ, Uid, UidGenerator, User, WhowasEntry, observer::StateObserver};
+use dashmap::{DashMap, DashSet};
 use slirc_proto::sync::clock::ServerId;
 use slirc_proto::{Command, Message, Prefix};
-use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use std::sync::Mutex;
-use std::time::Instant;
-use tokio::sync::{RwLock, mpsc};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
 
 /// Default maximum number of WHOWAS entries to keep per nickname.
 const DEFAULT_WHOWAS_GROUPSIZE: usize = 10;
 /// Default maximum unique nicks in WHOWAS history (LRU eviction).
 const DEFAULT_WHOWAS_MAXGROUPS: usize = 1000;
+/// Minimum time between +g "is messaging you" notices to one user.
+const CALLER_ID_NOTIFY_INTERVAL: Duration = Duration::from_secs(60);
 
 /// Manages all user-related state and behavior.
 ///
 /// The UserManager is responsible for:
 /// - Tracking connected users and their nicknames.
+/// - Indexing users by host, ident, account and IP for mask lookups.
 /// - Managing message senders for broadcasting.
 /// - Maintaining WHOWAS history for disconnected users.
 /// - Generating unique identifiers (UIDs).
@@ -33,7 +37,7 @@ const DEFAULT_WHOWAS_MAXGROUPS: usize = 1000;
 #[derive(Clone)]
 pub struct SessionSender {
     pub session_id: SessionId,
-    pub tx: mpsc::Sender<Arc<Message>>,
+    pub tx: SendQ,
 }
 
 pub struct UserManager {
@@ -44,9 +48,19 @@ pub struct UserManager {
     pub senders: DashMap<Uid, Vec<SessionSender>>,
     /// Per-session capabilities (IRCv3 caps negotiated by that session).
     pub session_caps: DashMap<SessionId, HashSet<String>>,
+    /// Per-session METADATA subscriptions (draft/metadata-2 keys).
+    pub metadata_subs: DashMap<SessionId, HashSet<String>>,
     pub whowas: DashMap<String, VecDeque<WhowasEntry>>,
     pub uid_gen: UidGenerator,
     pub enforce_timers: DashMap<Uid, Instant>,
+    /// When each +g user was last told that someone tried to message them.
+    pub caller_id_notified: DashMap<Uid, Instant>,
+    /// UIDs of users flagged `+S` (services and other pseudo-clients).
+    ///
+    /// Mirrors `modes.service` so counts can skip them without taking user locks.
+    pub services: DashSet<Uid>,
+    /// Users by host, ident, account and IP prefix, for mask-based lookups.
+    pub index: UserIndex,
     /// This server's name (required for snomask and whowas).
     pub server_name: String,
     /// This server's SID (TS6).
@@ -74,9 +88,13 @@ impl UserManager {
             nicks: DashMap::new(),
             senders: DashMap::new(),
             session_caps: DashMap::new(),
+            metadata_subs: DashMap::new(),
             whowas: DashMap::new(),
             uid_gen: UidGenerator::new(server_sid.clone()),
             enforce_timers: DashMap::new(),
+            caller_id_notified: DashMap::new(),
+            services: DashSet::new(),
+            index: UserIndex::default(),
             server_name,
             server_sid,
 
@@ -93,10 +111,39 @@ impl UserManager {
         }
     }
 
-    /// Get count of real users (excluding services/bots if tagged).
-    /// Currently returns total user count until service tagging is fully implemented.
-    pub async fn real_user_count(&self) -> usize {
-        self.users.len()
+    /// Get count of real users (excluding services and other `+S` pseudo-clients).
+    pub fn real_user_count(&self) -> usize {
+        self.users.len().saturating_sub(self.services.len())
+    }
+
+    /// Whether `uid` is a service or other `+S` pseudo-client.
+    pub fn is_service(&self, uid: &str) -> bool {
+        self.services.contains(uid)
+    }
+
+    /// Record a change of `uid`'s `+S` flag.
+    pub fn set_service(&self, uid: &str, is_service: bool) {
+        if is_service {
+            self.services.insert(uid.to_string());
+        } else {
+            self.services.remove(uid);
+        }
+    }
+
+    /// Count users per origin server, keyed by the SID prefix of their UID.
+    ///
+    /// Services are not counted.
+    pub fn count_by_server(&self) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for entry in self.users.iter() {
+            if self.is_service(entry.key()) {
+                continue;
+            }
+            if let Some(sid) = entry.key().get(..3) {
+                *counts.entry(sid.to_string()).or_insert(0) += 1;
+            }
+        }
+        counts
     }
 
     /// Configure WHOWAS limits from config.
@@ -192,6 +239,26 @@ impl UserManager {
         }
     }
 
+    /// Remove one session's sender, leaving any other sessions on the UID.
+    ///
+    /// The connection notices the missing registration on its next ERROR and closes.
+    pub fn take_session_sender(&self, uid: &str, session_id: SessionId) -> Option<SendQ> {
+        let mut entry = self.senders.get_mut(uid)?;
+        let pos = entry.iter().position(|s| s.session_id == session_id)?;
+        let sender = entry.remove(pos);
+        drop(entry);
+        self.session_caps.remove(&session_id);
+        self.metadata_subs.remove(&session_id);
+        Some(sender.tx)
+    }
+
+    /// Whether a session is still registered under a UID.
+    pub fn has_session(&self, uid: &str, session_id: SessionId) -> bool {
+        self.senders
+            .get(uid)
+            .is_some_and(|senders| senders.iter().any(|s| s.session_id == session_id))
+    }
+
     /// Get a cloned list of senders for a UID (for cases that need direct access).
     #[allow(dead_code)]
     pub fn get_senders_cloned(&self, uid: &str) -> Option<Vec<SessionSender>> {
@@ -200,7 +267,7 @@ impl UserManager {
 
     /// Get a cloned first sender for a UID (for backward compatibility).
     /// This returns the first sender if any exist.
-    pub fn get_first_sender(&self, uid: &str) -> Option<mpsc::Sender<Arc<Message>>> {
+    pub fn get_first_sender(&self, uid: &str) -> Option<SendQ> {
         self.senders
             .get(uid)
             .and_then(|r| r.value().first().map(|s| s.tx.clone()))
@@ -211,7 +278,7 @@ impl UserManager {
         &self,
         uid: &str,
         session_id: SessionId,
-        sender: mpsc::Sender<Arc<Message>>,
+        sender: SendQ,
         caps: HashSet<String>,
     ) {
         self.session_caps.insert(session_id, caps);
@@ -243,6 +310,54 @@ impl UserManager {
         self.session_caps.insert(session_id, caps);
     }
 
+    /// Whether a session subscribed to the METADATA `key`.
+    pub fn is_metadata_subscribed(&self, session_id: SessionId, key: &str) -> bool {
+        self.metadata_subs
+            .get(&session_id)
+            .is_some_and(|subs| subs.contains(key))
+    }
+
+    /// Whether a +g user may be told about a blocked message now.
+    ///
+    /// Records the notice, so at most one is sent per interval.
+    pub fn caller_id_notify_due(&self, uid: &str) -> bool {
+        let now = Instant::now();
+        let mut due = false;
+        self.caller_id_notified
+            .entry(uid.to_string())
+            .and_modify(|last| {
+                if now.duration_since(*last) >= CALLER_ID_NOTIFY_INTERVAL {
+                    *last = now;
+                    due = true;
+                }
+            })
+            .or_insert_with(|| {
+                due = true;
+                now
+            });
+        due
+    }
+
+    /// Remove a departed nick from every user's ACCEPT list.
+    pub async fn forget_accepted(&self, nick: &str) {
+        let nick_lower = slirc_proto::irc_to_lower(nick);
+        let users: Vec<_> = self.users.iter().map(|u| u.value().clone()).collect();
+        for user in users {
+            if user.read().await.accept_list.contains(&nick_lower) {
+                user.write().await.accept_list.remove(&nick_lower);
+            }
+        }
+    }
+
+    /// Drop every session sender of a UID, with its METADATA subscriptions.
+    pub fn remove_senders(&self, uid: &str) {
+        if let Some((_, sessions)) = self.senders.remove(uid) {
+            for sess in sessions {
+                self.metadata_subs.remove(&sess.session_id);
+            }
+        }
+    }
+
     /// Get capabilities for a specific session.
     pub fn get_session_caps(&self, session_id: SessionId) -> Option<HashSet<String>> {
         self.session_caps
@@ -257,13 +372,16 @@ impl UserManager {
 
     /// Notify the observer of a state change.
     /// `source` is the ServerId that originated the change, or None if local.
-    #[allow(clippy::collapsible_if)]
+    ///
+    /// Also re-files the user in the mask index, since every change that
+    /// reaches the network passes through here.
     pub async fn notify_observer(&self, uid: &str, source: Option<ServerId>) {
-        if let Some(observer) = &self.observer {
-            // Clone Arc to release DashMap lock before awaiting
-            let user_arc = self.users.get(uid).map(|r| r.value().clone());
-            if let Some(user_arc) = user_arc {
-                let user = user_arc.read().await;
+        // Clone Arc to release DashMap lock before awaiting
+        let user_arc = self.users.get(uid).map(|r| r.value().clone());
+        if let Some(user_arc) = user_arc {
+            let user = user_arc.read().await;
+            self.index.update(&user);
+            if let Some(observer) = &self.observer {
                 let crdt = user.to_crdt();
                 observer.on_user_update(&crdt, source);
             }
@@ -301,7 +419,7 @@ impl UserManager {
     /// Unlike `add_local_user`, this is synchronous and does NOT notify
     /// the observer because services are registered at startup before
     /// the server accepts connections.
-    pub fn register_service_user(&mut self, user: User) {
+    pub fn register_service_user(&self, user: User) {
         let uid = user.uid.clone();
         let nick_lower = slirc_proto::irc_to_lower(&user.nick);
 
@@ -309,6 +427,8 @@ impl UserManager {
             .entry(nick_lower)
             .or_insert_with(Vec::new)
             .push(uid.clone());
+        self.set_service(&uid, user.modes.service);
+        self.index.update(&user);
         self.users.insert(uid.clone(), Arc::new(RwLock::new(user)));
     }
 
@@ -410,6 +530,7 @@ impl UserManager {
             let old_nick_lower = slirc_proto::irc_to_lower(&user.nick);
             let old_oper = user.modes.oper;
             let old_invisible = user.modes.invisible;
+            let old_service = user.modes.service;
             let is_local = user.uid.starts_with(&self.server_sid);
 
             user.merge_crdt(crdt);
@@ -417,9 +538,23 @@ impl UserManager {
             let new_nick_lower = slirc_proto::irc_to_lower(&user.nick);
             let new_oper = user.modes.oper;
             let new_invisible = user.modes.invisible;
+            let new_service = user.modes.service;
+
+            if old_service != new_service {
+                self.set_service(&uid, new_service);
+            }
 
             // Update stats for mode changes
             if let Some(stats) = &self.stats_manager {
+                // Services are not counted as users
+                if !is_local && old_service != new_service {
+                    if new_service {
+                        stats.remote_user_disconnected();
+                    } else {
+                        stats.remote_user_connected();
+                    }
+                }
+
                 // Oper change
                 if old_oper != new_oper {
                     if new_oper {
@@ -461,20 +596,24 @@ impl UserManager {
                     .push(uid.clone());
             }
         } else {
-            let user = User::from_crdt(crdt);
+            let mut user = User::from_crdt(crdt);
+            user.server = source.clone();
             let nick_lower = slirc_proto::irc_to_lower(&user.nick);
             let is_remote = source.is_some();
             let is_oper = user.modes.oper;
+            let is_service = user.modes.service;
 
             self.nicks
                 .entry(nick_lower)
                 .or_insert_with(Vec::new)
                 .push(uid.clone());
+            self.set_service(&uid, is_service);
             self.users.insert(uid.clone(), Arc::new(RwLock::new(user)));
 
-            // Update stats for new remote users
+            // Update stats for new remote users (services are not counted)
             if let Some(stats) = &self.stats_manager
                 && is_remote
+                && !is_service
             {
                 stats.remote_user_connected();
                 if is_oper {
@@ -491,18 +630,25 @@ impl UserManager {
     ///
     /// Automatically detects local vs. remote users by UID prefix and updates
     /// StatsManager accordingly.
-    pub async fn kill_user(&self, uid: &str, reason: &str, _source: Option<ServerId>) {
+    ///
+    /// A `source` marks the removal as coming from that peer, which keeps the
+    /// observer from echoing a QUIT back onto the network.
+    pub async fn kill_user(&self, uid: &str, reason: &str, source: Option<ServerId>) {
         if let Some((_, user_arc)) = self.users.remove(uid) {
             let user = user_arc.read().await;
             let nick_lower = slirc_proto::irc_to_lower(&user.nick);
             let is_oper = user.modes.oper;
             let is_invisible = user.modes.invisible;
+            let is_service = user.modes.service;
 
             // Auto-detect local vs remote by UID prefix (first 3 chars = SID)
             let is_local = uid.starts_with(&self.server_sid);
 
-            // Update stats based on user locality
-            if let Some(stats) = &self.stats_manager {
+            // Update stats based on user locality. Services are never counted,
+            // so there is nothing to undo for them.
+            if let Some(stats) = &self.stats_manager
+                && !is_service
+            {
                 if is_local {
                     stats.user_disconnected();
                     if is_invisible {
@@ -528,14 +674,16 @@ impl UserManager {
                 }
             }
 
-            self.senders.remove(uid);
+            self.remove_senders(uid);
+            self.services.remove(uid);
+            self.index.remove(uid);
 
             // Record WHOWAS
             self.record_whowas(&user.nick, &user.user, &user.host, &user.realname);
 
             // Notify observer
             if let Some(observer) = &self.observer {
-                observer.on_user_quit(uid, reason, None);
+                observer.on_user_quit(uid, reason, source);
             }
         }
     }
@@ -592,6 +740,20 @@ impl UserManager {
         }
     }
 
+    /// Send a server notice to every locally connected user.
+    pub async fn send_notice_to_local_users(&self, message: &str) {
+        let notice_msg = Arc::new(Message {
+            tags: None,
+            prefix: Some(Prefix::ServerName(self.server_name.clone())),
+            command: Command::NOTICE("*".to_string(), format!("*** Notice -- {}", message)),
+        });
+
+        let uids: Vec<_> = self.senders.iter().map(|e| e.key().clone()).collect();
+        for uid in uids {
+            self.send_to_uid(&uid, notice_msg.clone()).await;
+        }
+    }
+
     /// Increment the unregistered connections counter.
     ///
     /// Call this when a new connection is established (before registration).
@@ -697,6 +859,52 @@ impl UserManager {
             lru.retain(|n| !removed_nicks.contains(n));
         }
     }
+
+    /// Export WHOWAS history grouped by nick, oldest group first.
+    ///
+    /// Used by the warm-boot snapshot; the order lets [`Self::restore_whowas`]
+    /// rebuild the LRU tracker.
+    pub fn export_whowas(&self) -> Vec<(String, Vec<WhowasEntry>)> {
+        let order: Vec<String> = match self.whowas_lru.lock() {
+            Ok(lru) => lru.iter().cloned().collect(),
+            Err(_) => self.whowas.iter().map(|e| e.key().clone()).collect(),
+        };
+
+        order
+            .into_iter()
+            .filter_map(|nick_lower| {
+                let entries = self.whowas.get(&nick_lower)?;
+                Some((nick_lower, entries.iter().cloned().collect()))
+            })
+            .collect()
+    }
+
+    /// Restore WHOWAS history exported by [`Self::export_whowas`].
+    ///
+    /// Nicks that already have history are left alone. Group count and size
+    /// limits are applied as if the entries had just been recorded.
+    pub fn restore_whowas(&self, groups: Vec<(String, Vec<WhowasEntry>)>) {
+        let Ok(mut lru) = self.whowas_lru.lock() else {
+            return;
+        };
+
+        for (nick_lower, mut entries) in groups {
+            if entries.is_empty() || self.whowas.contains_key(&nick_lower) {
+                continue;
+            }
+            while lru.len() >= self.whowas_maxgroups {
+                match lru.pop_front() {
+                    Some(oldest) => {
+                        self.whowas.remove(&oldest);
+                    }
+                    None => break,
+                }
+            }
+            entries.truncate(self.whowas_groupsize);
+            lru.push_back(nick_lower.clone());
+            self.whowas.insert(nick_lower, entries.into());
+        }
+    }
 }
 
 #[cfg(test)]
@@ -859,4 +1067,36 @@ mod tests {
             "Global user count should be 0 after remote kill"
         );
     }
+
+    #[tokio::test]
+    async fn test_services_are_not_counted() {
+        use crate::state::managers::stats::StatsManager;
+
+        let stats = Arc::new(StatsManager::new());
+        let mut manager = UserManager::new("001".to_string(), "test.server".to_string());
+        manager.set_stats_manager(stats.clone());
+
+        let sid_remote = ServerId::new("00A");
+        let ts = HybridTimestamp::new(100, 0, &sid_remote);
+        let mut service = create_user("00AAAAAAS", "Bridge", ts);
+        service.modes.service.update(true, ts.increment());
+        manager
+            .merge_user_crdt(service, Some(sid_remote.clone()))
+            .await;
+        manager
+            .merge_user_crdt(create_user("00AAAAA01", "Remote", ts), Some(sid_remote))
+            .await;
+
+        assert!(manager.is_service("00AAAAAAS"));
+        assert!(!manager.is_service("00AAAAA01"));
+        assert_eq!(stats.global_users(), 1);
+        assert_eq!(manager.real_user_count(), 1);
+        assert_eq!(manager.count_by_server().get("00A"), Some(&1));
+
+        manager
+            .kill_user("00AAAAAAS", "Gone", Some(ServerId::new("00A")))
+            .await;
+        assert!(!manager.is_service("00AAAAAAS"));
+        assert_eq!(stats.global_users(), 1);
+    }
 }
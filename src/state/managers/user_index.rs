@@ -0,0 +1,289 @@
+//! Inverted index from user attributes to UIDs.
+//!
+//! Mask-based operations (ban enforcement, AKICK sweeps, WHO masks) would
+//! otherwise lock and test every user on the network. The index files each
+//! user under its hosts, ident, realname, account and IP prefix, so a mask
+//! with a literal component narrows the search to one bucket. Candidates still
+//! have to be matched against the full mask; masks without a usable literal
+//! part fall back to a scan of all users.
+
+use crate::state::{Uid, User};
+use dashmap::DashMap;
+use slirc_proto::irc_to_lower;
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+/// Keys a user is currently filed under, kept so it can be unfiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IndexKeys {
+    hosts: Vec<String>,
+    ident: String,
+    realname: String,
+    account: Option<String>,
+    ip_prefixes: Vec<String>,
+}
+
+impl IndexKeys {
+    fn of(user: &User) -> Self {
+        let mut hosts = vec![irc_to_lower(&user.host)];
+        for host in [&user.visible_host, &user.ip] {
+            let host = irc_to_lower(host);
+            if !hosts.contains(&host) {
+                hosts.push(host);
+            }
+        }
+
+        let mut ip_prefixes = Vec::new();
+        for addr in [&user.ip, &user.host] {
+            if let Some(prefix) = addr.parse::<IpAddr>().ok().map(|ip| ip_prefix(&ip))
+                && !ip_prefixes.contains(&prefix)
+            {
+                ip_prefixes.push(prefix);
+            }
+        }
+
+        Self {
+            hosts,
+            ident: irc_to_lower(&user.user),
+            realname: irc_to_lower(&user.realname),
+            account: user.account.as_deref().map(irc_to_lower),
+            ip_prefixes,
+        }
+    }
+}
+
+/// Bucket key for an address: its /16 (IPv4) or /32 (IPv6) network.
+fn ip_prefix(ip: &IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            format!("{a}.{b}")
+        }
+        IpAddr::V6(v6) => {
+            let segments = v6.segments();
+            format!("{:x}:{:x}", segments[0], segments[1])
+        }
+    }
+}
+
+/// Bucket key for the addresses a host mask can match, if they all share
+/// one: `a.b.*`-style IPv4 wildcards and CIDR ranges at least as narrow as
+/// the bucket.
+fn mask_ip_prefix(host: &str) -> Option<String> {
+    if let Some((network, bits)) = host.split_once('/') {
+        let ip: IpAddr = network.parse().ok()?;
+        let bits: u8 = bits.parse().ok()?;
+        let min_bits = if ip.is_ipv4() { 16 } else { 32 };
+        return (bits >= min_bits).then(|| ip_prefix(&ip));
+    }
+
+    // Only digits and wildcards, so no hostname can match it
+    if !host
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '.' | '*' | '?'))
+    {
+        return None;
+    }
+    let mut octets = host.splitn(3, '.');
+    let (a, b) = (octets.next()?, octets.next()?);
+    octets.next()?;
+    let a: u8 = a.parse().ok()?;
+    let b: u8 = b.parse().ok()?;
+    Some(format!("{a}.{b}"))
+}
+
+fn is_literal(s: &str) -> bool {
+    !s.is_empty() && !s.contains(['*', '?'])
+}
+
+/// Inverted index over connected users.
+#[derive(Debug, Default)]
+pub struct UserIndex {
+    keys: DashMap<Uid, IndexKeys>,
+    by_host: DashMap<String, HashSet<Uid>>,
+    by_ident: DashMap<String, HashSet<Uid>>,
+    by_realname: DashMap<String, HashSet<Uid>>,
+    by_account: DashMap<String, HashSet<Uid>>,
+    by_ip_prefix: DashMap<String, HashSet<Uid>>,
+}
+
+impl UserIndex {
+    /// File `user` under its current attributes, replacing older entries.
+    pub fn update(&self, user: &User) {
+        let keys = IndexKeys::of(user);
+        if self.keys.get(&user.uid).is_some_and(|old| *old == keys) {
+            return;
+        }
+        self.remove(&user.uid);
+
+        for host in &keys.hosts {
+            Self::file(&self.by_host, host, &user.uid);
+        }
+        Self::file(&self.by_ident, &keys.ident, &user.uid);
+        Self::file(&self.by_realname, &keys.realname, &user.uid);
+        if let Some(account) = &keys.account {
+            Self::file(&self.by_account, account, &user.uid);
+        }
+        for prefix in &keys.ip_prefixes {
+            Self::file(&self.by_ip_prefix, prefix, &user.uid);
+        }
+        self.keys.insert(user.uid.clone(), keys);
+    }
+
+    /// Drop `uid` from the index.
+    pub fn remove(&self, uid: &str) {
+        let Some((_, keys)) = self.keys.remove(uid) else {
+            return;
+        };
+        for host in &keys.hosts {
+            Self::unfile(&self.by_host, host, uid);
+        }
+        Self::unfile(&self.by_ident, &keys.ident, uid);
+        Self::unfile(&self.by_realname, &keys.realname, uid);
+        if let Some(account) = &keys.account {
+            Self::unfile(&self.by_account, account, uid);
+        }
+        for prefix in &keys.ip_prefixes {
+            Self::unfile(&self.by_ip_prefix, prefix, uid);
+        }
+    }
+
+    /// UIDs logged in to `account`.
+    pub fn by_account(&self, account: &str) -> Vec<Uid> {
+        Self::bucket(&self.by_account, &irc_to_lower(account))
+    }
+
+    /// UIDs that may match a `[nick!]user@host` mask, or `None` if the mask
+    /// has no literal part to narrow on and every user must be checked.
+    pub fn candidates(&self, mask: &str) -> Option<Vec<Uid>> {
+        let (userpart, host) = mask.rsplit_once('@')?;
+        let ident = userpart
+            .rsplit_once('!')
+            .map_or(userpart, |(_, ident)| ident);
+
+        if is_literal(host) {
+            return Some(Self::bucket(&self.by_host, &irc_to_lower(host)));
+        }
+        if let Some(prefix) = mask_ip_prefix(host) {
+            return Some(Self::bucket(&self.by_ip_prefix, &prefix));
+        }
+        if is_literal(ident) {
+            return Some(Self::bucket(&self.by_ident, &irc_to_lower(ident)));
+        }
+        None
+    }
+
+    /// UIDs whose host or IP may match a D/Z-line `pattern`, or `None` to
+    /// check every user.
+    pub fn host_candidates(&self, pattern: &str) -> Option<Vec<Uid>> {
+        if is_literal(pattern) && !pattern.contains('/') {
+            return Some(Self::bucket(&self.by_host, &irc_to_lower(pattern)));
+        }
+        mask_ip_prefix(pattern).map(|prefix| Self::bucket(&self.by_ip_prefix, &prefix))
+    }
+
+    /// UIDs whose host, IP, ident or realname equals `term`, or `None` if
+    /// `term` is a pattern, CIDR or hostmask. Nicks are not indexed here;
+    /// callers look those up in the nick map.
+    pub fn literal_candidates(&self, term: &str) -> Option<Vec<Uid>> {
+        if !is_literal(term) || term.contains(['/', '@', '!']) {
+            return None;
+        }
+        let term = irc_to_lower(term);
+        let mut uids: HashSet<Uid> = HashSet::new();
+        for map in [&self.by_host, &self.by_ident, &self.by_realname] {
+            uids.extend(Self::bucket(map, &term));
+        }
+        Some(uids.into_iter().collect())
+    }
+
+    fn bucket(map: &DashMap<String, HashSet<Uid>>, key: &str) -> Vec<Uid> {
+        map.get(key)
+            .map(|uids| uids.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn file(map: &DashMap<String, HashSet<Uid>>, key: &str, uid: &str) {
+        map.entry(key.to_string())
+            .or_default()
+            .insert(uid.to_string());
+    }
+
+    fn unfile(map: &DashMap<String, HashSet<Uid>>, key: &str, uid: &str) {
+        if let Some(mut uids) = map.get_mut(key) {
+            uids.remove(uid);
+            if uids.is_empty() {
+                drop(uids);
+                map.remove_if(key, |_, uids| uids.is_empty());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::UserParams;
+    use slirc_proto::sync::clock::{HybridTimestamp, ServerId};
+    use std::collections::HashSet;
+
+    fn user(uid: &str, ident: &str, host: &str, ip: &str) -> User {
+        User::new(UserParams {
+            uid: uid.to_string(),
+            nick: format!("n{uid}"),
+            user: ident.to_string(),
+            realname: "Test".to_string(),
+            host: host.to_string(),
+            ip: ip.to_string(),
+            cloak_secret: "secret".to_string(),
+            cloak_suffix: "ip".to_string(),
+            caps: HashSet::new(),
+            certfp: None,
+            last_modified: HybridTimestamp::now(&ServerId::new("001".to_string())),
+            session_id: uuid::Uuid::new_v4(),
+        })
+    }
+
+    #[test]
+    fn test_masks_narrow_to_matching_buckets() {
+        let index = UserIndex::default();
+        let mut alice = user("001AAAAAA", "alice", "host.example.com", "192.168.1.10");
+        alice.account = Some("Alice".to_string());
+        index.update(&alice);
+        index.update(&user("001AAAAAB", "bob", "other.example.net", "10.0.0.5"));
+
+        assert_eq!(
+            index.candidates("*!*@HOST.example.com"),
+            Some(vec!["001AAAAAA".to_string()])
+        );
+        assert_eq!(
+            index.candidates("*@192.168.*"),
+            Some(vec!["001AAAAAA".to_string()])
+        );
+        assert_eq!(
+            index.candidates("bob@*.net"),
+            Some(vec!["001AAAAAB".to_string()])
+        );
+        assert_eq!(
+            index.host_candidates("10.0.0.0/24"),
+            Some(vec!["001AAAAAB".to_string()])
+        );
+        assert_eq!(index.by_account("alice"), vec!["001AAAAAA".to_string()]);
+        assert_eq!(
+            index.literal_candidates("10.0.0.5"),
+            Some(vec!["001AAAAAB".to_string()])
+        );
+
+        // Nothing literal to narrow on
+        assert_eq!(index.candidates("*@*.com"), None);
+        assert_eq!(index.host_candidates("10.0.0.0/8"), None);
+        assert_eq!(index.literal_candidates("bo*"), None);
+
+        // Re-filed on change, forgotten on removal
+        alice.account = None;
+        index.update(&alice);
+        assert!(index.by_account("alice").is_empty());
+        index.remove("001AAAAAB");
+        assert_eq!(index.candidates("bob@*.net"), Some(Vec::new()));
+    }
+}
@@ -35,7 +35,7 @@ use crate::state::client::SessionId;
 use crate::state::managers::client::ClientManager;
 use crate::state::{
     ChannelManager, LifecycleManager, MonitorManager, SecurityManager, SecurityManagerParams,
-    ServiceManager, SyncManager, Uid, UserManager,
+    SendQ, SendQReceiver, ServiceManager, SyncManager, Uid, UserManager,
 };
 use parking_lot::RwLock;
 use slirc_proto::sync::clock::ServerId;
@@ -43,6 +43,7 @@ use slirc_proto::sync::clock::ServerId;
 use crate::config::{Config, OperBlock, SecurityConfig, ServerConfig};
 use crate::handlers::{cleanup_monitors, notify_monitors_offline};
 use crate::state::actor::ChannelEvent;
+use crate::state::observer::{DEFAULT_OBSERVER_QUEUE, MetricsObserver, ObserverBus, StateObserver};
 use slirc_proto::Message;
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -76,6 +77,10 @@ pub struct Matrix {
     /// Sync management state (Innovation 2: Distributed Server Linking).
     pub sync_manager: SyncManager,
 
+    /// State change fan-out (sync layer, metrics, and any other subscribers).
+    /// Publish local changes here rather than to `sync_manager` directly.
+    pub observers: Arc<ObserverBus>,
+
     /// Runtime statistics (user/channel counts, uptime).
     pub stats_manager: Arc<crate::state::managers::stats::StatsManager>,
 
@@ -99,10 +104,20 @@ pub struct Matrix {
     pub hot_config: RwLock<HotConfig>,
 
     /// Router channel for remote messages.
-    pub router_tx: mpsc::Sender<Arc<Message>>,
+    pub router_tx: SendQ,
 
     /// Database handle for server-wide persistence.
     pub db: crate::db::Database,
+
+    /// CRDT journal, set on startup when `[journal]` is enabled.
+    pub journal: std::sync::OnceLock<Arc<crate::sync::store::CrdtJournal>>,
+
+    /// Channel to the running gateway, set when it binds; REHASH sends the
+    /// new listener set through it.
+    pub listener_reload: std::sync::OnceLock<mpsc::Sender<crate::network::ListenerReload>>,
+
+    /// Reverse DNS for connecting clients; `None` when `[server.rdns]` is off.
+    pub rdns: Option<crate::network::ReverseDns>,
 }
 
 /// Configuration accessible to handlers via Matrix.
@@ -110,22 +125,32 @@ pub struct Matrix {
 pub struct MatrixConfig {
     /// Server configuration (name, network, password, etc.).
     pub server: ServerConfig,
-    /// Operator blocks.
-    pub oper_blocks: Vec<OperBlock>,
     /// Security configuration (cloaking, rate limiting).
     pub security: SecurityConfig,
     /// Account registration configuration.
     pub account_registration: crate::config::AccountRegistrationConfig,
+    /// ChanServ channel registration settings.
+    pub chanserv: crate::config::ChanServConfig,
+    /// External services package configuration.
+    pub services: crate::config::ServicesConfig,
     /// Multiclient/bouncer configuration.
     pub multiclient: crate::config::MulticlientConfig,
     /// Command output limits (WHO, LIST, NAMES).
     pub limits: crate::config::LimitsConfig,
+    /// Channel creation policy and empty-channel reaping.
+    pub channels: crate::config::ChannelsConfig,
+    /// Client-only tag relay policy.
+    pub client_tags: crate::config::ClientTagsConfig,
+    /// Metadata limits and key visibility.
+    pub metadata: crate::config::MetadataConfig,
     /// History configuration (Innovation 5: Event-Sourced History).
     pub history: crate::config::HistoryConfig,
     /// Link blocks for server peering.
     pub links: Vec<crate::config::LinkBlock>,
-    /// TLS configuration (for STS capability advertising).
-    pub tls: Option<crate::config::TlsConfig>,
+    /// Warm-boot state snapshot configuration.
+    pub snapshot: crate::config::SnapshotConfig,
+    /// CRDT journal configuration.
+    pub journal: crate::config::JournalConfig,
 }
 
 /// Hot-reloadable configuration fields that can be atomically swapped via REHASH.
@@ -138,10 +163,30 @@ pub struct HotConfig {
     pub motd_lines: Vec<String>,
     /// Operator blocks (for oper authentication).
     pub oper_blocks: Vec<OperBlock>,
+    /// Operator classes (privileges checked by the capability authority).
+    pub oper_classes: Vec<crate::config::OperClassBlock>,
     /// Admin info lines (RPL_ADMINLOC1, RPL_ADMINLOC2, RPL_ADMINEMAIL).
     pub admin_info: (Option<String>, Option<String>, Option<String>),
     /// ZNC playback max messages per target (history.znc-maxmessages).
     pub znc_maxmessages: Option<usize>,
+    /// Configured Q-lines (reserved nicknames and channel names).
+    pub qlines: Vec<crate::config::QlineBlock>,
+    /// Network name (server.network), advertised as NETWORK.
+    pub network: String,
+    /// MONITOR/WATCH list size (limits.max-monitor-targets).
+    pub max_monitor_targets: usize,
+    /// HMAC secret for host cloaks of new connections (security.cloak_secret).
+    pub cloak_secret: String,
+    /// Suffix for cloaked IP addresses (security.cloak_suffix).
+    pub cloak_suffix: String,
+    /// Policy for clients on Tor listeners.
+    pub tor: crate::config::TorConfig,
+    /// Client connection classes, looked up when a client registers.
+    pub classes: Vec<crate::config::ClassBlock>,
+    /// STS policy advertised in CAP LS (tls.sts).
+    pub sts: Option<crate::config::StsConfig>,
+    /// RPL_ISUPPORT tokens built from the values above.
+    pub isupport: Vec<String>,
 }
 
 impl HotConfig {
@@ -151,12 +196,26 @@ impl HotConfig {
             description: config.server.description.clone(),
             motd_lines: config.motd.load_lines(),
             oper_blocks: config.oper.clone(),
+            oper_classes: config.oper_classes.clone(),
             admin_info: (
                 config.server.admin_info1.clone(),
                 config.server.admin_info2.clone(),
                 config.server.admin_email.clone(),
             ),
             znc_maxmessages: config.history.znc_maxmessages,
+            qlines: config.qlines.clone(),
+            network: config.server.network.clone(),
+            max_monitor_targets: config.limits.max_monitor_targets,
+            cloak_secret: config.security.cloak_secret.clone(),
+            cloak_suffix: config.security.cloak_suffix.clone(),
+            tor: config.tor.clone(),
+            classes: config.classes.clone(),
+            sts: config.tls.as_ref().and_then(|tls| tls.sts.clone()),
+            isupport: crate::state::isupport::tokens(
+                &config.server.network,
+                config.server.casemapping,
+                config.limits.max_monitor_targets,
+            ),
         }
     }
 }
@@ -165,13 +224,10 @@ impl HotConfig {
 #[derive(Debug, Clone)]
 pub struct ServerInfo {
     pub name: String,
-    pub network: String,
     pub sid: String,
     pub description: String,
     #[allow(dead_code)]
     pub created: i64,
-    /// MOTD lines loaded from config file.
-    pub motd_lines: Vec<String>,
     /// Idle timeout configuration for ping/pong keepalive.
     pub idle_timeouts: crate::config::IdleTimeoutsConfig,
 }
@@ -190,6 +246,8 @@ pub struct MatrixParams<'a> {
     pub dlines: Vec<crate::db::Dline>,
     pub glines: Vec<crate::db::Gline>,
     pub zlines: Vec<crate::db::Zline>,
+    pub qlines: Vec<crate::db::Qline>,
+    pub session_limits: Vec<crate::db::SessionLimit>,
     pub disconnect_tx: mpsc::Sender<(Uid, String)>,
     /// Optional always-on store for bouncer persistence.
     pub always_on_store: Option<std::sync::Arc<crate::db::AlwaysOnStore>>,
@@ -200,17 +258,19 @@ struct UserDisconnectInfo {
     nick: String,
     user: String,
     host: String,
+    visible_host: String,
     realname: String,
     channels: Vec<String>,
     session_id: SessionId,
     account: Option<String>,
+    server: Option<ServerId>,
     is_invisible: bool,
     is_oper: bool,
 }
 
 impl Matrix {
     /// Create a new Matrix with the given server configuration.
-    pub fn new(params: MatrixParams<'_>) -> (Self, mpsc::Receiver<Arc<Message>>) {
+    pub fn new(params: MatrixParams<'_>) -> (Self, SendQReceiver) {
         let MatrixParams {
             config,
             config_path,
@@ -223,6 +283,8 @@ impl Matrix {
             dlines,
             glines,
             zlines,
+            qlines,
+            session_limits,
             disconnect_tx,
             always_on_store,
         } = params;
@@ -245,7 +307,13 @@ impl Matrix {
             config.links.clone(),
             &config.security.rate_limits,
         );
-        let sync_manager_arc = Arc::new(sync_manager);
+        let observers = Arc::new(ObserverBus::new());
+        observers.subscribe(
+            "sync",
+            Arc::new(sync_manager.clone()),
+            DEFAULT_OBSERVER_QUEUE,
+        );
+        observers.subscribe("metrics", Arc::new(MetricsObserver), DEFAULT_OBSERVER_QUEUE);
         let mut user_manager =
             UserManager::new(config.server.sid.clone(), config.server.name.clone());
         user_manager.configure_whowas(
@@ -253,7 +321,7 @@ impl Matrix {
             config.limits.whowas_groupsize,
             config.limits.whowas_entry_ttl_days,
         );
-        user_manager.set_observer(sync_manager_arc.clone());
+        user_manager.set_observer(observers.clone());
 
         let stats_manager = Arc::new(crate::state::managers::stats::StatsManager::new());
         user_manager.set_stats_manager(stats_manager.clone());
@@ -262,18 +330,24 @@ impl Matrix {
             registered_channel_names,
             stats_manager.clone(),
         );
-        channel_manager.set_observer(sync_manager_arc.clone());
+        channel_manager.set_observer(observers.clone());
 
         // Create ServiceManager with server SID for service UIDs
-        let service_manager = ServiceManager::new(db.clone(), history, &config.server.sid);
-
-        // Register service pseudoclients in UserManager
-        let service_users = service_manager.create_service_users(&config.server.name, &server_id);
-        for user in service_users {
-            user_manager.register_service_user(user);
+        let mailer =
+            crate::services::nickserv::mail::from_config(&config.account_registration.mail);
+        let service_manager = ServiceManager::new(db.clone(), history, mailer, &config.server.sid);
+
+        // Register service pseudoclients in UserManager; an external services
+        // package introduces its own over the link instead
+        if config.services.builtin() {
+            let service_users =
+                service_manager.create_service_users(&config.server.name, &server_id);
+            for user in service_users {
+                user_manager.register_service_user(user);
+            }
         }
 
-        let (router_tx, router_rx) = mpsc::channel(1000);
+        let (router_tx, router_rx) = SendQ::bounded(1000);
 
         // Create ClientManager with optional always-on store
         let client_manager = match always_on_store {
@@ -297,12 +371,14 @@ impl Matrix {
                     dlines,
                     glines,
                     zlines,
+                    qlines,
+                    session_limits,
                 }),
                 service_manager,
                 monitor_manager: MonitorManager::new(),
                 lifecycle_manager: LifecycleManager::new(disconnect_tx),
-                sync_manager: Arc::try_unwrap(sync_manager_arc)
-                    .unwrap_or_else(|arc| (*arc).clone()),
+                sync_manager,
+                observers,
                 stats_manager,
                 read_marker_manager: crate::state::managers::read_marker::ReadMarkerManager::new(
                     Some(db.clone()),
@@ -310,29 +386,39 @@ impl Matrix {
 
                 server_info: ServerInfo {
                     name: config.server.name.clone(),
-                    network: config.server.network.clone(),
                     sid: config.server.sid.clone(),
                     description: config.server.description.clone(),
                     created: now,
-                    motd_lines: config.motd.load_lines(),
                     idle_timeouts: config.server.idle_timeouts.clone(),
                 },
                 server_id,
                 config: MatrixConfig {
                     server: config.server.clone(),
-                    oper_blocks: config.oper.clone(),
                     security: config.security.clone(),
                     account_registration: config.account_registration.clone(),
+                    chanserv: config.chanserv.clone(),
+                    services: config.services.clone(),
                     multiclient: config.multiclient.clone(),
                     limits: config.limits.clone(),
+                    channels: config.channels.clone(),
+                    client_tags: config.client_tags.clone(),
+                    metadata: config.metadata.clone(),
                     history: config.history.clone(),
                     links: config.links.clone(),
-                    tls: config.tls.clone(),
+                    snapshot: config.snapshot.clone(),
+                    journal: config.journal.clone(),
                 },
                 config_path,
                 hot_config: RwLock::new(HotConfig::from_config(config)),
                 router_tx,
                 db,
+                journal: std::sync::OnceLock::new(),
+                listener_reload: std::sync::OnceLock::new(),
+                rdns: config
+                    .server
+                    .rdns
+                    .enabled
+                    .then(|| crate::network::ReverseDns::new(&config.server.rdns)),
             },
             router_rx,
         )
@@ -344,7 +430,7 @@ impl Matrix {
         &self,
         uid: &str,
         session_id: crate::state::client::SessionId,
-        sender: mpsc::Sender<Arc<Message>>,
+        sender: SendQ,
         caps: std::collections::HashSet<String>,
     ) {
         self.user_manager
@@ -356,6 +442,32 @@ impl Matrix {
         slirc_proto::sync::clock::HybridTimestamp::now(&self.server_id)
     }
 
+    /// Check a nickname or channel name against config and operator Q-lines.
+    ///
+    /// Returns the reservation reason if `name` is reserved.
+    pub fn find_qline(&self, name: &str) -> Option<String> {
+        use crate::db::Qline;
+
+        if let Some(block) = self
+            .hot_config
+            .read()
+            .qlines
+            .iter()
+            .find(|q| Qline::mask_matches(&q.mask, name))
+        {
+            return Some(block.reason.clone());
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        self.security_manager
+            .qlines
+            .iter()
+            .find(|q| {
+                q.expires_at.is_none_or(|exp| exp > now) && Qline::mask_matches(&q.mask, name)
+            })
+            .map(|q| q.reason.clone().unwrap_or_else(|| "Reserved".to_string()))
+    }
+
     /// Request that a user be disconnected.
     ///
     /// This is safe to call from channel actors because it is non-blocking.
@@ -375,6 +487,7 @@ impl Matrix {
     /// 3. Removes from nicks mapping
     /// 4. Removes from users collection
     /// 5. Drops the sender (terminates connection task)
+    /// 6. Notifies state observers (propagates the QUIT to peers)
     ///
     /// Returns the list of channels the user was in (for logging).
     pub async fn disconnect_user(
@@ -422,6 +535,10 @@ impl Matrix {
         // 5. Final Cleanup (Maps, Timers, Metrics)
         self.cleanup_user_state(target_uid, &info).await;
 
+        // 6. Notify observers; a remote user's quit is attributed to its link
+        self.observers
+            .on_user_quit(target_uid, quit_reason, info.server.clone());
+
         info.channels
     }
 
@@ -438,10 +555,12 @@ impl Matrix {
             nick: user.nick.clone(),
             user: user.user.clone(),
             host: user.host.clone(),
+            visible_host: user.visible_host.clone(),
             realname: user.realname.clone(),
             channels: user.channels.iter().cloned().collect(),
             session_id: explicit_session_id.unwrap_or(user.session_id),
             account: user.account.clone(),
+            server: user.server.clone(),
             is_invisible: user.modes.invisible,
             is_oper: user.modes.oper,
         })
@@ -488,7 +607,7 @@ impl Matrix {
                         && let Some(user_arc) = self.user_manager.users.get(uid)
                     {
                         let mut user = user_arc.write().await;
-                        user.away = Some(away_msg.clone());
+                        user.set_away(Some(away_msg.clone()), self.clock());
                         tracing::debug!(
                             uid = %uid,
                             away = %away_msg,
@@ -519,7 +638,7 @@ impl Matrix {
             .record_whowas(&info.nick, &info.user, &info.host, &info.realname);
 
         // Notify MONITOR watchers
-        notify_monitors_offline(self, &info.nick).await;
+        notify_monitors_offline(self, &info.nick, &info.user, &info.visible_host).await;
     }
 
     async fn broadcast_quit_and_leave_channels(
@@ -556,13 +675,8 @@ impl Matrix {
                     })
                     .await;
 
-                if let Ok(remaining) = rx.await
-                    && remaining == 0
-                    && self.channel_manager.channels.remove(channel_name).is_some()
-                {
-                    crate::metrics::dec_active_channels();
-                    self.stats_manager.channel_destroyed();
-                }
+                // Wait for the QUIT to land; an emptied channel unregisters itself
+                let _ = rx.await;
             }
         }
     }
@@ -580,18 +694,25 @@ impl Matrix {
 
         // Remove user from matrix
         self.user_manager.users.remove(uid);
+        self.user_manager.index.remove(uid);
+
+        // Accepts name a nick, which someone else may take next
+        self.user_manager.forget_accepted(&info.nick).await;
 
-        // Remove enforcement timer
+        // Remove enforcement timer, +g notice time and service notice history
         self.user_manager.enforce_timers.remove(uid);
+        self.user_manager.caller_id_notified.remove(uid);
+        self.service_manager.notice_dedupe.forget(uid);
 
         // Drop sender
-        self.user_manager.senders.remove(uid);
+        self.user_manager.remove_senders(uid);
 
         // Clean up rate limiter
         self.security_manager.rate_limiter.remove_client(uid);
 
         // Update metrics
         crate::metrics::dec_connected_users();
+        crate::activity::global().connection_closed(uid);
 
         // Update StatsManager
         if uid.starts_with(self.server_id.as_str()) {
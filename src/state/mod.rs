@@ -11,11 +11,15 @@
 mod channel;
 pub mod client;
 pub mod dashmap_ext;
+pub mod isupport;
 pub mod managers;
 mod matrix;
 pub mod observer;
 pub mod persistence;
+pub mod rehash;
+pub mod sendq;
 pub mod session;
+pub mod snapshot;
 mod uid;
 mod user;
 
@@ -23,20 +27,21 @@ pub use crate::sync::SyncManager;
 pub use channel::{ListEntry, MemberModes, Topic};
 pub use client::ChannelMembership;
 pub use managers::channel::ChannelManager;
-pub use managers::lifecycle::LifecycleManager;
+pub use managers::lifecycle::{LifecycleManager, ShutdownRequest};
 pub use managers::monitor::MonitorManager;
 pub use managers::security::{SecurityManager, SecurityManagerParams};
 pub use managers::service::ServiceManager;
 pub use managers::user::UserManager;
 pub use matrix::{HotConfig, Matrix, MatrixParams};
+pub use sendq::{SendQ, SendQReceiver};
 pub use user::WhowasEntry;
 pub mod actor;
-pub use user::{User, UserModes, UserParams};
+pub use user::{User, UserModes, UserParams, UserStamps};
 
 // Session state types (Innovation 1: Typestate pattern)
 pub use session::{
-    BatchRouting, InitiatorData, ReattachInfo, RegisteredState, SaslAccess, ServerState,
-    SessionState, UnregisteredState,
+    BatchRouting, InitiatorData, PendingMaskKill, ReattachInfo, RegisteredState, RegistrationPhase,
+    SaslAccess, ServerState, SessionState, UnregisteredState,
 };
 
 // Internal re-exports
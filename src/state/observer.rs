@@ -1,14 +1,28 @@
-//! State observer trait for distributed synchronization (Innovation 2).
+//! State observer trait and fan-out bus (Innovation 2).
 //!
-//! This module defines the `StateObserver` trait, which allows the `SyncManager`
-//! to hook into local state changes and broadcast them as `DELTA` updates.
+//! This module defines the `StateObserver` trait, which allows subscribers
+//! such as the `SyncManager` to hook into local state changes, and the
+//! `ObserverBus` that fans each change out to every registered subscriber.
+//!
+//! Managers hold the bus as their single observer. Each subscriber gets its
+//! own bounded queue drained by a dedicated task, so a slow subscriber only
+//! drops its own events and never stalls user or channel updates. A
+//! subscriber that cannot afford to miss events is told when it has, through
+//! [`StateObserver::on_events_dropped`], and repairs its state from there.
 
+use parking_lot::RwLock;
 use slirc_proto::sync::channel::ChannelCrdt;
 use slirc_proto::sync::clock::ServerId;
 use slirc_proto::sync::user::UserCrdt;
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::{debug, warn};
+
+/// Default queue depth for each bus subscriber.
+pub const DEFAULT_OBSERVER_QUEUE: usize = 4096;
 
 /// Type of global ban for S2S propagation.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum GlobalBanType {
     /// G-line: Global user@host ban.
     Gline,
@@ -63,11 +77,387 @@ pub trait StateObserver: Send + Sync {
     /// Propagates account login/logout to peers so they can enforce ACLs.
     /// `account` is the account name, or None for logout.
     fn on_account_change(&self, uid: &str, account: Option<&str>, source: Option<ServerId>);
+
+    /// Called by [`ObserverBus`] when an event for this observer was dropped
+    /// because its queue was full.
+    ///
+    /// Runs on the thread that published the event, so it must not block.
+    fn on_events_dropped(&self) {}
+}
+
+/// A state change queued for delivery to one subscriber.
+#[derive(Clone)]
+enum StateEvent {
+    UserUpdate(Box<UserCrdt>, Option<ServerId>),
+    UserQuit {
+        uid: String,
+        reason: String,
+        source: Option<ServerId>,
+    },
+    ChannelUpdate(Box<ChannelCrdt>, Option<ServerId>),
+    ChannelDestroy(String, Option<ServerId>),
+    BanAdd {
+        ban_type: GlobalBanType,
+        mask: String,
+        reason: String,
+        setter: String,
+        duration: Option<i64>,
+        source: Option<ServerId>,
+    },
+    BanRemove(GlobalBanType, String, Option<ServerId>),
+    AccountChange {
+        uid: String,
+        account: Option<String>,
+        source: Option<ServerId>,
+    },
+}
+
+impl StateEvent {
+    /// Metric label for this event.
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::UserUpdate(..) => "user_update",
+            Self::UserQuit { .. } => "user_quit",
+            Self::ChannelUpdate(..) => "channel_update",
+            Self::ChannelDestroy(..) => "channel_destroy",
+            Self::BanAdd { .. } => "ban_add",
+            Self::BanRemove(..) => "ban_remove",
+            Self::AccountChange { .. } => "account_change",
+        }
+    }
+
+    fn deliver(self, observer: &dyn StateObserver) {
+        match self {
+            Self::UserUpdate(user, source) => observer.on_user_update(&user, source),
+            Self::UserQuit {
+                uid,
+                reason,
+                source,
+            } => observer.on_user_quit(&uid, &reason, source),
+            Self::ChannelUpdate(channel, source) => observer.on_channel_update(&channel, source),
+            Self::ChannelDestroy(name, source) => observer.on_channel_destroy(&name, source),
+            Self::BanAdd {
+                ban_type,
+                mask,
+                reason,
+                setter,
+                duration,
+                source,
+            } => observer.on_ban_add(ban_type, &mask, &reason, &setter, duration, source),
+            Self::BanRemove(ban_type, mask, source) => {
+                observer.on_ban_remove(ban_type, &mask, source)
+            }
+            Self::AccountChange {
+                uid,
+                account,
+                source,
+            } => observer.on_account_change(&uid, account.as_deref(), source),
+        }
+    }
+}
+
+struct Subscriber {
+    name: &'static str,
+    tx: mpsc::Sender<StateEvent>,
+    observer: Arc<dyn StateObserver>,
+}
+
+/// Fans state changes out to any number of subscribers.
+///
+/// Dispatch never blocks: events are pushed onto each subscriber's bounded
+/// queue with `try_send`, and a full queue drops the event for that
+/// subscriber only (counted in `slircd_observer_dropped_total`, and reported
+/// to the subscriber's [`StateObserver::on_events_dropped`]). Events are
+/// delivered to each subscriber in the order they were dispatched.
+#[derive(Default)]
+pub struct ObserverBus {
+    subscribers: RwLock<Vec<Subscriber>>,
+}
+
+impl ObserverBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a subscriber with a queue of `capacity` events.
+    ///
+    /// Spawns the task that drains the queue, so this must be called from
+    /// within a Tokio runtime. The task exits when the bus is dropped.
+    pub fn subscribe(&self, name: &'static str, observer: Arc<dyn StateObserver>, capacity: usize) {
+        let (tx, mut rx) = mpsc::channel::<StateEvent>(capacity.max(1));
+        let drain = Arc::clone(&observer);
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                event.deliver(drain.as_ref());
+            }
+            debug!(observer = name, "State observer queue closed");
+        });
+        self.subscribers
+            .write()
+            .push(Subscriber { name, tx, observer });
+    }
+
+    fn dispatch(&self, event: StateEvent) {
+        let subscribers = self.subscribers.read();
+        let Some((last, rest)) = subscribers.split_last() else {
+            return;
+        };
+        for subscriber in rest {
+            Self::offer(subscriber, event.clone());
+        }
+        Self::offer(last, event);
+    }
+
+    fn offer(subscriber: &Subscriber, event: StateEvent) {
+        let kind = event.kind();
+        match subscriber.tx.try_send(event) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                warn!(
+                    observer = subscriber.name,
+                    event = kind,
+                    "State observer queue full, dropping event"
+                );
+                crate::metrics::inc_observer_dropped(subscriber.name, kind);
+                subscriber.observer.on_events_dropped();
+            }
+            Err(TrySendError::Closed(_)) => {
+                debug!(
+                    observer = subscriber.name,
+                    event = kind,
+                    "State observer gone"
+                );
+            }
+        }
+    }
+}
+
+impl StateObserver for ObserverBus {
+    fn on_user_update(&self, user: &UserCrdt, source: Option<ServerId>) {
+        self.dispatch(StateEvent::UserUpdate(Box::new(user.clone()), source));
+    }
+
+    fn on_user_quit(&self, uid: &str, reason: &str, source: Option<ServerId>) {
+        self.dispatch(StateEvent::UserQuit {
+            uid: uid.to_string(),
+            reason: reason.to_string(),
+            source,
+        });
+    }
+
+    fn on_channel_update(&self, channel: &ChannelCrdt, source: Option<ServerId>) {
+        self.dispatch(StateEvent::ChannelUpdate(Box::new(channel.clone()), source));
+    }
+
+    fn on_channel_destroy(&self, name: &str, source: Option<ServerId>) {
+        self.dispatch(StateEvent::ChannelDestroy(name.to_string(), source));
+    }
+
+    fn on_ban_add(
+        &self,
+        ban_type: GlobalBanType,
+        mask: &str,
+        reason: &str,
+        setter: &str,
+        duration: Option<i64>,
+        source: Option<ServerId>,
+    ) {
+        self.dispatch(StateEvent::BanAdd {
+            ban_type,
+            mask: mask.to_string(),
+            reason: reason.to_string(),
+            setter: setter.to_string(),
+            duration,
+            source,
+        });
+    }
+
+    fn on_ban_remove(&self, ban_type: GlobalBanType, mask: &str, source: Option<ServerId>) {
+        self.dispatch(StateEvent::BanRemove(ban_type, mask.to_string(), source));
+    }
+
+    fn on_account_change(&self, uid: &str, account: Option<&str>, source: Option<ServerId>) {
+        self.dispatch(StateEvent::AccountChange {
+            uid: uid.to_string(),
+            account: account.map(str::to_string),
+            source,
+        });
+    }
+}
+
+/// Counts state changes by kind and origin (`slircd_state_events_total`).
+pub struct MetricsObserver;
+
+impl MetricsObserver {
+    fn record(event: &str, source: &Option<ServerId>) {
+        let origin = if source.is_some() { "remote" } else { "local" };
+        crate::metrics::inc_state_event(event, origin);
+    }
+}
+
+impl StateObserver for MetricsObserver {
+    fn on_user_update(&self, _user: &UserCrdt, source: Option<ServerId>) {
+        Self::record("user_update", &source);
+    }
+
+    fn on_user_quit(&self, _uid: &str, _reason: &str, source: Option<ServerId>) {
+        Self::record("user_quit", &source);
+    }
+
+    fn on_channel_update(&self, _channel: &ChannelCrdt, source: Option<ServerId>) {
+        Self::record("channel_update", &source);
+    }
+
+    fn on_channel_destroy(&self, _name: &str, source: Option<ServerId>) {
+        Self::record("channel_destroy", &source);
+    }
+
+    fn on_ban_add(
+        &self,
+        _ban_type: GlobalBanType,
+        _mask: &str,
+        _reason: &str,
+        _setter: &str,
+        _duration: Option<i64>,
+        source: Option<ServerId>,
+    ) {
+        Self::record("ban_add", &source);
+    }
+
+    fn on_ban_remove(&self, _ban_type: GlobalBanType, _mask: &str, source: Option<ServerId>) {
+        Self::record("ban_remove", &source);
+    }
+
+    fn on_account_change(&self, _uid: &str, _account: Option<&str>, source: Option<ServerId>) {
+        Self::record("account_change", &source);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// Records the uid of each quit it sees, optionally stalling first,
+    /// and the realname of each user update.
+    struct Recorder {
+        seen: Mutex<Vec<String>>,
+        realnames: Mutex<Vec<String>>,
+        dropped: std::sync::atomic::AtomicUsize,
+        stall: Option<Duration>,
+    }
+
+    impl Recorder {
+        fn new(stall: Option<Duration>) -> Arc<Self> {
+            Arc::new(Self {
+                seen: Mutex::new(Vec::new()),
+                realnames: Mutex::new(Vec::new()),
+                dropped: Default::default(),
+                stall,
+            })
+        }
+
+        fn seen(&self) -> Vec<String> {
+            self.seen.lock().unwrap().clone()
+        }
+    }
+
+    impl StateObserver for Recorder {
+        fn on_user_update(&self, user: &UserCrdt, _: Option<ServerId>) {
+            let realname = user.realname.value().clone();
+            self.realnames.lock().unwrap().push(realname);
+        }
+        fn on_user_quit(&self, uid: &str, _: &str, _: Option<ServerId>) {
+            if let Some(stall) = self.stall {
+                std::thread::sleep(stall);
+            }
+            self.seen.lock().unwrap().push(uid.to_string());
+        }
+        fn on_channel_update(&self, _: &ChannelCrdt, _: Option<ServerId>) {}
+        fn on_channel_destroy(&self, _: &str, _: Option<ServerId>) {}
+        fn on_ban_add(
+            &self,
+            _: GlobalBanType,
+            _: &str,
+            _: &str,
+            _: &str,
+            _: Option<i64>,
+            _: Option<ServerId>,
+        ) {
+        }
+        fn on_ban_remove(&self, _: GlobalBanType, _: &str, _: Option<ServerId>) {}
+        fn on_account_change(&self, _: &str, _: Option<&str>, _: Option<ServerId>) {}
+        fn on_events_dropped(&self) {
+            self.dropped
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    async fn wait_for(recorder: &Recorder, count: usize) {
+        for _ in 0..200 {
+            if recorder.seen().len() >= count {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    // ========================================================================
+    // ObserverBus tests
+    // ========================================================================
+
+    #[tokio::test]
+    async fn bus_fans_out_to_every_subscriber_in_order() {
+        let bus = ObserverBus::new();
+        let a = Recorder::new(None);
+        let b = Recorder::new(None);
+        bus.subscribe("a", a.clone(), 16);
+        bus.subscribe("b", b.clone(), 16);
+
+        for uid in ["001AAAAAA", "001AAAAAB", "001AAAAAC"] {
+            bus.on_user_quit(uid, "bye", None);
+        }
+        wait_for(&a, 3).await;
+        wait_for(&b, 3).await;
+
+        let expected = vec!["001AAAAAA", "001AAAAAB", "001AAAAAC"];
+        assert_eq!(a.seen(), expected);
+        assert_eq!(b.seen(), expected);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn slow_subscriber_drops_without_stalling_others() {
+        let bus = ObserverBus::new();
+        let slow = Recorder::new(Some(Duration::from_millis(50)));
+        let fast = Recorder::new(None);
+        bus.subscribe("slow", slow.clone(), 1);
+        bus.subscribe("fast", fast.clone(), 64);
+
+        let started = std::time::Instant::now();
+        for i in 0..32 {
+            bus.on_user_quit(&format!("001AAA{i:03}"), "bye", None);
+        }
+        assert!(started.elapsed() < Duration::from_millis(50));
+
+        wait_for(&fast, 32).await;
+        assert_eq!(fast.seen().len(), 32);
+        assert!(slow.seen().len() < 32);
+
+        // Only the subscriber that lost events is told about it, once per
+        // lost event
+        let dropped = |r: &Recorder| r.dropped.load(std::sync::atomic::Ordering::Relaxed);
+        assert!(dropped(&slow) > 0);
+        assert_eq!(dropped(&fast), 0);
+        wait_for(&slow, 32 - dropped(&slow)).await;
+        assert_eq!(slow.seen().len() + dropped(&slow), 32);
+    }
+
+    #[test]
+    fn bus_without_subscribers_is_a_no_op() {
+        let bus = ObserverBus::new();
+        bus.on_channel_destroy("#test", None);
+    }
 
     // ========================================================================
     // GlobalBanType equality tests
@@ -91,4 +481,42 @@ mod tests {
         let bt2 = bt; // Copy
         assert_eq!(bt, bt2); // Both should still be valid
     }
+
+    // ========================================================================
+    // Emit point tests
+    // ========================================================================
+
+    #[tokio::test]
+    async fn user_changes_and_quit_reach_subscribers() {
+        let tm = crate::test_support::TestMatrixBuilder::new().build().await;
+        let recorder = Recorder::new(None);
+        tm.matrix.observers.subscribe("test", recorder.clone(), 64);
+        let mut alice = tm.user("alice").cap("setname").build().await;
+
+        tm.run(&mut alice, "SETNAME :New Name").await;
+        for _ in 0..200 {
+            if recorder
+                .realnames
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|r| r == "New Name")
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert!(
+            recorder
+                .realnames
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|r| r == "New Name")
+        );
+
+        tm.matrix.disconnect_user(&alice.uid, "Leaving").await;
+        wait_for(&recorder, 1).await;
+        assert_eq!(recorder.seen(), vec![alice.uid.clone()]);
+    }
 }
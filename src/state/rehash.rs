@@ -0,0 +1,271 @@
+//! Live configuration reload, shared by REHASH and SIGHUP.
+//!
+//! The config file is loaded and validated as a whole; if that fails nothing
+//! changes. Each section is then applied on its own and reported, so an
+//! unreadable MOTD file or a weak cloak secret keeps its old value without
+//! holding back the rest. Listeners are opened and closed by the gateway and
+//! the `[tls.sts]` policy applies to the next CAP LS; TLS certificates,
+//! links, reverse DNS and the database need a restart.
+
+use super::{HotConfig, Matrix};
+use crate::config::Config;
+use crate::handlers::server_reply;
+use crate::state::dashmap_ext::DashMapExt;
+use slirc_proto::Response;
+use std::fmt;
+use std::sync::Arc;
+
+/// Outcome of applying one section of the config.
+#[derive(Debug)]
+pub struct SectionResult {
+    /// Section name as shown to the oper.
+    pub section: &'static str,
+    /// What was applied, or why the section kept its old values.
+    pub outcome: Result<String, String>,
+}
+
+impl SectionResult {
+    fn ok(section: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            section,
+            outcome: Ok(detail.into()),
+        }
+    }
+
+    fn failed(section: &'static str, reason: impl Into<String>) -> Self {
+        Self {
+            section,
+            outcome: Err(reason.into()),
+        }
+    }
+}
+
+impl fmt::Display for SectionResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.outcome {
+            Ok(detail) => write!(f, "{}: ok ({})", self.section, detail),
+            Err(reason) => write!(f, "{}: FAILED ({})", self.section, reason),
+        }
+    }
+}
+
+/// Re-read the config file and apply what can change without a restart.
+///
+/// Returns `Err` without changing anything if the file does not load or
+/// validate; otherwise one result per section.
+pub async fn rehash(matrix: &Matrix) -> Result<Vec<SectionResult>, String> {
+    let mut config = Config::load(&matrix.config_path)
+        .map_err(|e| format!("Failed to load {}: {}", matrix.config_path, e))?;
+    if let Err(errors) = crate::config::validate(&config) {
+        let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        return Err(format!("Invalid config: {}", errors.join("; ")));
+    }
+
+    // Nick and channel keys were folded with the startup casemapping
+    let casemapping = matrix.config.server.casemapping;
+    if config.server.casemapping != casemapping {
+        tracing::warn!("server.casemapping change ignored until restart");
+        config.server.casemapping = casemapping;
+    }
+
+    let mut results = Vec::new();
+    let mut hot_config = HotConfig::from_config(&config);
+    let old = matrix.hot_config.read().clone();
+
+    match config.motd.try_load_lines() {
+        Ok(lines) => {
+            hot_config.motd_lines = lines;
+            results.push(SectionResult::ok(
+                "motd",
+                format!("{} lines", hot_config.motd_lines.len()),
+            ));
+        }
+        Err(e) => {
+            hot_config.motd_lines = old.motd_lines.clone();
+            let path = config.motd.file.as_deref().unwrap_or_default();
+            results.push(SectionResult::failed("motd", format!("{path}: {e}")));
+        }
+    }
+
+    results.push(SectionResult::ok(
+        "opers",
+        format!(
+            "{} blocks, {} classes",
+            hot_config.oper_blocks.len(),
+            hot_config.oper_classes.len()
+        ),
+    ));
+
+    let weak_secret = crate::security::cloaking::is_default_secret(&hot_config.cloak_secret)
+        && std::env::var("SLIRCD_ALLOW_INSECURE_CLOAK").is_err();
+    if weak_secret {
+        hot_config.cloak_secret = old.cloak_secret.clone();
+        hot_config.cloak_suffix = old.cloak_suffix.clone();
+        results.push(SectionResult::failed(
+            "cloak",
+            "weak cloak_secret; keeping the current one",
+        ));
+    } else if (&hot_config.cloak_secret, &hot_config.cloak_suffix)
+        == (&old.cloak_secret, &old.cloak_suffix)
+    {
+        results.push(SectionResult::ok("cloak", "unchanged"));
+    } else {
+        // Connected users keep their hosts; rewriting them would split bans
+        results.push(SectionResult::ok("cloak", "applies to new connections"));
+    }
+
+    results.push(SectionResult::ok(
+        "sts",
+        match &hot_config.sts {
+            Some(sts) => format!("port {}, duration {}s", sts.port, sts.duration),
+            None => "disabled".to_string(),
+        },
+    ));
+
+    let isupport_changes = crate::state::isupport::changes(&old.isupport, &hot_config.isupport);
+    *matrix.hot_config.write() = hot_config;
+    readvertise_isupport(matrix, &isupport_changes).await;
+    results.push(SectionResult::ok(
+        "server",
+        format!("{} ISUPPORT tokens changed", isupport_changes.len()),
+    ));
+
+    matrix
+        .security_manager
+        .rate_limiter
+        .set_config(config.security.rate_limits.clone());
+    results.push(SectionResult::ok("rate limits", "applied"));
+
+    let rbl = config.security.spam.rbl.clone();
+    let rbl_enabled = rbl.http_enabled || rbl.dns_enabled;
+    let dns_lists = rbl.dns_lists.len();
+    results.push(match &matrix.security_manager.spam_detector {
+        Some(spam_lock) => {
+            spam_lock.write().await.set_rbl(rbl);
+            if rbl_enabled {
+                SectionResult::ok("dnsbl", format!("{dns_lists} DNS lists"))
+            } else {
+                SectionResult::ok("dnsbl", "disabled")
+            }
+        }
+        None if rbl_enabled => {
+            SectionResult::failed("dnsbl", "spam detection is disabled; restart to enable it")
+        }
+        None => SectionResult::ok("dnsbl", "disabled"),
+    });
+
+    results.push(reload_ip_bans(matrix).await);
+
+    if let Some(result) = reload_listeners(matrix, config.listeners()).await {
+        results.push(result);
+    }
+
+    for result in &results {
+        match &result.outcome {
+            Ok(detail) => {
+                tracing::info!(section = result.section, %detail, "Config section reloaded")
+            }
+            Err(reason) => {
+                tracing::warn!(section = result.section, %reason, "Config section kept old values")
+            }
+        }
+    }
+    Ok(results)
+}
+
+/// Reload D-lines and Z-lines from the database into the IP deny list.
+async fn reload_ip_bans(matrix: &Matrix) -> SectionResult {
+    let bans = async {
+        let dlines = matrix.db.bans().get_active_dlines().await?;
+        let zlines = matrix.db.bans().get_active_zlines().await?;
+        Ok::<_, crate::db::DbError>((dlines, zlines))
+    };
+    let (dlines, zlines) = match bans.await {
+        Ok(bans) => bans,
+        Err(e) => return SectionResult::failed("ip bans", e.to_string()),
+    };
+
+    match matrix.security_manager.ip_deny_list.write() {
+        Ok(mut deny_list) => {
+            deny_list.reload_from_database(&dlines, &zlines);
+            SectionResult::ok(
+                "ip bans",
+                format!("{} D-lines, {} Z-lines", dlines.len(), zlines.len()),
+            )
+        }
+        Err(e) => SectionResult::failed("ip bans", format!("deny list lock poisoned: {e}")),
+    }
+}
+
+/// Hand the new listener set to the gateway; `None` when no gateway is bound.
+async fn reload_listeners(
+    matrix: &Matrix,
+    listeners: Vec<crate::config::ListenConfig>,
+) -> Option<SectionResult> {
+    let reload_tx = matrix.listener_reload.get()?;
+    let (reply, outcome) = tokio::sync::oneshot::channel();
+    let reload = crate::network::ListenerReload { listeners, reply };
+    if reload_tx.send(reload).await.is_err() {
+        return Some(SectionResult::failed("listeners", "gateway stopped"));
+    }
+    Some(match outcome.await {
+        Ok(Ok(detail)) => SectionResult::ok("listeners", detail),
+        Ok(Err(reason)) => SectionResult::failed("listeners", reason),
+        Err(_) => SectionResult::failed("listeners", "gateway stopped"),
+    })
+}
+
+/// Send changed RPL_ISUPPORT tokens to every local client.
+async fn readvertise_isupport(matrix: &Matrix, changes: &[String]) {
+    if changes.is_empty() {
+        return;
+    }
+    tracing::info!(tokens = %changes.join(" "), "Re-advertising ISUPPORT");
+    let lines = crate::state::isupport::lines(changes);
+    let uids: Vec<_> = matrix
+        .user_manager
+        .senders
+        .iter()
+        .map(|e| e.key().clone())
+        .collect();
+    for uid in uids {
+        let Some(user_arc) = matrix.user_manager.users.get_cloned(&uid) else {
+            continue;
+        };
+        let nick = user_arc.read().await.nick.clone();
+        for line in &lines {
+            let reply = server_reply(
+                &matrix.server_info.name,
+                Response::RPL_ISUPPORT,
+                vec![
+                    nick.clone(),
+                    line.clone(),
+                    "are supported by this server".to_string(),
+                ],
+            );
+            matrix.user_manager.send_to_uid(&uid, Arc::new(reply)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::{Command, Response};
+
+    #[tokio::test]
+    async fn isupport_changes_reach_local_clients() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let mut alice = tm.user("alice").build().await;
+
+        let changes = vec!["NETWORK=NewNet".to_string(), "-WHOX".to_string()];
+        super::readvertise_isupport(&tm.matrix, &changes).await;
+
+        let sent = alice.drain();
+        assert!(sent.iter().any(|m| matches!(
+            &m.command,
+            Command::Response(Response::RPL_ISUPPORT, args)
+                if args == &["alice", "NETWORK=NewNet -WHOX", "are supported by this server"]
+        )));
+    }
+}
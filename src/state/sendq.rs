@@ -0,0 +1,396 @@
+//! Per-connection send queues.
+//!
+//! Everything written to a client passes through its [`SendQ`]: handler
+//! replies, channel broadcasts and messages routed from other sessions.
+//! The connection drains the matching [`SendQReceiver`] into the socket.
+//!
+//! Queues built for a connection class count the bytes waiting and apply
+//! the class's [`SendQPolicy`] when a client stops reading, instead of
+//! letting a fixed-size channel fill up and refuse messages at random.
+//! Other queues (handshake, server replies, the remote router) only bound
+//! the number of messages, as before.
+
+use crate::config::{ClassBlock, SendQPolicy};
+use metrics::{Gauge, gauge, histogram};
+use slirc_proto::{Command, Message, Response, encode::IrcEncode};
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use tokio::sync::Notify;
+use tokio::sync::mpsc::{self, error::SendError, error::TryRecvError, error::TrySendError};
+
+/// Sending half of a connection's queue. Cheap to clone.
+#[derive(Clone)]
+pub struct SendQ {
+    tx: Tx,
+}
+
+#[derive(Clone)]
+enum Tx {
+    Bounded(mpsc::Sender<Arc<Message>>),
+    Budgeted(mpsc::UnboundedSender<(Arc<Message>, usize)>, Arc<Budget>),
+}
+
+/// Receiving half of a connection's queue.
+pub struct SendQReceiver {
+    rx: Rx,
+}
+
+enum Rx {
+    Bounded(mpsc::Receiver<Arc<Message>>),
+    Budgeted(mpsc::UnboundedReceiver<(Arc<Message>, usize)>, Arc<Budget>),
+}
+
+/// Byte accounting shared by both halves of a class queue.
+struct Budget {
+    class: String,
+    limit: usize,
+    policy: SendQPolicy,
+    queued: AtomicUsize,
+    peak: AtomicUsize,
+    exceeded: AtomicBool,
+    /// Wakes the receiver once the queue is exceeded.
+    notify: Notify,
+    /// `irc_sendq_bytes` for the class.
+    gauge: Gauge,
+}
+
+/// What to do with a message offered to a class queue.
+enum Admit {
+    Queue,
+    Drop,
+    Exceed,
+}
+
+impl Budget {
+    fn admit(&self, msg: &Message, len: usize) -> Admit {
+        let before = self.queued.fetch_add(len, Ordering::AcqRel);
+        let after = before + len;
+        let ceiling = match self.policy {
+            SendQPolicy::Block => self.limit.saturating_mul(2),
+            SendQPolicy::Disconnect | SendQPolicy::Drop => self.limit,
+        };
+        let verdict = if self.policy == SendQPolicy::Drop && after > self.limit / 2 && is_bulk(msg)
+        {
+            Admit::Drop
+        } else if after > ceiling {
+            Admit::Exceed
+        } else {
+            self.peak.fetch_max(after, Ordering::Relaxed);
+            return Admit::Queue;
+        };
+        self.queued.fetch_sub(len, Ordering::AcqRel);
+        verdict
+    }
+
+    fn exceed(&self) {
+        if !self.exceeded.swap(true, Ordering::AcqRel) {
+            crate::metrics::inc_sendq_event(&self.class, "exceeded");
+            self.notify.notify_one();
+        }
+    }
+
+    fn release(&self, len: usize) {
+        self.queued.fetch_sub(len, Ordering::AcqRel);
+        self.gauge.decrement(len as f64);
+    }
+}
+
+/// Bulk listing replies the `drop` policy may discard.
+fn is_bulk(msg: &Message) -> bool {
+    matches!(
+        msg.command,
+        Command::Response(
+            Response::RPL_WHOREPLY | Response::RPL_WHOSPCRPL | Response::RPL_LIST,
+            _
+        )
+    )
+}
+
+/// Bytes `msg` takes on the wire.
+fn wire_len(msg: &Message) -> usize {
+    msg.encode(&mut io::sink()).unwrap_or(0)
+}
+
+impl SendQ {
+    /// A queue holding at most `capacity` messages, with no byte accounting.
+    pub fn bounded(capacity: usize) -> (Self, SendQReceiver) {
+        let (tx, rx) = mpsc::channel(capacity);
+        (
+            Self {
+                tx: Tx::Bounded(tx),
+            },
+            SendQReceiver {
+                rx: Rx::Bounded(rx),
+            },
+        )
+    }
+
+    /// A queue limited to the class's `sendq` bytes, under its policy.
+    pub fn for_class(class: &ClassBlock) -> (Self, SendQReceiver) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let budget = Arc::new(Budget {
+            class: class.name.clone(),
+            limit: class.sendq,
+            policy: class.sendq_policy,
+            queued: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            exceeded: AtomicBool::new(false),
+            notify: Notify::new(),
+            gauge: gauge!("irc_sendq_bytes", "class" => class.name.clone()),
+        });
+        (
+            Self {
+                tx: Tx::Budgeted(tx, Arc::clone(&budget)),
+            },
+            SendQReceiver {
+                rx: Rx::Budgeted(rx, budget),
+            },
+        )
+    }
+
+    /// Queue a message without waiting.
+    ///
+    /// A class queue refuses a message that would take it past its limit
+    /// with [`TrySendError::Full`] and marks itself exceeded, so the
+    /// connection closes. Bulk replies discarded by the `drop` policy
+    /// count as sent.
+    pub fn try_send(&self, msg: Arc<Message>) -> Result<(), TrySendError<Arc<Message>>> {
+        match &self.tx {
+            Tx::Bounded(tx) => tx.try_send(msg),
+            Tx::Budgeted(tx, budget) => {
+                let len = wire_len(&msg);
+                match budget.admit(&msg, len) {
+                    Admit::Queue => match tx.send((msg, len)) {
+                        Ok(()) => {
+                            budget.gauge.increment(len as f64);
+                            Ok(())
+                        }
+                        Err(SendError((msg, len))) => {
+                            budget.queued.fetch_sub(len, Ordering::AcqRel);
+                            Err(TrySendError::Closed(msg))
+                        }
+                    },
+                    Admit::Drop => {
+                        crate::metrics::inc_sendq_event(&budget.class, "dropped");
+                        Ok(())
+                    }
+                    Admit::Exceed => {
+                        budget.exceed();
+                        Err(TrySendError::Full(msg))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Queue a message. Bounded queues wait for room; class queues never
+    /// wait, and fail where [`try_send`](Self::try_send) would.
+    pub async fn send(&self, msg: Arc<Message>) -> Result<(), SendError<Arc<Message>>> {
+        match &self.tx {
+            Tx::Bounded(tx) => tx.send(msg).await,
+            Tx::Budgeted(..) => self.try_send(msg).map_err(|e| match e {
+                TrySendError::Full(msg) | TrySendError::Closed(msg) => SendError(msg),
+            }),
+        }
+    }
+
+    /// How full the queue is, and its limit: messages for bounded queues,
+    /// bytes for class queues.
+    pub fn depth(&self) -> (usize, usize) {
+        match &self.tx {
+            Tx::Bounded(tx) => (tx.max_capacity() - tx.capacity(), tx.max_capacity()),
+            Tx::Budgeted(_, budget) => (budget.queued.load(Ordering::Acquire), budget.limit),
+        }
+    }
+}
+
+impl fmt::Debug for SendQ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (used, limit) = self.depth();
+        let mut out = f.debug_struct("SendQ");
+        if let Tx::Budgeted(_, budget) = &self.tx {
+            out.field("class", &budget.class)
+                .field("policy", &budget.policy);
+        }
+        out.field("used", &used).field("limit", &limit).finish()
+    }
+}
+
+impl SendQReceiver {
+    /// Next queued message, or `None` once every sender is gone.
+    pub async fn recv(&mut self) -> Option<Arc<Message>> {
+        match &mut self.rx {
+            Rx::Bounded(rx) => rx.recv().await,
+            Rx::Budgeted(rx, budget) => {
+                let (msg, len) = rx.recv().await?;
+                budget.release(len);
+                Some(msg)
+            }
+        }
+    }
+
+    /// Next queued message, if one is ready.
+    pub fn try_recv(&mut self) -> Result<Arc<Message>, TryRecvError> {
+        match &mut self.rx {
+            Rx::Bounded(rx) => rx.try_recv(),
+            Rx::Budgeted(rx, budget) => {
+                let (msg, len) = rx.try_recv()?;
+                budget.release(len);
+                Ok(msg)
+            }
+        }
+    }
+
+    /// Resolves once the queue has refused a message for being full.
+    /// Never resolves for bounded queues.
+    ///
+    /// Does not borrow the receiver, so it can be awaited next to
+    /// [`recv`](Self::recv).
+    pub fn exceeded(&self) -> impl Future<Output = ()> + Send + 'static {
+        let budget = match &self.rx {
+            Rx::Bounded(_) => None,
+            Rx::Budgeted(_, budget) => Some(Arc::clone(budget)),
+        };
+        async move {
+            let Some(budget) = budget else {
+                return std::future::pending().await;
+            };
+            while !budget.exceeded.load(Ordering::Acquire) {
+                budget.notify.notified().await;
+            }
+        }
+    }
+
+    /// Whether the `block` policy wants the client's input paused.
+    pub fn is_blocking(&self) -> bool {
+        match &self.rx {
+            Rx::Bounded(_) => false,
+            Rx::Budgeted(_, budget) => {
+                budget.policy == SendQPolicy::Block
+                    && budget.queued.load(Ordering::Acquire) > budget.limit
+            }
+        }
+    }
+
+    /// Connection class of the queue, for class queues.
+    pub fn class(&self) -> Option<&str> {
+        match &self.rx {
+            Rx::Bounded(_) => None,
+            Rx::Budgeted(_, budget) => Some(&budget.class),
+        }
+    }
+}
+
+impl Drop for SendQReceiver {
+    fn drop(&mut self) {
+        if let Rx::Budgeted(rx, budget) = &mut self.rx {
+            rx.close();
+            while let Ok((_, len)) = rx.try_recv() {
+                budget.release(len);
+            }
+            histogram!("irc_sendq_peak_bytes", "class" => budget.class.clone())
+                .record(budget.peak.load(Ordering::Relaxed) as f64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(sendq: usize, sendq_policy: SendQPolicy) -> ClassBlock {
+        ClassBlock {
+            name: "test".to_string(),
+            sendq,
+            sendq_policy,
+        }
+    }
+
+    fn privmsg(text: &str) -> Arc<Message> {
+        Arc::new(Message::privmsg("#chan", text))
+    }
+
+    fn who_reply() -> Arc<Message> {
+        Arc::new(Message::from(Command::Response(
+            Response::RPL_WHOREPLY,
+            vec!["nick".to_string(), "#chan".to_string()],
+        )))
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_policy_refuses_past_limit() {
+        let line = privmsg("hello");
+        let len = wire_len(&line);
+        let (tx, mut rx) = SendQ::for_class(&class(len * 2, SendQPolicy::Disconnect));
+
+        tx.try_send(line.clone()).unwrap();
+        tx.try_send(line.clone()).unwrap();
+        assert_eq!(tx.depth(), (len * 2, len * 2));
+        assert!(matches!(
+            tx.try_send(line.clone()),
+            Err(TrySendError::Full(_))
+        ));
+        rx.exceeded().await;
+
+        rx.recv().await.unwrap();
+        assert_eq!(tx.depth().0, len);
+    }
+
+    #[tokio::test]
+    async fn test_drop_policy_discards_bulk_replies_first() {
+        let line = privmsg("hello");
+        let len = wire_len(&line);
+        let (tx, mut rx) = SendQ::for_class(&class(len * 2, SendQPolicy::Drop));
+
+        tx.try_send(line.clone()).unwrap();
+        // Half full: WHO replies go, everything else still queues
+        tx.try_send(who_reply()).unwrap();
+        tx.try_send(line.clone()).unwrap();
+        assert_eq!(tx.depth(), (len * 2, len * 2));
+        assert!(!rx.budget_exceeded());
+
+        assert!(rx.try_recv().is_ok());
+        assert!(rx.try_recv().is_ok());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_block_policy_pauses_then_allows_double() {
+        let line = privmsg("hello");
+        let len = wire_len(&line);
+        let (tx, mut rx) = SendQ::for_class(&class(len, SendQPolicy::Block));
+
+        tx.try_send(line.clone()).unwrap();
+        assert!(!rx.is_blocking());
+        tx.try_send(line.clone()).unwrap();
+        assert!(rx.is_blocking());
+        assert!(tx.try_send(line.clone()).is_err());
+
+        rx.recv().await.unwrap();
+        assert!(!rx.is_blocking());
+    }
+
+    #[tokio::test]
+    async fn test_bounded_queue_counts_messages() {
+        let (tx, mut rx) = SendQ::bounded(1);
+        tx.try_send(privmsg("one")).unwrap();
+        assert!(matches!(
+            tx.try_send(privmsg("two")),
+            Err(TrySendError::Full(_))
+        ));
+        assert_eq!(tx.depth(), (1, 1));
+        assert!(!rx.is_blocking());
+        rx.recv().await.unwrap();
+    }
+
+    impl SendQReceiver {
+        fn budget_exceeded(&self) -> bool {
+            match &self.rx {
+                Rx::Bounded(_) => false,
+                Rx::Budgeted(_, budget) => budget.exceeded.load(Ordering::Acquire),
+            }
+        }
+    }
+}
@@ -20,11 +20,23 @@
 //! │  user: Option       │                        │   user: String ✓    │
 //! └─────────────────────┘                        └─────────────────────┘
 //! ```
+//!
+//! Within `UnregisteredState`, [`RegistrationPhase`] tracks how far the
+//! handshake has got and which commands may be dispatched:
+//!
+//! ```text
+//! CapNegotiating ──▶ SaslInProgress ──▶ AwaitingNickUser ──▶ Registered
+//! ```
+//!
+//! Phases can be skipped (a client without CAP goes straight to
+//! `AwaitingNickUser`) and NICK/USER may arrive in any phase.
 
+use crate::config::ListenConfig;
 use crate::handlers::{BatchState, SaslState};
 use crate::state::client::{DeviceId, SessionId};
 use slirc_proto::sync::clock::ServerId;
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::time::Instant;
 use uuid::Uuid;
 
@@ -108,6 +120,24 @@ pub trait SessionState: Send {
     /// Whether this is a TLS connection.
     fn is_tls(&self) -> bool;
 
+    /// Whether the connection came in on a Tor listener.
+    ///
+    /// Onion circuits are encrypted end to end even though the hop from
+    /// the local Tor daemon is plaintext.
+    fn is_tor(&self) -> bool {
+        false
+    }
+
+    /// Whether the connection came in on a WebSocket listener.
+    fn is_websocket(&self) -> bool {
+        false
+    }
+
+    /// Whether the client may still switch on compression with COMPRESS.
+    fn offers_compression(&self) -> bool {
+        false
+    }
+
     /// Get TLS certificate fingerprint.
     fn certfp(&self) -> Option<&str>;
 
@@ -304,12 +334,24 @@ pub struct UnregisteredState {
     pub is_tls: bool,
     /// TLS client certificate fingerprint (SHA-256, hex-encoded).
     pub certfp: Option<String>,
+    /// Listener this connection arrived on (`None` in tests).
+    pub listener: Option<Arc<ListenConfig>>,
+    /// Whether COMPRESS has switched on compression.
+    pub is_compressed: bool,
     /// Whether WEBIRC was used to set client info.
     pub webirc_used: bool,
     /// Real IP address from WEBIRC (overrides connection IP).
     pub webirc_ip: Option<String>,
     /// Real hostname from WEBIRC (overrides reverse DNS).
     pub webirc_host: Option<String>,
+    /// Gateway name sent with WEBIRC.
+    pub webirc_gateway: Option<String>,
+    /// WEBIRC `secure` option: the client reached the gateway over TLS.
+    pub webirc_secure: bool,
+    /// Reverse DNS lookup started when the client connected (or sent WEBIRC).
+    pub rdns: Option<tokio::task::JoinHandle<Option<String>>>,
+    /// Forward-confirmed hostname from that lookup.
+    pub resolved_host: Option<String>,
     /// Password received via PASS command.
     pub pass_received: Option<String>,
     /// Active batch state for client-to-server batches (e.g., draft/multiline).
@@ -336,6 +378,37 @@ pub struct UnregisteredState {
     pub reattach_info: Option<ReattachInfo>,
 }
 
+/// Where an unregistered client is in the registration handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrationPhase {
+    /// CAP LS or REQ was sent and CAP END has not been.
+    CapNegotiating,
+    /// An AUTHENTICATE exchange has started and not finished.
+    SaslInProgress,
+    /// Nothing is being negotiated; NICK or USER is still missing.
+    AwaitingNickUser,
+    /// Every requirement is met; the connection registers once the current
+    /// command has been handled.
+    Registered,
+}
+
+impl RegistrationPhase {
+    /// Whether `command` (upper case) may be dispatched in this phase.
+    ///
+    /// During SASL only commands that leave the exchange intact are taken:
+    /// the transport (STARTTLS) and claimed identity (PASS, WEBIRC) must not
+    /// change under it, and a server handshake cannot start.
+    pub fn admits(self, command: &str) -> bool {
+        match self {
+            Self::SaslInProgress => matches!(
+                command,
+                "AUTHENTICATE" | "CAP" | "NICK" | "USER" | "PING" | "PONG" | "QUIT"
+            ),
+            Self::CapNegotiating | Self::AwaitingNickUser | Self::Registered => true,
+        }
+    }
+}
+
 /// Data for initiating a server connection.
 #[derive(Debug, Clone)]
 pub struct InitiatorData {
@@ -386,6 +459,18 @@ impl SessionState for UnregisteredState {
         self.is_tls
     }
 
+    fn is_tor(&self) -> bool {
+        self.listener.as_ref().is_some_and(|l| l.tor)
+    }
+
+    fn is_websocket(&self) -> bool {
+        self.listener.as_ref().is_some_and(|l| l.websocket)
+    }
+
+    fn offers_compression(&self) -> bool {
+        !self.is_compressed && self.listener.as_ref().is_some_and(|l| l.compression)
+    }
+
     fn certfp(&self) -> Option<&str> {
         self.certfp.as_deref()
     }
@@ -430,14 +515,46 @@ impl SaslAccess for UnregisteredState {
 }
 
 impl UnregisteredState {
+    /// Current phase of the registration handshake.
+    pub fn phase(&self) -> RegistrationPhase {
+        if !matches!(self.sasl_state, SaslState::None | SaslState::Authenticated) {
+            RegistrationPhase::SaslInProgress
+        } else if self.cap_negotiating {
+            RegistrationPhase::CapNegotiating
+        } else if self.nick.is_none() || self.user.is_none() {
+            RegistrationPhase::AwaitingNickUser
+        } else {
+            RegistrationPhase::Registered
+        }
+    }
+
     /// Check if registration requirements are met.
     ///
     /// Requirements:
     /// - NICK has been provided
     /// - USER has been provided
     /// - CAP negotiation is not in progress (if started)
+    /// - No SASL exchange is in progress
     pub fn can_register(&self) -> bool {
-        self.nick.is_some() && self.user.is_some() && !self.cap_negotiating
+        self.phase() == RegistrationPhase::Registered
+    }
+
+    /// Abort a SASL exchange that registration has overtaken.
+    ///
+    /// A client that finishes CAP negotiation and sends NICK and USER while
+    /// still authenticating registers without an account. Returns true if
+    /// the exchange was aborted, in which case the caller sends
+    /// ERR_SASLABORT.
+    pub fn abort_overtaken_sasl(&mut self) -> bool {
+        let overtaken = self.phase() == RegistrationPhase::SaslInProgress
+            && !self.cap_negotiating
+            && self.nick.is_some()
+            && self.user.is_some();
+        if overtaken {
+            self.sasl_state = SaslState::None;
+            self.sasl_buffer.clear();
+        }
+        overtaken
     }
 
     /// Check if server registration requirements are met.
@@ -490,8 +607,10 @@ impl UnregisteredState {
     /// state into a registered state once, rather than checking a flag repeatedly.
     #[allow(clippy::result_large_err)] // By design: Err returns self to continue registration
     pub fn try_register(self) -> Result<RegisteredState, Self> {
-        match (&self.nick, &self.user) {
-            (Some(nick), Some(user)) if !self.cap_negotiating => {
+        match (self.phase(), &self.nick, &self.user) {
+            (RegistrationPhase::Registered, Some(nick), Some(user)) => {
+                let is_tor = self.is_tor();
+                let is_websocket = self.is_websocket();
                 Ok(RegisteredState {
                     session_id: self.session_id,
                     device_id: None, // Set by SASL handler after registration
@@ -501,11 +620,14 @@ impl UnregisteredState {
                     capabilities: self.capabilities,
                     account: self.account,
                     is_tls: self.is_tls,
+                    is_tor,
+                    is_websocket,
                     certfp: self.certfp,
                     cap_version: self.cap_version,
                     // Post-registration state starts fresh
                     failed_oper_attempts: 0,
                     last_oper_attempt: None,
+                    oper_challenge: None,
                     active_batch: None,
                     active_batch_ref: None,
                     // Ping timeout tracking starts fresh
@@ -520,6 +642,7 @@ impl UnregisteredState {
                     sasl_buffer: self.sasl_buffer,
                     // Reattach info is carried forward from UnregisteredState
                     reattach_info: self.reattach_info,
+                    pending_mask_kill: None,
                 })
             }
             _ => Err(self),
@@ -560,12 +683,18 @@ pub struct RegisteredState {
     pub account: Option<String>,
     /// Whether this is a TLS connection.
     pub is_tls: bool,
+    /// Whether the connection came in on a Tor listener.
+    pub is_tor: bool,
+    /// Whether the connection came in on a WebSocket listener.
+    pub is_websocket: bool,
     /// TLS client certificate fingerprint.
     pub certfp: Option<String>,
     /// Failed OPER attempts counter (brute-force protection).
     pub failed_oper_attempts: u8,
     /// Timestamp of last OPER attempt (for rate limiting).
     pub last_oper_attempt: Option<Instant>,
+    /// CHALLENGE issued to this connection and awaiting its signature.
+    pub oper_challenge: Option<crate::security::challenge::OperChallenge>,
     /// Active batch state for client-to-server batches (e.g., draft/multiline).
     pub active_batch: Option<BatchState>,
     /// Reference tag for the active batch.
@@ -590,6 +719,20 @@ pub struct RegisteredState {
     pub sasl_buffer: String,
     /// Reattach info for bouncer session auto-replay (consumed after registration).
     pub reattach_info: Option<ReattachInfo>,
+    /// Mask KILL previewed to this oper, awaiting `CONFIRM`.
+    pub pending_mask_kill: Option<PendingMaskKill>,
+}
+
+/// A mask KILL whose matches were shown to the oper.
+///
+/// `KILL <mask> CONFIRM` is only accepted while this is pending for the same
+/// mask and the mask still matches exactly the users that were shown.
+#[derive(Debug, Clone)]
+pub struct PendingMaskKill {
+    pub mask: String,
+    /// UIDs listed in the preview.
+    pub uids: HashSet<String>,
+    pub expires: Instant,
 }
 
 impl RegisteredState {
@@ -646,6 +789,14 @@ impl SessionState for RegisteredState {
         self.is_tls
     }
 
+    fn is_tor(&self) -> bool {
+        self.is_tor
+    }
+
+    fn is_websocket(&self) -> bool {
+        self.is_websocket
+    }
+
     fn certfp(&self) -> Option<&str> {
         self.certfp.as_deref()
     }
@@ -744,6 +895,136 @@ mod tests {
         assert_eq!(registered.account, Some("testaccount".to_string()));
     }
 
+    #[test]
+    fn test_phase_full_handshake() {
+        // CAP LS, CAP REQ :sasl, AUTHENTICATE ..., CAP END, NICK, USER
+        let mut state = UnregisteredState::default();
+        assert_eq!(state.phase(), RegistrationPhase::AwaitingNickUser);
+        state.cap_negotiating = true;
+        assert_eq!(state.phase(), RegistrationPhase::CapNegotiating);
+        state.sasl_state = SaslState::WaitingForData;
+        assert_eq!(state.phase(), RegistrationPhase::SaslInProgress);
+        state.sasl_state = SaslState::Authenticated;
+        assert_eq!(state.phase(), RegistrationPhase::CapNegotiating);
+        state.cap_negotiating = false;
+        assert_eq!(state.phase(), RegistrationPhase::AwaitingNickUser);
+        state.nick = Some("test".to_string());
+        assert_eq!(state.phase(), RegistrationPhase::AwaitingNickUser);
+        state.user = Some("testuser".to_string());
+        assert_eq!(state.phase(), RegistrationPhase::Registered);
+        assert!(state.try_register().is_ok());
+    }
+
+    #[test]
+    fn test_phase_user_before_nick() {
+        let mut state = UnregisteredState {
+            user: Some("testuser".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(state.phase(), RegistrationPhase::AwaitingNickUser);
+        state.nick = Some("test".to_string());
+        assert_eq!(state.phase(), RegistrationPhase::Registered);
+    }
+
+    #[test]
+    fn test_phase_nick_user_before_cap_end() {
+        let mut state = UnregisteredState {
+            nick: Some("test".to_string()),
+            user: Some("testuser".to_string()),
+            cap_negotiating: true,
+            ..Default::default()
+        };
+        assert_eq!(state.phase(), RegistrationPhase::CapNegotiating);
+        state.cap_negotiating = false;
+        assert!(state.can_register());
+    }
+
+    #[test]
+    fn test_sasl_blocks_registration_until_finished() {
+        let mut state = UnregisteredState {
+            nick: Some("test".to_string()),
+            user: Some("testuser".to_string()),
+            cap_negotiating: true,
+            sasl_state: SaslState::WaitingForData,
+            ..Default::default()
+        };
+        assert!(!state.can_register());
+        assert!(!state.abort_overtaken_sasl());
+
+        // A failed exchange returns to negotiation, not to SASL
+        state.sasl_state = SaslState::None;
+        assert_eq!(state.phase(), RegistrationPhase::CapNegotiating);
+    }
+
+    #[test]
+    fn test_cap_end_before_sasl_completion_aborts_sasl() {
+        let mut state = UnregisteredState {
+            nick: Some("test".to_string()),
+            user: Some("testuser".to_string()),
+            cap_negotiating: true,
+            sasl_state: SaslState::WaitingForData,
+            sasl_buffer: "partial".to_string(),
+            ..Default::default()
+        };
+        state.cap_negotiating = false;
+        assert_eq!(state.phase(), RegistrationPhase::SaslInProgress);
+        assert!(!state.can_register());
+
+        assert!(state.abort_overtaken_sasl());
+        assert!(state.sasl_buffer.is_empty());
+        assert!(!state.abort_overtaken_sasl());
+        let registered = state.try_register().expect("should register");
+        assert_eq!(registered.account, None);
+    }
+
+    #[test]
+    fn test_cap_end_during_sasl_without_user_keeps_exchange() {
+        let mut state = UnregisteredState {
+            nick: Some("test".to_string()),
+            sasl_state: SaslState::WaitingForExternal,
+            ..Default::default()
+        };
+        assert_eq!(state.phase(), RegistrationPhase::SaslInProgress);
+        assert!(!state.abort_overtaken_sasl());
+        assert_eq!(state.sasl_state, SaslState::WaitingForExternal);
+    }
+
+    #[test]
+    fn test_phase_admits() {
+        for phase in [
+            RegistrationPhase::CapNegotiating,
+            RegistrationPhase::AwaitingNickUser,
+            RegistrationPhase::Registered,
+        ] {
+            for command in [
+                "PASS",
+                "WEBIRC",
+                "STARTTLS",
+                "SERVER",
+                "NICK",
+                "AUTHENTICATE",
+            ] {
+                assert!(phase.admits(command), "{phase:?} should admit {command}");
+            }
+        }
+
+        let sasl = RegistrationPhase::SaslInProgress;
+        for command in [
+            "AUTHENTICATE",
+            "CAP",
+            "NICK",
+            "USER",
+            "PING",
+            "PONG",
+            "QUIT",
+        ] {
+            assert!(sasl.admits(command), "SASL should admit {command}");
+        }
+        for command in ["PASS", "WEBIRC", "STARTTLS", "SERVER", "CAPAB", "PRIVMSG"] {
+            assert!(!sasl.admits(command), "SASL should not admit {command}");
+        }
+    }
+
     #[test]
     fn test_registered_has_cap() {
         let state = RegisteredState {
@@ -755,9 +1036,12 @@ mod tests {
             capabilities: ["echo-message".to_string()].into_iter().collect(),
             account: None,
             is_tls: false,
+            is_tor: false,
+            is_websocket: false,
             certfp: None,
             failed_oper_attempts: 0,
             last_oper_attempt: None,
+            oper_challenge: None,
             active_batch: None,
             active_batch_ref: None,
             cap_version: 302,
@@ -769,6 +1053,7 @@ mod tests {
             sasl_state: SaslState::default(),
             sasl_buffer: String::new(),
             reattach_info: None,
+            pending_mask_kill: None,
         };
 
         assert!(state.has_cap("echo-message"));
@@ -0,0 +1,260 @@
+//! Warm-boot state snapshot.
+//!
+//! On shutdown, volatile state that is otherwise rebuilt only by user activity
+//! is written to a CBOR file: every channel (modes, key, limit, topic,
+//! metadata and ban/except/invex/quiet lists) and the WHOWAS history. On the
+//! next start the snapshot is loaded before the gateway opens, then deleted so
+//! a later crash never restores stale state.
+//!
+//! Reputation scores are kept in the database and survive restarts without
+//! the snapshot.
+
+use crate::state::actor::ChannelEvent;
+use crate::state::{ListEntry, Matrix, Topic, WhowasEntry};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+/// Bumped whenever the snapshot layout changes; older files are ignored.
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Errors from reading or writing a snapshot.
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("serialization error: {0}")]
+    Serialization(String),
+
+    #[error("unsupported snapshot version {0}")]
+    Version(u32),
+}
+
+/// A channel's full restorable state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelSnapshot {
+    pub name: String,
+    pub created: i64,
+    /// Flag modes without parameters (e.g. `+nt`).
+    pub modes: String,
+    pub key: Option<String>,
+    pub limit: Option<i32>,
+    pub topic: Option<Topic>,
+    pub metadata: HashMap<String, String>,
+    pub bans: Vec<ListEntry>,
+    pub excepts: Vec<ListEntry>,
+    pub invex: Vec<ListEntry>,
+    pub quiets: Vec<ListEntry>,
+}
+
+/// Everything written to the snapshot file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub version: u32,
+    /// Unix timestamp (seconds) when the snapshot was taken.
+    pub taken_at: i64,
+    pub channels: Vec<ChannelSnapshot>,
+    /// WHOWAS groups keyed by lowercase nick, oldest first.
+    pub whowas: Vec<(String, Vec<WhowasEntry>)>,
+}
+
+impl StateSnapshot {
+    /// Collect a snapshot from live server state.
+    pub async fn capture(matrix: &Matrix) -> Self {
+        let senders: Vec<_> = matrix
+            .channel_manager
+            .channels
+            .iter()
+            .map(|e| e.value().clone())
+            .collect();
+
+        let mut channels = Vec::with_capacity(senders.len());
+        for tx in senders {
+            let (reply_tx, reply_rx) = oneshot::channel();
+            if tx
+                .send(ChannelEvent::GetSnapshot { reply_tx })
+                .await
+                .is_err()
+            {
+                continue;
+            }
+            if let Ok(channel) = reply_rx.await {
+                channels.push(channel);
+            }
+        }
+
+        Self {
+            version: SNAPSHOT_VERSION,
+            taken_at: chrono::Utc::now().timestamp(),
+            channels,
+            whowas: matrix.user_manager.export_whowas(),
+        }
+    }
+
+    /// Write the snapshot to disk (temp file + atomic rename).
+    ///
+    /// The temp file is flushed and synced before the rename, so a crash
+    /// never leaves a truncated snapshot in place of the old one.
+    pub fn save(&self, path: &Path) -> Result<(), SnapshotError> {
+        let temp_path = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        ciborium::into_writer(self, &mut writer)
+            .map_err(|e| SnapshotError::Serialization(e.to_string()))?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// Read a snapshot from disk.
+    pub fn load(path: &Path) -> Result<Self, SnapshotError> {
+        let reader = BufReader::new(File::open(path)?);
+        let snapshot: Self = ciborium::from_reader(reader)
+            .map_err(|e| SnapshotError::Serialization(e.to_string()))?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::Version(snapshot.version));
+        }
+        Ok(snapshot)
+    }
+
+    /// Apply the snapshot to a freshly created Matrix.
+    pub async fn restore(self, matrix: &Arc<Matrix>) {
+        let channel_count = self.channels.len();
+        let whowas_count = self.whowas.len();
+
+        matrix
+            .channel_manager
+            .restore_snapshot(self.channels, Arc::downgrade(matrix))
+            .await;
+        matrix.user_manager.restore_whowas(self.whowas);
+
+        info!(
+            channels = channel_count,
+            whowas = whowas_count,
+            "Restored state snapshot"
+        );
+    }
+}
+
+/// Capture and write a snapshot if enabled in the config.
+///
+/// Called on every shutdown path before clients are disconnected, while
+/// channels still exist. Also folds the CRDT journal, if one is running.
+pub async fn save_on_shutdown(matrix: &Matrix) {
+    if let Some(journal) = matrix.journal.get() {
+        journal.snapshot().await;
+    }
+
+    let config = &matrix.config.snapshot;
+    if !config.enabled {
+        return;
+    }
+
+    let snapshot = StateSnapshot::capture(matrix).await;
+    let channels = snapshot.channels.len();
+    match snapshot.save(Path::new(&config.path)) {
+        Ok(()) => info!(path = %config.path, channels, "State snapshot saved"),
+        Err(e) => warn!(path = %config.path, error = %e, "Failed to save state snapshot"),
+    }
+}
+
+/// Load, apply and delete the snapshot if enabled in the config.
+pub async fn restore_on_startup(matrix: &Arc<Matrix>) {
+    let config = &matrix.config.snapshot;
+    if !config.enabled {
+        return;
+    }
+
+    let path = Path::new(&config.path);
+    if !path.exists() {
+        info!(path = %config.path, "No state snapshot to restore");
+        return;
+    }
+
+    match StateSnapshot::load(path) {
+        Ok(snapshot) => {
+            let age = chrono::Utc::now().timestamp() - snapshot.taken_at;
+            if age < 0 || age as u64 > config.max_age_secs {
+                warn!(age_secs = age, "Ignoring stale state snapshot");
+            } else {
+                snapshot.restore(matrix).await;
+            }
+        }
+        Err(e) => warn!(path = %config.path, error = %e, "Failed to load state snapshot"),
+    }
+
+    if let Err(e) = fs::remove_file(path) {
+        warn!(path = %config.path, error = %e, "Failed to remove state snapshot");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_roundtrip() {
+        let path = std::env::temp_dir().join(format!("slircd-snap-{}", uuid::Uuid::new_v4()));
+        let snapshot = StateSnapshot {
+            version: SNAPSHOT_VERSION,
+            taken_at: 1_700_000_000,
+            channels: vec![ChannelSnapshot {
+                name: "#test".to_string(),
+                created: 1_600_000_000,
+                modes: "+ntk".to_string(),
+                key: Some("secret".to_string()),
+                limit: None,
+                topic: Some(Topic {
+                    text: "Hello".to_string(),
+                    set_by: "alice".to_string(),
+                    set_at: 1_650_000_000,
+                }),
+                metadata: HashMap::new(),
+                bans: vec![ListEntry {
+                    mask: "*!*@bad.host".to_string(),
+                    set_by: "alice".to_string(),
+                    set_at: 1_650_000_001,
+                    expires_at: None,
+                }],
+                excepts: Vec::new(),
+                invex: Vec::new(),
+                quiets: Vec::new(),
+            }],
+            whowas: Vec::new(),
+        };
+
+        snapshot.save(&path).unwrap();
+        let loaded = StateSnapshot::load(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(loaded.taken_at, 1_700_000_000);
+        let channel = &loaded.channels[0];
+        assert_eq!(channel.name, "#test");
+        assert_eq!(channel.key.as_deref(), Some("secret"));
+        assert_eq!(channel.topic.as_ref().unwrap().text, "Hello");
+        assert_eq!(channel.bans[0].mask, "*!*@bad.host");
+    }
+
+    #[test]
+    fn snapshot_rejects_other_versions() {
+        let path = std::env::temp_dir().join(format!("slircd-snap-{}", uuid::Uuid::new_v4()));
+        let snapshot = StateSnapshot {
+            version: SNAPSHOT_VERSION + 1,
+            taken_at: 0,
+            channels: Vec::new(),
+            whowas: Vec::new(),
+        };
+
+        snapshot.save(&path).unwrap();
+        let result = StateSnapshot::load(&path);
+        let _ = fs::remove_file(&path);
+
+        assert!(matches!(result, Err(SnapshotError::Version(_))));
+    }
+}
@@ -10,15 +10,15 @@ pub type Uid = String;
 /// Format: SID (3 chars) + Client ID (6 chars base36) = 9 chars total.
 /// Example: "001AAAAAB"
 ///
-/// Note: Counter starts at 2 because 0 (AAAAAA) and 1 (AAAAAB) are reserved
-/// for service pseudoclients (NickServ, ChanServ).
+/// Note: Counter starts at 5 because 0-4 (AAAAAA-AAAAAE) are reserved for
+/// service pseudoclients (NickServ, ChanServ, HostServ, OperServ, BotServ).
 pub struct UidGenerator {
     sid: String,
     counter: AtomicU64,
 }
 
-/// Start counter at 2 to skip reserved service UIDs (AAAAAA, AAAAAB).
-const UID_COUNTER_START: u64 = 2;
+/// Start counter at 5 to skip reserved service UIDs (AAAAAA-AAAAAE).
+const UID_COUNTER_START: u64 = 5;
 
 impl UidGenerator {
     /// Create a new UID generator for the given server ID.
@@ -56,10 +56,10 @@ mod tests {
     #[test]
     fn test_uid_generation() {
         let generator = UidGenerator::new("001".to_string());
-        // First UID is AAAAAC because AAAAAA/AAAAAB are reserved for services
-        assert_eq!(generator.next(), "001AAAAAC");
-        assert_eq!(generator.next(), "001AAAAAD");
-        assert_eq!(generator.next(), "001AAAAAE");
+        // First UID is AAAAAF because AAAAAA-AAAAAE are reserved for services
+        assert_eq!(generator.next(), "001AAAAAF");
+        assert_eq!(generator.next(), "001AAAAAG");
+        assert_eq!(generator.next(), "001AAAAAH");
     }
 
     #[test]
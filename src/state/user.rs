@@ -1,10 +1,11 @@
 //! User-related types and state.
 
 use crate::state::client::SessionId;
-use slirc_proto::sync::clock::HybridTimestamp;
+use serde::{Deserialize, Serialize};
+use slirc_proto::sync::clock::{HybridTimestamp, ServerId};
 use slirc_proto::sync::traits::LwwRegister;
 use slirc_proto::sync::user::{UserCrdt, UserModesCrdt};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
 /// A connected user.
@@ -21,6 +22,8 @@ pub struct User {
     pub visible_host: String,
     /// Unique session identifier for this connection (guards against ghost joins).
     pub session_id: Uuid,
+    /// Peer link that introduced this user; `None` for users on this server.
+    pub server: Option<ServerId>,
     /// Channels this user is in (lowercase names).
     pub channels: HashSet<String>,
     /// User modes.
@@ -37,6 +40,8 @@ pub struct User {
     pub caps: HashSet<String>,
     /// TLS certificate fingerprint (SHA-256 hex) if client presented one.
     pub certfp: Option<String>,
+    /// WEBIRC gateway this user connected through.
+    pub gateway: Option<String>,
     /// SILENCE list: masks of users to ignore (server-side ignore).
     pub silence_list: HashSet<String>,
     /// ACCEPT list: nicknames allowed to PM even if +R is set (Caller ID).
@@ -45,11 +50,53 @@ pub struct User {
     pub created_at: i64,
     /// Last modified timestamp for CRDT synchronization.
     pub last_modified: HybridTimestamp,
+    /// When away, account and modes last changed; see [`UserStamps`].
+    pub stamps: UserStamps,
     /// Last active timestamp (Unix millis) for IDLE tracking.
     /// Updated on every PRIVMSG/NOTICE sent by the user.
     pub last_active: std::sync::atomic::AtomicI64,
 }
 
+/// When fields that change after registration were last set.
+///
+/// Each is replicated as its own LWW register, so an AWAY on one server and
+/// an IDENTIFY on another both survive the merge. Fields with no stamp were
+/// last set at the user's `last_modified`.
+#[derive(Debug, Default, Clone)]
+pub struct UserStamps {
+    pub nick: Option<HybridTimestamp>,
+    pub user: Option<HybridTimestamp>,
+    pub realname: Option<HybridTimestamp>,
+    pub visible_host: Option<HybridTimestamp>,
+    pub away: Option<HybridTimestamp>,
+    pub account: Option<HybridTimestamp>,
+    /// Keyed by mode character; `o` also covers the operator type.
+    pub modes: HashMap<char, HybridTimestamp>,
+}
+
+/// Boolean user modes replicated as LWW registers.
+const CRDT_FLAGS: [char; 13] = [
+    'i', 'w', 'o', 'r', 'Z', 'R', 'T', 'B', 'S', 'p', 'd', 'g', 'N',
+];
+
+impl UserStamps {
+    /// Take the stamps of a merged CRDT, so converting back reproduces it.
+    fn from_crdt(crdt: &UserCrdt) -> Self {
+        Self {
+            nick: Some(crdt.nick.timestamp()),
+            user: Some(crdt.user.timestamp()),
+            realname: Some(crdt.realname.timestamp()),
+            visible_host: Some(crdt.visible_host.timestamp()),
+            away: Some(crdt.away.timestamp()),
+            account: Some(crdt.account.timestamp()),
+            modes: CRDT_FLAGS
+                .iter()
+                .filter_map(|&c| Some((c, crdt.modes.flag(c)?.timestamp())))
+                .collect(),
+        }
+    }
+}
+
 /// User modes.
 #[derive(Debug, Default, Clone)]
 pub struct UserModes {
@@ -124,6 +171,30 @@ impl UserModes {
         if s == "+" { "+".to_string() } else { s }
     }
 
+    /// Set a boolean mode by its mode character.
+    ///
+    /// Returns `false` for characters that are not boolean user modes.
+    pub fn set_flag(&mut self, mode: char, on: bool) -> bool {
+        let flag = match mode {
+            'i' => &mut self.invisible,
+            'w' => &mut self.wallops,
+            'o' => &mut self.oper,
+            'r' => &mut self.registered,
+            'Z' => &mut self.secure,
+            'R' => &mut self.registered_only,
+            'T' => &mut self.no_ctcp,
+            'B' => &mut self.bot,
+            'S' => &mut self.service,
+            'p' => &mut self.hide_channels,
+            'd' => &mut self.deaf,
+            'g' => &mut self.caller_id,
+            'N' => &mut self.net_admin,
+            _ => return false,
+        };
+        *flag = on;
+        true
+    }
+
     /// Check if user has a specific snomask.
     pub fn has_snomask(&self, mask: char) -> bool {
         self.snomasks.contains(&mask)
@@ -146,7 +217,7 @@ impl UserModes {
             deaf: *crdt.deaf.value(),
             caller_id: *crdt.caller_id.value(),
             net_admin: *crdt.net_admin.value(),
-            service: false, // Services are never remote users
+            service: *crdt.service.value(),
         }
     }
 
@@ -169,6 +240,7 @@ impl UserModes {
         crdt.deaf = LwwRegister::new(self.deaf, timestamp);
         crdt.caller_id = LwwRegister::new(self.caller_id, timestamp);
         crdt.net_admin = LwwRegister::new(self.net_admin, timestamp);
+        crdt.service = LwwRegister::new(self.service, timestamp);
         crdt
     }
 }
@@ -213,15 +285,8 @@ impl User {
         } = params;
 
         // Try to parse as IP for proper cloaking, fall back to hostname cloaking
-        let visible_host = if let Ok(addr) = ip.parse::<std::net::IpAddr>() {
-            crate::security::cloaking::cloak_ip_hmac_with_suffix(
-                &addr,
-                &cloak_secret,
-                &cloak_suffix,
-            )
-        } else {
-            crate::security::cloaking::cloak_hostname(&host, &cloak_secret)
-        };
+        let visible_host =
+            crate::security::cloaking::cloak_user_host(&ip, &host, &cloak_secret, &cloak_suffix);
         Self {
             uid,
             nick,
@@ -231,6 +296,7 @@ impl User {
             ip,
             visible_host,
             session_id,
+            server: None,
             channels: HashSet::new(),
             modes: UserModes::default(),
             account: None,
@@ -239,10 +305,12 @@ impl User {
             metadata: std::collections::HashMap::new(),
             caps,
             certfp,
+            gateway: None,
             silence_list: HashSet::new(),
             accept_list: HashSet::new(),
             created_at: chrono::Utc::now().timestamp(),
             last_modified,
+            stamps: UserStamps::default(),
             last_active: std::sync::atomic::AtomicI64::new(chrono::Utc::now().timestamp_millis()),
         }
     }
@@ -258,8 +326,21 @@ impl User {
             self.visible_host.clone(),
             self.last_modified,
         );
-        crdt.account = LwwRegister::new(self.account.clone(), self.last_modified);
-        crdt.away = LwwRegister::new(self.away.clone(), self.last_modified);
+        let stamp = |ts: Option<HybridTimestamp>| ts.unwrap_or(self.last_modified);
+        crdt.nick = LwwRegister::new(self.nick.clone(), stamp(self.stamps.nick));
+        crdt.user = LwwRegister::new(self.user.clone(), stamp(self.stamps.user));
+        crdt.realname = LwwRegister::new(self.realname.clone(), stamp(self.stamps.realname));
+        crdt.visible_host =
+            LwwRegister::new(self.visible_host.clone(), stamp(self.stamps.visible_host));
+        let account_ts = self.stamps.account.unwrap_or(self.last_modified);
+        crdt.account = LwwRegister::new(self.account.clone(), account_ts);
+        let away_ts = self.stamps.away.unwrap_or(self.last_modified);
+        crdt.away = LwwRegister::new(self.away.clone(), away_ts);
+        crdt.ip = LwwRegister::new(Some(self.ip.clone()), self.last_modified);
+        crdt.certfp = LwwRegister::new(self.certfp.clone(), self.last_modified);
+        for (key, value) in &self.metadata {
+            crdt.set_metadata(key.clone(), Some(value.clone()), self.last_modified);
+        }
         for chan in &self.channels {
             crdt.channels.add(chan.clone(), self.last_modified);
         }
@@ -267,6 +348,14 @@ impl User {
             crdt.caps.add(cap.clone(), self.last_modified);
         }
         crdt.modes = self.modes.to_crdt(self.last_modified);
+        for (&mode, &ts) in &self.stamps.modes {
+            if let Some(reg) = crdt.modes.flag_mut(mode) {
+                *reg = LwwRegister::new(*reg.value(), ts);
+            }
+        }
+        if let Some(&ts) = self.stamps.modes.get(&'o') {
+            crdt.modes.oper_type = LwwRegister::new(self.modes.oper_type.clone(), ts);
+        }
         for mask in &self.silence_list {
             crdt.silence_list.add(mask.clone(), self.last_modified);
         }
@@ -276,30 +365,105 @@ impl User {
         crdt
     }
 
+    /// Change the nickname.
+    pub fn set_nick(&mut self, nick: String, timestamp: HybridTimestamp) {
+        self.nick = nick;
+        self.stamps.nick = Some(timestamp);
+    }
+
+    /// Change the username (ident).
+    pub fn set_ident(&mut self, user: String, timestamp: HybridTimestamp) {
+        self.user = user;
+        self.stamps.user = Some(timestamp);
+    }
+
+    /// Change the realname (gecos).
+    pub fn set_realname(&mut self, realname: String, timestamp: HybridTimestamp) {
+        self.realname = realname;
+        self.stamps.realname = Some(timestamp);
+    }
+
+    /// Change the hostname shown to other users.
+    pub fn set_visible_host(&mut self, visible_host: String, timestamp: HybridTimestamp) {
+        self.visible_host = visible_host;
+        self.stamps.visible_host = Some(timestamp);
+    }
+
+    /// Set or clear the away message.
+    pub fn set_away(&mut self, away: Option<String>, timestamp: HybridTimestamp) {
+        self.away = away;
+        self.stamps.away = Some(timestamp);
+    }
+
+    /// Log in to (`Some`) or out of (`None`) an account.
+    pub fn set_account(&mut self, account: Option<String>, timestamp: HybridTimestamp) {
+        self.account = account;
+        self.stamps.account = Some(timestamp);
+    }
+
+    /// Set or clear a boolean user mode by its mode character.
+    ///
+    /// Clearing +o also drops the operator type. Returns `false` for
+    /// characters that are not boolean user modes.
+    pub fn set_mode(&mut self, mode: char, on: bool, timestamp: HybridTimestamp) -> bool {
+        if !self.modes.set_flag(mode, on) {
+            return false;
+        }
+        if mode == 'o' && !on {
+            self.modes.oper_type = None;
+        }
+        self.stamps.modes.insert(mode, timestamp);
+        true
+    }
+
+    /// Record that modes already applied to `self.modes` changed now.
+    pub fn stamp_modes(
+        &mut self,
+        modes: impl IntoIterator<Item = char>,
+        timestamp: HybridTimestamp,
+    ) {
+        for mode in modes {
+            // Local operators are plain +o here
+            let mode = if mode == 'O' { 'o' } else { mode };
+            if CRDT_FLAGS.contains(&mode) {
+                self.stamps.modes.insert(mode, timestamp);
+            }
+        }
+    }
+
     /// Create a User from a CRDT representation.
     pub fn from_crdt(crdt: UserCrdt) -> Self {
         let last_modified = crdt.nick.timestamp();
+        let stamps = UserStamps::from_crdt(&crdt);
         Self {
             uid: crdt.uid.clone(),
             nick: crdt.nick.value().clone(),
             user: crdt.user.value().clone(),
             realname: crdt.realname.value().clone(),
             host: crdt.host.value().clone(),
-            ip: "0.0.0.0".to_string(), // Remote users don't have local IP
+            // Older peers don't send the IP
+            ip: crdt
+                .ip
+                .value()
+                .clone()
+                .unwrap_or_else(|| "0.0.0.0".to_string()),
             visible_host: crdt.visible_host.value().clone(),
             session_id: Uuid::nil(), // Remote users don't have local session
+            server: None,
             channels: crdt.channels.iter().cloned().collect(),
             modes: UserModes::from_crdt(&crdt.modes),
             account: crdt.account.value().clone(),
             account_id: None, // Cached ID not synced via CRDT
             away: crdt.away.value().clone(),
-            metadata: std::collections::HashMap::new(),
+            metadata: metadata_from_crdt(&crdt),
             caps: crdt.caps.iter().cloned().collect(),
-            certfp: None,
+            certfp: crdt.certfp.value().clone(),
+            gateway: None,
             silence_list: crdt.silence_list.iter().cloned().collect(),
             accept_list: crdt.accept_list.iter().cloned().collect(),
             created_at: last_modified.millis / 1000, // Convert from HybridTimestamp millis
             last_modified,
+            stamps,
             // For remote users, accurate idle time requires protocol extension. Default to 'now'.
             last_active: std::sync::atomic::AtomicI64::new(last_modified.millis),
         }
@@ -326,11 +490,17 @@ impl User {
         self.account = new_account;
 
         self.away = merged.away.value().clone();
+        if let Some(ip) = merged.ip.value() {
+            self.ip = ip.clone();
+        }
+        self.certfp = merged.certfp.value().clone();
+        self.metadata = metadata_from_crdt(&merged);
         self.caps = merged.caps.iter().cloned().collect();
         self.silence_list = merged.silence_list.iter().cloned().collect();
         self.accept_list = merged.accept_list.iter().cloned().collect();
 
         self.last_modified = merged.nick.timestamp();
+        self.stamps = UserStamps::from_crdt(&merged);
 
         // Remote users have a nil session_id; for them, keep created_at derived from CRDT.
         // For local users, preserve the local created_at.
@@ -340,8 +510,15 @@ impl User {
     }
 }
 
+/// Collect the live (non-deleted) metadata keys of a CRDT user.
+fn metadata_from_crdt(crdt: &UserCrdt) -> std::collections::HashMap<String, String> {
+    crdt.metadata_iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
 /// An entry in the WHOWAS history for a disconnected user.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WhowasEntry {
     /// The user's nickname (case-preserved).
     pub nick: String,
@@ -530,4 +707,98 @@ mod tests {
         assert!(modes.has_snomask('k'));
         assert!(!modes.has_snomask('o'));
     }
+
+    // ========== CRDT round-trip tests ==========
+
+    fn user_at(millis: i64) -> User {
+        let ts = HybridTimestamp::new(millis, 0, &slirc_proto::sync::ServerId::new("001"));
+        User::new(UserParams {
+            uid: "001AAAAAA".to_string(),
+            nick: "nick".to_string(),
+            user: "user".to_string(),
+            realname: "Real Name".to_string(),
+            host: "host.example".to_string(),
+            ip: "192.0.2.1".to_string(),
+            cloak_secret: "secret".to_string(),
+            cloak_suffix: "ip".to_string(),
+            caps: HashSet::new(),
+            certfp: None,
+            last_modified: ts,
+            session_id: Uuid::new_v4(),
+        })
+    }
+
+    #[test]
+    fn later_changes_survive_merge_with_stale_copy() {
+        let server = slirc_proto::sync::ServerId::new("001");
+        let mut user = user_at(1000);
+        let stale = user.to_crdt();
+
+        let now = HybridTimestamp::new(2000, 0, &server);
+        user.set_away(Some("lunch".to_string()), now);
+        user.set_account(Some("acct".to_string()), now);
+        user.set_mode('o', true, now);
+        user.modes.invisible = true;
+        user.stamp_modes("+i".chars(), now);
+
+        user.merge_crdt(stale);
+        assert_eq!(user.away.as_deref(), Some("lunch"));
+        assert_eq!(user.account.as_deref(), Some("acct"));
+        assert!(user.modes.oper);
+        assert!(user.modes.invisible);
+    }
+
+    #[test]
+    fn concurrent_field_changes_both_apply() {
+        let server = slirc_proto::sync::ServerId::new("002");
+        let mut local = user_at(1000);
+        let mut remote = User::from_crdt(local.to_crdt());
+
+        local.set_away(
+            Some("brb".to_string()),
+            HybridTimestamp::new(2000, 0, &server),
+        );
+        remote.set_mode('o', true, HybridTimestamp::new(2500, 0, &server));
+        remote.modes.oper_type = Some("admin".to_string());
+        remote.set_away(None, HybridTimestamp::new(1500, 0, &server));
+
+        local.merge_crdt(remote.to_crdt());
+        assert_eq!(local.away.as_deref(), Some("brb"));
+        assert!(local.modes.oper);
+        assert_eq!(local.modes.oper_type.as_deref(), Some("admin"));
+
+        // The stamps came along, so converting back loses nothing
+        let mut stale = user_at(1000);
+        stale.merge_crdt(local.to_crdt());
+        assert_eq!(stale.away.as_deref(), Some("brb"));
+        assert!(stale.modes.oper);
+    }
+
+    #[test]
+    fn crdt_round_trip_preserves_ip_certfp_metadata_and_service() {
+        let ts = HybridTimestamp::new(1000, 0, &slirc_proto::sync::ServerId::new("001"));
+        let mut user = User::new(UserParams {
+            uid: "001AAAAAA".to_string(),
+            nick: "nick".to_string(),
+            user: "user".to_string(),
+            realname: "Real Name".to_string(),
+            host: "host.example".to_string(),
+            ip: "192.0.2.1".to_string(),
+            cloak_secret: "secret".to_string(),
+            cloak_suffix: "ip".to_string(),
+            caps: HashSet::new(),
+            certfp: Some("abcdef".to_string()),
+            last_modified: ts,
+            session_id: Uuid::new_v4(),
+        });
+        user.modes.service = true;
+        user.metadata
+            .insert("url".to_string(), "https://example.org".to_string());
+
+        let remote = User::from_crdt(user.to_crdt());
+        assert_eq!(remote.ip, "192.0.2.1");
+        assert_eq!(remote.certfp.as_deref(), Some("abcdef"));
+        assert_eq!(remote.metadata, user.metadata);
+        assert!(remote.modes.service);
+    }
 }
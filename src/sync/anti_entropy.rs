@@ -0,0 +1,590 @@
+//! Anti-entropy repair between linked servers.
+//!
+//! Live propagation is fire-and-forget: if a UID, JOIN or QUIT is lost on a
+//! link, the two sides drift apart and nothing notices. Every
+//! [`ANTI_ENTROPY_INTERVAL`] each server sends every synced peer a digest of
+//! the state it is authoritative for - its own users and their channel
+//! memberships - hashed into [`DIGEST_BUCKETS`] buckets:
+//!
+//! ```text
+//! :<sid> ENCAP <peer> DIGEST U <hash>,<hash>,...
+//! :<sid> ENCAP <peer> DIGEST C <hash>,<hash>,...
+//! ```
+//!
+//! The peer hashes its own view of those entities the same way and asks for
+//! each bucket that differs, listing what it holds there:
+//!
+//! ```text
+//! :<peer> ENCAP <sid> DIGESTREQ U <bucket> :<uid> <uid> ...
+//! :<peer> ENCAP <sid> DIGESTREQ C <bucket> :<channel> <channel> ...
+//! ```
+//!
+//! The owner re-sends only that bucket: a UID for each of its users in it and
+//! a QUIT for each listed UID it no longer has; for channels, the SJOIN plus
+//! `DIGESTCHAN <channel> :<uid> ...` naming its members, so the peer also
+//! drops memberships whose PART it missed.
+//!
+//! Service pseudoclients are left out on both sides; every server runs its
+//! own. A request lists at most [`MAX_REQUEST_KEYS`] entries, so a badly
+//! diverged bucket may take a few rounds to converge.
+
+use crate::handlers::server::part::part_remote_member;
+use crate::state::Matrix;
+use crate::state::actor::ChannelEvent;
+use crate::state::dashmap_ext::DashMapExt;
+use crate::state::observer::GlobalBanType;
+use sha2::{Digest as _, Sha256};
+use slirc_proto::sync::clock::ServerId;
+use slirc_proto::{Command, Message, Prefix, irc_to_lower};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info, warn};
+
+use super::SyncManager;
+use super::burst::{channel_burst, generate_burst};
+use super::handshake::HandshakeState;
+use super::manager::BAN_TOMBSTONE_TTL;
+use super::observer::{ban_remove_command, login_command, uid_mode_string};
+
+/// How often digests are sent to each peer.
+pub const ANTI_ENTROPY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Number of buckets a digest is split into.
+pub const DIGEST_BUCKETS: usize = 16;
+
+/// Most entries named in one `DIGESTREQ`.
+pub const MAX_REQUEST_KEYS: usize = 40;
+
+/// Which state a digest covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestKind {
+    /// Users introduced by the sending server.
+    Users,
+    /// Channel memberships of those users.
+    Channels,
+}
+
+impl DigestKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            DigestKind::Users => "U",
+            DigestKind::Channels => "C",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "U" => Some(DigestKind::Users),
+            "C" => Some(DigestKind::Channels),
+            _ => None,
+        }
+    }
+}
+
+/// One server's view of another server's entities, hashed into buckets.
+struct Digest {
+    hashes: [u64; DIGEST_BUCKETS],
+    keys: [Vec<String>; DIGEST_BUCKETS],
+}
+
+impl Digest {
+    fn new() -> Self {
+        Self {
+            hashes: [0; DIGEST_BUCKETS],
+            keys: std::array::from_fn(|_| Vec::new()),
+        }
+    }
+
+    /// Add an entity; bucket hashes are order-independent sums.
+    fn add(&mut self, key: String, hash: u64) {
+        let bucket = bucket_of(&key);
+        self.hashes[bucket] = self.hashes[bucket].wrapping_add(hash);
+        self.keys[bucket].push(key);
+    }
+
+    fn encode(&self) -> String {
+        self.hashes
+            .iter()
+            .map(|h| format!("{:x}", h))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn decode(s: &str) -> Option<[u64; DIGEST_BUCKETS]> {
+        let mut hashes = [0; DIGEST_BUCKETS];
+        let mut parts = s.split(',');
+        for hash in hashes.iter_mut() {
+            *hash = u64::from_str_radix(parts.next()?, 16).ok()?;
+        }
+        parts.next().is_none().then_some(hashes)
+    }
+}
+
+/// Stable hash of `fields`; both ends of a link must agree on it.
+fn hash_fields(fields: &[&str]) -> u64 {
+    let mut hasher = Sha256::new();
+    for field in fields {
+        hasher.update(field.as_bytes());
+        hasher.update([0]);
+    }
+    let digest = hasher.finalize();
+    u64::from_be_bytes(digest[..8].try_into().expect("SHA-256 output is 32 bytes"))
+}
+
+fn bucket_of(key: &str) -> usize {
+    (hash_fields(&[key]) % DIGEST_BUCKETS as u64) as usize
+}
+
+/// Digest of the non-service users whose UID starts with `origin`.
+///
+/// Each user is hashed over the fields a UID carries, so a user and its
+/// copy on a peer hash alike.
+async fn user_digest(matrix: &Matrix, origin: &str) -> Digest {
+    let mut digest = Digest::new();
+    let users: Vec<_> = matrix
+        .user_manager
+        .users
+        .iter()
+        .filter(|e| e.key().starts_with(origin))
+        .map(|e| e.value().clone())
+        .collect();
+
+    for user_arc in users {
+        let user = user_arc.read().await;
+        if user.modes.service {
+            continue;
+        }
+        let modes = uid_mode_string(&user.modes.to_crdt(user.last_modified));
+        let hash = hash_fields(&[
+            &user.uid,
+            &user.nick,
+            &user.user,
+            &user.visible_host,
+            &modes,
+            &user.realname,
+        ]);
+        digest.add(user.uid.clone(), hash);
+    }
+    digest
+}
+
+/// Digest of the channel memberships of `origin`'s non-service users.
+///
+/// Channels none of those users are in are left out.
+async fn channel_digest(matrix: &Matrix, origin: &str) -> Digest {
+    let mut digest = Digest::new();
+    let channels: Vec<_> = matrix
+        .channel_manager
+        .channels
+        .iter()
+        .map(|e| (e.key().clone(), e.value().clone()))
+        .collect();
+
+    for (name, tx) in channels {
+        let members = origin_members(matrix, &tx, origin).await;
+        if members.is_empty() {
+            continue;
+        }
+        let mut fields = vec![name.as_str()];
+        for (uid, prefixes) in &members {
+            fields.push(uid);
+            fields.push(prefixes);
+        }
+        let hash = hash_fields(&fields);
+        digest.add(name, hash);
+    }
+    digest
+}
+
+async fn digest_of(matrix: &Matrix, kind: DigestKind, origin: &str) -> Digest {
+    match kind {
+        DigestKind::Users => user_digest(matrix, origin).await,
+        DigestKind::Channels => channel_digest(matrix, origin).await,
+    }
+}
+
+/// Members of a channel whose UID starts with `origin`, services excluded,
+/// as `(uid, prefixes)` sorted by UID.
+async fn origin_members(
+    matrix: &Matrix,
+    tx: &mpsc::Sender<ChannelEvent>,
+    origin: &str,
+) -> Vec<(String, String)> {
+    let (reply_tx, reply_rx) = oneshot::channel();
+    if tx
+        .send(ChannelEvent::GetMembers { reply_tx })
+        .await
+        .is_err()
+    {
+        return Vec::new();
+    }
+    let Ok(members) = reply_rx.await else {
+        return Vec::new();
+    };
+
+    let mut result = Vec::new();
+    for (uid, modes) in members.iter() {
+        if !uid.starts_with(origin) {
+            continue;
+        }
+        if let Some(user_arc) = matrix.user_manager.users.get_cloned(uid)
+            && user_arc.read().await.modes.service
+        {
+            continue;
+        }
+        result.push((uid.clone(), modes.all_prefix_chars()));
+    }
+    result.sort();
+    result
+}
+
+fn encap(matrix: &Matrix, peer: &ServerId, subcommand: &str, params: Vec<String>) -> Message {
+    Message {
+        tags: None,
+        prefix: Some(Prefix::new_from_str(matrix.server_info.sid.as_str())),
+        command: Command::ENCAP(peer.as_str().to_string(), subcommand.to_string(), params),
+    }
+}
+
+async fn send_to_peer(matrix: &Matrix, peer: &ServerId, msg: Message) {
+    let Some(link) = matrix.sync_manager.get_peer_for_server(peer) else {
+        debug!(peer = %peer.as_str(), "Peer gone, dropping anti-entropy message");
+        return;
+    };
+    if let Err(e) = link.tx.send(Arc::new(msg)).await {
+        warn!(peer = %peer.as_str(), error = %e, "Failed to send anti-entropy message");
+    }
+}
+
+impl SyncManager {
+    /// Send digests to every synced peer every [`ANTI_ENTROPY_INTERVAL`],
+    /// and resync every peer whenever [`SyncManager::resync`] is woken.
+    pub fn start_anti_entropy(
+        &self,
+        matrix: Arc<Matrix>,
+        mut shutdown_rx: tokio::sync::broadcast::Receiver<()>,
+    ) {
+        let resync = Arc::clone(&self.resync);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(ANTI_ENTROPY_INTERVAL);
+            // The first tick fires immediately; links that exist now have
+            // just exchanged bursts.
+            interval.tick().await;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => send_digests(&matrix).await,
+                    _ = resync.notified() => resync_peers(&matrix).await,
+                    _ = shutdown_rx.recv() => {
+                        info!("Anti-entropy task stopping due to shutdown");
+                        break;
+                    }
+                }
+            }
+        });
+    }
+}
+
+fn synced_peers(matrix: &Matrix) -> Vec<ServerId> {
+    matrix
+        .sync_manager
+        .links
+        .iter()
+        .filter(|e| e.value().state == HandshakeState::Synced)
+        .map(|e| e.key().clone())
+        .collect()
+}
+
+/// Re-send our state to every synced peer after replication events were
+/// dropped: the removals of recently removed bans, the burst (bans, users,
+/// channels), the account of each of our users, and then digests, so peers
+/// also remove what we no longer have.
+pub async fn resync_peers(matrix: &Matrix) {
+    let peers = synced_peers(matrix);
+    info!(
+        peers = peers.len(),
+        "Resyncing peers after dropped replication events"
+    );
+    let local_sid = matrix.server_info.sid.as_str();
+    let removed = ban_tombstones(matrix);
+    let accounts = local_accounts(matrix).await;
+    for peer in &peers {
+        let burst = generate_burst(matrix, local_sid, peer.as_str(), None).await;
+        // A ban added again since its removal is in the burst; leave it be
+        let active: HashSet<_> = burst.iter().filter_map(burst_ban).collect();
+        let removals: Vec<_> = removed
+            .iter()
+            .filter(|(ban_type, mask)| !active.contains(&(*ban_type, mask.as_str())))
+            .map(|(ban_type, mask)| Message {
+                tags: None,
+                prefix: Some(Prefix::ServerName(matrix.server_info.name.clone())),
+                command: ban_remove_command(*ban_type, mask),
+            })
+            .collect();
+        for msg in removals {
+            send_to_peer(matrix, peer, msg).await;
+        }
+
+        // Servers are not replicated through the observer; the peer already
+        // knows them all
+        for msg in burst {
+            if !matches!(msg.command, Command::SID(..)) {
+                send_to_peer(matrix, peer, msg).await;
+            }
+        }
+
+        // The burst carries no accounts, so a dropped logout needs this
+        for (uid, account) in &accounts {
+            let msg = Message {
+                tags: None,
+                prefix: Some(Prefix::new_from_str(local_sid)),
+                command: login_command(uid, account.as_deref()),
+            };
+            send_to_peer(matrix, peer, msg).await;
+        }
+    }
+    send_digests(matrix).await;
+}
+
+/// Bans removed within [`BAN_TOMBSTONE_TTL`], forgetting older ones.
+fn ban_tombstones(matrix: &Matrix) -> Vec<(GlobalBanType, String)> {
+    let tombstones = &matrix.sync_manager.ban_tombstones;
+    tombstones.retain(|_, removed_at| removed_at.elapsed() < BAN_TOMBSTONE_TTL);
+    tombstones.iter().map(|e| e.key().clone()).collect()
+}
+
+/// The global ban a burst message adds, if any.
+fn burst_ban(msg: &Message) -> Option<(GlobalBanType, &str)> {
+    let (ban_type, mask) = match &msg.command {
+        Command::SHUN(mask, _) => (GlobalBanType::Shun, mask),
+        Command::Raw(name, params) => {
+            let ban_type = match name.as_str() {
+                "GLINE" => GlobalBanType::Gline,
+                "ZLINE" => GlobalBanType::Zline,
+                "RLINE" => GlobalBanType::Rline,
+                _ => return None,
+            };
+            (ban_type, params.first()?)
+        }
+        _ => return None,
+    };
+    Some((ban_type, mask.as_str()))
+}
+
+/// The account of each of our own non-service users, `None` when logged out.
+async fn local_accounts(matrix: &Matrix) -> Vec<(String, Option<String>)> {
+    let local_sid = matrix.server_info.sid.as_str();
+    let users: Vec<_> = matrix
+        .user_manager
+        .users
+        .iter()
+        .filter(|e| e.key().starts_with(local_sid))
+        .map(|e| e.value().clone())
+        .collect();
+    let mut accounts = Vec::with_capacity(users.len());
+    for user in users {
+        let user = user.read().await;
+        if user.modes.service {
+            continue;
+        }
+        accounts.push((user.uid.clone(), user.account.clone()));
+    }
+    accounts
+}
+
+/// Send our user and channel digests to every synced peer.
+pub async fn send_digests(matrix: &Matrix) {
+    let peers = synced_peers(matrix);
+    if peers.is_empty() {
+        return;
+    }
+
+    let local_sid = matrix.server_info.sid.as_str();
+    for kind in [DigestKind::Users, DigestKind::Channels] {
+        let encoded = digest_of(matrix, kind, local_sid).await.encode();
+        for peer in &peers {
+            let params = vec![kind.as_str().to_string(), encoded.clone()];
+            send_to_peer(matrix, peer, encap(matrix, peer, "DIGEST", params)).await;
+        }
+    }
+}
+
+/// `DIGEST <kind> <hashes>` from `peer`: request every bucket that differs
+/// from our view of the peer's state.
+pub(crate) async fn handle_digest(matrix: &Matrix, peer: &ServerId, kind: &str, hashes: &str) {
+    let (Some(kind), Some(theirs)) = (DigestKind::parse(kind), Digest::decode(hashes)) else {
+        warn!(peer = %peer.as_str(), kind = %kind, "Malformed DIGEST");
+        return;
+    };
+
+    let ours = digest_of(matrix, kind, peer.as_str()).await;
+    for (bucket, (mine, their)) in ours.hashes.iter().zip(theirs.iter()).enumerate() {
+        if mine == their {
+            continue;
+        }
+        debug!(peer = %peer.as_str(), kind = ?kind, bucket, "Digest bucket differs");
+        crate::metrics::inc_distributed_digest_mismatches(peer.as_str(), kind.as_str());
+
+        let held: Vec<&str> = ours.keys[bucket]
+            .iter()
+            .take(MAX_REQUEST_KEYS)
+            .map(String::as_str)
+            .collect();
+        let params = vec![
+            kind.as_str().to_string(),
+            bucket.to_string(),
+            held.join(" "),
+        ];
+        send_to_peer(matrix, peer, encap(matrix, peer, "DIGESTREQ", params)).await;
+    }
+}
+
+/// `DIGESTREQ <kind> <bucket> :<held>` from `peer`: re-send our state for
+/// that bucket, and retract whatever the peer holds that we no longer have.
+pub(crate) async fn handle_digest_request(
+    matrix: &Matrix,
+    peer: &ServerId,
+    kind: &str,
+    bucket: &str,
+    held: &str,
+) {
+    let (Some(kind), Some(bucket)) = (
+        DigestKind::parse(kind),
+        bucket.parse::<usize>().ok().filter(|b| *b < DIGEST_BUCKETS),
+    ) else {
+        warn!(peer = %peer.as_str(), kind = %kind, bucket = %bucket, "Malformed DIGESTREQ");
+        return;
+    };
+
+    let local_sid = matrix.server_info.sid.as_str();
+    let ours = digest_of(matrix, kind, local_sid).await;
+    let owned = &ours.keys[bucket];
+    match kind {
+        DigestKind::Users => {
+            for uid in owned {
+                let Some(user_arc) = matrix.user_manager.users.get_cloned(uid) else {
+                    continue;
+                };
+                let crdt = user_arc.read().await.to_crdt();
+                let uid_cmd = matrix.sync_manager.build_uid_command(&crdt);
+                send_to_peer(matrix, peer, Message::from(uid_cmd)).await;
+            }
+            for uid in held.split_whitespace() {
+                if !uid.starts_with(local_sid) || matrix.user_manager.users.contains_key(uid) {
+                    continue;
+                }
+                let quit = Message {
+                    tags: None,
+                    prefix: Some(Prefix::new_from_str(uid)),
+                    command: Command::QUIT(Some("Ghost user removed".to_string())),
+                };
+                send_to_peer(matrix, peer, quit).await;
+            }
+        }
+        DigestKind::Channels => {
+            let mut names: Vec<String> = owned.clone();
+            for name in held.split_whitespace().map(irc_to_lower) {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            for name in names {
+                let tx = matrix
+                    .channel_manager
+                    .channels
+                    .get(&name)
+                    .map(|c| c.value().clone());
+                let mut uids = Vec::new();
+                if let Some(tx) = &tx {
+                    uids = origin_members(matrix, tx, local_sid)
+                        .await
+                        .into_iter()
+                        .map(|(uid, _)| uid)
+                        .collect();
+                    // Only vouch for our own members; anyone else in the
+                    // channel is their own server's to repair.
+                    for cmd in channel_burst(&name, tx, None).await {
+                        let cmd = match cmd {
+                            Command::SJOIN(ts, chan, modes, args, users) => {
+                                let users = users
+                                    .into_iter()
+                                    .filter(|(_, uid)| uids.contains(uid))
+                                    .collect();
+                                Command::SJOIN(ts, chan, modes, args, users)
+                            }
+                            other => other,
+                        };
+                        send_to_peer(matrix, peer, Message::from(cmd)).await;
+                    }
+                }
+                let params = vec![name, uids.join(" ")];
+                send_to_peer(matrix, peer, encap(matrix, peer, "DIGESTCHAN", params)).await;
+            }
+        }
+    }
+}
+
+/// `DIGESTCHAN <channel> :<uids>` from `peer`: the peer's complete list of
+/// its own members; drop any of its users we still have in the channel.
+pub(crate) async fn handle_digest_members(
+    matrix: &Matrix,
+    peer: &ServerId,
+    channel: &str,
+    uids: &str,
+) {
+    let Some(tx) = matrix
+        .channel_manager
+        .channels
+        .get(&irc_to_lower(channel))
+        .map(|c| c.value().clone())
+    else {
+        return;
+    };
+
+    let listed: HashSet<&str> = uids.split_whitespace().collect();
+    for (uid, _) in origin_members(matrix, &tx, peer.as_str()).await {
+        if !listed.contains(uid.as_str()) {
+            info!(uid = %uid, channel = %channel, peer = %peer.as_str(), "Removing stale channel member");
+            part_remote_member(matrix, &uid, channel, None).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digest_encode_round_trip() {
+        let mut digest = Digest::new();
+        digest.add("001AAAAAB".to_string(), 42);
+        digest.add("001AAAAAC".to_string(), u64::MAX);
+        let decoded = Digest::decode(&digest.encode()).unwrap();
+        assert_eq!(decoded, digest.hashes);
+    }
+
+    #[test]
+    fn test_digest_decode_rejects_wrong_bucket_count() {
+        assert!(Digest::decode("1,2,3").is_none());
+        let too_many = vec!["0"; DIGEST_BUCKETS + 1].join(",");
+        assert!(Digest::decode(&too_many).is_none());
+        assert!(Digest::decode(&vec!["zz"; DIGEST_BUCKETS].join(",")).is_none());
+    }
+
+    #[test]
+    fn test_digest_is_order_independent() {
+        let mut a = Digest::new();
+        a.add("x".to_string(), hash_fields(&["x", "1"]));
+        a.add("y".to_string(), hash_fields(&["y", "2"]));
+        let mut b = Digest::new();
+        b.add("y".to_string(), hash_fields(&["y", "2"]));
+        b.add("x".to_string(), hash_fields(&["x", "1"]));
+        assert_eq!(a.hashes, b.hashes);
+    }
+
+    #[test]
+    fn test_hash_fields_separates_fields() {
+        assert_ne!(hash_fields(&["ab", "c"]), hash_fields(&["a", "bc"]));
+    }
+}
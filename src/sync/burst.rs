@@ -2,16 +2,25 @@
 //!
 //! When a new server link is established, both sides exchange a "burst"
 //! containing their complete state. This module generates the burst commands:
-//! - Global bans (G-lines, Z-lines, Shuns) - sent first
+//! - Global bans (G-lines, Z-lines, R-lines, Shuns) - sent first
+//! - `SID` for every other server we can reach, parents before children
 //! - `UID` for each user (including service pseudoclients)
 //! - `SJOIN` for each channel (with members, modes, topic)
 //!
 //! The burst is sent after handshake completion and before operational messages.
+//!
+//! If the peer offered the vector clock it recovered from its journal, channels
+//! whose modes and topic that clock already covers are sent membership-only.
 
 use crate::state::Matrix;
 use crate::state::actor::ChannelEvent;
-use slirc_proto::Command;
-use tokio::sync::oneshot;
+use crate::state::observer::GlobalBanType;
+use crate::sync::TopologyGraph;
+use crate::sync::observer::ban_add_command;
+use slirc_proto::sync::clock::VectorClock;
+use slirc_proto::{Command, Message, Prefix};
+use std::collections::HashSet;
+use tokio::sync::{mpsc, oneshot};
 use tracing::error;
 
 /// Generates the burst of commands to synchronize state with a new peer.
@@ -23,13 +32,67 @@ use tracing::error;
 ///
 /// * `state` - The global server state (Matrix).
 /// * `target_sid` - The SID of the server we are bursting TO (for Split Horizon).
-pub async fn generate_burst(state: &Matrix, local_sid: &str, target_sid: &str) -> Vec<Command> {
+/// * `since` - The clock the target recovered from its journal, if it sent one.
+pub async fn generate_burst(
+    state: &Matrix,
+    local_sid: &str,
+    target_sid: &str,
+    since: Option<&VectorClock>,
+) -> Vec<Message> {
     let mut commands = Vec::new();
 
+    // SPLIT HORIZON: the target and everything behind it already know their
+    // own servers and users.
+    let behind_target: HashSet<String> = state
+        .sync_manager
+        .topology
+        .get_downstream_sids(&slirc_proto::sync::clock::ServerId::new(target_sid))
+        .into_iter()
+        .map(|sid| sid.as_str().to_string())
+        .collect();
+
     // 0. Burst Global Bans (before users/channels to prevent race conditions)
-    // G-lines
-    for (mask, reason, _expires) in state.security_manager.ban_cache.iter_glines() {
-        commands.push(Command::GLINE(mask, Some(reason)));
+    // Read from the database rather than the caches: the IP deny list also
+    // holds local D-lines. Durations are what is left of each ban, so it
+    // expires network-wide at the same time.
+    let now = chrono::Utc::now().timestamp();
+    let remaining = |expires_at: Option<i64>| expires_at.map(|exp| (exp - now).max(1));
+    let bans = state.db.bans();
+    match bans.get_active_glines().await {
+        Ok(glines) => commands.extend(glines.into_iter().map(|g| {
+            ban_add_command(
+                GlobalBanType::Gline,
+                &g.mask,
+                remaining(g.expires_at),
+                &g.set_by,
+                g.reason.as_deref().unwrap_or("No reason given"),
+            )
+        })),
+        Err(e) => error!(error = %e, "Failed to load G-lines, skipping G-line burst"),
+    }
+    match bans.get_active_zlines().await {
+        Ok(zlines) => commands.extend(zlines.into_iter().map(|z| {
+            ban_add_command(
+                GlobalBanType::Zline,
+                &z.mask,
+                remaining(z.expires_at),
+                &z.set_by,
+                z.reason.as_deref().unwrap_or("No reason given"),
+            )
+        })),
+        Err(e) => error!(error = %e, "Failed to load Z-lines, skipping Z-line burst"),
+    }
+    match bans.get_active_rlines().await {
+        Ok(rlines) => commands.extend(rlines.into_iter().map(|r| {
+            ban_add_command(
+                GlobalBanType::Rline,
+                &r.mask,
+                remaining(r.expires_at),
+                &r.set_by,
+                r.reason.as_deref().unwrap_or("No reason given"),
+            )
+        })),
+        Err(e) => error!(error = %e, "Failed to load R-lines, skipping R-line burst"),
     }
 
     // Shuns
@@ -38,22 +101,42 @@ pub async fn generate_burst(state: &Matrix, local_sid: &str, target_sid: &str) -
         commands.push(Command::SHUN(shun.mask.clone(), shun.reason.clone()));
     }
 
-    // Z-lines (IP bans from ip_deny_list)
-    // Note: Use ok() to gracefully handle lock poisoning - if the lock is poisoned,
-    // skip Z-line burst rather than crash. The peer will sync eventually.
-    if let Ok(ip_deny) = state.security_manager.ip_deny_list.read() {
-        for (ip_mask, meta) in ip_deny.iter() {
-            if !meta.is_expired() {
-                commands.push(Command::ZLINE(ip_mask.clone(), Some(meta.reason.clone())));
-            }
-        }
-    } else {
-        error!("ip_deny_list lock poisoned, skipping Z-line burst");
+    // Bans and everything below are unprefixed, as before
+    let mut burst: Vec<Message> = commands.drain(..).map(Message::from).collect();
+
+    // 1. Burst Other Servers (Network Topology)
+    // Each SID is prefixed with its uplink so the peer rebuilds the same
+    // tree; lower hop counts first so uplinks are always known.
+    let mut servers: Vec<_> = state
+        .sync_manager
+        .topology
+        .servers
+        .iter()
+        .map(|e| e.value().clone())
+        .filter(|info| info.sid.as_str() != local_sid && !behind_target.contains(info.sid.as_str()))
+        .collect();
+    servers.sort_by_key(|info| info.hopcount);
+    for info in servers {
+        // Direct peers are recorded with us (or themselves) as the uplink
+        let uplink = match &info.via {
+            Some(via) if via != &info.sid => via.as_str().to_string(),
+            _ => local_sid.to_string(),
+        };
+        burst.push(Message {
+            tags: None,
+            prefix: Some(Prefix::new_from_str(&uplink)),
+            command: Command::SID(
+                info.name.clone(),
+                (info.hopcount + 1).to_string(), // Increment hopcount
+                info.sid.as_str().to_string(),
+                info.info.clone(),
+            ),
+        });
     }
 
-    // 1. Burst Users (UID) - LOCAL USERS ONLY
-    // Only burst users whose UID starts with local_sid to prevent bouncing
-    // users back to their origin server (which causes nick collisions).
+    // 2. Burst Users (UID)
+    // Users from the target's side of the network are never sent back to it
+    // (which would cause nick collisions).
 
     // Collect user Arcs to release DashMap lock before awaiting
     let user_arcs: Vec<_> = state
@@ -66,16 +149,27 @@ pub async fn generate_burst(state: &Matrix, local_sid: &str, target_sid: &str) -
     for user_arc in user_arcs {
         let user = user_arc.read().await;
 
-        // SPLIT HORIZON: Do not send users back to the server they originated from.
-        // We know a user is from `target_sid` if their UID starts with `target_sid`.
-        if user.uid.starts_with(target_sid) {
+        // SPLIT HORIZON: Do not send users back to the side they originated from.
+        let Some(origin) = TopologyGraph::origin_of(&user.uid) else {
+            continue;
+        };
+        if behind_target.contains(origin.as_str()) {
             continue;
         }
 
         // UID nick hopcount timestamp username hostname uid modes realname
-        // For local users, hopcount is 1. For remote users, increment on relay.
-        // Phase 2 note: When multi-hop is implemented, store hopcount in User struct.
-        let hopcount = "1".to_string();
+        // For local users, hopcount is 1; remote users are one hop past their server.
+        let hopcount = if origin.as_str() == local_sid {
+            1
+        } else {
+            state
+                .sync_manager
+                .topology
+                .servers
+                .get(&origin)
+                .map_or(2, |s| s.hopcount + 1)
+        }
+        .to_string();
         let timestamp = user.created_at.to_string();
 
         commands.push(Command::UID(
@@ -90,121 +184,153 @@ pub async fn generate_burst(state: &Matrix, local_sid: &str, target_sid: &str) -
         ));
     }
 
-    // 2. Burst Channels (SJOIN)
+    // 3. Burst Channels (SJOIN)
     for entry in state.channel_manager.channels.iter() {
         let channel_name = entry.key();
-        let tx = entry.value();
         tracing::info!(channel = %channel_name, "Generating SJOIN for channel burst");
+        commands.extend(channel_burst(channel_name, entry.value(), since).await);
+    }
 
-        // Get Channel Info (Modes, Topic, TS)
-        let (info_tx, info_rx) = oneshot::channel();
-        if let Err(e) = tx
-            .send(ChannelEvent::GetInfo {
-                requester_uid: None,
-                reply_tx: info_tx,
-            })
-            .await
-        {
-            error!("Failed to request info for channel {}: {}", channel_name, e);
-            continue;
+    // 4. End of Burst
+    commands.push(Command::EOB);
+
+    burst.extend(commands.into_iter().map(Message::from));
+    burst
+}
+
+/// SJOIN (and TB, if a topic is set) describing one channel's current state.
+///
+/// When `since` already covers every mode and topic stamp, the SJOIN carries
+/// no modes and no TB is sent. Returns nothing if the channel actor does not
+/// answer.
+pub(super) async fn channel_burst(
+    channel_name: &str,
+    tx: &mpsc::Sender<ChannelEvent>,
+    since: Option<&VectorClock>,
+) -> Vec<Command> {
+    let mut commands = Vec::new();
+
+    // Get Channel Info (Modes, Topic, TS)
+    let (info_tx, info_rx) = oneshot::channel();
+    if let Err(e) = tx
+        .send(ChannelEvent::GetInfo {
+            requester_uid: None,
+            reply_tx: info_tx,
+        })
+        .await
+    {
+        error!("Failed to request info for channel {}: {}", channel_name, e);
+        return Vec::new();
+    }
+
+    let info = match info_rx.await {
+        Ok(i) => i,
+        Err(e) => {
+            error!("Failed to receive info for channel {}: {}", channel_name, e);
+            return Vec::new();
         }
+    };
 
-        let info = match info_rx.await {
-            Ok(i) => i,
-            Err(e) => {
-                error!("Failed to receive info for channel {}: {}", channel_name, e);
-                continue;
-            }
-        };
+    // Get Members (UIDs and Prefixes)
+    let (members_tx, members_rx) = oneshot::channel();
+    if let Err(e) = tx
+        .send(ChannelEvent::GetMembers {
+            reply_tx: members_tx,
+        })
+        .await
+    {
+        error!(
+            "Failed to request members for channel {}: {}",
+            channel_name, e
+        );
+        return Vec::new();
+    }
 
-        // Get Members (UIDs and Prefixes)
-        let (members_tx, members_rx) = oneshot::channel();
-        if let Err(e) = tx
-            .send(ChannelEvent::GetMembers {
-                reply_tx: members_tx,
-            })
-            .await
-        {
+    let members = match members_rx.await {
+        Ok(m) => m,
+        Err(e) => {
             error!(
-                "Failed to request members for channel {}: {}",
+                "Failed to receive members for channel {}: {}",
                 channel_name, e
             );
-            continue;
+            return Vec::new();
         }
+    };
 
-        let members = match members_rx.await {
-            Ok(m) => m,
-            Err(e) => {
-                error!(
-                    "Failed to receive members for channel {}: {}",
-                    channel_name, e
-                );
-                continue;
-            }
-        };
+    // Construct SJOIN
+    // SJOIN ts channel modes [args...] :users
 
-        // Construct SJOIN
-        // SJOIN ts channel modes [args...] :users
+    // Convert modes to string and args
+    // ChannelInfo has `modes: HashSet<ChannelMode>`.
+    // We need to convert this to "+nt" and args.
+    // This is tricky without a helper.
+    // `ChannelActor` has `modes_to_string`.
+    // But `ChannelInfo` just has the set.
+    // Wait, `ChannelInfo` has `modes: HashSet<ChannelMode>`.
+    // `ChannelMode` enum has variants like `Key(String, TS)`.
+    // So we can extract args.
 
-        // Convert modes to string and args
-        // ChannelInfo has `modes: HashSet<ChannelMode>`.
-        // We need to convert this to "+nt" and args.
-        // This is tricky without a helper.
-        // `ChannelActor` has `modes_to_string`.
-        // But `ChannelInfo` just has the set.
-        // Wait, `ChannelInfo` has `modes: HashSet<ChannelMode>`.
-        // `ChannelMode` enum has variants like `Key(String, TS)`.
-        // So we can extract args.
+    let unchanged = match since {
+        Some(clock) => state_covered(channel_name, tx, clock).await,
+        None => false,
+    };
 
+    let (mode_str, mode_args) = if unchanged {
+        ("+".to_string(), Vec::new())
+    } else {
         let mode_string_full = crate::state::actor::modes_to_string(&info.modes);
         let mut parts = mode_string_full.split_whitespace();
         let mode_str = parts.next().unwrap_or("+").to_string();
         let mode_args: Vec<String> = parts.map(|s| s.to_string()).collect();
+        (mode_str, mode_args)
+    };
 
-        // Convert members to (prefix, uid) list
-        let mut user_list = Vec::new();
-        for (uid, modes) in members {
-            let prefixes = modes.all_prefix_chars();
-            user_list.push((prefixes, uid));
-        }
+    // Convert members to (prefix, uid) list
+    let mut user_list = Vec::new();
+    for (uid, modes) in members {
+        let prefixes = modes.all_prefix_chars();
+        user_list.push((prefixes, uid));
+    }
+
+    commands.push(Command::SJOIN(
+        info.created as u64,
+        info.name.clone(),
+        mode_str,
+        mode_args,
+        user_list,
+    ));
 
-        commands.push(Command::SJOIN(
-            info.created as u64,
+    // Burst Topic (TB) if it exists
+    if let Some(topic) = info.topic.as_ref().filter(|_| !unchanged) {
+        commands.push(Command::TB(
             info.name.clone(),
-            mode_str,
-            mode_args,
-            user_list,
+            topic.set_at as u64,
+            Some(topic.set_by.clone()),
+            topic.text.clone(),
         ));
-
-        // 2.a Burst Topic (TB) if it exists
-        if let Some(topic) = &info.topic {
-            commands.push(Command::TB(
-                info.name.clone(),
-                topic.set_at as u64,
-                Some(topic.set_by.clone()),
-                topic.text.clone(),
-            ));
-        }
     }
 
-    // 3. Burst Other Servers (Network Topology)
-    // Send SID for all known servers in the topology except ourselves and the target server
-    for entry in state.sync_manager.topology.servers.iter() {
-        let info = entry.value();
-        if info.sid.as_str() != local_sid {
-            // :<uplink_sid> SID <name> <hopcount> <sid> :<description>
-            // For now, satisfy the enum Command::SID(name, hopcount, sid, description)
-            commands.push(Command::SID(
-                info.name.clone(),
-                (info.hopcount + 1).to_string(), // Increment hopcount
-                info.sid.as_str().to_string(),
-                info.info.clone(),
-            ));
+    commands
+}
+
+/// Whether `clock` already covers every mode and topic stamp of a channel.
+///
+/// SJOIN merges as a CRDT, so a peer holding this state loses nothing when
+/// the modes are left out.
+async fn state_covered(
+    channel_name: &str,
+    tx: &mpsc::Sender<ChannelEvent>,
+    clock: &VectorClock,
+) -> bool {
+    let (reply_tx, reply_rx) = oneshot::channel();
+    if tx.send(ChannelEvent::GetCrdt { reply_tx }).await.is_err() {
+        return false;
+    }
+    match reply_rx.await {
+        Ok(crdt) => crdt.state_timestamps().all(|ts| clock.covers(&ts)),
+        Err(e) => {
+            error!("Failed to receive CRDT for channel {}: {}", channel_name, e);
+            false
         }
     }
-
-    // 4. End of Burst
-    commands.push(Command::EOB);
-
-    commands
 }
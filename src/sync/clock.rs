@@ -0,0 +1,71 @@
+//! Peer clock skew tracking.
+//!
+//! Peers report their wall clock in SVINFO. The offset from our clock is
+//! exported per peer so operators can spot a misconfigured clock before it
+//! breaks LWW merges. CRDT timestamps arriving from peers are bounded by the
+//! process-wide [`HybridClock`], so a peer running ahead cannot win every
+//! conflict.
+
+use slirc_proto::sync::{HybridClock, HybridTimestamp, ServerId};
+use tracing::warn;
+
+/// Record a peer's clock offset from the time in its SVINFO.
+///
+/// Returns the skew in seconds (positive if the peer is ahead).
+pub fn record_svinfo(peer_sid: &str, remote_secs: u64) -> i64 {
+    let remote_millis = i64::try_from(remote_secs)
+        .unwrap_or(i64::MAX)
+        .saturating_mul(1000);
+    let skew_secs = HybridClock::skew_ms(remote_millis) / 1000;
+    crate::metrics::set_s2s_clock_skew(peer_sid, skew_secs);
+
+    let limit_secs = HybridClock::global().max_drift_ms() / 1000;
+    if skew_secs.abs() > limit_secs {
+        warn!(
+            peer = %peer_sid,
+            skew_secs,
+            limit_secs,
+            "Peer clock skew exceeds drift bound; check NTP on both servers"
+        );
+    }
+    skew_secs
+}
+
+/// Bound a timestamp received from `peer` to the allowed drift.
+///
+/// Timestamps too far in the future are clamped to the drift limit.
+pub fn bound_peer_timestamp(peer: &ServerId, ts: HybridTimestamp) -> HybridTimestamp {
+    let clock = HybridClock::global();
+    match clock.observe(&ts) {
+        Ok(()) => ts,
+        Err(e) => {
+            warn!(peer = %peer, skew_ms = e.skew_ms, "Clamping future timestamp from peer");
+            crate::metrics::inc_s2s_clock_clamped(peer.as_str());
+            clock.clamp(ts)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_svinfo_reports_offset() {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let skew = record_svinfo("00A", now + 120);
+        assert!((119..=120).contains(&skew));
+        assert!(record_svinfo("00A", now).abs() <= 1);
+    }
+
+    #[test]
+    fn bound_peer_timestamp_clamps_far_future() {
+        let peer = ServerId::new("00A");
+        let far = chrono::Utc::now().timestamp_millis() + 24 * 3600 * 1000;
+        let ts = bound_peer_timestamp(&peer, HybridTimestamp::new(far, 0, &peer));
+        assert!(ts.millis < far);
+
+        let past = HybridTimestamp::new(1_000, 0, &peer);
+        assert_eq!(bound_peer_timestamp(&peer, past), past);
+    }
+}
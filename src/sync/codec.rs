@@ -0,0 +1,186 @@
+//! Line framing for S2S links, with optional zstd compression.
+//!
+//! Links start out as plain newline-delimited lines. When both ends
+//! advertise `ZSTD` in CAPAB, each end switches its codec to compressed
+//! framing as soon as the handshake completes: from then on each direction
+//! of the link is a single zstd stream, flushed after every line so the peer
+//! can act on it at once while the compression context carries across lines.
+//!
+//! A side completes its handshake on the last line the peer sends before
+//! switching, so any compressed bytes already read sit behind that line in
+//! the read buffer and are decoded in compressed mode.
+
+use bytes::BytesMut;
+use bytes::buf::{BufMut, Writer};
+use std::io::{self, Write};
+use tokio_util::codec::{Decoder, Encoder, LinesCodec, LinesCodecError};
+
+/// Codec for S2S links: [`LinesCodec`], optionally wrapped in zstd.
+#[derive(Default)]
+pub struct S2SCodec {
+    lines: LinesCodec,
+    compressor: Option<zstd::stream::write::Encoder<'static, Vec<u8>>>,
+    /// Decompresses into the buffer lines are split from.
+    decompressor: Option<Decompressor>,
+}
+
+impl S2SCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compress everything sent and received from now on.
+    pub fn enable_compression(&mut self) -> io::Result<()> {
+        self.compressor = Some(zstd::stream::write::Encoder::new(
+            Vec::new(),
+            zstd::DEFAULT_COMPRESSION_LEVEL,
+        )?);
+        self.decompressor = Some(zstd::stream::write::Decoder::new(BytesMut::new().writer())?);
+        Ok(())
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.compressor.is_some()
+    }
+}
+
+type Decompressor = zstd::stream::write::Decoder<'static, Writer<BytesMut>>;
+
+/// Move compressed input from `src` into the decompressed line buffer, if
+/// the link is compressed.
+fn inflate<'a>(
+    decompressor: &'a mut Option<Decompressor>,
+    src: &mut BytesMut,
+) -> io::Result<Option<&'a mut BytesMut>> {
+    let Some(decompressor) = decompressor.as_mut() else {
+        return Ok(None);
+    };
+    if !src.is_empty() {
+        decompressor.write_all(src)?;
+        decompressor.flush()?;
+        src.clear();
+    }
+    Ok(Some(decompressor.get_mut().get_mut()))
+}
+
+impl Decoder for S2SCodec {
+    type Item = String;
+    type Error = LinesCodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<String>, LinesCodecError> {
+        match inflate(&mut self.decompressor, src)? {
+            Some(plain) => self.lines.decode(plain),
+            None => self.lines.decode(src),
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<String>, LinesCodecError> {
+        match inflate(&mut self.decompressor, src)? {
+            Some(plain) => self.lines.decode_eof(plain),
+            None => self.lines.decode_eof(src),
+        }
+    }
+}
+
+impl<T: AsRef<str>> Encoder<T> for S2SCodec {
+    type Error = LinesCodecError;
+
+    fn encode(&mut self, line: T, dst: &mut BytesMut) -> Result<(), LinesCodecError> {
+        let Some(compressor) = self.compressor.as_mut() else {
+            return self.lines.encode(line, dst);
+        };
+        compressor.write_all(line.as_ref().as_bytes())?;
+        compressor.write_all(b"\n")?;
+        compressor.flush()?;
+        let compressed = compressor.get_mut();
+        dst.extend_from_slice(compressed);
+        compressed.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_lines_pass_through() {
+        let mut codec = S2SCodec::new();
+        let mut buf = BytesMut::new();
+        codec.encode("PING :001", &mut buf).unwrap();
+        assert_eq!(&buf[..], b"PING :001\n");
+        assert_eq!(
+            codec.decode(&mut buf).unwrap().as_deref(),
+            Some("PING :001")
+        );
+    }
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let mut tx = S2SCodec::new();
+        let mut rx = S2SCodec::new();
+        tx.enable_compression().unwrap();
+        rx.enable_compression().unwrap();
+
+        let lines: Vec<String> = (0..100)
+            .map(|i| format!(":001 UID user{i} 1 0 +i user host 0 001AAAA{i:02} :Real Name"))
+            .collect();
+        let mut wire = BytesMut::new();
+        for line in &lines {
+            tx.encode(line.as_str(), &mut wire).unwrap();
+        }
+        let plain_len: usize = lines.iter().map(|l| l.len() + 1).sum();
+        assert!(wire.len() < plain_len / 2, "burst did not compress");
+
+        let mut decoded = Vec::new();
+        while let Some(line) = rx.decode(&mut wire).unwrap() {
+            decoded.push(line);
+        }
+        assert_eq!(decoded, lines);
+    }
+
+    #[test]
+    fn test_each_line_decodes_as_soon_as_it_arrives() {
+        let mut tx = S2SCodec::new();
+        let mut rx = S2SCodec::new();
+        tx.enable_compression().unwrap();
+        rx.enable_compression().unwrap();
+
+        for line in ["SVINFO 6 6 0 :1", "EOB"] {
+            let mut wire = BytesMut::new();
+            tx.encode(line, &mut wire).unwrap();
+            assert_eq!(rx.decode(&mut wire).unwrap().as_deref(), Some(line));
+            assert_eq!(rx.decode(&mut wire).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_switch_mid_buffer() {
+        // The last plain handshake line and the first compressed bytes
+        // arrive in the same read.
+        let mut tx = S2SCodec::new();
+        let mut wire = BytesMut::new();
+        tx.encode("SVINFO 6 6 0 :1", &mut wire).unwrap();
+        tx.enable_compression().unwrap();
+        tx.encode("SID leaf.test 2 002 :Leaf", &mut wire).unwrap();
+
+        let mut rx = S2SCodec::new();
+        assert_eq!(
+            rx.decode(&mut wire).unwrap().as_deref(),
+            Some("SVINFO 6 6 0 :1")
+        );
+        rx.enable_compression().unwrap();
+        assert_eq!(
+            rx.decode(&mut wire).unwrap().as_deref(),
+            Some("SID leaf.test 2 002 :Leaf")
+        );
+    }
+
+    #[test]
+    fn test_garbage_is_an_error() {
+        let mut rx = S2SCodec::new();
+        rx.enable_compression().unwrap();
+        let mut wire = BytesMut::from(&b"PING :not compressed\n"[..]);
+        assert!(rx.decode(&mut wire).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+//! User updates shipped as CRDT deltas.
+//!
+//! Peers that offer `DELTA` in CAPAB receive changes to a local user as
+//!
+//! ```text
+//! :<sid> ENCAP * UDELTA <delta>
+//! ```
+//!
+//! where `<delta>` is the base64 wire encoding of the [`UserDelta`] between
+//! the state last shipped for the user and its current state, so an AWAY or
+//! SETNAME carries only that register. Other peers keep receiving the full
+//! UID, as does every peer for a user's first update.
+
+use crate::state::Matrix;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use slirc_proto::sync::clock::ServerId;
+use slirc_proto::sync::wire;
+use slirc_proto::sync::{DeltaCrdt, UserDelta};
+use tracing::debug;
+
+/// Encoded deltas longer than this go out as a UID instead.
+pub const MAX_DELTA_LEN: usize = 4096;
+
+/// Encode a delta for `ENCAP * UDELTA`, or `None` if it is too long.
+pub fn encode_user_delta(delta: &UserDelta) -> Option<String> {
+    let payload = STANDARD.encode(wire::encode(delta).ok()?);
+    (payload.len() <= MAX_DELTA_LEN).then_some(payload)
+}
+
+/// Decode the payload of an `ENCAP * UDELTA`.
+pub fn decode_user_delta(payload: &str) -> Option<UserDelta> {
+    let bytes = STANDARD.decode(payload).ok()?;
+    wire::decode(&bytes).ok()
+}
+
+/// Handle an `ENCAP * UDELTA` from `origin`, received over the link to `peer`.
+///
+/// The delta is merged into the user it names and relayed onwards, as a
+/// delta to peers that negotiated them and as a UID to the rest. A delta
+/// for a user we do not know is dropped; the anti-entropy digests bring the
+/// user back in full.
+pub async fn handle_user_delta(matrix: &Matrix, payload: &str, origin: &str, peer: &ServerId) {
+    let Some(delta) = decode_user_delta(payload) else {
+        debug!(peer = %peer.as_str(), "Dropping undecodable user delta");
+        return;
+    };
+    let user_arc = matrix
+        .user_manager
+        .users
+        .get(&delta.uid)
+        .map(|u| u.value().clone());
+    let Some(user_arc) = user_arc else {
+        debug!(uid = %delta.uid, "Dropping delta for unknown user");
+        return;
+    };
+
+    let mut crdt = user_arc.read().await.to_crdt();
+    crdt.merge_delta(&delta);
+    matrix
+        .user_manager
+        .merge_user_crdt(crdt.clone(), Some(peer.clone()))
+        .await;
+    matrix
+        .sync_manager
+        .queue_user_update(&crdt, Some(payload.to_string()), origin, Some(peer));
+}
@@ -4,8 +4,11 @@
 //! Implements the TS6-like handshake protocol defined in `docs/S2S_PROTOCOL.md`.
 
 use crate::config::LinkBlock;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
 use slirc_proto::Command;
-use slirc_proto::sync::clock::ServerId;
+use slirc_proto::sync::clock::{ServerId, VectorClock};
+use slirc_proto::sync::wire;
 use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -56,6 +59,15 @@ pub struct HandshakeMachine {
     pub remote_info: Option<String>,
     pub remote_capab: Option<Vec<String>>,
     pub remote_svinfo: Option<(u32, u32, u32, u64)>,
+    /// Both ends offered [`COMPRESSION_CAPAB`]; set when the handshake completes.
+    pub compress: bool,
+    /// The peer offered [`DELTA_CAPAB`]; set when the handshake completes.
+    pub deltas: bool,
+    /// Clock the peer offered as [`CLOCK_CAPAB`]; set when the handshake
+    /// completes.
+    pub remote_clock: Option<VectorClock>,
+    /// Clock we offer as [`CLOCK_CAPAB`] when answering an inbound link.
+    pub local_clock: Option<VectorClock>,
 
     // Local identity
     pub local_sid: ServerId,
@@ -65,10 +77,63 @@ pub struct HandshakeMachine {
 
 /// Capabilities supported by slircd-ng for S2S.
 pub const SUPPORTED_CAPABS: &[&str] = &[
-    "QS", "ENCAP", "EX", "IE", "UNKLN", "KLN", "GLN", "HOPS", "CHW", "EOB", "KNOCK", "TB",
+    "QS",
+    "ENCAP",
+    "EX",
+    "IE",
+    "UNKLN",
+    "KLN",
+    "GLN",
+    "HOPS",
+    "CHW",
+    "EOB",
+    "KNOCK",
+    "TB",
     "SERVICES",
+    DELTA_CAPAB,
 ];
 
+/// CAPAB token for user updates sent as `ENCAP * UDELTA` instead of UID.
+pub const DELTA_CAPAB: &str = "DELTA";
+
+/// CAPAB token offering zstd compression of the link after the handshake.
+pub const COMPRESSION_CAPAB: &str = "ZSTD";
+
+/// CAPAB token carrying our CRDT journal clock, as `CLOCK=<base64>`; the
+/// peer's burst then leaves out the channel state the clock covers.
+pub const CLOCK_CAPAB: &str = "CLOCK";
+
+/// Encoded clocks longer than this are not offered; the peer sends a full
+/// burst instead.
+pub const MAX_CLOCK_LEN: usize = 256;
+
+/// Capabilities we advertise on `link`, offering `clock` if there is one.
+pub fn local_capabs(link: &LinkBlock, clock: Option<&VectorClock>) -> Vec<String> {
+    let mut capabs: Vec<String> = SUPPORTED_CAPABS.iter().map(|s| s.to_string()).collect();
+    if link.compression {
+        capabs.push(COMPRESSION_CAPAB.to_string());
+    }
+    if let Some(clock) = clock.and_then(encode_clock) {
+        capabs.push(format!("{CLOCK_CAPAB}={clock}"));
+    }
+    capabs
+}
+
+/// Encode a clock for [`CLOCK_CAPAB`], or `None` if it is too long.
+fn encode_clock(clock: &VectorClock) -> Option<String> {
+    let encoded = STANDARD.encode(wire::encode(clock).ok()?);
+    (encoded.len() <= MAX_CLOCK_LEN).then_some(encoded)
+}
+
+/// The clock offered in a CAPAB token list, if any.
+fn offered_clock(capabs: &[String]) -> Option<VectorClock> {
+    let encoded = capabs
+        .iter()
+        .find_map(|c| c.strip_prefix(CLOCK_CAPAB)?.strip_prefix('='))?;
+    let bytes = STANDARD.decode(encoded).ok()?;
+    wire::decode(&bytes).ok()
+}
+
 impl HandshakeMachine {
     pub fn new(local_sid: ServerId, local_name: String, local_desc: String) -> Self {
         Self {
@@ -80,6 +145,10 @@ impl HandshakeMachine {
             remote_info: None,
             remote_capab: None,
             remote_svinfo: None,
+            compress: false,
+            deltas: false,
+            remote_clock: None,
+            local_clock: None,
             local_sid,
             local_name,
             local_desc,
@@ -121,7 +190,11 @@ impl HandshakeMachine {
             && self.remote_svinfo.is_some()
             && self.remote_capab.is_some()
         {
-            self.verify_credentials(links)?;
+            let link = self.verify_credentials(links)?;
+            let offered = |token: &str| self.remote_capab.iter().flatten().any(|c| c == token);
+            self.compress = link.compression && offered(COMPRESSION_CAPAB);
+            self.deltas = offered(DELTA_CAPAB);
+            self.remote_clock = self.remote_capab.as_deref().and_then(offered_clock);
             self.state = HandshakeState::Bursting;
             Ok(true)
         } else {
@@ -268,7 +341,7 @@ impl HandshakeMachine {
                     password: link.password.clone(),
                     sid: self.local_sid.as_str().to_string(),
                 },
-                Command::CAPAB(SUPPORTED_CAPABS.iter().map(|s| s.to_string()).collect()),
+                Command::CAPAB(local_capabs(link, self.local_clock.as_ref())),
                 Command::SERVER(
                     self.local_name.clone(),
                     1,
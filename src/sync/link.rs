@@ -5,6 +5,13 @@ use std::sync::atomic::AtomicU64;
 use std::time::Instant;
 use tokio::sync::mpsc;
 
+/// Messages queued for a peer before the link counts as stalled.
+///
+/// Nothing drains the queue while our burst is written, so it must hold
+/// every delta published meanwhile. A full queue drops the link (like an
+/// exceeded sendq); the peer then relinks and receives a fresh burst.
+pub const LINK_QUEUE_CAPACITY: usize = 8192;
+
 /// Represents the state of a link to a peer server.
 #[derive(Debug)]
 pub struct LinkState {
@@ -14,6 +21,8 @@ pub struct LinkState {
     pub state: handshake::HandshakeState,
     /// The name of the peer server.
     pub name: String,
+    /// The peer takes user updates as deltas (negotiated `DELTA` CAPAB).
+    pub deltas: bool,
     /// Last time we received a PONG (or any data) from this peer.
     pub last_pong: Instant,
     /// Last time we sent a PING to this peer.
@@ -32,6 +41,7 @@ impl Clone for LinkState {
             tx: self.tx.clone(),
             state: self.state.clone(),
             name: self.name.clone(),
+            deltas: self.deltas,
             last_pong: self.last_pong,
             last_ping: self.last_ping,
             connected_at: self.connected_at,
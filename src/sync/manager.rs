@@ -1,17 +1,20 @@
 use crate::config::LinkBlock;
 use crate::state::Matrix;
-use dashmap::DashMap;
-use slirc_proto::sync::ServerId;
+use crate::state::observer::GlobalBanType;
+use dashmap::{DashMap, DashSet};
+use parking_lot::Mutex;
+use slirc_proto::sync::{ServerId, StableVersionVector, UserCrdt, VectorClock};
 use slirc_proto::{Command, Message};
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::sync::atomic::AtomicU64;
 use std::time::{Duration, Instant};
-use tokio::sync::mpsc;
+use tokio::sync::{Notify, mpsc};
 use tracing::info;
 
 use super::burst;
 use super::handshake;
-use super::link::LinkState;
+use super::link::{LINK_QUEUE_CAPACITY, LinkState};
 use super::network;
 use super::topology::{ServerInfo, TopologyGraph};
 
@@ -29,8 +32,30 @@ pub struct SyncManager {
     pub topology: Arc<TopologyGraph>,
     /// S2S rate limiter for flood protection.
     pub rate_limiter: Arc<crate::security::rate_limit::S2SRateLimiter>,
+    /// Names of links dropped by SQUIT; their outbound loops stop retrying.
+    pub squit_links: Arc<DashSet<String>>,
+    /// What every server has seen, for tombstone GC.
+    pub stability: Arc<Mutex<StableVersionVector>>,
+    /// Woken when replication events were dropped; the anti-entropy task
+    /// then re-sends our state to every peer.
+    pub resync: Arc<Notify>,
+    /// Each local user as last shipped to peers; the base for its next delta.
+    pub shipped_users: Arc<DashMap<String, UserCrdt>>,
+    /// Global bans removed in the last [`BAN_TOMBSTONE_TTL`], re-sent by a
+    /// resync in case the removal never reached a peer.
+    pub ban_tombstones: Arc<DashMap<(GlobalBanType, String), Instant>>,
+    /// Servers that split off, with when (millis). Retired once gone for
+    /// the grace period (see [`super::retirement`]).
+    pub departed: Arc<DashMap<ServerId, i64>>,
+    /// Retirement markers for servers that left the network for good.
+    pub retired: Arc<Mutex<VectorClock>>,
+    /// For each retirement still marked, the servers that acknowledged it.
+    pub retirement_acks: Arc<DashMap<ServerId, HashSet<ServerId>>>,
 }
 
+/// How long a removed global ban is remembered for resyncs.
+pub const BAN_TOMBSTONE_TTL: Duration = Duration::from_secs(3600);
+
 impl SyncManager {
     pub fn new(
         local_id: ServerId,
@@ -40,7 +65,6 @@ impl SyncManager {
         rate_limit_config: &crate::config::RateLimitConfig,
     ) -> Self {
         Self {
-            local_id,
             local_name,
             local_desc,
             configured_links,
@@ -49,9 +73,27 @@ impl SyncManager {
             rate_limiter: Arc::new(crate::security::rate_limit::S2SRateLimiter::new(
                 rate_limit_config,
             )),
+            squit_links: Arc::new(DashSet::new()),
+            stability: Arc::new(Mutex::new(StableVersionVector::new(local_id.clone()))),
+            resync: Arc::new(Notify::new()),
+            shipped_users: Arc::new(DashMap::new()),
+            ban_tombstones: Arc::new(DashMap::new()),
+            departed: Arc::new(DashMap::new()),
+            retired: Arc::new(Mutex::new(VectorClock::new())),
+            retirement_acks: Arc::new(DashMap::new()),
+            local_id,
         }
     }
 
+    /// Remember that a global ban was removed.
+    ///
+    /// Called where the ban is removed rather than from the observer, so the
+    /// tombstone exists even if the removal event is dropped on its way here.
+    pub fn record_ban_removal(&self, ban_type: GlobalBanType, mask: &str) {
+        self.ban_tombstones
+            .insert((ban_type, mask.to_string()), Instant::now());
+    }
+
     /// Route a message to a remote user.
     ///
     /// Resolves the target server from the UID, finds the next hop,
@@ -180,13 +222,14 @@ impl SyncManager {
         info: String,
     ) -> mpsc::Receiver<Arc<Message>> {
         let peer_sid = sid.clone();
-        let (tx, rx) = mpsc::channel(1000);
+        let (tx, rx) = mpsc::channel(LINK_QUEUE_CAPACITY);
         self.links.insert(
             peer_sid.clone(),
             LinkState {
                 tx,
                 state: handshake::HandshakeState::Synced,
                 name: name.clone(),
+                deltas: false,
                 last_pong: Instant::now(),
                 last_ping: Instant::now(),
                 connected_at: Instant::now(),
@@ -202,6 +245,7 @@ impl SyncManager {
                 info,
                 hopcount,
                 via: Some(peer_sid), // Direct peer routes through itself
+                linked_at: Instant::now(),
             },
         );
         rx
@@ -209,13 +253,13 @@ impl SyncManager {
 
     pub async fn send_burst(&self, sid: &ServerId, matrix: &Matrix) {
         info!("Sending burst to {}", sid.as_str());
-        let commands = burst::generate_burst(matrix, self.local_id.as_str(), sid.as_str()).await;
+        let commands =
+            burst::generate_burst(matrix, self.local_id.as_str(), sid.as_str(), None).await;
 
         let link = self.links.get(sid).map(|l| l.value().clone());
         if let Some(link) = link {
-            for cmd in commands {
-                let msg = Arc::new(Message::from(cmd));
-                if let Err(e) = link.tx.send(msg).await {
+            for msg in commands {
+                if let Err(e) = link.tx.send(Arc::new(msg)).await {
                     tracing::error!("Failed to send burst command to {}: {}", sid.as_str(), e);
                     break;
                 }
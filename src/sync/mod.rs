@@ -3,13 +3,21 @@
 //! This module manages the distributed state of the IRC network.
 //! It handles server linking, handshake, and CRDT state replication.
 
+pub mod anti_entropy;
 pub mod burst;
+pub mod clock;
+pub mod codec;
+pub mod delta;
 pub mod handshake;
 pub mod link;
 pub mod manager;
 pub mod network;
 mod observer;
+pub mod retirement;
+pub mod router;
 pub mod split;
+pub mod stability;
+pub mod store;
 pub mod stream;
 pub mod tls;
 mod topology;
@@ -4,7 +4,9 @@ use crate::security::rate_limit::S2SRateLimitResult;
 use crate::state::Matrix;
 use crate::sync::{
     LinkState, SyncManager, burst,
+    codec::S2SCodec,
     handshake::{HandshakeMachine, HandshakeState},
+    link::LINK_QUEUE_CAPACITY,
     split,
     stream::S2SStream,
     tls::DangerousNoVerifier,
@@ -13,7 +15,8 @@ use futures_util::{SinkExt, StreamExt};
 use rustls_pemfile::{certs, pkcs8_private_keys};
 use sha2::{Digest, Sha256};
 use slirc_proto::sync::ServerId;
-use slirc_proto::{Command, Message};
+use slirc_proto::sync::clock::VectorClock;
+use slirc_proto::{Command, Message, Prefix};
 use std::io::Cursor;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -24,7 +27,7 @@ use tokio_rustls::TlsAcceptor;
 use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
 use tokio_rustls::rustls::server::WebPkiClientVerifier;
 use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerConfig};
-use tokio_util::codec::{Framed, LinesCodec};
+use tokio_util::codec::Framed;
 use tracing::info;
 
 /// Upgrades a TCP stream to TLS for outbound connections.
@@ -313,6 +316,74 @@ async fn run_s2s_plaintext_listener(
     }
 }
 
+/// Register a newly handshaken peer link and send it our burst.
+///
+/// The link is registered in `Bursting` state before the burst snapshot is
+/// taken. Deltas published while the burst is generated and written queue on
+/// the link's channel and are delivered right after the burst, so no change
+/// falls between the snapshot and live propagation. Returns the link's
+/// receiver, or `None` (with the link removed) if the burst could not be sent.
+/// `deltas` is whether the peer negotiated user deltas in CAPAB, and `since`
+/// the journal clock it offered, if any.
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn register_and_burst(
+    manager: &SyncManager,
+    matrix: &Matrix,
+    framed: &mut Framed<S2SStream, S2SCodec>,
+    sid: &ServerId,
+    name: String,
+    info: String,
+    deltas: bool,
+    since: Option<&VectorClock>,
+) -> Option<mpsc::Receiver<Arc<Message>>> {
+    let (tx, rx) = mpsc::channel::<Arc<Message>>(LINK_QUEUE_CAPACITY);
+    manager.links.insert(
+        sid.clone(),
+        LinkState {
+            tx,
+            state: HandshakeState::Bursting,
+            name: name.clone(),
+            deltas,
+            last_pong: Instant::now(),
+            last_ping: Instant::now(),
+            connected_at: Instant::now(),
+            bytes_sent: Arc::new(AtomicU64::new(0)),
+            bytes_recv: Arc::new(AtomicU64::new(0)),
+        },
+    );
+
+    let burst = burst::generate_burst(matrix, manager.local_id.as_str(), sid.as_str(), since).await;
+    for msg in burst {
+        if let Err(e) = framed.send(msg.to_string().trim_end()).await {
+            tracing::error!(peer = %sid.as_str(), error = %e, "Failed to send burst");
+            manager.links.remove(sid);
+            return None;
+        }
+    }
+
+    if let Some(mut link) = manager.links.get_mut(sid) {
+        link.state = HandshakeState::Synced;
+    }
+
+    // Add to topology (direct peer's parent/uplink is the local server) and
+    // introduce it to the rest of the network
+    manager.topology.add_server(
+        sid.clone(),
+        name.clone(),
+        info.clone(),
+        1,
+        Some(manager.local_id.clone()),
+    );
+    let intro = Message {
+        tags: None,
+        prefix: Some(Prefix::new_from_str(manager.local_id.as_str())),
+        command: Command::SID(name, "2".to_string(), sid.as_str().to_string(), info),
+    };
+    manager.broadcast(Arc::new(intro), Some(sid)).await;
+
+    Some(rx)
+}
+
 async fn handle_inbound_connection(
     manager: SyncManager,
     matrix: Arc<Matrix>,
@@ -323,13 +394,14 @@ async fn handle_inbound_connection(
     is_tls: bool,
 ) {
     let mut shutdown_rx = matrix.lifecycle_manager.shutdown_tx.subscribe();
-    let mut framed = Framed::new(stream, LinesCodec::new());
+    let mut framed = Framed::new(stream, S2SCodec::new());
 
     let mut machine = HandshakeMachine::new(
         manager.local_id.clone(),
         manager.local_name.clone(),
         manager.local_desc.clone(),
     );
+    machine.local_clock = matrix.journal.get().map(|journal| journal.clock());
     // For inbound connections, we wait for the remote to send PASS/CAPAB/SERVER first
     machine.transition(HandshakeState::InboundReceived);
 
@@ -404,18 +476,17 @@ async fn handle_inbound_connection(
                     remote_sid = machine.remote_sid.clone();
                     remote_name = machine.remote_name.clone();
                     remote_info = machine.remote_info.clone();
+                    if let (Some(sid), Some((_, _, _, t))) = (&remote_sid, machine.remote_svinfo) {
+                        crate::sync::clock::record_svinfo(sid.as_str(), t);
+                    }
 
-                    // Generate and send burst
-                    let target = remote_sid.as_ref().map(|s| s.as_str()).unwrap_or("");
-                    let burst =
-                        burst::generate_burst(&matrix, manager.local_id.as_str(), target).await;
-                    for cmd in burst {
-                        if let Err(e) = framed.send(Message::from(cmd).to_string().trim_end()).await
-                        {
-                            tracing::error!(peer = %remote_addr, error = %e, "Failed to send burst");
-                            return;
-                        }
+                    if machine.compress
+                        && let Err(e) = framed.codec_mut().enable_compression()
+                    {
+                        tracing::error!(peer = %remote_addr, error = %e, "Failed to enable link compression");
+                        return;
                     }
+
                     handshake_success = true;
                     break;
                 }
@@ -436,21 +507,21 @@ async fn handle_inbound_connection(
         }
     };
 
-    // Register link
-    let (tx, mut rx) = mpsc::channel::<Arc<Message>>(100);
-    manager.links.insert(
-        remote_sid_val.clone(),
-        LinkState {
-            tx,
-            state: HandshakeState::Synced,
-            name: remote_name.clone().unwrap_or_default(),
-            last_pong: Instant::now(),
-            last_ping: Instant::now(),
-            connected_at: Instant::now(),
-            bytes_sent: Arc::new(AtomicU64::new(0)),
-            bytes_recv: Arc::new(AtomicU64::new(0)),
-        },
-    );
+    // Register link and send burst
+    let Some(mut rx) = register_and_burst(
+        &manager,
+        &matrix,
+        &mut framed,
+        &remote_sid_val,
+        remote_name.clone().unwrap_or_default(),
+        remote_info.clone().unwrap_or_default(),
+        machine.deltas,
+        machine.remote_clock.as_ref(),
+    )
+    .await
+    else {
+        return;
+    };
     // Get references to counters for the loop (cheap Arc clones)
     let link_bytes_sent = manager
         .links
@@ -465,20 +536,12 @@ async fn handle_inbound_connection(
         .bytes_recv
         .clone();
 
-    // Add to topology (direct peer's parent/uplink is the local server)
-    manager.topology.add_server(
-        remote_sid_val.clone(),
-        remote_name.clone().unwrap_or_default(),
-        remote_info.clone().unwrap_or_default(),
-        1,
-        Some(manager.local_id.clone()),
-    );
-
     info!(
         peer = %remote_addr,
         sid = %remote_sid_val.as_str(),
         name = %remote_name.as_deref().unwrap_or("unknown"),
         tls = is_tls,
+        compressed = framed.codec().is_compressed(),
         "Inbound S2S link established"
     );
 
@@ -498,7 +561,7 @@ async fn handle_inbound_connection(
     };
 
     // Reply channel for handler responses
-    let (reply_tx, mut reply_rx) = mpsc::channel::<Arc<Message>>(100);
+    let (reply_tx, mut reply_rx) = crate::state::SendQ::bounded(100);
 
     // Main message loop
     loop {
@@ -618,7 +681,8 @@ async fn handle_inbound_connection(
             }
             _ = shutdown_rx.recv() => {
                 info!(peer = %remote_addr, "Inbound S2S connection stopping due to shutdown");
-                let err_cmd = Message::from(Command::ERROR("Server shutting down".into()))
+                let reason = matrix.lifecycle_manager.shutdown_message();
+                let err_cmd = Message::from(Command::ERROR(reason))
                     .to_string()
                     .trim_end()
                     .to_string();
@@ -694,7 +758,7 @@ pub fn connect_to_peer(
                 S2SStream::Plain(tcp_stream)
             };
 
-            let mut framed = Framed::new(stream, LinesCodec::new());
+            let mut framed = Framed::new(stream, S2SCodec::new());
 
             let mut machine = HandshakeMachine::new(
                 manager.local_id.clone(),
@@ -713,12 +777,11 @@ pub fn connect_to_peer(
                 password: config.password.clone(),
                 sid: manager.local_id.as_str().to_string(),
             };
-            let capab_cmd = Command::CAPAB(
-                crate::sync::handshake::SUPPORTED_CAPABS
-                    .iter()
-                    .map(|s| s.to_string())
-                    .collect(),
-            );
+            let clock = matrix.journal.get().map(|journal| journal.clock());
+            let capab_cmd = Command::CAPAB(crate::sync::handshake::local_capabs(
+                &config,
+                clock.as_ref(),
+            ));
             let server_cmd = Command::SERVER(
                 manager.local_name.clone(),
                 1,
@@ -819,31 +882,23 @@ pub fn connect_to_peer(
                             remote_sid = machine.remote_sid.clone();
                             remote_name = machine.remote_name.clone();
                             remote_info = machine.remote_info.clone();
+                            if let (Some(sid), Some((_, _, _, t))) =
+                                (&remote_sid, machine.remote_svinfo)
+                            {
+                                crate::sync::clock::record_svinfo(sid.as_str(), t);
+                            }
 
-                            // Generate Burst
-                            let target = remote_sid.as_ref().map(|s| s.as_str()).unwrap_or("");
-                            let burst =
-                                burst::generate_burst(&matrix, manager.local_id.as_str(), target)
-                                    .await;
-                            for cmd in burst {
-                                if let Err(e) =
-                                    framed.send(Message::from(cmd).to_string().trim_end()).await
-                                {
-                                    tracing::error!("Failed to send burst: {}", e);
-                                    // Connection failed, handle netsplit if we had a peer
-                                    if let Some(sid) = &remote_sid {
-                                        let rn = remote_name.as_deref().unwrap_or("unknown");
-                                        split::handle_netsplit(
-                                            &matrix,
-                                            sid,
-                                            &manager.local_name,
-                                            rn,
-                                        )
-                                        .await;
-                                    }
-                                    break;
-                                }
+                            if machine.compress
+                                && let Err(e) = framed.codec_mut().enable_compression()
+                            {
+                                tracing::error!(
+                                    "Failed to enable link compression with {}: {}",
+                                    config.hostname,
+                                    e
+                                );
+                                break;
                             }
+
                             handshake_success = true;
                             break;
                         }
@@ -878,21 +933,22 @@ pub fn connect_to_peer(
                 continue;
             }
 
-            // Register link
-            let (tx, mut rx) = mpsc::channel::<Arc<Message>>(100);
-            manager.links.insert(
-                remote_sid_val.clone(),
-                LinkState {
-                    tx,
-                    state: HandshakeState::Synced,
-                    name: remote_name.clone().unwrap_or_default(),
-                    last_pong: Instant::now(),
-                    last_ping: Instant::now(),
-                    connected_at: Instant::now(),
-                    bytes_sent: Arc::new(AtomicU64::new(0)),
-                    bytes_recv: Arc::new(AtomicU64::new(0)),
-                },
-            );
+            // Register link and send burst
+            let Some(mut rx) = register_and_burst(
+                &manager,
+                &matrix,
+                &mut framed,
+                &remote_sid_val,
+                remote_name.clone().unwrap_or_default(),
+                remote_info.clone().unwrap_or_default(),
+                machine.deltas,
+                machine.remote_clock.as_ref(),
+            )
+            .await
+            else {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                continue;
+            };
             // Get references to counters for the loop
             let link_bytes_sent = manager
                 .links
@@ -907,15 +963,6 @@ pub fn connect_to_peer(
                 .bytes_recv
                 .clone();
 
-            // Add to topology (direct peer's parent/uplink is the local server)
-            manager.topology.add_server(
-                remote_sid_val.clone(),
-                remote_name.clone().unwrap_or_default(),
-                remote_info.clone().unwrap_or_default(),
-                1,
-                Some(manager.local_id.clone()),
-            );
-
             // Create ServerState for Registry dispatch
             let mut server_state = crate::state::ServerState {
                 name: remote_name.clone().unwrap_or_default(),
@@ -932,7 +979,7 @@ pub fn connect_to_peer(
             };
 
             // Reply channel for handler responses
-            let (reply_tx, mut reply_rx) = mpsc::channel::<Arc<Message>>(100);
+            let (reply_tx, mut reply_rx) = crate::state::SendQ::bounded(100);
             let remote_addr = format!("{}:{}", config.hostname, config.port)
                 .parse()
                 .unwrap_or_else(|_| std::net::SocketAddr::from(([127, 0, 0, 1], 0)));
@@ -943,7 +990,9 @@ pub fn connect_to_peer(
                         info!(peer = %config.hostname, "Outbound S2S connection stopping");
                         let _ = framed
                             .send(
-                                Message::from(Command::ERROR("Server shutting down".into()))
+                                Message::from(Command::ERROR(
+                                    matrix.lifecycle_manager.shutdown_message(),
+                                ))
                                     .to_string()
                                     .trim_end(),
                             )
@@ -1058,7 +1107,15 @@ pub fn connect_to_peer(
                 }
             }
 
-            // Connection ended - handle netsplit
+            // Connection ended - close the socket now so the peer sees the
+            // split too, rather than only when the retry below reconnects
+            drop(framed);
+
+            // A SQUIT means stay down until the next CONNECT. Checked before
+            // the cleanup so a CONNECT racing it is not undone.
+            let squit = manager.squit_links.remove(&config.name).is_some();
+
+            // Handle netsplit
             let rn = remote_name.as_deref().unwrap_or("unknown");
             info!(remote_sid = %remote_sid_val.as_str(), "Peer disconnected, initiating netsplit cleanup");
             split::handle_netsplit(&matrix, &remote_sid_val, &manager.local_name, rn).await;
@@ -1066,6 +1123,11 @@ pub fn connect_to_peer(
             // Clean up rate limiter state for this peer
             manager.rate_limiter.remove_peer(remote_sid_val.as_str());
 
+            if squit {
+                info!(peer = %config.name, "Link closed by SQUIT, not reconnecting");
+                break 'reconnect_loop;
+            }
+
             // Retry after disconnect
             info!("Reconnecting to {} in 5s...", config.hostname);
             tokio::time::sleep(Duration::from_secs(5)).await;
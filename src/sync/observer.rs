@@ -4,17 +4,140 @@
 //! This is the real-time delta propagation component of Innovation 2.
 
 use crate::state::observer::{GlobalBanType, StateObserver};
-use crate::sync::LinkState;
+use slirc_proto::sync::DeltaCrdt;
 use slirc_proto::sync::channel::ChannelCrdt;
 use slirc_proto::sync::clock::ServerId;
-use slirc_proto::sync::user::UserCrdt;
+use slirc_proto::sync::user::{UserCrdt, UserModesCrdt};
 use slirc_proto::{Command, Message};
 use std::sync::Arc;
+use tokio::sync::mpsc::error::TrySendError;
 use tracing::{debug, info, warn};
 
-use super::SyncManager;
+use super::delta::encode_user_delta;
+use super::{LinkState, SyncManager};
+
+/// Build the S2S form of a global ban.
+///
+/// Format: `<GLINE|ZLINE|RLINE> <mask> <duration> <setter> :<reason>`, where
+/// `<duration>` is the remaining lifetime in seconds and 0 means permanent.
+/// Shuns keep the plain `SHUN <mask> :<reason>` form.
+pub(super) fn ban_add_command(
+    ban_type: GlobalBanType,
+    mask: &str,
+    duration: Option<i64>,
+    setter: &str,
+    reason: &str,
+) -> Command {
+    let name = match ban_type {
+        GlobalBanType::Gline => "GLINE",
+        GlobalBanType::Zline => "ZLINE",
+        GlobalBanType::Rline => "RLINE",
+        GlobalBanType::Shun => return Command::SHUN(mask.to_string(), Some(reason.to_string())),
+    };
+    Command::Raw(
+        name.to_string(),
+        vec![
+            mask.to_string(),
+            duration.unwrap_or(0).to_string(),
+            setter.to_string(),
+            reason.to_string(),
+        ],
+    )
+}
+
+/// Build the S2S removal of a global ban: `UNGLINE <mask>` and friends.
+pub(super) fn ban_remove_command(ban_type: GlobalBanType, mask: &str) -> Command {
+    match ban_type {
+        GlobalBanType::Gline => Command::UNGLINE(mask.to_string()),
+        GlobalBanType::Zline => Command::UNZLINE(mask.to_string()),
+        GlobalBanType::Rline => Command::UNRLINE(mask.to_string()),
+        GlobalBanType::Shun => Command::UNSHUN(mask.to_string()),
+    }
+}
+
+/// Build `ENCAP * LOGIN <uid> <account>`, with `*` for a logout.
+pub(super) fn login_command(uid: &str, account: Option<&str>) -> Command {
+    Command::ENCAP(
+        "*".to_string(),
+        "LOGIN".to_string(),
+        vec![uid.to_string(), account.unwrap_or("*").to_string()],
+    )
+}
 
 impl SyncManager {
+    /// Queue `msg` on every peer link except `skip`, in call order.
+    ///
+    /// Uses `try_send` so deltas reach each link in the order they were
+    /// published. A link whose queue is full is dropped rather than waited
+    /// on: holding the delta back would reorder it behind later ones, so the
+    /// peer relinks and resyncs from a fresh burst instead.
+    fn queue_to_peers(&self, msg: Arc<Message>, skip: Option<&ServerId>, what: &'static str) {
+        self.queue_to_peers_with(|_| msg.clone(), skip, what);
+    }
+
+    /// Like [`Self::queue_to_peers`], choosing the message for each link.
+    fn queue_to_peers_with(
+        &self,
+        msg_for: impl Fn(&LinkState) -> Arc<Message>,
+        skip: Option<&ServerId>,
+        what: &'static str,
+    ) {
+        let mut stalled = Vec::new();
+        for entry in self.links.iter() {
+            let peer_sid = entry.key();
+            if skip == Some(peer_sid) {
+                continue;
+            }
+            match entry.value().tx.try_send(msg_for(entry.value())) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => stalled.push(peer_sid.clone()),
+                Err(TrySendError::Closed(_)) => {
+                    warn!(peer = %peer_sid.as_str(), "Failed to send {}: link closed", what);
+                }
+            }
+        }
+
+        for peer_sid in stalled {
+            warn!(peer = %peer_sid.as_str(), "Send queue full while sending {}; dropping link", what);
+            crate::metrics::inc_s2s_sendq_exceeded(peer_sid.as_str());
+            self.links.remove(&peer_sid);
+        }
+    }
+
+    /// Queue a user update on every peer link except `skip`.
+    ///
+    /// Links that negotiated deltas get `delta` as `ENCAP * UDELTA` from
+    /// `origin`; the rest, or every link when there is no delta, get a UID.
+    pub(super) fn queue_user_update(
+        &self,
+        user: &UserCrdt,
+        delta: Option<String>,
+        origin: &str,
+        skip: Option<&ServerId>,
+    ) {
+        let uid_msg = Arc::new(Message::from(self.build_uid_command(user)));
+        let Some(delta) = delta else {
+            self.queue_to_peers(uid_msg, skip, "UID");
+            return;
+        };
+        let delta_msg = Arc::new(Message {
+            tags: None,
+            prefix: Some(slirc_proto::Prefix::new_from_str(origin)),
+            command: Command::ENCAP("*".to_string(), "UDELTA".to_string(), vec![delta]),
+        });
+        self.queue_to_peers_with(
+            |link| {
+                if link.deltas {
+                    delta_msg.clone()
+                } else {
+                    uid_msg.clone()
+                }
+            },
+            skip,
+            "user update",
+        );
+    }
+
     /// Build an SJOIN command for a channel state.
     fn build_sjoin_command(&self, channel: &ChannelCrdt) -> Command {
         // SJOIN timestamp channel modes [args] :[@user1 +user2 ...]
@@ -98,38 +221,21 @@ impl SyncManager {
     }
 
     /// Build a UID command for a user.
-    fn build_uid_command(&self, user: &UserCrdt) -> Command {
+    pub(super) fn build_uid_command(&self, user: &UserCrdt) -> Command {
         // UID nick hopcount ts user host uid modes :realname
-        let ts = chrono::Utc::now().timestamp().to_string();
+        // Use the nick register's stamp rather than the wall clock, so
+        // re-sending a UID for another field never bumps the collision TS.
+        let ts = (user.nick.timestamp().millis / 1000).to_string();
         let hopcount = "1".to_string();
 
-        // Build mode string
-        let mut modes = "+".to_string();
-        if *user.modes.invisible.value() {
-            modes.push('i');
-        }
-        if *user.modes.oper.value() {
-            modes.push('o');
-        }
-        if *user.modes.registered.value() {
-            modes.push('r');
-        }
-        if *user.modes.wallops.value() {
-            modes.push('w');
-        }
-        if *user.modes.secure.value() {
-            modes.push('Z');
-        }
-        if *user.modes.bot.value() {
-            modes.push('B');
-        }
+        let modes = uid_mode_string(&user.modes);
 
         Command::UID(
             user.nick.value().clone(),
             hopcount,
             ts,
             user.user.value().clone(),
-            user.host.value().clone(),
+            user.visible_host.value().clone(),
             user.uid.clone(),
             modes,
             user.realname.value().clone(),
@@ -137,6 +243,30 @@ impl SyncManager {
     }
 }
 
+/// User mode string carried in a UID for `modes`.
+pub(super) fn uid_mode_string(modes: &UserModesCrdt) -> String {
+    let mut s = "+".to_string();
+    if *modes.invisible.value() {
+        s.push('i');
+    }
+    if *modes.oper.value() {
+        s.push('o');
+    }
+    if *modes.registered.value() {
+        s.push('r');
+    }
+    if *modes.wallops.value() {
+        s.push('w');
+    }
+    if *modes.secure.value() {
+        s.push('Z');
+    }
+    if *modes.bot.value() {
+        s.push('B');
+    }
+    s
+}
+
 impl StateObserver for SyncManager {
     fn on_user_update(&self, user: &UserCrdt, source: Option<ServerId>) {
         if source.is_some() {
@@ -145,23 +275,27 @@ impl StateObserver for SyncManager {
             return;
         }
 
-        info!(uid = %user.uid, nick = %user.nick.value(), "Broadcasting user update to peers");
+        // Peers that negotiated deltas get only what changed since the state
+        // last shipped; the first update for a user is always a full UID.
+        let base = self.shipped_users.insert(user.uid.clone(), user.clone());
+        let delta = match &base {
+            Some(base) => match user.delta_from(base) {
+                Some(delta) => encode_user_delta(&delta),
+                None => {
+                    debug!(uid = %user.uid, "Skipping user update (nothing changed)");
+                    return;
+                }
+            },
+            None => None,
+        };
 
-        let msg = Arc::new(Message::from(self.build_uid_command(user)));
-        let links = self.links.clone();
+        info!(uid = %user.uid, nick = %user.nick.value(), delta = delta.is_some(), "Broadcasting user update to peers");
 
-        // Spawn async broadcast (we can't await in a sync trait method)
-        tokio::spawn(async move {
-            for entry in links.iter() {
-                let link: LinkState = entry.value().clone();
-                if let Err(e) = link.tx.send(msg.clone()).await {
-                    warn!(peer = %entry.key().as_str(), error = %e, "Failed to send UID");
-                }
-            }
-        });
+        self.queue_user_update(user, delta, self.local_id.as_str(), None);
     }
 
     fn on_user_quit(&self, uid: &str, reason: &str, source: Option<ServerId>) {
+        self.shipped_users.remove(uid);
         if source.is_some() {
             debug!(uid = %uid, "Skipping quit broadcast (remote origin)");
             return;
@@ -176,16 +310,7 @@ impl StateObserver for SyncManager {
             command: Command::QUIT(Some(reason.to_string())),
         });
 
-        let links = self.links.clone();
-
-        tokio::spawn(async move {
-            for entry in links.iter() {
-                let link: LinkState = entry.value().clone();
-                if let Err(e) = link.tx.send(quit_msg.clone()).await {
-                    warn!(peer = %entry.key().as_str(), error = %e, "Failed to send QUIT");
-                }
-            }
-        });
+        self.queue_to_peers(quit_msg, None, "QUIT");
     }
 
     fn on_channel_update(&self, channel: &ChannelCrdt, source: Option<ServerId>) {
@@ -197,16 +322,7 @@ impl StateObserver for SyncManager {
         info!(channel = %channel.name, members = channel.members.len(), "Broadcasting channel update to peers");
 
         let msg = Arc::new(Message::from(self.build_sjoin_command(channel)));
-        let links = self.links.clone();
-
-        tokio::spawn(async move {
-            for entry in links.iter() {
-                let link: LinkState = entry.value().clone();
-                if let Err(e) = link.tx.send(msg.clone()).await {
-                    warn!(peer = %entry.key().as_str(), error = %e, "Failed to send SJOIN");
-                }
-            }
-        });
+        self.queue_to_peers(msg, None, "SJOIN");
     }
 
     fn on_channel_destroy(&self, name: &str, source: Option<ServerId>) {
@@ -230,77 +346,43 @@ impl StateObserver for SyncManager {
         duration: Option<i64>,
         source: Option<ServerId>,
     ) {
-        if source.is_some() {
-            debug!(ban_type = ?ban_type, mask = %mask, "Skipping ban add (remote origin)");
-            return;
+        if let Some(src) = &source {
+            debug!(ban_type = ?ban_type, mask = %mask, source = %src.as_str(), "Propagating ban add from peer");
+        } else {
+            info!(
+                ban_type = ?ban_type,
+                mask = %mask,
+                reason = %reason,
+                setter = %setter,
+                duration = ?duration,
+                "Broadcasting global ban to peers"
+            );
         }
 
-        info!(
-            ban_type = ?ban_type,
-            mask = %mask,
-            reason = %reason,
-            setter = %setter,
-            duration = ?duration,
-            "Broadcasting global ban to peers"
-        );
-
-        // Build the ban command: GLINE/ZLINE/RLINE/SHUN mask :reason
-        // Format: :<setter> GLINE <mask> :<reason>
-        // For timed bans, we could use extended syntax but keep it simple for now
-        let command = match ban_type {
-            GlobalBanType::Gline => Command::GLINE(mask.to_string(), Some(reason.to_string())),
-            GlobalBanType::Zline => Command::ZLINE(mask.to_string(), Some(reason.to_string())),
-            GlobalBanType::Rline => Command::RLINE(mask.to_string(), Some(reason.to_string())),
-            GlobalBanType::Shun => Command::SHUN(mask.to_string(), Some(reason.to_string())),
-        };
         let msg = Arc::new(Message {
             tags: None,
             prefix: Some(slirc_proto::Prefix::ServerName(self.local_name.clone())),
-            command,
+            command: ban_add_command(ban_type, mask, duration, setter, reason),
         });
 
-        let links = self.links.clone();
-
-        tokio::spawn(async move {
-            for entry in links.iter() {
-                let link: LinkState = entry.value().clone();
-                if let Err(e) = link.tx.send(msg.clone()).await {
-                    warn!(peer = %entry.key().as_str(), error = %e, "Failed to send ban");
-                }
-            }
-        });
+        // Split-horizon: don't send back to source
+        self.queue_to_peers(msg, source.as_ref(), "ban");
     }
 
     fn on_ban_remove(&self, ban_type: GlobalBanType, mask: &str, source: Option<ServerId>) {
-        if source.is_some() {
-            debug!(ban_type = ?ban_type, mask = %mask, "Skipping ban remove (remote origin)");
-            return;
+        if let Some(src) = &source {
+            debug!(ban_type = ?ban_type, mask = %mask, source = %src.as_str(), "Propagating ban removal from peer");
+        } else {
+            info!(ban_type = ?ban_type, mask = %mask, "Broadcasting ban removal to peers");
         }
 
-        info!(ban_type = ?ban_type, mask = %mask, "Broadcasting ban removal to peers");
-
-        let command = match ban_type {
-            GlobalBanType::Gline => Command::UNGLINE(mask.to_string()),
-            GlobalBanType::Zline => Command::UNZLINE(mask.to_string()),
-            GlobalBanType::Rline => Command::UNRLINE(mask.to_string()),
-            GlobalBanType::Shun => Command::UNSHUN(mask.to_string()),
-        };
         let msg = Arc::new(Message {
             tags: None,
             prefix: Some(slirc_proto::Prefix::ServerName(self.local_name.clone())),
-            command,
+            command: ban_remove_command(ban_type, mask),
         });
 
-        let links = self.links.clone();
-
-        tokio::spawn(async move {
-            for entry in links.iter() {
-                let link: LinkState = entry.value().clone();
-                if let Err(e) = link.tx.send(msg.clone()).await {
-                    warn!(peer = %entry.key().as_str(), error = %e, "Failed to send ban removal");
-                }
-            }
-        });
+        self.queue_to_peers(msg, source.as_ref(), "ban removal");
     }
 
     fn on_account_change(&self, uid: &str, account: Option<&str>, source: Option<ServerId>) {
@@ -310,32 +392,21 @@ impl StateObserver for SyncManager {
             info!(uid = %uid, account = ?account, "Broadcasting local account change to peers");
         }
 
-        let account_str = account.unwrap_or("*");
-
-        // Use ACCOUNT command: :<uid> ACCOUNT <account>
         let msg = Arc::new(Message {
             tags: None,
-            prefix: Some(slirc_proto::Prefix::new_from_str(uid)),
-            command: Command::ACCOUNT(account_str.to_string()),
+            prefix: Some(slirc_proto::Prefix::new_from_str(self.local_id.as_str())),
+            command: login_command(uid, account),
         });
 
-        let links = self.links.clone();
-
-        tokio::spawn(async move {
-            for entry in links.iter() {
-                let peer_sid = entry.key();
-                // Split-horizon: don't send back to source
-                if let Some(src) = &source
-                    && peer_sid == src
-                {
-                    continue;
-                }
+        // Split-horizon: don't send back to source
+        self.queue_to_peers(msg, source.as_ref(), "LOGIN");
+    }
 
-                let link: LinkState = entry.value().clone();
-                if let Err(e) = link.tx.send(msg.clone()).await {
-                    warn!(peer = %peer_sid.as_str(), error = %e, "Failed to send ACCOUNT");
-                }
-            }
-        });
+    fn on_events_dropped(&self) {
+        // Peers would miss the change until anti-entropy happened to notice,
+        // and digests cover neither bans nor accounts; the resync re-sends
+        // both, with ban removals from the tombstones
+        warn!("Replication events dropped; scheduling a resync of every peer");
+        self.resync.notify_one();
     }
 }
@@ -0,0 +1,170 @@
+//! Retiring servers that have left the network for good.
+//!
+//! A server that splits off is remembered in [`SyncManager::departed`]. If it
+//! has not come back after the grace period, the server that saw it leave
+//! retires it: its clock entries, announced vectors and journal clock entry
+//! are dropped, a marker is kept so no replica brings them back, and the
+//! network is told:
+//!
+//! ```text
+//! :<sid> ENCAP * RETIRE <retired-sid> <millis>
+//! ```
+//!
+//! Every server applies the retirement and acknowledges it, including on a
+//! repeat, so a lost acknowledgement is repaired by the next announcement:
+//!
+//! ```text
+//! :<sid> ENCAP * RETIRED <retired-sid>
+//! ```
+//!
+//! A marker can only go once no replica can still hold the server's entries,
+//! so until every server in the topology has acknowledged it, the
+//! retirement is announced again on each tombstone GC tick and its grace
+//! period restarts. After that the marker expires with
+//! [`VectorClock::expire_retirements`] and the SID may be reused.
+//!
+//! [`SyncManager::departed`]: super::SyncManager::departed
+//! [`VectorClock::expire_retirements`]: slirc_proto::sync::VectorClock::expire_retirements
+
+use crate::state::Matrix;
+use slirc_proto::sync::ServerId;
+use slirc_proto::{Command, Message, Prefix};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+use super::SyncManager;
+
+impl SyncManager {
+    /// Remember that `sids` split off at `now_millis`.
+    pub fn record_departures(&self, sids: &[ServerId], now_millis: i64) {
+        for sid in sids {
+            self.departed.insert(sid.clone(), now_millis);
+        }
+    }
+
+    /// Check that every server in the topology but `sid` itself has
+    /// acknowledged its retirement.
+    fn retirement_acknowledged(&self, sid: &ServerId) -> bool {
+        let Some(acks) = self.retirement_acks.get(sid) else {
+            return false;
+        };
+        self.topology
+            .servers
+            .iter()
+            .all(|e| e.key() == sid || acks.contains(e.key()))
+    }
+}
+
+/// Retire departed servers gone past `grace_ms`, re-announce retirements not
+/// every server has acknowledged, and expire the markers of the rest.
+///
+/// Runs on each tombstone GC tick.
+pub async fn retire_departed(matrix: &Matrix, now_millis: i64, grace_ms: i64) {
+    let sync = &matrix.sync_manager;
+
+    // A server that came back is no longer departed
+    sync.departed
+        .retain(|sid, _| !sync.topology.servers.contains_key(sid));
+    let due: Vec<ServerId> = sync
+        .departed
+        .iter()
+        .filter(|e| e.value().saturating_add(grace_ms) <= now_millis)
+        .map(|e| e.key().clone())
+        .collect();
+    for sid in &due {
+        sync.departed.remove(sid);
+        info!(sid = %sid, "Retiring server gone past the grace period");
+    }
+
+    let held: Vec<ServerId> = sync
+        .retirement_acks
+        .iter()
+        .map(|e| e.key().clone())
+        .collect();
+    let pending: Vec<ServerId> = held
+        .into_iter()
+        .filter(|sid| !sync.retirement_acknowledged(sid))
+        .chain(due)
+        .collect();
+    for sid in pending {
+        retire(matrix, &sid, now_millis);
+        let msg = Message {
+            tags: None,
+            prefix: Some(Prefix::new_from_str(sync.local_id.as_str())),
+            command: Command::ENCAP(
+                "*".to_string(),
+                "RETIRE".to_string(),
+                vec![sid.as_str().to_string(), now_millis.to_string()],
+            ),
+        };
+        sync.broadcast(Arc::new(msg), None).await;
+    }
+
+    let expired = sync.retired.lock().expire_retirements(now_millis, grace_ms);
+    if let Some(journal) = matrix.journal.get() {
+        journal.expire_retirements(now_millis, grace_ms);
+    }
+    if expired > 0 {
+        let retired = sync.retired.lock();
+        sync.retirement_acks
+            .retain(|sid, _| retired.is_retired(sid));
+        info!(expired, "Expired acknowledged server retirements");
+    }
+}
+
+/// Drop what is kept per server for `sid` and mark it retired at `at_millis`.
+fn retire(matrix: &Matrix, sid: &ServerId, at_millis: i64) {
+    let sync = &matrix.sync_manager;
+    sync.retired.lock().retire(sid, at_millis);
+    sync.stability.lock().forget(sid);
+    if let Some(journal) = matrix.journal.get() {
+        journal.retire(sid, at_millis);
+    }
+    sync.retirement_acks
+        .entry(sid.clone())
+        .or_default()
+        .insert(sync.local_id.clone());
+}
+
+/// `RETIRE <sid> <millis>` announced by `origin`.
+pub(crate) async fn handle_retire(matrix: &Matrix, origin: &ServerId, sid: &str, at: &str) {
+    let sync = &matrix.sync_manager;
+    let sid = ServerId::new(sid);
+    let Ok(at_millis) = at.parse::<i64>() else {
+        debug!(origin = %origin, "Ignoring malformed RETIRE");
+        return;
+    };
+    if sid == sync.local_id || sync.topology.servers.contains_key(&sid) {
+        debug!(origin = %origin, sid = %sid, "Ignoring RETIRE for a linked server");
+        return;
+    }
+
+    sync.departed.remove(&sid);
+    retire(matrix, &sid, at_millis);
+    sync.retirement_acks
+        .entry(sid.clone())
+        .or_default()
+        .insert(origin.clone());
+
+    let msg = Message {
+        tags: None,
+        prefix: Some(Prefix::new_from_str(sync.local_id.as_str())),
+        command: Command::ENCAP(
+            "*".to_string(),
+            "RETIRED".to_string(),
+            vec![sid.as_str().to_string()],
+        ),
+    };
+    sync.broadcast(Arc::new(msg), None).await;
+}
+
+/// `RETIRED <sid>` acknowledged by `origin`.
+pub(crate) fn handle_retired(matrix: &Matrix, origin: &ServerId, sid: &str) {
+    if let Some(mut acks) = matrix
+        .sync_manager
+        .retirement_acks
+        .get_mut(&ServerId::new(sid))
+    {
+        acks.insert(origin.clone());
+    }
+}
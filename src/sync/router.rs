@@ -0,0 +1,69 @@
+//! Router task for messages addressed to remote users.
+//!
+//! Channel actors and handlers hand messages for users on other servers to
+//! `Matrix::router_tx`; this task forwards them to the peer link that
+//! leads to the user's server.
+
+use crate::state::{Matrix, SendQReceiver};
+use slirc_proto::sync::clock::ServerId;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Spawn the task draining `router_rx` for the lifetime of the server.
+///
+/// The target is the `x-target-uid` tag if present (the PRIVMSG/NOTICE
+/// target is rewritten to it), otherwise the command target itself. Only
+/// PRIVMSG and NOTICE are routed.
+pub fn spawn_router(matrix: Arc<Matrix>, mut router_rx: SendQReceiver) {
+    tokio::spawn(async move {
+        while let Some(msg_arc) = router_rx.recv().await {
+            let mut msg = (*msg_arc).clone();
+            // Check for x-target-uid tag
+            let target_uid = msg
+                .tags
+                .as_ref()
+                .and_then(|tags| tags.iter().find(|t| t.0 == "x-target-uid"))
+                .and_then(|t| t.1.as_ref())
+                .cloned();
+
+            let target_uid = if let Some(uid) = target_uid {
+                // Rewrite command to target UID
+                match &msg.command {
+                    slirc_proto::Command::PRIVMSG(_, text) => {
+                        msg.command = slirc_proto::Command::PRIVMSG(uid.clone(), text.clone());
+                    }
+                    slirc_proto::Command::NOTICE(_, text) => {
+                        msg.command = slirc_proto::Command::NOTICE(uid.clone(), text.clone());
+                    }
+                    _ => {}
+                }
+                uid
+            } else {
+                // Fallback to command target (if it's a UID)
+                match &msg.command {
+                    slirc_proto::Command::PRIVMSG(target, _) => target.clone(),
+                    slirc_proto::Command::NOTICE(target, _) => target.clone(),
+                    _ => continue,
+                }
+            };
+
+            info!(target_uid = %target_uid, "Router task received message");
+
+            // Look up server for target UID
+            // Assuming UID prefix is SID (3 chars)
+            if target_uid.len() >= 3 {
+                let sid_prefix = &target_uid[0..3];
+                let target_sid = ServerId::new(sid_prefix.to_string());
+
+                if let Some(peer) = matrix.sync_manager.get_peer_for_server(&target_sid) {
+                    info!(target_sid = %target_sid.as_str(), "Routing message to peer");
+                    if let Err(e) = peer.tx.send(Arc::new(msg)).await {
+                        warn!(target_sid = %target_sid.as_str(), error = %e, "Failed to route message to peer (link likely dead)");
+                    }
+                } else {
+                    warn!(target_sid = %target_sid.as_str(), "No peer found for target server");
+                }
+            }
+        }
+    });
+}
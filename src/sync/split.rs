@@ -3,21 +3,35 @@
 //! When a server link drops, this module handles the cleanup:
 //! - Identifies all servers that became unreachable
 //! - Performs a "mass quit" for all affected users
+//! - Relays their QUITs, and a SQUIT for the lost server, to the servers
+//!   still linked
 //! - Updates the topology graph
-//! - Notifies local clients of the splits
+//! - Notifies local clients that shared a channel with them
+//!
+//! Nothing is kept for the split side: when the link comes back, the burst
+//! re-introduces its users and channels and the usual merge rules apply.
 
+use super::TopologyGraph;
 use crate::state::Matrix;
 use slirc_proto::sync::clock::ServerId;
 use slirc_proto::{BatchSubCommand, Command, Message, Prefix, Tag, generate_batch_ref};
+use std::collections::HashSet;
 use std::sync::Arc;
 use tracing::{debug, info};
 
+/// A netsplit QUIT and the channels (lowercased) its user was in.
+struct SplitQuit {
+    message: Message,
+    channels: HashSet<String>,
+}
+
 /// Handle a netsplit when a server link drops.
 ///
 /// This function:
 /// 1. Calculates all servers that became unreachable
-/// 2. Removes all users from those servers
-/// 3. Updates the topology graph
+/// 2. Removes all users from those servers and relays their QUITs to the
+///    remaining peers
+/// 3. Updates the topology graph and tells the remaining peers with SQUIT
 /// 4. Notifies local clients of the splits (using BATCH if capable)
 ///
 /// # Arguments
@@ -36,18 +50,98 @@ pub async fn handle_netsplit(
         "Netsplit detected, calculating affected scope"
     );
 
-    // 1. Calculate all SIDs that are now unreachable
-    let affected_sids = matrix
+    let quit_reason = netsplit_reason(local_name, remote_name);
+    if split_servers(matrix, dead_link_sid, &quit_reason, remote_name, true).await {
+        // The QUITs went out above; this drops the servers themselves
+        let squit = Message {
+            tags: None,
+            prefix: Some(Prefix::new_from_str(matrix.sync_manager.local_id.as_str())),
+            command: Command::SQUIT(dead_link_sid.as_str().to_string(), quit_reason),
+        };
+        matrix
+            .sync_manager
+            .broadcast(Arc::new(squit), Some(dead_link_sid))
+            .await;
+    }
+
+    // Remove the dead link from direct links
+    matrix.sync_manager.links.remove(dead_link_sid);
+}
+
+/// Remove a server from the network on a SQUIT.
+///
+/// A direct link is closed (the SQUIT is sent down it first) and will not
+/// be retried; the connection loop then runs the netsplit, which tells the
+/// other peers. A remote server is split off here and the SQUIT relayed to
+/// every link but `source`.
+pub async fn squit_server(
+    matrix: &Matrix,
+    sid: &ServerId,
+    reason: &str,
+    source: Option<&ServerId>,
+) {
+    let squit = Arc::new(Message {
+        tags: None,
+        prefix: Some(Prefix::new_from_str(matrix.sync_manager.local_id.as_str())),
+        command: Command::SQUIT(sid.as_str().to_string(), reason.to_string()),
+    });
+
+    if let Some((_, link)) = matrix.sync_manager.links.remove(sid) {
+        matrix.sync_manager.squit_links.insert(link.name.clone());
+        let _ = link.tx.send(squit).await;
+        return;
+    }
+
+    handle_remote_split(matrix, sid).await;
+    matrix.sync_manager.broadcast(squit, source).await;
+}
+
+/// Handle the loss of a server we are not directly linked to (SQUIT).
+///
+/// Its users and everything behind it are removed like in a netsplit, but
+/// no QUITs are relayed: the SQUIT itself travels on to the other peers and
+/// each server does its own cleanup.
+pub async fn handle_remote_split(matrix: &Matrix, lost_sid: &ServerId) {
+    let Some(lost) = matrix
         .sync_manager
         .topology
-        .get_downstream_sids(dead_link_sid);
+        .servers
+        .get(lost_sid)
+        .map(|s| s.value().clone())
+    else {
+        debug!(sid = %lost_sid.as_str(), "SQUIT for unknown server");
+        return;
+    };
+    let uplink_name = lost
+        .via
+        .as_ref()
+        .and_then(|via| matrix.sync_manager.topology.servers.get(via))
+        .map_or_else(
+            || matrix.server_info.name.clone(),
+            |uplink| uplink.name.clone(),
+        );
+
+    info!(sid = %lost_sid.as_str(), name = %lost.name, "Remote server split off");
+    let quit_reason = netsplit_reason(&uplink_name, &lost.name);
+    split_servers(matrix, lost_sid, &quit_reason, &lost.name, false).await;
+}
+
+/// Remove `lost_sid`, everything behind it and all their users.
+///
+/// Returns `false` if the server was not in the topology.
+async fn split_servers(
+    matrix: &Matrix,
+    lost_sid: &ServerId,
+    quit_reason: &str,
+    remote_name: &str,
+    relay_quits: bool,
+) -> bool {
+    // 1. Calculate all SIDs that are now unreachable
+    let affected_sids = matrix.sync_manager.topology.get_downstream_sids(lost_sid);
 
     if affected_sids.is_empty() {
-        debug!(
-            "No servers affected by netsplit from {}",
-            dead_link_sid.as_str()
-        );
-        return;
+        debug!("No servers affected by netsplit from {}", lost_sid.as_str());
+        return false;
     }
 
     info!(
@@ -56,22 +150,14 @@ pub async fn handle_netsplit(
         affected_sids.len()
     );
 
-    // Build the quit reason
-    let quit_reason = netsplit_reason(local_name, remote_name);
-
     // 2. Mass quit: Find and remove all users from affected servers
     let mut affected_users = Vec::new();
 
-    // Collect affected users (users whose UID starts with an affected SID)
+    // Collect affected users (users that originated on an affected server)
     for entry in matrix.user_manager.users.iter() {
         let uid = entry.key();
-
-        // Extract SID from UID (first 3 characters)
-        if uid.len() >= 3 {
-            let user_sid = ServerId::new(uid[0..3].to_string());
-            if affected_sids.contains(&user_sid) {
-                affected_users.push(uid.clone());
-            }
+        if TopologyGraph::origin_of(uid).is_some_and(|sid| affected_sids.contains(&sid)) {
+            affected_users.push(uid.clone());
         }
     }
 
@@ -82,99 +168,129 @@ pub async fn handle_netsplit(
     );
 
     // Collection of QUIT messages for batch broadcast
-    let mut quit_msgs = Vec::with_capacity(affected_users.len());
+    let mut quits = Vec::with_capacity(affected_users.len());
 
     // 3. Build QUIT messages and kill users (kill_user handles stats + cleanup)
     for uid in &affected_users {
+        let Some(user_arc) = matrix
+            .user_manager
+            .users
+            .get(uid)
+            .map(|u| u.value().clone())
+        else {
+            continue;
+        };
+
         // Build QUIT message (before killing user)
-        if let Some(user_arc) = matrix.user_manager.users.get(uid) {
+        let quit = {
             let user = user_arc.read().await;
-            quit_msgs.push(Message {
-                tags: None,
-                prefix: Some(Prefix::Nickname(
-                    user.nick.clone(),
-                    user.user.clone(),
-                    user.visible_host.clone(),
-                )),
-                command: Command::QUIT(Some(quit_reason.clone())),
-            });
-        }
+            SplitQuit {
+                message: Message {
+                    tags: None,
+                    prefix: Some(Prefix::Nickname(
+                        user.nick.clone(),
+                        user.user.clone(),
+                        user.visible_host.clone(),
+                    )),
+                    command: Command::QUIT(Some(quit_reason.to_string())),
+                },
+                channels: user
+                    .channels
+                    .iter()
+                    .map(|c| slirc_proto::irc_to_lower(c))
+                    .collect(),
+            }
+        };
 
         // Remove from channels
-        remove_user_from_channels(matrix, uid).await;
+        remove_user_from_channels(matrix, uid, &quit.channels).await;
 
-        // Kill user (handles stats, whowas, nicks, senders, observer)
+        // Kill user (handles stats, whowas, nicks, senders). The source keeps
+        // the observer quiet; the QUIT is relayed below instead.
         matrix
             .user_manager
-            .kill_user(uid, &quit_reason, Some(dead_link_sid.clone()))
+            .kill_user(uid, quit_reason, Some(lost_sid.clone()))
             .await;
+
+        // Unless a SQUIT carries the split, the rest of the network only
+        // learns of it through us
+        if relay_quits {
+            let relay = Message {
+                tags: None,
+                prefix: Some(Prefix::new_from_str(uid)),
+                command: Command::QUIT(Some(quit_reason.to_string())),
+            };
+            matrix
+                .sync_manager
+                .broadcast(Arc::new(relay), Some(lost_sid))
+                .await;
+        }
+
+        quits.push(quit);
     }
 
     // Broadcast QUITs to local users (using batch if possible)
-    broadcast_netsplit_batch(matrix, remote_name, quit_msgs).await;
+    broadcast_netsplit_batch(matrix, remote_name, quits).await;
 
     // 4. Remove affected servers from topology
     let sid_list: Vec<ServerId> = affected_sids.into_iter().collect();
     matrix.sync_manager.topology.remove_servers(&sid_list);
-
-    // 5. Remove the dead link from direct links
-    matrix.sync_manager.links.remove(dead_link_sid);
+    matrix
+        .sync_manager
+        .record_departures(&sid_list, matrix.clock().millis);
 
     info!(
-        dead_sid = %dead_link_sid.as_str(),
+        lost_sid = %lost_sid.as_str(),
         users_removed = affected_users.len(),
         servers_removed = sid_list.len(),
         "Netsplit cleanup complete"
     );
+    true
 }
 
-/// Remove a user from all channels they are in.
-async fn remove_user_from_channels(matrix: &Matrix, uid: &str) {
-    // Get list of channels the user is in
-    let channels: Vec<String> = if let Some(user_arc) = matrix
-        .user_manager
-        .users
-        .get(uid)
-        .map(|u| u.value().clone())
-    {
-        let user = user_arc.read().await;
-        user.channels.iter().cloned().collect()
-    } else {
-        return;
-    };
-
-    // Send PART event to each channel actor
-    for channel_name in channels {
-        let channel_lower = slirc_proto::irc_to_lower(&channel_name);
+/// Remove a user from the given channels, destroying any left empty.
+async fn remove_user_from_channels(matrix: &Matrix, uid: &str, channels: &HashSet<String>) {
+    use crate::state::actor::ChannelEvent;
 
+    for channel_lower in channels {
         let channel_tx = matrix
             .channel_manager
             .channels
-            .get(&channel_lower)
+            .get(channel_lower)
             .map(|c| c.value().clone());
 
         if let Some(channel_tx) = channel_tx {
-            use crate::state::actor::ChannelEvent;
-
             // Send a netsplit removal event
-            // Using the PART event structure but could also be a dedicated event
-            let (reply_tx, _reply_rx) = tokio::sync::oneshot::channel();
+            let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
             let event = ChannelEvent::NetsplitQuit {
                 uid: uid.to_string(),
                 reply_tx,
             };
+            if channel_tx.send(event).await.is_err() {
+                continue;
+            }
 
-            // Send and don't wait for response (async cleanup)
-            let _ = channel_tx.send(event).await;
+            if let Ok(remaining) = reply_rx.await
+                && remaining == 0
+                && matrix
+                    .channel_manager
+                    .channels
+                    .remove(channel_lower)
+                    .is_some()
+            {
+                crate::metrics::dec_active_channels();
+                matrix.stats_manager.channel_destroyed();
+            }
         }
     }
 }
 
-/// Broadcast a batch of netsplit QUITs to all local users.
+/// Broadcast a batch of netsplit QUITs to local users.
 ///
-/// Uses IRCv3 BATCH capability if supported by the client.
-async fn broadcast_netsplit_batch(matrix: &Matrix, remote_server: &str, quit_msgs: Vec<Message>) {
-    if quit_msgs.is_empty() {
+/// Each session only sees the QUITs of users it shared a channel with. Uses
+/// IRCv3 BATCH capability if supported by the client.
+async fn broadcast_netsplit_batch(matrix: &Matrix, remote_server: &str, quits: Vec<SplitQuit>) {
+    if quits.is_empty() {
         return;
     }
 
@@ -202,28 +318,56 @@ async fn broadcast_netsplit_batch(matrix: &Matrix, remote_server: &str, quit_msg
     let end_arc = Arc::new(batch_end);
 
     // Pre-calculate tagged messages for batch-capable clients
-    let tagged_msgs: Vec<Arc<Message>> = quit_msgs
+    let tagged_msgs: Vec<Arc<Message>> = quits
         .iter()
-        .map(|msg| {
-            let mut m = msg.clone();
+        .map(|quit| {
+            let mut m = quit.message.clone();
             m.tags = Some(vec![Tag::new("batch", Some(batch_ref.clone()))]);
             Arc::new(m)
         })
         .collect();
 
     // Pre-calculate legacy messages
-    let legacy_msgs: Vec<Arc<Message>> = quit_msgs.into_iter().map(Arc::new).collect();
+    let legacy_msgs: Vec<Arc<Message>> = quits
+        .iter()
+        .map(|quit| Arc::new(quit.message.clone()))
+        .collect();
 
     // Collect all sessions to iterate (avoids holding lock on senders map)
-    // We need the sender and session_id
+    // We need the owning uid, the sender and session_id
     let mut sessions = Vec::new();
     for entry in matrix.user_manager.senders.iter() {
         for session in entry.value() {
-            sessions.push((session.tx.clone(), session.session_id));
+            sessions.push((entry.key().clone(), session.tx.clone(), session.session_id));
         }
     }
 
-    for (tx, session_id) in sessions {
+    for (uid, tx, session_id) in sessions {
+        let Some(user_arc) = matrix
+            .user_manager
+            .users
+            .get(&uid)
+            .map(|u| u.value().clone())
+        else {
+            continue;
+        };
+        let visible: Vec<usize> = {
+            let user = user_arc.read().await;
+            quits
+                .iter()
+                .enumerate()
+                .filter(|(_, quit)| {
+                    user.channels
+                        .iter()
+                        .any(|c| quit.channels.contains(&slirc_proto::irc_to_lower(c)))
+                })
+                .map(|(i, _)| i)
+                .collect()
+        };
+        if visible.is_empty() {
+            continue;
+        }
+
         let caps = matrix
             .user_manager
             .get_session_caps(session_id)
@@ -232,14 +376,14 @@ async fn broadcast_netsplit_batch(matrix: &Matrix, remote_server: &str, quit_msg
         if caps.contains("batch") {
             // Send batch
             let _ = tx.try_send(start_arc.clone());
-            for msg in &tagged_msgs {
-                let _ = tx.try_send(msg.clone());
+            for &i in &visible {
+                let _ = tx.try_send(tagged_msgs[i].clone());
             }
             let _ = tx.try_send(end_arc.clone());
         } else {
             // Send individual messages
-            for msg in &legacy_msgs {
-                let _ = tx.try_send(msg.clone());
+            for &i in &visible {
+                let _ = tx.try_send(legacy_msgs[i].clone());
             }
         }
     }
@@ -259,4 +403,64 @@ mod tests {
         let reason = netsplit_reason("irc.local.net", "irc.remote.net");
         assert_eq!(reason, "irc.local.net irc.remote.net");
     }
+
+    #[tokio::test]
+    async fn test_netsplit_quits_reach_channel_peers_and_other_links() {
+        use crate::test_support::TestMatrixBuilder;
+
+        let tm = TestMatrixBuilder::new().build().await;
+        let _dead = tm.peer("00A").await;
+        let mut other = tm.peer("00B").await;
+        let mut alice = tm.user("alice").build().await;
+        let mut carol = tm.user("carol").build().await;
+        tm.run(&mut alice, "JOIN #shared").await;
+        tm.remote_user("00A", "00AAAAAAA", "bob").await;
+        tm.run_server("00A", ":00AAAAAAA JOIN 1700000000 #shared +")
+            .await;
+        tm.run_server("00A", ":00AAAAAAA JOIN 1700000000 #remote +")
+            .await;
+        alice.drain();
+        carol.drain();
+        while other.try_recv().is_ok() {}
+
+        let local_name = tm.matrix.server_info.name.clone();
+        handle_netsplit(&tm.matrix, &ServerId::new("00A"), &local_name, "00a.test").await;
+
+        let quit = alice
+            .recv_matching(|m| matches!(&m.command, Command::QUIT(_)))
+            .await
+            .expect("channel member sees the netsplit quit");
+        assert_eq!(quit.source_nickname(), Some("bob"));
+        assert_eq!(
+            quit.command,
+            Command::QUIT(Some(format!("{} 00a.test", local_name)))
+        );
+        assert!(
+            !carol
+                .drain()
+                .iter()
+                .any(|m| matches!(&m.command, Command::QUIT(_)))
+        );
+
+        assert!(!tm.matrix.user_manager.users.contains_key("00AAAAAAA"));
+        assert!(tm.matrix.channel_manager.channels.contains_key("#shared"));
+        assert!(!tm.matrix.channel_manager.channels.contains_key("#remote"));
+        assert!(
+            !tm.matrix
+                .sync_manager
+                .links
+                .contains_key(&ServerId::new("00A"))
+        );
+
+        let relayed: Vec<_> = std::iter::from_fn(|| other.try_recv().ok()).collect();
+        assert!(relayed.iter().any(|m| {
+            matches!(&m.command, Command::QUIT(_))
+                && m.prefix.as_ref().map(|p| p.to_string()).as_deref() == Some("00AAAAAAA")
+        }));
+        assert!(
+            relayed
+                .iter()
+                .any(|m| matches!(&m.command, Command::SQUIT(sid, _) if sid == "00A"))
+        );
+    }
 }
@@ -0,0 +1,192 @@
+//! Tombstone garbage collection across linked servers.
+//!
+//! Removals from the CRDT sets (unbans, parts, deleted metadata) leave
+//! tombstones so a peer that has not seen the removal cannot bring the
+//! entry back. Once every server has seen every update stamped before some
+//! point, no such peer is left and older tombstones can go.
+//!
+//! Every [`TOMBSTONE_GC_INTERVAL`] each server announces its clock and the
+//! latest clock it has heard announced by every other server:
+//!
+//! ```text
+//! :<sid> ENCAP * STABLE :<sid>=<millis> <sid>=<millis> ...
+//! ```
+//!
+//! The lowest entry across all the vectors is the stable point (see
+//! [`StableVersionVector`]). Channel actors and the CRDT journal then drop
+//! tombstones stamped before it.
+//!
+//! Pruning is skipped while any configured link is down or any server in
+//! the topology has not announced yet: a server that cannot be heard from
+//! may still hold the state a tombstone is there to cancel. A server that
+//! splits pins the stable point until it returns, or until it has been
+//! silent for [`DEFAULT_RETIREMENT_GRACE_MS`].
+//!
+//! [`StableVersionVector`]: slirc_proto::sync::StableVersionVector
+
+use crate::state::Matrix;
+use slirc_proto::sync::clock::{DEFAULT_RETIREMENT_GRACE_MS, ServerId};
+use slirc_proto::{Command, Message, Prefix};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info};
+
+use super::SyncManager;
+use super::handshake::HandshakeState;
+
+/// How often clocks are announced and stable tombstones pruned.
+pub const TOMBSTONE_GC_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Encode a vector as `<sid>=<millis>` pairs, sorted by SID.
+fn encode_vector(vector: &HashMap<ServerId, i64>) -> String {
+    let mut entries: Vec<_> = vector.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+        .into_iter()
+        .map(|(sid, millis)| format!("{sid}={millis}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn decode_vector(s: &str) -> Option<HashMap<ServerId, i64>> {
+    s.split_whitespace()
+        .map(|pair| {
+            let (sid, millis) = pair.split_once('=')?;
+            Some((ServerId::new(sid), millis.parse().ok()?))
+        })
+        .collect()
+}
+
+impl SyncManager {
+    /// Announce our clock and prune stable tombstones every
+    /// [`TOMBSTONE_GC_INTERVAL`].
+    pub fn start_tombstone_gc(
+        &self,
+        matrix: Arc<Matrix>,
+        mut shutdown_rx: tokio::sync::broadcast::Receiver<()>,
+    ) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TOMBSTONE_GC_INTERVAL);
+            interval.tick().await;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => collect_tombstones(&matrix).await,
+                    _ = shutdown_rx.recv() => {
+                        info!("Tombstone GC task stopping due to shutdown");
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Check that every configured link is up and no link is mid-handshake.
+    fn all_links_synced(&self) -> bool {
+        let synced = |state: &HandshakeState| *state == HandshakeState::Synced;
+        self.links.iter().all(|e| synced(&e.value().state))
+            && self
+                .configured_links
+                .iter()
+                .filter(|link| !self.squit_links.contains(&link.name))
+                .all(|link| {
+                    self.links
+                        .iter()
+                        .any(|e| e.value().name == link.name && synced(&e.value().state))
+                })
+    }
+
+    /// Millis before which tombstones may be pruned, if any.
+    ///
+    /// `None` while a link is down or some server has not announced.
+    pub fn stable_millis(&self) -> Option<i64> {
+        let stability = self.stability.lock();
+        let all_heard = self
+            .topology
+            .servers
+            .iter()
+            .all(|e| stability.contains(e.key()));
+        stability
+            .stable_millis()
+            .filter(|_| all_heard && self.all_links_synced())
+    }
+}
+
+/// Announce our vector to the network, then prune what is stable.
+///
+/// Also retires servers gone past the grace period (see
+/// [`retirement`](super::retirement)).
+pub async fn collect_tombstones(matrix: &Matrix) {
+    let sync = &matrix.sync_manager;
+    let now = matrix.clock().millis;
+    super::retirement::retire_departed(matrix, now, DEFAULT_RETIREMENT_GRACE_MS).await;
+    let vector = {
+        let mut stability = sync.stability.lock();
+        let expired = stability.expire(now, DEFAULT_RETIREMENT_GRACE_MS);
+        if expired > 0 {
+            info!(
+                expired,
+                "Stopped waiting on servers silent past the grace period"
+            );
+        }
+        stability.announce(now)
+    };
+
+    if !sync.links.is_empty() {
+        let msg = Message {
+            tags: None,
+            prefix: Some(Prefix::new_from_str(sync.local_id.as_str())),
+            command: Command::ENCAP(
+                "*".to_string(),
+                "STABLE".to_string(),
+                vec![encode_vector(&vector)],
+            ),
+        };
+        sync.broadcast(Arc::new(msg), None).await;
+    }
+
+    let Some(stable_millis) = sync.stable_millis() else {
+        debug!("Tombstone GC waiting for every server to announce");
+        return;
+    };
+    matrix.channel_manager.prune_tombstones(stable_millis).await;
+    if let Some(journal) = matrix.journal.get() {
+        journal.prune_tombstones(stable_millis);
+    }
+}
+
+/// `STABLE <vector>` announced by `origin`.
+pub(crate) fn handle_stable(matrix: &Matrix, origin: &ServerId, encoded: &str) {
+    let Some(vector) = decode_vector(encoded) else {
+        debug!(origin = %origin, "Ignoring malformed STABLE vector");
+        return;
+    };
+    let now = chrono::Utc::now().timestamp_millis();
+    matrix
+        .sync_manager
+        .stability
+        .lock()
+        .observe(origin, &vector, now);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_round_trip() {
+        let vector: HashMap<_, _> = [(ServerId::new("00B"), 200), (ServerId::new("00A"), -5)]
+            .into_iter()
+            .collect();
+        let encoded = encode_vector(&vector);
+        assert_eq!(encoded, "00A=-5 00B=200");
+        assert_eq!(decode_vector(&encoded), Some(vector));
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_entries() {
+        assert_eq!(decode_vector("00A=1 00B"), None);
+        assert_eq!(decode_vector("00A=soon"), None);
+        assert_eq!(decode_vector(""), Some(HashMap::new()));
+    }
+}
@@ -0,0 +1,745 @@
+//! CRDT journal and snapshot for crash recovery.
+//!
+//! The warm-boot snapshot only covers a clean shutdown. With `[journal]`
+//! enabled, every replicated channel change is also appended to
+//! `crdt_journal` in the database as it happens. Every
+//! `snapshot_interval_secs`, or once `max_entries` changes are pending, the
+//! journal is folded into `crdt_snapshot` in one transaction and cleared.
+//!
+//! On startup the snapshot is loaded and the journal replayed on top of it.
+//! Channels come back with their modes, topic, key, limit and lists, and
+//! merge with whatever peers burst when links come up, so a crashed server
+//! does not lose its channel TS or topics to a recreated channel. Members
+//! and users are neither journaled nor restored: every local session ended
+//! with the process, and remote users are reintroduced by their servers'
+//! bursts.
+//!
+//! The journal also keeps a vector clock holding, for each server, the
+//! newest topic or mode stamp it issued that the journaled channels carry.
+//! The clock goes to peers in the handshake (see
+//! [`CLOCK_CAPAB`](super::handshake::CLOCK_CAPAB)), and their burst leaves
+//! out the modes and topic of every channel whose stamps it already covers.
+//! The clock is written with each snapshot; rows replayed on top of it only
+//! make it lag, which costs a larger burst and nothing else.
+//!
+//! The journal is fed from the [`ObserverBus`](crate::state::observer::ObserverBus),
+//! which drops events when the journal falls behind. It then rebuilds its
+//! state from the live channels and writes a snapshot straight away.
+
+use crate::config::JournalConfig;
+use crate::db::DbError;
+use crate::state::Matrix;
+use crate::state::observer::{DEFAULT_OBSERVER_QUEUE, GlobalBanType, StateObserver};
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use slirc_proto::irc_to_lower;
+use slirc_proto::sync::channel::ChannelCrdt;
+use slirc_proto::sync::clock::{ServerId, VectorClock};
+use slirc_proto::sync::traits::Crdt;
+use slirc_proto::sync::user::UserCrdt;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info, warn};
+
+/// Journal `kind` for users, which older versions journaled.
+const KIND_USER: &str = "U";
+
+/// Journal `kind` for channels.
+const KIND_CHANNEL: &str = "C";
+
+/// Replicated state as held by the journal.
+#[derive(Debug, Clone, Default)]
+pub struct StoredState {
+    /// Channels by lowercase name.
+    pub channels: HashMap<String, ChannelCrdt>,
+    /// Newest topic or mode stamp (millis) per issuing server.
+    pub clock: VectorClock,
+}
+
+impl StoredState {
+    /// Replace or remove one entity, as recorded in a journal row.
+    fn apply_row(&mut self, kind: &str, key: String, state: Option<&[u8]>) {
+        match (kind, state) {
+            (KIND_USER, _) => {}
+            (KIND_CHANNEL, Some(bytes)) => {
+                if let Some(channel) = decode::<ChannelCrdt>(bytes) {
+                    self.channels.insert(key, channel);
+                }
+            }
+            (KIND_CHANNEL, None) => {
+                self.channels.remove(&key);
+            }
+            _ => warn!(kind = %kind, key = %key, "Skipping unknown CRDT journal row"),
+        }
+    }
+}
+
+/// Load the last snapshot and replay the journal written since.
+pub async fn load(pool: &SqlitePool) -> Result<StoredState, DbError> {
+    let mut state = StoredState::default();
+
+    let clock: Option<Vec<u8>> = sqlx::query_scalar("SELECT clock FROM crdt_clock WHERE id = 1")
+        .fetch_optional(pool)
+        .await?;
+    if let Some(clock) = clock.as_deref().and_then(decode) {
+        state.clock = clock;
+    }
+
+    let rows = sqlx::query_as::<_, (String, String, Vec<u8>)>(
+        "SELECT kind, key, state FROM crdt_snapshot",
+    )
+    .fetch_all(pool)
+    .await?;
+    for (kind, key, bytes) in rows {
+        state.apply_row(&kind, key, Some(&bytes));
+    }
+
+    let rows = sqlx::query_as::<_, (String, String, String, Option<Vec<u8>>)>(
+        "SELECT kind, key, origin, state FROM crdt_journal ORDER BY seq",
+    )
+    .fetch_all(pool)
+    .await?;
+    for (kind, key, _origin, bytes) in rows {
+        state.apply_row(&kind, key, bytes.as_deref());
+    }
+
+    Ok(state)
+}
+
+/// Replay the journal, restore channels and start journaling, if enabled.
+///
+/// Called once on startup, after the warm-boot snapshot is restored and
+/// before clients or peers connect.
+pub async fn recover_on_startup(matrix: &Arc<Matrix>) {
+    let config = &matrix.config.journal;
+    if !config.enabled {
+        return;
+    }
+
+    let pool = matrix.db.pool().clone();
+    let state = match load(&pool).await {
+        Ok(state) => state,
+        Err(e) => {
+            warn!(error = %e, "Failed to load CRDT journal, starting empty");
+            StoredState::default()
+        }
+    };
+
+    let channels: Vec<_> = state.channels.values().cloned().collect();
+    let channel_count = channels.len();
+    matrix
+        .channel_manager
+        .restore_crdts(channels, Arc::downgrade(matrix))
+        .await;
+    info!(
+        channels = channel_count,
+        servers = state.clock.len(),
+        "Recovered CRDT journal"
+    );
+
+    let local_id = matrix.sync_manager.local_id.clone();
+    let journal = CrdtJournal::spawn(pool, local_id, Arc::downgrade(matrix), state, config);
+    matrix
+        .observers
+        .subscribe("journal", journal.clone(), DEFAULT_OBSERVER_QUEUE);
+    let _ = matrix.journal.set(journal);
+}
+
+/// A change queued for the journal writer.
+enum JournalOp {
+    Channel {
+        name: String,
+        origin: ServerId,
+        channel: Option<Box<ChannelCrdt>>,
+    },
+    Snapshot(oneshot::Sender<()>),
+    /// Drop tombstones stamped before this point (millis).
+    Prune(i64),
+    /// Replace the state with every live channel and snapshot it.
+    Rebuild(Vec<ChannelCrdt>),
+}
+
+/// State observer that journals every channel change.
+///
+/// Observer callbacks are synchronous, so changes are queued to a writer
+/// task that owns the database writes and the in-memory copy of the state
+/// the next snapshot is taken from.
+pub struct CrdtJournal {
+    tx: mpsc::UnboundedSender<JournalOp>,
+    /// Origin of changes made on this server.
+    local_id: ServerId,
+    matrix: Weak<Matrix>,
+    /// The writer's clock, as of the last change it recorded.
+    clock: Arc<Mutex<VectorClock>>,
+    /// Set when events were dropped, until the rebuild reads the channels.
+    dirty: Arc<AtomicBool>,
+}
+
+impl CrdtJournal {
+    /// Start the writer task, taking an initial snapshot of `state`.
+    ///
+    /// `matrix` names the servers whose stamps the clock records, and is
+    /// where the state is rebuilt from after dropped events.
+    pub fn spawn(
+        pool: SqlitePool,
+        local_id: ServerId,
+        matrix: Weak<Matrix>,
+        state: StoredState,
+        config: &JournalConfig,
+    ) -> Arc<Self> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let clock = Arc::new(Mutex::new(state.clock.clone()));
+        let writer = JournalWriter {
+            pool,
+            state,
+            local_id: local_id.clone(),
+            matrix: matrix.clone(),
+            clock: Arc::clone(&clock),
+            pending: 0,
+            max_entries: config.max_entries.max(1),
+        };
+        let interval = Duration::from_secs(config.snapshot_interval_secs.max(1));
+        tokio::spawn(writer.run(rx, interval));
+        Arc::new(Self {
+            tx,
+            local_id,
+            matrix,
+            clock,
+            dirty: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// What the journaled channels have seen from each server, for a peer
+    /// to send only what changed since.
+    pub fn clock(&self) -> VectorClock {
+        self.clock.lock().clone()
+    }
+
+    /// Drop a retired server from the clock (see [`super::retirement`]).
+    ///
+    /// Written with the next snapshot.
+    pub fn retire(&self, server: &ServerId, at_millis: i64) {
+        self.clock.lock().retire(server, at_millis);
+    }
+
+    /// Drop retirement markers older than `grace_ms` at `now_millis`.
+    pub fn expire_retirements(&self, now_millis: i64, grace_ms: i64) {
+        self.clock.lock().expire_retirements(now_millis, grace_ms);
+    }
+
+    /// Fold the journal into the snapshot now and wait for it.
+    pub async fn snapshot(&self) {
+        let (tx, rx) = oneshot::channel();
+        if self.tx.send(JournalOp::Snapshot(tx)).is_ok() {
+            let _ = rx.await;
+        }
+    }
+
+    /// Drop tombstones stamped before `stable_millis` from the journaled
+    /// state; the next snapshot no longer carries them.
+    pub fn prune_tombstones(&self, stable_millis: i64) {
+        self.send(JournalOp::Prune(stable_millis));
+    }
+
+    fn send(&self, op: JournalOp) {
+        if self.tx.send(op).is_err() {
+            debug!("CRDT journal writer gone");
+        }
+    }
+}
+
+impl StateObserver for CrdtJournal {
+    // Users are not restored, so they are not journaled either
+    fn on_user_update(&self, _user: &UserCrdt, _source: Option<ServerId>) {}
+
+    fn on_user_quit(&self, _uid: &str, _reason: &str, _source: Option<ServerId>) {}
+
+    fn on_channel_update(&self, channel: &ChannelCrdt, source: Option<ServerId>) {
+        self.send(JournalOp::Channel {
+            name: irc_to_lower(&channel.name),
+            origin: source.unwrap_or_else(|| self.local_id.clone()),
+            channel: Some(Box::new(channel.clone())),
+        });
+    }
+
+    fn on_channel_destroy(&self, name: &str, source: Option<ServerId>) {
+        self.send(JournalOp::Channel {
+            name: irc_to_lower(name),
+            origin: source.unwrap_or_else(|| self.local_id.clone()),
+            channel: None,
+        });
+    }
+
+    fn on_ban_add(
+        &self,
+        _ban_type: GlobalBanType,
+        _mask: &str,
+        _reason: &str,
+        _setter: &str,
+        _duration: Option<i64>,
+        _source: Option<ServerId>,
+    ) {
+        // Global bans are already persisted by the ban handlers
+    }
+
+    fn on_ban_remove(&self, _ban_type: GlobalBanType, _mask: &str, _source: Option<ServerId>) {}
+
+    fn on_account_change(&self, _uid: &str, _account: Option<&str>, _source: Option<ServerId>) {}
+
+    fn on_events_dropped(&self) {
+        // One rebuild covers every drop until it reads the channels
+        if self.dirty.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        warn!("CRDT journal events dropped; rebuilding it from the live channels");
+        let (matrix, dirty, tx) = (
+            self.matrix.clone(),
+            Arc::clone(&self.dirty),
+            self.tx.clone(),
+        );
+        tokio::spawn(async move {
+            let Some(matrix) = matrix.upgrade() else {
+                return;
+            };
+            dirty.store(false, Ordering::Release);
+            let channels = matrix.channel_manager.crdts().await;
+            let _ = tx.send(JournalOp::Rebuild(channels));
+        });
+    }
+}
+
+/// Owns the journal tables and the state the next snapshot is taken from.
+struct JournalWriter {
+    pool: SqlitePool,
+    state: StoredState,
+    local_id: ServerId,
+    matrix: Weak<Matrix>,
+    /// Shared copy of `state.clock`.
+    clock: Arc<Mutex<VectorClock>>,
+    /// Journal rows written since the last snapshot.
+    pending: usize,
+    max_entries: usize,
+}
+
+impl JournalWriter {
+    async fn run(mut self, mut rx: mpsc::UnboundedReceiver<JournalOp>, interval: Duration) {
+        // Compact whatever was replayed on startup
+        self.snapshot_or_warn().await;
+
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            tokio::select! {
+                op = rx.recv() => {
+                    let Some(op) = op else { break };
+                    if let JournalOp::Snapshot(done) = op {
+                        self.snapshot_or_warn().await;
+                        let _ = done.send(());
+                        continue;
+                    }
+                    if let JournalOp::Prune(stable_millis) = op {
+                        self.prune(stable_millis);
+                        continue;
+                    }
+                    if let JournalOp::Rebuild(channels) = op {
+                        self.rebuild(channels);
+                        self.snapshot_or_warn().await;
+                        continue;
+                    }
+                    if let Err(e) = self.record(op).await {
+                        warn!(error = %e, "Failed to append to CRDT journal");
+                    }
+                    if self.pending >= self.max_entries {
+                        self.snapshot_or_warn().await;
+                    }
+                }
+                _ = ticker.tick() => {
+                    if self.pending > 0 {
+                        self.snapshot_or_warn().await;
+                    }
+                }
+            }
+        }
+        debug!("CRDT journal writer stopped");
+    }
+
+    /// Apply a change to the in-memory state and append the result.
+    async fn record(&mut self, op: JournalOp) -> Result<(), DbError> {
+        let servers = self.known_servers();
+        let (kind, key, origin, bytes) = match op {
+            JournalOp::Channel {
+                name,
+                origin,
+                channel,
+            } => {
+                let bytes = match channel {
+                    Some(mut channel) => {
+                        // Merging needs identical names; keep the casing we have
+                        if let Some(current) = self.state.channels.get(&name) {
+                            channel.name.clone_from(&current.name);
+                        }
+                        let merged = merge_into(&mut self.state.channels, &name, *channel);
+                        advance_clock(&mut self.state.clock, merged, &servers);
+                        Some(encode(merged)?)
+                    }
+                    None => {
+                        self.state.channels.remove(&name);
+                        None
+                    }
+                };
+                (KIND_CHANNEL, name, origin, bytes)
+            }
+            JournalOp::Snapshot(_) | JournalOp::Prune(_) | JournalOp::Rebuild(_) => {
+                return Ok(());
+            }
+        };
+
+        *self.clock.lock() = self.state.clock.clone();
+        sqlx::query(
+            "INSERT INTO crdt_journal (kind, key, origin, state, recorded_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(kind)
+        .bind(&key)
+        .bind(origin.as_str())
+        .bind(bytes)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        self.pending += 1;
+        Ok(())
+    }
+
+    /// Drop stable tombstones from the in-memory state.
+    ///
+    /// Nothing is appended: the pruned state only differs in what no
+    /// replica needs any more, so it is written with the next snapshot.
+    fn prune(&mut self, stable_millis: i64) {
+        let pruned: usize = self
+            .state
+            .channels
+            .values_mut()
+            .map(|channel| channel.prune_tombstones(stable_millis))
+            .sum();
+        if pruned > 0 {
+            crate::metrics::inc_crdt_tombstones_pruned("journal", pruned);
+            // Have the next tick write it out
+            self.pending = self.pending.max(1);
+        }
+    }
+
+    /// Replace the state with `channels`, read from the live channels after
+    /// events were dropped.
+    fn rebuild(&mut self, channels: Vec<ChannelCrdt>) {
+        let servers = self.known_servers();
+        self.state.channels.clear();
+        for channel in channels {
+            advance_clock(&mut self.state.clock, &channel, &servers);
+            self.state
+                .channels
+                .insert(irc_to_lower(&channel.name), channel);
+        }
+        *self.clock.lock() = self.state.clock.clone();
+    }
+
+    /// Servers whose stamps the clock can record: this one and every server
+    /// in the topology.
+    fn known_servers(&self) -> Vec<ServerId> {
+        let mut servers = vec![self.local_id.clone()];
+        if let Some(matrix) = self.matrix.upgrade() {
+            servers.extend(
+                matrix
+                    .sync_manager
+                    .topology
+                    .servers
+                    .iter()
+                    .map(|e| e.key().clone()),
+            );
+        }
+        servers
+    }
+
+    /// Replace the snapshot with the current state and clear the journal.
+    async fn snapshot(&mut self) -> Result<(), DbError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM crdt_snapshot")
+            .execute(&mut *tx)
+            .await?;
+        for (key, channel) in &self.state.channels {
+            sqlx::query("INSERT INTO crdt_snapshot (kind, key, state) VALUES (?, ?, ?)")
+                .bind(KIND_CHANNEL)
+                .bind(key)
+                .bind(encode(channel)?)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query("INSERT OR REPLACE INTO crdt_clock (id, clock, snapshot_at) VALUES (1, ?, ?)")
+            .bind(encode(&self.state.clock)?)
+            .bind(chrono::Utc::now().timestamp())
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM crdt_journal")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        debug!(
+            channels = self.state.channels.len(),
+            folded = self.pending,
+            "CRDT snapshot written"
+        );
+        self.pending = 0;
+        Ok(())
+    }
+
+    async fn snapshot_or_warn(&mut self) {
+        if let Err(e) = self.snapshot().await {
+            warn!(error = %e, "Failed to write CRDT snapshot");
+        }
+    }
+}
+
+/// Raise `clock` to every topic and mode stamp of `channel` issued by one
+/// of `servers`.
+fn advance_clock(clock: &mut VectorClock, channel: &ChannelCrdt, servers: &[ServerId]) {
+    for ts in channel.state_timestamps() {
+        if let (Some(server), Some(position)) = (
+            servers.iter().find(|server| ts.is_from(server)),
+            ts.position(),
+        ) {
+            clock.advance(server, position);
+        }
+    }
+}
+
+/// Merge `value` into the entry for `key`, returning the result.
+fn merge_into<'a, T: Crdt>(map: &'a mut HashMap<String, T>, key: &str, value: T) -> &'a T {
+    match map.entry(key.to_string()) {
+        std::collections::hash_map::Entry::Occupied(entry) => {
+            let current = entry.into_mut();
+            current.merge(&value);
+            current
+        }
+        std::collections::hash_map::Entry::Vacant(entry) => entry.insert(value),
+    }
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, DbError> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).map_err(|e| DbError::Internal(e.to_string()))?;
+    Ok(bytes)
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Option<T> {
+    match ciborium::from_reader(bytes) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!(error = %e, "Skipping undecodable CRDT journal entry");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::state::actor::ChannelEvent;
+    use crate::state::dashmap_ext::DashMapExt;
+    use crate::sync::TopologyGraph;
+    use crate::test_support::TestMatrixBuilder;
+    use slirc_proto::sync::channel::TopicCrdt;
+    use slirc_proto::sync::clock::HybridTimestamp;
+
+    fn journal_config() -> JournalConfig {
+        JournalConfig {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    const T0: i64 = 1_700_000_000_000;
+
+    fn local() -> ServerId {
+        ServerId::new("00T")
+    }
+
+    fn channel(name: &str, topic: &str, sid: &ServerId, millis: i64) -> ChannelCrdt {
+        let ts = HybridTimestamp::new(millis, 0, sid);
+        let mut crdt = ChannelCrdt::new(name.to_string(), ts);
+        crdt.topic.update(
+            Some(TopicCrdt {
+                text: topic.to_string(),
+                set_by: "alice".to_string(),
+                set_at: 1_700_000_000,
+            }),
+            ts.increment(),
+        );
+        crdt.members.join("00AAAAAAA".to_string(), ts);
+        crdt
+    }
+
+    fn user(uid: &str, nick: &str) -> UserCrdt {
+        let sid = TopologyGraph::origin_of(uid).unwrap();
+        UserCrdt::new(
+            uid.to_string(),
+            nick.to_string(),
+            "user".to_string(),
+            "Real Name".to_string(),
+            "host".to_string(),
+            "host".to_string(),
+            HybridTimestamp::new(T0, 0, &sid),
+        )
+    }
+
+    async fn count(pool: &SqlitePool, table: &str) -> i64 {
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table}"))
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn journal_is_replayed_on_load() {
+        let db = Database::new(":memory:").await.unwrap();
+        let pool = db.pool().clone();
+        let peer = ServerId::new("00A");
+        let journal = CrdtJournal::spawn(
+            pool.clone(),
+            local(),
+            Weak::new(),
+            StoredState::default(),
+            &journal_config(),
+        );
+        journal.snapshot().await;
+
+        journal.on_user_update(&user("00AAAAAAA", "alice"), Some(peer.clone()));
+        journal.on_channel_update(&channel("#Chan", "hello", &peer, T0), Some(peer.clone()));
+        journal.on_channel_update(&channel("#gone", "x", &peer, T0), Some(peer.clone()));
+        journal.on_channel_destroy("#gone", Some(peer.clone()));
+        journal.on_channel_update(&channel("#mine", "ours", &local(), T0), None);
+
+        journal.snapshot().await;
+        // Replayed on top of the snapshot
+        journal.on_channel_update(
+            &channel("#chan", "newer", &peer, T0 + 1),
+            Some(peer.clone()),
+        );
+        journal.on_account_change("00AAAAAAA", Some("alice"), Some(peer.clone()));
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+        while count(&pool, "crdt_journal").await < 1 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        // Users and accounts are not journaled
+        assert_eq!(count(&pool, "crdt_journal").await, 1);
+
+        let state = load(&pool).await.unwrap();
+        assert_eq!(state.channels.len(), 2);
+        let topic = state.channels["#chan"].topic.value().clone().unwrap();
+        assert_eq!(topic.text, "newer");
+        // Only known servers' stamps are recorded, as of the snapshot
+        let topic_ts = HybridTimestamp::new(T0, 1, &local());
+        assert_eq!(state.clock.get(&local()), topic_ts.position().unwrap());
+        assert_eq!(state.clock.get(&peer), 0);
+        assert!(journal.clock().covers(&topic_ts));
+    }
+
+    #[tokio::test]
+    async fn snapshot_folds_and_clears_journal() {
+        let db = Database::new(":memory:").await.unwrap();
+        let pool = db.pool().clone();
+        let peer = ServerId::new("00A");
+        let config = JournalConfig {
+            max_entries: 2,
+            ..journal_config()
+        };
+        let journal = CrdtJournal::spawn(
+            pool.clone(),
+            local(),
+            Weak::new(),
+            StoredState::default(),
+            &config,
+        );
+
+        journal.on_channel_update(&channel("#a", "a", &peer, T0), Some(peer.clone()));
+        journal.on_channel_update(&channel("#b", "b", &peer, T0), Some(peer.clone()));
+        journal.on_channel_update(&channel("#c", "c", &peer, T0), Some(peer.clone()));
+        journal.snapshot().await;
+
+        assert_eq!(count(&pool, "crdt_journal").await, 0);
+        assert_eq!(count(&pool, "crdt_snapshot").await, 3);
+        let state = load(&pool).await.unwrap();
+        assert_eq!(state.channels.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn recovery_restores_channels_without_members() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let pool = tm.matrix.db.pool().clone();
+        let peer = ServerId::new("00A");
+        let journal = CrdtJournal::spawn(
+            pool.clone(),
+            local(),
+            Weak::new(),
+            StoredState::default(),
+            &journal_config(),
+        );
+        journal.on_channel_update(&channel("#Chan", "restored", &peer, T0), Some(peer.clone()));
+        journal.snapshot().await;
+
+        let mut state = load(&pool).await.unwrap();
+        let channels = std::mem::take(&mut state.channels).into_values().collect();
+        tm.matrix
+            .channel_manager
+            .restore_crdts(channels, Arc::downgrade(&tm.matrix))
+            .await;
+
+        let tx = tm.matrix.channel_manager.channels.get_cloned("#chan");
+        let (reply_tx, reply_rx) = oneshot::channel();
+        tx.expect("channel restored")
+            .send(ChannelEvent::GetMembers { reply_tx })
+            .await
+            .unwrap();
+        assert!(reply_rx.await.unwrap().is_empty());
+
+        let mut bob = tm.user("bob").build().await;
+        let replies = tm.run(&mut bob, "JOIN #chan").await;
+        assert!(replies.iter().any(|m| m.to_string().contains("restored")));
+    }
+
+    #[tokio::test]
+    async fn dropped_events_rebuild_from_live_channels() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let pool = tm.matrix.db.pool().clone();
+        let journal = CrdtJournal::spawn(
+            pool.clone(),
+            tm.matrix.server_id.clone(),
+            Arc::downgrade(&tm.matrix),
+            StoredState::default(),
+            &journal_config(),
+        );
+        journal.snapshot().await;
+
+        // #live changes while the journal misses every event
+        let mut bob = tm.user("bob").build().await;
+        tm.run(&mut bob, "JOIN #live").await;
+        tm.run(&mut bob, "TOPIC #live :kept").await;
+        assert!(load(&pool).await.unwrap().channels.is_empty());
+
+        journal.on_events_dropped();
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+        while count(&pool, "crdt_snapshot").await < 1 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let state = load(&pool).await.unwrap();
+        let topic = state.channels["#live"].topic.value().clone().unwrap();
+        assert_eq!(topic.text, "kept");
+        let topic_ts = state.channels["#live"].topic.timestamp();
+        assert!(journal.clock().covers(&topic_ts));
+    }
+}
@@ -14,6 +14,7 @@ fn create_link(name: &str, password: &str) -> LinkBlock {
         cert_fingerprint: None,
         autoconnect: false,
         sid: None,
+        compression: false,
     }
 }
 
@@ -59,11 +60,15 @@ fn test_handshake_flow() {
     assert!(res.is_empty());
 
     // 2 processes SERVER
-    let res = machine2.step(server1, std::slice::from_ref(&link2)).unwrap();
+    let res = machine2
+        .step(server1, std::slice::from_ref(&link2))
+        .unwrap();
     assert!(res.is_empty()); // Not complete yet, waiting for SVINFO
 
     // 4. SVINFO1 from 1 - now 2 is complete
-    let res = machine2.step(svinfo1, std::slice::from_ref(&link2)).unwrap();
+    let res = machine2
+        .step(svinfo1, std::slice::from_ref(&link2))
+        .unwrap();
     assert_eq!(machine2.state, HandshakeState::Bursting);
     assert_eq!(res.len(), 4); // Should send PASS, CAPAB, SERVER, SVINFO back
 
@@ -112,15 +117,92 @@ fn test_handshake_flow() {
     );
 
     // 1 processes SERVER from 2
-    let res = machine1.step(server2, std::slice::from_ref(&link1)).unwrap();
+    let res = machine1
+        .step(server2, std::slice::from_ref(&link1))
+        .unwrap();
     assert!(res.is_empty());
 
     // 1 processes SVINFO from 2 - now 1 is complete
-    let res = machine1.step(svinfo2, std::slice::from_ref(&link1)).unwrap();
+    let res = machine1
+        .step(svinfo2, std::slice::from_ref(&link1))
+        .unwrap();
     assert_eq!(machine1.state, HandshakeState::Bursting);
     assert!(res.is_empty());
 }
 
+/// Run an inbound handshake from a peer offering `remote_capabs`; returns
+/// the machine and the CAPAB it replied with.
+fn inbound_handshake(link: &LinkBlock, remote_capabs: &[&str]) -> (HandshakeMachine, Command) {
+    let mut machine = HandshakeMachine::new(
+        ServerId::new("002".to_string()),
+        "server2".to_string(),
+        "Server 2".to_string(),
+    );
+    machine.transition(HandshakeState::InboundReceived);
+    let links = std::slice::from_ref(link);
+    let commands = [
+        Command::PassTs6 {
+            password: "secret".to_string(),
+            sid: "001".to_string(),
+        },
+        Command::CAPAB(remote_capabs.iter().map(|c| c.to_string()).collect()),
+        Command::SERVER(
+            "server1".to_string(),
+            1,
+            "001".to_string(),
+            "Server 1".to_string(),
+        ),
+        Command::SVINFO(6, 6, 0, 1234567890),
+    ];
+    let mut replies = Vec::new();
+    for command in commands {
+        replies = machine.step(command, links).unwrap();
+    }
+    assert_eq!(machine.state, HandshakeState::Bursting);
+    (machine, replies.swap_remove(1))
+}
+
+#[test]
+fn test_handshake_negotiates_compression_only_when_both_offer() {
+    use super::handshake::COMPRESSION_CAPAB;
+
+    let offers_zstd = |capab: &Command| match capab {
+        Command::CAPAB(caps) => caps.iter().any(|c| c == COMPRESSION_CAPAB),
+        other => panic!("expected CAPAB, got {other:?}"),
+    };
+    let mut link = create_link("server1", "secret");
+
+    let (machine, capab) = inbound_handshake(&link, &["QS", COMPRESSION_CAPAB]);
+    assert!(!offers_zstd(&capab));
+    assert!(!machine.compress);
+
+    link.compression = true;
+    let (machine, capab) = inbound_handshake(&link, &["QS"]);
+    assert!(offers_zstd(&capab));
+    assert!(!machine.compress);
+
+    let (machine, _) = inbound_handshake(&link, &["QS", COMPRESSION_CAPAB]);
+    assert!(machine.compress);
+}
+
+#[test]
+fn test_handshake_carries_journal_clock() {
+    use super::handshake::local_capabs;
+    use slirc_proto::sync::clock::VectorClock;
+
+    let link = create_link("server1", "secret");
+    let mut clock = VectorClock::new();
+    clock.advance(&ServerId::new("001"), 1_700_000_000_000);
+
+    let capabs = local_capabs(&link, Some(&clock));
+    let offered: Vec<&str> = capabs.iter().map(String::as_str).collect();
+    let (machine, _) = inbound_handshake(&link, &offered);
+    assert_eq!(machine.remote_clock, Some(clock));
+
+    let (machine, _) = inbound_handshake(&link, &["QS", "CLOCK=not base64!"]);
+    assert_eq!(machine.remote_clock, None);
+}
+
 #[test]
 fn test_handshake_mismatched_sid() {
     let sid1 = ServerId::new("001".to_string());
@@ -283,6 +365,7 @@ async fn test_state_observer_split_horizon() {
         bans: AwSet::new(),
         excepts: AwSet::new(),
         invites: AwSet::new(),
+        quiets: AwSet::new(),
     };
 
     // Notify with NO source (local change) - should broadcast to all peers
@@ -346,6 +429,7 @@ async fn test_state_observer_skip_source() {
         bans: AwSet::new(),
         excepts: AwSet::new(),
         invites: AwSet::new(),
+        quiets: AwSet::new(),
     };
 
     // Notify WITH source (remote change) - should NOT broadcast back
@@ -444,3 +528,915 @@ fn test_s2s_tls_config_parsing() {
     assert_eq!(cfg.client_auth, ClientAuth::Optional);
     assert_eq!(cfg.ca_path.as_deref(), Some("/etc/slircd/ca.crt"));
 }
+
+#[tokio::test]
+async fn test_full_link_queue_drops_link_without_reordering() {
+    use super::SyncManager;
+    use super::link::LINK_QUEUE_CAPACITY;
+    use crate::state::observer::{GlobalBanType, StateObserver};
+
+    let sync = SyncManager::new(
+        ServerId::new("001".to_string()),
+        "test.server".to_string(),
+        "Test Server".to_string(),
+        vec![],
+        &crate::config::RateLimitConfig::default(),
+    );
+    let peer_sid = ServerId::new("002".to_string());
+    let mut rx = sync
+        .register_peer(
+            peer_sid.clone(),
+            "peer.server".to_string(),
+            1,
+            "Peer".to_string(),
+        )
+        .await;
+
+    // The peer reads nothing; one delta more than the queue holds
+    for i in 0..=LINK_QUEUE_CAPACITY {
+        sync.on_ban_remove(GlobalBanType::Gline, &format!("*@{i}.example"), None);
+    }
+    assert!(!sync.links.contains_key(&peer_sid));
+
+    // Everything queued arrives in order, then the link is closed
+    for i in 0..LINK_QUEUE_CAPACITY {
+        let msg = rx.recv().await.expect("queued delta");
+        assert!(
+            matches!(&msg.command, Command::UNGLINE(mask) if *mask == format!("*@{i}.example"))
+        );
+    }
+    assert!(rx.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn test_link_registered_before_burst_queues_deltas() {
+    use super::codec::S2SCodec;
+    use super::network::register_and_burst;
+    use super::stream::S2SStream;
+    use crate::state::observer::StateObserver;
+    use futures_util::StreamExt;
+    use tokio_util::codec::{Framed, LinesCodec};
+
+    let tm = crate::test_support::TestMatrixBuilder::new().build().await;
+    let alice = tm.user("alice").build().await;
+    let manager = tm.matrix.sync_manager.clone();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let (server, _) = listener.accept().await.unwrap();
+    let mut framed = Framed::new(S2SStream::Plain(server), S2SCodec::new());
+    let mut peer = Framed::new(client, LinesCodec::new());
+
+    let peer_sid = ServerId::new("002".to_string());
+    let mut rx = register_and_burst(
+        &manager,
+        &tm.matrix,
+        &mut framed,
+        &peer_sid,
+        "peer.server".to_string(),
+        "Peer".to_string(),
+        true,
+        None,
+    )
+    .await
+    .expect("burst is written");
+    assert_eq!(
+        manager.links.get(&peer_sid).unwrap().state,
+        HandshakeState::Synced
+    );
+    assert_eq!(manager.topology.servers.get(&peer_sid).unwrap().hopcount, 1);
+
+    // The burst went straight to the socket and introduces alice
+    let mut introduced = false;
+    while let Ok(Some(Ok(line))) =
+        tokio::time::timeout(std::time::Duration::from_millis(200), peer.next()).await
+    {
+        introduced |= line.starts_with("UID alice ");
+    }
+    assert!(introduced, "burst does not introduce alice");
+
+    // Deltas are queued on the link, in order, for the writer loop
+    manager.on_user_quit(&alice.uid, "first", None);
+    manager.on_user_quit(&alice.uid, "second", None);
+    let reasons: Vec<_> = [rx.try_recv().unwrap(), rx.try_recv().unwrap()]
+        .into_iter()
+        .map(|m| match &m.command {
+            Command::QUIT(reason) => reason.clone(),
+            other => panic!("unexpected command {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        reasons,
+        [Some("first".to_string()), Some("second".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn test_burst_sends_topology_first_and_skips_target_side() {
+    let tm = crate::test_support::TestMatrixBuilder::new().build().await;
+    let _alice = tm.user("alice").build().await;
+    let _a = tm.peer("00A").await;
+    let _b = tm.peer("00B").await;
+    tm.run_server("00A", ":00A SID far.test 2 00C :Far").await;
+    tm.remote_user("00A", "00CAAAAAA", "carol").await;
+    tm.remote_user("00B", "00BAAAAAA", "dave").await;
+
+    let burst = super::burst::generate_burst(&tm.matrix, "00T", "00A", None).await;
+    let sids: Vec<_> = burst
+        .iter()
+        .filter_map(|m| match &m.command {
+            Command::SID(_, hops, sid, _) => Some((sid.as_str(), hops.as_str())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(sids, [("00B", "2")], "only servers off the target's side");
+    let nicks: Vec<_> = burst
+        .iter()
+        .filter_map(|m| match &m.command {
+            Command::UID(nick, hops, ..) => Some((nick.as_str(), hops.as_str())),
+            _ => None,
+        })
+        .collect();
+    assert!(nicks.contains(&("alice", "1")));
+    assert!(nicks.contains(&("dave", "2")));
+    assert!(!nicks.iter().any(|(nick, _)| *nick == "carol"));
+    let first_uid = burst
+        .iter()
+        .position(|m| matches!(m.command, Command::UID(..)))
+        .unwrap();
+    let last_sid = burst
+        .iter()
+        .rposition(|m| matches!(m.command, Command::SID(..)))
+        .unwrap();
+    assert!(last_sid < first_uid, "servers are introduced before users");
+}
+
+#[tokio::test]
+async fn test_burst_sends_global_bans_but_not_dlines() {
+    let tm = crate::test_support::TestMatrixBuilder::new().build().await;
+    let bans = tm.matrix.db.bans();
+    bans.add_gline("*@bad.example", Some("spam"), "oper", Some(3600))
+        .await
+        .unwrap();
+    bans.add_zline("192.0.2.0/24", Some("botnet"), "oper", None)
+        .await
+        .unwrap();
+    bans.add_dline("198.51.100.1", Some("local only"), "oper", None)
+        .await
+        .unwrap();
+
+    let burst = super::burst::generate_burst(&tm.matrix, "00T", "00A", None).await;
+    let bans: Vec<_> = burst
+        .iter()
+        .filter_map(|m| match &m.command {
+            Command::Raw(cmd, args) if cmd.ends_with("LINE") => Some((cmd.as_str(), args)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(bans.len(), 2);
+    let (_, gline) = bans.iter().find(|(cmd, _)| *cmd == "GLINE").unwrap();
+    assert_eq!(gline[0], "*@bad.example");
+    let remaining: i64 = gline[1].parse().unwrap();
+    assert!(remaining > 3500 && remaining <= 3600);
+    assert_eq!(gline[2], "oper");
+    let (_, zline) = bans.iter().find(|(cmd, _)| *cmd == "ZLINE").unwrap();
+    assert_eq!(zline[..3], ["192.0.2.0/24", "0", "oper"]);
+}
+
+#[tokio::test]
+async fn test_burst_leaves_out_channel_state_the_peer_clock_covers() {
+    use slirc_proto::sync::clock::VectorClock;
+
+    let tm = crate::test_support::TestMatrixBuilder::new().build().await;
+    let mut alice = tm.user("alice").build().await;
+    tm.run(&mut alice, "JOIN #delta").await;
+    tm.run(&mut alice, "TOPIC #delta :kept").await;
+    tm.run(&mut alice, "MODE #delta +m").await;
+    // Mode changes reach the channel actor after the reply is written.
+    let applied = |mode: char| {
+        let manager = &tm.matrix.channel_manager;
+        async move {
+            for _ in 0..100 {
+                let crdts = manager.crdts().await;
+                if crdts
+                    .iter()
+                    .any(|crdt| crdt.modes.flag(mode).is_some_and(|flag| *flag.value()))
+                {
+                    return;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+            panic!("+{mode} was never applied");
+        }
+    };
+    applied('m').await;
+
+    let sjoin_and_tb = |burst: &[slirc_proto::Message]| {
+        let modes = burst.iter().find_map(|m| match &m.command {
+            Command::SJOIN(_, name, modes, ..) if name == "#delta" => Some(modes.clone()),
+            _ => None,
+        });
+        let tb = burst
+            .iter()
+            .any(|m| matches!(&m.command, Command::TB(name, ..) if name == "#delta"));
+        (modes, tb)
+    };
+
+    let (modes, tb) =
+        sjoin_and_tb(&super::burst::generate_burst(&tm.matrix, "00T", "00A", None).await);
+    assert!(modes.unwrap().contains('m'));
+    assert!(tb, "a peer without a clock gets the topic");
+
+    let newest = tm
+        .matrix
+        .channel_manager
+        .crdts()
+        .await
+        .iter()
+        .flat_map(|crdt| crdt.state_timestamps().collect::<Vec<_>>())
+        .filter_map(|ts| ts.position())
+        .max()
+        .unwrap();
+    let mut clock = VectorClock::new();
+    clock.advance(&tm.matrix.sync_manager.local_id, newest);
+    let burst = super::burst::generate_burst(&tm.matrix, "00T", "00A", Some(&clock)).await;
+    assert_eq!(sjoin_and_tb(&burst), (Some("+".to_string()), false));
+    assert!(
+        burst
+            .iter()
+            .any(|m| matches!(&m.command, Command::SJOIN(.., users) if !users.is_empty())),
+        "membership is still sent"
+    );
+
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    tm.run(&mut alice, "MODE #delta +i").await;
+    applied('i').await;
+    let burst = super::burst::generate_burst(&tm.matrix, "00T", "00A", Some(&clock)).await;
+    let (modes, tb) = sjoin_and_tb(&burst);
+    assert!(modes.unwrap().contains('i'));
+    assert!(tb, "newer state than the clock is sent in full");
+}
+
+/// Members of `channel` on `tm`, by UID.
+async fn channel_members(tm: &crate::test_support::TestMatrix, channel: &str) -> Vec<String> {
+    let Some(tx) = tm
+        .matrix
+        .channel_manager
+        .channels
+        .get(channel)
+        .map(|c| c.value().clone())
+    else {
+        return Vec::new();
+    };
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    if tx
+        .send(crate::state::actor::ChannelEvent::GetMembers { reply_tx })
+        .await
+        .is_err()
+    {
+        return Vec::new();
+    }
+    reply_rx
+        .await
+        .map(|m| m.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn test_two_servers_see_each_others_users_after_burst() {
+    use crate::test_support::{TestNetwork, wait_until};
+
+    let net = TestNetwork::new().await;
+    let alice = net.hub.user("alice").build().await;
+    let bob = net.leaf.user("bob").build().await;
+    net.link().await;
+
+    assert!(
+        wait_until(|| {
+            net.leaf.matrix.user_manager.get_first_uid("alice") == Some(alice.uid.clone())
+                && net.hub.matrix.user_manager.get_first_uid("bob") == Some(bob.uid.clone())
+        })
+        .await
+    );
+
+    // Users registering after the link are propagated too
+    let carol = net.leaf.user("carol").build().await;
+    assert!(
+        wait_until(|| net.hub.matrix.user_manager.get_first_uid("carol") == Some(carol.uid.clone()))
+            .await
+    );
+}
+
+#[tokio::test]
+async fn test_two_servers_link_with_compression() {
+    use crate::test_support::{TestNetwork, wait_until};
+
+    let net = TestNetwork::compressed().await;
+    let alice = net.hub.user("alice").build().await;
+    let bob = net.leaf.user("bob").build().await;
+    net.link().await;
+
+    // The burst and live traffic both cross the compressed link
+    assert!(
+        wait_until(|| {
+            net.leaf.matrix.user_manager.get_first_uid("alice") == Some(alice.uid.clone())
+                && net.hub.matrix.user_manager.get_first_uid("bob") == Some(bob.uid.clone())
+        })
+        .await
+    );
+    let carol = net.hub.user("carol").build().await;
+    assert!(
+        wait_until(
+            || net.leaf.matrix.user_manager.get_first_uid("carol") == Some(carol.uid.clone())
+        )
+        .await
+    );
+}
+
+#[tokio::test]
+async fn test_two_servers_route_privmsg_across_link() {
+    use crate::test_support::{TestNetwork, wait_until};
+
+    let net = TestNetwork::new().await;
+    let mut alice = net.hub.user("alice").build().await;
+    let mut bob = net.leaf.user("bob").build().await;
+    net.link().await;
+    assert!(wait_until(|| net.hub.matrix.user_manager.get_first_uid("bob").is_some()).await);
+
+    net.hub
+        .run(&mut alice, "PRIVMSG bob :hello over the link")
+        .await;
+    let msg = bob
+        .recv_matching(
+            |m| matches!(&m.command, Command::PRIVMSG(_, text) if text == "hello over the link"),
+        )
+        .await
+        .expect("bob receives the message");
+    assert_eq!(msg.source_nickname(), Some("alice"));
+
+    net.leaf.run(&mut bob, "PRIVMSG alice :and back").await;
+    let msg = alice
+        .recv_matching(|m| matches!(&m.command, Command::PRIVMSG(_, text) if text == "and back"))
+        .await
+        .expect("alice receives the reply");
+    assert_eq!(msg.source_nickname(), Some("bob"));
+}
+
+#[tokio::test]
+async fn test_two_servers_merge_channel_membership() {
+    use crate::test_support::TestNetwork;
+
+    let net = TestNetwork::new().await;
+    let mut alice = net.hub.user("alice").build().await;
+    let mut bob = net.leaf.user("bob").build().await;
+    net.hub.run(&mut alice, "JOIN #shared").await;
+    net.leaf.run(&mut bob, "JOIN #shared").await;
+    net.link().await;
+
+    let mut merged = false;
+    for _ in 0..250 {
+        let hub_members = channel_members(&net.hub, "#shared").await;
+        let leaf_members = channel_members(&net.leaf, "#shared").await;
+        if [&alice.uid, &bob.uid]
+            .iter()
+            .all(|uid| hub_members.contains(uid) && leaf_members.contains(uid))
+        {
+            merged = true;
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(merged, "#shared does not hold both users on both servers");
+
+    // Channel traffic crosses the link once merged
+    net.hub.run(&mut alice, "PRIVMSG #shared :hi channel").await;
+    assert!(
+        bob.recv_matching(
+            |m| matches!(&m.command, Command::PRIVMSG(_, text) if text == "hi channel")
+        )
+        .await
+        .is_some()
+    );
+}
+
+#[tokio::test]
+async fn test_two_servers_clean_up_after_squit() {
+    use crate::test_support::{TestNetwork, wait_until};
+
+    let net = TestNetwork::new().await;
+    let mut alice = net.hub.user("alice").oper().build().await;
+    let mut bob = net.leaf.user("bob").build().await;
+    net.hub.run(&mut alice, "JOIN #split").await;
+    net.leaf.run(&mut bob, "JOIN #split").await;
+    net.link().await;
+    assert!(
+        wait_until(|| {
+            net.hub.matrix.user_manager.get_first_uid("bob").is_some()
+                && net
+                    .leaf
+                    .matrix
+                    .user_manager
+                    .get_first_uid("alice")
+                    .is_some()
+        })
+        .await
+    );
+    // Bob only gets a netsplit QUIT to alice once his join has arrived
+    assert!(wait_for_members(&net.hub, "#split", |m| m.len() == 2).await);
+    alice.drain();
+
+    net.hub.run(&mut alice, "SQUIT leaf.test :Split test").await;
+
+    assert!(
+        wait_until(|| {
+            net.hub.matrix.user_manager.get_first_uid("bob").is_none()
+                && net
+                    .leaf
+                    .matrix
+                    .user_manager
+                    .get_first_uid("alice")
+                    .is_none()
+        })
+        .await,
+        "users from the other side survived the split: hub bob={:?} leaf alice={:?}",
+        net.hub.matrix.user_manager.get_first_uid("bob"),
+        net.leaf.matrix.user_manager.get_first_uid("alice")
+    );
+    assert!(
+        !net.hub
+            .matrix
+            .sync_manager
+            .links
+            .contains_key(&ServerId::new("002"))
+    );
+    let quit = alice
+        .recv_matching(|m| {
+            matches!(&m.command, Command::QUIT(_)) && m.source_nickname() == Some("bob")
+        })
+        .await;
+    assert!(quit.is_some(), "alice does not see bob split off");
+}
+
+#[tokio::test]
+async fn test_two_servers_reconcile_after_relink() {
+    use crate::test_support::{TestNetwork, wait_until};
+
+    let net = TestNetwork::new().await;
+    let mut alice = net.hub.user("alice").oper().build().await;
+    let mut bob = net.leaf.user("bob").build().await;
+    net.hub.run(&mut alice, "JOIN #split").await;
+    net.leaf.run(&mut bob, "JOIN #split").await;
+    net.leaf.run(&mut bob, "JOIN #leafonly").await;
+    net.link().await;
+    assert!(wait_for_members(&net.hub, "#leafonly", |m| m.len() == 1).await);
+
+    net.hub.run(&mut alice, "SQUIT leaf.test :Split test").await;
+    assert!(wait_until(|| net.hub.matrix.user_manager.get_first_uid("bob").is_none()).await);
+    assert!(
+        !net.hub
+            .matrix
+            .channel_manager
+            .channels
+            .contains_key("#leafonly")
+    );
+
+    // While split, a new user shows up on the leaf
+    let mut carol = net.leaf.user("carol").build().await;
+    net.leaf.run(&mut carol, "JOIN #split").await;
+
+    // SQUIT keeps the link down until an operator asks for it again
+    tokio::time::sleep(std::time::Duration::from_secs(6)).await;
+    assert!(net.hub.matrix.user_manager.get_first_uid("carol").is_none());
+    net.hub.run(&mut alice, "CONNECT leaf.test").await;
+
+    // The bursts restore both sides
+    let relinked = || {
+        net.hub.matrix.user_manager.get_first_uid("carol").is_some()
+            && net
+                .leaf
+                .matrix
+                .user_manager
+                .get_first_uid("alice")
+                .is_some()
+    };
+    let mut back = false;
+    for _ in 0..3 {
+        if wait_until(relinked).await {
+            back = true;
+            break;
+        }
+    }
+    assert!(back, "link did not come back");
+
+    let expected = [&alice.uid, &bob.uid, &carol.uid];
+    for tm in [&net.hub, &net.leaf] {
+        assert!(
+            wait_for_members(tm, "#split", |m| {
+                m.len() == 3 && expected.iter().all(|uid| m.contains(uid))
+            })
+            .await
+        );
+    }
+    assert!(wait_for_members(&net.hub, "#leafonly", |m| m == [bob.uid.clone()]).await);
+}
+
+/// Poll `channel`'s members on `tm` until `cond` holds, for up to five seconds.
+async fn wait_for_members(
+    tm: &crate::test_support::TestMatrix,
+    channel: &str,
+    cond: impl Fn(&[String]) -> bool,
+) -> bool {
+    for _ in 0..250 {
+        if cond(&channel_members(tm, channel).await) {
+            return true;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    false
+}
+
+#[tokio::test]
+async fn test_two_servers_replicate_account_login_and_logout() {
+    use crate::services::effect::{ServiceEffect, apply_effect_no_sender};
+    use crate::test_support::{TestNetwork, wait_until};
+
+    let net = TestNetwork::new().await;
+    let alice = net.hub.user("alice").build().await;
+    net.link().await;
+    let leaf_users = &net.leaf.matrix.user_manager;
+    assert!(wait_until(|| leaf_users.get_first_uid("alice").is_some()).await);
+    let leaf_account = || {
+        leaf_users
+            .users
+            .get(&alice.uid)
+            .and_then(|u| u.try_read().ok().and_then(|u| u.account.clone()))
+    };
+
+    let login = ServiceEffect::AccountIdentify {
+        target_uid: alice.uid.clone(),
+        account: "alice".to_string(),
+        account_id: None,
+        metadata: Default::default(),
+    };
+    apply_effect_no_sender(&net.hub.matrix, "alice", login).await;
+    assert!(wait_until(|| leaf_account().as_deref() == Some("alice")).await);
+
+    let logout = ServiceEffect::AccountClear {
+        target_uid: alice.uid.clone(),
+    };
+    apply_effect_no_sender(&net.hub.matrix, "alice", logout).await;
+    assert!(wait_until(|| leaf_account().is_none()).await);
+}
+
+#[tokio::test]
+async fn test_two_servers_replicate_user_changes_as_deltas() {
+    use crate::test_support::{TestNetwork, wait_until};
+
+    let net = TestNetwork::new().await;
+    let mut alice = net.hub.user("alice").cap("setname").build().await;
+    net.link().await;
+    let leaf_users = &net.leaf.matrix.user_manager;
+    assert!(wait_until(|| leaf_users.get_first_uid("alice").is_some()).await);
+    assert!(
+        net.hub
+            .matrix
+            .sync_manager
+            .links
+            .iter()
+            .all(|link| link.deltas)
+    );
+
+    net.hub.run(&mut alice, "SETNAME :Alice Liddell").await;
+    let leaf_realname = || {
+        leaf_users
+            .users
+            .get(&alice.uid)
+            .and_then(|u| u.try_read().ok().map(|u| u.realname.clone()))
+    };
+    assert!(wait_until(|| leaf_realname().as_deref() == Some("Alice Liddell")).await);
+}
+
+#[tokio::test]
+async fn test_uid_updates_keep_ts_and_skip_local_only_fields() {
+    use crate::test_support::TestMatrixBuilder;
+    use std::time::Duration;
+
+    let tm = TestMatrixBuilder::new().build().await;
+    let mut link = tm.peer("00A").await;
+    let mut alice = tm.user("alice").cap("setname").build().await;
+    let registered_ts = {
+        let user = tm
+            .matrix
+            .user_manager
+            .users
+            .get(&alice.uid)
+            .unwrap()
+            .clone();
+        (user.read().await.last_modified.millis / 1000).to_string()
+    };
+
+    // ACCEPT is local-only; SETNAME changes the realname the UID carries
+    tm.run(&mut alice, "ACCEPT bob").await;
+    tm.run(&mut alice, "SETNAME :Alice Liddell").await;
+
+    let mut uids = Vec::new();
+    while let Ok(Some(msg)) = tokio::time::timeout(Duration::from_millis(300), link.recv()).await {
+        if let Command::UID(_, _, ts, _, _, _, _, realname) = &msg.command {
+            uids.push((ts.clone(), realname.clone()));
+        }
+    }
+    // Registration, then SETNAME; nothing for ACCEPT, and the TS never moves
+    let expected = vec![
+        (registered_ts.clone(), "Test User alice".to_string()),
+        (registered_ts, "Alice Liddell".to_string()),
+    ];
+    assert_eq!(uids, expected);
+}
+
+#[tokio::test]
+async fn test_anti_entropy_restores_lost_user_and_removes_ghost() {
+    use crate::sync::anti_entropy::send_digests;
+    use crate::test_support::{TestNetwork, wait_until};
+
+    let net = TestNetwork::new().await;
+    let alice = net.hub.user("alice").build().await;
+    net.link().await;
+    assert!(
+        wait_until(|| net
+            .leaf
+            .matrix
+            .user_manager
+            .get_first_uid("alice")
+            .is_some())
+        .await
+    );
+
+    // The leaf lost alice's UID, and missed the QUIT of another hub user
+    let leaf_users = &net.leaf.matrix.user_manager;
+    leaf_users.users.remove(&alice.uid);
+    leaf_users.nicks.remove("alice");
+    net.leaf.remote_user("001", "001ZZZZZZ", "ghost").await;
+    assert!(
+        net.leaf
+            .matrix
+            .user_manager
+            .get_first_uid("ghost")
+            .is_some()
+    );
+
+    send_digests(&net.hub.matrix).await;
+
+    assert!(
+        wait_until(|| {
+            net.leaf.matrix.user_manager.get_first_uid("alice") == Some(alice.uid.clone())
+                && net
+                    .leaf
+                    .matrix
+                    .user_manager
+                    .get_first_uid("ghost")
+                    .is_none()
+        })
+        .await
+    );
+    // Nothing was lost in the other direction
+    assert_eq!(
+        net.hub.matrix.user_manager.get_first_uid("alice"),
+        Some(alice.uid.clone())
+    );
+}
+
+#[tokio::test]
+async fn test_dropped_replication_events_resync_peers() {
+    use crate::state::observer::StateObserver;
+    use crate::test_support::{TestNetwork, wait_until};
+
+    let net = TestNetwork::new().await;
+    let alice = net.hub.user("alice").build().await;
+    net.link().await;
+    let leaf_users = &net.leaf.matrix.user_manager;
+    assert!(wait_until(|| leaf_users.get_first_uid("alice").is_some()).await);
+
+    // The leaf missed alice, as if her UID had been dropped on the hub's bus
+    leaf_users.users.remove(&alice.uid);
+    leaf_users.nicks.remove("alice");
+
+    let (shutdown_tx, _) = tokio::sync::broadcast::channel(1);
+    let hub_sync = &net.hub.matrix.sync_manager;
+    hub_sync.start_anti_entropy(net.hub.matrix.clone(), shutdown_tx.subscribe());
+    hub_sync.on_events_dropped();
+
+    assert!(wait_until(|| leaf_users.get_first_uid("alice") == Some(alice.uid.clone())).await);
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn test_resync_repairs_dropped_ban_removal_and_logout() {
+    use crate::state::observer::GlobalBanType;
+    use crate::sync::anti_entropy::resync_peers;
+    use crate::test_support::{TestNetwork, wait_until};
+
+    let net = TestNetwork::new().await;
+    let alice = net.hub.user("alice").build().await;
+    net.link().await;
+    let leaf_users = &net.leaf.matrix.user_manager;
+    assert!(wait_until(|| leaf_users.get_first_uid("alice").is_some()).await);
+
+    // The leaf holds a G-line and alice's login the hub has since undone,
+    // as if the UNGLINE and the logout had been dropped on the hub's bus
+    net.leaf
+        .run_server("001", ":hub.test GLINE *@evil.example 0 oper :spam")
+        .await;
+    net.leaf
+        .run_server("001", &format!(":001 ENCAP * LOGIN {} alice", alice.uid))
+        .await;
+    let leaf_bans = net.leaf.matrix.db.bans();
+    assert_eq!(leaf_bans.get_active_glines().await.unwrap().len(), 1);
+    net.hub
+        .matrix
+        .sync_manager
+        .record_ban_removal(GlobalBanType::Gline, "*@evil.example");
+
+    resync_peers(&net.hub.matrix).await;
+
+    let leaf_alice = leaf_users.users.get(&alice.uid).unwrap().clone();
+    let mut repaired = false;
+    for _ in 0..250 {
+        repaired = leaf_bans.get_active_glines().await.unwrap().is_empty()
+            && leaf_alice.read().await.account.is_none();
+        if repaired {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(
+        repaired,
+        "resync did not remove the G-line and log alice out"
+    );
+}
+
+#[tokio::test]
+async fn test_anti_entropy_repairs_channel_membership() {
+    use crate::handlers::server::part::part_remote_member;
+    use crate::sync::anti_entropy::send_digests;
+    use crate::test_support::TestNetwork;
+
+    let net = TestNetwork::new().await;
+    let mut alice = net.hub.user("alice").build().await;
+    net.hub.run(&mut alice, "JOIN #kept").await;
+    net.link().await;
+    assert!(wait_for_members(&net.leaf, "#kept", |m| m.contains(&alice.uid)).await);
+
+    // The leaf missed alice joining #kept, and missed her parting #stale
+    part_remote_member(&net.leaf.matrix, &alice.uid, "#kept", None).await;
+    net.leaf
+        .run_server("001", &format!(":{} JOIN 1700000000 #stale +", alice.uid))
+        .await;
+    assert!(
+        channel_members(&net.leaf, "#stale")
+            .await
+            .contains(&alice.uid)
+    );
+
+    send_digests(&net.hub.matrix).await;
+
+    assert!(wait_for_members(&net.leaf, "#kept", |m| m.contains(&alice.uid)).await);
+    assert!(wait_for_members(&net.leaf, "#stale", |m| !m.contains(&alice.uid)).await);
+}
+
+#[tokio::test]
+async fn test_digest_requests_only_differing_buckets() {
+    use crate::sync::anti_entropy::DIGEST_BUCKETS;
+    use crate::test_support::TestMatrixBuilder;
+
+    let tm = TestMatrixBuilder::new().build().await;
+    let mut peer = tm.peer("00A").await;
+    let empty = vec!["0"; DIGEST_BUCKETS].join(",");
+
+    // We hold nothing from 00A, so an empty digest matches
+    tm.run_server("00A", &format!(":00A ENCAP 00T DIGEST U {empty}"))
+        .await;
+    assert!(peer.try_recv().is_err());
+
+    // Holding one of its users, exactly one bucket differs and names it
+    tm.remote_user("00A", "00AAAAAAA", "bob").await;
+    tm.run_server("00A", &format!(":00A ENCAP 00T DIGEST U {empty}"))
+        .await;
+    let requests: Vec<_> = std::iter::from_fn(|| peer.try_recv().ok()).collect();
+    assert_eq!(requests.len(), 1);
+    let Command::ENCAP(target, sub, params) = &requests[0].command else {
+        panic!("expected ENCAP, got {:?}", requests[0].command);
+    };
+    assert_eq!((target.as_str(), sub.as_str()), ("00A", "DIGESTREQ"));
+    assert_eq!(params[0], "U");
+    assert_eq!(params[2], "00AAAAAAA");
+}
+
+#[tokio::test]
+async fn test_tombstone_gc_waits_for_every_server() {
+    use crate::sync::stability::collect_tombstones;
+    use crate::test_support::TestMatrixBuilder;
+
+    let tm = TestMatrixBuilder::new().build().await;
+    let mut peer = tm.peer("00A").await;
+
+    collect_tombstones(&tm.matrix).await;
+    let announced = peer.try_recv().expect("clock announced to the peer");
+    let Command::ENCAP(target, sub, params) = &announced.command else {
+        panic!("expected ENCAP, got {:?}", announced.command);
+    };
+    assert_eq!((target.as_str(), sub.as_str()), ("*", "STABLE"));
+    let ours: i64 = params[0]
+        .strip_prefix("00T=")
+        .and_then(|millis| millis.parse().ok())
+        .expect("our own clock");
+
+    // 00A has not announced yet, so nothing is stable
+    assert_eq!(tm.matrix.sync_manager.stable_millis(), None);
+
+    // Once it has heard ours, everything before our announcement is
+    tm.run_server(
+        "00A",
+        &format!(":00A ENCAP * STABLE :00A={} 00T={ours}", ours + 5),
+    )
+    .await;
+    assert_eq!(tm.matrix.sync_manager.stable_millis(), Some(ours));
+
+    // A server joining behind 00A holds it back again
+    tm.run_server("00A", ":00A SID leaf2.test 2 00B :Leaf 2")
+        .await;
+    assert!(
+        tm.matrix
+            .sync_manager
+            .topology
+            .servers
+            .contains_key(&ServerId::new("00B"))
+    );
+    assert_eq!(tm.matrix.sync_manager.stable_millis(), None);
+}
+
+#[tokio::test]
+async fn test_departed_server_is_retired_and_expires_once_acknowledged() {
+    use crate::sync::retirement::retire_departed;
+    use crate::test_support::{TestNetwork, wait_until};
+
+    let net = TestNetwork::new().await;
+    net.link().await;
+    let (hub, leaf) = (&net.hub.matrix.sync_manager, &net.leaf.matrix.sync_manager);
+    let gone = ServerId::new("003");
+    let grace = 60_000;
+
+    net.hub
+        .run_server("002", ":002 SID gone.test 2 003 :Gone")
+        .await;
+    net.hub
+        .run_server("002", ":002 SQUIT 003 :gone.test left")
+        .await;
+    assert!(hub.departed.contains_key(&gone));
+    // A peer that never acknowledges holds the marker back
+    let mut silent = net.hub.peer("00X").await;
+
+    // Within the grace period nothing happens
+    let split_at = *hub.departed.get(&gone).unwrap();
+    retire_departed(&net.hub.matrix, split_at + grace - 1, grace).await;
+    assert!(!hub.retired.lock().is_retired(&gone));
+
+    let retired_at = split_at + grace;
+    retire_departed(&net.hub.matrix, retired_at, grace).await;
+    assert!(hub.retired.lock().is_retired(&gone));
+    assert!(!hub.departed.contains_key(&gone));
+    let announced = silent.try_recv().expect("retirement announced");
+    assert_eq!(
+        announced.command,
+        Command::ENCAP(
+            "*".to_string(),
+            "RETIRE".to_string(),
+            vec!["003".to_string(), retired_at.to_string()]
+        )
+    );
+
+    // The leaf applies it and acknowledges
+    assert!(
+        wait_until(|| leaf.retired.lock().is_retired(&gone)
+            && hub
+                .retirement_acks
+                .get(&gone)
+                .is_some_and(|acks| acks.contains(&ServerId::new("002"))))
+        .await,
+        "leaf did not acknowledge the retirement"
+    );
+
+    // 00X has not acknowledged, so the marker is kept and announced again
+    let later = retired_at + 2 * grace;
+    retire_departed(&net.hub.matrix, later, grace).await;
+    assert!(hub.retired.lock().is_retired(&gone));
+    assert!(silent.try_recv().is_ok(), "retirement announced again");
+
+    // Once every server has, it expires after the grace period
+    net.hub.run_server("00X", ":00X ENCAP * RETIRED 003").await;
+    retire_departed(&net.hub.matrix, later + grace - 1, grace).await;
+    assert!(hub.retired.lock().is_retired(&gone));
+    retire_departed(&net.hub.matrix, later + grace, grace).await;
+    assert!(!hub.retired.lock().is_retired(&gone));
+    assert!(!hub.retirement_acks.contains_key(&gone));
+}
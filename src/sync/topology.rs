@@ -6,6 +6,7 @@
 use dashmap::DashMap;
 use slirc_proto::sync::clock::ServerId;
 use std::collections::HashSet;
+use std::time::Instant;
 
 /// Information about a server in the network.
 #[derive(Debug, Clone)]
@@ -23,6 +24,8 @@ pub struct ServerInfo {
     /// This preserves the full tree for accurate remote netsplit handling.
     /// `None` is reserved for our own local server entry.
     pub via: Option<ServerId>,
+    /// When this server joined our view of the network.
+    pub linked_at: Instant,
 }
 
 /// Tracks the network topology as a spanning tree.
@@ -70,10 +73,19 @@ impl TopologyGraph {
                 info,
                 hopcount,
                 via,
+                linked_at: Instant::now(),
             },
         );
     }
 
+    /// The server a user originated from.
+    ///
+    /// UIDs are prefixed with their server's SID, so this needs no lookup and
+    /// stays correct for users whose server has already split off.
+    pub fn origin_of(uid: &str) -> Option<ServerId> {
+        uid.get(..3).map(ServerId::new)
+    }
+
     /// Get the uplink/parent SID for a target server.
     pub fn get_route(&self, target: &ServerId) -> Option<ServerId> {
         self.servers.get(target).and_then(|info| info.via.clone())
@@ -133,6 +145,15 @@ impl Default for TopologyGraph {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_origin_of_uid() {
+        assert_eq!(
+            TopologyGraph::origin_of("00AAAAAAB"),
+            Some(ServerId::new("00A"))
+        );
+        assert_eq!(TopologyGraph::origin_of("00"), None);
+    }
+
     #[test]
     fn test_downstream_sids_linear() {
         // Linear topology (parent pointers): Local -> A -> B -> C
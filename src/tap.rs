@@ -0,0 +1,266 @@
+//! Raw traffic taps behind the oper `TAP` command.
+//!
+//! An operator arms a tap on a UID or an IP; every line that connection reads
+//! or writes is then copied, with secrets redacted, into a small ring buffer
+//! the operator reads back with `TAP <target> SHOW`. Connections check one
+//! atomic counter per line while no tap is armed.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use slirc_proto::Message;
+use std::collections::VecDeque;
+use std::net::IpAddr;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Lines kept per tap; older lines are dropped first.
+pub const TAP_CAPACITY: usize = 500;
+
+/// Taps that may be armed at once.
+pub const MAX_TAPS: usize = 32;
+
+/// Replaces the secret part of a redacted line.
+const REDACTED: &str = "<redacted>";
+
+static TAPS: LazyLock<Taps> = LazyLock::new(Taps::default);
+
+/// The process-wide taps.
+pub fn global() -> &'static Taps {
+    &TAPS
+}
+
+/// What a tap is armed on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TapTarget {
+    /// One connection, by UID.
+    Uid(String),
+    /// Every connection from an address, including before registration.
+    Ip(IpAddr),
+}
+
+impl std::fmt::Display for TapTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Uid(uid) => f.write_str(uid),
+            Self::Ip(ip) => write!(f, "{ip}"),
+        }
+    }
+}
+
+/// Direction of a tapped line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Read from the client.
+    Inbound,
+    /// Written to the client.
+    Outbound,
+}
+
+/// One captured line.
+#[derive(Debug, Clone)]
+pub struct TapLine {
+    pub at: DateTime<Utc>,
+    pub uid: String,
+    pub direction: Direction,
+    /// The line without CRLF, secrets redacted.
+    pub line: String,
+}
+
+impl std::fmt::Display for TapLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let arrow = match self.direction {
+            Direction::Inbound => "<<",
+            Direction::Outbound => ">>",
+        };
+        write!(
+            f,
+            "{} {} {} {}",
+            self.at.format("%H:%M:%S%.3f"),
+            self.uid,
+            arrow,
+            self.line
+        )
+    }
+}
+
+/// Armed taps and their buffers.
+#[derive(Debug, Default)]
+pub struct Taps {
+    taps: DashMap<TapTarget, Mutex<VecDeque<TapLine>>>,
+    armed: AtomicUsize,
+}
+
+impl Taps {
+    /// Arm a tap. Re-arming keeps the lines captured so far.
+    ///
+    /// Returns false when [`MAX_TAPS`] are already armed.
+    pub fn start(&self, target: TapTarget) -> bool {
+        if self.taps.contains_key(&target) {
+            return true;
+        }
+        if self.taps.len() >= MAX_TAPS {
+            return false;
+        }
+        self.taps.entry(target).or_default();
+        self.armed.store(self.taps.len(), Ordering::Relaxed);
+        true
+    }
+
+    /// Disarm a tap and drop its buffer. Returns false if it wasn't armed.
+    pub fn stop(&self, target: &TapTarget) -> bool {
+        let removed = self.taps.remove(target).is_some();
+        self.armed.store(self.taps.len(), Ordering::Relaxed);
+        removed
+    }
+
+    /// The last `count` lines of a tap, oldest first.
+    pub fn lines(&self, target: &TapTarget, count: usize) -> Option<Vec<TapLine>> {
+        let tap = self.taps.get(target)?;
+        let lines = tap.lock();
+        let skip = lines.len().saturating_sub(count);
+        Some(lines.iter().skip(skip).cloned().collect())
+    }
+
+    /// Armed taps with the number of lines each holds.
+    pub fn list(&self) -> Vec<(TapTarget, usize)> {
+        let mut taps: Vec<_> = self
+            .taps
+            .iter()
+            .map(|e| (e.key().clone(), e.value().lock().len()))
+            .collect();
+        taps.sort_by_key(|(target, _)| target.to_string());
+        taps
+    }
+
+    /// Record a line read from a connection.
+    pub fn inbound(&self, uid: &str, ip: IpAddr, line: &str) {
+        if self.armed.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        self.record(uid, ip, Direction::Inbound, line);
+    }
+
+    /// Record a message written to a connection.
+    pub fn outbound(&self, uid: &str, ip: IpAddr, msg: &Message) {
+        if self.armed.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        self.record(uid, ip, Direction::Outbound, &msg.to_string());
+    }
+
+    fn record(&self, uid: &str, ip: IpAddr, direction: Direction, line: &str) {
+        let mut entry = None;
+        for target in [TapTarget::Uid(uid.to_string()), TapTarget::Ip(ip)] {
+            let Some(tap) = self.taps.get(&target) else {
+                continue;
+            };
+            let line = entry.get_or_insert_with(|| TapLine {
+                at: Utc::now(),
+                uid: uid.to_string(),
+                direction,
+                line: redact(line.trim_end_matches(['\r', '\n'])),
+            });
+            let mut lines = tap.lock();
+            if lines.len() >= TAP_CAPACITY {
+                lines.pop_front();
+            }
+            lines.push_back(line.clone());
+        }
+    }
+}
+
+/// Blank out passwords and SASL payloads in a raw line.
+///
+/// Covers PASS, AUTHENTICATE, WEBIRC and CHALLENGE payloads, the OPER
+/// password, account REGISTER, and anything sent to NickServ (directly, via
+/// the NS alias or as a PRIVMSG), since most of its commands take a password.
+pub fn redact(line: &str) -> String {
+    // Split off tags and prefix, which never hold secrets
+    let mut head_len = 0;
+    for word in line.split(' ') {
+        if word.starts_with('@') || word.starts_with(':') {
+            head_len += word.len() + 1;
+        } else {
+            break;
+        }
+    }
+    let head = &line[..head_len.min(line.len())];
+    let rest = &line[head_len.min(line.len())..];
+
+    let mut words = rest.splitn(3, ' ');
+    let command = words.next().unwrap_or_default();
+    let first = words.next();
+    let third = words.next();
+    let kept = match command.to_ascii_uppercase().as_str() {
+        "PASS" | "AUTHENTICATE" | "WEBIRC" | "CHALLENGE" | "REGISTER" => 0,
+        "OPER" | "NS" | "NICKSERV" => 1,
+        "PRIVMSG" | "NOTICE" if first.is_some_and(|t| t.eq_ignore_ascii_case("NickServ")) => 1,
+        _ => return line.to_string(),
+    };
+
+    let mut out = format!("{head}{command}");
+    if kept == 1
+        && let Some(first) = first
+    {
+        out.push(' ');
+        out.push_str(first);
+    }
+    if (kept == 0 && first.is_some()) || (kept == 1 && third.is_some()) {
+        out.push(' ');
+        out.push_str(REDACTED);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_secrets() {
+        assert_eq!(redact("PASS hunter2"), "PASS <redacted>");
+        assert_eq!(
+            redact("AUTHENTICATE dXNlcgB1c2Vy"),
+            "AUTHENTICATE <redacted>"
+        );
+        assert_eq!(redact("OPER admin hunter2"), "OPER admin <redacted>");
+        assert_eq!(
+            redact("@label=1 PRIVMSG NickServ :IDENTIFY hunter2"),
+            "@label=1 PRIVMSG NickServ <redacted>"
+        );
+        assert_eq!(redact("NS IDENTIFY hunter2"), "NS IDENTIFY <redacted>");
+        assert_eq!(
+            redact("PRIVMSG #chan :PASS hunter2"),
+            "PRIVMSG #chan :PASS hunter2"
+        );
+        assert_eq!(redact("CAP LS 302"), "CAP LS 302");
+    }
+
+    #[test]
+    fn taps_keep_a_bounded_ring_per_target() {
+        let taps = Taps::default();
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        taps.inbound("001AAAAAA", ip, "NICK before");
+        assert!(taps.list().is_empty());
+
+        assert!(taps.start(TapTarget::Ip(ip)));
+        assert!(taps.start(TapTarget::Uid("001AAAAAB".to_string())));
+        for i in 0..TAP_CAPACITY + 5 {
+            taps.inbound("001AAAAAA", ip, &format!("PING {i}\r\n"));
+        }
+        taps.inbound("001AAAAAB", "192.0.2.2".parse().unwrap(), "PASS secret");
+
+        let by_ip = taps.lines(&TapTarget::Ip(ip), TAP_CAPACITY * 2).unwrap();
+        assert_eq!(by_ip.len(), TAP_CAPACITY);
+        assert_eq!(by_ip[0].line, "PING 5");
+        let by_uid = taps
+            .lines(&TapTarget::Uid("001AAAAAB".to_string()), 10)
+            .unwrap();
+        assert_eq!(by_uid[0].line, "PASS <redacted>");
+        assert_eq!(by_uid[0].direction, Direction::Inbound);
+
+        assert!(taps.stop(&TapTarget::Ip(ip)));
+        assert!(taps.lines(&TapTarget::Ip(ip), 10).is_none());
+    }
+}
@@ -24,6 +24,7 @@ impl Drop for CommandTimer {
     fn drop(&mut self) {
         let duration = self.start.elapsed().as_secs_f64();
         crate::metrics::record_command(&self.command, duration);
+        crate::activity::global().command(&self.command);
     }
 }
 
@@ -0,0 +1,546 @@
+//! Test doubles for handler unit tests.
+//!
+//! Handlers need a full [`Matrix`], a session state and a [`Context`]. This
+//! module builds all three in memory so a handler can be exercised directly,
+//! without a listener, config file or on-disk database:
+//!
+//! - [`TestMatrixBuilder`] creates a Matrix from a minimal config, backed by
+//!   an in-memory SQLite database and the no-op history provider.
+//! - [`TestUserBuilder`] registers a local user and returns a [`TestUser`]
+//!   holding its [`RegisteredState`] and the receiver for messages routed
+//!   to it (channel broadcasts, notices from other handlers).
+//! - [`TestMatrix::context`] builds a [`Context`] whose replies are captured,
+//!   and [`TestMatrix::run`] dispatches one raw line through the registry.
+//!   [`TestMatrix::run_server`] does the same for a line from a peer link.
+//! - [`TestNetwork`] builds two such servers and links them over a loopback
+//!   TCP connection, for end-to-end tests of server linking.
+//!
+//! ```ignore
+//! let tm = TestMatrixBuilder::new().build().await;
+//! let mut alice = tm.user("alice").oper().build().await;
+//! let replies = tm.run(&mut alice, "MAINTENANCE").await;
+//! ```
+//!
+//! The crate is binary-only, so this is compiled for unit tests only;
+//! integration tests under `tests/` drive a real server instead.
+
+use crate::config::{Config, LinkBlock};
+use crate::db::Database;
+use crate::handlers::{Context, Registry, ResponseMiddleware, SaslState};
+use crate::state::{
+    Matrix, MatrixParams, RegisteredState, SendQ, SendQReceiver, ServerState, UnregisteredState,
+    User, UserParams,
+};
+use crate::sync::handshake::HandshakeState;
+use slirc_proto::sync::clock::ServerId;
+use slirc_proto::{Message, MessageRef};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, mpsc};
+
+/// Minimal configuration accepted by [`Config`] deserialization.
+const MINIMAL_CONFIG: &str = r#"
+[server]
+name = "test.server"
+network = "TestNet"
+sid = "00T"
+description = "Unit Test Server"
+
+[listen]
+address = "127.0.0.1:6667"
+
+[security]
+cloak_secret = "unit-test-cloak-secret"
+"#;
+
+/// Builder for an in-memory [`TestMatrix`].
+pub struct TestMatrixBuilder {
+    config: Config,
+    config_path: String,
+}
+
+impl TestMatrixBuilder {
+    /// Start from the minimal test configuration.
+    pub fn new() -> Self {
+        Self {
+            config: toml::from_str(MINIMAL_CONFIG).expect("minimal test config parses"),
+            config_path: String::new(),
+        }
+    }
+
+    /// Adjust the configuration before the Matrix is built.
+    pub fn config(mut self, f: impl FnOnce(&mut Config)) -> Self {
+        f(&mut self.config);
+        self
+    }
+
+    /// Set the config file REHASH reads.
+    pub fn config_path(mut self, path: impl Into<String>) -> Self {
+        self.config_path = path.into();
+        self
+    }
+
+    /// Create the database and Matrix.
+    pub async fn build(self) -> TestMatrix {
+        let db = Database::new(":memory:")
+            .await
+            .expect("in-memory database opens");
+        let (disconnect_tx, disconnect_rx) = mpsc::channel(64);
+        let data_dir = tempfile::tempdir().expect("temp data dir is created");
+        // The in-memory history backend needs no files, so tests can enable it
+        let history: Arc<dyn crate::history::HistoryProvider> =
+            if self.config.history.enabled && self.config.history.backend == "memory" {
+                Arc::new(crate::history::memory::MemoryProvider::new(
+                    self.config.history.memory_max_items,
+                ))
+            } else {
+                Arc::new(crate::history::noop::NoOpProvider)
+            };
+
+        let (matrix, router_rx) = Matrix::new(MatrixParams {
+            config: &self.config,
+            config_path: self.config_path,
+            data_dir: Some(data_dir.path()),
+            db: db.clone(),
+            history,
+            registered_channels: Vec::new(),
+            shuns: Vec::new(),
+            klines: Vec::new(),
+            dlines: Vec::new(),
+            glines: Vec::new(),
+            zlines: Vec::new(),
+            qlines: Vec::new(),
+            session_limits: Vec::new(),
+            disconnect_tx,
+            always_on_store: None,
+        });
+
+        TestMatrix {
+            matrix: Arc::new(matrix),
+            registry: Arc::new(Registry::new(self.config.webirc.clone())),
+            db,
+            remote_addr: "127.0.0.1:50000".parse().expect("valid socket address"),
+            router_rx: Some(router_rx),
+            _disconnect_rx: disconnect_rx,
+            _data_dir: data_dir,
+        }
+    }
+}
+
+/// An in-memory server with its handler registry.
+pub struct TestMatrix {
+    pub matrix: Arc<Matrix>,
+    pub registry: Arc<Registry>,
+    pub db: Database,
+    pub remote_addr: SocketAddr,
+    /// Kept alive so routing to remote users and disconnect requests do not
+    /// fail, until [`TestMatrix::spawn_router`] hands it to the router task.
+    router_rx: Option<SendQReceiver>,
+    _disconnect_rx: mpsc::Receiver<(String, String)>,
+    /// Holds files the Matrix persists (IP deny list) for the test's lifetime.
+    _data_dir: tempfile::TempDir,
+}
+
+impl TestMatrix {
+    /// Forward messages for remote users to peer links, as a running server does.
+    pub fn spawn_router(&mut self) {
+        if let Some(router_rx) = self.router_rx.take() {
+            crate::sync::router::spawn_router(Arc::clone(&self.matrix), router_rx);
+        }
+    }
+
+    /// Start building a local user with the given nick.
+    pub fn user<'a>(&'a self, nick: &str) -> TestUserBuilder<'a> {
+        TestUserBuilder {
+            tm: self,
+            nick: nick.to_string(),
+            caps: HashSet::new(),
+            account: None,
+            oper: false,
+        }
+    }
+
+    /// Build a handler context for `user` whose replies go to `replies`.
+    pub fn context<'a>(
+        &'a self,
+        user: &'a mut TestUser,
+        replies: &'a Mutex<Vec<Message>>,
+    ) -> Context<'a, RegisteredState> {
+        Context {
+            uid: &user.uid,
+            matrix: &self.matrix,
+            sender: ResponseMiddleware::Capturing(replies),
+            state: &mut user.state,
+            db: &self.db,
+            remote_addr: self.remote_addr,
+            label: None,
+            suppress_labeled_ack: false,
+            active_batch_id: None,
+            registry: &self.registry,
+        }
+    }
+
+    /// Dispatch one raw line from `user` and return the direct replies.
+    ///
+    /// Panics if the line does not parse or the handler returns an error.
+    pub async fn run(&self, user: &mut TestUser, line: &str) -> Vec<Message> {
+        let msg = MessageRef::parse(line).expect("test line parses");
+        let replies = Mutex::new(Vec::new());
+        {
+            let mut ctx = self.context(user, &replies);
+            self.registry
+                .dispatch_post_reg(&mut ctx, &msg)
+                .await
+                .expect("handler succeeds");
+        }
+        replies.into_inner()
+    }
+
+    /// Dispatch one raw line as if it arrived on the link to peer `sid`.
+    ///
+    /// Panics if the line does not parse or the handler returns an error.
+    pub async fn run_server(&self, sid: &str, line: &str) {
+        let msg = MessageRef::parse(line).expect("test line parses");
+        let mut state = ServerState {
+            name: format!("{}.test", sid.to_ascii_lowercase()),
+            sid: sid.to_string(),
+            info: String::new(),
+            hopcount: 1,
+            capabilities: HashSet::new(),
+            is_tls: false,
+            active_batch: None,
+            active_batch_ref: None,
+            batch_routing: None,
+            sasl_state: SaslState::default(),
+            sasl_buffer: String::new(),
+        };
+        let replies = Mutex::new(Vec::new());
+        let mut ctx = Context {
+            uid: sid,
+            matrix: &self.matrix,
+            sender: ResponseMiddleware::Capturing(&replies),
+            state: &mut state,
+            db: &self.db,
+            remote_addr: self.remote_addr,
+            label: None,
+            suppress_labeled_ack: false,
+            active_batch_id: None,
+            registry: &self.registry,
+        };
+        self.registry
+            .dispatch_server(&mut ctx, &msg)
+            .await
+            .expect("server handler succeeds");
+    }
+
+    /// Register a synced link to peer `sid` and return the queue of messages
+    /// sent to it.
+    pub async fn peer(&self, sid: &str) -> mpsc::Receiver<Arc<Message>> {
+        self.matrix
+            .sync_manager
+            .register_peer(
+                ServerId::new(sid),
+                format!("{}.test", sid.to_ascii_lowercase()),
+                1,
+                String::new(),
+            )
+            .await
+    }
+
+    /// Introduce remote user `nick` with `uid` as if burst by peer `sid`.
+    pub async fn remote_user(&self, sid: &str, uid: &str, nick: &str) {
+        let ts = chrono::Utc::now().timestamp();
+        let line = format!(":{sid} UID {nick} 1 {ts} {nick} {nick}.remote {uid} + :Remote {nick}");
+        self.run_server(sid, &line).await;
+    }
+}
+
+/// Builder for a registered local user.
+pub struct TestUserBuilder<'a> {
+    tm: &'a TestMatrix,
+    nick: String,
+    caps: HashSet<String>,
+    account: Option<String>,
+    oper: bool,
+}
+
+impl TestUserBuilder<'_> {
+    /// Enable an IRCv3 capability for the session.
+    pub fn cap(mut self, cap: &str) -> Self {
+        self.caps.insert(cap.to_string());
+        self
+    }
+
+    /// Mark the user as logged in to `account` (+r).
+    pub fn account(mut self, account: &str) -> Self {
+        self.account = Some(account.to_string());
+        self
+    }
+
+    /// Make the user an IRC operator (+o).
+    pub fn oper(mut self) -> Self {
+        self.oper = true;
+        self
+    }
+
+    /// Register the user in the Matrix and return its session.
+    pub async fn build(self) -> TestUser {
+        let matrix = &self.tm.matrix;
+        let uid = matrix.user_manager.uid_gen.next();
+        let session_id = uuid::Uuid::new_v4();
+
+        let state = UnregisteredState {
+            nick: Some(self.nick.clone()),
+            user: Some(self.nick.clone()),
+            realname: Some(format!("Test User {}", self.nick)),
+            capabilities: self.caps.clone(),
+            account: self.account.clone(),
+            session_id,
+            ..Default::default()
+        }
+        .try_register()
+        .expect("nick and user are set");
+
+        let mut user = User::new(UserParams {
+            uid: uid.clone(),
+            nick: self.nick.clone(),
+            user: self.nick.clone(),
+            realname: state.realname.clone(),
+            host: "127.0.0.1".to_string(),
+            ip: "127.0.0.1".to_string(),
+            cloak_secret: matrix.hot_config.read().cloak_secret.clone(),
+            cloak_suffix: matrix.hot_config.read().cloak_suffix.clone(),
+            caps: self.caps.clone(),
+            certfp: None,
+            last_modified: matrix.clock(),
+            session_id,
+        });
+        user.account = self.account;
+        user.modes.registered = user.account.is_some();
+        user.modes.oper = self.oper;
+
+        let (tx, rx) = SendQ::bounded(1024);
+        matrix.register_session_sender(&uid, session_id, tx, self.caps);
+        matrix.user_manager.add_local_user(user).await;
+
+        TestUser { uid, state, rx }
+    }
+}
+
+/// A registered local user and the messages routed to it.
+pub struct TestUser {
+    pub uid: String,
+    pub state: RegisteredState,
+    pub rx: SendQReceiver,
+}
+
+impl TestUser {
+    /// Messages routed to this user since the last call.
+    pub fn drain(&mut self) -> Vec<Message> {
+        let mut out = Vec::new();
+        while let Ok(msg) = self.rx.try_recv() {
+            out.push((*msg).clone());
+        }
+        out
+    }
+
+    /// Wait up to a second for a routed message matching `pred`, skipping
+    /// any others.
+    pub async fn recv_matching(&mut self, pred: impl Fn(&Message) -> bool) -> Option<Message> {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(1);
+        while let Ok(Some(msg)) = tokio::time::timeout_at(deadline, self.rx.recv()).await {
+            if pred(&msg) {
+                return Some((*msg).clone());
+            }
+        }
+        None
+    }
+}
+
+/// Password both sides of a [`TestNetwork`] link use.
+const LINK_PASSWORD: &str = "linkpass";
+
+/// Two in-process servers, `hub.test` (SID `001`) and `leaf.test`
+/// (SID `002`), each with its own in-memory database.
+///
+/// [`TestNetwork::link`] connects the hub to a plaintext S2S listener on the
+/// leaf over loopback TCP, so the real handshake, burst and link loops run.
+pub struct TestNetwork {
+    pub hub: TestMatrix,
+    pub leaf: TestMatrix,
+    leaf_s2s: SocketAddr,
+}
+
+impl TestNetwork {
+    /// Build both servers, configured to link to each other, without linking them.
+    pub async fn new() -> Self {
+        Self::build(false).await
+    }
+
+    /// Like [`TestNetwork::new`], with both ends offering link compression.
+    pub async fn compressed() -> Self {
+        Self::build(true).await
+    }
+
+    async fn build(compression: bool) -> Self {
+        let leaf_s2s = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|l| l.local_addr())
+            .expect("free loopback port");
+
+        let mut hub = TestMatrixBuilder::new()
+            .config(|c| {
+                c.server.name = "hub.test".to_string();
+                c.server.sid = "001".to_string();
+                c.links = vec![Self::link_block(
+                    "leaf.test",
+                    "002",
+                    leaf_s2s.port(),
+                    compression,
+                )];
+            })
+            .build()
+            .await;
+        let mut leaf = TestMatrixBuilder::new()
+            .config(|c| {
+                c.server.name = "leaf.test".to_string();
+                c.server.sid = "002".to_string();
+                c.links = vec![Self::link_block("hub.test", "001", 0, compression)];
+            })
+            .build()
+            .await;
+        hub.spawn_router();
+        leaf.spawn_router();
+
+        Self {
+            hub,
+            leaf,
+            leaf_s2s,
+        }
+    }
+
+    fn link_block(name: &str, sid: &str, port: u16, compression: bool) -> LinkBlock {
+        LinkBlock {
+            name: name.to_string(),
+            hostname: "127.0.0.1".to_string(),
+            port,
+            password: LINK_PASSWORD.to_string(),
+            tls: false,
+            verify_cert: false,
+            cert_fingerprint: None,
+            autoconnect: false,
+            sid: Some(sid.to_string()),
+            compression,
+        }
+    }
+
+    /// Link the hub to the leaf and wait until both have sent their burst.
+    ///
+    /// Panics if the link is not up within five seconds.
+    pub async fn link(&self) {
+        let leaf = &self.leaf;
+        leaf.matrix.sync_manager.start_inbound_listener(
+            Arc::clone(&leaf.matrix),
+            Arc::clone(&leaf.registry),
+            leaf.db.clone(),
+            None,
+            Some(self.leaf_s2s),
+        );
+        // The outbound side retries only every few seconds; connect once the
+        // listener is accepting.
+        assert!(
+            wait_until(|| std::net::TcpStream::connect(self.leaf_s2s).is_ok()).await,
+            "leaf S2S listener did not start"
+        );
+
+        let hub = &self.hub;
+        let link = hub.matrix.sync_manager.configured_links[0].clone();
+        hub.matrix.sync_manager.connect_to_peer(
+            Arc::clone(&hub.matrix),
+            Arc::clone(&hub.registry),
+            hub.db.clone(),
+            link,
+        );
+
+        let synced = |tm: &TestMatrix, sid: &str| {
+            tm.matrix
+                .sync_manager
+                .links
+                .get(&ServerId::new(sid))
+                .is_some_and(|l| l.state == HandshakeState::Synced)
+        };
+        assert!(
+            wait_until(|| synced(hub, "002") && synced(leaf, "001")).await,
+            "hub and leaf did not link"
+        );
+    }
+}
+
+/// Poll `cond` until it holds, for up to five seconds.
+pub async fn wait_until(cond: impl Fn() -> bool) -> bool {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while tokio::time::Instant::now() < deadline {
+        if cond() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    cond()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slirc_proto::Command;
+
+    #[tokio::test]
+    async fn builder_registers_user() {
+        let tm = TestMatrixBuilder::new().build().await;
+        let alice = tm
+            .user("alice")
+            .cap("message-tags")
+            .account("alice")
+            .build()
+            .await;
+
+        assert_eq!(alice.state.nick, "alice");
+        assert!(alice.state.has_cap("message-tags"));
+        assert_eq!(
+            tm.matrix.user_manager.get_first_uid("alice"),
+            Some(alice.uid.clone())
+        );
+
+        let user = tm
+            .matrix
+            .user_manager
+            .users
+            .get(&alice.uid)
+            .unwrap()
+            .clone();
+        let user = user.read().await;
+        assert!(user.modes.registered);
+        assert!(!user.modes.oper);
+    }
+
+    #[tokio::test]
+    async fn run_captures_replies_and_routed_messages() {
+        let tm = TestMatrixBuilder::new()
+            .config(|c| c.server.name = "unit.test".to_string())
+            .build()
+            .await;
+        let mut alice = tm.user("alice").build().await;
+        let mut bob = tm.user("bob").build().await;
+
+        let replies = tm.run(&mut alice, "PING :token").await;
+        assert!(matches!(&replies[0].command, Command::PONG(..)));
+
+        tm.run(&mut alice, "PRIVMSG bob :hello").await;
+        let routed = bob.drain();
+        assert!(
+            routed
+                .iter()
+                .any(|m| matches!(&m.command, Command::PRIVMSG(_, text) if text == "hello"))
+        );
+    }
+}
@@ -57,6 +57,7 @@ allow_plaintext_sasl_plain = true
 message_rate_per_second = 1000
 connection_burst_per_ip = 1000
 join_burst_per_client = 1000
+join_part_burst = 1000
 max_connections_per_ip = 200
 
 [motd]
@@ -149,6 +150,7 @@ allow_plaintext_sasl_plain = true
 message_rate_per_second = 1000
 connection_burst_per_ip = 1000
 join_burst_per_client = 1000
+join_part_burst = 1000
 max_connections_per_ip = 200
 
 [motd]
@@ -195,3 +195,37 @@ async fn test_multiple_concurrent_connections() {
         handle.await.expect("Client task panicked");
     }
 }
+
+#[tokio::test]
+async fn test_cap_end_during_sasl_aborts_and_registers() -> anyhow::Result<()> {
+    let server = TestServer::spawn(16785).await?;
+    let mut client = TestClient::connect(&server.address(), "saslabort").await?;
+
+    client.send_raw("CAP LS 302").await?;
+    client.send_raw("CAP REQ :sasl").await?;
+    client.recv_until(|m| m.to_string().contains("ACK")).await?;
+    client.send_raw("NICK saslabort").await?;
+    client.send_raw("USER saslabort 0 * :SASL Abort").await?;
+    client.send_raw("AUTHENTICATE PLAIN").await?;
+    client
+        .recv_until(|m| matches!(&m.command, Command::AUTHENTICATE(_)))
+        .await?;
+
+    // STARTTLS cannot swap the transport under the exchange
+    client.send_raw("STARTTLS").await?;
+    client
+        .recv_until(|m| matches!(&m.command, Command::Response(resp, _) if resp.code() == 451))
+        .await?;
+
+    // Finishing registration mid-exchange aborts it and registers without an account
+    client.send_raw("CAP END").await?;
+    let seen = client
+        .recv_until(|m| matches!(&m.command, Command::Response(resp, _) if resp.code() == 1))
+        .await?;
+    assert!(
+        seen.iter()
+            .any(|m| { matches!(&m.command, Command::Response(resp, _) if resp.code() == 906) })
+    );
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//! Integration test for COMPRESS on compression listeners.
+
+use anyhow::{Result, bail};
+use slirc_proto::{Message, ZeroCopyTransport};
+use std::fs;
+use tokio::net::TcpStream;
+use tokio::time::{Duration, timeout};
+
+mod common;
+
+const CONFIG: &str = r#"
+[server]
+name = "test.example.com"
+network = "TestNet"
+sid = "001"
+description = "Compression Test"
+metrics_port = 0
+
+[[listen]]
+address = "127.0.0.1:16766"
+
+[[listen]]
+address = "127.0.0.1:16767"
+compression = true
+
+[database]
+path = "{dir}/test.db"
+
+[history]
+enabled = false
+
+[security]
+cloak_secret = "e14d9fd27d9e2ae742fd32b46adceb630f0d517579d14651c15266859d70892a"
+spam_detection_enabled = false
+
+[security.rate_limits]
+connection_burst_per_ip = 1000
+"#;
+
+type Client = ZeroCopyTransport<TcpStream>;
+
+async fn send(client: &mut Client, line: &str) -> Result<()> {
+    client.write_message(&line.parse::<Message>()?).await?;
+    Ok(())
+}
+
+/// Read lines until one contains `needle`, returning every line read.
+async fn read_until(client: &mut Client, needle: &str) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    loop {
+        let line = match timeout(Duration::from_secs(5), client.next()).await? {
+            Some(msg) => msg?.to_string(),
+            None => bail!("connection closed before {needle:?}; got {lines:?}"),
+        };
+        let done = line.contains(needle);
+        lines.push(line);
+        if done {
+            return Ok(lines);
+        }
+    }
+}
+
+/// Only compression listeners advertise and accept COMPRESS; after it both
+/// directions are zstd.
+#[tokio::test]
+async fn test_compress() -> Result<()> {
+    let test_dir = std::env::temp_dir().join(format!("compress-{}", std::process::id()));
+    fs::create_dir_all(&test_dir)?;
+    let config_path = test_dir.join("config.toml");
+    fs::write(
+        &config_path,
+        CONFIG.replace("{dir}", &test_dir.display().to_string()),
+    )?;
+    let server = common::TestServer::spawn_with_config(16766, config_path).await?;
+
+    let mut plain = ZeroCopyTransport::new(TcpStream::connect("127.0.0.1:16766").await?);
+    send(&mut plain, "CAP LS 302").await?;
+    send(&mut plain, "PING :caps-done").await?;
+    let lines = read_until(&mut plain, "caps-done").await?;
+    assert!(!lines.iter().any(|l| l.contains("sid3.xyz/compress")));
+    send(&mut plain, "COMPRESS ZSTD").await?;
+    read_until(&mut plain, "FAIL COMPRESS NOT_AVAILABLE").await?;
+
+    let mut client = ZeroCopyTransport::new(TcpStream::connect("127.0.0.1:16767").await?);
+    send(&mut client, "CAP LS 302").await?;
+    send(&mut client, "PING :caps-done").await?;
+    let lines = read_until(&mut client, "caps-done").await?;
+    assert!(lines.iter().any(|l| l.contains("sid3.xyz/compress=zstd")));
+
+    send(&mut client, "COMPRESS LZ4").await?;
+    read_until(&mut client, "FAIL COMPRESS UNKNOWN_ALGORITHM").await?;
+    send(&mut client, "COMPRESS ZSTD").await?;
+    read_until(&mut client, "NOTE COMPRESS ACTIVE ZSTD").await?;
+    client.enable_zstd()?;
+
+    send(&mut client, "CAP END").await?;
+    send(&mut client, "NICK squeezed").await?;
+    send(&mut client, "USER squeezed 0 * :Compressed").await?;
+    read_until(&mut client, " 001 squeezed ").await?;
+    send(&mut client, "COMPRESS ZSTD").await?;
+    send(&mut client, "PING :still-compressed").await?;
+    read_until(&mut client, "still-compressed").await?;
+
+    drop(server);
+    Ok(())
+}
@@ -0,0 +1,202 @@
+//! Integration test for `[[listen]]` blocks - several listeners with their
+//! own policies, opened and closed by REHASH.
+
+use anyhow::Result;
+use slirc_proto::Command;
+use std::fs;
+use tokio::net::TcpStream;
+use tokio::time::{Duration, sleep};
+
+mod common;
+use common::TestClient;
+
+fn config(data_dir: &std::path::Path, listeners: &str) -> String {
+    format!(
+        r#"
+[server]
+name = "test.example.com"
+network = "TestNet"
+sid = "001"
+description = "Listener Test"
+metrics_port = 0
+
+{listeners}
+
+[database]
+path = "{}/test.db"
+
+[[oper]]
+name = "admin"
+password = "testpass"
+
+[history]
+enabled = false
+
+[[link]]
+name = "peer.example.com"
+hostname = "127.0.0.1"
+port = 7000
+password = "blockpass"
+
+[security]
+cloak_secret = "e14d9fd27d9e2ae742fd32b46adceb630f0d517579d14651c15266859d70892a"
+spam_detection_enabled = false
+
+[security.rate_limits]
+connection_burst_per_ip = 1000
+"#,
+        data_dir.display()
+    )
+}
+
+/// Clients register on every client listener, are refused on server-only
+/// ones where servers link with the bind password, and REHASH opens new
+/// listeners and closes removed ones.
+#[tokio::test]
+async fn test_listen_blocks_and_rehash() -> Result<()> {
+    let test_dir = std::env::temp_dir().join(format!("listen_blocks-{}", std::process::id()));
+    fs::create_dir_all(&test_dir)?;
+    let config_path = test_dir.join("config.toml");
+
+    let initial = r#"
+[[listen]]
+address = "127.0.0.1:16750"
+
+[[listen]]
+address = "127.0.0.1:16751"
+
+[[listen]]
+address = "127.0.0.1:16752"
+server_only = true
+password = "linkpass"
+"#;
+    fs::write(&config_path, config(&test_dir, initial))?;
+    let server = common::TestServer::spawn_with_config(16750, config_path.clone()).await?;
+
+    let mut admin = TestClient::connect("127.0.0.1:16750", "admin").await?;
+    admin.register().await?;
+    let mut second = TestClient::connect("127.0.0.1:16751", "second").await?;
+    second.register().await?;
+
+    // Server ports turn clients away at registration
+    let mut refused = TestClient::connect("127.0.0.1:16752", "refused").await?;
+    refused.send_raw("NICK refused").await?;
+    refused.send_raw("USER refused 0 * :Refused").await?;
+    refused
+        .recv_until(|m| matches!(&m.command, Command::ERROR(text) if text.contains("servers only")))
+        .await?;
+
+    // Servers linking there use the bind password instead of the link block's
+    let mut peer = TestClient::connect("127.0.0.1:16752", "peer").await?;
+    peer.send_raw("PASS linkpass TS 6 :002").await?;
+    peer.send_raw("SERVER peer.example.com 1 002 :Peer").await?;
+    peer.recv_until(|m| m.to_string().starts_with("PASS linkpass"))
+        .await?;
+
+    // Swap the second client port for a new one
+    let modified = r#"
+[[listen]]
+address = "127.0.0.1:16750"
+
+[[listen]]
+address = "127.0.0.1:16753"
+
+[[listen]]
+address = "127.0.0.1:16752"
+server_only = true
+password = "linkpass"
+"#;
+    fs::write(&config_path, config(&test_dir, modified))?;
+
+    admin.send_raw("OPER admin testpass").await?;
+    admin
+        .recv_until(|m| m.to_string().contains(" 381 "))
+        .await?;
+    admin.send_raw("REHASH").await?;
+    admin
+        .recv_until(|m| {
+            m.to_string()
+                .contains("REHASH listeners: ok (1 bound, 0 changed, 1 closed)")
+        })
+        .await?;
+    sleep(Duration::from_millis(100)).await;
+
+    let mut third = TestClient::connect("127.0.0.1:16753", "third").await?;
+    third.register().await?;
+    assert!(
+        TcpStream::connect("127.0.0.1:16751").await.is_err(),
+        "removed listener should be closed"
+    );
+
+    // Connections accepted on a closed listener stay up
+    second.send_raw("PING :still-here").await?;
+    second
+        .recv_until(|m| matches!(&m.command, Command::PONG(..)))
+        .await?;
+
+    drop(server);
+    Ok(())
+}
+
+/// PROXY headers from trusted sources replace the client address; other
+/// sources are refused.
+#[tokio::test]
+async fn test_proxy_protocol_trusted_sources() -> Result<()> {
+    let test_dir = std::env::temp_dir().join(format!("listen_proxy-{}", std::process::id()));
+    fs::create_dir_all(&test_dir)?;
+    let config_path = test_dir.join("config.toml");
+
+    let listeners = r#"
+[[listen]]
+address = "127.0.0.1:16754"
+
+[[listen]]
+address = "127.0.0.1:16755"
+proxy_protocol = true
+proxy_trusted = ["127.0.0.0/8"]
+
+[[listen]]
+address = "127.0.0.1:16756"
+proxy_protocol = true
+proxy_trusted = ["10.0.0.0/8"]
+"#;
+    fs::write(&config_path, config(&test_dir, listeners))?;
+    let server = common::TestServer::spawn_with_config(16754, config_path).await?;
+
+    let mut admin = TestClient::connect("127.0.0.1:16754", "admin").await?;
+    admin.register().await?;
+    admin.send_raw("OPER admin testpass").await?;
+    admin
+        .recv_until(|m| m.to_string().contains(" 381 "))
+        .await?;
+
+    let mut proxied = TestClient::connect("127.0.0.1:16755", "proxied").await?;
+    proxied
+        .send_raw("PROXY TCP4 198.51.100.7 127.0.0.1 40000 16755")
+        .await?;
+    proxied.register().await?;
+
+    admin.send_raw("WHOIS proxied").await?;
+    let host_line = admin
+        .recv_until(|m| m.to_string().contains(" 378 "))
+        .await?;
+    assert!(
+        host_line
+            .iter()
+            .any(|m| m.to_string().contains("198.51.100.7")),
+        "WHOIS should show the address from the PROXY header"
+    );
+
+    // Loopback is not a trusted proxy on this port
+    let mut spoofed = TestClient::connect("127.0.0.1:16756", "spoofed").await?;
+    spoofed
+        .send_raw("PROXY TCP4 198.51.100.8 127.0.0.1 40000 16756")
+        .await?;
+    assert!(
+        spoofed.register().await.is_err(),
+        "untrusted proxy source should be refused"
+    );
+
+    drop(server);
+    Ok(())
+}
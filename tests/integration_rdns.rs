@@ -0,0 +1,96 @@
+//! Integration test for reverse DNS of connecting clients.
+//!
+//! Relies on the hosts file mapping 127.0.0.1 to a name, as it does on
+//! every stock system.
+
+use anyhow::Result;
+use std::fs;
+
+mod common;
+use common::TestClient;
+
+const CONFIG: &str = r#"
+[server]
+name = "test.example.com"
+network = "TestNet"
+sid = "001"
+description = "rDNS Test"
+metrics_port = 0
+
+[server.rdns]
+enabled = true
+timeout_ms = 2000
+
+[listen]
+address = "127.0.0.1:16761"
+
+[database]
+path = "{dir}/test.db"
+
+[history]
+enabled = false
+
+[[webirc]]
+password = "webpass"
+hosts = ["127.0.0.1"]
+
+[security]
+cloak_secret = "e14d9fd27d9e2ae742fd32b46adceb630f0d517579d14651c15266859d70892a"
+spam_detection_enabled = false
+
+[security.rate_limits]
+connection_burst_per_ip = 1000
+"#;
+
+/// The `*@host ip` part of the user's own RPL_WHOISHOST.
+async fn own_host(client: &mut TestClient, nick: &str) -> Result<String> {
+    client.send_raw(&format!("WHOIS {nick}")).await?;
+    let replies = client
+        .recv_until(|m| m.to_string().contains(" 378 "))
+        .await?;
+    let line = replies.last().unwrap().to_string();
+    let host = line.split("*@").nth(1).unwrap_or_default().trim();
+    Ok(host.to_string())
+}
+
+/// Clients get their forward-confirmed hostname, directly and when a
+/// gateway forwards only an IP; a WEBIRC hostname is used as given.
+#[tokio::test]
+async fn test_rdns_hostnames() -> Result<()> {
+    let test_dir = std::env::temp_dir().join(format!("rdns-{}", std::process::id()));
+    fs::create_dir_all(&test_dir)?;
+    let config_path = test_dir.join("config.toml");
+    fs::write(
+        &config_path,
+        CONFIG.replace("{dir}", &test_dir.display().to_string()),
+    )?;
+    let server = common::TestServer::spawn_with_config(16761, config_path).await?;
+
+    let mut direct = TestClient::connect("127.0.0.1:16761", "direct").await?;
+    direct.register().await?;
+    let host = own_host(&mut direct, "direct").await?;
+    assert!(
+        host.ends_with(" 127.0.0.1") && !host.starts_with("127.0.0.1"),
+        "expected a resolved hostname, got {host}"
+    );
+
+    let mut forwarded = TestClient::connect("127.0.0.1:16761", "forwarded").await?;
+    forwarded
+        .send_raw("WEBIRC webpass gw 127.0.0.1 127.0.0.1")
+        .await?;
+    forwarded.register().await?;
+    assert_eq!(own_host(&mut forwarded, "forwarded").await?, host);
+
+    let mut named = TestClient::connect("127.0.0.1:16761", "named").await?;
+    named
+        .send_raw("WEBIRC webpass gw client.example.net 127.0.0.1")
+        .await?;
+    named.register().await?;
+    assert_eq!(
+        own_host(&mut named, "named").await?,
+        "client.example.net 127.0.0.1"
+    );
+
+    drop(server);
+    Ok(())
+}
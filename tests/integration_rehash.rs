@@ -155,7 +155,8 @@ lines = ["Welcome to the NEW MOTD", "This is AFTER REHASH - Hot reload works!"]
 
     // Read REHASH response and wait for completion
     let mut rehash_acknowledged = false;
-    for _ in 0..10 {
+    // One notice per config section precedes the summary
+    for _ in 0..20 {
         if let Ok(msg) = tokio::time::timeout(Duration::from_secs(1), admin.recv()).await
             && let Ok(response) = msg
         {
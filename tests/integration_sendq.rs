@@ -0,0 +1,116 @@
+//! Integration test for connection class send queues.
+
+use anyhow::Result;
+use slirc_proto::{Command, Message, Response};
+use std::fs;
+
+mod common;
+use common::TestClient;
+
+const CONFIG: &str = r#"
+[server]
+name = "test.example.com"
+network = "TestNet"
+sid = "001"
+description = "SendQ Test"
+metrics_port = 0
+
+[[listen]]
+address = "127.0.0.1:16768"
+
+[[listen]]
+address = "127.0.0.1:16769"
+class = "tiny"
+
+[[listen]]
+address = "127.0.0.1:16770"
+class = "patient"
+
+[[class]]
+name = "tiny"
+sendq = 1024
+
+[[class]]
+name = "patient"
+sendq = 1024
+sendq_policy = "block"
+
+[motd]
+lines = [{motd}]
+
+[database]
+path = "{dir}/test.db"
+
+[history]
+enabled = false
+
+[security]
+cloak_secret = "e14d9fd27d9e2ae742fd32b46adceb630f0d517579d14651c15266859d70892a"
+spam_detection_enabled = false
+
+[security.rate_limits]
+connection_burst_per_ip = 1000
+"#;
+
+fn is_end_of_motd(msg: &Message) -> bool {
+    matches!(&msg.command, Command::Response(Response::RPL_ENDOFMOTD, _))
+}
+
+/// A MOTD of about 1.5KB: past a 1KB sendq, but not twice past it.
+fn motd() -> String {
+    (0..16)
+        .map(|i| format!("\"line {i:02} of a message of the day long enough to matter\""))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Replies bigger than the class sendq disconnect under the default policy,
+/// and are held back rather than refused under `block`.
+#[tokio::test]
+async fn test_sendq_classes() -> Result<()> {
+    let test_dir = std::env::temp_dir().join(format!("sendq-{}", std::process::id()));
+    fs::create_dir_all(&test_dir)?;
+    let config_path = test_dir.join("config.toml");
+    fs::write(
+        &config_path,
+        CONFIG
+            .replace("{dir}", &test_dir.display().to_string())
+            .replace("{motd}", &motd()),
+    )?;
+    let server = common::TestServer::spawn_with_config(16768, config_path).await?;
+
+    // The built-in default class has room for the whole MOTD
+    let mut roomy = TestClient::connect("127.0.0.1:16768", "roomy").await?;
+    roomy.register().await?;
+    roomy.recv_until(is_end_of_motd).await?;
+    roomy.send_raw("MOTD").await?;
+    roomy.recv_until(is_end_of_motd).await?;
+
+    let mut tiny = TestClient::connect("127.0.0.1:16769", "tiny").await?;
+    tiny.register().await?;
+    tiny.recv_until(is_end_of_motd).await?;
+    tiny.send_raw("MOTD").await?;
+    tiny.recv_until(
+        |m| matches!(&m.command, Command::ERROR(text) if text.contains("SendQ exceeded")),
+    )
+    .await?;
+
+    let mut patient = TestClient::connect("127.0.0.1:16770", "patient").await?;
+    patient.register().await?;
+    patient.recv_until(is_end_of_motd).await?;
+    patient.send_raw("MOTD").await?;
+    patient.recv_until(is_end_of_motd).await?;
+    patient.send_raw("PING :still-here").await?;
+    patient
+        .recv_until(|m| matches!(&m.command, Command::PONG(..)))
+        .await?;
+
+    // The disconnected client is gone for everyone else too
+    roomy.send_raw("WHOIS tiny").await?;
+    roomy
+        .recv_until(|m| matches!(&m.command, Command::Response(Response::ERR_NOSUCHNICK, _)))
+        .await?;
+
+    drop(server);
+    Ok(())
+}
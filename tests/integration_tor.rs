@@ -0,0 +1,104 @@
+//! Integration test for Tor listeners.
+
+use anyhow::Result;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use slirc_proto::Command;
+use std::fs;
+
+mod common;
+use common::TestClient;
+
+const CONFIG: &str = r#"
+[server]
+name = "test.example.com"
+network = "TestNet"
+sid = "001"
+description = "Tor Test"
+metrics_port = 0
+
+[[listen]]
+address = "127.0.0.1:16762"
+
+[[listen]]
+address = "127.0.0.1:16763"
+tor = true
+
+[tor]
+host = "onion.test"
+
+[database]
+path = "{dir}/test.db"
+
+[history]
+enabled = false
+
+[[webirc]]
+password = "webpass"
+hosts = ["127.0.0.1"]
+
+[security]
+cloak_secret = "e14d9fd27d9e2ae742fd32b46adceb630f0d517579d14651c15266859d70892a"
+spam_detection_enabled = false
+
+[security.rate_limits]
+connection_burst_per_ip = 1000
+"#;
+
+/// Tor clients must log in with SASL, all share the configured host, and
+/// cannot swap it out with WEBIRC.
+#[tokio::test]
+async fn test_tor_listener() -> Result<()> {
+    let test_dir = std::env::temp_dir().join(format!("tor-{}", std::process::id()));
+    fs::create_dir_all(&test_dir)?;
+    let config_path = test_dir.join("config.toml");
+    fs::write(
+        &config_path,
+        CONFIG.replace("{dir}", &test_dir.display().to_string()),
+    )?;
+    let server = common::TestServer::spawn_with_config(16762, config_path).await?;
+
+    let mut owner = TestClient::connect("127.0.0.1:16762", "torowner").await?;
+    owner.register().await?;
+    owner
+        .send_raw("PRIVMSG NickServ :REGISTER torpass owner@example.com")
+        .await?;
+    owner
+        .recv_until(|m| m.to_string().contains("has been registered"))
+        .await?;
+
+    // No SASL, no entry
+    let mut anon = TestClient::connect("127.0.0.1:16763", "anon").await?;
+    anon.send_raw("NICK anon").await?;
+    anon.send_raw("USER anon 0 * :Anon").await?;
+    anon.recv_until(|m| matches!(&m.command, Command::ERROR(text) if text.contains("SASL")))
+        .await?;
+
+    let mut tor = TestClient::connect("127.0.0.1:16763", "toruser").await?;
+    tor.send_raw("CAP LS 302").await?;
+    tor.send_raw("CAP REQ :sasl").await?;
+    tor.recv_until(|m| m.to_string().contains("ACK")).await?;
+    tor.send_raw("WEBIRC webpass gw spoofed.example.net 198.51.100.30")
+        .await?;
+    tor.send_raw("NICK toruser").await?;
+    tor.send_raw("USER toruser 0 * :Tor User").await?;
+    tor.send_raw("AUTHENTICATE PLAIN").await?;
+    tor.recv_until(|m| matches!(&m.command, Command::AUTHENTICATE(_)))
+        .await?;
+    let credentials = STANDARD.encode("torowner\0torowner\0torpass");
+    tor.send_raw(&format!("AUTHENTICATE {credentials}")).await?;
+    tor.recv_until(|m| m.to_string().contains(" 903 ")).await?;
+    tor.send_raw("CAP END").await?;
+    tor.recv_until(|m| matches!(&m.command, Command::Response(resp, _) if resp.code() == 1))
+        .await?;
+
+    owner.send_raw("WHOIS toruser").await?;
+    let whois = owner
+        .recv_until(|m| m.to_string().contains(" 311 "))
+        .await?;
+    let line = whois.last().unwrap().to_string();
+    assert!(line.contains(" onion.test "), "unexpected host: {line}");
+
+    drop(server);
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//! Integration test for WEBIRC gateway blocks.
+
+use anyhow::Result;
+use slirc_proto::Command;
+use std::fs;
+
+mod common;
+use common::TestClient;
+
+const CONFIG: &str = r#"
+[server]
+name = "test.example.com"
+network = "TestNet"
+sid = "001"
+description = "WEBIRC Test"
+metrics_port = 0
+
+[listen]
+address = "127.0.0.1:16760"
+
+[database]
+path = "{dir}/test.db"
+
+[[oper]]
+name = "admin"
+password = "testpass"
+
+[history]
+enabled = false
+
+[[webirc]]
+name = "kiwiirc"
+password = "kiwipass"
+hosts = ["127.0.0.0/8"]
+
+[[webirc]]
+password = "remotepass"
+hosts = ["10.0.0.0/8"]
+
+[security]
+cloak_secret = "e14d9fd27d9e2ae742fd32b46adceb630f0d517579d14651c15266859d70892a"
+spam_detection_enabled = false
+
+[security.rate_limits]
+connection_burst_per_ip = 1000
+"#;
+
+/// Gateways are matched by password, name and address; the forwarded
+/// hostname, IP and `secure` flag reach the user, and opers see the gateway.
+#[tokio::test]
+async fn test_webirc_gateway_blocks() -> Result<()> {
+    let test_dir = std::env::temp_dir().join(format!("webirc-{}", std::process::id()));
+    fs::create_dir_all(&test_dir)?;
+    let config_path = test_dir.join("config.toml");
+    fs::write(
+        &config_path,
+        CONFIG.replace("{dir}", &test_dir.display().to_string()),
+    )?;
+    let server = common::TestServer::spawn_with_config(16760, config_path).await?;
+
+    let mut admin = TestClient::connect("127.0.0.1:16760", "admin").await?;
+    admin.register().await?;
+    admin.send_raw("OPER admin testpass").await?;
+    admin
+        .recv_until(|m| m.to_string().contains(" 381 "))
+        .await?;
+
+    let mut web = TestClient::connect("127.0.0.1:16760", "web").await?;
+    web.send_raw("WEBIRC kiwipass kiwiirc user.example.net 198.51.100.20 :secure")
+        .await?;
+    web.register().await?;
+    web.send_raw("MODE web").await?;
+    let modes = web.recv_until(|m| m.to_string().contains(" 221 ")).await?;
+    assert!(
+        modes.last().unwrap().to_string().contains('Z'),
+        "secure WEBIRC client should get +Z"
+    );
+
+    admin.send_raw("WHOIS web").await?;
+    let whois = admin
+        .recv_until(|m| m.to_string().contains(" 318 "))
+        .await?;
+    let whois: Vec<String> = whois.iter().map(ToString::to_string).collect();
+    assert!(
+        whois
+            .iter()
+            .any(|l| l.contains(" 378 ") && l.contains("*@user.example.net 198.51.100.20"))
+    );
+    assert!(
+        whois
+            .iter()
+            .any(|l| l.contains(" 350 ") && l.contains("kiwiirc WebIRC gateway"))
+    );
+
+    // The right password from outside the block's hosts is refused
+    let mut outsider = TestClient::connect("127.0.0.1:16760", "outsider").await?;
+    outsider
+        .send_raw("WEBIRC remotepass cgiirc host.example.org 198.51.100.21")
+        .await?;
+    outsider
+        .recv_until(|m| matches!(&m.command, Command::ERROR(text) if text.contains("WEBIRC")))
+        .await?;
+
+    drop(server);
+    Ok(())
+}
@@ -0,0 +1,120 @@
+//! Integration test for IRCv3 WebSocket subprotocols.
+
+use anyhow::{Result, bail};
+use futures_util::{SinkExt, StreamExt};
+use std::fs;
+use tokio::net::TcpStream;
+use tokio::time::{Duration, timeout};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async};
+
+mod common;
+
+const CONFIG: &str = r#"
+[server]
+name = "test.example.com"
+network = "TestNet"
+sid = "001"
+description = "WebSocket Test"
+metrics_port = 0
+
+[[listen]]
+address = "127.0.0.1:16764"
+
+[[listen]]
+address = "127.0.0.1:16765"
+websocket = true
+
+[websocket]
+allow_origins = ["*"]
+
+[database]
+path = "{dir}/test.db"
+
+[history]
+enabled = false
+
+[security]
+cloak_secret = "e14d9fd27d9e2ae742fd32b46adceb630f0d517579d14651c15266859d70892a"
+spam_detection_enabled = false
+
+[security.rate_limits]
+connection_burst_per_ip = 1000
+"#;
+
+type Ws = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Open a WebSocket offering `protocols`; returns the one the server picked.
+async fn connect(protocols: &str) -> Result<(Ws, Option<String>)> {
+    let mut request = "ws://127.0.0.1:16765/".into_client_request()?;
+    request
+        .headers_mut()
+        .insert("Sec-WebSocket-Protocol", protocols.parse()?);
+    let (ws, response) = connect_async(request).await?;
+    let chosen = response
+        .headers()
+        .get("Sec-WebSocket-Protocol")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    Ok((ws, chosen))
+}
+
+/// Read frames until one carries `needle`; fails on a frame of the wrong kind.
+async fn expect_line(ws: &mut Ws, binary: bool, needle: &str) -> Result<String> {
+    loop {
+        let frame = timeout(Duration::from_secs(5), ws.next()).await?;
+        let line = match frame {
+            Some(Ok(WsMessage::Binary(data))) if binary => String::from_utf8(data)?,
+            Some(Ok(WsMessage::Text(text))) if !binary => text,
+            Some(Ok(WsMessage::Ping(_) | WsMessage::Pong(_))) => continue,
+            other => bail!("unexpected frame: {other:?}"),
+        };
+        if line.contains(needle) {
+            return Ok(line);
+        }
+    }
+}
+
+/// Binary clients get binary frames and a FAIL for invalid UTF-8; text
+/// clients keep text frames.
+#[tokio::test]
+async fn test_websocket_subprotocols() -> Result<()> {
+    let test_dir = std::env::temp_dir().join(format!("websocket-{}", std::process::id()));
+    fs::create_dir_all(&test_dir)?;
+    let config_path = test_dir.join("config.toml");
+    fs::write(
+        &config_path,
+        CONFIG.replace("{dir}", &test_dir.display().to_string()),
+    )?;
+    let server = common::TestServer::spawn_with_config(16764, config_path).await?;
+
+    let (mut binary, chosen) = connect("binary.ircv3.net, text.ircv3.net").await?;
+    assert_eq!(chosen.as_deref(), Some("binary.ircv3.net"));
+    binary
+        .send(WsMessage::Binary(b"NICK wsbin\r\n".to_vec()))
+        .await?;
+    binary
+        .send(WsMessage::Binary(b"USER wsbin 0 * :Binary".to_vec()))
+        .await?;
+    expect_line(&mut binary, true, " 001 ").await?;
+
+    binary
+        .send(WsMessage::Binary(b"PRIVMSG wsbin :caf\xe9".to_vec()))
+        .await?;
+    expect_line(&mut binary, true, "INVALID_UTF8").await?;
+    binary
+        .send(WsMessage::Text("PING :still-here".into()))
+        .await?;
+    expect_line(&mut binary, true, "still-here").await?;
+
+    let (mut text, chosen) = connect("text.ircv3.net").await?;
+    assert_eq!(chosen.as_deref(), Some("text.ircv3.net"));
+    text.send(WsMessage::Text("NICK wstext".into())).await?;
+    text.send(WsMessage::Text("USER wstext 0 * :Text".into()))
+        .await?;
+    expect_line(&mut text, false, " 001 ").await?;
+
+    drop(server);
+    Ok(())
+}
@@ -0,0 +1,176 @@
+//! Labeled-response coverage: every labeled command gets exactly one
+//! response carrying its label, and commands with no output get an ACK.
+
+mod common;
+
+use common::{TestClient, TestServer};
+use slirc_proto::{Command, Message};
+use tokio::time::Duration;
+
+async fn drain(client: &mut TestClient) {
+    tokio::time::sleep(Duration::from_millis(120)).await;
+    while client.recv_timeout(Duration::from_millis(10)).await.is_ok() {}
+}
+
+/// Negotiate `caps` before registering.
+async fn register_with_caps(client: &mut TestClient, nick: &str, caps: &str) {
+    client.send_raw(&format!("CAP REQ :{caps}")).await.unwrap();
+    client
+        .recv_until(|m| matches!(&m.command, Command::CAP(..)))
+        .await
+        .expect("CAP ACK");
+    client.send_raw("CAP END").await.unwrap();
+    client.send_raw(&format!("NICK {nick}")).await.unwrap();
+    client
+        .send_raw(&format!("USER {nick} 0 * :{nick}"))
+        .await
+        .unwrap();
+    client
+        .recv_until(|m| matches!(&m.command, Command::Response(r, _) if r.code() == 1))
+        .await
+        .expect("welcome");
+}
+
+fn label_of(msg: &Message) -> Option<&str> {
+    msg.tag_value("label")
+}
+
+async fn recv_labeled(client: &mut TestClient) -> Message {
+    client
+        .recv_timeout(Duration::from_secs(2))
+        .await
+        .expect("labeled response")
+}
+
+/// Commands that produce no output must be answered with an empty ACK.
+#[tokio::test]
+async fn test_silent_commands_get_ack() {
+    let port = 16740;
+    let server = TestServer::spawn(port).await.expect("spawn");
+
+    let mut alice = TestClient::connect(&server.address(), "alice")
+        .await
+        .expect("connect");
+    register_with_caps(&mut alice, "alice", "labeled-response batch message-tags").await;
+    // bob has no message-tags, so never sees TAGMSG
+    let mut bob = TestClient::connect(&server.address(), "bob")
+        .await
+        .expect("connect");
+    bob.register().await.expect("register");
+    drain(&mut alice).await;
+    drain(&mut bob).await;
+
+    let silent = [
+        ("s1", "SILENCE +spam!*@*"),
+        ("t1", "TAGMSG bob"),
+        ("p1", "PONG :nothing"),
+    ];
+    for (label, command) in silent {
+        let tags = if command.starts_with("TAGMSG") {
+            format!("label={label};+typing=active")
+        } else {
+            format!("label={label}")
+        };
+        alice.send_raw(&format!("@{tags} {command}")).await.unwrap();
+        let reply = recv_labeled(&mut alice).await;
+        assert_eq!(reply.command, Command::ACK, "{command}: {reply}");
+        assert_eq!(label_of(&reply), Some(label), "{command}: {reply}");
+    }
+
+    assert!(
+        bob.recv_timeout(Duration::from_millis(200)).await.is_err(),
+        "TAGMSG reached a client without message-tags"
+    );
+}
+
+/// Errors, including those raised by the dispatcher, are the labeled
+/// response themselves and are not followed by an ACK.
+#[tokio::test]
+async fn test_errors_carry_the_label() {
+    let port = 16741;
+    let server = TestServer::spawn(port).await.expect("spawn");
+
+    let mut alice = TestClient::connect(&server.address(), "alice")
+        .await
+        .expect("connect");
+    register_with_caps(&mut alice, "alice", "labeled-response batch").await;
+    drain(&mut alice).await;
+
+    for (label, command, numeric) in [
+        ("e1", "PRIVMSG nobody :hi", 401),
+        ("e2", "JOIN", 461),
+        ("e3", "NOSUCHCOMMAND", 421),
+    ] {
+        alice
+            .send_raw(&format!("@label={label} {command}"))
+            .await
+            .unwrap();
+        let reply = recv_labeled(&mut alice).await;
+        assert!(
+            matches!(&reply.command, Command::Response(r, _) if r.code() == numeric),
+            "{command}: {reply}"
+        );
+        assert_eq!(label_of(&reply), Some(label), "{command}: {reply}");
+    }
+
+    // Nothing else was queued behind the errors
+    alice.send_raw("@label=ping PING :check").await.unwrap();
+    let reply = recv_labeled(&mut alice).await;
+    assert!(matches!(reply.command, Command::PONG(..)), "{reply}");
+    assert_eq!(label_of(&reply), Some("ping"));
+}
+
+/// Several replies are wrapped in a labeled batch; only the BATCH start
+/// carries the label.
+#[tokio::test]
+async fn test_multiple_replies_are_batched() {
+    let port = 16742;
+    let server = TestServer::spawn(port).await.expect("spawn");
+
+    let mut alice = TestClient::connect(&server.address(), "alice")
+        .await
+        .expect("connect");
+    register_with_caps(&mut alice, "alice", "labeled-response batch").await;
+    drain(&mut alice).await;
+
+    alice.send_raw("@label=w1 WHOIS alice").await.unwrap();
+    let replies = alice
+        .recv_until(|m| matches!(&m.command, Command::BATCH(r, ..) if r.starts_with('-')))
+        .await
+        .expect("batch");
+
+    let start = &replies[0];
+    assert!(
+        matches!(&start.command, Command::BATCH(r, ..) if r.starts_with('+')),
+        "{start}"
+    );
+    assert_eq!(label_of(start), Some("w1"));
+    for inner in &replies[1..replies.len() - 1] {
+        assert!(inner.tag_value("batch").is_some(), "{inner}");
+        assert_eq!(label_of(inner), None, "{inner}");
+    }
+}
+
+/// Labeled commands sent before registration completes are answered too.
+#[tokio::test]
+async fn test_pre_registration_commands_get_ack() {
+    let port = 16743;
+    let server = TestServer::spawn(port).await.expect("spawn");
+
+    let mut alice = TestClient::connect(&server.address(), "alice")
+        .await
+        .expect("connect");
+    alice
+        .send_raw("CAP REQ :labeled-response batch")
+        .await
+        .unwrap();
+    alice
+        .recv_until(|m| matches!(&m.command, Command::CAP(..)))
+        .await
+        .expect("CAP ACK");
+
+    alice.send_raw("@label=n1 NICK alice").await.unwrap();
+    let reply = recv_labeled(&mut alice).await;
+    assert_eq!(reply.command, Command::ACK, "{reply}");
+    assert_eq!(label_of(&reply), Some("n1"));
+}
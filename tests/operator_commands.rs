@@ -1,5 +1,5 @@
 // tests/operator_commands.rs
-//! Integration tests for operator commands: OPER, KILL, WALLOPS
+//! Integration tests for operator commands: OPER, KILL, WALLOPS, MAINTENANCE
 
 mod common;
 use common::{TestClient, TestServer};
@@ -178,3 +178,284 @@ async fn test_wallops_broadcast_to_wallops_users() {
         |m| matches!(&m.command, Command::WALLOPS(text) if text.contains("system maintenance"))
     ));
 }
+
+#[tokio::test]
+async fn test_kill_mask_requires_confirm() {
+    let port = 16704;
+    let server = TestServer::spawn(port)
+        .await
+        .expect("Failed to spawn test server");
+
+    let mut alice = TestClient::connect(&server.address(), "alice")
+        .await
+        .expect("Failed to connect Alice");
+    alice.register().await.expect("Registration failed");
+
+    let mut bob = TestClient::connect(&server.address(), "bob")
+        .await
+        .expect("Failed to connect Bob");
+    bob.register().await.expect("Registration failed");
+
+    drain(&mut alice).await;
+    drain(&mut bob).await;
+
+    alice
+        .send_raw("OPER testop testpass")
+        .await
+        .expect("Failed to send OPER");
+    let _ = alice
+        .recv_until(|msg| matches!(&msg.command, Command::Response(resp, _) if resp.code() == 381))
+        .await
+        .expect("Expected YOU'RE OPER");
+    drain(&mut alice).await;
+
+    // CONFIRM is refused until the server has shown the matches
+    alice
+        .send_raw("KILL b*!*@127.0.0.* CONFIRM :cleanup")
+        .await
+        .expect("Failed to send KILL");
+    alice
+        .recv_until(
+            |msg| matches!(&msg.command, Command::NOTICE(_, text) if text.contains("has not been previewed")),
+        )
+        .await
+        .expect("Expected unconfirmed KILL to be refused");
+    assert!(bob.recv_timeout(Duration::from_millis(200)).await.is_err());
+
+    // Without CONFIRM the mask is only previewed
+    alice
+        .send_raw("KILL b*!*@127.0.0.* :cleanup")
+        .await
+        .expect("Failed to send KILL");
+    let preview = alice
+        .recv_until(
+            |msg| matches!(&msg.command, Command::NOTICE(_, text) if text.contains("CONFIRM")),
+        )
+        .await
+        .expect("Expected KILL preview");
+    assert!(preview.iter().any(
+        |m| matches!(&m.command, Command::NOTICE(_, text) if text.contains("matches 1 user(s): bob"))
+    ));
+    assert!(bob.recv_timeout(Duration::from_millis(200)).await.is_err());
+
+    alice
+        .send_raw("KILL b*!*@127.0.0.* CONFIRM :cleanup")
+        .await
+        .expect("Failed to send KILL");
+    let messages = bob
+        .recv_until(|msg| matches!(&msg.command, Command::ERROR(_)))
+        .await
+        .expect("Bob should receive ERROR before disconnect");
+    assert!(
+        messages
+            .iter()
+            .any(|m| matches!(&m.command, Command::ERROR(text) if text.contains("cleanup")))
+    );
+
+    // CONFIRM without a reason kills with the default one
+    let mut carol = TestClient::connect(&server.address(), "carol")
+        .await
+        .expect("Failed to connect Carol");
+    carol.register().await.expect("Registration failed");
+    drain(&mut carol).await;
+
+    alice
+        .send_raw("KILL c*!*@127.0.0.*")
+        .await
+        .expect("Failed to send KILL");
+    alice
+        .recv_until(
+            |msg| matches!(&msg.command, Command::NOTICE(_, text) if text.contains("CONFIRM")),
+        )
+        .await
+        .expect("Expected KILL preview");
+    alice
+        .send_raw("KILL c*!*@127.0.0.* CONFIRM")
+        .await
+        .expect("Failed to send KILL");
+    let messages = carol
+        .recv_until(|msg| matches!(&msg.command, Command::ERROR(_)))
+        .await
+        .expect("Carol should receive ERROR before disconnect");
+    assert!(
+        messages.iter().any(
+            |m| matches!(&m.command, Command::ERROR(text) if text.contains("No reason given"))
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_kill_mask_confirm_requires_same_users() {
+    let port = 16707;
+    let server = TestServer::spawn(port)
+        .await
+        .expect("Failed to spawn test server");
+
+    let mut alice = TestClient::connect(&server.address(), "alice")
+        .await
+        .expect("Failed to connect Alice");
+    alice.register().await.expect("Registration failed");
+    let mut bob = TestClient::connect(&server.address(), "bob")
+        .await
+        .expect("Failed to connect Bob");
+    bob.register().await.expect("Registration failed");
+    drain(&mut alice).await;
+
+    alice
+        .send_raw("OPER testop testpass")
+        .await
+        .expect("Failed to send OPER");
+    alice
+        .recv_until(|msg| matches!(&msg.command, Command::Response(resp, _) if resp.code() == 381))
+        .await
+        .expect("Expected YOU'RE OPER");
+    alice
+        .send_raw("KILL b*!*@127.0.0.* :cleanup")
+        .await
+        .expect("Failed to send KILL");
+    alice
+        .recv_until(
+            |msg| matches!(&msg.command, Command::NOTICE(_, text) if text.contains("CONFIRM")),
+        )
+        .await
+        .expect("Expected KILL preview");
+
+    // Bob leaves and bert takes his place: same count, different user
+    bob.quit(None).await.expect("Failed to send QUIT");
+    let mut bert = TestClient::connect(&server.address(), "bert")
+        .await
+        .expect("Failed to connect Bert");
+    bert.register().await.expect("Registration failed");
+    drain(&mut bert).await;
+    alice
+        .send_raw("WHOIS bob")
+        .await
+        .expect("Failed to send WHOIS");
+    alice
+        .recv_until(|msg| matches!(&msg.command, Command::Response(resp, _) if resp.code() == 401))
+        .await
+        .expect("Bob should be gone");
+
+    alice
+        .send_raw("KILL b*!*@127.0.0.* CONFIRM :cleanup")
+        .await
+        .expect("Failed to send KILL");
+    let replies = alice
+        .recv_until(
+            |msg| matches!(&msg.command, Command::NOTICE(_, text) if text.contains("CONFIRM")),
+        )
+        .await
+        .expect("Expected a fresh KILL preview");
+    assert!(replies.iter().any(
+        |m| matches!(&m.command, Command::NOTICE(_, text) if text.contains("changed since the preview"))
+    ));
+    assert!(bert.recv_timeout(Duration::from_millis(200)).await.is_err());
+}
+
+#[tokio::test]
+async fn test_tracemask_lists_ip_matches() {
+    let port = 16705;
+    let server = TestServer::spawn(port)
+        .await
+        .expect("Failed to spawn test server");
+
+    let mut alice = TestClient::connect(&server.address(), "alice")
+        .await
+        .expect("Failed to connect Alice");
+    alice.register().await.expect("Registration failed");
+    drain(&mut alice).await;
+
+    alice
+        .send_raw("OPER testop testpass")
+        .await
+        .expect("Failed to send OPER");
+    let _ = alice
+        .recv_until(|msg| matches!(&msg.command, Command::Response(resp, _) if resp.code() == 381))
+        .await
+        .expect("Expected YOU'RE OPER");
+
+    alice
+        .send_raw("TRACEMASK 127.0.0.0/8")
+        .await
+        .expect("Failed to send TRACEMASK");
+    let messages = alice
+        .recv_until(|msg| matches!(&msg.command, Command::Response(resp, _) if resp.code() == 262))
+        .await
+        .expect("Expected RPL_TRACEEND");
+    assert!(messages.iter().any(|m| matches!(
+        &m.command,
+        Command::Response(resp, args) if resp.code() == 204 && args.iter().any(|a| a.starts_with("alice["))
+    )));
+}
+
+#[tokio::test]
+async fn test_maintenance_mode_refuses_registration_and_services_writes() {
+    let port = 16706;
+    let server = TestServer::spawn(port)
+        .await
+        .expect("Failed to spawn test server");
+
+    let mut alice = TestClient::connect(&server.address(), "alice")
+        .await
+        .expect("Failed to connect Alice");
+    alice.register().await.expect("Registration failed");
+    drain(&mut alice).await;
+
+    alice
+        .send_raw("OPER testop testpass")
+        .await
+        .expect("Failed to send OPER");
+    let _ = alice
+        .recv_until(|msg| matches!(&msg.command, Command::Response(resp, _) if resp.code() == 381))
+        .await
+        .expect("Expected YOU'RE OPER");
+    drain(&mut alice).await;
+
+    alice
+        .send_raw("MAINTENANCE ON :Upgrading")
+        .await
+        .expect("Failed to send MAINTENANCE");
+    let _ = alice
+        .recv_until(|msg| {
+            matches!(&msg.command, Command::NOTICE(_, text) if text.contains("enabled maintenance mode (Upgrading)"))
+        })
+        .await
+        .expect("Expected maintenance notice");
+
+    // New registrations are refused with a notice and ERROR
+    let mut bob = TestClient::connect(&server.address(), "bob")
+        .await
+        .expect("Failed to connect Bob");
+    assert!(bob.register().await.is_err());
+
+    // Services writes are refused for existing users
+    alice
+        .send_raw("PRIVMSG NickServ :REGISTER hunter22 alice@example.com")
+        .await
+        .expect("Failed to send REGISTER");
+    let _ = alice
+        .recv_until(
+            |msg| matches!(&msg.command, Command::NOTICE(_, text) if text.contains("read-only")),
+        )
+        .await
+        .expect("Expected read-only notice");
+
+    alice
+        .send_raw("MAINTENANCE OFF")
+        .await
+        .expect("Failed to send MAINTENANCE OFF");
+    let _ = alice
+        .recv_until(|msg| {
+            matches!(&msg.command, Command::NOTICE(_, text) if text.contains("disabled maintenance mode"))
+        })
+        .await
+        .expect("Expected maintenance off notice");
+
+    let mut carol = TestClient::connect(&server.address(), "carol")
+        .await
+        .expect("Failed to connect Carol");
+    carol
+        .register()
+        .await
+        .expect("Registration after maintenance failed");
+}
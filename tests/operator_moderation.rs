@@ -1,6 +1,6 @@
 // tests/operator_moderation.rs
 //! Integration tests for operator moderation commands: KLINE/GLINE/ZLINE/RLINE,
-//! and admin/broadcast commands: REHASH, GLOBOPS.
+//! RESV, and admin/broadcast commands: REHASH, GLOBOPS.
 
 mod common;
 use common::{TestClient, TestServer};
@@ -485,3 +485,114 @@ async fn test_unrline_removes_ban_and_allows_reconnect() {
     victim2.register().await.expect("victim re-register");
     drain(&mut victim2).await;
 }
+
+#[tokio::test]
+async fn test_resv_blocks_nick_and_channel_for_non_opers() {
+    let port = 16720;
+    let server = TestServer::spawn(port)
+        .await
+        .expect("Failed to spawn test server");
+
+    let mut oper = TestClient::connect(&server.address(), "alice")
+        .await
+        .expect("connect oper");
+    oper.register().await.expect("oper register");
+
+    let mut user = TestClient::connect(&server.address(), "bob")
+        .await
+        .expect("connect user");
+    user.register().await.expect("user register");
+
+    drain(&mut oper).await;
+    drain(&mut user).await;
+
+    become_oper(&mut oper).await;
+
+    oper.send_raw("RESV Bad*Nick :reserved nick")
+        .await
+        .expect("send RESV nick");
+    let _ = oper
+        .recv_until(
+            |m| matches!(&m.command, Command::NOTICE(_, text) if text.contains("Q-line added")),
+        )
+        .await
+        .expect("oper should receive Q-line confirmation");
+    oper.send_raw("RESV #reserved :reserved channel")
+        .await
+        .expect("send RESV channel");
+    let _ = oper
+        .recv_until(
+            |m| matches!(&m.command, Command::NOTICE(_, text) if text.contains("Q-line added")),
+        )
+        .await
+        .expect("oper should receive Q-line confirmation");
+
+    // Reserved nick is refused with 432 and the reason
+    user.send_raw("NICK BadXNick").await.expect("send NICK");
+    let msgs = user
+        .recv_until(|m| matches!(&m.command, Command::Response(resp, _) if resp.code() == 432))
+        .await
+        .expect("reserved nick should be refused with 432");
+    let refused = msgs.iter().any(|m| {
+        matches!(&m.command, Command::Response(resp, params)
+            if resp.code() == 432 && params.last().is_some_and(|p| p.contains("reserved nick")))
+    });
+    assert!(refused, "432 should carry the Q-line reason");
+
+    // Reserved channel is refused with 479
+    user.send_raw("JOIN #reserved").await.expect("send JOIN");
+    let _ = user
+        .recv_until(|m| matches!(&m.command, Command::Response(resp, _) if resp.code() == 479))
+        .await
+        .expect("reserved channel should be refused with 479");
+
+    // Q-lines are listed in STATS q
+    oper.send_raw("STATS q").await.expect("send STATS q");
+    let msgs = oper
+        .recv_until(|m| matches!(&m.command, Command::Response(resp, _) if resp.code() == 219))
+        .await
+        .expect("STATS q should end with 219");
+    let listed = |mask: &str| {
+        msgs.iter().any(|m| {
+            matches!(&m.command, Command::Response(resp, params)
+                if resp.code() == 220 && params.iter().any(|p| p == mask))
+        })
+    };
+    assert!(listed("Bad*Nick"), "STATS q should list the nick Q-line");
+    assert!(
+        listed("#reserved"),
+        "STATS q should list the channel Q-line"
+    );
+
+    // After UNRESV the nick is available again
+    oper.send_raw("UNRESV Bad*Nick").await.expect("send UNRESV");
+    let _ = oper
+        .recv_until(
+            |m| matches!(&m.command, Command::NOTICE(_, text) if text.contains("Q-line removed")),
+        )
+        .await
+        .expect("oper should receive Q-line removed notice");
+
+    user.send_raw("NICK BadXNick").await.expect("send NICK");
+    let _ = user
+        .recv_until(|m| matches!(&m.command, Command::NICK(nick) if nick == "BadXNick"))
+        .await
+        .expect("nick change should succeed after UNRESV");
+
+    // UNRESV matches the mask regardless of case
+    oper.send_raw("UNRESV #RESERVED")
+        .await
+        .expect("send UNRESV");
+    let _ = oper
+        .recv_until(
+            |m| matches!(&m.command, Command::NOTICE(_, text) if text.contains("Q-line removed")),
+        )
+        .await
+        .expect("oper should receive Q-line removed notice");
+
+    user.send_raw("JOIN #reserved").await.expect("send JOIN");
+    let _ = user
+        .recv_until(|m| matches!(&m.command, Command::JOIN(chan, _, _) if chan == "#reserved"))
+        .await
+        .expect("join should succeed after UNRESV");
+}
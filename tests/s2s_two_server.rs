@@ -145,6 +145,39 @@ async fn test_s2s_squit_cleanup() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Test that a Q-lined nick introduced by the peer is force-renamed at burst.
+#[tokio::test]
+async fn test_s2s_burst_renames_qlined_nick() -> anyhow::Result<()> {
+    let (test_dir, _server_a, _server_b, mut client_a, mut client_b) = setup_s2s_env().await?;
+
+    // Reserve Bob's nick on A only; B has no Q-line and lets him keep it.
+    client_a.send_raw("OPER admin operpass").await?;
+    expect_oper_success(&mut client_a).await?;
+    client_a.send_raw("RESV bob :Reserved for staff").await?;
+    let _ = expect_msg_containing(&mut client_a, "Q-line added").await?;
+
+    client_a.send_raw("CONNECT server-b.test 6667").await?;
+    wait_for_link(&mut client_a, "server-b.test").await?;
+
+    // A asks B (via ENCAP RSFNC) to rename Bob to his UID.
+    let msgs = client_b
+        .recv_until(|msg| matches!(&msg.command, Command::NICK(_)))
+        .await?;
+    let msg = msgs.last().expect("Should have found NICK message");
+    let Command::NICK(new_nick) = &msg.command else {
+        unreachable!()
+    };
+    assert!(
+        new_nick.starts_with("002"),
+        "renamed to UID, got {new_nick}"
+    );
+    assert!(msg.prefix.as_ref().unwrap().to_string().starts_with("bob!"));
+
+    // Cleanup
+    let _ = std::fs::remove_dir_all(&test_dir);
+    Ok(())
+}
+
 // --- Helpers ---
 
 fn get_free_port() -> u16 {
@@ -28,6 +28,7 @@ metrics_port = 0
 [listen]
 address = "127.0.0.1:{}"
 proxy_protocol = true
+proxy_trusted = ["127.0.0.1"]
 
 [database]
 path = "{}/test.db"
@@ -44,6 +45,7 @@ spam_detection_enabled = false
 message_rate_per_second = 1000
 connection_burst_per_ip = 1000
 join_burst_per_client = 1000
+join_part_burst = 1000
 max_connections_per_ip = 200
 
 [motd]
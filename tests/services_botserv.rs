@@ -0,0 +1,79 @@
+mod common;
+use common::TestServer;
+use slirc_proto::Command;
+
+#[tokio::test]
+async fn test_botserv_assign_greet_and_badwords() -> anyhow::Result<()> {
+    let server = TestServer::spawn(16783).await?;
+
+    let mut alice = server.connect("Alice").await?;
+    alice.register().await?;
+    alice
+        .privmsg("NickServ", "REGISTER password123 alice@example.com")
+        .await?;
+    alice
+        .recv_until(|m| m.to_string().contains("registered"))
+        .await?;
+    alice.join("#bots").await?;
+    alice
+        .recv_until(|m| matches!(&m.command, Command::JOIN(..)))
+        .await?;
+    alice.privmsg("ChanServ", "REGISTER #bots").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("has been registered"))
+        .await?;
+
+    // Only operators create bots
+    alice
+        .privmsg("BotServ", "BOT ADD Botty bot bots.example.net Friendly Bot")
+        .await?;
+    alice
+        .recv_until(|m| m.to_string().contains("Access denied"))
+        .await?;
+
+    let mut bob = server.connect("Bob").await?;
+    bob.register().await?;
+    bob.send_raw("OPER testop testpass").await?;
+    bob.recv_until(|m| matches!(&m.command, Command::Response(resp, _) if resp.code() == 381))
+        .await?;
+    bob.privmsg("BotServ", "BOT ADD Botty bot bots.example.net Friendly Bot")
+        .await?;
+    bob.recv_until(|m| m.to_string().contains("created"))
+        .await?;
+
+    // The founder assigns the bot, which joins the channel
+    alice.privmsg("BotServ", "ASSIGN #bots Botty").await?;
+    let seen = alice
+        .recv_until(|m| matches!(&m.command, Command::JOIN(..)))
+        .await?;
+    assert!(seen.iter().any(|m| m.to_string().starts_with(":Botty!bot@bots.example.net JOIN")));
+
+    alice
+        .privmsg("BotServ", "SET #bots GREET Welcome to the bot channel!")
+        .await?;
+    alice.recv_until(|m| m.to_string().contains("Greet message")).await?;
+    alice
+        .privmsg("BotServ", "SET #bots BADWORDS ADD darn")
+        .await?;
+    alice.recv_until(|m| m.to_string().contains("added to the badwords")).await?;
+
+    let mut carol = server.connect("Carol").await?;
+    carol.register().await?;
+    carol.join("#bots").await?;
+    carol
+        .recv_until(|m| {
+            m.to_string().starts_with(":Botty!")
+                && m.to_string().contains("[#bots] Welcome to the bot channel!")
+        })
+        .await?;
+
+    carol.privmsg("#bots", "oh darn it").await?;
+    let seen = carol
+        .recv_until(|m| matches!(&m.command, Command::KICK(..)))
+        .await?;
+    assert!(seen.iter().any(|m| {
+        matches!(&m.command, Command::KICK(channel, nick, _) if channel == "#bots" && nick == "Carol")
+    }));
+
+    Ok(())
+}
@@ -78,3 +78,335 @@ async fn test_chanserv_register_flow() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_chanserv_akick_bans_and_kicks() -> anyhow::Result<()> {
+    let server = TestServer::spawn(16780).await?;
+
+    let mut alice = server.connect("Alice").await?;
+    alice.register().await?;
+    alice
+        .privmsg("NickServ", "REGISTER password123 alice@example.com")
+        .await?;
+    alice
+        .recv_until(|m| m.to_string().contains("registered"))
+        .await?;
+    alice.join("#akick").await?;
+    alice
+        .recv_until(|m| matches!(&m.command, Command::JOIN(..)))
+        .await?;
+    alice.privmsg("ChanServ", "REGISTER #akick").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("has been registered"))
+        .await?;
+
+    let mut bob = server.connect("Bob").await?;
+    bob.register().await?;
+    bob.join("#akick").await?;
+    bob.recv_until(|m| matches!(&m.command, Command::JOIN(..)))
+        .await?;
+
+    // Adding the AKICK removes the member already inside
+    alice
+        .privmsg("ChanServ", "AKICK #akick ADD bob!*@* go away")
+        .await?;
+    let seen = alice
+        .recv_until(|m| matches!(&m.command, Command::KICK(..)))
+        .await?;
+    assert!(seen.iter().any(|m| {
+        let line = m.to_string();
+        line.contains("MODE #akick +b bob!*@*")
+    }));
+    let kicked = bob
+        .recv_until(|m| matches!(&m.command, Command::KICK(..)))
+        .await?;
+    assert!(kicked.last().unwrap().to_string().contains("go away"));
+
+    // Rejoining is refused at join time
+    bob.join("#akick").await?;
+    let refused = bob
+        .recv_until(|m| m.to_string().contains("not permitted"))
+        .await?;
+    assert!(
+        !refused
+            .iter()
+            .any(|m| matches!(&m.command, Command::JOIN(..)))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_chanserv_notes_for_staff_only() -> anyhow::Result<()> {
+    let server = TestServer::spawn(16784).await?;
+
+    let mut alice = server.connect("Alice").await?;
+    alice.register().await?;
+    alice
+        .privmsg("NickServ", "REGISTER password123 alice@example.com")
+        .await?;
+    alice
+        .recv_until(|m| m.to_string().contains("registered"))
+        .await?;
+    alice.join("#notes").await?;
+    alice
+        .recv_until(|m| matches!(&m.command, Command::JOIN(..)))
+        .await?;
+    alice.privmsg("ChanServ", "REGISTER #notes").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("has been registered"))
+        .await?;
+
+    alice
+        .privmsg(
+            "ChanServ",
+            "NOTE #notes ADD watch out for spam from *.example.org",
+        )
+        .await?;
+    alice
+        .recv_until(|m| m.to_string().contains("added to"))
+        .await?;
+    alice.privmsg("ChanServ", "NOTE #notes LIST").await?;
+    let listed = alice
+        .recv_until(|m| m.to_string().contains("End of notes"))
+        .await?;
+    assert!(listed.iter().any(|m| {
+        let line = m.to_string();
+        line.contains("by Alice") && line.contains("watch out for spam from *.example.org")
+    }));
+
+    // Users without access cannot read the notes
+    let mut bob = server.connect("Bob").await?;
+    bob.register().await?;
+    bob.privmsg("NickServ", "REGISTER password456 bob@example.com")
+        .await?;
+    bob.recv_until(|m| m.to_string().contains("registered"))
+        .await?;
+    bob.privmsg("ChanServ", "NOTE #notes LIST").await?;
+    let refused = bob
+        .recv_until(|m| m.to_string().contains("do not have access"))
+        .await?;
+    assert!(!refused.iter().any(|m| m.to_string().contains("spam")));
+
+    alice.privmsg("ChanServ", "NOTE #notes DEL 1").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("removed from"))
+        .await?;
+    alice.privmsg("ChanServ", "NOTE #notes LIST").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("has no notes"))
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_chanserv_flags_grant_commands_and_succession() -> anyhow::Result<()> {
+    let server = TestServer::spawn(16786).await?;
+
+    let mut alice = server.connect("Alice").await?;
+    alice.register().await?;
+    alice
+        .privmsg("NickServ", "REGISTER password123 alice@example.com")
+        .await?;
+    alice
+        .recv_until(|m| m.to_string().contains("registered"))
+        .await?;
+    alice.join("#flags").await?;
+    alice
+        .recv_until(|m| matches!(&m.command, Command::JOIN(..)))
+        .await?;
+    alice.privmsg("ChanServ", "REGISTER #flags").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("has been registered"))
+        .await?;
+
+    let mut bob = server.connect("Bob").await?;
+    bob.register().await?;
+    bob.privmsg("NickServ", "REGISTER password456 bob@example.com")
+        .await?;
+    bob.recv_until(|m| m.to_string().contains("registered"))
+        .await?;
+    bob.join("#flags").await?;
+    bob.recv_until(|m| matches!(&m.command, Command::JOIN(..)))
+        .await?;
+
+    // No flags, no TOPIC
+    bob.privmsg("ChanServ", "TOPIC #flags Hello").await?;
+    bob.recv_until(|m| m.to_string().contains("need +t"))
+        .await?;
+
+    alice.privmsg("ChanServ", "FLAGS #flags Bob +t").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("are now \x02+t\x02"))
+        .await?;
+    bob.privmsg("ChanServ", "TOPIC #flags Set through flags")
+        .await?;
+    bob.recv_until(
+        |m| matches!(&m.command, Command::TOPIC(_, Some(topic)) if topic == "Set through flags"),
+    )
+    .await?;
+
+    // +t does not grant OP
+    bob.privmsg("ChanServ", "OP #flags").await?;
+    bob.recv_until(|m| m.to_string().contains("do not have access"))
+        .await?;
+
+    // The only founder cannot step down; with a second founder, ownership passes
+    alice.privmsg("ChanServ", "FLAGS #flags Alice -F").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("last founder"))
+        .await?;
+    alice.privmsg("ChanServ", "FLAGS #flags Bob +F").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("are now \x02+tF\x02"))
+        .await?;
+    alice.privmsg("ChanServ", "FLAGS #flags Alice -F").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("Bob\x02 is now the founder"))
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_chanserv_mlock_enforced_and_reapplied() -> anyhow::Result<()> {
+    let server = TestServer::spawn(16787).await?;
+
+    let mut alice = server.connect("Alice").await?;
+    alice.register().await?;
+    alice
+        .privmsg("NickServ", "REGISTER password123 alice@example.com")
+        .await?;
+    alice
+        .recv_until(|m| m.to_string().contains("registered"))
+        .await?;
+    alice.join("#locked").await?;
+    alice
+        .recv_until(|m| matches!(&m.command, Command::JOIN(..)))
+        .await?;
+    alice.privmsg("ChanServ", "REGISTER #locked").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("has been registered"))
+        .await?;
+
+    // Invalid locks are refused
+    alice.privmsg("ChanServ", "SET #locked MLOCK +b").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("Invalid mode lock"))
+        .await?;
+
+    // Setting the lock applies it to the live channel
+    alice
+        .privmsg("ChanServ", "SET #locked MLOCK +ntm-s")
+        .await?;
+    alice
+        .recv_until(|m| m.to_string().contains("is now \x02+ntm-s\x02"))
+        .await?;
+    alice
+        .recv_until(|m| {
+            matches!(&m.command, Command::ChannelMODE(..)) && m.to_string().contains("+m")
+        })
+        .await?;
+
+    // Locked modes cannot be changed, other modes can
+    alice.send_raw("MODE #locked -m+i").await?;
+    alice
+        .recv_until(|m| {
+            matches!(&m.command, Command::ChannelMODE(..)) && m.to_string().contains("+i")
+        })
+        .await?;
+    alice.send_raw("MODE #locked").await?;
+    alice
+        .recv_until(|m| {
+            matches!(&m.command, Command::Response(resp, params)
+                if resp.code() == 324 && params.get(2).is_some_and(|modes| modes.contains('m')))
+        })
+        .await?;
+
+    // Recreating the channel re-applies the lock
+    alice.send_raw("PART #locked").await?;
+    alice
+        .recv_until(|m| matches!(&m.command, Command::PART(..)))
+        .await?;
+    // The founder gets back in past the restored +i; +m comes back with the
+    // saved modes, so there is no MODE to see
+    alice.join("#locked").await?;
+    alice.send_raw("MODE #locked").await?;
+    alice
+        .recv_until(|m| {
+            matches!(&m.command, Command::Response(resp, params)
+                if resp.code() == 324 && params.get(2).is_some_and(|modes| modes.contains('m')))
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_chanserv_successor_inherits_on_account_drop() -> anyhow::Result<()> {
+    let server = TestServer::spawn(16789).await?;
+
+    let mut alice = server.connect("Alice").await?;
+    alice.register().await?;
+    alice
+        .privmsg("NickServ", "REGISTER password123 alice@example.com")
+        .await?;
+    alice
+        .recv_until(|m| m.to_string().contains("registered"))
+        .await?;
+    alice.join("#heir").await?;
+    alice
+        .recv_until(|m| matches!(&m.command, Command::JOIN(..)))
+        .await?;
+    alice.privmsg("ChanServ", "REGISTER #heir").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("has been registered"))
+        .await?;
+
+    let mut bob = server.connect("Bob").await?;
+    bob.register().await?;
+    bob.privmsg("NickServ", "REGISTER password456 bob@example.com")
+        .await?;
+    bob.recv_until(|m| m.to_string().contains("registered"))
+        .await?;
+
+    // Only the founder names the successor, and it must be an account
+    bob.privmsg("ChanServ", "SET #heir SUCCESSOR Bob").await?;
+    bob.recv_until(|m| m.to_string().contains("need +s"))
+        .await?;
+    alice
+        .privmsg("ChanServ", "SET #heir SUCCESSOR Nobody")
+        .await?;
+    alice
+        .recv_until(|m| m.to_string().contains("is not registered"))
+        .await?;
+    alice.privmsg("ChanServ", "SET #heir SUCCESSOR Bob").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("now the successor"))
+        .await?;
+    alice.privmsg("ChanServ", "INFO #heir").await?;
+    let info = alice
+        .recv_until(|m| m.to_string().contains("End of info"))
+        .await?;
+    assert!(
+        info.iter()
+            .any(|m| m.to_string().contains("Successor  : Bob"))
+    );
+
+    // Dropping the founder's account hands the channel over
+    alice.privmsg("NickServ", "DROP password123").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("handed over to \x02Bob\x02"))
+        .await?;
+    bob.privmsg("ChanServ", "INFO #heir").await?;
+    let info = bob
+        .recv_until(|m| m.to_string().contains("End of info"))
+        .await?;
+    assert!(
+        info.iter()
+            .any(|m| m.to_string().contains("Founder    : Bob"))
+    );
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+mod common;
+use common::TestServer;
+use slirc_proto::Command;
+
+#[tokio::test]
+async fn test_hostserv_request_activate_and_toggle() -> anyhow::Result<()> {
+    let server = TestServer::spawn(16781).await?;
+
+    let mut alice = server.connect("Alice").await?;
+    alice.register().await?;
+    alice
+        .privmsg("NickServ", "REGISTER password123 alice@example.com")
+        .await?;
+    alice
+        .recv_until(|m| m.to_string().contains("registered"))
+        .await?;
+    alice.join("#vhost").await?;
+    alice
+        .recv_until(|m| matches!(&m.command, Command::JOIN(..)))
+        .await?;
+
+    // Bob shares a channel with Alice and sees host changes as CHGHOST
+    let mut bob = server.connect("Bob").await?;
+    bob.send_raw("CAP REQ :chghost").await?;
+    bob.send_raw("CAP END").await?;
+    bob.register().await?;
+    bob.send_raw("OPER testop testpass").await?;
+    bob.recv_until(|m| matches!(&m.command, Command::Response(resp, _) if resp.code() == 381))
+        .await?;
+    bob.join("#vhost").await?;
+    bob.recv_until(|m| matches!(&m.command, Command::JOIN(..)))
+        .await?;
+
+    alice
+        .privmsg("HostServ", "REQUEST alice.example.net")
+        .await?;
+    alice
+        .recv_until(|m| m.to_string().contains("sent to the operators"))
+        .await?;
+
+    // Only operators review requests
+    alice.privmsg("HostServ", "ACTIVATE Alice").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("Access denied"))
+        .await?;
+
+    bob.privmsg("HostServ", "LIST").await?;
+    let listed = bob
+        .recv_until(|m| m.to_string().contains("End of list"))
+        .await?;
+    assert!(
+        listed
+            .iter()
+            .any(|m| m.to_string().contains("-> alice.example.net"))
+    );
+
+    bob.privmsg("HostServ", "ACTIVATE Alice").await?;
+    let seen = bob
+        .recv_until(|m| matches!(&m.command, Command::CHGHOST(..)))
+        .await?;
+    assert!(seen.iter().any(|m| {
+        matches!(&m.command, Command::CHGHOST(_, host) if host == "alice.example.net")
+    }));
+    alice
+        .recv_until(|m| {
+            matches!(&m.command, Command::Response(resp, args)
+                if resp.code() == 396 && args.get(1).is_some_and(|h| h == "alice.example.net"))
+        })
+        .await?;
+
+    // OFF restores the cloak, ON brings the vhost back
+    alice.privmsg("HostServ", "OFF").await?;
+    let seen = bob
+        .recv_until(|m| matches!(&m.command, Command::CHGHOST(..)))
+        .await?;
+    assert!(seen.iter().any(|m| {
+        matches!(&m.command, Command::CHGHOST(_, host) if host != "alice.example.net")
+    }));
+
+    alice.privmsg("HostServ", "ON").await?;
+    let seen = bob
+        .recv_until(|m| matches!(&m.command, Command::CHGHOST(..)))
+        .await?;
+    assert!(seen.iter().any(|m| {
+        matches!(&m.command, Command::CHGHOST(_, host) if host == "alice.example.net")
+    }));
+
+    Ok(())
+}
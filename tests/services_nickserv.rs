@@ -0,0 +1,82 @@
+mod common;
+use common::TestServer;
+use slirc_proto::Command;
+
+#[tokio::test]
+async fn test_nickserv_sessions_list_and_kill() -> anyhow::Result<()> {
+    let server = TestServer::spawn(16790).await?;
+
+    let mut alice = server.connect("Alice").await?;
+    alice.register().await?;
+    alice
+        .privmsg("NickServ", "REGISTER password123 alice@example.com")
+        .await?;
+    alice
+        .recv_until(|m| m.to_string().contains("registered"))
+        .await?;
+
+    // A second connection logs into the same account
+    let mut laptop = server.connect("AliceLaptop").await?;
+    laptop.register().await?;
+    laptop
+        .privmsg("NickServ", "GROUP Alice password123")
+        .await?;
+    laptop
+        .recv_until(|m| m.to_string().contains("now identified"))
+        .await?;
+
+    alice.privmsg("NickServ", "SESSIONS").await?;
+    let listing = alice
+        .recv_until(|m| m.to_string().contains("End of session list"))
+        .await?;
+    assert!(listing.iter().any(|m| {
+        m.to_string()
+            .contains("Active sessions for \x02Alice\x02 (2 total)")
+    }));
+    let laptop_line = listing
+        .iter()
+        .map(|m| m.to_string())
+        .find(|line| line.contains(" AliceLaptop from "))
+        .expect("laptop session listed");
+    let laptop_id = laptop_line
+        .split('\x02')
+        .nth(1)
+        .expect("session id")
+        .to_string();
+
+    // Someone else's account is off limits
+    let mut bob = server.connect("Bob").await?;
+    bob.register().await?;
+    bob.privmsg("NickServ", "SESSIONS Alice").await?;
+    bob.recv_until(|m| m.to_string().contains("not logged in"))
+        .await?;
+
+    // Killing the laptop session closes only that connection
+    alice
+        .privmsg("NickServ", &format!("SESSIONS KILL {}", laptop_id))
+        .await?;
+    alice
+        .recv_until(|m| m.to_string().contains("has been disconnected"))
+        .await?;
+    let closed = laptop
+        .recv_until(|m| matches!(&m.command, Command::ERROR(_)))
+        .await?;
+    assert!(
+        closed
+            .last()
+            .unwrap()
+            .to_string()
+            .contains("Session killed by NickServ")
+    );
+
+    // Repeated identical notices are deduplicated, so wait on the new header
+    alice.privmsg("NickServ", "SESSIONS").await?;
+    alice
+        .recv_until(|m| {
+            m.to_string()
+                .contains("Active sessions for \x02Alice\x02 (1 total)")
+        })
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+mod common;
+use common::TestServer;
+use slirc_proto::Command;
+
+#[tokio::test]
+async fn test_operserv_akill_global_and_sessions() -> anyhow::Result<()> {
+    let server = TestServer::spawn(16782).await?;
+
+    let mut alice = server.connect("Alice").await?;
+    alice.register().await?;
+
+    let mut bob = server.connect("Bob").await?;
+    bob.register().await?;
+    bob.send_raw("OPER testop testpass").await?;
+    bob.recv_until(|m| matches!(&m.command, Command::Response(resp, _) if resp.code() == 381))
+        .await?;
+
+    // Only operators may use OperServ
+    alice.privmsg("OperServ", "GLOBAL hello").await?;
+    alice
+        .recv_until(|m| m.to_string().contains("Access denied"))
+        .await?;
+
+    bob.privmsg("OperServ", "AKILL ADD +1h baduser@nowhere.example Spamming")
+        .await?;
+    bob.recv_until(|m| m.to_string().contains("added")).await?;
+    bob.privmsg("OperServ", "AKILL LIST").await?;
+    let listed = bob
+        .recv_until(|m| m.to_string().contains("End of list"))
+        .await?;
+    assert!(
+        listed
+            .iter()
+            .any(|m| m.to_string().contains("baduser@nowhere.example"))
+    );
+    bob.privmsg("OperServ", "AKILL DEL baduser@nowhere.example")
+        .await?;
+    bob.recv_until(|m| m.to_string().contains("removed"))
+        .await?;
+
+    bob.privmsg("OperServ", "GLOBAL Maintenance at midnight")
+        .await?;
+    alice
+        .recv_until(|m| {
+            m.to_string()
+                .contains("[Network Notice] Maintenance at midnight")
+        })
+        .await?;
+
+    bob.privmsg("OperServ", "SESSION LIMIT ADD 127.0.0.1 25 Shared host")
+        .await?;
+    bob.recv_until(|m| m.to_string().contains("set to 25"))
+        .await?;
+    bob.privmsg("OperServ", "SESSION VIEW 127.0.0.1").await?;
+    let seen = bob
+        .recv_until(|m| m.to_string().contains("the limit is"))
+        .await?;
+    assert!(
+        seen.iter()
+            .any(|m| m.to_string().contains("the limit is 25"))
+    );
+
+    Ok(())
+}
@@ -43,18 +43,27 @@ async fn test_read_marker_sync() {
         .await
         .expect("Failed to send USER");
 
-    // 3. Receive CAP LS response
-    let msg = client.recv().await.expect("Failed to receive CAP LS");
-    if let Command::CAP(_, slirc_proto::CapSubCommand::LS, arg1, arg2) = msg.command {
+    // 3. Receive CAP LS response, which may continue over several lines
+    // marked with `*` before the final one
+    let mut advertised = String::new();
+    loop {
+        let msg = client.recv().await.expect("Failed to receive CAP LS");
+        let Command::CAP(_, slirc_proto::CapSubCommand::LS, arg1, arg2) = msg.command else {
+            panic!("Expected CAP LS response, got: {:?}", msg);
+        };
+        let more = arg1.as_deref() == Some("*") && arg2.is_some();
         let caps = arg2.as_ref().or(arg1.as_ref()).expect("No caps in LS");
-        assert!(
-            caps.contains("draft/read-marker"),
-            "Server did not advertise draft/read-marker in: {}",
-            caps
-        );
-    } else {
-        panic!("Expected CAP LS response, got: {:?}", msg);
+        advertised.push_str(caps);
+        advertised.push(' ');
+        if !more {
+            break;
+        }
     }
+    assert!(
+        advertised.contains("draft/read-marker"),
+        "Server did not advertise draft/read-marker in: {}",
+        advertised
+    );
 
     // 4. Request the capability
     client
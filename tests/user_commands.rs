@@ -492,3 +492,109 @@ async fn test_ison_command() {
         other => panic!("Expected RPL_ISON (303), got {:?}", other),
     }
 }
+
+#[tokio::test]
+async fn test_watch_command() {
+    let port = 16788;
+    let server = TestServer::spawn(port)
+        .await
+        .expect("Failed to spawn test server");
+
+    let mut alice = TestClient::connect(&server.address(), "alice")
+        .await
+        .expect("Failed to connect alice");
+    let mut bob = TestClient::connect(&server.address(), "bob")
+        .await
+        .expect("Failed to connect bob");
+
+    alice.register().await.expect("Alice registration failed");
+    bob.register().await.expect("Bob registration failed");
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    while alice
+        .recv_timeout(tokio::time::Duration::from_millis(10))
+        .await
+        .is_ok()
+    {}
+
+    let code_of = |msg: &slirc_proto::Message| match &msg.command {
+        Command::Response(resp, _) => resp.code(),
+        _ => 0,
+    };
+    let params_of = |msg: &slirc_proto::Message| match &msg.command {
+        Command::Response(_, params) => params.clone(),
+        _ => Vec::new(),
+    };
+
+    // Online and offline entries get RPL_NOWON (604) / RPL_NOWOFF (605)
+    alice
+        .send_raw("WATCH +bob +carol\r\n")
+        .await
+        .expect("Failed to send WATCH");
+    let msg = alice
+        .recv_timeout(tokio::time::Duration::from_secs(2))
+        .await
+        .expect("Failed to receive WATCH reply");
+    assert_eq!(code_of(&msg), 604, "Expected RPL_NOWON, got {:?}", msg);
+    assert_eq!(params_of(&msg)[1], "bob");
+    let msg = alice
+        .recv_timeout(tokio::time::Duration::from_secs(2))
+        .await
+        .expect("Failed to receive WATCH reply");
+    assert_eq!(code_of(&msg), 605, "Expected RPL_NOWOFF, got {:?}", msg);
+    assert_eq!(params_of(&msg)[1], "carol");
+
+    // Statistics, list and end of list
+    alice.send_raw("WATCH S\r\n").await.expect("Failed to send");
+    let msgs = alice
+        .recv_until(|msg| code_of(msg) == 607)
+        .await
+        .expect("Failed to receive WATCH S");
+    assert_eq!(code_of(&msgs[0]), 603);
+    assert!(
+        msgs.iter()
+            .any(|m| code_of(m) == 606 && params_of(m)[1] == "bob carol"),
+        "Expected watch list, got {:?}",
+        msgs
+    );
+
+    // Presence changes arrive as RPL_LOGOFF (601) / RPL_LOGON (600)
+    bob.send_raw("NICK carol\r\n")
+        .await
+        .expect("Failed to send");
+    let msg = alice
+        .recv_timeout(tokio::time::Duration::from_secs(2))
+        .await
+        .expect("Failed to receive logoff");
+    assert_eq!(code_of(&msg), 601, "Expected RPL_LOGOFF, got {:?}", msg);
+    assert_eq!(params_of(&msg)[1], "bob");
+    let msg = alice
+        .recv_timeout(tokio::time::Duration::from_secs(2))
+        .await
+        .expect("Failed to receive logon");
+    assert_eq!(code_of(&msg), 600, "Expected RPL_LOGON, got {:?}", msg);
+    assert_eq!(params_of(&msg)[1], "carol");
+
+    // Removing an entry confirms with RPL_WATCHOFF (602)
+    alice
+        .send_raw("WATCH -carol\r\n")
+        .await
+        .expect("Failed to send");
+    let msg = alice
+        .recv_timeout(tokio::time::Duration::from_secs(2))
+        .await
+        .expect("Failed to receive WATCHOFF");
+    assert_eq!(code_of(&msg), 602, "Expected RPL_WATCHOFF, got {:?}", msg);
+
+    // Clearing leaves an empty list
+    alice
+        .send_raw("WATCH C S\r\n")
+        .await
+        .expect("Failed to send");
+    let msg = alice
+        .recv_timeout(tokio::time::Duration::from_secs(2))
+        .await
+        .expect("Failed to receive WATCH S");
+    assert_eq!(code_of(&msg), 603);
+    assert!(params_of(&msg)[1].starts_with("You have 0 "));
+}
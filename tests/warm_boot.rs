@@ -0,0 +1,126 @@
+//! Integration test for the warm-boot state snapshot.
+//!
+//! Verifies that channel state written on shutdown (DIE) is restored by the
+//! next server process, even though every client has disconnected.
+
+mod common;
+
+use common::{TestClient, TestServer};
+use slirc_proto::Command;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::sleep;
+
+fn write_config(config_path: &Path, data_dir: &Path, port: u16) -> anyhow::Result<()> {
+    std::fs::write(
+        config_path,
+        format!(
+            r#"
+[server]
+name = "warm.test"
+network = "TestNet"
+sid = "00W"
+description = "Warm Boot Test"
+metrics_port = 0
+
+[listen]
+address = "127.0.0.1:{port}"
+
+[database]
+path = "{data}/test.db"
+
+[security]
+cloak_secret = "TestSecret-2026-Secure!9X"
+cloak_suffix = "test"
+spam_detection_enabled = false
+
+[security.rate_limits]
+message_rate_per_second = 100
+connection_burst_per_ip = 100
+join_burst_per_client = 100
+
+[[oper]]
+name = "admin"
+password = "operpass"
+
+[snapshot]
+enabled = true
+path = "{data}/state.snapshot"
+"#,
+            port = port,
+            data = data_dir.display()
+        ),
+    )?;
+    Ok(())
+}
+
+/// Test that channel modes, topic and bans survive a restart via the snapshot.
+#[tokio::test]
+async fn test_snapshot_restores_channel_state() -> anyhow::Result<()> {
+    let port = 16730;
+    let base = std::env::temp_dir().join(format!("slircd-warm-{}", uuid::Uuid::new_v4()));
+    // TestServer removes its config directory on drop, so keep data separate.
+    let config_dir = base.join("config");
+    let data_dir = base.join("data");
+    std::fs::create_dir_all(&data_dir)?;
+
+    std::fs::create_dir_all(&config_dir)?;
+    let config_path = config_dir.join("config.toml");
+    write_config(&config_path, &data_dir, port)?;
+    let server = TestServer::spawn_with_config(port, config_path.clone()).await?;
+
+    let mut alice = TestClient::connect(&server.address(), "alice").await?;
+    alice.register().await?;
+    alice.join("#warm").await?;
+    alice.topic("#warm", "Survives restarts").await?;
+    alice
+        .send_raw("MODE #warm +kb sekrit *!*@banned.example")
+        .await?;
+    sleep(Duration::from_millis(200)).await;
+
+    alice.send_raw("OPER admin operpass").await?;
+    alice
+        .recv_until(|m| matches!(&m.command, Command::Response(resp, _) if resp.code() == 381))
+        .await?;
+    alice.send_raw("DIE").await?;
+    sleep(Duration::from_millis(1000)).await;
+    drop(alice);
+    drop(server);
+
+    assert!(data_dir.join("state.snapshot").exists());
+
+    std::fs::create_dir_all(&config_dir)?;
+    write_config(&config_path, &data_dir, port)?;
+    let server = TestServer::spawn_with_config(port, config_path).await?;
+
+    let mut bob = TestClient::connect(&server.address(), "bob").await?;
+    bob.register().await?;
+
+    // The key survived: joining without it fails with ERR_BADCHANNELKEY
+    bob.send_raw("JOIN #warm").await?;
+    bob.recv_until(|m| matches!(&m.command, Command::Response(resp, _) if resp.code() == 475))
+        .await?;
+
+    // Topic and ban list survived
+    bob.send_raw("MODE #warm b").await?;
+    bob.recv_until(|m| {
+        matches!(&m.command, Command::Response(resp, params)
+            if resp.code() == 367 && params.iter().any(|p| p == "*!*@banned.example"))
+    })
+    .await?;
+
+    bob.send_raw("JOIN #warm sekrit").await?;
+    bob.recv_until(|m| {
+        matches!(&m.command, Command::Response(resp, params)
+            if resp.code() == 332 && params.last().is_some_and(|p| p == "Survives restarts"))
+    })
+    .await?;
+
+    // The snapshot is consumed on load
+    assert!(!data_dir.join("state.snapshot").exists());
+
+    drop(bob);
+    drop(server);
+    let _ = std::fs::remove_dir_all(&base);
+    Ok(())
+}